@@ -0,0 +1,150 @@
+//! Page Rendering Benchmarks
+//!
+//! Performance benchmarks for PDF page rendering and text layer extraction,
+//! the two MuPDF wrapper paths hit on every page turn.
+//!
+//! Targets from MuPDF Migration Remediation Plan:
+//! - Page render at 1.5x (p50): <100ms
+//!
+//! Run with: `cargo bench --bench page_rendering`
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::time::Duration;
+
+use amnesia_server::pdf::{ImageFormat, PageRenderRequest, PdfParser};
+
+/// Render scales exercised by the reader (thumbnail, 1x, default, retina, zoomed)
+const RENDER_SCALES: &[f32] = &[0.5, 1.0, 1.5, 2.0, 3.0];
+
+/// Create a single-page PDF with a few lines of text and vector content
+fn create_text_pdf() -> Vec<u8> {
+    let mut content = String::from("BT\n/F1 12 Tf\n14 TL\n72 720 Td\n");
+    for line in 0..40 {
+        content.push_str(&format!(
+            "(Line {} of the rendering benchmark with enough text to fill the width) '\n",
+            line
+        ));
+    }
+    content.push_str("ET\n0.5 w\n72 100 468 600 re S\n");
+
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::new();
+
+    offsets.push(pdf.len());
+    pdf.push_str("1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n");
+
+    offsets.push(pdf.len());
+    pdf.push_str("2 0 obj\n<< /Type /Pages /Kids [3 0 R] /Count 1 >>\nendobj\n");
+
+    offsets.push(pdf.len());
+    pdf.push_str(
+        "3 0 obj\n<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Contents 4 0 R \
+         /Resources << /Font << /F1 << /Type /Font /Subtype /Type1 /BaseFont /Helvetica >> >> >> >>\nendobj\n",
+    );
+
+    offsets.push(pdf.len());
+    pdf.push_str(&format!(
+        "4 0 obj\n<< /Length {} >>\nstream\n{}endstream\nendobj\n",
+        content.len(),
+        content
+    ));
+
+    let xref_offset = pdf.len();
+    pdf.push_str(&format!("xref\n0 {}\n", offsets.len() + 1));
+    pdf.push_str("0000000000 65535 f \n");
+    for offset in &offsets {
+        pdf.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    pdf.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF",
+        offsets.len() + 1,
+        xref_offset
+    ));
+
+    pdf.into_bytes()
+}
+
+/// Benchmark page rendering across the scales used by the reader
+fn bench_page_render(c: &mut Criterion) {
+    let pdf_data = create_text_pdf();
+    let parser = PdfParser::from_bytes(&pdf_data, "bench-render".to_string()).unwrap();
+
+    let mut group = c.benchmark_group("page_render");
+    group.measurement_time(Duration::from_secs(10));
+    group.sample_size(30);
+
+    for &scale in RENDER_SCALES {
+        group.bench_with_input(BenchmarkId::new("png", scale), &scale, |b, &scale| {
+            let request = PageRenderRequest {
+                page: 1,
+                scale,
+                format: ImageFormat::Png,
+                rotation: 0,
+            };
+
+            b.iter(|| {
+                let image = parser.render_page(black_box(&request));
+                black_box(image)
+            })
+        });
+    }
+
+    // Format comparison at the default reader scale
+    for format in [ImageFormat::Jpeg, ImageFormat::Webp] {
+        group.bench_with_input(
+            BenchmarkId::new(format.extension(), 1.5),
+            &format,
+            |b, &format| {
+                let request = PageRenderRequest {
+                    page: 1,
+                    scale: 1.5,
+                    format,
+                    rotation: 0,
+                };
+
+                b.iter(|| {
+                    let image = parser.render_page(black_box(&request));
+                    black_box(image)
+                })
+            },
+        );
+    }
+
+    group.bench_function("thumbnail_200", |b| {
+        b.iter(|| {
+            let image = parser.render_thumbnail(black_box(1), 200);
+            black_box(image)
+        })
+    });
+
+    group.finish();
+}
+
+/// Benchmark text layer extraction on a text-dense page
+fn bench_text_layer(c: &mut Criterion) {
+    let pdf_data = create_text_pdf();
+    let parser = PdfParser::from_bytes(&pdf_data, "bench-text-layer".to_string()).unwrap();
+
+    let mut group = c.benchmark_group("text_layer");
+    group.measurement_time(Duration::from_secs(10));
+
+    group.bench_function("dense_page", |b| {
+        b.iter(|| {
+            // get_text_layer uses 1-indexed page numbers
+            let layer = parser.get_text_layer(black_box(1));
+            black_box(layer)
+        })
+    });
+
+    group.bench_function("plain_text", |b| {
+        b.iter(|| {
+            let text = parser.get_page_text(black_box(1));
+            black_box(text)
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_page_render, bench_text_layer);
+criterion_main!(benches);
@@ -16,13 +16,15 @@
 //!   - Notes
 //!   - Bookmarks
 //!
-//! - SQLite persistence with sync metadata
+//! - Typed links to other books, with backlinks per book
+//!
+//! - SQLite persistence with sync metadata and revision history
 
 mod store;
 mod types;
 
-pub use store::{AnnotationQuery, AnnotationRepository};
+pub use store::{AnnotationQuery, AnnotationRepository, AnnotationVersion};
 pub use types::{
-    Annotation, AnnotationBody, AnnotationStyle, AnnotationTarget, AnnotationType, BodyType,
-    Selector, SyncMetadata,
+    validate_links, Annotation, AnnotationBody, AnnotationStyle, AnnotationTarget, AnnotationType,
+    BodyType, DocumentLink, LinkRelation, PdfPosition, PdfRect, Selector, SyncMetadata, MAX_LINKS,
 };
@@ -1,18 +1,39 @@
 //! SQLite storage for annotations
 //!
 //! Provides CRUD operations for annotations using SQLite.
+//!
+//! Saving over an annotation whose body or style changed first copies the
+//! old content to `annotation_versions`, so edits made on any device can be
+//! listed and undone with [`AnnotationRepository::restore_version`].
 
 use anyhow::Result;
 use chrono::{DateTime, Utc};
-use sqlx::SqlitePool;
+use serde::Serialize;
+use sqlx::{Sqlite, SqlitePool, Transaction};
+use utoipa::ToSchema;
 
-use super::types::{Annotation, AnnotationType};
+use super::types::{Annotation, AnnotationBody, AnnotationStyle, AnnotationType};
 
 /// Repository for annotation persistence
 pub struct AnnotationRepository<'a> {
     pool: &'a SqlitePool,
 }
 
+/// An annotation's body and style as they were before an edit
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnotationVersion {
+    pub annotation_id: String,
+    /// 1 for the original content, counting up with each edit
+    pub version: i64,
+    pub body: Option<AnnotationBody>,
+    pub style: Option<AnnotationStyle>,
+    /// When this content was saved
+    pub saved_at: DateTime<Utc>,
+    /// When an edit replaced it
+    pub replaced_at: DateTime<Utc>,
+}
+
 /// Query filters for listing annotations
 #[derive(Debug, Default)]
 pub struct AnnotationQuery {
@@ -55,6 +76,16 @@ impl<'a> AnnotationRepository<'a> {
             CREATE INDEX IF NOT EXISTS idx_annotations_user ON annotations(user_id);
             CREATE INDEX IF NOT EXISTS idx_annotations_type ON annotations(annotation_type);
             CREATE INDEX IF NOT EXISTS idx_annotations_source ON annotations(source);
+
+            CREATE TABLE IF NOT EXISTS annotation_versions (
+                annotation_id TEXT NOT NULL,
+                version INTEGER NOT NULL,
+                body_json TEXT,
+                style_json TEXT,
+                saved_at TEXT NOT NULL,
+                replaced_at TEXT NOT NULL,
+                PRIMARY KEY (annotation_id, version)
+            );
             "#,
         )
         .execute(self.pool)
@@ -64,6 +95,9 @@ impl<'a> AnnotationRepository<'a> {
     }
 
     /// Save an annotation (insert or update)
+    ///
+    /// When an update changes the body or style, the previous ones are kept
+    /// as a version.
     pub async fn save(&self, annotation: &Annotation) -> Result<()> {
         let annotation_type = match annotation.annotation_type {
             AnnotationType::Highlight => "highlight",
@@ -89,6 +123,9 @@ impl<'a> AnnotationRepository<'a> {
             .map(|s| serde_json::to_string(s))
             .transpose()?;
 
+        let mut tx = self.pool.begin().await?;
+        record_version(&mut tx, &annotation.id, &body_json, &style_json).await?;
+
         sqlx::query(
             r#"
             INSERT INTO annotations (
@@ -123,9 +160,10 @@ impl<'a> AnnotationRepository<'a> {
         .bind(&sync_json)
         .bind(annotation.created_at.to_rfc3339())
         .bind(annotation.updated_at.to_rfc3339())
-        .execute(self.pool)
+        .execute(&mut *tx)
         .await?;
 
+        tx.commit().await?;
         Ok(())
     }
 
@@ -214,26 +252,92 @@ impl<'a> AnnotationRepository<'a> {
         rows.into_iter().map(|r| r.into_annotation()).collect()
     }
 
-    /// Delete an annotation
+    /// Delete an annotation and its versions
     pub async fn delete(&self, id: &str) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM annotation_versions WHERE annotation_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
         let result = sqlx::query("DELETE FROM annotations WHERE id = ?")
             .bind(id)
-            .execute(self.pool)
+            .execute(&mut *tx)
             .await?;
+        tx.commit().await?;
 
         Ok(result.rows_affected() > 0)
     }
 
-    /// Delete all annotations for a book
+    /// Delete all annotations for a book, with their versions
     pub async fn delete_for_book(&self, book_id: &str) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            r#"
+            DELETE FROM annotation_versions
+            WHERE annotation_id IN (SELECT id FROM annotations WHERE book_id = ?)
+            "#,
+        )
+        .bind(book_id)
+        .execute(&mut *tx)
+        .await?;
         let result = sqlx::query("DELETE FROM annotations WHERE book_id = ?")
             .bind(book_id)
-            .execute(self.pool)
+            .execute(&mut *tx)
             .await?;
+        tx.commit().await?;
 
         Ok(result.rows_affected())
     }
 
+    /// Previous versions of an annotation, newest first
+    pub async fn versions(&self, id: &str) -> Result<Vec<AnnotationVersion>> {
+        let rows = sqlx::query_as::<_, VersionRow>(
+            r#"
+            SELECT annotation_id, version, body_json, style_json, saved_at, replaced_at
+            FROM annotation_versions
+            WHERE annotation_id = ?
+            ORDER BY version DESC
+            "#,
+        )
+        .bind(id)
+        .fetch_all(self.pool)
+        .await?;
+
+        rows.into_iter().map(|r| r.into_version()).collect()
+    }
+
+    /// Put a previous version's body and style back
+    ///
+    /// The content being replaced becomes a version itself, so a restore can
+    /// be undone too. `None` if the annotation or version does not exist.
+    pub async fn restore_version(&self, id: &str, version: i64) -> Result<Option<Annotation>> {
+        let Some(mut annotation) = self.get(id).await? else {
+            return Ok(None);
+        };
+        let row = sqlx::query_as::<_, VersionRow>(
+            r#"
+            SELECT annotation_id, version, body_json, style_json, saved_at, replaced_at
+            FROM annotation_versions
+            WHERE annotation_id = ? AND version = ?
+            "#,
+        )
+        .bind(id)
+        .bind(version)
+        .fetch_optional(self.pool)
+        .await?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        let restored = row.into_version()?;
+        annotation.body = restored.body;
+        annotation.style = restored.style;
+        annotation.updated_at = Utc::now();
+        self.save(&annotation).await?;
+
+        Ok(Some(annotation))
+    }
+
     /// Count annotations for a book
     pub async fn count_for_book(&self, book_id: &str) -> Result<i64> {
         let row: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM annotations WHERE book_id = ?")
@@ -244,6 +348,28 @@ impl<'a> AnnotationRepository<'a> {
         Ok(row.0)
     }
 
+    /// Annotations (in any book) whose body links to `book_id`
+    pub async fn backlinks(&self, book_id: &str) -> Result<Vec<Annotation>> {
+        let rows = sqlx::query_as::<_, AnnotationRow>(
+            r#"
+            SELECT id, book_id, user_id, annotation_type, source,
+                   selectors_json, body_json, style_json, sync_json,
+                   created_at, updated_at
+            FROM annotations
+            WHERE EXISTS (
+                SELECT 1 FROM json_each(annotations.body_json, '$.links') AS link
+                WHERE json_extract(link.value, '$.bookId') = ?
+            )
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(book_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        rows.into_iter().map(|r| r.into_annotation()).collect()
+    }
+
     /// Get annotations modified after a timestamp (for sync)
     pub async fn get_modified_since(
         &self,
@@ -269,6 +395,80 @@ impl<'a> AnnotationRepository<'a> {
     }
 }
 
+/// Copy an annotation's stored body and style to a new version if a save
+/// is about to change them
+async fn record_version(
+    tx: &mut Transaction<'_, Sqlite>,
+    id: &str,
+    body_json: &Option<String>,
+    style_json: &Option<String>,
+) -> Result<()> {
+    let current: Option<(Option<String>, Option<String>, String)> =
+        sqlx::query_as("SELECT body_json, style_json, updated_at FROM annotations WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&mut **tx)
+            .await?;
+    let Some((old_body, old_style, saved_at)) = current else {
+        return Ok(());
+    };
+    if old_body == *body_json && old_style == *style_json {
+        return Ok(());
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO annotation_versions (
+            annotation_id, version, body_json, style_json, saved_at, replaced_at
+        )
+        SELECT ?, COALESCE(MAX(version), 0) + 1, ?, ?, ?, ?
+        FROM annotation_versions
+        WHERE annotation_id = ?
+        "#,
+    )
+    .bind(id)
+    .bind(&old_body)
+    .bind(&old_style)
+    .bind(&saved_at)
+    .bind(Utc::now().to_rfc3339())
+    .bind(id)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+/// Internal row type for annotation version queries
+#[derive(sqlx::FromRow)]
+struct VersionRow {
+    annotation_id: String,
+    version: i64,
+    body_json: Option<String>,
+    style_json: Option<String>,
+    saved_at: String,
+    replaced_at: String,
+}
+
+impl VersionRow {
+    fn into_version(self) -> Result<AnnotationVersion> {
+        Ok(AnnotationVersion {
+            annotation_id: self.annotation_id,
+            version: self.version,
+            body: self
+                .body_json
+                .as_deref()
+                .map(serde_json::from_str)
+                .transpose()?,
+            style: self
+                .style_json
+                .as_deref()
+                .map(serde_json::from_str)
+                .transpose()?,
+            saved_at: DateTime::parse_from_rfc3339(&self.saved_at)?.with_timezone(&Utc),
+            replaced_at: DateTime::parse_from_rfc3339(&self.replaced_at)?.with_timezone(&Utc),
+        })
+    }
+}
+
 /// Internal row type for SQLite queries
 #[derive(sqlx::FromRow)]
 struct AnnotationRow {
@@ -287,7 +487,7 @@ struct AnnotationRow {
 
 impl AnnotationRow {
     fn into_annotation(self) -> Result<Annotation> {
-        use super::types::{AnnotationBody, AnnotationStyle, AnnotationTarget, Selector, SyncMetadata};
+        use super::types::{AnnotationTarget, Selector, SyncMetadata};
 
         let annotation_type = match self.annotation_type.as_str() {
             "highlight" => AnnotationType::Highlight,
@@ -388,6 +588,41 @@ mod tests {
         assert_eq!(results.len(), 3);
     }
 
+    #[tokio::test]
+    async fn test_backlinks() {
+        use crate::annotations::types::DocumentLink;
+
+        let pool = setup_test_db().await;
+        let repo = AnnotationRepository::new(&pool);
+        let link = |book_id: &str| DocumentLink {
+            book_id: book_id.to_string(),
+            locator: None,
+            relation: Default::default(),
+            label: None,
+        };
+
+        let target = AnnotationTarget::from_cfi("chapter1.xhtml", "epubcfi(/6/4!/4/2)");
+        let mut note = Annotation::new_note("book-a", target.clone(), "Compare with B and C");
+        note.set_links(vec![link("book-b"), link("book-c")]);
+        repo.save(&note).await.unwrap();
+
+        let mut highlight = Annotation::new_highlight("book-c", target.clone());
+        highlight.set_links(vec![link("book-b")]);
+        repo.save(&highlight).await.unwrap();
+
+        repo.save(&Annotation::new_highlight("book-b", target))
+            .await
+            .unwrap();
+
+        let backlinks = repo.backlinks("book-b").await.unwrap();
+        assert_eq!(backlinks.len(), 2);
+        let backlinks = repo.backlinks("book-c").await.unwrap();
+        assert_eq!(backlinks.len(), 1);
+        assert_eq!(backlinks[0].id, note.id);
+        assert_eq!(backlinks[0].links().len(), 2);
+        assert!(repo.backlinks("book-a").await.unwrap().is_empty());
+    }
+
     #[tokio::test]
     async fn test_delete() {
         let pool = setup_test_db().await;
@@ -403,4 +638,44 @@ mod tests {
         repo.delete(&id).await.unwrap();
         assert!(repo.get(&id).await.unwrap().is_none());
     }
+
+    #[tokio::test]
+    async fn test_versions_and_restore() {
+        let pool = setup_test_db().await;
+        let repo = AnnotationRepository::new(&pool);
+
+        let target = AnnotationTarget::from_cfi("chapter1.xhtml", "epubcfi(/6/4!/4/2)");
+        let mut note = Annotation::new_note("book-123", target, "First draft");
+        repo.save(&note).await.unwrap();
+        // Saving unchanged content records nothing
+        repo.save(&note).await.unwrap();
+        assert!(repo.versions(&note.id).await.unwrap().is_empty());
+
+        note.body.as_mut().unwrap().value = Some("Oops".to_string());
+        repo.save(&note).await.unwrap();
+        let versions = repo.versions(&note.id).await.unwrap();
+        assert_eq!(versions.len(), 1);
+        assert_eq!(versions[0].version, 1);
+        assert_eq!(
+            versions[0].body.as_ref().unwrap().value.as_deref(),
+            Some("First draft")
+        );
+
+        let restored = repo.restore_version(&note.id, 1).await.unwrap().unwrap();
+        assert_eq!(
+            restored.body.as_ref().unwrap().value.as_deref(),
+            Some("First draft")
+        );
+        // The overwritten edit can be restored in turn
+        let versions = repo.versions(&note.id).await.unwrap();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(
+            versions[0].body.as_ref().unwrap().value.as_deref(),
+            Some("Oops")
+        );
+        assert!(repo.restore_version(&note.id, 9).await.unwrap().is_none());
+
+        repo.delete(&note.id).await.unwrap();
+        assert!(repo.versions(&note.id).await.unwrap().is_empty());
+    }
 }
@@ -7,10 +7,17 @@
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
+use crate::cfi::{Cfi, CfiPath, CfiRange, SpatialOffset};
+use crate::db::ProgressLocator;
+
+/// Most links a single annotation may carry
+pub const MAX_LINKS: usize = 50;
+
 /// A complete annotation
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Annotation {
     /// Unique identifier (UUID)
     pub id: String,
@@ -43,7 +50,7 @@ pub struct Annotation {
 }
 
 /// Types of annotations
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum AnnotationType {
     /// Text highlight
@@ -57,7 +64,7 @@ pub enum AnnotationType {
 }
 
 /// The target of an annotation (what is being annotated)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct AnnotationTarget {
     /// Source document (spine item href)
     pub source: String,
@@ -67,7 +74,7 @@ pub struct AnnotationTarget {
 
 /// Selector types for identifying text/positions
 /// Multiple selectors provide fallback options for resolution
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(tag = "type", rename_all = "PascalCase")]
 pub enum Selector {
     // ============================================
@@ -75,6 +82,9 @@ pub enum Selector {
     // ============================================
 
     /// EPUB CFI fragment identifier
+    ///
+    /// A spatial range CFI (`@x:y` at each end) selects a region of an
+    /// illustration; see [`AnnotationTarget::from_image_region`].
     #[serde(rename = "FragmentSelector")]
     Fragment {
         /// The CFI value
@@ -161,14 +171,14 @@ pub enum Selector {
 }
 
 /// Normalized position on PDF page (0-1, origin top-left)
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema)]
 pub struct PdfPosition {
     pub x: f64,
     pub y: f64,
 }
 
 /// Normalized rectangle on PDF page (0-1, origin top-left)
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema)]
 pub struct PdfRect {
     pub x: f64,
     pub y: f64,
@@ -176,8 +186,29 @@ pub struct PdfRect {
     pub height: f64,
 }
 
+impl PdfRect {
+    /// Check that the rectangle lies within the page (or image)
+    pub fn validate(&self) -> Result<(), String> {
+        let within = |start: f64, size: f64| {
+            start.is_finite()
+                && size.is_finite()
+                && start >= 0.0
+                && size >= 0.0
+                && start + size <= 1.0 + f64::EPSILON
+        };
+        if within(self.x, self.width) && within(self.y, self.height) {
+            Ok(())
+        } else {
+            Err(format!(
+                "Region {}x{} at ({}, {}) is outside the 0-1 bounds",
+                self.width, self.height, self.x, self.y
+            ))
+        }
+    }
+}
+
 /// Body/content of an annotation
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct AnnotationBody {
     /// Type of body content
     #[serde(rename = "type")]
@@ -188,20 +219,87 @@ pub struct AnnotationBody {
     /// Format of the content
     #[serde(skip_serializing_if = "Option::is_none")]
     pub format: Option<String>,
+    /// Links to other documents (or places in them)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<DocumentLink>,
 }
 
 /// Types of annotation body content
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "PascalCase")]
 pub enum BodyType {
     /// Plain text note
     TextualBody,
+    /// Links to other documents only (see `links`)
+    SpecificResource,
     /// No body (e.g., simple highlight)
     None,
 }
 
+/// A typed link from an annotation to another book
+///
+/// Links turn notes into a graph across the library: a note in one book can
+/// cite a passage in another, and that book can list its backlinks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DocumentLink {
+    /// The linked book
+    #[serde(rename = "bookId")]
+    pub book_id: String,
+    /// Place in the linked book; `None` links the book as a whole
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locator: Option<ProgressLocator>,
+    /// How the annotation relates to the linked place
+    #[serde(rename = "rel", default)]
+    pub relation: LinkRelation,
+    /// Display text for the link
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+/// Relation of an annotation to the document it links to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkRelation {
+    /// Plain reference ("see also")
+    #[default]
+    References,
+    /// The annotated passage quotes the linked one
+    Quotes,
+    /// The linked passage supports the annotated one
+    Supports,
+    /// The linked passage contradicts the annotated one
+    Contradicts,
+}
+
+impl DocumentLink {
+    /// Check that the link names a book and its locator is well-formed
+    pub fn validate(&self) -> Result<(), String> {
+        if self.book_id.trim().is_empty() {
+            return Err("Link is missing a bookId".to_string());
+        }
+        if let Some(locator) = &self.locator {
+            locator
+                .validate(locator.default_format(), None)
+                .map_err(|e| format!("Invalid locator for '{}': {}", self.book_id, e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Validate a set of links for one annotation
+pub fn validate_links(links: &[DocumentLink]) -> Result<(), String> {
+    if links.len() > MAX_LINKS {
+        return Err(format!(
+            "An annotation can have at most {} links, got {}",
+            MAX_LINKS,
+            links.len()
+        ));
+    }
+    links.iter().try_for_each(DocumentLink::validate)
+}
+
 /// Visual style for highlights
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct AnnotationStyle {
     /// Highlight color (CSS color value)
     pub color: String,
@@ -220,7 +318,7 @@ impl Default for AnnotationStyle {
 }
 
 /// Sync metadata for conflict resolution
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct SyncMetadata {
     /// Version for optimistic locking
     pub version: u64,
@@ -265,6 +363,7 @@ impl Annotation {
                 body_type: BodyType::TextualBody,
                 value: Some(note.to_string()),
                 format: Some("text/plain".to_string()),
+                links: Vec::new(),
             }),
             style: Some(AnnotationStyle::default()),
             created_at: now,
@@ -305,6 +404,27 @@ impl Annotation {
         self
     }
 
+    /// Links to other documents
+    pub fn links(&self) -> &[DocumentLink] {
+        self.body.as_ref().map_or(&[], |body| &body.links)
+    }
+
+    /// Replace the links to other documents, adding a body if needed
+    pub fn set_links(&mut self, links: Vec<DocumentLink>) {
+        match &mut self.body {
+            Some(body) => body.links = links,
+            None if links.is_empty() => {}
+            None => {
+                self.body = Some(AnnotationBody {
+                    body_type: BodyType::SpecificResource,
+                    value: None,
+                    format: None,
+                    links,
+                })
+            }
+        }
+    }
+
     /// Get the primary CFI selector if available
     pub fn cfi(&self) -> Option<&str> {
         self.target.selectors.iter().find_map(|s| match s {
@@ -313,6 +433,28 @@ impl Annotation {
         })
     }
 
+    /// Seconds into the audio or video, if the CFI selector is temporal
+    /// (a read-aloud position or audiobook bookmark)
+    pub fn seconds(&self) -> Option<f64> {
+        crate::cfi::parse(self.cfi()?).ok()?.temporal_offset()
+    }
+
+    /// Region of an EPUB illustration, if the CFI selector is spatial
+    ///
+    /// Uses the same normalized coordinates as [`Self::pdf_region`]; a single
+    /// point is an empty rectangle.
+    pub fn image_region(&self) -> Option<PdfRect> {
+        let cfi = crate::cfi::parse(self.cfi()?).ok()?;
+        let (x, y) = cfi.spatial_offset()?;
+        let (end_x, end_y) = cfi.end().spatial_offset()?;
+        Some(PdfRect {
+            x: x / 100.0,
+            y: y / 100.0,
+            width: (end_x - x) / 100.0,
+            height: (end_y - y) / 100.0,
+        })
+    }
+
     /// Get the text quote selector if available
     pub fn text_quote(&self) -> Option<&str> {
         self.target.selectors.iter().find_map(|s| match s {
@@ -376,6 +518,50 @@ impl AnnotationTarget {
         }
     }
 
+    /// Create a target for a region of an image in an EPUB
+    ///
+    /// `image_cfi` points at the image element and `rect` is normalized like
+    /// a [`Selector::PdfRegion`]. The region is stored as a spatial range CFI
+    /// from its top-left to its bottom-right corner, so it sorts and syncs
+    /// like any other EPUB highlight.
+    pub fn from_image_region(source: &str, image_cfi: &str, rect: PdfRect) -> Result<Self, String> {
+        rect.validate()?;
+        let image = crate::cfi::parse(image_cfi)
+            .map_err(|e| format!("Invalid image CFI '{}': {}", image_cfi, e))?;
+        let mut path = image.path;
+        let element = match path.steps.pop() {
+            Some(step)
+                if image.range.is_none()
+                    && !step.is_indirection()
+                    && path.character_offset.is_none()
+                    && path.temporal_offset.is_none()
+                    && path.spatial_offset.is_none() =>
+            {
+                step
+            }
+            _ => return Err(format!("'{}' does not point at an image", image_cfi)),
+        };
+
+        // Percentages, rounded so the CFI doesn't carry float noise
+        let percent = |fraction: f64| (fraction * 1e6).round() / 1e4;
+        let corner = |x: f64, y: f64| CfiPath {
+            steps: vec![element.clone()],
+            spatial_offset: Some(SpatialOffset {
+                x: percent(x),
+                y: percent(y),
+            }),
+            ..CfiPath::new()
+        };
+        let region = Cfi::with_range(
+            path,
+            CfiRange {
+                start: corner(rect.x, rect.y),
+                end: corner(rect.x + rect.width, rect.y + rect.height),
+            },
+        );
+        Ok(Self::from_cfi(source, &region.to_string()))
+    }
+
     /// Create a target with multiple selectors for robust anchoring
     pub fn with_selectors(source: &str, selectors: Vec<Selector>) -> Self {
         Self {
@@ -498,6 +684,19 @@ mod tests {
         assert_eq!(highlight.cfi().unwrap(), "epubcfi(/6/4!/4/2/1:10)");
     }
 
+    #[test]
+    fn test_audio_bookmark() {
+        let target = AnnotationTarget::from_cfi("chapter1.xhtml", "epubcfi(/6/4!/4/2/6~95)");
+        let bookmark = Annotation::new_bookmark("book-123", target);
+        assert_eq!(bookmark.seconds(), Some(95.0));
+
+        let target = AnnotationTarget::from_cfi("chapter1.xhtml", "epubcfi(/6/4!/4/2/1:10)");
+        assert_eq!(
+            Annotation::new_highlight("book-123", target).seconds(),
+            None
+        );
+    }
+
     #[test]
     fn test_create_note() {
         let target = AnnotationTarget::from_cfi("chapter1.xhtml", "epubcfi(/6/4!/4/2)");
@@ -590,6 +789,48 @@ mod tests {
         assert!((region.width - 0.3).abs() < f64::EPSILON);
     }
 
+    #[test]
+    fn test_image_region_selector() {
+        let rect = PdfRect {
+            x: 0.1,
+            y: 0.2,
+            width: 0.3,
+            height: 0.5,
+        };
+        let target =
+            AnnotationTarget::from_image_region("chapter3.xhtml", "epubcfi(/6/8!/4/2/4)", rect)
+                .unwrap();
+        let highlight = Annotation::new_highlight("book-123", target);
+
+        assert_eq!(
+            highlight.cfi(),
+            Some("epubcfi(/6/8!/4/2,/4@10:20,/4@40:70)")
+        );
+        assert!(!highlight.is_pdf_annotation());
+        let region = highlight.image_region().unwrap();
+        assert!((region.x - 0.1).abs() < 1e-9);
+        assert!((region.y - 0.2).abs() < 1e-9);
+        assert!((region.width - 0.3).abs() < 1e-9);
+        assert!((region.height - 0.5).abs() < 1e-9);
+
+        // A point in the image is an empty region
+        let target = AnnotationTarget::from_cfi("chapter3.xhtml", "epubcfi(/6/8!/4/2/4@50:50)");
+        let pin = Annotation::new_bookmark("book-123", target)
+            .image_region()
+            .unwrap();
+        assert_eq!((pin.width, pin.height), (0.0, 0.0));
+
+        let outside = PdfRect { x: 0.8, ..rect };
+        assert!(
+            AnnotationTarget::from_image_region("c.xhtml", "epubcfi(/6/8!/4/2/4)", outside)
+                .is_err()
+        );
+        assert!(
+            AnnotationTarget::from_image_region("c.xhtml", "epubcfi(/6/8!/4/2/1:3)", rect).is_err()
+        );
+        assert!(AnnotationTarget::from_image_region("c.xhtml", "epubcfi(/6/8!)", rect).is_err());
+    }
+
     #[test]
     fn test_pdf_selector_serialization() {
         let rect = PdfRect {
@@ -628,6 +869,48 @@ mod tests {
         assert_eq!(highlight.progression(), Some(0.5));
     }
 
+    #[test]
+    fn test_document_links() {
+        let link = DocumentLink {
+            book_id: "book-b".to_string(),
+            locator: Some(ProgressLocator::Page { page: 12, y: None }),
+            relation: LinkRelation::Supports,
+            label: Some("Table 3".to_string()),
+        };
+        assert!(validate_links(std::slice::from_ref(&link)).is_ok());
+
+        let target = AnnotationTarget::from_cfi("chapter1.xhtml", "epubcfi(/6/4!/4/2)");
+        let mut highlight = Annotation::new_highlight("book-a", target);
+        assert!(highlight.links().is_empty());
+        highlight.set_links(vec![link.clone()]);
+        assert_eq!(
+            highlight.body.as_ref().unwrap().body_type,
+            BodyType::SpecificResource
+        );
+
+        let json = serde_json::to_string(&highlight).unwrap();
+        assert!(json.contains("\"bookId\":\"book-b\""));
+        assert!(json.contains("\"rel\":\"supports\""));
+        let parsed: Annotation = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.links(), [link]);
+
+        // Relation defaults to a plain reference
+        let parsed: DocumentLink = serde_json::from_str(r#"{"bookId": "book-c"}"#).unwrap();
+        assert_eq!(parsed.relation, LinkRelation::References);
+
+        let missing_book = DocumentLink {
+            book_id: " ".to_string(),
+            ..parsed.clone()
+        };
+        assert!(missing_book.validate().is_err());
+        let bad_locator = DocumentLink {
+            locator: Some(ProgressLocator::Page { page: 0, y: None }),
+            ..parsed.clone()
+        };
+        assert!(bad_locator.validate().is_err());
+        assert!(validate_links(&vec![parsed; MAX_LINKS + 1]).is_err());
+    }
+
     #[test]
     fn test_epub_annotation_is_not_pdf() {
         let target = AnnotationTarget::from_cfi("chapter1.xhtml", "epubcfi(/6/4!/4/2)");
@@ -15,6 +15,75 @@ pub fn generate_citation(metadata: &BookMetadata, format: CitationFormat) -> Cit
     }
 }
 
+/// Generate an in-text citation (or short note) pointing at a page
+///
+/// `page_label` is the printed page label ("xiv", "A-3"), not the page index.
+///
+/// Examples for page "xiv":
+/// - APA: (Zinsser, 1988, p. xiv)
+/// - MLA: (Zinsser xiv)
+/// - Chicago: Zinsser, *Writing to Learn*, xiv.
+/// - IEEE: [1, p. xiv]
+/// - BibTeX: `\cite[p.~xiv]{zinsser_1988_writing}`
+pub fn generate_in_text_citation(
+    metadata: &BookMetadata,
+    format: CitationFormat,
+    page_label: Option<&str>,
+) -> CitationResult<String> {
+    let citation = match format {
+        CitationFormat::BibTeX => match page_label {
+            Some(page) => format!("\\cite[p.~{}]{{{}}}", page, metadata.bibtex_key()),
+            None => format!("\\cite{{{}}}", metadata.bibtex_key()),
+        },
+        CitationFormat::APA => {
+            let year = metadata
+                .year
+                .map(|y| y.to_string())
+                .unwrap_or_else(|| "n.d.".to_string());
+            match page_label {
+                Some(page) => format!("({}, {}, p. {})", short_authors(metadata, "&"), year, page),
+                None => format!("({}, {})", short_authors(metadata, "&"), year),
+            }
+        }
+        CitationFormat::MLA => match page_label {
+            Some(page) => format!("({} {})", short_authors(metadata, "and"), page),
+            None => format!("({})", short_authors(metadata, "and")),
+        },
+        CitationFormat::Chicago => {
+            // Short notes drop the subtitle
+            let title = metadata.title.split(':').next().unwrap_or_default().trim();
+            match page_label {
+                Some(page) => {
+                    format!("{}, *{}*, {}.", short_authors(metadata, "and"), title, page)
+                }
+                None => format!("{}, *{}*.", short_authors(metadata, "and"), title),
+            }
+        }
+        // The reference number depends on the document's reference list
+        CitationFormat::IEEE => match page_label {
+            Some(page) => format!("[1, p. {}]", page),
+            None => "[1]".to_string(),
+        },
+    };
+    Ok(citation)
+}
+
+/// Author surnames for in-text citations: "A", "A and B", "A et al."
+fn short_authors(metadata: &BookMetadata, conjunction: &str) -> String {
+    let surnames: Vec<&str> = metadata
+        .authors
+        .iter()
+        .filter_map(|author| author.split_whitespace().last())
+        .collect();
+
+    match surnames.as_slice() {
+        [] => "Unknown Author".to_string(),
+        [one] => one.to_string(),
+        [first, second] => format!("{} {} {}", first, conjunction, second),
+        [first, ..] => format!("{} et al.", first),
+    }
+}
+
 /// Generate citations for multiple books
 pub fn generate_citation_list(
     metadata_list: &[BookMetadata],
@@ -432,4 +501,37 @@ mod tests {
         assert!(generate_chicago(&book).is_ok());
         assert!(generate_ieee(&book).is_ok());
     }
+
+    #[test]
+    fn test_in_text_citation() {
+        let book = BookMetadata {
+            title: "Writing to Learn: How to Write and Think Clearly".to_string(),
+            year: Some(1988),
+            ..BookMetadata::new("zinsser", "", vec!["William Zinsser".to_string()])
+        };
+        let cite = |format| generate_in_text_citation(&book, format, Some("xiv")).unwrap();
+
+        assert_eq!(cite(CitationFormat::APA), "(Zinsser, 1988, p. xiv)");
+        assert_eq!(cite(CitationFormat::MLA), "(Zinsser xiv)");
+        assert_eq!(
+            cite(CitationFormat::Chicago),
+            "Zinsser, *Writing to Learn*, xiv."
+        );
+        assert_eq!(cite(CitationFormat::IEEE), "[1, p. xiv]");
+        assert_eq!(
+            cite(CitationFormat::BibTeX),
+            r"\cite[p.~xiv]{zinsser_1988_writing}"
+        );
+
+        // Two authors, no page
+        let book = sample_book();
+        assert_eq!(
+            generate_in_text_citation(&book, CitationFormat::APA, None).unwrap(),
+            "(Klabnik & Nichols, 2023)"
+        );
+        assert_eq!(
+            generate_in_text_citation(&book, CitationFormat::MLA, Some("A-3")).unwrap(),
+            "(Klabnik and Nichols A-3)"
+        );
+    }
 }
@@ -31,7 +31,9 @@
 mod formatter;
 mod types;
 
-pub use formatter::{generate_bibtex, generate_citation, generate_citation_list};
+pub use formatter::{
+    generate_bibtex, generate_citation, generate_citation_list, generate_in_text_citation,
+};
 pub use types::{BookMetadata, CitationFormat};
 
 #[cfg(test)]
@@ -0,0 +1,478 @@
+//! Subcommand implementations
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+
+use amnesia_server::annotations::{AnnotationQuery, AnnotationRepository};
+use amnesia_server::config::Config;
+use amnesia_server::db::{self, DuplicatePageRepository, FTS5Search};
+use amnesia_server::document::DocumentFormat;
+use amnesia_server::formats::epub::{EpubDocumentHandler, LayoutConfig};
+use amnesia_server::formats::isolation;
+use amnesia_server::import::{self, ImportPlan, LibraryIndex};
+use amnesia_server::library::{
+    CalibreMetadata, DuplicatePageIndexer, FormatType, LibraryScanner, TextStatsIndexer,
+};
+use amnesia_server::storage::S3Client;
+use amnesia_server::vault;
+
+/// Print library statistics, or every book as JSON
+pub async fn scan(config: &Config, json: bool) -> Result<()> {
+    let scanner = LibraryScanner::new(S3Client::new(&config.storage).await?);
+    let books = scanner.scan_library().await?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&books)?);
+        return Ok(());
+    }
+
+    let stats = scanner.get_stats(&books).await;
+    println!("Books:   {}", stats.total_books);
+    println!("Authors: {}", stats.total_authors);
+    println!("Series:  {}", stats.total_series);
+
+    let mut formats: Vec<_> = stats.formats.into_iter().collect();
+    formats.sort();
+    println!("Formats:");
+    for (format, count) in formats {
+        println!("  {:<6} {}", format, count);
+    }
+
+    Ok(())
+}
+
+/// Rebuild the FTS5 indexes from the books and highlights tables
+pub async fn reindex(config: &Config, books: bool, highlights: bool) -> Result<()> {
+    let pool = db::create_pool(&config.database.url).await?;
+    let search = FTS5Search::new(&pool);
+    search
+        .initialize()
+        .await
+        .context("Failed to initialize FTS5 tables")?;
+
+    if books {
+        let count = search.rebuild_books_index().await?;
+        println!("Reindexed {} books", count);
+    }
+    if highlights {
+        let count = search.rebuild_highlights_index().await?;
+        println!("Reindexed {} highlights", count);
+    }
+
+    Ok(())
+}
+
+/// Lay out an EPUB at the given page size and write it as a PDF
+pub fn convert(input: &Path, output: &Path, width: f32, height: f32, em: f32) -> Result<()> {
+    let data = fs::read(input).with_context(|| format!("Failed to read {}", input.display()))?;
+    if DocumentFormat::from_magic_bytes(&data) != Some(DocumentFormat::Epub) {
+        bail!("{} is not an EPUB", input.display());
+    }
+
+    let id = input
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let handler =
+        EpubDocumentHandler::from_bytes_with_layout(data, id, LayoutConfig { width, height, em })?;
+    handler
+        .export_pdf(output)
+        .with_context(|| format!("Failed to write {}", output.display()))?;
+
+    println!("Wrote {}", output.display());
+    Ok(())
+}
+
+/// Parse every EPUB and PDF in a parse worker and report the failures
+///
+/// Uses the same subprocess isolation as uploads, so a document that crashes
+/// MuPDF is reported instead of aborting the run.
+pub async fn verify(config: &Config, prefix: Option<&str>) -> Result<()> {
+    let s3 = S3Client::new(&config.storage).await?;
+    let books = LibraryScanner::new(s3.clone()).scan_library().await?;
+
+    let mut checked = 0;
+    let mut failed = 0;
+    for book in &books {
+        for format in &book.formats {
+            if !matches!(format.format, FormatType::Epub | FormatType::Pdf) {
+                continue;
+            }
+            if prefix.is_some_and(|prefix| !format.s3_key.starts_with(prefix)) {
+                continue;
+            }
+
+            checked += 1;
+            let outcome = match s3.get_object(&format.s3_key).await {
+                Ok(object) => {
+                    isolation::parse_isolated(&object.data, &book.id, config.isolation.timeout_secs)
+                        .await
+                        .map(|_| ())
+                        .map_err(|e| e.to_string())
+                }
+                Err(e) => Err(e.to_string()),
+            };
+
+            match outcome {
+                Ok(()) => println!("ok      {}", format.s3_key),
+                Err(e) => {
+                    failed += 1;
+                    println!("FAILED  {}: {}", format.s3_key, e);
+                }
+            }
+        }
+    }
+
+    println!("{} checked, {} failed", checked, failed);
+    if failed > 0 {
+        bail!("{} of {} files failed verification", failed, checked);
+    }
+    Ok(())
+}
+
+/// Compute and store text statistics for the library's books
+pub async fn analyze_text(config: &Config, force: bool) -> Result<()> {
+    let s3 = S3Client::new(&config.storage).await?;
+    let books = LibraryScanner::new(s3.clone()).scan_library().await?;
+    let pool = db::create_pool(&config.database.url).await?;
+
+    let summary = TextStatsIndexer::new(s3, pool, config)
+        .run(&books, force)
+        .await?;
+    println!("{}", summary);
+    if summary.failed > 0 {
+        bail!("{} books could not be analyzed", summary.failed);
+    }
+    Ok(())
+}
+
+/// Check the library's PDFs for repeated pages and list every book with some
+pub async fn check_scans(config: &Config, force: bool) -> Result<()> {
+    let s3 = S3Client::new(&config.storage).await?;
+    let books = LibraryScanner::new(s3.clone()).scan_library().await?;
+    let pool = db::create_pool(&config.database.url).await?;
+
+    let summary = DuplicatePageIndexer::new(s3, pool.clone(), config)
+        .run(&books, force)
+        .await?;
+    for report in DuplicatePageRepository::new(&pool)
+        .with_duplicates()
+        .await?
+    {
+        println!("{}", report.source_key);
+        for duplicate in &report.duplicates {
+            println!(
+                "  page {} repeats page {} (distance {})",
+                duplicate.page + 1,
+                duplicate.duplicate_of + 1,
+                duplicate.distance
+            );
+        }
+    }
+    println!("{}", summary);
+    if summary.failed > 0 {
+        bail!("{} PDFs could not be checked", summary.failed);
+    }
+    Ok(())
+}
+
+/// Write annotations as a JSON array to `output` or stdout
+pub async fn export_annotations(
+    config: &Config,
+    book_id: Option<String>,
+    user_id: Option<String>,
+    output: Option<&Path>,
+) -> Result<()> {
+    let pool = db::create_pool(&config.database.url).await?;
+    let repo = AnnotationRepository::new(&pool);
+    repo.init().await?;
+
+    let annotations = repo
+        .list(&AnnotationQuery {
+            book_id,
+            user_id,
+            ..Default::default()
+        })
+        .await?;
+    let json = serde_json::to_string_pretty(&annotations)?;
+
+    match output {
+        Some(path) => {
+            fs::write(path, json).with_context(|| format!("Failed to write {}", path.display()))?;
+            eprintln!(
+                "Exported {} annotations to {}",
+                annotations.len(),
+                path.display()
+            );
+        }
+        None => println!("{}", json),
+    }
+
+    Ok(())
+}
+
+/// Write highlights and notes as a zip of Markdown pages, one per book
+///
+/// The library is scanned for book metadata; without S3 access the pages
+/// are still written, titled by book ID.
+pub async fn export_vault(
+    config: &Config,
+    output: &Path,
+    user_id: Option<&str>,
+    category: Option<&str>,
+) -> Result<()> {
+    let pool = db::create_pool(&config.database.url).await?;
+    let books = match S3Client::new(&config.storage).await {
+        Ok(s3) => LibraryScanner::new(s3).scan_library().await,
+        Err(e) => Err(e),
+    };
+    let books = books.unwrap_or_else(|e| {
+        tracing::warn!("Library scan failed, exporting without metadata: {}", e);
+        Vec::new()
+    });
+
+    let export = vault::export_vault(&pool, &books, user_id, category).await?;
+    fs::write(output, &export.zip)
+        .with_context(|| format!("Failed to write {}", output.display()))?;
+    eprintln!("{} to {}", export.summary, output.display());
+    Ok(())
+}
+
+/// Upload a local Calibre library, keeping its `Author/Title/` layout
+///
+/// That layout is what [`LibraryScanner`] reads, so imported books show up
+/// on the next scan. Existing objects are skipped unless `overwrite` is set.
+pub async fn import_calibre(
+    config: &Config,
+    library: &Path,
+    dry_run: bool,
+    overwrite: bool,
+) -> Result<()> {
+    let books = calibre_books(library)?;
+    if books.is_empty() {
+        bail!("No Calibre book folders found in {}", library.display());
+    }
+
+    let s3 = S3Client::new(&config.storage).await?;
+    let mut uploaded = 0;
+    let mut skipped = 0;
+
+    for book in &books {
+        println!(
+            "{} ({})",
+            book.title.as_deref().unwrap_or(&book.prefix),
+            book.prefix
+        );
+
+        for (path, key) in &book.files {
+            if !overwrite && s3.object_exists(key).await? {
+                skipped += 1;
+                println!("  exists  {}", key);
+                continue;
+            }
+
+            if !dry_run {
+                let data =
+                    fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+                let content_type = mime_guess::from_path(path).first_or_octet_stream();
+                s3.put_object(key, data, content_type.as_ref()).await?;
+            }
+            uploaded += 1;
+            println!("  upload  {}", key);
+        }
+    }
+
+    let verb = if dry_run { "would upload" } else { "uploaded" };
+    println!(
+        "{} books: {} {} files, skipped {} existing",
+        books.len(),
+        verb,
+        uploaded,
+        skipped
+    );
+    Ok(())
+}
+
+/// Import reading progress and shelves from a Calibre-Web `app.db`
+pub async fn import_calibre_web(
+    config: &Config,
+    app_db: &Path,
+    dry_run: bool,
+    json: bool,
+) -> Result<()> {
+    let plan = import::read_calibre_web(app_db).await?;
+    import_plan(config, plan, dry_run, json).await
+}
+
+/// Import read lists and one account's reading progress from Komga
+pub async fn import_komga(
+    config: &Config,
+    url: &str,
+    user: &str,
+    password: &str,
+    dry_run: bool,
+    json: bool,
+) -> Result<()> {
+    let plan = import::read_komga(url, user, password).await?;
+    import_plan(config, plan, dry_run, json).await
+}
+
+/// Match an import plan against the library and write (or report) it
+async fn import_plan(config: &Config, plan: ImportPlan, dry_run: bool, json: bool) -> Result<()> {
+    let scanner = LibraryScanner::new(S3Client::new(&config.storage).await?);
+    let library = LibraryIndex::new(&scanner.scan_library().await?);
+    let pool = db::create_pool(&config.database.url).await?;
+
+    let report = import::apply(&plan, &library, &pool, dry_run).await?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    let verb = if dry_run { "would import" } else { "imported" };
+    println!("Source:      {}", report.source);
+    println!("Users:       {}", report.users.join(", "));
+    println!(
+        "Progress:    {} {}, skipped {} already tracked",
+        verb, report.progress_imported, report.progress_existing
+    );
+    println!(
+        "Collections: {} {} ({} books added)",
+        verb, report.collections, report.collection_books
+    );
+    if !report.unmatched.is_empty() {
+        println!("Not in library ({}):", report.unmatched.len());
+        for label in &report.unmatched {
+            println!("  {}", label);
+        }
+    }
+    for note in &report.notes {
+        println!("Note: {}", note);
+    }
+    Ok(())
+}
+
+/// A book folder in a local Calibre library
+#[derive(Debug)]
+struct CalibreBook {
+    /// `Author/Title` key prefix
+    prefix: String,
+    /// Title from metadata.opf, when present and parseable
+    title: Option<String>,
+    /// Local file and its object key
+    files: Vec<(PathBuf, String)>,
+}
+
+/// Find `Author/Title/` folders holding at least one ebook format
+///
+/// Hidden folders such as `.caltrash` and files at the library root
+/// (`metadata.db`) are ignored.
+fn calibre_books(root: &Path) -> Result<Vec<CalibreBook>> {
+    let mut books = Vec::new();
+
+    for author in sorted_dirs(root)? {
+        for title in sorted_dirs(&author)? {
+            let prefix = format!("{}/{}", file_name(&author), file_name(&title));
+
+            let mut files = Vec::new();
+            let mut has_format = false;
+            let mut metadata = None;
+            for entry in fs::read_dir(&title)? {
+                let path = entry?.path();
+                if !path.is_file() {
+                    continue;
+                }
+
+                let name = file_name(&path);
+                if name == "metadata.opf" {
+                    metadata = fs::read_to_string(&path)
+                        .ok()
+                        .and_then(|xml| CalibreMetadata::parse(&xml).ok());
+                }
+                if let Some(ext) = path.extension() {
+                    has_format |=
+                        FormatType::from_extension(&ext.to_string_lossy()) != FormatType::Other;
+                }
+                files.push((path, format!("{}/{}", prefix, name)));
+            }
+
+            if has_format {
+                files.sort();
+                books.push(CalibreBook {
+                    prefix,
+                    title: metadata.and_then(|m| m.title),
+                    files,
+                });
+            }
+        }
+    }
+
+    Ok(books)
+}
+
+/// Non-hidden subdirectories, sorted by name
+fn sorted_dirs(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut dirs = Vec::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        if path.is_dir() && !file_name(&path).starts_with('.') {
+            dirs.push(path);
+        }
+    }
+    dirs.sort();
+    Ok(dirs)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calibre_books() {
+        let root = tempfile::tempdir().unwrap();
+        let book = root.path().join("Jane Austen/Emma (12)");
+        fs::create_dir_all(&book).unwrap();
+        fs::write(book.join("Emma - Jane Austen.epub"), b"PK").unwrap();
+        fs::write(book.join("cover.jpg"), b"jpg").unwrap();
+        fs::write(
+            book.join("metadata.opf"),
+            r#"<?xml version="1.0"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="2.0">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:title>Emma</dc:title>
+  </metadata>
+</package>"#,
+        )
+        .unwrap();
+
+        // No ebook format, hidden folder, and a root-level file
+        fs::create_dir_all(root.path().join("Jane Austen/Notes")).unwrap();
+        fs::write(root.path().join("Jane Austen/Notes/notes.txt"), b"").unwrap();
+        fs::create_dir_all(root.path().join(".caltrash/x/y")).unwrap();
+        fs::write(root.path().join(".caltrash/x/y/a.epub"), b"PK").unwrap();
+        fs::write(root.path().join("metadata.db"), b"").unwrap();
+
+        let books = calibre_books(root.path()).unwrap();
+        assert_eq!(books.len(), 1);
+        assert_eq!(books[0].prefix, "Jane Austen/Emma (12)");
+        assert_eq!(books[0].title.as_deref(), Some("Emma"));
+
+        let keys: Vec<_> = books[0].files.iter().map(|(_, key)| key.as_str()).collect();
+        assert_eq!(
+            keys,
+            vec![
+                "Jane Austen/Emma (12)/Emma - Jane Austen.epub",
+                "Jane Austen/Emma (12)/cover.jpg",
+                "Jane Austen/Emma (12)/metadata.opf",
+            ]
+        );
+    }
+}
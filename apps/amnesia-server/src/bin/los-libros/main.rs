@@ -0,0 +1,325 @@
+//! los-libros: library maintenance CLI
+//!
+//! Runs the server's library, database, and storage code directly, so admins
+//! can script maintenance without going through the HTTP API. Configuration
+//! is read from the environment (and `.env`) exactly as `amnesia-server`
+//! reads it.
+//!
+//! ```text
+//! los-libros scan [--json]
+//! los-libros reindex [--books | --highlights]
+//! los-libros convert book.epub book.pdf [--width W --height H --em EM]
+//! los-libros verify [--prefix Author/]
+//! los-libros analyze-text [--force]
+//! los-libros check-scans [--force]
+//! los-libros export-annotations [--book-id ID] [--user-id ID] [-o FILE]
+//! los-libros export-vault -o notes.zip [--user-id ID]
+//! los-libros import-calibre ~/Calibre\ Library [--dry-run] [--overwrite]
+//! los-libros import-calibre-web app.db [--dry-run] [--json]
+//! los-libros import-komga --url URL --user NAME [--password PW] [--dry-run] [--json]
+//! ```
+//!
+//! Results go to stdout and logs to stderr, so output can be piped.
+
+mod commands;
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+use amnesia_server::config::Config;
+use amnesia_server::formats::isolation;
+
+#[derive(Debug, Parser)]
+#[command(name = "los-libros", version, about = "Amnesia library maintenance")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Scan the S3 library and print statistics
+    Scan {
+        /// Print every book as JSON instead of a summary
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Rebuild the full-text search indexes (both by default)
+    Reindex {
+        /// Only rebuild the books index
+        #[arg(long, conflicts_with = "highlights")]
+        books: bool,
+
+        /// Only rebuild the highlights index
+        #[arg(long)]
+        highlights: bool,
+    },
+
+    /// Convert an EPUB to PDF
+    Convert {
+        /// EPUB file to read
+        input: PathBuf,
+
+        /// PDF file to write
+        output: PathBuf,
+
+        /// Page width in points
+        #[arg(long, default_value_t = 595.0)]
+        width: f32,
+
+        /// Page height in points
+        #[arg(long, default_value_t = 842.0)]
+        height: f32,
+
+        /// Em size in points
+        #[arg(long, default_value_t = 12.0)]
+        em: f32,
+    },
+
+    /// Parse every EPUB and PDF in the library and report broken files
+    Verify {
+        /// Only verify keys under this prefix
+        #[arg(long)]
+        prefix: Option<String>,
+    },
+
+    /// Compute readability statistics for books that are new or changed
+    AnalyzeText {
+        /// Re-analyze every book
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Look for repeated pages in PDFs that are new or changed, and list them
+    CheckScans {
+        /// Re-check every PDF
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Export annotations as JSON
+    ExportAnnotations {
+        /// Only export annotations for this book
+        #[arg(long)]
+        book_id: Option<String>,
+
+        /// Only export annotations for this user
+        #[arg(long)]
+        user_id: Option<String>,
+
+        /// Write to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Export highlights and notes as a zip of Markdown pages (Obsidian, Logseq)
+    ExportVault {
+        /// Zip file to write
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Only export this user's highlights and notes
+        #[arg(long)]
+        user_id: Option<String>,
+
+        /// Only export notes in this highlight palette category
+        #[arg(long)]
+        category: Option<String>,
+    },
+
+    /// Upload a local Calibre library to the S3 bucket
+    ImportCalibre {
+        /// Calibre library directory (the one containing metadata.db)
+        library: PathBuf,
+
+        /// List what would be uploaded without uploading
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Replace objects that already exist in the bucket
+        #[arg(long)]
+        overwrite: bool,
+    },
+
+    /// Import users' reading progress and shelves from Calibre-Web
+    ImportCalibreWeb {
+        /// Calibre-Web's app.db
+        app_db: PathBuf,
+
+        /// Report what would be imported without writing
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Import read lists and reading progress from Komga
+    ImportKomga {
+        /// Komga base URL, e.g. http://localhost:25600
+        #[arg(long)]
+        url: String,
+
+        /// Komga account; its progress is imported under this name
+        #[arg(long)]
+        user: String,
+
+        /// Account password (default: $KOMGA_PASSWORD)
+        #[arg(long)]
+        password: Option<String>,
+
+        /// Report what would be imported without writing
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    // `verify` re-executes this binary as a parse worker; stdout carries
+    // the IPC response, so this must run before anything else
+    if std::env::args().nth(1).as_deref() == Some(isolation::WORKER_ARG) {
+        return ExitCode::from(isolation::run_worker().await as u8);
+    }
+
+    let cli = Cli::parse();
+
+    // Load .env first so RUST_LOG from it applies
+    dotenvy::dotenv().ok();
+
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "amnesia_server=warn".into()),
+        )
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .init();
+
+    let config = Config::from_env().unwrap_or_else(|e| {
+        tracing::warn!("Failed to load config from env: {}, using defaults", e);
+        Config::default()
+    });
+
+    let result = match cli.command {
+        Command::Scan { json } => commands::scan(&config, json).await,
+        Command::Reindex { books, highlights } => {
+            // Neither flag means both indexes
+            let both = !books && !highlights;
+            commands::reindex(&config, books || both, highlights || both).await
+        }
+        Command::Convert {
+            input,
+            output,
+            width,
+            height,
+            em,
+        } => commands::convert(&input, &output, width, height, em),
+        Command::Verify { prefix } => commands::verify(&config, prefix.as_deref()).await,
+        Command::AnalyzeText { force } => commands::analyze_text(&config, force).await,
+        Command::CheckScans { force } => commands::check_scans(&config, force).await,
+        Command::ExportAnnotations {
+            book_id,
+            user_id,
+            output,
+        } => commands::export_annotations(&config, book_id, user_id, output.as_deref()).await,
+        Command::ExportVault {
+            output,
+            user_id,
+            category,
+        } => {
+            commands::export_vault(&config, &output, user_id.as_deref(), category.as_deref())
+                .await
+        }
+        Command::ImportCalibre {
+            library,
+            dry_run,
+            overwrite,
+        } => commands::import_calibre(&config, &library, dry_run, overwrite).await,
+        Command::ImportCalibreWeb {
+            app_db,
+            dry_run,
+            json,
+        } => commands::import_calibre_web(&config, &app_db, dry_run, json).await,
+        Command::ImportKomga {
+            url,
+            user,
+            password,
+            dry_run,
+            json,
+        } => {
+            let password = password
+                .or_else(|| std::env::var("KOMGA_PASSWORD").ok())
+                .unwrap_or_default();
+            commands::import_komga(&config, &url, &user, &password, dry_run, json).await
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {:#}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_parse_subcommands() {
+        let cli = Cli::parse_from(["los-libros", "reindex", "--books"]);
+        assert!(matches!(
+            cli.command,
+            Command::Reindex {
+                books: true,
+                highlights: false
+            }
+        ));
+
+        let cli = Cli::parse_from(["los-libros", "import-calibre", "/books", "--dry-run"]);
+        assert!(matches!(
+            cli.command,
+            Command::ImportCalibre {
+                dry_run: true,
+                overwrite: false,
+                ..
+            }
+        ));
+
+        let cli = Cli::parse_from([
+            "los-libros",
+            "import-komga",
+            "--url",
+            "http://k",
+            "--user",
+            "ana",
+        ]);
+        assert!(matches!(
+            cli.command,
+            Command::ImportKomga {
+                password: None,
+                dry_run: false,
+                ..
+            }
+        ));
+
+        assert!(Cli::try_parse_from(["los-libros", "reindex", "--books", "--highlights"]).is_err());
+    }
+}
@@ -0,0 +1,227 @@
+//! LRU cache bounded by bytes
+//!
+//! Rendered pages range from a few KB (thumbnails) to several MB (high-DPI
+//! PNGs), so capping caches by entry count says little about memory use.
+//! [`ByteLruCache`] tracks the size of every entry, evicts least recently
+//! used entries until the total fits the configured budget, and keeps hit,
+//! miss, and eviction counters for diagnostics.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use lru::LruCache;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Approximate heap size of a cached value
+pub trait ByteSize {
+    fn byte_size(&self) -> usize;
+}
+
+impl ByteSize for Vec<u8> {
+    fn byte_size(&self) -> usize {
+        self.len()
+    }
+}
+
+/// LRU cache that evicts by total byte size
+pub struct ByteLruCache<K: Hash + Eq, V> {
+    entries: LruCache<K, (V, usize)>,
+    bytes: usize,
+    budget: usize,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+impl<K: Hash + Eq, V: ByteSize> ByteLruCache<K, V> {
+    /// Create a cache holding at most `budget` bytes
+    pub fn new(budget: usize) -> Self {
+        Self {
+            entries: LruCache::unbounded(),
+            bytes: 0,
+            budget,
+            hits: 0,
+            misses: 0,
+            evictions: 0,
+        }
+    }
+
+    /// Look up an entry, marking it most recently used
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        match self.entries.get(key) {
+            Some((value, _)) => {
+                self.hits += 1;
+                Some(value)
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Insert an entry, evicting older entries until the budget is met
+    ///
+    /// Values larger than the whole budget are not cached.
+    pub fn put(&mut self, key: K, value: V) {
+        let size = value.byte_size();
+        if size > self.budget {
+            return;
+        }
+
+        if let Some((_, old_size)) = self.entries.put(key, (value, size)) {
+            self.bytes -= old_size;
+        }
+        self.bytes += size;
+
+        while self.bytes > self.budget {
+            match self.entries.pop_lru() {
+                Some((_, (_, evicted))) => {
+                    self.bytes -= evicted;
+                    self.evictions += 1;
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Remove every entry whose key matches `predicate`
+    pub fn remove_where(&mut self, predicate: impl Fn(&K) -> bool)
+    where
+        K: Clone,
+    {
+        let keys: Vec<K> = self
+            .entries
+            .iter()
+            .filter(|(key, _)| predicate(key))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in keys {
+            if let Some((_, size)) = self.entries.pop(&key) {
+                self.bytes -= size;
+            }
+        }
+    }
+
+    /// Remove all entries (counters are kept)
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.bytes = 0;
+    }
+
+    /// Number of cached entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Bytes currently cached
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Byte budget
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Bytes cached per group (e.g. per document ID)
+    pub fn bytes_by<G: Hash + Eq>(&self, group: impl Fn(&K) -> G) -> HashMap<G, usize> {
+        let mut totals = HashMap::new();
+        for (key, (_, size)) in self.entries.iter() {
+            *totals.entry(group(key)).or_insert(0) += size;
+        }
+        totals
+    }
+
+    /// Size and effectiveness counters
+    pub fn stats(&self) -> ByteCacheStats {
+        let lookups = self.hits + self.misses;
+        ByteCacheStats {
+            entries: self.entries.len(),
+            bytes: self.bytes,
+            budget_bytes: self.budget,
+            hits: self.hits,
+            misses: self.misses,
+            evictions: self.evictions,
+            hit_rate: if lookups == 0 {
+                0.0
+            } else {
+                self.hits as f64 / lookups as f64
+            },
+        }
+    }
+}
+
+/// Size and effectiveness counters for a [`ByteLruCache`]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ByteCacheStats {
+    pub entries: usize,
+    pub bytes: usize,
+    pub budget_bytes: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    /// Hits / (hits + misses), 0 before the first lookup
+    pub hit_rate: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evicts_by_bytes() {
+        let mut cache = ByteLruCache::new(10);
+        cache.put("a", vec![0u8; 4]);
+        cache.put("b", vec![0u8; 4]);
+        assert_eq!(cache.bytes(), 8);
+
+        // Touch "a" so "b" is the eviction candidate
+        assert!(cache.get(&"a").is_some());
+        cache.put("c", vec![0u8; 4]);
+
+        assert!(cache.get(&"b").is_none());
+        assert_eq!(cache.bytes(), 8);
+        assert_eq!(cache.stats().evictions, 1);
+    }
+
+    #[test]
+    fn test_replace_and_oversized() {
+        let mut cache = ByteLruCache::new(10);
+        cache.put("a", vec![0u8; 6]);
+        cache.put("a", vec![0u8; 2]);
+        assert_eq!(cache.bytes(), 2);
+
+        cache.put("huge", vec![0u8; 11]);
+        assert!(cache.get(&"huge").is_none());
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_stats_and_grouping() {
+        let mut cache = ByteLruCache::new(100);
+        cache.put(("doc-1", 0), vec![0u8; 10]);
+        cache.put(("doc-1", 1), vec![0u8; 5]);
+        cache.put(("doc-2", 0), vec![0u8; 7]);
+
+        let by_doc = cache.bytes_by(|(doc, _)| *doc);
+        assert_eq!(by_doc["doc-1"], 15);
+        assert_eq!(by_doc["doc-2"], 7);
+
+        cache.get(&("doc-1", 0));
+        cache.get(&("doc-3", 0));
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+        assert_eq!(stats.hit_rate, 0.5);
+
+        cache.remove_where(|(doc, _)| *doc == "doc-1");
+        assert_eq!(cache.bytes(), 7);
+    }
+}
@@ -1,13 +1,31 @@
 //! Configuration management for Los Libros Server
 
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::env;
+use std::path::PathBuf;
+
+use crate::formats::epub::EpubLimits;
+use crate::opds::DEFAULT_ENTRY_TEMPLATE;
+use crate::scheduler::{
+    TASK_CACHE_EVICTION, TASK_DUPLICATE_PAGES, TASK_FEED_INGEST, TASK_LIBRARY_RESCAN,
+    TASK_OCR_RETRY, TASK_STORAGE_TIERING, TASK_TEXT_STATS, TASK_UPLOAD_CLEANUP,
+};
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
     pub server: ServerConfig,
     pub storage: StorageConfig,
     pub database: DatabaseConfig,
+    pub cache: CacheBudgetConfig,
+    pub isolation: IsolationConfig,
+    pub epub: EpubLimits,
+    pub scheduler: SchedulerConfig,
+    pub ingest: IngestConfig,
+    pub tiering: TieringConfig,
+    pub fonts: FontConfig,
+    pub opds: OpdsConfig,
+    pub feeds: FeedsConfig,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -40,6 +58,228 @@ pub struct DatabaseConfig {
     pub url: String,
 }
 
+/// Byte budgets for the in-memory render and text caches
+#[derive(Debug, Clone, Deserialize)]
+pub struct CacheBudgetConfig {
+    /// Rendered pages/chapters in the document cache
+    pub render_bytes: usize,
+    /// Structured text in the document cache
+    pub text_bytes: usize,
+    /// Rendered pages in the legacy PDF cache
+    pub pdf_page_bytes: usize,
+    /// Text layers in the legacy PDF cache
+    pub pdf_text_bytes: usize,
+}
+
+impl Default for CacheBudgetConfig {
+    fn default() -> Self {
+        CacheBudgetConfig {
+            render_bytes: 256 * MIB,
+            text_bytes: 64 * MIB,
+            pdf_page_bytes: 128 * MIB,
+            pdf_text_bytes: 32 * MIB,
+        }
+    }
+}
+
+/// Where newly uploaded documents are first parsed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IsolationMode {
+    /// Parse directly in the server process
+    #[default]
+    InProcess,
+    /// Parse in a worker subprocess before accepting the document
+    Subprocess,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IsolationConfig {
+    pub mode: IsolationMode,
+    /// Seconds a parse worker may run before it is killed
+    pub timeout_secs: u64,
+}
+
+impl Default for IsolationConfig {
+    fn default() -> Self {
+        IsolationConfig {
+            mode: IsolationMode::InProcess,
+            timeout_secs: 30,
+        }
+    }
+}
+
+/// Cron schedules for background maintenance tasks
+#[derive(Debug, Clone, Deserialize)]
+pub struct SchedulerConfig {
+    /// Cron expression (UTC) per task name; tasks without one do not run
+    pub schedules: HashMap<String, String>,
+    /// Upper bound of the random delay added to each run (seconds)
+    pub jitter_secs: u64,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        SchedulerConfig {
+            schedules: [
+                (TASK_UPLOAD_CLEANUP, "*/5 * * * *"),
+                (TASK_CACHE_EVICTION, "*/15 * * * *"),
+                (TASK_LIBRARY_RESCAN, "0 * * * *"),
+                (TASK_FEED_INGEST, "*/30 * * * *"),
+                (TASK_TEXT_STATS, "30 3 * * *"),
+                (TASK_DUPLICATE_PAGES, "0 4 * * *"),
+                (TASK_STORAGE_TIERING, "0 5 * * 0"),
+                (TASK_OCR_RETRY, "*/10 * * * *"),
+            ]
+            .into_iter()
+            .map(|(task, schedule)| (task.to_string(), schedule.to_string()))
+            .collect(),
+            jitter_secs: 30,
+        }
+    }
+}
+
+/// RSS/Atom feeds whose articles are filed into the library
+#[derive(Debug, Clone, Deserialize)]
+pub struct IngestConfig {
+    /// Feed URLs; ingestion is off when empty
+    pub feeds: Vec<String>,
+    /// Collection new articles are added to
+    pub collection: String,
+    /// Owner of that collection; `None` shares it with every user
+    pub user_id: Option<String>,
+    /// Fetch each article's page for the full text rather than relying on
+    /// the content in the feed
+    pub fetch_articles: bool,
+    /// Newest items considered per feed on each poll
+    pub max_items: usize,
+}
+
+impl Default for IngestConfig {
+    fn default() -> Self {
+        IngestConfig {
+            feeds: Vec::new(),
+            collection: "Inbox".to_string(),
+            user_id: None,
+            fetch_articles: true,
+            max_items: 10,
+        }
+    }
+}
+
+/// Moving books nobody opens to a cheaper storage class
+#[derive(Debug, Clone, Deserialize)]
+pub struct TieringConfig {
+    /// Days since a book file was last served before it is archived;
+    /// tiering is off when 0
+    pub cold_after_days: u64,
+    /// S3 storage class archived files are moved to
+    pub storage_class: String,
+    /// Days a restored copy of an archived file is kept readable
+    pub restore_days: i32,
+    /// Most files archived per run
+    pub max_per_run: usize,
+}
+
+impl Default for TieringConfig {
+    fn default() -> Self {
+        TieringConfig {
+            cold_after_days: 0,
+            storage_class: "GLACIER".to_string(),
+            restore_days: 7,
+            max_per_run: 100,
+        }
+    }
+}
+
+/// Fonts MuPDF uses for text a document has no font for
+///
+/// Documents that name a font without embedding it, or contain text in a
+/// script none of their fonts cover (CJK, Arabic), otherwise render with
+/// MuPDF's built-in fonts, or as boxes when those lack the glyphs.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FontConfig {
+    /// Directories searched for font files (`.ttf`, `.otf`, `.ttc`, `.otc`)
+    /// by name
+    pub fallback_dirs: Vec<PathBuf>,
+    /// Font file to use per script (`han`, `hangul`, `arabic`, ...) or per
+    /// requested font name; a bare file name is looked up in
+    /// `fallback_dirs`
+    pub substitutions: HashMap<String, String>,
+}
+
+/// How OPDS entries describe books
+#[derive(Debug, Clone, Deserialize)]
+pub struct OpdsConfig {
+    /// Template for each entry's HTML content; see `opds::EntryTemplate`
+    /// for the placeholders
+    pub entry_template: String,
+    /// Characters of the description in an entry's content
+    pub description_chars: usize,
+    /// Characters of the plain-text summary
+    pub summary_chars: usize,
+    /// Width of the cover thumbnail in pixels
+    pub cover_width: u32,
+}
+
+impl Default for OpdsConfig {
+    fn default() -> Self {
+        OpdsConfig {
+            entry_template: DEFAULT_ENTRY_TEMPLATE.to_string(),
+            description_chars: 1000,
+            summary_chars: 200,
+            cover_width: 160,
+        }
+    }
+}
+
+/// Who may read the new-books feeds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FeedVisibility {
+    /// The feeds are not served
+    Off,
+    /// Anyone who can reach the server, like the OPDS catalog
+    #[default]
+    Public,
+    /// Requests with `?token=` set to [`FeedsConfig::token`]
+    Token,
+}
+
+/// Feeds of newly added books (`/feeds/new-books.atom`, `.json`)
+#[derive(Debug, Clone, Deserialize)]
+pub struct FeedsConfig {
+    pub visibility: FeedVisibility,
+    /// Secret for [`FeedVisibility::Token`]; the feeds are not served
+    /// without one
+    pub token: Option<String>,
+    /// Books per feed, newest first
+    pub limit: usize,
+    pub title: String,
+}
+
+impl Default for FeedsConfig {
+    fn default() -> Self {
+        FeedsConfig {
+            visibility: FeedVisibility::default(),
+            token: None,
+            limit: 50,
+            title: "New books".to_string(),
+        }
+    }
+}
+
+const MIB: usize = 1024 * 1024;
+
+/// Read a size in MiB from the environment, falling back to `default` bytes
+fn env_mib(name: &str, default: usize) -> usize {
+    env::var(name)
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .map(|mib| mib * MIB)
+        .unwrap_or(default)
+}
+
 impl Default for Config {
     fn default() -> Self {
         Config {
@@ -58,6 +298,15 @@ impl Default for Config {
             database: DatabaseConfig {
                 url: "sqlite:./libros.db".to_string(),
             },
+            cache: CacheBudgetConfig::default(),
+            isolation: IsolationConfig::default(),
+            epub: EpubLimits::default(),
+            scheduler: SchedulerConfig::default(),
+            ingest: IngestConfig::default(),
+            tiering: TieringConfig::default(),
+            fonts: FontConfig::default(),
+            opds: OpdsConfig::default(),
+            feeds: FeedsConfig::default(),
         }
     }
 }
@@ -88,6 +337,162 @@ impl Config {
             database: DatabaseConfig {
                 url: env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite:./libros.db".to_string()),
             },
+            cache: {
+                let defaults = CacheBudgetConfig::default();
+                CacheBudgetConfig {
+                    render_bytes: env_mib("CACHE_RENDER_MB", defaults.render_bytes),
+                    text_bytes: env_mib("CACHE_TEXT_MB", defaults.text_bytes),
+                    pdf_page_bytes: env_mib("PDF_CACHE_PAGE_MB", defaults.pdf_page_bytes),
+                    pdf_text_bytes: env_mib("PDF_CACHE_TEXT_MB", defaults.pdf_text_bytes),
+                }
+            },
+            isolation: IsolationConfig {
+                mode: match env::var("DOCUMENT_ISOLATION").unwrap_or_default().as_str() {
+                    "subprocess" => IsolationMode::Subprocess,
+                    _ => IsolationMode::InProcess,
+                },
+                timeout_secs: env::var("ISOLATION_TIMEOUT_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(30),
+            },
+            epub: {
+                let defaults = EpubLimits::default();
+                EpubLimits {
+                    layout_timeout_secs: env::var("EPUB_LAYOUT_TIMEOUT_SECS")
+                        .ok()
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or(defaults.layout_timeout_secs),
+                    max_pages: env::var("EPUB_MAX_PAGES")
+                        .ok()
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or(defaults.max_pages),
+                    max_chapter_bytes: env_mib(
+                        "EPUB_MAX_CHAPTER_MB",
+                        defaults.max_chapter_bytes as usize,
+                    ) as u64,
+                }
+            },
+            scheduler: {
+                let defaults = SchedulerConfig::default();
+                // SCHEDULE_<TASK> overrides a task's schedule; "off" disables it
+                let schedules = defaults
+                    .schedules
+                    .into_iter()
+                    .filter_map(|(task, default)| {
+                        let schedule = env::var(format!("SCHEDULE_{}", task.to_uppercase()))
+                            .unwrap_or(default);
+                        let schedule = schedule.trim();
+                        (!schedule.is_empty() && schedule != "off")
+                            .then(|| (task, schedule.to_string()))
+                    })
+                    .collect();
+                SchedulerConfig {
+                    schedules,
+                    jitter_secs: env::var("SCHEDULER_JITTER_SECS")
+                        .ok()
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or(defaults.jitter_secs),
+                }
+            },
+            ingest: {
+                let defaults = IngestConfig::default();
+                IngestConfig {
+                    // Separated by commas or whitespace
+                    feeds: env::var("INGEST_FEEDS")
+                        .unwrap_or_default()
+                        .split(|c: char| c == ',' || c.is_whitespace())
+                        .filter(|url| !url.is_empty())
+                        .map(str::to_string)
+                        .collect(),
+                    collection: env::var("INGEST_COLLECTION").unwrap_or(defaults.collection),
+                    user_id: env::var("INGEST_USER_ID").ok().filter(|id| !id.is_empty()),
+                    fetch_articles: env::var("INGEST_FETCH_ARTICLES")
+                        .map(|v| !matches!(v.as_str(), "0" | "false" | "off"))
+                        .unwrap_or(defaults.fetch_articles),
+                    max_items: env::var("INGEST_MAX_ITEMS")
+                        .ok()
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or(defaults.max_items),
+                }
+            },
+            tiering: {
+                let defaults = TieringConfig::default();
+                TieringConfig {
+                    cold_after_days: env::var("TIERING_COLD_AFTER_DAYS")
+                        .ok()
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or(defaults.cold_after_days),
+                    storage_class: env::var("TIERING_STORAGE_CLASS")
+                        .ok()
+                        .filter(|class| !class.is_empty())
+                        .unwrap_or(defaults.storage_class),
+                    restore_days: env::var("TIERING_RESTORE_DAYS")
+                        .ok()
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or(defaults.restore_days),
+                    max_per_run: env::var("TIERING_MAX_PER_RUN")
+                        .ok()
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or(defaults.max_per_run),
+                }
+            },
+            fonts: FontConfig {
+                // Separated like PATH
+                fallback_dirs: env::var_os("FONT_FALLBACK_DIRS")
+                    .map(|dirs| env::split_paths(&dirs).collect())
+                    .unwrap_or_default(),
+                // "han=NotoSansCJKsc-Regular.otf,Times New Roman=/fonts/Tinos.ttf"
+                substitutions: env::var("FONT_SUBSTITUTIONS")
+                    .unwrap_or_default()
+                    .split(',')
+                    .filter_map(|entry| entry.split_once('='))
+                    .map(|(key, file)| (key.trim().to_string(), file.trim().to_string()))
+                    .filter(|(key, file)| !key.is_empty() && !file.is_empty())
+                    .collect(),
+            },
+            opds: {
+                let defaults = OpdsConfig::default();
+                OpdsConfig {
+                    entry_template: env::var("OPDS_ENTRY_TEMPLATE")
+                        .ok()
+                        .filter(|template| !template.trim().is_empty())
+                        .unwrap_or(defaults.entry_template),
+                    description_chars: env::var("OPDS_DESCRIPTION_CHARS")
+                        .ok()
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or(defaults.description_chars),
+                    summary_chars: env::var("OPDS_SUMMARY_CHARS")
+                        .ok()
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or(defaults.summary_chars),
+                    cover_width: env::var("OPDS_COVER_WIDTH")
+                        .ok()
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or(defaults.cover_width),
+                }
+            },
+            feeds: {
+                let defaults = FeedsConfig::default();
+                FeedsConfig {
+                    visibility: match env::var("FEEDS_VISIBILITY").unwrap_or_default().as_str() {
+                        "off" => FeedVisibility::Off,
+                        "token" => FeedVisibility::Token,
+                        _ => FeedVisibility::Public,
+                    },
+                    token: env::var("FEEDS_TOKEN")
+                        .ok()
+                        .filter(|token| !token.is_empty()),
+                    limit: env::var("FEEDS_LIMIT")
+                        .ok()
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or(defaults.limit),
+                    title: env::var("FEEDS_TITLE")
+                        .ok()
+                        .filter(|title| !title.trim().is_empty())
+                        .unwrap_or(defaults.title),
+                }
+            },
         })
     }
 }
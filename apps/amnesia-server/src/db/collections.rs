@@ -0,0 +1,210 @@
+//! Collection database operations
+//!
+//! A collection is a named, ordered list of books: a user's shelf, a shared
+//! read list, or an inbox that other services file books into. Collections
+//! with no `user_id` are shared by every user.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+use crate::error::Result;
+
+/// Collection record
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Collection {
+    pub id: String,
+    pub name: String,
+    pub user_id: Option<String>,
+    pub description: Option<String>,
+    /// Where the collection came from ('user', 'calibre-web', 'komga', ...)
+    pub source: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// All columns to select for collections
+const COLLECTION_COLUMNS: &str = "id, name, user_id, description, source, created_at, updated_at";
+
+/// Collection repository
+pub struct CollectionRepository<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl<'a> CollectionRepository<'a> {
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Get a collection by ID
+    pub async fn get(&self, id: &str) -> Result<Option<Collection>> {
+        let query = format!(
+            "SELECT {} FROM collections WHERE id = ?",
+            COLLECTION_COLUMNS
+        );
+        let collection = sqlx::query_as::<_, Collection>(&query)
+            .bind(id)
+            .fetch_optional(self.pool)
+            .await?;
+
+        Ok(collection)
+    }
+
+    /// Find a collection by name for a user (`None` for shared collections)
+    pub async fn find_by_name(
+        &self,
+        name: &str,
+        user_id: Option<&str>,
+    ) -> Result<Option<Collection>> {
+        let query = format!(
+            "SELECT {} FROM collections WHERE name = ? AND user_id IS ?",
+            COLLECTION_COLUMNS
+        );
+        let collection = sqlx::query_as::<_, Collection>(&query)
+            .bind(name)
+            .bind(user_id)
+            .fetch_optional(self.pool)
+            .await?;
+
+        Ok(collection)
+    }
+
+    /// Get the collection with this name, creating it if needed
+    pub async fn get_or_create(
+        &self,
+        name: &str,
+        user_id: Option<&str>,
+        source: &str,
+    ) -> Result<Collection> {
+        if let Some(existing) = self.find_by_name(name, user_id).await? {
+            return Ok(existing);
+        }
+
+        let now = Utc::now().to_rfc3339();
+        let collection = Collection {
+            id: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            user_id: user_id.map(str::to_string),
+            description: None,
+            source: source.to_string(),
+            created_at: now.clone(),
+            updated_at: now,
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO collections (id, name, user_id, description, source, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&collection.id)
+        .bind(&collection.name)
+        .bind(&collection.user_id)
+        .bind(&collection.description)
+        .bind(&collection.source)
+        .bind(&collection.created_at)
+        .bind(&collection.updated_at)
+        .execute(self.pool)
+        .await?;
+
+        Ok(collection)
+    }
+
+    /// List collections visible to a user, including shared ones
+    pub async fn list(&self, user_id: Option<&str>) -> Result<Vec<Collection>> {
+        let query = format!(
+            "SELECT {} FROM collections WHERE user_id = ? OR user_id IS NULL ORDER BY name",
+            COLLECTION_COLUMNS
+        );
+        let collections = sqlx::query_as::<_, Collection>(&query)
+            .bind(user_id)
+            .fetch_all(self.pool)
+            .await?;
+
+        Ok(collections)
+    }
+
+    /// Add a book at the end of a collection
+    ///
+    /// Returns `false` if the book was already in it.
+    pub async fn add_book(&self, collection_id: &str, book_id: &str) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO collection_books (collection_id, book_id, position)
+            SELECT ?, ?, COALESCE(MAX(position) + 1, 0)
+            FROM collection_books WHERE collection_id = ?
+            "#,
+        )
+        .bind(collection_id)
+        .bind(book_id)
+        .bind(collection_id)
+        .execute(self.pool)
+        .await?;
+
+        if result.rows_affected() > 0 {
+            sqlx::query("UPDATE collections SET updated_at = ? WHERE id = ?")
+                .bind(Utc::now().to_rfc3339())
+                .bind(collection_id)
+                .execute(self.pool)
+                .await?;
+        }
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Book IDs in a collection, in order
+    pub async fn books(&self, collection_id: &str) -> Result<Vec<String>> {
+        let rows: Vec<(String,)> = sqlx::query_as(
+            "SELECT book_id FROM collection_books WHERE collection_id = ? ORDER BY position",
+        )
+        .bind(collection_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|(id,)| id).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::initialize_schema;
+
+    #[tokio::test]
+    async fn test_collections() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        initialize_schema(&pool).await.unwrap();
+        let repo = CollectionRepository::new(&pool);
+
+        let shelf = repo
+            .get_or_create("To Read", Some("ana"), "user")
+            .await
+            .unwrap();
+        let again = repo
+            .get_or_create("To Read", Some("ana"), "user")
+            .await
+            .unwrap();
+        assert_eq!(shelf.id, again.id);
+        assert_eq!(repo.get(&shelf.id).await.unwrap().unwrap().name, "To Read");
+        assert!(repo.get("missing").await.unwrap().is_none());
+
+        let shared = repo.get_or_create("To Read", None, "komga").await.unwrap();
+        assert_ne!(shared.id, shelf.id);
+        assert_eq!(
+            shared.id,
+            repo.get_or_create("To Read", None, "komga")
+                .await
+                .unwrap()
+                .id
+        );
+
+        assert!(repo.add_book(&shelf.id, "b").await.unwrap());
+        assert!(repo.add_book(&shelf.id, "a").await.unwrap());
+        assert!(!repo.add_book(&shelf.id, "b").await.unwrap());
+        assert_eq!(repo.books(&shelf.id).await.unwrap(), vec!["b", "a"]);
+
+        assert_eq!(repo.list(Some("ana")).await.unwrap().len(), 2);
+        assert_eq!(repo.list(Some("ben")).await.unwrap().len(), 1);
+    }
+}
@@ -7,7 +7,9 @@ use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
 use uuid::Uuid;
 
+use super::palette::PaletteColor;
 use crate::error::Result;
+use crate::pagination::{PageParams, SortOrder};
 
 /// Document format for highlights
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -112,6 +114,8 @@ pub struct Highlight {
     pub cfi: String,
     /// PDF page number (1-indexed, None for EPUB)
     pub page: Option<i32>,
+    /// Printed label of the PDF page ("xiv", "A-3"), for citations
+    pub page_label: Option<String>,
     /// Highlighted text
     pub text: String,
     /// Chapter name or "Page N"
@@ -162,6 +166,19 @@ impl Highlight {
             serde_json::from_str(json).ok()
         })
     }
+
+    /// Fill in a missing page label from the book's labels (one per page)
+    pub fn fill_page_label(&mut self, labels: &[String]) {
+        if self.page_label.is_none() {
+            self.page_label = label_for_page(self.page, labels);
+        }
+    }
+}
+
+/// Label of a 1-indexed page
+fn label_for_page(page: Option<i32>, labels: &[String]) -> Option<String> {
+    let index = usize::try_from(page?).ok()?.checked_sub(1)?;
+    labels.get(index).cloned()
 }
 
 /// Create highlight request (supports both EPUB and PDF)
@@ -178,6 +195,8 @@ pub struct CreateHighlight {
     pub cfi: Option<String>,
     /// PDF page number (1-indexed)
     pub page: Option<i32>,
+    /// Printed page label; filled from the PDF when omitted
+    pub page_label: Option<String>,
     /// Highlighted text
     pub text: String,
     /// Chapter name
@@ -198,6 +217,15 @@ pub struct CreateHighlight {
     pub rects: Option<Vec<PdfRect>>,
 }
 
+impl CreateHighlight {
+    /// Fill in a missing page label from the book's labels (one per page)
+    pub fn fill_page_label(&mut self, labels: &[String]) {
+        if self.page_label.is_none() {
+            self.page_label = label_for_page(self.page, labels);
+        }
+    }
+}
+
 /// Update highlight request
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -206,6 +234,51 @@ pub struct UpdateHighlight {
     pub annotation: Option<String>,
 }
 
+/// Sort keys for highlight listings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HighlightSort {
+    /// Creation time
+    #[default]
+    Created,
+    /// Last modification time
+    Updated,
+    /// Reading order within a book (page, then progress)
+    Position,
+}
+
+impl HighlightSort {
+    /// ORDER BY clause for this key
+    fn order_by(self, order: SortOrder) -> String {
+        let dir = order.as_sql();
+        match self {
+            Self::Created => format!("created_at {dir}"),
+            Self::Updated => format!("updated_at {dir}, created_at {dir}"),
+            Self::Position => {
+                format!("COALESCE(page, 0) {dir}, page_percent {dir}, created_at {dir}")
+            }
+        }
+    }
+}
+
+/// Filters, sorting, and pagination for listing highlights
+#[derive(Debug, Default)]
+pub struct HighlightQuery {
+    pub book_id: Option<String>,
+    pub user_id: Option<String>,
+    pub color: Option<String>,
+    /// Palette category, matched by name or hex since older highlights
+    /// stored either
+    pub category: Option<PaletteColor>,
+    pub annotation_type: Option<AnnotationType>,
+    pub document_format: Option<DocumentFormat>,
+    /// Substring match against the highlighted text or note
+    pub text: Option<String>,
+    pub sort: HighlightSort,
+    pub order: SortOrder,
+    pub page: PageParams,
+}
+
 /// Highlight repository
 pub struct HighlightRepository<'a> {
     pool: &'a SqlitePool,
@@ -213,7 +286,7 @@ pub struct HighlightRepository<'a> {
 
 /// All columns to select for highlights
 const HIGHLIGHT_COLUMNS: &str = r#"
-    id, book_id, user_id, document_format, type, cfi, page, text, chapter,
+    id, book_id, user_id, document_format, type, cfi, page, page_label, text, chapter,
     page_percent, color, annotation, text_prefix, text_suffix,
     region_x, region_y, region_width, region_height, rects_json,
     created_at, updated_at
@@ -308,6 +381,68 @@ impl<'a> HighlightRepository<'a> {
         Ok(highlights)
     }
 
+    /// List highlights matching a query, returning one page and the total match count
+    pub async fn list_paged(&self, query: &HighlightQuery) -> Result<(Vec<Highlight>, usize)> {
+        // user_id is always bound so that shared (NULL user) highlights are included
+        let mut conditions = vec!["(user_id = ? OR user_id IS NULL)"];
+        let mut binds: Vec<String> = Vec::new();
+
+        if let Some(ref book_id) = query.book_id {
+            conditions.push("book_id = ?");
+            binds.push(book_id.clone());
+        }
+        if let Some(ref color) = query.color {
+            conditions.push("color = ?");
+            binds.push(color.clone());
+        }
+        if let Some(ref category) = query.category {
+            conditions.push("(color = ? COLLATE NOCASE OR color = ? COLLATE NOCASE)");
+            binds.push(category.name.clone());
+            binds.push(category.color.clone());
+        }
+        if let Some(annotation_type) = query.annotation_type {
+            conditions.push("type = ?");
+            binds.push(annotation_type.to_string());
+        }
+        if let Some(format) = query.document_format {
+            conditions.push("document_format = ?");
+            binds.push(format.to_string());
+        }
+        if let Some(ref text) = query.text {
+            let pattern = contains_pattern(text);
+            conditions.push("(text LIKE ? ESCAPE '\\' OR annotation LIKE ? ESCAPE '\\')");
+            binds.push(pattern.clone());
+            binds.push(pattern);
+        }
+
+        let where_clause = conditions.join(" AND ");
+
+        let count_sql = format!("SELECT COUNT(*) FROM highlights WHERE {}", where_clause);
+        let mut count_query = sqlx::query_as::<_, (i64,)>(&count_sql).bind(&query.user_id);
+        for bind in &binds {
+            count_query = count_query.bind(bind);
+        }
+        let (total,) = count_query.fetch_one(self.pool).await?;
+
+        let list_sql = format!(
+            "SELECT {} FROM highlights WHERE {} ORDER BY {} LIMIT ? OFFSET ?",
+            HIGHLIGHT_COLUMNS,
+            where_clause,
+            query.sort.order_by(query.order)
+        );
+        let mut list_query = sqlx::query_as::<_, Highlight>(&list_sql).bind(&query.user_id);
+        for bind in &binds {
+            list_query = list_query.bind(bind);
+        }
+        let highlights = list_query
+            .bind(query.page.limit() as i64)
+            .bind(query.page.offset() as i64)
+            .fetch_all(self.pool)
+            .await?;
+
+        Ok((highlights, total as usize))
+    }
+
     /// Create a new highlight (supports both EPUB and PDF)
     pub async fn create(
         &self,
@@ -336,12 +471,12 @@ impl<'a> HighlightRepository<'a> {
         sqlx::query(
             r#"
             INSERT INTO highlights (
-                id, book_id, user_id, document_format, type, cfi, page, text, chapter,
-                page_percent, color, annotation, text_prefix, text_suffix,
+                id, book_id, user_id, document_format, type, cfi, page, page_label, text,
+                chapter, page_percent, color, annotation, text_prefix, text_suffix,
                 region_x, region_y, region_width, region_height, rects_json,
                 created_at, updated_at
             )
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(&id)
@@ -351,6 +486,7 @@ impl<'a> HighlightRepository<'a> {
         .bind(annotation_type)
         .bind(cfi)
         .bind(data.page)
+        .bind(&data.page_label)
         .bind(&data.text)
         .bind(&data.chapter)
         .bind(data.page_percent)
@@ -441,14 +577,14 @@ impl<'a> HighlightRepository<'a> {
         user_id: Option<&str>,
         query: &str,
     ) -> Result<Vec<Highlight>> {
-        let search_pattern = format!("%{}%", query);
+        let search_pattern = contains_pattern(query);
 
         let query = format!(
             r#"
             SELECT {}
             FROM highlights
             WHERE (user_id = ? OR user_id IS NULL)
-              AND (text LIKE ? OR annotation LIKE ?)
+              AND (text LIKE ? ESCAPE '\' OR annotation LIKE ? ESCAPE '\')
             ORDER BY created_at DESC
             "#,
             HIGHLIGHT_COLUMNS
@@ -464,3 +600,161 @@ impl<'a> HighlightRepository<'a> {
         Ok(highlights)
     }
 }
+
+/// LIKE pattern matching `text` anywhere, with its wildcards taken literally
+///
+/// Used with `ESCAPE '\'`.
+fn contains_pattern(text: &str) -> String {
+    let mut pattern = String::with_capacity(text.len() + 2);
+    pattern.push('%');
+    for c in text.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{initialize_schema, HighlightPalette};
+
+    async fn setup_test_db() -> SqlitePool {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        initialize_schema(&pool).await.unwrap();
+        pool
+    }
+
+    fn highlight(text: &str, color: &str, page: Option<i32>) -> CreateHighlight {
+        CreateHighlight {
+            document_format: Some(if page.is_some() { "pdf" } else { "epub" }.to_string()),
+            annotation_type: None,
+            cfi: None,
+            page,
+            page_label: None,
+            text: text.to_string(),
+            chapter: None,
+            page_percent: None,
+            color: Some(color.to_string()),
+            annotation: None,
+            text_prefix: None,
+            text_suffix: None,
+            region: None,
+            rects: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_paged_filters_and_counts() {
+        let pool = setup_test_db().await;
+        let repo = HighlightRepository::new(&pool);
+
+        for page in 1..=5 {
+            let color = if page % 2 == 0 { "blue" } else { "yellow" };
+            repo.create(
+                "book-a",
+                None,
+                &highlight(&format!("passage {page}"), color, Some(page)),
+            )
+            .await
+            .unwrap();
+        }
+        repo.create("book-b", None, &highlight("other book", "yellow", None))
+            .await
+            .unwrap();
+
+        let query = HighlightQuery {
+            book_id: Some("book-a".to_string()),
+            sort: HighlightSort::Position,
+            page: PageParams::new(2, 1),
+            ..Default::default()
+        };
+        let (items, total) = repo.list_paged(&query).await.unwrap();
+        assert_eq!(total, 5);
+        assert_eq!(
+            items.iter().map(|h| h.page).collect::<Vec<_>>(),
+            vec![Some(2), Some(3)]
+        );
+
+        let query = HighlightQuery {
+            color: Some("yellow".to_string()),
+            document_format: Some(DocumentFormat::Pdf),
+            ..Default::default()
+        };
+        let (items, total) = repo.list_paged(&query).await.unwrap();
+        assert_eq!(total, 3);
+        assert_eq!(items.len(), 3);
+
+        // A category matches highlights stored by name or by hex
+        repo.create("book-b", None, &highlight("by hex", "#DBEAFE", None))
+            .await
+            .unwrap();
+        let query = HighlightQuery {
+            category: HighlightPalette::default().resolve("blue").cloned(),
+            ..Default::default()
+        };
+        let (_, total) = repo.list_paged(&query).await.unwrap();
+        assert_eq!(total, 3);
+    }
+
+    #[tokio::test]
+    async fn test_text_query_matches_wildcards_literally() {
+        let pool = setup_test_db().await;
+        let repo = HighlightRepository::new(&pool);
+        for text in ["50% faster", "50 times faster", "snake_case", "snakes"] {
+            repo.create("book-a", None, &highlight(text, "yellow", None))
+                .await
+                .unwrap();
+        }
+
+        for (text, expected) in [("50%", "50% faster"), ("snake_", "snake_case")] {
+            let query = HighlightQuery {
+                text: Some(text.to_string()),
+                ..Default::default()
+            };
+            let (items, total) = repo.list_paged(&query).await.unwrap();
+            assert_eq!(total, 1, "query {text:?}");
+            assert_eq!(items[0].text, expected);
+
+            let found = repo.search(None, text).await.unwrap();
+            assert_eq!(found.len(), 1, "query {text:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_page_labels() {
+        let pool = setup_test_db().await;
+        let repo = HighlightRepository::new(&pool);
+        let labels: Vec<String> = ["i", "ii", "1", "2"].map(String::from).to_vec();
+
+        let mut data = highlight("preface", "yellow", Some(2));
+        data.fill_page_label(&labels);
+        let created = repo.create("book-a", None, &data).await.unwrap();
+        assert_eq!(created.page_label.as_deref(), Some("ii"));
+
+        // An explicit label is kept
+        let mut data = highlight("body", "yellow", Some(3));
+        data.page_label = Some("17".to_string());
+        data.fill_page_label(&labels);
+        assert_eq!(data.page_label.as_deref(), Some("17"));
+
+        // Highlights stored without a label get one when read
+        let mut created = repo
+            .create("book-a", None, &highlight("body", "yellow", Some(4)))
+            .await
+            .unwrap();
+        assert_eq!(created.page_label, None);
+        created.fill_page_label(&labels);
+        assert_eq!(created.page_label.as_deref(), Some("2"));
+
+        let mut epub = highlight("no page", "yellow", None);
+        epub.fill_page_label(&labels);
+        assert_eq!(epub.page_label, None);
+        let mut out_of_range = highlight("past the end", "yellow", Some(9));
+        out_of_range.fill_page_label(&labels);
+        assert_eq!(out_of_range.page_label, None);
+    }
+}
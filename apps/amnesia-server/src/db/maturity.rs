@@ -0,0 +1,193 @@
+//! Manual maturity overrides
+//!
+//! Ratings parsed from metadata are a best guess; an admin can pin a book's
+//! [`MaturityRating`] (and optionally its content warnings) here. Overrides
+//! are keyed by [`LibraryBook::stable_id`] and applied on top of the scanned
+//! metadata whenever the library is listed.
+
+use std::collections::HashMap;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use utoipa::ToSchema;
+
+use crate::error::{AppError, Result};
+use crate::library::{LibraryBook, MaturityRating};
+
+/// A manual maturity rating for one book
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MaturityOverride {
+    pub book_id: String,
+    pub rating: MaturityRating,
+    /// Replaces the metadata's content warnings when set
+    pub content_warnings: Option<Vec<String>>,
+    pub updated_at: String,
+}
+
+impl MaturityOverride {
+    /// Replace a book's parsed rating (and warnings, if given)
+    pub fn apply(&self, book: &mut LibraryBook) {
+        book.maturity = Some(self.rating);
+        if let Some(warnings) = &self.content_warnings {
+            book.content_warnings = warnings.clone();
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct MaturityOverrideRow {
+    book_id: String,
+    rating: MaturityRating,
+    /// JSON array
+    content_warnings: Option<String>,
+    updated_at: String,
+}
+
+impl TryFrom<MaturityOverrideRow> for MaturityOverride {
+    type Error = AppError;
+
+    fn try_from(row: MaturityOverrideRow) -> Result<Self> {
+        let content_warnings = row
+            .content_warnings
+            .map(|json| serde_json::from_str(&json))
+            .transpose()
+            .map_err(|e| AppError::Internal(format!("Invalid content warnings: {}", e)))?;
+        Ok(Self {
+            book_id: row.book_id,
+            rating: row.rating,
+            content_warnings,
+            updated_at: row.updated_at,
+        })
+    }
+}
+
+/// Maturity override repository
+pub struct MaturityRepository<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl<'a> MaturityRepository<'a> {
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Set or replace a book's override
+    pub async fn set(
+        &self,
+        book_id: &str,
+        rating: MaturityRating,
+        content_warnings: Option<Vec<String>>,
+    ) -> Result<MaturityOverride> {
+        let updated_at = Utc::now().to_rfc3339();
+        let warnings_json = content_warnings
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO maturity_overrides (book_id, rating, content_warnings, updated_at)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT(book_id) DO UPDATE SET
+                rating = excluded.rating,
+                content_warnings = excluded.content_warnings,
+                updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(book_id)
+        .bind(rating)
+        .bind(&warnings_json)
+        .bind(&updated_at)
+        .execute(self.pool)
+        .await?;
+
+        Ok(MaturityOverride {
+            book_id: book_id.to_string(),
+            rating,
+            content_warnings,
+            updated_at,
+        })
+    }
+
+    /// Remove a book's override; `false` if it had none
+    pub async fn delete(&self, book_id: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM maturity_overrides WHERE book_id = ?")
+            .bind(book_id)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// All overrides, ordered by book ID
+    pub async fn list(&self) -> Result<Vec<MaturityOverride>> {
+        let rows = sqlx::query_as::<_, MaturityOverrideRow>(
+            "SELECT book_id, rating, content_warnings, updated_at FROM maturity_overrides ORDER BY book_id",
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        rows.into_iter().map(MaturityOverride::try_from).collect()
+    }
+
+    /// Apply every stored override to `books`
+    pub async fn apply(&self, books: &mut [LibraryBook]) -> Result<()> {
+        let overrides: HashMap<String, MaturityOverride> = self
+            .list()
+            .await?
+            .into_iter()
+            .map(|o| (o.book_id.clone(), o))
+            .collect();
+        if overrides.is_empty() {
+            return Ok(());
+        }
+
+        for book in books {
+            if let Some(o) = overrides.get(&book.stable_id()) {
+                o.apply(book);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::initialize_schema;
+
+    #[tokio::test]
+    async fn test_maturity_overrides() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        initialize_schema(&pool).await.unwrap();
+        let repo = MaturityRepository::new(&pool);
+
+        let mut book = LibraryBook::new("Dune".to_string(), "Frank Herbert/Dune".to_string());
+        book.maturity = Some(MaturityRating::AllAges);
+        book.content_warnings = vec!["war".to_string()];
+
+        repo.set(&book.stable_id(), MaturityRating::Adult, None)
+            .await
+            .unwrap();
+        repo.set(&book.stable_id(), MaturityRating::Teen, None)
+            .await
+            .unwrap();
+        let mut books = vec![book];
+        repo.apply(&mut books).await.unwrap();
+        assert_eq!(books[0].maturity, Some(MaturityRating::Teen));
+        assert_eq!(books[0].content_warnings, vec!["war"]);
+
+        repo.set("Frank Herbert/Dune", MaturityRating::Teen, Some(Vec::new()))
+            .await
+            .unwrap();
+        repo.apply(&mut books).await.unwrap();
+        assert!(books[0].content_warnings.is_empty());
+        assert_eq!(repo.list().await.unwrap().len(), 1);
+
+        assert!(repo.delete("Frank Herbert/Dune").await.unwrap());
+        assert!(!repo.delete("Frank Herbert/Dune").await.unwrap());
+    }
+}
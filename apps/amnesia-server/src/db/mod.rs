@@ -1,19 +1,37 @@
 //! Database module for SQLite persistence
 //!
-//! Handles reading progress, highlights, library metadata storage,
+//! Handles reading progress, highlights, collections, library metadata,
+//! text statistics, maturity override, duplicate-page report, reader
+//! preference, highlight palette, notification, and storage tier storage,
 //! and full-text search via FTS5.
 
+mod collections;
 mod highlights;
+mod maturity;
+mod notifications;
+mod page_duplicates;
+mod palette;
+mod preferences;
 mod progress;
 mod schema;
 pub mod search;
+mod text_stats;
+mod tiering;
 
+pub use collections::*;
 pub use highlights::*;
+pub use maturity::*;
+pub use notifications::*;
+pub use page_duplicates::*;
+pub use palette::*;
+pub use preferences::*;
 pub use progress::*;
 pub use schema::*;
 pub use search::{
     BookSearchResult, FTS5Search, FTS5Stats, HighlightSearchResult, UnifiedSearchResult,
 };
+pub use text_stats::*;
+pub use tiering::*;
 
 use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
 use std::str::FromStr;
@@ -0,0 +1,250 @@
+//! Notification center
+//!
+//! Server events a reader may want to act on: feed articles filed into the
+//! library, OCR results, and sync conflicts. Notifications without a user
+//! belong to everyone (like progress rows without one) and are listed for
+//! every user.
+//!
+//! Share-link accesses are not reported: the server has no share links
+//! yet. They need their own [`NotificationKind`] once it does.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::Result;
+
+/// What raised a notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "kebab-case")]
+#[sqlx(rename_all = "kebab-case")]
+pub enum NotificationKind {
+    /// Feed ingestion added articles
+    Ingest,
+    /// An OCR request finished or failed
+    Ocr,
+    /// A sync push had conflicting changes
+    SyncConflict,
+}
+
+/// A stored notification
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Notification {
+    pub id: String,
+    pub user_id: Option<String>,
+    pub kind: NotificationKind,
+    pub title: String,
+    pub body: Option<String>,
+    /// Book the event is about, for linking to it
+    pub book_id: Option<String>,
+    /// RFC 3339 time it was marked read
+    pub read_at: Option<String>,
+    pub created_at: String,
+}
+
+/// A notification to record
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewNotification {
+    pub user_id: Option<String>,
+    pub kind: NotificationKind,
+    pub title: String,
+    pub body: Option<String>,
+    pub book_id: Option<String>,
+}
+
+impl NewNotification {
+    /// A notification for everyone
+    pub fn new(kind: NotificationKind, title: impl Into<String>) -> Self {
+        Self {
+            user_id: None,
+            kind,
+            title: title.into(),
+            body: None,
+            book_id: None,
+        }
+    }
+
+    pub fn with_body(mut self, body: impl Into<String>) -> Self {
+        self.body = Some(body.into());
+        self
+    }
+
+    pub fn with_book(mut self, book_id: impl Into<String>) -> Self {
+        self.book_id = Some(book_id.into());
+        self
+    }
+}
+
+const NOTIFICATION_COLUMNS: &str = "id, user_id, kind, title, body, book_id, read_at, created_at";
+
+/// Notification repository
+pub struct NotificationRepository<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl<'a> NotificationRepository<'a> {
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Record a notification
+    pub async fn create(&self, notification: &NewNotification) -> Result<Notification> {
+        let stored = Notification {
+            id: Uuid::new_v4().to_string(),
+            user_id: notification.user_id.clone(),
+            kind: notification.kind,
+            title: notification.title.clone(),
+            body: notification.body.clone(),
+            book_id: notification.book_id.clone(),
+            read_at: None,
+            created_at: Utc::now().to_rfc3339(),
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO notifications (id, user_id, kind, title, body, book_id, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&stored.id)
+        .bind(&stored.user_id)
+        .bind(stored.kind)
+        .bind(&stored.title)
+        .bind(&stored.body)
+        .bind(&stored.book_id)
+        .bind(&stored.created_at)
+        .execute(self.pool)
+        .await?;
+
+        Ok(stored)
+    }
+
+    /// Record a notification, logging instead of failing
+    ///
+    /// For event sources whose own work succeeded regardless.
+    pub async fn notify(&self, notification: NewNotification) {
+        if let Err(e) = self.create(&notification).await {
+            tracing::warn!(
+                "Failed to record notification '{}': {}",
+                notification.title,
+                e
+            );
+        }
+    }
+
+    /// A user's notifications, newest first
+    pub async fn list(
+        &self,
+        user_id: Option<&str>,
+        unread_only: bool,
+        limit: i64,
+    ) -> Result<Vec<Notification>> {
+        let sql = format!(
+            r#"
+            SELECT {}
+            FROM notifications
+            WHERE (user_id = ? OR user_id IS NULL) AND (? = 0 OR read_at IS NULL)
+            ORDER BY created_at DESC, id
+            LIMIT ?
+            "#,
+            NOTIFICATION_COLUMNS
+        );
+        let notifications = sqlx::query_as::<_, Notification>(&sql)
+            .bind(user_id)
+            .bind(unread_only)
+            .bind(limit)
+            .fetch_all(self.pool)
+            .await?;
+
+        Ok(notifications)
+    }
+
+    /// Number of a user's unread notifications
+    pub async fn unread_count(&self, user_id: Option<&str>) -> Result<i64> {
+        let (count,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM notifications WHERE (user_id = ? OR user_id IS NULL) AND read_at IS NULL",
+        )
+        .bind(user_id)
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// Mark one notification read; `None` if it does not exist
+    pub async fn mark_read(&self, id: &str) -> Result<Option<Notification>> {
+        sqlx::query("UPDATE notifications SET read_at = ? WHERE id = ? AND read_at IS NULL")
+            .bind(Utc::now().to_rfc3339())
+            .bind(id)
+            .execute(self.pool)
+            .await?;
+
+        let sql = format!(
+            "SELECT {} FROM notifications WHERE id = ?",
+            NOTIFICATION_COLUMNS
+        );
+        let notification = sqlx::query_as::<_, Notification>(&sql)
+            .bind(id)
+            .fetch_optional(self.pool)
+            .await?;
+
+        Ok(notification)
+    }
+
+    /// Mark all of a user's notifications read, returning how many changed
+    pub async fn mark_all_read(&self, user_id: Option<&str>) -> Result<u64> {
+        let result = sqlx::query(
+            "UPDATE notifications SET read_at = ? WHERE (user_id = ? OR user_id IS NULL) AND read_at IS NULL",
+        )
+        .bind(Utc::now().to_rfc3339())
+        .bind(user_id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::initialize_schema;
+
+    #[tokio::test]
+    async fn test_notifications() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        initialize_schema(&pool).await.unwrap();
+        let repo = NotificationRepository::new(&pool);
+
+        let everyone = repo
+            .create(&NewNotification::new(
+                NotificationKind::Ingest,
+                "Added 3 articles",
+            ))
+            .await
+            .unwrap();
+        let conflict = NewNotification {
+            user_id: Some("ana".to_string()),
+            ..NewNotification::new(NotificationKind::SyncConflict, "2 sync conflicts")
+                .with_book("dune")
+        };
+        repo.create(&conflict).await.unwrap();
+
+        assert_eq!(repo.unread_count(Some("ana")).await.unwrap(), 2);
+        assert_eq!(repo.unread_count(Some("ben")).await.unwrap(), 1);
+        let listed = repo.list(Some("ana"), false, 10).await.unwrap();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[1].kind, NotificationKind::Ingest);
+
+        let read = repo.mark_read(&everyone.id).await.unwrap().unwrap();
+        assert!(read.read_at.is_some());
+        assert!(repo.mark_read("missing").await.unwrap().is_none());
+        assert_eq!(repo.list(Some("ana"), true, 10).await.unwrap().len(), 1);
+
+        assert_eq!(repo.mark_all_read(Some("ana")).await.unwrap(), 1);
+        assert_eq!(repo.unread_count(Some("ana")).await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,187 @@
+//! Duplicate-page report storage
+//!
+//! Scanned PDFs are checked for repeated consecutive pages by
+//! [`crate::library::find_duplicate_pages`]. The result for each book is
+//! stored under its [`stable_id`](crate::library::LibraryBook::stable_id)
+//! together with the S3 key of the checked file, so a fixed rescan is checked
+//! again.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use utoipa::ToSchema;
+
+use crate::error::{AppError, Result};
+use crate::library::DuplicatePage;
+
+/// Duplicate-page check result for one book
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicatePageReport {
+    pub book_id: String,
+    /// S3 key of the checked PDF
+    pub source_key: String,
+    pub page_count: i64,
+    /// Pages that repeat the page before them, in page order
+    pub duplicates: Vec<DuplicatePage>,
+    pub computed_at: String,
+}
+
+#[derive(sqlx::FromRow)]
+struct DuplicatePageReportRow {
+    book_id: String,
+    source_key: String,
+    page_count: i64,
+    /// JSON array
+    duplicates: String,
+    computed_at: String,
+}
+
+impl TryFrom<DuplicatePageReportRow> for DuplicatePageReport {
+    type Error = AppError;
+
+    fn try_from(row: DuplicatePageReportRow) -> Result<Self> {
+        let duplicates = serde_json::from_str(&row.duplicates)
+            .map_err(|e| AppError::Internal(format!("Invalid duplicate pages: {}", e)))?;
+        Ok(Self {
+            book_id: row.book_id,
+            source_key: row.source_key,
+            page_count: row.page_count,
+            duplicates,
+            computed_at: row.computed_at,
+        })
+    }
+}
+
+/// Duplicate-page report repository
+pub struct DuplicatePageRepository<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl<'a> DuplicatePageRepository<'a> {
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Report for a book
+    pub async fn get(&self, book_id: &str) -> Result<Option<DuplicatePageReport>> {
+        let row = sqlx::query_as::<_, DuplicatePageReportRow>(
+            "SELECT book_id, source_key, page_count, duplicates, computed_at \
+             FROM page_duplicate_reports WHERE book_id = ?",
+        )
+        .bind(book_id)
+        .fetch_optional(self.pool)
+        .await?;
+
+        row.map(DuplicatePageReport::try_from).transpose()
+    }
+
+    /// Store a book's report, replacing any previous one
+    pub async fn upsert(
+        &self,
+        book_id: &str,
+        source_key: &str,
+        page_count: i64,
+        duplicates: Vec<DuplicatePage>,
+    ) -> Result<DuplicatePageReport> {
+        let computed_at = Utc::now().to_rfc3339();
+        let duplicates_json =
+            serde_json::to_string(&duplicates).map_err(|e| AppError::Internal(e.to_string()))?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO page_duplicate_reports (
+                book_id, source_key, page_count, duplicate_count, duplicates, computed_at
+            )
+            VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT(book_id) DO UPDATE SET
+                source_key = excluded.source_key,
+                page_count = excluded.page_count,
+                duplicate_count = excluded.duplicate_count,
+                duplicates = excluded.duplicates,
+                computed_at = excluded.computed_at
+            "#,
+        )
+        .bind(book_id)
+        .bind(source_key)
+        .bind(page_count)
+        .bind(duplicates.len() as i64)
+        .bind(&duplicates_json)
+        .bind(&computed_at)
+        .execute(self.pool)
+        .await?;
+
+        Ok(DuplicatePageReport {
+            book_id: book_id.to_string(),
+            source_key: source_key.to_string(),
+            page_count,
+            duplicates,
+            computed_at,
+        })
+    }
+
+    /// Reports that found duplicates, most duplicates first
+    pub async fn with_duplicates(&self) -> Result<Vec<DuplicatePageReport>> {
+        let rows = sqlx::query_as::<_, DuplicatePageReportRow>(
+            "SELECT book_id, source_key, page_count, duplicates, computed_at \
+             FROM page_duplicate_reports WHERE duplicate_count > 0 \
+             ORDER BY duplicate_count DESC, book_id",
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(DuplicatePageReport::try_from)
+            .collect()
+    }
+
+    /// Source key each checked book was checked from
+    pub async fn source_keys(&self) -> Result<Vec<(String, String)>> {
+        let rows: Vec<(String, String)> =
+            sqlx::query_as("SELECT book_id, source_key FROM page_duplicate_reports")
+                .fetch_all(self.pool)
+                .await?;
+
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::initialize_schema;
+
+    #[tokio::test]
+    async fn test_duplicate_page_reports() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        initialize_schema(&pool).await.unwrap();
+        let repo = DuplicatePageRepository::new(&pool);
+
+        let repeat = DuplicatePage {
+            page: 4,
+            duplicate_of: 3,
+            distance: 2,
+        };
+        repo.upsert("clean", "a/clean.pdf", 10, Vec::new())
+            .await
+            .unwrap();
+        repo.upsert("scan", "a/scan.pdf", 12, vec![repeat])
+            .await
+            .unwrap();
+
+        let reports = repo.with_duplicates().await.unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].book_id, "scan");
+        assert_eq!(reports[0].duplicates, vec![repeat]);
+
+        // A fixed rescan replaces the report
+        repo.upsert("scan", "a/scan-v2.pdf", 11, Vec::new())
+            .await
+            .unwrap();
+        assert!(repo.with_duplicates().await.unwrap().is_empty());
+        let report = repo.get("scan").await.unwrap().unwrap();
+        assert_eq!(report.source_key, "a/scan-v2.pdf");
+        assert_eq!(repo.source_keys().await.unwrap().len(), 2);
+        assert!(repo.get("missing").await.unwrap().is_none());
+    }
+}
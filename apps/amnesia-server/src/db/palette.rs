@@ -0,0 +1,263 @@
+//! Highlight color palettes
+//!
+//! Each user names their highlight colors and says what they mean ("green:
+//! definitions", "pink: disagree"), so every client offers the same choices
+//! and highlights can be filtered and exported by category. Highlights store
+//! the palette entry's name; clients may send either the name or its hex.
+//!
+//! Palettes are stored as one JSON document per user, like reader
+//! preferences; requests without a user ID use the server-wide palette, and
+//! users who never saved one get [`HighlightPalette::default`].
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use utoipa::ToSchema;
+
+use crate::error::{AppError, Result};
+
+/// Most colors a palette may hold
+pub const MAX_PALETTE_COLORS: usize = 32;
+
+/// A named highlight color
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PaletteColor {
+    /// Category name stored on highlights, e.g. "yellow" or "definitions"
+    pub name: String,
+    /// CSS hex color, "#rgb" or "#rrggbb"
+    pub color: String,
+    /// What highlights in this color mean
+    pub meaning: Option<String>,
+}
+
+impl PaletteColor {
+    fn new(name: &str, color: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            color: color.to_string(),
+            meaning: None,
+        }
+    }
+
+    /// Whether `color` names this entry or is its hex, ignoring case
+    pub fn matches(&self, color: &str) -> bool {
+        let color = color.trim();
+        self.name.eq_ignore_ascii_case(color) || self.color.eq_ignore_ascii_case(color)
+    }
+}
+
+/// A user's highlight colors, in the order clients should offer them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HighlightPalette {
+    pub colors: Vec<PaletteColor>,
+    /// RFC 3339 time of the last change; set by the server
+    #[serde(default)]
+    pub updated_at: Option<String>,
+}
+
+impl Default for HighlightPalette {
+    /// The colors clients offered before palettes existed
+    fn default() -> Self {
+        Self {
+            colors: vec![
+                PaletteColor::new("yellow", "#fef3c7"),
+                PaletteColor::new("green", "#d1fae5"),
+                PaletteColor::new("blue", "#dbeafe"),
+                PaletteColor::new("pink", "#fce7f3"),
+                PaletteColor::new("purple", "#ede9fe"),
+                PaletteColor::new("orange", "#ffedd5"),
+            ],
+            updated_at: None,
+        }
+    }
+}
+
+impl HighlightPalette {
+    /// Reject palettes clients could not offer unambiguously
+    pub fn validate(&self) -> Result<()> {
+        if self.colors.is_empty() || self.colors.len() > MAX_PALETTE_COLORS {
+            return Err(AppError::BadRequest(format!(
+                "A palette must have between 1 and {} colors",
+                MAX_PALETTE_COLORS
+            )));
+        }
+
+        for (i, entry) in self.colors.iter().enumerate() {
+            if entry.name.trim().is_empty() || entry.name.trim() != entry.name {
+                return Err(AppError::BadRequest(format!(
+                    "Invalid palette color name: '{}'",
+                    entry.name
+                )));
+            }
+            if !is_hex_color(&entry.color) {
+                return Err(AppError::BadRequest(format!(
+                    "Invalid color for '{}': '{}' (expected #rgb or #rrggbb)",
+                    entry.name, entry.color
+                )));
+            }
+            // Names and hexes are both accepted on highlights, so neither
+            // may stand for two entries
+            if let Some(other) = self.colors[..i]
+                .iter()
+                .find(|other| other.matches(&entry.name) || other.matches(&entry.color))
+            {
+                return Err(AppError::BadRequest(format!(
+                    "Palette colors '{}' and '{}' overlap",
+                    other.name, entry.name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// The entry a highlight color names, by name or hex
+    pub fn resolve(&self, color: &str) -> Option<&PaletteColor> {
+        self.colors.iter().find(|entry| entry.matches(color))
+    }
+
+    /// The entry a highlight color names, rejecting colors not in the
+    /// palette
+    pub fn lookup(&self, color: &str) -> Result<&PaletteColor> {
+        self.resolve(color).ok_or_else(|| {
+            let names: Vec<&str> = self.colors.iter().map(|c| c.name.as_str()).collect();
+            AppError::BadRequest(format!(
+                "Unknown highlight color '{}' (palette has: {})",
+                color,
+                names.join(", ")
+            ))
+        })
+    }
+}
+
+/// Whether `color` is a CSS "#rgb" or "#rrggbb" hex color
+fn is_hex_color(color: &str) -> bool {
+    color
+        .strip_prefix('#')
+        .is_some_and(|hex| matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Highlight palette repository
+pub struct PaletteRepository<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl<'a> PaletteRepository<'a> {
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// A user's palette, or the default if they never saved one
+    pub async fn get(&self, user_id: Option<&str>) -> Result<HighlightPalette> {
+        let row: Option<(String,)> =
+            sqlx::query_as("SELECT palette FROM highlight_palettes WHERE user_id = ?")
+                .bind(user_id.unwrap_or_default())
+                .fetch_optional(self.pool)
+                .await?;
+
+        match row {
+            Some((json,)) => serde_json::from_str(&json)
+                .map_err(|e| AppError::Internal(format!("Invalid highlight palette: {}", e))),
+            None => Ok(HighlightPalette::default()),
+        }
+    }
+
+    /// Replace a user's palette
+    pub async fn set(
+        &self,
+        user_id: Option<&str>,
+        palette: &HighlightPalette,
+    ) -> Result<HighlightPalette> {
+        palette.validate()?;
+
+        let stored = HighlightPalette {
+            updated_at: Some(Utc::now().to_rfc3339()),
+            ..palette.clone()
+        };
+        let json = serde_json::to_string(&stored).map_err(|e| AppError::Internal(e.to_string()))?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO highlight_palettes (user_id, palette, updated_at)
+            VALUES (?, ?, ?)
+            ON CONFLICT(user_id) DO UPDATE SET
+                palette = excluded.palette,
+                updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(user_id.unwrap_or_default())
+        .bind(&json)
+        .bind(&stored.updated_at)
+        .execute(self.pool)
+        .await?;
+
+        Ok(stored)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::initialize_schema;
+
+    fn palette(colors: &[(&str, &str)]) -> HighlightPalette {
+        HighlightPalette {
+            colors: colors
+                .iter()
+                .map(|(name, color)| PaletteColor::new(name, color))
+                .collect(),
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn test_palette_resolve_and_validate() {
+        let mut categories = palette(&[("definitions", "#D1FAE5"), ("disagree", "#fce7f3")]);
+        categories.colors[0].meaning = Some("Terms to look up".to_string());
+        assert!(categories.validate().is_ok());
+
+        assert_eq!(
+            categories.lookup("Definitions").unwrap().name,
+            "definitions"
+        );
+        assert_eq!(categories.lookup("#d1fae5").unwrap().name, "definitions");
+        assert!(matches!(
+            categories.lookup("yellow"),
+            Err(AppError::BadRequest(_))
+        ));
+
+        for invalid in [
+            palette(&[]),
+            palette(&[("red", "red")]),
+            palette(&[("red", "#ff00")]),
+            palette(&[(" red", "#f00")]),
+            palette(&[("red", "#f00"), ("RED", "#0f0")]),
+            palette(&[("red", "#f00"), ("alarm", "#F00")]),
+        ] {
+            assert!(invalid.validate().is_err(), "{:?}", invalid.colors);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_palette_roundtrip() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        initialize_schema(&pool).await.unwrap();
+        let repo = PaletteRepository::new(&pool);
+
+        assert_eq!(
+            repo.get(Some("ana")).await.unwrap(),
+            HighlightPalette::default()
+        );
+
+        let stored = repo
+            .set(Some("ana"), &palette(&[("quotes", "#ede9fe")]))
+            .await
+            .unwrap();
+        assert!(stored.updated_at.is_some());
+        assert_eq!(repo.get(Some("ana")).await.unwrap(), stored);
+
+        // Users do not see each other's palettes
+        assert_eq!(repo.get(None).await.unwrap(), HighlightPalette::default());
+    }
+}
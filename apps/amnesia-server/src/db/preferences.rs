@@ -0,0 +1,170 @@
+//! Default reader preferences
+//!
+//! Each user's preferred reader setup (theme, typography, page turns, TTS
+//! voice), so a new device starts the way the user left their others.
+//! Per-book settings stay on the client and override these.
+//!
+//! Preferences are stored as one JSON document per user; requests without
+//! a user ID read and write the server-wide defaults.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use utoipa::ToSchema;
+
+use crate::error::{AppError, Result};
+
+/// Smallest and largest accepted font size, in CSS pixels
+pub const FONT_SIZE_RANGE: std::ops::RangeInclusive<f32> = 8.0..=72.0;
+
+/// Text alignment of reflowable books
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TextAlign {
+    Left,
+    Justify,
+    Right,
+    Center,
+}
+
+/// Page turn animation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PageAnimation {
+    None,
+    Slide,
+    Curl,
+}
+
+/// A user's default reader setup
+///
+/// Unset fields leave the client's own default in place.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReaderPreferences {
+    /// Theme preset ("system", "light", "dark", "sepia", ...) or custom theme name
+    pub theme: Option<String>,
+    pub font_family: Option<String>,
+    /// Font size in CSS pixels
+    pub font_size: Option<f32>,
+    pub text_align: Option<TextAlign>,
+    pub page_animation: Option<PageAnimation>,
+    /// Voice name for text-to-speech
+    pub tts_voice: Option<String>,
+    /// RFC 3339 time of the last change; set by the server
+    #[serde(default)]
+    pub updated_at: Option<String>,
+}
+
+impl ReaderPreferences {
+    /// Reject values no reader could apply
+    pub fn validate(&self) -> Result<()> {
+        if let Some(size) = self.font_size {
+            if !FONT_SIZE_RANGE.contains(&size) {
+                return Err(AppError::BadRequest(format!(
+                    "fontSize must be between {} and {}",
+                    FONT_SIZE_RANGE.start(),
+                    FONT_SIZE_RANGE.end()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reader preferences repository
+pub struct PreferencesRepository<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl<'a> PreferencesRepository<'a> {
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// A user's preferences, or `None` if they never saved any
+    pub async fn get(&self, user_id: Option<&str>) -> Result<Option<ReaderPreferences>> {
+        let row: Option<(String,)> =
+            sqlx::query_as("SELECT preferences FROM reader_preferences WHERE user_id = ?")
+                .bind(user_id.unwrap_or_default())
+                .fetch_optional(self.pool)
+                .await?;
+
+        row.map(|(json,)| {
+            serde_json::from_str(&json)
+                .map_err(|e| AppError::Internal(format!("Invalid reader preferences: {}", e)))
+        })
+        .transpose()
+    }
+
+    /// Replace a user's preferences
+    pub async fn set(
+        &self,
+        user_id: Option<&str>,
+        preferences: &ReaderPreferences,
+    ) -> Result<ReaderPreferences> {
+        preferences.validate()?;
+
+        let stored = ReaderPreferences {
+            updated_at: Some(Utc::now().to_rfc3339()),
+            ..preferences.clone()
+        };
+        let json = serde_json::to_string(&stored).map_err(|e| AppError::Internal(e.to_string()))?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO reader_preferences (user_id, preferences, updated_at)
+            VALUES (?, ?, ?)
+            ON CONFLICT(user_id) DO UPDATE SET
+                preferences = excluded.preferences,
+                updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(user_id.unwrap_or_default())
+        .bind(&json)
+        .bind(&stored.updated_at)
+        .execute(self.pool)
+        .await?;
+
+        Ok(stored)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::initialize_schema;
+
+    #[tokio::test]
+    async fn test_preferences_roundtrip() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        initialize_schema(&pool).await.unwrap();
+        let repo = PreferencesRepository::new(&pool);
+
+        assert_eq!(repo.get(Some("ana")).await.unwrap(), None);
+
+        let preferences = ReaderPreferences {
+            theme: Some("sepia".to_string()),
+            font_size: Some(18.0),
+            text_align: Some(TextAlign::Justify),
+            page_animation: Some(PageAnimation::Curl),
+            tts_voice: Some("en-GB-Sonia".to_string()),
+            ..Default::default()
+        };
+        let stored = repo.set(Some("ana"), &preferences).await.unwrap();
+        assert!(stored.updated_at.is_some());
+        assert_eq!(repo.get(Some("ana")).await.unwrap(), Some(stored));
+
+        // Users do not see each other's preferences
+        assert_eq!(repo.get(None).await.unwrap(), None);
+
+        let too_small = ReaderPreferences {
+            font_size: Some(2.0),
+            ..Default::default()
+        };
+        assert!(matches!(
+            repo.set(None, &too_small).await,
+            Err(AppError::BadRequest(_))
+        ));
+    }
+}
@@ -1,11 +1,127 @@
 //! Reading progress database operations
+//!
+//! Progress is stored per document with a typed [`ProgressLocator`] so EPUBs
+//! (CFI) and PDFs (page + scroll offset) share one API. The legacy `cfi` and
+//! `page` columns are still written for older clients.
+
+use std::collections::HashMap;
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
 use sqlx::SqlitePool;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-use crate::error::Result;
+use super::highlights::DocumentFormat;
+use crate::error::{AppError, Result};
+
+/// Position within a document, in the form that suits its format
+///
+/// Read-aloud positions and audiobook bookmarks are CFIs with a temporal
+/// offset (`epubcfi(/6/4!/4/2/6~12.5)`), so they validate and sort like any
+/// other EPUB position.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ProgressLocator {
+    /// EPUB canonical fragment identifier
+    Cfi { cfi: String },
+    /// PDF page (1-indexed) and vertical scroll offset within it (0-1)
+    Page {
+        page: u32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        y: Option<f64>,
+    },
+    /// Layout-independent position (e.g. a Readium position index)
+    Position { position: u64 },
+    /// Fraction of the whole document (0-1)
+    Progression { progression: f64 },
+}
+
+impl ProgressLocator {
+    /// Format implied by the locator when the client does not say
+    pub fn default_format(&self) -> DocumentFormat {
+        match self {
+            Self::Page { .. } => DocumentFormat::Pdf,
+            _ => DocumentFormat::Epub,
+        }
+    }
+
+    /// Check that the locator makes sense for a document of `format`
+    pub fn validate(&self, format: DocumentFormat, total_pages: Option<i32>) -> Result<()> {
+        match self {
+            Self::Cfi { cfi } => {
+                if format != DocumentFormat::Epub {
+                    return Err(AppError::BadRequest(format!(
+                        "CFI locators are only valid for EPUB, not {}",
+                        format
+                    )));
+                }
+                crate::cfi::parse(cfi)
+                    .map_err(|e| AppError::BadRequest(format!("Invalid CFI: {}", e)))?;
+            }
+            Self::Page { page, y } => {
+                if format != DocumentFormat::Pdf {
+                    return Err(AppError::BadRequest(format!(
+                        "Page locators are only valid for PDF, not {}",
+                        format
+                    )));
+                }
+                if *page == 0 {
+                    return Err(AppError::BadRequest("Page numbers start at 1".to_string()));
+                }
+                if let Some(total) = total_pages {
+                    if i64::from(*page) > i64::from(total) {
+                        return Err(AppError::BadRequest(format!(
+                            "Page {} is beyond the last page ({})",
+                            page, total
+                        )));
+                    }
+                }
+                if let Some(y) = y {
+                    check_fraction("y", *y)?;
+                }
+            }
+            Self::Position { .. } => {}
+            Self::Progression { progression } => check_fraction("progression", *progression)?,
+        }
+        Ok(())
+    }
+
+    /// CFI for the legacy `cfi` column (empty for non-CFI locators)
+    pub fn cfi(&self) -> &str {
+        match self {
+            Self::Cfi { cfi } => cfi,
+            _ => "",
+        }
+    }
+
+    /// Seconds into the audio or video, for a temporal CFI
+    pub fn seconds(&self) -> Option<f64> {
+        match self {
+            Self::Cfi { cfi } => crate::cfi::parse(cfi).ok()?.temporal_offset(),
+            _ => None,
+        }
+    }
+
+    /// Page for the legacy `page` column
+    pub fn page(&self) -> Option<i32> {
+        match self {
+            Self::Page { page, .. } => i32::try_from(*page).ok(),
+            _ => None,
+        }
+    }
+}
+
+fn check_fraction(name: &str, value: f64) -> Result<()> {
+    if !(0.0..=1.0).contains(&value) {
+        return Err(AppError::BadRequest(format!(
+            "'{}' must be between 0 and 1, got {}",
+            name, value
+        )));
+    }
+    Ok(())
+}
 
 /// Reading progress record
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
@@ -13,7 +129,11 @@ pub struct ReadingProgress {
     pub id: String,
     pub book_id: String,
     pub user_id: Option<String>,
+    /// Document format: 'epub' or 'pdf'
+    pub document_format: String,
     pub percent: f64,
+    pub locator: Json<ProgressLocator>,
+    /// Legacy EPUB location (empty for other locators)
     pub cfi: String,
     pub page: Option<i32>,
     pub total_pages: Option<i32>,
@@ -24,15 +144,62 @@ pub struct ReadingProgress {
 }
 
 /// Progress update request
+///
+/// New clients send `locator` (and `documentFormat` when it cannot be
+/// inferred); older clients send only `cfi` or `page`, which are converted.
 #[derive(Debug, Clone, Deserialize)]
 pub struct ProgressUpdate {
+    /// Percentage through the document (0-100)
     pub percent: f64,
-    pub cfi: String,
+    #[serde(default, alias = "documentFormat")]
+    pub document_format: Option<DocumentFormat>,
+    #[serde(default)]
+    pub locator: Option<ProgressLocator>,
+    #[serde(default)]
+    pub cfi: Option<String>,
     pub page: Option<i32>,
     pub total_pages: Option<i32>,
     pub device_id: Option<String>,
 }
 
+impl ProgressUpdate {
+    /// Determine and validate the format and locator to store
+    pub fn resolve(&self) -> Result<(DocumentFormat, ProgressLocator)> {
+        if !(0.0..=100.0).contains(&self.percent) {
+            return Err(AppError::BadRequest(format!(
+                "'percent' must be between 0 and 100, got {}",
+                self.percent
+            )));
+        }
+
+        let locator = match (&self.locator, self.cfi.as_deref(), self.page) {
+            (Some(locator), _, _) => locator.clone(),
+            (None, Some(cfi), _) if !cfi.is_empty() => ProgressLocator::Cfi {
+                cfi: cfi.to_string(),
+            },
+            (None, _, Some(page)) if page > 0 => ProgressLocator::Page {
+                page: page as u32,
+                y: None,
+            },
+            _ => ProgressLocator::Progression {
+                progression: self.percent / 100.0,
+            },
+        };
+        let format = self
+            .document_format
+            .unwrap_or_else(|| locator.default_format());
+
+        locator.validate(format, self.total_pages)?;
+        Ok((format, locator))
+    }
+}
+
+/// All columns to select for reading progress
+const PROGRESS_COLUMNS: &str = r#"
+    id, book_id, user_id, document_format, percent, locator, cfi, page, total_pages,
+    device_id, last_read, created_at, updated_at
+"#;
+
 /// Progress repository
 pub struct ProgressRepository<'a> {
     pool: &'a SqlitePool,
@@ -44,59 +211,105 @@ impl<'a> ProgressRepository<'a> {
     }
 
     /// Get progress for a specific book
-    pub async fn get(&self, book_id: &str, user_id: Option<&str>) -> Result<Option<ReadingProgress>> {
-        let progress = sqlx::query_as::<_, ReadingProgress>(
+    pub async fn get(
+        &self,
+        book_id: &str,
+        user_id: Option<&str>,
+    ) -> Result<Option<ReadingProgress>> {
+        let query = format!(
             r#"
-            SELECT id, book_id, user_id, percent, cfi, page, total_pages,
-                   device_id, last_read, created_at, updated_at
+            SELECT {}
             FROM reading_progress
             WHERE book_id = ? AND (user_id = ? OR user_id IS NULL)
             ORDER BY last_read DESC
             LIMIT 1
             "#,
-        )
-        .bind(book_id)
-        .bind(user_id)
-        .fetch_optional(self.pool)
-        .await?;
+            PROGRESS_COLUMNS
+        );
+        let progress = sqlx::query_as::<_, ReadingProgress>(&query)
+            .bind(book_id)
+            .bind(user_id)
+            .fetch_optional(self.pool)
+            .await?;
 
         Ok(progress)
     }
 
+    /// Get the latest progress for several books in one query, keyed by book ID
+    pub async fn get_many(
+        &self,
+        book_ids: &[String],
+        user_id: Option<&str>,
+    ) -> Result<HashMap<String, ReadingProgress>> {
+        if book_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let placeholders = vec!["?"; book_ids.len()].join(", ");
+        let query = format!(
+            r#"
+            SELECT {}
+            FROM reading_progress
+            WHERE book_id IN ({}) AND (user_id = ? OR user_id IS NULL)
+            ORDER BY last_read DESC
+            "#,
+            PROGRESS_COLUMNS, placeholders
+        );
+
+        let mut sql_query = sqlx::query_as::<_, ReadingProgress>(&query);
+        for book_id in book_ids {
+            sql_query = sql_query.bind(book_id);
+        }
+        let rows = sql_query.bind(user_id).fetch_all(self.pool).await?;
+
+        // Rows are newest first, so keep the first one seen per book
+        let mut latest = HashMap::new();
+        for row in rows {
+            latest.entry(row.book_id.clone()).or_insert(row);
+        }
+        Ok(latest)
+    }
+
     /// Get all progress for a user
     pub async fn list(&self, user_id: Option<&str>) -> Result<Vec<ReadingProgress>> {
-        let progress = sqlx::query_as::<_, ReadingProgress>(
+        let query = format!(
             r#"
-            SELECT id, book_id, user_id, percent, cfi, page, total_pages,
-                   device_id, last_read, created_at, updated_at
+            SELECT {}
             FROM reading_progress
             WHERE user_id = ? OR user_id IS NULL
             ORDER BY last_read DESC
             "#,
-        )
-        .bind(user_id)
-        .fetch_all(self.pool)
-        .await?;
+            PROGRESS_COLUMNS
+        );
+        let progress = sqlx::query_as::<_, ReadingProgress>(&query)
+            .bind(user_id)
+            .fetch_all(self.pool)
+            .await?;
 
         Ok(progress)
     }
 
-    /// Update or create progress for a book
+    /// Update or create progress for a document
+    ///
+    /// The update is validated first; an invalid locator is a `BadRequest`.
     pub async fn upsert(
         &self,
         book_id: &str,
         user_id: Option<&str>,
         update: &ProgressUpdate,
     ) -> Result<ReadingProgress> {
+        let (format, locator) = update.resolve()?;
         let now = Utc::now().to_rfc3339();
         let id = Uuid::new_v4().to_string();
 
         sqlx::query(
             r#"
-            INSERT INTO reading_progress (id, book_id, user_id, percent, cfi, page, total_pages, device_id, last_read, updated_at)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO reading_progress (id, book_id, user_id, document_format, percent, locator, cfi, page, total_pages, device_id, last_read, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             ON CONFLICT(book_id, user_id, device_id) DO UPDATE SET
+                document_format = excluded.document_format,
                 percent = excluded.percent,
+                locator = excluded.locator,
                 cfi = excluded.cfi,
                 page = excluded.page,
                 total_pages = excluded.total_pages,
@@ -107,9 +320,11 @@ impl<'a> ProgressRepository<'a> {
         .bind(&id)
         .bind(book_id)
         .bind(user_id)
+        .bind(format.to_string())
         .bind(update.percent)
-        .bind(&update.cfi)
-        .bind(update.page)
+        .bind(Json(&locator))
+        .bind(locator.cfi())
+        .bind(locator.page().or(update.page))
         .bind(update.total_pages)
         .bind(&update.device_id)
         .bind(&now)
@@ -120,7 +335,7 @@ impl<'a> ProgressRepository<'a> {
         // Fetch the updated record
         self.get(book_id, user_id)
             .await?
-            .ok_or_else(|| crate::error::AppError::Internal("Failed to fetch upserted progress".to_string()))
+            .ok_or_else(|| AppError::Internal("Failed to fetch upserted progress".to_string()))
     }
 
     /// Delete progress for a book
@@ -141,20 +356,21 @@ impl<'a> ProgressRepository<'a> {
 
     /// Get the most recently read books
     pub async fn recent(&self, user_id: Option<&str>, limit: i32) -> Result<Vec<ReadingProgress>> {
-        let progress = sqlx::query_as::<_, ReadingProgress>(
+        let query = format!(
             r#"
-            SELECT id, book_id, user_id, percent, cfi, page, total_pages,
-                   device_id, last_read, created_at, updated_at
+            SELECT {}
             FROM reading_progress
             WHERE (user_id = ? OR user_id IS NULL) AND percent > 0
             ORDER BY last_read DESC
             LIMIT ?
             "#,
-        )
-        .bind(user_id)
-        .bind(limit)
-        .fetch_all(self.pool)
-        .await?;
+            PROGRESS_COLUMNS
+        );
+        let progress = sqlx::query_as::<_, ReadingProgress>(&query)
+            .bind(user_id)
+            .bind(limit)
+            .fetch_all(self.pool)
+            .await?;
 
         Ok(progress)
     }
@@ -294,3 +510,198 @@ impl<'a> SessionRepository<'a> {
         Ok(result.0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::initialize_schema;
+
+    async fn setup_test_db() -> SqlitePool {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        initialize_schema(&pool).await.unwrap();
+        pool
+    }
+
+    fn update(locator: Option<ProgressLocator>) -> ProgressUpdate {
+        ProgressUpdate {
+            percent: 25.0,
+            document_format: None,
+            locator,
+            cfi: None,
+            page: None,
+            total_pages: None,
+            device_id: None,
+        }
+    }
+
+    #[test]
+    fn test_locator_validation_per_format() {
+        let cfi = ProgressLocator::Cfi {
+            cfi: "epubcfi(/6/4!/4/2/1:0)".to_string(),
+        };
+        let page = ProgressLocator::Page {
+            page: 3,
+            y: Some(0.5),
+        };
+
+        assert!(cfi.validate(DocumentFormat::Epub, None).is_ok());
+        assert!(cfi.validate(DocumentFormat::Pdf, None).is_err());
+        assert!(page.validate(DocumentFormat::Pdf, Some(10)).is_ok());
+        assert!(page.validate(DocumentFormat::Epub, None).is_err());
+        assert!(page.validate(DocumentFormat::Pdf, Some(2)).is_err());
+
+        let bad_cfi = ProgressLocator::Cfi {
+            cfi: "not a cfi".to_string(),
+        };
+        assert!(bad_cfi.validate(DocumentFormat::Epub, None).is_err());
+
+        let audio = ProgressLocator::Cfi {
+            cfi: "epubcfi(/6/4!/4/2/6~754.5)".to_string(),
+        };
+        assert!(audio.validate(DocumentFormat::Epub, None).is_ok());
+        assert_eq!(audio.seconds(), Some(754.5));
+        assert_eq!(cfi.seconds(), None);
+        let before_start = ProgressLocator::Cfi {
+            cfi: "epubcfi(/6/4!/4/2/6~-2)".to_string(),
+        };
+        assert!(before_start.validate(DocumentFormat::Epub, None).is_err());
+        let bad_y = ProgressLocator::Page {
+            page: 1,
+            y: Some(1.5),
+        };
+        assert!(bad_y.validate(DocumentFormat::Pdf, None).is_err());
+    }
+
+    #[test]
+    fn test_locator_serialization() {
+        let locator: ProgressLocator =
+            serde_json::from_str(r#"{"type": "page", "page": 7, "y": 0.25}"#).unwrap();
+        assert_eq!(
+            locator,
+            ProgressLocator::Page {
+                page: 7,
+                y: Some(0.25)
+            }
+        );
+
+        let json =
+            serde_json::to_string(&ProgressLocator::Progression { progression: 0.5 }).unwrap();
+        assert_eq!(json, r#"{"type":"progression","progression":0.5}"#);
+    }
+
+    #[test]
+    fn test_legacy_update_resolves_locator() {
+        let mut legacy = update(None);
+        legacy.page = Some(4);
+        let (format, locator) = legacy.resolve().unwrap();
+        assert_eq!(format, DocumentFormat::Pdf);
+        assert_eq!(locator, ProgressLocator::Page { page: 4, y: None });
+
+        let (format, locator) = update(None).resolve().unwrap();
+        assert_eq!(format, DocumentFormat::Epub);
+        assert_eq!(locator, ProgressLocator::Progression { progression: 0.25 });
+    }
+
+    #[tokio::test]
+    async fn test_upsert_stores_typed_locator() {
+        let pool = setup_test_db().await;
+        let repo = ProgressRepository::new(&pool);
+
+        let progress = repo
+            .upsert(
+                "doc-1",
+                None,
+                &update(Some(ProgressLocator::Page {
+                    page: 2,
+                    y: Some(0.1),
+                })),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(progress.document_format, "pdf");
+        assert_eq!(
+            progress.locator.0,
+            ProgressLocator::Page {
+                page: 2,
+                y: Some(0.1)
+            }
+        );
+        assert_eq!(progress.page, Some(2));
+        assert_eq!(progress.cfi, "");
+
+        let mut invalid = update(Some(ProgressLocator::Page { page: 2, y: None }));
+        invalid.document_format = Some(DocumentFormat::Epub);
+        assert!(matches!(
+            repo.upsert("doc-1", None, &invalid).await,
+            Err(AppError::BadRequest(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_many() {
+        let pool = setup_test_db().await;
+        let repo = ProgressRepository::new(&pool);
+
+        let mut phone = update(None);
+        phone.device_id = Some("phone".to_string());
+        repo.upsert("doc-1", Some("alice"), &phone).await.unwrap();
+        let mut tablet = update(None);
+        tablet.percent = 60.0;
+        tablet.device_id = Some("tablet".to_string());
+        repo.upsert("doc-1", Some("alice"), &tablet).await.unwrap();
+        repo.upsert("doc-2", None, &update(None)).await.unwrap();
+        repo.upsert("doc-3", Some("bob"), &update(None))
+            .await
+            .unwrap();
+
+        let ids = ["doc-1", "doc-2", "doc-3", "missing"].map(String::from);
+        let found = repo.get_many(&ids, Some("alice")).await.unwrap();
+
+        // Shared progress is included, other users' and unknown books are not
+        let mut books: Vec<_> = found.keys().map(String::as_str).collect();
+        books.sort();
+        assert_eq!(books, vec!["doc-1", "doc-2"]);
+        // The most recently read device wins
+        assert_eq!(found["doc-1"].percent, 60.0);
+
+        assert!(repo.get_many(&[], Some("alice")).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_migration_backfills_legacy_rows() {
+        let pool = setup_test_db().await;
+        sqlx::query(
+            r#"
+            INSERT INTO reading_progress (id, book_id, percent, cfi, page, last_read)
+            VALUES ('a', 'epub-book', 10, 'epubcfi(/6/2!/4/2)', NULL, '2024-01-01'),
+                   ('b', 'pdf-book', 20, '', 5, '2024-01-01'),
+                   ('c', 'other-book', 50, '', NULL, '2024-01-01')
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        initialize_schema(&pool).await.unwrap();
+        let repo = ProgressRepository::new(&pool);
+
+        let epub = repo.get("epub-book", None).await.unwrap().unwrap();
+        assert_eq!(
+            epub.locator.0,
+            ProgressLocator::Cfi {
+                cfi: "epubcfi(/6/2!/4/2)".to_string()
+            }
+        );
+
+        let pdf = repo.get("pdf-book", None).await.unwrap().unwrap();
+        assert_eq!(pdf.document_format, "pdf");
+        assert_eq!(pdf.locator.0, ProgressLocator::Page { page: 5, y: None });
+
+        let other = repo.get("other-book", None).await.unwrap().unwrap();
+        assert_eq!(
+            other.locator.0,
+            ProgressLocator::Progression { progression: 0.5 }
+        );
+    }
+}
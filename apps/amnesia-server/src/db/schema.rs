@@ -101,9 +101,68 @@ async fn run_migrations(pool: &SqlitePool) -> Result<()> {
             .await?;
     }
 
+    // Add page_label column if missing
+    if !column_names.contains(&"page_label") {
+        sqlx::query("ALTER TABLE highlights ADD COLUMN page_label TEXT")
+            .execute(pool)
+            .await?;
+    }
+
+    migrate_progress_locators(pool).await?;
+
     Ok(())
 }
 
+/// Migration: typed progress locators
+///
+/// Adds `document_format` and `locator` to `reading_progress` and derives a
+/// locator for rows written before they existed: a CFI if one was stored,
+/// otherwise the page (as a PDF), otherwise the overall progression.
+async fn migrate_progress_locators(pool: &SqlitePool) -> Result<()> {
+    let columns: Vec<(String,)> =
+        sqlx::query_as("SELECT name FROM pragma_table_info('reading_progress')")
+            .fetch_all(pool)
+            .await?;
+
+    let column_names: Vec<&str> = columns.iter().map(|(n,)| n.as_str()).collect();
+
+    if !column_names.contains(&"document_format") {
+        sqlx::query(
+            "ALTER TABLE reading_progress ADD COLUMN document_format TEXT NOT NULL DEFAULT 'epub'",
+        )
+        .execute(pool)
+        .await?;
+    }
+
+    if !column_names.contains(&"locator") {
+        sqlx::query("ALTER TABLE reading_progress ADD COLUMN locator TEXT")
+            .execute(pool)
+            .await?;
+    }
+
+    sqlx::query(PROGRESS_LOCATOR_BACKFILL_SQL)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Backfill locators for legacy progress rows (idempotent)
+const PROGRESS_LOCATOR_BACKFILL_SQL: &str = r#"
+UPDATE reading_progress
+SET locator = json_object('type', 'cfi', 'cfi', cfi)
+WHERE locator IS NULL AND cfi != '';
+
+UPDATE reading_progress
+SET locator = json_object('type', 'page', 'page', page),
+    document_format = 'pdf'
+WHERE locator IS NULL AND page > 0;
+
+UPDATE reading_progress
+SET locator = json_object('type', 'progression', 'progression', MIN(MAX(percent / 100.0, 0.0), 1.0))
+WHERE locator IS NULL;
+"#;
+
 /// SQL for creating tables (without indexes)
 const SCHEMA_TABLES_SQL: &str = r#"
 -- Books table (for deduplication and metadata)
@@ -143,7 +202,12 @@ CREATE TABLE IF NOT EXISTS reading_progress (
     id TEXT PRIMARY KEY,
     book_id TEXT NOT NULL,
     user_id TEXT,
+    -- Format: 'epub' or 'pdf'
+    document_format TEXT NOT NULL DEFAULT 'epub',
     percent REAL NOT NULL DEFAULT 0,
+    -- Typed locator (JSON, see ProgressLocator)
+    locator TEXT,
+    -- Legacy EPUB location, kept in sync with CFI locators
     cfi TEXT NOT NULL DEFAULT '',
     page INTEGER,
     total_pages INTEGER,
@@ -168,6 +232,8 @@ CREATE TABLE IF NOT EXISTS highlights (
     cfi TEXT NOT NULL DEFAULT '',
     -- PDF location (page number, 1-indexed)
     page INTEGER,
+    -- Printed label of that page ("xiv", "A-3")
+    page_label TEXT,
     -- Highlighted text
     text TEXT NOT NULL,
     chapter TEXT,
@@ -238,6 +304,112 @@ CREATE TABLE IF NOT EXISTS sync_versions (
     last_sync TEXT,
     device_id TEXT
 );
+
+-- Collections (shelves, read lists); user_id NULL means shared
+CREATE TABLE IF NOT EXISTS collections (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    user_id TEXT,
+    description TEXT,
+    -- Where the collection came from: 'user', 'calibre-web', 'komga', ...
+    source TEXT NOT NULL DEFAULT 'user',
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+-- Books in a collection, in display order
+CREATE TABLE IF NOT EXISTS collection_books (
+    collection_id TEXT NOT NULL REFERENCES collections(id) ON DELETE CASCADE,
+    book_id TEXT NOT NULL,
+    position INTEGER NOT NULL DEFAULT 0,
+    added_at TEXT NOT NULL DEFAULT (datetime('now')),
+
+    PRIMARY KEY (collection_id, book_id)
+);
+
+-- Readability metrics per library book (see library::TextStats)
+CREATE TABLE IF NOT EXISTS book_text_stats (
+    book_id TEXT PRIMARY KEY,
+    -- S3 key of the file the text came from
+    source_key TEXT NOT NULL,
+    words INTEGER NOT NULL,
+    sentences INTEGER NOT NULL,
+    syllables INTEGER NOT NULL,
+    unique_words INTEGER NOT NULL,
+    avg_sentence_length REAL NOT NULL,
+    avg_syllables_per_word REAL NOT NULL,
+    flesch_reading_ease REAL NOT NULL,
+    flesch_kincaid_grade REAL NOT NULL,
+    vocabulary_richness REAL NOT NULL,
+    computed_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+-- Manual maturity ratings, applied over ratings parsed from metadata
+CREATE TABLE IF NOT EXISTS maturity_overrides (
+    book_id TEXT PRIMARY KEY,
+    -- 'all-ages', 'teen', 'mature', or 'adult'
+    rating TEXT NOT NULL,
+    -- JSON array; NULL keeps the metadata's content warnings
+    content_warnings TEXT,
+    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+-- Likely repeated pages in scanned PDFs (see library::find_duplicate_pages)
+CREATE TABLE IF NOT EXISTS page_duplicate_reports (
+    book_id TEXT PRIMARY KEY,
+    -- S3 key of the scanned PDF
+    source_key TEXT NOT NULL,
+    page_count INTEGER NOT NULL,
+    duplicate_count INTEGER NOT NULL,
+    -- JSON array of library::DuplicatePage
+    duplicates TEXT NOT NULL,
+    computed_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+-- Default reader preferences ('' is the server-wide default user)
+CREATE TABLE IF NOT EXISTS reader_preferences (
+    user_id TEXT PRIMARY KEY,
+    -- JSON of db::ReaderPreferences
+    preferences TEXT NOT NULL,
+    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+-- Highlight color palettes ('' is the server-wide palette)
+CREATE TABLE IF NOT EXISTS highlight_palettes (
+    user_id TEXT PRIMARY KEY,
+    -- JSON of db::HighlightPalette
+    palette TEXT NOT NULL,
+    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+-- Last time each book file was served (see library::StorageTiering)
+CREATE TABLE IF NOT EXISTS file_access (
+    s3_key TEXT PRIMARY KEY,
+    accessed_at TEXT NOT NULL
+);
+
+-- Book files moved to a colder storage class
+CREATE TABLE IF NOT EXISTS storage_tiers (
+    s3_key TEXT PRIMARY KEY,
+    storage_class TEXT NOT NULL,
+    -- 'archived' or 'retrieving'
+    status TEXT NOT NULL,
+    archived_at TEXT NOT NULL,
+    restore_requested_at TEXT
+);
+
+-- Notification center; NULL user_id notifies every user
+CREATE TABLE IF NOT EXISTS notifications (
+    id TEXT PRIMARY KEY,
+    user_id TEXT,
+    -- 'ingest', 'ocr', or 'sync-conflict'
+    kind TEXT NOT NULL,
+    title TEXT NOT NULL,
+    body TEXT,
+    book_id TEXT,
+    read_at TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
 "#;
 
 /// SQL for creating indexes (run after migrations)
@@ -270,4 +442,13 @@ CREATE INDEX IF NOT EXISTS idx_sync_queue_timestamp ON sync_queue(timestamp);
 CREATE INDEX IF NOT EXISTS idx_sync_book ON sync_operations(book_id);
 CREATE INDEX IF NOT EXISTS idx_sync_timestamp ON sync_operations(timestamp);
 CREATE INDEX IF NOT EXISTS idx_sync_entity ON sync_operations(entity_type, entity_id);
+
+CREATE INDEX IF NOT EXISTS idx_collections_user_id ON collections(user_id);
+CREATE INDEX IF NOT EXISTS idx_collection_books_book_id ON collection_books(book_id);
+
+CREATE INDEX IF NOT EXISTS idx_text_stats_grade ON book_text_stats(flesch_kincaid_grade);
+CREATE INDEX IF NOT EXISTS idx_page_duplicates_count ON page_duplicate_reports(duplicate_count);
+
+CREATE INDEX IF NOT EXISTS idx_notifications_user_id ON notifications(user_id);
+CREATE INDEX IF NOT EXISTS idx_notifications_created_at ON notifications(created_at);
 "#;
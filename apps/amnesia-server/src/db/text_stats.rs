@@ -0,0 +1,220 @@
+//! Per-book text statistics storage
+//!
+//! Readability metrics are computed from a book's text by
+//! [`crate::library::TextAnalyzer`] and stored under the book's
+//! [`stable_id`](crate::library::LibraryBook::stable_id), together with the
+//! S3 key of the file they were computed from so a changed file is
+//! re-analyzed.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::error::Result;
+use crate::library::TextStats;
+
+/// Stored text statistics for a book
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct BookTextStats {
+    pub book_id: String,
+    /// S3 key of the analyzed file
+    pub source_key: String,
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub stats: TextStats,
+    pub computed_at: String,
+}
+
+/// Reading level filter; unset bounds are not applied
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextStatsFilter {
+    /// Minimum Flesch-Kincaid grade
+    pub min_grade: Option<f64>,
+    /// Maximum Flesch-Kincaid grade
+    pub max_grade: Option<f64>,
+    /// Minimum Flesch reading ease
+    pub min_reading_ease: Option<f64>,
+    /// Maximum Flesch reading ease
+    pub max_reading_ease: Option<f64>,
+    pub min_words: Option<i64>,
+    pub max_words: Option<i64>,
+}
+
+impl TextStatsFilter {
+    /// Whether any bound is set
+    pub fn is_empty(&self) -> bool {
+        self.min_grade.is_none()
+            && self.max_grade.is_none()
+            && self.min_reading_ease.is_none()
+            && self.max_reading_ease.is_none()
+            && self.min_words.is_none()
+            && self.max_words.is_none()
+    }
+}
+
+/// All columns to select for text statistics
+const TEXT_STATS_COLUMNS: &str = "book_id, source_key, words, sentences, syllables, unique_words, \
+     avg_sentence_length, avg_syllables_per_word, flesch_reading_ease, flesch_kincaid_grade, \
+     vocabulary_richness, computed_at";
+
+/// Text statistics repository
+pub struct TextStatsRepository<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl<'a> TextStatsRepository<'a> {
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Statistics for a book
+    pub async fn get(&self, book_id: &str) -> Result<Option<BookTextStats>> {
+        let query = format!(
+            "SELECT {} FROM book_text_stats WHERE book_id = ?",
+            TEXT_STATS_COLUMNS
+        );
+        let stats = sqlx::query_as::<_, BookTextStats>(&query)
+            .bind(book_id)
+            .fetch_optional(self.pool)
+            .await?;
+
+        Ok(stats)
+    }
+
+    /// Store a book's statistics, replacing any previous ones
+    pub async fn upsert(
+        &self,
+        book_id: &str,
+        source_key: &str,
+        stats: &TextStats,
+    ) -> Result<BookTextStats> {
+        let computed_at = Utc::now().to_rfc3339();
+
+        sqlx::query(
+            r#"
+            INSERT INTO book_text_stats (
+                book_id, source_key, words, sentences, syllables, unique_words,
+                avg_sentence_length, avg_syllables_per_word, flesch_reading_ease,
+                flesch_kincaid_grade, vocabulary_richness, computed_at
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(book_id) DO UPDATE SET
+                source_key = excluded.source_key,
+                words = excluded.words,
+                sentences = excluded.sentences,
+                syllables = excluded.syllables,
+                unique_words = excluded.unique_words,
+                avg_sentence_length = excluded.avg_sentence_length,
+                avg_syllables_per_word = excluded.avg_syllables_per_word,
+                flesch_reading_ease = excluded.flesch_reading_ease,
+                flesch_kincaid_grade = excluded.flesch_kincaid_grade,
+                vocabulary_richness = excluded.vocabulary_richness,
+                computed_at = excluded.computed_at
+            "#,
+        )
+        .bind(book_id)
+        .bind(source_key)
+        .bind(stats.words)
+        .bind(stats.sentences)
+        .bind(stats.syllables)
+        .bind(stats.unique_words)
+        .bind(stats.avg_sentence_length)
+        .bind(stats.avg_syllables_per_word)
+        .bind(stats.flesch_reading_ease)
+        .bind(stats.flesch_kincaid_grade)
+        .bind(stats.vocabulary_richness)
+        .bind(&computed_at)
+        .execute(self.pool)
+        .await?;
+
+        Ok(BookTextStats {
+            book_id: book_id.to_string(),
+            source_key: source_key.to_string(),
+            stats: *stats,
+            computed_at,
+        })
+    }
+
+    /// Books matching a reading level filter, easiest first
+    pub async fn find(&self, filter: &TextStatsFilter, limit: i64) -> Result<Vec<BookTextStats>> {
+        let query = format!(
+            r#"
+            SELECT {} FROM book_text_stats
+            WHERE (?1 IS NULL OR flesch_kincaid_grade >= ?1)
+              AND (?2 IS NULL OR flesch_kincaid_grade <= ?2)
+              AND (?3 IS NULL OR flesch_reading_ease >= ?3)
+              AND (?4 IS NULL OR flesch_reading_ease <= ?4)
+              AND (?5 IS NULL OR words >= ?5)
+              AND (?6 IS NULL OR words <= ?6)
+            ORDER BY flesch_kincaid_grade
+            LIMIT ?7
+            "#,
+            TEXT_STATS_COLUMNS
+        );
+        let stats = sqlx::query_as::<_, BookTextStats>(&query)
+            .bind(filter.min_grade)
+            .bind(filter.max_grade)
+            .bind(filter.min_reading_ease)
+            .bind(filter.max_reading_ease)
+            .bind(filter.min_words)
+            .bind(filter.max_words)
+            .bind(limit)
+            .fetch_all(self.pool)
+            .await?;
+
+        Ok(stats)
+    }
+
+    /// Source key each analyzed book was computed from
+    pub async fn source_keys(&self) -> Result<Vec<(String, String)>> {
+        let rows: Vec<(String, String)> =
+            sqlx::query_as("SELECT book_id, source_key FROM book_text_stats")
+                .fetch_all(self.pool)
+                .await?;
+
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::initialize_schema;
+
+    #[tokio::test]
+    async fn test_text_stats_filter() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        initialize_schema(&pool).await.unwrap();
+        let repo = TextStatsRepository::new(&pool);
+
+        let easy = TextStats::from_text("The cat sat. The dog ran.");
+        let hard = TextStats::from_text(
+            "Comprehensive institutional restructuring fundamentally transformed departmental \
+             responsibilities.",
+        );
+        repo.upsert("easy", "a/easy.epub", &easy).await.unwrap();
+        repo.upsert("hard", "a/hard.epub", &hard).await.unwrap();
+        repo.upsert("hard", "a/hard-v2.epub", &hard).await.unwrap();
+
+        let stored = repo.get("hard").await.unwrap().unwrap();
+        assert_eq!(stored.source_key, "a/hard-v2.epub");
+        assert_eq!(stored.stats, hard);
+
+        let all = repo.find(&TextStatsFilter::default(), 10).await.unwrap();
+        assert_eq!(
+            all.iter().map(|s| s.book_id.as_str()).collect::<Vec<_>>(),
+            vec!["easy", "hard"]
+        );
+
+        let filter = TextStatsFilter {
+            max_grade: Some(8.0),
+            ..Default::default()
+        };
+        let found = repo.find(&filter, 10).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].book_id, "easy");
+        assert!(!filter.is_empty());
+    }
+}
@@ -0,0 +1,232 @@
+//! Storage tier bookkeeping
+//!
+//! Records when each book file was last served and which files the storage
+//! tiering task moved to a colder storage class (see
+//! [`crate::library::StorageTiering`]), so files can be restored when they
+//! are next opened.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use utoipa::ToSchema;
+
+use crate::error::{AppError, Result};
+
+/// Where an archived file stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TierStatus {
+    /// In the cold storage class
+    Archived,
+    /// A restore was requested and has not finished
+    Retrieving,
+}
+
+impl TierStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            TierStatus::Archived => "archived",
+            TierStatus::Retrieving => "retrieving",
+        }
+    }
+}
+
+/// A book file in cold storage
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchivedFile {
+    pub s3_key: String,
+    pub storage_class: String,
+    pub status: TierStatus,
+    pub archived_at: String,
+    pub restore_requested_at: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct ArchivedFileRow {
+    s3_key: String,
+    storage_class: String,
+    status: String,
+    archived_at: String,
+    restore_requested_at: Option<String>,
+}
+
+impl TryFrom<ArchivedFileRow> for ArchivedFile {
+    type Error = AppError;
+
+    fn try_from(row: ArchivedFileRow) -> Result<Self> {
+        let status = match row.status.as_str() {
+            "archived" => TierStatus::Archived,
+            "retrieving" => TierStatus::Retrieving,
+            other => {
+                return Err(AppError::Internal(format!(
+                    "Invalid storage tier status: {}",
+                    other
+                )))
+            }
+        };
+        Ok(Self {
+            s3_key: row.s3_key,
+            storage_class: row.storage_class,
+            status,
+            archived_at: row.archived_at,
+            restore_requested_at: row.restore_requested_at,
+        })
+    }
+}
+
+const ARCHIVED_COLUMNS: &str = "s3_key, storage_class, status, archived_at, restore_requested_at";
+
+/// Storage tier repository
+pub struct TieringRepository<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl<'a> TieringRepository<'a> {
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Note that a file was just served
+    pub async fn record_access(&self, s3_key: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO file_access (s3_key, accessed_at) VALUES (?, ?)
+            ON CONFLICT(s3_key) DO UPDATE SET accessed_at = excluded.accessed_at
+            "#,
+        )
+        .bind(s3_key)
+        .bind(Utc::now().to_rfc3339())
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// When each file that was ever served was last served
+    pub async fn last_access(&self) -> Result<HashMap<String, DateTime<Utc>>> {
+        let rows: Vec<(String, String)> =
+            sqlx::query_as("SELECT s3_key, accessed_at FROM file_access")
+                .fetch_all(self.pool)
+                .await?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|(key, at)| {
+                let at = DateTime::parse_from_rfc3339(&at).ok()?;
+                Some((key, at.with_timezone(&Utc)))
+            })
+            .collect())
+    }
+
+    /// An archived file, if the key is archived
+    pub async fn get(&self, s3_key: &str) -> Result<Option<ArchivedFile>> {
+        let query = format!(
+            "SELECT {} FROM storage_tiers WHERE s3_key = ?",
+            ARCHIVED_COLUMNS
+        );
+        let row = sqlx::query_as::<_, ArchivedFileRow>(&query)
+            .bind(s3_key)
+            .fetch_optional(self.pool)
+            .await?;
+
+        row.map(ArchivedFile::try_from).transpose()
+    }
+
+    /// Every archived file, oldest first
+    pub async fn list(&self) -> Result<Vec<ArchivedFile>> {
+        let query = format!(
+            "SELECT {} FROM storage_tiers ORDER BY archived_at, s3_key",
+            ARCHIVED_COLUMNS
+        );
+        let rows = sqlx::query_as::<_, ArchivedFileRow>(&query)
+            .fetch_all(self.pool)
+            .await?;
+
+        rows.into_iter().map(ArchivedFile::try_from).collect()
+    }
+
+    /// Record a file as moved to `storage_class`
+    pub async fn mark_archived(&self, s3_key: &str, storage_class: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO storage_tiers (s3_key, storage_class, status, archived_at)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT(s3_key) DO UPDATE SET
+                storage_class = excluded.storage_class,
+                status = excluded.status,
+                archived_at = excluded.archived_at,
+                restore_requested_at = NULL
+            "#,
+        )
+        .bind(s3_key)
+        .bind(storage_class)
+        .bind(TierStatus::Archived.as_str())
+        .bind(Utc::now().to_rfc3339())
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Record that a restore was requested for an archived file
+    pub async fn mark_retrieving(&self, s3_key: &str) -> Result<()> {
+        sqlx::query(
+            "UPDATE storage_tiers SET status = ?, restore_requested_at = ? WHERE s3_key = ?",
+        )
+        .bind(TierStatus::Retrieving.as_str())
+        .bind(Utc::now().to_rfc3339())
+        .bind(s3_key)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Forget an archived file once it is back in the default class
+    pub async fn remove(&self, s3_key: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM storage_tiers WHERE s3_key = ?")
+            .bind(s3_key)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::initialize_schema;
+
+    #[tokio::test]
+    async fn test_tiering_repository() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        initialize_schema(&pool).await.unwrap();
+        let repo = TieringRepository::new(&pool);
+
+        repo.record_access("a/book.epub").await.unwrap();
+        repo.record_access("a/book.epub").await.unwrap();
+        let access = repo.last_access().await.unwrap();
+        assert_eq!(access.len(), 1);
+        assert!(Utc::now() - access["a/book.epub"] < chrono::Duration::minutes(1));
+
+        assert!(repo.get("b/book.pdf").await.unwrap().is_none());
+        repo.mark_archived("b/book.pdf", "GLACIER").await.unwrap();
+        let archived = repo.get("b/book.pdf").await.unwrap().unwrap();
+        assert_eq!(archived.status, TierStatus::Archived);
+        assert_eq!(archived.storage_class, "GLACIER");
+        assert!(archived.restore_requested_at.is_none());
+
+        repo.mark_retrieving("b/book.pdf").await.unwrap();
+        let archived = repo.get("b/book.pdf").await.unwrap().unwrap();
+        assert_eq!(archived.status, TierStatus::Retrieving);
+        assert!(archived.restore_requested_at.is_some());
+        assert_eq!(repo.list().await.unwrap().len(), 1);
+
+        assert!(repo.remove("b/book.pdf").await.unwrap());
+        assert!(!repo.remove("b/book.pdf").await.unwrap());
+    }
+}
@@ -3,6 +3,21 @@
 //! Provides a single caching layer for all document formats (PDF, EPUB).
 //! Uses LRU eviction for bounded memory usage.
 //!
+//! # Open documents
+//!
+//! Clients signal when a document is opened and closed. An open document is
+//! pinned (its parser and renderer survive LRU eviction) and a
+//! [`PrewarmPlan`] renders the first pages and extracts the text layer around
+//! the saved position ahead of the first request. Closing the last session
+//! unpins it and drops its cached output.
+//!
+//! # Memory budget
+//!
+//! Rendered output and structured text are evicted by total byte size
+//! ([`ByteLruCache`]) rather than entry count, since a high-DPI page can be
+//! a hundred times larger than a thumbnail. [`DocumentCache::composition`]
+//! reports how those bytes are spread across documents.
+//!
 //! # Thread Safety
 //!
 //! All caches use `tokio::sync::RwLock` for async-safe access.
@@ -13,13 +28,16 @@ use std::num::NonZeroUsize;
 use std::sync::Arc;
 
 use lru::LruCache;
+use serde::Serialize;
 use tokio::sync::RwLock;
 use tokio::time::{timeout, Duration};
+use utoipa::ToSchema;
 
 use super::{
     DocumentError, DocumentParser, DocumentRenderer, DocumentResult, ImageFormat, ParsedDocument,
     RenderRequest, RenderResult, SearchOptions, SearchResult, StructuredText,
 };
+use crate::byte_cache::{ByteCacheStats, ByteLruCache, ByteSize};
 
 /// Timeout for document parsing operations
 const PARSE_TIMEOUT_SECS: u64 = 30;
@@ -30,6 +48,13 @@ const TEXT_TIMEOUT_SECS: u64 = 15;
 /// Timeout for search operations
 const SEARCH_TIMEOUT_SECS: u64 = 30;
 
+/// Leading items rendered when a document is opened
+pub const DEFAULT_PREWARM_ITEMS: usize = 3;
+/// Render scale for prewarmed items (matches the render endpoint default)
+pub const DEFAULT_PREWARM_SCALE: f32 = 1.5;
+/// Items on each side of the resume position whose text layer is prewarmed
+const PREWARM_TEXT_RADIUS: usize = 1;
+
 /// Cache configuration options
 #[derive(Debug, Clone)]
 pub struct CacheConfig {
@@ -37,10 +62,10 @@ pub struct CacheConfig {
     pub max_parsers: usize,
     /// Maximum number of renderer instances to keep
     pub max_renderers: usize,
-    /// Maximum number of rendered pages/chapters to cache
-    pub max_renders: usize,
-    /// Maximum number of structured text entries to cache
-    pub max_stext: usize,
+    /// Byte budget for rendered pages/chapters
+    pub max_render_bytes: usize,
+    /// Byte budget for structured text
+    pub max_stext_bytes: usize,
 }
 
 impl Default for CacheConfig {
@@ -48,8 +73,8 @@ impl Default for CacheConfig {
         Self {
             max_parsers: 50,
             max_renderers: 50,
-            max_renders: 500,
-            max_stext: 1000,
+            max_render_bytes: 256 * 1024 * 1024,
+            max_stext_bytes: 64 * 1024 * 1024,
         }
     }
 }
@@ -67,6 +92,8 @@ pub struct RenderCacheKey {
     pub rotation: u16,
     /// Output format
     pub format: ImageFormat,
+    /// JPEG quality
+    pub quality: Option<u8>,
 }
 
 impl RenderCacheKey {
@@ -78,6 +105,7 @@ impl RenderCacheKey {
             scale: (request.scale * 100.0) as u32,
             rotation: request.rotation as u16,
             format: request.format,
+            quality: request.quality,
         }
     }
 
@@ -89,8 +117,77 @@ impl RenderCacheKey {
             scale: max_size,
             rotation: 0,
             format: ImageFormat::Jpeg,
+            quality: None,
+        }
+    }
+}
+
+/// Items to warm when a document is opened
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrewarmPlan {
+    /// Items to render: the leading items, then the resume item and the next
+    pub render_items: Vec<usize>,
+    /// Items whose structured text is extracted (around the resume item)
+    pub text_items: Vec<usize>,
+    /// Render scale for prewarmed items
+    pub scale: f32,
+    /// Render format for prewarmed items
+    pub format: ImageFormat,
+    /// JPEG quality for prewarmed items
+    pub quality: Option<u8>,
+}
+
+impl PrewarmPlan {
+    /// Plan for a document with `item_count` items
+    ///
+    /// Renders the first `leading` items plus the resume position; without a
+    /// resume position the text layer is warmed from the first item.
+    pub fn new(item_count: usize, leading: usize, resume_item: Option<usize>) -> Self {
+        let resume_item = resume_item.filter(|&index| index < item_count);
+
+        let mut render_items: Vec<usize> = (0..leading.min(item_count)).collect();
+        if let Some(resume) = resume_item {
+            for index in [resume, resume + 1] {
+                if index < item_count && !render_items.contains(&index) {
+                    render_items.push(index);
+                }
+            }
+        }
+
+        let center = resume_item.unwrap_or(0);
+        let text_items = (center.saturating_sub(PREWARM_TEXT_RADIUS)
+            ..=center + PREWARM_TEXT_RADIUS)
+            .filter(|&index| index < item_count)
+            .collect();
+
+        Self {
+            render_items,
+            text_items,
+            scale: DEFAULT_PREWARM_SCALE,
+            format: ImageFormat::Png,
+            quality: None,
         }
     }
+
+    /// Use the render settings the client will request pages with
+    pub fn with_render_settings(
+        mut self,
+        scale: f32,
+        format: ImageFormat,
+        quality: Option<u8>,
+    ) -> Self {
+        self.scale = scale;
+        self.format = format;
+        self.quality = quality;
+        self
+    }
+}
+
+/// Parser and renderer held while a document has open sessions
+struct PinnedDocument {
+    parser: Arc<dyn DocumentParser>,
+    renderer: Arc<dyn DocumentRenderer>,
+    open_count: usize,
 }
 
 /// Unified document cache for all formats
@@ -109,10 +206,13 @@ pub struct DocumentCache {
     renderers: Arc<RwLock<LruCache<String, Arc<dyn DocumentRenderer>>>>,
 
     /// Rendered output cache (pages/chapters)
-    render_cache: Arc<RwLock<LruCache<RenderCacheKey, Vec<u8>>>>,
+    render_cache: Arc<RwLock<ByteLruCache<RenderCacheKey, Vec<u8>>>>,
 
     /// Structured text cache
-    stext_cache: Arc<RwLock<LruCache<(String, usize), StructuredText>>>,
+    stext_cache: Arc<RwLock<ByteLruCache<(String, usize), StructuredText>>>,
+
+    /// Open documents, exempt from parser/renderer eviction
+    pinned: Arc<RwLock<HashMap<String, PinnedDocument>>>,
 
     /// Configuration
     config: CacheConfig,
@@ -131,17 +231,14 @@ impl DocumentCache {
             .unwrap_or(NonZeroUsize::new(50).unwrap());
         let renderers_size = NonZeroUsize::new(config.max_renderers)
             .unwrap_or(NonZeroUsize::new(50).unwrap());
-        let renders_size = NonZeroUsize::new(config.max_renders)
-            .unwrap_or(NonZeroUsize::new(500).unwrap());
-        let stext_size = NonZeroUsize::new(config.max_stext)
-            .unwrap_or(NonZeroUsize::new(1000).unwrap());
 
         Self {
             documents: Arc::new(RwLock::new(HashMap::new())),
             parsers: Arc::new(RwLock::new(LruCache::new(parsers_size))),
             renderers: Arc::new(RwLock::new(LruCache::new(renderers_size))),
-            render_cache: Arc::new(RwLock::new(LruCache::new(renders_size))),
-            stext_cache: Arc::new(RwLock::new(LruCache::new(stext_size))),
+            render_cache: Arc::new(RwLock::new(ByteLruCache::new(config.max_render_bytes))),
+            stext_cache: Arc::new(RwLock::new(ByteLruCache::new(config.max_stext_bytes))),
+            pinned: Arc::new(RwLock::new(HashMap::new())),
             config,
         }
     }
@@ -211,16 +308,117 @@ impl DocumentCache {
         docs.contains_key(doc_id)
     }
 
-    /// Get parser for a document
+    /// Get parser for a document (open documents are always available)
     pub async fn get_parser(&self, doc_id: &str) -> Option<Arc<dyn DocumentParser>> {
-        let mut parsers = self.parsers.write().await;
-        parsers.get(doc_id).cloned()
+        if let Some(parser) = self.parsers.write().await.get(doc_id).cloned() {
+            return Some(parser);
+        }
+        let pinned = self.pinned.read().await;
+        pinned.get(doc_id).map(|doc| Arc::clone(&doc.parser))
     }
 
-    /// Get renderer for a document
+    /// Get renderer for a document (open documents are always available)
     pub async fn get_renderer(&self, doc_id: &str) -> Option<Arc<dyn DocumentRenderer>> {
-        let mut renderers = self.renderers.write().await;
-        renderers.get(doc_id).cloned()
+        if let Some(renderer) = self.renderers.write().await.get(doc_id).cloned() {
+            return Some(renderer);
+        }
+        let pinned = self.pinned.read().await;
+        pinned.get(doc_id).map(|doc| Arc::clone(&doc.renderer))
+    }
+
+    /// Start a session for a document and pin it
+    ///
+    /// Returns the number of open sessions for the document.
+    pub async fn open(
+        &self,
+        doc_id: &str,
+        parsed: ParsedDocument,
+        parser: Arc<dyn DocumentParser>,
+        renderer: Arc<dyn DocumentRenderer>,
+    ) -> usize {
+        self.store_document_with_renderer(
+            doc_id.to_string(),
+            parsed,
+            Arc::clone(&parser),
+            Arc::clone(&renderer),
+        )
+        .await;
+
+        let mut pinned = self.pinned.write().await;
+        let doc = pinned
+            .entry(doc_id.to_string())
+            .or_insert_with(|| PinnedDocument {
+                parser,
+                renderer,
+                open_count: 0,
+            });
+        doc.open_count += 1;
+        doc.open_count
+    }
+
+    /// End a session for a document
+    ///
+    /// Closing the last session unpins the document and drops its cached
+    /// renders and text. Returns the remaining session count, or `None` if
+    /// the document was not open.
+    pub async fn close(&self, doc_id: &str) -> Option<usize> {
+        let remaining = {
+            let mut pinned = self.pinned.write().await;
+            let doc = pinned.get_mut(doc_id)?;
+            doc.open_count -= 1;
+            let remaining = doc.open_count;
+            if remaining == 0 {
+                pinned.remove(doc_id);
+            }
+            remaining
+        };
+
+        if remaining == 0 {
+            self.remove_outputs(doc_id).await;
+        }
+        Some(remaining)
+    }
+
+    /// Number of open sessions for a document
+    pub async fn open_count(&self, doc_id: &str) -> usize {
+        let pinned = self.pinned.read().await;
+        pinned.get(doc_id).map_or(0, |doc| doc.open_count)
+    }
+
+    /// Render and extract text for the planned items
+    ///
+    /// Items already cached are skipped by the normal cache lookups. Failures
+    /// are logged and do not stop the remaining items. Returns the number of
+    /// items warmed.
+    pub async fn prewarm(&self, doc_id: &str, plan: &PrewarmPlan) -> usize {
+        let mut warmed = 0;
+
+        for &item_index in &plan.render_items {
+            let request = RenderRequest {
+                item_index,
+                scale: plan.scale,
+                format: plan.format,
+                quality: plan.quality,
+                ..Default::default()
+            };
+            match self.render(doc_id, &request).await {
+                Ok(_) => warmed += 1,
+                Err(e) => {
+                    tracing::debug!("Prewarm render of {}#{} failed: {}", doc_id, item_index, e)
+                }
+            }
+        }
+
+        for &item_index in &plan.text_items {
+            match self.get_structured_text(doc_id, item_index).await {
+                Ok(_) => warmed += 1,
+                Err(e) => {
+                    tracing::debug!("Prewarm text of {}#{} failed: {}", doc_id, item_index, e)
+                }
+            }
+        }
+
+        warmed
     }
 
     /// Extract text from a document item with caching
@@ -242,6 +440,23 @@ impl DocumentCache {
         result
     }
 
+    /// Extract text through the cache, or with `parser` once the cache no
+    /// longer holds a parser for the document
+    ///
+    /// Parsers are LRU-bounded, so a closed document can lose its parser
+    /// while its metadata is still cached.
+    pub async fn extract_text_or(
+        &self,
+        doc_id: &str,
+        item_index: usize,
+        parser: &dyn DocumentParser,
+    ) -> DocumentResult<String> {
+        match self.extract_text(doc_id, item_index).await {
+            Err(DocumentError::NotFound(_)) => parser.extract_text(item_index).await,
+            result => result,
+        }
+    }
+
     /// Get structured text with caching
     pub async fn get_structured_text(
         &self,
@@ -278,6 +493,20 @@ impl DocumentCache {
         Ok(result)
     }
 
+    /// Get structured text through the cache, or with `parser` once the
+    /// cache no longer holds a parser for the document
+    pub async fn get_structured_text_or(
+        &self,
+        doc_id: &str,
+        item_index: usize,
+        parser: &dyn DocumentParser,
+    ) -> DocumentResult<StructuredText> {
+        match self.get_structured_text(doc_id, item_index).await {
+            Err(DocumentError::NotFound(_)) => parser.get_structured_text(item_index).await,
+            result => result,
+        }
+    }
+
     /// Search document with timeout
     pub async fn search(
         &self,
@@ -315,6 +544,7 @@ impl DocumentCache {
                     format: request.format,
                     width: 0,  // TODO: cache dimensions
                     height: 0,
+                    warnings: Vec::new(),
                 });
             }
         }
@@ -339,6 +569,20 @@ impl DocumentCache {
         Ok(result)
     }
 
+    /// Render an item through the cache, or with `renderer` once the cache
+    /// no longer holds a renderer for the document
+    pub async fn render_or(
+        &self,
+        doc_id: &str,
+        request: &RenderRequest,
+        renderer: &dyn DocumentRenderer,
+    ) -> DocumentResult<RenderResult> {
+        match self.render(doc_id, request).await {
+            Err(DocumentError::NotFound(_)) => renderer.render_item(request).await,
+            result => result,
+        }
+    }
+
     /// Render a thumbnail with caching
     pub async fn render_thumbnail(
         &self,
@@ -357,6 +601,7 @@ impl DocumentCache {
                     format: ImageFormat::Jpeg,
                     width: 0,
                     height: 0,
+                    warnings: Vec::new(),
                 });
             }
         }
@@ -401,30 +646,49 @@ impl DocumentCache {
             renderers.pop(doc_id);
         }
 
-        // Remove cached renders
+        // Drop any pin
         {
-            let mut cache = self.render_cache.write().await;
-            let keys_to_remove: Vec<RenderCacheKey> = cache
-                .iter()
-                .filter(|(k, _)| k.doc_id == doc_id)
-                .map(|(k, _)| k.clone())
-                .collect();
-            for key in keys_to_remove {
-                cache.pop(&key);
-            }
+            let mut pinned = self.pinned.write().await;
+            pinned.remove(doc_id);
         }
 
-        // Remove cached structured text
+        self.remove_outputs(doc_id).await;
+    }
+
+    /// Remove documents whose parser and renderer were both evicted
+    ///
+    /// Metadata outlives the LRU-bounded parsers, so without this such
+    /// documents still look cached but can no longer render. Open documents
+    /// are kept. Returns the number of documents removed.
+    pub async fn evict_orphans(&self) -> usize {
+        let orphans: Vec<String> = {
+            let docs = self.documents.read().await;
+            let parsers = self.parsers.read().await;
+            let renderers = self.renderers.read().await;
+            let pinned = self.pinned.read().await;
+            docs.keys()
+                .filter(|id| {
+                    !parsers.contains(*id) && !renderers.contains(*id) && !pinned.contains_key(*id)
+                })
+                .cloned()
+                .collect()
+        };
+
+        for doc_id in &orphans {
+            self.remove(doc_id).await;
+        }
+        orphans.len()
+    }
+
+    /// Remove cached renders and structured text for a document
+    async fn remove_outputs(&self, doc_id: &str) {
+        {
+            let mut cache = self.render_cache.write().await;
+            cache.remove_where(|k| k.doc_id == doc_id);
+        }
         {
             let mut cache = self.stext_cache.write().await;
-            let keys_to_remove: Vec<(String, usize)> = cache
-                .iter()
-                .filter(|((id, _), _)| id == doc_id)
-                .map(|(k, _)| k.clone())
-                .collect();
-            for key in keys_to_remove {
-                cache.pop(&key);
-            }
+            cache.remove_where(|(id, _)| id == doc_id);
         }
     }
 
@@ -450,6 +714,10 @@ impl DocumentCache {
             let mut cache = self.stext_cache.write().await;
             cache.clear();
         }
+        {
+            let mut pinned = self.pinned.write().await;
+            pinned.clear();
+        }
     }
 
     /// Get the number of cached documents
@@ -478,14 +746,9 @@ impl DocumentCache {
             let renderers = self.renderers.read().await;
             (renderers.len(), renderers.cap().get())
         };
-        let renders = {
-            let cache = self.render_cache.read().await;
-            (cache.len(), cache.cap().get())
-        };
-        let stext = {
-            let cache = self.stext_cache.read().await;
-            (cache.len(), cache.cap().get())
-        };
+        let renders = self.render_cache.read().await.stats();
+        let stext = self.stext_cache.read().await.stats();
+        let open_documents = self.pinned.read().await.len();
 
         CacheStats {
             documents,
@@ -493,12 +756,81 @@ impl DocumentCache {
             parsers_capacity: parsers.1,
             renderers_used: renderers.0,
             renderers_capacity: renderers.1,
-            renders_used: renders.0,
-            renders_capacity: renders.1,
-            stext_used: stext.0,
-            stext_capacity: stext.1,
+            renders,
+            stext,
+            open_documents,
         }
     }
+
+    /// Cached bytes per document, largest first
+    pub async fn composition(&self) -> Vec<DocumentCacheUsage> {
+        let render_bytes = self
+            .render_cache
+            .read()
+            .await
+            .bytes_by(|k| k.doc_id.clone());
+        let stext_bytes = self.stext_cache.read().await.bytes_by(|(id, _)| id.clone());
+        let pinned = self.pinned.read().await;
+
+        let mut ids: Vec<&String> = render_bytes.keys().chain(stext_bytes.keys()).collect();
+        ids.sort();
+        ids.dedup();
+
+        let mut usage: Vec<DocumentCacheUsage> = ids
+            .into_iter()
+            .map(|id| {
+                let render_bytes = render_bytes.get(id).copied().unwrap_or(0);
+                let stext_bytes = stext_bytes.get(id).copied().unwrap_or(0);
+                DocumentCacheUsage {
+                    id: id.clone(),
+                    render_bytes,
+                    stext_bytes,
+                    total_bytes: render_bytes + stext_bytes,
+                    open: pinned.contains_key(id),
+                }
+            })
+            .collect();
+        usage.sort_by(|a, b| b.total_bytes.cmp(&a.total_bytes));
+        usage
+    }
+}
+
+impl ByteSize for StructuredText {
+    fn byte_size(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self
+                .blocks
+                .iter()
+                .map(|block| {
+                    std::mem::size_of_val(block)
+                        + block
+                            .lines
+                            .iter()
+                            .map(|line| {
+                                std::mem::size_of_val(line)
+                                    + std::mem::size_of_val(line.chars.as_slice())
+                                    + line.text.as_ref().map_or(0, String::capacity)
+                            })
+                            .sum::<usize>()
+                })
+                .sum::<usize>()
+    }
+}
+
+/// Cached output held for one document
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentCacheUsage {
+    /// Document ID
+    pub id: String,
+    /// Bytes of rendered pages/chapters
+    pub render_bytes: usize,
+    /// Bytes of structured text
+    pub stext_bytes: usize,
+    /// Sum of the above
+    pub total_bytes: usize,
+    /// Whether the document has an open session
+    pub open: bool,
 }
 
 /// Cache statistics
@@ -514,14 +846,12 @@ pub struct CacheStats {
     pub renderers_used: usize,
     /// Renderer cache capacity
     pub renderers_capacity: usize,
-    /// Number of cached render outputs
-    pub renders_used: usize,
-    /// Render cache capacity
-    pub renders_capacity: usize,
-    /// Number of cached structured text entries
-    pub stext_used: usize,
-    /// Structured text cache capacity
-    pub stext_capacity: usize,
+    /// Rendered output cache usage
+    pub renders: ByteCacheStats,
+    /// Structured text cache usage
+    pub stext: ByteCacheStats,
+    /// Number of documents with open sessions (pinned)
+    pub open_documents: usize,
 }
 
 #[cfg(test)]
@@ -540,16 +870,17 @@ mod tests {
         let config = CacheConfig {
             max_parsers: 10,
             max_renderers: 10,
-            max_renders: 100,
-            max_stext: 200,
+            max_render_bytes: 1024,
+            max_stext_bytes: 2048,
         };
         let cache = DocumentCache::new(config);
         let stats = cache.stats().await;
 
         assert_eq!(stats.parsers_capacity, 10);
         assert_eq!(stats.renderers_capacity, 10);
-        assert_eq!(stats.renders_capacity, 100);
-        assert_eq!(stats.stext_capacity, 200);
+        assert_eq!(stats.renders.budget_bytes, 1024);
+        assert_eq!(stats.stext.budget_bytes, 2048);
+        assert_eq!(stats.renders.bytes, 0);
     }
 
     #[tokio::test]
@@ -561,6 +892,7 @@ mod tests {
             format: ImageFormat::Png,
             clip: None,
             background: None,
+            quality: None,
         };
         let key = RenderCacheKey::new("doc-123", &request);
 
@@ -570,6 +902,233 @@ mod tests {
         assert_eq!(key.rotation, 90);
     }
 
+    #[test]
+    fn test_prewarm_plan() {
+        let plan = PrewarmPlan::new(100, 3, Some(40));
+        assert_eq!(plan.render_items, vec![0, 1, 2, 40, 41]);
+        assert_eq!(plan.text_items, vec![39, 40, 41]);
+
+        // Resume inside the leading items, at the end, and out of range
+        assert_eq!(
+            PrewarmPlan::new(100, 3, Some(1)).render_items,
+            vec![0, 1, 2]
+        );
+        assert_eq!(
+            PrewarmPlan::new(5, 3, Some(4)).render_items,
+            vec![0, 1, 2, 4]
+        );
+        let plan = PrewarmPlan::new(2, 3, Some(9));
+        assert_eq!(plan.render_items, vec![0, 1]);
+        assert_eq!(plan.text_items, vec![0, 1]);
+    }
+
+    /// Renderer that counts calls so cache hits can be observed
+    #[derive(Default)]
+    struct CountingDocument {
+        renders: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl DocumentParser for CountingDocument {
+        async fn parse(&self) -> DocumentResult<ParsedDocument> {
+            Ok(parsed_document())
+        }
+        fn item_count(&self) -> usize {
+            10
+        }
+        async fn extract_toc(&self) -> DocumentResult<Vec<crate::document::TocEntry>> {
+            Ok(Vec::new())
+        }
+        async fn extract_text(&self, _item_index: usize) -> DocumentResult<String> {
+            Ok(String::new())
+        }
+        async fn get_structured_text(&self, item_index: usize) -> DocumentResult<StructuredText> {
+            Err(DocumentError::ItemNotFound(item_index))
+        }
+        async fn search(
+            &self,
+            _query: &str,
+            _options: SearchOptions,
+        ) -> DocumentResult<Vec<SearchResult>> {
+            Ok(Vec::new())
+        }
+        fn get_item_dimensions(&self, _item_index: usize) -> DocumentResult<(f32, f32)> {
+            Ok((100.0, 100.0))
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl DocumentRenderer for CountingDocument {
+        async fn render_item(&self, request: &RenderRequest) -> DocumentResult<RenderResult> {
+            self.renders
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(RenderResult {
+                data: vec![0],
+                format: request.format,
+                width: 1,
+                height: 1,
+                warnings: Vec::new(),
+            })
+        }
+        async fn render_thumbnail(
+            &self,
+            item_index: usize,
+            max_size: u32,
+        ) -> DocumentResult<RenderResult> {
+            self.render_item(&RenderRequest {
+                item_index,
+                scale: max_size as f32,
+                ..Default::default()
+            })
+            .await
+        }
+        async fn get_resource(&self, href: &str) -> DocumentResult<crate::document::Resource> {
+            Err(DocumentError::ResourceNotFound(href.to_string()))
+        }
+    }
+
+    fn parsed_document() -> ParsedDocument {
+        ParsedDocument {
+            id: "doc-1".to_string(),
+            format: crate::document::DocumentFormat::Pdf,
+            metadata: Default::default(),
+            toc: Vec::new(),
+            item_count: 10,
+            item_labels: None,
+            has_text_layer: true,
+            chapter_checksums: Vec::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_open_prewarm_close() {
+        let cache = DocumentCache::default();
+        let doc = Arc::new(CountingDocument::default());
+
+        assert_eq!(
+            cache
+                .open("doc-1", parsed_document(), doc.clone(), doc.clone())
+                .await,
+            1
+        );
+        assert_eq!(
+            cache
+                .open("doc-1", parsed_document(), doc.clone(), doc.clone())
+                .await,
+            2
+        );
+
+        let plan = PrewarmPlan::new(10, 2, None);
+        // Text extraction fails in this fixture, so only renders count
+        assert_eq!(cache.prewarm("doc-1", &plan).await, 2);
+        cache.prewarm("doc-1", &plan).await;
+        assert_eq!(doc.renders.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        assert_eq!(cache.close("doc-1").await, Some(1));
+        assert_eq!(cache.stats().await.renders.entries, 2);
+        let usage = cache.composition().await;
+        assert_eq!(usage.len(), 1);
+        assert_eq!(usage[0].render_bytes, 2);
+        assert!(usage[0].open);
+
+        assert_eq!(cache.close("doc-1").await, Some(0));
+        assert_eq!(cache.stats().await.renders.entries, 0);
+        assert!(cache.composition().await.is_empty());
+        assert_eq!(cache.stats().await.open_documents, 0);
+        assert_eq!(cache.close("doc-1").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_evict_orphans() {
+        let cache = DocumentCache::new(CacheConfig {
+            max_parsers: 1,
+            max_renderers: 1,
+            ..CacheConfig::default()
+        });
+        let doc = Arc::new(CountingDocument::default());
+
+        cache
+            .open("open", parsed_document(), doc.clone(), doc.clone())
+            .await;
+        for id in ["doc-1", "doc-2"] {
+            cache
+                .store_document_with_renderer(
+                    id.to_string(),
+                    parsed_document(),
+                    doc.clone(),
+                    doc.clone(),
+                )
+                .await;
+        }
+
+        // "open" and "doc-1" lost their parsers, but "open" is pinned
+        assert_eq!(cache.evict_orphans().await, 1);
+        assert!(!cache.contains("doc-1").await);
+        assert!(cache.contains("doc-2").await);
+        assert!(cache.contains("open").await);
+        assert_eq!(cache.evict_orphans().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_fallback_after_eviction() {
+        let cache = DocumentCache::new(CacheConfig {
+            max_parsers: 1,
+            max_renderers: 1,
+            ..CacheConfig::default()
+        });
+        let doc = Arc::new(CountingDocument::default());
+        let other = Arc::new(CountingDocument::default());
+
+        cache
+            .open("doc-1", parsed_document(), doc.clone(), doc.clone())
+            .await;
+        assert_eq!(cache.close("doc-1").await, Some(0));
+        cache
+            .store_document_with_renderer(
+                "doc-2".to_string(),
+                parsed_document(),
+                other.clone(),
+                other.clone(),
+            )
+            .await;
+
+        // The metadata outlives the evicted parser and renderer
+        assert!(cache.contains("doc-1").await);
+        assert!(cache.get_parser("doc-1").await.is_none());
+        let request = RenderRequest::default();
+        assert!(matches!(
+            cache.render("doc-1", &request).await,
+            Err(DocumentError::NotFound(_))
+        ));
+
+        cache
+            .render_or("doc-1", &request, doc.as_ref())
+            .await
+            .unwrap();
+        assert_eq!(doc.renders.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert_eq!(
+            cache
+                .extract_text_or("doc-1", 0, doc.as_ref())
+                .await
+                .unwrap(),
+            ""
+        );
+        // Parser errors are passed through rather than masked
+        assert!(matches!(
+            cache.get_structured_text_or("doc-1", 3, doc.as_ref()).await,
+            Err(DocumentError::ItemNotFound(3))
+        ));
+
+        // Documents the cache still holds are served from it
+        cache
+            .render_or("doc-2", &request, doc.as_ref())
+            .await
+            .unwrap();
+        assert_eq!(other.renders.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert_eq!(doc.renders.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
     #[tokio::test]
     async fn test_thumbnail_cache_key() {
         let key = RenderCacheKey::thumbnail("doc-456", 0, 256);
@@ -67,6 +67,10 @@ pub enum DocumentError {
     #[error("Operation timed out after {0} seconds")]
     Timeout(u64),
 
+    /// Document exceeds a layout ceiling (time, pages, or chapter size)
+    #[error("Layout limit exceeded: {0}")]
+    LayoutLimit(String),
+
     /// Image processing error
     #[error("Image error: {0}")]
     ImageError(String),
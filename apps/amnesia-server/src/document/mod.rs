@@ -50,14 +50,22 @@
 
 mod cache;
 mod error;
+mod text;
 mod traits;
 mod types;
 
-pub use cache::{CacheConfig, CacheStats, DocumentCache, RenderCacheKey as CacheRenderKey};
+pub use cache::{
+    CacheConfig, CacheStats, DocumentCache, DocumentCacheUsage, PrewarmPlan,
+    RenderCacheKey as CacheRenderKey, DEFAULT_PREWARM_ITEMS, DEFAULT_PREWARM_SCALE,
+};
 pub use error::{DocumentError, DocumentResult, Result};
+pub use text::{
+    block_text, find_urls, item_chunk, normalize_text, occurrence_blocks, WhitespaceMode,
+};
 pub use traits::{Document, DocumentParser, DocumentRenderer, RenderCacheKey};
 pub use types::{
-    BoundingBox, CharPosition, Creator, DocumentFormat, DocumentMetadata, ImageFormat,
-    ParsedDocument, Rect, RenderRequest, RenderResult, Resource, SearchOptions, SearchResult,
-    StructuredText, TextBlock, TextDirection, TextLine, TocEntry,
+    BoundingBox, ChapterMarkup, CharPosition, Creator, DocumentFonts, DocumentFormat,
+    DocumentMetadata, DocumentWarning, FontSubstitution, ImageFormat, ParsedDocument, Rect,
+    RenderRequest, RenderResult, RequestedFont, Resource, SearchOptions, SearchResult,
+    StructuredText, TextBlock, TextDirection, TextLine, TocEntry, WebLink,
 };
@@ -0,0 +1,286 @@
+//! Plain-text export
+//!
+//! Text extracted from a page or chapter keeps the layout's whitespace:
+//! PDF lines end where the printed line did, and XHTML leaves indentation and
+//! runs of blank lines behind. [`WhitespaceMode`] picks how much of that an
+//! export keeps.
+//!
+//! Search hits can also be widened to the text block (paragraph) around them
+//! with [`block_text`] and [`occurrence_blocks`]. [`find_urls`] picks out the
+//! web addresses printed in a page, for checking links in documents without
+//! link markup.
+
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use super::types::{StructuredText, TextBlock};
+
+/// Whitespace normalization for plain-text export
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum WhitespaceMode {
+    /// Text exactly as extracted
+    Preserve,
+    /// Trim lines, collapse runs of spaces, and keep at most one blank line
+    #[default]
+    Lines,
+    /// One line per paragraph, joining wrapped and hyphenated lines
+    Paragraphs,
+    /// Each item on a single line, so line N of the export is item N - 1
+    Collapse,
+}
+
+/// Normalize an item's text
+pub fn normalize_text(text: &str, mode: WhitespaceMode) -> String {
+    match mode {
+        WhitespaceMode::Preserve => text.to_string(),
+        WhitespaceMode::Lines => paragraphs(text)
+            .iter()
+            .map(|lines| lines.join("\n"))
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        WhitespaceMode::Paragraphs => paragraphs(text)
+            .iter()
+            .map(|lines| join_lines(lines))
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        WhitespaceMode::Collapse => collapse_spaces(text),
+    }
+}
+
+/// An item's text as a chunk of a whole-document export
+///
+/// Items are separated by a blank line, except in [`WhitespaceMode::Collapse`]
+/// where every item is one line, empty or not. Empty items are otherwise
+/// left out.
+pub fn item_chunk(text: &str, mode: WhitespaceMode) -> String {
+    let mut chunk = normalize_text(text, mode);
+    if mode == WhitespaceMode::Collapse {
+        chunk.push('\n');
+        return chunk;
+    }
+    if chunk.trim().is_empty() {
+        return String::new();
+    }
+    let kept = chunk.trim_end_matches(['\n', '\r']).len();
+    chunk.truncate(kept);
+    chunk.push_str("\n\n");
+    chunk
+}
+
+/// A block's text as one paragraph
+pub fn block_text(block: &TextBlock) -> String {
+    let lines: Vec<String> = block
+        .lines
+        .iter()
+        .map(|line| match &line.text {
+            Some(text) => text.clone(),
+            None => line.chars.iter().map(|c| c.char).collect(),
+        })
+        .collect();
+    normalize_text(&lines.join("\n"), WhitespaceMode::Paragraphs)
+}
+
+/// Index of the block holding each occurrence of `query`, in reading order
+///
+/// Matching ignores case and runs of whitespace, like MuPDF's search, so the
+/// n-th entry is the block of an item's n-th search hit. Occurrences that
+/// cross block boundaries are not found.
+pub fn occurrence_blocks(stext: &StructuredText, query: &str) -> Vec<usize> {
+    let query = collapse_spaces(query).to_lowercase();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    stext
+        .blocks
+        .iter()
+        .enumerate()
+        .flat_map(|(index, block)| {
+            let count = block_text(block)
+                .to_lowercase()
+                .matches(query.as_str())
+                .count();
+            vec![index; count]
+        })
+        .collect()
+}
+
+/// `http:` and `https:` URLs printed in text, in order
+///
+/// A URL runs to the next whitespace or quote. Trailing sentence
+/// punctuation and closing brackets without an opening one in the URL are
+/// left out, so "(see https://example.com/a_(b))." yields
+/// `https://example.com/a_(b)`.
+pub fn find_urls(text: &str) -> Vec<&str> {
+    // ASCII lowercasing keeps byte offsets
+    let lower = text.to_ascii_lowercase();
+    let mut urls = Vec::new();
+    let mut pos = 0;
+    while let Some(offset) = lower[pos..].find("http") {
+        let start = pos + offset;
+        let scheme_len = match &lower[start + 4..] {
+            rest if rest.starts_with("://") => 7,
+            rest if rest.starts_with("s://") => 8,
+            _ => {
+                pos = start + 4;
+                continue;
+            }
+        };
+        let end = text[start..]
+            .find(|c: char| c.is_whitespace() || matches!(c, '"' | '<' | '>' | '`'))
+            .map_or(text.len(), |len| start + len);
+        let url = trim_url(&text[start..end]);
+        if url.len() > scheme_len {
+            urls.push(url);
+        }
+        pos = end;
+    }
+    urls
+}
+
+/// A URL without the punctuation of the sentence around it
+fn trim_url(mut url: &str) -> &str {
+    loop {
+        let trimmed = url.trim_end_matches(['.', ',', ';', ':', '!', '?', '\'', '*']);
+        let unbalanced = |open: char, close: char| {
+            trimmed.ends_with(close)
+                && trimmed.matches(open).count() < trimmed.matches(close).count()
+        };
+        let trimmed = if unbalanced('(', ')') || unbalanced('[', ']') {
+            &trimmed[..trimmed.len() - 1]
+        } else {
+            trimmed
+        };
+        if trimmed.len() == url.len() {
+            return url;
+        }
+        url = trimmed;
+    }
+}
+
+/// Non-empty lines with collapsed spaces, grouped at blank lines
+fn paragraphs(text: &str) -> Vec<Vec<String>> {
+    let mut paragraphs = Vec::new();
+    let mut current = Vec::new();
+    for line in text.lines().map(collapse_spaces) {
+        if !line.is_empty() {
+            current.push(line);
+        } else if !current.is_empty() {
+            paragraphs.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        paragraphs.push(current);
+    }
+    paragraphs
+}
+
+/// Join a paragraph's lines, undoing hyphenation at line breaks
+fn join_lines(lines: &[String]) -> String {
+    let mut joined = String::new();
+    for line in lines {
+        let continues_word = joined.ends_with('-')
+            && !joined.ends_with("--")
+            && line.chars().next().is_some_and(char::is_lowercase);
+        if continues_word {
+            joined.pop();
+        } else if !joined.is_empty() {
+            joined.push(' ');
+        }
+        joined.push_str(line);
+    }
+    joined
+}
+
+/// Words separated by single spaces
+fn collapse_spaces(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = "  Chapter 1\r\n\n\n\nIt was a  bright cold day in\nApril, and the clocks were strik-\ning thirteen.\n\t\nWinston Smith \u{a0}slipped\n";
+
+    #[test]
+    fn test_find_urls() {
+        let text = "See https://example.com/a_(b). Or (http://x.org/path),\n\
+                    HTTPS://Y.ORG/Q?a=1 and https:// alone; httpd is a server.";
+        assert_eq!(
+            find_urls(text),
+            vec![
+                "https://example.com/a_(b)",
+                "http://x.org/path",
+                "HTTPS://Y.ORG/Q?a=1"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_normalize_text() {
+        assert_eq!(normalize_text(PAGE, WhitespaceMode::Preserve), PAGE);
+        assert_eq!(
+            normalize_text(PAGE, WhitespaceMode::Lines),
+            "Chapter 1\n\nIt was a bright cold day in\nApril, and the clocks were strik-\n\
+             ing thirteen.\n\nWinston Smith slipped"
+        );
+        assert_eq!(
+            normalize_text(PAGE, WhitespaceMode::Paragraphs),
+            "Chapter 1\n\nIt was a bright cold day in April, and the clocks were striking \
+             thirteen.\n\nWinston Smith slipped"
+        );
+        assert_eq!(
+            normalize_text(PAGE, WhitespaceMode::Collapse),
+            "Chapter 1 It was a bright cold day in April, and the clocks were strik- ing \
+             thirteen. Winston Smith slipped"
+        );
+    }
+
+    #[test]
+    fn test_occurrence_blocks() {
+        use crate::document::{BoundingBox, TextLine};
+
+        let block = |lines: &[&str]| TextBlock {
+            bbox: BoundingBox::new(0.0, 0.0, 100.0, 20.0),
+            lines: lines
+                .iter()
+                .map(|text| TextLine {
+                    bbox: BoundingBox::new(0.0, 0.0, 100.0, 10.0),
+                    dir: None,
+                    chars: Vec::new(),
+                    text: Some(text.to_string()),
+                })
+                .collect(),
+        };
+        let stext = StructuredText {
+            item_index: 0,
+            width: 100.0,
+            height: 100.0,
+            blocks: vec![
+                block(&["The whale, the whale!"]),
+                block(&["Call me Ishmael."]),
+                block(&["A white", "Whale sur-", "faced."]),
+            ],
+        };
+
+        assert_eq!(block_text(&stext.blocks[2]), "A white Whale surfaced.");
+        assert_eq!(occurrence_blocks(&stext, "whale"), vec![0, 0, 2]);
+        assert_eq!(occurrence_blocks(&stext, "white  whale"), vec![2]);
+        assert!(occurrence_blocks(&stext, "Ahab").is_empty());
+        assert!(occurrence_blocks(&stext, " ").is_empty());
+    }
+
+    #[test]
+    fn test_item_chunk() {
+        assert_eq!(
+            item_chunk("One\ntwo\n\n", WhitespaceMode::Lines),
+            "One\ntwo\n\n"
+        );
+        assert_eq!(item_chunk(" \n\n", WhitespaceMode::Lines), "");
+        assert_eq!(item_chunk("x\n", WhitespaceMode::Preserve), "x\n\n");
+        // Collapsed exports keep one line per item, even for empty items
+        assert_eq!(item_chunk(" \n", WhitespaceMode::Collapse), "\n");
+        assert_eq!(item_chunk("a\nb", WhitespaceMode::Collapse), "a b\n");
+    }
+}
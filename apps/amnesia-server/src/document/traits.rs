@@ -3,11 +3,14 @@
 //! Format-agnostic interfaces for document parsing and rendering.
 
 use async_trait::async_trait;
+use epub_core::{BookIndex, Glossary, LinkGraph};
+use search_core::{IndexOptions, SearchIndexData};
 
-use super::error::Result;
+use super::error::{DocumentError, Result};
+use super::text::find_urls;
 use super::types::{
-    ParsedDocument, RenderRequest, RenderResult, Resource, SearchOptions, SearchResult,
-    StructuredText, TocEntry,
+    ChapterMarkup, DocumentFonts, ParsedDocument, RenderRequest, RenderResult, Resource,
+    SearchOptions, SearchResult, StructuredText, TocEntry, WebLink,
 };
 
 /// Format-agnostic document parser
@@ -36,6 +39,76 @@ pub trait DocumentParser: Send + Sync {
 
     /// Get item dimensions (page size)
     fn get_item_dimensions(&self, item_index: usize) -> Result<(f32, f32)>;
+
+    /// Build a search index the WASM reader can import
+    ///
+    /// Only reflowable formats rendered client-side (EPUB) support this.
+    async fn build_search_index(&self, _options: &IndexOptions) -> Result<SearchIndexData> {
+        Err(DocumentError::UnsupportedFormat(
+            "Search index export is only available for EPUB".into(),
+        ))
+    }
+
+    /// Links between the document's chapters
+    ///
+    /// Only reflowable formats with content documents (EPUB) support this.
+    async fn link_graph(&self) -> Result<LinkGraph> {
+        Err(DocumentError::UnsupportedFormat(
+            "Link graphs are only available for EPUB".into(),
+        ))
+    }
+
+    /// Links to web pages, in reading order
+    ///
+    /// By default these are the `http:` and `https:` URLs printed in each
+    /// item's text; formats with link markup (EPUB) read the links instead.
+    async fn external_links(&self) -> Result<Vec<WebLink>> {
+        let mut links = Vec::new();
+        for item_index in 0..self.item_count() {
+            let text = self.extract_text(item_index).await?;
+            links.extend(find_urls(&text).into_iter().map(|url| WebLink {
+                item_index,
+                href: None,
+                url: url.to_string(),
+                text: url.to_string(),
+            }));
+        }
+        Ok(links)
+    }
+
+    /// The back-of-book index, if the document has one
+    ///
+    /// Only formats with marked-up index documents (EPUB) support this.
+    async fn book_index(&self) -> Result<BookIndex> {
+        Err(DocumentError::UnsupportedFormat(
+            "Back-of-book indexes are only available for EPUB".into(),
+        ))
+    }
+
+    /// Glossary terms and their definitions, if the document has a glossary
+    ///
+    /// Only formats with marked-up glossaries (EPUB) support this.
+    async fn glossary(&self) -> Result<Glossary> {
+        Err(DocumentError::UnsupportedFormat(
+            "Glossaries are only available for EPUB".into(),
+        ))
+    }
+
+    /// The markup of the spine item at `spine_index`
+    ///
+    /// Only formats with content documents (EPUB) support this.
+    async fn chapter_markup(&self, _spine_index: usize) -> Result<ChapterMarkup> {
+        Err(DocumentError::UnsupportedFormat(
+            "Chapter markup is only available for EPUB".into(),
+        ))
+    }
+
+    /// Fonts the document requests and the substitutions MuPDF made
+    async fn fonts(&self) -> Result<DocumentFonts> {
+        Err(DocumentError::UnsupportedFormat(
+            "Font listing is not available for this format".into(),
+        ))
+    }
 }
 
 /// Format-agnostic document renderer
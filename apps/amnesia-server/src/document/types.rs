@@ -3,6 +3,7 @@
 //! Format-agnostic types for unified document handling.
 
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 /// Document format
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -83,6 +84,62 @@ pub struct ParsedDocument {
     pub item_labels: Option<Vec<String>>,
     /// Whether document has extractable text
     pub has_text_layer: bool,
+    /// Content checksum of each EPUB spine item, for finding the chapters
+    /// that changed when the book file is updated
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chapter_checksums: Vec<epub_core::ChapterChecksum>,
+    /// Warnings MuPDF reported while opening and parsing the document
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub diagnostics: Vec<DocumentWarning>,
+}
+
+/// A recoverable problem MuPDF reported, such as a broken embedded font
+/// or a glyph no font has, which can make text render as boxes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentWarning {
+    pub message: String,
+    /// Times it was reported
+    pub count: usize,
+}
+
+/// A font a document asks for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestedFont {
+    /// Font name without any subset prefix (`ABCDEF+`), or CSS font family
+    pub name: String,
+    /// Whether the document carries the font itself
+    pub embedded: bool,
+    /// PDF font type (`TrueType`, `Type0`, ...); `None` for EPUB
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+}
+
+/// A font MuPDF had to find outside the document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FontSubstitution {
+    /// Font the document named; `None` when MuPDF only needed some font
+    /// for a script
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requested: Option<String>,
+    /// Script MuPDF needed glyphs for (`han`, `arabic`, ...)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub script: Option<String>,
+    /// Configured font file used instead; `None` when MuPDF fell back to
+    /// its built-in fonts
+    pub substitute: Option<String>,
+}
+
+/// Fonts a document requests and what was substituted for them
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentFonts {
+    pub fonts: Vec<RequestedFont>,
+    /// Substitutions made by the operations run so far; rendering more of
+    /// the document can add to them
+    pub substitutions: Vec<FontSubstitution>,
 }
 
 /// Document metadata
@@ -124,7 +181,7 @@ pub struct Creator {
 }
 
 /// Table of contents entry
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct TocEntry {
     /// Entry label/title
@@ -142,7 +199,7 @@ pub struct TocEntry {
 }
 
 /// Structured text from a document page/chapter
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct StructuredText {
     /// Item index (page/chapter)
@@ -156,20 +213,22 @@ pub struct StructuredText {
 }
 
 /// Text block (paragraph, heading, etc.)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct TextBlock {
     /// Bounding box
+    #[schema(value_type = Rect)]
     pub bbox: BoundingBox,
     /// Text lines within block
     pub lines: Vec<TextLine>,
 }
 
 /// Text line
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct TextLine {
     /// Bounding box
+    #[schema(value_type = Rect)]
     pub bbox: BoundingBox,
     /// Writing direction
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -182,10 +241,11 @@ pub struct TextLine {
 }
 
 /// Character position with bounding box
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct CharPosition {
     /// Character
+    #[schema(value_type = String)]
     pub char: char,
     /// X position
     pub x: f32,
@@ -210,7 +270,7 @@ pub struct CharPosition {
 }
 
 /// Text direction
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum TextDirection {
     Ltr,
@@ -226,7 +286,7 @@ impl Default for TextDirection {
 }
 
 /// Rectangle (bounding box)
-#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema)]
 pub struct Rect {
     pub x: f32,
     pub y: f32,
@@ -324,6 +384,8 @@ pub struct RenderRequest {
     pub clip: Option<Rect>,
     /// Background color (RGBA)
     pub background: Option<[u8; 4]>,
+    /// JPEG quality (1-100); the encoder default when `None`
+    pub quality: Option<u8>,
 }
 
 impl Default for RenderRequest {
@@ -335,6 +397,7 @@ impl Default for RenderRequest {
             rotation: 0,
             clip: None,
             background: None,
+            quality: None,
         }
     }
 }
@@ -349,9 +412,16 @@ pub struct RenderResult {
     /// Rendered dimensions
     pub width: u32,
     pub height: u32,
+    /// Warnings MuPDF reported while rendering; empty when served from cache
+    pub warnings: Vec<DocumentWarning>,
 }
 
 impl RenderResult {
+    /// Attach the warnings captured while rendering
+    pub fn with_warnings(self, warnings: Vec<DocumentWarning>) -> Self {
+        Self { warnings, ..self }
+    }
+
     pub fn content_type(&self) -> &'static str {
         self.format.content_type()
     }
@@ -368,6 +438,32 @@ pub struct Resource {
     pub content: Vec<u8>,
 }
 
+/// A chapter's markup as stored in the document, for rendering outside
+/// the reader
+#[derive(Debug, Clone)]
+pub struct ChapterMarkup {
+    /// Archive path of the chapter, which its URLs are relative to
+    pub path: String,
+    pub html: String,
+    /// Label of the first ToC entry pointing into the chapter
+    pub label: Option<String>,
+}
+
+/// A link from a document to a web page
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WebLink {
+    /// Where the link is: 0-based page for PDF, spine index for EPUB
+    pub item_index: usize,
+    /// Chapter href relative to the OPF directory (EPUB)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub href: Option<String>,
+    /// `http:` or `https:` URL
+    pub url: String,
+    /// Link text; the URL itself for URLs found in page text
+    pub text: String,
+}
+
 // ============================================================================
 // Type aliases for backward compatibility with existing code
 // ============================================================================
@@ -1,13 +1,13 @@
 //! Error types for the Los Libros server
 
-use axum::{
-    http::StatusCode,
-    response::{IntoResponse, Response},
-    Json,
-};
-use serde::Serialize;
 use thiserror::Error;
 
+#[cfg(feature = "server")]
+mod api;
+
+#[cfg(feature = "server")]
+pub use api::{problem_instance, ApiError, ProblemDetails, PROBLEM_JSON, PROBLEM_TYPE_PREFIX};
+
 /// Application-wide result type
 pub type Result<T> = std::result::Result<T, AppError>;
 
@@ -60,102 +60,3 @@ pub enum StorageError {
     #[error("S3 SDK error: {0}")]
     SdkError(String),
 }
-
-/// Error response body
-#[derive(Serialize)]
-struct ErrorResponse {
-    error: String,
-    message: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    details: Option<String>,
-}
-
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        let (status, error_type, message) = match &self {
-            AppError::NotFound(msg) => (StatusCode::NOT_FOUND, "not_found", msg.clone()),
-            AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, "bad_request", msg.clone()),
-            AppError::Internal(msg) => {
-                tracing::error!("Internal error: {}", msg);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "internal_error",
-                    "An internal error occurred".to_string(),
-                )
-            }
-            AppError::Storage(e) => {
-                tracing::error!("Storage error: {}", e);
-                match e {
-                    StorageError::ObjectNotFound(key) => {
-                        (StatusCode::NOT_FOUND, "not_found", format!("Object not found: {}", key))
-                    }
-                    StorageError::BucketNotFound(bucket) => {
-                        (StatusCode::NOT_FOUND, "not_found", format!("Bucket not found: {}", bucket))
-                    }
-                    StorageError::AccessDenied(_) => (
-                        StatusCode::FORBIDDEN,
-                        "access_denied",
-                        "Access denied".to_string(),
-                    ),
-                    _ => (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        "storage_error",
-                        "Storage error".to_string(),
-                    ),
-                }
-            }
-            AppError::Database(e) => {
-                tracing::error!("Database error: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "database_error",
-                    "Database error".to_string(),
-                )
-            }
-            AppError::XmlParse(e) => {
-                tracing::error!("XML parse error: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "parse_error",
-                    "Failed to parse XML".to_string(),
-                )
-            }
-            AppError::XmlDeserialize(e) => {
-                tracing::error!("XML deserialize error: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "parse_error",
-                    "Failed to deserialize XML".to_string(),
-                )
-            }
-            AppError::Utf8(e) => {
-                tracing::error!("UTF-8 error: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "encoding_error",
-                    "Invalid UTF-8 encoding".to_string(),
-                )
-            }
-            AppError::Io(e) => {
-                tracing::error!("IO error: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "io_error",
-                    "IO error".to_string(),
-                )
-            }
-        };
-
-        let body = Json(ErrorResponse {
-            error: error_type.to_string(),
-            message,
-            details: if cfg!(debug_assertions) {
-                Some(self.to_string())
-            } else {
-                None
-            },
-        });
-
-        (status, body).into_response()
-    }
-}
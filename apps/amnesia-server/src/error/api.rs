@@ -0,0 +1,382 @@
+//! HTTP error responses (RFC 7807 problem details)
+//!
+//! [`ApiError`] is the error type returned by route handlers. It renders as an
+//! `application/problem+json` body:
+//!
+//! ```json
+//! {
+//!   "type": "urn:amnesia:problem:not-found",
+//!   "title": "Not found",
+//!   "status": 404,
+//!   "detail": "Document 'abc' not found",
+//!   "instance": "/api/v1/documents/abc",
+//!   "retryable": false,
+//!   "requestId": "9b2f6c1e-4d7a-4f1b-8a53-0c2d9e7b1f44"
+//! }
+//! ```
+//!
+//! `instance` and `requestId` are filled in by the [`problem_instance`]
+//! middleware, since the handler that builds the error does not usually have
+//! the request URI or correlation ID.
+
+use std::borrow::Cow;
+
+use axum::{
+    extract::Request,
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::{AppError, StorageError};
+use crate::routes::request_id::RequestId;
+
+/// Prefix for problem `type` URIs
+pub const PROBLEM_TYPE_PREFIX: &str = "urn:amnesia:problem:";
+
+/// Media type for problem details responses
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// Error returned by route handlers, rendered as problem+json
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    status: StatusCode,
+    /// Problem type slug, appended to [`PROBLEM_TYPE_PREFIX`]
+    kind: Cow<'static, str>,
+    /// Occurrence-specific explanation
+    detail: String,
+    /// Underlying cause (e.g. the parser or storage error message)
+    reason: Option<String>,
+    /// Request path the problem occurred on
+    instance: Option<String>,
+    /// Correlation ID of the failing request
+    request_id: Option<String>,
+    retryable: bool,
+}
+
+impl ApiError {
+    /// Create an error with the default problem type for `status`
+    pub fn new(status: StatusCode, detail: impl Into<String>) -> Self {
+        Self {
+            status,
+            kind: Cow::Borrowed(default_kind(status)),
+            detail: detail.into(),
+            reason: None,
+            instance: None,
+            request_id: None,
+            retryable: default_retryable(status),
+        }
+    }
+
+    /// 400 Bad Request
+    pub fn bad_request(detail: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, detail)
+    }
+
+    /// 404 Not Found
+    pub fn not_found(detail: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, detail)
+    }
+
+    /// 409 Conflict
+    pub fn conflict(detail: impl Into<String>) -> Self {
+        Self::new(StatusCode::CONFLICT, detail)
+    }
+
+    /// 500 Internal Server Error
+    pub fn internal(detail: impl Into<String>) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, detail)
+    }
+
+    /// 503 Service Unavailable (retryable)
+    pub fn unavailable(detail: impl Into<String>) -> Self {
+        Self::new(StatusCode::SERVICE_UNAVAILABLE, detail)
+    }
+
+    /// Use a specific problem type slug (e.g. `"session-expired"`)
+    pub fn with_type(mut self, kind: impl Into<Cow<'static, str>>) -> Self {
+        self.kind = kind.into();
+        self
+    }
+
+    /// Attach the underlying cause
+    pub fn with_reason(mut self, reason: impl ToString) -> Self {
+        self.reason = Some(reason.to_string());
+        self
+    }
+
+    /// Set the request path the problem occurred on
+    pub fn with_instance(mut self, instance: impl Into<String>) -> Self {
+        self.instance = Some(instance.into());
+        self
+    }
+
+    /// Set the correlation ID of the failing request
+    pub fn with_request_id(mut self, request_id: impl Into<String>) -> Self {
+        self.request_id = Some(request_id.into());
+        self
+    }
+
+    /// Override whether clients may retry the request unchanged
+    pub fn retryable(mut self, retryable: bool) -> Self {
+        self.retryable = retryable;
+        self
+    }
+
+    /// HTTP status code
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Full problem type URI
+    pub fn problem_type(&self) -> String {
+        format!("{}{}", PROBLEM_TYPE_PREFIX, self.kind)
+    }
+
+    /// Occurrence-specific explanation
+    pub fn detail(&self) -> &str {
+        &self.detail
+    }
+
+    /// Whether clients may retry the request unchanged
+    pub fn is_retryable(&self) -> bool {
+        self.retryable
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.reason {
+            Some(reason) => write!(f, "{}: {}", self.detail, reason),
+            None => f.write_str(&self.detail),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// Problem details body (RFC 7807 §3.1 plus `retryable` and `reason` extensions)
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ProblemDetails {
+    /// Problem type URI
+    #[serde(rename = "type")]
+    #[schema(example = "urn:amnesia:problem:not-found")]
+    pub problem_type: String,
+    /// Short summary of the problem type
+    pub title: String,
+    /// HTTP status code
+    pub status: u16,
+    /// Explanation specific to this occurrence
+    pub detail: String,
+    /// Request path the problem occurred on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    /// Whether the request may succeed if retried unchanged
+    pub retryable: bool,
+    /// Underlying cause, when available
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Correlation ID, matching the `X-Request-Id` response header
+    #[serde(rename = "requestId", skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+impl ApiError {
+    /// Render the problem+json response without logging
+    fn render(self) -> Response {
+        let body = ProblemDetails {
+            problem_type: self.problem_type(),
+            title: title_for(&self.kind),
+            status: self.status.as_u16(),
+            detail: self.detail.clone(),
+            instance: self.instance.clone(),
+            retryable: self.retryable,
+            reason: self.reason.clone(),
+            request_id: self.request_id.clone(),
+        };
+
+        let mut response = (self.status, Json(body)).into_response();
+        response
+            .headers_mut()
+            .insert(header::CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON));
+        // Keep the error around so `problem_instance` can fill in the path
+        response.extensions_mut().insert(self);
+        response
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        if self.status.is_server_error() {
+            tracing::error!("{} ({}): {}", self.status, self.kind, self);
+        }
+        self.render()
+    }
+}
+
+/// Middleware that sets `instance` (the request path) and `requestId` on problem responses
+pub async fn problem_instance(request: Request, next: Next) -> Response {
+    let path = request.uri().path().to_string();
+    let request_id = request.extensions().get::<RequestId>().cloned();
+    let (mut parts, body) = next.run(request).await.into_parts();
+
+    match parts.extensions.remove::<ApiError>() {
+        Some(mut error) if error.instance.is_none() => {
+            if let (None, Some(id)) = (&error.request_id, request_id) {
+                error.request_id = Some(id.0);
+            }
+            let mut rebuilt = error.with_instance(path).render();
+            // Keep headers added on the way out (e.g. deprecation notices)
+            for (name, value) in parts.headers.iter() {
+                if name != header::CONTENT_TYPE && name != header::CONTENT_LENGTH {
+                    rebuilt.headers_mut().insert(name.clone(), value.clone());
+                }
+            }
+            rebuilt
+        }
+        Some(error) => {
+            parts.extensions.insert(error);
+            Response::from_parts(parts, body)
+        }
+        None => Response::from_parts(parts, body),
+    }
+}
+
+impl From<AppError> for ApiError {
+    fn from(err: AppError) -> Self {
+        // Underlying causes are only exposed in debug builds
+        let reason = cfg!(debug_assertions).then(|| err.to_string());
+
+        let error = match &err {
+            AppError::NotFound(msg) => Self::not_found(msg.clone()),
+            AppError::BadRequest(msg) => Self::bad_request(msg.clone()),
+            AppError::Internal(_) => Self::internal("An internal error occurred"),
+            AppError::Storage(e) => match e {
+                StorageError::ObjectNotFound(key) => {
+                    Self::not_found(format!("Object not found: {}", key))
+                }
+                StorageError::BucketNotFound(bucket) => {
+                    Self::not_found(format!("Bucket not found: {}", bucket))
+                }
+                StorageError::AccessDenied(_) => {
+                    Self::new(StatusCode::FORBIDDEN, "Access denied").with_type("access-denied")
+                }
+                StorageError::ConnectionFailed(_) => {
+                    Self::unavailable("Storage unavailable").with_type("storage-error")
+                }
+                _ => Self::internal("Storage error").with_type("storage-error"),
+            },
+            AppError::Database(_) => Self::internal("Database error").with_type("database-error"),
+            AppError::XmlParse(_) => Self::internal("Failed to parse XML").with_type("parse-error"),
+            AppError::XmlDeserialize(_) => {
+                Self::internal("Failed to deserialize XML").with_type("parse-error")
+            }
+            AppError::Utf8(_) => {
+                Self::internal("Invalid UTF-8 encoding").with_type("encoding-error")
+            }
+            AppError::Io(_) => Self::internal("IO error").with_type("io-error"),
+        };
+
+        match reason {
+            Some(reason) => error.with_reason(reason),
+            None => error,
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        ApiError::from(self).into_response()
+    }
+}
+
+/// Default problem type slug for a status code
+fn default_kind(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::BAD_REQUEST => "bad-request",
+        StatusCode::UNAUTHORIZED => "unauthorized",
+        StatusCode::FORBIDDEN => "forbidden",
+        StatusCode::NOT_FOUND => "not-found",
+        StatusCode::CONFLICT => "conflict",
+        StatusCode::GONE => "gone",
+        StatusCode::PAYLOAD_TOO_LARGE => "payload-too-large",
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => "unsupported-media-type",
+        StatusCode::UNPROCESSABLE_ENTITY => "unprocessable-entity",
+        StatusCode::TOO_MANY_REQUESTS => "too-many-requests",
+        StatusCode::NOT_IMPLEMENTED => "not-implemented",
+        StatusCode::SERVICE_UNAVAILABLE => "service-unavailable",
+        StatusCode::GATEWAY_TIMEOUT => "timeout",
+        s if s.is_client_error() => "client-error",
+        _ => "internal-error",
+    }
+}
+
+/// Statuses where retrying the same request can succeed
+fn default_retryable(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::REQUEST_TIMEOUT
+            | StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// Human-readable title for a problem type slug ("session-expired" -> "Session expired")
+fn title_for(kind: &str) -> String {
+    let mut title = kind.replace('-', " ");
+    if let Some(first) = title.get_mut(0..1) {
+        first.make_ascii_uppercase();
+    }
+    title
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_type_and_title() {
+        let err = ApiError::not_found("Document 'abc' not found");
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+        assert_eq!(err.problem_type(), "urn:amnesia:problem:not-found");
+        assert_eq!(title_for("not-found"), "Not found");
+        assert!(!err.is_retryable());
+    }
+
+    #[test]
+    fn test_retryable_defaults() {
+        assert!(ApiError::unavailable("busy").is_retryable());
+        assert!(!ApiError::internal("boom").is_retryable());
+        assert!(ApiError::internal("boom").retryable(true).is_retryable());
+    }
+
+    #[test]
+    fn test_custom_type() {
+        let err = ApiError::new(StatusCode::GONE, "Session expired").with_type("session-expired");
+        assert_eq!(err.problem_type(), "urn:amnesia:problem:session-expired");
+        assert_eq!(title_for("session-expired"), "Session expired");
+    }
+
+    #[test]
+    fn test_response_is_problem_json() {
+        let response = ApiError::bad_request("Invalid page").into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            PROBLEM_JSON
+        );
+        assert!(response.extensions().get::<ApiError>().is_some());
+    }
+
+    #[test]
+    fn test_from_app_error() {
+        let err = ApiError::from(AppError::NotFound("Book 'x'".into()));
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+        assert_eq!(err.detail(), "Book 'x'");
+    }
+}
@@ -0,0 +1,251 @@
+//! Feeds of newly added books
+//!
+//! `/feeds/new-books.atom` and `/feeds/new-books.json` (JSON Feed 1.1) list
+//! the books most recently added to the library, newest first, so any feed
+//! reader can follow a library's additions. Books are dated by when they
+//! were added (Calibre's `calibre:timestamp`, or else when their first book
+//! file was stored), and described with the OPDS [`EntryTemplate`], so
+//! readers show the cover and description. Entry ids are the books' stable
+//! ids and dates come from S3, so a rescan neither repeats nor re-dates
+//! entries.
+//!
+//! Who may read the feeds is set by [`FeedsConfig::visibility`].
+
+use std::cmp::Reverse;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::config::{FeedVisibility, FeedsConfig};
+use crate::library::LibraryBook;
+use crate::opds::{mime, rel, EntryTemplate, OPDSEntry, OPDSFeed, OPDSLink};
+
+/// Atom id of the new-books feed
+pub const NEW_BOOKS_FEED_ID: &str = "urn:los-libros:feeds:new-books";
+
+/// JSON Feed version the feeds follow
+pub const JSON_FEED_VERSION: &str = "https://jsonfeed.org/version/1.1";
+
+/// Content type of a JSON Feed
+pub const JSON_FEED_MIME: &str = "application/feed+json";
+
+/// Whether a request presenting `token` may read the feeds
+pub fn is_visible(config: &FeedsConfig, token: Option<&str>) -> bool {
+    match config.visibility {
+        FeedVisibility::Off => false,
+        FeedVisibility::Public => true,
+        FeedVisibility::Token => config
+            .token
+            .as_deref()
+            .is_some_and(|expected| token == Some(expected)),
+    }
+}
+
+/// The `limit` most recently added books, newest first
+pub fn newest(mut books: Vec<LibraryBook>, limit: usize) -> Vec<LibraryBook> {
+    books.sort_by_key(|book| Reverse(book.added_at));
+    books.truncate(limit);
+    books
+}
+
+/// Atom feed of `books`, which are newest first
+pub fn atom_feed(
+    books: &[LibraryBook],
+    title: &str,
+    self_href: &str,
+    base_url: &str,
+    template: &EntryTemplate,
+) -> OPDSFeed {
+    let mut feed = OPDSFeed::acquisition(title, self_href);
+    feed.id = NEW_BOOKS_FEED_ID.to_string();
+    feed.links[0].link_type = Some(mime::ATOM_XML.to_string());
+    if let Some(newest) = books.first() {
+        feed.updated = newest.added_at;
+    }
+
+    for book in books {
+        let mut entry = OPDSEntry::from_book(book, base_url, template);
+        entry.id = entry_id(book);
+        entry.updated = book.added_at;
+        entry.published = Some(book.added_at.to_rfc3339());
+        // Feed readers open an entry's alternate link
+        if let Some(format) = book.primary_format() {
+            let mut link = OPDSLink::acquisition(format, base_url);
+            link.rel = Some(rel::ALTERNATE.to_string());
+            entry.links.insert(0, link);
+        }
+        feed.entries.push(entry);
+    }
+    feed
+}
+
+/// A JSON Feed (<https://jsonfeed.org/version/1.1>)
+#[derive(Debug, Clone, Serialize)]
+pub struct JsonFeed {
+    pub version: &'static str,
+    pub title: String,
+    pub feed_url: String,
+    pub items: Vec<JsonFeedItem>,
+}
+
+/// A JSON Feed item; it has `content_html`, or `content_text` when the
+/// entry template renders nothing
+#[derive(Debug, Clone, Serialize)]
+pub struct JsonFeedItem {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_html: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    pub date_published: DateTime<Utc>,
+    pub date_modified: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub authors: Vec<JsonFeedAuthor>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JsonFeedAuthor {
+    pub name: String,
+}
+
+/// JSON Feed of `books`, which are newest first
+pub fn json_feed(
+    books: &[LibraryBook],
+    title: &str,
+    feed_url: &str,
+    base_url: &str,
+    template: &EntryTemplate,
+) -> JsonFeed {
+    let items = books
+        .iter()
+        .map(|book| {
+            let content_html = template.render(book, base_url);
+            JsonFeedItem {
+                id: entry_id(book),
+                url: book
+                    .primary_format()
+                    .map(|format| OPDSLink::acquisition(format, base_url).href),
+                title: book.title.clone(),
+                content_text: content_html.is_none().then(|| book.title.clone()),
+                content_html,
+                summary: template.summary(book),
+                image: book
+                    .cover_key
+                    .as_ref()
+                    .map(|key| format!("{}/files/{}", base_url, key)),
+                date_published: book.added_at,
+                date_modified: book.updated_at.max(book.added_at),
+                authors: book
+                    .authors
+                    .iter()
+                    .map(|name| JsonFeedAuthor { name: name.clone() })
+                    .collect(),
+                tags: book.tags.clone(),
+                language: book.language.clone(),
+            }
+        })
+        .collect();
+
+    JsonFeed {
+        version: JSON_FEED_VERSION,
+        title: title.to_string(),
+        feed_url: feed_url.to_string(),
+        items,
+    }
+}
+
+/// Entry id that survives rescans, unlike [`LibraryBook::id`]
+fn entry_id(book: &LibraryBook) -> String {
+    format!("urn:los-libros:book:{}", book.stable_id())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    use crate::library::{BookFormat, FormatType};
+
+    fn book(title: &str, day: u32) -> LibraryBook {
+        let mut book = LibraryBook::new(title.to_string(), format!("Author/{}", title));
+        book.authors = vec!["Author".to_string()];
+        book.added_at = Utc.with_ymd_and_hms(2024, 3, day, 12, 0, 0).unwrap();
+        book.formats = vec![BookFormat {
+            format: FormatType::Epub,
+            s3_key: format!("Author/{}/{}.epub", title, title),
+            size: 1,
+        }];
+        book
+    }
+
+    #[test]
+    fn test_newest_books_feeds() {
+        let books = newest(vec![book("Old", 1), book("New", 3), book("Mid", 2)], 2);
+        let titles: Vec<_> = books.iter().map(|b| b.title.as_str()).collect();
+        assert_eq!(titles, ["New", "Mid"]);
+
+        let template = EntryTemplate::default();
+        let feed = atom_feed(
+            &books,
+            "New books",
+            "http://host/feeds/new-books.atom",
+            "http://host",
+            &template,
+        );
+        assert_eq!(feed.id, NEW_BOOKS_FEED_ID);
+        assert_eq!(feed.updated, books[0].added_at);
+        let entry = &feed.entries[0];
+        assert_eq!(entry.id, "urn:los-libros:book:Author/New");
+        assert_eq!(
+            entry.published.as_deref(),
+            Some("2024-03-03T12:00:00+00:00")
+        );
+        assert_eq!(entry.links[0].rel.as_deref(), Some(rel::ALTERNATE));
+        assert_eq!(entry.links[0].href, "http://host/files/Author/New/New.epub");
+
+        let feed = json_feed(
+            &books,
+            "New books",
+            "http://host/feeds/new-books.json",
+            "http://host",
+            &template,
+        );
+        let json = serde_json::to_value(&feed).unwrap();
+        assert_eq!(json["version"], JSON_FEED_VERSION);
+        let item = &json["items"][0];
+        assert_eq!(item["id"], "urn:los-libros:book:Author/New");
+        assert_eq!(item["url"], "http://host/files/Author/New/New.epub");
+        assert_eq!(item["date_published"], "2024-03-03T12:00:00Z");
+        assert_eq!(item["authors"][0]["name"], "Author");
+        // Nothing for the template to show
+        assert_eq!(item["content_text"], "New");
+        assert!(item.get("content_html").is_none());
+    }
+
+    #[test]
+    fn test_feed_visibility() {
+        let mut config = FeedsConfig::default();
+        assert!(is_visible(&config, None));
+
+        config.visibility = FeedVisibility::Token;
+        assert!(!is_visible(&config, Some("secret")));
+        config.token = Some("secret".to_string());
+        assert!(is_visible(&config, Some("secret")));
+        assert!(!is_visible(&config, Some("guess")));
+        assert!(!is_visible(&config, None));
+
+        config.visibility = FeedVisibility::Off;
+        assert!(!is_visible(&config, Some("secret")));
+    }
+}
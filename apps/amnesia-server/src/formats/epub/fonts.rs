@@ -0,0 +1,157 @@
+//! Fonts named by EPUB stylesheets
+//!
+//! An EPUB asks for fonts by CSS family name, in stylesheets, `<style>`
+//! elements and `style` attributes. A family is embedded when an
+//! `@font-face` rule in the book defines it; any other family is left to
+//! the reading system, which for MuPDF means a configured or built-in
+//! substitute. Generic families (`serif`, `monospace`) are not listed.
+
+use std::collections::BTreeMap;
+use std::io::{Cursor, Read};
+use std::ops::Range;
+
+use zip::ZipArchive;
+
+use crate::document::RequestedFont;
+
+/// Keywords and generic families `font-family` may name
+const GENERIC_FAMILIES: &[&str] = &[
+    "serif",
+    "sans-serif",
+    "monospace",
+    "cursive",
+    "fantasy",
+    "system-ui",
+    "ui-serif",
+    "ui-sans-serif",
+    "ui-monospace",
+    "ui-rounded",
+    "math",
+    "emoji",
+    "fangsong",
+    "inherit",
+    "initial",
+    "unset",
+    "revert",
+];
+
+/// Font families used by the book's stylesheets and content documents
+pub(super) fn document_fonts(epub_bytes: &[u8]) -> Vec<RequestedFont> {
+    let Ok(mut archive) = ZipArchive::new(Cursor::new(epub_bytes)) else {
+        return Vec::new();
+    };
+
+    let mut families = FontFamilies::default();
+    for i in 0..archive.len() {
+        let Ok(mut entry) = archive.by_index(i) else {
+            continue;
+        };
+        let name = entry.name().to_ascii_lowercase();
+        let has_css = [".css", ".xhtml", ".html", ".htm"]
+            .iter()
+            .any(|ext| name.ends_with(ext));
+        let mut text = String::new();
+        if has_css && entry.read_to_string(&mut text).is_ok() {
+            families.scan(&text);
+        }
+    }
+    families.into_fonts()
+}
+
+/// Families seen so far, keyed case-insensitively
+#[derive(Debug, Default)]
+struct FontFamilies {
+    /// Lowercased family to its first spelling and whether it is embedded
+    families: BTreeMap<String, (String, bool)>,
+}
+
+impl FontFamilies {
+    /// Record every `font-family` in `css`, which may also be HTML
+    fn scan(&mut self, css: &str) {
+        // ASCII lowercasing keeps byte offsets valid in `css`
+        let lower = css.to_ascii_lowercase();
+        let faces = font_face_blocks(&lower);
+
+        let mut pos = 0;
+        while let Some(found) = lower[pos..].find("font-family") {
+            let at = pos + found;
+            pos = at + "font-family".len();
+
+            let Some(value) = css[pos..].trim_start().strip_prefix(':') else {
+                continue;
+            };
+            let end = value
+                .find([';', '}', '<', '>', '\n'])
+                .unwrap_or(value.len());
+            let embedded = faces.iter().any(|face| face.contains(&at));
+
+            for family in value[..end].split(',') {
+                let family = family.trim().trim_matches(['"', '\'']).trim();
+                if family.is_empty() || GENERIC_FAMILIES.contains(&family.to_lowercase().as_str()) {
+                    continue;
+                }
+                let known = self
+                    .families
+                    .entry(family.to_lowercase())
+                    .or_insert_with(|| (family.to_string(), false));
+                known.1 |= embedded;
+            }
+        }
+    }
+
+    fn into_fonts(self) -> Vec<RequestedFont> {
+        self.families
+            .into_values()
+            .map(|(name, embedded)| RequestedFont {
+                name,
+                embedded,
+                kind: None,
+            })
+            .collect()
+    }
+}
+
+/// Byte ranges of the `@font-face` rules in lowercased CSS
+fn font_face_blocks(lower: &str) -> Vec<Range<usize>> {
+    let mut blocks = Vec::new();
+    let mut pos = 0;
+    while let Some(found) = lower[pos..].find("@font-face") {
+        let start = pos + found;
+        let end = lower[start..]
+            .find('}')
+            .map_or(lower.len(), |close| start + close);
+        blocks.push(start..end);
+        pos = end;
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_font_families() {
+        let mut families = FontFamilies::default();
+        families.scan(
+            r#"@font-face { font-family: "Source Serif"; src: url(fonts/serif.otf); }
+body { font-family: 'Source Serif', Georgia, serif; }
+h1 { FONT-FAMILY : "Noto Sans CJK SC" }"#,
+        );
+        families.scan(r#"<p style="font-family: georgia">Text</p>"#);
+
+        let fonts = families.into_fonts();
+        let summary: Vec<(&str, bool)> = fonts
+            .iter()
+            .map(|font| (font.name.as_str(), font.embedded))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Georgia", false),
+                ("Noto Sans CJK SC", false),
+                ("Source Serif", true),
+            ]
+        );
+    }
+}
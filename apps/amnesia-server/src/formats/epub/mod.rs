@@ -17,9 +17,10 @@
 //! page rendering and text extraction APIs. Raw XHTML access would require
 //! custom FFI bindings or using rbook as a fallback.
 
+mod fonts;
 mod parser;
 mod renderer;
 
-pub use parser::EpubDocumentHandler;
 pub use parser::EpubDocumentParser;
+pub use parser::{EpubDocumentHandler, EpubLimits, LayoutConfig};
 pub use renderer::EpubDocumentRenderer;
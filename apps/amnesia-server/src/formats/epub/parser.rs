@@ -14,20 +14,41 @@
 //! The MuPDF Rust bindings (v0.5) don't expose the `fz_archive` API, so
 //! direct access to raw XHTML content is not available. Content is accessed
 //! via MuPDF's page rendering and text extraction APIs.
+//!
+//! MuPDF's metadata API only covers title, author, subject, and date. The OPF
+//! package document is therefore also read from the archive with the shared
+//! `epub-core` parser to fill in language, identifier, publisher, and cover.
+//!
+//! # Limits
+//!
+//! MuPDF layout of a pathological EPUB (say, a single 50MB chapter) can run
+//! for minutes. [`EpubLimits`] caps content document size before MuPDF sees
+//! the archive, the page count produced by layout, and the wall-clock time
+//! of loading and parsing. Exceeding any of them yields
+//! [`DocumentError::LayoutLimit`].
 
+use std::io::{Cursor, Read};
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use epub_core::{BookIndex, Glossary, LinkGraph, SpineItem, TocDocInfo};
 use mupdf::{MetadataName, TextPageOptions};
 use parking_lot::RwLock;
+use search_core::{IndexOptions, IndexedChapter, SearchIndexData};
+use serde::Deserialize;
+use tokio::time::{timeout, Duration};
+use zip::ZipArchive;
 
 use crate::document::{
-    BoundingBox, CharPosition, Creator, DocumentError, DocumentFormat, DocumentMetadata,
-    DocumentParser, DocumentResult, ParsedDocument, SearchOptions, SearchResult, StructuredText,
-    TextBlock, TextDirection, TextLine, TocEntry,
+    BoundingBox, ChapterMarkup, CharPosition, Creator, DocumentError, DocumentFonts,
+    DocumentFormat, DocumentMetadata, DocumentParser, DocumentResult, ParsedDocument,
+    SearchOptions, SearchResult, StructuredText, TextBlock, TextDirection, TextLine, TocEntry,
+    WebLink,
 };
 use crate::mupdf::SafeDocument;
 
+use super::fonts;
+
 /// Default layout width for EPUB rendering (points)
 const DEFAULT_LAYOUT_WIDTH: f32 = 800.0;
 
@@ -37,6 +58,27 @@ const DEFAULT_LAYOUT_HEIGHT: f32 = 600.0;
 /// Default em size for EPUB text layout (points)
 const DEFAULT_EM_SIZE: f32 = 12.0;
 
+/// Resource ceilings for loading and laying out an EPUB
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct EpubLimits {
+    /// Wall-clock limit for loading or parsing (seconds)
+    pub layout_timeout_secs: u64,
+    /// Maximum number of pages layout may produce
+    pub max_pages: usize,
+    /// Maximum uncompressed size of a single content document (bytes)
+    pub max_chapter_bytes: u64,
+}
+
+impl Default for EpubLimits {
+    fn default() -> Self {
+        Self {
+            layout_timeout_secs: 60,
+            max_pages: 50_000,
+            max_chapter_bytes: 16 * 1024 * 1024,
+        }
+    }
+}
+
 /// Layout configuration for reflowable documents
 #[derive(Debug, Clone, Copy)]
 pub struct LayoutConfig {
@@ -82,16 +124,35 @@ pub struct EpubDocumentHandler {
 
     /// Cached page count after initial layout
     page_count: RwLock<Option<usize>>,
+
+    /// Layout and size ceilings
+    limits: EpubLimits,
 }
 
 impl EpubDocumentHandler {
     /// Create a new EPUB handler from bytes
     pub fn from_bytes(data: Vec<u8>, id: String) -> DocumentResult<Self> {
+        Self::from_bytes_with_limits(data, id, EpubLimits::default())
+    }
+
+    /// Create a new EPUB handler from bytes, enforcing `limits`
+    ///
+    /// The chapter size and page count ceilings are checked here; the
+    /// wall-clock limit needs [`load`](Self::load).
+    pub fn from_bytes_with_limits(
+        data: Vec<u8>,
+        id: String,
+        limits: EpubLimits,
+    ) -> DocumentResult<Self> {
+        // Checked before MuPDF opens the archive, since opening lays it out
+        check_chapter_sizes(&data, limits.max_chapter_bytes)?;
+
         let doc = SafeDocument::from_bytes(data, id)?;
         let handler = Self {
             doc: Arc::new(doc),
             layout_config: RwLock::new(LayoutConfig::default()),
             page_count: RwLock::new(None),
+            limits,
         };
 
         // Perform initial layout to cache page count
@@ -100,6 +161,21 @@ impl EpubDocumentHandler {
         Ok(handler)
     }
 
+    /// Load an EPUB on the blocking pool, enforcing all of `limits`
+    ///
+    /// On timeout the layout thread cannot be interrupted and runs to
+    /// completion in the background, but the caller is released.
+    pub async fn load(data: Vec<u8>, id: String, limits: EpubLimits) -> DocumentResult<Self> {
+        let secs = limits.layout_timeout_secs;
+        timeout(
+            Duration::from_secs(secs),
+            tokio::task::spawn_blocking(move || Self::from_bytes_with_limits(data, id, limits)),
+        )
+        .await
+        .map_err(|_| layout_timeout(secs))?
+        .map_err(|e| DocumentError::ParseError(format!("Task join error: {}", e)))?
+    }
+
     /// Create a new EPUB handler from bytes with custom layout configuration
     pub fn from_bytes_with_layout(
         data: Vec<u8>,
@@ -111,6 +187,7 @@ impl EpubDocumentHandler {
             doc: Arc::new(doc),
             layout_config: RwLock::new(layout_config),
             page_count: RwLock::new(None),
+            limits: EpubLimits::default(),
         };
 
         handler.perform_initial_layout()?;
@@ -125,6 +202,7 @@ impl EpubDocumentHandler {
             doc: Arc::new(doc),
             layout_config: RwLock::new(LayoutConfig::default()),
             page_count: RwLock::new(None),
+            limits: EpubLimits::default(),
         };
 
         handler.perform_initial_layout()?;
@@ -142,6 +220,11 @@ impl EpubDocumentHandler {
         *self.layout_config.read()
     }
 
+    /// Get the layout and size ceilings
+    pub fn limits(&self) -> EpubLimits {
+        self.limits
+    }
+
     /// Perform initial layout to cache page count
     ///
     /// This is called once during construction to establish the initial
@@ -158,6 +241,7 @@ impl EpubDocumentHandler {
 
             // Cache page count after layout
             let count = mupdf_doc.page_count()? as usize;
+            check_page_count(count, self.limits.max_pages)?;
             *self.page_count.write() = Some(count);
 
             Ok(())
@@ -172,22 +256,44 @@ impl EpubDocumentHandler {
     /// Note: Due to SafeDocument's fresh-document-per-operation pattern,
     /// all subsequent operations will automatically use the new layout.
     pub fn relayout(&self, config: LayoutConfig) -> DocumentResult<()> {
-        // Update configuration (interior mutable)
-        *self.layout_config.write() = config;
-
         // Re-run layout to update page count cache
         self.doc.with_doc_mut(|mupdf_doc| {
             if mupdf_doc.is_reflowable().unwrap_or(false) {
                 mupdf_doc.layout(config.width, config.height, config.em)?;
             }
 
+            // A layout that exceeds the page ceiling is rejected and the
+            // previous configuration kept
             let count = mupdf_doc.page_count()? as usize;
+            check_page_count(count, self.limits.max_pages)?;
+
+            *self.layout_config.write() = config;
             *self.page_count.write() = Some(count);
 
             Ok(())
         })
     }
 
+    /// Write the laid-out book to `path` as a PDF
+    ///
+    /// Each page of the current layout becomes one PDF page, so the layout
+    /// configuration determines the page size and font scale.
+    pub fn export_pdf<P: AsRef<std::path::Path>>(&self, path: P) -> DocumentResult<()> {
+        let config = self.layout_config();
+        let path = path.as_ref().to_string_lossy().into_owned();
+
+        self.doc.with_doc_mut(|mupdf_doc| {
+            if mupdf_doc.is_reflowable().unwrap_or(false) {
+                mupdf_doc.layout(config.width, config.height, config.em)?;
+            }
+
+            let count = mupdf_doc.page_count()?;
+            let pdf = mupdf_doc.convert_to_pdf(0, count, 0)?;
+            pdf.save(&path)?;
+            Ok(())
+        })
+    }
+
     /// Get cached page count
     fn get_page_count(&self) -> usize {
         self.page_count.read().unwrap_or(0)
@@ -202,9 +308,22 @@ impl DocumentParser for EpubDocumentHandler {
     async fn parse(&self) -> DocumentResult<ParsedDocument> {
         let doc = self.doc.clone();
         let layout_config = self.layout_config();
-
-        tokio::task::spawn_blocking(move || {
-            doc.with_doc_mut(|mupdf_doc| {
+        let timeout_secs = self.limits.layout_timeout_secs;
+
+        let task = tokio::task::spawn_blocking(move || {
+            // OPF package metadata (best effort - MuPDF metadata is the fallback)
+            let bytes = doc.get_bytes().ok();
+            let opf = bytes
+                .as_deref()
+                .and_then(|bytes| read_package(bytes))
+                .map(|package| package.metadata)
+                .unwrap_or_default();
+            let chapter_checksums = bytes
+                .as_deref()
+                .map(|bytes| spine_checksums(bytes))
+                .unwrap_or_default();
+
+            let mut parsed = doc.with_doc_mut(|mupdf_doc| {
                 // Ensure layout before accessing pages
                 if mupdf_doc.is_reflowable().unwrap_or(false) {
                     mupdf_doc.layout(layout_config.width, layout_config.height, layout_config.em)?;
@@ -215,36 +334,50 @@ impl DocumentParser for EpubDocumentHandler {
                     mupdf_doc.metadata(name).ok().filter(|s| !s.is_empty())
                 };
 
-                let title = get_meta(MetadataName::Title).unwrap_or_else(|| doc.id().to_string());
+                let title = Some(opf.title)
+                    .filter(|t| !t.is_empty())
+                    .or_else(|| get_meta(MetadataName::Title))
+                    .unwrap_or_else(|| doc.id().to_string());
                 let author = get_meta(MetadataName::Author);
                 let subject = get_meta(MetadataName::Subject);
                 let creation_date = get_meta(MetadataName::CreationDate);
 
-                // Build creators list from author
-                let creators = author
-                    .map(|a| {
-                        // Split multiple authors by common separators
-                        a.split(&[',', ';', '&'][..])
-                            .map(|name| Creator {
-                                name: name.trim().to_string(),
-                                role: Some("author".to_string()),
-                                file_as: None,
-                            })
-                            .collect()
-                    })
-                    .unwrap_or_default();
+                // Build creators list from the OPF, falling back to MuPDF's author string
+                let creators = if !opf.creators.is_empty() {
+                    opf.creators
+                        .into_iter()
+                        .map(|c| Creator {
+                            name: c.name,
+                            role: c.role,
+                            file_as: c.file_as,
+                        })
+                        .collect()
+                } else {
+                    author
+                        .map(|a| {
+                            // Split multiple authors by common separators
+                            a.split(&[',', ';', '&'][..])
+                                .map(|name| Creator {
+                                    name: name.trim().to_string(),
+                                    role: Some("author".to_string()),
+                                    file_as: None,
+                                })
+                                .collect()
+                        })
+                        .unwrap_or_default()
+                };
 
                 let metadata = DocumentMetadata {
                     title,
                     creators,
-                    publisher: None,
-                    language: None, // MuPDF doesn't expose dc:language directly
-                    identifier: None,
-                    description: subject,
-                    cover_href: None,
-                    date: creation_date,
-                    rights: None,
-                    subjects: Vec::new(),
+                    publisher: opf.publisher,
+                    language: opf.language,
+                    identifier: opf.identifier,
+                    description: opf.description.or(subject),
+                    cover_href: opf.cover_href,
+                    date: opf.date.or(creation_date),
+                    rights: opf.rights,
+                    subjects: opf.subjects,
                 };
 
                 // Extract table of contents
@@ -264,11 +397,20 @@ impl DocumentParser for EpubDocumentHandler {
                     item_count,
                     item_labels: None, // EPUB doesn't have page labels like PDF
                     has_text_layer,
+                    chapter_checksums,
+                    diagnostics: Vec::new(),
                 })
-            })
-        })
-        .await
-        .map_err(|e| DocumentError::ParseError(format!("Task join error: {}", e)))?
+            })?;
+
+            // Includes what was reported while opening and laying out
+            parsed.diagnostics = doc.warnings();
+            Ok(parsed)
+        });
+
+        timeout(Duration::from_secs(timeout_secs), task)
+            .await
+            .map_err(|_| layout_timeout(timeout_secs))?
+            .map_err(|e| DocumentError::ParseError(format!("Task join error: {}", e)))?
     }
 
     fn item_count(&self) -> usize {
@@ -395,6 +537,111 @@ impl DocumentParser for EpubDocumentHandler {
             Ok((bounds.x1 - bounds.x0, bounds.y1 - bounds.y0))
         })
     }
+
+    async fn build_search_index(&self, options: &IndexOptions) -> DocumentResult<SearchIndexData> {
+        let doc = self.doc.clone();
+        let options = *options;
+
+        tokio::task::spawn_blocking(move || {
+            let bytes = doc.get_bytes()?;
+            let chapters = index_spine_chapters(&bytes, &options)?;
+            Ok(SearchIndexData::new(doc.id(), chapters))
+        })
+        .await
+        .map_err(|e| DocumentError::ParseError(format!("Task join error: {}", e)))?
+    }
+
+    async fn link_graph(&self) -> DocumentResult<LinkGraph> {
+        let doc = self.doc.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let bytes = doc.get_bytes()?;
+            read_spine_documents(&bytes, |spine, opf_dir, read| {
+                LinkGraph::build(spine, opf_dir, read)
+            })
+        })
+        .await
+        .map_err(|e| DocumentError::ParseError(format!("Task join error: {}", e)))?
+    }
+
+    async fn external_links(&self) -> DocumentResult<Vec<WebLink>> {
+        let doc = self.doc.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let bytes = doc.get_bytes()?;
+            read_spine_documents(&bytes, |spine, opf_dir, read| {
+                let mut links = Vec::new();
+                for (item_index, item) in spine.iter().enumerate() {
+                    let Some(html) = read(&epub_core::path::resolve_href(opf_dir, &item.href))
+                    else {
+                        continue;
+                    };
+                    links.extend(epub_core::extract_external_links(&html).into_iter().map(
+                        |link| WebLink {
+                            item_index,
+                            href: Some(item.href.clone()),
+                            url: link.url,
+                            text: link.text,
+                        },
+                    ));
+                }
+                links
+            })
+        })
+        .await
+        .map_err(|e| DocumentError::ParseError(format!("Task join error: {}", e)))?
+    }
+
+    async fn book_index(&self) -> DocumentResult<BookIndex> {
+        let doc = self.doc.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let bytes = doc.get_bytes()?;
+            read_spine_documents(&bytes, |spine, opf_dir, read| {
+                BookIndex::build(spine, opf_dir, read)
+            })
+        })
+        .await
+        .map_err(|e| DocumentError::ParseError(format!("Task join error: {}", e)))?
+    }
+
+    async fn glossary(&self) -> DocumentResult<Glossary> {
+        let doc = self.doc.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let bytes = doc.get_bytes()?;
+            read_spine_documents(&bytes, |spine, opf_dir, read| {
+                Glossary::build(spine, opf_dir, read)
+            })
+        })
+        .await
+        .map_err(|e| DocumentError::ParseError(format!("Task join error: {}", e)))?
+    }
+
+    async fn chapter_markup(&self, spine_index: usize) -> DocumentResult<ChapterMarkup> {
+        let doc = self.doc.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let bytes = doc.get_bytes()?;
+            read_chapter_markup(&bytes, spine_index)
+        })
+        .await
+        .map_err(|e| DocumentError::ParseError(format!("Task join error: {}", e)))?
+    }
+
+    async fn fonts(&self) -> DocumentResult<DocumentFonts> {
+        let doc = self.doc.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let bytes = doc.get_bytes()?;
+            Ok(DocumentFonts {
+                fonts: fonts::document_fonts(&bytes),
+                substitutions: doc.substitutions(),
+            })
+        })
+        .await
+        .map_err(|e| DocumentError::ParseError(format!("Task join error: {}", e)))?
+    }
 }
 
 impl EpubDocumentHandler {
@@ -408,6 +655,245 @@ impl EpubDocumentHandler {
 
 // Helper functions
 
+fn layout_timeout(secs: u64) -> DocumentError {
+    DocumentError::LayoutLimit(format!(
+        "EPUB layout did not finish within {} seconds",
+        secs
+    ))
+}
+
+fn check_page_count(count: usize, max_pages: usize) -> DocumentResult<()> {
+    if count > max_pages {
+        return Err(DocumentError::LayoutLimit(format!(
+            "EPUB layout produced {} pages; the limit is {}",
+            count, max_pages
+        )));
+    }
+    Ok(())
+}
+
+/// Reject archives containing a content document larger than `max_bytes`
+///
+/// Unreadable archives pass through so that MuPDF reports the parse error.
+fn check_chapter_sizes(epub_bytes: &[u8], max_bytes: u64) -> DocumentResult<()> {
+    let Ok(mut archive) = ZipArchive::new(Cursor::new(epub_bytes)) else {
+        return Ok(());
+    };
+
+    for i in 0..archive.len() {
+        let Ok(entry) = archive.by_index_raw(i) else {
+            continue;
+        };
+        let name = entry.name().to_ascii_lowercase();
+        let is_content =
+            name.ends_with(".xhtml") || name.ends_with(".html") || name.ends_with(".htm");
+        if is_content && entry.size() > max_bytes {
+            return Err(DocumentError::LayoutLimit(format!(
+                "Chapter '{}' is {} bytes; the limit is {}",
+                entry.name(),
+                entry.size(),
+                max_bytes
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Read and parse the OPF package document directly from the EPUB archive
+///
+/// The cover href is rebased onto the archive root so it can be fetched via
+/// the resources endpoint.
+fn read_package(epub_bytes: &[u8]) -> Option<epub_core::Package> {
+    let mut archive = ZipArchive::new(Cursor::new(epub_bytes)).ok()?;
+
+    let container = read_archive_text(&mut archive, epub_core::container::CONTAINER_PATH)?;
+    let opf_path = epub_core::find_opf_path(&container).ok()?;
+    let opf = read_archive_text(&mut archive, &opf_path)?;
+
+    let mut package = epub_core::parse_opf(&opf).ok()?;
+    let opf_dir = epub_core::path::opf_dir(&opf_path);
+    package.metadata.cover_href = package
+        .metadata
+        .cover_href
+        .map(|href| epub_core::path::resolve_href(&opf_dir, &href));
+
+    Some(package)
+}
+
+/// Extract and normalize the text of every spine chapter
+///
+/// Mirrors `SearchIndex::build` in the WASM processor: chapters are keyed by
+/// their OPF-relative spine href, oversize items are indexed per virtual
+/// chunk, and unreadable chapters are skipped, so the serialized index is
+/// identical to one built in the browser.
+fn index_spine_chapters(
+    epub_bytes: &[u8],
+    index_options: &IndexOptions,
+) -> DocumentResult<Vec<IndexedChapter>> {
+    let mut archive = ZipArchive::new(Cursor::new(epub_bytes))
+        .map_err(|e| DocumentError::InvalidContent(format!("Invalid EPUB archive: {}", e)))?;
+
+    let container = read_archive_text(&mut archive, epub_core::container::CONTAINER_PATH)
+        .ok_or_else(|| DocumentError::InvalidContent("Missing META-INF/container.xml".into()))?;
+    let opf_path = epub_core::find_opf_path(&container)
+        .map_err(|e| DocumentError::ParseError(e.to_string()))?;
+    let opf = read_archive_text(&mut archive, &opf_path)
+        .ok_or_else(|| DocumentError::InvalidContent(format!("Missing package {}", opf_path)))?;
+    let package =
+        epub_core::parse_opf(&opf).map_err(|e| DocumentError::ParseError(e.to_string()))?;
+    let opf_dir = epub_core::path::opf_dir(&opf_path);
+
+    let options = epub_core::ChunkOptions::default();
+    let mut chapters = Vec::new();
+    for (spine_index, item) in package.spine.iter().enumerate() {
+        let path = epub_core::path::resolve_href(&opf_dir, &item.href);
+        let Some(html) = read_archive_text(&mut archive, &path) else {
+            continue;
+        };
+
+        let chunks = epub_core::chunk_spine_item(&item.href, &html, &options);
+        if chunks.is_empty() {
+            chapters.push(IndexedChapter::from_html_with(
+                &item.href,
+                spine_index,
+                &html,
+                index_options,
+            ));
+        } else {
+            chapters.extend(chunks.iter().map(|chunk| {
+                IndexedChapter::from_html_with(&chunk.href, spine_index, &chunk.html, index_options)
+            }));
+        }
+    }
+
+    Ok(chapters)
+}
+
+/// Read the package's spine and hand it to `build`, with a reader for the
+/// archive's documents
+fn read_spine_documents<T>(
+    epub_bytes: &[u8],
+    build: impl FnOnce(&[SpineItem], &str, &mut dyn FnMut(&str) -> Option<String>) -> T,
+) -> DocumentResult<T> {
+    let mut archive = ZipArchive::new(Cursor::new(epub_bytes))
+        .map_err(|e| DocumentError::InvalidContent(format!("Invalid EPUB archive: {}", e)))?;
+
+    let container = read_archive_text(&mut archive, epub_core::container::CONTAINER_PATH)
+        .ok_or_else(|| DocumentError::InvalidContent("Missing META-INF/container.xml".into()))?;
+    let opf_path = epub_core::find_opf_path(&container)
+        .map_err(|e| DocumentError::ParseError(e.to_string()))?;
+    let opf = read_archive_text(&mut archive, &opf_path)
+        .ok_or_else(|| DocumentError::InvalidContent(format!("Missing package {}", opf_path)))?;
+    let package =
+        epub_core::parse_opf(&opf).map_err(|e| DocumentError::ParseError(e.to_string()))?;
+    let opf_dir = epub_core::path::opf_dir(&opf_path);
+
+    Ok(build(&package.spine, &opf_dir, &mut |path| {
+        read_archive_text(&mut archive, path)
+    }))
+}
+
+/// Read a spine item's markup, labelled from the ToC document
+fn read_chapter_markup(epub_bytes: &[u8], spine_index: usize) -> DocumentResult<ChapterMarkup> {
+    let mut archive = ZipArchive::new(Cursor::new(epub_bytes))
+        .map_err(|e| DocumentError::InvalidContent(format!("Invalid EPUB archive: {}", e)))?;
+
+    let container = read_archive_text(&mut archive, epub_core::container::CONTAINER_PATH)
+        .ok_or_else(|| DocumentError::InvalidContent("Missing META-INF/container.xml".into()))?;
+    let opf_path = epub_core::find_opf_path(&container)
+        .map_err(|e| DocumentError::ParseError(e.to_string()))?;
+    let opf = read_archive_text(&mut archive, &opf_path)
+        .ok_or_else(|| DocumentError::InvalidContent(format!("Missing package {}", opf_path)))?;
+    let package =
+        epub_core::parse_opf(&opf).map_err(|e| DocumentError::ParseError(e.to_string()))?;
+    let opf_dir = epub_core::path::opf_dir(&opf_path);
+
+    let item = package
+        .spine
+        .get(spine_index)
+        .ok_or(DocumentError::ItemNotFound(spine_index))?;
+    let path = epub_core::path::resolve_href(&opf_dir, &item.href);
+    let html = read_archive_text(&mut archive, &path)
+        .ok_or_else(|| DocumentError::ResourceNotFound(path.clone()))?;
+
+    // A missing ToC document only costs the label
+    let toc = match &package.toc_doc {
+        TocDocInfo::Nav { href } | TocDocInfo::Ncx { href } => {
+            let toc_path = epub_core::path::resolve_href(&opf_dir, href);
+            read_archive_text(&mut archive, &toc_path).map(|content| {
+                if matches!(package.toc_doc, TocDocInfo::Nav { .. }) {
+                    epub_core::parse_nav_document(&content)
+                } else {
+                    epub_core::parse_ncx_document(&content)
+                }
+            })
+        }
+        TocDocInfo::None => None,
+    };
+
+    Ok(ChapterMarkup {
+        label: toc.and_then(|toc| toc_label(&toc, &item.href)),
+        path,
+        html,
+    })
+}
+
+/// Label of the first ToC entry, in document order, pointing into `href`
+fn toc_label(entries: &[epub_core::TocEntry], href: &str) -> Option<String> {
+    entries.iter().find_map(|entry| {
+        let target = entry.href.split('#').next().unwrap_or_default();
+        if target == href {
+            Some(entry.label.clone())
+        } else {
+            toc_label(&entry.children, href)
+        }
+    })
+}
+
+/// Checksum the raw bytes of every spine item, as the WASM processor does
+///
+/// Items missing from the archive are left out; an unreadable package gives
+/// an empty list.
+fn spine_checksums(epub_bytes: &[u8]) -> Vec<epub_core::ChapterChecksum> {
+    let Ok(mut archive) = ZipArchive::new(Cursor::new(epub_bytes)) else {
+        return Vec::new();
+    };
+    let package = read_archive_text(&mut archive, epub_core::container::CONTAINER_PATH)
+        .and_then(|container| epub_core::find_opf_path(&container).ok())
+        .and_then(|opf_path| {
+            let opf = read_archive_text(&mut archive, &opf_path)?;
+            let package = epub_core::parse_opf(&opf).ok()?;
+            Some((package, epub_core::path::opf_dir(&opf_path)))
+        });
+    let Some((package, opf_dir)) = package else {
+        return Vec::new();
+    };
+
+    package
+        .spine
+        .iter()
+        .enumerate()
+        .filter_map(|(spine_index, item)| {
+            let path = epub_core::path::resolve_href(&opf_dir, &item.href);
+            let mut file = archive.by_name(&path).ok()?;
+            let mut bytes = Vec::new();
+            file.read_to_end(&mut bytes).ok()?;
+            Some(epub_core::ChapterChecksum {
+                spine_index,
+                href: item.href.clone(),
+                checksum: epub_core::content_checksum(&bytes),
+            })
+        })
+        .collect()
+}
+
+fn read_archive_text(archive: &mut ZipArchive<Cursor<&[u8]>>, path: &str) -> Option<String> {
+    let mut file = archive.by_name(path).ok()?;
+    let mut content = String::new();
+    file.read_to_string(&mut content).ok()?;
+    Some(content)
+}
+
 fn extract_toc(doc: &mupdf::Document) -> DocumentResult<Vec<TocEntry>> {
     let outlines = doc.outlines()?;
     Ok(convert_outlines_to_toc(&outlines))
@@ -596,6 +1082,42 @@ mod tests {
         assert_eq!(config.em, DEFAULT_EM_SIZE);
     }
 
+    fn epub_with_chapter(size: usize) -> Vec<u8> {
+        use std::io::Write;
+        let mut buf = Cursor::new(Vec::new());
+        {
+            let mut zip = zip::ZipWriter::new(&mut buf);
+            let options = zip::write::SimpleFileOptions::default();
+            zip.start_file("mimetype", options).unwrap();
+            zip.write_all(b"application/epub+zip").unwrap();
+            zip.start_file("OEBPS/chapter1.xhtml", options).unwrap();
+            zip.write_all(&vec![b'a'; size]).unwrap();
+            zip.finish().unwrap();
+        }
+        buf.into_inner()
+    }
+
+    #[test]
+    fn test_chapter_size_limit() {
+        let epub = epub_with_chapter(2048);
+        assert!(check_chapter_sizes(&epub, 4096).is_ok());
+        assert!(matches!(
+            check_chapter_sizes(&epub, 1024),
+            Err(DocumentError::LayoutLimit(_))
+        ));
+        // Not a zip: left for MuPDF to reject
+        assert!(check_chapter_sizes(b"not a zip", 1).is_ok());
+    }
+
+    #[test]
+    fn test_page_count_limit() {
+        assert!(check_page_count(10, 10).is_ok());
+        assert!(matches!(
+            check_page_count(11, 10),
+            Err(DocumentError::LayoutLimit(_))
+        ));
+    }
+
     #[test]
     fn test_convert_outlines_empty() {
         let outlines: Vec<mupdf::Outline> = vec![];
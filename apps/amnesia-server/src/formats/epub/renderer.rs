@@ -16,6 +16,7 @@
 use std::io::{Cursor, Read};
 
 use async_trait::async_trait;
+use image::codecs::jpeg::JpegEncoder;
 use image::DynamicImage;
 use mupdf::{Colorspace, Matrix};
 use zip::ZipArchive;
@@ -24,6 +25,7 @@ use crate::document::{
     DocumentError, DocumentParser, DocumentRenderer, DocumentResult, ImageFormat, RenderRequest,
     RenderResult, Resource,
 };
+use crate::mupdf::capture_warnings;
 
 use super::parser::EpubDocumentHandler;
 
@@ -39,38 +41,47 @@ impl DocumentRenderer for EpubDocumentHandler {
         let scale = request.scale.clamp(0.1, 4.0);
         let rotation = request.rotation;
         let format = request.format;
+        let quality = request.quality;
         let layout_config = self.layout_config();
 
         tokio::task::spawn_blocking(move || {
-            doc.with_doc_mut(|mupdf_doc| {
-                // Ensure document is laid out
-                if mupdf_doc.is_reflowable().unwrap_or(false) {
-                    mupdf_doc.layout(layout_config.width, layout_config.height, layout_config.em)?;
-                }
-
-                let page = mupdf_doc.load_page(item_index as i32)?;
-
-                // Build transformation matrix with scale and rotation
-                let mut matrix = Matrix::new_scale(scale, scale);
-                if rotation != 0 {
-                    let rotation_matrix = Matrix::new_rotate(rotation as f32);
-                    matrix.concat(rotation_matrix);
-                }
-
-                // Render to pixmap with alpha for proper text rendering
-                let colorspace = Colorspace::device_rgb();
-                let pixmap = page.to_pixmap(&matrix, &colorspace, true, true)?;
-
-                // Encode to requested format
-                let (data, width, height) = encode_pixmap(&pixmap, format)?;
-
-                Ok(RenderResult {
-                    data,
-                    format,
-                    width,
-                    height,
+            let (result, warnings) = capture_warnings(|| {
+                doc.with_doc_mut(|mupdf_doc| {
+                    // Ensure document is laid out
+                    if mupdf_doc.is_reflowable().unwrap_or(false) {
+                        mupdf_doc.layout(
+                            layout_config.width,
+                            layout_config.height,
+                            layout_config.em,
+                        )?;
+                    }
+
+                    let page = mupdf_doc.load_page(item_index as i32)?;
+
+                    // Build transformation matrix with scale and rotation
+                    let mut matrix = Matrix::new_scale(scale, scale);
+                    if rotation != 0 {
+                        let rotation_matrix = Matrix::new_rotate(rotation as f32);
+                        matrix.concat(rotation_matrix);
+                    }
+
+                    // Render to pixmap with alpha for proper text rendering
+                    let colorspace = Colorspace::device_rgb();
+                    let pixmap = page.to_pixmap(&matrix, &colorspace, true, true)?;
+
+                    // Encode to requested format
+                    let (data, width, height) = encode_pixmap(&pixmap, format, quality)?;
+
+                    Ok(RenderResult {
+                        data,
+                        format,
+                        width,
+                        height,
+                        warnings: Vec::new(),
+                    })
                 })
-            })
+            });
+            Ok(result?.with_warnings(warnings))
         })
         .await
         .map_err(|e| DocumentError::RenderError(format!("Task join error: {}", e)))?
@@ -89,34 +100,43 @@ impl DocumentRenderer for EpubDocumentHandler {
         let layout_config = self.layout_config();
 
         tokio::task::spawn_blocking(move || {
-            doc.with_doc_mut(|mupdf_doc| {
-                // Ensure document is laid out
-                if mupdf_doc.is_reflowable().unwrap_or(false) {
-                    mupdf_doc.layout(layout_config.width, layout_config.height, layout_config.em)?;
-                }
-
-                let page = mupdf_doc.load_page(item_index as i32)?;
-                let bounds = page.bounds()?;
-
-                // Calculate scale to fit within max_size
-                let width = bounds.x1 - bounds.x0;
-                let height = bounds.y1 - bounds.y0;
-                let scale = (max_size as f32) / width.max(height);
-
-                let matrix = Matrix::new_scale(scale, scale);
-                let colorspace = Colorspace::device_rgb();
-                let pixmap = page.to_pixmap(&matrix, &colorspace, true, false)?;
-
-                // JPEG for smaller thumbnails
-                let (data, out_width, out_height) = encode_pixmap(&pixmap, ImageFormat::Jpeg)?;
-
-                Ok(RenderResult {
-                    data,
-                    format: ImageFormat::Jpeg,
-                    width: out_width,
-                    height: out_height,
+            let (result, warnings) = capture_warnings(|| {
+                doc.with_doc_mut(|mupdf_doc| {
+                    // Ensure document is laid out
+                    if mupdf_doc.is_reflowable().unwrap_or(false) {
+                        mupdf_doc.layout(
+                            layout_config.width,
+                            layout_config.height,
+                            layout_config.em,
+                        )?;
+                    }
+
+                    let page = mupdf_doc.load_page(item_index as i32)?;
+                    let bounds = page.bounds()?;
+
+                    // Calculate scale to fit within max_size
+                    let width = bounds.x1 - bounds.x0;
+                    let height = bounds.y1 - bounds.y0;
+                    let scale = (max_size as f32) / width.max(height);
+
+                    let matrix = Matrix::new_scale(scale, scale);
+                    let colorspace = Colorspace::device_rgb();
+                    let pixmap = page.to_pixmap(&matrix, &colorspace, true, false)?;
+
+                    // JPEG for smaller thumbnails
+                    let (data, out_width, out_height) =
+                        encode_pixmap(&pixmap, ImageFormat::Jpeg, None)?;
+
+                    Ok(RenderResult {
+                        data,
+                        format: ImageFormat::Jpeg,
+                        width: out_width,
+                        height: out_height,
+                        warnings: Vec::new(),
+                    })
                 })
-            })
+            });
+            Ok(result?.with_warnings(warnings))
         })
         .await
         .map_err(|e| DocumentError::RenderError(format!("Task join error: {}", e)))?
@@ -186,6 +206,7 @@ impl DocumentRenderer for EpubDocumentRenderer {
 fn encode_pixmap(
     pixmap: &mupdf::Pixmap,
     format: ImageFormat,
+    quality: Option<u8>,
 ) -> DocumentResult<(Vec<u8>, u32, u32)> {
     let width = pixmap.width() as u32;
     let height = pixmap.height() as u32;
@@ -224,11 +245,19 @@ fn encode_pixmap(
                 .write_to(&mut Cursor::new(&mut output), image::ImageFormat::Png)
                 .map_err(|e| DocumentError::ImageError(e.to_string()))?;
         }
-        ImageFormat::Jpeg => {
-            dynamic_img
-                .write_to(&mut Cursor::new(&mut output), image::ImageFormat::Jpeg)
-                .map_err(|e| DocumentError::ImageError(e.to_string()))?;
-        }
+        ImageFormat::Jpeg => match quality {
+            Some(quality) => {
+                dynamic_img
+                    .to_rgb8()
+                    .write_with_encoder(JpegEncoder::new_with_quality(&mut output, quality))
+                    .map_err(|e| DocumentError::ImageError(e.to_string()))?;
+            }
+            None => {
+                dynamic_img
+                    .write_to(&mut Cursor::new(&mut output), image::ImageFormat::Jpeg)
+                    .map_err(|e| DocumentError::ImageError(e.to_string()))?;
+            }
+        },
         ImageFormat::Webp => {
             dynamic_img
                 .write_to(&mut Cursor::new(&mut output), image::ImageFormat::WebP)
@@ -300,17 +329,9 @@ fn extract_epub_resource(epub_bytes: &[u8], href: &str) -> DocumentResult<Resour
 ///
 /// - URL-decode percent-encoded characters
 /// - Replace backslashes with forward slashes
-/// - Remove leading "./" or "/"
+/// - Remove leading "./" or "/" and resolve "." / ".." components
 fn normalize_epub_path(path: &str) -> String {
-    // URL decode
-    let decoded = urlencoding::decode(path).unwrap_or_else(|_| path.into());
-
-    // Normalize separators and leading chars
-    decoded
-        .replace('\\', "/")
-        .trim_start_matches("./")
-        .trim_start_matches('/')
-        .to_string()
+    epub_core::path::normalize_path(&epub_core::path::percent_decode(path))
 }
 
 /// Find a matching file in the archive using fuzzy matching
@@ -0,0 +1,229 @@
+//! Subprocess isolation for untrusted documents
+//!
+//! A malformed PDF can crash MuPDF and, in-process, the whole server. When
+//! isolation is enabled, a newly uploaded document is first parsed (and its
+//! first item rendered) by a short-lived worker: the server binary re-executed
+//! with [`WORKER_ARG`]. Only if the worker succeeds is the document parsed
+//! in-process and cached.
+//!
+//! # Protocol
+//!
+//! ```text
+//! stdin:  <doc id>\n<raw document bytes until EOF>
+//! stdout: one JSON WorkerReport line
+//! ```
+//!
+//! A worker killed by a signal, exiting non-zero, or exceeding the timeout is
+//! reported as [`IsolationError::Crashed`] or [`IsolationError::Timeout`].
+
+use std::io::{Read, Write};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::document::{
+    DocumentError, DocumentFormat, DocumentParser, DocumentRenderer, DocumentResult, ParsedDocument,
+};
+
+use super::epub::EpubDocumentHandler;
+use super::pdf::PdfDocumentHandler;
+
+/// Command-line argument that starts the binary as a parse worker
+pub const WORKER_ARG: &str = "--parse-worker";
+
+/// Size of the thumbnail rendered to exercise the renderer
+const PROBE_THUMBNAIL_SIZE: u32 = 128;
+
+/// Result of a worker run, written to stdout as JSON
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum WorkerReport {
+    /// Parsing and the first-item render succeeded
+    Parsed { document: ParsedDocument },
+    /// The document was rejected with an ordinary error
+    Failed { error: String },
+}
+
+/// Errors from an isolated parse
+#[derive(Debug, Error)]
+pub enum IsolationError {
+    #[error("Failed to start parse worker: {0}")]
+    Spawn(#[from] std::io::Error),
+
+    #[error("Parse worker timed out after {0} seconds")]
+    Timeout(u64),
+
+    #[error("Parse worker crashed: {0}")]
+    Crashed(String),
+
+    #[error("Document rejected: {0}")]
+    Rejected(String),
+
+    #[error("Invalid worker response: {0}")]
+    Protocol(String),
+}
+
+impl IsolationError {
+    /// Whether the failure was caused by the document rather than the host
+    pub fn is_document_fault(&self) -> bool {
+        matches!(
+            self,
+            Self::Timeout(_) | Self::Crashed(_) | Self::Rejected(_)
+        )
+    }
+}
+
+/// Parse a document in a worker subprocess
+///
+/// Returns the worker's parse result; the caller still parses in-process to
+/// obtain a live parser and renderer.
+pub async fn parse_isolated(
+    data: &[u8],
+    doc_id: &str,
+    timeout_secs: u64,
+) -> Result<ParsedDocument, IsolationError> {
+    let exe = std::env::current_exe()?;
+    let mut child = Command::new(exe)
+        .arg(WORKER_ARG)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| IsolationError::Protocol("worker stdin unavailable".to_string()))?;
+    let mut input = Vec::with_capacity(doc_id.len() + 1 + data.len());
+    input.extend_from_slice(doc_id.as_bytes());
+    input.push(b'\n');
+    input.extend_from_slice(data);
+
+    let run = async move {
+        // A worker that dies mid-read closes the pipe; its exit status is
+        // the more useful error, so a failed write is not fatal here
+        let _ = stdin.write_all(&input).await;
+        drop(stdin);
+        child.wait_with_output().await
+    };
+
+    let output = tokio::time::timeout(Duration::from_secs(timeout_secs), run)
+        .await
+        .map_err(|_| IsolationError::Timeout(timeout_secs))??;
+
+    if !output.status.success() {
+        return Err(IsolationError::Crashed(output.status.to_string()));
+    }
+
+    match serde_json::from_slice::<WorkerReport>(&output.stdout) {
+        Ok(WorkerReport::Parsed { document }) => Ok(document),
+        Ok(WorkerReport::Failed { error }) => Err(IsolationError::Rejected(error)),
+        Err(e) => Err(IsolationError::Protocol(e.to_string())),
+    }
+}
+
+/// Entry point for the worker process; returns the exit code
+///
+/// Reads the request from stdin, writes a [`WorkerReport`] to stdout, and
+/// exits 0 whenever a report was written. A MuPDF crash terminates the
+/// process before that point.
+pub async fn run_worker() -> i32 {
+    let mut input = Vec::new();
+    if std::io::stdin().read_to_end(&mut input).is_err() {
+        return 2;
+    }
+
+    let report = match split_request(&input) {
+        Some((doc_id, data)) => match probe(doc_id, data.to_vec()).await {
+            Ok(document) => WorkerReport::Parsed { document },
+            Err(e) => WorkerReport::Failed {
+                error: e.to_string(),
+            },
+        },
+        None => WorkerReport::Failed {
+            error: "Malformed worker request".to_string(),
+        },
+    };
+
+    let mut stdout = std::io::stdout().lock();
+    match serde_json::to_writer(&mut stdout, &report) {
+        Ok(()) => {
+            let _ = stdout.write_all(b"\n");
+            let _ = stdout.flush();
+            0
+        }
+        Err(_) => 2,
+    }
+}
+
+/// Split `<doc id>\n<bytes>` into its parts
+fn split_request(input: &[u8]) -> Option<(String, &[u8])> {
+    let newline = input.iter().position(|&b| b == b'\n')?;
+    let doc_id = std::str::from_utf8(&input[..newline]).ok()?;
+    if doc_id.is_empty() {
+        return None;
+    }
+    Some((doc_id.to_string(), &input[newline + 1..]))
+}
+
+/// Parse the document and render its first item
+async fn probe(doc_id: String, data: Vec<u8>) -> DocumentResult<ParsedDocument> {
+    let format = DocumentFormat::from_magic_bytes(&data)
+        .ok_or_else(|| DocumentError::UnsupportedFormat("Unknown format".into()))?;
+
+    let (parser, renderer): (Arc<dyn DocumentParser>, Arc<dyn DocumentRenderer>) = match format {
+        DocumentFormat::Pdf => {
+            let handler = Arc::new(PdfDocumentHandler::from_bytes(data, doc_id)?);
+            (handler.clone(), handler)
+        }
+        DocumentFormat::Epub => {
+            let handler = Arc::new(EpubDocumentHandler::from_bytes(data, doc_id)?);
+            (handler.clone(), handler)
+        }
+    };
+
+    let parsed = parser.parse().await?;
+    if parsed.item_count > 0 {
+        renderer.render_thumbnail(0, PROBE_THUMBNAIL_SIZE).await?;
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_request() {
+        let (doc_id, data) = split_request(b"book-1\n%PDF-1.7\nrest").unwrap();
+        assert_eq!(doc_id, "book-1");
+        assert_eq!(data, b"%PDF-1.7\nrest");
+
+        assert!(split_request(b"no newline").is_none());
+        assert!(split_request(b"\n%PDF").is_none());
+    }
+
+    #[test]
+    fn test_worker_report_round_trip() {
+        let json = serde_json::to_string(&WorkerReport::Failed {
+            error: "bad xref".to_string(),
+        })
+        .unwrap();
+        assert_eq!(json, r#"{"status":"failed","error":"bad xref"}"#);
+
+        let report: WorkerReport = serde_json::from_str(&json).unwrap();
+        assert!(matches!(report, WorkerReport::Failed { error } if error == "bad xref"));
+    }
+
+    #[tokio::test]
+    async fn test_probe_rejects_unknown_format() {
+        let result = probe("x".to_string(), b"not a document".to_vec()).await;
+        assert!(matches!(result, Err(DocumentError::UnsupportedFormat(_))));
+    }
+}
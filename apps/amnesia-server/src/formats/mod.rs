@@ -11,6 +11,10 @@
 //!
 //! These implementations wrap the lower-level MuPDF bindings and provide
 //! the unified interface defined in the `document` module.
+//!
+//! `isolation` runs the same parsers in a worker subprocess so that a
+//! document that crashes MuPDF cannot take the server down with it.
 
 pub mod epub;
+pub mod isolation;
 pub mod pdf;
@@ -0,0 +1,504 @@
+//! Chapter detection for PDFs without an outline
+//!
+//! Many DRM-free PDFs ship without bookmarks. When `outlines()` is empty, a
+//! table of contents is synthesized from the text layer by looking for
+//! heading-like lines:
+//!
+//! - "Chapter 3", "Part II", "Appendix A", and standalone "Prologue" or
+//!   "Index" lines, set larger than body text or after a page sink
+//! - numbered headings ("2 Methods", "2.1 Sampling") set larger than body
+//!   text, nested by their number depth
+//! - any short line set well above body size with whitespace above it
+//!
+//! Body size is the most common font size across the document. A "sink" is
+//! the extra space above the first line of a chapter's opening page compared
+//! with the usual top margin, which running headers never have. Headings
+//! whose text repeats are running headers and only count once. Consecutive
+//! heading lines ("CHAPTER 3" / "The Return") are joined into one entry.
+//!
+//! Entries are at most two levels deep. Documents with fewer than
+//! [`MIN_HEADINGS`] headings (including scans without text) get no TOC.
+
+use std::collections::{HashMap, HashSet};
+
+use mupdf::TextPageOptions;
+
+use crate::document::{DocumentResult, TocEntry};
+
+/// Fewest headings worth returning as a table of contents
+pub const MIN_HEADINGS: usize = 2;
+
+/// Font size ratio to body text above which a chapter pattern is a heading
+const PATTERN_SIZE_RATIO: f32 = 1.1;
+
+/// Font size ratio to body text above which any short line is a heading
+const LARGE_SIZE_RATIO: f32 = 1.3;
+
+/// Extra space above a page's first line, in body font sizes, that makes a
+/// chapter-opening sink
+const SINK_RATIO: f32 = 3.0;
+
+/// Space above a line, in body font sizes, that sets it apart from the text
+const GAP_RATIO: f32 = 1.5;
+
+/// Longest heading, in words
+const MAX_HEADING_WORDS: usize = 12;
+
+/// Deepest TOC level (0-based)
+const MAX_LEVEL: usize = 1;
+
+/// One line of a page's text layer (y grows downward)
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageLine {
+    pub text: String,
+    /// Largest character size on the line
+    pub font_size: f32,
+    pub top: f32,
+    pub bottom: f32,
+}
+
+/// Synthesize a TOC from a document's text layer
+pub fn detect_toc(doc: &mupdf::Document) -> DocumentResult<Vec<TocEntry>> {
+    let mut pages = Vec::new();
+    for index in 0..doc.page_count()? {
+        pages.push(page_lines(&doc.load_page(index)?)?);
+    }
+    Ok(detect_chapters(&pages))
+}
+
+/// Text lines of a page in reading order
+fn page_lines(page: &mupdf::Page) -> DocumentResult<Vec<PageLine>> {
+    let text_page = page.to_text_page(TextPageOptions::empty())?;
+    let mut lines = Vec::new();
+
+    for block in text_page.blocks() {
+        for line in block.lines() {
+            let mut text = String::new();
+            let mut font_size: f32 = 0.0;
+            for ch in line.chars() {
+                if let Some(c) = ch.char() {
+                    text.push(c);
+                    font_size = font_size.max(ch.size());
+                }
+            }
+
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            if !text.is_empty() {
+                let bounds = line.bounds();
+                lines.push(PageLine {
+                    text,
+                    font_size,
+                    top: bounds.y0,
+                    bottom: bounds.y1,
+                });
+            }
+        }
+    }
+
+    Ok(lines)
+}
+
+/// What a line's wording says about it being a heading
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pattern {
+    /// "Part II", "Book 1": above chapters
+    Part,
+    /// "Chapter 3", "Appendix A", "Epilogue"
+    Chapter,
+    /// "2.1 Sampling": depth from the number of components
+    Numbered(usize),
+}
+
+/// A detected heading before nesting
+#[derive(Debug, Clone)]
+struct Heading {
+    label: String,
+    page: usize,
+    font_size: f32,
+    pattern: Option<Pattern>,
+}
+
+/// Build a TOC from the text lines of every page
+pub fn detect_chapters(pages: &[Vec<PageLine>]) -> Vec<TocEntry> {
+    let Some(body) = body_font_size(pages) else {
+        return Vec::new();
+    };
+    let margin = usual_top_margin(pages);
+
+    let mut headings: Vec<Heading> = Vec::new();
+    let mut seen = HashSet::new();
+    for (page, lines) in pages.iter().enumerate() {
+        let mut current: Option<(Heading, f32)> = None;
+
+        for (index, line) in lines.iter().enumerate() {
+            let set_apart = match index.checked_sub(1).map(|i| &lines[i]) {
+                Some(previous) => line.top - previous.bottom >= GAP_RATIO * body,
+                None => margin.is_none_or(|margin| line.top - margin >= SINK_RATIO * body),
+            };
+            let pattern = heading_pattern(&line.text);
+            let ratio = line.font_size / body;
+
+            let is_heading = match pattern {
+                Some(Pattern::Numbered(depth)) => {
+                    ratio >= PATTERN_SIZE_RATIO && depth <= MAX_LEVEL + 1
+                }
+                Some(_) => ratio >= PATTERN_SIZE_RATIO || (index == 0 && set_apart),
+                None => {
+                    ratio >= LARGE_SIZE_RATIO
+                        && (index == 0 || set_apart || current.is_some())
+                        && line.text.chars().filter(|c| c.is_alphabetic()).count() >= 3
+                }
+            } && line.text.split_whitespace().count() <= MAX_HEADING_WORDS;
+
+            if !is_heading {
+                if let Some((heading, _)) = current.take() {
+                    push_heading(&mut headings, &mut seen, heading);
+                }
+                continue;
+            }
+
+            // An unnumbered heading line directly under another continues it
+            if let Some((heading, bottom)) = &mut current {
+                if pattern.is_none() && line.top - *bottom < GAP_RATIO * body {
+                    // "CHAPTER 3" / "The Return" reads as "CHAPTER 3: The Return"
+                    let labelled =
+                        matches!(heading.pattern, Some(Pattern::Part | Pattern::Chapter));
+                    let separator = if labelled && heading.label.split_whitespace().count() <= 2 {
+                        ": "
+                    } else {
+                        " "
+                    };
+                    heading.label = format!("{}{}{}", heading.label, separator, line.text);
+                    heading.font_size = heading.font_size.max(line.font_size);
+                    *bottom = line.bottom;
+                    continue;
+                }
+            }
+
+            if let Some((heading, _)) = current.take() {
+                push_heading(&mut headings, &mut seen, heading);
+            }
+            current = Some((
+                Heading {
+                    label: line.text.clone(),
+                    page,
+                    font_size: line.font_size,
+                    pattern,
+                },
+                line.bottom,
+            ));
+        }
+
+        if let Some((heading, _)) = current {
+            push_heading(&mut headings, &mut seen, heading);
+        }
+    }
+
+    if headings.len() < MIN_HEADINGS {
+        return Vec::new();
+    }
+    nest(headings)
+}
+
+/// Keep the first heading with a given text; later ones are running headers
+fn push_heading(headings: &mut Vec<Heading>, seen: &mut HashSet<String>, heading: Heading) {
+    if seen.insert(heading.label.to_lowercase()) {
+        headings.push(heading);
+    }
+}
+
+/// Most common font size by character count, to the half point
+fn body_font_size(pages: &[Vec<PageLine>]) -> Option<f32> {
+    let mut counts: HashMap<i32, usize> = HashMap::new();
+    for line in pages.iter().flatten() {
+        if line.font_size > 0.0 {
+            *counts
+                .entry((line.font_size * 2.0).round() as i32)
+                .or_insert(0) += line.text.len();
+        }
+    }
+    counts
+        .into_iter()
+        .max_by_key(|&(size, count)| (count, -size))
+        .map(|(size, _)| size as f32 / 2.0)
+}
+
+/// Lower quartile of the first line's top across pages
+///
+/// Not the median, since short documents can have as many sunk chapter
+/// openers as ordinary pages.
+fn usual_top_margin(pages: &[Vec<PageLine>]) -> Option<f32> {
+    let mut tops: Vec<f32> = pages
+        .iter()
+        .filter_map(|lines| lines.first().map(|line| line.top))
+        .collect();
+    tops.sort_by(f32::total_cmp);
+    tops.get(tops.len() / 4).copied()
+}
+
+/// Recognize chapter wording at the start of a line
+fn heading_pattern(text: &str) -> Option<Pattern> {
+    let mut words = text.split_whitespace();
+    let first = words.next()?;
+    let keyword = first
+        .trim_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase();
+
+    match keyword.as_str() {
+        "part" | "book" | "chapter" | "appendix" => {
+            let number = words
+                .next()?
+                .trim_matches(|c: char| !c.is_alphanumeric())
+                .to_lowercase();
+            let is_number = number.chars().all(|c| c.is_ascii_digit())
+                || number.chars().all(|c| "ivxlcdm".contains(c))
+                || NUMBER_WORDS.contains(&number.as_str())
+                || (keyword == "appendix" && number.len() == 1);
+            if number.is_empty() || !is_number {
+                return None;
+            }
+            Some(if matches!(keyword.as_str(), "part" | "book") {
+                Pattern::Part
+            } else {
+                Pattern::Chapter
+            })
+        }
+        _ if text.split_whitespace().count() == 1
+            && STANDALONE_HEADINGS.contains(&keyword.as_str()) =>
+        {
+            Some(Pattern::Chapter)
+        }
+        _ => {
+            // "2", "2.", "2.1" followed by a capitalized word
+            let number = first.trim_end_matches('.');
+            let is_numbered = !number.is_empty()
+                && number.split('.').all(|part| {
+                    !part.is_empty() && part.len() <= 3 && part.chars().all(|c| c.is_ascii_digit())
+                });
+            let titled = words
+                .next()
+                .and_then(|word| word.chars().next())
+                .is_some_and(char::is_uppercase);
+            (is_numbered && titled).then(|| Pattern::Numbered(number.split('.').count()))
+        }
+    }
+}
+
+const NUMBER_WORDS: &[&str] = &[
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "ten",
+    "eleven",
+    "twelve",
+    "thirteen",
+    "fourteen",
+    "fifteen",
+    "sixteen",
+    "seventeen",
+    "eighteen",
+    "nineteen",
+    "twenty",
+];
+
+const STANDALONE_HEADINGS: &[&str] = &[
+    "prologue",
+    "epilogue",
+    "preface",
+    "foreword",
+    "introduction",
+    "afterword",
+    "conclusion",
+    "acknowledgments",
+    "acknowledgements",
+    "bibliography",
+    "references",
+    "glossary",
+    "index",
+];
+
+/// Nest headings two levels deep
+///
+/// Numbered headings nest by their depth and parts are always top-level;
+/// other headings are top-level when set in the largest heading size that
+/// occurs more than once (a one-off title page does not count).
+fn nest(headings: Vec<Heading>) -> Vec<TocEntry> {
+    let mut size_counts: HashMap<i32, usize> = HashMap::new();
+    for heading in &headings {
+        *size_counts.entry(size_key(heading.font_size)).or_insert(0) += 1;
+    }
+    let top_size = size_counts
+        .iter()
+        .filter(|&(_, &count)| count > 1)
+        .map(|(&size, _)| size)
+        .max();
+
+    let mut toc: Vec<TocEntry> = Vec::new();
+    for heading in headings {
+        let level = match heading.pattern {
+            Some(Pattern::Part) => 0,
+            Some(Pattern::Numbered(depth)) => (depth - 1).min(MAX_LEVEL),
+            _ => match top_size {
+                Some(top) if size_key(heading.font_size) < top => MAX_LEVEL,
+                _ => 0,
+            },
+        };
+
+        let entry = TocEntry {
+            label: heading.label,
+            href: format!("page:{}", heading.page + 1),
+            item_index: Some(heading.page),
+            children: Vec::new(),
+            play_order: Some((heading.page + 1) as u32),
+        };
+        match toc.last_mut() {
+            Some(parent) if level > 0 => parent.children.push(entry),
+            _ => toc.push(entry),
+        }
+    }
+    toc
+}
+
+fn size_key(font_size: f32) -> i32 {
+    font_size.round() as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str, font_size: f32, top: f32) -> PageLine {
+        PageLine {
+            text: text.to_string(),
+            font_size,
+            top,
+            bottom: top + font_size * 1.2,
+        }
+    }
+
+    /// A page of body text starting at `top`
+    fn body_page(header: Option<&str>, top: f32) -> Vec<PageLine> {
+        let mut lines: Vec<PageLine> = header.map(|h| line(h, 10.0, 40.0)).into_iter().collect();
+        lines.extend((0..30).map(|i| {
+            line(
+                "Body text that runs the full width of the page, more or less.",
+                10.0,
+                top + i as f32 * 14.0,
+            )
+        }));
+        lines
+    }
+
+    fn labels(toc: &[TocEntry]) -> Vec<(String, usize, usize)> {
+        toc.iter()
+            .map(|e| (e.label.clone(), e.item_index.unwrap(), e.children.len()))
+            .collect()
+    }
+
+    #[test]
+    fn test_heading_pattern() {
+        assert_eq!(heading_pattern("Chapter 3"), Some(Pattern::Chapter));
+        assert_eq!(heading_pattern("CHAPTER XII"), Some(Pattern::Chapter));
+        assert_eq!(
+            heading_pattern("Chapter One: Beginnings"),
+            Some(Pattern::Chapter)
+        );
+        assert_eq!(heading_pattern("Part II"), Some(Pattern::Part));
+        assert_eq!(heading_pattern("Appendix B"), Some(Pattern::Chapter));
+        assert_eq!(heading_pattern("Epilogue"), Some(Pattern::Chapter));
+        assert_eq!(heading_pattern("2.1 Sampling"), Some(Pattern::Numbered(2)));
+        assert_eq!(heading_pattern("3. Results"), Some(Pattern::Numbered(1)));
+        assert_eq!(heading_pattern("Chapter and verse"), None);
+        assert_eq!(heading_pattern("Index of the past was lost"), None);
+        assert_eq!(heading_pattern("2019 was a year"), None);
+        assert_eq!(heading_pattern("12 apples"), None);
+    }
+
+    #[test]
+    fn test_detect_novel_chapters() {
+        let pages = vec![
+            vec![line("A Long Journey", 28.0, 200.0)],
+            // Chapter openers: sunk, larger, two-line heading
+            {
+                let mut page = vec![
+                    line("CHAPTER 1", 14.0, 180.0),
+                    line("The Road", 20.0, 200.0),
+                ];
+                page.extend(body_page(None, 260.0));
+                page
+            },
+            body_page(Some("Chapter 1 The Road"), 60.0),
+            {
+                let mut page = vec![
+                    line("CHAPTER 2", 14.0, 180.0),
+                    line("The River", 20.0, 200.0),
+                ];
+                page.extend(body_page(None, 260.0));
+                page
+            },
+            body_page(Some("Chapter 2 The River"), 60.0),
+        ];
+
+        let toc = detect_chapters(&pages);
+        assert_eq!(
+            labels(&toc),
+            vec![
+                ("A Long Journey".to_string(), 0, 0),
+                ("CHAPTER 1: The Road".to_string(), 1, 0),
+                ("CHAPTER 2: The River".to_string(), 3, 0),
+            ]
+        );
+        assert_eq!(toc[1].href, "page:2");
+    }
+
+    #[test]
+    fn test_detect_numbered_sections() {
+        let pages = vec![
+            {
+                let mut page = vec![
+                    line("1 Introduction", 16.0, 60.0),
+                    line("1.1 Background", 12.0, 82.0),
+                ];
+                page.extend(body_page(None, 110.0));
+                page
+            },
+            {
+                let mut page = body_page(None, 60.0);
+                page.push(line("1.1 Motivation", 12.0, 500.0));
+                page.push(line("1.1.1 Too deep to list", 11.0, 530.0));
+                page
+            },
+            {
+                let mut page = vec![line("2 Methods", 16.0, 60.0)];
+                page.extend(body_page(None, 90.0));
+                page.push(line("2 The body mentions a number", 10.0, 520.0));
+                page
+            },
+        ];
+
+        let toc = detect_chapters(&pages);
+        assert_eq!(
+            labels(&toc),
+            vec![
+                ("1 Introduction".to_string(), 0, 2),
+                ("2 Methods".to_string(), 2, 0)
+            ]
+        );
+        assert_eq!(toc[0].children[1].label, "1.1 Motivation");
+    }
+
+    #[test]
+    fn test_no_chapters() {
+        assert!(detect_chapters(&[]).is_empty());
+        assert!(detect_chapters(&[Vec::new(), Vec::new()]).is_empty());
+
+        let pages: Vec<Vec<PageLine>> = (0..5).map(|_| body_page(None, 60.0)).collect();
+        assert!(detect_chapters(&pages).is_empty());
+    }
+}
@@ -0,0 +1,108 @@
+//! Fonts used by PDF pages
+//!
+//! Each page lists its fonts in `/Resources /Font`, possibly inherited from
+//! an ancestor in the page tree. A font is embedded when its descriptor has
+//! a `/FontFile`, `/FontFile2` or `/FontFile3` stream; for composite
+//! (`Type0`) fonts the descriptor belongs to the descendant font. Fonts
+//! used only inside form XObjects are not listed.
+
+use std::collections::BTreeMap;
+
+use mupdf::pdf::{PdfDocument, PdfObject};
+
+use crate::document::RequestedFont;
+use crate::mupdf::strip_subset_prefix;
+
+/// Deepest page tree searched for inherited resources
+const MAX_TREE_DEPTH: usize = 32;
+
+/// Fonts named by the document's pages, by name
+pub fn document_fonts(pdf: &PdfDocument, page_count: usize) -> Vec<RequestedFont> {
+    let mut fonts = BTreeMap::new();
+    for index in 0..page_count {
+        if let Err(e) = collect_page_fonts(pdf, index, &mut fonts) {
+            tracing::debug!("Skipping fonts of page {}: {}", index, e);
+        }
+    }
+    fonts.into_values().collect()
+}
+
+fn collect_page_fonts(
+    pdf: &PdfDocument,
+    index: usize,
+    fonts: &mut BTreeMap<String, RequestedFont>,
+) -> Result<(), mupdf::Error> {
+    let page = pdf.find_page(index as i32)?;
+    let Some(resources) = inherited_resources(page)? else {
+        return Ok(());
+    };
+    let Some(font_dict) = resources.get_dict("Font")? else {
+        return Ok(());
+    };
+
+    for i in 0..font_dict.dict_len()? as i32 {
+        let Some(font) = font_dict.get_dict_val(i)? else {
+            continue;
+        };
+        if let Some(requested) = read_font(&font)? {
+            // A font embedded anywhere counts as embedded
+            fonts
+                .entry(requested.name.clone())
+                .and_modify(|known: &mut RequestedFont| known.embedded |= requested.embedded)
+                .or_insert(requested);
+        }
+    }
+    Ok(())
+}
+
+/// `/Resources` of a page, or of its nearest ancestor that has them
+fn inherited_resources(page: PdfObject) -> Result<Option<PdfObject>, mupdf::Error> {
+    let mut node = page;
+    for _ in 0..MAX_TREE_DEPTH {
+        if let Some(resources) = node.get_dict("Resources")? {
+            return Ok(Some(resources));
+        }
+        match node.get_dict("Parent")? {
+            Some(parent) => node = parent,
+            None => break,
+        }
+    }
+    Ok(None)
+}
+
+fn read_font(font: &PdfObject) -> Result<Option<RequestedFont>, mupdf::Error> {
+    let Some(base_font) = font.get_dict("BaseFont")? else {
+        // Type 3 fonts have no name; their glyphs are drawn by the document
+        return Ok(None);
+    };
+    let name = String::from_utf8_lossy(base_font.as_name()?).into_owned();
+    let kind = match font.get_dict("Subtype")? {
+        Some(subtype) => Some(String::from_utf8_lossy(subtype.as_name()?).into_owned()),
+        None => None,
+    };
+
+    let descriptor_owner = match font.get_dict("DescendantFonts")? {
+        Some(descendants) => descendants.get_array(0)?,
+        None => None,
+    };
+    let descriptor = descriptor_owner
+        .as_ref()
+        .unwrap_or(font)
+        .get_dict("FontDescriptor")?;
+    let embedded = match descriptor {
+        Some(descriptor) => {
+            let mut embedded = false;
+            for key in ["FontFile", "FontFile2", "FontFile3"] {
+                embedded |= descriptor.get_dict(key)?.is_some();
+            }
+            embedded
+        }
+        None => false,
+    };
+
+    Ok(Some(RequestedFont {
+        name: strip_subset_prefix(&name).to_string(),
+        embedded,
+        kind,
+    }))
+}
@@ -7,9 +7,15 @@
 //!
 //! - [`PdfDocumentParser`]: Implements parsing and text extraction
 //! - [`PdfDocumentRenderer`]: Implements page rendering and thumbnails
+//! - [`chapters`]: Synthesizes a TOC for PDFs without an outline
+//! - [`page_labels`]: Reads printed page labels ("xiv", "A-3")
+//! - [`fonts`]: Lists the fonts pages use and whether they are embedded
 //!
 //! Both use [`SafeDocument`] from the mupdf module for thread-safe access.
 
+pub mod chapters;
+pub mod fonts;
+pub mod page_labels;
 mod parser;
 mod renderer;
 
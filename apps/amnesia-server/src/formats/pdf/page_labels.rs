@@ -0,0 +1,268 @@
+//! PDF page labels
+//!
+//! Printed page numbers often differ from page indices: front matter is
+//! numbered "i", "ii", ..., appendices "A-1", "A-2", and the body may start
+//! at 1 on the fifteenth page. A PDF records this in the catalog's
+//! `/PageLabels` number tree, as ranges that each start at a page index and
+//! set a numbering style, a prefix, and a first number (PDF 32000-1, 12.4.2).
+//!
+//! Documents without `/PageLabels` are labelled "1", "2", ... by index.
+
+use mupdf::pdf::{PdfDocument, PdfObject};
+
+/// Numbering style of a label range (`/S`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LabelStyle {
+    /// `D`: 1, 2, 3
+    Decimal,
+    /// `R`: I, II, III
+    UpperRoman,
+    /// `r`: i, ii, iii
+    LowerRoman,
+    /// `A`: A to Z, then AA to ZZ
+    UpperLetters,
+    /// `a`: a to z, then aa to zz
+    LowerLetters,
+}
+
+impl LabelStyle {
+    fn from_name(name: &[u8]) -> Option<Self> {
+        match name {
+            b"D" => Some(Self::Decimal),
+            b"R" => Some(Self::UpperRoman),
+            b"r" => Some(Self::LowerRoman),
+            b"A" => Some(Self::UpperLetters),
+            b"a" => Some(Self::LowerLetters),
+            _ => None,
+        }
+    }
+
+    fn format(self, number: u32) -> String {
+        match self {
+            Self::Decimal => number.to_string(),
+            Self::UpperRoman => roman(number),
+            Self::LowerRoman => roman(number).to_lowercase(),
+            Self::UpperLetters => letters(number),
+            Self::LowerLetters => letters(number).to_lowercase(),
+        }
+    }
+}
+
+/// Labels for the pages from `start` up to the next range
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageLabelRange {
+    /// Zero-based index of the range's first page
+    pub start: usize,
+    /// Numbering style; `None` labels pages with the prefix alone
+    pub style: Option<LabelStyle>,
+    pub prefix: String,
+    /// Number of the range's first page (`/St`, default 1)
+    pub first: u32,
+}
+
+/// Label of every page, or `None` if the document defines no labels
+pub fn page_labels(pdf: &PdfDocument, page_count: usize) -> Option<Vec<String>> {
+    let ranges = read_ranges(pdf).unwrap_or_else(|e| {
+        tracing::debug!("Ignoring unreadable page labels: {}", e);
+        Vec::new()
+    });
+    expand_page_labels(&ranges, page_count)
+}
+
+/// Label of every page from a document's label ranges
+pub fn expand_page_labels(ranges: &[PageLabelRange], page_count: usize) -> Option<Vec<String>> {
+    if ranges.is_empty() {
+        return None;
+    }
+
+    let mut ranges = ranges.to_vec();
+    ranges.sort_by_key(|range| range.start);
+
+    let labels = (0..page_count)
+        .map(|page| {
+            // Pages before the first range are labelled by index
+            match ranges.iter().rev().find(|range| range.start <= page) {
+                Some(range) => {
+                    let number = range.first.saturating_add((page - range.start) as u32);
+                    let numeral = range.style.map(|style| style.format(number));
+                    format!("{}{}", range.prefix, numeral.unwrap_or_default())
+                }
+                None => (page + 1).to_string(),
+            }
+        })
+        .collect();
+    Some(labels)
+}
+
+/// Read the `/PageLabels` number tree from the document catalog
+fn read_ranges(pdf: &PdfDocument) -> Result<Vec<PageLabelRange>, mupdf::Error> {
+    let mut ranges = Vec::new();
+    let Some(root) = pdf.trailer()?.get_dict("Root")? else {
+        return Ok(ranges);
+    };
+    if let Some(tree) = root.get_dict("PageLabels")? {
+        collect_ranges(&tree, &mut ranges, 0)?;
+    }
+    Ok(ranges)
+}
+
+/// Deepest number tree to follow through `/Kids`
+const MAX_TREE_DEPTH: usize = 16;
+
+fn collect_ranges(
+    node: &PdfObject,
+    ranges: &mut Vec<PageLabelRange>,
+    depth: usize,
+) -> Result<(), mupdf::Error> {
+    if depth > MAX_TREE_DEPTH {
+        return Ok(());
+    }
+
+    // Leaf: /Nums [index1 dict1 index2 dict2 ...]
+    if let Some(nums) = node.get_dict("Nums")? {
+        let len = nums.len().unwrap_or(0) as i32;
+        for i in (0..len.saturating_sub(1)).step_by(2) {
+            let (Some(index), Some(dict)) = (nums.get_array(i)?, nums.get_array(i + 1)?) else {
+                continue;
+            };
+            let Ok(start) = index.as_int() else {
+                continue;
+            };
+
+            let style = match dict.get_dict("S")? {
+                Some(s) => s.as_name().ok().and_then(LabelStyle::from_name),
+                None => None,
+            };
+            let prefix = match dict.get_dict("P")? {
+                Some(p) => p.as_string().unwrap_or_default().to_string(),
+                None => String::new(),
+            };
+            let first = match dict.get_dict("St")? {
+                Some(st) => st.as_int().unwrap_or(1),
+                None => 1,
+            };
+            ranges.push(PageLabelRange {
+                start: start.max(0) as usize,
+                style,
+                prefix,
+                first: first.max(1) as u32,
+            });
+        }
+    }
+
+    // Intermediate node: /Kids [node node ...]
+    if let Some(kids) = node.get_dict("Kids")? {
+        for i in 0..kids.len().unwrap_or(0) as i32 {
+            if let Some(kid) = kids.get_array(i)? {
+                collect_ranges(&kid, ranges, depth + 1)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Uppercase Roman numeral (numbers past 3999 repeat "M")
+fn roman(mut number: u32) -> String {
+    const NUMERALS: &[(u32, &str)] = &[
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+
+    let mut out = String::new();
+    for &(value, numeral) in NUMERALS {
+        while number >= value {
+            out.push_str(numeral);
+            number -= value;
+        }
+    }
+    out
+}
+
+/// Uppercase letter label: A to Z, then AA to ZZ, AAA to ZZZ, ...
+fn letters(number: u32) -> String {
+    if number == 0 {
+        return String::new();
+    }
+    let letter = char::from(b'A' + ((number - 1) % 26) as u8);
+    letter.to_string().repeat(((number - 1) / 26 + 1) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_numerals() {
+        assert_eq!(roman(1), "I");
+        assert_eq!(roman(4), "IV");
+        assert_eq!(roman(14), "XIV");
+        assert_eq!(roman(1994), "MCMXCIV");
+        assert_eq!(letters(1), "A");
+        assert_eq!(letters(26), "Z");
+        assert_eq!(letters(27), "AA");
+        assert_eq!(letters(53), "AAA");
+    }
+
+    #[test]
+    fn test_expand_page_labels() {
+        let ranges = vec![
+            PageLabelRange {
+                start: 8,
+                style: Some(LabelStyle::Decimal),
+                prefix: String::new(),
+                first: 1,
+            },
+            PageLabelRange {
+                start: 0,
+                style: Some(LabelStyle::LowerRoman),
+                prefix: String::new(),
+                first: 1,
+            },
+            PageLabelRange {
+                start: 10,
+                style: Some(LabelStyle::Decimal),
+                prefix: "A-".to_string(),
+                first: 3,
+            },
+            PageLabelRange {
+                start: 12,
+                style: None,
+                prefix: "Cover".to_string(),
+                first: 1,
+            },
+        ];
+
+        let labels = expand_page_labels(&ranges, 13).unwrap();
+        assert_eq!(&labels[..3], ["i", "ii", "iii"]);
+        assert_eq!(labels[7], "viii");
+        assert_eq!(&labels[8..10], ["1", "2"]);
+        assert_eq!(&labels[10..12], ["A-3", "A-4"]);
+        assert_eq!(labels[12], "Cover");
+
+        // Pages before the first range fall back to their index
+        let late = vec![PageLabelRange {
+            start: 2,
+            style: Some(LabelStyle::UpperLetters),
+            prefix: String::new(),
+            first: 1,
+        }];
+        assert_eq!(
+            expand_page_labels(&late, 4).unwrap(),
+            vec!["1", "2", "A", "B"]
+        );
+
+        assert_eq!(expand_page_labels(&[], 4), None);
+    }
+}
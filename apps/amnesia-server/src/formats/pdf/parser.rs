@@ -9,13 +9,15 @@ use async_trait::async_trait;
 use mupdf::{MetadataName, TextPageOptions};
 
 use crate::document::{
-    BoundingBox, CharPosition, Creator, DocumentError, DocumentFormat, DocumentMetadata,
-    DocumentParser, DocumentRenderer, DocumentResult, ParsedDocument, RenderRequest, RenderResult,
-    Resource, SearchOptions, SearchResult, StructuredText, TextBlock, TextDirection, TextLine,
-    TocEntry,
+    BoundingBox, CharPosition, Creator, DocumentError, DocumentFonts, DocumentFormat,
+    DocumentMetadata, DocumentParser, DocumentRenderer, DocumentResult, ParsedDocument,
+    RenderRequest, RenderResult, Resource, SearchOptions, SearchResult, StructuredText, TextBlock,
+    TextDirection, TextLine, TocEntry,
 };
 use crate::mupdf::SafeDocument;
 
+use super::{chapters, fonts, page_labels};
+
 /// PDF implementation of DocumentParser and DocumentRenderer
 ///
 /// This is a unified handler that implements both traits, allowing
@@ -54,7 +56,14 @@ impl DocumentParser for PdfDocumentHandler {
 
         // Offload to blocking task since MuPDF operations are CPU-bound
         tokio::task::spawn_blocking(move || {
-            doc.with_doc(|mupdf_doc| {
+            // Printed page labels come from the PDF catalog; read them
+            // first since `with_doc` holds the same lock
+            let item_labels = doc
+                .with_pdf_doc(|pdf| Ok(page_labels::page_labels(pdf, doc.item_count())))
+                .ok()
+                .flatten();
+
+            let mut parsed = doc.with_doc(|mupdf_doc| {
                 // Extract metadata
                 let get_meta = |name: MetadataName| -> Option<String> {
                     mupdf_doc.metadata(name).ok().filter(|s| !s.is_empty())
@@ -96,12 +105,11 @@ impl DocumentParser for PdfDocumentHandler {
                 // Check for text layer
                 let has_text_layer = check_text_layer(mupdf_doc, doc.item_count())?;
 
-                // Generate page labels (1, 2, 3, ...)
-                let item_labels = if doc.item_count() > 0 {
-                    Some((1..=doc.item_count()).map(|n| n.to_string()).collect())
-                } else {
-                    None
-                };
+                // Without /PageLabels, label pages 1, 2, 3, ...
+                let item_labels = item_labels.or_else(|| {
+                    (doc.item_count() > 0)
+                        .then(|| (1..=doc.item_count()).map(|n| n.to_string()).collect())
+                });
 
                 Ok(ParsedDocument {
                     id: doc.id().to_string(),
@@ -111,8 +119,14 @@ impl DocumentParser for PdfDocumentHandler {
                     item_count: doc.item_count(),
                     item_labels,
                     has_text_layer,
+                    chapter_checksums: Vec::new(),
+                    diagnostics: Vec::new(),
                 })
-            })
+            })?;
+
+            // Includes what was reported while opening the document
+            parsed.diagnostics = doc.warnings();
+            Ok(parsed)
         })
         .await
         .map_err(|e| DocumentError::ParseError(format!("Task join error: {}", e)))?
@@ -245,6 +259,20 @@ impl DocumentParser for PdfDocumentHandler {
             Ok((bounds.x1 - bounds.x0, bounds.y1 - bounds.y0))
         })
     }
+
+    async fn fonts(&self) -> DocumentResult<DocumentFonts> {
+        let doc = self.doc.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let fonts = doc.with_pdf_doc(|pdf| Ok(fonts::document_fonts(pdf, doc.item_count())))?;
+            Ok(DocumentFonts {
+                fonts,
+                substitutions: doc.substitutions(),
+            })
+        })
+        .await
+        .map_err(|e| DocumentError::ParseError(format!("Task join error: {}", e)))?
+    }
 }
 
 impl PdfDocumentHandler {
@@ -260,6 +288,9 @@ impl PdfDocumentHandler {
 
 fn extract_toc(doc: &mupdf::Document) -> DocumentResult<Vec<TocEntry>> {
     let outlines = doc.outlines()?;
+    if outlines.is_empty() {
+        return chapters::detect_toc(doc);
+    }
     Ok(convert_outlines_to_toc(&outlines))
 }
 
@@ -7,6 +7,7 @@ use std::io::Cursor;
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use image::codecs::jpeg::JpegEncoder;
 use image::DynamicImage;
 use mupdf::{Colorspace, Matrix};
 
@@ -14,7 +15,7 @@ use crate::document::{
     DocumentError, DocumentRenderer, DocumentResult, ImageFormat, RenderRequest, RenderResult,
     Resource,
 };
-use crate::mupdf::SafeDocument;
+use crate::mupdf::{capture_warnings, SafeDocument};
 
 use super::PdfDocumentHandler;
 
@@ -30,32 +31,37 @@ impl DocumentRenderer for PdfDocumentHandler {
         let scale = request.scale.clamp(0.1, 4.0);
         let rotation = request.rotation;
         let format = request.format;
+        let quality = request.quality;
 
         tokio::task::spawn_blocking(move || {
-            doc.with_doc(|mupdf_doc| {
-                let page = mupdf_doc.load_page(item_index as i32)?;
-
-                // Build transformation matrix with scale and rotation
-                let mut matrix = Matrix::new_scale(scale, scale);
-                if rotation != 0 {
-                    let rotation_matrix = Matrix::new_rotate(rotation as f32);
-                    matrix.concat(rotation_matrix);
-                }
-
-                // Render to pixmap
-                let colorspace = Colorspace::device_rgb();
-                let pixmap = page.to_pixmap(&matrix, &colorspace, true, true)?;
-
-                // Encode to requested format
-                let (data, width, height) = encode_pixmap(&pixmap, format)?;
-
-                Ok(RenderResult {
-                    data,
-                    format,
-                    width,
-                    height,
+            let (result, warnings) = capture_warnings(|| {
+                doc.with_doc(|mupdf_doc| {
+                    let page = mupdf_doc.load_page(item_index as i32)?;
+
+                    // Build transformation matrix with scale and rotation
+                    let mut matrix = Matrix::new_scale(scale, scale);
+                    if rotation != 0 {
+                        let rotation_matrix = Matrix::new_rotate(rotation as f32);
+                        matrix.concat(rotation_matrix);
+                    }
+
+                    // Render to pixmap
+                    let colorspace = Colorspace::device_rgb();
+                    let pixmap = page.to_pixmap(&matrix, &colorspace, true, true)?;
+
+                    // Encode to requested format
+                    let (data, width, height) = encode_pixmap(&pixmap, format, quality)?;
+
+                    Ok(RenderResult {
+                        data,
+                        format,
+                        width,
+                        height,
+                        warnings: Vec::new(),
+                    })
                 })
-            })
+            });
+            Ok(result?.with_warnings(warnings))
         })
         .await
         .map_err(|e| DocumentError::RenderError(format!("Task join error: {}", e)))?
@@ -73,29 +79,34 @@ impl DocumentRenderer for PdfDocumentHandler {
         let doc = self.doc.clone();
 
         tokio::task::spawn_blocking(move || {
-            doc.with_doc(|mupdf_doc| {
-                let page = mupdf_doc.load_page(item_index as i32)?;
-                let bounds = page.bounds()?;
-
-                // Calculate scale to fit within max_size
-                let width = bounds.x1 - bounds.x0;
-                let height = bounds.y1 - bounds.y0;
-                let scale = (max_size as f32) / width.max(height);
-
-                let matrix = Matrix::new_scale(scale, scale);
-                let colorspace = Colorspace::device_rgb();
-                let pixmap = page.to_pixmap(&matrix, &colorspace, true, false)?;
-
-                // JPEG for smaller thumbnails
-                let (data, out_width, out_height) = encode_pixmap(&pixmap, ImageFormat::Jpeg)?;
-
-                Ok(RenderResult {
-                    data,
-                    format: ImageFormat::Jpeg,
-                    width: out_width,
-                    height: out_height,
+            let (result, warnings) = capture_warnings(|| {
+                doc.with_doc(|mupdf_doc| {
+                    let page = mupdf_doc.load_page(item_index as i32)?;
+                    let bounds = page.bounds()?;
+
+                    // Calculate scale to fit within max_size
+                    let width = bounds.x1 - bounds.x0;
+                    let height = bounds.y1 - bounds.y0;
+                    let scale = (max_size as f32) / width.max(height);
+
+                    let matrix = Matrix::new_scale(scale, scale);
+                    let colorspace = Colorspace::device_rgb();
+                    let pixmap = page.to_pixmap(&matrix, &colorspace, true, false)?;
+
+                    // JPEG for smaller thumbnails
+                    let (data, out_width, out_height) =
+                        encode_pixmap(&pixmap, ImageFormat::Jpeg, None)?;
+
+                    Ok(RenderResult {
+                        data,
+                        format: ImageFormat::Jpeg,
+                        width: out_width,
+                        height: out_height,
+                        warnings: Vec::new(),
+                    })
                 })
-            })
+            });
+            Ok(result?.with_warnings(warnings))
         })
         .await
         .map_err(|e| DocumentError::RenderError(format!("Task join error: {}", e)))?
@@ -141,28 +152,33 @@ impl DocumentRenderer for PdfDocumentRenderer {
         let scale = request.scale.clamp(0.1, 4.0);
         let rotation = request.rotation;
         let format = request.format;
+        let quality = request.quality;
 
         tokio::task::spawn_blocking(move || {
-            doc.with_doc(|mupdf_doc| {
-                let page = mupdf_doc.load_page(item_index as i32)?;
-
-                let mut matrix = Matrix::new_scale(scale, scale);
-                if rotation != 0 {
-                    let rotation_matrix = Matrix::new_rotate(rotation as f32);
-                    matrix.concat(rotation_matrix);
-                }
-
-                let colorspace = Colorspace::device_rgb();
-                let pixmap = page.to_pixmap(&matrix, &colorspace, true, true)?;
-                let (data, width, height) = encode_pixmap(&pixmap, format)?;
-
-                Ok(RenderResult {
-                    data,
-                    format,
-                    width,
-                    height,
+            let (result, warnings) = capture_warnings(|| {
+                doc.with_doc(|mupdf_doc| {
+                    let page = mupdf_doc.load_page(item_index as i32)?;
+
+                    let mut matrix = Matrix::new_scale(scale, scale);
+                    if rotation != 0 {
+                        let rotation_matrix = Matrix::new_rotate(rotation as f32);
+                        matrix.concat(rotation_matrix);
+                    }
+
+                    let colorspace = Colorspace::device_rgb();
+                    let pixmap = page.to_pixmap(&matrix, &colorspace, true, true)?;
+                    let (data, width, height) = encode_pixmap(&pixmap, format, quality)?;
+
+                    Ok(RenderResult {
+                        data,
+                        format,
+                        width,
+                        height,
+                        warnings: Vec::new(),
+                    })
                 })
-            })
+            });
+            Ok(result?.with_warnings(warnings))
         })
         .await
         .map_err(|e| DocumentError::RenderError(format!("Task join error: {}", e)))?
@@ -180,26 +196,31 @@ impl DocumentRenderer for PdfDocumentRenderer {
         let doc = self.doc.clone();
 
         tokio::task::spawn_blocking(move || {
-            doc.with_doc(|mupdf_doc| {
-                let page = mupdf_doc.load_page(item_index as i32)?;
-                let bounds = page.bounds()?;
-
-                let width = bounds.x1 - bounds.x0;
-                let height = bounds.y1 - bounds.y0;
-                let scale = (max_size as f32) / width.max(height);
-
-                let matrix = Matrix::new_scale(scale, scale);
-                let colorspace = Colorspace::device_rgb();
-                let pixmap = page.to_pixmap(&matrix, &colorspace, true, false)?;
-                let (data, out_width, out_height) = encode_pixmap(&pixmap, ImageFormat::Jpeg)?;
-
-                Ok(RenderResult {
-                    data,
-                    format: ImageFormat::Jpeg,
-                    width: out_width,
-                    height: out_height,
+            let (result, warnings) = capture_warnings(|| {
+                doc.with_doc(|mupdf_doc| {
+                    let page = mupdf_doc.load_page(item_index as i32)?;
+                    let bounds = page.bounds()?;
+
+                    let width = bounds.x1 - bounds.x0;
+                    let height = bounds.y1 - bounds.y0;
+                    let scale = (max_size as f32) / width.max(height);
+
+                    let matrix = Matrix::new_scale(scale, scale);
+                    let colorspace = Colorspace::device_rgb();
+                    let pixmap = page.to_pixmap(&matrix, &colorspace, true, false)?;
+                    let (data, out_width, out_height) =
+                        encode_pixmap(&pixmap, ImageFormat::Jpeg, None)?;
+
+                    Ok(RenderResult {
+                        data,
+                        format: ImageFormat::Jpeg,
+                        width: out_width,
+                        height: out_height,
+                        warnings: Vec::new(),
+                    })
                 })
-            })
+            });
+            Ok(result?.with_warnings(warnings))
         })
         .await
         .map_err(|e| DocumentError::RenderError(format!("Task join error: {}", e)))?
@@ -217,6 +238,7 @@ impl DocumentRenderer for PdfDocumentRenderer {
 fn encode_pixmap(
     pixmap: &mupdf::Pixmap,
     format: ImageFormat,
+    quality: Option<u8>,
 ) -> DocumentResult<(Vec<u8>, u32, u32)> {
     let width = pixmap.width() as u32;
     let height = pixmap.height() as u32;
@@ -255,11 +277,19 @@ fn encode_pixmap(
                 .write_to(&mut Cursor::new(&mut output), image::ImageFormat::Png)
                 .map_err(|e| DocumentError::ImageError(e.to_string()))?;
         }
-        ImageFormat::Jpeg => {
-            dynamic_img
-                .write_to(&mut Cursor::new(&mut output), image::ImageFormat::Jpeg)
-                .map_err(|e| DocumentError::ImageError(e.to_string()))?;
-        }
+        ImageFormat::Jpeg => match quality {
+            Some(quality) => {
+                dynamic_img
+                    .to_rgb8()
+                    .write_with_encoder(JpegEncoder::new_with_quality(&mut output, quality))
+                    .map_err(|e| DocumentError::ImageError(e.to_string()))?;
+            }
+            None => {
+                dynamic_img
+                    .write_to(&mut Cursor::new(&mut output), image::ImageFormat::Jpeg)
+                    .map_err(|e| DocumentError::ImageError(e.to_string()))?;
+            }
+        },
         ImageFormat::Webp => {
             dynamic_img
                 .write_to(&mut Cursor::new(&mut output), image::ImageFormat::WebP)
@@ -4,11 +4,14 @@
 //! - Highlight span injection
 //! - HTML sanitization
 //! - URL rewriting
+//! - Print-ready documents for a chapter or page
 //!
 //! Uses lol_html for efficient streaming HTML processing.
 
 mod highlight_injector;
+mod print;
 
 pub use highlight_injector::{
     inject_highlights, rewrite_urls, sanitize_html, HighlightConfig, InjectError, InjectionResult,
 };
+pub use print::{print_chapter, print_page, PrintLabels};
@@ -0,0 +1,215 @@
+//! Print-ready HTML for one chapter or page
+//!
+//! `GET /api/v1/documents/:id/items/:index/print` wraps an EPUB chapter, or
+//! a high-resolution render of a PDF page, in a standalone document whose
+//! `@page` rules put the book's title and the chapter in the running header
+//! and a page label in the footer. Printing it from a browser gives clean
+//! pages without the reader's interface around them.
+//!
+//! Running headers use CSS margin boxes (`@top-left`, ...), which browsers
+//! without paged-media support ignore; the content still prints.
+
+use epub_core::path::{parent_dir, percent_encode_path, resolve_href};
+use epub_core::ChapterStyle;
+
+/// Rules appended after the book's own styles
+const PRINT_CSS: &str = "\
+img, svg, figure, table, pre { break-inside: avoid; }
+img, svg { max-width: 100%; height: auto; }
+h1, h2, h3, h4, h5, h6 { break-after: avoid; }
+p { orphans: 3; widows: 3; }
+";
+
+/// Text of the running header and footer
+#[derive(Debug, Clone, Default)]
+pub struct PrintLabels {
+    /// Book title, top left
+    pub title: String,
+    /// Chapter title, top right
+    pub section: Option<String>,
+    /// Page label for the footer; by default pages are numbered as printed
+    pub page: Option<String>,
+}
+
+/// A chapter as a print-ready document
+///
+/// `chapter_path` is the chapter's archive path; its images, stylesheets
+/// and links are pointed at `{resource_base}{path}` (the resources
+/// endpoint). Scripts are removed.
+pub fn print_chapter(
+    markup: &str,
+    chapter_path: &str,
+    resource_base: &str,
+    labels: &PrintLabels,
+) -> String {
+    let resource_url = |path: &str| format!("{}{}", resource_base, percent_encode_path(path));
+    let chapter_dir = parent_dir(chapter_path);
+    let parts = epub_core::split_chapter(&epub_core::sanitize_html(markup));
+
+    let mut head = String::new();
+    for style in &parts.styles {
+        match style {
+            ChapterStyle::Link(href) => {
+                let url = resource_url(&resolve_href(chapter_dir, href));
+                head.push_str(&format!(
+                    "<link rel=\"stylesheet\" href=\"{}\"/>\n",
+                    escape(&url)
+                ));
+            }
+            ChapterStyle::Inline(css) => {
+                let css = epub_core::rewrite_css_urls(css, chapter_dir, resource_url);
+                head.push_str(&format!("<style>\n{}\n</style>\n", css.trim()));
+            }
+        }
+    }
+    head.push_str(&format!(
+        "<style>\n{}\n{}</style>\n",
+        page_rule(labels, "margin: 20mm 18mm;"),
+        PRINT_CSS
+    ));
+
+    let body = epub_core::rewrite_urls(&parts.body, chapter_dir, resource_url);
+    let body_tag = match &parts.body_class {
+        Some(class) => format!("<body class=\"{}\">", class),
+        None => "<body>".to_string(),
+    };
+    let body = format!("{}\n{}\n</body>", body_tag, body.trim());
+    document(labels, &head, &body)
+}
+
+/// A rendered page as a print-ready document
+///
+/// The image at `image_url` is fitted to the printed page, which is turned
+/// to landscape for pages wider than they are tall.
+pub fn print_page(image_url: &str, width: f32, height: f32, labels: &PrintLabels) -> String {
+    let orientation = if width > height {
+        "landscape"
+    } else {
+        "portrait"
+    };
+    let head = format!(
+        "<style>\n{}\nhtml, body {{ margin: 0; height: 100%; }}\n\
+         img {{ display: block; width: 100%; height: 100%; object-fit: contain; }}\n</style>\n",
+        page_rule(labels, &format!("size: {}; margin: 12mm;", orientation)),
+    );
+    let body = format!(
+        "<body>\n<img src=\"{}\" alt=\"{}\"/>\n</body>",
+        escape(image_url),
+        escape(labels.page.as_deref().unwrap_or_default())
+    );
+    document(labels, &head, &body)
+}
+
+/// The `@page` rule with the running header and footer
+fn page_rule(labels: &PrintLabels, page_css: &str) -> String {
+    let margin_box = |position: &str, content: &str| {
+        format!(
+            "  @{} {{ content: {}; font: 9pt sans-serif; color: #555; }}\n",
+            position, content
+        )
+    };
+    let mut rule = format!("@page {{\n  {}\n", page_css);
+    rule.push_str(&margin_box("top-left", &css_string(&labels.title)));
+    if let Some(section) = &labels.section {
+        rule.push_str(&margin_box("top-right", &css_string(section)));
+    }
+    let page = match &labels.page {
+        Some(page) => css_string(page),
+        None => "counter(page)".to_string(),
+    };
+    rule.push_str(&margin_box("bottom-center", &page));
+    rule.push('}');
+    rule
+}
+
+fn document(labels: &PrintLabels, head: &str, body: &str) -> String {
+    let title = match &labels.section {
+        Some(section) => format!("{} - {}", labels.title, section),
+        None => labels.title.clone(),
+    };
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\"/>\n<title>{}</title>\n{}</head>\n{}\n</html>\n",
+        escape(&title),
+        head,
+        body
+    )
+}
+
+/// A CSS string literal; `<` is escaped so it cannot close the `<style>`
+fn css_string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '<' => out.push_str("\\3C "),
+            '\n' | '\r' => out.push(' '),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Escape text for an HTML attribute or element
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_print_chapter() {
+        let markup = r#"<?xml version="1.0"?><html><head><title>x</title>
+<link rel="stylesheet" href="../Styles/book.css"/><script>track()</script></head>
+<body class="chapter"><h1>One</h1><p><img src="../Images/map one.png" alt=""/></p></body></html>"#;
+        let labels = PrintLabels {
+            title: "Ships & \"Sails\"".to_string(),
+            section: Some("Chapter One".to_string()),
+            page: None,
+        };
+
+        let html = print_chapter(
+            markup,
+            "OEBPS/Text/ch1.xhtml",
+            "/api/v1/documents/b1/resources/",
+            &labels,
+        );
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>Ships &amp; &quot;Sails&quot; - Chapter One</title>"));
+        assert!(html.contains(
+            r#"<link rel="stylesheet" href="/api/v1/documents/b1/resources/OEBPS/Styles/book.css"/>"#
+        ));
+        assert!(html.contains(r#"@top-left { content: "Ships & \"Sails\"";"#));
+        assert!(html.contains(r#"@top-right { content: "Chapter One";"#));
+        assert!(html.contains("@bottom-center { content: counter(page);"));
+        assert!(html.contains(
+            r#"<body class="chapter">
+<h1>One</h1><p><img src="/api/v1/documents/b1/resources/OEBPS/Images/map%20one.png" alt=""/></p>
+</body>"#
+        ));
+        assert!(!html.contains("track()"));
+    }
+
+    #[test]
+    fn test_print_page() {
+        let labels = PrintLabels {
+            title: "Atlas".to_string(),
+            section: None,
+            page: Some("xiv".to_string()),
+        };
+        let html = print_page("/render?scale=4", 842.0, 595.0, &labels);
+        assert!(html.contains("size: landscape;"));
+        assert!(html.contains(r#"@bottom-center { content: "xiv";"#));
+        assert!(!html.contains("@top-right"));
+        assert!(html.contains(r#"<img src="/render?scale=4" alt="xiv"/>"#));
+        assert_eq!(css_string("a</style>"), "\"a\\3C /style>\"");
+    }
+}
@@ -0,0 +1,208 @@
+//! Calibre-Web `app.db` reader
+//!
+//! Calibre-Web keeps its own data in `app.db` next to Calibre's
+//! `metadata.db`; book ids there are Calibre ids, which match the `(id)`
+//! suffix of the library's folder names. Read status 1 is finished and 2 is
+//! in progress; positions only exist for books synced through Kobo.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::SqlitePool;
+
+use super::{ImportPlan, ImportedCollection, ImportedProgress, SourceBook};
+
+const SOURCE: &str = "calibre-web";
+
+const STATUS_FINISHED: i64 = 1;
+
+/// Read users, progress, and shelves from a Calibre-Web `app.db`
+pub async fn read_calibre_web(app_db: &Path) -> Result<ImportPlan> {
+    let options = SqliteConnectOptions::new().filename(app_db).read_only(true);
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(options)
+        .await
+        .with_context(|| format!("Failed to open {}", app_db.display()))?;
+
+    let plan = read_plan(&pool).await;
+    pool.close().await;
+    plan
+}
+
+async fn read_plan(pool: &SqlitePool) -> Result<ImportPlan> {
+    // The anonymous browsing account has no reading state worth keeping
+    let users: HashMap<i64, String> =
+        sqlx::query_as::<_, (i64, String)>(r#"SELECT id, name FROM "user" WHERE name != 'Guest'"#)
+            .fetch_all(pool)
+            .await
+            .context("Not a Calibre-Web database (no user table)")?
+            .into_iter()
+            .collect();
+
+    // (user, book) -> percent, where None is in progress without a position
+    let mut progress: BTreeMap<(i64, i64), Option<f64>> = BTreeMap::new();
+
+    let read_links: Vec<(i64, i64, i64)> = sqlx::query_as(
+        "SELECT user_id, book_id, read_status FROM book_read_link WHERE read_status != 0",
+    )
+    .fetch_all(pool)
+    .await?;
+    for (user_id, book_id, status) in read_links {
+        let percent = (status == STATUS_FINISHED).then_some(100.0);
+        progress.insert((user_id, book_id), percent);
+    }
+
+    if has_table(pool, "kobo_bookmark").await? {
+        let bookmarks: Vec<(i64, i64, f64)> = sqlx::query_as(
+            r#"
+            SELECT s.user_id, s.book_id, b.progress_percent
+            FROM kobo_reading_state s
+            JOIN kobo_bookmark b ON b.kobo_reading_state_id = s.id
+            WHERE b.progress_percent IS NOT NULL
+            "#,
+        )
+        .fetch_all(pool)
+        .await?;
+        for (user_id, book_id, percent) in bookmarks {
+            let entry = progress.entry((user_id, book_id)).or_insert(None);
+            // A finished read status wins over a stale bookmark
+            if entry.is_none() {
+                *entry = Some(percent);
+            }
+        }
+    }
+
+    let mut plan = ImportPlan {
+        source: SOURCE.to_string(),
+        ..Default::default()
+    };
+
+    let mut without_position = 0;
+    for ((user_id, book_id), percent) in progress {
+        let Some(user) = users.get(&user_id) else {
+            continue;
+        };
+        let Some(percent) = percent else {
+            without_position += 1;
+            continue;
+        };
+        plan.progress.push(ImportedProgress {
+            user_id: user.clone(),
+            book: calibre_book(book_id),
+            percent,
+        });
+    }
+    if without_position > 0 {
+        plan.notes.push(format!(
+            "{} in-progress books have no position (only Kobo-synced books do) and were not imported",
+            without_position
+        ));
+    }
+
+    let shelves: Vec<(i64, String, i64, bool)> =
+        sqlx::query_as("SELECT id, name, user_id, is_public FROM shelf ORDER BY id")
+            .fetch_all(pool)
+            .await?;
+    let links: Vec<(i64, i64)> =
+        sqlx::query_as(r#"SELECT shelf, book_id FROM book_shelf_link ORDER BY shelf, "order""#)
+            .fetch_all(pool)
+            .await?;
+
+    for (shelf_id, name, owner, is_public) in shelves {
+        let Some(user) = users.get(&owner) else {
+            continue;
+        };
+        plan.collections.push(ImportedCollection {
+            name,
+            // Public shelves are visible to everyone
+            user_id: (!is_public).then(|| user.clone()),
+            books: links
+                .iter()
+                .filter(|(shelf, _)| *shelf == shelf_id)
+                .map(|(_, book_id)| calibre_book(*book_id))
+                .collect(),
+        });
+    }
+
+    let mut names: Vec<String> = users.into_values().collect();
+    names.sort();
+    plan.users = names;
+
+    Ok(plan)
+}
+
+fn calibre_book(id: i64) -> SourceBook {
+    SourceBook {
+        label: format!("calibre:{}", id),
+        calibre_id: Some(id),
+        ..Default::default()
+    }
+}
+
+async fn has_table(pool: &SqlitePool, name: &str) -> Result<bool> {
+    let found: Option<(String,)> =
+        sqlx::query_as("SELECT name FROM sqlite_master WHERE type = 'table' AND name = ?")
+            .bind(name)
+            .fetch_optional(pool)
+            .await?;
+    Ok(found.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The parts of Calibre-Web's schema the importer reads
+    const APP_DB: &str = r#"
+        CREATE TABLE user (id INTEGER PRIMARY KEY, name TEXT, email TEXT);
+        CREATE TABLE book_read_link (id INTEGER PRIMARY KEY, book_id INTEGER, user_id INTEGER,
+            read_status INTEGER, last_modified DATETIME);
+        CREATE TABLE kobo_reading_state (id INTEGER PRIMARY KEY, user_id INTEGER, book_id INTEGER);
+        CREATE TABLE kobo_bookmark (id INTEGER PRIMARY KEY, kobo_reading_state_id INTEGER,
+            progress_percent FLOAT);
+        CREATE TABLE shelf (id INTEGER PRIMARY KEY, uuid TEXT, name TEXT, is_public INTEGER,
+            user_id INTEGER);
+        CREATE TABLE book_shelf_link (id INTEGER PRIMARY KEY, book_id INTEGER, "order" INTEGER,
+            shelf INTEGER);
+
+        INSERT INTO user VALUES (1, 'admin', 'admin@example.com'), (2, 'Guest', '');
+        INSERT INTO book_read_link (book_id, user_id, read_status) VALUES
+            (10, 1, 1), (11, 1, 2), (12, 1, 2), (13, 1, 0), (10, 2, 1);
+        INSERT INTO kobo_reading_state VALUES (1, 1, 11), (2, 1, 10);
+        INSERT INTO kobo_bookmark VALUES (1, 1, 37.5), (2, 2, 80.0);
+        INSERT INTO shelf VALUES (1, 'u1', 'Favorites', 0, 1), (2, 'u2', 'Club', 1, 1);
+        INSERT INTO book_shelf_link (book_id, "order", shelf) VALUES (12, 2, 1), (10, 1, 1), (11, 1, 2);
+    "#;
+
+    #[tokio::test]
+    async fn test_read_calibre_web() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.db");
+        let options = SqliteConnectOptions::new()
+            .filename(&path)
+            .create_if_missing(true);
+        let pool = SqlitePool::connect_with(options).await.unwrap();
+        sqlx::query(APP_DB).execute(&pool).await.unwrap();
+        pool.close().await;
+
+        let plan = read_calibre_web(&path).await.unwrap();
+        assert_eq!(plan.users, vec!["admin"]);
+
+        let progress: Vec<(Option<i64>, f64)> = plan
+            .progress
+            .iter()
+            .map(|p| (p.book.calibre_id, p.percent))
+            .collect();
+        assert_eq!(progress, vec![(Some(10), 100.0), (Some(11), 37.5)]);
+        assert_eq!(plan.notes.len(), 1);
+
+        let favorites = &plan.collections[0];
+        assert_eq!(favorites.user_id.as_deref(), Some("admin"));
+        let ids: Vec<Option<i64>> = favorites.books.iter().map(|b| b.calibre_id).collect();
+        assert_eq!(ids, vec![Some(10), Some(12)]);
+        assert_eq!(plan.collections[1].user_id, None);
+    }
+}
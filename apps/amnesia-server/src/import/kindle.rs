@@ -0,0 +1,512 @@
+//! Kindle `My Clippings.txt` reader
+//!
+//! Kindles append every highlight, note, and bookmark to one text file,
+//! each entry ending with a line of `=`:
+//!
+//! ```text
+//! Dune (Herbert, Frank)
+//! - Your Highlight on page 12 | Location 180-182 | Added on Monday, March 4, 2019 10:21:33 PM
+//!
+//! Fear is the mind-killer.
+//! ==========
+//! ```
+//!
+//! Clippings name books only by title and author, so they are matched with
+//! [`LibraryIndex::resolve_fuzzy`]. Highlights become highlight annotations
+//! anchored by a TextQuote selector (a PdfTextQuote selector on the Kindle
+//! page for PDFs), dated when they were made. Kindle keeps a note as its
+//! own clipping at the location where the highlight ends; such notes are
+//! attached to that highlight, which becomes a note annotation. Bookmarks
+//! and notes without a highlight have no text to anchor and are skipped.
+//!
+//! Extending a highlight on a Kindle adds a new clipping without removing
+//! the old one, so a highlight contained in an overlapping one is dropped.
+//! Only English headers are understood, and timestamps, which have no time
+//! zone, are read as UTC.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use utoipa::ToSchema;
+
+use super::LibraryIndex;
+use crate::annotations::{Annotation, AnnotationQuery, AnnotationRepository, AnnotationTarget};
+use crate::db::DocumentFormat;
+
+const SEPARATOR: &str = "==========";
+
+/// Header timestamp formats, as written by different firmware versions
+const DATE_FORMATS: &[&str] = &[
+    "%A, %B %d, %Y %I:%M:%S %p",
+    "%A, %B %d, %Y, %I:%M %p",
+    "%A, %d %B %Y %H:%M:%S",
+];
+
+/// What a clipping records
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClippingKind {
+    Highlight,
+    Note,
+    Bookmark,
+}
+
+/// One entry of a clippings file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clipping {
+    pub title: String,
+    pub author: Option<String>,
+    pub kind: ClippingKind,
+    /// Printed page number, when the book has them
+    pub page: Option<usize>,
+    /// First and last Kindle location
+    pub location: Option<(u32, u32)>,
+    pub added_at: Option<DateTime<Utc>>,
+    /// Highlighted text or note; empty for bookmarks
+    pub text: String,
+}
+
+impl Clipping {
+    /// "Title (Author)", as the clippings file names the book
+    fn label(&self) -> String {
+        match &self.author {
+            Some(author) => format!("{} ({})", self.title, author),
+            None => self.title.clone(),
+        }
+    }
+
+    /// Whether this highlight is an earlier, shorter copy of `other`
+    fn superseded_by(&self, other: &Clipping, other_is_later: bool) -> bool {
+        let overlaps = match (self.location, other.location) {
+            (Some((start, end)), Some((other_start, other_end))) => {
+                start <= other_end && other_start <= end
+            }
+            _ => true,
+        };
+        overlaps
+            && other.text.contains(&self.text)
+            && (other.text.len() > self.text.len() || other_is_later)
+    }
+}
+
+/// Outcome of [`import_clippings`]
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ClippingsReport {
+    pub dry_run: bool,
+    /// Entries read from the file
+    pub clippings: usize,
+    /// Annotations written (or that would be written)
+    pub imported: usize,
+    /// Notes attached to imported highlights
+    pub notes: usize,
+    /// Highlights the user already had
+    pub existing: usize,
+    /// Bookmarks, notes without a highlight, and superseded highlights
+    pub skipped: usize,
+    /// Library books the clippings were matched to
+    pub books: usize,
+    /// Books, as "Title (Author)", with no match in the library
+    pub unmatched: Vec<String>,
+}
+
+/// Parse a `My Clippings.txt` file, skipping entries it cannot read
+pub fn parse_clippings(text: &str) -> Vec<Clipping> {
+    text.trim_start_matches('\u{feff}')
+        .split(SEPARATOR)
+        .filter_map(parse_entry)
+        .collect()
+}
+
+fn parse_entry(entry: &str) -> Option<Clipping> {
+    let mut lines = entry
+        .lines()
+        .map(|line| line.trim_start_matches('\u{feff}').trim_end())
+        .skip_while(|line| line.trim().is_empty());
+    let (title, author) = split_title(lines.next()?.trim());
+    let header = lines.next()?.strip_prefix("- ")?;
+    let text = lines.collect::<Vec<_>>().join("\n").trim().to_string();
+
+    let mut parts = header.split(" | ");
+    let first = parts.next()?.to_lowercase();
+    let kind = if first.contains("highlight") {
+        ClippingKind::Highlight
+    } else if first.contains("note") {
+        ClippingKind::Note
+    } else if first.contains("bookmark") {
+        ClippingKind::Bookmark
+    } else {
+        return None;
+    };
+
+    let mut clipping = Clipping {
+        title,
+        author,
+        kind,
+        page: None,
+        location: None,
+        added_at: None,
+        text,
+    };
+    for part in std::iter::once(first.as_str()).chain(parts.clone()) {
+        let lower = part.to_lowercase();
+        if let Some(page) = value_after(&lower, "page ") {
+            clipping.page = page.parse().ok();
+        }
+        if let Some(range) =
+            value_after(&lower, "location ").or_else(|| value_after(&lower, "loc. "))
+        {
+            clipping.location = parse_location(range);
+        }
+    }
+    clipping.added_at = parts
+        .find_map(|part| part.trim().strip_prefix("Added on "))
+        .and_then(parse_date);
+
+    Some(clipping)
+}
+
+/// Split "Title (Author)" at its last parenthesized group
+fn split_title(line: &str) -> (String, Option<String>) {
+    let Some(inner) = line.strip_suffix(')') else {
+        return (line.to_string(), None);
+    };
+
+    let mut depth = 0usize;
+    for (i, c) in inner.char_indices().rev() {
+        match c {
+            ')' => depth += 1,
+            '(' if depth > 0 => depth -= 1,
+            '(' => {
+                let title = inner[..i].trim();
+                if title.is_empty() {
+                    break;
+                }
+                let author = inner[i + 1..].trim();
+                return (title.to_string(), Some(author.to_string()));
+            }
+            _ => {}
+        }
+    }
+    (line.to_string(), None)
+}
+
+/// The number or range following `keyword`
+fn value_after<'a>(part: &'a str, keyword: &str) -> Option<&'a str> {
+    let start = part.find(keyword)? + keyword.len();
+    let value = &part[start..];
+    let end = value
+        .find(|c: char| !c.is_ascii_digit() && c != '-')
+        .unwrap_or(value.len());
+    Some(&value[..end]).filter(|value| !value.is_empty())
+}
+
+/// "180-182", "180", or the older shorthand "180-82"
+fn parse_location(range: &str) -> Option<(u32, u32)> {
+    let (start, end) = range.split_once('-').unwrap_or((range, range));
+    let first: u32 = start.parse().ok()?;
+    let last: u32 = if end.len() < start.len() {
+        format!("{}{}", &start[..start.len() - end.len()], end)
+            .parse()
+            .ok()?
+    } else {
+        end.parse().ok()?
+    };
+    Some((first, last.max(first)))
+}
+
+fn parse_date(text: &str) -> Option<DateTime<Utc>> {
+    DATE_FORMATS.iter().find_map(|format| {
+        NaiveDateTime::parse_from_str(text.trim(), format)
+            .ok()
+            .map(|date| date.and_utc())
+    })
+}
+
+/// Match clippings to library books and write them as annotations
+///
+/// Highlights whose text the user already has in that book are left alone,
+/// so importing a newer copy of the same file only adds what is new.
+pub async fn import_clippings(
+    clippings: &[Clipping],
+    library: &LibraryIndex,
+    pool: &SqlitePool,
+    user_id: Option<&str>,
+    dry_run: bool,
+) -> Result<ClippingsReport> {
+    let repo = AnnotationRepository::new(pool);
+    let mut report = ClippingsReport {
+        dry_run,
+        clippings: clippings.len(),
+        ..Default::default()
+    };
+
+    // Books in file order, each with its clippings
+    let mut books: Vec<(&Clipping, Vec<&Clipping>)> = Vec::new();
+    let mut positions: HashMap<(&str, Option<&str>), usize> = HashMap::new();
+    for clipping in clippings {
+        let key = (clipping.title.as_str(), clipping.author.as_deref());
+        let i = *positions.entry(key).or_insert_with(|| {
+            books.push((clipping, Vec::new()));
+            books.len() - 1
+        });
+        books[i].1.push(clipping);
+    }
+
+    for (book, book_clippings) in books {
+        let Some((book_id, format)) = library.resolve_fuzzy(&book.title, book.author.as_deref())
+        else {
+            report.unmatched.push(book.label());
+            continue;
+        };
+        report.books += 1;
+
+        let highlights = current_highlights(&book_clippings);
+        let mut notes: Vec<Vec<&str>> = vec![Vec::new(); highlights.len()];
+        for clipping in &book_clippings {
+            match clipping.kind {
+                ClippingKind::Highlight => {}
+                ClippingKind::Note => match note_target(&highlights, clipping) {
+                    Some(i) if !clipping.text.is_empty() => notes[i].push(&clipping.text),
+                    _ => report.skipped += 1,
+                },
+                ClippingKind::Bookmark => report.skipped += 1,
+            }
+        }
+        report.skipped += book_clippings
+            .iter()
+            .filter(|clipping| clipping.kind == ClippingKind::Highlight)
+            .count()
+            - highlights.len();
+
+        let query = AnnotationQuery {
+            book_id: Some(book_id.to_string()),
+            user_id: user_id.map(str::to_string),
+            ..Default::default()
+        };
+        let existing: HashSet<String> = repo
+            .list(&query)
+            .await?
+            .iter()
+            .filter_map(|annotation| {
+                annotation
+                    .text_quote()
+                    .or_else(|| annotation.pdf_text_quote())
+            })
+            .map(str::to_string)
+            .collect();
+
+        for (highlight, notes) in highlights.iter().zip(notes) {
+            if existing.contains(&highlight.text) {
+                report.existing += 1;
+                continue;
+            }
+            if !dry_run {
+                let note = (!notes.is_empty()).then(|| notes.join("\n\n"));
+                let annotation = to_annotation(book_id, format, highlight, note, user_id);
+                repo.save(&annotation).await?;
+            }
+            report.imported += 1;
+            report.notes += notes.len();
+        }
+    }
+
+    Ok(report)
+}
+
+/// A book's highlights without the copies Kindle kept of their earlier,
+/// shorter versions
+fn current_highlights<'a>(clippings: &[&'a Clipping]) -> Vec<&'a Clipping> {
+    let highlights: Vec<&Clipping> = clippings
+        .iter()
+        .copied()
+        .filter(|clipping| clipping.kind == ClippingKind::Highlight && !clipping.text.is_empty())
+        .collect();
+
+    highlights
+        .iter()
+        .enumerate()
+        .filter(|(i, highlight)| {
+            !highlights
+                .iter()
+                .enumerate()
+                .any(|(j, other)| j != *i && highlight.superseded_by(other, j > *i))
+        })
+        .map(|(_, highlight)| *highlight)
+        .collect()
+}
+
+/// Index of the highlight a note was typed on: the last one whose
+/// locations include the note's
+fn note_target(highlights: &[&Clipping], note: &Clipping) -> Option<usize> {
+    let (location, _) = note.location?;
+    highlights.iter().rposition(|highlight| {
+        highlight
+            .location
+            .is_some_and(|(start, end)| (start..=end).contains(&location))
+    })
+}
+
+fn to_annotation(
+    book_id: &str,
+    format: DocumentFormat,
+    highlight: &Clipping,
+    note: Option<String>,
+    user_id: Option<&str>,
+) -> Annotation {
+    // Clippings do not say which chapter they are in; the quote is found
+    // anywhere in the book
+    let target = match (format, highlight.page) {
+        (DocumentFormat::Pdf, Some(page)) => {
+            AnnotationTarget::from_pdf_text("", page, &highlight.text, None, None)
+        }
+        _ => {
+            let mut target = AnnotationTarget::with_selectors("", Vec::new());
+            target.add_text_quote(&highlight.text, None, None);
+            target
+        }
+    };
+
+    let mut annotation = match note {
+        Some(note) => Annotation::new_note(book_id, target, &note),
+        None => Annotation::new_highlight(book_id, target),
+    };
+    if let Some(user_id) = user_id {
+        annotation = annotation.with_user(user_id);
+    }
+    if let Some(added_at) = highlight.added_at {
+        annotation.created_at = added_at;
+        annotation.updated_at = added_at;
+    }
+    annotation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::initialize_schema;
+    use crate::library::LibraryBook;
+
+    const CLIPPINGS: &str = "\u{feff}Dune (Herbert, Frank)
+- Your Highlight on page 12 | Location 180-182 | Added on Monday, March 4, 2019 10:21:33 PM
+
+Fear is the mind-killer.
+==========
+Dune (Herbert, Frank)
+- Your Highlight on page 12 | Location 180-183 | Added on Monday, March 4, 2019 10:22:01 PM
+
+I must not fear. Fear is the mind-killer.
+==========
+Dune (Herbert, Frank)
+- Your Note on page 12 | Location 183 | Added on Monday, March 4, 2019 10:22:30 PM
+
+Litany against fear
+==========
+Dune (Herbert, Frank)
+- Your Bookmark on page 40 | Location 600 | Added on Tuesday, March 5, 2019 8:00:00 AM
+
+
+==========
+Emma: A Novel (Jane Austen)
+- Highlight Loc. 1020-24 | Added on Friday, July 5, 2013, 09:15 PM
+
+Emma Woodhouse, handsome, clever, and rich
+==========
+Ulysses (James Joyce)
+- Your Highlight on Location 10-11 | Added on Sunday, June 16, 2019 11:00:00 AM
+
+Stately, plump Buck Mulligan
+==========
+";
+
+    #[test]
+    fn test_parse_clippings() {
+        let clippings = parse_clippings(CLIPPINGS);
+        assert_eq!(clippings.len(), 6);
+
+        let first = &clippings[0];
+        assert_eq!(first.title, "Dune");
+        assert_eq!(first.author.as_deref(), Some("Herbert, Frank"));
+        assert_eq!(first.kind, ClippingKind::Highlight);
+        assert_eq!((first.page, first.location), (Some(12), Some((180, 182))));
+        assert_eq!(
+            first.added_at.unwrap().to_rfc3339(),
+            "2019-03-04T22:21:33+00:00"
+        );
+        assert_eq!(first.text, "Fear is the mind-killer.");
+
+        assert_eq!(clippings[2].kind, ClippingKind::Note);
+        assert_eq!(clippings[2].location, Some((183, 183)));
+        assert_eq!(clippings[3].kind, ClippingKind::Bookmark);
+        assert!(clippings[3].text.is_empty());
+
+        // Older firmware: "Loc." with shortened ranges and no seconds
+        let emma = &clippings[4];
+        assert_eq!(emma.location, Some((1020, 1024)));
+        assert_eq!(emma.page, None);
+        assert_eq!(
+            emma.added_at.unwrap().to_rfc3339(),
+            "2013-07-05T21:15:00+00:00"
+        );
+
+        assert_eq!(
+            split_title("Title (Part 2) (An Author)"),
+            ("Title (Part 2)".to_string(), Some("An Author".to_string()))
+        );
+        assert_eq!(split_title("(Untitled)"), ("(Untitled)".to_string(), None));
+    }
+
+    #[tokio::test]
+    async fn test_import_clippings() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        initialize_schema(&pool).await.unwrap();
+        AnnotationRepository::new(&pool).init().await.unwrap();
+
+        let mut dune = LibraryBook::new(
+            "Dune (Dune Chronicles, Book 1)".to_string(),
+            "d".to_string(),
+        );
+        dune.author = Some("Frank Herbert".to_string());
+        let mut emma = LibraryBook::new("Emma".to_string(), "e".to_string());
+        emma.author = Some("Jane Austen".to_string());
+        let library = LibraryIndex::new(&[dune, emma]);
+        let clippings = parse_clippings(CLIPPINGS);
+
+        let dry = import_clippings(&clippings, &library, &pool, Some("ana"), true)
+            .await
+            .unwrap();
+        assert_eq!((dry.books, dry.imported, dry.notes), (2, 2, 1));
+        // The bookmark and the first, shorter Dune highlight
+        assert_eq!(dry.skipped, 2);
+        assert_eq!(dry.unmatched, vec!["Ulysses (James Joyce)"]);
+
+        let report = import_clippings(&clippings, &library, &pool, Some("ana"), false)
+            .await
+            .unwrap();
+        assert_eq!(report.imported, 2);
+        let query = AnnotationQuery {
+            book_id: Some("d".to_string()),
+            ..Default::default()
+        };
+        let annotations = AnnotationRepository::new(&pool).list(&query).await.unwrap();
+        assert_eq!(annotations.len(), 1);
+        let note = &annotations[0];
+        assert_eq!(
+            note.text_quote(),
+            Some("I must not fear. Fear is the mind-killer.")
+        );
+        assert_eq!(
+            note.body.as_ref().unwrap().value.as_deref(),
+            Some("Litany against fear")
+        );
+        assert_eq!(note.user_id.as_deref(), Some("ana"));
+        assert_eq!(note.created_at.to_rfc3339(), "2019-03-04T22:22:01+00:00");
+
+        let again = import_clippings(&clippings, &library, &pool, Some("ana"), false)
+            .await
+            .unwrap();
+        assert_eq!((again.imported, again.existing), (0, 2));
+    }
+}
@@ -0,0 +1,192 @@
+//! Komga REST API reader
+//!
+//! Read lists are shared by every Komga user, so they become shared
+//! collections. Reading progress is per account and only visible to the
+//! account making the requests, so only that account's progress is
+//! imported, under its login name.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use super::{calibre_id_from_path, ImportPlan, ImportedCollection, ImportedProgress, SourceBook};
+
+const SOURCE: &str = "komga";
+
+#[derive(Debug, Deserialize)]
+struct Page<T> {
+    content: Vec<T>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReadList {
+    name: String,
+    book_ids: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Book {
+    id: String,
+    name: String,
+    /// Path of the file on the Komga server
+    url: String,
+    metadata: BookMetadata,
+    media: Media,
+    read_progress: Option<ReadProgress>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BookMetadata {
+    title: String,
+    #[serde(default)]
+    isbn: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Media {
+    pages_count: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReadProgress {
+    page: u32,
+    completed: bool,
+}
+
+struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    username: String,
+    password: String,
+}
+
+impl Client {
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let url = format!("{}/api/v1/{}", self.base_url, path);
+        let response = self
+            .http
+            .get(&url)
+            .basic_auth(&self.username, Some(&self.password))
+            .send()
+            .await
+            .with_context(|| format!("Request to {} failed", url))?
+            .error_for_status()?;
+        Ok(response.json().await?)
+    }
+}
+
+/// Read read lists, and the progress of `username`, from a Komga server
+pub async fn read_komga(base_url: &str, username: &str, password: &str) -> Result<ImportPlan> {
+    let client = Client {
+        http: reqwest::Client::new(),
+        base_url: base_url.trim_end_matches('/').to_string(),
+        username: username.to_string(),
+        password: password.to_string(),
+    };
+
+    let read_lists: Page<ReadList> = client.get("readlists?unpaged=true").await?;
+    let started: Page<Book> = client
+        .get("books?read_status=IN_PROGRESS&read_status=READ&unpaged=true")
+        .await?;
+
+    let mut books: HashMap<String, Book> = HashMap::new();
+    let mut plan = ImportPlan {
+        source: SOURCE.to_string(),
+        users: vec![username.to_string()],
+        ..Default::default()
+    };
+
+    for book in started.content {
+        if let Some(percent) = book
+            .read_progress
+            .as_ref()
+            .map(|p| progress_percent(p, &book.media))
+        {
+            plan.progress.push(ImportedProgress {
+                user_id: username.to_string(),
+                book: source_book(&book),
+                percent,
+            });
+        }
+        books.insert(book.id.clone(), book);
+    }
+
+    for list in read_lists.content {
+        let mut members = Vec::with_capacity(list.book_ids.len());
+        for id in &list.book_ids {
+            if !books.contains_key(id) {
+                let book: Book = client.get(&format!("books/{}", id)).await?;
+                books.insert(id.clone(), book);
+            }
+            members.push(source_book(&books[id]));
+        }
+        plan.collections.push(ImportedCollection {
+            name: list.name,
+            user_id: None,
+            books: members,
+        });
+    }
+
+    Ok(plan)
+}
+
+fn progress_percent(progress: &ReadProgress, media: &Media) -> f64 {
+    if progress.completed {
+        return 100.0;
+    }
+    if media.pages_count == 0 {
+        return 0.0;
+    }
+    // Komga pages are 1-indexed and `page` is the last page viewed
+    (f64::from(progress.page) / f64::from(media.pages_count) * 100.0).min(100.0)
+}
+
+fn source_book(book: &Book) -> SourceBook {
+    // Komga libraries pointed at a Calibre library keep its `Title (id)`
+    // folders, which identify the book exactly
+    let folder = book.url.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+    SourceBook {
+        label: format!("{} ({})", book.metadata.title, book.name),
+        calibre_id: calibre_id_from_path(folder),
+        isbn: Some(book.metadata.isbn.clone()).filter(|isbn| !isbn.is_empty()),
+        title: Some(book.metadata.title.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_book_mapping() {
+        let book: Book = serde_json::from_value(serde_json::json!({
+            "id": "0B1",
+            "name": "Dune - Frank Herbert",
+            "url": "/books/Frank Herbert/Dune (42)/Dune - Frank Herbert.epub",
+            "metadata": { "title": "Dune", "isbn": "" },
+            "media": { "pagesCount": 200 },
+            "readProgress": { "page": 50, "completed": false }
+        }))
+        .unwrap();
+
+        let source = source_book(&book);
+        assert_eq!(source.calibre_id, Some(42));
+        assert_eq!(source.isbn, None);
+        assert_eq!(source.title.as_deref(), Some("Dune"));
+        assert_eq!(
+            progress_percent(book.read_progress.as_ref().unwrap(), &book.media),
+            25.0
+        );
+
+        let done = ReadProgress {
+            page: 3,
+            completed: true,
+        };
+        assert_eq!(progress_percent(&done, &Media { pages_count: 0 }), 100.0);
+    }
+}
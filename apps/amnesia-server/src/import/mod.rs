@@ -0,0 +1,540 @@
+//! Import users, reading progress, and collections from other book servers
+//!
+//! Each source is read into an [`ImportPlan`] that names books the way the
+//! source does (Calibre id, ISBN, title). [`apply`] matches those books
+//! against the scanned library through a [`LibraryIndex`] and writes
+//! progress and collections, or with `dry_run` only reports what it would
+//! write.
+//!
+//! Sources:
+//!
+//! - Calibre-Web: users, read status and Kobo sync positions, and shelves,
+//!   read from its `app.db`
+//! - Komga: read lists and the reading progress of one account, fetched from
+//!   its REST API
+//! - Kindle: highlights and notes from a `My Clippings.txt` file, imported
+//!   as annotations by [`import_clippings`] rather than through a plan
+//!
+//! Neither server's users are created here: a source user becomes the
+//! free-form `user_id` that progress and collections are stored under.
+//! Progress a user already has in Los Libros is never overwritten.
+
+mod calibre_web;
+mod kindle;
+mod komga;
+
+pub use calibre_web::read_calibre_web;
+pub use kindle::{import_clippings, parse_clippings, Clipping, ClippingKind, ClippingsReport};
+pub use komga::read_komga;
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use anyhow::Result;
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+use crate::db::{
+    CollectionRepository, DocumentFormat, ProgressLocator, ProgressRepository, ProgressUpdate,
+};
+use crate::library::{FormatType, LibraryBook};
+
+/// A book as the source server identifies it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SourceBook {
+    /// Label used in reports when the book cannot be matched
+    pub label: String,
+    pub calibre_id: Option<i64>,
+    pub isbn: Option<String>,
+    pub title: Option<String>,
+}
+
+/// Reading progress to import
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedProgress {
+    pub user_id: String,
+    pub book: SourceBook,
+    /// Percentage through the book (0-100)
+    pub percent: f64,
+}
+
+/// Collection to import
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedCollection {
+    pub name: String,
+    /// Owner, or `None` for a collection shared by all users
+    pub user_id: Option<String>,
+    pub books: Vec<SourceBook>,
+}
+
+/// Everything read from a source, before matching
+#[derive(Debug, Clone, Default)]
+pub struct ImportPlan {
+    /// Source name, stored as the collection source and progress device
+    pub source: String,
+    pub users: Vec<String>,
+    pub progress: Vec<ImportedProgress>,
+    pub collections: Vec<ImportedCollection>,
+    /// Things the source had that could not be imported
+    pub notes: Vec<String>,
+}
+
+/// Outcome of [`apply`]
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub source: String,
+    pub dry_run: bool,
+    pub users: Vec<String>,
+    /// Progress records written (or that would be written)
+    pub progress_imported: usize,
+    /// Progress records skipped because the user already had progress
+    pub progress_existing: usize,
+    pub collections: usize,
+    /// Books added to collections (or that would be added)
+    pub collection_books: usize,
+    /// Source books with no match in the library
+    pub unmatched: Vec<String>,
+    pub notes: Vec<String>,
+}
+
+/// Lookup from source book identifiers to library book IDs
+pub struct LibraryIndex {
+    by_calibre_id: HashMap<i64, usize>,
+    by_isbn: HashMap<String, usize>,
+    by_title: HashMap<String, usize>,
+    books: Vec<(String, DocumentFormat)>,
+    /// Title and author words per book, for [`LibraryIndex::resolve_fuzzy`]
+    words: Vec<(TitleWords, HashSet<String>)>,
+}
+
+/// Smallest [`LibraryIndex::resolve_fuzzy`] score accepted as a match
+const FUZZY_THRESHOLD: f64 = 0.8;
+
+impl LibraryIndex {
+    pub fn new(library: &[LibraryBook]) -> Self {
+        let mut index = Self {
+            by_calibre_id: HashMap::new(),
+            by_isbn: HashMap::new(),
+            by_title: HashMap::new(),
+            books: Vec::with_capacity(library.len()),
+            words: Vec::with_capacity(library.len()),
+        };
+
+        for (i, book) in library.iter().enumerate() {
+            let calibre_id = book
+                .identifiers
+                .get("calibre")
+                .and_then(|id| id.parse().ok())
+                .or_else(|| calibre_id_from_path(&book.s3_prefix));
+            if let Some(id) = calibre_id {
+                index.by_calibre_id.insert(id, i);
+            }
+            if let Some(isbn) = book.identifiers.get("isbn") {
+                index.by_isbn.insert(normalize_isbn(isbn), i);
+            }
+            index
+                .by_title
+                .entry(normalize_title(&book.title))
+                .or_insert(i);
+
+            let format = match book.primary_format().map(|f| f.format) {
+                Some(FormatType::Pdf) => DocumentFormat::Pdf,
+                _ => DocumentFormat::Epub,
+            };
+            index.books.push((book.stable_id(), format));
+
+            let authors = book
+                .author
+                .iter()
+                .chain(&book.authors)
+                .flat_map(|author| words(author))
+                .collect();
+            index.words.push((TitleWords::new(&book.title), authors));
+        }
+
+        index
+    }
+
+    /// Library book ID and format for a source book, trying the Calibre id,
+    /// then the ISBN, then the title
+    pub fn resolve(&self, book: &SourceBook) -> Option<(&str, DocumentFormat)> {
+        let i = book
+            .calibre_id
+            .and_then(|id| self.by_calibre_id.get(&id))
+            .or_else(|| {
+                book.isbn
+                    .as_deref()
+                    .and_then(|isbn| self.by_isbn.get(&normalize_isbn(isbn)))
+            })
+            .or_else(|| {
+                book.title
+                    .as_deref()
+                    .and_then(|title| self.by_title.get(&normalize_title(title)))
+            })?;
+        let (id, format) = &self.books[*i];
+        Some((id, *format))
+    }
+
+    /// Library book ID and format for a title and author that may be
+    /// spelled differently than in the library
+    ///
+    /// Titles are compared by their words, ignoring case, punctuation, and
+    /// parenthesized series names; a subtitle on only one side still
+    /// matches. Authors are compared as sets of words, so "Herbert, Frank"
+    /// matches "Frank Herbert", and a different author only matches an
+    /// identical title. The best book scoring at least
+    /// [`FUZZY_THRESHOLD`] wins.
+    pub fn resolve_fuzzy(
+        &self,
+        title: &str,
+        author: Option<&str>,
+    ) -> Option<(&str, DocumentFormat)> {
+        let title = TitleWords::new(title);
+        let author: HashSet<String> = author.map(words).unwrap_or_default();
+
+        let mut best: Option<(usize, f64)> = None;
+        for (i, (book_title, book_authors)) in self.words.iter().enumerate() {
+            let mut score = title.similarity(book_title);
+            if !author.is_empty() && !book_authors.is_empty() {
+                if author.is_disjoint(book_authors) {
+                    score -= 0.2;
+                } else {
+                    score += 0.1;
+                }
+            }
+            if score >= FUZZY_THRESHOLD && !best.is_some_and(|(_, best)| best >= score) {
+                best = Some((i, score));
+            }
+        }
+
+        let (id, format) = &self.books[best?.0];
+        Some((id, *format))
+    }
+}
+
+/// Words of a title, with and without its subtitle
+struct TitleWords {
+    full: HashSet<String>,
+    /// Words before the first colon
+    main: HashSet<String>,
+}
+
+impl TitleWords {
+    fn new(title: &str) -> Self {
+        let title = strip_parenthesized(title);
+        let main = title.split(':').next().unwrap_or_default();
+        Self {
+            full: words(&title),
+            main: words(main),
+        }
+    }
+
+    /// Dice coefficient of the word sets, the better of full and main titles
+    fn similarity(&self, other: &TitleWords) -> f64 {
+        dice(&self.full, &other.full)
+            .max(dice(&self.main, &other.main))
+            .max(dice(&self.full, &other.main))
+            .max(dice(&self.main, &other.full))
+    }
+}
+
+fn dice(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    2.0 * a.intersection(b).count() as f64 / (a.len() + b.len()) as f64
+}
+
+/// Lowercase alphanumeric words, with apostrophes dropped
+fn words(text: &str) -> HashSet<String> {
+    text.chars()
+        .filter(|c| !matches!(c, '\'' | '\u{2019}'))
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .flat_map(char::to_lowercase)
+        .collect::<String>()
+        .split_whitespace()
+        .map(str::to_string)
+        .collect()
+}
+
+/// Title without `(...)` and `[...]` parts such as series names
+fn strip_parenthesized(title: &str) -> String {
+    let mut depth = 0usize;
+    let mut stripped = String::with_capacity(title.len());
+    for c in title.chars() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = depth.saturating_sub(1),
+            _ if depth == 0 => stripped.push(c),
+            _ => {}
+        }
+    }
+    stripped
+}
+
+/// Write a plan's progress and collections
+pub async fn apply(
+    plan: &ImportPlan,
+    library: &LibraryIndex,
+    pool: &SqlitePool,
+    dry_run: bool,
+) -> Result<ImportReport> {
+    let progress_repo = ProgressRepository::new(pool);
+    let collection_repo = CollectionRepository::new(pool);
+    let device_id = format!("import:{}", plan.source);
+    let mut unmatched = BTreeSet::new();
+
+    let mut report = ImportReport {
+        source: plan.source.clone(),
+        dry_run,
+        users: plan.users.clone(),
+        notes: plan.notes.clone(),
+        ..Default::default()
+    };
+
+    for progress in &plan.progress {
+        let Some((book_id, format)) = library.resolve(&progress.book) else {
+            unmatched.insert(progress.book.label.clone());
+            continue;
+        };
+
+        let existing = progress_repo.get(book_id, Some(&progress.user_id)).await?;
+        if existing.is_some_and(|p| p.device_id.as_deref() != Some(device_id.as_str())) {
+            report.progress_existing += 1;
+            continue;
+        }
+
+        if !dry_run {
+            let percent = progress.percent.clamp(0.0, 100.0);
+            let update = ProgressUpdate {
+                percent,
+                document_format: Some(format),
+                locator: Some(ProgressLocator::Progression {
+                    progression: percent / 100.0,
+                }),
+                cfi: None,
+                page: None,
+                total_pages: None,
+                device_id: Some(device_id.clone()),
+            };
+            progress_repo
+                .upsert(book_id, Some(&progress.user_id), &update)
+                .await?;
+        }
+        report.progress_imported += 1;
+    }
+
+    for collection in &plan.collections {
+        let book_ids: Vec<&str> = collection
+            .books
+            .iter()
+            .filter_map(|book| match library.resolve(book) {
+                Some((id, _)) => Some(id),
+                None => {
+                    unmatched.insert(book.label.clone());
+                    None
+                }
+            })
+            .collect();
+
+        report.collections += 1;
+        if dry_run {
+            report.collection_books += book_ids.len();
+            continue;
+        }
+
+        let stored = collection_repo
+            .get_or_create(
+                &collection.name,
+                collection.user_id.as_deref(),
+                &plan.source,
+            )
+            .await?;
+        for book_id in book_ids {
+            if collection_repo.add_book(&stored.id, book_id).await? {
+                report.collection_books += 1;
+            }
+        }
+    }
+
+    report.unmatched = unmatched.into_iter().collect();
+    Ok(report)
+}
+
+/// Calibre book id from a folder name like `Title (42)`
+fn calibre_id_from_path(path: &str) -> Option<i64> {
+    let folder = path.trim_end_matches('/').rsplit('/').next()?;
+    let inner = folder.strip_suffix(')')?;
+    let (_, id) = inner.rsplit_once('(')?;
+    id.parse().ok()
+}
+
+fn normalize_isbn(isbn: &str) -> String {
+    isbn.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+fn normalize_title(title: &str) -> String {
+    title
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::initialize_schema;
+
+    fn library() -> Vec<LibraryBook> {
+        let mut dune = LibraryBook::new("Dune".to_string(), "Frank Herbert/Dune (42)".to_string());
+        dune.identifiers
+            .insert("uuid".to_string(), "uuid-dune".to_string());
+        let mut emma = LibraryBook::new("Emma".to_string(), "Jane Austen/Emma (7)".to_string());
+        emma.identifiers
+            .insert("isbn".to_string(), "978-0141439587".to_string());
+        vec![dune, emma]
+    }
+
+    fn book(calibre_id: Option<i64>, isbn: Option<&str>, title: Option<&str>) -> SourceBook {
+        SourceBook {
+            label: format!("{:?}", (calibre_id, isbn, title)),
+            calibre_id,
+            isbn: isbn.map(str::to_string),
+            title: title.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_library_index_resolve() {
+        let index = LibraryIndex::new(&library());
+
+        let id = |b: SourceBook| index.resolve(&b).map(|(id, _)| id.to_string());
+        assert_eq!(id(book(Some(42), None, None)).as_deref(), Some("uuid-dune"));
+        assert_eq!(
+            id(book(None, Some("9780141439587"), None)).as_deref(),
+            Some("Jane Austen/Emma (7)")
+        );
+        assert_eq!(
+            id(book(None, None, Some("DUNE"))).as_deref(),
+            Some("uuid-dune")
+        );
+        assert_eq!(id(book(Some(99), None, Some("Ulysses"))), None);
+        assert_eq!(calibre_id_from_path("A/B (12)/"), Some(12));
+        assert_eq!(calibre_id_from_path("A/B"), None);
+    }
+
+    #[test]
+    fn test_library_index_resolve_fuzzy() {
+        let mut books = library();
+        books[0].title = "Dune (Dune Chronicles, Book 1)".to_string();
+        books[0].author = Some("Frank Herbert".to_string());
+        books[1].author = Some("Jane Austen".to_string());
+        let mut messiah = LibraryBook::new("Dune Messiah".to_string(), "messiah".to_string());
+        messiah.author = Some("Frank Herbert".to_string());
+        books.push(messiah);
+        let index = LibraryIndex::new(&books);
+
+        let id = |title: &str, author: Option<&str>| {
+            index
+                .resolve_fuzzy(title, author)
+                .map(|(id, _)| id.to_string())
+        };
+        assert_eq!(
+            id("Dune", Some("Herbert, Frank")).as_deref(),
+            Some("uuid-dune")
+        );
+        assert_eq!(id("DUNE MESSIAH", None).as_deref(), Some("messiah"));
+        assert_eq!(
+            id("Emma: A Novel", Some("Austen, Jane")).as_deref(),
+            Some("Jane Austen/Emma (7)")
+        );
+        // Same title, different author
+        assert_eq!(
+            id("Emma", Some("Someone Else")).as_deref(),
+            Some("Jane Austen/Emma (7)")
+        );
+        assert_eq!(id("Emma and Friends", Some("Someone Else")), None);
+        assert_eq!(id("Children of Dune", Some("Frank Herbert")), None);
+    }
+
+    #[tokio::test]
+    async fn test_apply_keeps_existing_progress() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        initialize_schema(&pool).await.unwrap();
+        let index = LibraryIndex::new(&library());
+
+        // Native progress for Emma must survive the import
+        let native = ProgressUpdate {
+            percent: 10.0,
+            document_format: None,
+            locator: None,
+            cfi: None,
+            page: None,
+            total_pages: None,
+            device_id: Some("phone".to_string()),
+        };
+        ProgressRepository::new(&pool)
+            .upsert("Jane Austen/Emma (7)", Some("ana"), &native)
+            .await
+            .unwrap();
+
+        let plan = ImportPlan {
+            source: "calibre-web".to_string(),
+            users: vec!["ana".to_string()],
+            progress: vec![
+                ImportedProgress {
+                    user_id: "ana".to_string(),
+                    book: book(Some(42), None, None),
+                    percent: 100.0,
+                },
+                ImportedProgress {
+                    user_id: "ana".to_string(),
+                    book: book(Some(7), None, None),
+                    percent: 50.0,
+                },
+            ],
+            collections: vec![ImportedCollection {
+                name: "Favorites".to_string(),
+                user_id: Some("ana".to_string()),
+                books: vec![book(Some(42), None, None), book(Some(99), None, None)],
+            }],
+            notes: Vec::new(),
+        };
+
+        let dry = apply(&plan, &index, &pool, true).await.unwrap();
+        assert_eq!((dry.progress_imported, dry.progress_existing), (1, 1));
+        assert_eq!((dry.collections, dry.collection_books), (1, 1));
+        assert_eq!(dry.unmatched, vec!["(Some(99), None, None)"]);
+        assert!(ProgressRepository::new(&pool)
+            .get("uuid-dune", Some("ana"))
+            .await
+            .unwrap()
+            .is_none());
+
+        let report = apply(&plan, &index, &pool, false).await.unwrap();
+        assert_eq!(report.progress_imported, 1);
+        assert_eq!(report.collection_books, 1);
+
+        let progress = ProgressRepository::new(&pool);
+        let dune = progress
+            .get("uuid-dune", Some("ana"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(dune.percent, 100.0);
+        let emma = progress
+            .get("Jane Austen/Emma (7)", Some("ana"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(emma.percent, 10.0);
+
+        // Re-running updates the imported rows instead of counting them as existing
+        let again = apply(&plan, &index, &pool, false).await.unwrap();
+        assert_eq!((again.progress_imported, again.collection_books), (1, 0));
+    }
+}
@@ -0,0 +1,296 @@
+//! EPUB 3 writer for ingested articles
+//!
+//! Each article becomes a single-chapter EPUB, plus a Calibre-style
+//! `metadata.opf` stored next to it so the library scanner picks up the
+//! title, author, and UUID without opening the EPUB.
+
+use std::io::{Cursor, Write};
+
+use chrono::Utc;
+use html_escape::{encode_double_quoted_attribute, encode_text};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use super::readability::{Block, BlockKind};
+
+/// An article ready to be written out
+#[derive(Debug, Clone)]
+pub struct Article {
+    /// UUID, used as the EPUB identifier and the library book ID
+    pub id: String,
+    pub title: String,
+    pub author: String,
+    pub published: Option<String>,
+    pub language: Option<String>,
+    /// Page the article came from
+    pub source_url: Option<String>,
+    pub blocks: Vec<Block>,
+}
+
+const CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+const STYLE_CSS: &str = "\
+body { margin: 0 1em; line-height: 1.5; }
+h1 { margin-bottom: 0.2em; }
+.byline, .source { font-size: 0.85em; opacity: 0.7; }
+blockquote { margin-left: 1.5em; font-style: italic; }
+pre { white-space: pre-wrap; }
+";
+
+/// Build the EPUB file for an article
+pub fn build_epub(article: &Article) -> zip::result::ZipResult<Vec<u8>> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let deflated = SimpleFileOptions::default();
+
+    // The mimetype must come first and be stored uncompressed
+    zip.start_file(
+        "mimetype",
+        SimpleFileOptions::default().compression_method(CompressionMethod::Stored),
+    )?;
+    zip.write_all(b"application/epub+zip")?;
+
+    zip.start_file("META-INF/container.xml", deflated)?;
+    zip.write_all(CONTAINER_XML.as_bytes())?;
+    zip.start_file("OEBPS/content.opf", deflated)?;
+    zip.write_all(package_opf(article).as_bytes())?;
+    zip.start_file("OEBPS/nav.xhtml", deflated)?;
+    zip.write_all(nav_xhtml(article).as_bytes())?;
+    zip.start_file("OEBPS/article.xhtml", deflated)?;
+    zip.write_all(article_xhtml(article).as_bytes())?;
+    zip.start_file("OEBPS/style.css", deflated)?;
+    zip.write_all(STYLE_CSS.as_bytes())?;
+
+    Ok(zip.finish()?.into_inner())
+}
+
+/// Calibre-style `metadata.opf` for the library scanner
+pub fn metadata_opf(article: &Article) -> String {
+    let mut metadata = String::new();
+    metadata.push_str(&format!(
+        "        <dc:identifier opf:scheme=\"uuid\" id=\"uuid\">{}</dc:identifier>\n",
+        article.id
+    ));
+    metadata.push_str(&format!(
+        "        <dc:title>{}</dc:title>\n",
+        xml_text(&article.title)
+    ));
+    metadata.push_str(&format!(
+        "        <dc:creator opf:role=\"aut\">{}</dc:creator>\n",
+        xml_text(&article.author)
+    ));
+    if let Some(published) = &article.published {
+        metadata.push_str(&format!(
+            "        <dc:date>{}</dc:date>\n",
+            xml_text(published)
+        ));
+    }
+    metadata.push_str(&format!(
+        "        <dc:language>{}</dc:language>\n",
+        xml_text(language(article))
+    ));
+    if let Some(url) = &article.source_url {
+        metadata.push_str(&format!(
+            "        <dc:source>{}</dc:source>\n",
+            xml_text(url)
+        ));
+    }
+
+    format!(
+        r#"<?xml version='1.0' encoding='utf-8'?>
+<package xmlns="http://www.idpf.org/2007/opf" unique-identifier="uuid" version="2.0">
+    <metadata xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:opf="http://www.idpf.org/2007/opf">
+{}    </metadata>
+</package>
+"#,
+        metadata
+    )
+}
+
+fn package_opf(article: &Article) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="uid">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="uid">urn:uuid:{id}</dc:identifier>
+    <dc:title>{title}</dc:title>
+    <dc:creator>{author}</dc:creator>
+    <dc:language>{language}</dc:language>
+    <meta property="dcterms:modified">{modified}</meta>
+  </metadata>
+  <manifest>
+    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="article" href="article.xhtml" media-type="application/xhtml+xml"/>
+    <item id="css" href="style.css" media-type="text/css"/>
+  </manifest>
+  <spine>
+    <itemref idref="article"/>
+  </spine>
+</package>
+"#,
+        id = article.id,
+        title = xml_text(&article.title),
+        author = xml_text(&article.author),
+        language = xml_text(language(article)),
+        modified = Utc::now().format("%Y-%m-%dT%H:%M:%SZ"),
+    )
+}
+
+fn nav_xhtml(article: &Article) -> String {
+    xhtml_document(
+        article,
+        &format!(
+            "  <nav epub:type=\"toc\" id=\"toc\">\n    <ol><li><a href=\"article.xhtml\">{}</a></li></ol>\n  </nav>\n",
+            xml_text(&article.title)
+        ),
+    )
+}
+
+fn article_xhtml(article: &Article) -> String {
+    let mut body = format!("  <h1>{}</h1>\n", xml_text(&article.title));
+
+    let byline = match &article.published {
+        Some(published) => format!("{} · {}", article.author, published),
+        None => article.author.clone(),
+    };
+    body.push_str(&format!(
+        "  <p class=\"byline\">{}</p>\n",
+        xml_text(&byline)
+    ));
+
+    let mut in_list = false;
+    for block in &article.blocks {
+        let is_item = block.kind == BlockKind::ListItem;
+        if is_item != in_list {
+            body.push_str(if is_item { "  <ul>\n" } else { "  </ul>\n" });
+            in_list = is_item;
+        }
+
+        let text = xml_text(&block.text);
+        let element = match block.kind {
+            BlockKind::Paragraph => format!("  <p>{}</p>\n", text),
+            BlockKind::Heading(level) => {
+                // The article title is the only h1
+                let level = level.clamp(2, 6);
+                format!("  <h{0}>{1}</h{0}>\n", level, text)
+            }
+            BlockKind::ListItem => format!("    <li>{}</li>\n", text),
+            BlockKind::Quote => format!("  <blockquote><p>{}</p></blockquote>\n", text),
+            BlockKind::Preformatted => format!("  <pre>{}</pre>\n", text),
+        };
+        body.push_str(&element);
+    }
+    if in_list {
+        body.push_str("  </ul>\n");
+    }
+
+    if let Some(url) = &article.source_url {
+        body.push_str(&format!(
+            "  <p class=\"source\"><a href=\"{}\">{}</a></p>\n",
+            encode_double_quoted_attribute(&xml_chars(url)),
+            xml_text(url)
+        ));
+    }
+
+    xhtml_document(article, &body)
+}
+
+fn xhtml_document(article: &Article, body: &str) -> String {
+    let lang = xml_text(language(article));
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" xml:lang="{lang}" lang="{lang}">
+<head>
+  <meta charset="utf-8"/>
+  <title>{title}</title>
+  <link rel="stylesheet" type="text/css" href="style.css"/>
+</head>
+<body>
+{body}</body>
+</html>
+"#,
+        lang = lang,
+        title = xml_text(&article.title),
+        body = body,
+    )
+}
+
+fn language(article: &Article) -> &str {
+    article.language.as_deref().unwrap_or("en")
+}
+
+/// Escape text for XML, dropping characters XML 1.0 does not allow
+fn xml_text(text: &str) -> String {
+    encode_text(&xml_chars(text)).into_owned()
+}
+
+fn xml_chars(text: &str) -> String {
+    text.chars()
+        .filter(|&c| !c.is_control() || matches!(c, '\t' | '\n' | '\r'))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::CalibreMetadata;
+    use std::io::Read;
+
+    fn article() -> Article {
+        Article {
+            id: "2f1c7a4e-0000-4000-8000-000000000001".to_string(),
+            title: "Fish & <Chips>".to_string(),
+            author: "Weekly Letter".to_string(),
+            published: None,
+            language: None,
+            source_url: Some("https://example.com/a?x=1&y=2".to_string()),
+            blocks: vec![
+                Block {
+                    kind: BlockKind::Paragraph,
+                    text: "Intro\u{1}".to_string(),
+                },
+                Block {
+                    kind: BlockKind::ListItem,
+                    text: "One".to_string(),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_build_epub() {
+        let epub = build_epub(&article()).unwrap();
+        let mut zip = zip::ZipArchive::new(Cursor::new(epub)).unwrap();
+
+        let first = zip.by_index(0).unwrap();
+        assert_eq!(first.name(), "mimetype");
+        assert_eq!(first.compression(), CompressionMethod::Stored);
+        drop(first);
+
+        let mut xhtml = String::new();
+        zip.by_name("OEBPS/article.xhtml")
+            .unwrap()
+            .read_to_string(&mut xhtml)
+            .unwrap();
+        assert!(xhtml.contains("<h1>Fish &amp; &lt;Chips&gt;</h1>"));
+        assert!(xhtml.contains("<p>Intro</p>\n  <ul>\n    <li>One</li>\n  </ul>"));
+        assert!(xhtml.contains("href=\"https://example.com/a?x=1&amp;y=2\""));
+    }
+
+    #[test]
+    fn test_metadata_opf_is_readable_by_scanner() {
+        let metadata = CalibreMetadata::parse(&metadata_opf(&article())).unwrap();
+        assert_eq!(metadata.title.as_deref(), Some("Fish & <Chips>"));
+        assert_eq!(metadata.author.as_deref(), Some("Weekly Letter"));
+        assert_eq!(
+            metadata.identifiers.get("uuid").map(String::as_str),
+            Some("2f1c7a4e-0000-4000-8000-000000000001")
+        );
+    }
+}
@@ -0,0 +1,244 @@
+//! RSS and Atom feed parsing
+//!
+//! Handles RSS 2.0, RSS 1.0 (RDF), and Atom. Element names are matched
+//! without their namespace prefix, so `content:encoded`, `dc:creator`, and
+//! `dc:date` are picked up wherever they are declared.
+
+use quick_xml::events::{BytesStart, BytesText, Event};
+use quick_xml::Reader;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum FeedError {
+    #[error("Invalid feed XML: {0}")]
+    Xml(#[from] quick_xml::Error),
+
+    #[error("Not an RSS or Atom feed")]
+    NotAFeed,
+}
+
+/// A parsed feed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Feed {
+    pub title: String,
+    pub language: Option<String>,
+    /// Items in document order (usually newest first)
+    pub items: Vec<FeedItem>,
+}
+
+/// One article in a feed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FeedItem {
+    /// GUID or Atom id, falling back to the link or title
+    pub id: String,
+    pub title: String,
+    pub link: Option<String>,
+    pub author: Option<String>,
+    /// Publication date as written in the feed
+    pub published: Option<String>,
+    /// Full content (`content:encoded` or Atom `content`), as HTML
+    pub content: String,
+    /// Summary (`description` or Atom `summary`), as HTML
+    pub summary: String,
+}
+
+/// Parse an RSS or Atom document
+pub fn parse_feed(xml: &str) -> Result<Feed, FeedError> {
+    let mut reader = Reader::from_str(xml);
+    let mut feed = Feed::default();
+    let mut item: Option<FeedItem> = None;
+    let mut stack: Vec<String> = Vec::new();
+    let mut text = String::new();
+    let mut is_feed = false;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) => {
+                let name = local_name(&e);
+                match name.as_str() {
+                    "rss" | "feed" | "RDF" => is_feed = true,
+                    "item" | "entry" => item = Some(FeedItem::default()),
+                    "link" => atom_link(&e, item.as_mut(), &stack),
+                    _ => {}
+                }
+                stack.push(name);
+                text.clear();
+            }
+            Event::Empty(e) => {
+                if local_name(&e) == "link" {
+                    atom_link(&e, item.as_mut(), &stack);
+                }
+            }
+            Event::Text(e) => text.push_str(&unescape_lossy(&e)),
+            Event::CData(e) => text.push_str(&String::from_utf8_lossy(&e.into_inner())),
+            Event::End(_) => {
+                let name = stack.pop().unwrap_or_default();
+                let parent = stack.last().map(String::as_str).unwrap_or("");
+                let value = std::mem::take(&mut text).trim().to_string();
+
+                if name == "item" || name == "entry" {
+                    if let Some(mut done) = item.take() {
+                        if done.id.is_empty() {
+                            done.id = done.link.clone().unwrap_or_else(|| done.title.clone());
+                        }
+                        feed.items.push(done);
+                    }
+                } else if let Some(current) = item.as_mut() {
+                    if parent == "item" || parent == "entry" {
+                        set_item_field(current, &name, value);
+                    } else if parent == "author" && name == "name" && !value.is_empty() {
+                        current.author = Some(value);
+                    }
+                } else if parent == "channel" || parent == "feed" {
+                    match name.as_str() {
+                        "title" => feed.title = value,
+                        "language" if !value.is_empty() => feed.language = Some(value),
+                        _ => {}
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if !is_feed {
+        return Err(FeedError::NotAFeed);
+    }
+    Ok(feed)
+}
+
+fn set_item_field(item: &mut FeedItem, name: &str, value: String) {
+    if value.is_empty() {
+        return;
+    }
+    match name {
+        "title" => item.title = value,
+        "link" => item.link = Some(value),
+        "guid" | "id" => item.id = value,
+        "author" | "creator" => item.author = Some(value),
+        "published" | "pubDate" => item.published = Some(value),
+        "updated" | "date" => {
+            item.published.get_or_insert(value);
+        }
+        "encoded" | "content" => item.content = value,
+        "description" | "summary" => item.summary = value,
+        _ => {}
+    }
+}
+
+/// Atom links carry the URL in `href`; only the alternate link is the article
+fn atom_link(e: &BytesStart, item: Option<&mut FeedItem>, stack: &[String]) {
+    let Some(item) = item else {
+        return;
+    };
+    if stack.last().map(String::as_str) != Some("entry") {
+        return;
+    }
+
+    let mut href = None;
+    let mut alternate = true;
+    for attr in e.attributes().flatten() {
+        let value = attr
+            .unescape_value()
+            .map(|v| v.into_owned())
+            .unwrap_or_default();
+        match attr.key.local_name().as_ref() {
+            b"href" => href = Some(value),
+            b"rel" => alternate = value == "alternate",
+            _ => {}
+        }
+    }
+    if alternate && href.is_some() {
+        item.link = href;
+    }
+}
+
+fn local_name(e: &BytesStart) -> String {
+    String::from_utf8_lossy(e.local_name().as_ref()).into_owned()
+}
+
+/// Unescape XML text, keeping it as-is if it uses entities XML lacks
+fn unescape_lossy(e: &BytesText) -> String {
+    e.unescape()
+        .map(|text| text.into_owned())
+        .unwrap_or_else(|_| String::from_utf8_lossy(e).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rss() {
+        let xml = r#"<?xml version="1.0"?>
+<rss version="2.0" xmlns:content="http://purl.org/rss/1.0/modules/content/"
+     xmlns:dc="http://purl.org/dc/elements/1.1/">
+  <channel>
+    <title>Weekly Letter</title>
+    <link>https://example.com/</link>
+    <language>en</language>
+    <item>
+      <title>Issue &#35;12</title>
+      <link>https://example.com/12</link>
+      <guid isPermaLink="false">letter-12</guid>
+      <dc:creator>Ana</dc:creator>
+      <pubDate>Mon, 06 May 2024 08:00:00 GMT</pubDate>
+      <description>&lt;p&gt;Short&lt;/p&gt;</description>
+      <content:encoded><![CDATA[<p>Full &nbsp;text</p>]]></content:encoded>
+    </item>
+    <item>
+      <title>Issue 11</title>
+      <link>https://example.com/11</link>
+    </item>
+  </channel>
+</rss>"#;
+
+        let feed = parse_feed(xml).unwrap();
+        assert_eq!(feed.title, "Weekly Letter");
+        assert_eq!(feed.language.as_deref(), Some("en"));
+        assert_eq!(feed.items.len(), 2);
+
+        let item = &feed.items[0];
+        assert_eq!(item.id, "letter-12");
+        assert_eq!(item.title, "Issue #12");
+        assert_eq!(item.author.as_deref(), Some("Ana"));
+        assert_eq!(item.summary, "<p>Short</p>");
+        assert_eq!(item.content, "<p>Full &nbsp;text</p>");
+        // No GUID: the link identifies the item
+        assert_eq!(feed.items[1].id, "https://example.com/11");
+    }
+
+    #[test]
+    fn test_parse_atom() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>A Blog</title>
+  <link href="https://blog.example/" rel="alternate"/>
+  <entry>
+    <title>Hello</title>
+    <id>tag:blog.example,2024:1</id>
+    <link rel="replies" href="https://blog.example/hello#comments"/>
+    <link href="https://blog.example/hello"/>
+    <updated>2024-05-02T00:00:00Z</updated>
+    <published>2024-05-01T00:00:00Z</published>
+    <author><name>Ben</name></author>
+    <content type="html">&lt;p&gt;Hi&lt;/p&gt;</content>
+  </entry>
+</feed>"#;
+
+        let feed = parse_feed(xml).unwrap();
+        assert_eq!(feed.title, "A Blog");
+        let entry = &feed.items[0];
+        assert_eq!(entry.id, "tag:blog.example,2024:1");
+        assert_eq!(entry.link.as_deref(), Some("https://blog.example/hello"));
+        assert_eq!(entry.author.as_deref(), Some("Ben"));
+        assert_eq!(entry.published.as_deref(), Some("2024-05-01T00:00:00Z"));
+        assert_eq!(entry.content, "<p>Hi</p>");
+
+        assert!(matches!(
+            parse_feed("<html><body/></html>"),
+            Err(FeedError::NotAFeed)
+        ));
+    }
+}
@@ -0,0 +1,298 @@
+//! Feed ingestion: RSS and Atom articles as library books
+//!
+//! The [`Ingestor`] polls the feeds in [`IngestConfig`], turns each new
+//! article into a single-chapter EPUB, and files it into the library and a
+//! collection ("Inbox" by default), making the server a reading queue for
+//! newsletters and blogs.
+//!
+//! For each item the article page is fetched and run through
+//! [`readability::extract`]; the feed's own content is used when that fails
+//! or yields less text. Books are written under `<feed title>/<article
+//! title> [<hash>]/` with a `metadata.opf`, the layout the library scanner
+//! reads, so they appear in the catalog after the next rescan. The hash comes
+//! from the item's GUID, and an item whose folder already exists is skipped,
+//! so polling is idempotent without any state outside the bucket.
+//!
+//! A run that adds articles leaves a notification for the collection's owner.
+//!
+//! [`IngestConfig`]: crate::config::IngestConfig
+
+pub mod epub;
+pub mod feed;
+pub mod readability;
+
+use std::fmt;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+
+use crate::config::IngestConfig;
+use crate::db::{CollectionRepository, NewNotification, NotificationKind, NotificationRepository};
+use crate::storage::S3Client;
+
+use epub::Article;
+use feed::{Feed, FeedItem};
+
+/// Collection source recorded for ingested articles
+const SOURCE: &str = "feed";
+
+/// Longest folder name written for a feed or article title
+const MAX_NAME_CHARS: usize = 80;
+
+/// Polls feeds and files new articles into the library
+pub struct Ingestor {
+    s3: S3Client,
+    pool: SqlitePool,
+    http: reqwest::Client,
+    config: IngestConfig,
+}
+
+/// Outcome of one [`Ingestor::run`]
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestSummary {
+    pub feeds: usize,
+    /// Feeds that could not be fetched or parsed
+    pub failed_feeds: usize,
+    pub added: usize,
+    /// Items already in the library
+    pub existing: usize,
+    /// Items that could not be converted
+    pub failed: usize,
+}
+
+impl fmt::Display for IngestSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Added {} articles from {} feeds ({} already present, {} failed, {} feeds unreachable)",
+            self.added, self.feeds, self.existing, self.failed, self.failed_feeds
+        )
+    }
+}
+
+impl Ingestor {
+    pub fn new(s3: S3Client, pool: SqlitePool, config: IngestConfig) -> Self {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .user_agent(concat!("amnesia-server/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .unwrap_or_default();
+        Self {
+            s3,
+            pool,
+            http,
+            config,
+        }
+    }
+
+    /// Poll every feed once
+    ///
+    /// A feed that cannot be fetched is logged and counted; the run only
+    /// fails if no feed could be read.
+    pub async fn run(&self) -> Result<IngestSummary> {
+        let mut summary = IngestSummary {
+            feeds: self.config.feeds.len(),
+            ..Default::default()
+        };
+        let mut added_by_feed = Vec::new();
+
+        for url in &self.config.feeds {
+            let feed = match self.fetch_feed(url).await {
+                Ok(feed) => feed,
+                Err(e) => {
+                    tracing::warn!(feed = %url, "Feed ingestion failed: {:#}", e);
+                    summary.failed_feeds += 1;
+                    continue;
+                }
+            };
+
+            let mut feed_added = 0;
+            for item in feed.items.iter().take(self.config.max_items) {
+                match self.ingest_item(&feed, item).await {
+                    Ok(true) => feed_added += 1,
+                    Ok(false) => summary.existing += 1,
+                    Err(e) => {
+                        tracing::warn!(feed = %url, item = %item.id, "Article ingestion failed: {:#}", e);
+                        summary.failed += 1;
+                    }
+                }
+            }
+            if feed_added > 0 {
+                added_by_feed.push(format!("{} from {}", feed_added, feed.title));
+                summary.added += feed_added;
+            }
+        }
+
+        if summary.added > 0 {
+            let notification = NewNotification {
+                user_id: self.config.user_id.clone(),
+                ..NewNotification::new(
+                    NotificationKind::Ingest,
+                    format!(
+                        "Added {} articles to {}",
+                        summary.added, self.config.collection
+                    ),
+                )
+                .with_body(added_by_feed.join(", "))
+            };
+            NotificationRepository::new(&self.pool)
+                .notify(notification)
+                .await;
+        }
+
+        if summary.feeds > 0 && summary.failed_feeds == summary.feeds {
+            bail!("None of the {} feeds could be read", summary.feeds);
+        }
+        Ok(summary)
+    }
+
+    async fn fetch_feed(&self, url: &str) -> Result<Feed> {
+        let xml = self.fetch_text(url).await?;
+        let mut feed = feed::parse_feed(&xml)?;
+        if feed.title.trim().is_empty() {
+            feed.title = url.to_string();
+        }
+        Ok(feed)
+    }
+
+    async fn fetch_text(&self, url: &str) -> Result<String> {
+        let response = self
+            .http
+            .get(url)
+            .send()
+            .await
+            .with_context(|| format!("Request to {} failed", url))?
+            .error_for_status()?;
+        Ok(response.text().await?)
+    }
+
+    /// Convert and store one item; `false` if it was already ingested
+    async fn ingest_item(&self, feed: &Feed, item: &FeedItem) -> Result<bool> {
+        let folder = article_folder(&feed.title, item);
+        let metadata_key = format!("{}/metadata.opf", folder);
+        if self.s3.object_exists(&metadata_key).await? {
+            return Ok(false);
+        }
+
+        let mut blocks = readability::extract(if item.content.is_empty() {
+            &item.summary
+        } else {
+            &item.content
+        });
+        if let Some(link) = item.link.as_deref().filter(|_| self.config.fetch_articles) {
+            match self.fetch_text(link).await {
+                Ok(html) => {
+                    let page = readability::extract(&html);
+                    if readability::text_len(&page) > readability::text_len(&blocks) {
+                        blocks = page;
+                    }
+                }
+                Err(e) => tracing::debug!(link, "Using feed content: {:#}", e),
+            }
+        }
+        if blocks.is_empty() {
+            bail!("No readable content");
+        }
+
+        let article = Article {
+            id: uuid::Uuid::new_v4().to_string(),
+            title: if item.title.is_empty() {
+                "Untitled".to_string()
+            } else {
+                item.title.clone()
+            },
+            author: item.author.clone().unwrap_or_else(|| feed.title.clone()),
+            published: item.published.clone(),
+            language: feed.language.clone(),
+            source_url: item.link.clone(),
+            blocks,
+        };
+
+        let data = epub::build_epub(&article).context("Failed to build EPUB")?;
+        let epub_key = format!("{}/{}.epub", folder, folder_name(&article.title));
+        self.s3
+            .put_object(&epub_key, data, "application/epub+zip")
+            .await?;
+        // Written last: its presence marks the item as ingested
+        self.s3
+            .put_object(
+                &metadata_key,
+                epub::metadata_opf(&article).into_bytes(),
+                "application/oebps-package+xml",
+            )
+            .await?;
+
+        let collections = CollectionRepository::new(&self.pool);
+        let collection = collections
+            .get_or_create(
+                &self.config.collection,
+                self.config.user_id.as_deref(),
+                SOURCE,
+            )
+            .await?;
+        collections.add_book(&collection.id, &article.id).await?;
+
+        tracing::info!(key = %epub_key, "Ingested article");
+        Ok(true)
+    }
+}
+
+/// `<feed title>/<article title> [<hash>]`, with the hash taken from the
+/// item's ID so the same item always maps to the same folder
+pub fn article_folder(feed_title: &str, item: &FeedItem) -> String {
+    let hash = hex::encode(Sha256::digest(item.id.as_bytes()));
+    format!(
+        "{}/{} [{}]",
+        folder_name(feed_title),
+        folder_name(&item.title),
+        &hash[..8]
+    )
+}
+
+/// A title made safe for use as one path segment
+fn folder_name(title: &str) -> String {
+    let name: String = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .take(MAX_NAME_CHARS)
+        .collect();
+    let name = name.trim().trim_matches('.').trim();
+    if name.is_empty() {
+        "Untitled".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_article_folder() {
+        let item = FeedItem {
+            id: "letter-12".to_string(),
+            title: "Issue 12: What/Why?".to_string(),
+            ..Default::default()
+        };
+        let folder = article_folder("Weekly Letter", &item);
+        assert!(folder.starts_with("Weekly Letter/Issue 12_ What_Why_ ["));
+        assert_eq!(folder, article_folder("Weekly Letter", &item));
+
+        let other = FeedItem {
+            id: "letter-13".to_string(),
+            ..item
+        };
+        assert_ne!(folder, article_folder("Weekly Letter", &other));
+        assert_eq!(folder_name("  ..  "), "Untitled");
+        assert_eq!(folder_name(&"x".repeat(200)).len(), MAX_NAME_CHARS);
+    }
+}
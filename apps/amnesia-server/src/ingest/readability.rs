@@ -0,0 +1,239 @@
+//! Readable text extraction from web pages and feed HTML
+//!
+//! Reduces HTML to a flat list of text blocks (paragraphs, headings, list
+//! items, quotes, preformatted text) that can be written back out as strict
+//! XHTML. Page chrome (`nav`, `header`, `footer`, `aside`, forms, scripts)
+//! is dropped, and when the page marks up its content with `<article>` or
+//! `<main>` only that part is kept.
+//!
+//! lol_html strips the markup while marking where each block starts and ends
+//! with control characters, and the marked text is then split into blocks.
+//! Inline formatting and images are not kept.
+
+use lol_html::html_content::ContentType;
+use lol_html::{comments, element, rewrite_str, RewriteStrSettings};
+
+const OPEN: char = '\u{1}';
+const CLOSE: char = '\u{2}';
+
+/// Elements removed together with their content
+const CHROME: &[&str] = &[
+    "head", "script", "style", "noscript", "template", "nav", "header", "footer", "aside", "form",
+    "button", "iframe", "svg", "canvas", "video", "audio",
+];
+
+/// Kind of a text block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockKind {
+    Paragraph,
+    /// Heading level 1-6
+    Heading(u8),
+    ListItem,
+    Quote,
+    /// Whitespace is significant
+    Preformatted,
+}
+
+/// A block of plain text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub kind: BlockKind,
+    pub text: String,
+}
+
+/// Extract the readable blocks of an HTML page or fragment
+pub fn extract(html: &str) -> Vec<Block> {
+    let marked = match mark_blocks(strip_doctype(html)) {
+        Ok(marked) => marked,
+        Err(e) => {
+            tracing::debug!("Could not rewrite HTML for extraction: {}", e);
+            return Vec::new();
+        }
+    };
+
+    // (block, inside <article>, inside <main>)
+    let mut blocks: Vec<(Block, bool, bool)> = Vec::new();
+    let (mut article, mut main) = (0usize, 0usize);
+
+    let mut segments = marked.split(OPEN);
+    let leading = segments.next().unwrap_or_default();
+    push_block(&mut blocks, BlockKind::Paragraph, leading, false, false);
+
+    for segment in segments {
+        let (tag, raw) = segment.split_once(CLOSE).unwrap_or(("", segment));
+        let kind = match tag {
+            "article" => {
+                article += 1;
+                BlockKind::Paragraph
+            }
+            "/article" => {
+                article = article.saturating_sub(1);
+                BlockKind::Paragraph
+            }
+            "main" => {
+                main += 1;
+                BlockKind::Paragraph
+            }
+            "/main" => {
+                main = main.saturating_sub(1);
+                BlockKind::Paragraph
+            }
+            _ => block_kind(tag).unwrap_or(BlockKind::Paragraph),
+        };
+        push_block(&mut blocks, kind, raw, article > 0, main > 0);
+    }
+
+    let keep: fn(&(Block, bool, bool)) -> bool = if blocks.iter().any(|b| b.1) {
+        |b| b.1
+    } else if blocks.iter().any(|b| b.2) {
+        |b| b.2
+    } else {
+        |_| true
+    };
+    blocks
+        .into_iter()
+        .filter(keep)
+        .map(|(block, _, _)| block)
+        .collect()
+}
+
+/// Number of characters of text in `blocks`
+pub fn text_len(blocks: &[Block]) -> usize {
+    blocks.iter().map(|b| b.text.chars().count()).sum()
+}
+
+/// Strip all markup, leaving text with block boundaries marked as
+/// `OPEN tag CLOSE`; a bare `OPEN CLOSE` ends a block
+fn mark_blocks(html: &str) -> Result<String, lol_html::errors::RewritingError> {
+    rewrite_str(
+        html,
+        RewriteStrSettings {
+            element_content_handlers: vec![
+                element!("*", |el| {
+                    if el.removed() {
+                        return Ok(());
+                    }
+                    let tag = el.tag_name();
+                    if CHROME.contains(&tag.as_str()) {
+                        el.remove();
+                        return Ok(());
+                    }
+
+                    if matches!(tag.as_str(), "article" | "main") {
+                        el.before(&format!("{}{}{}", OPEN, tag, CLOSE), ContentType::Html);
+                        el.after(&format!("{}/{}{}", OPEN, tag, CLOSE), ContentType::Html);
+                    } else if block_kind(&tag).is_some() {
+                        el.before(&format!("{}{}{}", OPEN, tag, CLOSE), ContentType::Html);
+                        el.after(&format!("{}{}", OPEN, CLOSE), ContentType::Html);
+                    } else if tag == "br" {
+                        el.before("\n", ContentType::Html);
+                    }
+                    el.remove_and_keep_content();
+                    Ok(())
+                }),
+                comments!("*", |c| {
+                    c.remove();
+                    Ok(())
+                }),
+            ],
+            ..RewriteStrSettings::default()
+        },
+    )
+}
+
+fn block_kind(tag: &str) -> Option<BlockKind> {
+    Some(match tag {
+        "p" | "div" | "section" | "dd" | "dt" | "td" | "figcaption" | "tr" => BlockKind::Paragraph,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => BlockKind::Heading(tag.as_bytes()[1] - b'0'),
+        "li" => BlockKind::ListItem,
+        "blockquote" => BlockKind::Quote,
+        "pre" => BlockKind::Preformatted,
+        _ => return None,
+    })
+}
+
+fn push_block(
+    blocks: &mut Vec<(Block, bool, bool)>,
+    kind: BlockKind,
+    raw: &str,
+    in_article: bool,
+    in_main: bool,
+) {
+    let decoded = html_escape::decode_html_entities(raw);
+    let text = if kind == BlockKind::Preformatted {
+        decoded.trim_matches('\n').to_string()
+    } else {
+        decoded.split_whitespace().collect::<Vec<_>>().join(" ")
+    };
+    if !text.trim().is_empty() {
+        blocks.push((Block { kind, text }, in_article, in_main));
+    }
+}
+
+fn strip_doctype(html: &str) -> &str {
+    let trimmed = html.trim_start();
+    if trimmed
+        .get(..9)
+        .is_some_and(|start| start.eq_ignore_ascii_case("<!doctype"))
+    {
+        if let Some(end) = trimmed.find('>') {
+            return &trimmed[end + 1..];
+        }
+    }
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(blocks: &[Block]) -> Vec<&str> {
+        blocks.iter().map(|b| b.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_extract_article() {
+        let html = r#"<!DOCTYPE html>
+<html><head><title>Page</title><script>var x = "<p>no</p>";</script></head>
+<body>
+  <nav><a href="/">Home</a></nav>
+  <p>Sidebar blurb</p>
+  <article>
+    <h2>The   Title</h2>
+    <p>First &amp; <em>second</em>&nbsp;words.</p>
+    <!-- comment -->
+    <ul><li>One</li><li>Two</li></ul>
+    <pre>  indented
+code</pre>
+    <footer>Share this</footer>
+  </article>
+</body></html>"#;
+
+        let blocks = extract(html);
+        assert_eq!(
+            texts(&blocks),
+            vec![
+                "The Title",
+                "First & second words.",
+                "One",
+                "Two",
+                "  indented\ncode"
+            ]
+        );
+        assert_eq!(blocks[0].kind, BlockKind::Heading(2));
+        assert_eq!(blocks[2].kind, BlockKind::ListItem);
+        assert_eq!(blocks[4].kind, BlockKind::Preformatted);
+    }
+
+    #[test]
+    fn test_extract_fragment() {
+        // Feed summaries are often bare text or a single paragraph
+        assert_eq!(texts(&extract("Just text")), vec!["Just text"]);
+        assert_eq!(
+            texts(&extract("<p>One</p> loose <p>Two</p>")),
+            vec!["One", "loose", "Two"]
+        );
+        assert_eq!(text_len(&extract("<p>abc</p><p>de</p>")), 5);
+        assert!(extract("<script>x()</script>").is_empty());
+    }
+}
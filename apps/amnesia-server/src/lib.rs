@@ -1,19 +1,93 @@
 //! Amnesia Server Library
 //!
-//! This crate exposes types needed for benchmarking and testing.
-//! The main server binary is in main.rs.
+//! The document pipeline behind the Amnesia server, usable from other Rust
+//! applications without pulling in the HTTP layer. The `amnesia-server`
+//! binary in `main.rs` is a thin wrapper around this crate.
+//!
+//! # Feature flags
+//!
+//! - `server` (default): Axum routes, shared [`state::AppState`], and the
+//!   `IntoResponse` impls for error types. Implies `s3` and `link-check`.
+//! - `s3` (default): S3-compatible storage client, library scanning, and
+//!   chunked uploads.
+//! - `ocr` (default): OCR providers and PDF text-layer injection.
+//! - `ocr-tesseract`: local Tesseract OCR provider. Implies `ocr`.
+//! - `import`: importers for Calibre-Web and Komga data and Kindle
+//!   clippings.
+//! - `ingest` (default): RSS/Atom feed ingestion into the library. Implies
+//!   `s3`.
+//! - `link-check`: dead-link checks for the web links in documents.
+//! - `cli` (default): the `los-libros` maintenance binary. Implies `s3` and
+//!   `import`.
+//!
+//! With `default-features = false` the crate still provides document parsing,
+//! rendering, caching, annotations, sync, CFI handling, OPDS feed generation,
+//! bibliography export, and vault export.
 //!
 //! # Modules
 //!
 //! - `document`: Unified document abstraction (format-agnostic)
 //! - `formats`: Format-specific implementations (PDF, EPUB)
 //! - `pdf`: Low-level PDF parsing via MuPDF
+//! - `byte_cache`: LRU cache bounded by bytes, used by the render caches
+//! - `annotations`: Annotation model and SQLite store
+//! - `sync`: Multi-device sync operations and conflict resolution
+//! - `db`: SQLite connection, schema, and progress/highlight queries
+//! - `cfi`: EPUB CFI types (re-exported from `cfi-core`)
+//! - `html`: Highlight injection into rendered chapter HTML
+//! - `library`: Calibre library model and metadata parsing
+//! - `opds`: OPDS 1.2 feed generation
+//! - `feeds`: Atom and JSON Feed of newly added books
+//! - `bibliography`: Citation and bibliography export
+//! - `config`: Environment-driven configuration
+//! - `pagination`: Limit/offset pagination and sorting for list endpoints
+//! - `scheduler`: Cron-scheduled background maintenance tasks
+//! - `import`: Users, progress, and collections from other book servers,
+//!   and Kindle highlights
+//! - `ingest`: Feed articles converted to EPUBs and filed into the library
+//! - `link_check`: Rate-limited liveness checks of documents' web links
+//! - `vault`: Highlights and notes exported as an Obsidian-style Markdown vault
+//! - `error`: Crate-wide error types
 
-// Core modules needed for benchmarks
+pub mod annotations;
+pub mod bibliography;
+pub mod byte_cache;
+pub mod cfi;
+pub mod config;
+pub mod db;
 pub mod document;
+pub mod error;
+pub mod feeds;
 pub mod formats;
+pub mod html;
+pub mod library;
+pub mod opds;
+pub mod pagination;
 pub mod pdf;
+pub mod scheduler;
+pub mod sync;
+pub mod vault;
+
+#[cfg(feature = "import")]
+pub mod import;
+#[cfg(feature = "ocr")]
+pub mod ocr;
+
+#[cfg(feature = "ingest")]
+pub mod ingest;
+#[cfg(feature = "link-check")]
+pub mod link_check;
+#[cfg(feature = "s3")]
+pub mod storage;
+#[cfg(feature = "s3")]
+pub mod upload;
+
+#[cfg(feature = "server")]
+pub mod routes;
+#[cfg(feature = "server")]
+pub mod state;
 
-// Internal modules that document/formats/pdf depend on
-// These are not exposed publicly but are needed for compilation
+// MuPDF bindings are an implementation detail of `pdf` and `formats`; the
+// server only schedules the context pool's health checks
 mod mupdf;
+pub use mupdf::spawn_pool_health_checks;
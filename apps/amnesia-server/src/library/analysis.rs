@@ -0,0 +1,140 @@
+//! Text statistics for library books
+//!
+//! Downloads each book's primary EPUB or PDF, extracts its text, and stores
+//! the [`TextStats`] in the database. A book is only analyzed again when its
+//! primary file changes, so repeated runs are cheap.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+use crate::config::{Config, IsolationConfig, IsolationMode};
+use crate::db::TextStatsRepository;
+use crate::document::{DocumentParser, DocumentResult};
+use crate::error::{AppError, Result};
+use crate::formats::epub::{EpubDocumentHandler, EpubLimits};
+use crate::formats::isolation;
+use crate::formats::pdf::PdfDocumentHandler;
+use crate::storage::S3Client;
+
+use super::book::{FormatType, LibraryBook};
+use super::text_stats::{TextAnalyzer, TextStats};
+
+/// Computes and stores text statistics for library books
+pub struct TextStatsIndexer {
+    s3: S3Client,
+    pool: SqlitePool,
+    epub: EpubLimits,
+    isolation: IsolationConfig,
+}
+
+/// Outcome of one [`TextStatsIndexer::run`]
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalysisSummary {
+    pub analyzed: usize,
+    /// Books whose statistics were already up to date
+    pub unchanged: usize,
+    /// Books without an EPUB or PDF
+    pub skipped: usize,
+    pub failed: usize,
+}
+
+impl fmt::Display for AnalysisSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Analyzed {} books ({} unchanged, {} without text, {} failed)",
+            self.analyzed, self.unchanged, self.skipped, self.failed
+        )
+    }
+}
+
+impl TextStatsIndexer {
+    pub fn new(s3: S3Client, pool: SqlitePool, config: &Config) -> Self {
+        Self {
+            s3,
+            pool,
+            epub: config.epub,
+            isolation: config.isolation.clone(),
+        }
+    }
+
+    /// Analyze books whose statistics are missing or stale (all books with
+    /// `force`)
+    pub async fn run(&self, books: &[LibraryBook], force: bool) -> Result<AnalysisSummary> {
+        let repo = TextStatsRepository::new(&self.pool);
+        let analyzed: HashMap<String, String> = repo.source_keys().await?.into_iter().collect();
+        let mut summary = AnalysisSummary::default();
+
+        for book in books {
+            let Some(format) = book
+                .primary_format()
+                .filter(|f| matches!(f.format, FormatType::Epub | FormatType::Pdf))
+            else {
+                summary.skipped += 1;
+                continue;
+            };
+
+            let book_id = book.stable_id();
+            if !force && analyzed.get(&book_id) == Some(&format.s3_key) {
+                summary.unchanged += 1;
+                continue;
+            }
+
+            match self.analyze(&book_id, &format.s3_key, format.format).await {
+                Ok(stats) => {
+                    repo.upsert(&book_id, &format.s3_key, &stats).await?;
+                    summary.analyzed += 1;
+                }
+                Err(e) => {
+                    tracing::warn!(key = %format.s3_key, "Text analysis failed: {}", e);
+                    summary.failed += 1;
+                }
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Compute the statistics for one file
+    pub async fn analyze(&self, book_id: &str, key: &str, format: FormatType) -> Result<TextStats> {
+        let data = self.s3.get_object(key).await?.data;
+
+        if self.isolation.mode == IsolationMode::Subprocess {
+            isolation::parse_isolated(&data, book_id, self.isolation.timeout_secs)
+                .await
+                .map_err(|e| AppError::BadRequest(format!("Isolated parse failed: {}", e)))?;
+        }
+
+        let stats = match format {
+            FormatType::Pdf => {
+                let handler = PdfDocumentHandler::from_bytes(data, book_id.to_string())
+                    .map_err(parse_error)?;
+                document_text_stats(&handler).await
+            }
+            _ => {
+                let handler = EpubDocumentHandler::load(data, book_id.to_string(), self.epub)
+                    .await
+                    .map_err(parse_error)?;
+                document_text_stats(&handler).await
+            }
+        };
+        stats.map_err(parse_error)
+    }
+}
+
+/// Text statistics over every item of a parsed document
+pub async fn document_text_stats(parser: &dyn DocumentParser) -> DocumentResult<TextStats> {
+    let mut analyzer = TextAnalyzer::new();
+    for item in 0..parser.item_count() {
+        analyzer.add_text(&parser.extract_text(item).await?);
+    }
+    Ok(analyzer.finish())
+}
+
+fn parse_error(e: impl fmt::Display) -> AppError {
+    AppError::BadRequest(format!("Failed to read document text: {}", e))
+}
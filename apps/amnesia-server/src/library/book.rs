@@ -5,6 +5,8 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use uuid::Uuid;
 
+use super::maturity::MaturityRating;
+
 /// A book in the library
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LibraryBook {
@@ -47,6 +49,18 @@ pub struct LibraryBook {
     /// Identifiers (isbn, uuid, amazon, etc.)
     pub identifiers: HashMap<String, String>,
 
+    /// Normalized maturity rating, if the metadata gives one
+    #[serde(default)]
+    pub maturity: Option<MaturityRating>,
+
+    /// Content warnings (e.g., "violence")
+    #[serde(default)]
+    pub content_warnings: Vec<String>,
+
+    /// Calibre rating, 0-10 (two per star)
+    #[serde(default)]
+    pub rating: Option<u8>,
+
     /// Available formats with their S3 keys
     pub formats: Vec<BookFormat>,
 
@@ -59,7 +73,7 @@ pub struct LibraryBook {
     /// When the book was added to the library
     pub added_at: DateTime<Utc>,
 
-    /// When the book metadata was last updated
+    /// When the book's files or metadata were last updated
     pub updated_at: DateTime<Utc>,
 }
 
@@ -81,6 +95,9 @@ impl LibraryBook {
             series_index: None,
             tags: Vec::new(),
             identifiers: HashMap::new(),
+            maturity: None,
+            content_warnings: Vec::new(),
+            rating: None,
             formats: Vec::new(),
             cover_key: None,
             s3_prefix,
@@ -104,6 +121,18 @@ impl LibraryBook {
         self.formats.iter().find(|f| f.format == FormatType::Epub)
     }
 
+    /// Stable ID to store per-book data under
+    ///
+    /// [`LibraryBook::id`] is regenerated on every scan, so this uses the
+    /// Calibre UUID from `metadata.opf` (what clients use as the book ID), or
+    /// the book's S3 prefix when there is none.
+    pub fn stable_id(&self) -> String {
+        self.identifiers
+            .get("uuid")
+            .cloned()
+            .unwrap_or_else(|| self.s3_prefix.clone())
+    }
+
     /// Get display author (first author or "Unknown")
     pub fn display_author(&self) -> &str {
         self.author.as_deref().unwrap_or("Unknown Author")
@@ -0,0 +1,336 @@
+//! Zip bundles of library books
+//!
+//! [`plan_bundle`] picks one file per book (EPUB first, then PDF, then
+//! whatever else the book has) and names it "Author - Title.ext";
+//! [`stream_bundle`] then writes the zip as it reads each file from S3, so
+//! only the file being added is ever held in memory, never the bundle.
+//! Books are already compressed, so entries are stored as-is.
+
+use std::collections::HashSet;
+use std::io::{self, Seek, SeekFrom, Write};
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use tokio::sync::mpsc;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use super::{FormatType, LibraryBook};
+use crate::error::{AppError, Result};
+use crate::storage::S3Client;
+
+/// Largest bundle that may be requested
+pub const MAX_BUNDLE_BYTES: u64 = 8 * 1024 * 1024 * 1024;
+
+/// Formats in order of preference when a book has several
+const FORMAT_PREFERENCE: [FormatType; 7] = [
+    FormatType::Epub,
+    FormatType::Pdf,
+    FormatType::Cbz,
+    FormatType::Azw3,
+    FormatType::Mobi,
+    FormatType::Fb2,
+    FormatType::Cbr,
+];
+
+/// A file to put in a bundle
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BundleEntry {
+    /// Name inside the zip
+    pub name: String,
+    pub s3_key: String,
+    pub size: u64,
+}
+
+/// The files for a set of books
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BundlePlan {
+    pub entries: Vec<BundleEntry>,
+    /// Book IDs not in the library or without a file
+    pub missing: Vec<String>,
+    /// Sum of the entries' sizes
+    pub total_bytes: u64,
+}
+
+/// Choose a file for each book, in the order given
+pub fn plan_bundle(library: &[LibraryBook], book_ids: &[String]) -> BundlePlan {
+    let mut plan = BundlePlan::default();
+    let mut names = HashSet::new();
+
+    for book_id in book_ids {
+        let Some(book) = library.iter().find(|book| book.stable_id() == *book_id) else {
+            plan.missing.push(book_id.clone());
+            continue;
+        };
+        let Some(format) = book.formats.iter().min_by_key(|format| {
+            FORMAT_PREFERENCE
+                .iter()
+                .position(|preferred| *preferred == format.format)
+                .unwrap_or(FORMAT_PREFERENCE.len())
+        }) else {
+            plan.missing.push(book_id.clone());
+            continue;
+        };
+
+        let extension = format.s3_key.rsplit_once('.').map(|(_, ext)| ext);
+        let size = format.size.max(0) as u64;
+        plan.total_bytes += size;
+        plan.entries.push(BundleEntry {
+            name: unique_name(&mut names, book, extension),
+            s3_key: format.s3_key.clone(),
+            size,
+        });
+    }
+
+    plan
+}
+
+/// "Author - Title.ext", numbered if another book took the name
+fn unique_name(taken: &mut HashSet<String>, book: &LibraryBook, extension: Option<&str>) -> String {
+    let stem = sanitize(&format!("{} - {}", book.display_author(), book.title));
+    let suffix = extension.map(|ext| format!(".{}", ext)).unwrap_or_default();
+
+    let mut name = format!("{}{}", stem, suffix);
+    let mut n = 2;
+    while !taken.insert(name.to_lowercase()) {
+        name = format!("{} ({}){}", stem, n, suffix);
+        n += 1;
+    }
+    name
+}
+
+/// Replace characters that are not allowed in file names
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Write a bundle's zip to `chunks` as it is built
+///
+/// A failure is sent as the last chunk, so the receiver can tell a
+/// truncated zip from a finished one.
+pub async fn stream_bundle(
+    s3: S3Client,
+    entries: Vec<BundleEntry>,
+    chunks: mpsc::Sender<io::Result<Vec<u8>>>,
+) {
+    if let Err(e) = write_bundle(&s3, &entries, &chunks).await {
+        let _ = chunks.send(Err(io::Error::other(e.to_string()))).await;
+    }
+}
+
+async fn write_bundle(
+    s3: &S3Client,
+    entries: &[BundleEntry],
+    chunks: &mpsc::Sender<io::Result<Vec<u8>>>,
+) -> Result<()> {
+    let buffer = ZipBuffer::default();
+    let mut zip = ZipWriter::new(buffer.clone());
+
+    for entry in entries {
+        let options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Stored)
+            .large_file(entry.size >= u32::MAX as u64);
+        // Starting a file finishes the one before, so everything written
+        // before its header is final
+        let header_start = buffer.len();
+        zip.start_file(entry.name.as_str(), options)
+            .map_err(zip_error)?;
+        send(chunks, buffer.take_until(header_start)).await?;
+
+        let mut object = s3.get_object_stream(&entry.s3_key).await?;
+        while let Some(bytes) = object
+            .try_next()
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to read {}: {}", entry.s3_key, e)))?
+        {
+            zip.write_all(&bytes)?;
+        }
+    }
+
+    zip.finish().map_err(zip_error)?;
+    send(chunks, buffer.take_until(buffer.len())).await
+}
+
+/// Send a chunk, failing if the receiver went away (the client hung up)
+async fn send(chunks: &mpsc::Sender<io::Result<Vec<u8>>>, chunk: Vec<u8>) -> Result<()> {
+    if chunk.is_empty() {
+        return Ok(());
+    }
+    chunks
+        .send(Ok(chunk))
+        .await
+        .map_err(|_| AppError::Internal("Bundle download was cancelled".to_string()))
+}
+
+fn zip_error(e: zip::result::ZipError) -> AppError {
+    AppError::Internal(format!("Failed to write zip: {}", e))
+}
+
+/// The zip as the writer builds it, from the first byte not yet sent
+///
+/// [`ZipWriter`] seeks back to fill in a file's CRC and sizes once the file
+/// is written, so the current file stays here until the next one starts;
+/// only earlier bytes can be taken.
+#[derive(Clone, Default)]
+struct ZipBuffer(Arc<Mutex<ZipBufferState>>);
+
+#[derive(Default)]
+struct ZipBufferState {
+    bytes: Vec<u8>,
+    /// Zip offset of `bytes[0]`
+    start: u64,
+    /// Zip offset the writer is at
+    position: u64,
+}
+
+impl ZipBuffer {
+    /// Zip offset of the end of what has been written
+    fn len(&self) -> u64 {
+        let state = self.0.lock();
+        state.start + state.bytes.len() as u64
+    }
+
+    /// The bytes before zip offset `end`, which the writer must be done with
+    fn take_until(&self, end: u64) -> Vec<u8> {
+        let mut state = self.0.lock();
+        let count = (end - state.start) as usize;
+        state.start = end;
+        state.bytes.drain(..count).collect()
+    }
+}
+
+impl Write for ZipBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut state = self.0.lock();
+        let Some(offset) = state.position.checked_sub(state.start) else {
+            return Err(io::Error::other(
+                "Zip writer went back to bytes already sent",
+            ));
+        };
+        let offset = offset as usize;
+        let overwrite = buf.len().min(state.bytes.len().saturating_sub(offset));
+        state.bytes[offset..offset + overwrite].copy_from_slice(&buf[..overwrite]);
+        state.bytes.extend_from_slice(&buf[overwrite..]);
+        state.position += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for ZipBuffer {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let mut state = self.0.lock();
+        let end = state.start + state.bytes.len() as u64;
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => end.checked_add_signed(delta),
+            SeekFrom::Current(delta) => state.position.checked_add_signed(delta),
+        };
+        match position {
+            Some(position) if position <= end => {
+                state.position = position;
+                Ok(position)
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Seek outside the zip",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::BookFormat;
+
+    fn book(title: &str, author: &str, formats: &[(FormatType, &str, i64)]) -> LibraryBook {
+        let mut book = LibraryBook::new(title.to_string(), format!("{}/{}", author, title));
+        book.author = Some(author.to_string());
+        book.formats = formats
+            .iter()
+            .map(|(format, key, size)| BookFormat {
+                format: *format,
+                s3_key: key.to_string(),
+                size: *size,
+            })
+            .collect();
+        book
+    }
+
+    #[test]
+    fn test_plan_bundle() {
+        let mut library = vec![
+            book(
+                "Dune",
+                "Frank Herbert",
+                &[
+                    (FormatType::Pdf, "Herbert/Dune/dune.pdf", 900),
+                    (FormatType::Epub, "Herbert/Dune/dune.epub", 300),
+                ],
+            ),
+            book(
+                "Dune",
+                "Frank Herbert",
+                &[(FormatType::Mobi, "Herbert/Dune (2)/dune.mobi", 200)],
+            ),
+            book("Why? How: A/B", "Anon", &[]),
+        ];
+        library[1].s3_prefix = "Frank Herbert/Dune (2)".to_string();
+        let ids: Vec<String> = library
+            .iter()
+            .map(LibraryBook::stable_id)
+            .chain(["gone".to_string()])
+            .collect();
+
+        let plan = plan_bundle(&library, &ids);
+        let names: Vec<&str> = plan.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["Frank Herbert - Dune.epub", "Frank Herbert - Dune.mobi"]
+        );
+        assert_eq!(plan.entries[0].s3_key, "Herbert/Dune/dune.epub");
+        assert_eq!(plan.total_bytes, 500);
+        assert_eq!(plan.missing, vec![ids[2].clone(), "gone".to_string()]);
+
+        // Same name and extension get a number
+        let twice = plan_bundle(&library, &[ids[0].clone(), ids[0].clone()]);
+        assert_eq!(twice.entries[1].name, "Frank Herbert - Dune (2).epub");
+        assert_eq!(sanitize(" Why? How: A/B "), "Why_ How_ A_B");
+    }
+
+    #[test]
+    fn test_zip_buffer_sends_finished_files() {
+        let buffer = ZipBuffer::default();
+        let mut zip = ZipWriter::new(buffer.clone());
+        let mut sent = Vec::new();
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+        for (name, text) in [("a.txt", "first"), ("b.txt", "second")] {
+            let header_start = buffer.len();
+            zip.start_file(name, options).unwrap();
+            sent.extend(buffer.take_until(header_start));
+            zip.write_all(text.as_bytes()).unwrap();
+        }
+        // "b.txt" is still being written
+        assert!(!sent.windows(6).any(|w| w == b"second"));
+
+        zip.finish().unwrap();
+        sent.extend(buffer.take_until(buffer.len()));
+        let mut archive = zip::ZipArchive::new(io::Cursor::new(sent)).unwrap();
+        let mut text = String::new();
+        io::Read::read_to_string(&mut archive.by_name("b.txt").unwrap(), &mut text).unwrap();
+        assert_eq!(text, "second");
+    }
+}
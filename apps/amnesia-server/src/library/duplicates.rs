@@ -0,0 +1,142 @@
+//! Duplicate-page detection for library PDFs
+//!
+//! Renders every page of each book's PDF as a small thumbnail, hashes it with
+//! [`PageHash`], and stores the consecutive pages that look alike. A book is
+//! only checked again when its PDF changes, so repeated runs are cheap.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+use crate::config::{Config, IsolationConfig, IsolationMode};
+use crate::db::{DuplicatePageReport, DuplicatePageRepository};
+use crate::document::{DocumentParser, DocumentRenderer};
+use crate::error::{AppError, Result};
+use crate::formats::isolation;
+use crate::formats::pdf::PdfDocumentHandler;
+use crate::storage::S3Client;
+
+use super::book::{FormatType, LibraryBook};
+use super::page_hash::{find_duplicate_pages, PageHash, DEFAULT_MAX_DISTANCE};
+
+/// Longest side of the thumbnail each page is hashed from (pixels)
+const HASH_RENDER_SIZE: u32 = 256;
+
+/// Finds and stores repeated pages in library PDFs
+pub struct DuplicatePageIndexer {
+    s3: S3Client,
+    pool: SqlitePool,
+    isolation: IsolationConfig,
+}
+
+/// Outcome of one [`DuplicatePageIndexer::run`]
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicatePageSummary {
+    pub checked: usize,
+    /// Checked books with at least one repeated page
+    pub with_duplicates: usize,
+    /// Books whose report was already up to date
+    pub unchanged: usize,
+    /// Books without a PDF
+    pub skipped: usize,
+    pub failed: usize,
+}
+
+impl fmt::Display for DuplicatePageSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Checked {} PDFs, {} with repeated pages ({} unchanged, {} without PDF, {} failed)",
+            self.checked, self.with_duplicates, self.unchanged, self.skipped, self.failed
+        )
+    }
+}
+
+impl DuplicatePageIndexer {
+    pub fn new(s3: S3Client, pool: SqlitePool, config: &Config) -> Self {
+        Self {
+            s3,
+            pool,
+            isolation: config.isolation.clone(),
+        }
+    }
+
+    /// Check books whose report is missing or stale (all books with `force`)
+    pub async fn run(&self, books: &[LibraryBook], force: bool) -> Result<DuplicatePageSummary> {
+        let repo = DuplicatePageRepository::new(&self.pool);
+        let checked: HashMap<String, String> = repo.source_keys().await?.into_iter().collect();
+        let mut summary = DuplicatePageSummary::default();
+
+        for book in books {
+            let Some(format) = book.formats.iter().find(|f| f.format == FormatType::Pdf) else {
+                summary.skipped += 1;
+                continue;
+            };
+
+            let book_id = book.stable_id();
+            if !force && checked.get(&book_id) == Some(&format.s3_key) {
+                summary.unchanged += 1;
+                continue;
+            }
+
+            match self.check(&book_id, &format.s3_key).await {
+                Ok(report) => {
+                    summary.checked += 1;
+                    if !report.duplicates.is_empty() {
+                        summary.with_duplicates += 1;
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!(key = %format.s3_key, "Duplicate page check failed: {}", e);
+                    summary.failed += 1;
+                }
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Check one PDF and store its report
+    pub async fn check(&self, book_id: &str, key: &str) -> Result<DuplicatePageReport> {
+        let data = self.s3.get_object(key).await?.data;
+
+        if self.isolation.mode == IsolationMode::Subprocess {
+            isolation::parse_isolated(&data, book_id, self.isolation.timeout_secs)
+                .await
+                .map_err(|e| AppError::BadRequest(format!("Isolated parse failed: {}", e)))?;
+        }
+
+        let handler =
+            PdfDocumentHandler::from_bytes(data, book_id.to_string()).map_err(render_error)?;
+        let hashes = page_hashes(&handler).await?;
+        let duplicates = find_duplicate_pages(&hashes, DEFAULT_MAX_DISTANCE);
+
+        DuplicatePageRepository::new(&self.pool)
+            .upsert(book_id, key, hashes.len() as i64, duplicates)
+            .await
+    }
+}
+
+/// Perceptual hash of every page, `None` for blank pages
+async fn page_hashes<D>(document: &D) -> Result<Vec<Option<PageHash>>>
+where
+    D: DocumentParser + DocumentRenderer,
+{
+    let mut hashes = Vec::with_capacity(document.item_count());
+    for page in 0..document.item_count() {
+        let thumbnail = document
+            .render_thumbnail(page, HASH_RENDER_SIZE)
+            .await
+            .map_err(render_error)?;
+        let image = image::load_from_memory(&thumbnail.data).map_err(render_error)?;
+        hashes.push(PageHash::from_image(&image));
+    }
+    Ok(hashes)
+}
+
+fn render_error(e: impl fmt::Display) -> AppError {
+    AppError::BadRequest(format!("Failed to render PDF pages: {}", e))
+}
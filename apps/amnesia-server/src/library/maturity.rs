@@ -0,0 +1,380 @@
+//! Maturity ratings and content warnings
+//!
+//! Age ratings come from several places with no common vocabulary: Calibre
+//! tags ("Young Adult", "Erotica", "Ages 8-12"), OPF `<meta>` elements
+//! (`age-rating`, `dcterms:audience`, `schema:typicalAgeRange`, a Calibre
+//! `#age_rating` custom column), and ComicInfo-style strings ("Mature 17+",
+//! "Everyone 10+"). They are normalized onto the four-step
+//! [`MaturityRating`] scale. An explicit `<meta>` rating wins over tags; when
+//! several hints disagree, the most mature one is kept.
+//!
+//! Content warnings are read from tags prefixed with `CW:`, `TW:`, or
+//! `Content Warning:`, and from `content-warnings` metadata.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::book::LibraryBook;
+
+/// Normalized maturity rating, ordered from least to most mature
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    ToSchema,
+    sqlx::Type,
+)]
+#[serde(rename_all = "kebab-case")]
+#[sqlx(rename_all = "kebab-case")]
+pub enum MaturityRating {
+    /// Suitable for children
+    AllAges,
+    /// 13 and up
+    Teen,
+    /// 16 and up
+    Mature,
+    /// 18 and up
+    Adult,
+}
+
+impl MaturityRating {
+    /// Youngest recommended reader age
+    pub fn min_age(self) -> u8 {
+        match self {
+            Self::AllAges => 0,
+            Self::Teen => 13,
+            Self::Mature => 16,
+            Self::Adult => 18,
+        }
+    }
+
+    /// Rating for a minimum reader age
+    pub fn from_age(age: u32) -> Self {
+        match age {
+            0..=12 => Self::AllAges,
+            13..=15 => Self::Teen,
+            16..=17 => Self::Mature,
+            _ => Self::Adult,
+        }
+    }
+
+    /// Interpret a free-form rating ("Teen", "Mature 17+", "Ages 8-12",
+    /// "R18+", "PG-13"); `None` if it carries no rating
+    pub fn parse_hint(value: &str) -> Option<Self> {
+        let value = value.trim().to_lowercase();
+        if value.is_empty()
+            || ["pending", "unknown", "not rated", "unrated"]
+                .iter()
+                .any(|s| value.contains(s))
+        {
+            return None;
+        }
+
+        // The first number is the minimum age ("18+", "ages 8-12", "ma15+")
+        let digits: String = value
+            .chars()
+            .skip_while(|c| !c.is_ascii_digit())
+            .take_while(char::is_ascii_digit)
+            .collect();
+        if let Ok(age) = digits.parse::<u32>() {
+            if age <= 21 {
+                return Some(Self::from_age(age));
+            }
+        }
+
+        let words: Vec<&str> = value
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .collect();
+        let has = |word: &str| words.contains(&word);
+
+        if value.contains("young adult") || has("teen") || has("teens") || has("ya") {
+            Some(Self::Teen)
+        } else if has("adult")
+            || has("adults")
+            || has("explicit")
+            || has("erotica")
+            || has("erotic")
+            || has("nsfw")
+            || has("xxx")
+        {
+            Some(Self::Adult)
+        } else if has("mature") || has("m") {
+            Some(Self::Mature)
+        } else if has("everyone")
+            || has("children")
+            || has("kids")
+            || has("juvenile")
+            || has("childhood")
+            || value.contains("all ages")
+            || has("g")
+            || has("pg")
+        {
+            Some(Self::AllAges)
+        } else {
+            None
+        }
+    }
+}
+
+impl std::fmt::Display for MaturityRating {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::AllAges => "all-ages",
+            Self::Teen => "teen",
+            Self::Mature => "mature",
+            Self::Adult => "adult",
+        })
+    }
+}
+
+/// What an OPF `<meta>` element says about maturity
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MaturityHint {
+    Rating(MaturityRating),
+    ContentWarnings(Vec<String>),
+}
+
+/// Read a maturity hint from an OPF `<meta>` name (or property) and value
+///
+/// Calibre custom columns are stored as JSON with the value under
+/// `#value#`; plain values are used as-is.
+pub fn meta_hint(name: &str, value: &str) -> Option<MaturityHint> {
+    let name = name
+        .trim()
+        .to_lowercase()
+        .replace("calibre:user_metadata:#", "")
+        .replace('_', "-");
+    let values = meta_values(value);
+
+    match name.as_str() {
+        "age-rating"
+        | "agerating"
+        | "maturity-rating"
+        | "maturity"
+        | "dcterms:audience"
+        | "schema:typicalagerange" => values
+            .iter()
+            .filter_map(|v| MaturityRating::parse_hint(v))
+            .max()
+            .map(MaturityHint::Rating),
+        "content-warnings" | "content-warning" | "contentwarnings" => {
+            let warnings: Vec<String> = values
+                .iter()
+                .flat_map(|v| v.split(','))
+                .map(str::trim)
+                .filter(|w| !w.is_empty())
+                .map(str::to_string)
+                .collect();
+            (!warnings.is_empty()).then_some(MaturityHint::ContentWarnings(warnings))
+        }
+        _ => None,
+    }
+}
+
+fn meta_values(value: &str) -> Vec<String> {
+    let value = value.trim();
+    if value.starts_with('{') {
+        if let Ok(column) = serde_json::from_str::<serde_json::Value>(value) {
+            return match column.get("#value#") {
+                Some(serde_json::Value::String(s)) => vec![s.clone()],
+                Some(serde_json::Value::Array(items)) => items
+                    .iter()
+                    .filter_map(|item| item.as_str().map(str::to_string))
+                    .collect(),
+                _ => Vec::new(),
+            };
+        }
+    }
+    vec![value.to_string()]
+}
+
+/// The most mature rating implied by a book's tags
+///
+/// Tags are matched whole, since genre tags like "Adult Fiction" only mean
+/// "not for young readers", not explicit content.
+pub fn rating_from_tags(tags: &[String]) -> Option<MaturityRating> {
+    tags.iter()
+        .filter_map(|tag| {
+            let tag = tag.trim().to_lowercase();
+            let rating = match tag.as_str() {
+                "adult" | "adults only" | "erotica" | "erotic" | "nsfw" | "explicit" | "xxx"
+                | "smut" | "18+" => MaturityRating::Adult,
+                "mature" | "mature content" | "16+" | "17+" => MaturityRating::Mature,
+                "young adult" | "young adult fiction" | "ya" | "teen" | "teens" | "13+" => {
+                    MaturityRating::Teen
+                }
+                "children"
+                | "children's"
+                | "childrens"
+                | "children's books"
+                | "kids"
+                | "juvenile"
+                | "juvenile fiction"
+                | "juvenile nonfiction"
+                | "picture books"
+                | "middle grade"
+                | "all ages" => MaturityRating::AllAges,
+                // "Ages 8-12", "Age 13+"
+                _ if tag.starts_with("age ") || tag.starts_with("ages ") => {
+                    return MaturityRating::parse_hint(&tag)
+                }
+                _ => return None,
+            };
+            Some(rating)
+        })
+        .max()
+}
+
+/// Content warnings from `CW:`/`TW:`/`Content Warning:` tags
+pub fn warnings_from_tags(tags: &[String]) -> Vec<String> {
+    const PREFIXES: &[&str] = &[
+        "content warning:",
+        "content warnings:",
+        "trigger warning:",
+        "cw:",
+        "tw:",
+    ];
+    tags.iter()
+        .filter_map(|tag| {
+            let lower = tag.to_lowercase();
+            PREFIXES
+                .iter()
+                .find(|prefix| lower.starts_with(*prefix))
+                .and_then(|prefix| tag.get(prefix.len()..))
+                .map(|warning| warning.trim().to_string())
+        })
+        .filter(|warning| !warning.is_empty())
+        .collect()
+}
+
+/// Maturity filter for library listings
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaturityFilter {
+    /// Hide books rated above this
+    pub max_maturity: Option<MaturityRating>,
+    /// Whether unrated books pass `max_maturity` (default: true)
+    pub include_unrated: Option<bool>,
+    /// Comma-separated content warnings to hide
+    pub exclude_warnings: Option<String>,
+}
+
+impl MaturityFilter {
+    /// Whether a book passes the filter
+    pub fn allows(&self, book: &LibraryBook) -> bool {
+        if let Some(max) = self.max_maturity {
+            match book.maturity {
+                Some(rating) if rating > max => return false,
+                None if !self.include_unrated.unwrap_or(true) => return false,
+                _ => {}
+            }
+        }
+
+        match &self.exclude_warnings {
+            Some(excluded) => !excluded.split(',').map(str::trim).any(|excluded| {
+                !excluded.is_empty()
+                    && book
+                        .content_warnings
+                        .iter()
+                        .any(|w| w.eq_ignore_ascii_case(excluded))
+            }),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hint() {
+        use MaturityRating::*;
+        assert_eq!(MaturityRating::parse_hint("Teen"), Some(Teen));
+        assert_eq!(MaturityRating::parse_hint("Mature 17+"), Some(Mature));
+        assert_eq!(MaturityRating::parse_hint("Adults Only 18+"), Some(Adult));
+        assert_eq!(MaturityRating::parse_hint("R18+"), Some(Adult));
+        assert_eq!(MaturityRating::parse_hint("MA15+"), Some(Teen));
+        assert_eq!(MaturityRating::parse_hint("Everyone 10+"), Some(AllAges));
+        assert_eq!(MaturityRating::parse_hint("Ages 8-12"), Some(AllAges));
+        assert_eq!(MaturityRating::parse_hint("PG-13"), Some(Teen));
+        assert_eq!(MaturityRating::parse_hint("Young Adult"), Some(Teen));
+        assert_eq!(MaturityRating::parse_hint("M"), Some(Mature));
+        assert_eq!(MaturityRating::parse_hint("Rating Pending"), None);
+        assert_eq!(MaturityRating::parse_hint("Fantasy"), None);
+    }
+
+    #[test]
+    fn test_meta_hint() {
+        assert_eq!(
+            meta_hint("age-rating", "Mature 17+"),
+            Some(MaturityHint::Rating(MaturityRating::Mature))
+        );
+        assert_eq!(
+            meta_hint(
+                "calibre:user_metadata:#age_rating",
+                r##"{"datatype": "text", "#value#": "Teen"}"##
+            ),
+            Some(MaturityHint::Rating(MaturityRating::Teen))
+        );
+        assert_eq!(
+            meta_hint("content-warnings", "violence, grief"),
+            Some(MaturityHint::ContentWarnings(vec![
+                "violence".to_string(),
+                "grief".to_string()
+            ]))
+        );
+        assert_eq!(meta_hint("calibre:series", "Teen Titans"), None);
+    }
+
+    #[test]
+    fn test_tags() {
+        let tags: Vec<String> = ["Fantasy", "Young Adult", "CW: violence", "Adult Fiction"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        assert_eq!(rating_from_tags(&tags), Some(MaturityRating::Teen));
+        assert_eq!(warnings_from_tags(&tags), vec!["violence"]);
+
+        let tags = vec!["Juvenile Fiction".to_string(), "Erotica".to_string()];
+        assert_eq!(rating_from_tags(&tags), Some(MaturityRating::Adult));
+        assert_eq!(
+            rating_from_tags(&["Ages 8-12".to_string()]),
+            Some(MaturityRating::AllAges)
+        );
+        assert_eq!(rating_from_tags(&["Fiction".to_string()]), None);
+    }
+
+    #[test]
+    fn test_filter() {
+        let mut book = LibraryBook::new("Book".to_string(), "A/Book".to_string());
+        let kids = MaturityFilter {
+            max_maturity: Some(MaturityRating::AllAges),
+            ..Default::default()
+        };
+        assert!(kids.allows(&book));
+        assert!(!MaturityFilter {
+            include_unrated: Some(false),
+            ..kids.clone()
+        }
+        .allows(&book));
+
+        book.maturity = Some(MaturityRating::Teen);
+        book.content_warnings = vec!["Violence".to_string()];
+        assert!(!kids.allows(&book));
+        assert!(MaturityFilter::default().allows(&book));
+        assert!(!MaturityFilter {
+            exclude_warnings: Some("gore, violence".to_string()),
+            ..Default::default()
+        }
+        .allows(&book));
+    }
+}
@@ -2,12 +2,15 @@
 //!
 //! Parses Dublin Core metadata from Calibre's metadata.opf XML files.
 
+use chrono::{DateTime, Utc};
 use quick_xml::de::from_str;
 use serde::Deserialize;
 use std::collections::HashMap;
 
 use crate::error::Result;
 
+use super::maturity::{self, MaturityHint, MaturityRating};
+
 /// Parsed Calibre metadata
 #[derive(Debug, Clone, Default)]
 pub struct CalibreMetadata {
@@ -24,6 +27,13 @@ pub struct CalibreMetadata {
     pub tags: Vec<String>,
     pub identifiers: HashMap<String, String>,
     pub cover_path: Option<String>,
+    /// Rating from `<meta>` hints, or else from tags
+    pub maturity: Option<MaturityRating>,
+    pub content_warnings: Vec<String>,
+    /// `calibre:rating`, 0-10; unrated books have none
+    pub rating: Option<u8>,
+    /// `calibre:timestamp`, when the book was added to the library
+    pub added_at: Option<DateTime<Utc>>,
 }
 
 impl CalibreMetadata {
@@ -89,8 +99,23 @@ impl CalibreMetadata {
         }
 
         // Calibre-specific metadata
+        let mut meta_maturity = None;
         if let Some(metas) = metadata.meta {
             for meta in metas {
+                let key = meta.name.as_deref().or(meta.property.as_deref());
+                let value = meta.content.as_deref().or(meta.text.as_deref());
+                if let Some(hint) = key.zip(value).and_then(|(k, v)| maturity::meta_hint(k, v)) {
+                    match hint {
+                        MaturityHint::Rating(rating) => {
+                            meta_maturity = meta_maturity.max(Some(rating));
+                        }
+                        MaturityHint::ContentWarnings(warnings) => {
+                            result.content_warnings.extend(warnings);
+                        }
+                    }
+                    continue;
+                }
+
                 match meta.name.as_deref() {
                     Some("calibre:series") => {
                         result.series = meta.content;
@@ -98,6 +123,19 @@ impl CalibreMetadata {
                     Some("calibre:series_index") => {
                         result.series_index = meta.content.and_then(|s| s.parse().ok());
                     }
+                    Some("calibre:rating") => {
+                        result.rating = meta
+                            .content
+                            .and_then(|s| s.trim().parse::<f32>().ok())
+                            .map(|rating| rating.round().clamp(0.0, 10.0) as u8)
+                            .filter(|&rating| rating > 0);
+                    }
+                    Some("calibre:timestamp") => {
+                        result.added_at = meta
+                            .content
+                            .and_then(|s| DateTime::parse_from_rfc3339(s.trim()).ok())
+                            .map(|added| added.with_timezone(&Utc));
+                    }
                     Some("calibre:author_link_map") => {
                         // Could parse author links if needed
                     }
@@ -109,6 +147,18 @@ impl CalibreMetadata {
             }
         }
 
+        // Maturity: explicit metadata wins over tags
+        result.maturity = meta_maturity.or_else(|| maturity::rating_from_tags(&result.tags));
+        for warning in maturity::warnings_from_tags(&result.tags) {
+            if !result
+                .content_warnings
+                .iter()
+                .any(|w| w.eq_ignore_ascii_case(&warning))
+            {
+                result.content_warnings.push(warning);
+            }
+        }
+
         // Cover from manifest
         if result.cover_path.is_none() {
             if let Some(manifest) = package.manifest {
@@ -255,6 +305,8 @@ mod tests {
         <dc:identifier id="isbn" opf:scheme="ISBN">978-1234567890</dc:identifier>
         <meta name="calibre:series" content="Test Series"/>
         <meta name="calibre:series_index" content="1.0"/>
+        <meta name="calibre:rating" content="8.0"/>
+        <meta name="calibre:timestamp" content="2021-05-01T12:30:00.123000+00:00"/>
     </metadata>
 </package>"#;
 
@@ -264,5 +316,34 @@ mod tests {
         assert_eq!(metadata.language, Some("en".to_string()));
         assert_eq!(metadata.series, Some("Test Series".to_string()));
         assert_eq!(metadata.series_index, Some(1.0));
+        assert_eq!(metadata.rating, Some(8));
+        assert_eq!(
+            metadata.added_at.map(|added| added.to_rfc3339()),
+            Some("2021-05-01T12:30:00.123+00:00".to_string())
+        );
+        assert_eq!(metadata.maturity, None);
+    }
+
+    #[test]
+    fn test_parse_maturity() {
+        let xml = r#"<?xml version='1.0' encoding='utf-8'?>
+<package xmlns="http://www.idpf.org/2007/opf" version="2.0">
+    <metadata xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:opf="http://www.idpf.org/2007/opf">
+        <dc:title>Test Book</dc:title>
+        <dc:subject>Young Adult</dc:subject>
+        <dc:subject>CW: Violence</dc:subject>
+        <meta name="calibre:user_metadata:#age_rating" content="{&quot;datatype&quot;: &quot;text&quot;, &quot;#value#&quot;: &quot;Mature 17+&quot;}"/>
+        <meta name="content-warnings" content="grief, violence"/>
+    </metadata>
+</package>"#;
+
+        let metadata = CalibreMetadata::parse(xml).unwrap();
+        // The custom column overrides the "Young Adult" tag
+        assert_eq!(metadata.maturity, Some(MaturityRating::Mature));
+        assert_eq!(metadata.content_warnings, vec!["grief", "violence"]);
+
+        let tags_only = xml.replace("calibre:user_metadata:#age_rating", "calibre:rating");
+        let metadata = CalibreMetadata::parse(&tags_only).unwrap();
+        assert_eq!(metadata.maturity, Some(MaturityRating::Teen));
     }
 }
@@ -1,11 +1,36 @@
 //! Library module for book management
 //!
-//! Handles Calibre library scanning, metadata parsing, and book indexing.
+//! Handles Calibre library scanning, metadata parsing, book indexing,
+//! maturity ratings, per-book text statistics, duplicate-page detection
+//! for scanned PDFs, zip bundles of books for download, and moving unread
+//! books to colder storage.
 
+#[cfg(feature = "s3")]
+mod analysis;
 mod book;
+#[cfg(feature = "s3")]
+pub mod bundle;
+#[cfg(feature = "s3")]
+mod duplicates;
+pub mod maturity;
 mod metadata;
+mod page_hash;
+#[cfg(feature = "s3")]
 mod scanner;
+mod text_stats;
+#[cfg(feature = "s3")]
+mod tiering;
 
+#[cfg(feature = "s3")]
+pub use analysis::*;
 pub use book::*;
+#[cfg(feature = "s3")]
+pub use duplicates::*;
+pub use maturity::{MaturityFilter, MaturityRating};
 pub use metadata::*;
+pub use page_hash::*;
+#[cfg(feature = "s3")]
 pub use scanner::*;
+pub use text_stats::*;
+#[cfg(feature = "s3")]
+pub use tiering::*;
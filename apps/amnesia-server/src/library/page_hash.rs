@@ -0,0 +1,187 @@
+//! Perceptual page hashes and duplicate-page detection
+//!
+//! Scanners sometimes feed the same sheet twice, leaving two identical (or
+//! nearly identical, after jitter and compression) pages in a row. Each page
+//! is reduced to a 512-bit difference hash (dHash): the page is shrunk to
+//! 17x17 grayscale pixels and every bit records whether a pixel is
+//! noticeably brighter than its right-hand (or, for the second half, lower)
+//! neighbour. Checking both directions matters for text, where every line
+//! starts at the same margin; line ends and paragraph breaks are what tell
+//! pages apart. Rescans of the same page differ by a few bits; different
+//! pages differ by dozens.
+//!
+//! Blank pages all hash alike, so pages with almost no contrast get no hash
+//! and are never reported.
+
+use image::imageops::FilterType;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Hash grid width and height
+const HASH_SIZE: u32 = 16;
+
+/// Brightness step (0-255) that sets a hash bit; smaller steps are treated
+/// as flat so scanner noise in margins does not flip bits
+const GRADIENT_MIN: u8 = 3;
+
+/// Grayscale standard deviation below which a page counts as blank
+const BLANK_STDDEV: f64 = 4.0;
+
+/// Largest hash distance (of 512 bits) still reported as a near-duplicate
+pub const DEFAULT_MAX_DISTANCE: u32 = 24;
+
+/// 512-bit difference hash of a page image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PageHash([u64; 8]);
+
+impl PageHash {
+    /// Hash a rendered page; `None` for blank pages
+    pub fn from_image(image: &DynamicImage) -> Option<Self> {
+        let gray = image.to_luma8();
+        let pixels = gray.as_raw();
+        if pixels.is_empty() {
+            return None;
+        }
+
+        let mean = pixels.iter().map(|&p| f64::from(p)).sum::<f64>() / pixels.len() as f64;
+        let variance = pixels
+            .iter()
+            .map(|&p| (f64::from(p) - mean).powi(2))
+            .sum::<f64>()
+            / pixels.len() as f64;
+        if variance.sqrt() < BLANK_STDDEV {
+            return None;
+        }
+
+        let small =
+            image::imageops::resize(&gray, HASH_SIZE + 1, HASH_SIZE + 1, FilterType::Triangle);
+        let brighter = |a: (u32, u32), b: (u32, u32)| {
+            small.get_pixel(a.0, a.1)[0] > small.get_pixel(b.0, b.1)[0].saturating_add(GRADIENT_MIN)
+        };
+
+        let mut bits = [0u64; 8];
+        let cells = (HASH_SIZE * HASH_SIZE) as usize;
+        for y in 0..HASH_SIZE {
+            for x in 0..HASH_SIZE {
+                let cell = (y * HASH_SIZE + x) as usize;
+                for (bit, set) in [
+                    (cell, brighter((x, y), (x + 1, y))),
+                    (cells + cell, brighter((x, y), (x, y + 1))),
+                ] {
+                    if set {
+                        bits[bit / 64] |= 1 << (bit % 64);
+                    }
+                }
+            }
+        }
+        Some(Self(bits))
+    }
+
+    /// Number of differing bits (0-512)
+    pub fn distance(&self, other: &Self) -> u32 {
+        self.0
+            .iter()
+            .zip(other.0)
+            .map(|(a, b)| (a ^ b).count_ones())
+            .sum()
+    }
+}
+
+/// A page that looks like a repeat of the page before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicatePage {
+    /// Zero-based index of the repeated page
+    pub page: usize,
+    /// Zero-based index of the page it repeats
+    pub duplicate_of: usize,
+    /// Differing hash bits; 0 is a pixel-for-pixel repeat at hash scale
+    pub distance: u32,
+}
+
+/// Consecutive pages whose hashes are within `max_distance` bits
+///
+/// `hashes` holds one entry per page in order, `None` for blank pages. A
+/// blank page breaks a run, so "page, blank, same page" is not reported.
+pub fn find_duplicate_pages(hashes: &[Option<PageHash>], max_distance: u32) -> Vec<DuplicatePage> {
+    hashes
+        .windows(2)
+        .enumerate()
+        .filter_map(|(index, pair)| {
+            let (Some(previous), Some(current)) = (pair[0], pair[1]) else {
+                return None;
+            };
+            let distance = previous.distance(&current);
+            (distance <= max_distance).then_some(DuplicatePage {
+                page: index + 1,
+                duplicate_of: index,
+                distance,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+
+    /// A page of text-like lines whose breaks depend on `seed`; `rescan`
+    /// shifts it by a pixel and adds noise
+    fn page(seed: u32, rescan: bool) -> DynamicImage {
+        let shift = u32::from(rescan);
+        DynamicImage::ImageLuma8(GrayImage::from_fn(170, 220, |x, y| {
+            let line = y / 10;
+            let hash = (line + 1)
+                .wrapping_mul(2654435761)
+                .wrapping_add(seed.wrapping_mul(40503));
+            let paragraph_break = (hash >> 7) % 4 == 0;
+            let start = if (hash >> 3) % 3 == 0 { 25 } else { 10 };
+            let end = 60 + (hash >> 11) % 100;
+            let ink = line % 2 == 1
+                && !paragraph_break
+                && x >= start + shift
+                && x < end + shift
+                && (x / 3) % 4 != 0;
+            let noise = if rescan {
+                ((x * 7 + y * 13) % 5) as u8
+            } else {
+                0
+            };
+            Luma([if ink { 20 } else { 235 } - noise])
+        }))
+    }
+
+    #[test]
+    fn test_page_hash() {
+        let a = PageHash::from_image(&page(1, false)).unwrap();
+        let rescan = PageHash::from_image(&page(1, true)).unwrap();
+        let b = PageHash::from_image(&page(2, false)).unwrap();
+
+        assert_eq!(a.distance(&a), 0);
+        assert!(a.distance(&rescan) <= DEFAULT_MAX_DISTANCE);
+        assert!(a.distance(&b) > DEFAULT_MAX_DISTANCE);
+
+        let blank = DynamicImage::ImageLuma8(GrayImage::from_pixel(170, 220, Luma([250])));
+        assert_eq!(PageHash::from_image(&blank), None);
+    }
+
+    #[test]
+    fn test_find_duplicate_pages() {
+        let a = PageHash::from_image(&page(1, false));
+        let rescan = PageHash::from_image(&page(1, true));
+        let b = PageHash::from_image(&page(2, false));
+
+        let duplicates = find_duplicate_pages(&[a, b, b, rescan, a, None, a], DEFAULT_MAX_DISTANCE);
+        let pages: Vec<(usize, usize)> = duplicates
+            .iter()
+            .map(|d| (d.page, d.duplicate_of))
+            .collect();
+        assert_eq!(pages, vec![(2, 1), (4, 3)]);
+        assert_eq!(duplicates[0].distance, 0);
+
+        assert!(find_duplicate_pages(&[None, None], DEFAULT_MAX_DISTANCE).is_empty());
+        assert!(find_duplicate_pages(&[], DEFAULT_MAX_DISTANCE).is_empty());
+    }
+}
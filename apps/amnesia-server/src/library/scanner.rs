@@ -1,12 +1,16 @@
 //! Library scanner for Calibre folder structure
 //!
 //! Scans S3 bucket for books following Calibre's Author/Title structure.
+//!
+//! A book's dates come from its files, so rescans leave them alone: it was
+//! added at Calibre's `calibre:timestamp`, or else when its first book file
+//! was stored, and updated when any of its files last changed.
 
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use std::collections::{HashMap, HashSet};
 
 use crate::error::Result;
-use crate::storage::{ListOptions, S3Client};
+use crate::storage::{ListOptions, ObjectMetadata, S3Client};
 
 use super::book::{BookFormat, FormatType, LibraryBook, LibraryStats};
 use super::metadata::CalibreMetadata;
@@ -32,19 +36,14 @@ impl LibraryScanner {
         tracing::info!("Found {} objects in bucket", objects.len());
 
         // Group objects by book folder (Author/Title/)
-        let mut book_folders: HashMap<String, Vec<(String, i64)>> = HashMap::new();
+        let mut book_folders: HashMap<String, Vec<ObjectMetadata>> = HashMap::new();
 
         for obj in objects {
-            let key = &obj.key;
-
             // Parse path: Author/Title/filename
-            let parts: Vec<&str> = key.split('/').collect();
+            let parts: Vec<&str> = obj.key.split('/').collect();
             if parts.len() >= 3 {
                 let folder = format!("{}/{}", parts[0], parts[1]);
-                book_folders
-                    .entry(folder)
-                    .or_default()
-                    .push((key.clone(), obj.size));
+                book_folders.entry(folder).or_default().push(obj);
             }
         }
 
@@ -81,57 +80,14 @@ impl LibraryScanner {
     async fn process_book_folder(
         &self,
         folder: &str,
-        files: &[(String, i64)],
+        files: &[ObjectMetadata],
     ) -> Result<Option<LibraryBook>> {
-        let parts: Vec<&str> = folder.split('/').collect();
-        if parts.len() < 2 {
-            return Ok(None);
-        }
-
-        let author_folder = parts[0];
-        let title_folder = parts[1];
-
-        // Find metadata.opf file
+        // Parse metadata if available
         let metadata_key = files
             .iter()
-            .find(|(key, _)| key.ends_with("metadata.opf"))
-            .map(|(key, _)| key.clone());
-
-        // Find book formats
-        let mut formats = Vec::new();
-        for (key, size) in files {
-            if let Some(ext) = key.rsplit('.').next() {
-                let format_type = FormatType::from_extension(ext);
-                if format_type != FormatType::Other
-                    || ext.eq_ignore_ascii_case("epub")
-                    || ext.eq_ignore_ascii_case("pdf")
-                {
-                    formats.push(BookFormat {
-                        format: format_type,
-                        s3_key: key.clone(),
-                        size: *size,
-                    });
-                }
-            }
-        }
-
-        // Must have at least one format
-        if formats.is_empty() {
-            return Ok(None);
-        }
-
-        // Find cover image
-        let cover_key = files
-            .iter()
-            .find(|(key, _)| {
-                key.ends_with("cover.jpg")
-                    || key.ends_with("cover.jpeg")
-                    || key.ends_with("cover.png")
-            })
-            .map(|(key, _)| key.clone());
-
-        // Parse metadata if available
-        let metadata = if let Some(ref key) = metadata_key {
+            .find(|obj| obj.key.ends_with("metadata.opf"))
+            .map(|obj| &obj.key);
+        let metadata = if let Some(key) = metadata_key {
             match self.s3_client.get_object(key).await {
                 Ok(obj) => {
                     let xml = String::from_utf8_lossy(&obj.data);
@@ -146,36 +102,7 @@ impl LibraryScanner {
             None
         };
 
-        // Build book from metadata or folder names
-        let mut book = if let Some(meta) = metadata {
-            let mut b = LibraryBook::new(
-                meta.title.unwrap_or_else(|| title_folder.to_string()),
-                folder.to_string(),
-            );
-            b.author = meta.author;
-            b.author_sort = meta.author_sort;
-            b.authors = meta.authors;
-            b.publisher = meta.publisher;
-            b.pubdate = meta.pubdate;
-            b.language = meta.language;
-            b.description = meta.description;
-            b.series = meta.series;
-            b.series_index = meta.series_index;
-            b.tags = meta.tags;
-            b.identifiers = meta.identifiers;
-            b
-        } else {
-            // Fallback to folder names
-            let mut b = LibraryBook::new(title_folder.to_string(), folder.to_string());
-            b.author = Some(author_folder.to_string());
-            b
-        };
-
-        book.formats = formats;
-        book.cover_key = cover_key;
-        book.updated_at = Utc::now();
-
-        Ok(Some(book))
+        Ok(build_book(folder, files, metadata))
     }
 
     /// Get library statistics
@@ -262,6 +189,96 @@ impl LibraryScanner {
     }
 }
 
+/// Build a book from its folder's files and parsed metadata.opf
+///
+/// `None` if the folder is not Author/Title or holds no book file.
+fn build_book(
+    folder: &str,
+    files: &[ObjectMetadata],
+    metadata: Option<CalibreMetadata>,
+) -> Option<LibraryBook> {
+    let (author_folder, title_folder) = folder.split_once('/')?;
+
+    // Find book formats
+    let mut formats = Vec::new();
+    let mut first_stored: Option<DateTime<Utc>> = None;
+    for obj in files {
+        if let Some(ext) = obj.key.rsplit('.').next() {
+            let format_type = FormatType::from_extension(ext);
+            if format_type != FormatType::Other
+                || ext.eq_ignore_ascii_case("epub")
+                || ext.eq_ignore_ascii_case("pdf")
+            {
+                formats.push(BookFormat {
+                    format: format_type,
+                    s3_key: obj.key.clone(),
+                    size: obj.size,
+                });
+                if let Some(stored) = obj.last_modified {
+                    first_stored = Some(first_stored.map_or(stored, |first| first.min(stored)));
+                }
+            }
+        }
+    }
+
+    // Must have at least one format
+    if formats.is_empty() {
+        return None;
+    }
+
+    // Find cover image
+    let cover_key = files
+        .iter()
+        .find(|obj| {
+            obj.key.ends_with("cover.jpg")
+                || obj.key.ends_with("cover.jpeg")
+                || obj.key.ends_with("cover.png")
+        })
+        .map(|obj| obj.key.clone());
+
+    // Build book from metadata or folder names
+    let mut book = if let Some(meta) = metadata {
+        let mut b = LibraryBook::new(
+            meta.title.unwrap_or_else(|| title_folder.to_string()),
+            folder.to_string(),
+        );
+        b.author = meta.author;
+        b.author_sort = meta.author_sort;
+        b.authors = meta.authors;
+        b.publisher = meta.publisher;
+        b.pubdate = meta.pubdate;
+        b.language = meta.language;
+        b.description = meta.description;
+        b.series = meta.series;
+        b.series_index = meta.series_index;
+        b.tags = meta.tags;
+        b.identifiers = meta.identifiers;
+        b.maturity = meta.maturity;
+        b.content_warnings = meta.content_warnings;
+        b.rating = meta.rating;
+        if let Some(added_at) = meta.added_at.or(first_stored) {
+            b.added_at = added_at;
+        }
+        b
+    } else {
+        // Fallback to folder names
+        let mut b = LibraryBook::new(title_folder.to_string(), folder.to_string());
+        b.author = Some(author_folder.to_string());
+        if let Some(added_at) = first_stored {
+            b.added_at = added_at;
+        }
+        b
+    };
+
+    book.formats = formats;
+    book.cover_key = cover_key;
+    if let Some(updated_at) = files.iter().filter_map(|obj| obj.last_modified).max() {
+        book.updated_at = updated_at;
+    }
+
+    Some(book)
+}
+
 /// Changes detected in the library
 #[derive(Debug)]
 pub struct LibraryChanges {
@@ -275,3 +292,78 @@ impl LibraryChanges {
         self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    use crate::feeds::{json_feed, newest};
+    use crate::opds::EntryTemplate;
+
+    fn object(key: &str, day: u32) -> ObjectMetadata {
+        ObjectMetadata {
+            key: key.to_string(),
+            size: 100,
+            last_modified: Some(Utc.with_ymd_and_hms(2024, 3, day, 12, 0, 0).unwrap()),
+            content_type: None,
+            etag: None,
+        }
+    }
+
+    fn scan() -> Vec<LibraryBook> {
+        let timestamp = CalibreMetadata {
+            added_at: Some(Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap()),
+            ..CalibreMetadata::default()
+        };
+        vec![
+            build_book(
+                "Anon/Old",
+                &[
+                    object("Anon/Old/old.epub", 9),
+                    object("Anon/Old/metadata.opf", 9),
+                ],
+                Some(timestamp),
+            ),
+            build_book(
+                "Anon/New",
+                &[
+                    object("Anon/New/new.pdf", 5),
+                    object("Anon/New/new.epub", 3),
+                ],
+                None,
+            ),
+            build_book("Anon/Cover", &[object("Anon/Cover/cover.jpg", 1)], None),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    #[test]
+    fn test_book_dates_survive_rescans() {
+        let books = scan();
+        assert_eq!(books.len(), 2);
+        // calibre:timestamp, then the first book file stored
+        assert_eq!(books[0].added_at.to_rfc3339(), "2020-01-01T00:00:00+00:00");
+        assert_eq!(books[1].added_at.to_rfc3339(), "2024-03-03T12:00:00+00:00");
+        assert_eq!(
+            books[1].updated_at.to_rfc3339(),
+            "2024-03-05T12:00:00+00:00"
+        );
+
+        let feed = |books| {
+            let feed = json_feed(
+                &newest(books, 10),
+                "New books",
+                "http://host/feeds/new-books.json",
+                "http://host",
+                &EntryTemplate::default(),
+            );
+            serde_json::to_value(feed).unwrap()
+        };
+        let first = feed(books);
+        assert_eq!(first["items"][0]["title"], "New");
+        assert_eq!(feed(scan()), first);
+    }
+}
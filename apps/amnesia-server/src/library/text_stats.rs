@@ -0,0 +1,244 @@
+//! Text statistics and readability scores
+//!
+//! Computes Flesch reading ease, Flesch-Kincaid grade level, average
+//! sentence length, and vocabulary richness from a book's plain text.
+//!
+//! Syllables are counted with an English vowel-group heuristic, so the
+//! Flesch scores are only meaningful for English text; sentence length and
+//! vocabulary richness hold for any language that separates words with
+//! spaces. Vocabulary richness is the moving-average type-token ratio
+//! (MATTR) over [`RICHNESS_WINDOW`] words, which unlike the plain ratio of
+//! unique to total words does not fall as a book gets longer.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+/// Window size, in words, for vocabulary richness
+pub const RICHNESS_WINDOW: usize = 100;
+
+/// Readability metrics for a text
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct TextStats {
+    pub words: i64,
+    pub sentences: i64,
+    pub syllables: i64,
+    /// Distinct words, case-insensitive
+    pub unique_words: i64,
+    /// Words per sentence
+    pub avg_sentence_length: f64,
+    pub avg_syllables_per_word: f64,
+    /// Flesch reading ease (higher is easier; 60-70 is plain English)
+    pub flesch_reading_ease: f64,
+    /// Flesch-Kincaid grade level (US school grade)
+    pub flesch_kincaid_grade: f64,
+    /// Moving-average type-token ratio, 0-1
+    pub vocabulary_richness: f64,
+}
+
+impl TextStats {
+    /// Statistics for a single piece of text
+    pub fn from_text(text: &str) -> Self {
+        let mut analyzer = TextAnalyzer::new();
+        analyzer.add_text(text);
+        analyzer.finish()
+    }
+}
+
+/// Accumulates statistics over a text fed in pieces (chapters, pages)
+///
+/// Pieces are read as one continuous text, so a sentence may span two of them.
+#[derive(Debug, Default)]
+pub struct TextAnalyzer {
+    words: u64,
+    sentences: u64,
+    /// Words since the last sentence end
+    sentence_words: u64,
+    syllables: u64,
+    vocabulary: HashSet<String>,
+    /// Last [`RICHNESS_WINDOW`] words and their counts
+    window: VecDeque<String>,
+    window_counts: HashMap<String, usize>,
+    /// Sum of the type-token ratios of every full window
+    ttr_sum: f64,
+    windows: u64,
+}
+
+impl TextAnalyzer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a piece of text
+    pub fn add_text(&mut self, text: &str) {
+        for token in text.split_whitespace() {
+            let ends_sentence = ends_sentence(token);
+            for word in token
+                .split(|c: char| !c.is_alphanumeric() && c != '\'' && c != '’')
+                .map(|w| w.trim_matches(|c| c == '\'' || c == '’'))
+                .filter(|w| w.chars().any(char::is_alphabetic))
+            {
+                self.add_word(word);
+                self.sentence_words += 1;
+            }
+            if ends_sentence && self.sentence_words > 0 {
+                self.sentences += 1;
+                self.sentence_words = 0;
+            }
+        }
+    }
+
+    fn add_word(&mut self, word: &str) {
+        let word = word.to_lowercase();
+        self.words += 1;
+        self.syllables += syllables(&word) as u64;
+
+        *self.window_counts.entry(word.clone()).or_insert(0) += 1;
+        self.window.push_back(word.clone());
+        if self.window.len() > RICHNESS_WINDOW {
+            if let Some(old) = self.window.pop_front() {
+                if let Some(count) = self.window_counts.get_mut(&old) {
+                    *count -= 1;
+                    if *count == 0 {
+                        self.window_counts.remove(&old);
+                    }
+                }
+            }
+        }
+        if self.window.len() == RICHNESS_WINDOW {
+            self.ttr_sum += self.window_counts.len() as f64 / RICHNESS_WINDOW as f64;
+            self.windows += 1;
+        }
+
+        self.vocabulary.insert(word);
+    }
+
+    /// Compute the statistics for everything added so far
+    pub fn finish(&self) -> TextStats {
+        if self.words == 0 {
+            return TextStats::default();
+        }
+
+        // Text after the last full stop still makes a sentence
+        let sentences = self.sentences + u64::from(self.sentence_words > 0);
+        let words = self.words as f64;
+        let avg_sentence_length = words / sentences as f64;
+        let avg_syllables_per_word = self.syllables as f64 / words;
+        let vocabulary_richness = if self.windows > 0 {
+            self.ttr_sum / self.windows as f64
+        } else {
+            // Shorter than one window
+            self.vocabulary.len() as f64 / words
+        };
+
+        TextStats {
+            words: self.words as i64,
+            sentences: sentences as i64,
+            syllables: self.syllables as i64,
+            unique_words: self.vocabulary.len() as i64,
+            avg_sentence_length,
+            avg_syllables_per_word,
+            flesch_reading_ease: 206.835
+                - 1.015 * avg_sentence_length
+                - 84.6 * avg_syllables_per_word,
+            flesch_kincaid_grade: 0.39 * avg_sentence_length + 11.8 * avg_syllables_per_word
+                - 15.59,
+            vocabulary_richness,
+        }
+    }
+}
+
+/// Whether a whitespace-separated token ends a sentence ("end.", "end?!",
+/// "end.\"")
+fn ends_sentence(token: &str) -> bool {
+    token
+        .trim_end_matches(|c: char| matches!(c, '"' | '\'' | '”' | '’' | ')' | ']' | '»'))
+        .ends_with(['.', '!', '?', '…'])
+}
+
+/// Estimate the syllables in a lowercase English word
+fn syllables(word: &str) -> usize {
+    let is_vowel = |c: char| matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y');
+    let letters: Vec<char> = word.chars().filter(|c| c.is_alphabetic()).collect();
+
+    let mut count = 0;
+    let mut previous_vowel = false;
+    for &c in &letters {
+        let vowel = is_vowel(c);
+        if vowel && !previous_vowel {
+            count += 1;
+        }
+        previous_vowel = vowel;
+    }
+
+    // Silent final "e" ("make"), but not "-le" ("table") or "-ee" ("free")
+    if let [.., before, 'e'] = letters[..] {
+        if count > 1 && before != 'l' && !is_vowel(before) {
+            count -= 1;
+        }
+    }
+
+    count.max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_syllables() {
+        assert_eq!(syllables("cat"), 1);
+        assert_eq!(syllables("make"), 1);
+        assert_eq!(syllables("table"), 2);
+        assert_eq!(syllables("reading"), 2);
+        assert_eq!(syllables("vocabulary"), 5);
+        assert_eq!(syllables("free"), 1);
+        assert_eq!(syllables("rhythm"), 1);
+    }
+
+    #[test]
+    fn test_text_stats() {
+        let stats = TextStats::from_text("The cat sat. The dog ran! Did the cat see it?");
+        assert_eq!(stats.words, 11);
+        assert_eq!(stats.sentences, 3);
+        assert_eq!(stats.unique_words, 8);
+        assert!((stats.avg_sentence_length - 11.0 / 3.0).abs() < 1e-9);
+        // One-syllable words in short sentences: very easy
+        assert!(stats.flesch_reading_ease > 100.0);
+        assert!(stats.flesch_kincaid_grade < 0.0);
+
+        let hard = TextStats::from_text(
+            "Notwithstanding considerable institutional opposition, the \
+             administration implemented comprehensive organizational \
+             restructuring, fundamentally transforming departmental responsibilities.",
+        );
+        assert_eq!(hard.sentences, 1);
+        assert!(hard.flesch_kincaid_grade > 16.0);
+        assert!(hard.flesch_reading_ease < stats.flesch_reading_ease);
+
+        assert_eq!(TextStats::from_text("  ... "), TextStats::default());
+
+        // Pieces are one continuous text
+        let mut analyzer = TextAnalyzer::new();
+        analyzer.add_text("The cat");
+        analyzer.add_text("sat. The dog");
+        assert_eq!(analyzer.finish().sentences, 2);
+    }
+
+    #[test]
+    fn test_vocabulary_richness() {
+        let mut repetitive = TextAnalyzer::new();
+        let mut varied = TextAnalyzer::new();
+        for i in 0..50 {
+            repetitive.add_text("the cat sat on the mat.");
+            varied.add_text(&format!("word{0}a word{0}b word{0}c word{0}d", i));
+        }
+
+        let repetitive = repetitive.finish();
+        let varied = varied.finish();
+        assert_eq!(repetitive.sentences, 50);
+        assert!(repetitive.vocabulary_richness < 0.1);
+        assert!((varied.vocabulary_richness - 1.0).abs() < 1e-9);
+    }
+}
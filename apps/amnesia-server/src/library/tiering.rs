@@ -0,0 +1,288 @@
+//! Storage tiering for books nobody reads
+//!
+//! Book files not served for [`TieringConfig::cold_after_days`] are moved to
+//! a cheaper S3 storage class in place, keeping their keys, so the library
+//! scan and OPDS catalog do not change. [`StorageTiering::ensure_available`]
+//! brings a file back when it is requested again: files in instant-access
+//! classes (STANDARD_IA, GLACIER_IR) go straight back to the default class,
+//! while files in archive classes (GLACIER, DEEP_ARCHIVE) need a restore that
+//! takes hours, during which the file reports [`Availability::Retrieving`].
+//! Each tiering run also moves back files whose restore has finished.
+//!
+//! Files are tracked from the first run that sees them, so a newly enabled
+//! policy archives nothing for `cold_after_days`.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+use crate::config::{Config, TieringConfig};
+use crate::db::{TierStatus, TieringRepository};
+use crate::error::Result;
+use crate::storage::S3Client;
+
+use super::book::LibraryBook;
+
+/// The storage class files are restored to
+const DEFAULT_STORAGE_CLASS: &str = "STANDARD";
+
+/// Whether a file can be served now
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Availability {
+    Available,
+    /// Being restored from an archive class; try again later
+    Retrieving,
+}
+
+/// Archives unread book files and restores them on access
+pub struct StorageTiering {
+    s3: S3Client,
+    pool: SqlitePool,
+    config: TieringConfig,
+}
+
+/// Outcome of one [`StorageTiering::run`]
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TieringSummary {
+    pub archived: usize,
+    /// Files whose restore finished and that are back in the default class
+    pub restored: usize,
+    /// Files still being restored
+    pub retrieving: usize,
+    /// Files seen for the first time
+    pub tracked: usize,
+    pub failed: usize,
+}
+
+impl fmt::Display for TieringSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Archived {} files, restored {} ({} still retrieving, {} newly tracked, {} failed)",
+            self.archived, self.restored, self.retrieving, self.tracked, self.failed
+        )
+    }
+}
+
+/// Files a run should act on
+#[derive(Debug, Default, PartialEq)]
+struct ArchivePlan {
+    /// Not served since the cutoff, oldest first
+    cold: Vec<String>,
+    /// Never served nor seen by a run
+    untracked: Vec<String>,
+}
+
+impl StorageTiering {
+    pub fn new(s3: S3Client, pool: SqlitePool, config: &Config) -> Self {
+        Self {
+            s3,
+            pool,
+            config: config.tiering.clone(),
+        }
+    }
+
+    /// Finish completed restores, then archive cold files
+    pub async fn run(&self, books: &[LibraryBook]) -> Result<TieringSummary> {
+        let repo = TieringRepository::new(&self.pool);
+        let mut summary = TieringSummary::default();
+
+        let archived = repo.list().await?;
+        for file in archived
+            .iter()
+            .filter(|f| f.status == TierStatus::Retrieving)
+        {
+            match self.finish_restore(&file.s3_key).await {
+                Ok(true) => summary.restored += 1,
+                Ok(false) => summary.retrieving += 1,
+                Err(e) => {
+                    tracing::warn!(key = %file.s3_key, "Finishing restore failed: {}", e);
+                    summary.failed += 1;
+                }
+            }
+        }
+
+        if self.config.cold_after_days == 0 {
+            return Ok(summary);
+        }
+
+        let archived: HashSet<String> = archived.into_iter().map(|f| f.s3_key).collect();
+        let cutoff = Utc::now() - Duration::days(self.config.cold_after_days as i64);
+        let plan = plan_archive(
+            books,
+            &repo.last_access().await?,
+            &archived,
+            cutoff,
+            self.config.max_per_run,
+        );
+
+        // Start the clock for files no run has seen yet
+        for key in &plan.untracked {
+            repo.record_access(key).await?;
+        }
+        summary.tracked = plan.untracked.len();
+
+        for key in &plan.cold {
+            match self.archive(key).await {
+                Ok(()) => summary.archived += 1,
+                Err(e) => {
+                    tracing::warn!(key = %key, "Archiving failed: {}", e);
+                    summary.failed += 1;
+                }
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Move a file to the cold storage class
+    async fn archive(&self, key: &str) -> Result<()> {
+        self.s3
+            .set_storage_class(key, &self.config.storage_class)
+            .await?;
+        TieringRepository::new(&self.pool)
+            .mark_archived(key, &self.config.storage_class)
+            .await
+    }
+
+    /// Note that a file is being served, restoring it if it was archived
+    pub async fn ensure_available(&self, key: &str) -> Result<Availability> {
+        let repo = TieringRepository::new(&self.pool);
+        repo.record_access(key).await?;
+
+        let Some(file) = repo.get(key).await? else {
+            return Ok(Availability::Available);
+        };
+        if !needs_restore(&file.storage_class) {
+            self.s3
+                .set_storage_class(key, DEFAULT_STORAGE_CLASS)
+                .await?;
+            repo.remove(key).await?;
+            return Ok(Availability::Available);
+        }
+
+        if self.finish_restore(key).await? {
+            return Ok(Availability::Available);
+        }
+        if file.status == TierStatus::Archived {
+            self.s3
+                .restore_object(key, self.config.restore_days)
+                .await?;
+            repo.mark_retrieving(key).await?;
+            tracing::info!(key = %key, "Restoring archived file");
+        }
+        Ok(Availability::Retrieving)
+    }
+
+    /// Move a restored file back to the default class
+    ///
+    /// Returns `false` while the file still needs restoring.
+    async fn finish_restore(&self, key: &str) -> Result<bool> {
+        let state = self.s3.storage_state(key).await?;
+        let archived = state.storage_class.as_deref().is_some_and(needs_restore);
+        if archived && !state.is_restored() {
+            return Ok(false);
+        }
+
+        self.s3
+            .set_storage_class(key, DEFAULT_STORAGE_CLASS)
+            .await?;
+        TieringRepository::new(&self.pool).remove(key).await?;
+        Ok(true)
+    }
+}
+
+/// Whether files in a storage class must be restored before they are read
+fn needs_restore(storage_class: &str) -> bool {
+    matches!(
+        storage_class.to_ascii_uppercase().as_str(),
+        "GLACIER" | "DEEP_ARCHIVE"
+    )
+}
+
+/// Pick files not served since `cutoff`, up to `limit`
+fn plan_archive(
+    books: &[LibraryBook],
+    last_access: &HashMap<String, DateTime<Utc>>,
+    archived: &HashSet<String>,
+    cutoff: DateTime<Utc>,
+    limit: usize,
+) -> ArchivePlan {
+    let mut plan = ArchivePlan::default();
+    let mut cold = Vec::new();
+
+    for format in books.iter().flat_map(|book| &book.formats) {
+        let key = &format.s3_key;
+        if archived.contains(key) {
+            continue;
+        }
+        match last_access.get(key) {
+            Some(at) if *at < cutoff => cold.push((*at, key.clone())),
+            Some(_) => {}
+            None => plan.untracked.push(key.clone()),
+        }
+    }
+
+    cold.sort();
+    plan.cold = cold.into_iter().take(limit).map(|(_, key)| key).collect();
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::{BookFormat, FormatType};
+
+    fn book(keys: &[&str]) -> LibraryBook {
+        let mut book = LibraryBook::new("Title".to_string(), "Author/Title".to_string());
+        book.formats = keys
+            .iter()
+            .map(|key| BookFormat {
+                format: FormatType::Epub,
+                s3_key: key.to_string(),
+                size: 1,
+            })
+            .collect();
+        book
+    }
+
+    #[test]
+    fn test_plan_archive() {
+        let now = Utc::now();
+        let books = vec![book(&["a.epub", "a.pdf"]), book(&["b.epub", "c.epub"])];
+        let last_access: HashMap<String, DateTime<Utc>> = [
+            ("a.epub", now - Duration::days(400)),
+            ("a.pdf", now - Duration::days(200)),
+            ("b.epub", now - Duration::days(1)),
+            ("old.epub", now - Duration::days(900)),
+        ]
+        .into_iter()
+        .map(|(key, at)| (key.to_string(), at))
+        .collect();
+        let cutoff = now - Duration::days(180);
+
+        let plan = plan_archive(&books, &last_access, &HashSet::new(), cutoff, 10);
+        // Oldest first; files no longer in the library are left alone
+        assert_eq!(plan.cold, vec!["a.epub", "a.pdf"]);
+        assert_eq!(plan.untracked, vec!["c.epub"]);
+
+        let archived = HashSet::from(["a.epub".to_string()]);
+        let plan = plan_archive(&books, &last_access, &archived, cutoff, 10);
+        assert_eq!(plan.cold, vec!["a.pdf"]);
+
+        let plan = plan_archive(&books, &last_access, &HashSet::new(), cutoff, 1);
+        assert_eq!(plan.cold, vec!["a.epub"]);
+    }
+
+    #[test]
+    fn test_needs_restore() {
+        assert!(needs_restore("GLACIER"));
+        assert!(needs_restore("deep_archive"));
+        assert!(!needs_restore("GLACIER_IR"));
+        assert!(!needs_restore("STANDARD_IA"));
+    }
+}
@@ -0,0 +1,462 @@
+//! Dead-link checks for the web links in documents
+//!
+//! Technical documentation goes stale as the sites it links to move. A
+//! [`LinkChecker`] takes a document's links (see
+//! [`DocumentParser::external_links`]), requests each distinct URL once, and
+//! reports every URL with the places in the document that link to it, so
+//! dead ones can be fixed or annotated.
+//!
+//! Checks are rate limited: at most [`LinkCheckOptions::concurrency`]
+//! requests are in flight, and requests to the same host are spaced
+//! [`LinkCheckOptions::host_interval`] apart. Each URL is tried with `HEAD`
+//! and, when that does not succeed, with `GET`, since some servers reject
+//! or mishandle `HEAD`. Redirects are followed.
+//!
+//! Links come from uploaded documents, so they are not trusted: only `http`
+//! and `https` URLs are requested, and only public addresses are connected
+//! to. Hosts are resolved by the checker itself, and loopback, private,
+//! link-local (such as the `169.254.169.254` metadata endpoint) and other
+//! reserved addresses are refused, for the first request and after every
+//! redirect.
+//!
+//! [`DocumentParser::external_links`]: crate::document::DocumentParser::external_links
+
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::{stream, StreamExt};
+use hyper::client::connect::dns::Name;
+use parking_lot::Mutex;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use reqwest::{redirect, Method, Url};
+use serde::Serialize;
+use tokio::time::Instant;
+use utoipa::ToSchema;
+
+use crate::document::WebLink;
+
+/// Limits on how hard a check hits the linked sites
+#[derive(Debug, Clone)]
+pub struct LinkCheckOptions {
+    /// Requests in flight at once
+    pub concurrency: usize,
+    /// Least time between two requests to the same host
+    pub host_interval: Duration,
+    /// Time allowed for each request
+    pub timeout: Duration,
+}
+
+impl Default for LinkCheckOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            host_interval: Duration::from_secs(1),
+            timeout: Duration::from_secs(15),
+        }
+    }
+}
+
+/// What checking a URL found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkHealth {
+    /// Answered with a success status
+    Alive,
+    /// The page is gone (404, 410 and other client errors)
+    Broken,
+    /// No answer (DNS failure, refused connection, timeout) or a server
+    /// error
+    Unreachable,
+    /// The server would not say (401, 403, 429)
+    Unverified,
+}
+
+impl LinkHealth {
+    /// Health of a URL answering with `status`, redirects followed
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            200..=399 => Self::Alive,
+            401 | 403 | 429 => Self::Unverified,
+            400..=499 => Self::Broken,
+            _ => Self::Unreachable,
+        }
+    }
+
+    /// Whether the link should be reported as dead
+    pub fn is_dead(self) -> bool {
+        matches!(self, Self::Broken | Self::Unreachable)
+    }
+}
+
+/// A checked URL and the links to it
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkReport {
+    /// URL as requested, without its fragment
+    pub url: String,
+    pub health: LinkHealth,
+    /// Status of the last response, if the server answered
+    pub status: Option<u16>,
+    /// Why no response arrived
+    pub error: Option<String>,
+    /// Every link to the URL, in reading order
+    pub locations: Vec<WebLink>,
+}
+
+/// Checks URLs within the rate limits of [`LinkCheckOptions`]
+pub struct LinkChecker {
+    http: reqwest::Client,
+    concurrency: usize,
+    hosts: HostLimiter,
+}
+
+impl LinkChecker {
+    pub fn new(options: LinkCheckOptions) -> Self {
+        let limit = redirect::Policy::default();
+        let http = reqwest::Client::builder()
+            .timeout(options.timeout)
+            .user_agent(concat!("amnesia-server/", env!("CARGO_PKG_VERSION")))
+            // A proxy would resolve hosts where the checks below cannot see
+            .no_proxy()
+            .dns_resolver(Arc::new(PublicResolver))
+            .redirect(redirect::Policy::custom(move |attempt| {
+                match check_url(attempt.url()) {
+                    Ok(()) => limit.redirect(attempt),
+                    Err(refused) => attempt.error(refused),
+                }
+            }))
+            .build()
+            // Falling back to a default client would drop the address checks
+            .expect("Failed to build the link checker's HTTP client");
+        Self {
+            http,
+            concurrency: options.concurrency.max(1),
+            hosts: HostLimiter::new(options.host_interval),
+        }
+    }
+
+    /// Check each distinct URL of `links`, in order of first appearance
+    ///
+    /// `progress` is called with the number of URLs checked so far and the
+    /// number of distinct URLs, once before the first check and after each.
+    pub async fn check(
+        &self,
+        links: Vec<WebLink>,
+        mut progress: impl FnMut(usize, usize),
+    ) -> Vec<LinkReport> {
+        let groups = group_links(links);
+        let total = groups.len();
+        let mut checked = 0;
+        progress(checked, total);
+        stream::iter(groups)
+            .map(|(url, locations)| async move {
+                let (health, status, error) = match self.fetch_status(&url).await {
+                    Ok(status) => (LinkHealth::from_status(status), Some(status), None),
+                    Err(error) => (LinkHealth::Unreachable, None, Some(error)),
+                };
+                LinkReport {
+                    url,
+                    health,
+                    status,
+                    error,
+                    locations,
+                }
+            })
+            .buffered(self.concurrency)
+            .inspect(|_| {
+                checked += 1;
+                progress(checked, total);
+            })
+            .collect()
+            .await
+    }
+
+    /// Final status of `url`, by `HEAD` and then `GET`
+    async fn fetch_status(&self, url: &str) -> Result<u16, String> {
+        let parsed = Url::parse(url).map_err(|e| format!("Invalid URL: {}", e))?;
+        check_url(&parsed).map_err(|refused| refused.to_string())?;
+        let host = parsed.host_str().unwrap_or_default().to_ascii_lowercase();
+
+        match self.request(Method::HEAD, parsed.clone(), &host).await {
+            Ok(status) if LinkHealth::from_status(status) == LinkHealth::Alive => Ok(status),
+            Ok(_) => self.request(Method::GET, parsed, &host).await,
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn request(&self, method: Method, url: Url, host: &str) -> Result<u16, String> {
+        tokio::time::sleep(self.hosts.reserve(host, Instant::now())).await;
+        // Only the status is needed; the body is dropped unread
+        let response = self.http.request(method, url).send().await.map_err(|e| {
+            if let Some(refused) = refused_cause(&e) {
+                refused.to_string()
+            } else if e.is_timeout() {
+                "Timed out".to_string()
+            } else if e.is_connect() {
+                "Could not connect".to_string()
+            } else {
+                e.to_string()
+            }
+        })?;
+        Ok(response.status().as_u16())
+    }
+}
+
+/// A URL or address the checker will not connect to
+#[derive(Debug)]
+struct Refused(String);
+
+impl fmt::Display for Refused {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Refused: {}", self.0)
+    }
+}
+
+impl std::error::Error for Refused {}
+
+/// The [`Refused`] that made a request fail, if any
+fn refused_cause(error: &reqwest::Error) -> Option<&Refused> {
+    let mut source = std::error::Error::source(error);
+    while let Some(error) = source {
+        if let Some(refused) = error.downcast_ref::<Refused>() {
+            return Some(refused);
+        }
+        source = error.source();
+    }
+    None
+}
+
+/// Refuse URLs that are not `http` or `https`, and IP address hosts that
+/// are not public; named hosts are checked by [`PublicResolver`]
+fn check_url(url: &Url) -> Result<(), Refused> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(Refused(format!("{} URLs are not checked", url.scheme())));
+    }
+    let Some(host) = url.host_str() else {
+        return Err(Refused("URL has no host".to_string()));
+    };
+    // IPv6 hosts keep their brackets
+    match host.trim_start_matches('[').trim_end_matches(']').parse() {
+        Ok(ip) if !is_public(ip) => Err(Refused(format!("{} is not a public address", ip))),
+        _ => Ok(()),
+    }
+}
+
+/// Resolves hosts with the system resolver, keeping only public addresses
+///
+/// Connections go to the addresses returned here, so a host cannot pass
+/// the check and then resolve elsewhere.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let host = name.as_str();
+            let (public, other): (Vec<_>, Vec<_>) = tokio::net::lookup_host((host, 0))
+                .await?
+                .partition(|addr| is_public(addr.ip()));
+            match other.first() {
+                Some(addr) if public.is_empty() => Err(Refused(format!(
+                    "{} resolves to {}, which is not a public address",
+                    host,
+                    addr.ip()
+                ))
+                .into()),
+                _ => Ok(Box::new(public.into_iter()) as Addrs),
+            }
+        })
+    }
+}
+
+/// Whether `ip` is a public internet address
+///
+/// Loopback, private, link-local, shared (carrier-grade NAT), multicast,
+/// documentation, benchmarking and reserved ranges are not. IPv4 addresses
+/// embedded in IPv6 ones are judged as IPv4.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => is_public_v6(ip),
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || a == 0
+        || (a == 100 && (64..128).contains(&b))
+        || (a == 192 && b == 0 && c == 0)
+        || (a == 198 && (b == 18 || b == 19))
+        || a >= 240)
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let segments = ip.segments();
+    // IPv4-mapped (::ffff:0:0/96) and NAT64 (64:ff9b::/96) addresses
+    if let Some(v4) = ip.to_ipv4_mapped() {
+        return is_public_v4(v4);
+    }
+    if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+        let [.., a, b, c, d] = ip.octets();
+        return is_public_v4(Ipv4Addr::new(a, b, c, d));
+    }
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        || segments[0] & 0xfe00 == 0xfc00
+        || segments[0] & 0xffc0 == 0xfe80
+        || (segments[0] == 0x2001 && segments[1] == 0x0db8))
+}
+
+/// Spaces requests to the same host a fixed interval apart
+struct HostLimiter {
+    interval: Duration,
+    /// Earliest time of the next request to each host
+    next: Mutex<HashMap<String, Instant>>,
+}
+
+impl HostLimiter {
+    fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            next: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// How long to wait, from `now`, before requesting from `host`; the
+    /// slot is taken, so concurrent callers queue up behind each other
+    fn reserve(&self, host: &str, now: Instant) -> Duration {
+        let mut next = self.next.lock();
+        let slot = next.get(host).map_or(now, |&at| at.max(now));
+        next.insert(host.to_string(), slot + self.interval);
+        slot - now
+    }
+}
+
+/// Links grouped by URL, fragment removed, in order of first appearance
+fn group_links(links: Vec<WebLink>) -> Vec<(String, Vec<WebLink>)> {
+    let mut groups: Vec<(String, Vec<WebLink>)> = Vec::new();
+    let mut index_of: HashMap<String, usize> = HashMap::new();
+    for link in links {
+        let url = link.url.split('#').next().unwrap_or_default().to_string();
+        match index_of.get(&url) {
+            Some(&index) => groups[index].1.push(link),
+            None => {
+                index_of.insert(url.clone(), groups.len());
+                groups.push((url, vec![link]));
+            }
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(item_index: usize, url: &str) -> WebLink {
+        WebLink {
+            item_index,
+            href: None,
+            url: url.to_string(),
+            text: url.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_health_from_status() {
+        assert_eq!(LinkHealth::from_status(200), LinkHealth::Alive);
+        assert_eq!(LinkHealth::from_status(304), LinkHealth::Alive);
+        assert_eq!(LinkHealth::from_status(404), LinkHealth::Broken);
+        assert_eq!(LinkHealth::from_status(410), LinkHealth::Broken);
+        assert_eq!(LinkHealth::from_status(403), LinkHealth::Unverified);
+        assert_eq!(LinkHealth::from_status(429), LinkHealth::Unverified);
+        assert_eq!(LinkHealth::from_status(503), LinkHealth::Unreachable);
+        assert!(LinkHealth::Broken.is_dead());
+        assert!(!LinkHealth::Unverified.is_dead());
+    }
+
+    #[test]
+    fn test_host_limiter_spaces_requests_per_host() {
+        let limiter = HostLimiter::new(Duration::from_secs(1));
+        let now = Instant::now();
+
+        assert_eq!(limiter.reserve("a.org", now), Duration::ZERO);
+        assert_eq!(limiter.reserve("a.org", now), Duration::from_secs(1));
+        assert_eq!(limiter.reserve("a.org", now), Duration::from_secs(2));
+        assert_eq!(limiter.reserve("b.org", now), Duration::ZERO);
+        // Slots in the past are not owed
+        let later = now + Duration::from_secs(10);
+        assert_eq!(limiter.reserve("a.org", later), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_public_addresses() {
+        for ip in ["93.184.216.34", "8.8.8.8", "2606:4700::1111"] {
+            assert!(is_public(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::",
+            "::1",
+            "fe80::1",
+            "fd00::1",
+            "::ffff:127.0.0.1",
+            "64:ff9b::a9fe:a9fe",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_refuses_internal_urls() {
+        let checker = LinkChecker::new(LinkCheckOptions::default());
+        let reports = checker
+            .check(
+                vec![
+                    link(0, "http://169.254.169.254/latest/meta-data/"),
+                    link(0, "http://[::1]:8080/"),
+                    link(1, "http://localhost:9/"),
+                    link(2, "file:///etc/passwd"),
+                ],
+                |_, _| {},
+            )
+            .await;
+        assert_eq!(reports.len(), 4);
+        for report in &reports {
+            assert_eq!(report.health, LinkHealth::Unreachable);
+            let error = report.error.as_deref().unwrap_or_default();
+            assert!(error.starts_with("Refused"), "{}: {}", report.url, error);
+        }
+    }
+
+    #[test]
+    fn test_group_links() {
+        let groups = group_links(vec![
+            link(0, "https://a.org/x#intro"),
+            link(1, "https://b.org/"),
+            link(3, "https://a.org/x#usage"),
+        ]);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].0, "https://a.org/x");
+        let items: Vec<usize> = groups[0].1.iter().map(|l| l.item_index).collect();
+        assert_eq!(items, vec![0, 3]);
+        assert_eq!(groups[1].0, "https://b.org/");
+    }
+}
@@ -4,44 +4,54 @@
 //! and multi-device reading progress sync.
 
 use axum::{
+    middleware,
     routing::get,
     Router,
     Json,
-    extract::State,
+    extract::{DefaultBodyLimit, State},
 };
 use serde::Serialize;
+use std::future::Future;
 use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::signal;
 use tower_http::cors::{CorsLayer, Any};
 use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-mod annotations;
-mod bibliography;
-mod cfi;
-mod config;
-mod db;
-mod document;
-mod error;
-mod formats;
-mod html;
-mod library;
-mod mupdf;
-mod ocr;
-mod opds;
-mod pdf;
-mod routes;
-mod state;
-mod storage;
-mod sync;
-mod upload;
-
-use config::Config;
-use library::LibraryScanner;
-use routes::opds::LibraryCache;
-use routes::upload::create_upload_state;
-use state::AppState;
-use storage::S3Client;
+use amnesia_server::config::Config;
+use amnesia_server::db;
+#[cfg(feature = "ocr")]
+use amnesia_server::db::NotificationRepository;
+use amnesia_server::error::problem_instance;
+use amnesia_server::formats::isolation;
+#[cfg(feature = "ingest")]
+use amnesia_server::ingest::Ingestor;
+use amnesia_server::library::{
+    DuplicatePageIndexer, LibraryScanner, StorageTiering, TextStatsIndexer,
+};
+#[cfg(feature = "ocr")]
+use amnesia_server::ocr::{OcrService, OcrServiceConfig};
+use amnesia_server::routes;
+use amnesia_server::routes::limits::{self, JSON_BODY_LIMIT};
+use amnesia_server::routes::opds::LibraryCache;
+use amnesia_server::routes::request_id::{self, REQUEST_ID_HEADER};
+use amnesia_server::routes::upload::create_upload_state;
+#[cfg(feature = "ingest")]
+use amnesia_server::scheduler::TASK_FEED_INGEST;
+#[cfg(feature = "ocr")]
+use amnesia_server::scheduler::TASK_OCR_RETRY;
+use amnesia_server::scheduler::{
+    Scheduler, TASK_CACHE_EVICTION, TASK_DUPLICATE_PAGES, TASK_LIBRARY_RESCAN,
+    TASK_STORAGE_TIERING, TASK_TEXT_STATS, TASK_UPLOAD_CLEANUP,
+};
+use amnesia_server::spawn_pool_health_checks;
+use amnesia_server::state::AppState;
+use amnesia_server::storage::S3Client;
+
+/// Interval between MuPDF context pool health checks
+const CONTEXT_POOL_HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(60);
 
 #[derive(Serialize)]
 struct HealthResponse {
@@ -58,6 +68,12 @@ async fn health_check(State(_state): State<AppState>) -> Json<HealthResponse> {
 
 #[tokio::main]
 async fn main() {
+    // Parse worker mode: stdout carries the IPC response, so this must run
+    // before logging is set up
+    if std::env::args().nth(1).as_deref() == Some(isolation::WORKER_ARG) {
+        std::process::exit(isolation::run_worker().await);
+    }
+
     // Initialize tracing
     tracing_subscriber::registry()
         .with(tracing_subscriber::EnvFilter::try_from_default_env()
@@ -93,7 +109,7 @@ async fn main() {
 
     // Create library cache and initial scan
     let library_cache = LibraryCache::new();
-    let scanner = LibraryScanner::new(s3_client);
+    let scanner = Arc::new(LibraryScanner::new(s3_client.clone()));
     if let Err(e) = library_cache.refresh(&scanner).await {
         tracing::warn!("Initial library scan failed: {}. Will retry on /opds/refresh", e);
     } else {
@@ -105,7 +121,8 @@ async fn main() {
     let cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_methods(Any)
-        .allow_headers(Any);
+        .allow_headers(Any)
+        .expose_headers([REQUEST_ID_HEADER.clone()]);
 
     // Create upload state with local chunk storage
     let chunk_base_path = std::env::var("CHUNK_STORAGE_PATH")
@@ -115,8 +132,106 @@ async fn main() {
         std::path::PathBuf::from(chunk_base_path),
     );
 
-    // Start upload session cleanup task
-    upload_state.session_manager.clone().start_cleanup_task();
+    // Schedule background maintenance
+    let scheduler = Scheduler::new(Duration::from_secs(config.scheduler.jitter_secs));
+    let sessions = upload_state.session_manager.clone();
+    schedule(&scheduler, &config, TASK_UPLOAD_CLEANUP, move || {
+        let sessions = sessions.clone();
+        async move {
+            let count = sessions.cleanup_expired().await;
+            Ok(format!("Removed {} expired upload sessions", count))
+        }
+    });
+    let document_cache = app_state.document_cache().clone();
+    schedule(&scheduler, &config, TASK_CACHE_EVICTION, move || {
+        let document_cache = document_cache.clone();
+        async move {
+            let count = document_cache.evict_orphans().await;
+            Ok(format!("Evicted {} orphaned documents", count))
+        }
+    });
+    let rescan_cache = library_cache.clone();
+    schedule(&scheduler, &config, TASK_LIBRARY_RESCAN, move || {
+        let (library_cache, scanner) = (rescan_cache.clone(), Arc::clone(&scanner));
+        async move {
+            library_cache.refresh(&scanner).await?;
+            Ok(format!(
+                "Library has {} books",
+                library_cache.get_books().await.len()
+            ))
+        }
+    });
+    let indexer = Arc::new(TextStatsIndexer::new(
+        s3_client.clone(),
+        db_pool.clone(),
+        &config,
+    ));
+    let stats_cache = library_cache.clone();
+    schedule(&scheduler, &config, TASK_TEXT_STATS, move || {
+        let (library_cache, indexer) = (stats_cache.clone(), Arc::clone(&indexer));
+        async move {
+            let books = library_cache.get_books().await;
+            Ok(indexer.run(&books, false).await?.to_string())
+        }
+    });
+    let duplicates = Arc::new(DuplicatePageIndexer::new(
+        s3_client.clone(),
+        db_pool.clone(),
+        &config,
+    ));
+    let duplicates_cache = library_cache.clone();
+    schedule(&scheduler, &config, TASK_DUPLICATE_PAGES, move || {
+        let (library_cache, duplicates) = (duplicates_cache.clone(), Arc::clone(&duplicates));
+        async move {
+            let books = library_cache.get_books().await;
+            Ok(duplicates.run(&books, false).await?.to_string())
+        }
+    });
+    let tiering = Arc::new(StorageTiering::new(
+        s3_client.clone(),
+        db_pool.clone(),
+        &config,
+    ));
+    let tiering_cache = library_cache.clone();
+    schedule(&scheduler, &config, TASK_STORAGE_TIERING, move || {
+        let (library_cache, tiering) = (tiering_cache.clone(), Arc::clone(&tiering));
+        async move {
+            let books = library_cache.get_books().await;
+            Ok(tiering.run(&books).await?.to_string())
+        }
+    });
+    #[cfg(feature = "ocr")]
+    {
+        let state = app_state.clone();
+        schedule(&scheduler, &config, TASK_OCR_RETRY, move || {
+            let state = state.clone();
+            async move {
+                let service = OcrService::new(OcrServiceConfig::default());
+                let notifications = NotificationRepository::new(state.db());
+                let summary = state
+                    .ocr_retries()
+                    .retry(&service, state.pdf_cache(), &notifications)
+                    .await;
+                Ok(summary.to_string())
+            }
+        });
+    }
+    #[cfg(feature = "ingest")]
+    if !config.ingest.feeds.is_empty() {
+        let ingestor = Arc::new(Ingestor::new(
+            s3_client,
+            db_pool.clone(),
+            config.ingest.clone(),
+        ));
+        schedule(&scheduler, &config, TASK_FEED_INGEST, move || {
+            let ingestor = Arc::clone(&ingestor);
+            async move { Ok(ingestor.run().await?.to_string()) }
+        });
+    }
+    scheduler.start();
+
+    // Recycle poisoned or worn-out MuPDF contexts between requests
+    spawn_pool_health_checks(CONTEXT_POOL_HEALTH_CHECK_INTERVAL);
 
     // Build router
     let app = Router::new()
@@ -126,16 +241,36 @@ async fn main() {
         // Legacy /api/v1/books endpoint removed - use /api/v1/documents instead
         .nest("/api/v1/pdf", routes::pdf::router())
         .nest("/api/v1/upload", routes::upload::router(upload_state))
-        .nest("/opds", routes::opds::router(library_cache))
+        .nest("/opds", routes::opds::router(library_cache.clone()))
+        .nest("/feeds", routes::feeds::router(library_cache.clone()))
+        .nest(routes::hypothesis::API_PATH, routes::hypothesis::router())
         .nest("/files", routes::files::router())
         .nest("/api/v1/progress", routes::progress::router(db_pool.clone()))
         .nest("/api/v1/highlights", routes::highlights::router(db_pool.clone()))
-        .nest("/api/v1/annotations", routes::annotations::router())
+        .nest("/api/v1/annotations", routes::annotations::router(library_cache.clone()))
         .nest("/api/v1/sync", routes::sync::router())
+        .nest("/api/v1/account", routes::account::router())
+        .nest(
+            "/api/v1/collections",
+            routes::collections::router(library_cache.clone()),
+        )
+        .nest("/api/v1/notifications", routes::notifications::router())
         .nest("/api/v1/search", routes::search::router())
         .nest("/api/v1/extract", routes::extract::router())
         .nest("/api/v1/bibliography", routes::bibliography::router())
-        .layer(TraceLayer::new_for_http())
+        .nest("/api/v1/client-errors", routes::client_errors::router())
+        .nest(
+            "/api/v1/admin",
+            routes::admin::router(scheduler, library_cache),
+        )
+        .merge(routes::openapi::router())
+        // Routers and routes that take larger or smaller bodies set their own
+        .layer(DefaultBodyLimit::max(JSON_BODY_LIMIT))
+        .layer(middleware::from_fn(limits::problem_payload_too_large))
+        .layer(middleware::from_fn(problem_instance))
+        .layer(TraceLayer::new_for_http().make_span_with(request_id::make_span))
+        // Outside TraceLayer so the request span can record the ID
+        .layer(middleware::from_fn(request_id::request_id))
         .layer(cors)
         .with_state(app_state);
 
@@ -153,6 +288,21 @@ async fn main() {
     tracing::info!("Server shutdown complete");
 }
 
+/// Register a task if the configuration gives it a schedule
+fn schedule<F, Fut>(scheduler: &Scheduler, config: &Config, name: &str, job: F)
+where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = anyhow::Result<String>> + Send + 'static,
+{
+    let Some(expression) = config.scheduler.schedules.get(name) else {
+        tracing::info!("Task {} is disabled", name);
+        return;
+    };
+    if let Err(e) = scheduler.register(name, expression, job) {
+        tracing::warn!("Task {} not scheduled: {}", name, e);
+    }
+}
+
 /// Graceful shutdown signal handler
 async fn shutdown_signal() {
     let ctrl_c = async {
@@ -12,6 +12,8 @@
 //! 1. **Concurrency Limiting**: Tracks how many operations are in flight
 //! 2. **Metrics Collection**: Provides statistics on pool utilization
 //! 3. **Operation Wrapping**: Ensures proper document cleanup via RAII
+//! 4. **Health Checks**: Recycles slots after a fixed number of operations or
+//!    after an error that suggests MuPDF state was corrupted
 //!
 //! The "pool" terminology is kept for API familiarity, but this is effectively
 //! a concurrency limiter with metrics tracking.
@@ -32,14 +34,92 @@
 //! │                            [active_count--]                    │
 //! └────────────────────────────────────────────────────────────────┘
 //! ```
+//!
+//! # Recycling
+//!
+//! Each slot counts its operations and failures. On release, a slot that
+//! has served [`PoolConfig::max_operations`] operations, hit a poisoning
+//! error (a MuPDF exception or a panic), or failed
+//! [`PoolConfig::max_consecutive_failures`] times in a row is dropped instead
+//! of returned. The pool keeps [`PoolConfig::warm_standby`] fresh slots ready
+//! so that recycling never leaves the next caller creating one.
 
 use parking_lot::Mutex;
-use std::ops::Deref;
+use serde::Serialize;
+use std::cell::Cell;
+use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use utoipa::ToSchema;
 
 use crate::document::DocumentError;
 
+static SHARED_POOL: OnceLock<SharedContextPool> = OnceLock::new();
+
+/// Pool sizing and recycling policy
+#[derive(Debug, Clone)]
+pub struct PoolConfig {
+    /// Maximum idle contexts kept in the pool
+    pub max_size: usize,
+    /// Idle contexts created ahead of demand (capped at `max_size`)
+    pub warm_standby: usize,
+    /// Operations a context serves before it is recycled
+    pub max_operations: usize,
+    /// Consecutive failures after which a context is recycled
+    pub max_consecutive_failures: usize,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            max_size: 8,
+            warm_standby: 2,
+            max_operations: 1000,
+            max_consecutive_failures: 3,
+        }
+    }
+}
+
+/// How an operation failure affects the context that ran it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// Failure specific to the request (bad index, unsupported input)
+    Recoverable,
+    /// MuPDF raised an exception or the operation panicked; the context is
+    /// no longer trusted
+    Poisoning,
+}
+
+impl ErrorClass {
+    /// Classify an operation error
+    pub fn of(error: &DocumentError) -> Self {
+        match error {
+            DocumentError::ContextError(_) => Self::Poisoning,
+            _ => Self::Recoverable,
+        }
+    }
+}
+
+/// Error of an operation run on a pooled context
+pub trait PooledError {
+    /// How the failure affects the context that ran the operation
+    fn class(&self) -> ErrorClass;
+
+    /// The error reported for an operation that panicked
+    fn panicked() -> Self;
+}
+
+impl PooledError for DocumentError {
+    fn class(&self) -> ErrorClass {
+        ErrorClass::of(self)
+    }
+
+    fn panicked() -> Self {
+        DocumentError::ContextError("MuPDF operation panicked".to_string())
+    }
+}
+
 /// Thread-safe MuPDF context pool
 ///
 /// MuPDF's fz_context is NOT thread-safe. Each operation requires its own context.
@@ -47,12 +127,18 @@ use crate::document::DocumentError;
 pub struct ContextPool {
     /// Pool of available contexts (wrapped in Option to allow take/put)
     available: Mutex<Vec<ContextWrapper>>,
-    /// Maximum pool size
-    max_size: usize,
+    /// Sizing and recycling policy
+    config: PoolConfig,
     /// Total contexts created (for metrics)
     created_count: AtomicUsize,
     /// Active contexts (borrowed from pool)
     active_count: AtomicUsize,
+    /// Contexts dropped by health checks
+    recycled_count: AtomicUsize,
+    /// Operations run across all contexts
+    operation_count: AtomicUsize,
+    /// Failed operations across all contexts
+    failure_count: AtomicUsize,
 }
 
 /// Wrapper to make mupdf types work with our pool
@@ -60,16 +146,65 @@ pub struct ContextPool {
 struct ContextWrapper {
     /// Marker to track pool membership
     _id: usize,
+    /// Operations served by this context
+    operations: Cell<usize>,
+    /// Failures since the last success
+    consecutive_failures: Cell<usize>,
+    /// Set after a poisoning error
+    poisoned: Cell<bool>,
+}
+
+impl ContextWrapper {
+    fn new(id: usize) -> Self {
+        Self {
+            _id: id,
+            operations: Cell::new(0),
+            consecutive_failures: Cell::new(0),
+            poisoned: Cell::new(false),
+        }
+    }
+
+    fn is_healthy(&self, config: &PoolConfig) -> bool {
+        !self.poisoned.get()
+            && self.operations.get() < config.max_operations
+            && self.consecutive_failures.get() < config.max_consecutive_failures
+    }
 }
 
 impl ContextPool {
     /// Create a new context pool
     pub fn new(max_size: usize) -> Self {
-        Self {
-            available: Mutex::new(Vec::with_capacity(max_size)),
+        Self::with_config(PoolConfig {
             max_size,
+            ..PoolConfig::default()
+        })
+    }
+
+    /// Create a pool with the given policy and fill its warm standby
+    pub fn with_config(config: PoolConfig) -> Self {
+        let pool = Self {
+            available: Mutex::new(Vec::with_capacity(config.max_size)),
+            config,
             created_count: AtomicUsize::new(0),
             active_count: AtomicUsize::new(0),
+            recycled_count: AtomicUsize::new(0),
+            operation_count: AtomicUsize::new(0),
+            failure_count: AtomicUsize::new(0),
+        };
+        pool.refill_standby(&mut pool.available.lock());
+        pool
+    }
+
+    fn create_context(&self) -> ContextWrapper {
+        let id = self.created_count.fetch_add(1, Ordering::Relaxed);
+        ContextWrapper::new(id)
+    }
+
+    /// Top the idle list up to the warm standby level
+    fn refill_standby(&self, available: &mut Vec<ContextWrapper>) {
+        let target = self.config.warm_standby.min(self.config.max_size);
+        while available.len() < target {
+            available.push(self.create_context());
         }
     }
 
@@ -82,10 +217,7 @@ impl ContextPool {
             pool.pop()
         };
 
-        let wrapper = wrapper.unwrap_or_else(|| {
-            let id = self.created_count.fetch_add(1, Ordering::Relaxed);
-            ContextWrapper { _id: id }
-        });
+        let wrapper = wrapper.unwrap_or_else(|| self.create_context());
 
         self.active_count.fetch_add(1, Ordering::Relaxed);
 
@@ -95,31 +227,75 @@ impl ContextPool {
         }
     }
 
-    /// Return a context to the pool
+    /// Return a context to the pool, recycling it if unhealthy
     fn release(&self, wrapper: ContextWrapper) {
         self.active_count.fetch_sub(1, Ordering::Relaxed);
 
         let mut pool = self.available.lock();
-        if pool.len() < self.max_size {
+        if !wrapper.is_healthy(&self.config) {
+            self.recycled_count.fetch_add(1, Ordering::Relaxed);
+            self.refill_standby(&mut pool);
+        } else if pool.len() < self.config.max_size {
             pool.push(wrapper);
         }
         // Drop if pool is full
     }
 
+    /// Drop unhealthy idle contexts and restore the warm standby
+    ///
+    /// Returns the number of contexts recycled.
+    pub fn health_check(&self) -> usize {
+        let mut pool = self.available.lock();
+        let before = pool.len();
+        pool.retain(|wrapper| wrapper.is_healthy(&self.config));
+        let recycled = before - pool.len();
+        self.recycled_count.fetch_add(recycled, Ordering::Relaxed);
+        self.refill_standby(&mut pool);
+        recycled
+    }
+
+    /// Run [`health_check`](Self::health_check) on an interval and log pool
+    /// stats under the `mupdf_pool` target
+    pub fn spawn_health_checks(self: Arc<Self>, period: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                let recycled = self.health_check();
+                let stats = self.stats();
+                tracing::debug!(
+                    target: "mupdf_pool",
+                    created = stats.created,
+                    active = stats.active,
+                    available = stats.available,
+                    recycled = stats.recycled,
+                    operations = stats.operations,
+                    failures = stats.failures,
+                    "context pool health check recycled {} context(s)",
+                    recycled
+                );
+            }
+        })
+    }
+
     /// Get pool statistics
     pub fn stats(&self) -> PoolStats {
         PoolStats {
             created: self.created_count.load(Ordering::Relaxed),
             active: self.active_count.load(Ordering::Relaxed),
             available: self.available.lock().len(),
-            max_size: self.max_size,
+            max_size: self.config.max_size,
+            warm_standby: self.config.warm_standby.min(self.config.max_size),
+            recycled: self.recycled_count.load(Ordering::Relaxed),
+            operations: self.operation_count.load(Ordering::Relaxed),
+            failures: self.failure_count.load(Ordering::Relaxed),
         }
     }
 }
 
 impl Default for ContextPool {
     fn default() -> Self {
-        Self::new(8)
+        Self::with_config(PoolConfig::default())
     }
 }
 
@@ -130,6 +306,35 @@ pub struct PooledContext<'a> {
 }
 
 impl<'a> PooledContext<'a> {
+    /// Run an operation against this context, recording its outcome
+    ///
+    /// Panics are caught and reported as [`PooledError::panicked`].
+    pub fn run<F, T, E>(&self, f: F) -> Result<T, E>
+    where
+        F: FnOnce() -> Result<T, E>,
+        E: PooledError,
+    {
+        let result = catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|_| Err(E::panicked()));
+
+        self.pool.operation_count.fetch_add(1, Ordering::Relaxed);
+        if let Some(wrapper) = &self.wrapper {
+            wrapper.operations.set(wrapper.operations.get() + 1);
+            match &result {
+                Ok(_) => wrapper.consecutive_failures.set(0),
+                Err(error) => {
+                    self.pool.failure_count.fetch_add(1, Ordering::Relaxed);
+                    wrapper
+                        .consecutive_failures
+                        .set(wrapper.consecutive_failures.get() + 1);
+                    if error.class() == ErrorClass::Poisoning {
+                        wrapper.poisoned.set(true);
+                    }
+                }
+            }
+        }
+        result
+    }
+
     /// Execute an operation with a fresh MuPDF document
     ///
     /// Opens the document, executes the operation, and ensures cleanup.
@@ -142,8 +347,10 @@ impl<'a> PooledContext<'a> {
     where
         F: FnOnce(&mupdf::Document) -> Result<T, DocumentError>,
     {
-        let doc = mupdf::Document::from_bytes(data, mime_type)?;
-        f(&doc)
+        self.run(|| {
+            let doc = mupdf::Document::from_bytes(data, mime_type)?;
+            f(&doc)
+        })
     }
 
     /// Execute an operation with a fresh MuPDF document from path
@@ -155,8 +362,10 @@ impl<'a> PooledContext<'a> {
     where
         F: FnOnce(&mupdf::Document) -> Result<T, DocumentError>,
     {
-        let doc = mupdf::Document::open(path)?;
-        f(&doc)
+        self.run(|| {
+            let doc = mupdf::Document::open(path)?;
+            f(&doc)
+        })
     }
 }
 
@@ -169,7 +378,7 @@ impl Drop for PooledContext<'_> {
 }
 
 /// Pool statistics
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct PoolStats {
     /// Total contexts ever created
     pub created: usize,
@@ -179,6 +388,14 @@ pub struct PoolStats {
     pub available: usize,
     /// Maximum pool size
     pub max_size: usize,
+    /// Idle contexts kept ready ahead of demand
+    pub warm_standby: usize,
+    /// Contexts dropped by health checks
+    pub recycled: usize,
+    /// Operations run across all contexts
+    pub operations: usize,
+    /// Failed operations across all contexts
+    pub failures: usize,
 }
 
 impl PoolStats {
@@ -192,6 +409,14 @@ impl PoolStats {
         let reused = self.created.saturating_sub(self.max_size);
         reused as f64 / self.created as f64
     }
+
+    /// Fraction of operations that failed (0.0 to 1.0)
+    pub fn failure_rate(&self) -> f64 {
+        if self.operations == 0 {
+            return 0.0;
+        }
+        self.failures as f64 / self.operations as f64
+    }
 }
 
 /// Shared context pool for the application
@@ -202,13 +427,40 @@ pub fn create_shared_pool(max_size: usize) -> SharedContextPool {
     Arc::new(ContextPool::new(max_size))
 }
 
+/// The process-wide pool that MuPDF operations run through
+pub fn shared_pool() -> &'static SharedContextPool {
+    SHARED_POOL.get_or_init(|| create_shared_pool(PoolConfig::default().max_size))
+}
+
+/// Run [`ContextPool::spawn_health_checks`] on the [`shared_pool`]
+pub fn spawn_pool_health_checks(period: Duration) -> tokio::task::JoinHandle<()> {
+    Arc::clone(shared_pool()).spawn_health_checks(period)
+}
+
+/// Run an operation on a context from the [`shared_pool`]
+pub fn run_pooled<F, T, E>(f: F) -> Result<T, E>
+where
+    F: FnOnce() -> Result<T, E>,
+    E: PooledError,
+{
+    shared_pool().acquire().run(f)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn pool(max_size: usize) -> ContextPool {
+        ContextPool::with_config(PoolConfig {
+            max_size,
+            warm_standby: 0,
+            ..PoolConfig::default()
+        })
+    }
+
     #[test]
     fn test_pool_acquire_release() {
-        let pool = ContextPool::new(2);
+        let pool = pool(2);
 
         // Acquire first context
         {
@@ -234,7 +486,7 @@ mod tests {
 
     #[test]
     fn test_pool_max_size() {
-        let pool = ContextPool::new(2);
+        let pool = pool(2);
 
         // Acquire 3 contexts (exceeds pool size)
         let ctx1 = pool.acquire();
@@ -252,4 +504,91 @@ mod tests {
         // Only max_size should be in pool
         assert_eq!(pool.stats().available, 2);
     }
+
+    #[test]
+    fn test_warm_standby() {
+        let pool = ContextPool::with_config(PoolConfig {
+            max_size: 4,
+            warm_standby: 2,
+            ..PoolConfig::default()
+        });
+        assert_eq!(pool.stats().available, 2);
+        assert_eq!(pool.stats().created, 2);
+
+        // Served from standby, no new creation
+        drop(pool.acquire());
+        assert_eq!(pool.stats().created, 2);
+    }
+
+    #[test]
+    fn test_recycle_after_max_operations() {
+        let pool = ContextPool::with_config(PoolConfig {
+            max_size: 2,
+            warm_standby: 1,
+            max_operations: 2,
+            ..PoolConfig::default()
+        });
+
+        {
+            let ctx = pool.acquire();
+            ctx.run(|| Ok::<_, DocumentError>(())).unwrap();
+            ctx.run(|| Ok::<_, DocumentError>(())).unwrap();
+        }
+
+        let stats = pool.stats();
+        assert_eq!(stats.recycled, 1);
+        assert_eq!(stats.operations, 2);
+        // Standby was refilled with a fresh context
+        assert_eq!(stats.available, 1);
+        assert_eq!(stats.created, 2);
+    }
+
+    #[test]
+    fn test_recycle_on_error_class() {
+        let pool = pool(2);
+
+        // Request-level errors keep the context
+        {
+            let ctx = pool.acquire();
+            let _ = ctx.run(|| Err::<(), _>(DocumentError::ItemNotFound(9)));
+        }
+        assert_eq!(pool.stats().recycled, 0);
+        assert_eq!(pool.stats().available, 1);
+
+        // MuPDF exceptions and panics poison it
+        {
+            let ctx = pool.acquire();
+            let _ = ctx.run(|| Err::<(), _>(DocumentError::ContextError("fz_throw".into())));
+        }
+        {
+            let ctx = pool.acquire();
+            let result: Result<(), DocumentError> = ctx.run(|| panic!("bad document"));
+            assert!(matches!(result, Err(DocumentError::ContextError(_))));
+        }
+
+        let stats = pool.stats();
+        assert_eq!(stats.recycled, 2);
+        assert_eq!(stats.failures, 3);
+        assert_eq!(stats.available, 0);
+    }
+
+    #[test]
+    fn test_health_check_restores_standby() {
+        let pool = ContextPool::with_config(PoolConfig {
+            max_size: 4,
+            warm_standby: 3,
+            ..PoolConfig::default()
+        });
+        pool.available.lock().clear();
+
+        assert_eq!(pool.health_check(), 0);
+        assert_eq!(pool.stats().available, 3);
+    }
+
+    #[test]
+    fn test_run_pooled_counts_on_shared_pool() {
+        let before = shared_pool().stats().operations;
+        run_pooled(|| Ok::<_, DocumentError>(())).unwrap();
+        assert!(shared_pool().stats().operations > before);
+    }
 }
@@ -0,0 +1,161 @@
+//! MuPDF warning capture
+//!
+//! MuPDF reports recoverable problems (a broken embedded font, a glyph no
+//! font has, a damaged xref) as warnings and carries on, which by default
+//! only prints them to stderr. A page then renders with boxes and nobody
+//! downstream learns why.
+//!
+//! [`capture_warnings`] installs a warning callback on the calling thread's MuPDF
+//! context and collects whatever MuPDF reports while the closure runs.
+//! Captures nest: an inner capture's warnings also reach the enclosing one,
+//! so [`SafeDocument`](super::SafeDocument) can log each operation's
+//! warnings while a renderer collects them for its response. Warnings
+//! outside any capture are logged with `tracing`.
+
+use std::cell::{Cell, RefCell};
+use std::ffi::{c_char, c_void, CStr};
+
+use mupdf_sys::fz_context;
+
+use crate::document::DocumentWarning;
+
+/// Distinct warnings kept per capture; later ones are only counted
+const MAX_WARNINGS: usize = 64;
+
+thread_local! {
+    /// Open captures on this thread, innermost last
+    static CAPTURES: RefCell<Vec<Vec<DocumentWarning>>> = const { RefCell::new(Vec::new()) };
+    static INSTALLED: Cell<bool> = const { Cell::new(false) };
+    /// The last warning MuPDF reported, which a repeat message refers to
+    static LAST_WARNING: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+/// Run `f`, returning what it returned and the warnings MuPDF reported
+pub fn capture_warnings<R>(f: impl FnOnce() -> R) -> (R, Vec<DocumentWarning>) {
+    install();
+    CAPTURES.with(|captures| captures.borrow_mut().push(Vec::new()));
+
+    let result = f();
+
+    // Repeated warnings are only counted once MuPDF flushes them
+    // SAFETY: the context belongs to this thread
+    unsafe { mupdf_sys::fz_flush_warnings(thread_context()) };
+    let warnings = CAPTURES.with(|captures| {
+        let mut captures = captures.borrow_mut();
+        let warnings = captures.pop().unwrap_or_default();
+        if let Some(outer) = captures.last_mut() {
+            merge_warnings(outer, &warnings);
+        }
+        warnings
+    });
+    (result, warnings)
+}
+
+/// Install the warning callback on this thread's context, once
+fn install() {
+    if INSTALLED.with(|installed| installed.replace(true)) {
+        return;
+    }
+    // SAFETY: the context belongs to this thread, and the callback only
+    // touches thread-locals
+    unsafe {
+        mupdf_sys::fz_set_warning_callback(
+            thread_context(),
+            Some(on_warning),
+            std::ptr::null_mut(),
+        );
+    }
+}
+
+/// The MuPDF context the `mupdf` crate uses on this thread
+///
+/// `mupdf` keeps one context per thread but exposes neither the pointer
+/// nor a warning hook, and mupdf-sys cannot reach a context it did not
+/// create; `mupdf::Context` is just that pointer. Hooks that MuPDF calls
+/// with a context (the font loaders) use the one they are given, and in
+/// debug builds check it against this one.
+pub(super) fn thread_context() -> *mut fz_context {
+    const _: () =
+        assert!(std::mem::size_of::<mupdf::Context>() == std::mem::size_of::<*mut fz_context>());
+    // SAFETY: `Context` wraps only the thread's `*mut fz_context` (checked
+    // in size above) and does not free it on drop
+    unsafe { std::mem::transmute::<mupdf::Context, *mut fz_context>(mupdf::Context::get()) }
+}
+
+unsafe extern "C" fn on_warning(_user: *mut c_void, message: *const c_char) {
+    if message.is_null() {
+        return;
+    }
+    // SAFETY: MuPDF passes a NUL-terminated message valid for the call
+    let message = unsafe { CStr::from_ptr(message) }.to_string_lossy();
+
+    // MuPDF collapses consecutive duplicates into a follow-up message that
+    // counts every occurrence, the one already reported included
+    let (warning, count) = match repeat_count(&message) {
+        Some(times) => (
+            LAST_WARNING.with(|last| last.borrow().clone()),
+            times.saturating_sub(1),
+        ),
+        None => {
+            LAST_WARNING.with(|last| *last.borrow_mut() = message.to_string());
+            (message.to_string(), 1)
+        }
+    };
+
+    let captured = CAPTURES.with(|captures| {
+        let mut captures = captures.borrow_mut();
+        let warnings = captures.last_mut()?;
+        add(warnings, &warning, count);
+        Some(())
+    });
+    if captured.is_none() {
+        tracing::warn!("MuPDF: {}", message);
+    }
+}
+
+/// Count `warnings` into `into`, as [`capture_warnings`] collects them
+pub(super) fn merge_warnings(into: &mut Vec<DocumentWarning>, warnings: &[DocumentWarning]) {
+    for warning in warnings {
+        add(into, &warning.message, warning.count);
+    }
+}
+
+/// Count `message` in `warnings`, adding it if new and there is room
+fn add(warnings: &mut Vec<DocumentWarning>, message: &str, count: usize) {
+    if let Some(warning) = warnings.iter_mut().find(|w| w.message == message) {
+        warning.count += count;
+    } else if warnings.len() < MAX_WARNINGS {
+        warnings.push(DocumentWarning {
+            message: message.to_string(),
+            count,
+        });
+    }
+}
+
+/// Total occurrences in MuPDF's "... repeated N times..." message
+fn repeat_count(message: &str) -> Option<usize> {
+    message
+        .strip_prefix("... repeated ")?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_and_repeat_count() {
+        let mut warnings = Vec::new();
+        add(&mut warnings, "unknown font", 1);
+        add(&mut warnings, "missing glyph", 1);
+        add(&mut warnings, "unknown font", 3);
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0].count, 4);
+
+        assert_eq!(repeat_count("... repeated 12 times..."), Some(12));
+        assert_eq!(repeat_count("cannot load font"), None);
+    }
+}
@@ -0,0 +1,503 @@
+//! Font substitution
+//!
+//! MuPDF asks its context for a font whenever a document names one it does
+//! not embed, or has text in a script none of its fonts cover. By default
+//! the answer is always "none", and MuPDF falls back to its built-in fonts;
+//! a build without the CJK fonts then renders Chinese or Japanese as boxes.
+//!
+//! [`configure_fonts`] sets the font files to answer with, from
+//! [`FontConfig`]: a per-script or per-name substitution first, then a file
+//! in the fallback directories named like the requested font. Every request
+//! is recorded, answered or not, and [`capture_substitutions`] collects the
+//! ones made while a closure runs, the way warnings are captured.
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use mupdf_sys::{fz_context, fz_font, mupdf_error_t};
+
+use super::diagnostics::thread_context;
+use crate::config::FontConfig;
+use crate::document::FontSubstitution;
+
+/// Font file extensions picked up from the fallback directories
+const FONT_EXTENSIONS: &[&str] = &["ttf", "otf", "ttc", "otc"];
+
+/// Distinct substitutions kept per capture
+const MAX_SUBSTITUTIONS: usize = 64;
+
+static RESOLVER: OnceLock<FontResolver> = OnceLock::new();
+
+thread_local! {
+    /// Open captures on this thread, innermost last
+    static CAPTURES: RefCell<Vec<Vec<FontSubstitution>>> = const { RefCell::new(Vec::new()) };
+    static INSTALLED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Set the fonts MuPDF falls back to; only the first call takes effect
+pub fn configure_fonts(config: &FontConfig) {
+    let resolver = FontResolver::new(config);
+    tracing::info!(
+        "Font fallback: {} font file(s), {} substitution(s)",
+        resolver.files.len(),
+        resolver.substitutions.len()
+    );
+    if RESOLVER.set(resolver).is_err() {
+        tracing::warn!("Font fallback already configured; ignoring new configuration");
+    }
+}
+
+/// Run `f`, returning what it returned and the fonts MuPDF substituted
+pub fn capture_substitutions<R>(f: impl FnOnce() -> R) -> (R, Vec<FontSubstitution>) {
+    install();
+    CAPTURES.with(|captures| captures.borrow_mut().push(Vec::new()));
+
+    let result = f();
+
+    let substitutions = CAPTURES.with(|captures| {
+        let mut captures = captures.borrow_mut();
+        let substitutions = captures.pop().unwrap_or_default();
+        if let Some(outer) = captures.last_mut() {
+            merge_substitutions(outer, &substitutions);
+        }
+        substitutions
+    });
+    (result, substitutions)
+}
+
+/// Add `substitutions` to `into`, skipping ones already there
+pub(super) fn merge_substitutions(
+    into: &mut Vec<FontSubstitution>,
+    substitutions: &[FontSubstitution],
+) {
+    for substitution in substitutions {
+        if !into.contains(substitution) && into.len() < MAX_SUBSTITUTIONS {
+            into.push(substitution.clone());
+        }
+    }
+}
+
+/// Install the font loaders on this thread's context, once
+fn install() {
+    if INSTALLED.with(|installed| installed.replace(true)) {
+        return;
+    }
+    // SAFETY: the context belongs to this thread, and the loaders only
+    // touch thread-locals and the configured resolver
+    unsafe {
+        mupdf_sys::fz_install_load_system_font_funcs(
+            thread_context(),
+            Some(load_font),
+            Some(load_cjk_font),
+            Some(load_fallback_font),
+        );
+    }
+}
+
+/// Font files by normalized name, and the configured substitutions
+#[derive(Debug, Default)]
+struct FontResolver {
+    /// File stem (normalized) to path, from the fallback directories
+    files: HashMap<String, PathBuf>,
+    /// Script or font name (normalized) to font file
+    substitutions: HashMap<String, PathBuf>,
+}
+
+impl FontResolver {
+    fn new(config: &FontConfig) -> Self {
+        let mut files = HashMap::new();
+        for dir in &config.fallback_dirs {
+            let entries = match std::fs::read_dir(dir) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    tracing::warn!("Cannot read font directory {}: {}", dir.display(), e);
+                    continue;
+                }
+            };
+            for path in entries.flatten().map(|entry| entry.path()) {
+                let is_font = path
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| {
+                        FONT_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str())
+                    });
+                if let (true, Some(stem)) = (is_font, path.file_stem().and_then(|s| s.to_str())) {
+                    // Earlier directories take precedence
+                    files.entry(normalize(stem)).or_insert(path);
+                }
+            }
+        }
+
+        let mut resolver = FontResolver {
+            files,
+            substitutions: HashMap::new(),
+        };
+        for (key, file) in &config.substitutions {
+            match resolver.locate(file, &config.fallback_dirs) {
+                Some(path) => {
+                    resolver.substitutions.insert(normalize(key), path);
+                }
+                None => tracing::warn!("Font substitution for '{}': {} not found", key, file),
+            }
+        }
+        resolver
+    }
+
+    /// Path of a configured font file: absolute, in a fallback directory,
+    /// or named by its stem
+    fn locate(&self, file: &str, dirs: &[PathBuf]) -> Option<PathBuf> {
+        let path = Path::new(file);
+        if path.is_absolute() {
+            return path.is_file().then(|| path.to_path_buf());
+        }
+        dirs.iter()
+            .map(|dir| dir.join(path))
+            .find(|candidate| candidate.is_file())
+            .or_else(|| {
+                let stem = path.file_stem()?.to_str()?;
+                self.files.get(&normalize(stem)).cloned()
+            })
+    }
+
+    /// File for a font requested by name
+    fn by_name(&self, name: &str, bold: bool, italic: bool) -> Option<&Path> {
+        let name = normalize(strip_subset_prefix(name));
+        if let Some(path) = self.substitutions.get(&name) {
+            return Some(path);
+        }
+
+        // Bold "Foo" is looked for as "FooBold", then "Foo"
+        let mut candidates = Vec::new();
+        match (bold, italic) {
+            (true, true) => candidates.extend([
+                format!("{}bolditalic", name),
+                format!("{}boldoblique", name),
+            ]),
+            (true, false) => candidates.push(format!("{}bold", name)),
+            (false, true) => {
+                candidates.extend([format!("{}italic", name), format!("{}oblique", name)])
+            }
+            (false, false) => candidates.push(format!("{}regular", name)),
+        }
+        candidates.push(name);
+        candidates
+            .iter()
+            .find_map(|candidate| self.files.get(candidate))
+            .map(PathBuf::as_path)
+    }
+
+    /// File for text in a script, trying each key in order
+    fn by_script(&self, keys: &[&str]) -> Option<&Path> {
+        keys.iter()
+            .find_map(|key| self.substitutions.get(*key))
+            .map(PathBuf::as_path)
+    }
+}
+
+/// Lowercase alphanumerics only, so "Noto Sans-CJK_SC" matches
+/// "notosanscjksc"
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Drop the `ABCDEF+` tag PDF producers put before subset font names
+pub fn strip_subset_prefix(name: &str) -> &str {
+    match name.split_once('+') {
+        Some((tag, rest)) if tag.len() == 6 && tag.bytes().all(|b| b.is_ascii_uppercase()) => rest,
+        _ => name,
+    }
+}
+
+/// Substitution keys for a CJK font by Adobe character collection
+/// (`FZ_ADOBE_CNS`, `FZ_ADOBE_GB`, `FZ_ADOBE_JAPAN`, `FZ_ADOBE_KOREA`)
+fn cjk_keys(ordering: c_int) -> &'static [&'static str] {
+    match ordering {
+        0 => &["hant", "han", "cjk"],
+        1 => &["hans", "han", "cjk"],
+        2 => &["japanese", "han", "cjk"],
+        3 => &["korean", "hangul", "cjk"],
+        _ => &["cjk"],
+    }
+}
+
+/// Names of the UCDN script codes MuPDF passes for fallback fonts, in the
+/// order of `UCDN_SCRIPT_*` in ucdn.h
+const SCRIPTS: &[&str] = &[
+    "common",
+    "latin",
+    "greek",
+    "cyrillic",
+    "armenian",
+    "hebrew",
+    "arabic",
+    "syriac",
+    "thaana",
+    "devanagari",
+    "bengali",
+    "gurmukhi",
+    "gujarati",
+    "oriya",
+    "tamil",
+    "telugu",
+    "kannada",
+    "malayalam",
+    "sinhala",
+    "thai",
+    "lao",
+    "tibetan",
+    "myanmar",
+    "georgian",
+    "hangul",
+    "ethiopic",
+    "cherokee",
+    "canadianaboriginal",
+    "ogham",
+    "runic",
+    "khmer",
+    "mongolian",
+    "hiragana",
+    "katakana",
+    "bopomofo",
+    "han",
+    "yi",
+];
+
+/// Name of a UCDN script code, if known
+fn script_name(script: c_int) -> Option<&'static str> {
+    usize::try_from(script)
+        .ok()
+        .and_then(|index| SCRIPTS.get(index))
+        .copied()
+}
+
+/// Substitution keys for a script, most specific first
+fn script_keys(script: &'static str) -> Vec<&'static str> {
+    let mut keys = vec![script];
+    match script {
+        "han" => keys.push("cjk"),
+        "hiragana" | "katakana" => keys.extend(["japanese", "cjk"]),
+        "hangul" => keys.extend(["korean", "cjk"]),
+        "bopomofo" => keys.extend(["hant", "cjk"]),
+        _ => {}
+    }
+    keys
+}
+
+/// Record a request, then load the file chosen for it in `ctx`
+fn substitute(
+    ctx: *mut fz_context,
+    requested: Option<String>,
+    script: Option<&str>,
+    file: Option<&Path>,
+) -> *mut fz_font {
+    // The loaders were installed through `thread_context`, so this is where
+    // its reading of `mupdf::Context` can be checked
+    debug_assert_eq!(ctx, thread_context());
+    record(FontSubstitution {
+        requested,
+        script: script.map(str::to_string),
+        substitute: file.map(|path| path.display().to_string()),
+    });
+    file.map_or(std::ptr::null_mut(), |path| load_file(ctx, path))
+}
+
+fn record(substitution: FontSubstitution) {
+    let captured = CAPTURES.with(|captures| {
+        let mut captures = captures.borrow_mut();
+        let substitutions = captures.last_mut()?;
+        merge_substitutions(substitutions, std::slice::from_ref(&substitution));
+        Some(())
+    });
+    if captured.is_none() {
+        tracing::debug!("MuPDF font substitution: {:?}", substitution);
+    }
+}
+
+/// Load a font file in `ctx` as a new MuPDF font reference, or null on
+/// failure; MuPDF owns the returned reference
+fn load_file(ctx: *mut fz_context, path: &Path) -> *mut fz_font {
+    let data = match std::fs::read(path) {
+        Ok(data) => data,
+        Err(e) => {
+            tracing::warn!("Cannot read font {}: {}", path.display(), e);
+            return std::ptr::null_mut();
+        }
+    };
+    let Ok(len) = c_int::try_from(data.len()) else {
+        tracing::warn!("Cannot load font {}: file too large", path.display());
+        return std::ptr::null_mut();
+    };
+    let name = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(|stem| CString::new(stem).ok())
+        .unwrap_or_default();
+
+    let mut error: *mut mupdf_error_t = std::ptr::null_mut();
+    // SAFETY: `ctx` is the context MuPDF called the loader with. The
+    // wrapper copies `data` into a buffer for `fz_new_font_from_buffer` and
+    // catches MuPDF exceptions into `error`, which would otherwise longjmp
+    // through this frame.
+    let font = unsafe {
+        mupdf_sys::mupdf_new_font_from_memory(ctx, name.as_ptr(), 0, data.as_ptr(), len, &mut error)
+    };
+    if !error.is_null() {
+        // SAFETY: the wrapper set `error` to an error it allocated, with a
+        // NUL-terminated message or null
+        let message = unsafe {
+            let message = c_name((*error).message);
+            mupdf_sys::mupdf_drop_error(error);
+            message
+        };
+        tracing::warn!(
+            "Cannot load font {}: {}",
+            path.display(),
+            message.unwrap_or_default()
+        );
+        return std::ptr::null_mut();
+    }
+    font
+}
+
+/// `name` as a string, if MuPDF passed one
+///
+/// # Safety
+///
+/// `name` must be null or NUL-terminated and valid for the call.
+unsafe fn c_name(name: *const c_char) -> Option<String> {
+    if name.is_null() {
+        return None;
+    }
+    // SAFETY: guaranteed by the caller
+    Some(
+        unsafe { CStr::from_ptr(name) }
+            .to_string_lossy()
+            .into_owned(),
+    )
+}
+
+unsafe extern "C" fn load_font(
+    ctx: *mut fz_context,
+    name: *const c_char,
+    bold: c_int,
+    italic: c_int,
+    _needs_exact_metrics: c_int,
+) -> *mut fz_font {
+    // SAFETY: MuPDF passes a NUL-terminated name valid for the call
+    let Some(name) = (unsafe { c_name(name) }) else {
+        return std::ptr::null_mut();
+    };
+    let file = RESOLVER
+        .get()
+        .and_then(|resolver| resolver.by_name(&name, bold != 0, italic != 0));
+    substitute(
+        ctx,
+        Some(strip_subset_prefix(&name).to_string()),
+        None,
+        file,
+    )
+}
+
+unsafe extern "C" fn load_cjk_font(
+    ctx: *mut fz_context,
+    name: *const c_char,
+    ordering: c_int,
+    _serif: c_int,
+) -> *mut fz_font {
+    // SAFETY: MuPDF passes a NUL-terminated name valid for the call
+    let name = unsafe { c_name(name) };
+    let keys = cjk_keys(ordering);
+    let file = RESOLVER.get().and_then(|resolver| {
+        let by_name = name.as_deref().and_then(|name| {
+            resolver
+                .substitutions
+                .get(&normalize(strip_subset_prefix(name)))
+        });
+        by_name
+            .map(PathBuf::as_path)
+            .or_else(|| resolver.by_script(keys))
+    });
+    let requested = name.map(|name| strip_subset_prefix(&name).to_string());
+    substitute(ctx, requested, keys.first().copied(), file)
+}
+
+unsafe extern "C" fn load_fallback_font(
+    ctx: *mut fz_context,
+    script: c_int,
+    _language: c_int,
+    _serif: c_int,
+    _bold: c_int,
+    _italic: c_int,
+) -> *mut fz_font {
+    let Some(script) = script_name(script) else {
+        return std::ptr::null_mut();
+    };
+    let file = RESOLVER
+        .get()
+        .and_then(|resolver| resolver.by_script(&script_keys(script)));
+    substitute(ctx, None, Some(script), file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_by_name_and_script() {
+        let path = |name: &str| PathBuf::from(format!("/fonts/{}", name));
+        let resolver = FontResolver {
+            files: [
+                ("liberationserif", "LiberationSerif.ttf"),
+                ("liberationserifbold", "LiberationSerif-Bold.ttf"),
+            ]
+            .into_iter()
+            .map(|(stem, file)| (stem.to_string(), path(file)))
+            .collect(),
+            substitutions: [("cjk", "NotoSansCJK.ttc"), ("timesnewroman", "Tinos.ttf")]
+                .into_iter()
+                .map(|(key, file)| (key.to_string(), path(file)))
+                .collect(),
+        };
+
+        assert_eq!(
+            resolver.by_name("ABCDEF+Times New Roman", false, false),
+            Some(path("Tinos.ttf").as_path())
+        );
+        assert_eq!(
+            resolver.by_name("Liberation-Serif", true, false),
+            Some(path("LiberationSerif-Bold.ttf").as_path())
+        );
+        // No italic file: the regular one
+        assert_eq!(
+            resolver.by_name("LiberationSerif", false, true),
+            Some(path("LiberationSerif.ttf").as_path())
+        );
+        assert_eq!(resolver.by_name("Garamond", false, false), None);
+
+        assert_eq!(
+            resolver.by_script(&script_keys("hiragana")),
+            Some(path("NotoSansCJK.ttc").as_path())
+        );
+        assert_eq!(
+            resolver.by_script(cjk_keys(1)),
+            Some(path("NotoSansCJK.ttc").as_path())
+        );
+        assert_eq!(resolver.by_script(&script_keys("arabic")), None);
+    }
+
+    #[test]
+    fn test_script_names() {
+        assert_eq!(script_name(6), Some("arabic"));
+        assert_eq!(script_name(24), Some("hangul"));
+        assert_eq!(script_name(35), Some("han"));
+        assert_eq!(script_name(-1), None);
+        assert_eq!(strip_subset_prefix("ABCDEF+Minion"), "Minion");
+        assert_eq!(strip_subset_prefix("C++Sans"), "C++Sans");
+    }
+}
@@ -8,17 +8,26 @@
 //!
 //! MuPDF's `fz_context` is **NOT thread-safe**. This module addresses this via:
 //!
-//! 1. **ContextPool**: Reuses contexts to avoid creation overhead
+//! 1. **ContextPool**: Reuses contexts to avoid creation overhead, recycling
+//!    them after a fixed number of operations or a poisoning error. Every
+//!    [`SafeDocument`] operation and every PDF cache operation runs through
+//!    the process-wide [`shared_pool`]; the server runs its health checks
+//!    and reports its stats from `GET /api/v1/admin/cache`
 //! 2. **SafeDocument**: Opens fresh document per operation for thread safety
 //! 3. **Operation Serialization**: Mutex guards for document-level operations
 //!
+//! MuPDF warnings (broken fonts, missing glyphs) are captured per operation
+//! on the calling thread; see [`capture_warnings`]. Fonts a document lacks
+//! are substituted from the configured fallback fonts, and recorded the
+//! same way; see [`configure_fonts`].
+//!
 //! # Usage
 //!
 //! ```rust,ignore
-//! use amnesia_server::mupdf::{SafeDocument, create_shared_pool};
+//! use amnesia_server::mupdf::{SafeDocument, spawn_pool_health_checks};
 //!
-//! // Create shared pool
-//! let pool = create_shared_pool(8);
+//! // Recycle unhealthy contexts in the background
+//! spawn_pool_health_checks(Duration::from_secs(60));
 //!
 //! // Load document
 //! let doc = SafeDocument::from_bytes(pdf_bytes, "doc-123".into(), "application/pdf".into())?;
@@ -35,9 +44,15 @@
 //! ```
 
 mod context;
+mod diagnostics;
+mod fonts;
 mod safe;
 mod stext;
 
-pub use context::{create_shared_pool, ContextPool, PoolStats, PooledContext, SharedContextPool};
+pub use context::{
+    run_pooled, shared_pool, spawn_pool_health_checks, ErrorClass, PoolStats, PooledError,
+};
+pub use diagnostics::capture_warnings;
+pub use fonts::{capture_substitutions, configure_fonts, strip_subset_prefix};
 pub use safe::{DocumentSource, SafeDocument};
 pub use stext::{extract_plain_text, extract_structured_text, search_text, StextOptions};
@@ -13,14 +13,25 @@
 //!
 //! This approach avoids the need to hold long-lived Document references
 //! and ensures that each operation gets a clean document state.
+//!
+//! Warnings MuPDF reports during any operation are kept per document (see
+//! [`SafeDocument::warnings`]) and still reach an enclosing
+//! [`capture_warnings`]; so are the fonts it substituted (see
+//! [`SafeDocument::substitutions`]).
 
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+use mupdf::pdf::PdfDocument;
 use mupdf::Document;
 use parking_lot::Mutex;
 
-use crate::document::{DocumentError, DocumentFormat, DocumentResult};
+use super::diagnostics::merge_warnings;
+use super::fonts::merge_substitutions;
+use super::{capture_substitutions, capture_warnings, run_pooled};
+use crate::document::{
+    DocumentError, DocumentFormat, DocumentResult, DocumentWarning, FontSubstitution,
+};
 
 /// Source data for a document
 #[derive(Clone)]
@@ -56,6 +67,10 @@ pub struct SafeDocument {
     format: DocumentFormat,
     /// Cached page/item count
     item_count: usize,
+    /// Warnings from every operation so far
+    warnings: Mutex<Vec<DocumentWarning>>,
+    /// Fonts substituted by every operation so far
+    substitutions: Mutex<Vec<FontSubstitution>>,
     /// Mutex for serializing access
     _lock: Mutex<()>,
 }
@@ -84,16 +99,18 @@ pub struct SafeDocument {
 //    - Each operation opens a fresh document, performs work, and drops it
 //
 // 6. No shared mutable state:
-//    - All fields except _lock are immutable after construction
-//    - _lock provides interior mutability but is explicitly designed for Send + Sync
+//    - All fields except _lock, warnings and substitutions are immutable
+//      after construction
+//    - _lock, warnings and substitutions provide interior mutability but are
+//      parking_lot mutexes, explicitly designed for Send + Sync
 //
 // Therefore, SafeDocument can be safely sent between threads (Send) and accessed
 // concurrently (Sync) because all access is serialized through the mutex.
 //
-// NOTE: Concurrency control at the application level (limiting simultaneous MuPDF
-// operations) is handled by the ContextPool at the cache/service layer, following
-// the pattern established in pdf/cache.rs where tokio::spawn_blocking is used with
-// timeouts for CPU-bound MuPDF operations.
+// NOTE: Every MuPDF operation runs on a context from the shared ContextPool
+// (see `record`), which counts operations and failures and recycles contexts
+// after a poisoning error; callers still offload the CPU-bound work with
+// tokio::spawn_blocking and timeouts, as in pdf/cache.rs.
 unsafe impl Send for SafeDocument {}
 unsafe impl Sync for SafeDocument {}
 
@@ -106,14 +123,22 @@ impl SafeDocument {
 
         // Validate document can be opened and get item count
         let mime = Self::format_to_mime(format);
-        let doc = Document::from_bytes(&data, mime)?;
-        let item_count = doc.page_count()? as usize;
+        let ((item_count, warnings), substitutions) = capture_substitutions(|| {
+            capture_warnings(|| {
+                run_pooled(|| -> DocumentResult<usize> {
+                    let doc = Document::from_bytes(&data, mime)?;
+                    Ok(doc.page_count()? as usize)
+                })
+            })
+        });
 
         Ok(Self {
             source: DocumentSource::from_bytes(data),
             id,
             format,
-            item_count,
+            item_count: item_count?,
+            warnings: Mutex::new(warnings),
+            substitutions: Mutex::new(substitutions),
             _lock: Mutex::new(()),
         })
     }
@@ -131,14 +156,22 @@ impl SafeDocument {
 
         // Validate document can be opened and get item count
         let path_str = path_buf.to_string_lossy();
-        let doc = Document::open(&*path_str)?;
-        let item_count = doc.page_count()? as usize;
+        let ((item_count, warnings), substitutions) = capture_substitutions(|| {
+            capture_warnings(|| {
+                run_pooled(|| -> DocumentResult<usize> {
+                    let doc = Document::open(&*path_str)?;
+                    Ok(doc.page_count()? as usize)
+                })
+            })
+        });
 
         Ok(Self {
             source: DocumentSource::from_path(path_buf),
             id,
             format,
-            item_count,
+            item_count: item_count?,
+            warnings: Mutex::new(warnings),
+            substitutions: Mutex::new(substitutions),
             _lock: Mutex::new(()),
         })
     }
@@ -158,6 +191,36 @@ impl SafeDocument {
         self.item_count
     }
 
+    /// Warnings MuPDF reported for this document so far, distinct messages
+    /// with how often each was reported
+    pub fn warnings(&self) -> Vec<DocumentWarning> {
+        self.warnings.lock().clone()
+    }
+
+    /// Fonts MuPDF substituted for this document so far
+    pub fn substitutions(&self) -> Vec<FontSubstitution> {
+        self.substitutions.lock().clone()
+    }
+
+    /// Run an operation on a pooled context, keeping the warnings MuPDF
+    /// reports and the fonts it substitutes during it
+    fn record<R>(&self, f: impl FnOnce() -> DocumentResult<R>) -> DocumentResult<R> {
+        let ((result, warnings), substitutions) =
+            capture_substitutions(|| capture_warnings(|| run_pooled(f)));
+        if !substitutions.is_empty() {
+            merge_substitutions(&mut self.substitutions.lock(), &substitutions);
+        }
+        if !warnings.is_empty() {
+            tracing::debug!(
+                "MuPDF reported {} warning(s) for document '{}'",
+                warnings.len(),
+                self.id
+            );
+            merge_warnings(&mut self.warnings.lock(), &warnings);
+        }
+        result
+    }
+
     /// Open a fresh document instance for an operation
     ///
     /// This is called internally by `with_doc` to ensure each operation
@@ -195,11 +258,37 @@ impl SafeDocument {
         // Serialize access
         let _guard = self._lock.lock();
 
-        // Open fresh document
-        let doc = self.open_document()?;
+        self.record(|| {
+            // Open fresh document
+            let doc = self.open_document()?;
 
-        // Execute operation
-        f(&doc)
+            // Execute operation
+            f(&doc)
+        })
+    }
+
+    /// Execute a closure with access to the PDF-specific document
+    ///
+    /// Gives access to PDF objects such as the catalog, which the generic
+    /// `Document` does not expose. Only valid for PDF documents.
+    pub fn with_pdf_doc<F, R>(&self, f: F) -> DocumentResult<R>
+    where
+        F: FnOnce(&PdfDocument) -> DocumentResult<R>,
+    {
+        if self.format != DocumentFormat::Pdf {
+            return Err(DocumentError::UnsupportedFormat(
+                "PDF objects are only available for PDF documents".to_string(),
+            ));
+        }
+
+        let _guard = self._lock.lock();
+        self.record(|| {
+            let doc = match &self.source {
+                DocumentSource::Bytes(data) => PdfDocument::from_bytes(data)?,
+                DocumentSource::Path(path) => PdfDocument::open(&*path.to_string_lossy())?,
+            };
+            f(&doc)
+        })
     }
 
     /// Execute a closure that may fail with a custom error
@@ -212,8 +301,10 @@ impl SafeDocument {
         E: Into<DocumentError>,
     {
         let _guard = self._lock.lock();
-        let doc = self.open_document()?;
-        f(&doc).map_err(Into::into)
+        self.record(|| {
+            let doc = self.open_document()?;
+            f(&doc).map_err(Into::into)
+        })
     }
 
     /// Execute a closure with mutable access to the document
@@ -236,11 +327,13 @@ impl SafeDocument {
         // Serialize access
         let _guard = self._lock.lock();
 
-        // Open fresh document
-        let mut doc = self.open_document()?;
+        self.record(|| {
+            // Open fresh document
+            let mut doc = self.open_document()?;
 
-        // Execute operation
-        f(&mut doc)
+            // Execute operation
+            f(&mut doc)
+        })
     }
 
     /// Get MIME type for format
@@ -6,6 +6,8 @@
 //!
 //! - **Text Extraction**: Extract text from image regions in PDFs
 //! - **Text Layer Injection**: Permanently embed searchable text into scanned PDFs
+//! - **Retries**: Requests that failed while a provider was down are queued
+//!   and run again by the `ocr_retry` scheduler task
 //!
 //! ## Extraction Backends
 //!
@@ -19,7 +21,7 @@
 //! ## Usage
 //!
 //! ```rust,ignore
-//! use los_libros_server::ocr::{OcrService, OcrServiceConfig, OcrRect, OcrInjector};
+//! use amnesia_server::ocr::{OcrService, OcrServiceConfig, OcrRect, OcrInjector};
 //!
 //! // Text extraction
 //! let config = OcrServiceConfig::default();
@@ -40,11 +42,16 @@
 
 mod injector;
 mod provider;
+mod retry;
 mod service;
 mod types;
 
 pub use injector::{OcrInjectionResult, OcrInjector, OcrInjectorConfig};
 pub use provider::{OcrProviderTrait, OllamaProvider};
+pub use retry::{
+    failed_notification, finished_notification, FailedOcr, OcrRetryQueue, RetrySummary,
+    MAX_OCR_ATTEMPTS,
+};
 pub use service::{OcrService, OcrServiceConfig};
 pub use types::{OcrError, OcrProvider, OcrRect, OcrRequest, OcrResult, OcrWord, PixelRect};
 
@@ -0,0 +1,245 @@
+//! Retries of failed OCR requests
+//!
+//! OCR providers fail for reasons that go away on their own: Ollama is not
+//! running yet, or a vision API times out. A region whose OCR failed that
+//! way is queued, and the `ocr_retry` scheduler task runs the queue again,
+//! reporting each outcome to the notification center. Requests that failed
+//! because of what they asked for (a region outside the page, a PDF that is
+//! not loaded) are not retried.
+
+use std::fmt;
+
+use parking_lot::Mutex;
+
+use crate::db::{NewNotification, NotificationKind, NotificationRepository};
+use crate::pdf::PdfCache;
+
+use super::{OcrError, OcrProvider, OcrRect, OcrResult, OcrService};
+
+/// Attempts a request gets in all, the one that first failed included
+pub const MAX_OCR_ATTEMPTS: u32 = 3;
+
+/// A region whose OCR failed and will be tried again
+#[derive(Debug, Clone)]
+pub struct FailedOcr {
+    pub pdf_id: String,
+    /// Page number (1-indexed)
+    pub page: usize,
+    pub rect: OcrRect,
+    pub provider: Option<OcrProvider>,
+    pub language: Option<String>,
+    /// Attempts made so far
+    pub attempts: u32,
+}
+
+/// What a retry run did
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetrySummary {
+    /// Requests that succeeded this time
+    pub recovered: usize,
+    /// Requests that failed again and stay queued
+    pub requeued: usize,
+    /// Requests that failed for good
+    pub abandoned: usize,
+}
+
+impl fmt::Display for RetrySummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Recovered {} OCR requests ({} requeued, {} abandoned)",
+            self.recovered, self.requeued, self.abandoned
+        )
+    }
+}
+
+/// Failed OCR requests waiting for the next retry run
+#[derive(Debug, Default)]
+pub struct OcrRetryQueue {
+    pending: Mutex<Vec<FailedOcr>>,
+}
+
+impl OcrRetryQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a failed request if `error` may go away on its own; returns
+    /// whether it was queued
+    pub fn push(&self, failed: FailedOcr, error: &OcrError) -> bool {
+        if !error.is_transient() || failed.attempts >= MAX_OCR_ATTEMPTS {
+            return false;
+        }
+        self.pending.lock().push(failed);
+        true
+    }
+
+    /// Number of queued requests
+    pub fn len(&self) -> usize {
+        self.pending.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Run every queued request again
+    ///
+    /// Requests that fail transiently again are queued for the next run,
+    /// until they have had [`MAX_OCR_ATTEMPTS`]. Successes and final
+    /// failures are reported as notifications.
+    pub async fn retry(
+        &self,
+        service: &OcrService,
+        pdf_cache: &PdfCache,
+        notifications: &NotificationRepository<'_>,
+    ) -> RetrySummary {
+        // Requests failing during the run are queued for the next one
+        let queued = std::mem::take(&mut *self.pending.lock());
+        let mut summary = RetrySummary::default();
+
+        for mut failed in queued {
+            let result = service
+                .ocr_pdf_region(
+                    &failed.pdf_id,
+                    failed.page,
+                    &failed.rect,
+                    failed.provider,
+                    failed.language.as_deref(),
+                    pdf_cache,
+                )
+                .await;
+            failed.attempts += 1;
+
+            match result {
+                Ok(result) => {
+                    summary.recovered += 1;
+                    notifications
+                        .notify(finished_notification(&failed.pdf_id, failed.page, &result))
+                        .await;
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "OCR retry {} for PDF '{}' page {} failed: {}",
+                        failed.attempts,
+                        failed.pdf_id,
+                        failed.page,
+                        e
+                    );
+                    let (pdf_id, page) = (failed.pdf_id.clone(), failed.page);
+                    if self.push(failed, &e) {
+                        summary.requeued += 1;
+                    } else {
+                        summary.abandoned += 1;
+                        notifications
+                            .notify(failed_notification(&pdf_id, page, &e, false))
+                            .await;
+                    }
+                }
+            }
+        }
+        summary
+    }
+}
+
+/// Notification for a finished OCR request
+pub fn finished_notification(pdf_id: &str, page: usize, result: &OcrResult) -> NewNotification {
+    NewNotification::new(
+        NotificationKind::Ocr,
+        format!("OCR finished on page {}", page),
+    )
+    .with_body(format!(
+        "{} words recognized ({:.0}% confidence)",
+        result.text.split_whitespace().count(),
+        result.confidence
+    ))
+    .with_book(pdf_id)
+}
+
+/// Notification for a failed OCR request; `retrying` if it was queued
+pub fn failed_notification(
+    pdf_id: &str,
+    page: usize,
+    error: &OcrError,
+    retrying: bool,
+) -> NewNotification {
+    let body = if retrying {
+        format!("{}. It will be retried.", error)
+    } else {
+        error.to_string()
+    };
+    NewNotification::new(
+        NotificationKind::Ocr,
+        format!("OCR failed on page {}", page),
+    )
+    .with_body(body)
+    .with_book(pdf_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::initialize_schema;
+    use crate::ocr::OcrServiceConfig;
+    use sqlx::SqlitePool;
+
+    fn failed(pdf_id: &str, attempts: u32) -> FailedOcr {
+        FailedOcr {
+            pdf_id: pdf_id.to_string(),
+            page: 1,
+            rect: OcrRect {
+                x: 0.1,
+                y: 0.1,
+                width: 0.5,
+                height: 0.5,
+            },
+            provider: None,
+            language: None,
+            attempts,
+        }
+    }
+
+    #[test]
+    fn test_only_transient_failures_are_queued() {
+        let queue = OcrRetryQueue::new();
+        let unavailable = OcrError::ProviderNotAvailable("ollama".to_string());
+
+        assert!(queue.push(failed("a", 1), &unavailable));
+        assert!(!queue.push(
+            failed("b", 1),
+            &OcrError::InvalidRegion("outside".to_string())
+        ));
+        assert!(!queue.push(failed("c", MAX_OCR_ATTEMPTS), &unavailable));
+        assert_eq!(queue.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retry_abandons_permanent_failures() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        initialize_schema(&pool).await.unwrap();
+        let notifications = NotificationRepository::new(&pool);
+        let queue = OcrRetryQueue::new();
+        queue.push(
+            failed("gone", 1),
+            &OcrError::ApiError("timed out".to_string()),
+        );
+
+        // The PDF is no longer loaded, which retrying will not fix
+        let cache = PdfCache::with_budget(1024 * 1024, 1024 * 1024);
+        let service = OcrService::new(OcrServiceConfig::default());
+        let summary = queue.retry(&service, &cache, &notifications).await;
+
+        assert_eq!(
+            summary,
+            RetrySummary {
+                abandoned: 1,
+                ..Default::default()
+            }
+        );
+        assert!(queue.is_empty());
+        let listed = notifications.list(None, false, 10).await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].title, "OCR failed on page 1");
+        assert_eq!(listed[0].book_id.as_deref(), Some("gone"));
+    }
+}
@@ -220,7 +220,7 @@ mod tests {
             height: 0.3,
         };
 
-        let cache = crate::pdf::PdfCache::with_capacity(10);
+        let cache = crate::pdf::PdfCache::with_budget(1024 * 1024, 1024 * 1024);
         let result = service
             .ocr_pdf_region("test", 1, &invalid_rect, None, None, &cache)
             .await;
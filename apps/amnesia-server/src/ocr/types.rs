@@ -115,6 +115,16 @@ pub enum OcrError {
 }
 
 impl OcrError {
+    /// Whether the same request may succeed later: the provider was down
+    /// or failed, rather than the request being unusable
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Self::ProviderNotAvailable(_) | Self::ProcessingError(_) | Self::ApiError(_)
+        )
+    }
+
+    #[cfg(feature = "server")]
     pub fn status_code(&self) -> axum::http::StatusCode {
         use axum::http::StatusCode;
         match self {
@@ -3,6 +3,7 @@
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
+use super::EntryTemplate;
 use crate::library::{BookFormat, FormatType, LibraryBook};
 
 /// OPDS feed types
@@ -15,6 +16,7 @@ pub enum FeedType {
 /// OPDS link relations
 pub mod rel {
     pub const SELF: &str = "self";
+    pub const ALTERNATE: &str = "alternate";
     pub const START: &str = "start";
     pub const UP: &str = "up";
     pub const SUBSECTION: &str = "subsection";
@@ -35,6 +37,12 @@ pub mod mime {
     pub const OPENSEARCH: &str = "application/opensearchdescription+xml";
 }
 
+/// Category schemes for server-specific categories
+pub mod scheme {
+    pub const MATURITY: &str = "urn:los-libros:maturity";
+    pub const CONTENT_WARNING: &str = "urn:los-libros:content-warning";
+}
+
 /// An OPDS feed
 #[derive(Debug, Clone)]
 pub struct OPDSFeed {
@@ -91,10 +99,11 @@ impl OPDSFeed {
         self.entries.push(entry);
     }
 
-    /// Add entries from books
-    pub fn add_books(&mut self, books: &[LibraryBook], base_url: &str) {
+    /// Add entries from books, described with `template`
+    pub fn add_books(&mut self, books: &[LibraryBook], base_url: &str, template: &EntryTemplate) {
         for book in books {
-            self.entries.push(OPDSEntry::from_book(book, base_url));
+            self.entries
+                .push(OPDSEntry::from_book(book, base_url, template));
         }
     }
 
@@ -169,8 +178,8 @@ impl OPDSEntry {
         }
     }
 
-    /// Create an entry from a LibraryBook
-    pub fn from_book(book: &LibraryBook, base_url: &str) -> Self {
+    /// Create an entry from a LibraryBook, with content from `template`
+    pub fn from_book(book: &LibraryBook, base_url: &str, template: &EntryTemplate) -> Self {
         let mut links = Vec::new();
 
         // Add acquisition links for each format
@@ -203,7 +212,7 @@ impl OPDSEntry {
             })
             .collect();
 
-        let categories: Vec<OPDSCategory> = book
+        let mut categories: Vec<OPDSCategory> = book
             .tags
             .iter()
             .map(|tag| OPDSCategory {
@@ -212,18 +221,22 @@ impl OPDSEntry {
                 scheme: None,
             })
             .collect();
-
-        // Build content with description and series info
-        let content = book.description.as_ref().map(|desc| {
-            let mut html = desc.clone();
-            if let Some(ref series) = book.series {
-                let index = book.series_index.map_or(String::new(), |i| format!(" #{}", i));
-                html = format!("<p><em>Series: {}{}</em></p>{}", series, index, html);
-            }
-            OPDSContent {
-                content_type: "html".to_string(),
-                value: html,
-            }
+        if let Some(rating) = book.maturity {
+            categories.push(OPDSCategory {
+                term: rating.to_string(),
+                label: Some(format!("Rated {} ({}+)", rating, rating.min_age())),
+                scheme: Some(scheme::MATURITY.to_string()),
+            });
+        }
+        categories.extend(book.content_warnings.iter().map(|warning| OPDSCategory {
+            term: warning.clone(),
+            label: Some(format!("Content warning: {}", warning)),
+            scheme: Some(scheme::CONTENT_WARNING.to_string()),
+        }));
+
+        let content = template.render(book, base_url).map(|html| OPDSContent {
+            content_type: "html".to_string(),
+            value: html,
         });
 
         Self {
@@ -232,14 +245,7 @@ impl OPDSEntry {
             updated: book.updated_at,
             links,
             content,
-            summary: book.description.clone().map(|d| {
-                // Truncate for summary
-                if d.len() > 200 {
-                    format!("{}...", &d[..197])
-                } else {
-                    d
-                }
-            }),
+            summary: template.summary(book),
             authors,
             categories,
             published: book.pubdate.clone(),
@@ -1,9 +1,13 @@
 //! OPDS (Open Publication Distribution System) module
 //!
 //! Generates OPDS 1.2 Atom feeds for browsing and downloading books.
+//! Book entries describe their book in HTML from a configurable template
+//! ([`EntryTemplate`]).
 
 mod feed;
+mod summary;
 mod xml;
 
 pub use feed::*;
+pub use summary::{EntryTemplate, DEFAULT_ENTRY_TEMPLATE};
 pub use xml::*;
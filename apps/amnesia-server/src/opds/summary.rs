@@ -0,0 +1,335 @@
+//! HTML content for OPDS book entries
+//!
+//! OPDS clients show an entry's `<content>` as the book's detail page, and
+//! Calibre-Web's clients expect more there than the description: the cover,
+//! series, rating and tags. [`EntryTemplate`] renders it from a template in
+//! which each `{{placeholder}}` expands to an HTML block for the book:
+//!
+//! | Placeholder | Block |
+//! |-------------|-------|
+//! | `{{cover}}` | Cover thumbnail |
+//! | `{{title}}` | Title, as a heading |
+//! | `{{authors}}` | "By" and the authors |
+//! | `{{series}}` | Series name and position |
+//! | `{{rating}}` | Calibre rating, in stars |
+//! | `{{tags}}` | Tags |
+//! | `{{publisher}}`, `{{published}}`, `{{language}}` | The field, labelled |
+//! | `{{description}}` | Description as paragraphs, cut to length |
+//!
+//! A block for metadata the book lacks renders as nothing, so one template
+//! serves every book. Unknown placeholders are left as written. The
+//! template and lengths come from [`OpdsConfig`].
+
+use html_escape::{encode_double_quoted_attribute, encode_text};
+
+use crate::config::OpdsConfig;
+use crate::library::LibraryBook;
+
+/// Template used unless the config gives another
+pub const DEFAULT_ENTRY_TEMPLATE: &str = "{{cover}}{{series}}{{rating}}{{tags}}{{description}}";
+
+/// A parsed entry template, with the lengths to cut text to
+#[derive(Debug, Clone)]
+pub struct EntryTemplate {
+    parts: Vec<Part>,
+    description_chars: usize,
+    summary_chars: usize,
+    cover_width: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    Field(Field),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Cover,
+    Title,
+    Authors,
+    Series,
+    Rating,
+    Tags,
+    Publisher,
+    Published,
+    Language,
+    Description,
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "cover" => Self::Cover,
+            "title" => Self::Title,
+            "authors" => Self::Authors,
+            "series" => Self::Series,
+            "rating" => Self::Rating,
+            "tags" => Self::Tags,
+            "publisher" => Self::Publisher,
+            "published" => Self::Published,
+            "language" => Self::Language,
+            "description" => Self::Description,
+            _ => return None,
+        })
+    }
+}
+
+impl EntryTemplate {
+    pub fn new(config: &OpdsConfig) -> Self {
+        Self {
+            parts: parse(&config.entry_template),
+            description_chars: config.description_chars,
+            summary_chars: config.summary_chars,
+            cover_width: config.cover_width,
+        }
+    }
+
+    /// HTML content for a book's entry; `None` if every block is empty
+    pub fn render(&self, book: &LibraryBook, base_url: &str) -> Option<String> {
+        let mut html = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => html.push_str(text),
+                Part::Field(field) => html.push_str(&self.block(*field, book, base_url)),
+            }
+        }
+        (!html.trim().is_empty()).then_some(html)
+    }
+
+    /// Plain-text summary: the description on one line, cut to length
+    pub fn summary(&self, book: &LibraryBook) -> Option<String> {
+        let description = book.description.as_deref()?;
+        let text = html_to_text(description).replace('\n', " ");
+        (!text.is_empty()).then(|| truncate_words(&text, self.summary_chars))
+    }
+
+    fn block(&self, field: Field, book: &LibraryBook, base_url: &str) -> String {
+        let labelled = |label: &str, value: &Option<String>| {
+            value
+                .as_deref()
+                .map(|value| format!("<p>{}: {}</p>", label, encode_text(value)))
+                .unwrap_or_default()
+        };
+        match field {
+            Field::Cover => book
+                .cover_key
+                .as_deref()
+                .map(|key| {
+                    let src = format!("{}/files/{}", base_url, key);
+                    format!(
+                        r#"<p><img src="{}" alt="Cover" width="{}"/></p>"#,
+                        encode_double_quoted_attribute(&src),
+                        self.cover_width
+                    )
+                })
+                .unwrap_or_default(),
+            Field::Title => format!("<h3>{}</h3>", encode_text(&book.title)),
+            Field::Authors if book.authors.is_empty() => String::new(),
+            Field::Authors => format!("<p>By {}</p>", encode_text(&book.authors.join(", "))),
+            Field::Series => book
+                .series
+                .as_deref()
+                .map(|series| {
+                    let index = book
+                        .series_index
+                        .map_or(String::new(), |i| format!(" #{}", i));
+                    format!("<p><em>Series: {}{}</em></p>", encode_text(series), index)
+                })
+                .unwrap_or_default(),
+            Field::Rating => book
+                .rating
+                .map(|rating| format!("<p>Rating: {}</p>", stars(rating)))
+                .unwrap_or_default(),
+            Field::Tags if book.tags.is_empty() => String::new(),
+            Field::Tags => format!("<p>Tags: {}</p>", encode_text(&book.tags.join(", "))),
+            Field::Publisher => labelled("Publisher", &book.publisher),
+            Field::Published => labelled("Published", &book.pubdate),
+            Field::Language => labelled("Language", &book.language),
+            Field::Description => book
+                .description
+                .as_deref()
+                .map(|description| {
+                    let text = truncate_words(&html_to_text(description), self.description_chars);
+                    text.lines()
+                        .map(|paragraph| format!("<p>{}</p>", encode_text(paragraph)))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl Default for EntryTemplate {
+    fn default() -> Self {
+        Self::new(&OpdsConfig::default())
+    }
+}
+
+/// Split a template into text and placeholders
+fn parse(template: &str) -> Vec<Part> {
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start..].find("}}") else {
+            break;
+        };
+        let name = rest[start + 2..start + len].trim();
+        match Field::parse(name) {
+            Some(field) => {
+                text.push_str(&rest[..start]);
+                parts.push(Part::Text(std::mem::take(&mut text)));
+                parts.push(Part::Field(field));
+            }
+            None => text.push_str(&rest[..start + len + 2]),
+        }
+        rest = &rest[start + len + 2..];
+    }
+    text.push_str(rest);
+    parts.push(Part::Text(text));
+    parts.retain(|part| *part != Part::Text(String::new()));
+    parts
+}
+
+/// A Calibre rating (0-10, two per star) as five stars
+fn stars(rating: u8) -> String {
+    let rating = rating.min(10) as usize;
+    let half = if rating % 2 == 1 { "½" } else { "" };
+    format!(
+        "{}{}{}",
+        "★".repeat(rating / 2),
+        half,
+        "☆".repeat(5 - rating.div_ceil(2))
+    )
+}
+
+/// Text of an HTML fragment, one line per paragraph
+///
+/// Calibre stores descriptions as HTML; entries show them cut to length,
+/// which would leave tags unclosed, so only the text is kept.
+fn html_to_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = rest[start + 1..start + end].trim_start_matches('/');
+        let name = tag
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        if matches!(
+            name.as_str(),
+            "p" | "br" | "div" | "li" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "blockquote"
+        ) {
+            text.push('\n');
+        }
+        rest = &rest[start + end + 1..];
+    }
+    text.push_str(rest);
+
+    html_escape::decode_html_entities(&text)
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Cut text to at most `max` characters at a word boundary, marking the cut
+/// with an ellipsis
+fn truncate_words(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    // Leave room for the ellipsis
+    let end = text
+        .char_indices()
+        .nth(max.saturating_sub(1))
+        .map_or(text.len(), |(i, _)| i);
+    let cut = &text[..end];
+    let cut = match cut.rfind(char::is_whitespace) {
+        Some(space) if space > 0 => &cut[..space],
+        _ => cut,
+    };
+    format!("{}…", cut.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(source: &str) -> EntryTemplate {
+        EntryTemplate::new(&OpdsConfig {
+            entry_template: source.to_string(),
+            description_chars: 40,
+            ..OpdsConfig::default()
+        })
+    }
+
+    fn book() -> LibraryBook {
+        let mut book = LibraryBook::new("Dune".to_string(), "Frank Herbert/Dune".to_string());
+        book.authors = vec!["Frank Herbert".to_string()];
+        book.series = Some("Dune".to_string());
+        book.series_index = Some(1.0);
+        book.rating = Some(9);
+        book.tags = vec!["Science Fiction".to_string(), "Classics".to_string()];
+        book.cover_key = Some("Frank Herbert/Dune/cover.jpg".to_string());
+        book.description = Some(
+            "<div><p>Set on the desert planet Arrakis, <b>Dune</b> is the story of the boy \
+             Paul Atreides.</p><p>A &quot;stunning&quot; blend.</p></div>"
+                .to_string(),
+        );
+        book
+    }
+
+    #[test]
+    fn test_render_default_template() {
+        let html = EntryTemplate::default()
+            .render(&book(), "http://host")
+            .unwrap();
+        assert!(html.starts_with(
+            r#"<p><img src="http://host/files/Frank Herbert/Dune/cover.jpg" alt="Cover" width="160"/></p>"#
+        ));
+        assert!(html.contains("<p><em>Series: Dune #1</em></p>"));
+        assert!(html.contains("<p>Rating: ★★★★½</p>"));
+        assert!(html.contains("<p>Tags: Science Fiction, Classics</p>"));
+        assert!(html.ends_with(r#"<p>A "stunning" blend.</p>"#));
+    }
+
+    #[test]
+    fn test_render_custom_template() {
+        let custom =
+            template("<div>{{ title }}{{authors}}{{unknown}}{{language}}{{description}}</div>");
+        let html = custom.render(&book(), "http://host").unwrap();
+        assert_eq!(
+            html,
+            "<div><h3>Dune</h3><p>By Frank Herbert</p>{{unknown}}\
+             <p>Set on the desert planet Arrakis, Dune…</p></div>"
+        );
+
+        // Nothing to show
+        let bare = LibraryBook::new("Untitled".to_string(), "x".to_string());
+        assert_eq!(
+            template("{{cover}}{{rating}}").render(&bare, "http://host"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_summary_is_plain_text() {
+        let summary = EntryTemplate::default().summary(&book()).unwrap();
+        assert_eq!(
+            summary,
+            "Set on the desert planet Arrakis, Dune is the story of the boy Paul Atreides. \
+             A \"stunning\" blend."
+        );
+        assert_eq!(truncate_words("ñandú ñandú ñandú", 8), "ñandú…");
+        assert_eq!(stars(4), "★★☆☆☆");
+    }
+}
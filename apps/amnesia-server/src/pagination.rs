@@ -0,0 +1,190 @@
+//! Pagination and sorting for list endpoints
+//!
+//! List endpoints accept the same `limit`/`offset` query parameters and
+//! respond with the items plus a flattened [`PageInfo`]:
+//!
+//! ```json
+//! { "documents": [...], "total": 120, "limit": 50, "offset": 50, "hasMore": true }
+//! ```
+//!
+//! Sort keys and filters are endpoint-specific; the direction is always a
+//! [`SortOrder`].
+
+use std::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+/// Page size used when `limit` is not given
+pub const DEFAULT_LIMIT: usize = 50;
+
+/// Largest page size a client can request
+pub const MAX_LIMIT: usize = 500;
+
+/// `limit`/`offset` query parameters
+#[derive(Debug, Clone, Copy, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PageParams {
+    /// Maximum items to return (default 50, max 500)
+    pub limit: Option<usize>,
+    /// Number of items to skip
+    pub offset: Option<usize>,
+}
+
+impl PageParams {
+    /// Create params for a specific page
+    pub fn new(limit: usize, offset: usize) -> Self {
+        Self {
+            limit: Some(limit),
+            offset: Some(offset),
+        }
+    }
+
+    /// Effective page size, clamped to `1..=MAX_LIMIT`
+    pub fn limit(&self) -> usize {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+
+    /// Effective offset
+    pub fn offset(&self) -> usize {
+        self.offset.unwrap_or(0)
+    }
+
+    /// Page metadata for a result set of `total` items
+    pub fn info(&self, total: usize) -> PageInfo {
+        let limit = self.limit();
+        let offset = self.offset();
+        PageInfo {
+            total,
+            limit,
+            offset,
+            has_more: offset.saturating_add(limit) < total,
+        }
+    }
+
+    /// Slice an already filtered and sorted list down to this page
+    pub fn apply<T>(&self, items: Vec<T>) -> (Vec<T>, PageInfo) {
+        let info = self.info(items.len());
+        let page = items
+            .into_iter()
+            .skip(info.offset)
+            .take(info.limit)
+            .collect();
+        (page, info)
+    }
+}
+
+/// Page metadata included in every list response
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PageInfo {
+    /// Total items matching the filters, before pagination
+    pub total: usize,
+    /// Page size that was applied
+    pub limit: usize,
+    /// Offset that was applied
+    pub offset: usize,
+    /// Whether more items follow this page
+    pub has_more: bool,
+}
+
+/// Sort direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    /// Apply this direction to an ascending comparison
+    pub fn apply(self, ordering: Ordering) -> Ordering {
+        match self {
+            SortOrder::Asc => ordering,
+            SortOrder::Desc => ordering.reverse(),
+        }
+    }
+
+    /// SQL keyword for this direction
+    pub fn as_sql(self) -> &'static str {
+        match self {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        }
+    }
+}
+
+/// Case-insensitive comparison for titles and names
+///
+/// Missing values sort last in either direction.
+pub fn compare_text(a: Option<&str>, b: Option<&str>, order: SortOrder) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => order.apply(a.to_lowercase().cmp(&b.to_lowercase())),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Case-insensitive substring match used by text filters
+pub fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_and_clamping() {
+        let params = PageParams::default();
+        assert_eq!(params.limit(), DEFAULT_LIMIT);
+        assert_eq!(params.offset(), 0);
+
+        assert_eq!(PageParams::new(0, 0).limit(), 1);
+        assert_eq!(PageParams::new(10_000, 0).limit(), MAX_LIMIT);
+    }
+
+    #[test]
+    fn test_apply_slices_and_reports_total() {
+        let items: Vec<u32> = (0..25).collect();
+
+        let (page, info) = PageParams::new(10, 10).apply(items.clone());
+        assert_eq!(page, (10..20).collect::<Vec<_>>());
+        assert_eq!(info.total, 25);
+        assert!(info.has_more);
+
+        let (page, info) = PageParams::new(10, 20).apply(items.clone());
+        assert_eq!(page.len(), 5);
+        assert!(!info.has_more);
+
+        let (page, info) = PageParams::new(10, 100).apply(items);
+        assert!(page.is_empty());
+        assert_eq!(info.total, 25);
+    }
+
+    #[test]
+    fn test_sort_order() {
+        assert_eq!(SortOrder::Asc.apply(1.cmp(&2)), Ordering::Less);
+        assert_eq!(SortOrder::Desc.apply(1.cmp(&2)), Ordering::Greater);
+        assert_eq!(SortOrder::Desc.as_sql(), "DESC");
+    }
+
+    #[test]
+    fn test_compare_text_missing_last() {
+        let asc = SortOrder::Asc;
+        let desc = SortOrder::Desc;
+        assert_eq!(
+            compare_text(Some("alpha"), Some("Beta"), asc),
+            Ordering::Less
+        );
+        assert_eq!(
+            compare_text(Some("alpha"), Some("Beta"), desc),
+            Ordering::Greater
+        );
+        assert_eq!(compare_text(Some("zeta"), None, asc), Ordering::Less);
+        assert_eq!(compare_text(Some("zeta"), None, desc), Ordering::Less);
+        assert_eq!(compare_text(None, None, asc), Ordering::Equal);
+    }
+}
@@ -1,16 +1,16 @@
 //! PDF cache for parsed documents and rendered pages
 //!
 //! In-memory cache to avoid re-parsing PDFs and re-rendering pages.
+//! Rendered pages and text layers are evicted by byte budget.
 //!
 //! Thread Safety: MuPDF's fz_context is not thread-safe, so each PdfParser
 //! is wrapped in a parking_lot::Mutex for efficient serialization. This ensures
 //! safe concurrent access to PDF documents.
 
 use std::collections::HashMap;
-use std::num::NonZeroUsize;
 use std::sync::Arc;
 
-use lru::LruCache;
+use chrono::{DateTime, Utc};
 use parking_lot::Mutex;
 use tokio::sync::RwLock;
 use tokio::time::{timeout, Duration};
@@ -26,6 +26,13 @@ const SEARCH_TIMEOUT_SECS: u64 = 30; // 30 seconds max for search
 
 use super::mupdf_parser::{PdfParseError, PdfParser};
 use super::types::{FormInfo, ImageFormat, PageRenderRequest, ParsedPdf, SignatureInfo, TextLayer};
+use crate::byte_cache::{ByteCacheStats, ByteLruCache, ByteSize};
+use crate::mupdf::run_pooled;
+
+/// Default byte budget for rendered pages
+pub const DEFAULT_PAGE_CACHE_BYTES: usize = 128 * 1024 * 1024;
+/// Default byte budget for text layers
+pub const DEFAULT_TEXT_CACHE_BYTES: usize = 32 * 1024 * 1024;
 
 /// Thread-safe wrapper for PdfParser that serializes all operations
 /// MuPDF is NOT thread-safe, so we use parking_lot::Mutex for efficient serialization
@@ -40,52 +47,53 @@ impl SafePdfParser {
         }
     }
 
+    /// Run an operation with exclusive access, on a pooled MuPDF context
+    fn run<T>(
+        &self,
+        f: impl FnOnce(&PdfParser) -> Result<T, PdfParseError>,
+    ) -> Result<T, PdfParseError> {
+        let parser = self.inner.lock();
+        run_pooled(|| f(&parser))
+    }
+
     /// Render a page with exclusive access to the parser
     pub fn render_page(&self, request: &PageRenderRequest) -> Result<Vec<u8>, PdfParseError> {
-        let parser = self.inner.lock();
-        parser.render_page(request)
+        self.run(|parser| parser.render_page(request))
     }
 
     /// Render a thumbnail with exclusive access
     pub fn render_thumbnail(&self, page: usize, max_size: u32) -> Result<Vec<u8>, PdfParseError> {
-        let parser = self.inner.lock();
-        parser.render_thumbnail(page, max_size)
+        self.run(|parser| parser.render_thumbnail(page, max_size))
     }
 
     /// Get text layer with exclusive access
     pub fn get_text_layer(&self, page: usize) -> Result<TextLayer, PdfParseError> {
-        let parser = self.inner.lock();
-        parser.get_text_layer(page)
+        self.run(|parser| parser.get_text_layer(page))
     }
 
     /// Search with exclusive access
     pub fn search(&self, query: &str, limit: usize) -> Result<Vec<super::types::PdfSearchResult>, PdfParseError> {
-        let parser = self.inner.lock();
-        parser.search(query, limit)
+        self.run(|parser| parser.search(query, limit))
     }
 
     /// Get page text with exclusive access
     pub fn get_page_text(&self, page: usize) -> Result<String, PdfParseError> {
-        let parser = self.inner.lock();
-        parser.get_page_text(page)
+        self.run(|parser| parser.get_page_text(page))
     }
 
     /// Get page dimensions with exclusive access
     pub fn get_page_dimensions(&self, page: usize) -> Result<super::types::PageDimensions, PdfParseError> {
-        let parser = self.inner.lock();
-        parser.get_page_dimensions(page)
+        self.run(|parser| parser.get_page_dimensions(page))
     }
 
     /// Get form information with exclusive access
     pub fn get_form_info(&self) -> Result<FormInfo, PdfParseError> {
-        let parser = self.inner.lock();
-        parser.get_form_info()
+        self.run(|parser| parser.get_form_info())
     }
 
     /// Get signatures with exclusive access
     pub fn get_signatures(&self) -> Result<Vec<SignatureInfo>, PdfParseError> {
-        let parser = self.inner.lock();
-        parser.get_signatures()
+        self.run(|parser| parser.get_signatures())
     }
 
     /// Check if PDF has forms with exclusive access
@@ -137,17 +145,34 @@ impl std::hash::Hash for ImageFormat {
     }
 }
 
+/// Parsed PDF metadata along with when it entered the cache
+#[derive(Debug, Clone)]
+pub struct CachedPdf {
+    pub pdf: ParsedPdf,
+    /// When the PDF was loaded (used as the "added" date in listings)
+    pub added_at: DateTime<Utc>,
+}
+
+impl CachedPdf {
+    fn new(pdf: ParsedPdf) -> Self {
+        Self {
+            pdf,
+            added_at: Utc::now(),
+        }
+    }
+}
+
 /// Thread-safe PDF cache
 #[derive(Clone)]
 pub struct PdfCache {
     /// Parsed PDF metadata cache
-    pdfs: Arc<RwLock<HashMap<String, ParsedPdf>>>,
+    pdfs: Arc<RwLock<HashMap<String, CachedPdf>>>,
     /// Active parser instances wrapped in SafePdfParser for thread-safety
     parsers: Arc<RwLock<HashMap<String, Arc<SafePdfParser>>>>,
-    /// LRU cache for rendered pages
-    page_cache: Arc<RwLock<LruCache<PageCacheKey, Vec<u8>>>>,
-    /// LRU cache for text layers (bounded to prevent memory leaks)
-    text_cache: Arc<RwLock<LruCache<(String, usize), TextLayer>>>,
+    /// LRU cache for rendered pages, bounded by bytes
+    page_cache: Arc<RwLock<ByteLruCache<PageCacheKey, Vec<u8>>>>,
+    /// LRU cache for text layers, bounded by bytes
+    text_cache: Arc<RwLock<ByteLruCache<(String, usize), TextLayer>>>,
 }
 
 impl Default for PdfCache {
@@ -159,20 +184,16 @@ impl Default for PdfCache {
 impl PdfCache {
     /// Create a new empty cache
     pub fn new() -> Self {
-        Self::with_capacity(100) // Default to 100 cached pages
+        Self::with_budget(DEFAULT_PAGE_CACHE_BYTES, DEFAULT_TEXT_CACHE_BYTES)
     }
 
-    /// Create a cache with specified page cache capacity
-    pub fn with_capacity(page_cache_size: usize) -> Self {
-        let page_size = NonZeroUsize::new(page_cache_size).unwrap_or(NonZeroUsize::new(100).unwrap());
-        // Text cache is 2x page cache size (text layers are smaller than rendered pages)
-        let text_size = NonZeroUsize::new(page_cache_size * 2).unwrap_or(NonZeroUsize::new(200).unwrap());
-
+    /// Create a cache with byte budgets for rendered pages and text layers
+    pub fn with_budget(page_cache_bytes: usize, text_cache_bytes: usize) -> Self {
         Self {
             pdfs: Arc::new(RwLock::new(HashMap::new())),
             parsers: Arc::new(RwLock::new(HashMap::new())),
-            page_cache: Arc::new(RwLock::new(LruCache::new(page_size))),
-            text_cache: Arc::new(RwLock::new(LruCache::new(text_size))),
+            page_cache: Arc::new(RwLock::new(ByteLruCache::new(page_cache_bytes))),
+            text_cache: Arc::new(RwLock::new(ByteLruCache::new(text_cache_bytes))),
         }
     }
 
@@ -192,9 +213,11 @@ impl PdfCache {
         let parse_result = timeout(
             Duration::from_secs(PARSE_TIMEOUT_SECS),
             tokio::task::spawn_blocking(move || {
-                let parser = PdfParser::from_bytes(&data_owned, book_id_clone)?;
-                let pdf = parser.parse()?;
-                Ok::<_, PdfParseError>((parser, pdf))
+                run_pooled(|| {
+                    let parser = PdfParser::from_bytes(&data_owned, book_id_clone)?;
+                    let pdf = parser.parse()?;
+                    Ok::<_, PdfParseError>((parser, pdf))
+                })
             }),
         )
         .await;
@@ -211,7 +234,7 @@ impl PdfCache {
         // Cache the parsed metadata
         {
             let mut pdfs = self.pdfs.write().await;
-            pdfs.insert(id.clone(), pdf.clone());
+            pdfs.insert(id.clone(), CachedPdf::new(pdf.clone()));
         }
 
         // Cache the parser wrapped in SafePdfParser for thread-safety
@@ -238,9 +261,11 @@ impl PdfCache {
         let parse_result = timeout(
             Duration::from_secs(PARSE_TIMEOUT_SECS),
             tokio::task::spawn_blocking(move || {
-                let parser = PdfParser::from_path(&path_owned, book_id_clone)?;
-                let pdf = parser.parse()?;
-                Ok::<_, PdfParseError>((parser, pdf))
+                run_pooled(|| {
+                    let parser = PdfParser::from_path(&path_owned, book_id_clone)?;
+                    let pdf = parser.parse()?;
+                    Ok::<_, PdfParseError>((parser, pdf))
+                })
             }),
         )
         .await;
@@ -257,7 +282,7 @@ impl PdfCache {
         // Cache the parsed metadata
         {
             let mut pdfs = self.pdfs.write().await;
-            pdfs.insert(id.clone(), pdf.clone());
+            pdfs.insert(id.clone(), CachedPdf::new(pdf.clone()));
         }
 
         // Cache the parser wrapped in SafePdfParser for thread-safety
@@ -272,11 +297,17 @@ impl PdfCache {
     /// Get cached PDF metadata
     pub async fn get_pdf(&self, id: &str) -> Option<ParsedPdf> {
         let pdfs = self.pdfs.read().await;
-        pdfs.get(id).cloned()
+        pdfs.get(id).map(|cached| cached.pdf.clone())
     }
 
     /// Get all cached PDFs
     pub async fn get_all_pdfs(&self) -> Vec<ParsedPdf> {
+        let pdfs = self.pdfs.read().await;
+        pdfs.values().map(|cached| cached.pdf.clone()).collect()
+    }
+
+    /// Get all cached PDFs with their load times
+    pub async fn get_all_cached(&self) -> Vec<CachedPdf> {
         let pdfs = self.pdfs.read().await;
         pdfs.values().cloned().collect()
     }
@@ -528,31 +559,16 @@ impl PdfCache {
             parsers.remove(id);
         }
 
-        // Remove cached pages (need to iterate through LRU)
+        // Remove cached pages
         {
             let mut page_cache = self.page_cache.write().await;
-            // LruCache doesn't have a filter method, so we collect keys to remove
-            let keys_to_remove: Vec<PageCacheKey> = page_cache
-                .iter()
-                .filter(|(k, _)| k.book_id == id)
-                .map(|(k, _)| k.clone())
-                .collect();
-            for key in keys_to_remove {
-                page_cache.pop(&key);
-            }
+            page_cache.remove_where(|k| k.book_id == id);
         }
 
-        // Remove cached text layers (LruCache doesn't have retain, so collect and pop)
+        // Remove cached text layers
         {
             let mut text_cache = self.text_cache.write().await;
-            let keys_to_remove: Vec<(String, usize)> = text_cache
-                .iter()
-                .filter(|((book_id, _), _)| book_id == id)
-                .map(|(k, _)| k.clone())
-                .collect();
-            for key in keys_to_remove {
-                text_cache.pop(&key);
-            }
+            text_cache.remove_where(|(book_id, _)| book_id == id);
         }
     }
 
@@ -589,9 +605,46 @@ impl PdfCache {
     }
 
     /// Get page cache statistics
-    pub async fn page_cache_stats(&self) -> (usize, usize) {
-        let page_cache = self.page_cache.read().await;
-        (page_cache.len(), page_cache.cap().get())
+    pub async fn page_cache_stats(&self) -> ByteCacheStats {
+        self.page_cache.read().await.stats()
+    }
+
+    /// Get text layer cache statistics
+    pub async fn text_cache_stats(&self) -> ByteCacheStats {
+        self.text_cache.read().await.stats()
+    }
+
+    /// Cached page and text layer bytes per PDF, keyed by book ID
+    pub async fn bytes_by_book(&self) -> HashMap<String, (usize, usize)> {
+        let pages = self.page_cache.read().await.bytes_by(|k| k.book_id.clone());
+        let text = self.text_cache.read().await.bytes_by(|(id, _)| id.clone());
+
+        let mut totals: HashMap<String, (usize, usize)> = HashMap::new();
+        for (id, bytes) in pages {
+            totals.entry(id).or_default().0 = bytes;
+        }
+        for (id, bytes) in text {
+            totals.entry(id).or_default().1 = bytes;
+        }
+        totals
+    }
+}
+
+impl ByteSize for TextLayer {
+    fn byte_size(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self
+                .items
+                .iter()
+                .map(|item| {
+                    std::mem::size_of_val(item)
+                        + item.text.capacity()
+                        + item
+                            .char_positions
+                            .as_ref()
+                            .map_or(0, |chars| std::mem::size_of_val(chars.as_slice()))
+                })
+                .sum::<usize>()
     }
 }
 
@@ -607,10 +660,11 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_pdf_cache_with_capacity() {
-        let cache = PdfCache::with_capacity(50);
-        let (_, cap) = cache.page_cache_stats().await;
-        assert_eq!(cap, 50);
+    async fn test_pdf_cache_with_budget() {
+        let cache = PdfCache::with_budget(4096, 1024);
+        assert_eq!(cache.page_cache_stats().await.budget_bytes, 4096);
+        assert_eq!(cache.text_cache_stats().await.budget_bytes, 1024);
+        assert!(cache.bytes_by_book().await.is_empty());
     }
 
     #[tokio::test]
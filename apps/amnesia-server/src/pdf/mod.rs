@@ -20,7 +20,7 @@ pub use annotation_extractor::{
     extract_annotations, ExtractedAnnotation, ExtractedAnnotationType, ExtractionOptions,
     ExtractionResult, ExtractionStats,
 };
-pub use cache::PdfCache;
+pub use cache::{CachedPdf, PdfCache};
 pub use mupdf_parser::{PdfParseError, PdfParser};
 pub use types::{
     BoundingBox, CharPosition, FillFormRequest, FillFormResult, FormField, FormFieldType,
@@ -17,6 +17,8 @@ use mupdf::{Colorspace, Document, Matrix, MetadataName, TextPageOptions};
 use thiserror::Error;
 
 use crate::document::TocEntry;
+use crate::formats::pdf::{chapters, page_labels};
+use crate::mupdf::{ErrorClass, PooledError};
 
 use super::types::{
     BoundingBox, CharPosition, FormField, FormFieldType, FormInfo, FormOption, ImageFormat,
@@ -52,6 +54,19 @@ impl From<mupdf::Error> for PdfParseError {
     }
 }
 
+impl PooledError for PdfParseError {
+    fn class(&self) -> ErrorClass {
+        match self {
+            PdfParseError::MuPdfInit(_) | PdfParseError::MuPdfError(_) => ErrorClass::Poisoning,
+            _ => ErrorClass::Recoverable,
+        }
+    }
+
+    fn panicked() -> Self {
+        PdfParseError::MuPdfError("MuPDF operation panicked".to_string())
+    }
+}
+
 /// Thread-safe MuPDF PDF parser
 ///
 /// MuPDF's fz_context is not thread-safe, so we use a Mutex to serialize
@@ -187,9 +202,13 @@ impl PdfParser {
         })
     }
 
-    /// Extract PDF outline/bookmarks as TocEntry
+    /// Extract PDF outline/bookmarks as TocEntry, detecting chapters from
+    /// the text when there is no outline
     fn extract_outline(&self, doc: &Document) -> Result<Vec<TocEntry>, PdfParseError> {
         let outlines = doc.outlines()?;
+        if outlines.is_empty() {
+            return chapters::detect_toc(doc).map_err(|e| PdfParseError::MuPdfError(e.to_string()));
+        }
         Ok(self.convert_outlines_to_toc(&outlines))
     }
 
@@ -233,10 +252,13 @@ impl PdfParser {
         Ok(false)
     }
 
-    /// Extract page labels if available
+    /// Extract page labels, numbering pages 1, 2, 3, ... without `/PageLabels`
     fn extract_page_labels(&self) -> Result<Option<Vec<String>>, PdfParseError> {
-        // MuPDF supports page labels via pdf_page_label
-        // For now, generate default labels (can be enhanced later)
+        let pdf_doc = self.open_pdf_document()?;
+        if let Some(labels) = page_labels::page_labels(&pdf_doc, self.page_count) {
+            return Ok(Some(labels));
+        }
+
         if self.page_count > 0 {
             Ok(Some((1..=self.page_count).map(|n| n.to_string()).collect()))
         } else {
@@ -0,0 +1,127 @@
+//! Account API endpoints
+//!
+//! Default reader preferences and the highlight color palette, shared by all
+//! of a user's devices. Without a `userId` the server-wide defaults are read
+//! and written.
+
+use axum::{
+    extract::{Query, State},
+    routing::get,
+    Json, Router,
+};
+use serde::Deserialize;
+
+use crate::db::{HighlightPalette, PaletteRepository, PreferencesRepository, ReaderPreferences};
+use crate::error::ApiError;
+use crate::state::AppState;
+
+/// Create the account router
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/preferences", get(get_preferences).put(put_preferences))
+        .route("/palette", get(get_palette).put(put_palette))
+}
+
+/// Whose preferences or palette to use
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct AccountQuery {
+    pub user_id: Option<String>,
+}
+
+/// Get the default reader preferences
+///
+/// A user who never saved any gets empty preferences, leaving every setting
+/// at the client's default.
+#[utoipa::path(
+    get,
+    path = "/api/v1/account/preferences",
+    tag = "account",
+    params(AccountQuery),
+    responses(
+        (status = 200, description = "Default reader preferences", body = ReaderPreferences),
+        (status = 500, description = "Database error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn get_preferences(
+    State(state): State<AppState>,
+    Query(query): Query<AccountQuery>,
+) -> Result<Json<ReaderPreferences>, ApiError> {
+    let preferences = PreferencesRepository::new(state.db())
+        .get(query.user_id.as_deref())
+        .await?;
+    Ok(Json(preferences.unwrap_or_default()))
+}
+
+/// Replace the default reader preferences
+#[utoipa::path(
+    put,
+    path = "/api/v1/account/preferences",
+    tag = "account",
+    params(AccountQuery),
+    request_body = ReaderPreferences,
+    responses(
+        (status = 200, description = "Stored preferences", body = ReaderPreferences),
+        (status = 400, description = "Out-of-range value", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn put_preferences(
+    State(state): State<AppState>,
+    Query(query): Query<AccountQuery>,
+    Json(preferences): Json<ReaderPreferences>,
+) -> Result<Json<ReaderPreferences>, ApiError> {
+    let stored = PreferencesRepository::new(state.db())
+        .set(query.user_id.as_deref(), &preferences)
+        .await?;
+    Ok(Json(stored))
+}
+
+/// Get the highlight color palette
+///
+/// A user who never saved one gets the default colors.
+#[utoipa::path(
+    get,
+    path = "/api/v1/account/palette",
+    tag = "account",
+    params(AccountQuery),
+    responses(
+        (status = 200, description = "Highlight color palette", body = HighlightPalette),
+        (status = 500, description = "Database error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn get_palette(
+    State(state): State<AppState>,
+    Query(query): Query<AccountQuery>,
+) -> Result<Json<HighlightPalette>, ApiError> {
+    let palette = PaletteRepository::new(state.db())
+        .get(query.user_id.as_deref())
+        .await?;
+    Ok(Json(palette))
+}
+
+/// Replace the highlight color palette
+///
+/// Existing highlights keep their stored category; renaming a color leaves
+/// them out of the new category's filters.
+#[utoipa::path(
+    put,
+    path = "/api/v1/account/palette",
+    tag = "account",
+    params(AccountQuery),
+    request_body = HighlightPalette,
+    responses(
+        (status = 200, description = "Stored palette", body = HighlightPalette),
+        (status = 400, description = "Empty palette, invalid hex, or overlapping colors", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn put_palette(
+    State(state): State<AppState>,
+    Query(query): Query<AccountQuery>,
+    Json(palette): Json<HighlightPalette>,
+) -> Result<Json<HighlightPalette>, ApiError> {
+    let stored = PaletteRepository::new(state.db())
+        .set(query.user_id.as_deref(), &palette)
+        .await?;
+    Ok(Json(stored))
+}
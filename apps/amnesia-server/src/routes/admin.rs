@@ -0,0 +1,366 @@
+//! Administrative diagnostics
+//!
+//! `GET /api/v1/admin/cache` reports what the in-memory caches are holding:
+//! byte usage against the configured budgets, hit rates, and the bytes held
+//! per document (largest first), so memory growth can be traced to the
+//! documents responsible. It also reports the MuPDF context pool's
+//! utilization, recycling and failure counts.
+//!
+//! `GET /api/v1/admin/tasks` lists the scheduled background tasks with their
+//! schedule, next run, and the outcome of their last run.
+//!
+//! `GET /api/v1/admin/maturity` lists manual maturity overrides;
+//! `PUT`/`DELETE /api/v1/admin/maturity/{book_id}` set or clear one book's
+//! rating and content warnings.
+//!
+//! `GET /api/v1/admin/duplicate-pages` lists scanned PDFs with likely
+//! repeated pages (found by the `duplicate_pages` task), so the scans can be
+//! fixed before reading; `GET /api/v1/admin/duplicate-pages/{book_id}` gives
+//! one book's report, including clean ones.
+//!
+//! `GET /api/v1/admin/export/vault` downloads every highlight and note as a
+//! zip of Markdown pages, one per book, with wiki-links between books (see
+//! [`crate::vault`]).
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, put},
+    Extension, Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::byte_cache::ByteCacheStats;
+use crate::db::{
+    DuplicatePageReport, DuplicatePageRepository, MaturityOverride, MaturityRepository,
+};
+use crate::document::DocumentCacheUsage;
+use crate::error::ApiError;
+use crate::library::MaturityRating;
+use crate::mupdf::{shared_pool, PoolStats};
+use crate::routes::opds::LibraryCache;
+use crate::scheduler::{Scheduler, TaskStatus};
+use crate::state::AppState;
+use crate::vault;
+
+/// Create the admin router
+pub fn router(scheduler: Scheduler, library: LibraryCache) -> Router<AppState> {
+    Router::new()
+        .route("/cache", get(cache_report))
+        .route("/tasks", get(list_tasks))
+        .route("/tasks/:name", get(get_task))
+        .route("/maturity", get(list_maturity_overrides))
+        .route(
+            "/maturity/:book_id",
+            put(set_maturity_override).delete(delete_maturity_override),
+        )
+        .route("/duplicate-pages", get(list_duplicate_pages))
+        .route("/duplicate-pages/:book_id", get(get_duplicate_pages))
+        .route("/export/vault", get(export_vault))
+        .layer(Extension(scheduler))
+        .layer(Extension(library))
+}
+
+/// Composition of the document and PDF caches
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheReport {
+    pub document_cache: DocumentCacheReport,
+    pub pdf_cache: PdfCacheReport,
+    /// MuPDF contexts that document and PDF operations run on
+    pub context_pool: PoolStats,
+    /// Bytes held across both caches
+    pub total_bytes: usize,
+}
+
+/// Unified document cache usage
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentCacheReport {
+    /// Documents with cached metadata
+    pub documents: usize,
+    /// Documents with an open session
+    pub open_documents: usize,
+    /// Rendered pages/chapters
+    pub renders: ByteCacheStats,
+    /// Structured text
+    pub stext: ByteCacheStats,
+    /// Bytes per document, largest first
+    pub per_document: Vec<DocumentCacheUsage>,
+}
+
+/// Legacy PDF cache usage
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PdfCacheReport {
+    /// Loaded PDFs
+    pub pdfs: usize,
+    /// Rendered pages
+    pub pages: ByteCacheStats,
+    /// Text layers
+    pub text_layers: ByteCacheStats,
+    /// Bytes per PDF, largest first
+    pub per_document: Vec<PdfCacheUsage>,
+}
+
+/// Cached output held for one PDF
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PdfCacheUsage {
+    pub id: String,
+    pub page_bytes: usize,
+    pub text_bytes: usize,
+    pub total_bytes: usize,
+}
+
+/// Report cache byte usage, hit rates, and per-document composition
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/cache",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Cache composition", body = CacheReport)
+    )
+)]
+async fn cache_report(State(state): State<AppState>) -> Json<CacheReport> {
+    let document_cache = state.document_cache();
+    let stats = document_cache.stats().await;
+    let document_report = DocumentCacheReport {
+        documents: stats.documents,
+        open_documents: stats.open_documents,
+        renders: stats.renders,
+        stext: stats.stext,
+        per_document: document_cache.composition().await,
+    };
+
+    let pdf_cache = state.pdf_cache();
+    let mut pdf_usage: Vec<PdfCacheUsage> = pdf_cache
+        .bytes_by_book()
+        .await
+        .into_iter()
+        .map(|(id, (page_bytes, text_bytes))| PdfCacheUsage {
+            id,
+            page_bytes,
+            text_bytes,
+            total_bytes: page_bytes + text_bytes,
+        })
+        .collect();
+    pdf_usage.sort_by(|a, b| b.total_bytes.cmp(&a.total_bytes).then(a.id.cmp(&b.id)));
+    let pdf_report = PdfCacheReport {
+        pdfs: pdf_cache.len().await,
+        pages: pdf_cache.page_cache_stats().await,
+        text_layers: pdf_cache.text_cache_stats().await,
+        per_document: pdf_usage,
+    };
+
+    let total_bytes = document_report.renders.bytes
+        + document_report.stext.bytes
+        + pdf_report.pages.bytes
+        + pdf_report.text_layers.bytes;
+
+    Json(CacheReport {
+        document_cache: document_report,
+        pdf_cache: pdf_report,
+        context_pool: shared_pool().stats(),
+        total_bytes,
+    })
+}
+
+/// List scheduled tasks and their last-run status
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/tasks",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Scheduled tasks by name", body = Vec<TaskStatus>)
+    )
+)]
+async fn list_tasks(Extension(scheduler): Extension<Scheduler>) -> Json<Vec<TaskStatus>> {
+    Json(scheduler.statuses())
+}
+
+/// Get one scheduled task's status
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/tasks/{name}",
+    tag = "admin",
+    params(("name" = String, Path, description = "Task name, e.g. library_rescan")),
+    responses(
+        (status = 200, description = "Task status", body = TaskStatus),
+        (status = 404, description = "No task with that name is scheduled", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn get_task(
+    Extension(scheduler): Extension<Scheduler>,
+    Path(name): Path<String>,
+) -> Result<Json<TaskStatus>, ApiError> {
+    scheduler
+        .status(&name)
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("No scheduled task named '{}'", name)))
+}
+
+/// Manual maturity rating for a book
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MaturityOverrideRequest {
+    pub rating: MaturityRating,
+    /// Replaces the book's parsed content warnings when set
+    pub content_warnings: Option<Vec<String>>,
+}
+
+/// List manual maturity overrides
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/maturity",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Overrides by book ID", body = Vec<MaturityOverride>)
+    )
+)]
+async fn list_maturity_overrides(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<MaturityOverride>>, ApiError> {
+    let overrides = MaturityRepository::new(state.db()).list().await?;
+    Ok(Json(overrides))
+}
+
+/// Set a book's maturity rating, overriding its metadata
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/maturity/{book_id}",
+    tag = "admin",
+    params(("book_id" = String, Path, description = "Stable book ID (Calibre UUID or library path)")),
+    request_body = MaturityOverrideRequest,
+    responses(
+        (status = 200, description = "Override stored", body = MaturityOverride)
+    )
+)]
+async fn set_maturity_override(
+    State(state): State<AppState>,
+    Path(book_id): Path<String>,
+    Json(request): Json<MaturityOverrideRequest>,
+) -> Result<Json<MaturityOverride>, ApiError> {
+    let stored = MaturityRepository::new(state.db())
+        .set(&book_id, request.rating, request.content_warnings)
+        .await?;
+    Ok(Json(stored))
+}
+
+/// Remove a book's maturity override
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/maturity/{book_id}",
+    tag = "admin",
+    params(("book_id" = String, Path, description = "Stable book ID")),
+    responses(
+        (status = 204, description = "Override removed"),
+        (status = 404, description = "The book has no override", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn delete_maturity_override(
+    State(state): State<AppState>,
+    Path(book_id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    if MaturityRepository::new(state.db()).delete(&book_id).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::not_found(format!(
+            "No maturity override for '{}'",
+            book_id
+        )))
+    }
+}
+
+/// List books whose PDF has likely repeated pages
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/duplicate-pages",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Reports with duplicates, most duplicates first", body = Vec<DuplicatePageReport>)
+    )
+)]
+async fn list_duplicate_pages(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<DuplicatePageReport>>, ApiError> {
+    let reports = DuplicatePageRepository::new(state.db())
+        .with_duplicates()
+        .await?;
+    Ok(Json(reports))
+}
+
+/// Get one book's duplicate-page report
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/duplicate-pages/{book_id}",
+    tag = "admin",
+    params(("book_id" = String, Path, description = "Stable book ID")),
+    responses(
+        (status = 200, description = "Duplicate-page report", body = DuplicatePageReport),
+        (status = 404, description = "The book's PDF has not been checked", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn get_duplicate_pages(
+    State(state): State<AppState>,
+    Path(book_id): Path<String>,
+) -> Result<Json<DuplicatePageReport>, ApiError> {
+    DuplicatePageRepository::new(state.db())
+        .get(&book_id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("No duplicate-page report for '{}'", book_id)))
+}
+
+/// Vault export parameters
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct VaultExportQuery {
+    /// Only export this user's highlights and notes
+    pub user_id: Option<String>,
+    /// Only export notes in this palette category
+    pub category: Option<String>,
+}
+
+/// Download highlights and notes as a Markdown vault (zip)
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/export/vault",
+    tag = "admin",
+    params(VaultExportQuery),
+    responses(
+        (status = 200, description = "Zip of Markdown pages, one per book", body = Vec<u8>, content_type = "application/zip")
+    )
+)]
+async fn export_vault(
+    State(state): State<AppState>,
+    Extension(library): Extension<LibraryCache>,
+    Query(query): Query<VaultExportQuery>,
+) -> Result<Response, ApiError> {
+    let books = library.get_books().await;
+    let export = vault::export_vault(
+        state.db(),
+        &books,
+        query.user_id.as_deref(),
+        query.category.as_deref(),
+    )
+    .await?;
+    tracing::info!("{}", export.summary);
+
+    let filename = format!(
+        "attachment; filename=\"los-libros-vault-{}.zip\"",
+        chrono::Utc::now().format("%Y%m%d")
+    );
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (header::CONTENT_DISPOSITION, filename),
+        ],
+        export.zip,
+    )
+        .into_response())
+}
@@ -1,31 +1,66 @@
 //! Annotation API endpoints
 //!
 //! Provides REST API for managing annotations (highlights, notes, bookmarks).
+//! Edits keep the previous body and style as versions, which can be listed
+//! and restored.
+//!
+//! With the `import` feature, `POST /api/v1/annotations/import/kindle`
+//! imports a Kindle `My Clippings.txt` file sent as the request body (see
+//! [`crate::import::import_clippings`]).
 
 use axum::{
-    extract::{Path, Query, State},
+    extract::{DefaultBodyLimit, Path, Query, State},
     http::StatusCode,
-    routing::get,
-    Json, Router,
+    routing::{get, post},
+    Extension, Json, Router,
 };
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 
 use crate::annotations::{
-    Annotation, AnnotationQuery, AnnotationRepository, AnnotationTarget, AnnotationType,
+    validate_links, Annotation, AnnotationQuery, AnnotationRepository, AnnotationTarget,
+    AnnotationType, AnnotationVersion, DocumentLink, PdfRect,
 };
+#[cfg(feature = "import")]
+use crate::import::{import_clippings, parse_clippings, ClippingsReport, LibraryIndex};
+use crate::routes::opds::LibraryCache;
 use crate::state::AppState;
 
+use super::limits::ANNOTATION_BODY_LIMIT;
+
+/// Largest accepted clippings file
+#[cfg(feature = "import")]
+const MAX_CLIPPINGS_BYTES: usize = 64 * 1024 * 1024;
+
 /// Create the annotations router
-pub fn router() -> Router<AppState> {
-    Router::new()
+pub fn router(library: LibraryCache) -> Router<AppState> {
+    let router = Router::new()
         .route("/", get(list_annotations).post(create_annotation))
         .route("/{id}", get(get_annotation).put(update_annotation).delete(delete_annotation))
+        .route("/{id}/versions", get(list_annotation_versions))
+        .route(
+            "/{id}/versions/{version}/restore",
+            post(restore_annotation_version),
+        )
         .route("/book/{book_id}", get(list_book_annotations))
         .route("/book/{book_id}/count", get(count_book_annotations))
+        .route("/book/{book_id}/backlinks", get(list_book_backlinks));
+
+    // Clippings import is only available when the `import` feature is enabled
+    #[cfg(feature = "import")]
+    let router = router.route(
+        "/import/kindle",
+        post(import_kindle_clippings).layer(DefaultBodyLimit::max(MAX_CLIPPINGS_BYTES)),
+    );
+
+    router
+        .layer(DefaultBodyLimit::max(ANNOTATION_BODY_LIMIT))
+        .layer(Extension(library))
 }
 
 /// Query parameters for listing annotations
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct ListParams {
     book_id: Option<String>,
     user_id: Option<String>,
@@ -36,8 +71,19 @@ pub struct ListParams {
     offset: Option<i32>,
 }
 
-/// Request body for creating/updating annotations
+/// Query parameters for importing Kindle clippings
+#[cfg(feature = "import")]
 #[derive(Debug, Deserialize)]
+pub struct KindleImportParams {
+    /// User the annotations are stored under
+    user_id: Option<String>,
+    /// Report what would be imported without writing it
+    #[serde(default)]
+    dry_run: bool,
+}
+
+/// Request body for creating/updating annotations
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateAnnotationRequest {
     #[serde(rename = "bookId")]
     pub book_id: String,
@@ -50,63 +96,84 @@ pub struct CreateAnnotationRequest {
     pub style: Option<AnnotationStyleRequest>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct AnnotationTargetRequest {
     pub source: String,
     pub cfi: Option<String>,
+    /// Region of the image at `cfi` (normalized 0-1), to highlight part of
+    /// an illustration
+    pub region: Option<PdfRect>,
     #[serde(rename = "textQuote")]
     pub text_quote: Option<TextQuoteRequest>,
     pub progression: Option<f64>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct TextQuoteRequest {
     pub exact: String,
     pub prefix: Option<String>,
     pub suffix: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct AnnotationBodyRequest {
     pub value: Option<String>,
+    /// Links to other books; replaces existing links on update
+    pub links: Option<Vec<DocumentLink>>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct AnnotationStyleRequest {
     pub color: Option<String>,
     pub opacity: Option<f32>,
 }
 
 /// Request body for updating annotations
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct UpdateAnnotationRequest {
     pub body: Option<AnnotationBodyRequest>,
     pub style: Option<AnnotationStyleRequest>,
 }
 
 /// Response types
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct AnnotationResponse {
     pub annotation: Annotation,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct AnnotationsListResponse {
     pub annotations: Vec<Annotation>,
     pub total: usize,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AnnotationVersionsResponse {
+    /// Newest first
+    pub versions: Vec<AnnotationVersion>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
 pub struct CountResponse {
     pub count: i64,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ErrorResponse {
     pub error: String,
 }
 
 /// List annotations with optional filters
+#[utoipa::path(
+    get,
+    path = "/api/v1/annotations",
+    tag = "annotations",
+    params(ListParams),
+    responses(
+        (status = 200, description = "Matching annotations", body = AnnotationsListResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
 async fn list_annotations(
     State(state): State<AppState>,
     Query(params): Query<ListParams>,
@@ -136,6 +203,16 @@ async fn list_annotations(
 }
 
 /// List annotations for a specific book
+#[utoipa::path(
+    get,
+    path = "/api/v1/annotations/book/{book_id}",
+    tag = "annotations",
+    params(("book_id" = String, Path, description = "Book ID"), ListParams),
+    responses(
+        (status = 200, description = "Annotations for the book", body = AnnotationsListResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
 async fn list_book_annotations(
     State(state): State<AppState>,
     Path(book_id): Path<String>,
@@ -165,7 +242,47 @@ async fn list_book_annotations(
     Ok(Json(AnnotationsListResponse { annotations, total }))
 }
 
+/// List annotations in other books that link to a book
+#[utoipa::path(
+    get,
+    path = "/api/v1/annotations/book/{book_id}/backlinks",
+    tag = "annotations",
+    params(("book_id" = String, Path, description = "Linked book ID")),
+    responses(
+        (status = 200, description = "Annotations linking to the book", body = AnnotationsListResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+async fn list_book_backlinks(
+    State(state): State<AppState>,
+    Path(book_id): Path<String>,
+) -> Result<Json<AnnotationsListResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let repo = AnnotationRepository::new(state.db());
+
+    let annotations = repo.backlinks(&book_id).await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    let total = annotations.len();
+    Ok(Json(AnnotationsListResponse { annotations, total }))
+}
+
 /// Get annotation count for a book
+#[utoipa::path(
+    get,
+    path = "/api/v1/annotations/book/{book_id}/count",
+    tag = "annotations",
+    params(("book_id" = String, Path, description = "Book ID")),
+    responses(
+        (status = 200, description = "Annotation count", body = CountResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
 async fn count_book_annotations(
     State(state): State<AppState>,
     Path(book_id): Path<String>,
@@ -185,17 +302,43 @@ async fn count_book_annotations(
 }
 
 /// Create a new annotation
+#[utoipa::path(
+    post,
+    path = "/api/v1/annotations",
+    tag = "annotations",
+    request_body = CreateAnnotationRequest,
+    responses(
+        (status = 201, description = "Annotation created", body = AnnotationResponse),
+        (status = 400, description = "Invalid document link or image region", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
 async fn create_annotation(
     State(state): State<AppState>,
     Json(req): Json<CreateAnnotationRequest>,
 ) -> Result<(StatusCode, Json<AnnotationResponse>), (StatusCode, Json<ErrorResponse>)> {
     let repo = AnnotationRepository::new(state.db());
 
+    let links = req
+        .body
+        .as_ref()
+        .and_then(|b| b.links.clone())
+        .unwrap_or_default();
+    validate_links(&links).map_err(bad_request)?;
+
     // Build target with selectors
-    let mut target = if let Some(cfi) = &req.target.cfi {
-        AnnotationTarget::from_cfi(&req.target.source, cfi)
-    } else {
-        AnnotationTarget::with_selectors(&req.target.source, vec![])
+    let source = &req.target.source;
+    let mut target = match (&req.target.cfi, req.target.region) {
+        (Some(cfi), Some(region)) => {
+            AnnotationTarget::from_image_region(source, cfi, region).map_err(bad_request)?
+        }
+        (Some(cfi), None) => AnnotationTarget::from_cfi(source, cfi),
+        (None, Some(_)) => {
+            return Err(bad_request(
+                "An image region needs the image's CFI".to_string(),
+            ))
+        }
+        (None, None) => AnnotationTarget::with_selectors(source, vec![]),
     };
 
     // Add text quote selector if provided
@@ -235,6 +378,8 @@ async fn create_annotation(
         }
     }
 
+    annotation.set_links(links);
+
     repo.save(&annotation).await.map_err(|e| {
         (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -248,6 +393,16 @@ async fn create_annotation(
 }
 
 /// Get a single annotation
+#[utoipa::path(
+    get,
+    path = "/api/v1/annotations/{id}",
+    tag = "annotations",
+    params(("id" = String, Path, description = "Annotation ID")),
+    responses(
+        (status = 200, description = "Annotation", body = AnnotationResponse),
+        (status = 404, description = "Annotation not found", body = ErrorResponse)
+    )
+)]
 async fn get_annotation(
     State(state): State<AppState>,
     Path(id): Path<String>,
@@ -275,6 +430,18 @@ async fn get_annotation(
 }
 
 /// Update an annotation
+#[utoipa::path(
+    put,
+    path = "/api/v1/annotations/{id}",
+    tag = "annotations",
+    params(("id" = String, Path, description = "Annotation ID")),
+    request_body = UpdateAnnotationRequest,
+    responses(
+        (status = 200, description = "Updated annotation", body = AnnotationResponse),
+        (status = 400, description = "Invalid document link", body = ErrorResponse),
+        (status = 404, description = "Annotation not found", body = ErrorResponse)
+    )
+)]
 async fn update_annotation(
     State(state): State<AppState>,
     Path(id): Path<String>,
@@ -304,15 +471,30 @@ async fn update_annotation(
 
     // Update body if provided
     if let Some(body_req) = req.body {
+        if let Some(links) = &body_req.links {
+            validate_links(links).map_err(bad_request)?;
+        }
+
         if let Some(ref mut body) = annotation.body {
+            if body_req.value.is_some()
+                && body.body_type == crate::annotations::BodyType::SpecificResource
+            {
+                body.body_type = crate::annotations::BodyType::TextualBody;
+                body.format = Some("text/plain".to_string());
+            }
             body.value = body_req.value;
         } else if body_req.value.is_some() {
             annotation.body = Some(crate::annotations::AnnotationBody {
                 body_type: crate::annotations::BodyType::TextualBody,
                 value: body_req.value,
                 format: Some("text/plain".to_string()),
+                links: Vec::new(),
             });
         }
+
+        if let Some(links) = body_req.links {
+            annotation.set_links(links);
+        }
     }
 
     // Update style if provided
@@ -343,6 +525,16 @@ async fn update_annotation(
 }
 
 /// Delete an annotation
+#[utoipa::path(
+    delete,
+    path = "/api/v1/annotations/{id}",
+    tag = "annotations",
+    params(("id" = String, Path, description = "Annotation ID")),
+    responses(
+        (status = 204, description = "Annotation deleted"),
+        (status = 404, description = "Annotation not found", body = ErrorResponse)
+    )
+)]
 async fn delete_annotation(
     State(state): State<AppState>,
     Path(id): Path<String>,
@@ -370,6 +562,125 @@ async fn delete_annotation(
     }
 }
 
+/// List an annotation's previous versions
+#[utoipa::path(
+    get,
+    path = "/api/v1/annotations/{id}/versions",
+    tag = "annotations",
+    params(("id" = String, Path, description = "Annotation ID")),
+    responses(
+        (status = 200, description = "Previous bodies and styles, newest first", body = AnnotationVersionsResponse),
+        (status = 404, description = "Annotation not found", body = ErrorResponse)
+    )
+)]
+async fn list_annotation_versions(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<AnnotationVersionsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let repo = AnnotationRepository::new(state.db());
+
+    if repo.get(&id).await.map_err(internal_error)?.is_none() {
+        return Err(not_found(format!("Annotation '{}' not found", id)));
+    }
+    let versions = repo.versions(&id).await.map_err(internal_error)?;
+
+    Ok(Json(AnnotationVersionsResponse { versions }))
+}
+
+/// Restore an annotation's body and style from a previous version
+///
+/// The replaced content is kept as a new version, so restoring can be
+/// undone the same way.
+#[utoipa::path(
+    post,
+    path = "/api/v1/annotations/{id}/versions/{version}/restore",
+    tag = "annotations",
+    params(
+        ("id" = String, Path, description = "Annotation ID"),
+        ("version" = i64, Path, description = "Version to restore")
+    ),
+    responses(
+        (status = 200, description = "Restored annotation", body = AnnotationResponse),
+        (status = 404, description = "Annotation or version not found", body = ErrorResponse)
+    )
+)]
+async fn restore_annotation_version(
+    State(state): State<AppState>,
+    Path((id, version)): Path<(String, i64)>,
+) -> Result<Json<AnnotationResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let repo = AnnotationRepository::new(state.db());
+
+    let annotation = repo
+        .restore_version(&id, version)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| not_found(format!("Annotation '{}' has no version {}", id, version)))?;
+
+    Ok(Json(AnnotationResponse { annotation }))
+}
+
+/// Import highlights and notes from a Kindle `My Clippings.txt`
+#[cfg(feature = "import")]
+async fn import_kindle_clippings(
+    State(state): State<AppState>,
+    Extension(library): Extension<LibraryCache>,
+    Query(params): Query<KindleImportParams>,
+    body: String,
+) -> Result<Json<ClippingsReport>, (StatusCode, Json<ErrorResponse>)> {
+    let clippings = parse_clippings(&body);
+    if clippings.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "No clippings found; expected a Kindle My Clippings.txt file".to_string(),
+            }),
+        ));
+    }
+
+    let library = LibraryIndex::new(&library.get_books().await);
+    let report = import_clippings(
+        &clippings,
+        &library,
+        state.db(),
+        params.user_id.as_deref(),
+        params.dry_run,
+    )
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+    })?;
+
+    tracing::info!(
+        "Imported {} Kindle highlights into {} books ({} unmatched books)",
+        report.imported,
+        report.books,
+        report.unmatched.len()
+    );
+    Ok(Json(report))
+}
+
+fn internal_error(error: anyhow::Error) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse {
+            error: error.to_string(),
+        }),
+    )
+}
+
+fn not_found(error: String) -> (StatusCode, Json<ErrorResponse>) {
+    (StatusCode::NOT_FOUND, Json(ErrorResponse { error }))
+}
+
+fn bad_request(error: String) -> (StatusCode, Json<ErrorResponse>) {
+    (StatusCode::BAD_REQUEST, Json(ErrorResponse { error }))
+}
+
 fn parse_type(s: &String) -> Option<AnnotationType> {
     match s.as_str() {
         "highlight" => Some(AnnotationType::Highlight),
@@ -0,0 +1,208 @@
+//! Bandwidth profiles for renders and resources
+//!
+//! Clients on slow or metered connections pick a lighter profile and get
+//! smaller renders, thumbnails and images without passing sizes to every
+//! endpoint. The profile comes from, in order:
+//!
+//! 1. the `profile` query parameter (`low`, `medium` or `high`)
+//! 2. the `X-Bandwidth-Profile` header, which a device sets once for all its
+//!    requests
+//! 3. `Save-Data: on`, which browsers send in data saver mode, for `low`
+//! 4. `medium`, which matches the endpoints' historical defaults
+//!
+//! Explicit parameters such as `scale` or `size` still win over the profile.
+
+use std::io::Cursor;
+
+use axum::http::{HeaderMap, HeaderName};
+use image::codecs::jpeg::JpegEncoder;
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::document::ImageFormat;
+use crate::error::ApiError;
+
+/// Header a device uses to choose its profile
+pub const PROFILE_HEADER: HeaderName = HeaderName::from_static("x-bandwidth-profile");
+
+/// `Vary` value for responses that depend on the profile
+pub const PROFILE_VARY: &str = "x-bandwidth-profile, save-data";
+
+/// How much bandwidth a client wants to spend
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BandwidthProfile {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+/// Defaults an endpoint uses for a profile
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProfileSettings {
+    /// Render scale when none is requested
+    pub render_scale: f32,
+    /// Render format when none is requested
+    pub render_format: ImageFormat,
+    /// JPEG quality for renders and shrunk images (1-100)
+    pub jpeg_quality: u8,
+    /// Thumbnail size when none is requested
+    pub thumbnail_size: u32,
+    /// Longest side embedded images are shrunk to, if any
+    pub max_image_dimension: Option<u32>,
+}
+
+impl BandwidthProfile {
+    /// Parse a profile name, ignoring case
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "low" => Some(Self::Low),
+            "medium" => Some(Self::Medium),
+            "high" => Some(Self::High),
+            _ => None,
+        }
+    }
+
+    /// Defaults for this profile
+    pub fn settings(self) -> ProfileSettings {
+        match self {
+            Self::Low => ProfileSettings {
+                render_scale: 1.0,
+                render_format: ImageFormat::Jpeg,
+                jpeg_quality: 60,
+                thumbnail_size: 120,
+                max_image_dimension: Some(1200),
+            },
+            Self::Medium => ProfileSettings {
+                render_scale: 1.5,
+                render_format: ImageFormat::Png,
+                jpeg_quality: 80,
+                thumbnail_size: 200,
+                max_image_dimension: None,
+            },
+            Self::High => ProfileSettings {
+                render_scale: 2.0,
+                render_format: ImageFormat::Png,
+                jpeg_quality: 92,
+                thumbnail_size: 320,
+                max_image_dimension: None,
+            },
+        }
+    }
+}
+
+/// `profile` query parameter
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ProfileQuery {
+    /// Bandwidth profile (low, medium, high); overrides the
+    /// `X-Bandwidth-Profile` and `Save-Data` headers
+    #[param(inline)]
+    pub profile: Option<BandwidthProfile>,
+}
+
+impl ProfileQuery {
+    /// The profile for a request, from the query or its headers
+    pub fn resolve(&self, headers: &HeaderMap) -> Result<BandwidthProfile, ApiError> {
+        if let Some(profile) = self.profile {
+            return Ok(profile);
+        }
+        if let Some(value) = headers.get(&PROFILE_HEADER) {
+            return value
+                .to_str()
+                .ok()
+                .and_then(BandwidthProfile::parse)
+                .ok_or_else(|| {
+                    ApiError::bad_request("X-Bandwidth-Profile must be 'low', 'medium' or 'high'")
+                });
+        }
+
+        let save_data = headers
+            .get("save-data")
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.trim().eq_ignore_ascii_case("on"));
+        Ok(if save_data {
+            BandwidthProfile::Low
+        } else {
+            BandwidthProfile::default()
+        })
+    }
+}
+
+/// Shrink a JPEG or PNG image so its longest side is at most `max_dimension`
+///
+/// Returns `None` when the image is already small enough, is in another
+/// format, or cannot be decoded, so the original bytes are served. PNGs stay
+/// PNGs to keep transparency.
+pub fn shrink_image(
+    bytes: &[u8],
+    mime_type: &str,
+    max_dimension: u32,
+    jpeg_quality: u8,
+) -> Option<Vec<u8>> {
+    let format = match mime_type {
+        "image/jpeg" | "image/jpg" => image::ImageFormat::Jpeg,
+        "image/png" => image::ImageFormat::Png,
+        _ => return None,
+    };
+    let img = image::load_from_memory_with_format(bytes, format).ok()?;
+    if img.width().max(img.height()) <= max_dimension {
+        return None;
+    }
+
+    let img = img.thumbnail(max_dimension, max_dimension);
+    let mut output = Vec::new();
+    match format {
+        image::ImageFormat::Jpeg => img
+            .to_rgb8()
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut output, jpeg_quality))
+            .ok()?,
+        _ => img
+            .write_to(&mut Cursor::new(&mut output), image::ImageFormat::Png)
+            .ok()?,
+    }
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_resolve_profile() {
+        let mut headers = HeaderMap::new();
+        let none = ProfileQuery::default();
+        assert_eq!(none.resolve(&headers).unwrap(), BandwidthProfile::Medium);
+
+        headers.insert("save-data", HeaderValue::from_static("on"));
+        assert_eq!(none.resolve(&headers).unwrap(), BandwidthProfile::Low);
+
+        // The device setting wins over Save-Data, the query over both
+        headers.insert(PROFILE_HEADER, HeaderValue::from_static("High"));
+        assert_eq!(none.resolve(&headers).unwrap(), BandwidthProfile::High);
+        let query = ProfileQuery {
+            profile: Some(BandwidthProfile::Low),
+        };
+        assert_eq!(query.resolve(&headers).unwrap(), BandwidthProfile::Low);
+
+        headers.insert(PROFILE_HEADER, HeaderValue::from_static("ultra"));
+        assert!(none.resolve(&headers).is_err());
+    }
+
+    #[test]
+    fn test_shrink_image() {
+        let img = image::DynamicImage::new_rgb8(400, 200);
+        let mut png = Vec::new();
+        img.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+
+        assert!(shrink_image(&png, "image/png", 400, 80).is_none());
+        assert!(shrink_image(&png, "image/svg+xml", 100, 80).is_none());
+
+        let shrunk = shrink_image(&png, "image/png", 100, 80).unwrap();
+        let shrunk = image::load_from_memory(&shrunk).unwrap();
+        assert_eq!((shrunk.width(), shrunk.height()), (100, 50));
+    }
+}
@@ -12,8 +12,10 @@ use axum::{
 use serde::{Deserialize, Serialize};
 
 use crate::bibliography::{
-    generate_bibtex, generate_citation, generate_citation_list, BookMetadata, CitationFormat,
+    generate_bibtex, generate_citation, generate_citation_list, generate_in_text_citation,
+    BookMetadata, CitationFormat,
 };
+use crate::db::HighlightRepository;
 use crate::error::{AppError, Result};
 use crate::state::AppState;
 
@@ -21,6 +23,7 @@ use crate::state::AppState;
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/books/:book_id/citation", get(get_book_citation))
+        .route("/highlights/:id/citation", get(get_highlight_citation))
         .route("/generate", post(batch_generate_citations))
         .route("/formats", get(list_formats))
 }
@@ -72,6 +75,61 @@ async fn get_book_citation(
         .into_response())
 }
 
+/// Citation of a highlighted passage
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HighlightCitationResponse {
+    pub highlight_id: String,
+    pub book_id: String,
+    pub format: CitationFormat,
+    /// Highlighted text
+    pub quote: String,
+    /// Printed page label the passage is on, if known
+    pub page_label: Option<String>,
+    /// Full reference-list entry for the book
+    pub citation: String,
+    /// In-text citation or short note pointing at the page
+    pub in_text: String,
+}
+
+/// Cite a highlight by its printed page label
+///
+/// GET /api/v1/bibliography/highlights/{id}/citation?format=apa
+async fn get_highlight_citation(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<CitationQuery>,
+) -> Result<Json<HighlightCitationResponse>> {
+    let format: CitationFormat = query
+        .format
+        .parse()
+        .map_err(|_| AppError::BadRequest(format!("Invalid format: {}", query.format)))?;
+
+    let mut highlight = HighlightRepository::new(state.db())
+        .get(&id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Highlight not found: {}", id)))?;
+    if let Some(labels) = state.page_labels(&highlight.book_id).await {
+        highlight.fill_page_label(&labels);
+    }
+
+    let metadata = get_book_metadata(&state, &highlight.book_id).await?;
+    let citation = generate_citation(&metadata, format)
+        .map_err(|e| AppError::Internal(format!("Citation generation failed: {}", e)))?;
+    let in_text = generate_in_text_citation(&metadata, format, highlight.page_label.as_deref())
+        .map_err(|e| AppError::Internal(format!("Citation generation failed: {}", e)))?;
+
+    Ok(Json(HighlightCitationResponse {
+        highlight_id: highlight.id,
+        book_id: highlight.book_id,
+        format,
+        quote: highlight.text,
+        page_label: highlight.page_label,
+        citation,
+        in_text,
+    }))
+}
+
 /// Batch generation request
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
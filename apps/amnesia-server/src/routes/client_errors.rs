@@ -0,0 +1,164 @@
+//! Client error reporting endpoint
+//!
+//! The reading app posts front-end failures here so they land in the server
+//! logs next to the request that triggered them. Reports are logged under the
+//! `client_error` target with both the report's `requestId` (the server request
+//! the client was handling) and this request's own correlation ID.
+
+use axum::{
+    extract::{DefaultBodyLimit, Extension},
+    http::StatusCode,
+    routing::post,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::request_id::RequestId;
+use crate::error::ApiError;
+use crate::state::AppState;
+
+/// Maximum report body size
+const MAX_REPORT_BYTES: usize = 64 * 1024;
+/// Maximum length kept for the message and stack fields
+const MAX_FIELD_LEN: usize = 8 * 1024;
+
+/// Severity of a client report
+#[derive(Debug, Clone, Copy, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ClientErrorLevel {
+    Warning,
+    #[default]
+    Error,
+    Fatal,
+}
+
+/// Front-end failure report
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientErrorReport {
+    /// Error message
+    pub message: String,
+    #[serde(default)]
+    pub level: ClientErrorLevel,
+    /// Stack trace, if available
+    pub stack: Option<String>,
+    /// `X-Request-Id` of the server response the client was handling
+    pub request_id: Option<String>,
+    /// Problem type or error code from the failed response
+    pub code: Option<String>,
+    /// Component or view that failed (e.g. "epub-renderer")
+    pub component: Option<String>,
+    /// App version
+    pub app_version: Option<String>,
+    /// Document being read when the error occurred
+    pub book_id: Option<String>,
+    /// Additional structured context
+    #[schema(value_type = Option<Object>)]
+    pub context: Option<serde_json::Value>,
+}
+
+/// Acknowledgement for a logged report
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientErrorResponse {
+    /// Correlation ID of the report request itself
+    pub report_id: String,
+}
+
+/// Create the client errors router
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", post(report_client_error))
+        .layer(DefaultBodyLimit::max(MAX_REPORT_BYTES))
+}
+
+/// Record a front-end failure in the server logs
+#[utoipa::path(
+    post,
+    path = "/api/v1/client-errors",
+    tag = "client-errors",
+    request_body = ClientErrorReport,
+    responses(
+        (status = 202, description = "Report logged", body = ClientErrorResponse),
+        (status = 400, description = "Empty message", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn report_client_error(
+    report_id: Option<Extension<RequestId>>,
+    Json(report): Json<ClientErrorReport>,
+) -> Result<(StatusCode, Json<ClientErrorResponse>), ApiError> {
+    if report.message.trim().is_empty() {
+        return Err(ApiError::bad_request("Report message must not be empty"));
+    }
+
+    let report_id = report_id
+        .map(|Extension(id)| id.0)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    let message = truncate(&report.message, MAX_FIELD_LEN);
+    let stack = report.stack.as_deref().map(|s| truncate(s, MAX_FIELD_LEN));
+    let context = report.context.as_ref().map(|c| c.to_string());
+
+    macro_rules! log_report {
+        ($level:ident) => {
+            tracing::$level!(
+                target: "client_error",
+                request_id = report.request_id.as_deref().unwrap_or("-"),
+                code = report.code.as_deref().unwrap_or("-"),
+                component = report.component.as_deref().unwrap_or("-"),
+                app_version = report.app_version.as_deref().unwrap_or("-"),
+                book_id = report.book_id.as_deref().unwrap_or("-"),
+                stack = stack.unwrap_or("-"),
+                context = context.as_deref().unwrap_or("-"),
+                "{}",
+                message
+            )
+        };
+    }
+
+    match report.level {
+        ClientErrorLevel::Warning => log_report!(warn),
+        ClientErrorLevel::Error | ClientErrorLevel::Fatal => log_report!(error),
+    }
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(ClientErrorResponse { report_id }),
+    ))
+}
+
+/// Truncate to at most `max` bytes on a char boundary
+fn truncate(s: &str, max: usize) -> &str {
+    if s.len() <= max {
+        return s;
+    }
+    let mut end = max;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_respects_char_boundaries() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("abcdef", 3), "abc");
+        // "é" is two bytes; cutting inside it backs off to the boundary
+        assert_eq!(truncate("aé", 2), "a");
+    }
+
+    #[test]
+    fn test_report_deserializes_with_defaults() {
+        let report: ClientErrorReport = serde_json::from_str(
+            r#"{"message": "Chapter failed to render", "requestId": "abc-123"}"#,
+        )
+        .unwrap();
+        assert!(matches!(report.level, ClientErrorLevel::Error));
+        assert_eq!(report.request_id.as_deref(), Some("abc-123"));
+    }
+}
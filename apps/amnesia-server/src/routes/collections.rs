@@ -0,0 +1,457 @@
+//! Collection API endpoints
+//!
+//! `GET /api/v1/collections/:id/download` downloads every book on a shelf as
+//! one zip (see [`crate::library::bundle`]). Collections up to
+//! [`DIRECT_DOWNLOAD_BYTES`] stream straight back; larger ones are written
+//! to a temporary file by a background job and the request answers `202`
+//! with the job. Clients poll `GET /api/v1/collections/downloads/:job_id`
+//! and fetch `.../file` once it is ready. Finished bundles are kept for
+//! [`JOB_TTL_SECS`] so an interrupted download can be retried.
+
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Extension, Json, Router,
+};
+use chrono::{DateTime, Duration, Utc};
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::mpsc;
+use utoipa::ToSchema;
+
+use crate::db::CollectionRepository;
+use crate::error::ApiError;
+use crate::library::bundle::{plan_bundle, stream_bundle, BundleEntry, MAX_BUNDLE_BYTES};
+use crate::routes::opds::LibraryCache;
+use crate::state::AppState;
+
+/// Largest bundle streamed directly; larger ones are prepared by a job
+pub const DIRECT_DOWNLOAD_BYTES: u64 = 512 * 1024 * 1024;
+
+/// How long a prepared bundle is kept
+pub const JOB_TTL_SECS: i64 = 60 * 60;
+
+/// Zip chunks buffered between the S3 reader and the client
+const CHUNK_BUFFER: usize = 16;
+
+/// Read size when serving a prepared bundle
+const FILE_CHUNK_BYTES: usize = 256 * 1024;
+
+/// Create the collections router
+pub fn router(library: LibraryCache) -> Router<AppState> {
+    Router::new()
+        .route("/:id/download", get(download_collection))
+        .route("/downloads/:job_id", get(get_download_job))
+        .route("/downloads/:job_id/file", get(get_download_file))
+        .layer(Extension(library))
+        .layer(Extension(DownloadJobs::default()))
+}
+
+/// Progress of a prepared download
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadStatus {
+    Preparing,
+    Ready,
+    Failed,
+}
+
+/// A collection bundle being written to disk
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadJob {
+    pub id: String,
+    pub collection_id: String,
+    pub status: DownloadStatus,
+    /// Books in the bundle
+    pub books: usize,
+    /// Size of the books in the bundle
+    pub total_bytes: u64,
+    /// Zip bytes written so far
+    pub written_bytes: u64,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip)]
+    path: PathBuf,
+    /// [`contents_key`] of the bundle's entries
+    #[serde(skip)]
+    contents: u64,
+}
+
+impl DownloadJob {
+    fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        now - self.created_at > Duration::seconds(JOB_TTL_SECS)
+    }
+}
+
+/// Download jobs by ID
+#[derive(Clone, Default)]
+pub struct DownloadJobs(Arc<Mutex<HashMap<String, DownloadJob>>>);
+
+impl DownloadJobs {
+    fn get(&self, id: &str) -> Option<DownloadJob> {
+        self.0.lock().get(id).cloned()
+    }
+
+    /// A collection's job that has not failed and bundles the same files,
+    /// if one is still kept
+    fn find(&self, collection_id: &str, contents: u64) -> Option<DownloadJob> {
+        self.0
+            .lock()
+            .values()
+            .find(|job| {
+                job.collection_id == collection_id
+                    && job.contents == contents
+                    && job.status != DownloadStatus::Failed
+            })
+            .cloned()
+    }
+
+    fn insert(&self, job: DownloadJob) {
+        self.0.lock().insert(job.id.clone(), job);
+    }
+
+    fn update(&self, id: &str, f: impl FnOnce(&mut DownloadJob)) {
+        if let Some(job) = self.0.lock().get_mut(id) {
+            f(job);
+        }
+    }
+
+    /// Forget expired jobs and delete their files
+    fn prune(&self) {
+        let now = Utc::now();
+        let expired: Vec<DownloadJob> = {
+            let mut jobs = self.0.lock();
+            let ids: Vec<String> = jobs
+                .values()
+                .filter(|job| job.status != DownloadStatus::Preparing && job.is_expired(now))
+                .map(|job| job.id.clone())
+                .collect();
+            ids.iter().filter_map(|id| jobs.remove(id)).collect()
+        };
+
+        for job in expired {
+            if let Err(e) = std::fs::remove_file(&job.path) {
+                if e.kind() != io::ErrorKind::NotFound {
+                    tracing::warn!("Failed to delete bundle {}: {}", job.path.display(), e);
+                }
+            }
+        }
+    }
+}
+
+/// Download a collection's books as a zip
+///
+/// Each book contributes one file, preferring EPUB, then PDF. Books no
+/// longer in the library are left out. Collections over
+/// `DIRECT_DOWNLOAD_BYTES` answer `202` with a job to poll instead.
+#[utoipa::path(
+    get,
+    path = "/api/v1/collections/{id}/download",
+    tag = "collections",
+    params(("id" = String, Path, description = "Collection ID")),
+    responses(
+        (status = 200, description = "Zip of the collection's books", body = Vec<u8>, content_type = "application/zip"),
+        (status = 202, description = "Bundle is being prepared", body = DownloadJob),
+        (status = 404, description = "Collection not found or has no downloadable books", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 413, description = "Collection larger than the download limit", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn download_collection(
+    State(state): State<AppState>,
+    Extension(library): Extension<LibraryCache>,
+    Extension(jobs): Extension<DownloadJobs>,
+    Path(id): Path<String>,
+) -> Result<Response, ApiError> {
+    let repo = CollectionRepository::new(state.db());
+    let collection = repo
+        .get(&id)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("Collection '{}' not found", id)))?;
+    let book_ids = repo.books(&collection.id).await?;
+
+    let plan = plan_bundle(&library.get_books().await, &book_ids);
+    if plan.entries.is_empty() {
+        return Err(ApiError::not_found(format!(
+            "Collection '{}' has no downloadable books",
+            collection.name
+        )));
+    }
+    if plan.total_bytes > MAX_BUNDLE_BYTES {
+        return Err(ApiError::new(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!(
+                "Collection '{}' is {} bytes; downloads are limited to {} bytes",
+                collection.name, plan.total_bytes, MAX_BUNDLE_BYTES
+            ),
+        ));
+    }
+    if !plan.missing.is_empty() {
+        tracing::info!(
+            "Download of collection {} skips {} missing books",
+            collection.id,
+            plan.missing.len()
+        );
+    }
+
+    if plan.total_bytes <= DIRECT_DOWNLOAD_BYTES {
+        let (tx, rx) = mpsc::channel(CHUNK_BUFFER);
+        tokio::spawn(stream_bundle(state.s3_client().clone(), plan.entries, tx));
+        let chunks = futures::stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|chunk| (chunk, rx))
+        });
+
+        return Ok(zip_response(&collection.name, Body::from_stream(chunks)));
+    }
+
+    jobs.prune();
+    // A bundle made before books were added or removed is not reused
+    let job = match jobs.find(&collection.id, contents_key(&plan.entries)) {
+        Some(job) => job,
+        None => start_job(
+            &state,
+            &jobs,
+            collection.id.clone(),
+            plan.entries,
+            plan.total_bytes,
+        ),
+    };
+    let location = format!("/api/v1/collections/downloads/{}", job.id);
+
+    Ok((
+        StatusCode::ACCEPTED,
+        [(header::LOCATION, location)],
+        Json(job),
+    )
+        .into_response())
+}
+
+/// Identifies a bundle's files: their names, S3 keys and sizes
+fn contents_key(entries: &[BundleEntry]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    entries.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Write a bundle to a temporary file in the background
+fn start_job(
+    state: &AppState,
+    jobs: &DownloadJobs,
+    collection_id: String,
+    entries: Vec<BundleEntry>,
+    total_bytes: u64,
+) -> DownloadJob {
+    let id = uuid::Uuid::new_v4().to_string();
+    let contents = contents_key(&entries);
+    let job = DownloadJob {
+        path: std::env::temp_dir().join(format!("los-libros-bundle-{}.zip", id)),
+        id,
+        collection_id,
+        status: DownloadStatus::Preparing,
+        books: entries.len(),
+        total_bytes,
+        written_bytes: 0,
+        error: None,
+        created_at: Utc::now(),
+        contents,
+    };
+    jobs.insert(job.clone());
+
+    let (tx, rx) = mpsc::channel(CHUNK_BUFFER);
+    tokio::spawn(stream_bundle(state.s3_client().clone(), entries, tx));
+
+    let (jobs, id, path) = (jobs.clone(), job.id.clone(), job.path.clone());
+    tokio::spawn(async move {
+        match write_job_file(&jobs, &id, &path, rx).await {
+            Ok(()) => jobs.update(&id, |job| job.status = DownloadStatus::Ready),
+            Err(e) => {
+                tracing::error!("Preparing bundle {} failed: {}", id, e);
+                let _ = tokio::fs::remove_file(&path).await;
+                jobs.update(&id, |job| {
+                    job.status = DownloadStatus::Failed;
+                    job.error = Some(e.to_string());
+                });
+            }
+        }
+    });
+
+    job
+}
+
+async fn write_job_file(
+    jobs: &DownloadJobs,
+    id: &str,
+    path: &std::path::Path,
+    mut chunks: mpsc::Receiver<io::Result<Vec<u8>>>,
+) -> io::Result<()> {
+    let mut file = tokio::fs::File::create(path).await?;
+    while let Some(chunk) = chunks.recv().await {
+        let chunk = chunk?;
+        file.write_all(&chunk).await?;
+        jobs.update(id, |job| job.written_bytes += chunk.len() as u64);
+    }
+    file.flush().await
+}
+
+/// Get a download job
+#[utoipa::path(
+    get,
+    path = "/api/v1/collections/downloads/{job_id}",
+    tag = "collections",
+    params(("job_id" = String, Path, description = "Download job ID")),
+    responses(
+        (status = 200, description = "Download job", body = DownloadJob),
+        (status = 404, description = "Job not found or expired", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn get_download_job(
+    Extension(jobs): Extension<DownloadJobs>,
+    Path(job_id): Path<String>,
+) -> Result<Json<DownloadJob>, ApiError> {
+    jobs.prune();
+    jobs.get(&job_id)
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("Download job '{}' not found", job_id)))
+}
+
+/// Download a prepared bundle
+#[utoipa::path(
+    get,
+    path = "/api/v1/collections/downloads/{job_id}/file",
+    tag = "collections",
+    params(("job_id" = String, Path, description = "Download job ID")),
+    responses(
+        (status = 200, description = "Zip of the collection's books", body = Vec<u8>, content_type = "application/zip"),
+        (status = 404, description = "Job not found or expired", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "Bundle is still being prepared or failed", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn get_download_file(
+    State(state): State<AppState>,
+    Extension(jobs): Extension<DownloadJobs>,
+    Path(job_id): Path<String>,
+) -> Result<Response, ApiError> {
+    jobs.prune();
+    let job = jobs
+        .get(&job_id)
+        .ok_or_else(|| ApiError::not_found(format!("Download job '{}' not found", job_id)))?;
+    match job.status {
+        DownloadStatus::Ready => {}
+        DownloadStatus::Preparing => {
+            return Err(ApiError::conflict(format!(
+                "Download job '{}' is still preparing",
+                job_id
+            )))
+        }
+        DownloadStatus::Failed => {
+            return Err(ApiError::conflict(format!(
+                "Download job '{}' failed: {}",
+                job_id,
+                job.error.unwrap_or_default()
+            )))
+        }
+    }
+
+    let file = tokio::fs::File::open(&job.path)
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to open bundle: {}", e)))?;
+    let chunks = futures::stream::unfold(Some(file), |file| async move {
+        let mut file = file?;
+        let mut chunk = vec![0; FILE_CHUNK_BYTES];
+        match file.read(&mut chunk).await {
+            Ok(0) => None,
+            Ok(n) => {
+                chunk.truncate(n);
+                Some((Ok(chunk), Some(file)))
+            }
+            Err(e) => Some((Err(e), None)),
+        }
+    });
+
+    let name = CollectionRepository::new(state.db())
+        .get(&job.collection_id)
+        .await?
+        .map_or_else(|| job.collection_id.clone(), |collection| collection.name);
+    let mut response = zip_response(&name, Body::from_stream(chunks));
+    response
+        .headers_mut()
+        .insert(header::CONTENT_LENGTH, job.written_bytes.into());
+    Ok(response)
+}
+
+/// A zip attachment named after the collection
+fn zip_response(name: &str, body: Body) -> Response {
+    let filename: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || " -_".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/zip")
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}.zip\"", filename.trim()),
+        )
+        .body(body)
+        .expect("sanitized headers cannot fail")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(id: &str, status: DownloadStatus, age_secs: i64) -> DownloadJob {
+        DownloadJob {
+            id: id.to_string(),
+            collection_id: "shelf".to_string(),
+            status,
+            books: 1,
+            total_bytes: 10,
+            written_bytes: 10,
+            error: None,
+            created_at: Utc::now() - Duration::seconds(age_secs),
+            path: std::env::temp_dir().join(format!("los-libros-bundle-test-{}.zip", id)),
+            contents: 1,
+        }
+    }
+
+    #[test]
+    fn test_download_jobs_prune_and_reuse() {
+        let jobs = DownloadJobs::default();
+        jobs.insert(job("old", DownloadStatus::Ready, JOB_TTL_SECS + 1));
+        jobs.insert(job("slow", DownloadStatus::Preparing, JOB_TTL_SECS + 1));
+        jobs.insert(job("failed", DownloadStatus::Failed, 0));
+        std::fs::write(&jobs.get("old").unwrap().path, b"zip").unwrap();
+
+        jobs.prune();
+        assert!(jobs.get("old").is_none());
+        assert!(!std::env::temp_dir()
+            .join("los-libros-bundle-test-old.zip")
+            .exists());
+        // Jobs still writing are kept however old they are
+        assert!(jobs.get("slow").is_some());
+
+        // Failed jobs are not reused
+        assert_eq!(jobs.find("shelf", 1).unwrap().id, "slow");
+        assert!(jobs.find("other", 1).is_none());
+        // Nor are bundles of what the collection used to hold
+        assert!(jobs.find("shelf", 2).is_none());
+    }
+}
@@ -6,9 +6,18 @@
 //! - Get document metadata and TOC
 //! - Render items (pages/chapters)
 //! - Get structured text with positions
-//! - Search content with bounding boxes
+//! - Export plain text of a whole document (streamed) or of one item
+//! - Print-ready HTML of one item, with running headers and page labels
+//! - Search content with bounding boxes, optionally with each hit's paragraph
+//! - Download a prebuilt search index for the WASM reader (EPUB)
+//! - Check a document's web links for dead ones, as a background job
+//! - Snap highlight selections to whole words before they are stored (EPUB)
+//! - Open/close signals that pin a document and prewarm its first pages
 //! - Get embedded resources (CSS, images, fonts, XHTML chapters)
 //!
+//! Renders, thumbnails, embedded images and prewarming follow the client's
+//! bandwidth profile (see `routes/bandwidth.rs`) unless told otherwise.
+//!
 //! This is the unified API that replaces separate `/books` and `/pdf` endpoints.
 //! It uses the `DocumentParser` and `DocumentRenderer` traits for format-agnostic
 //! operations.
@@ -17,7 +26,7 @@
 //!
 //! For EPUBs, the resources endpoint supports accessing raw XHTML chapter content:
 //!
-//! ```
+//! ```text
 //! GET /api/v1/documents/:id/resources/OEBPS/Text/chapter1.xhtml
 //! ```
 //!
@@ -34,22 +43,42 @@
 use axum::{
     body::Body,
     extract::{DefaultBodyLimit, Multipart, Path, Query, State},
-    http::{header, StatusCode},
-    response::Response,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
     routing::{delete, get, post},
     Json, Router,
 };
+use chrono::{DateTime, Utc};
+use epub_core::{BookIndex, Glossary, GlossaryEntry, IndexEntry, IndexMatch, LinkGraph};
+use futures::{stream, StreamExt};
+use search_core::{IndexOptions, TextFolding, TextSelection};
 use serde::{Deserialize, Serialize};
+use std::collections::{hash_map::Entry, HashMap};
 use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
 
+use crate::annotations::Selector;
+use crate::config::IsolationMode;
+use crate::db::{ProgressLocator, ProgressRepository, ReadingProgress};
 use crate::document::{
-    DocumentFormat, DocumentParser, DocumentRenderer, ImageFormat, ParsedDocument, RenderRequest,
-    SearchOptions, StructuredText, TocEntry,
+    block_text, item_chunk, normalize_text, occurrence_blocks, DocumentError, DocumentFonts,
+    DocumentFormat, DocumentParser, DocumentRenderer, DocumentResult, DocumentWarning, ImageFormat,
+    ParsedDocument, PrewarmPlan, RenderRequest, SearchOptions, StructuredText, TocEntry,
+    WhitespaceMode, DEFAULT_PREWARM_ITEMS,
 };
+use crate::error::ApiError;
 use crate::formats::epub::EpubDocumentHandler;
+use crate::formats::isolation;
 use crate::formats::pdf::PdfDocumentHandler;
+use crate::html::{print_chapter, print_page, PrintLabels};
+use crate::link_check::{LinkCheckOptions, LinkChecker, LinkHealth, LinkReport};
+use crate::pagination::{compare_text, contains_ignore_case, PageInfo, PageParams, SortOrder};
 use crate::state::AppState;
 
+use super::bandwidth::{shrink_image, ProfileQuery, ProfileSettings, PROFILE_VARY};
+use super::fields::FieldSelection;
+use super::limits::{multipart_error, spool_field, DOCUMENT_UPLOAD_LIMIT};
+
 // ============================================================================
 // Input Validation Constants
 // ============================================================================
@@ -58,25 +87,38 @@ use crate::state::AppState;
 const MAX_SCALE: f32 = 4.0;
 /// Minimum scale factor for rendering
 const MIN_SCALE: f32 = 0.1;
+
+/// Maximum leading items a client can ask to prewarm
+const MAX_PREWARM_ITEMS: usize = 20;
 /// Valid rotation values in degrees
 const VALID_ROTATIONS: &[u16] = &[0, 90, 180, 270];
 /// Maximum search results to prevent memory exhaustion
 const MAX_SEARCH_LIMIT: usize = 1000;
 /// Maximum context length in characters
 const MAX_CONTEXT_LENGTH: usize = 500;
+/// Maximum index entries per lookup
+const MAX_INDEX_MATCHES: usize = 200;
 /// Maximum thumbnail dimension
 const MAX_THUMBNAIL_SIZE: u32 = 2048;
+/// Maximum document IDs per batch metadata request
+const MAX_BATCH_SIZE: usize = 200;
+
+/// Response header with the MuPDF warnings of a render (a JSON array of
+/// `{message, count}`), sent only when there were any. Renders served from
+/// the render cache carry none.
+pub const RENDER_WARNINGS_HEADER: &str = "x-render-warnings";
 
 /// Response for document list
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct DocumentListResponse {
     pub documents: Vec<DocumentSummary>,
-    pub total: usize,
+    #[serde(flatten)]
+    pub page: PageInfo,
 }
 
 /// Summary of a document for list view
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct DocumentSummary {
     pub id: String,
@@ -84,10 +126,175 @@ pub struct DocumentSummary {
     pub title: String,
     pub author: Option<String>,
     pub item_count: usize,
+    pub added_at: DateTime<Utc>,
+}
+
+/// Sort keys for the document list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DocumentSortKey {
+    Title,
+    Author,
+    Added,
+}
+
+/// Sorting and filter parameters for the document list
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DocumentListQuery {
+    /// Sort key (default: title)
+    #[param(inline)]
+    pub sort: Option<DocumentSortKey>,
+    /// Sort direction (default: asc, or desc when sorting by `added`)
+    #[param(inline)]
+    pub order: Option<SortOrder>,
+    /// Only include documents of this format
+    #[param(value_type = Option<String>, example = "epub")]
+    pub format: Option<DocumentFormat>,
+    /// Case-insensitive match against any creator name
+    pub author: Option<String>,
+    /// Case-insensitive match against the title
+    pub q: Option<String>,
+}
+
+impl DocumentListQuery {
+    /// Whether a cached document passes the format/author/title filters
+    fn matches(&self, entry: &CachedDocument) -> bool {
+        let metadata = &entry.metadata.metadata;
+        if let Some(format) = self.format {
+            if entry.metadata.format != format {
+                return false;
+            }
+        }
+        if let Some(author) = &self.author {
+            if !metadata
+                .creators
+                .iter()
+                .any(|c| contains_ignore_case(&c.name, author))
+            {
+                return false;
+            }
+        }
+        if let Some(q) = &self.q {
+            if !contains_ignore_case(&metadata.title, q) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Batch metadata request
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BatchDocumentsRequest {
+    /// Document IDs to look up (max 200, duplicates ignored)
+    pub ids: Vec<String>,
+}
+
+/// A proposed highlight selection in an EPUB chapter
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct NormalizeSelectionRequest {
+    /// Chapter href as listed in the spine, or a virtual chunk href
+    pub href: String,
+    /// Start offset (UTF-16 code units into the chapter's plain text)
+    pub start: usize,
+    /// End offset (exclusive)
+    pub end: usize,
+}
+
+/// A selection snapped to whole words, with selectors ready to store
+#[derive(Debug, Serialize, ToSchema)]
+pub struct NormalizedSelectionResponse {
+    pub href: String,
+    /// Start offset (inclusive)
+    pub start: usize,
+    /// End offset (exclusive)
+    pub end: usize,
+    /// Selected text
+    pub exact: String,
+    /// Up to 32 characters of context before `exact`
+    pub prefix: String,
+    /// Up to 32 characters of context after `exact`
+    pub suffix: String,
+    /// TextQuote and TextPosition selectors for the annotation target
+    pub selectors: Vec<Selector>,
+}
+
+impl NormalizedSelectionResponse {
+    fn new(href: String, selection: TextSelection) -> Self {
+        let selectors = vec![
+            Selector::TextQuote {
+                exact: selection.exact.clone(),
+                prefix: Some(selection.prefix.clone()).filter(|p| !p.is_empty()),
+                suffix: Some(selection.suffix.clone()).filter(|s| !s.is_empty()),
+            },
+            Selector::TextPosition {
+                start: selection.start,
+                end: selection.end,
+            },
+        ];
+        Self {
+            href,
+            start: selection.start,
+            end: selection.end,
+            exact: selection.exact,
+            prefix: selection.prefix,
+            suffix: selection.suffix,
+            selectors,
+        }
+    }
+}
+
+/// Batch metadata response
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchDocumentsResponse {
+    /// Found documents, in request order
+    pub documents: Vec<BatchDocumentEntry>,
+    /// Requested IDs that are not loaded
+    pub missing: Vec<String>,
+}
+
+/// Document summary with cover and reading progress merged in
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchDocumentEntry {
+    #[serde(flatten)]
+    pub summary: DocumentSummary,
+    /// URL of the cover image (EPUB cover resource or PDF first-page thumbnail)
+    pub cover_url: Option<String>,
+    /// Latest reading progress, if the book has been opened
+    pub progress: Option<ProgressSummary>,
+}
+
+/// Reading progress as shown on a shelf
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressSummary {
+    pub percent: f64,
+    pub locator: ProgressLocator,
+    /// Legacy EPUB location (empty for non-CFI locators)
+    pub cfi: String,
+    pub page: Option<i32>,
+    pub total_pages: Option<i32>,
+    pub last_read: String,
+}
+
+impl From<ReadingProgress> for ProgressSummary {
+    fn from(progress: ReadingProgress) -> Self {
+        Self {
+            percent: progress.percent,
+            locator: progress.locator.0,
+            cfi: progress.cfi,
+            page: progress.page,
+            total_pages: progress.total_pages,
+            last_read: progress.last_read,
+        }
+    }
 }
 
 /// Full document details response
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct DocumentDetailResponse {
     pub id: String,
@@ -100,70 +307,353 @@ pub struct DocumentDetailResponse {
     pub toc: Vec<TocEntry>,
     pub item_count: usize,
     pub has_text_layer: bool,
+    /// Warnings MuPDF reported while opening and parsing the document, such
+    /// as broken fonts that make text render as boxes
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub diagnostics: Vec<DocumentWarning>,
 }
 
 /// Creator info response
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct CreatorResponse {
     pub name: String,
     pub role: Option<String>,
 }
 
-/// Upload response
-#[derive(Serialize)]
+/// Content checksum of an EPUB spine item
+#[derive(Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
-pub struct UploadResponse {
+pub struct ChapterChecksumResponse {
+    pub spine_index: usize,
+    pub href: String,
+    /// Hex SHA-1 of the item's bytes
+    pub checksum: String,
+}
+
+/// Links between an EPUB's spine items
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkGraphResponse {
+    /// Adjacency of every spine item, in spine order
+    pub chapters: Vec<ChapterLinksResponse>,
+    /// Every link, by source then document order
+    pub links: Vec<CrossReferenceResponse>,
+}
+
+/// Spine items a spine item links to and is linked from
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChapterLinksResponse {
+    pub spine_index: usize,
+    pub href: String,
+    /// Spine indexes of other items this one links to
+    pub links_to: Vec<usize>,
+    /// Spine indexes of other items linking to this one
+    pub linked_from: Vec<usize>,
+}
+
+/// A link from one spine item to another
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CrossReferenceResponse {
+    /// Spine index of the linking item
+    pub source: usize,
+    /// Spine index of the linked item
+    pub target: usize,
+    /// Element id the link points at
+    pub fragment: Option<String>,
+    /// Link text
+    pub text: String,
+    /// `epub:type` of the link, such as `noteref`
+    pub epub_type: Option<String>,
+}
+
+impl From<LinkGraph> for LinkGraphResponse {
+    fn from(graph: LinkGraph) -> Self {
+        Self {
+            chapters: graph
+                .chapters
+                .into_iter()
+                .map(|chapter| ChapterLinksResponse {
+                    spine_index: chapter.spine_index,
+                    href: chapter.href,
+                    links_to: chapter.links_to,
+                    linked_from: chapter.linked_from,
+                })
+                .collect(),
+            links: graph
+                .links
+                .into_iter()
+                .map(|link| CrossReferenceResponse {
+                    source: link.source,
+                    target: link.target,
+                    fragment: link.fragment,
+                    text: link.text,
+                    epub_type: link.epub_type,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Progress of a link check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkCheckStatus {
+    Running,
+    Completed,
+    Failed,
+}
+
+/// A check of a document's web links
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkCheckJob {
     pub id: String,
-    pub format: String,
-    pub title: String,
-    pub item_count: usize,
-    pub message: String,
+    pub document_id: String,
+    pub status: LinkCheckStatus,
+    /// Links found in the document
+    pub links: usize,
+    /// Distinct URLs among them, each requested once
+    pub urls: usize,
+    /// URLs checked so far
+    pub checked: usize,
+    /// URLs that are broken or unreachable, with every link to them;
+    /// filled in when the check completes
+    pub dead: Vec<LinkReport>,
+    /// URLs whose servers would not say (401, 403, 429)
+    pub unverified: usize,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// A back-of-book index
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BookIndexResponse {
+    /// Spine indexes of the items holding the index
+    pub documents: Vec<usize>,
+    /// Top-level entries, in index order
+    pub entries: Vec<IndexEntryResponse>,
+}
+
+/// A term of a back-of-book index
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexEntryResponse {
+    pub term: String,
+    /// Where the term is discussed
+    pub locators: Vec<IndexLocatorResponse>,
+    /// Terms to look up instead ("see")
+    pub see: Vec<String>,
+    /// Terms to look up as well ("see also")
+    pub see_also: Vec<String>,
+    pub subentries: Vec<IndexEntryResponse>,
+}
+
+/// A place an index entry points to
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexLocatorResponse {
+    pub spine_index: usize,
+    pub href: String,
+    /// Element id the locator points at
+    pub fragment: Option<String>,
+    /// Locator text, usually a page number
+    pub label: String,
+}
+
+/// Index entries matching a lookup
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexLookupResponse {
+    pub query: String,
+    /// Exact matches first, then terms starting with the query
+    pub matches: Vec<IndexMatchResponse>,
+}
+
+/// An index entry found by a lookup
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexMatchResponse {
+    /// Terms from the top-level entry down to the match
+    pub terms: Vec<String>,
+    pub entry: IndexEntryResponse,
+}
+
+impl From<IndexEntry> for IndexEntryResponse {
+    fn from(entry: IndexEntry) -> Self {
+        Self {
+            term: entry.term,
+            locators: entry
+                .locators
+                .into_iter()
+                .map(|locator| IndexLocatorResponse {
+                    spine_index: locator.spine_index,
+                    href: locator.href,
+                    fragment: locator.fragment,
+                    label: locator.label,
+                })
+                .collect(),
+            see: entry.see,
+            see_also: entry.see_also,
+            subentries: entry.subentries.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<BookIndex> for BookIndexResponse {
+    fn from(index: BookIndex) -> Self {
+        Self {
+            documents: index.documents,
+            entries: index.entries.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<IndexMatch> for IndexMatchResponse {
+    fn from(found: IndexMatch) -> Self {
+        Self {
+            terms: found.terms,
+            entry: found.entry.into(),
+        }
+    }
+}
+
+/// Glossary of a document
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GlossaryResponse {
+    /// Spine indexes of the items holding a glossary
+    pub documents: Vec<usize>,
+    /// Entries in document order
+    pub entries: Vec<GlossaryEntryResponse>,
 }
 
-/// Error response
-#[derive(Serialize)]
-pub struct ErrorResponse {
-    pub error: String,
-    pub details: Option<String>,
+/// A glossary term and its definitions
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GlossaryEntryResponse {
+    /// The term and any variants listed with it
+    pub terms: Vec<String>,
+    /// Definitions as plain text
+    pub definitions: Vec<String>,
+    /// Spine item holding the glossary
+    pub spine_index: usize,
+    pub href: String,
+    /// Element id of the term in the glossary
+    pub id: Option<String>,
 }
 
-impl ErrorResponse {
-    fn new(error: impl Into<String>) -> Self {
+impl From<GlossaryEntry> for GlossaryEntryResponse {
+    fn from(entry: GlossaryEntry) -> Self {
         Self {
-            error: error.into(),
-            details: None,
+            terms: entry.terms,
+            definitions: entry.definitions,
+            spine_index: entry.spine_index,
+            href: entry.href,
+            id: entry.id,
         }
     }
+}
 
-    fn with_details(error: impl Into<String>, details: impl Into<String>) -> Self {
+impl From<Glossary> for GlossaryResponse {
+    fn from(glossary: Glossary) -> Self {
         Self {
-            error: error.into(),
-            details: Some(details.into()),
+            documents: glossary.documents,
+            entries: glossary.entries.into_iter().map(Into::into).collect(),
         }
     }
 }
 
+/// Upload response
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadResponse {
+    pub id: String,
+    pub format: String,
+    pub title: String,
+    pub item_count: usize,
+    pub message: String,
+}
+
+/// Multipart form for document upload
+#[derive(ToSchema)]
+pub struct DocumentUploadForm {
+    /// PDF or EPUB file (field name `file` or `document`)
+    #[schema(value_type = String, format = Binary)]
+    pub file: Vec<u8>,
+}
+
 /// Query parameters for item rendering
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct RenderQuery {
-    /// Scale factor (default: 1.5)
-    #[serde(default = "default_scale")]
-    pub scale: f32,
+    /// Scale factor (default: from the bandwidth profile, 1.5 for medium)
+    pub scale: Option<f32>,
     /// Rotation in degrees (0, 90, 180, 270)
     #[serde(default)]
     pub rotation: u16,
-    /// Output format (png, jpeg, webp)
+    /// Output format (png, jpeg, webp; default: from the bandwidth profile)
     #[serde(default)]
     pub format: String,
 }
 
-fn default_scale() -> f32 {
-    1.5
+/// Query parameters for plain-text export
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PlainTextQuery {
+    /// Whitespace normalization (default: lines)
+    #[serde(default)]
+    #[param(inline)]
+    pub whitespace: WhitespaceMode,
+    /// Replace curly quotes, dashes and ellipses with ASCII punctuation
+    #[serde(default)]
+    pub fold_punctuation: bool,
+    /// Split typographic ligatures (`ﬁ`, `ﬂ`, ...) into their letters
+    #[serde(default)]
+    pub fold_ligatures: bool,
+}
+
+impl PlainTextQuery {
+    fn folding(&self) -> TextFolding {
+        TextFolding {
+            punctuation: self.fold_punctuation,
+            ligatures: self.fold_ligatures,
+        }
+    }
+}
+
+/// What a downloaded search index covers besides body text
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchIndexQuery {
+    /// Index image alt text
+    #[serde(default)]
+    pub alt_text: bool,
+    /// Index figure captions
+    #[serde(default)]
+    pub captions: bool,
+    /// Index footnotes and endnotes
+    #[serde(default)]
+    pub footnotes: bool,
+}
+
+impl From<SearchIndexQuery> for IndexOptions {
+    fn from(query: SearchIndexQuery) -> Self {
+        IndexOptions {
+            alt_text: query.alt_text,
+            captions: query.captions,
+            footnotes: query.footnotes,
+        }
+    }
 }
 
 /// Query parameters for search
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct SearchQuery {
     /// Search query
     pub q: String,
@@ -182,12 +672,49 @@ pub struct SearchQuery {
     /// Whole word matching
     #[serde(default)]
     pub whole_word: bool,
+    /// `context` for prefix/suffix around each hit, or `paragraph` to also
+    /// return the enclosing text block
+    #[serde(default)]
+    #[param(inline)]
+    pub mode: SearchMode,
+}
+
+/// What a search returns around each hit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    #[default]
+    Context,
+    Paragraph,
 }
 
 fn default_limit() -> usize {
     100
 }
 
+/// Query parameters for an index lookup
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct IndexLookupQuery {
+    /// Term or start of a term, case-insensitive
+    pub q: String,
+    /// Maximum entries (default: 20)
+    #[serde(default = "default_index_limit")]
+    pub limit: usize,
+}
+
+fn default_index_limit() -> usize {
+    20
+}
+
+/// Query parameters for a glossary definition
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DefineTermQuery {
+    /// Term to define, case-insensitive
+    pub term: String,
+}
+
 fn default_include_context() -> bool {
     true
 }
@@ -197,19 +724,36 @@ fn default_context_length() -> usize {
 }
 
 /// Query parameters for thumbnail
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct ThumbnailQuery {
-    /// Maximum dimension (default: 200)
-    #[serde(default = "default_thumbnail_size")]
-    pub size: u32,
+    /// Maximum dimension (default: from the bandwidth profile, 200 for medium)
+    pub size: Option<u32>,
+}
+
+/// Query parameters for print views
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PrintQuery {
+    /// Render scale of PDF pages (default: 4, about 288 DPI)
+    pub scale: Option<f32>,
 }
 
-fn default_thumbnail_size() -> u32 {
-    200
+/// Query parameters for embedded resources
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ResourceQuery {
+    /// Add deterministic `data-anchor` attributes to (X)HTML chapters
+    #[serde(default)]
+    pub anchors: bool,
+    /// Add ARIA roles derived from `epub:type` and label sections with their
+    /// headings
+    #[serde(default)]
+    pub aria: bool,
 }
 
 /// Search result response
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SearchResultResponse {
     pub results: Vec<SearchHit>,
@@ -218,7 +762,7 @@ pub struct SearchResultResponse {
 }
 
 /// Individual search hit
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SearchHit {
     pub item_index: usize,
@@ -226,10 +770,26 @@ pub struct SearchHit {
     pub prefix: Option<String>,
     pub suffix: Option<String>,
     pub bounds: Vec<BoundingBoxResponse>,
+    /// Enclosing text block, with `mode=paragraph`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub paragraph: Option<SearchParagraph>,
+}
+
+/// The text block (paragraph, heading, ...) a search hit is in
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchParagraph {
+    /// Index of the block in the item's structured text
+    pub block_index: usize,
+    /// Block text, with wrapped lines joined
+    pub text: String,
+    pub bounds: BoundingBoxResponse,
+    /// Where the hit is: a page for PDFs, a progression for EPUBs
+    pub locator: ProgressLocator,
 }
 
 /// Bounding box for search results
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct BoundingBoxResponse {
     pub x: f32,
     pub y: f32,
@@ -237,12 +797,87 @@ pub struct BoundingBoxResponse {
     pub height: f32,
 }
 
+/// Open signal with optional prewarm settings
+#[derive(Debug, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenDocumentRequest {
+    /// Leading items to render ahead of time (default 3, max 20)
+    pub prewarm_items: Option<usize>,
+    /// Item the reader will resume at (default: from saved progress)
+    pub resume_item: Option<usize>,
+    /// Render scale the client will request (default: from the bandwidth profile)
+    pub scale: Option<f32>,
+    /// Render format the client will request (png, jpeg, webp; default: from
+    /// the bandwidth profile)
+    pub format: Option<String>,
+}
+
+/// Result of an open signal
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenDocumentResponse {
+    pub id: String,
+    /// Open sessions for the document, including this one
+    pub open_sessions: usize,
+    /// Resume position used for prewarming
+    pub resume_item: Option<usize>,
+    /// Items being rendered in the background
+    pub prewarm_items: Vec<usize>,
+    /// Items whose text layer is being extracted in the background
+    pub prewarm_text_items: Vec<usize>,
+}
+
+/// Result of a close signal
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CloseDocumentResponse {
+    pub id: String,
+    /// Sessions still open
+    pub open_sessions: usize,
+    /// Whether the document was unpinned and its cached output dropped
+    pub released: bool,
+}
+
 /// Cached document entry containing all related data
 /// Using a single struct prevents race conditions between separate maps
 struct CachedDocument {
     parser: Arc<dyn DocumentParser>,
     renderer: Arc<dyn DocumentRenderer>,
     metadata: ParsedDocument,
+    added_at: DateTime<Utc>,
+}
+
+impl CachedDocument {
+    /// Summary for list and batch views
+    fn summary(&self) -> DocumentSummary {
+        DocumentSummary {
+            id: self.metadata.id.clone(),
+            format: format!("{:?}", self.metadata.format).to_lowercase(),
+            title: self.metadata.metadata.title.clone(),
+            author: self
+                .metadata
+                .metadata
+                .creators
+                .first()
+                .map(|c| c.name.clone()),
+            item_count: self.metadata.item_count,
+            added_at: self.added_at,
+        }
+    }
+
+    /// Cover image URL: the EPUB cover resource, or the first page thumbnail for PDFs
+    fn cover_url(&self) -> Option<String> {
+        let id = urlencoding::encode(&self.metadata.id);
+        match self.metadata.format {
+            DocumentFormat::Epub => self
+                .metadata
+                .metadata
+                .cover_href
+                .as_ref()
+                .map(|href| format!("/api/v1/documents/{}/resources/{}", id, href)),
+            DocumentFormat::Pdf => Some(format!("/api/v1/documents/{}/items/0/thumbnail", id)),
+        }
+    }
 }
 
 /// In-memory document store (temporary until we integrate with the unified cache)
@@ -279,6 +914,7 @@ impl DocumentStore {
                 parser,
                 renderer,
                 metadata,
+                added_at: Utc::now(),
             },
         );
     }
@@ -292,61 +928,212 @@ impl DocumentStore {
 static DOCUMENT_STORE: std::sync::LazyLock<DocumentStore> =
     std::sync::LazyLock::new(DocumentStore::new);
 
+/// How long a finished link check is kept
+const LINK_CHECK_TTL_SECS: i64 = 60 * 60;
+
+/// Link checks by job ID
+#[derive(Default)]
+struct LinkCheckJobs(parking_lot::Mutex<std::collections::HashMap<String, LinkCheckJob>>);
+
+impl LinkCheckJobs {
+    fn get(&self, id: &str) -> Option<LinkCheckJob> {
+        self.0.lock().get(id).cloned()
+    }
+
+    /// A document's check that is still running
+    fn running(&self, document_id: &str) -> Option<LinkCheckJob> {
+        self.0
+            .lock()
+            .values()
+            .find(|job| job.document_id == document_id && job.status == LinkCheckStatus::Running)
+            .cloned()
+    }
+
+    fn insert(&self, job: LinkCheckJob) {
+        self.0.lock().insert(job.id.clone(), job);
+    }
+
+    fn update(&self, id: &str, f: impl FnOnce(&mut LinkCheckJob)) {
+        if let Some(job) = self.0.lock().get_mut(id) {
+            f(job);
+        }
+    }
+
+    /// Forget checks that finished more than [`LINK_CHECK_TTL_SECS`] ago
+    fn prune(&self) {
+        let cutoff = Utc::now() - chrono::Duration::seconds(LINK_CHECK_TTL_SECS);
+        self.0
+            .lock()
+            .retain(|_, job| job.finished_at.is_none_or(|finished| finished > cutoff));
+    }
+}
+
+static LINK_CHECKS: std::sync::LazyLock<LinkCheckJobs> =
+    std::sync::LazyLock::new(LinkCheckJobs::default);
+
 /// Create the documents router
 pub fn router() -> Router<AppState> {
     Router::new()
-        .route("/", get(list_documents).post(upload_document))
+        // Only uploads take large bodies
+        .route(
+            "/",
+            get(list_documents)
+                .post(upload_document)
+                .layer(DefaultBodyLimit::max(DOCUMENT_UPLOAD_LIMIT)),
+        )
+        .route("/batch", post(batch_documents))
         .route("/:id", get(get_document).delete(delete_document))
         .route("/:id/items/:index/render", get(render_item))
+        .route("/:id/text", get(get_document_text))
         .route("/:id/items/:index/text", get(get_structured_text))
+        .route("/:id/items/:index/plain-text", get(get_item_text))
         .route("/:id/items/:index/thumbnail", get(render_thumbnail))
+        .route("/:id/items/:index/print", get(print_item))
         .route("/:id/search", get(search_document))
+        .route("/:id/search-index", get(get_search_index))
+        .route("/:id/checksums", get(get_chapter_checksums))
+        .route("/:id/links", get(get_link_graph))
+        .route("/:id/link-check", post(start_link_check))
+        .route("/link-checks/:job_id", get(get_link_check))
+        .route("/:id/book-index", get(get_book_index))
+        .route("/:id/book-index/lookup", get(lookup_book_index))
+        .route("/:id/glossary", get(get_glossary))
+        .route("/:id/glossary/define", get(define_term))
+        .route("/:id/fonts", get(get_document_fonts))
+        .route("/:id/selections/normalize", post(normalize_selection))
+        .route("/:id/open", post(open_document))
+        .route("/:id/close", post(close_document))
         .route("/:id/resources/*href", get(get_resource))
-        // Allow up to 200MB uploads for large documents
-        .layer(DefaultBodyLimit::max(200 * 1024 * 1024))
 }
 
-/// List all cached documents
-async fn list_documents(State(_state): State<AppState>) -> Json<DocumentListResponse> {
+/// List cached documents with sorting, filtering, and pagination
+#[utoipa::path(
+    get,
+    path = "/api/v1/documents",
+    tag = "documents",
+    params(PageParams, DocumentListQuery),
+    responses((status = 200, description = "Page of cached documents", body = DocumentListResponse))
+)]
+async fn list_documents(
+    State(_state): State<AppState>,
+    Query(page): Query<PageParams>,
+    Query(query): Query<DocumentListQuery>,
+) -> Json<DocumentListResponse> {
     let entries = DOCUMENT_STORE.entries.read().await;
 
-    let summaries: Vec<DocumentSummary> = entries
+    let mut matching: Vec<&CachedDocument> = entries
         .values()
-        .map(|entry| DocumentSummary {
-            id: entry.metadata.id.clone(),
-            format: format!("{:?}", entry.metadata.format).to_lowercase(),
-            title: entry.metadata.metadata.title.clone(),
-            author: entry.metadata.metadata.creators.first().map(|c| c.name.clone()),
-            item_count: entry.metadata.item_count,
-        })
+        .filter(|entry| query.matches(entry))
         .collect();
 
-    let total = summaries.len();
+    let key = query.sort.unwrap_or(DocumentSortKey::Title);
+    let order = query.order.unwrap_or(match key {
+        DocumentSortKey::Added => SortOrder::Desc,
+        _ => SortOrder::Asc,
+    });
+    matching.sort_by(|a, b| match key {
+        DocumentSortKey::Title => compare_text(
+            Some(a.metadata.metadata.title.as_str()),
+            Some(b.metadata.metadata.title.as_str()),
+            order,
+        ),
+        DocumentSortKey::Author => compare_text(
+            a.metadata
+                .metadata
+                .creators
+                .first()
+                .map(|c| c.name.as_str()),
+            b.metadata
+                .metadata
+                .creators
+                .first()
+                .map(|c| c.name.as_str()),
+            order,
+        ),
+        DocumentSortKey::Added => order.apply(a.added_at.cmp(&b.added_at)),
+    });
+
+    let (matching, page) = page.apply(matching);
+    let documents = matching.into_iter().map(CachedDocument::summary).collect();
+
+    Json(DocumentListResponse { documents, page })
+}
 
-    Json(DocumentListResponse {
-        documents: summaries,
-        total,
-    })
+/// Get summaries, covers, and progress for several documents at once
+#[utoipa::path(
+    post,
+    path = "/api/v1/documents/batch",
+    tag = "documents",
+    request_body = BatchDocumentsRequest,
+    responses(
+        (status = 200, description = "Summaries for the loaded documents", body = BatchDocumentsResponse),
+        (status = 400, description = "Too many IDs", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn batch_documents(
+    State(state): State<AppState>,
+    Json(request): Json<BatchDocumentsRequest>,
+) -> Result<Json<BatchDocumentsResponse>, ApiError> {
+    let mut ids = request.ids;
+    let mut seen = std::collections::HashSet::new();
+    ids.retain(|id| seen.insert(id.clone()));
+
+    if ids.len() > MAX_BATCH_SIZE {
+        return Err(ApiError::bad_request(format!(
+            "Too many document IDs: {} (max {})",
+            ids.len(),
+            MAX_BATCH_SIZE
+        )));
+    }
+
+    let mut progress = ProgressRepository::new(state.db())
+        .get_many(&ids, None)
+        .await?;
+
+    let entries = DOCUMENT_STORE.entries.read().await;
+    let mut documents = Vec::with_capacity(ids.len());
+    let mut missing = Vec::new();
+
+    for id in ids {
+        match entries.get(&id) {
+            Some(entry) => documents.push(BatchDocumentEntry {
+                summary: entry.summary(),
+                cover_url: entry.cover_url(),
+                progress: progress.remove(&id).map(ProgressSummary::from),
+            }),
+            None => missing.push(id),
+        }
+    }
+
+    Ok(Json(BatchDocumentsResponse { documents, missing }))
 }
 
 /// Upload a new document (PDF or EPUB)
+#[utoipa::path(
+    post,
+    path = "/api/v1/documents",
+    tag = "documents",
+    request_body(content = DocumentUploadForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Document parsed and cached", body = UploadResponse),
+        (status = 400, description = "Missing file, unsupported format, or rejected by isolated parsing", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "Document ID already exists", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 413, description = "File larger than the upload limit; use the chunked upload API", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 422, description = "EPUB exceeds a layout limit (time, pages, or chapter size)", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
 async fn upload_document(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
     mut multipart: Multipart,
-) -> Result<Json<UploadResponse>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<UploadResponse>, ApiError> {
     tracing::debug!("Starting document upload processing");
 
     // Extract the file from multipart
-    while let Some(field) = multipart.next_field().await.map_err(|e| {
-        tracing::error!("Failed to read multipart field: {}", e);
-        (
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse::with_details(
-                "Failed to read upload",
-                e.to_string(),
-            )),
-        )
-    })? {
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| multipart_error("Failed to read upload", e))?
+    {
         let name = field.name().unwrap_or("").to_string();
         let filename = field.file_name().map(|s| s.to_string());
         let content_type = field.content_type().map(|s| s.to_string());
@@ -364,27 +1151,15 @@ async fn upload_document(
                 .map(|s| s.to_string())
                 .unwrap_or_else(|| "unknown".to_string());
 
-            // Read file bytes
-            let data = field.bytes().await.map_err(|e| {
-                tracing::error!("Failed to read file data: {}", e);
-                (
-                    StatusCode::BAD_REQUEST,
-                    Json(ErrorResponse::with_details(
-                        "Failed to read file data",
-                        e.to_string(),
-                    )),
-                )
-            })?;
+            let upload = spool_field(field, DOCUMENT_UPLOAD_LIMIT).await?;
 
-            tracing::debug!("Read {} bytes of file data", data.len());
+            tracing::debug!("Spooled {} bytes of file data", upload.len());
 
             // Detect format from magic bytes
-            let format = DocumentFormat::from_magic_bytes(&data).ok_or_else(|| {
-                (
-                    StatusCode::BAD_REQUEST,
-                    Json(ErrorResponse::new(
-                        "Unsupported document format. Only PDF and EPUB are supported.",
-                    )),
+            let head = upload.head(64).await?;
+            let format = DocumentFormat::from_magic_bytes(&head).ok_or_else(|| {
+                ApiError::bad_request(
+                    "Unsupported document format. Only PDF and EPUB are supported.",
                 )
             })?;
 
@@ -400,15 +1175,17 @@ async fn upload_document(
 
             // Check if document ID already exists (prevent silent overwrites)
             if DOCUMENT_STORE.contains(&doc_id).await {
-                return Err((
-                    StatusCode::CONFLICT,
-                    Json(ErrorResponse::new(format!(
-                        "Document with ID '{}' already exists. Use DELETE first to replace.",
-                        doc_id
-                    ))),
-                ));
+                return Err(ApiError::conflict(format!(
+                    "Document with ID '{}' already exists. Use DELETE first to replace.",
+                    doc_id
+                )));
             }
 
+            let data = upload.read().await?;
+            drop(upload);
+
+            check_isolated(&state, &data, &doc_id).await?;
+
             // Parse the document based on format
             let (parser, renderer, parsed): (
                 Arc<dyn DocumentParser>,
@@ -416,51 +1193,31 @@ async fn upload_document(
                 ParsedDocument,
             ) = match format {
                 DocumentFormat::Pdf => {
-                    let handler = PdfDocumentHandler::from_bytes(data.to_vec(), doc_id.clone())
-                        .map_err(|e| {
+                    let handler =
+                        PdfDocumentHandler::from_bytes(data, doc_id.clone()).map_err(|e| {
                             tracing::error!("Failed to parse PDF: {}", e);
-                            (
-                                StatusCode::BAD_REQUEST,
-                                Json(ErrorResponse::with_details(
-                                    "Failed to parse PDF",
-                                    e.to_string(),
-                                )),
-                            )
+                            ApiError::bad_request("Failed to parse PDF").with_reason(e.to_string())
                         })?;
                     let handler = Arc::new(handler);
                     let parsed = handler.parse().await.map_err(|e| {
-                        (
-                            StatusCode::BAD_REQUEST,
-                            Json(ErrorResponse::with_details(
-                                "Failed to parse PDF metadata",
-                                e.to_string(),
-                            )),
-                        )
+                        ApiError::bad_request("Failed to parse PDF metadata")
+                            .with_reason(e.to_string())
                     })?;
                     (handler.clone(), handler, parsed)
                 }
                 DocumentFormat::Epub => {
-                    let handler = EpubDocumentHandler::from_bytes(data.to_vec(), doc_id.clone())
-                        .map_err(|e| {
-                            tracing::error!("Failed to parse EPUB: {}", e);
-                            (
-                                StatusCode::BAD_REQUEST,
-                                Json(ErrorResponse::with_details(
-                                    "Failed to parse EPUB",
-                                    e.to_string(),
-                                )),
-                            )
-                        })?;
+                    let handler =
+                        EpubDocumentHandler::load(data, doc_id.clone(), state.config().epub)
+                            .await
+                            .map_err(|e| {
+                                tracing::error!("Failed to parse EPUB: {}", e);
+                                epub_parse_error("Failed to parse EPUB", e)
+                            })?;
                     let handler = Arc::new(handler);
-                    let parsed = handler.parse().await.map_err(|e| {
-                        (
-                            StatusCode::BAD_REQUEST,
-                            Json(ErrorResponse::with_details(
-                                "Failed to parse EPUB metadata",
-                                e.to_string(),
-                            )),
-                        )
-                    })?;
+                    let parsed = handler
+                        .parse()
+                        .await
+                        .map_err(|e| epub_parse_error("Failed to parse EPUB metadata", e))?;
                     (handler.clone(), handler, parsed)
                 }
             };
@@ -493,32 +1250,50 @@ async fn upload_document(
     }
 
     tracing::warn!("No file field found in multipart upload");
-    Err((
-        StatusCode::BAD_REQUEST,
-        Json(ErrorResponse::new(
-            "No file provided. Use field name 'file' or 'document'",
-        )),
+    Err(ApiError::bad_request(
+        "No file provided. Use field name 'file' or 'document'",
     ))
 }
 
+/// Map an EPUB load failure, reporting exceeded layout ceilings as 422
+fn epub_parse_error(detail: &str, error: DocumentError) -> ApiError {
+    match error {
+        DocumentError::LayoutLimit(reason) => {
+            ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, detail)
+                .with_type("layout-limit")
+                .with_reason(reason)
+        }
+        e => ApiError::bad_request(detail).with_reason(e.to_string()),
+    }
+}
+
 /// Get document details by ID
+#[utoipa::path(
+    get,
+    path = "/api/v1/documents/{id}",
+    tag = "documents",
+    params(("id" = String, Path, description = "Document ID"), FieldSelection),
+    responses(
+        (status = 200, description = "Document details (only the selected fields when `fields`/`exclude` is given)", body = DocumentDetailResponse),
+        (status = 400, description = "Unknown field name", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Document not found", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
 async fn get_document(
     State(_state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<Json<DocumentDetailResponse>, (StatusCode, Json<ErrorResponse>)> {
+    Query(selection): Query<FieldSelection>,
+) -> Result<Json<serde_json::Value>, ApiError> {
     tracing::debug!("Looking up document with ID: '{}'", id);
 
     let entries = DOCUMENT_STORE.entries.read().await;
     let entry = entries.get(&id).ok_or_else(|| {
         tracing::warn!("Document '{}' not found", id);
-        (
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new(format!("Document '{}' not found", id))),
-        )
+        ApiError::not_found(format!("Document '{}' not found", id))
     })?;
     let doc = &entry.metadata;
 
-    Ok(Json(DocumentDetailResponse {
+    let detail = DocumentDetailResponse {
         id: doc.id.clone(),
         format: format!("{:?}", doc.format).to_lowercase(),
         title: doc.metadata.title.clone(),
@@ -537,91 +1312,111 @@ async fn get_document(
         toc: doc.toc.clone(),
         item_count: doc.item_count,
         has_text_layer: doc.has_text_layer,
-    }))
+        diagnostics: doc.diagnostics.clone(),
+    };
+
+    Ok(Json(selection.apply(&detail)?))
 }
 
 /// Delete a document
+#[utoipa::path(
+    delete,
+    path = "/api/v1/documents/{id}",
+    tag = "documents",
+    params(("id" = String, Path, description = "Document ID")),
+    responses(
+        (status = 204, description = "Document removed"),
+        (status = 404, description = "Document not found", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
 async fn delete_document(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<StatusCode, ApiError> {
     // Remove atomically - returns false if document didn't exist
     if !DOCUMENT_STORE.remove(&id).await {
-        return Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new(format!("Document '{}' not found", id))),
-        ));
+        return Err(ApiError::not_found(format!("Document '{}' not found", id)));
     }
+    state.document_cache().remove(&id).await;
 
     tracing::info!("Document '{}' deleted", id);
     Ok(StatusCode::NO_CONTENT)
 }
 
 /// Render an item (page for PDF, chapter for EPUB) as an image
+#[utoipa::path(
+    get,
+    path = "/api/v1/documents/{id}/items/{index}/render",
+    tag = "documents",
+    params(("id" = String, Path, description = "Document ID"), ("index" = usize, Path, description = "Item index (0-based page or chapter)"), RenderQuery, ProfileQuery),
+    responses(
+        (status = 200, description = "Rendered image (PNG, JPEG, or WebP)", content_type = "image/png",
+            headers(("x-render-warnings" = String, description = "JSON array of MuPDF warnings ({message, count}), when there were any"))),
+        (status = 400, description = "Invalid rotation or bandwidth profile", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Document or item not found", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
 async fn render_item(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
     Path((id, index)): Path<(String, usize)>,
     Query(query): Query<RenderQuery>,
-) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    Query(profile): Query<ProfileQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let settings = profile.resolve(&headers)?.settings();
+
     // Validate rotation parameter
     if !VALID_ROTATIONS.contains(&query.rotation) {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse::new(
-                "Rotation must be 0, 90, 180, or 270 degrees",
-            )),
+        return Err(ApiError::bad_request(
+            "Rotation must be 0, 90, 180, or 270 degrees",
         ));
     }
 
     // Clamp scale to valid range
-    let scale = query.scale.clamp(MIN_SCALE, MAX_SCALE);
+    let scale = query
+        .scale
+        .unwrap_or(settings.render_scale)
+        .clamp(MIN_SCALE, MAX_SCALE);
 
     // Get entry (contains renderer, parser, and metadata)
     let entries = DOCUMENT_STORE.entries.read().await;
-    let entry = entries.get(&id).ok_or_else(|| {
-        (
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new(format!("Document '{}' not found", id))),
-        )
-    })?;
+    let entry = entries
+        .get(&id)
+        .ok_or_else(|| ApiError::not_found(format!("Document '{}' not found", id)))?;
 
     // Validate item index before expensive rendering
     if index >= entry.metadata.item_count {
-        return Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new(format!(
-                "Item {} not found. Document has {} items (0-{})",
-                index,
-                entry.metadata.item_count,
-                entry.metadata.item_count.saturating_sub(1)
-            ))),
-        ));
+        return Err(ApiError::not_found(format!(
+            "Item {} not found. Document has {} items (0-{})",
+            index,
+            entry.metadata.item_count,
+            entry.metadata.item_count.saturating_sub(1)
+        )));
     }
 
-    // Parse format
-    let format = match query.format.to_lowercase().as_str() {
-        "jpeg" | "jpg" => ImageFormat::Jpeg,
-        "webp" => ImageFormat::Webp,
-        _ => ImageFormat::Png,
-    };
+    let (format, quality) = render_output(&query.format, &settings);
 
     let request = RenderRequest {
         item_index: index,
         scale,
         format,
         rotation: query.rotation,
+        quality,
         ..Default::default()
     };
 
-    let result = entry.renderer.render_item(&request).await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse::with_details(
-                format!("Failed to render item {} of document '{}'", index, id),
-                e.to_string(),
-            )),
-        )
-    })?;
+    // Open documents go through the cache so prewarmed pages are served from it
+    let result = state
+        .document_cache()
+        .render_or(&id, &request, entry.renderer.as_ref())
+        .await
+        .map_err(|e| {
+            ApiError::internal(format!(
+                "Failed to render item {} of document '{}'",
+                index, id
+            ))
+            .with_reason(e.to_string())
+        })?;
 
     // Build response with proper content type
     let content_type = match result.format {
@@ -630,88 +1425,397 @@ async fn render_item(
         ImageFormat::Webp => "image/webp",
     };
 
-    let response = Response::builder()
+    let mut response = Response::builder()
         .status(StatusCode::OK)
         .header(header::CONTENT_TYPE, content_type)
         .header(header::CACHE_CONTROL, "max-age=3600")
+        .header(header::VARY, PROFILE_VARY);
+    if let Some(warnings) = render_warnings_header(&result.warnings) {
+        response = response.header(RENDER_WARNINGS_HEADER, warnings);
+    }
+    let response = response
         .body(Body::from(result.data))
         .expect("hardcoded headers cannot fail");
 
     Ok(response)
 }
 
-/// Get structured text with character positions for an item
-async fn get_structured_text(
-    State(_state): State<AppState>,
+/// The [`RENDER_WARNINGS_HEADER`] value for a render's warnings, if any
+fn render_warnings_header(warnings: &[DocumentWarning]) -> Option<HeaderValue> {
+    if warnings.is_empty() {
+        return None;
+    }
+    let json = serde_json::to_string(warnings).ok()?;
+
+    // Header values must be visible ASCII; escape the rest as JSON does
+    let mut ascii = String::with_capacity(json.len());
+    for c in json.chars() {
+        if c.is_ascii() && !c.is_ascii_control() {
+            ascii.push(c);
+        } else {
+            for unit in c.encode_utf16(&mut [0; 2]) {
+                ascii.push_str(&format!("\\u{:04x}", unit));
+            }
+        }
+    }
+    HeaderValue::from_str(&ascii).ok()
+}
+
+/// Get structured text with character positions for an item
+#[utoipa::path(
+    get,
+    path = "/api/v1/documents/{id}/items/{index}/text",
+    tag = "documents",
+    params(("id" = String, Path, description = "Document ID"), ("index" = usize, Path, description = "Item index (0-based page or chapter)")),
+    responses(
+        (status = 200, description = "Text blocks with character positions", body = StructuredText),
+        (status = 404, description = "Document or item not found", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn get_structured_text(
+    State(state): State<AppState>,
     Path((id, index)): Path<(String, usize)>,
-) -> Result<Json<StructuredText>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<StructuredText>, ApiError> {
     // Get entry
     let entries = DOCUMENT_STORE.entries.read().await;
-    let entry = entries.get(&id).ok_or_else(|| {
-        (
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new(format!("Document '{}' not found", id))),
-        )
-    })?;
+    let entry = entries
+        .get(&id)
+        .ok_or_else(|| ApiError::not_found(format!("Document '{}' not found", id)))?;
 
     // Validate item index before expensive operation
     if index >= entry.metadata.item_count {
-        return Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new(format!(
-                "Item {} not found. Document has {} items (0-{})",
-                index,
-                entry.metadata.item_count,
-                entry.metadata.item_count.saturating_sub(1)
-            ))),
-        ));
+        return Err(ApiError::not_found(format!(
+            "Item {} not found. Document has {} items (0-{})",
+            index,
+            entry.metadata.item_count,
+            entry.metadata.item_count.saturating_sub(1)
+        )));
     }
 
-    let stext = entry.parser.get_structured_text(index).await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse::with_details(
-                format!(
-                    "Failed to get structured text for item {} of document '{}'",
-                    index, id
-                ),
-                e.to_string(),
-            )),
-        )
-    })?;
+    let stext = state
+        .document_cache()
+        .get_structured_text_or(&id, index, entry.parser.as_ref())
+        .await
+        .map_err(|e| {
+            ApiError::internal(format!(
+                "Failed to get structured text for item {} of document '{}'",
+                index, id
+            ))
+            .with_reason(e.to_string())
+        })?;
 
     Ok(Json(stext))
 }
 
+/// Export a whole document as plain text
+///
+/// Items are extracted one at a time and streamed in reading order, so large
+/// documents start arriving at once. Items are separated by a blank line, or
+/// are one line each with `whitespace=collapse`. If an item fails to extract,
+/// the response ends early.
+#[utoipa::path(
+    get,
+    path = "/api/v1/documents/{id}/text",
+    tag = "documents",
+    params(("id" = String, Path, description = "Document ID"), PlainTextQuery),
+    responses(
+        (status = 200, description = "Document text in reading order", content_type = "text/plain", body = String),
+        (status = 404, description = "Document not found", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn get_document_text(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<PlainTextQuery>,
+) -> Result<Response, ApiError> {
+    // Hold the store lock only long enough to take the parser
+    let (parser, item_count) = {
+        let entries = DOCUMENT_STORE.entries.read().await;
+        let entry = entries
+            .get(&id)
+            .ok_or_else(|| ApiError::not_found(format!("Document '{}' not found", id)))?;
+        (entry.parser.clone(), entry.metadata.item_count)
+    };
+
+    let mode = query.whitespace;
+    let folding = query.folding();
+    let chunks = stream::iter(0..item_count).then(move |index| {
+        let state = state.clone();
+        let parser = parser.clone();
+        let id = id.clone();
+        async move {
+            let text = extract_item_text(&state, parser.as_ref(), &id, index)
+                .await
+                .inspect_err(|e| {
+                    tracing::warn!("Text export of {} stopped at item {}: {}", id, index, e)
+                })?;
+            Ok::<_, DocumentError>(folding.fold(&item_chunk(&text, mode)))
+        }
+    });
+
+    let response = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .header(header::CACHE_CONTROL, "max-age=3600")
+        .body(Body::from_stream(chunks))
+        .expect("hardcoded headers cannot fail");
+
+    Ok(response)
+}
+
+/// Get the plain text of an item
+#[utoipa::path(
+    get,
+    path = "/api/v1/documents/{id}/items/{index}/plain-text",
+    tag = "documents",
+    params(("id" = String, Path, description = "Document ID"), ("index" = usize, Path, description = "Item index (0-based page or chapter)"), PlainTextQuery),
+    responses(
+        (status = 200, description = "Item text", content_type = "text/plain", body = String),
+        (status = 404, description = "Document or item not found", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn get_item_text(
+    State(state): State<AppState>,
+    Path((id, index)): Path<(String, usize)>,
+    Query(query): Query<PlainTextQuery>,
+) -> Result<Response, ApiError> {
+    // Get entry
+    let entries = DOCUMENT_STORE.entries.read().await;
+    let entry = entries
+        .get(&id)
+        .ok_or_else(|| ApiError::not_found(format!("Document '{}' not found", id)))?;
+
+    // Validate item index before expensive operation
+    if index >= entry.metadata.item_count {
+        return Err(ApiError::not_found(format!(
+            "Item {} not found. Document has {} items (0-{})",
+            index,
+            entry.metadata.item_count,
+            entry.metadata.item_count.saturating_sub(1)
+        )));
+    }
+
+    let text = extract_item_text(&state, entry.parser.as_ref(), &id, index)
+        .await
+        .map_err(|e| {
+            ApiError::internal(format!(
+                "Failed to extract text for item {} of document '{}'",
+                index, id
+            ))
+            .with_reason(e.to_string())
+        })?;
+
+    let response = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .header(header::CACHE_CONTROL, "max-age=3600")
+        .body(Body::from(
+            query
+                .folding()
+                .fold(&normalize_text(&text, query.whitespace)),
+        ))
+        .expect("hardcoded headers cannot fail");
+
+    Ok(response)
+}
+
+/// Parse an upload in a worker subprocess when isolation is enabled
+///
+/// A document must pass the worker before it is parsed in-process. Does
+/// nothing with in-process isolation.
+pub(crate) async fn check_isolated(
+    state: &AppState,
+    data: &[u8],
+    doc_id: &str,
+) -> Result<(), ApiError> {
+    let isolation = &state.config().isolation;
+    if isolation.mode != IsolationMode::Subprocess {
+        return Ok(());
+    }
+    isolation::parse_isolated(data, doc_id, isolation.timeout_secs)
+        .await
+        .map(|_| ())
+        .map_err(|e| {
+            tracing::warn!("Isolated parse of '{}' failed: {}", doc_id, e);
+            if e.is_document_fault() {
+                ApiError::bad_request("Document failed isolated parsing")
+                    .with_type("document-rejected")
+                    .with_reason(e.to_string())
+            } else {
+                ApiError::internal("Failed to run parse worker").with_reason(e.to_string())
+            }
+        })
+}
+
+/// Extract an item's text, through the document cache while it holds the parser
+async fn extract_item_text(
+    state: &AppState,
+    parser: &dyn DocumentParser,
+    id: &str,
+    index: usize,
+) -> DocumentResult<String> {
+    state
+        .document_cache()
+        .extract_text_or(id, index, parser)
+        .await
+}
+
+/// Print-ready HTML for an item
+///
+/// For EPUB, `index` is a spine index: the chapter is returned as written,
+/// with scripts removed and its stylesheets and images served from the
+/// resources endpoint. For PDF, the page is shown as a high-resolution
+/// render from the render endpoint, fitted to the printed page. `@page`
+/// rules put the title and chapter in the running header and the page
+/// label in the footer.
+#[utoipa::path(
+    get,
+    path = "/api/v1/documents/{id}/items/{index}/print",
+    tag = "documents",
+    params(("id" = String, Path, description = "Document ID"), ("index" = usize, Path, description = "Item index (0-based page for PDF, spine index for EPUB)"), PrintQuery),
+    responses(
+        (status = 200, description = "Print-ready HTML document", content_type = "text/html", body = String),
+        (status = 404, description = "Document or item not found", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn print_item(
+    State(_state): State<AppState>,
+    Path((id, index)): Path<(String, usize)>,
+    Query(query): Query<PrintQuery>,
+) -> Result<Response, ApiError> {
+    let entries = DOCUMENT_STORE.entries.read().await;
+    let entry = entries
+        .get(&id)
+        .ok_or_else(|| ApiError::not_found(format!("Document '{}' not found", id)))?;
+    let document = &entry.metadata;
+
+    let html = match document.format {
+        DocumentFormat::Epub => {
+            let chapter = entry
+                .parser
+                .chapter_markup(index)
+                .await
+                .map_err(|e| match e {
+                    DocumentError::ItemNotFound(_) => ApiError::not_found(format!(
+                        "Chapter {} not found in document '{}'",
+                        index, id
+                    )),
+                    e => ApiError::internal(format!(
+                        "Failed to read chapter {} of document '{}'",
+                        index, id
+                    ))
+                    .with_reason(e.to_string()),
+                })?;
+            let labels = PrintLabels {
+                title: document.metadata.title.clone(),
+                section: chapter.label,
+                page: None,
+            };
+            let resource_base = format!("/api/v1/documents/{}/resources/", id);
+            print_chapter(&chapter.html, &chapter.path, &resource_base, &labels)
+        }
+        DocumentFormat::Pdf => {
+            if index >= document.item_count {
+                return Err(ApiError::not_found(format!(
+                    "Item {} not found. Document has {} items (0-{})",
+                    index,
+                    document.item_count,
+                    document.item_count.saturating_sub(1)
+                )));
+            }
+            let (width, height) = entry.parser.get_item_dimensions(index).map_err(|e| {
+                ApiError::internal(format!(
+                    "Failed to measure item {} of document '{}'",
+                    index, id
+                ))
+                .with_reason(e.to_string())
+            })?;
+            let page = document
+                .item_labels
+                .as_ref()
+                .and_then(|labels| labels.get(index).cloned())
+                .unwrap_or_else(|| (index + 1).to_string());
+            let labels = PrintLabels {
+                title: document.metadata.title.clone(),
+                section: section_at(&document.toc, index).map(|entry| entry.label.clone()),
+                page: Some(page),
+            };
+            let scale = query.scale.unwrap_or(MAX_SCALE).clamp(MIN_SCALE, MAX_SCALE);
+            let image_url = format!(
+                "/api/v1/documents/{}/items/{}/render?scale={}&format=png",
+                id, index, scale
+            );
+            print_page(&image_url, width, height, &labels)
+        }
+    };
+
+    let response = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
+        .header(header::CACHE_CONTROL, "max-age=3600")
+        .body(Body::from(html))
+        .expect("hardcoded headers cannot fail");
+
+    Ok(response)
+}
+
+/// The deepest ToC entry starting at or before item `index`, in reading
+/// order
+fn section_at(toc: &[TocEntry], index: usize) -> Option<&TocEntry> {
+    let mut section = None;
+    for entry in toc {
+        match entry.item_index {
+            Some(start) if start <= index => {
+                section = section_at(&entry.children, index).or(Some(entry));
+            }
+            Some(_) => break,
+            None => {}
+        }
+    }
+    section
+}
+
 /// Render a thumbnail for an item
+#[utoipa::path(
+    get,
+    path = "/api/v1/documents/{id}/items/{index}/thumbnail",
+    tag = "documents",
+    params(("id" = String, Path, description = "Document ID"), ("index" = usize, Path, description = "Item index (0-based page or chapter)"), ThumbnailQuery, ProfileQuery),
+    responses(
+        (status = 200, description = "Thumbnail image", content_type = "image/png",
+            headers(("x-render-warnings" = String, description = "JSON array of MuPDF warnings ({message, count}), when there were any"))),
+        (status = 400, description = "Invalid bandwidth profile", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Document or item not found", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
 async fn render_thumbnail(
     State(_state): State<AppState>,
     Path((id, index)): Path<(String, usize)>,
     Query(query): Query<ThumbnailQuery>,
-) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    Query(profile): Query<ProfileQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
     // Clamp size to valid range
-    let size = query.size.min(MAX_THUMBNAIL_SIZE);
+    let size = match query.size {
+        Some(size) => size,
+        None => profile.resolve(&headers)?.settings().thumbnail_size,
+    }
+    .min(MAX_THUMBNAIL_SIZE);
 
     // Get entry
     let entries = DOCUMENT_STORE.entries.read().await;
-    let entry = entries.get(&id).ok_or_else(|| {
-        (
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new(format!("Document '{}' not found", id))),
-        )
-    })?;
+    let entry = entries
+        .get(&id)
+        .ok_or_else(|| ApiError::not_found(format!("Document '{}' not found", id)))?;
 
     // Validate item index before expensive operation
     if index >= entry.metadata.item_count {
-        return Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new(format!(
-                "Item {} not found. Document has {} items (0-{})",
-                index,
-                entry.metadata.item_count,
-                entry.metadata.item_count.saturating_sub(1)
-            ))),
-        ));
+        return Err(ApiError::not_found(format!(
+            "Item {} not found. Document has {} items (0-{})",
+            index,
+            entry.metadata.item_count,
+            entry.metadata.item_count.saturating_sub(1)
+        )));
     }
 
     let result = entry
@@ -719,16 +1823,11 @@ async fn render_thumbnail(
         .render_thumbnail(index, size)
         .await
         .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::with_details(
-                    format!(
-                        "Failed to render thumbnail for item {} of document '{}'",
-                        index, id
-                    ),
-                    e.to_string(),
-                )),
-            )
+            ApiError::internal(format!(
+                "Failed to render thumbnail for item {} of document '{}'",
+                index, id
+            ))
+            .with_reason(e.to_string())
         })?;
 
     // Thumbnails are typically JPEG
@@ -738,10 +1837,15 @@ async fn render_thumbnail(
         ImageFormat::Webp => "image/webp",
     };
 
-    let response = Response::builder()
+    let mut response = Response::builder()
         .status(StatusCode::OK)
         .header(header::CONTENT_TYPE, content_type)
         .header(header::CACHE_CONTROL, "max-age=86400")
+        .header(header::VARY, PROFILE_VARY);
+    if let Some(warnings) = render_warnings_header(&result.warnings) {
+        response = response.header(RENDER_WARNINGS_HEADER, warnings);
+    }
+    let response = response
         .body(Body::from(result.data))
         .expect("hardcoded headers cannot fail");
 
@@ -749,23 +1853,30 @@ async fn render_thumbnail(
 }
 
 /// Search document content
+#[utoipa::path(
+    get,
+    path = "/api/v1/documents/{id}/search",
+    tag = "documents",
+    params(("id" = String, Path, description = "Document ID"), SearchQuery),
+    responses(
+        (status = 200, description = "Search hits", body = SearchResultResponse),
+        (status = 404, description = "Document not found", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
 async fn search_document(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
     Path(id): Path<String>,
     Query(query): Query<SearchQuery>,
-) -> Result<Json<SearchResultResponse>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<SearchResultResponse>, ApiError> {
     // Clamp search parameters to prevent resource exhaustion
     let limit = query.limit.min(MAX_SEARCH_LIMIT);
     let context_length = query.context_length.min(MAX_CONTEXT_LENGTH);
 
     // Get entry
     let entries = DOCUMENT_STORE.entries.read().await;
-    let entry = entries.get(&id).ok_or_else(|| {
-        (
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new(format!("Document '{}' not found", id))),
-        )
-    })?;
+    let entry = entries
+        .get(&id)
+        .ok_or_else(|| ApiError::not_found(format!("Document '{}' not found", id)))?;
 
     let options = SearchOptions {
         limit,
@@ -777,17 +1888,11 @@ async fn search_document(
     };
 
     let results = entry.parser.search(&query.q, options).await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse::with_details(
-                format!("Failed to search document '{}'", id),
-                e.to_string(),
-            )),
-        )
+        ApiError::internal(format!("Failed to search document '{}'", id)).with_reason(e.to_string())
     })?;
 
     let total = results.len();
-    let hits: Vec<SearchHit> = results
+    let mut hits: Vec<SearchHit> = results
         .into_iter()
         .map(|r| SearchHit {
             item_index: r.item_index,
@@ -804,8 +1909,12 @@ async fn search_document(
                     height: b.height,
                 })
                 .collect(),
+            paragraph: None,
         })
         .collect();
+    if query.mode == SearchMode::Paragraph {
+        attach_paragraphs(&state, entry, &id, &query.q, &mut hits).await;
+    }
 
     Ok(Json(SearchResultResponse {
         results: hits,
@@ -814,36 +1923,802 @@ async fn search_document(
     }))
 }
 
-/// Get an embedded resource (image, CSS, font)
+/// Fill in the text block each hit is in
+///
+/// Hits are matched to blocks by occurrence: the n-th hit on an item is the
+/// n-th occurrence of the query in the item's blocks. Hits whose item text
+/// cannot be read, or that span blocks, are left without a paragraph.
+async fn attach_paragraphs(
+    state: &AppState,
+    entry: &CachedDocument,
+    id: &str,
+    query: &str,
+    hits: &mut [SearchHit],
+) {
+    let cache = state.document_cache();
+    let mut items: HashMap<usize, Option<(StructuredText, Vec<usize>)>> = HashMap::new();
+    let mut hits_seen: HashMap<usize, usize> = HashMap::new();
+
+    for hit in hits.iter_mut() {
+        let index = hit.item_index;
+        if let Entry::Vacant(slot) = items.entry(index) {
+            let blocks = cache
+                .get_structured_text_or(id, index, entry.parser.as_ref())
+                .await
+                .inspect_err(|e| {
+                    tracing::debug!("No paragraphs for item {} of {}: {}", index, id, e)
+                })
+                .ok()
+                .map(|stext| {
+                    let blocks = occurrence_blocks(&stext, query);
+                    (stext, blocks)
+                });
+            slot.insert(blocks);
+        }
+
+        let seen = hits_seen.entry(index).or_insert(0);
+        let occurrence = *seen;
+        *seen += 1;
+
+        let Some(Some((stext, blocks))) = items.get(&index) else {
+            continue;
+        };
+        let Some(&block_index) = blocks.get(occurrence) else {
+            continue;
+        };
+        let block = &stext.blocks[block_index];
+        hit.paragraph = Some(SearchParagraph {
+            block_index,
+            text: block_text(block),
+            bounds: BoundingBoxResponse {
+                x: block.bbox.x,
+                y: block.bbox.y,
+                width: block.bbox.width,
+                height: block.bbox.height,
+            },
+            locator: hit_locator(&entry.metadata, hit, stext.height),
+        });
+    }
+}
+
+/// Locator for a search hit, using its vertical position on the item
+fn hit_locator(document: &ParsedDocument, hit: &SearchHit, item_height: f32) -> ProgressLocator {
+    let y = hit
+        .bounds
+        .first()
+        .filter(|_| item_height > 0.0)
+        .map(|bounds| f64::from((bounds.y / item_height).clamp(0.0, 1.0)));
+    match document.format {
+        DocumentFormat::Pdf => ProgressLocator::Page {
+            page: hit.item_index as u32 + 1,
+            y,
+        },
+        DocumentFormat::Epub => ProgressLocator::Progression {
+            progression: (hit.item_index as f64 + y.unwrap_or(0.0))
+                / document.item_count.max(1) as f64,
+        },
+    }
+}
+
+/// Download a search index in the shared binary format
+///
+/// The WASM reader imports this with `importSearchIndex` and can then search
+/// the book offline without indexing every chapter in the browser. Alt text,
+/// captions and footnotes are only searchable if asked for.
+#[utoipa::path(
+    get,
+    path = "/api/v1/documents/{id}/search-index",
+    tag = "documents",
+    params(("id" = String, Path, description = "Document ID"), SearchIndexQuery),
+    responses(
+        (status = 200, description = "Serialized search index", content_type = "application/octet-stream", body = Vec<u8>),
+        (status = 400, description = "Format does not support search index export", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Document not found", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn get_search_index(
+    State(_state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<SearchIndexQuery>,
+) -> Result<Response, ApiError> {
+    let entries = DOCUMENT_STORE.entries.read().await;
+    let entry = entries
+        .get(&id)
+        .ok_or_else(|| ApiError::not_found(format!("Document '{}' not found", id)))?;
+
+    let index = entry
+        .parser
+        .build_search_index(&query.into())
+        .await
+        .map_err(|e| search_index_error(&id, e))?;
+
+    let response = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/octet-stream")
+        .header(header::CACHE_CONTROL, "max-age=3600")
+        .body(Body::from(index.to_bytes()))
+        .expect("hardcoded headers cannot fail");
+
+    Ok(response)
+}
+
+/// Content checksums of an EPUB's spine items
+///
+/// The same checksums the WASM processor reports in `ParsedBook.checksums`.
+/// After a book file is replaced, comparing them with the previous list
+/// tells clients and sync which chapters to reindex and reanchor.
+#[utoipa::path(
+    get,
+    path = "/api/v1/documents/{id}/checksums",
+    tag = "documents",
+    params(("id" = String, Path, description = "Document ID")),
+    responses(
+        (status = 200, description = "Checksums in spine order", body = Vec<ChapterChecksumResponse>),
+        (status = 400, description = "Format has no chapters", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Document not found", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn get_chapter_checksums(
+    State(_state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<ChapterChecksumResponse>>, ApiError> {
+    let entries = DOCUMENT_STORE.entries.read().await;
+    let entry = entries
+        .get(&id)
+        .ok_or_else(|| ApiError::not_found(format!("Document '{}' not found", id)))?;
+    if entry.metadata.format != DocumentFormat::Epub {
+        return Err(ApiError::bad_request(
+            "Chapter checksums are only available for EPUB documents",
+        ));
+    }
+
+    let checksums = entry
+        .metadata
+        .chapter_checksums
+        .iter()
+        .map(|item| ChapterChecksumResponse {
+            spine_index: item.spine_index,
+            href: item.href.clone(),
+            checksum: item.checksum.clone(),
+        })
+        .collect();
+
+    Ok(Json(checksums))
+}
+
+/// Internal links between an EPUB's chapters
+///
+/// Reads every `<a href>` in the spine items that points at another spine
+/// item. `chapters` lists, per item, the items it links to and the items
+/// linking to it, for "referenced by" navigation; `links` has each link
+/// with its text and target fragment. Links within a chapter appear in
+/// `links` only.
+#[utoipa::path(
+    get,
+    path = "/api/v1/documents/{id}/links",
+    tag = "documents",
+    params(("id" = String, Path, description = "Document ID")),
+    responses(
+        (status = 200, description = "Link graph of the spine", body = LinkGraphResponse),
+        (status = 400, description = "Format has no linked chapters", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Document not found", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn get_link_graph(
+    State(_state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<LinkGraphResponse>, ApiError> {
+    let entries = DOCUMENT_STORE.entries.read().await;
+    let entry = entries
+        .get(&id)
+        .ok_or_else(|| ApiError::not_found(format!("Document '{}' not found", id)))?;
+
+    let graph = entry.parser.link_graph().await.map_err(|e| match e {
+        DocumentError::UnsupportedFormat(msg) => {
+            ApiError::bad_request(msg).with_type("unsupported-format")
+        }
+        e => ApiError::internal(format!("Failed to read links of '{}'", id))
+            .with_reason(e.to_string()),
+    })?;
+    Ok(Json(graph.into()))
+}
+
+/// Start checking a document's web links
+///
+/// Links are read from the EPUB markup, or picked out of the PDF page text.
+/// Each distinct URL is requested once in the background, at most one
+/// request per second to each host, and the job lists the broken and
+/// unreachable ones with every place linking to them. Poll the job at
+/// `/api/v1/documents/link-checks/{job_id}`. While a check of the document
+/// is running, it is returned instead of starting another.
+#[utoipa::path(
+    post,
+    path = "/api/v1/documents/{id}/link-check",
+    tag = "documents",
+    params(("id" = String, Path, description = "Document ID")),
+    responses(
+        (status = 202, description = "Link check started", body = LinkCheckJob),
+        (status = 404, description = "Document not found", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn start_link_check(
+    State(_state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Response, ApiError> {
+    let parser = {
+        let entries = DOCUMENT_STORE.entries.read().await;
+        let entry = entries
+            .get(&id)
+            .ok_or_else(|| ApiError::not_found(format!("Document '{}' not found", id)))?;
+        Arc::clone(&entry.parser)
+    };
+
+    LINK_CHECKS.prune();
+    let job = match LINK_CHECKS.running(&id) {
+        Some(job) => job,
+        None => start_link_check_job(id, parser),
+    };
+    let location = format!("/api/v1/documents/link-checks/{}", job.id);
+
+    Ok((
+        StatusCode::ACCEPTED,
+        [(header::LOCATION, location)],
+        Json(job),
+    )
+        .into_response())
+}
+
+/// Read a document's links and check them in the background
+fn start_link_check_job(document_id: String, parser: Arc<dyn DocumentParser>) -> LinkCheckJob {
+    let job = LinkCheckJob {
+        id: uuid::Uuid::new_v4().to_string(),
+        document_id,
+        status: LinkCheckStatus::Running,
+        links: 0,
+        urls: 0,
+        checked: 0,
+        dead: Vec::new(),
+        unverified: 0,
+        error: None,
+        created_at: Utc::now(),
+        finished_at: None,
+    };
+    LINK_CHECKS.insert(job.clone());
+
+    let id = job.id.clone();
+    tokio::spawn(async move {
+        let links = match parser.external_links().await {
+            Ok(links) => links,
+            Err(e) => {
+                tracing::error!("Reading links for link check {} failed: {}", id, e);
+                LINK_CHECKS.update(&id, |job| {
+                    job.status = LinkCheckStatus::Failed;
+                    job.error = Some(e.to_string());
+                    job.finished_at = Some(Utc::now());
+                });
+                return;
+            }
+        };
+        LINK_CHECKS.update(&id, |job| job.links = links.len());
+
+        let checker = LinkChecker::new(LinkCheckOptions::default());
+        let reports = checker
+            .check(links, |checked, urls| {
+                LINK_CHECKS.update(&id, |job| {
+                    job.checked = checked;
+                    job.urls = urls;
+                })
+            })
+            .await;
+
+        let unverified = reports
+            .iter()
+            .filter(|report| report.health == LinkHealth::Unverified)
+            .count();
+        let dead: Vec<LinkReport> = reports
+            .into_iter()
+            .filter(|report| report.health.is_dead())
+            .collect();
+        LINK_CHECKS.update(&id, |job| {
+            job.status = LinkCheckStatus::Completed;
+            job.dead = dead;
+            job.unverified = unverified;
+            job.finished_at = Some(Utc::now());
+        });
+    });
+
+    job
+}
+
+/// Get a link check
+#[utoipa::path(
+    get,
+    path = "/api/v1/documents/link-checks/{job_id}",
+    tag = "documents",
+    params(("job_id" = String, Path, description = "Link check job ID")),
+    responses(
+        (status = 200, description = "Link check job", body = LinkCheckJob),
+        (status = 404, description = "Job not found or expired", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn get_link_check(
+    State(_state): State<AppState>,
+    Path(job_id): Path<String>,
+) -> Result<Json<LinkCheckJob>, ApiError> {
+    LINK_CHECKS.prune();
+    LINK_CHECKS
+        .get(&job_id)
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("Link check '{}' not found", job_id)))
+}
+
+/// Back-of-book index of a document
+///
+/// Reads the EPUB index documents (`epub:type="index"`) into terms, each
+/// with locators into the spine, "see" and "see also" references, and
+/// subentries. A book without an index has no entries.
+#[utoipa::path(
+    get,
+    path = "/api/v1/documents/{id}/book-index",
+    tag = "documents",
+    params(("id" = String, Path, description = "Document ID")),
+    responses(
+        (status = 200, description = "Index entries", body = BookIndexResponse),
+        (status = 400, description = "Format has no index documents", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Document not found", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn get_book_index(
+    State(_state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<BookIndexResponse>, ApiError> {
+    Ok(Json(read_book_index(&id).await?.into()))
+}
+
+/// Look up a term in a document's back-of-book index
+///
+/// Matches terms and subentries at any depth, ignoring case and runs of
+/// whitespace: entries named exactly `q` first, then entries starting with
+/// it, each in index order.
+#[utoipa::path(
+    get,
+    path = "/api/v1/documents/{id}/book-index/lookup",
+    tag = "documents",
+    params(("id" = String, Path, description = "Document ID"), IndexLookupQuery),
+    responses(
+        (status = 200, description = "Matching index entries", body = IndexLookupResponse),
+        (status = 400, description = "Format has no index documents", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Document not found", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn lookup_book_index(
+    State(_state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<IndexLookupQuery>,
+) -> Result<Json<IndexLookupResponse>, ApiError> {
+    let index = read_book_index(&id).await?;
+    let matches = index
+        .lookup(&query.q, query.limit.min(MAX_INDEX_MATCHES))
+        .into_iter()
+        .map(Into::into)
+        .collect();
+    Ok(Json(IndexLookupResponse {
+        query: query.q,
+        matches,
+    }))
+}
+
+async fn read_book_index(id: &str) -> Result<BookIndex, ApiError> {
+    let entries = DOCUMENT_STORE.entries.read().await;
+    let entry = entries
+        .get(id)
+        .ok_or_else(|| ApiError::not_found(format!("Document '{}' not found", id)))?;
+
+    entry.parser.book_index().await.map_err(|e| match e {
+        DocumentError::UnsupportedFormat(msg) => {
+            ApiError::bad_request(msg).with_type("unsupported-format")
+        }
+        e => ApiError::internal(format!("Failed to read the index of '{}'", id))
+            .with_reason(e.to_string()),
+    })
+}
+
+/// Glossary of a document
+///
+/// Reads the EPUB glossaries (`epub:type="glossary"` definition lists) into
+/// terms with their definitions as plain text. A book without a glossary
+/// has no entries.
+#[utoipa::path(
+    get,
+    path = "/api/v1/documents/{id}/glossary",
+    tag = "documents",
+    params(("id" = String, Path, description = "Document ID")),
+    responses(
+        (status = 200, description = "Glossary entries", body = GlossaryResponse),
+        (status = 400, description = "Format has no glossaries", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Document not found", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn get_glossary(
+    State(_state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<GlossaryResponse>, ApiError> {
+    Ok(Json(read_glossary(&id).await?.into()))
+}
+
+/// The document's own definition of a term
+///
+/// Looks the term up in the book's glossary, ignoring case, runs of
+/// whitespace and a plural `s` or `es`, so readers can show the book's
+/// definition before falling back to a dictionary.
+#[utoipa::path(
+    get,
+    path = "/api/v1/documents/{id}/glossary/define",
+    tag = "documents",
+    params(("id" = String, Path, description = "Document ID"), DefineTermQuery),
+    responses(
+        (status = 200, description = "Glossary entry for the term", body = GlossaryEntryResponse),
+        (status = 400, description = "Format has no glossaries", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Document not found or term not defined", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn define_term(
+    State(_state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<DefineTermQuery>,
+) -> Result<Json<GlossaryEntryResponse>, ApiError> {
+    let glossary = read_glossary(&id).await?;
+    let entry = glossary.define(&query.term).cloned().ok_or_else(|| {
+        ApiError::not_found(format!(
+            "'{}' is not in the glossary of '{}'",
+            query.term, id
+        ))
+    })?;
+    Ok(Json(entry.into()))
+}
+
+async fn read_glossary(id: &str) -> Result<Glossary, ApiError> {
+    let entries = DOCUMENT_STORE.entries.read().await;
+    let entry = entries
+        .get(id)
+        .ok_or_else(|| ApiError::not_found(format!("Document '{}' not found", id)))?;
+
+    entry.parser.glossary().await.map_err(|e| match e {
+        DocumentError::UnsupportedFormat(msg) => {
+            ApiError::bad_request(msg).with_type("unsupported-format")
+        }
+        e => ApiError::internal(format!("Failed to read the glossary of '{}'", id))
+            .with_reason(e.to_string()),
+    })
+}
+
+/// Fonts a document requests and the substitutions made for them
+///
+/// Lists every font the document names, and whether it embeds it: PDF page
+/// fonts, or EPUB CSS font families. Substitutions are the fonts MuPDF
+/// looked for while parsing and rendering so far, with the configured file
+/// it used (see `FONT_FALLBACK_DIRS` and `FONT_SUBSTITUTIONS`) or none when
+/// it fell back to a built-in font. Text in a script with no substitute
+/// renders as boxes.
+#[utoipa::path(
+    get,
+    path = "/api/v1/documents/{id}/fonts",
+    tag = "documents",
+    params(("id" = String, Path, description = "Document ID")),
+    responses(
+        (status = 200, description = "Requested fonts and substitutions", body = DocumentFonts),
+        (status = 404, description = "Document not found", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn get_document_fonts(
+    State(_state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<DocumentFonts>, ApiError> {
+    let entries = DOCUMENT_STORE.entries.read().await;
+    let entry = entries
+        .get(&id)
+        .ok_or_else(|| ApiError::not_found(format!("Document '{}' not found", id)))?;
+
+    let fonts = entry.parser.fonts().await.map_err(|e| {
+        ApiError::internal(format!("Failed to list fonts of '{}'", id)).with_reason(e.to_string())
+    })?;
+    Ok(Json(fonts))
+}
+
+/// Snap a highlight selection to whole words and trim its whitespace
+///
+/// Offsets are UTF-16 code units into the chapter's plain text, the text
+/// the WASM reader's `getChapterText` returns. Clients normalize a
+/// selection before storing the highlight, so the same passage always gets
+/// the same selectors whatever the user's selection drifted over.
+#[utoipa::path(
+    post,
+    path = "/api/v1/documents/{id}/selections/normalize",
+    tag = "documents",
+    params(("id" = String, Path, description = "Document ID")),
+    request_body = NormalizeSelectionRequest,
+    responses(
+        (status = 200, description = "Corrected selection and selectors", body = NormalizedSelectionResponse),
+        (status = 400, description = "Only whitespace selected, or format has no chapter text", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Document or chapter not found", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn normalize_selection(
+    State(_state): State<AppState>,
+    Path(id): Path<String>,
+    Json(request): Json<NormalizeSelectionRequest>,
+) -> Result<Json<NormalizedSelectionResponse>, ApiError> {
+    let entries = DOCUMENT_STORE.entries.read().await;
+    let entry = entries
+        .get(&id)
+        .ok_or_else(|| ApiError::not_found(format!("Document '{}' not found", id)))?;
+
+    // Chapter text as the search index and the WASM reader extract it
+    let index = entry
+        .parser
+        .build_search_index(&IndexOptions::default())
+        .await
+        .map_err(|e| search_index_error(&id, e))?;
+    let chapter = index
+        .chapters
+        .iter()
+        .find(|chapter| chapter.href == request.href)
+        .ok_or_else(|| {
+            ApiError::not_found(format!(
+                "Chapter '{}' not found in document '{}'",
+                request.href, id
+            ))
+        })?;
+
+    let selection =
+        search_core::normalize_selection(&chapter.original_text, request.start, request.end)
+            .ok_or_else(|| {
+                ApiError::bad_request("Selection contains only whitespace")
+                    .with_type("empty-selection")
+            })?;
+
+    Ok(Json(NormalizedSelectionResponse::new(
+        request.href,
+        selection,
+    )))
+}
+
+fn search_index_error(id: &str, e: DocumentError) -> ApiError {
+    match e {
+        DocumentError::UnsupportedFormat(msg) => {
+            ApiError::bad_request(msg).with_type("unsupported-format")
+        }
+        e => ApiError::internal(format!("Failed to build search index for '{}'", id))
+            .with_reason(e.to_string()),
+    }
+}
+
+/// Get an embedded resource (image, CSS, font, chapter XHTML)
+///
+/// With `anchors=true`, chapter documents get the same `data-anchor`
+/// attributes the WASM processor adds, for anchoring when CFIs fail. With
+/// `aria=true`, they get DPUB-ARIA roles and labelled regions for clients
+/// that embed the markup directly. JPEG and PNG images are shrunk for the
+/// `low` bandwidth profile.
+#[utoipa::path(
+    get,
+    path = "/api/v1/documents/{id}/resources/{href}",
+    tag = "documents",
+    params(("id" = String, Path, description = "Document ID"), ("href" = String, Path, description = "Resource path inside the EPUB"), ResourceQuery, ProfileQuery),
+    responses(
+        (status = 200, description = "Raw resource bytes with detected content type"),
+        (status = 404, description = "Document or resource not found", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
 async fn get_resource(
     State(_state): State<AppState>,
     Path((id, href)): Path<(String, String)>,
-) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    Query(query): Query<ResourceQuery>,
+    Query(profile): Query<ProfileQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let settings = profile.resolve(&headers)?.settings();
+
     // Get entry
     let entries = DOCUMENT_STORE.entries.read().await;
-    let entry = entries.get(&id).ok_or_else(|| {
-        (
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new(format!("Document '{}' not found", id))),
-        )
-    })?;
+    let entry = entries
+        .get(&id)
+        .ok_or_else(|| ApiError::not_found(format!("Document '{}' not found", id)))?;
 
     let resource = entry.renderer.get_resource(&href).await.map_err(|e| {
-        (
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse::with_details(
-                format!("Resource '{}' not found in document '{}'", href, id),
-                e.to_string(),
-            )),
-        )
+        ApiError::not_found(format!(
+            "Resource '{}' not found in document '{}'",
+            href, id
+        ))
+        .with_reason(e.to_string())
     })?;
 
+    let content = if (query.anchors || query.aria) && resource.mime_type.contains("html") {
+        match String::from_utf8(resource.content) {
+            Ok(mut html) => {
+                if query.anchors {
+                    html = epub_core::inject_anchors(&html);
+                }
+                if query.aria {
+                    html = epub_core::add_aria_roles(&html);
+                }
+                html.into_bytes()
+            }
+            Err(e) => e.into_bytes(),
+        }
+    } else if let Some(max_dimension) = settings.max_image_dimension {
+        let mime_type = resource.mime_type.clone();
+        let content = resource.content;
+        // Decoding and resizing is CPU-bound
+        tokio::task::spawn_blocking(move || {
+            shrink_image(&content, &mime_type, max_dimension, settings.jpeg_quality)
+                .unwrap_or(content)
+        })
+        .await
+        .map_err(|e| ApiError::internal("Failed to shrink image").with_reason(e.to_string()))?
+    } else {
+        resource.content
+    };
+
     let response = Response::builder()
         .status(StatusCode::OK)
         .header(header::CONTENT_TYPE, resource.mime_type)
         .header(header::CACHE_CONTROL, "max-age=3600")
-        .body(Body::from(resource.content))
+        .header(header::VARY, PROFILE_VARY)
+        .body(Body::from(content))
         .expect("hardcoded headers cannot fail");
 
     Ok(response)
 }
+
+/// Signal that a reader opened a document
+///
+/// Pins the document's parser and renderer and, in the background, renders
+/// the first pages plus the saved position and extracts the text layer
+/// around it, so the first requests after a cold start hit the cache.
+#[utoipa::path(
+    post,
+    path = "/api/v1/documents/{id}/open",
+    tag = "documents",
+    params(("id" = String, Path, description = "Document ID"), ProfileQuery),
+    request_body(content = OpenDocumentRequest, description = "Optional prewarm settings"),
+    responses(
+        (status = 200, description = "Document pinned and prewarm started", body = OpenDocumentResponse),
+        (status = 404, description = "Document not found", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn open_document(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(profile): Query<ProfileQuery>,
+    headers: HeaderMap,
+    body: Option<Json<OpenDocumentRequest>>,
+) -> Result<Json<OpenDocumentResponse>, ApiError> {
+    let request = body.map(|Json(request)| request).unwrap_or_default();
+    let settings = profile.resolve(&headers)?.settings();
+
+    let (parser, renderer, metadata) = {
+        let entries = DOCUMENT_STORE.entries.read().await;
+        let entry = entries
+            .get(&id)
+            .ok_or_else(|| ApiError::not_found(format!("Document '{}' not found", id)))?;
+        (
+            Arc::clone(&entry.parser),
+            Arc::clone(&entry.renderer),
+            entry.metadata.clone(),
+        )
+    };
+    let item_count = metadata.item_count;
+
+    let resume_item = match request.resume_item {
+        Some(index) => Some(index),
+        None => match ProgressRepository::new(state.db()).get(&id, None).await {
+            Ok(progress) => progress.and_then(|p| resume_item_index(&p, item_count)),
+            Err(e) => {
+                tracing::warn!("Failed to load progress for '{}': {}", id, e);
+                None
+            }
+        },
+    };
+
+    let leading = request
+        .prewarm_items
+        .unwrap_or(DEFAULT_PREWARM_ITEMS)
+        .min(MAX_PREWARM_ITEMS);
+    let scale = request
+        .scale
+        .unwrap_or(settings.render_scale)
+        .clamp(MIN_SCALE, MAX_SCALE);
+    let (format, quality) = render_output(request.format.as_deref().unwrap_or(""), &settings);
+    let plan = PrewarmPlan::new(item_count, leading, resume_item)
+        .with_render_settings(scale, format, quality);
+
+    let cache = state.document_cache().clone();
+    let open_sessions = cache.open(&id, metadata, parser, renderer).await;
+
+    let response = OpenDocumentResponse {
+        id: id.clone(),
+        open_sessions,
+        resume_item,
+        prewarm_items: plan.render_items.clone(),
+        prewarm_text_items: plan.text_items.clone(),
+    };
+
+    tokio::spawn(async move {
+        let warmed = cache.prewarm(&id, &plan).await;
+        tracing::debug!("Prewarmed {} items for '{}'", warmed, id);
+    });
+
+    Ok(Json(response))
+}
+
+/// Signal that a reader closed a document
+///
+/// When the last session closes, the document is unpinned and its cached
+/// renders and text are released.
+#[utoipa::path(
+    post,
+    path = "/api/v1/documents/{id}/close",
+    tag = "documents",
+    params(("id" = String, Path, description = "Document ID")),
+    responses(
+        (status = 200, description = "Session closed", body = CloseDocumentResponse),
+        (status = 404, description = "Document not found", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn close_document(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<CloseDocumentResponse>, ApiError> {
+    if !DOCUMENT_STORE.contains(&id).await {
+        return Err(ApiError::not_found(format!("Document '{}' not found", id)));
+    }
+
+    // Closing a document that is not open is a no-op so clients can close
+    // unconditionally (e.g. on window unload)
+    let remaining = state.document_cache().close(&id).await;
+
+    Ok(Json(CloseDocumentResponse {
+        id,
+        open_sessions: remaining.unwrap_or(0),
+        released: remaining == Some(0),
+    }))
+}
+
+/// Parse a render format name, defaulting to PNG
+fn parse_image_format(format: &str) -> ImageFormat {
+    match format.to_lowercase().as_str() {
+        "jpeg" | "jpg" => ImageFormat::Jpeg,
+        "webp" => ImageFormat::Webp,
+        _ => ImageFormat::Png,
+    }
+}
+
+/// Render format and JPEG quality, with the profile's format when none is given
+fn render_output(format: &str, settings: &ProfileSettings) -> (ImageFormat, Option<u8>) {
+    let format = if format.is_empty() {
+        settings.render_format
+    } else {
+        parse_image_format(format)
+    };
+    (
+        format,
+        (format == ImageFormat::Jpeg).then_some(settings.jpeg_quality),
+    )
+}
+
+/// Item index to resume at for saved progress
+fn resume_item_index(progress: &ReadingProgress, item_count: usize) -> Option<usize> {
+    if item_count == 0 {
+        return None;
+    }
+
+    let index = match &progress.locator.0 {
+        ProgressLocator::Page { page, .. } => (*page as usize).saturating_sub(1),
+        ProgressLocator::Progression { progression } => (progression * item_count as f64) as usize,
+        // CFIs and positions don't map to rendered pages; use the percentage
+        _ => (progress.percent / 100.0 * item_count as f64) as usize,
+    };
+
+    Some(index.min(item_count - 1))
+}
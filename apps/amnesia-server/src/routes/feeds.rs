@@ -0,0 +1,92 @@
+//! Feeds of newly added books
+//!
+//! `GET /feeds/new-books.atom` and `GET /feeds/new-books.json` serve the
+//! feeds built by [`crate::feeds`]. With `FEEDS_VISIBILITY=token` both take
+//! `?token=`; feeds a request may not read answer 404, as when they are
+//! off. Maturity parameters narrow the feeds as they do OPDS feeds (see
+//! [`MaturityFilter`]).
+
+use axum::{
+    extract::{Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde::Deserialize;
+
+use crate::error::{AppError, Result};
+use crate::feeds::{self, JSON_FEED_MIME};
+use crate::library::{LibraryBook, MaturityFilter};
+use crate::opds::{mime, serialize_feed};
+use crate::routes::opds::{base_url, entry_template, visible_books, LibraryCache};
+use crate::state::AppState;
+
+/// Create the feeds router
+pub fn router(cache: LibraryCache) -> Router<AppState> {
+    Router::new()
+        .route("/new-books.atom", get(new_books_atom))
+        .route("/new-books.json", get(new_books_json))
+        .layer(axum::Extension(cache))
+}
+
+#[derive(Deserialize)]
+struct FeedQuery {
+    token: Option<String>,
+}
+
+/// The newest books a request may see, or 404 if it may not read the feeds
+async fn new_books(
+    state: &AppState,
+    cache: &LibraryCache,
+    query: &FeedQuery,
+    filter: &MaturityFilter,
+) -> Result<Vec<LibraryBook>> {
+    let config = &state.config().feeds;
+    if !feeds::is_visible(config, query.token.as_deref()) {
+        return Err(AppError::NotFound("Feed not found".to_string()));
+    }
+    let books = visible_books(state, cache, filter).await?;
+    Ok(feeds::newest(books, config.limit))
+}
+
+/// Newly added books as Atom
+async fn new_books_atom(
+    State(state): State<AppState>,
+    axum::Extension(cache): axum::Extension<LibraryCache>,
+    Query(query): Query<FeedQuery>,
+    Query(filter): Query<MaturityFilter>,
+) -> Result<Response> {
+    let books = new_books(&state, &cache, &query, &filter).await?;
+    let base = base_url(&state);
+
+    let feed = feeds::atom_feed(
+        &books,
+        &state.config().feeds.title,
+        &format!("{}/feeds/new-books.atom", base),
+        &base,
+        &entry_template(&state),
+    );
+    let xml = serialize_feed(&feed)?;
+    Ok(([(header::CONTENT_TYPE, mime::ATOM_XML)], xml).into_response())
+}
+
+/// Newly added books as a JSON Feed
+async fn new_books_json(
+    State(state): State<AppState>,
+    axum::Extension(cache): axum::Extension<LibraryCache>,
+    Query(query): Query<FeedQuery>,
+    Query(filter): Query<MaturityFilter>,
+) -> Result<Response> {
+    let books = new_books(&state, &cache, &query, &filter).await?;
+    let base = base_url(&state);
+
+    let feed = feeds::json_feed(
+        &books,
+        &state.config().feeds.title,
+        &format!("{}/feeds/new-books.json", base),
+        &base,
+        &entry_template(&state),
+    );
+    Ok(([(header::CONTENT_TYPE, JSON_FEED_MIME)], Json(feed)).into_response())
+}
@@ -0,0 +1,143 @@
+//! Field selection for detail responses
+//!
+//! Detail endpoints accept `fields` or `exclude` (comma-separated top-level
+//! field names, as they appear in the JSON) to trim the response:
+//!
+//! ```text
+//! GET /api/v1/documents/:id?fields=title,creators,itemCount
+//! GET /api/v1/documents/:id?exclude=toc
+//! ```
+//!
+//! `id` is always kept so clients can correlate partial responses.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use utoipa::IntoParams;
+
+use crate::error::ApiError;
+
+/// Field that is always included in partial responses
+const ALWAYS_INCLUDED: &str = "id";
+
+/// `fields`/`exclude` query parameters
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FieldSelection {
+    /// Comma-separated fields to include (all others are omitted)
+    pub fields: Option<String>,
+    /// Comma-separated fields to omit
+    pub exclude: Option<String>,
+}
+
+impl FieldSelection {
+    /// Whether the full response should be returned unchanged
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_none() && self.exclude.is_none()
+    }
+
+    /// Serialize `body` and keep only the selected top-level fields
+    ///
+    /// Unknown field names are rejected so that typos do not silently return
+    /// an empty object.
+    pub fn apply<T: Serialize>(&self, body: &T) -> Result<Value, ApiError> {
+        let value = serde_json::to_value(body)
+            .map_err(|e| ApiError::internal("Failed to serialize response").with_reason(e))?;
+        if self.is_empty() {
+            return Ok(value);
+        }
+        if self.fields.is_some() && self.exclude.is_some() {
+            return Err(ApiError::bad_request(
+                "Use either 'fields' or 'exclude', not both",
+            ));
+        }
+
+        let Value::Object(object) = value else {
+            return Ok(value);
+        };
+
+        let requested = parse_list(self.fields.as_deref().or(self.exclude.as_deref()));
+        if let Some(unknown) = requested.iter().find(|name| !object.contains_key(**name)) {
+            let mut known: Vec<&str> = object.keys().map(String::as_str).collect();
+            known.sort_unstable();
+            return Err(ApiError::bad_request(format!(
+                "Unknown field '{}'. Available fields: {}",
+                unknown,
+                known.join(", ")
+            ))
+            .with_type("unknown-field"));
+        }
+
+        let include = self.fields.is_some();
+        let selected: Map<String, Value> = object
+            .into_iter()
+            .filter(|(key, _)| {
+                key == ALWAYS_INCLUDED || requested.contains(&key.as_str()) == include
+            })
+            .collect();
+
+        Ok(Value::Object(selected))
+    }
+}
+
+/// Split a comma-separated list, ignoring whitespace and empty entries
+fn parse_list(list: Option<&str>) -> Vec<&str> {
+    list.unwrap_or("")
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use serde_json::json;
+
+    fn body() -> Value {
+        json!({ "id": "doc-1", "title": "Dune", "toc": [1, 2, 3], "itemCount": 3 })
+    }
+
+    fn select(fields: Option<&str>, exclude: Option<&str>) -> Result<Value, ApiError> {
+        FieldSelection {
+            fields: fields.map(String::from),
+            exclude: exclude.map(String::from),
+        }
+        .apply(&body())
+    }
+
+    #[test]
+    fn test_no_selection_returns_everything() {
+        assert_eq!(select(None, None).unwrap(), body());
+    }
+
+    #[test]
+    fn test_fields_keeps_id() {
+        let value = select(Some("title, itemCount"), None).unwrap();
+        assert_eq!(
+            value,
+            json!({ "id": "doc-1", "title": "Dune", "itemCount": 3 })
+        );
+    }
+
+    #[test]
+    fn test_exclude() {
+        let value = select(None, Some("toc")).unwrap();
+        assert_eq!(
+            value,
+            json!({ "id": "doc-1", "title": "Dune", "itemCount": 3 })
+        );
+    }
+
+    #[test]
+    fn test_unknown_field_rejected() {
+        let err = select(Some("tilte"), None).unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+        assert!(err.detail().contains("tilte"));
+    }
+
+    #[test]
+    fn test_fields_and_exclude_conflict() {
+        assert!(select(Some("title"), Some("toc")).is_err());
+    }
+}
@@ -1,19 +1,25 @@
 //! File serving routes
 //!
-//! Serves book files and covers from S3 storage.
+//! Serves book files and covers from S3 storage. Files the storage tiering
+//! task archived are restored on request; while an archive restore runs the
+//! response is 503 with a `retrieving` problem type and `Retry-After`.
 
 use axum::{
     body::Body,
     extract::{Path, State},
     http::{header, StatusCode},
-    response::Response,
+    response::{IntoResponse, Response},
     routing::get,
     Router,
 };
 
-use crate::error::{AppError, Result};
+use crate::error::{ApiError, AppError, Result};
+use crate::library::{Availability, StorageTiering};
 use crate::state::AppState;
 
+/// Seconds a client should wait before asking for a file being restored
+const RETRIEVING_RETRY_SECS: &str = "3600";
+
 /// Create the files router
 pub fn router() -> Router<AppState> {
     Router::new().route("/*path", get(serve_file))
@@ -26,6 +32,13 @@ async fn serve_file(
 ) -> Result<Response> {
     let s3_client = state.s3_client();
 
+    let tiering = StorageTiering::new(s3_client.clone(), state.db().clone(), state.config());
+    if tiering.ensure_available(&path).await? == Availability::Retrieving {
+        let error = ApiError::unavailable("The file is being restored from archive storage")
+            .with_type("retrieving");
+        return Ok(([(header::RETRY_AFTER, RETRIEVING_RETRY_SECS)], error).into_response());
+    }
+
     // Get object metadata first
     let metadata = s3_client.head_object(&path).await?;
 
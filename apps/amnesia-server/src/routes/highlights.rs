@@ -1,15 +1,20 @@
 //! Highlights API routes
 
 use axum::{
-    extract::Path,
+    extract::{Path, Query, State},
     http::StatusCode,
     routing::{delete, get, patch, post},
     Json, Router,
 };
+use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
 
-use crate::db::{CreateHighlight, Highlight, HighlightRepository, UpdateHighlight};
+use crate::db::{
+    AnnotationType, CreateHighlight, DocumentFormat, Highlight, HighlightQuery,
+    HighlightRepository, HighlightSort, PaletteRepository, UpdateHighlight,
+};
 use crate::error::{AppError, Result};
+use crate::pagination::{PageInfo, PageParams, SortOrder};
 use crate::state::AppState;
 
 /// Extended state with database pool
@@ -36,41 +41,163 @@ pub fn router(pool: SqlitePool) -> Router<AppState> {
         .layer(axum::Extension(state))
 }
 
+/// Paginated highlight list
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HighlightListResponse {
+    pub highlights: Vec<Highlight>,
+    #[serde(flatten)]
+    pub page: PageInfo,
+}
+
+/// Sorting and filter parameters for highlight lists
+#[derive(Debug, Default, Deserialize)]
+pub struct HighlightListQuery {
+    /// Sort key (default: created for all highlights, position within a book)
+    pub sort: Option<HighlightSort>,
+    /// Sort direction (default: desc for created/updated, asc for position)
+    pub order: Option<SortOrder>,
+    pub color: Option<String>,
+    /// Palette category, by name or hex
+    pub category: Option<String>,
+    #[serde(rename = "type")]
+    pub annotation_type: Option<AnnotationType>,
+    pub format: Option<DocumentFormat>,
+}
+
+impl HighlightListQuery {
+    /// Build a repository query, using `default_sort` when no key was given
+    ///
+    /// A category is looked up in the palette, rejecting unknown ones.
+    async fn into_query(
+        self,
+        pool: &SqlitePool,
+        page: PageParams,
+        default_sort: HighlightSort,
+    ) -> Result<HighlightQuery> {
+        let category = match self.category {
+            Some(ref category) => {
+                let palette = PaletteRepository::new(pool).get(None).await?;
+                Some(palette.lookup(category)?.clone())
+            }
+            None => None,
+        };
+        let sort = self.sort.unwrap_or(default_sort);
+        let order = self.order.unwrap_or(match sort {
+            HighlightSort::Position => SortOrder::Asc,
+            HighlightSort::Created | HighlightSort::Updated => SortOrder::Desc,
+        });
+
+        Ok(HighlightQuery {
+            color: self.color,
+            category,
+            annotation_type: self.annotation_type,
+            document_format: self.format,
+            sort,
+            order,
+            page,
+            ..Default::default()
+        })
+    }
+}
+
+/// Run a highlight query and wrap the page in a list envelope
+async fn list_page(
+    state: &HighlightsState,
+    query: HighlightQuery,
+) -> Result<HighlightListResponse> {
+    let repo = HighlightRepository::new(&state.pool);
+    let (highlights, total) = repo.list_paged(&query).await?;
+    Ok(HighlightListResponse {
+        highlights,
+        page: query.page.info(total),
+    })
+}
+
+/// The palette category for a requested highlight color
+///
+/// Highlights store the category name, so a hex from the palette and a
+/// differently cased name are stored alike. Colors outside the palette are
+/// rejected.
+async fn canonical_color(pool: &SqlitePool, color: Option<&str>) -> Result<Option<String>> {
+    let Some(color) = color else {
+        return Ok(None);
+    };
+    let palette = PaletteRepository::new(pool).get(None).await?;
+    Ok(Some(palette.lookup(color)?.name.clone()))
+}
+
+/// Fill in missing page labels on a book's highlights from its loaded PDF
+async fn fill_page_labels(app: &AppState, book_id: &str, highlights: &mut [Highlight]) {
+    if highlights
+        .iter()
+        .all(|h| h.page_label.is_some() || h.page.is_none())
+    {
+        return;
+    }
+    if let Some(labels) = app.page_labels(book_id).await {
+        for highlight in highlights {
+            highlight.fill_page_label(&labels);
+        }
+    }
+}
+
 /// List all highlights
 async fn list_all_highlights(
     axum::Extension(state): axum::Extension<HighlightsState>,
-) -> Result<Json<Vec<Highlight>>> {
-    let repo = HighlightRepository::new(&state.pool);
-    let highlights = repo.list(None).await?;
-    Ok(Json(highlights))
+    Query(page): Query<PageParams>,
+    Query(params): Query<HighlightListQuery>,
+) -> Result<Json<HighlightListResponse>> {
+    let query = params
+        .into_query(&state.pool, page, HighlightSort::Created)
+        .await?;
+    Ok(Json(list_page(&state, query).await?))
 }
 
 /// List highlights for a specific book
 async fn list_book_highlights(
+    State(app): State<AppState>,
     axum::Extension(state): axum::Extension<HighlightsState>,
     Path(book_id): Path<String>,
-) -> Result<Json<Vec<Highlight>>> {
-    let repo = HighlightRepository::new(&state.pool);
-    let highlights = repo.list_for_book(&book_id, None).await?;
-    Ok(Json(highlights))
+    Query(page): Query<PageParams>,
+    Query(params): Query<HighlightListQuery>,
+) -> Result<Json<HighlightListResponse>> {
+    let query = HighlightQuery {
+        book_id: Some(book_id.clone()),
+        ..params
+            .into_query(&state.pool, page, HighlightSort::Position)
+            .await?
+    };
+    let mut response = list_page(&state, query).await?;
+    fill_page_labels(&app, &book_id, &mut response.highlights).await;
+    Ok(Json(response))
 }
 
 /// List PDF highlights for a specific page
 async fn list_pdf_page_highlights(
+    State(app): State<AppState>,
     axum::Extension(state): axum::Extension<HighlightsState>,
     Path((book_id, page)): Path<(String, i32)>,
 ) -> Result<Json<Vec<Highlight>>> {
     let repo = HighlightRepository::new(&state.pool);
-    let highlights = repo.list_for_pdf_page(&book_id, page, None).await?;
+    let mut highlights = repo.list_for_pdf_page(&book_id, page, None).await?;
+    fill_page_labels(&app, &book_id, &mut highlights).await;
     Ok(Json(highlights))
 }
 
-/// Create a new highlight
+/// Create a new highlight, storing the PDF page's printed label
 async fn create_highlight(
+    State(app): State<AppState>,
     axum::Extension(state): axum::Extension<HighlightsState>,
     Path(book_id): Path<String>,
-    Json(data): Json<CreateHighlight>,
+    Json(mut data): Json<CreateHighlight>,
 ) -> Result<(StatusCode, Json<Highlight>)> {
+    if data.page_label.is_none() && data.page.is_some() {
+        if let Some(labels) = app.page_labels(&book_id).await {
+            data.fill_page_label(&labels);
+        }
+    }
+    data.color = canonical_color(&state.pool, data.color.as_deref()).await?;
     let repo = HighlightRepository::new(&state.pool);
     let highlight = repo.create(&book_id, None, &data).await?;
     Ok((StatusCode::CREATED, Json(highlight)))
@@ -78,14 +205,17 @@ async fn create_highlight(
 
 /// Get a specific highlight
 async fn get_highlight(
+    State(app): State<AppState>,
     axum::Extension(state): axum::Extension<HighlightsState>,
     Path(id): Path<String>,
 ) -> Result<Json<Highlight>> {
     let repo = HighlightRepository::new(&state.pool);
-    let highlight = repo
+    let mut highlight = repo
         .get(&id)
         .await?
         .ok_or_else(|| AppError::NotFound(format!("Highlight not found: {}", id)))?;
+    let book_id = highlight.book_id.clone();
+    fill_page_labels(&app, &book_id, std::slice::from_mut(&mut highlight)).await;
     Ok(Json(highlight))
 }
 
@@ -93,8 +223,9 @@ async fn get_highlight(
 async fn update_highlight(
     axum::Extension(state): axum::Extension<HighlightsState>,
     Path(id): Path<String>,
-    Json(data): Json<UpdateHighlight>,
+    Json(mut data): Json<UpdateHighlight>,
 ) -> Result<Json<Highlight>> {
+    data.color = canonical_color(&state.pool, data.color.as_deref()).await?;
     let repo = HighlightRepository::new(&state.pool);
     let highlight = repo
         .update(&id, &data)
@@ -128,7 +259,7 @@ async fn count_highlights(
 }
 
 /// Search query parameters
-#[derive(serde::Deserialize)]
+#[derive(Deserialize)]
 struct SearchQuery {
     q: String,
 }
@@ -136,9 +267,15 @@ struct SearchQuery {
 /// Search highlights
 async fn search_highlights(
     axum::Extension(state): axum::Extension<HighlightsState>,
-    axum::extract::Query(query): axum::extract::Query<SearchQuery>,
-) -> Result<Json<Vec<Highlight>>> {
-    let repo = HighlightRepository::new(&state.pool);
-    let highlights = repo.search(None, &query.q).await?;
-    Ok(Json(highlights))
+    Query(search): Query<SearchQuery>,
+    Query(page): Query<PageParams>,
+    Query(params): Query<HighlightListQuery>,
+) -> Result<Json<HighlightListResponse>> {
+    let query = HighlightQuery {
+        text: Some(search.q),
+        ..params
+            .into_query(&state.pool, page, HighlightSort::Created)
+            .await?
+    };
+    Ok(Json(list_page(&state, query).await?))
 }
@@ -0,0 +1,642 @@
+//! Hypothes.is-compatible annotation API
+//!
+//! The subset of the [Hypothes.is API](https://h.readthedocs.io/en/latest/api-reference/v1/)
+//! its clients use to read and write annotations, served under
+//! [`API_PATH`] and backed by the annotations store, so an existing
+//! Hypothes.is client or browser extension pointed at this server writes
+//! into the library:
+//!
+//! - `GET /hypothesis/api`: links to the endpoints below
+//! - `GET /hypothesis/api/search`: filter by `uri` and `user`, paged with
+//!   `limit`/`offset` and ordered with `sort`/`order`
+//! - `POST /hypothesis/api/annotations`
+//! - `GET`/`PATCH`/`DELETE /hypothesis/api/annotations/:id`
+//!
+//! An annotation's `uri` names the book: `urn:x-los-libros:book:<id>` for a
+//! library book, anything else is kept as the book ID verbatim. TextQuote,
+//! TextPosition, Fragment (CFI), and Range selectors are stored as their
+//! annotation-store equivalents; other selectors are dropped. A
+//! Hypothes.is annotation with text is a note, one without text a
+//! highlight, and one without selectors either (a page note) a bookmark.
+//!
+//! There are no accounts: the `user` of a new annotation (`acct:name@...`
+//! or a plain name) becomes its `userId`. Groups, tags, replies, and
+//! moderation are not supported; everything is in the public group.
+
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::annotations::{
+    Annotation, AnnotationBody, AnnotationQuery, AnnotationRepository, AnnotationTarget,
+    AnnotationType, BodyType, Selector,
+};
+use crate::routes::opds::base_url;
+use crate::state::AppState;
+
+/// Path the API is served under
+pub const API_PATH: &str = "/hypothesis/api";
+
+/// URI prefix naming a library book
+const BOOK_URN_PREFIX: &str = "urn:x-los-libros:book:";
+
+/// Authority of the `acct:` user IDs this server returns
+const AUTHORITY: &str = "los-libros";
+
+/// User ID returned for annotations without a user
+const ANONYMOUS: &str = "anonymous";
+
+/// The only group
+const PUBLIC_GROUP: &str = "__world__";
+
+const CFI_CONFORMS_TO: &str = "http://www.idpf.org/epub/linking/cfi/epub-cfi.html";
+
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = 200;
+
+/// Create the Hypothes.is compatibility router
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(api_root))
+        .route("/search", get(search))
+        .route("/annotations", post(create_annotation))
+        .route(
+            "/annotations/:id",
+            get(get_annotation)
+                .patch(update_annotation)
+                .delete(delete_annotation),
+        )
+}
+
+/// An annotation as Hypothes.is represents it
+#[derive(Debug, Clone, Serialize)]
+pub struct HypothesisAnnotation {
+    pub id: String,
+    pub created: DateTime<Utc>,
+    pub updated: DateTime<Utc>,
+    /// `acct:name@authority`
+    pub user: String,
+    pub uri: String,
+    pub text: String,
+    pub tags: Vec<String>,
+    pub group: String,
+    pub permissions: Permissions,
+    pub target: Vec<HypothesisTarget>,
+    pub document: HashMap<String, Vec<String>>,
+    pub flagged: bool,
+    pub hidden: bool,
+}
+
+/// Who may read and change an annotation
+#[derive(Debug, Clone, Serialize)]
+pub struct Permissions {
+    pub read: Vec<String>,
+    pub admin: Vec<String>,
+    pub update: Vec<String>,
+    pub delete: Vec<String>,
+}
+
+/// The part of a document an annotation is about
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HypothesisTarget {
+    pub source: String,
+    #[serde(default)]
+    pub selector: Vec<HypothesisSelector>,
+}
+
+/// W3C selectors as Hypothes.is clients send them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum HypothesisSelector {
+    TextQuoteSelector {
+        exact: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prefix: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        suffix: Option<String>,
+    },
+    TextPositionSelector {
+        start: usize,
+        end: usize,
+    },
+    FragmentSelector {
+        value: String,
+        #[serde(
+            rename = "conformsTo",
+            default,
+            skip_serializing_if = "Option::is_none"
+        )]
+        conforms_to: Option<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    RangeSelector {
+        start_container: String,
+        start_offset: usize,
+        end_container: String,
+        end_offset: usize,
+    },
+    /// Any selector the store has no equivalent for
+    #[serde(other)]
+    Unsupported,
+}
+
+/// Body of `POST /annotations`
+#[derive(Debug, Deserialize)]
+pub struct CreateRequest {
+    pub uri: String,
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub target: Vec<HypothesisTarget>,
+    /// `acct:name@authority` or a plain name
+    pub user: Option<String>,
+    /// IDs of the annotations this replies to
+    #[serde(default)]
+    pub references: Vec<String>,
+}
+
+/// Body of `PATCH /annotations/:id`
+#[derive(Debug, Deserialize)]
+pub struct UpdateRequest {
+    pub text: Option<String>,
+    pub target: Option<Vec<HypothesisTarget>>,
+}
+
+/// Query parameters of `GET /search`
+#[derive(Debug, Deserialize)]
+pub struct SearchParams {
+    /// Document URI; `url` is accepted as an alias
+    #[serde(alias = "url")]
+    pub uri: Option<String>,
+    pub user: Option<String>,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+    /// `updated` (default) or `created`
+    pub sort: Option<String>,
+    /// `desc` (default) or `asc`
+    pub order: Option<String>,
+}
+
+/// Response of `GET /search`
+#[derive(Debug, Serialize)]
+pub struct SearchResponse {
+    pub total: usize,
+    pub rows: Vec<HypothesisAnnotation>,
+}
+
+/// Error in the Hypothes.is format
+#[derive(Debug)]
+pub struct HypothesisError {
+    status: StatusCode,
+    reason: String,
+}
+
+impl HypothesisError {
+    fn new(status: StatusCode, reason: impl Into<String>) -> Self {
+        Self {
+            status,
+            reason: reason.into(),
+        }
+    }
+
+    fn not_found(id: &str) -> Self {
+        Self::new(
+            StatusCode::NOT_FOUND,
+            format!("Annotation '{}' not found", id),
+        )
+    }
+}
+
+impl From<anyhow::Error> for HypothesisError {
+    fn from(e: anyhow::Error) -> Self {
+        tracing::error!("Hypothes.is API error: {}", e);
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    }
+}
+
+impl IntoResponse for HypothesisError {
+    fn into_response(self) -> Response {
+        let body = json!({ "status": "failure", "reason": self.reason });
+        (self.status, Json(body)).into_response()
+    }
+}
+
+/// Links to the supported endpoints, which clients read to find them
+async fn api_root(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let api = format!("{}{}", base_url(&state), API_PATH);
+    let link = |method: &str, path: &str, desc: &str| {
+        let url = format!("{}{}", api, path);
+        json!({ "method": method, "url": url, "desc": desc })
+    };
+    Json(json!({
+        "links": {
+            "annotation": {
+                "create": link("POST", "/annotations", "Create an annotation"),
+                "read": link("GET", "/annotations/:id", "Fetch an annotation"),
+                "update": link("PATCH", "/annotations/:id", "Update an annotation"),
+                "delete": link("DELETE", "/annotations/:id", "Delete an annotation"),
+            },
+            "search": link("GET", "/search", "Search for annotations"),
+        }
+    }))
+}
+
+/// Search annotations
+async fn search(
+    State(state): State<AppState>,
+    Query(params): Query<SearchParams>,
+) -> Result<Json<SearchResponse>, HypothesisError> {
+    let query = AnnotationQuery {
+        book_id: params.uri.as_deref().map(book_id_for_uri),
+        user_id: params.user.as_deref().and_then(user_id_for_acct),
+        ..Default::default()
+    };
+    let mut annotations = AnnotationRepository::new(state.db()).list(&query).await?;
+
+    let by_created = params.sort.as_deref() == Some("created");
+    annotations.sort_by_key(|a| {
+        if by_created {
+            a.created_at
+        } else {
+            a.updated_at
+        }
+    });
+    if params.order.as_deref() != Some("asc") {
+        annotations.reverse();
+    }
+
+    let total = annotations.len();
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+    let rows = annotations
+        .iter()
+        .skip(params.offset.unwrap_or(0))
+        .take(limit)
+        .map(to_hypothesis)
+        .collect();
+    Ok(Json(SearchResponse { total, rows }))
+}
+
+/// Create an annotation
+async fn create_annotation(
+    State(state): State<AppState>,
+    Json(req): Json<CreateRequest>,
+) -> Result<Json<HypothesisAnnotation>, HypothesisError> {
+    if !req.references.is_empty() {
+        return Err(HypothesisError::new(
+            StatusCode::BAD_REQUEST,
+            "Replies are not supported",
+        ));
+    }
+    if req.uri.trim().is_empty() {
+        return Err(HypothesisError::new(
+            StatusCode::BAD_REQUEST,
+            "uri: Required",
+        ));
+    }
+
+    let annotation = from_hypothesis(&req);
+    AnnotationRepository::new(state.db())
+        .save(&annotation)
+        .await?;
+    Ok(Json(to_hypothesis(&annotation)))
+}
+
+/// Fetch an annotation
+async fn get_annotation(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<HypothesisAnnotation>, HypothesisError> {
+    let annotation = AnnotationRepository::new(state.db())
+        .get(&id)
+        .await?
+        .ok_or_else(|| HypothesisError::not_found(&id))?;
+    Ok(Json(to_hypothesis(&annotation)))
+}
+
+/// Change an annotation's text or target
+async fn update_annotation(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(req): Json<UpdateRequest>,
+) -> Result<Json<HypothesisAnnotation>, HypothesisError> {
+    let repo = AnnotationRepository::new(state.db());
+    let mut annotation = repo
+        .get(&id)
+        .await?
+        .ok_or_else(|| HypothesisError::not_found(&id))?;
+
+    if let Some(targets) = &req.target {
+        // Keep selectors Hypothes.is cannot show, such as PDF regions
+        let mut selectors: Vec<Selector> = annotation
+            .target
+            .selectors
+            .drain(..)
+            .filter(|selector| to_hypothesis_selector(selector).is_none())
+            .collect();
+        selectors.extend(store_selectors(targets));
+        annotation.target.selectors = selectors;
+    }
+    if let Some(text) = req.text {
+        set_text(&mut annotation, text);
+    }
+    annotation.updated_at = Utc::now();
+
+    repo.save(&annotation).await?;
+    Ok(Json(to_hypothesis(&annotation)))
+}
+
+/// Delete an annotation
+async fn delete_annotation(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<serde_json::Value>, HypothesisError> {
+    if !AnnotationRepository::new(state.db()).delete(&id).await? {
+        return Err(HypothesisError::not_found(&id));
+    }
+    Ok(Json(json!({ "id": id, "deleted": true })))
+}
+
+/// Book ID named by a Hypothes.is document URI
+fn book_id_for_uri(uri: &str) -> String {
+    uri.strip_prefix(BOOK_URN_PREFIX).unwrap_or(uri).to_string()
+}
+
+/// Document URI for a book ID, the inverse of [`book_id_for_uri`]
+fn uri_for_book(book_id: &str) -> String {
+    if book_id.contains(':') {
+        book_id.to_string()
+    } else {
+        format!("{}{}", BOOK_URN_PREFIX, book_id)
+    }
+}
+
+/// User ID of `acct:name@authority` or a plain name
+fn user_id_for_acct(user: &str) -> Option<String> {
+    let name = user.strip_prefix("acct:").unwrap_or(user);
+    let name = name.split_once('@').map_or(name, |(name, _)| name).trim();
+    (!name.is_empty() && name != ANONYMOUS).then(|| name.to_string())
+}
+
+fn acct(user_id: Option<&str>) -> String {
+    format!("acct:{}@{}", user_id.unwrap_or(ANONYMOUS), AUTHORITY)
+}
+
+fn to_hypothesis(annotation: &Annotation) -> HypothesisAnnotation {
+    let uri = uri_for_book(&annotation.book_id);
+    let user = acct(annotation.user_id.as_deref());
+    let source = match annotation.target.source.as_str() {
+        "" => uri.clone(),
+        source => source.to_string(),
+    };
+    let selector: Vec<HypothesisSelector> = annotation
+        .target
+        .selectors
+        .iter()
+        .filter_map(to_hypothesis_selector)
+        .collect();
+    let text = annotation
+        .body
+        .as_ref()
+        .and_then(|body| body.value.clone())
+        .unwrap_or_default();
+
+    HypothesisAnnotation {
+        id: annotation.id.clone(),
+        created: annotation.created_at,
+        updated: annotation.updated_at,
+        uri,
+        text,
+        tags: Vec::new(),
+        group: PUBLIC_GROUP.to_string(),
+        permissions: Permissions {
+            read: vec![format!("group:{}", PUBLIC_GROUP)],
+            admin: vec![user.clone()],
+            update: vec![user.clone()],
+            delete: vec![user.clone()],
+        },
+        user,
+        target: vec![HypothesisTarget { source, selector }],
+        document: HashMap::new(),
+        flagged: false,
+        hidden: false,
+    }
+}
+
+fn to_hypothesis_selector(selector: &Selector) -> Option<HypothesisSelector> {
+    match selector {
+        Selector::TextQuote {
+            exact,
+            prefix,
+            suffix,
+        } => Some(HypothesisSelector::TextQuoteSelector {
+            exact: exact.clone(),
+            prefix: prefix.clone(),
+            suffix: suffix.clone(),
+        }),
+        Selector::TextPosition { start, end } => Some(HypothesisSelector::TextPositionSelector {
+            start: *start,
+            end: *end,
+        }),
+        Selector::Fragment { value } => Some(HypothesisSelector::FragmentSelector {
+            value: value.clone(),
+            conforms_to: value
+                .starts_with("epubcfi(")
+                .then(|| CFI_CONFORMS_TO.to_string()),
+        }),
+        Selector::DomRange {
+            start_container_path,
+            start_offset,
+            end_container_path,
+            end_offset,
+        } => Some(HypothesisSelector::RangeSelector {
+            start_container: start_container_path.clone(),
+            start_offset: *start_offset,
+            end_container: end_container_path.clone(),
+            end_offset: *end_offset,
+        }),
+        _ => None,
+    }
+}
+
+fn store_selectors(targets: &[HypothesisTarget]) -> Vec<Selector> {
+    targets
+        .iter()
+        .flat_map(|target| &target.selector)
+        .filter_map(|selector| match selector.clone() {
+            HypothesisSelector::TextQuoteSelector {
+                exact,
+                prefix,
+                suffix,
+            } => Some(Selector::TextQuote {
+                exact,
+                prefix,
+                suffix,
+            }),
+            HypothesisSelector::TextPositionSelector { start, end } => {
+                Some(Selector::TextPosition { start, end })
+            }
+            HypothesisSelector::FragmentSelector { value, .. } => {
+                Some(Selector::Fragment { value })
+            }
+            HypothesisSelector::RangeSelector {
+                start_container,
+                start_offset,
+                end_container,
+                end_offset,
+            } => Some(Selector::DomRange {
+                start_container_path: start_container,
+                start_offset,
+                end_container_path: end_container,
+                end_offset,
+            }),
+            HypothesisSelector::Unsupported => None,
+        })
+        .collect()
+}
+
+fn from_hypothesis(req: &CreateRequest) -> Annotation {
+    let book_id = book_id_for_uri(&req.uri);
+    // A target on the document as a whole needs no source of its own
+    let source = req
+        .target
+        .first()
+        .map(|target| target.source.as_str())
+        .filter(|source| *source != req.uri)
+        .unwrap_or_default();
+    let target = AnnotationTarget::with_selectors(source, store_selectors(&req.target));
+
+    let mut annotation = if !req.text.is_empty() {
+        Annotation::new_note(&book_id, target, &req.text)
+    } else if target.selectors.is_empty() {
+        Annotation::new_bookmark(&book_id, target)
+    } else {
+        Annotation::new_highlight(&book_id, target)
+    };
+    if let Some(user_id) = req.user.as_deref().and_then(user_id_for_acct) {
+        annotation = annotation.with_user(&user_id);
+    }
+    annotation
+}
+
+/// Replace an annotation's text, turning highlights into notes and back
+fn set_text(annotation: &mut Annotation, text: String) {
+    let links = annotation
+        .body
+        .take()
+        .map(|body| body.links)
+        .unwrap_or_default();
+
+    if text.is_empty() {
+        if annotation.annotation_type == AnnotationType::Note {
+            annotation.annotation_type = AnnotationType::Highlight;
+        }
+        annotation.set_links(links);
+        return;
+    }
+
+    if annotation.annotation_type == AnnotationType::Highlight {
+        annotation.annotation_type = AnnotationType::Note;
+    }
+    annotation.body = Some(AnnotationBody {
+        body_type: BodyType::TextualBody,
+        value: Some(text),
+        format: Some("text/plain".to_string()),
+        links,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hypothesis_round_trip() {
+        let req: CreateRequest = serde_json::from_value(json!({
+            "uri": "urn:x-los-libros:book:dune",
+            "text": "The spice must flow",
+            "user": "acct:ana@hypothes.is",
+            "group": "__world__",
+            "tags": ["spice"],
+            "target": [{
+                "source": "urn:x-los-libros:book:dune",
+                "selector": [
+                    { "type": "TextQuoteSelector", "exact": "spice", "prefix": "the " },
+                    { "type": "TextPositionSelector", "start": 10, "end": 15 },
+                    { "type": "CssSelector", "value": "p" },
+                ],
+            }],
+        }))
+        .unwrap();
+
+        let annotation = from_hypothesis(&req);
+        assert_eq!(annotation.book_id, "dune");
+        assert_eq!(annotation.user_id.as_deref(), Some("ana"));
+        assert_eq!(annotation.annotation_type, AnnotationType::Note);
+        assert_eq!(annotation.target.source, "");
+        assert_eq!(annotation.target.selectors.len(), 2);
+
+        let out = to_hypothesis(&annotation);
+        assert_eq!(out.uri, "urn:x-los-libros:book:dune");
+        assert_eq!(out.user, "acct:ana@los-libros");
+        assert_eq!(out.text, "The spice must flow");
+        assert_eq!(out.target[0].source, out.uri);
+        assert_eq!(
+            out.target[0].selector[0],
+            HypothesisSelector::TextQuoteSelector {
+                exact: "spice".to_string(),
+                prefix: Some("the ".to_string()),
+                suffix: None,
+            }
+        );
+
+        let json = serde_json::to_value(&out).unwrap();
+        assert_eq!(
+            json["target"][0]["selector"][1]["type"],
+            "TextPositionSelector"
+        );
+        assert_eq!(json["permissions"]["read"][0], "group:__world__");
+
+        // Web pages keep their URL as the book ID
+        assert_eq!(
+            book_id_for_uri("https://example.com/a"),
+            "https://example.com/a"
+        );
+        assert_eq!(
+            uri_for_book("https://example.com/a"),
+            "https://example.com/a"
+        );
+        assert_eq!(user_id_for_acct("acct:anonymous@los-libros"), None);
+        assert_eq!(user_id_for_acct("ben").as_deref(), Some("ben"));
+    }
+
+    #[test]
+    fn test_set_text() {
+        let target = AnnotationTarget::from_cfi("ch1.xhtml", "epubcfi(/6/4!/4/2/1:0)");
+        let mut annotation = Annotation::new_highlight("dune", target);
+
+        set_text(&mut annotation, "A note".to_string());
+        assert_eq!(annotation.annotation_type, AnnotationType::Note);
+        assert_eq!(to_hypothesis(&annotation).text, "A note");
+        let fragment = &to_hypothesis(&annotation).target[0].selector[0];
+        assert!(matches!(
+            fragment,
+            HypothesisSelector::FragmentSelector {
+                conforms_to: Some(_),
+                ..
+            }
+        ));
+
+        set_text(&mut annotation, String::new());
+        assert_eq!(annotation.annotation_type, AnnotationType::Highlight);
+        assert!(annotation.body.is_none());
+    }
+}
@@ -0,0 +1,234 @@
+//! Request body size limits
+//!
+//! Every body is read through an extractor (`Json`, `Bytes`, `Multipart`),
+//! and those stop reading once a body passes the `DefaultBodyLimit` of its
+//! route, so no request is buffered past its limit. The server sets
+//! [`JSON_BODY_LIMIT`] for everything; routers and routes that take more,
+//! or should take less, set their own, and the innermost limit wins:
+//!
+//! | Route | Limit |
+//! |-------|-------|
+//! | Default | [`JSON_BODY_LIMIT`] (1MB) |
+//! | `/api/v1/annotations` | [`ANNOTATION_BODY_LIMIT`] (256KB) |
+//! | `/api/v1/annotations/import/kindle` | 64MB |
+//! | `/api/v1/sync` | [`SYNC_BODY_LIMIT`] (16MB) |
+//! | `/api/v1/client-errors` | 64KB |
+//! | `/api/v1/upload/:id/chunks/:index` | [`MAX_CHUNK_SIZE`] (16MB) |
+//! | `POST /api/v1/documents`, `POST /api/v1/pdf` | [`DOCUMENT_UPLOAD_LIMIT`] (200MB) |
+//!
+//! Large files go through the chunked upload API, so no single request
+//! carries more than a chunk. Multipart document uploads are streamed to a
+//! temporary file by [`spool_field`], which rejects the file as soon as it
+//! passes its limit, so the request body is never held in memory; the file
+//! is only read back once the whole upload has arrived and been accepted.
+//!
+//! axum answers an oversized body with a plain-text 413;
+//! [`problem_payload_too_large`] turns it into problem+json like every
+//! other error.
+//!
+//! [`MAX_CHUNK_SIZE`]: crate::upload::MAX_CHUNK_SIZE
+
+use std::path::Path;
+
+use axum::{
+    extract::{
+        multipart::{Field, MultipartError},
+        Request,
+    },
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tempfile::NamedTempFile;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::error::ApiError;
+
+/// Default limit for request bodies (1MB)
+pub const JSON_BODY_LIMIT: usize = 1024 * 1024;
+
+/// Limit for annotation bodies (256KB); notes are text, not attachments
+pub const ANNOTATION_BODY_LIMIT: usize = 256 * 1024;
+
+/// Limit for sync pushes and pulls (16MB), which batch operations
+pub const SYNC_BODY_LIMIT: usize = 16 * 1024 * 1024;
+
+/// Limit for single-request document uploads (200MB)
+pub const DOCUMENT_UPLOAD_LIMIT: usize = 200 * 1024 * 1024;
+
+/// Middleware that renders axum's plain-text 413 as problem+json
+pub async fn problem_payload_too_large(request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+    if response.status() == StatusCode::PAYLOAD_TOO_LARGE
+        && response.extensions().get::<ApiError>().is_none()
+    {
+        return payload_too_large("Request body exceeds this endpoint's size limit")
+            .into_response();
+    }
+    response
+}
+
+/// A multipart file field written to a temporary file, deleted on drop
+pub struct SpooledUpload {
+    file: NamedTempFile,
+    len: usize,
+}
+
+impl SpooledUpload {
+    /// Size of the file in bytes
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Path of the temporary file
+    pub fn path(&self) -> &Path {
+        self.file.path()
+    }
+
+    /// The first `len` bytes of the file, for sniffing its format
+    pub async fn head(&self, len: usize) -> Result<Vec<u8>, ApiError> {
+        let file = tokio::fs::File::open(self.path())
+            .await
+            .map_err(spool_error)?;
+        let mut head = Vec::with_capacity(len);
+        file.take(len as u64)
+            .read_to_end(&mut head)
+            .await
+            .map_err(spool_error)?;
+        Ok(head)
+    }
+
+    /// Read the whole file into memory
+    pub async fn read(&self) -> Result<Vec<u8>, ApiError> {
+        tokio::fs::read(self.path()).await.map_err(spool_error)
+    }
+}
+
+/// Stream a multipart file field to a temporary file, failing once it
+/// passes `max` bytes
+pub async fn spool_field(mut field: Field<'_>, max: usize) -> Result<SpooledUpload, ApiError> {
+    let file = NamedTempFile::new().map_err(spool_error)?;
+    let mut writer = tokio::fs::File::from_std(file.as_file().try_clone().map_err(spool_error)?);
+    let mut len = 0;
+    while let Some(chunk) = field
+        .chunk()
+        .await
+        .map_err(|e| multipart_error("Failed to read file data", e))?
+    {
+        len += chunk.len();
+        if len > max {
+            return Err(payload_too_large(format!(
+                "File exceeds the upload limit of {} bytes; use the chunked upload API",
+                max
+            )));
+        }
+        writer.write_all(&chunk).await.map_err(spool_error)?;
+    }
+    writer.flush().await.map_err(spool_error)?;
+    Ok(SpooledUpload { file, len })
+}
+
+fn spool_error(error: std::io::Error) -> ApiError {
+    tracing::error!("Failed to spool upload: {}", error);
+    ApiError::internal("Failed to store upload").with_reason(error.to_string())
+}
+
+/// Map a multipart error, keeping 413 for bodies over the limit
+pub fn multipart_error(detail: &str, error: MultipartError) -> ApiError {
+    tracing::error!("{}: {}", detail, error);
+    if error.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        payload_too_large(detail).with_reason(error.body_text())
+    } else {
+        ApiError::bad_request(detail).with_reason(error.body_text())
+    }
+}
+
+fn payload_too_large(detail: impl Into<String>) -> ApiError {
+    ApiError::new(StatusCode::PAYLOAD_TOO_LARGE, detail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::{Body, Bytes},
+        extract::{DefaultBodyLimit, Multipart},
+        http::{self, header},
+        middleware,
+        routing::post,
+        Router,
+    };
+    use tower::ServiceExt;
+
+    fn request(body: &'static str) -> http::Request<Body> {
+        http::Request::post("/").body(Body::from(body)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body_is_problem_json() {
+        let app = Router::new()
+            .route(
+                "/",
+                post(|body: Bytes| async move { body.len().to_string() }),
+            )
+            .layer(DefaultBodyLimit::max(8))
+            .layer(middleware::from_fn(problem_payload_too_large));
+
+        let response = app.clone().oneshot(request("12345678")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app.oneshot(request("123456789")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            crate::error::PROBLEM_JSON
+        );
+    }
+
+    fn multipart_request(file: &str) -> http::Request<Body> {
+        let body = format!(
+            "--b\r\nContent-Disposition: form-data; name=\"file\"; filename=\"a.pdf\"\r\n\r\n{}\r\n--b--\r\n",
+            file
+        );
+        http::Request::post("/")
+            .header(header::CONTENT_TYPE, "multipart/form-data; boundary=b")
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_spool_field_streams_to_disk() {
+        let app = Router::new().route(
+            "/",
+            post(|mut multipart: Multipart| async move {
+                let field = multipart.next_field().await.unwrap().unwrap();
+                let upload = spool_field(field, 8).await?;
+                let data = upload.read().await?;
+                assert_eq!(upload.len(), data.len());
+                assert_eq!(upload.head(4).await?, &data[..4]);
+                let path = upload.path().to_path_buf();
+                drop(upload);
+                assert!(!path.exists());
+                Ok::<_, ApiError>(String::from_utf8(data).unwrap())
+            }),
+        );
+
+        let response = app
+            .clone()
+            .oneshot(multipart_request("%PDF-1.7"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), 64)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"%PDF-1.7");
+
+        let response = app.oneshot(multipart_request("%PDF-1.7 x")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
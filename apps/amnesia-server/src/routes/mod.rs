@@ -1,16 +1,28 @@
 //! Route modules for Los Libros Server
 
+pub mod account;
+pub mod admin;
 pub mod annotations;
+pub mod bandwidth;
 pub mod bibliography;
+pub mod client_errors;
+pub mod collections;
 // pub mod books;  // Deprecated - use documents API instead
 pub mod documents;
 pub mod extract;
+pub mod feeds;
+pub mod fields;
 pub mod files;
 pub mod health;
 pub mod highlights;
+pub mod hypothesis;
+pub mod limits;
+pub mod notifications;
 pub mod opds;
+pub mod openapi;
 pub mod pdf;
 pub mod progress;
+pub mod request_id;
 pub mod search;
 pub mod sync;
 pub mod upload;
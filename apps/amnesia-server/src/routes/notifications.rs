@@ -0,0 +1,155 @@
+//! Notification center API endpoints
+//!
+//! Lists the events recorded in [`crate::db::NotificationRepository`] and
+//! tracks which ones the user has seen, so the client can show a bell with
+//! an unread count.
+
+use axum::{
+    extract::{Path, Query, State},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::db::{Notification, NotificationRepository};
+use crate::error::ApiError;
+use crate::pagination::{DEFAULT_LIMIT, MAX_LIMIT};
+use crate::state::AppState;
+
+use super::account::AccountQuery;
+
+/// Create the notifications router
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_notifications))
+        .route("/unread-count", get(unread_count))
+        .route("/read-all", post(mark_all_read))
+        .route("/:id/read", post(mark_read))
+}
+
+/// Notification list parameters
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct NotificationQuery {
+    pub user_id: Option<String>,
+    /// Only list notifications not yet marked read
+    #[serde(default)]
+    pub unread_only: bool,
+    /// Maximum notifications to return (default 50, max 500)
+    pub limit: Option<usize>,
+}
+
+/// A user's notifications
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationList {
+    /// Newest first
+    pub notifications: Vec<Notification>,
+    pub unread_count: i64,
+}
+
+/// Unread notification count
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UnreadCount {
+    pub unread_count: i64,
+}
+
+/// Result of marking all notifications read
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MarkAllReadResponse {
+    /// Notifications that were unread
+    pub marked: u64,
+}
+
+/// List notifications, newest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/notifications",
+    tag = "notifications",
+    params(NotificationQuery),
+    responses(
+        (status = 200, description = "Notifications and the unread count", body = NotificationList),
+        (status = 500, description = "Database error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn list_notifications(
+    State(state): State<AppState>,
+    Query(query): Query<NotificationQuery>,
+) -> Result<Json<NotificationList>, ApiError> {
+    let repo = NotificationRepository::new(state.db());
+    let user_id = query.user_id.as_deref();
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    Ok(Json(NotificationList {
+        notifications: repo.list(user_id, query.unread_only, limit as i64).await?,
+        unread_count: repo.unread_count(user_id).await?,
+    }))
+}
+
+/// Count unread notifications
+#[utoipa::path(
+    get,
+    path = "/api/v1/notifications/unread-count",
+    tag = "notifications",
+    params(AccountQuery),
+    responses(
+        (status = 200, description = "Unread count", body = UnreadCount),
+        (status = 500, description = "Database error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn unread_count(
+    State(state): State<AppState>,
+    Query(query): Query<AccountQuery>,
+) -> Result<Json<UnreadCount>, ApiError> {
+    let unread_count = NotificationRepository::new(state.db())
+        .unread_count(query.user_id.as_deref())
+        .await?;
+    Ok(Json(UnreadCount { unread_count }))
+}
+
+/// Mark a notification read
+#[utoipa::path(
+    post,
+    path = "/api/v1/notifications/{id}/read",
+    tag = "notifications",
+    params(("id" = String, Path, description = "Notification ID")),
+    responses(
+        (status = 200, description = "The notification, marked read", body = Notification),
+        (status = 404, description = "Notification not found", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn mark_read(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Notification>, ApiError> {
+    NotificationRepository::new(state.db())
+        .mark_read(&id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("Notification '{}' not found", id)))
+}
+
+/// Mark all of a user's notifications read
+#[utoipa::path(
+    post,
+    path = "/api/v1/notifications/read-all",
+    tag = "notifications",
+    params(AccountQuery),
+    responses(
+        (status = 200, description = "Number of notifications marked read", body = MarkAllReadResponse),
+        (status = 500, description = "Database error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn mark_all_read(
+    State(state): State<AppState>,
+    Query(query): Query<AccountQuery>,
+) -> Result<Json<MarkAllReadResponse>, ApiError> {
+    let marked = NotificationRepository::new(state.db())
+        .mark_all_read(query.user_id.as_deref())
+        .await?;
+    Ok(Json(MarkAllReadResponse { marked }))
+}
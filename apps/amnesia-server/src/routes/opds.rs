@@ -1,22 +1,29 @@
 //! OPDS catalog routes
 //!
 //! Serves OPDS 1.2 Atom feeds for book browsing and acquisition.
+//!
+//! Every feed that lists books accepts maturity parameters (see
+//! [`MaturityFilter`]): `maxMaturity=all-ages|teen|mature|adult` hides books
+//! rated above it (and unrated ones with `includeUnrated=false`), and
+//! `excludeWarnings=violence,gore` hides books carrying those content
+//! warnings. A kids' reader can point at `/opds/all?maxMaturity=all-ages`.
 
 use axum::{
-    extract::{Path, Query, State},
+    extract::{Path, Query, RawQuery, State},
     http::header,
     response::{IntoResponse, Response},
     routing::get,
     Router,
 };
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+use crate::db::{MaturityRepository, TextStatsFilter, TextStatsRepository};
 use crate::error::Result;
-use crate::library::{LibraryBook, LibraryScanner};
-use crate::opds::{serialize_feed, mime, OPDSEntry, OPDSFeed};
+use crate::library::{LibraryBook, LibraryScanner, MaturityFilter};
+use crate::opds::{mime, serialize_feed, EntryTemplate, OPDSEntry, OPDSFeed};
 use crate::state::AppState;
 
 /// Cached library state
@@ -71,8 +78,22 @@ impl IntoResponse for OPDSResponse {
     }
 }
 
+/// Library books with maturity overrides applied, limited by `filter`
+pub(crate) async fn visible_books(
+    state: &AppState,
+    cache: &LibraryCache,
+    filter: &MaturityFilter,
+) -> Result<Vec<LibraryBook>> {
+    let mut books = cache.get_books().await;
+    MaturityRepository::new(state.db())
+        .apply(&mut books)
+        .await?;
+    books.retain(|book| filter.allows(book));
+    Ok(books)
+}
+
 /// Get base URL from request
-fn base_url(state: &AppState) -> String {
+pub(crate) fn base_url(state: &AppState) -> String {
     format!(
         "http://{}:{}",
         state.config().server.host,
@@ -80,6 +101,11 @@ fn base_url(state: &AppState) -> String {
     )
 }
 
+/// Template describing books in entries, from the server's config
+pub(crate) fn entry_template(state: &AppState) -> EntryTemplate {
+    EntryTemplate::new(&state.config().opds)
+}
+
 /// Root catalog
 async fn root_catalog(State(state): State<AppState>) -> Result<OPDSResponse> {
     let feed = OPDSFeed::root_catalog(&base_url(&state));
@@ -91,8 +117,9 @@ async fn root_catalog(State(state): State<AppState>) -> Result<OPDSResponse> {
 async fn all_books(
     State(state): State<AppState>,
     axum::Extension(cache): axum::Extension<LibraryCache>,
+    Query(filter): Query<MaturityFilter>,
 ) -> Result<OPDSResponse> {
-    let books = cache.get_books().await;
+    let books = visible_books(&state, &cache, &filter).await?;
     let base = base_url(&state);
 
     let mut feed = OPDSFeed::acquisition("All Books", &format!("{}/opds/all", base));
@@ -102,7 +129,7 @@ async fn all_books(
         link_type: Some(mime::ATOM_CATALOG.to_string()),
         title: None,
     });
-    feed.add_books(&books, &base);
+    feed.add_books(&books, &base, &entry_template(&state));
 
     let xml = serialize_feed(&feed)?;
     Ok(OPDSResponse(xml))
@@ -112,8 +139,9 @@ async fn all_books(
 async fn authors_list(
     State(state): State<AppState>,
     axum::Extension(cache): axum::Extension<LibraryCache>,
+    Query(filter): Query<MaturityFilter>,
 ) -> Result<OPDSResponse> {
-    let books = cache.get_books().await;
+    let books = visible_books(&state, &cache, &filter).await?;
     let base = base_url(&state);
 
     // Group by author
@@ -153,8 +181,9 @@ async fn author_books(
     State(state): State<AppState>,
     axum::Extension(cache): axum::Extension<LibraryCache>,
     Path(name): Path<String>,
+    Query(filter): Query<MaturityFilter>,
 ) -> Result<OPDSResponse> {
-    let books = cache.get_books().await;
+    let books = visible_books(&state, &cache, &filter).await?;
     let base = base_url(&state);
 
     let author_books: Vec<_> = books
@@ -170,7 +199,7 @@ async fn author_books(
         link_type: Some(mime::ATOM_CATALOG.to_string()),
         title: None,
     });
-    feed.add_books(&author_books, &base);
+    feed.add_books(&author_books, &base, &entry_template(&state));
 
     let xml = serialize_feed(&feed)?;
     Ok(OPDSResponse(xml))
@@ -180,8 +209,9 @@ async fn author_books(
 async fn series_list(
     State(state): State<AppState>,
     axum::Extension(cache): axum::Extension<LibraryCache>,
+    Query(filter): Query<MaturityFilter>,
 ) -> Result<OPDSResponse> {
-    let books = cache.get_books().await;
+    let books = visible_books(&state, &cache, &filter).await?;
     let base = base_url(&state);
 
     // Group by series
@@ -221,8 +251,9 @@ async fn series_books(
     State(state): State<AppState>,
     axum::Extension(cache): axum::Extension<LibraryCache>,
     Path(name): Path<String>,
+    Query(filter): Query<MaturityFilter>,
 ) -> Result<OPDSResponse> {
-    let books = cache.get_books().await;
+    let books = visible_books(&state, &cache, &filter).await?;
     let base = base_url(&state);
 
     let mut series_books: Vec<_> = books
@@ -246,7 +277,7 @@ async fn series_books(
         link_type: Some(mime::ATOM_CATALOG.to_string()),
         title: None,
     });
-    feed.add_books(&series_books, &base);
+    feed.add_books(&series_books, &base, &entry_template(&state));
 
     let xml = serialize_feed(&feed)?;
     Ok(OPDSResponse(xml))
@@ -256,8 +287,9 @@ async fn series_books(
 async fn recent_books(
     State(state): State<AppState>,
     axum::Extension(cache): axum::Extension<LibraryCache>,
+    Query(filter): Query<MaturityFilter>,
 ) -> Result<OPDSResponse> {
-    let mut books = cache.get_books().await;
+    let mut books = visible_books(&state, &cache, &filter).await?;
     let base = base_url(&state);
 
     // Sort by added date, most recent first
@@ -273,7 +305,7 @@ async fn recent_books(
         link_type: Some(mime::ATOM_CATALOG.to_string()),
         title: None,
     });
-    feed.add_books(&recent, &base);
+    feed.add_books(&recent, &base, &entry_template(&state));
 
     let xml = serialize_feed(&feed)?;
     Ok(OPDSResponse(xml))
@@ -281,19 +313,36 @@ async fn recent_books(
 
 #[derive(Deserialize)]
 struct SearchQuery {
+    #[serde(default)]
     q: String,
 }
 
 /// Search books
+///
+/// Reading level parameters (`minGrade`, `maxGrade`, ...; see
+/// [`TextStatsFilter`]) restrict results to books with matching text
+/// statistics.
 async fn search_books(
     State(state): State<AppState>,
     axum::Extension(cache): axum::Extension<LibraryCache>,
     Query(query): Query<SearchQuery>,
+    Query(level): Query<TextStatsFilter>,
+    Query(filter): Query<MaturityFilter>,
+    RawQuery(raw_query): RawQuery,
 ) -> Result<OPDSResponse> {
-    let books = cache.get_books().await;
+    let books = visible_books(&state, &cache, &filter).await?;
     let base = base_url(&state);
     let q = query.q.to_lowercase();
 
+    let level_matches: Option<HashSet<String>> = if level.is_empty() {
+        None
+    } else {
+        let stats = TextStatsRepository::new(state.db())
+            .find(&level, i64::MAX)
+            .await?;
+        Some(stats.into_iter().map(|s| s.book_id).collect())
+    };
+
     // Simple search: match title, author, or tags
     let results: Vec<_> = books
         .iter()
@@ -303,12 +352,17 @@ async fn search_books(
                 || b.tags.iter().any(|t| t.to_lowercase().contains(&q))
                 || b.series.as_ref().map_or(false, |s| s.to_lowercase().contains(&q))
         })
+        .filter(|b| {
+            level_matches
+                .as_ref()
+                .is_none_or(|ids| ids.contains(&b.stable_id()))
+        })
         .cloned()
         .collect();
 
     let mut feed = OPDSFeed::acquisition(
         &format!("Search: {}", query.q),
-        &format!("{}/opds/search?q={}", base, urlencoding::encode(&query.q)),
+        &format!("{}/opds/search?{}", base, raw_query.unwrap_or_default()),
     );
     feed.links.push(crate::opds::OPDSLink {
         href: "/opds".to_string(),
@@ -316,7 +370,7 @@ async fn search_books(
         link_type: Some(mime::ATOM_CATALOG.to_string()),
         title: None,
     });
-    feed.add_books(&results, &base);
+    feed.add_books(&results, &base, &entry_template(&state));
 
     let xml = serialize_feed(&feed)?;
     Ok(OPDSResponse(xml))
@@ -0,0 +1,300 @@
+//! OpenAPI specification and Swagger UI
+//!
+//! Serves the generated spec at `/api/v1/openapi.json` and an interactive
+//! Swagger UI at `/api/docs`. The spec is assembled from the `#[utoipa::path]`
+//! annotations on the route handlers, so it stays in sync with the code.
+
+use axum::Router;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::annotations::{
+    Annotation, AnnotationBody, AnnotationStyle, AnnotationTarget, AnnotationType,
+    AnnotationVersion, BodyType, DocumentLink, LinkRelation, PdfPosition, PdfRect, Selector,
+    SyncMetadata,
+};
+use crate::byte_cache::ByteCacheStats;
+use crate::db::{
+    DuplicatePageReport, HighlightPalette, MaturityOverride, Notification, NotificationKind,
+    PageAnimation, PaletteColor, ProgressLocator, ReaderPreferences, TextAlign,
+};
+use crate::document::{
+    CharPosition, DocumentCacheUsage, DocumentFonts, DocumentWarning, FontSubstitution, Rect,
+    RequestedFont, StructuredText, TextBlock, TextDirection, TextLine, TocEntry, WebLink,
+};
+use crate::error::ProblemDetails;
+use crate::library::{DuplicatePage, MaturityRating};
+use crate::link_check::{LinkHealth, LinkReport};
+use crate::mupdf::PoolStats;
+use crate::pagination::PageInfo;
+use crate::scheduler::{TaskRun, TaskStatus};
+use crate::state::AppState;
+use crate::sync::{
+    Conflict, ConflictResolution, EntityType, OperationType, PullRequest, PullResponse,
+    PushRequest, PushResponse, SyncOperation, SyncSnapshot, SyncStatus,
+};
+use crate::upload::{
+    ChunkUploadResponse, FinalizeResponse, HandshakeRequest, HandshakeResponse, SessionStatus,
+};
+
+use super::{
+    account, admin, annotations, client_errors, collections, documents, notifications, sync,
+    upload,
+};
+
+/// Path the OpenAPI JSON is served from
+pub const OPENAPI_PATH: &str = "/api/v1/openapi.json";
+
+/// Path the Swagger UI is served from
+pub const SWAGGER_UI_PATH: &str = "/api/docs";
+
+/// OpenAPI document for the documents, annotations, sync, account, collection,
+/// notification, and upload APIs
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Amnesia Server API",
+        description = "Document parsing, annotation, sync, and chunked upload endpoints"
+    ),
+    paths(
+        documents::list_documents,
+        documents::batch_documents,
+        documents::upload_document,
+        documents::get_document,
+        documents::delete_document,
+        documents::render_item,
+        documents::get_structured_text,
+        documents::get_document_text,
+        documents::get_item_text,
+        documents::render_thumbnail,
+        documents::print_item,
+        documents::search_document,
+        documents::get_search_index,
+        documents::get_chapter_checksums,
+        documents::get_link_graph,
+        documents::start_link_check,
+        documents::get_link_check,
+        documents::get_book_index,
+        documents::lookup_book_index,
+        documents::get_glossary,
+        documents::define_term,
+        documents::get_document_fonts,
+        documents::normalize_selection,
+        documents::open_document,
+        documents::close_document,
+        documents::get_resource,
+        annotations::list_annotations,
+        annotations::list_book_annotations,
+        annotations::count_book_annotations,
+        annotations::list_book_backlinks,
+        annotations::create_annotation,
+        annotations::get_annotation,
+        annotations::update_annotation,
+        annotations::delete_annotation,
+        annotations::list_annotation_versions,
+        annotations::restore_annotation_version,
+        sync::push_changes,
+        sync::pull_changes,
+        sync::get_sync_status,
+        sync::get_snapshot,
+        account::get_preferences,
+        account::put_preferences,
+        account::get_palette,
+        account::put_palette,
+        collections::download_collection,
+        collections::get_download_job,
+        collections::get_download_file,
+        notifications::list_notifications,
+        notifications::unread_count,
+        notifications::mark_read,
+        notifications::mark_all_read,
+        upload::handshake,
+        upload::upload_chunk,
+        upload::finalize,
+        upload::get_session,
+        upload::cancel_session,
+        client_errors::report_client_error,
+        admin::cache_report,
+        admin::list_tasks,
+        admin::get_task,
+        admin::list_maturity_overrides,
+        admin::set_maturity_override,
+        admin::delete_maturity_override,
+        admin::list_duplicate_pages,
+        admin::get_duplicate_pages,
+        admin::export_vault,
+    ),
+    components(schemas(
+        ProblemDetails,
+        PageInfo,
+        // Documents
+        documents::DocumentListResponse,
+        documents::DocumentSummary,
+        documents::DocumentDetailResponse,
+        documents::CreatorResponse,
+        DocumentWarning,
+        DocumentFonts,
+        RequestedFont,
+        FontSubstitution,
+        documents::ChapterChecksumResponse,
+        documents::LinkGraphResponse,
+        documents::ChapterLinksResponse,
+        documents::CrossReferenceResponse,
+        documents::LinkCheckJob,
+        documents::LinkCheckStatus,
+        LinkReport,
+        LinkHealth,
+        WebLink,
+        documents::BookIndexResponse,
+        documents::IndexEntryResponse,
+        documents::IndexLocatorResponse,
+        documents::IndexLookupResponse,
+        documents::IndexMatchResponse,
+        documents::GlossaryResponse,
+        documents::GlossaryEntryResponse,
+        documents::UploadResponse,
+        documents::BatchDocumentsRequest,
+        documents::NormalizeSelectionRequest,
+        documents::NormalizedSelectionResponse,
+        documents::BatchDocumentsResponse,
+        documents::BatchDocumentEntry,
+        documents::ProgressSummary,
+        documents::OpenDocumentRequest,
+        documents::OpenDocumentResponse,
+        documents::CloseDocumentResponse,
+        ProgressLocator,
+        documents::DocumentUploadForm,
+        documents::SearchResultResponse,
+        documents::SearchHit,
+        documents::SearchParagraph,
+        documents::BoundingBoxResponse,
+        TocEntry,
+        StructuredText,
+        TextBlock,
+        TextLine,
+        CharPosition,
+        TextDirection,
+        Rect,
+        // Annotations
+        annotations::CreateAnnotationRequest,
+        annotations::AnnotationTargetRequest,
+        annotations::TextQuoteRequest,
+        annotations::AnnotationBodyRequest,
+        annotations::AnnotationStyleRequest,
+        annotations::UpdateAnnotationRequest,
+        annotations::AnnotationResponse,
+        annotations::AnnotationsListResponse,
+        annotations::AnnotationVersionsResponse,
+        annotations::CountResponse,
+        annotations::ErrorResponse,
+        Annotation,
+        AnnotationType,
+        AnnotationTarget,
+        Selector,
+        PdfPosition,
+        PdfRect,
+        AnnotationBody,
+        BodyType,
+        DocumentLink,
+        LinkRelation,
+        AnnotationStyle,
+        AnnotationVersion,
+        SyncMetadata,
+        // Sync
+        PushRequest,
+        PushResponse,
+        PullRequest,
+        PullResponse,
+        SyncOperation,
+        OperationType,
+        EntityType,
+        SyncStatus,
+        SyncSnapshot,
+        Conflict,
+        ConflictResolution,
+        // Account
+        ReaderPreferences,
+        TextAlign,
+        PageAnimation,
+        HighlightPalette,
+        PaletteColor,
+        // Collections
+        collections::DownloadJob,
+        collections::DownloadStatus,
+        // Notifications
+        Notification,
+        NotificationKind,
+        notifications::NotificationList,
+        notifications::UnreadCount,
+        notifications::MarkAllReadResponse,
+        // Upload
+        HandshakeRequest,
+        HandshakeResponse,
+        ChunkUploadResponse,
+        FinalizeResponse,
+        SessionStatus,
+        upload::SessionStatusResponse,
+        // Client errors
+        client_errors::ClientErrorReport,
+        client_errors::ClientErrorLevel,
+        client_errors::ClientErrorResponse,
+        // Admin
+        admin::CacheReport,
+        admin::DocumentCacheReport,
+        admin::PdfCacheReport,
+        admin::PdfCacheUsage,
+        admin::MaturityOverrideRequest,
+        MaturityOverride,
+        MaturityRating,
+        DuplicatePageReport,
+        DuplicatePage,
+        ByteCacheStats,
+        PoolStats,
+        DocumentCacheUsage,
+        TaskStatus,
+        TaskRun,
+    )),
+    tags(
+        (name = "documents", description = "Unified PDF/EPUB document API"),
+        (name = "annotations", description = "Highlights, notes, and bookmarks"),
+        (name = "sync", description = "Multi-device sync"),
+        (name = "account", description = "Per-user reader preferences and highlight palette"),
+        (name = "collections", description = "Collection downloads"),
+        (name = "notifications", description = "Ingest, OCR, and sync conflict events"),
+        (name = "upload", description = "Resumable chunked uploads"),
+        (name = "client-errors", description = "Front-end failure reports"),
+        (name = "admin", description = "Server diagnostics"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Router serving the OpenAPI spec and Swagger UI
+pub fn router() -> Router<AppState> {
+    Router::new().merge(SwaggerUi::new(SWAGGER_UI_PATH).url(OPENAPI_PATH, ApiDoc::openapi()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec_covers_route_groups() {
+        let spec = ApiDoc::openapi();
+        let paths = &spec.paths.paths;
+
+        assert!(paths.contains_key("/api/v1/documents/{id}"));
+        assert!(paths.contains_key("/api/v1/annotations"));
+        assert!(paths.contains_key("/api/v1/sync/push"));
+        assert!(paths.contains_key("/api/v1/account/preferences"));
+        assert!(paths.contains_key("/api/v1/account/palette"));
+        assert!(paths.contains_key("/api/v1/collections/{id}/download"));
+        assert!(paths.contains_key("/api/v1/notifications"));
+        assert!(paths.contains_key("/api/v1/upload/handshake"));
+    }
+
+    #[test]
+    fn test_spec_serializes() {
+        let json = ApiDoc::openapi().to_pretty_json().unwrap();
+        assert!(json.contains("ProblemDetails"));
+    }
+}
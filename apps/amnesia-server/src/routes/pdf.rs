@@ -29,22 +29,36 @@ use axum::{
     routing::{delete, get, post, put},
     Json, Router,
 };
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "ocr")]
+use crate::db::NotificationRepository;
 use crate::db::{CreateHighlight, Highlight, HighlightRepository, UpdateHighlight};
 use crate::document::TocEntry;
-use crate::ocr::{OcrRect, OcrRequest, OcrResult, OcrService, OcrServiceConfig};
+use crate::error::ApiError;
+#[cfg(feature = "ocr")]
+use crate::ocr::{
+    failed_notification, finished_notification, FailedOcr, OcrRect, OcrRequest, OcrResult,
+    OcrService, OcrServiceConfig,
+};
+use crate::pagination::{compare_text, contains_ignore_case, PageInfo, PageParams, SortOrder};
 use crate::pdf::{
-    FormField, FormInfo, ImageFormat, PageRenderRequest, ParsedPdf, PdfMetadata, PdfSearchResult,
-    SignatureInfo, TextLayer,
+    CachedPdf, FormField, FormInfo, ImageFormat, PageRenderRequest, ParsedPdf, PdfMetadata,
+    PdfSearchResult, SignatureInfo, TextLayer,
 };
 use crate::state::AppState;
 
+use super::documents::check_isolated;
+use super::fields::FieldSelection;
+use super::limits::{multipart_error, spool_field, DOCUMENT_UPLOAD_LIMIT};
+
 /// Response for PDF list
 #[derive(Serialize)]
 pub struct PdfListResponse {
     pub pdfs: Vec<PdfSummary>,
-    pub total: usize,
+    #[serde(flatten)]
+    pub page: PageInfo,
 }
 
 /// Summary of a PDF for list view
@@ -55,6 +69,48 @@ pub struct PdfSummary {
     pub title: String,
     pub author: Option<String>,
     pub page_count: usize,
+    pub added_at: DateTime<Utc>,
+}
+
+/// Sort keys for the PDF list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PdfSortKey {
+    Title,
+    Author,
+    Added,
+}
+
+/// Sorting and filter parameters for the PDF list
+#[derive(Debug, Default, Deserialize)]
+pub struct PdfListQuery {
+    /// Sort key (default: title)
+    pub sort: Option<PdfSortKey>,
+    /// Sort direction (default: asc, or desc when sorting by `added`)
+    pub order: Option<SortOrder>,
+    /// Case-insensitive match against the author
+    pub author: Option<String>,
+    /// Case-insensitive match against the title
+    pub q: Option<String>,
+}
+
+impl PdfListQuery {
+    /// Whether a cached PDF passes the author/title filters
+    fn matches(&self, cached: &CachedPdf) -> bool {
+        let metadata = &cached.pdf.metadata;
+        if let Some(author) = &self.author {
+            match &metadata.author {
+                Some(name) if contains_ignore_case(name, author) => {}
+                _ => return false,
+            }
+        }
+        if let Some(q) = &self.q {
+            if !contains_ignore_case(&metadata.title, q) {
+                return false;
+            }
+        }
+        true
+    }
 }
 
 /// Full PDF details response
@@ -78,29 +134,6 @@ pub struct UploadResponse {
     pub page_count: usize,
 }
 
-/// Error response
-#[derive(Serialize)]
-pub struct ErrorResponse {
-    pub error: String,
-    pub details: Option<String>,
-}
-
-impl ErrorResponse {
-    fn new(error: impl Into<String>) -> Self {
-        Self {
-            error: error.into(),
-            details: None,
-        }
-    }
-
-    fn with_details(error: impl Into<String>, details: impl Into<String>) -> Self {
-        Self {
-            error: error.into(),
-            details: Some(details.into()),
-        }
-    }
-}
-
 /// Query parameters for page rendering
 #[derive(Debug, Deserialize)]
 pub struct PageRenderQuery {
@@ -151,27 +184,22 @@ async fn validate_page_range(
     state: &AppState,
     id: &str,
     page: usize,
-) -> Result<ParsedPdf, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<ParsedPdf, ApiError> {
     // First check PDF exists
-    let pdf = state.pdf_cache().get_pdf(id).await.ok_or_else(|| {
-        (
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new(format!("PDF '{}' not found", id))),
-        )
-    })?;
+    let pdf = state
+        .pdf_cache()
+        .get_pdf(id)
+        .await
+        .ok_or_else(|| ApiError::not_found(format!("PDF '{}' not found", id)))?;
 
     // Validate page range (pages are 1-indexed)
     if page < 1 || page > pdf.page_count {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse::with_details(
-                "Invalid page number",
-                format!(
-                    "Page {} is out of range. PDF '{}' has {} pages (valid range: 1-{})",
-                    page, id, pdf.page_count, pdf.page_count
-                ),
+        return Err(
+            ApiError::bad_request("Invalid page number").with_reason(format!(
+                "Page {} is out of range. PDF '{}' has {} pages (valid range: 1-{})",
+                page, id, pdf.page_count, pdf.page_count
             )),
-        ));
+        );
     }
 
     Ok(pdf)
@@ -218,15 +246,19 @@ async fn add_deprecation_header(
 /// **DEPRECATED**: Use `/api/v1/documents` instead.
 /// This router adds deprecation headers to all responses.
 pub fn router() -> Router<AppState> {
-    Router::new()
-        .route("/", get(list_pdfs).post(upload_pdf))
+    let router = Router::new()
+        // Only uploads take large bodies
+        .route(
+            "/",
+            get(list_pdfs)
+                .post(upload_pdf)
+                .layer(DefaultBodyLimit::max(DOCUMENT_UPLOAD_LIMIT)),
+        )
         .route("/:id", get(get_pdf).delete(delete_pdf))
         .route("/:id/pages/:page", get(render_page))
         .route("/:id/pages/:page/text", get(get_text_layer))
         .route("/:id/pages/:page/thumbnail", get(render_thumbnail))
-        .route("/:id/pages/:page/ocr", post(ocr_region))
         .route("/:id/search", get(search_pdf))
-        .route("/:id/ocr/providers", get(list_ocr_providers))
         // Annotations (per Phase 8 plan)
         .route(
             "/:id/annotations",
@@ -241,32 +273,67 @@ pub fn router() -> Router<AppState> {
         // Forms (Phase 9)
         .route("/:id/forms", get(get_form_info))
         .route("/:id/forms/fields", get(list_form_fields))
-        .route("/:id/forms/signatures", get(list_signatures))
-        // Allow up to 200MB uploads for large PDFs
-        .layer(DefaultBodyLimit::max(200 * 1024 * 1024))
+        .route("/:id/forms/signatures", get(list_signatures));
+
+    // OCR endpoints are only available when the `ocr` feature is enabled
+    #[cfg(feature = "ocr")]
+    let router = router
+        .route("/:id/pages/:page/ocr", post(ocr_region))
+        .route("/:id/ocr/providers", get(list_ocr_providers));
+
+    router
         // Add deprecation headers to all responses
         .layer(middleware::from_fn(add_deprecation_header))
 }
 
-/// List all cached PDFs
-async fn list_pdfs(State(state): State<AppState>) -> Json<PdfListResponse> {
-    let pdfs = state.pdf_cache().get_all_pdfs().await;
-
-    let summaries: Vec<PdfSummary> = pdfs
-        .iter()
-        .map(|pdf| PdfSummary {
-            id: pdf.id.clone(),
-            title: pdf.metadata.title.clone(),
-            author: pdf.metadata.author.clone(),
-            page_count: pdf.page_count,
-        })
+/// List cached PDFs with sorting, filtering, and pagination
+async fn list_pdfs(
+    State(state): State<AppState>,
+    Query(page): Query<PageParams>,
+    Query(query): Query<PdfListQuery>,
+) -> Json<PdfListResponse> {
+    let mut pdfs: Vec<CachedPdf> = state
+        .pdf_cache()
+        .get_all_cached()
+        .await
+        .into_iter()
+        .filter(|cached| query.matches(cached))
         .collect();
 
-    let total = summaries.len();
+    let key = query.sort.unwrap_or(PdfSortKey::Title);
+    let order = query.order.unwrap_or(match key {
+        PdfSortKey::Added => SortOrder::Desc,
+        _ => SortOrder::Asc,
+    });
+    pdfs.sort_by(|a, b| match key {
+        PdfSortKey::Title => compare_text(
+            Some(a.pdf.metadata.title.as_str()),
+            Some(b.pdf.metadata.title.as_str()),
+            order,
+        ),
+        PdfSortKey::Author => compare_text(
+            a.pdf.metadata.author.as_deref(),
+            b.pdf.metadata.author.as_deref(),
+            order,
+        ),
+        PdfSortKey::Added => order.apply(a.added_at.cmp(&b.added_at)),
+    });
+
+    let (pdfs, page) = page.apply(pdfs);
+    let summaries = pdfs
+        .into_iter()
+        .map(|cached| PdfSummary {
+            id: cached.pdf.id,
+            title: cached.pdf.metadata.title,
+            author: cached.pdf.metadata.author,
+            page_count: cached.pdf.page_count,
+            added_at: cached.added_at,
+        })
+        .collect();
 
     Json(PdfListResponse {
         pdfs: summaries,
-        total,
+        page,
     })
 }
 
@@ -274,17 +341,15 @@ async fn list_pdfs(State(state): State<AppState>) -> Json<PdfListResponse> {
 async fn upload_pdf(
     State(state): State<AppState>,
     mut multipart: Multipart,
-) -> Result<Json<UploadResponse>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<UploadResponse>, ApiError> {
     tracing::debug!("Starting PDF upload processing");
 
     // Extract the file from multipart
-    while let Some(field) = multipart.next_field().await.map_err(|e| {
-        tracing::error!("Failed to read multipart field: {}", e);
-        (
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse::with_details("Failed to read upload", e.to_string())),
-        )
-    })? {
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| multipart_error("Failed to read upload", e))?
+    {
         let name = field.name().unwrap_or("").to_string();
         let filename = field.file_name().map(|s| s.to_string());
         let content_type = field.content_type().map(|s| s.to_string());
@@ -306,16 +371,15 @@ async fn upload_pdf(
                 .unwrap_or(&filename)
                 .to_string();
 
-            // Read file bytes
-            let data = field.bytes().await.map_err(|e| {
-                tracing::error!("Failed to read file data: {}", e);
-                (
-                    StatusCode::BAD_REQUEST,
-                    Json(ErrorResponse::with_details("Failed to read file data", e.to_string())),
-                )
-            })?;
+            let upload = spool_field(field, DOCUMENT_UPLOAD_LIMIT).await?;
+
+            tracing::debug!("Spooled {} bytes of file data", upload.len());
+
+            let data = upload.read().await?;
+            drop(upload);
 
-            tracing::debug!("Read {} bytes of file data", data.len());
+            // With isolation enabled, a worker must parse the PDF first
+            check_isolated(&state, &data, &pdf_id).await?;
 
             // Parse the PDF
             let pdf = state
@@ -324,10 +388,7 @@ async fn upload_pdf(
                 .await
                 .map_err(|e| {
                     tracing::error!("Failed to parse PDF: {}", e);
-                    (
-                        StatusCode::BAD_REQUEST,
-                        Json(ErrorResponse::with_details("Failed to parse PDF", e.to_string())),
-                    )
+                    ApiError::bad_request("Failed to parse PDF").with_reason(e.to_string())
                 })?;
 
             tracing::info!("PDF uploaded: '{}' with {} pages", pdf.id, pdf.page_count);
@@ -360,47 +421,43 @@ async fn upload_pdf(
     }
 
     tracing::warn!("No file field found in multipart upload");
-    Err((
-        StatusCode::BAD_REQUEST,
-        Json(ErrorResponse::new("No file provided. Use field name 'file' or 'pdf'")),
+    Err(ApiError::bad_request(
+        "No file provided. Use field name 'file' or 'pdf'",
     ))
 }
 
-/// Get PDF details by ID
+/// Get PDF details by ID, optionally limited to selected fields
 async fn get_pdf(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<Json<PdfDetailResponse>, (StatusCode, Json<ErrorResponse>)> {
+    Query(selection): Query<FieldSelection>,
+) -> Result<Json<serde_json::Value>, ApiError> {
     tracing::debug!("Looking up PDF with ID: '{}'", id);
 
     let pdf = state.pdf_cache().get_pdf(&id).await.ok_or_else(|| {
         tracing::warn!("PDF '{}' not found in cache", id);
-        (
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new(format!("PDF '{}' not found", id))),
-        )
+        ApiError::not_found(format!("PDF '{}' not found", id))
     })?;
 
-    Ok(Json(PdfDetailResponse {
+    let detail = PdfDetailResponse {
         id: pdf.id,
         metadata: pdf.metadata,
         toc: pdf.toc,
         page_count: pdf.page_count,
         has_text_layer: pdf.has_text_layer,
         orientation: format!("{:?}", pdf.orientation).to_lowercase(),
-    }))
+    };
+
+    Ok(Json(selection.apply(&detail)?))
 }
 
 /// Delete a PDF from cache
 async fn delete_pdf(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<StatusCode, ApiError> {
     if !state.pdf_cache().contains(&id).await {
-        return Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new(format!("PDF '{}' not found", id))),
-        ));
+        return Err(ApiError::not_found(format!("PDF '{}' not found", id)));
     }
 
     state.pdf_cache().remove(&id).await;
@@ -412,7 +469,7 @@ async fn render_page(
     State(state): State<AppState>,
     Path((id, page)): Path<(String, usize)>,
     Query(query): Query<PageRenderQuery>,
-) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Response, ApiError> {
     // Validate page exists before rendering
     validate_page_range(&state, &id, page).await?;
 
@@ -435,13 +492,8 @@ async fn render_page(
         .render_page(&id, &request)
         .await
         .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::with_details(
-                    format!("Failed to render page {} of PDF '{}'", page, id),
-                    e.to_string(),
-                )),
-            )
+            ApiError::internal(format!("Failed to render page {} of PDF '{}'", page, id))
+                .with_reason(e.to_string())
         })?;
 
     // Build response with proper content type
@@ -460,7 +512,7 @@ async fn render_thumbnail(
     State(state): State<AppState>,
     Path((id, page)): Path<(String, usize)>,
     Query(query): Query<ThumbnailQuery>,
-) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Response, ApiError> {
     // Validate page exists before rendering
     validate_page_range(&state, &id, page).await?;
 
@@ -469,13 +521,11 @@ async fn render_thumbnail(
         .render_thumbnail(&id, page, query.size)
         .await
         .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::with_details(
-                    format!("Failed to render thumbnail for page {} of PDF '{}'", page, id),
-                    e.to_string(),
-                )),
-            )
+            ApiError::internal(format!(
+                "Failed to render thumbnail for page {} of PDF '{}'",
+                page, id
+            ))
+            .with_reason(e.to_string())
         })?;
 
     // Thumbnails are always JPEG
@@ -493,7 +543,7 @@ async fn render_thumbnail(
 async fn get_text_layer(
     State(state): State<AppState>,
     Path((id, page)): Path<(String, usize)>,
-) -> Result<Json<TextLayer>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<TextLayer>, ApiError> {
     // Validate page exists before extracting text
     validate_page_range(&state, &id, page).await?;
 
@@ -502,13 +552,11 @@ async fn get_text_layer(
         .get_text_layer(&id, page)
         .await
         .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::with_details(
-                    format!("Failed to get text layer for page {} of PDF '{}'", page, id),
-                    e.to_string(),
-                )),
-            )
+            ApiError::internal(format!(
+                "Failed to get text layer for page {} of PDF '{}'",
+                page, id
+            ))
+            .with_reason(e.to_string())
         })?;
 
     Ok(Json(layer))
@@ -519,41 +567,34 @@ async fn search_pdf(
     State(state): State<AppState>,
     Path(id): Path<String>,
     Query(query): Query<SearchQuery>,
-) -> Result<Json<Vec<PdfSearchResult>>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<Vec<PdfSearchResult>>, ApiError> {
     let results = state
         .pdf_cache()
         .search(&id, &query.q, query.limit)
         .await
         .map_err(|e| {
-            (
-                StatusCode::NOT_FOUND,
-                Json(ErrorResponse::with_details(
-                    format!("Failed to search PDF '{}'", id),
-                    e.to_string(),
-                )),
-            )
+            ApiError::not_found(format!("Failed to search PDF '{}'", id)).with_reason(e.to_string())
         })?;
 
     Ok(Json(results))
 }
 
 /// Response for available OCR providers
+#[cfg(feature = "ocr")]
 #[derive(Serialize)]
 pub struct OcrProvidersResponse {
     pub providers: Vec<String>,
 }
 
 /// List available OCR providers
+#[cfg(feature = "ocr")]
 async fn list_ocr_providers(
     State(_state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<Json<OcrProvidersResponse>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<OcrProvidersResponse>, ApiError> {
     // Check if PDF exists
     if !_state.pdf_cache().contains(&id).await {
-        return Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new(format!("PDF '{}' not found", id))),
-        ));
+        return Err(ApiError::not_found(format!("PDF '{}' not found", id)));
     }
 
     // Create OCR service and get available providers
@@ -572,11 +613,12 @@ async fn list_ocr_providers(
 }
 
 /// OCR a region of a PDF page
+#[cfg(feature = "ocr")]
 async fn ocr_region(
     State(state): State<AppState>,
     Path((id, page)): Path<(String, usize)>,
     Json(request): Json<OcrRequest>,
-) -> Result<Json<OcrResult>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<OcrResult>, ApiError> {
     tracing::debug!(
         "OCR request for PDF '{}' page {} region {:?}",
         id,
@@ -586,10 +628,7 @@ async fn ocr_region(
 
     // Check if PDF exists
     if !state.pdf_cache().contains(&id).await {
-        return Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new(format!("PDF '{}' not found", id))),
-        ));
+        return Err(ApiError::not_found(format!("PDF '{}' not found", id)));
     }
 
     // Create OCR service
@@ -606,17 +645,31 @@ async fn ocr_region(
             request.language.as_deref(),
             state.pdf_cache(),
         )
-        .await
-        .map_err(|e| {
+        .await;
+    let notifications = NotificationRepository::new(state.db());
+    let result = match result {
+        Ok(result) => result,
+        Err(e) => {
             tracing::error!("OCR failed for PDF '{}' page {}: {}", id, page, e);
-            (
+            let failed = FailedOcr {
+                pdf_id: id.clone(),
+                page,
+                rect: request.rect.clone(),
+                provider: request.provider,
+                language: request.language.clone(),
+                attempts: 1,
+            };
+            let retrying = state.ocr_retries().push(failed, &e);
+            notifications
+                .notify(failed_notification(&id, page, &e, retrying))
+                .await;
+            return Err(ApiError::new(
                 e.status_code(),
-                Json(ErrorResponse::with_details(
-                    format!("OCR failed for page {} of PDF '{}'", page, id),
-                    e.to_string(),
-                )),
+                format!("OCR failed for page {} of PDF '{}'", page, id),
             )
-        })?;
+            .with_reason(e.to_string()));
+        }
+    };
 
     tracing::info!(
         "OCR completed for PDF '{}' page {} using {:?} (confidence: {:.1}%)",
@@ -625,6 +678,9 @@ async fn ocr_region(
         result.provider,
         result.confidence
     );
+    notifications
+        .notify(finished_notification(&id, page, &result))
+        .await;
 
     Ok(Json(result))
 }
@@ -645,35 +701,31 @@ pub struct AnnotationsResponse {
 async fn list_annotations(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<Json<AnnotationsResponse>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<AnnotationsResponse>, ApiError> {
     // Check if PDF exists in cache
     if !state.pdf_cache().contains(&id).await {
-        return Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new(format!("PDF '{}' not found", id))),
-        ));
+        return Err(ApiError::not_found(format!("PDF '{}' not found", id)));
     }
 
     let repo = HighlightRepository::new(state.db());
     let annotations = repo
         .list_for_book(&id, None)
         .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::with_details(
-                    "Failed to list annotations",
-                    e.to_string(),
-                )),
-            )
-        })?;
+        .map_err(|e| ApiError::internal("Failed to list annotations").with_reason(e.to_string()))?;
 
     // Filter to only PDF annotations
-    let pdf_annotations: Vec<Highlight> = annotations
+    let mut pdf_annotations: Vec<Highlight> = annotations
         .into_iter()
         .filter(|a| a.document_format == "pdf")
         .collect();
 
+    // Label annotations stored before page labels were recorded
+    if let Some(labels) = state.page_labels(&id).await {
+        for annotation in &mut pdf_annotations {
+            annotation.fill_page_label(&labels);
+        }
+    }
+
     let total = pdf_annotations.len();
 
     Ok(Json(AnnotationsResponse {
@@ -687,31 +739,24 @@ async fn create_annotation(
     State(state): State<AppState>,
     Path(id): Path<String>,
     Json(mut data): Json<CreateHighlight>,
-) -> Result<(StatusCode, Json<Highlight>), (StatusCode, Json<ErrorResponse>)> {
+) -> Result<(StatusCode, Json<Highlight>), ApiError> {
     // Check if PDF exists in cache
     if !state.pdf_cache().contains(&id).await {
-        return Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new(format!("PDF '{}' not found", id))),
-        ));
+        return Err(ApiError::not_found(format!("PDF '{}' not found", id)));
     }
 
     // Force document_format to pdf
     data.document_format = Some("pdf".to_string());
 
+    // Record the printed page label for citations
+    if let Some(labels) = state.page_labels(&id).await {
+        data.fill_page_label(&labels);
+    }
+
     let repo = HighlightRepository::new(state.db());
-    let annotation = repo
-        .create(&id, None, &data)
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::with_details(
-                    "Failed to create annotation",
-                    e.to_string(),
-                )),
-            )
-        })?;
+    let annotation = repo.create(&id, None, &data).await.map_err(|e| {
+        ApiError::internal("Failed to create annotation").with_reason(e.to_string())
+    })?;
 
     Ok((StatusCode::CREATED, Json(annotation)))
 }
@@ -720,47 +765,25 @@ async fn create_annotation(
 async fn get_annotation(
     State(state): State<AppState>,
     Path((id, annotation_id)): Path<(String, String)>,
-) -> Result<Json<Highlight>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<Highlight>, ApiError> {
     // Check if PDF exists in cache
     if !state.pdf_cache().contains(&id).await {
-        return Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new(format!("PDF '{}' not found", id))),
-        ));
+        return Err(ApiError::not_found(format!("PDF '{}' not found", id)));
     }
 
     let repo = HighlightRepository::new(state.db());
     let annotation = repo
         .get(&annotation_id)
         .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::with_details(
-                    "Failed to get annotation",
-                    e.to_string(),
-                )),
-            )
-        })?
-        .ok_or_else(|| {
-            (
-                StatusCode::NOT_FOUND,
-                Json(ErrorResponse::new(format!(
-                    "Annotation '{}' not found",
-                    annotation_id
-                ))),
-            )
-        })?;
+        .map_err(|e| ApiError::internal("Failed to get annotation").with_reason(e.to_string()))?
+        .ok_or_else(|| ApiError::not_found(format!("Annotation '{}' not found", annotation_id)))?;
 
     // Verify the annotation belongs to this PDF
     if annotation.book_id != id {
-        return Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new(format!(
-                "Annotation '{}' not found in PDF '{}'",
-                annotation_id, id
-            ))),
-        ));
+        return Err(ApiError::not_found(format!(
+            "Annotation '{}' not found in PDF '{}'",
+            annotation_id, id
+        )));
     }
 
     Ok(Json(annotation))
@@ -771,13 +794,10 @@ async fn update_annotation(
     State(state): State<AppState>,
     Path((id, annotation_id)): Path<(String, String)>,
     Json(data): Json<UpdateHighlight>,
-) -> Result<Json<Highlight>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<Highlight>, ApiError> {
     // Check if PDF exists in cache
     if !state.pdf_cache().contains(&id).await {
-        return Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new(format!("PDF '{}' not found", id))),
-        ));
+        return Err(ApiError::not_found(format!("PDF '{}' not found", id)));
     }
 
     let repo = HighlightRepository::new(state.db());
@@ -786,57 +806,22 @@ async fn update_annotation(
     let existing = repo
         .get(&annotation_id)
         .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::with_details(
-                    "Failed to get annotation",
-                    e.to_string(),
-                )),
-            )
-        })?
-        .ok_or_else(|| {
-            (
-                StatusCode::NOT_FOUND,
-                Json(ErrorResponse::new(format!(
-                    "Annotation '{}' not found",
-                    annotation_id
-                ))),
-            )
-        })?;
+        .map_err(|e| ApiError::internal("Failed to get annotation").with_reason(e.to_string()))?
+        .ok_or_else(|| ApiError::not_found(format!("Annotation '{}' not found", annotation_id)))?;
 
     if existing.book_id != id {
-        return Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new(format!(
-                "Annotation '{}' not found in PDF '{}'",
-                annotation_id, id
-            ))),
-        ));
+        return Err(ApiError::not_found(format!(
+            "Annotation '{}' not found in PDF '{}'",
+            annotation_id, id
+        )));
     }
 
     // Update the annotation
     let annotation = repo
         .update(&annotation_id, &data)
         .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::with_details(
-                    "Failed to update annotation",
-                    e.to_string(),
-                )),
-            )
-        })?
-        .ok_or_else(|| {
-            (
-                StatusCode::NOT_FOUND,
-                Json(ErrorResponse::new(format!(
-                    "Annotation '{}' not found",
-                    annotation_id
-                ))),
-            )
-        })?;
+        .map_err(|e| ApiError::internal("Failed to update annotation").with_reason(e.to_string()))?
+        .ok_or_else(|| ApiError::not_found(format!("Annotation '{}' not found", annotation_id)))?;
 
     Ok(Json(annotation))
 }
@@ -845,13 +830,10 @@ async fn update_annotation(
 async fn delete_annotation(
     State(state): State<AppState>,
     Path((id, annotation_id)): Path<(String, String)>,
-) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<StatusCode, ApiError> {
     // Check if PDF exists in cache
     if !state.pdf_cache().contains(&id).await {
-        return Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new(format!("PDF '{}' not found", id))),
-        ));
+        return Err(ApiError::not_found(format!("PDF '{}' not found", id)));
     }
 
     let repo = HighlightRepository::new(state.db());
@@ -860,59 +842,28 @@ async fn delete_annotation(
     let existing = repo
         .get(&annotation_id)
         .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::with_details(
-                    "Failed to get annotation",
-                    e.to_string(),
-                )),
-            )
-        })?
-        .ok_or_else(|| {
-            (
-                StatusCode::NOT_FOUND,
-                Json(ErrorResponse::new(format!(
-                    "Annotation '{}' not found",
-                    annotation_id
-                ))),
-            )
-        })?;
+        .map_err(|e| ApiError::internal("Failed to get annotation").with_reason(e.to_string()))?
+        .ok_or_else(|| ApiError::not_found(format!("Annotation '{}' not found", annotation_id)))?;
 
     if existing.book_id != id {
-        return Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new(format!(
-                "Annotation '{}' not found in PDF '{}'",
-                annotation_id, id
-            ))),
-        ));
+        return Err(ApiError::not_found(format!(
+            "Annotation '{}' not found in PDF '{}'",
+            annotation_id, id
+        )));
     }
 
     // Delete the annotation
-    let deleted = repo
-        .delete(&annotation_id)
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::with_details(
-                    "Failed to delete annotation",
-                    e.to_string(),
-                )),
-            )
-        })?;
+    let deleted = repo.delete(&annotation_id).await.map_err(|e| {
+        ApiError::internal("Failed to delete annotation").with_reason(e.to_string())
+    })?;
 
     if deleted {
         Ok(StatusCode::NO_CONTENT)
     } else {
-        Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new(format!(
-                "Annotation '{}' not found",
-                annotation_id
-            ))),
-        ))
+        Err(ApiError::not_found(format!(
+            "Annotation '{}' not found",
+            annotation_id
+        )))
     }
 }
 
@@ -962,13 +913,10 @@ pub struct SignaturesResponse {
 async fn get_form_info(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<Json<FormInfoResponse>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<FormInfoResponse>, ApiError> {
     // Check if PDF exists in cache
     if !state.pdf_cache().contains(&id).await {
-        return Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new(format!("PDF '{}' not found", id))),
-        ));
+        return Err(ApiError::not_found(format!("PDF '{}' not found", id)));
     }
 
     // Get the parser for form extraction
@@ -976,20 +924,9 @@ async fn get_form_info(
         .pdf_cache()
         .with_parser(&id, |parser| parser.get_form_info())
         .await
-        .ok_or_else(|| {
-            (
-                StatusCode::NOT_FOUND,
-                Json(ErrorResponse::new(format!("PDF '{}' not found", id))),
-            )
-        })?
+        .ok_or_else(|| ApiError::not_found(format!("PDF '{}' not found", id)))?
         .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::with_details(
-                    "Failed to extract form information",
-                    e.to_string(),
-                )),
-            )
+            ApiError::internal("Failed to extract form information").with_reason(e.to_string())
         })?;
 
     Ok(Json(FormInfoResponse {
@@ -1008,13 +945,10 @@ async fn get_form_info(
 async fn list_form_fields(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<Json<FormFieldsResponse>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<FormFieldsResponse>, ApiError> {
     // Check if PDF exists in cache
     if !state.pdf_cache().contains(&id).await {
-        return Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new(format!("PDF '{}' not found", id))),
-        ));
+        return Err(ApiError::not_found(format!("PDF '{}' not found", id)));
     }
 
     // Get form fields
@@ -1022,20 +956,9 @@ async fn list_form_fields(
         .pdf_cache()
         .with_parser(&id, |parser| parser.get_form_info())
         .await
-        .ok_or_else(|| {
-            (
-                StatusCode::NOT_FOUND,
-                Json(ErrorResponse::new(format!("PDF '{}' not found", id))),
-            )
-        })?
+        .ok_or_else(|| ApiError::not_found(format!("PDF '{}' not found", id)))?
         .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::with_details(
-                    "Failed to extract form fields",
-                    e.to_string(),
-                )),
-            )
+            ApiError::internal("Failed to extract form fields").with_reason(e.to_string())
         })?;
 
     let total = form_info.fields.len();
@@ -1056,13 +979,10 @@ async fn list_form_fields(
 async fn list_signatures(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<Json<SignaturesResponse>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<SignaturesResponse>, ApiError> {
     // Check if PDF exists in cache
     if !state.pdf_cache().contains(&id).await {
-        return Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new(format!("PDF '{}' not found", id))),
-        ));
+        return Err(ApiError::not_found(format!("PDF '{}' not found", id)));
     }
 
     // Get signatures
@@ -1070,20 +990,9 @@ async fn list_signatures(
         .pdf_cache()
         .with_parser(&id, |parser| parser.get_signatures())
         .await
-        .ok_or_else(|| {
-            (
-                StatusCode::NOT_FOUND,
-                Json(ErrorResponse::new(format!("PDF '{}' not found", id))),
-            )
-        })?
+        .ok_or_else(|| ApiError::not_found(format!("PDF '{}' not found", id)))?
         .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::with_details(
-                    "Failed to extract signatures",
-                    e.to_string(),
-                )),
-            )
+            ApiError::internal("Failed to extract signatures").with_reason(e.to_string())
         })?;
 
     let total = signatures.len();
@@ -1,4 +1,14 @@
 //! Reading progress API routes
+//!
+//! Progress is keyed by document ID and works for both EPUB and PDF. Updates
+//! carry a typed locator that is validated against the document format:
+//!
+//! ```json
+//! { "percent": 42.0, "locator": { "type": "cfi", "cfi": "epubcfi(/6/4!/4/2/1:0)" } }
+//! { "percent": 12.5, "locator": { "type": "page", "page": 17, "y": 0.4 } }
+//! ```
+//!
+//! Bodies with only the legacy `cfi` or `page` fields are still accepted.
 
 use axum::{
     extract::{Path, State},
@@ -53,7 +63,10 @@ async fn get_progress(
     Ok(Json(progress))
 }
 
-/// Update progress for a book
+/// Update progress for a document
+///
+/// Returns 400 when the locator does not fit the document format (e.g. a
+/// page locator for an EPUB) or is out of range.
 async fn update_progress(
     axum::Extension(state): axum::Extension<ProgressState>,
     Path(book_id): Path<String>,
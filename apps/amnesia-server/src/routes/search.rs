@@ -2,18 +2,22 @@
 //!
 //! Provides FTS5-powered search endpoints for books and highlights.
 //! Performance: ~50x faster than LIKE queries.
+//!
+//! Library books can also be filtered by reading level using their stored
+//! text statistics.
 
 use axum::{
-    extract::{Query, State},
+    extract::{Path, Query, State},
     routing::get,
     Json, Router,
 };
 use serde::{Deserialize, Serialize};
 
 use crate::db::{
-    BookSearchResult, FTS5Search, FTS5Stats, HighlightSearchResult, UnifiedSearchResult,
+    BookSearchResult, BookTextStats, FTS5Search, FTS5Stats, HighlightSearchResult, TextStatsFilter,
+    TextStatsRepository, UnifiedSearchResult,
 };
-use crate::error::Result;
+use crate::error::{AppError, Result};
 use crate::state::AppState;
 
 /// Create the search router
@@ -23,6 +27,8 @@ pub fn router() -> Router<AppState> {
         .route("/highlights", get(search_highlights))
         .route("/unified", get(search_unified))
         .route("/stats", get(get_search_stats))
+        .route("/reading-level", get(search_reading_level))
+        .route("/reading-level/:book_id", get(get_text_stats))
         .route("/rebuild", get(rebuild_indexes))
 }
 
@@ -140,6 +146,42 @@ async fn search_unified(
     }))
 }
 
+/// Result limit for reading level search
+#[derive(Debug, Deserialize)]
+pub struct ReadingLevelLimit {
+    /// Maximum results (default: 100)
+    #[serde(default = "default_limit")]
+    pub limit: i32,
+}
+
+/// Find library books by reading level, easiest first
+///
+/// GET /api/v1/search/reading-level?minGrade=3&maxGrade=6&maxWords=40000
+async fn search_reading_level(
+    State(state): State<AppState>,
+    Query(filter): Query<TextStatsFilter>,
+    Query(page): Query<ReadingLevelLimit>,
+) -> Result<Json<Vec<BookTextStats>>> {
+    let results = TextStatsRepository::new(state.db())
+        .find(&filter, page.limit.into())
+        .await?;
+    Ok(Json(results))
+}
+
+/// Text statistics for one library book
+///
+/// GET /api/v1/search/reading-level/:book_id
+async fn get_text_stats(
+    State(state): State<AppState>,
+    Path(book_id): Path<String>,
+) -> Result<Json<BookTextStats>> {
+    TextStatsRepository::new(state.db())
+        .get(&book_id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound(format!("No text statistics for book {}", book_id)))
+}
+
 /// Get search index statistics
 ///
 /// GET /api/v1/search/stats
@@ -3,38 +3,50 @@
 //! Provides endpoints for multi-device synchronization.
 
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{DefaultBodyLimit, Path, Query, State},
     routing::{get, post},
     Json, Router,
 };
-use serde::{Deserialize, Serialize};
 
+use crate::db::{
+    NewNotification, NotificationKind, NotificationRepository, PreferencesRepository,
+    ProgressRepository,
+};
+use crate::error::ApiError;
 use crate::state::AppState;
 use crate::sync::{
     ConflictResolver, PullRequest, PullResponse, PushRequest, PushResponse, SyncRepository,
-    SyncStatus,
+    SyncSnapshot, SyncStatus,
 };
 
+use super::account::AccountQuery;
+use super::limits::SYNC_BODY_LIMIT;
+
 /// Create the sync router
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/push", post(push_changes))
         .route("/pull", post(pull_changes))
         .route("/status/{book_id}", get(get_sync_status))
-}
-
-/// Error response
-#[derive(Debug, Serialize)]
-pub struct ErrorResponse {
-    pub error: String,
+        .route("/snapshot", get(get_snapshot))
+        .layer(DefaultBodyLimit::max(SYNC_BODY_LIMIT))
 }
 
 /// Push local changes to server
+#[utoipa::path(
+    post,
+    path = "/api/v1/sync/push",
+    tag = "sync",
+    request_body = PushRequest,
+    responses(
+        (status = 200, description = "Accepted operations and detected conflicts", body = PushResponse),
+        (status = 500, description = "Database error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
 async fn push_changes(
     State(state): State<AppState>,
     Json(req): Json<PushRequest>,
-) -> Result<Json<PushResponse>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<PushResponse>, ApiError> {
     let repo = SyncRepository::new(state.db());
     let resolver = ConflictResolver::default();
 
@@ -42,14 +54,7 @@ async fn push_changes(
     let server_ops = repo
         .get_operations_since(&req.book_id, req.last_known_version, None)
         .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: e.to_string(),
-                }),
-            )
-        })?;
+        .map_err(|e| ApiError::internal(e.to_string()))?;
 
     let mut conflicts = Vec::new();
     let mut accepted = Vec::new();
@@ -68,18 +73,26 @@ async fn push_changes(
         }
     }
 
+    if !conflicts.is_empty() {
+        let notification = NewNotification::new(
+            NotificationKind::SyncConflict,
+            format!("{} sync conflicts need resolving", conflicts.len()),
+        )
+        .with_body(format!(
+            "Changes from device {} conflict with newer changes on the server",
+            req.device_id
+        ))
+        .with_book(req.book_id.as_str());
+        NotificationRepository::new(state.db())
+            .notify(notification)
+            .await;
+    }
+
     // Increment version if we accepted any operations
     let new_version = if !accepted.is_empty() {
         repo.increment_version(&req.book_id, &req.device_id)
             .await
-            .map_err(|e| {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(ErrorResponse {
-                        error: e.to_string(),
-                    }),
-                )
-            })?
+            .map_err(|e| ApiError::internal(e.to_string()))?
     } else {
         repo.get_version(&req.book_id).await.unwrap_or(0)
     };
@@ -93,23 +106,26 @@ async fn push_changes(
 }
 
 /// Pull changes from server
+#[utoipa::path(
+    post,
+    path = "/api/v1/sync/pull",
+    tag = "sync",
+    request_body = PullRequest,
+    responses(
+        (status = 200, description = "Operations since the requested version", body = PullResponse),
+        (status = 500, description = "Database error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
 async fn pull_changes(
     State(state): State<AppState>,
     Json(req): Json<PullRequest>,
-) -> Result<Json<PullResponse>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<PullResponse>, ApiError> {
     let repo = SyncRepository::new(state.db());
 
     let operations = repo
         .get_operations_since(&req.book_id, req.since_version, Some(100))
         .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: e.to_string(),
-                }),
-            )
-        })?;
+        .map_err(|e| ApiError::internal(e.to_string()))?;
 
     let current_version = repo.get_version(&req.book_id).await.unwrap_or(0);
 
@@ -124,20 +140,55 @@ async fn pull_changes(
 }
 
 /// Get sync status for a book
+#[utoipa::path(
+    get,
+    path = "/api/v1/sync/status/{book_id}",
+    tag = "sync",
+    params(("book_id" = String, Path, description = "Book ID")),
+    responses(
+        (status = 200, description = "Sync status for the book", body = SyncStatus),
+        (status = 500, description = "Database error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
 async fn get_sync_status(
     State(state): State<AppState>,
     Path(book_id): Path<String>,
-) -> Result<Json<SyncStatus>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<SyncStatus>, ApiError> {
     let repo = SyncRepository::new(state.db());
 
-    let status = repo.get_status(&book_id).await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: e.to_string(),
-            }),
-        )
-    })?;
+    let status = repo
+        .get_status(&book_id)
+        .await
+        .map_err(|e| ApiError::internal(e.to_string()))?;
 
     Ok(Json(status))
 }
+
+/// Get account-wide state for a new device
+#[utoipa::path(
+    get,
+    path = "/api/v1/sync/snapshot",
+    tag = "sync",
+    params(AccountQuery),
+    responses(
+        (status = 200, description = "Reader preferences and reading progress", body = SyncSnapshot),
+        (status = 500, description = "Database error", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
+async fn get_snapshot(
+    State(state): State<AppState>,
+    Query(query): Query<AccountQuery>,
+) -> Result<Json<SyncSnapshot>, ApiError> {
+    let user_id = query.user_id.as_deref();
+    let preferences = PreferencesRepository::new(state.db())
+        .get(user_id)
+        .await?
+        .unwrap_or_default();
+    let progress = ProgressRepository::new(state.db()).list(user_id).await?;
+
+    Ok(Json(SyncSnapshot {
+        preferences,
+        progress,
+        generated_at: chrono::Utc::now(),
+    }))
+}
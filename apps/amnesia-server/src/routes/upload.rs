@@ -10,7 +10,7 @@
 //! - GET /api/v1/upload/:session_id - Get session status
 
 use axum::{
-    extract::{Path, State},
+    extract::{DefaultBodyLimit, Path, State},
     http::StatusCode,
     response::IntoResponse,
     routing::{delete, get, post},
@@ -19,13 +19,15 @@ use axum::{
 use axum::body::Bytes;
 use axum::http::header;
 use serde::Serialize;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
+use crate::error::ApiError;
 use crate::state::AppState;
 use crate::upload::{
     ChunkStore, DeduplicationService, SessionManager,
     HandshakeRequest, HandshakeResponse, ChunkUploadResponse, FinalizeResponse,
-    UploadError, UploadSession, SessionStatus, MAX_FILE_SIZE,
+    UploadError, UploadSession, SessionStatus, MAX_CHUNK_SIZE, MAX_FILE_SIZE,
 };
 
 // ============================================================================
@@ -45,36 +47,38 @@ pub struct UploadState {
 // Error Response
 // ============================================================================
 
-#[derive(Serialize)]
-struct ErrorResponse {
-    error: String,
-    code: String,
+impl From<UploadError> for ApiError {
+    fn from(err: UploadError) -> Self {
+        let kind = match &err {
+            UploadError::SessionNotFound(_) => "session-not-found",
+            UploadError::SessionExpired(_) => "session-expired",
+            UploadError::SessionComplete => "session-complete",
+            UploadError::ChunkHashMismatch { .. } => "chunk-hash-mismatch",
+            UploadError::ChunkIndexOutOfBounds { .. } => "chunk-index-out-of-bounds",
+            UploadError::ChunkAlreadyReceived(_) => "chunk-already-received",
+            UploadError::FileTooLarge { .. } => "file-too-large",
+            UploadError::ChunkTooLarge { .. } => "chunk-too-large",
+            UploadError::InvalidFileType(_) => "invalid-file-type",
+            UploadError::MissingChunks(_) => "missing-chunks",
+            UploadError::StorageError(_) => "storage-error",
+            UploadError::DatabaseError(_) => "database-error",
+            UploadError::InternalError(_) => "internal-error",
+        };
+        // A corrupted chunk or a storage hiccup can succeed on resend
+        let retryable = matches!(
+            err,
+            UploadError::ChunkHashMismatch { .. } | UploadError::StorageError(_)
+        );
+
+        ApiError::new(err.status_code(), err.to_string())
+            .with_type(kind)
+            .retryable(retryable)
+    }
 }
 
 impl IntoResponse for UploadError {
     fn into_response(self) -> axum::response::Response {
-        let status = self.status_code();
-        let code = match &self {
-            UploadError::SessionNotFound(_) => "SESSION_NOT_FOUND",
-            UploadError::SessionExpired(_) => "SESSION_EXPIRED",
-            UploadError::SessionComplete => "SESSION_COMPLETE",
-            UploadError::ChunkHashMismatch { .. } => "CHUNK_HASH_MISMATCH",
-            UploadError::ChunkIndexOutOfBounds { .. } => "CHUNK_INDEX_OUT_OF_BOUNDS",
-            UploadError::ChunkAlreadyReceived(_) => "CHUNK_ALREADY_RECEIVED",
-            UploadError::FileTooLarge { .. } => "FILE_TOO_LARGE",
-            UploadError::InvalidFileType(_) => "INVALID_FILE_TYPE",
-            UploadError::MissingChunks(_) => "MISSING_CHUNKS",
-            UploadError::StorageError(_) => "STORAGE_ERROR",
-            UploadError::DatabaseError(_) => "DATABASE_ERROR",
-            UploadError::InternalError(_) => "INTERNAL_ERROR",
-        };
-
-        let body = Json(ErrorResponse {
-            error: self.to_string(),
-            code: code.to_string(),
-        });
-
-        (status, body).into_response()
+        ApiError::from(self).into_response()
     }
 }
 
@@ -86,7 +90,10 @@ impl IntoResponse for UploadError {
 pub fn router(state: UploadState) -> Router<AppState> {
     Router::new()
         .route("/handshake", post(handshake))
-        .route("/{session_id}/chunks/{index}", post(upload_chunk))
+        .route(
+            "/{session_id}/chunks/{index}",
+            post(upload_chunk).layer(DefaultBodyLimit::max(MAX_CHUNK_SIZE)),
+        )
         .route("/{session_id}/finalize", post(finalize))
         .route("/{session_id}", get(get_session))
         .route("/{session_id}", delete(cancel_session))
@@ -100,6 +107,17 @@ pub fn router(state: UploadState) -> Router<AppState> {
 /// POST /api/v1/upload/handshake
 ///
 /// Initiate a chunked upload. Returns session ID and which chunks are needed.
+#[utoipa::path(
+    post,
+    path = "/api/v1/upload/handshake",
+    tag = "upload",
+    request_body = HandshakeRequest,
+    responses(
+        (status = 200, description = "Session created, or duplicate detected", body = HandshakeResponse),
+        (status = 413, description = "File or chunk size too large", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 415, description = "Unsupported file type", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
 async fn handshake(
     State(state): State<UploadState>,
     Json(request): Json<HandshakeRequest>,
@@ -112,6 +130,14 @@ async fn handshake(
         });
     }
 
+    // Chunks are read whole, so they must fit the chunk route's body limit
+    if let Some(chunk_size) = request.chunk_size.filter(|&size| size > MAX_CHUNK_SIZE) {
+        return Err(UploadError::ChunkTooLarge {
+            size: chunk_size,
+            max: MAX_CHUNK_SIZE,
+        });
+    }
+
     // Validate file type
     if !is_valid_file_type(&request.mime_type) {
         return Err(UploadError::InvalidFileType(request.mime_type.clone()));
@@ -162,6 +188,24 @@ async fn handshake(
 /// POST /api/v1/upload/:session_id/chunks/:index
 ///
 /// Upload a single chunk. The chunk data is the raw request body.
+#[utoipa::path(
+    post,
+    path = "/api/v1/upload/{session_id}/chunks/{index}",
+    tag = "upload",
+    params(
+        ("session_id" = String, Path, description = "Upload session ID"),
+        ("index" = usize, Path, description = "Chunk index (0-based)"),
+        ("X-Chunk-Hash" = Option<String>, Header, description = "SHA-256 of the chunk, checked against the handshake")
+    ),
+    request_body(content = Vec<u8>, content_type = "application/octet-stream"),
+    responses(
+        (status = 200, description = "Chunk stored", body = ChunkUploadResponse),
+        (status = 413, description = "Chunk larger than the session's chunk size", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Session not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "Hash mismatch or chunk already received", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 410, description = "Session expired", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
 async fn upload_chunk(
     State(state): State<UploadState>,
     Path((session_id, chunk_index)): Path<(String, usize)>,
@@ -193,6 +237,13 @@ async fn upload_chunk(
         return Err(UploadError::ChunkAlreadyReceived(chunk_index));
     }
 
+    if body.len() > session.chunk_size {
+        return Err(UploadError::ChunkTooLarge {
+            size: body.len(),
+            max: session.chunk_size,
+        });
+    }
+
     // Get expected hash
     let expected_hash = &session.chunk_hashes[chunk_index];
 
@@ -246,6 +297,18 @@ async fn upload_chunk(
 /// POST /api/v1/upload/:session_id/finalize
 ///
 /// Assemble chunks and store the final file.
+#[utoipa::path(
+    post,
+    path = "/api/v1/upload/{session_id}/finalize",
+    tag = "upload",
+    params(("session_id" = String, Path, description = "Upload session ID")),
+    responses(
+        (status = 200, description = "File assembled and stored", body = FinalizeResponse),
+        (status = 400, description = "Chunks missing", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Session not found", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 410, description = "Session expired", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
 async fn finalize(
     State(state): State<UploadState>,
     Path(session_id): Path<String>,
@@ -330,6 +393,16 @@ async fn finalize(
 /// GET /api/v1/upload/:session_id
 ///
 /// Get upload session status.
+#[utoipa::path(
+    get,
+    path = "/api/v1/upload/{session_id}",
+    tag = "upload",
+    params(("session_id" = String, Path, description = "Upload session ID")),
+    responses(
+        (status = 200, description = "Session status", body = SessionStatusResponse),
+        (status = 404, description = "Session not found", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
 async fn get_session(
     State(state): State<UploadState>,
     Path(session_id): Path<String>,
@@ -354,9 +427,10 @@ async fn get_session(
     }))
 }
 
-#[derive(Serialize)]
+/// Upload session status
+#[derive(Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
-struct SessionStatusResponse {
+pub struct SessionStatusResponse {
     session_id: String,
     file_name: String,
     file_size: u64,
@@ -371,6 +445,16 @@ struct SessionStatusResponse {
 /// DELETE /api/v1/upload/:session_id
 ///
 /// Cancel an upload session.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/upload/{session_id}",
+    tag = "upload",
+    params(("session_id" = String, Path, description = "Upload session ID")),
+    responses(
+        (status = 204, description = "Session cancelled"),
+        (status = 404, description = "Session not found", body = ProblemDetails, content_type = "application/problem+json")
+    )
+)]
 async fn cancel_session(
     State(state): State<UploadState>,
     Path(session_id): Path<String>,
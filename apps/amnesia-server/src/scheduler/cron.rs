@@ -0,0 +1,265 @@
+//! Cron expressions
+//!
+//! Standard five-field expressions (`minute hour day-of-month month
+//! day-of-week`), evaluated in UTC. Fields accept `*`, single values, ranges
+//! (`1-5`), lists (`1,15`), and steps (`*/10`, `0-30/5`, `5/15`). Day of week
+//! runs 0-6 from Sunday, and 7 is also Sunday. When both day fields are
+//! restricted a time matches if either does, as in Vixie cron.
+//!
+//! The shorthands `@hourly`, `@daily`, `@weekly`, and `@monthly` are also
+//! accepted.
+
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Duration, TimeZone, Timelike, Utc};
+use thiserror::Error;
+
+/// How far ahead [`Schedule::next_after`] looks before giving up, so that
+/// expressions that never match (`0 0 31 2 *`) terminate
+const SEARCH_LIMIT_DAYS: i64 = 366 * 5;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum CronError {
+    #[error("Cron expression '{0}' must have 5 fields")]
+    FieldCount(String),
+
+    #[error("Invalid cron field '{field}': {reason}")]
+    InvalidField { field: String, reason: String },
+}
+
+/// A parsed cron expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    expression: String,
+    minutes: Field,
+    hours: Field,
+    days: Field,
+    months: Field,
+    weekdays: Field,
+    /// Day of month and day of week were both restricted
+    either_day: bool,
+}
+
+/// Bit set of the values a field matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Field(u64);
+
+impl Field {
+    fn contains(self, value: u32) -> bool {
+        self.0 & (1 << value) != 0
+    }
+
+    fn parse(text: &str, min: u32, max: u32) -> Result<Self, CronError> {
+        let invalid = |reason: String| CronError::InvalidField {
+            field: text.to_string(),
+            reason,
+        };
+
+        let mut bits = 0u64;
+        for part in text.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => {
+                    let step: u32 = step
+                        .parse()
+                        .map_err(|_| invalid(format!("bad step '{}'", step)))?;
+                    if step == 0 {
+                        return Err(invalid("step must be positive".to_string()));
+                    }
+                    (range, Some(step))
+                }
+                None => (part, None),
+            };
+
+            let value = |s: &str| -> Result<u32, CronError> {
+                let n: u32 = s
+                    .parse()
+                    .map_err(|_| invalid(format!("bad value '{}'", s)))?;
+                if n < min || n > max {
+                    return Err(invalid(format!("{} is outside {}-{}", n, min, max)));
+                }
+                Ok(n)
+            };
+
+            let (start, end) = match range {
+                "*" => (min, max),
+                _ => match range.split_once('-') {
+                    Some((a, b)) => (value(a)?, value(b)?),
+                    // `5/15` means from 5 to the end in steps of 15
+                    None if step.is_some() => (value(range)?, max),
+                    None => {
+                        let n = value(range)?;
+                        (n, n)
+                    }
+                },
+            };
+            if start > end {
+                return Err(invalid(format!("range {}-{} is reversed", start, end)));
+            }
+
+            for n in (start..=end).step_by(step.unwrap_or(1) as usize) {
+                bits |= 1 << n;
+            }
+        }
+
+        Ok(Field(bits))
+    }
+}
+
+impl Schedule {
+    /// The expression this schedule was parsed from
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    /// First matching minute strictly after `after`
+    ///
+    /// Returns `None` if nothing matches within five years.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let limit = after + Duration::days(SEARCH_LIMIT_DAYS);
+        let mut t = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+
+        while t <= limit {
+            if !self.months.contains(t.month()) {
+                let (year, month) = if t.month() == 12 {
+                    (t.year() + 1, 1)
+                } else {
+                    (t.year(), t.month() + 1)
+                };
+                t = Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0).single()?;
+                continue;
+            }
+            if !self.day_matches(t) {
+                t = Utc
+                    .with_ymd_and_hms(t.year(), t.month(), t.day(), 0, 0, 0)
+                    .single()?
+                    + Duration::days(1);
+                continue;
+            }
+            if !self.hours.contains(t.hour()) {
+                t = t.with_minute(0)? + Duration::hours(1);
+                continue;
+            }
+            if !self.minutes.contains(t.minute()) {
+                t += Duration::minutes(1);
+                continue;
+            }
+            return Some(t);
+        }
+
+        None
+    }
+
+    fn day_matches(&self, t: DateTime<Utc>) -> bool {
+        let day = self.days.contains(t.day());
+        let weekday = self.weekdays.contains(t.weekday().num_days_from_sunday());
+        if self.either_day {
+            day || weekday
+        } else {
+            day && weekday
+        }
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = CronError;
+
+    fn from_str(expression: &str) -> Result<Self, CronError> {
+        let expanded = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(CronError::FieldCount(expression.to_string()));
+        };
+
+        let mut weekdays = Field::parse(weekday, 0, 7)?;
+        if weekdays.contains(7) {
+            weekdays = Field((weekdays.0 | 1) & !(1 << 7));
+        }
+
+        Ok(Self {
+            expression: expression.trim().to_string(),
+            minutes: Field::parse(minute, 0, 59)?,
+            hours: Field::parse(hour, 0, 23)?,
+            days: Field::parse(day, 1, 31)?,
+            months: Field::parse(month, 1, 12)?,
+            weekdays,
+            either_day: !day.starts_with('*') && !weekday.starts_with('*'),
+        })
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.expression)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+    }
+
+    fn next(expression: &str, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        expression.parse::<Schedule>().unwrap().next_after(after)
+    }
+
+    #[test]
+    fn test_next_after() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 10, 14, 7, 30).unwrap();
+
+        assert_eq!(next("* * * * *", now), Some(at(2024, 3, 10, 14, 8)));
+        assert_eq!(next("*/5 * * * *", now), Some(at(2024, 3, 10, 14, 10)));
+        assert_eq!(next("@hourly", now), Some(at(2024, 3, 10, 15, 0)));
+        assert_eq!(next("30 2 * * *", now), Some(at(2024, 3, 11, 2, 30)));
+        // 2024-03-10 is a Sunday
+        assert_eq!(next("0 9 * * 1-5", now), Some(at(2024, 3, 11, 9, 0)));
+        assert_eq!(next("0 0 1 * *", now), Some(at(2024, 4, 1, 0, 0)));
+        assert_eq!(next("0 0 29 2 *", now), Some(at(2028, 2, 29, 0, 0)));
+        assert_eq!(
+            next("0 0 1 1 *", at(2024, 12, 31, 23, 59)),
+            Some(at(2025, 1, 1, 0, 0))
+        );
+    }
+
+    #[test]
+    fn test_day_fields() {
+        let now = at(2024, 3, 10, 0, 0);
+        // Either the 15th or a Wednesday
+        assert_eq!(next("0 0 15 * 3", now), Some(at(2024, 3, 13, 0, 0)));
+        // 7 is Sunday
+        assert_eq!(next("0 12 * * 7", now), Some(at(2024, 3, 10, 12, 0)));
+        // Never matches
+        assert_eq!(next("0 0 31 2 *", now), None);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            "* * *".parse::<Schedule>(),
+            Err(CronError::FieldCount(_))
+        ));
+        for bad in [
+            "60 * * * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "a * * * *",
+            "* * 0 * *",
+        ] {
+            assert!(
+                matches!(bad.parse::<Schedule>(), Err(CronError::InvalidField { .. })),
+                "{} should be rejected",
+                bad
+            );
+        }
+    }
+}
@@ -0,0 +1,341 @@
+//! Periodic background tasks
+//!
+//! Maintenance jobs (expiring upload sessions, evicting orphaned cache
+//! entries, rescanning the library, polling feeds, computing text
+//! statistics, archiving unread books) register with a [`Scheduler`] under a name and a cron
+//! expression from [`SchedulerConfig`]. Each task runs on its own loop:
+//!
+//! - every run is delayed by a random jitter of up to
+//!   [`SchedulerConfig::jitter_secs`], so tasks sharing a schedule (or
+//!   several servers sharing a bucket) do not fire at once;
+//! - a run is skipped while the previous run of the same task is still in
+//!   progress;
+//! - the outcome of the last run is kept and reported by
+//!   `GET /api/v1/admin/tasks`.
+//!
+//! [`SchedulerConfig`]: crate::config::SchedulerConfig
+//! [`SchedulerConfig::jitter_secs`]: crate::config::SchedulerConfig::jitter_secs
+
+mod cron;
+
+pub use cron::{CronError, Schedule};
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Removes expired chunked upload sessions
+pub const TASK_UPLOAD_CLEANUP: &str = "upload_cleanup";
+
+/// Drops cached documents whose parser and renderer were evicted
+pub const TASK_CACHE_EVICTION: &str = "cache_eviction";
+
+/// Rescans the S3 library for the OPDS catalog
+pub const TASK_LIBRARY_RESCAN: &str = "library_rescan";
+
+/// Polls RSS/Atom feeds and files new articles into the library
+pub const TASK_FEED_INGEST: &str = "feed_ingest";
+
+/// Computes readability statistics for new or changed books
+pub const TASK_TEXT_STATS: &str = "text_stats";
+
+/// Looks for repeated pages in new or changed PDFs
+pub const TASK_DUPLICATE_PAGES: &str = "duplicate_pages";
+
+/// Moves books nobody has opened in a while to a cheaper storage class
+pub const TASK_STORAGE_TIERING: &str = "storage_tiering";
+
+/// Runs OCR requests again that failed while a provider was down
+pub const TASK_OCR_RETRY: &str = "ocr_retry";
+
+/// Future returned by a task; resolves to a short summary of the run
+pub type TaskFuture = Pin<Box<dyn Future<Output = anyhow::Result<String>> + Send>>;
+
+type Job = Arc<dyn Fn() -> TaskFuture + Send + Sync>;
+
+/// Runs registered tasks on their cron schedules
+#[derive(Clone, Default)]
+pub struct Scheduler {
+    tasks: Arc<RwLock<Vec<Arc<Task>>>>,
+    jitter: Duration,
+}
+
+struct Task {
+    name: String,
+    schedule: Schedule,
+    job: Job,
+    running: AtomicBool,
+    state: Mutex<TaskState>,
+}
+
+#[derive(Default)]
+struct TaskState {
+    next_run: Option<DateTime<Utc>>,
+    last_run: Option<TaskRun>,
+    runs: u64,
+    failures: u64,
+    skipped: u64,
+}
+
+/// Status of a registered task
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskStatus {
+    pub name: String,
+    /// Cron expression (UTC)
+    pub schedule: String,
+    /// Whether a run is in progress
+    pub running: bool,
+    /// When the next run is due, jitter included
+    pub next_run: Option<DateTime<Utc>>,
+    /// The most recent completed run
+    pub last_run: Option<TaskRun>,
+    /// Completed runs since startup
+    pub runs: u64,
+    /// Completed runs that failed
+    pub failures: u64,
+    /// Runs skipped because the previous one was still in progress
+    pub skipped: u64,
+}
+
+/// Outcome of one run
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskRun {
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    pub succeeded: bool,
+    /// Summary on success, error message on failure
+    pub message: String,
+}
+
+impl Scheduler {
+    /// Create a scheduler that delays each run by up to `jitter`
+    pub fn new(jitter: Duration) -> Self {
+        Self {
+            tasks: Arc::new(RwLock::new(Vec::new())),
+            jitter,
+        }
+    }
+
+    /// Register a task under `name`, replacing any task with that name
+    ///
+    /// `job` is called once per run and returns a summary of what it did.
+    /// Tasks only run after [`start`](Self::start).
+    pub fn register<F, Fut>(&self, name: &str, expression: &str, job: F) -> Result<(), CronError>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = anyhow::Result<String>> + Send + 'static,
+    {
+        let task = Arc::new(Task {
+            name: name.to_string(),
+            schedule: expression.parse()?,
+            job: Arc::new(move || Box::pin(job()) as TaskFuture),
+            running: AtomicBool::new(false),
+            state: Mutex::new(TaskState::default()),
+        });
+
+        let mut tasks = self.tasks.write();
+        tasks.retain(|t| t.name != name);
+        tasks.push(task);
+        Ok(())
+    }
+
+    /// Spawn one loop per registered task
+    pub fn start(&self) -> Vec<tokio::task::JoinHandle<()>> {
+        self.tasks
+            .read()
+            .iter()
+            .map(|task| {
+                tracing::info!(task = %task.name, schedule = %task.schedule, "Scheduled task");
+                tokio::spawn(Arc::clone(task).run_loop(self.jitter))
+            })
+            .collect()
+    }
+
+    /// Status of every registered task, by name
+    pub fn statuses(&self) -> Vec<TaskStatus> {
+        let mut statuses: Vec<TaskStatus> = self.tasks.read().iter().map(|t| t.status()).collect();
+        statuses.sort_by(|a, b| a.name.cmp(&b.name));
+        statuses
+    }
+
+    /// Status of one task
+    pub fn status(&self, name: &str) -> Option<TaskStatus> {
+        self.tasks
+            .read()
+            .iter()
+            .find(|t| t.name == name)
+            .map(|t| t.status())
+    }
+}
+
+impl Task {
+    async fn run_loop(self: Arc<Self>, jitter: Duration) {
+        let mut slot = Utc::now();
+        loop {
+            // Resume from now rather than replaying slots missed while the
+            // host was suspended
+            let Some(next) = self
+                .schedule
+                .next_after(slot)
+                .filter(|next| *next > Utc::now())
+                .or_else(|| self.schedule.next_after(Utc::now()))
+            else {
+                tracing::warn!(task = %self.name, "Schedule never fires again; stopping");
+                return;
+            };
+            slot = next;
+
+            let due = next
+                + chrono::Duration::from_std(random_jitter(jitter))
+                    .unwrap_or_else(|_| chrono::Duration::zero());
+            self.state.lock().next_run = Some(due);
+            tokio::time::sleep((due - Utc::now()).to_std().unwrap_or_default()).await;
+
+            if self.running.swap(true, Ordering::AcqRel) {
+                self.state.lock().skipped += 1;
+                tracing::warn!(task = %self.name, "Previous run still in progress; skipping");
+                continue;
+            }
+            tokio::spawn(Arc::clone(&self).execute());
+        }
+    }
+
+    /// Run the job once and record the outcome; `running` must already be set
+    async fn execute(self: Arc<Self>) {
+        let started_at = Utc::now();
+        let start = Instant::now();
+
+        // Spawned so a panicking job is recorded as a failure instead of
+        // leaving the task marked as running
+        let result = match tokio::spawn((self.job)()).await {
+            Ok(result) => result,
+            Err(e) => Err(anyhow::anyhow!("Task panicked: {}", e)),
+        };
+
+        let run = TaskRun {
+            started_at,
+            duration_ms: start.elapsed().as_millis() as u64,
+            succeeded: result.is_ok(),
+            message: match result {
+                Ok(summary) => summary,
+                Err(e) => format!("{:#}", e),
+            },
+        };
+
+        if run.succeeded {
+            tracing::info!(task = %self.name, duration_ms = run.duration_ms, "{}", run.message);
+        } else {
+            tracing::warn!(task = %self.name, duration_ms = run.duration_ms, "Task failed: {}", run.message);
+        }
+
+        {
+            let mut state = self.state.lock();
+            state.runs += 1;
+            if !run.succeeded {
+                state.failures += 1;
+            }
+            state.last_run = Some(run);
+        }
+        self.running.store(false, Ordering::Release);
+    }
+
+    fn status(&self) -> TaskStatus {
+        let state = self.state.lock();
+        TaskStatus {
+            name: self.name.clone(),
+            schedule: self.schedule.to_string(),
+            running: self.running.load(Ordering::Acquire),
+            next_run: state.next_run,
+            last_run: state.last_run.clone(),
+            runs: state.runs,
+            failures: state.failures,
+            skipped: state.skipped,
+        }
+    }
+}
+
+/// Uniformly random delay in `[0, max]`
+fn random_jitter(max: Duration) -> Duration {
+    let max_ms = max.as_millis() as u64;
+    if max_ms == 0 {
+        return Duration::ZERO;
+    }
+    // uuid's v4 generator is the crate's only randomness source
+    let random = uuid::Uuid::new_v4().as_u128() as u64;
+    Duration::from_millis(random % (max_ms + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_and_status() {
+        let scheduler = Scheduler::new(Duration::ZERO);
+        scheduler
+            .register("b", "*/5 * * * *", || async { Ok("done".to_string()) })
+            .unwrap();
+        scheduler
+            .register("a", "@hourly", || async { Ok("done".to_string()) })
+            .unwrap();
+        assert!(scheduler
+            .register("c", "not cron", || async { Ok(String::new()) })
+            .is_err());
+
+        let names: Vec<String> = scheduler.statuses().into_iter().map(|s| s.name).collect();
+        assert_eq!(names, vec!["a", "b"]);
+
+        let status = scheduler.status("b").unwrap();
+        assert_eq!(status.schedule, "*/5 * * * *");
+        assert!(!status.running);
+        assert!(status.last_run.is_none());
+        assert!(scheduler.status("c").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_execute_records_outcome() {
+        let scheduler = Scheduler::new(Duration::ZERO);
+        scheduler
+            .register("ok", "@daily", || async { Ok("cleaned 3".to_string()) })
+            .unwrap();
+        scheduler
+            .register("fails", "@daily", || async {
+                Err(anyhow::anyhow!("bucket offline"))
+            })
+            .unwrap();
+
+        let tasks = scheduler.tasks.read().clone();
+        for task in tasks {
+            task.running.store(true, Ordering::Release);
+            task.execute().await;
+        }
+
+        let ok = scheduler.status("ok").unwrap();
+        let run = ok.last_run.unwrap();
+        assert!(run.succeeded);
+        assert_eq!(run.message, "cleaned 3");
+        assert_eq!((ok.runs, ok.failures), (1, 0));
+        assert!(!ok.running);
+
+        let fails = scheduler.status("fails").unwrap();
+        assert_eq!(fails.last_run.unwrap().message, "bucket offline");
+        assert_eq!((fails.runs, fails.failures), (1, 1));
+    }
+
+    #[test]
+    fn test_random_jitter_bounds() {
+        assert_eq!(random_jitter(Duration::ZERO), Duration::ZERO);
+        for _ in 0..100 {
+            assert!(random_jitter(Duration::from_millis(50)) <= Duration::from_millis(50));
+        }
+    }
+}
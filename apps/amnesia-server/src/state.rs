@@ -6,6 +6,9 @@ use sqlx::SqlitePool;
 
 use crate::config::Config;
 use crate::document::{CacheConfig, DocumentCache};
+use crate::mupdf::configure_fonts;
+#[cfg(feature = "ocr")]
+use crate::ocr::OcrRetryQueue;
 use crate::pdf::PdfCache;
 use crate::storage::S3Client;
 
@@ -23,18 +26,33 @@ struct AppStateInner {
     pub document_cache: DocumentCache,
     /// Legacy PDF cache (for backward compatibility with routes/pdf.rs)
     pub pdf_cache: PdfCache,
+    /// OCR requests to run again once their provider is back
+    #[cfg(feature = "ocr")]
+    pub ocr_retries: OcrRetryQueue,
 }
 
 impl AppState {
     /// Create a new application state
     pub async fn new(config: Config, s3_client: S3Client, db: SqlitePool) -> Self {
+        configure_fonts(&config.fonts);
+
+        let document_cache = DocumentCache::new(CacheConfig {
+            max_render_bytes: config.cache.render_bytes,
+            max_stext_bytes: config.cache.text_bytes,
+            ..CacheConfig::default()
+        });
+        let pdf_cache =
+            PdfCache::with_budget(config.cache.pdf_page_bytes, config.cache.pdf_text_bytes);
+
         Self {
             inner: Arc::new(AppStateInner {
                 config,
                 s3_client,
                 db,
-                document_cache: DocumentCache::new(CacheConfig::default()),
-                pdf_cache: PdfCache::new(),
+                document_cache,
+                pdf_cache,
+                #[cfg(feature = "ocr")]
+                ocr_retries: OcrRetryQueue::new(),
             }),
         }
     }
@@ -63,4 +81,28 @@ impl AppState {
     pub fn pdf_cache(&self) -> &PdfCache {
         &self.inner.pdf_cache
     }
+
+    /// Get the queue of OCR requests waiting to be retried
+    #[cfg(feature = "ocr")]
+    pub fn ocr_retries(&self) -> &OcrRetryQueue {
+        &self.inner.ocr_retries
+    }
+
+    /// Printed page labels of a loaded PDF, one per page
+    ///
+    /// Checks both document caches; `None` if the book is not loaded.
+    pub async fn page_labels(&self, book_id: &str) -> Option<Vec<String>> {
+        if let Some(labels) = self
+            .document_cache()
+            .get_document(book_id)
+            .await
+            .and_then(|doc| doc.item_labels)
+        {
+            return Some(labels);
+        }
+        self.pdf_cache()
+            .get_pdf(book_id)
+            .await
+            .and_then(|pdf| pdf.page_labels)
+    }
 }
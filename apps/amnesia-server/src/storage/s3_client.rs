@@ -6,6 +6,7 @@ use aws_config::BehaviorVersion;
 use aws_sdk_s3::{
     config::{Credentials, Region},
     primitives::ByteStream,
+    types::{RestoreRequest, StorageClass},
     Client,
 };
 use chrono::{DateTime, Utc};
@@ -13,7 +14,7 @@ use chrono::{DateTime, Utc};
 use crate::config::StorageConfig;
 use crate::error::{AppError, Result, StorageError};
 
-use super::types::{ListOptions, ObjectList, ObjectMetadata, StorageObject};
+use super::types::{ListOptions, ObjectList, ObjectMetadata, StorageObject, StorageState};
 
 /// S3-compatible storage client
 #[derive(Clone)]
@@ -241,6 +242,80 @@ impl S3Client {
         Ok(())
     }
 
+    /// Storage class and restore state of an object
+    pub async fn storage_state(&self, key: &str) -> Result<StorageState> {
+        let response = self
+            .client
+            .head_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| {
+                if e.to_string().contains("404") || e.to_string().contains("NoSuchKey") {
+                    AppError::Storage(StorageError::ObjectNotFound(key.to_string()))
+                } else {
+                    AppError::Storage(StorageError::SdkError(format!(
+                        "Failed to head object {}: {}",
+                        key, e
+                    )))
+                }
+            })?;
+
+        Ok(StorageState {
+            storage_class: response.storage_class().map(|class| class.as_str().to_string()),
+            restore: response.restore().map(|s| s.to_string()),
+        })
+    }
+
+    /// Move an object to another storage class by copying it onto itself
+    ///
+    /// Objects in archive classes must be restored first.
+    pub async fn set_storage_class(&self, key: &str, storage_class: &str) -> Result<()> {
+        self.client
+            .copy_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .copy_source(format!("{}/{}", self.bucket, encode_copy_source(key)))
+            .storage_class(StorageClass::from(storage_class))
+            .send()
+            .await
+            .map_err(|e| {
+                AppError::Storage(StorageError::SdkError(format!(
+                    "Failed to move {} to {}: {}",
+                    key, storage_class, e
+                )))
+            })?;
+
+        tracing::debug!("Moved object {} to {}", key, storage_class);
+        Ok(())
+    }
+
+    /// Ask for a temporary readable copy of an archived object
+    ///
+    /// The copy is kept for `days`; an already requested restore is not an
+    /// error.
+    pub async fn restore_object(&self, key: &str, days: i32) -> Result<()> {
+        let request = RestoreRequest::builder().days(days).build();
+
+        match self
+            .client
+            .restore_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .restore_request(request)
+            .send()
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) if e.to_string().contains("RestoreAlreadyInProgress") => Ok(()),
+            Err(e) => Err(AppError::Storage(StorageError::SdkError(format!(
+                "Failed to restore {}: {}",
+                key, e
+            )))),
+        }
+    }
+
     /// Delete an object from the bucket
     pub async fn delete_object(&self, key: &str) -> Result<()> {
         self.client
@@ -303,6 +378,18 @@ impl S3Client {
     }
 }
 
+/// Percent-encode a key for `x-amz-copy-source`, keeping `/`
+fn encode_copy_source(key: &str) -> String {
+    key.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -321,4 +408,13 @@ mod tests {
         assert_eq!(options.delimiter, Some("/".to_string()));
         assert_eq!(options.max_keys, Some(100));
     }
+
+    #[test]
+    fn test_encode_copy_source() {
+        assert_eq!(
+            encode_copy_source("Le Guin/A Wizard of Earthsea (12)/book.epub"),
+            "Le%20Guin/A%20Wizard%20of%20Earthsea%20%2812%29/book.epub"
+        );
+        assert_eq!(encode_copy_source("Gödel.pdf"), "G%C3%B6del.pdf");
+    }
 }
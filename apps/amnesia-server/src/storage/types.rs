@@ -13,6 +13,31 @@ pub struct ObjectMetadata {
     pub etag: Option<String>,
 }
 
+/// Storage class and archive restore state of an object
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StorageState {
+    /// `None` for the bucket's default (STANDARD) class
+    pub storage_class: Option<String>,
+    /// The `x-amz-restore` header, e.g. `ongoing-request="true"`
+    pub restore: Option<String>,
+}
+
+impl StorageState {
+    /// Whether a restore has been requested and is not finished
+    pub fn restore_ongoing(&self) -> bool {
+        self.restore
+            .as_deref()
+            .is_some_and(|restore| restore.contains("ongoing-request=\"true\""))
+    }
+
+    /// Whether a temporary restored copy can be read
+    pub fn is_restored(&self) -> bool {
+        self.restore
+            .as_deref()
+            .is_some_and(|restore| restore.contains("ongoing-request=\"false\""))
+    }
+}
+
 /// A storage object with its data
 #[derive(Debug)]
 pub struct StorageObject {
@@ -13,6 +13,9 @@
 //! 4. Client resolves conflicts and retries if needed
 //! 5. Client sends `PullRequest` to get server changes
 //!
+//! A new device first fetches a `SyncSnapshot` of account-wide state
+//! (reader preferences and progress), then syncs each book as above.
+//!
 //! # Conflict Resolution
 //!
 //! - Delete wins over update
@@ -27,5 +30,5 @@ pub use conflict::{ConflictResolver, ConflictWinner, ResolvedConflict};
 pub use store::SyncRepository;
 pub use types::{
     Conflict, ConflictResolution, EntityType, OperationType, PullRequest, PullResponse,
-    PushRequest, PushResponse, SyncOperation, SyncRecord, SyncStatus,
+    PushRequest, PushResponse, SyncOperation, SyncRecord, SyncSnapshot, SyncStatus,
 };
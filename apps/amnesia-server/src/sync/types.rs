@@ -7,6 +7,9 @@
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::db::{ReaderPreferences, ReadingProgress};
 
 /// A sync record wrapping any syncable entity
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -55,7 +58,7 @@ impl<T> SyncRecord<T> {
 }
 
 /// Types of sync operations
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum OperationType {
     Create,
@@ -64,7 +67,7 @@ pub enum OperationType {
 }
 
 /// A sync operation representing a change
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct SyncOperation {
     /// Unique operation ID
     pub id: String,
@@ -79,6 +82,7 @@ pub struct SyncOperation {
     pub entity_id: String,
     /// The data payload (JSON)
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
     pub payload: Option<serde_json::Value>,
     /// Base version this operation was made against
     #[serde(rename = "baseVersion")]
@@ -91,7 +95,7 @@ pub struct SyncOperation {
 }
 
 /// Types of entities that can be synced
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum EntityType {
     Annotation,
@@ -100,7 +104,7 @@ pub enum EntityType {
 }
 
 /// Sync status for a book or device
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct SyncStatus {
     /// Last successful sync timestamp
     #[serde(rename = "lastSync")]
@@ -131,7 +135,7 @@ impl Default for SyncStatus {
 }
 
 /// Request to push changes to server
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct PushRequest {
     /// Device making the push
     #[serde(rename = "deviceId")]
@@ -147,7 +151,7 @@ pub struct PushRequest {
 }
 
 /// Response from push operation
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct PushResponse {
     /// Whether push was successful
     pub success: bool,
@@ -161,7 +165,7 @@ pub struct PushResponse {
 }
 
 /// Request to pull changes from server
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct PullRequest {
     /// Device making the pull
     #[serde(rename = "deviceId")]
@@ -175,7 +179,7 @@ pub struct PullRequest {
 }
 
 /// Response from pull operation
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct PullResponse {
     /// Operations since the requested version
     pub operations: Vec<SyncOperation>,
@@ -187,8 +191,21 @@ pub struct PullResponse {
     pub has_more: bool,
 }
 
+/// Account-wide state for setting up a new device
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SyncSnapshot {
+    /// Default reader preferences (empty if never saved)
+    pub preferences: ReaderPreferences,
+    /// Reading progress for every book
+    #[schema(value_type = Vec<Object>)]
+    pub progress: Vec<ReadingProgress>,
+    /// When the snapshot was taken
+    #[serde(rename = "generatedAt")]
+    pub generated_at: DateTime<Utc>,
+}
+
 /// A conflict between local and remote changes
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Conflict {
     /// Entity type in conflict
     #[serde(rename = "entityType")]
@@ -204,16 +221,18 @@ pub struct Conflict {
     pub server_version: u64,
     /// Local data
     #[serde(rename = "localData")]
+    #[schema(value_type = Object)]
     pub local_data: serde_json::Value,
     /// Server data
     #[serde(rename = "serverData")]
+    #[schema(value_type = Object)]
     pub server_data: serde_json::Value,
     /// Suggested resolution
     pub resolution: ConflictResolution,
 }
 
 /// How to resolve a conflict
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ConflictResolution {
     /// Keep the server version
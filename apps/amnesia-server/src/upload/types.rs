@@ -2,6 +2,7 @@
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 // ============================================================================
@@ -11,6 +12,9 @@ use uuid::Uuid;
 /// Default chunk size: 2MB
 pub const DEFAULT_CHUNK_SIZE: usize = 2 * 1024 * 1024;
 
+/// Maximum chunk size: 16MB, the body limit of the chunk upload route
+pub const MAX_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
 /// Maximum file size: 2GB (supports very large PDFs like dictionaries)
 pub const MAX_FILE_SIZE: u64 = 2 * 1024 * 1024 * 1024;
 
@@ -25,7 +29,7 @@ pub const MAX_CONCURRENT_UPLOADS: usize = 5;
 // ============================================================================
 
 /// Request to initiate a chunked upload
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct HandshakeRequest {
     /// Original file name
@@ -43,13 +47,13 @@ pub struct HandshakeRequest {
     /// MIME type of the file
     pub mime_type: String,
 
-    /// Optional: Expected chunk size (defaults to 2MB)
+    /// Optional: Expected chunk size (defaults to 2MB, at most 16MB)
     #[serde(default)]
     pub chunk_size: Option<usize>,
 }
 
 /// Response to handshake request
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct HandshakeResponse {
     /// Upload session ID
@@ -80,7 +84,7 @@ pub struct HandshakeResponse {
 // ============================================================================
 
 /// Response after uploading a chunk
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ChunkUploadResponse {
     /// Chunk index that was uploaded
@@ -104,7 +108,7 @@ pub struct ChunkUploadResponse {
 // ============================================================================
 
 /// Response after finalizing an upload
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct FinalizeResponse {
     /// The new book ID
@@ -221,7 +225,7 @@ impl UploadSession {
 }
 
 /// Session status
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum SessionStatus {
     /// Waiting for chunks
@@ -266,6 +270,9 @@ pub enum UploadError {
     #[error("File too large: {size} bytes (max: {max})")]
     FileTooLarge { size: u64, max: u64 },
 
+    #[error("Chunk too large: {size} bytes (max: {max})")]
+    ChunkTooLarge { size: usize, max: usize },
+
     #[error("Invalid file type: {0}")]
     InvalidFileType(String),
 
@@ -284,6 +291,7 @@ pub enum UploadError {
 
 impl UploadError {
     /// Get HTTP status code for this error
+    #[cfg(feature = "server")]
     pub fn status_code(&self) -> axum::http::StatusCode {
         use axum::http::StatusCode;
         match self {
@@ -294,6 +302,7 @@ impl UploadError {
             Self::ChunkIndexOutOfBounds { .. } => StatusCode::BAD_REQUEST,
             Self::ChunkAlreadyReceived(_) => StatusCode::CONFLICT,
             Self::FileTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::ChunkTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
             Self::InvalidFileType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
             Self::MissingChunks(_) => StatusCode::BAD_REQUEST,
             Self::StorageError(_) => StatusCode::INTERNAL_SERVER_ERROR,
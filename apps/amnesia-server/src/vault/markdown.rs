@@ -0,0 +1,310 @@
+//! Markdown rendering for vault pages
+//!
+//! Pages follow the conventions Obsidian and Logseq share: YAML front matter
+//! between `---` lines, and `[[Page]]` / `[[Page|label]]` wiki-links that
+//! resolve by file name, whatever folder the file ends up in.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use crate::annotations::{DocumentLink, LinkRelation};
+use crate::db::ProgressLocator;
+
+use super::{VaultBook, VaultNote};
+
+/// Longest file name (without `.md`) written for a book
+const MAX_STEM_CHARS: usize = 100;
+
+/// File name for a book title, without characters that break wiki-links or
+/// are not allowed in file names on some platform
+pub fn file_stem(title: &str) -> String {
+    let cleaned: String = title
+        .chars()
+        .map(|c| match c {
+            '[' | ']' | '#' | '^' | '|' | '\\' | '/' | ':' | '*' | '?' | '"' | '<' | '>' => ' ',
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .collect();
+    let stem: String = cleaned
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(MAX_STEM_CHARS)
+        .collect();
+
+    // Leading dots hide files; trailing dots and spaces are dropped on Windows
+    let stem = stem.trim_start_matches('.').trim_end_matches(['.', ' ']);
+    if stem.is_empty() {
+        "Untitled".to_string()
+    } else {
+        stem.to_string()
+    }
+}
+
+/// A distinct file name for every book, numbering repeated titles
+///
+/// Books are named in title order (then ID), so the same library always
+/// gets the same names.
+pub fn unique_stems<'a>(books: impl IntoIterator<Item = &'a VaultBook>) -> HashMap<String, String> {
+    let mut books: Vec<&VaultBook> = books.into_iter().collect();
+    books.sort_by(|a, b| (&a.title, &a.id).cmp(&(&b.title, &b.id)));
+
+    let mut taken = HashSet::new();
+    let mut stems = HashMap::new();
+    for book in books {
+        let base = file_stem(&book.title);
+        let mut stem = base.clone();
+        let mut n = 2;
+        // File names are case-insensitive on macOS and Windows
+        while !taken.insert(stem.to_lowercase()) {
+            stem = format!("{} ({})", base, n);
+            n += 1;
+        }
+        stems.insert(book.id.clone(), stem);
+    }
+    stems
+}
+
+/// Render a book's page: front matter, then its notes in reading order
+///
+/// `stems` maps book IDs to file names and must cover every linked book.
+pub fn render_book(
+    book: &VaultBook,
+    notes: &[VaultNote],
+    stems: &HashMap<String, String>,
+) -> String {
+    let mut out = String::from("---\n");
+    front_matter_str(&mut out, "title", Some(&book.title));
+    front_matter_list(&mut out, "authors", &book.authors);
+    front_matter_str(&mut out, "bookId", Some(&book.id));
+    front_matter_str(&mut out, "series", book.series.as_deref());
+    if let Some(index) = book.series_index {
+        let _ = writeln!(out, "seriesIndex: {}", index);
+    }
+    front_matter_str(&mut out, "publisher", book.publisher.as_deref());
+    front_matter_str(&mut out, "published", book.published.as_deref());
+    front_matter_str(&mut out, "language", book.language.as_deref());
+    front_matter_str(&mut out, "isbn", book.isbn.as_deref());
+    // Obsidian tags cannot contain spaces
+    let tags: Vec<String> = book
+        .tags
+        .iter()
+        .map(|tag| tag.split_whitespace().collect::<Vec<_>>().join("-"))
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    front_matter_list(&mut out, "tags", &tags);
+    let _ = writeln!(out, "highlights: {}", notes.len());
+    out.push_str("---\n\n");
+
+    let _ = writeln!(out, "# {}", book.title);
+    if !book.authors.is_empty() {
+        let _ = writeln!(out, "\n*{}*", book.authors.join(", "));
+    }
+
+    if !notes.is_empty() {
+        out.push_str("\n## Highlights\n");
+    }
+    for note in notes {
+        out.push('\n');
+        render_note(&mut out, note, stems);
+    }
+    out
+}
+
+fn render_note(out: &mut String, note: &VaultNote, stems: &HashMap<String, String>) {
+    if let Some(quote) = note.quote.as_deref().filter(|q| !q.trim().is_empty()) {
+        for line in quote.trim().lines() {
+            let _ = writeln!(out, "> {}", line.trim_end());
+        }
+        out.push('\n');
+    }
+    if let Some(text) = note.note.as_deref().filter(|n| !n.trim().is_empty()) {
+        let _ = writeln!(out, "{}\n", text.trim());
+    }
+    if !note.links.is_empty() {
+        for link in &note.links {
+            let _ = writeln!(out, "- {}", render_link(link, stems));
+        }
+        out.push('\n');
+    }
+
+    let mut details = vec![note.kind.clone()];
+    details.extend(note.location.clone());
+    details.extend(note.color.clone());
+    details.extend(note.created_at.get(..10).map(str::to_string));
+    let _ = writeln!(out, "*{}*", details.join(" · "));
+}
+
+/// One link as a list item: relation, wiki-link, and where in the book
+fn render_link(link: &DocumentLink, stems: &HashMap<String, String>) -> String {
+    let target = stems
+        .get(&link.book_id)
+        .cloned()
+        .unwrap_or_else(|| file_stem(&link.book_id));
+    let wiki = match link
+        .label
+        .as_deref()
+        .map(|l| l.replace(['[', ']', '|'], " "))
+    {
+        Some(label) if !label.trim().is_empty() => format!("[[{}|{}]]", target, label.trim()),
+        _ => format!("[[{}]]", target),
+    };
+    let relation = match link.relation {
+        LinkRelation::References => "See",
+        LinkRelation::Quotes => "Quotes",
+        LinkRelation::Supports => "Supported by",
+        LinkRelation::Contradicts => "Contradicted by",
+    };
+    match link.locator.as_ref().and_then(locator_text) {
+        Some(place) => format!("{} {} ({})", relation, wiki, place),
+        None => format!("{} {}", relation, wiki),
+    }
+}
+
+/// Human-readable place in a book; CFIs are not worth showing, except for
+/// the time of a temporal one
+fn locator_text(locator: &ProgressLocator) -> Option<String> {
+    match locator {
+        ProgressLocator::Cfi { .. } => locator.seconds().map(timestamp),
+        ProgressLocator::Page { page, .. } => Some(format!("p. {}", page)),
+        ProgressLocator::Position { position } => Some(format!("position {}", position)),
+        ProgressLocator::Progression { progression } => {
+            Some(format!("{:.0}%", progression * 100.0))
+        }
+    }
+}
+
+/// `m:ss` or `h:mm:ss` for a time into audio or video
+pub fn timestamp(seconds: f64) -> String {
+    let total = seconds.max(0.0) as u64;
+    let (hours, minutes, seconds) = (total / 3600, total / 60 % 60, total % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+fn front_matter_str(out: &mut String, key: &str, value: Option<&str>) {
+    if let Some(value) = value.filter(|v| !v.trim().is_empty()) {
+        let _ = writeln!(out, "{}: {}", key, yaml_string(value));
+    }
+}
+
+fn front_matter_list(out: &mut String, key: &str, values: &[String]) {
+    if values.is_empty() {
+        return;
+    }
+    let _ = writeln!(out, "{}:", key);
+    for value in values {
+        let _ = writeln!(out, "  - {}", yaml_string(value));
+    }
+}
+
+/// Double-quoted YAML scalar
+fn yaml_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => {}
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book(id: &str, title: &str) -> VaultBook {
+        VaultBook {
+            id: id.to_string(),
+            title: title.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_file_stems() {
+        assert_eq!(
+            file_stem("Gödel, Escher, Bach: an Eternal Golden Braid"),
+            "Gödel, Escher, Bach an Eternal Golden Braid"
+        );
+        assert_eq!(file_stem("What? [Revised]"), "What Revised");
+        assert_eq!(
+            file_stem("...And Then There Were None."),
+            "And Then There Were None"
+        );
+        assert_eq!(file_stem("///"), "Untitled");
+
+        let books = [
+            book("b", "Dune"),
+            book("a", "Dune"),
+            book("c", "dune"),
+            book("d", "Emma"),
+        ];
+        let stems = unique_stems(&books);
+        assert_eq!(stems["a"], "Dune");
+        assert_eq!(stems["b"], "Dune (2)");
+        assert_eq!(stems["c"], "dune (3)");
+        assert_eq!(stems["d"], "Emma");
+    }
+
+    #[test]
+    fn test_timestamp() {
+        assert_eq!(timestamp(0.0), "0:00");
+        assert_eq!(timestamp(75.9), "1:15");
+        assert_eq!(timestamp(3725.0), "1:02:05");
+    }
+
+    #[test]
+    fn test_render_book() {
+        let writing = VaultBook {
+            authors: vec!["William Zinsser".to_string()],
+            tags: vec!["Non Fiction".to_string()],
+            series_index: Some(2.0),
+            ..book("zinsser", "Writing to Learn: \"How\" to Write")
+        };
+        let other = book("other", "On Writing Well");
+        let stems = unique_stems([&writing, &other]);
+
+        let note = VaultNote {
+            kind: "note".to_string(),
+            quote: Some("Writing is thinking on paper.\nSecond line".to_string()),
+            note: Some("Compare with Zinsser's other book".to_string()),
+            color: Some("yellow".to_string()),
+            location: Some("p. xiv".to_string()),
+            links: vec![DocumentLink {
+                book_id: "other".to_string(),
+                locator: Some(ProgressLocator::Page { page: 12, y: None }),
+                relation: LinkRelation::Supports,
+                label: Some("clutter".to_string()),
+            }],
+            created_at: "2024-03-01T10:00:00+00:00".to_string(),
+        };
+        let page = render_book(&writing, &[note], &stems);
+
+        assert!(page.starts_with("---\ntitle: \"Writing to Learn: \\\"How\\\" to Write\"\n"));
+        assert!(page.contains("authors:\n  - \"William Zinsser\"\n"));
+        assert!(page.contains("seriesIndex: 2\n"));
+        assert!(page.contains("tags:\n  - \"Non-Fiction\"\n"));
+        assert!(page.contains("highlights: 1\n---\n\n# Writing to Learn"));
+        assert!(page.contains("> Writing is thinking on paper.\n> Second line\n"));
+        assert!(page.contains("- Supported by [[On Writing Well|clutter]] (p. 12)\n"));
+        assert!(page.contains("*note · p. xiv · yellow · 2024-03-01*"));
+
+        // A book without notes is still a node in the graph
+        let stub = render_book(&other, &[], &stems);
+        assert!(stub.contains("highlights: 0"));
+        assert!(!stub.contains("## Highlights"));
+    }
+}
@@ -0,0 +1,395 @@
+//! Notes export as a Markdown vault (Obsidian, Logseq)
+//!
+//! [`export_vault`] writes one Markdown page per book that has highlights or
+//! notes, from both the highlights table and the annotation store. Each page
+//! starts with YAML front matter holding the book's metadata, and links
+//! between books ([`DocumentLink`]) become `[[wiki-links]]`, so the note
+//! graph carries over when the zip is unpacked into a vault. Books that are
+//! only linked to get a page too, so every link resolves.
+//!
+//! Book IDs are matched against [`LibraryBook::stable_id`]; highlights of
+//! books no longer in the library are exported under their ID. Colors are
+//! written as the user's palette category names, and an export can be
+//! limited to one category.
+
+pub mod markdown;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::io::{Cursor, Write};
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+use crate::annotations::{
+    Annotation, AnnotationQuery, AnnotationRepository, AnnotationType, DocumentLink,
+};
+use crate::db::{Highlight, HighlightPalette, HighlightRepository, PaletteRepository};
+use crate::error::{AppError, Result};
+use crate::library::LibraryBook;
+
+/// Folder the pages are written to inside the zip
+pub const VAULT_DIR: &str = "Los Libros";
+
+/// Book metadata for a vault page's front matter
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VaultBook {
+    pub id: String,
+    pub title: String,
+    pub authors: Vec<String>,
+    pub series: Option<String>,
+    pub series_index: Option<f32>,
+    pub tags: Vec<String>,
+    pub publisher: Option<String>,
+    pub published: Option<String>,
+    pub language: Option<String>,
+    pub isbn: Option<String>,
+}
+
+impl VaultBook {
+    /// Placeholder for a book that is not in the library
+    pub fn unknown(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            title: id.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<&LibraryBook> for VaultBook {
+    fn from(book: &LibraryBook) -> Self {
+        let mut authors = book.authors.clone();
+        if authors.is_empty() {
+            authors.extend(book.author.clone());
+        }
+        Self {
+            id: book.stable_id(),
+            title: book.title.clone(),
+            authors,
+            series: book.series.clone(),
+            series_index: book.series_index,
+            tags: book.tags.clone(),
+            publisher: book.publisher.clone(),
+            published: book.pubdate.clone(),
+            language: book.language.clone(),
+            isbn: book.identifiers.get("isbn").cloned(),
+        }
+    }
+}
+
+/// A highlight or note, from either store
+#[derive(Debug, Clone, PartialEq)]
+pub struct VaultNote {
+    /// "highlight", "note", "underline", or "bookmark"
+    pub kind: String,
+    /// Highlighted text
+    pub quote: Option<String>,
+    /// The reader's note
+    pub note: Option<String>,
+    pub color: Option<String>,
+    /// Where in the book, e.g. "p. xiv" or a chapter name
+    pub location: Option<String>,
+    pub links: Vec<DocumentLink>,
+    /// RFC 3339 creation time
+    pub created_at: String,
+}
+
+impl VaultNote {
+    /// Replace a palette color's hex with its category name
+    ///
+    /// Colors outside the palette are left as stored.
+    fn categorize(&mut self, palette: &HighlightPalette) {
+        if let Some(entry) = self.color.as_deref().and_then(|c| palette.resolve(c)) {
+            self.color = Some(entry.name.clone());
+        }
+    }
+}
+
+impl From<&Highlight> for VaultNote {
+    fn from(highlight: &Highlight) -> Self {
+        let location = match (&highlight.page_label, highlight.page) {
+            (Some(label), _) => Some(format!("p. {}", label)),
+            (None, Some(page)) => Some(format!("p. {}", page)),
+            (None, None) => highlight.chapter.clone(),
+        };
+        Self {
+            kind: highlight.annotation_type.clone(),
+            quote: Some(highlight.text.clone()),
+            note: highlight.annotation.clone(),
+            color: Some(highlight.color.clone()),
+            location,
+            links: Vec::new(),
+            created_at: highlight.created_at.clone(),
+        }
+    }
+}
+
+impl From<&Annotation> for VaultNote {
+    fn from(annotation: &Annotation) -> Self {
+        let kind = match annotation.annotation_type {
+            AnnotationType::Highlight => "highlight",
+            AnnotationType::Bookmark => "bookmark",
+            AnnotationType::Note => "note",
+            AnnotationType::Underline => "underline",
+        };
+        Self {
+            kind: kind.to_string(),
+            quote: annotation
+                .text_quote()
+                .or_else(|| annotation.pdf_text_quote())
+                .map(str::to_string),
+            note: annotation.body.as_ref().and_then(|b| b.value.clone()),
+            color: annotation.style.as_ref().map(|s| s.color.clone()),
+            location: annotation
+                .pdf_page()
+                .map(|page| format!("p. {}", page))
+                .or_else(|| annotation.seconds().map(markdown::timestamp)),
+            links: annotation.links().to_vec(),
+            created_at: annotation.created_at.to_rfc3339(),
+        }
+    }
+}
+
+/// A Markdown page in the vault
+#[derive(Debug, Clone, PartialEq)]
+pub struct VaultFile {
+    /// Path inside the zip
+    pub path: String,
+    pub contents: String,
+}
+
+/// What an export contained
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultSummary {
+    /// Pages written, including pages for books that are only linked to
+    pub books: usize,
+    pub notes: usize,
+    pub links: usize,
+}
+
+impl fmt::Display for VaultSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Exported {} notes with {} links across {} books",
+            self.notes, self.links, self.books
+        )
+    }
+}
+
+/// A packaged vault
+#[derive(Debug, Clone)]
+pub struct VaultExport {
+    /// Zip archive of the Markdown pages
+    pub zip: Vec<u8>,
+    pub summary: VaultSummary,
+}
+
+/// Export every highlight and note (only `user_id`'s, if given) as a zip
+///
+/// With a `category`, only notes in that palette color are exported; a
+/// category missing from the palette is rejected.
+pub async fn export_vault(
+    pool: &SqlitePool,
+    library: &[LibraryBook],
+    user_id: Option<&str>,
+    category: Option<&str>,
+) -> Result<VaultExport> {
+    let palette = PaletteRepository::new(pool).get(user_id).await?;
+    let category = category
+        .map(|category| palette.lookup(category).map(|entry| entry.name.clone()))
+        .transpose()?;
+
+    let mut found = Vec::new();
+    for highlight in HighlightRepository::new(pool).list(user_id).await? {
+        found.push((highlight.book_id.clone(), VaultNote::from(&highlight)));
+    }
+
+    let annotations = AnnotationRepository::new(pool);
+    annotations.init().await.map_err(annotation_error)?;
+    let query = AnnotationQuery {
+        user_id: user_id.map(str::to_string),
+        ..Default::default()
+    };
+    for annotation in annotations.list(&query).await.map_err(annotation_error)? {
+        found.push((annotation.book_id.clone(), VaultNote::from(&annotation)));
+    }
+
+    let mut notes: BTreeMap<String, Vec<VaultNote>> = BTreeMap::new();
+    for (book_id, mut note) in found {
+        note.categorize(&palette);
+        if category.is_none() || note.color == category {
+            notes.entry(book_id).or_default().push(note);
+        }
+    }
+
+    let books: HashMap<String, VaultBook> = library
+        .iter()
+        .map(VaultBook::from)
+        .map(|book| (book.id.clone(), book))
+        .collect();
+    let (files, summary) = build_vault(&books, notes);
+    Ok(VaultExport {
+        zip: write_zip(&files)?,
+        summary,
+    })
+}
+
+/// Render the pages for each book's notes
+///
+/// `books` is the library's metadata by book ID; books with notes or links
+/// that it lacks are rendered under their ID.
+pub fn build_vault(
+    books: &HashMap<String, VaultBook>,
+    mut notes: BTreeMap<String, Vec<VaultNote>>,
+) -> (Vec<VaultFile>, VaultSummary) {
+    let linked: BTreeSet<String> = notes
+        .values()
+        .flatten()
+        .flat_map(|note| note.links.iter().map(|link| link.book_id.clone()))
+        .collect();
+    let pages: Vec<VaultBook> = notes
+        .keys()
+        .chain(linked.iter())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(|id| {
+            books
+                .get(id)
+                .cloned()
+                .unwrap_or_else(|| VaultBook::unknown(id))
+        })
+        .collect();
+    let stems = markdown::unique_stems(&pages);
+
+    let mut summary = VaultSummary {
+        books: pages.len(),
+        ..Default::default()
+    };
+    let files = pages
+        .iter()
+        .map(|book| {
+            let mut book_notes = notes.remove(&book.id).unwrap_or_default();
+            book_notes.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+            summary.notes += book_notes.len();
+            summary.links += book_notes.iter().map(|n| n.links.len()).sum::<usize>();
+            VaultFile {
+                path: format!("{}/{}.md", VAULT_DIR, stems[&book.id]),
+                contents: markdown::render_book(book, &book_notes, &stems),
+            }
+        })
+        .collect();
+    (files, summary)
+}
+
+/// Package pages as a zip archive
+pub fn write_zip(files: &[VaultFile]) -> Result<Vec<u8>> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+    for file in files {
+        zip.start_file(file.path.as_str(), options)
+            .map_err(zip_error)?;
+        zip.write_all(file.contents.as_bytes())?;
+    }
+    Ok(zip.finish().map_err(zip_error)?.into_inner())
+}
+
+fn annotation_error(e: anyhow::Error) -> AppError {
+    AppError::Internal(format!("Failed to read annotations: {}", e))
+}
+
+fn zip_error(e: zip::result::ZipError) -> AppError {
+    AppError::Internal(format!("Failed to write vault zip: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    use crate::annotations::{AnnotationTarget, LinkRelation};
+    use crate::db::{initialize_schema, CreateHighlight};
+
+    #[tokio::test]
+    async fn test_export_vault() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        initialize_schema(&pool).await.unwrap();
+
+        let mut library_book =
+            LibraryBook::new("Writing to Learn".to_string(), "Zinsser/W".to_string());
+        library_book.authors = vec!["William Zinsser".to_string()];
+        library_book
+            .identifiers
+            .insert("uuid".to_string(), "zinsser".to_string());
+
+        let highlight = CreateHighlight {
+            document_format: Some("pdf".to_string()),
+            annotation_type: None,
+            cfi: None,
+            page: Some(3),
+            page_label: Some("xiv".to_string()),
+            text: "Writing is thinking on paper.".to_string(),
+            chapter: None,
+            page_percent: None,
+            color: None,
+            annotation: None,
+            text_prefix: None,
+            text_suffix: None,
+            region: None,
+            rects: None,
+        };
+        HighlightRepository::new(&pool)
+            .create("zinsser", None, &highlight)
+            .await
+            .unwrap();
+
+        let repo = AnnotationRepository::new(&pool);
+        repo.init().await.unwrap();
+        let target = AnnotationTarget::from_cfi("ch1.xhtml", "epubcfi(/6/4!/4/2)");
+        let mut note = Annotation::new_note("zinsser", target, "Same idea as Didion");
+        note.set_links(vec![DocumentLink {
+            book_id: "didion".to_string(),
+            locator: None,
+            relation: LinkRelation::Supports,
+            label: None,
+        }]);
+        repo.save(&note).await.unwrap();
+
+        let export = export_vault(&pool, &[library_book.clone()], None, None)
+            .await
+            .unwrap();
+        assert_eq!(export.summary.books, 2);
+        assert_eq!(export.summary.notes, 2);
+        assert_eq!(export.summary.links, 1);
+
+        let mut archive = zip::ZipArchive::new(Cursor::new(export.zip)).unwrap();
+        let mut page = String::new();
+        archive
+            .by_name("Los Libros/Writing to Learn.md")
+            .unwrap()
+            .read_to_string(&mut page)
+            .unwrap();
+        assert!(page.contains("bookId: \"zinsser\""));
+        assert!(page.contains("> Writing is thinking on paper."));
+        assert!(page.contains("p. xiv"));
+        assert!(page.contains("Supported by [[didion]]"));
+        // The linked book is not in the library but still gets a page
+        assert!(archive.by_name("Los Libros/didion.md").is_ok());
+
+        // The highlight is in the default "yellow" category; the note's
+        // #ffff00 is not in the palette
+        let export = export_vault(&pool, &[library_book.clone()], None, Some("Yellow"))
+            .await
+            .unwrap();
+        assert_eq!(export.summary.notes, 1);
+        assert_eq!(export.summary.links, 0);
+        assert!(matches!(
+            export_vault(&pool, &[library_book], None, Some("teal")).await,
+            Err(AppError::BadRequest(_))
+        ));
+    }
+}
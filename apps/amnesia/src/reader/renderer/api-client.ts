@@ -11,6 +11,7 @@ import type {
   ParsedBook,
   ChapterContent,
   Annotation,
+  AnnotationVersion,
   ReadingProgress,
   SyncStatus,
   PushRequest,
@@ -44,13 +45,48 @@ export class ApiError extends Error {
   constructor(
     message: string,
     public statusCode: number,
-    public code?: string
+    public code?: string,
+    public retryable = false,
+    /** Server correlation ID (X-Request-Id) of the failed request */
+    public requestId?: string
   ) {
     super(message);
     this.name = 'ApiError';
   }
 }
 
+/**
+ * RFC 7807 problem details body returned by the server on errors
+ */
+interface ProblemDetails {
+  type: string;
+  title: string;
+  status: number;
+  detail?: string;
+  instance?: string;
+  retryable?: boolean;
+  reason?: string;
+  requestId?: string;
+}
+
+const PROBLEM_TYPE_PREFIX = 'urn:amnesia:problem:';
+
+/**
+ * Front-end failure report sent to POST /api/v1/client-errors
+ */
+export interface ClientErrorReport {
+  message: string;
+  level?: 'warning' | 'error' | 'fatal';
+  stack?: string;
+  /** X-Request-Id of the server response being handled, if any */
+  requestId?: string;
+  code?: string;
+  component?: string;
+  appVersion?: string;
+  bookId?: string;
+  context?: Record<string, unknown>;
+}
+
 /**
  * Amnesia API Client
  */
@@ -249,6 +285,62 @@ export class ApiClient {
     return response.blob();
   }
 
+  /**
+   * Download a prebuilt search index for the WASM reader
+   *
+   * Pass the bytes to the WASM processor's `importSearchIndex` to search
+   * the book offline. Alt text, captions and footnotes are only indexed
+   * when asked for.
+   */
+  async getSearchIndex(
+    bookId: string,
+    options: { altText?: boolean; captions?: boolean; footnotes?: boolean } = {}
+  ): Promise<Uint8Array> {
+    const params = new URLSearchParams();
+    if (options.altText) params.set('alt_text', 'true');
+    if (options.captions) params.set('captions', 'true');
+    if (options.footnotes) params.set('footnotes', 'true');
+    const query = params.toString();
+    const url = `/api/v1/documents/${encodeURIComponent(bookId)}/search-index${query ? `?${query}` : ''}`;
+    const response = await this.fetch(url, {});
+    return new Uint8Array(await response.arrayBuffer());
+  }
+
+  /**
+   * Content checksums of an EPUB's spine items
+   *
+   * Compare with the list from before a book file update (or pass it to the
+   * WASM processor's `changedChapters`) to find the chapters to reindex.
+   */
+  async getChapterChecksums(
+    bookId: string
+  ): Promise<Array<{ spineIndex: number; href: string; checksum: string }>> {
+    return this.request(`/api/v1/documents/${encodeURIComponent(bookId)}/checksums`);
+  }
+
+  /**
+   * Tell the server a document was opened so it can pin and prewarm it
+   */
+  async openDocument(
+    documentId: string,
+    options: { prewarmItems?: number; resumeItem?: number; scale?: number; format?: string } = {}
+  ): Promise<{ id: string; openSessions: number; resumeItem: number | null; prewarmItems: number[]; prewarmTextItems: number[] }> {
+    return this.request(`/api/v1/documents/${encodeURIComponent(documentId)}/open`, {
+      method: 'POST',
+      body: JSON.stringify(options),
+      headers: { 'Content-Type': 'application/json' },
+    });
+  }
+
+  /**
+   * Tell the server a document was closed so it can release cached output
+   */
+  async closeDocument(documentId: string): Promise<{ id: string; openSessions: number; released: boolean }> {
+    return this.request(`/api/v1/documents/${encodeURIComponent(documentId)}/close`, {
+      method: 'POST',
+    });
+  }
+
   /**
    * Get resource as data URL for embedding
    */
@@ -322,6 +414,27 @@ export class ApiClient {
     });
   }
 
+  /**
+   * List an annotation's previous versions, newest first
+   */
+  async listAnnotationVersions(annotationId: string): Promise<AnnotationVersion[]> {
+    const response = await this.request<{ versions: AnnotationVersion[] }>(
+      `/api/v1/annotations/${encodeURIComponent(annotationId)}/versions`
+    );
+    return response.versions;
+  }
+
+  /**
+   * Undo edits by restoring a previous version; the replaced content
+   * becomes a version itself
+   */
+  async restoreAnnotationVersion(annotationId: string, version: number): Promise<Annotation> {
+    return this.request<Annotation>(
+      `/api/v1/annotations/${encodeURIComponent(annotationId)}/versions/${version}/restore`,
+      { method: 'POST' }
+    );
+  }
+
   // ============================================================================
   // Progress Operations
   // ============================================================================
@@ -356,6 +469,48 @@ export class ApiClient {
     );
   }
 
+  // ============================================================================
+  // Client Error Reporting
+  // ============================================================================
+
+  /**
+   * Report a front-end failure so it is logged next to the server request.
+   * Never throws; reporting must not cause further errors.
+   */
+  async reportClientError(report: ClientErrorReport): Promise<void> {
+    try {
+      await this.fetch('/api/v1/client-errors', {
+        method: 'POST',
+        body: JSON.stringify(report),
+        headers: { 'Content-Type': 'application/json' },
+      });
+    } catch (e) {
+      console.warn('[ApiClient] Failed to report client error:', e);
+    }
+  }
+
+  /**
+   * Build a report from an error, carrying over the server request ID
+   */
+  static toClientErrorReport(
+    error: unknown,
+    extra: Omit<ClientErrorReport, 'message'> = {}
+  ): ClientErrorReport {
+    if (error instanceof ApiError) {
+      return {
+        ...extra,
+        message: error.message,
+        stack: error.stack,
+        requestId: error.requestId,
+        code: error.code,
+      };
+    }
+    if (error instanceof Error) {
+      return { ...extra, message: error.message, stack: error.stack };
+    }
+    return { ...extra, message: String(error) };
+  }
+
   // ============================================================================
   // Sync Operations
   // ============================================================================
@@ -739,13 +894,24 @@ export class ApiClient {
 
       if (!response.ok) {
         let message = `HTTP ${response.status}`;
+        let code: string | undefined;
+        let retryable = false;
+        let requestId = response.headers.get('x-request-id') ?? undefined;
         try {
           const error = await response.json();
-          message = error.error?.message || error.message || message;
+          if (response.headers.get('content-type')?.includes('application/problem+json')) {
+            const problem = error as ProblemDetails;
+            message = problem.detail || problem.title || message;
+            code = problem.type?.replace(PROBLEM_TYPE_PREFIX, '');
+            retryable = problem.retryable ?? false;
+            requestId = problem.requestId ?? requestId;
+          } else {
+            message = error.error?.message || error.message || message;
+          }
         } catch {
           // Ignore JSON parse errors
         }
-        throw new ApiError(message, response.status);
+        throw new ApiError(message, response.status, code, retryable, requestId);
       }
 
       return response;
@@ -374,12 +374,24 @@ export class EpubRenderer {
       // Handle navigation keys directly
       switch (e.key) {
         case 'ArrowLeft':
+        case 'ArrowRight': {
+          e.preventDefault();
+          e.stopPropagation();
+          if (e.repeat) return; // Ignore key repeat
+          // The page to the left is the next one in right-to-left books
+          const forward = (e.key === 'ArrowRight') !== this.isRightToLeft();
+          if (forward) {
+            this.next();
+          } else {
+            this.prev();
+          }
+          return;
+        }
         case 'PageUp':
           e.preventDefault();
           e.stopPropagation();
           if (!e.repeat) this.prev(); // Ignore key repeat
           return;
-        case 'ArrowRight':
         case 'PageDown':
           e.preventDefault();
           e.stopPropagation();
@@ -629,6 +641,18 @@ export class EpubRenderer {
     }
   }
 
+  /**
+   * Whether pages turn right to left (manga, Arabic, vertical Japanese).
+   * Books written right to left turn that way unless the spine says otherwise.
+   */
+  private isRightToLeft(): boolean {
+    const rendition = this.book?.rendition;
+    if (rendition?.pageProgressionDirection) {
+      return rendition.pageProgressionDirection === 'rtl';
+    }
+    return rendition?.writingMode?.endsWith('-rl') ?? false;
+  }
+
   /**
    * Get per-chapter page info from the book-wide page number
    * Returns the page number within the current chapter and the total pages in that chapter
@@ -46,6 +46,13 @@ export interface SpineItem {
   href: string;
   linear: boolean;
   mediaType: string;
+  /** Overrides the book's layout for this item */
+  layout?: 'reflowable' | 'pre-paginated';
+  pageSpread?: 'left' | 'right' | 'center';
+  /** Overrides the book's spread for this item */
+  spread?: string;
+  /** Page size in CSS pixels of a pre-paginated item */
+  viewport?: { width: number; height: number };
 }
 
 /**
@@ -56,6 +63,17 @@ export interface ParsedBook {
   metadata: BookMetadata;
   toc: TocEntry[];
   spine: SpineItem[];
+  /** Fixed-layout properties; absent means reflowable */
+  rendition?: {
+    layout: 'reflowable' | 'pre-paginated';
+    orientation?: string;
+    spread?: string;
+    viewport?: { width: number; height: number };
+    /** Direction pages turn in; unset for the default */
+    pageProgressionDirection?: 'ltr' | 'rtl';
+    /** e.g. "vertical-rl" */
+    writingMode?: string;
+  };
 }
 
 /**
@@ -248,6 +266,19 @@ export interface Annotation {
   deviceId: string;
 }
 
+/**
+ * An annotation's note and style as they were before an edit
+ */
+export interface AnnotationVersion {
+  annotationId: string;
+  /** 1 for the original content, counting up with each edit */
+  version: number;
+  body?: { value?: string };
+  style?: { color: string; opacity?: number };
+  savedAt: string;
+  replacedAt: string;
+}
+
 /**
  * Highlight rendered in the chapter (from server)
  */
@@ -3,6 +3,10 @@
  *
  * Uses the Rust WASM EPUB processor for offline book reading.
  * Falls back when server is unavailable.
+ *
+ * Search indexes are cached in IndexedDB, so a book is only indexed the
+ * first time it is searched rather than on every page load. Cached indexes
+ * are tagged with the book's fingerprint, so an edited book is re-indexed.
  */
 
 import type { ParsedBook, ChapterContent } from './types';
@@ -16,6 +20,16 @@ import {
   type ParsedBook as WasmParsedBook,
   type ChapterContent as WasmChapterContent,
 } from '../../wasm';
+import { getIndexedDBStore } from '../../cache/indexed-db-store';
+
+/** IndexedDB href under which a book's serialized search index is stored */
+const SEARCH_INDEX_HREF = '__search-index__';
+
+/**
+ * EPUBs at least this large are loaded lazily, decompressing resources as
+ * chapters request them instead of extracting every image up front
+ */
+const LAZY_LOAD_THRESHOLD = 64 * 1024 * 1024;
 
 /**
  * WASM-based book provider for offline reading
@@ -25,6 +39,10 @@ export class WasmBookProvider implements BookProvider {
 
   private processor: WasmEpubProcessor | null = null;
   private resourceUrls: Map<string, string> = new Map();
+  /** Books whose search index is loaded in the processor */
+  private indexedBooks: Set<string> = new Set();
+  /** Content fingerprint of each loaded book, by id */
+  private fingerprints: Map<string, string> = new Map();
   private wasmSource?: string | ArrayBuffer;
 
   /**
@@ -70,9 +88,14 @@ export class WasmBookProvider implements BookProvider {
     }
 
     const uint8Array = new Uint8Array(data);
-    const wasmBook = await this.processor.loadBook(uint8Array);
-
-    return this.convertParsedBook(wasmBook as WasmParsedBook);
+    const wasmBook = (await this.processor.loadBook(uint8Array, {
+      lazy: data.byteLength >= LAZY_LOAD_THRESHOLD,
+    })) as WasmParsedBook;
+    // A replaced book keeps its id but not its index
+    this.indexedBooks.delete(wasmBook.id);
+    this.fingerprints.set(wasmBook.id, wasmBook.fingerprint);
+
+    return this.convertParsedBook(wasmBook);
   }
 
   /**
@@ -135,6 +158,8 @@ export class WasmBookProvider implements BookProvider {
     if (this.processor) {
       this.processor.unloadBook(bookId);
     }
+    this.indexedBooks.delete(bookId);
+    this.fingerprints.delete(bookId);
 
     // Revoke blob URLs for this book
     for (const [key, url] of this.resourceUrls.entries()) {
@@ -153,12 +178,49 @@ export class WasmBookProvider implements BookProvider {
       throw new Error('WASM processor not available');
     }
 
-    // Build search index if not already built
-    await this.processor.buildSearchIndex(bookId);
+    await this.ensureSearchIndex(this.processor, bookId);
 
     return this.processor.search(bookId, query, limit);
   }
 
+  /**
+   * Load a book's search index from IndexedDB, or build it and cache it there
+   *
+   * Cache failures only cost a rebuild; an entry the processor cannot read
+   * (e.g. from an older index format) or that was built from other content
+   * under the same id is replaced.
+   */
+  private async ensureSearchIndex(processor: WasmEpubProcessor, bookId: string): Promise<void> {
+    if (this.indexedBooks.has(bookId)) {
+      return;
+    }
+
+    const store = getIndexedDBStore();
+    const fingerprint = this.fingerprints.get(bookId);
+    try {
+      const cached = await store.get(store.makeKey(bookId, SEARCH_INDEX_HREF));
+      if (cached && fingerprint && cached.metadata?.fingerprint === fingerprint) {
+        processor.importSearchIndex(bookId, new Uint8Array(cached.data));
+        this.indexedBooks.add(bookId);
+        return;
+      }
+    } catch (e) {
+      console.warn('[WasmProvider] Cached search index unusable, rebuilding:', e);
+    }
+
+    await processor.buildSearchIndex(bookId);
+    this.indexedBooks.add(bookId);
+
+    try {
+      const bytes = processor.exportSearchIndex(bookId);
+      await store.set(bookId, SEARCH_INDEX_HREF, bytes.slice().buffer, 'application/octet-stream', {
+        fingerprint,
+      });
+    } catch (e) {
+      console.warn('[WasmProvider] Failed to cache search index:', e);
+    }
+  }
+
   /**
    * Convert WASM ParsedBook to our ParsedBook type
    */
@@ -172,6 +234,10 @@ export class WasmBookProvider implements BookProvider {
       href: item.href,
       mediaType: item.mediaType,
       linear: item.linear,
+      layout: item.layout ?? undefined,
+      pageSpread: item.pageSpread ?? undefined,
+      spread: item.spread ?? undefined,
+      viewport: item.viewport ?? undefined,
     }));
 
     // Convert TOC entries, with fallback to generate from spine if empty
@@ -200,6 +266,14 @@ export class WasmBookProvider implements BookProvider {
       },
       spine,
       toc,
+      rendition: {
+        layout: wasm.rendition.layout,
+        orientation: wasm.rendition.orientation ?? undefined,
+        spread: wasm.rendition.spread ?? undefined,
+        viewport: wasm.rendition.viewport ?? undefined,
+        pageProgressionDirection: wasm.rendition.pageProgressionDirection ?? undefined,
+        writingMode: wasm.rendition.writingMode ?? undefined,
+      },
     };
   }
 
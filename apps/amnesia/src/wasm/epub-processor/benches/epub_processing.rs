@@ -0,0 +1,173 @@
+//! EPUB Processing Benchmarks
+//!
+//! Native benchmarks for the code paths the plugin runs in WASM: EPUB parse,
+//! search index build, and search query. Native numbers track WASM closely
+//! enough to catch regressions before a wasm-pack build.
+//!
+//! Run with: `cargo bench --bench epub_processing`
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::io::{Cursor, Write};
+use std::time::Duration;
+
+use epub_processor::epub::EpubBook;
+use epub_processor::SearchIndex;
+
+/// Book sizes (chapter count) exercised by the benchmarks
+const CHAPTER_COUNTS: &[usize] = &[10, 50, 200];
+
+/// Paragraphs per generated chapter
+const PARAGRAPHS_PER_CHAPTER: usize = 40;
+
+/// Build a synthetic EPUB 3 with the given number of chapters
+fn create_epub(chapters: usize) -> Vec<u8> {
+    use zip::write::FileOptions;
+    use zip::{CompressionMethod, ZipWriter};
+
+    let mut buffer = Vec::new();
+    {
+        let mut zip = ZipWriter::new(Cursor::new(&mut buffer));
+        let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+        let deflated = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        zip.start_file("mimetype", stored).unwrap();
+        zip.write_all(b"application/epub+zip").unwrap();
+
+        zip.start_file("META-INF/container.xml", deflated).unwrap();
+        zip.write_all(
+            br#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>"#,
+        )
+        .unwrap();
+
+        let mut manifest = String::new();
+        let mut spine = String::new();
+        let mut nav_items = String::new();
+        for i in 0..chapters {
+            manifest.push_str(&format!(
+                r#"<item id="ch{i}" href="Text/chapter{i}.xhtml" media-type="application/xhtml+xml"/>"#
+            ));
+            spine.push_str(&format!(r#"<itemref idref="ch{i}"/>"#));
+            nav_items.push_str(&format!(
+                r#"<li><a href="Text/chapter{i}.xhtml">Chapter {i}</a></li>"#
+            ));
+        }
+
+        zip.start_file("OEBPS/content.opf", deflated).unwrap();
+        zip.write_all(
+            format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="uid">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="uid">urn:uuid:bench-{chapters}</dc:identifier>
+    <dc:title>Benchmark Book</dc:title>
+    <dc:creator>Bench Author</dc:creator>
+    <dc:language>en</dc:language>
+  </metadata>
+  <manifest>
+    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    {manifest}
+  </manifest>
+  <spine>{spine}</spine>
+</package>"#
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+
+        zip.start_file("OEBPS/nav.xhtml", deflated).unwrap();
+        zip.write_all(
+            format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<body><nav epub:type="toc"><ol>{nav_items}</ol></nav></body>
+</html>"#
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+
+        for i in 0..chapters {
+            let mut body = format!("<h1>Chapter {i}</h1>");
+            for p in 0..PARAGRAPHS_PER_CHAPTER {
+                body.push_str(&format!(
+                    "<p>Paragraph {p} of chapter {i}. The quick brown fox jumps over the lazy dog \
+                     while the reader searches the shelves for a cafe guide and the needle{p}.</p>"
+                ));
+            }
+            zip.start_file(format!("OEBPS/Text/chapter{i}.xhtml"), deflated)
+                .unwrap();
+            zip.write_all(
+                format!(
+                    r#"<?xml version="1.0" encoding="UTF-8"?>
+<html xmlns="http://www.w3.org/1999/xhtml"><head><title>Chapter {i}</title></head>
+<body>{body}</body></html>"#
+                )
+                .as_bytes(),
+            )
+            .unwrap();
+        }
+
+        zip.finish().unwrap();
+    }
+    buffer
+}
+
+fn bench_epub_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("epub_parse");
+    group.measurement_time(Duration::from_secs(10));
+
+    for &chapters in CHAPTER_COUNTS {
+        let data = create_epub(chapters);
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(chapters), &data, |b, data| {
+            b.iter(|| EpubBook::from_bytes(black_box(data)).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_search_index_build(c: &mut Criterion) {
+    let mut group = c.benchmark_group("search_index_build");
+    group.measurement_time(Duration::from_secs(10));
+    group.sample_size(20);
+
+    for &chapters in CHAPTER_COUNTS {
+        let book = EpubBook::from_bytes(&create_epub(chapters)).unwrap();
+        group.throughput(Throughput::Elements(chapters as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(chapters), &book, |b, book| {
+            b.iter(|| SearchIndex::build(black_box(book)).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_search_query(c: &mut Criterion) {
+    let mut group = c.benchmark_group("search_query");
+
+    let book = EpubBook::from_bytes(&create_epub(200)).unwrap();
+    let index = SearchIndex::build(&book).unwrap();
+
+    // Common word, rare word, phrase, and a miss
+    for query in ["fox", "needle39", "cafe guide", "nonexistent"] {
+        group.bench_with_input(BenchmarkId::from_parameter(query), &query, |b, query| {
+            b.iter(|| index.search(black_box(query), 50))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_epub_parse,
+    bench_search_index_build,
+    bench_search_query
+);
+criterion_main!(benches);
@@ -0,0 +1,5 @@
+fn main() {
+    // Link flags for loading the addon into Node.js (needed on macOS)
+    #[cfg(feature = "node")]
+    napi_build::setup();
+}
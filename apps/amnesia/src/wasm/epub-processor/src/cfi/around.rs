@@ -0,0 +1,272 @@
+//! The text around a location, for dictionary lookups and quotes
+//!
+//! A dictionary wants the word under the reader's finger and the sentence
+//! it is used in; sharing a quote or previewing a highlight wants the
+//! selection with a little of its paragraph either side. [`text_around`]
+//! gives all of them for a point or range CFI.
+//!
+//! Text is taken from the location's paragraph only, with whitespace runs
+//! collapsed to single spaces (as for text quotes), and context is cut at
+//! word boundaries. Sentences end at `.`, `!`, `?` or `…` (and any closing
+//! quotes or brackets) followed by a space and a word that does not start
+//! in lower case, or at a full-width `。`, `！` or `？`.
+
+use serde::{Deserialize, Serialize};
+
+use super::quote::{text_offset, ChapterText};
+use super::{chapter_xhtml, dom, resolve_cfi_range, spine_item, CfiError};
+use crate::epub::EpubBook;
+
+/// Punctuation that can end a sentence before a space
+const SENTENCE_ENDS: &[char] = &['.', '!', '?', '…', '‽'];
+
+/// Punctuation that ends a sentence without a following space
+const FULL_WIDTH_SENTENCE_ENDS: &[char] = &['。', '！', '？'];
+
+/// Closing quotes and brackets that can follow a sentence's last mark
+const CLOSERS: &[char] = &[
+    '"', '\'', '\u{2019}', '\u{201D}', '\u{00BB}', ')', ']', '」', '』', '）',
+];
+
+/// The text at and around a location in a book
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextAround {
+    /// Spine item of the location
+    pub href: String,
+    /// Text of a range; empty for a point
+    pub text: String,
+    /// For a point, the word it is in (or just after), for dictionary
+    /// lookups
+    pub word: Option<String>,
+    /// Up to `chars_before` characters of the paragraph before the location
+    pub before: String,
+    /// Up to `chars_after` characters of the paragraph after the location
+    pub after: String,
+    /// The sentence or sentences the location is in, within `before` and
+    /// `after`
+    pub sentence: String,
+    /// Whether `before` was cut short of the start of the paragraph
+    pub truncated_before: bool,
+    /// Whether `after` was cut short of the end of the paragraph
+    pub truncated_after: bool,
+}
+
+/// The text around a point or range CFI
+///
+/// `before`, `after` and the sentence stay within the location's paragraph
+/// and within `chars_before` and `chars_after` characters of it; ask for
+/// more characters than a paragraph holds to get the whole of it.
+pub fn text_around(
+    book: &EpubBook,
+    cfi_str: &str,
+    chars_before: usize,
+    chars_after: usize,
+) -> Result<TextAround, CfiError> {
+    let range = resolve_cfi_range(book, cfi_str)?;
+    if range.start.spine_index != range.end.spine_index {
+        return Err(CfiError::InvalidArgument(
+            "Range spans more than one spine item".to_string(),
+        ));
+    }
+    let xhtml = chapter_xhtml(book, spine_item(book, range.start.spine_index)?)?;
+    let start = text_offset(&xhtml, &range.start)?;
+    let end = text_offset(&xhtml, &range.end)?.max(start);
+
+    let text = ChapterText::new(&dom::text_nodes(&xhtml)?);
+    let chars = &text.chars;
+    if chars.is_empty() {
+        return Err(CfiError::ResolutionFailed(
+            "Chapter has no text".to_string(),
+        ));
+    }
+
+    // The paragraph holding the start of the location
+    let blocks = dom::block_ranges(&xhtml)?;
+    let block = blocks
+        .iter()
+        .find(|block| start < block.end)
+        .or(blocks.last())
+        .cloned()
+        .unwrap_or(0..end);
+    let (mut block_from, mut block_to) = (text.index_at(block.start), text.index_after(block.end));
+    while block_from < block_to && chars[block_from] == ' ' {
+        block_from += 1;
+    }
+    while block_to > block_from && chars[block_to - 1] == ' ' {
+        block_to -= 1;
+    }
+
+    let mut from = text.index_at(start).clamp(block_from, block_to);
+    let mut to = text.index_after(end).clamp(from, block_to);
+    while from < to && chars[from] == ' ' {
+        from += 1;
+    }
+    while to > from && chars[to - 1] == ' ' {
+        to -= 1;
+    }
+    let collapsed = start == end;
+    if collapsed {
+        to = from;
+    }
+
+    let window_from = context_start(chars, block_from, from, chars_before);
+    let window_to = context_end(chars, to, block_to, chars_after);
+    let sentence_from = (window_from..=from)
+        .rev()
+        .find(|&i| i == block_from || is_sentence_start(chars, i))
+        .unwrap_or(window_from);
+    let sentence_to = (to.max(from + 1)..=window_to)
+        .find(|&i| i == block_to || is_sentence_start(chars, i))
+        .unwrap_or(window_to);
+
+    Ok(TextAround {
+        href: range.start.href,
+        text: chars[from..to].iter().collect(),
+        word: collapsed
+            .then(|| word_at(chars, block_from, block_to, from))
+            .flatten(),
+        before: chars[window_from..from].iter().collect(),
+        after: chars[to..window_to].iter().collect(),
+        sentence: chars[sentence_from..sentence_to]
+            .iter()
+            .collect::<String>()
+            .trim()
+            .to_string(),
+        truncated_before: window_from > block_from,
+        truncated_after: window_to < block_to,
+    })
+}
+
+/// Start of the context before `at`: `limit` characters back from it,
+/// moved forward to the start of a word
+fn context_start(chars: &[char], block_from: usize, at: usize, limit: usize) -> usize {
+    let mut start = at.saturating_sub(limit).max(block_from);
+    if start > block_from && chars[start - 1] != ' ' {
+        start = chars[start..at]
+            .iter()
+            .position(|&c| c == ' ')
+            .map_or(at, |space| start + space + 1);
+    }
+    start
+}
+
+/// End of the context after `at`: `limit` characters on from it, moved
+/// back to the end of a word
+fn context_end(chars: &[char], at: usize, block_to: usize, limit: usize) -> usize {
+    let mut end = (at + limit).min(block_to);
+    if end < block_to && chars[end] != ' ' {
+        end = chars[at..end]
+            .iter()
+            .rposition(|&c| c == ' ')
+            .map_or(at, |space| at + space);
+    }
+    end
+}
+
+/// Whether a sentence starts at `index`
+fn is_sentence_start(chars: &[char], index: usize) -> bool {
+    let Some(mut mark) = index.checked_sub(1) else {
+        return true;
+    };
+    let after_space = chars[mark] == ' ';
+    if after_space {
+        // A lower-case word after a full stop continues the sentence
+        // ("e.g. this one")
+        if chars.get(index).is_some_and(|c| c.is_lowercase()) {
+            return false;
+        }
+        mark = match mark.checked_sub(1) {
+            Some(mark) => mark,
+            None => return false,
+        };
+    }
+    while mark > 0 && CLOSERS.contains(&chars[mark]) {
+        mark -= 1;
+    }
+    FULL_WIDTH_SENTENCE_ENDS.contains(&chars[mark])
+        || (after_space && SENTENCE_ENDS.contains(&chars[mark]))
+}
+
+/// The word the character at `index` belongs to, or the word ending just
+/// before it
+fn word_at(chars: &[char], block_from: usize, block_to: usize, index: usize) -> Option<String> {
+    let is_word = |c: &char| c.is_alphanumeric() || matches!(c, '\'' | '\u{2019}' | '-');
+    let index = if chars.get(index).is_some_and(|c| c.is_alphanumeric()) {
+        index
+    } else if index > block_from && chars[index - 1].is_alphanumeric() {
+        index - 1
+    } else {
+        return None;
+    };
+    let start = chars[block_from..index]
+        .iter()
+        .rposition(|c| !is_word(c))
+        .map_or(block_from, |i| block_from + i + 1);
+    let end = chars[index..block_to]
+        .iter()
+        .position(|c| !is_word(c))
+        .map_or(block_to, |i| index + i);
+    let word: String = chars[start..end].iter().collect();
+    let word = word.trim_matches(|c: char| !c.is_alphanumeric());
+    Some(word.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epub::tests::build_epub;
+
+    fn book(body: &str) -> EpubBook {
+        let chapter = format!("<html><head><title>t</title></head><body>{body}</body></html>");
+        EpubBook::from_bytes(&build_epub(&[
+            (
+                "META-INF/container.xml",
+                br#"<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container"><rootfiles><rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/></rootfiles></container>"#,
+            ),
+            (
+                "OEBPS/content.opf",
+                br#"<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+<metadata xmlns:dc="http://purl.org/dc/elements/1.1/"><dc:title>Around</dc:title></metadata>
+<manifest><item id="ch1" href="ch1.xhtml" media-type="application/xhtml+xml"/></manifest>
+<spine><itemref idref="ch1"/></spine></package>"#,
+            ),
+            ("OEBPS/ch1.xhtml", chapter.as_bytes()),
+        ]))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_text_around() {
+        let book = book(
+            "<p>Heading.</p>\n<p>Call me Ishmael. Some years ago, e.g. never mind how \
+             long precisely, I <em>went</em> to sea. \u{201C}Why?\u{201D} Nobody asked.</p>",
+        );
+
+        // A point in "years"
+        let around = text_around(&book, "epubcfi(/6/2!/4/4/1:24)", 1000, 1000).unwrap();
+        assert_eq!(around.text, "");
+        assert_eq!(around.word.as_deref(), Some("years"));
+        assert_eq!(around.before, "Call me Ishmael. Some ye");
+        assert_eq!(
+            around.sentence,
+            "Some years ago, e.g. never mind how long precisely, I went to sea."
+        );
+        assert!(around.after.ends_with("Nobody asked."));
+        assert!(!around.truncated_before && !around.truncated_after);
+
+        // "went" selected, with little context either side
+        let around = text_around(&book, "epubcfi(/6/2!/4/4,/2/1:0,/2/1:4)", 13, 9).unwrap();
+        assert_eq!(around.text, "went");
+        assert_eq!(around.word, None);
+        assert_eq!(around.before, "precisely, I ");
+        assert_eq!(around.after, " to sea.");
+        assert_eq!(around.sentence, "precisely, I went to sea.");
+        assert!(around.truncated_before && around.truncated_after);
+
+        // In the closing sentence, after the quoted question
+        let around = text_around(&book, "epubcfi(/6/2!/4/4/3:18)", 1000, 1000).unwrap();
+        assert_eq!(around.word.as_deref(), Some("Nobody"));
+        assert_eq!(around.sentence, "Nobody asked.");
+    }
+}
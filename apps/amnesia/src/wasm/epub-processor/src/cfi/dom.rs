@@ -0,0 +1,843 @@
+//! CFI steps from a chapter's DOM
+//!
+//! The reader describes a position as a [`DomPosition`]: the `childNodes`
+//! indices from the document element down to a node, plus a `Range`-style
+//! offset. Both are cheap to compute in the browser. Walking the same path
+//! through the chapter XHTML gives the content-document part of the CFI:
+//!
+//! - element children are numbered 2, 4, 6, ...
+//! - the text before, between, and after them is numbered 1, 3, 5, ... Text
+//!   nodes between the same two elements form one chunk, and a character
+//!   offset counts from the start of its chunk.
+//! - elements with an `id` get an `[id]` assertion
+//!
+//! Offsets are UTF-16 code units, as in the DOM and epub.js.
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+use cfi_core::{CfiPath, CfiStep};
+use roxmltree::{Document, Node, ParsingOptions};
+use serde::{Deserialize, Serialize};
+
+use super::CfiError;
+
+/// A position in a chapter's DOM
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DomPosition {
+    /// `childNodes` indices from the document element (`<html>`) to the node
+    pub node_path: Vec<usize>,
+    /// Offset into a text node, or child index into an element, as in a DOM
+    /// `Range` boundary
+    pub offset: usize,
+}
+
+/// Content-document steps and character offset for a DOM position
+pub fn content_path(xhtml: &str, position: &DomPosition) -> Result<CfiPath, CfiError> {
+    let source = declare_entities(xhtml);
+    let doc = parse(&source)?;
+
+    let mut path = CfiPath::new();
+    let mut node = doc.root_element();
+    for &index in &position.node_path {
+        node = node.children().nth(index).ok_or_else(|| {
+            CfiError::ResolutionFailed(format!("Node {} not found in the chapter", index))
+        })?;
+        path.push(step(node));
+    }
+
+    if node.is_text() {
+        if position.offset > utf16_len(node) {
+            return Err(CfiError::ResolutionFailed(format!(
+                "Offset {} is past the end of the text node",
+                position.offset
+            )));
+        }
+        path.set_character_offset((chunk_prefix_len(node) + position.offset) as u32);
+    } else if node.is_element() && position.offset > 0 {
+        // A boundary inside an element: before its child at `offset`
+        let children: Vec<Node> = node.children().collect();
+        match children.get(position.offset) {
+            Some(&child) => {
+                path.push(step(child));
+                if child.is_text() {
+                    path.set_character_offset(chunk_prefix_len(child) as u32);
+                }
+            }
+            None if position.offset == children.len() => {
+                // The end of the element: the end of its last text chunk
+                let elements = node.children().filter(Node::is_element).count();
+                let trailing: usize = children
+                    .iter()
+                    .rev()
+                    .take_while(|child| !child.is_element())
+                    .filter(|child| child.is_text())
+                    .map(|&child| utf16_len(child))
+                    .sum();
+                path.push(CfiStep::element((elements * 2 + 1) as u32));
+                path.set_character_offset(trailing as u32);
+            }
+            None => {
+                return Err(CfiError::ResolutionFailed(format!(
+                    "Offset {} is past the end of the element",
+                    position.offset
+                )))
+            }
+        }
+    }
+
+    Ok(path)
+}
+
+/// Content paths every `chars_per_location` characters of a chapter's text
+///
+/// Walks the non-blank text nodes under `<body>` like epub.js locations: the
+/// first path is the start of the text and each one after starts a further
+/// `chars_per_location` UTF-16 units in. Text in different nodes counts
+/// towards the same location.
+pub fn location_paths(xhtml: &str, chars_per_location: usize) -> Result<Vec<CfiPath>, CfiError> {
+    let source = declare_entities(xhtml);
+    let doc = parse(&source)?;
+
+    let mut paths = Vec::new();
+    // Offset of the next location from the start of the current node
+    let mut until_next = 0;
+    for node in body_texts(&doc) {
+        let len = utf16_len(node);
+        let mut offset = until_next;
+        while offset < len {
+            paths.push(text_path(node, offset));
+            offset += chars_per_location;
+        }
+        until_next = offset - len;
+    }
+
+    Ok(paths)
+}
+
+/// Length of a chapter's text, counted like [`location_paths`]
+pub fn text_length(xhtml: &str) -> Result<usize, CfiError> {
+    let source = declare_entities(xhtml);
+    let doc = parse(&source)?;
+    Ok(body_texts(&doc).map(utf16_len).sum())
+}
+
+/// Content path of the element with id `id`, if the chapter has one
+///
+/// This is where a link to `chapter.xhtml#id` lands.
+pub fn element_path(xhtml: &str, id: &str) -> Result<Option<CfiPath>, CfiError> {
+    let source = declare_entities(xhtml);
+    let doc = parse(&source)?;
+    Ok(doc
+        .descendants()
+        .find(|n| n.is_element() && n.attribute("id") == Some(id))
+        .map(node_steps))
+}
+
+/// Text of every text node under `<body>`, in document order, with whether
+/// it counts towards offsets
+///
+/// Blank text nodes (the whitespace between block elements) do not count,
+/// but still separate the words around them.
+pub fn text_nodes(xhtml: &str) -> Result<Vec<(String, bool)>, CfiError> {
+    let source = declare_entities(xhtml);
+    let doc = parse(&source)?;
+    Ok(body(&doc)
+        .descendants()
+        .filter_map(|n| {
+            let text = n.text().filter(|_| n.is_text())?;
+            Some((text.to_string(), !text.trim().is_empty()))
+        })
+        .collect())
+}
+
+/// Elements whose text is a paragraph of its own for [`block_ranges`]
+const BLOCK_ELEMENTS: &[&str] = &[
+    "p",
+    "li",
+    "div",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "blockquote",
+    "pre",
+    "aside",
+    "section",
+    "article",
+    "header",
+    "footer",
+    "figcaption",
+    "dd",
+    "dt",
+    "td",
+    "th",
+    "caption",
+    "body",
+];
+
+/// Offsets of the text of each paragraph (the text nodes with the same
+/// nearest block element) in a chapter, counted like [`location_paths`]
+pub fn block_ranges(xhtml: &str) -> Result<Vec<Range<usize>>, CfiError> {
+    let source = declare_entities(xhtml);
+    let doc = parse(&source)?;
+
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    let mut current = None;
+    let mut offset = 0;
+    for node in body_texts(&doc) {
+        let block = node
+            .ancestors()
+            .find(|n| n.is_element() && BLOCK_ELEMENTS.contains(&n.tag_name().name()))
+            .map(|n| n.id());
+        let len = utf16_len(node);
+        match ranges.last_mut() {
+            Some(range) if block == current => range.end = offset + len,
+            _ => ranges.push(offset..offset + len),
+        }
+        current = block;
+        offset += len;
+    }
+    Ok(ranges)
+}
+
+/// Characters of a chapter's text before a DOM position, counted like
+/// [`location_paths`]
+pub fn text_offset(xhtml: &str, position: &DomPosition) -> Result<usize, CfiError> {
+    let source = declare_entities(xhtml);
+    let doc = parse(&source)?;
+
+    let mut node = doc.root_element();
+    for &index in &position.node_path {
+        node = node.children().nth(index).ok_or_else(|| {
+            CfiError::ResolutionFailed(format!("Node {} not found in the chapter", index))
+        })?;
+    }
+
+    // Node ids follow document order: count the text before the boundary
+    // node and, for a text node, its part before the offset. The end of an
+    // element is just after its last descendant.
+    let (boundary, within) = if node.is_text() {
+        (node.id().get(), position.offset.min(utf16_len(node)))
+    } else {
+        match node.children().nth(position.offset) {
+            Some(child) => (child.id().get(), 0),
+            None => (node.descendants().next_back().unwrap_or(node).id().get() + 1, 0),
+        }
+    };
+
+    let mut offset = 0;
+    for text in body_texts(&doc) {
+        let id = text.id().get();
+        if id >= boundary {
+            if id == boundary {
+                offset += within;
+            }
+            break;
+        }
+        offset += utf16_len(text);
+    }
+    Ok(offset)
+}
+
+/// Content path of the position `offset` characters into a chapter's text,
+/// counted like [`location_paths`]
+///
+/// Offsets past the end clamp to the end of the text. A chapter without
+/// text has the path of its `<body>`.
+pub fn offset_path(xhtml: &str, offset: usize) -> Result<CfiPath, CfiError> {
+    path_at(xhtml, offset, false)
+}
+
+/// Content path of the end of a range `offset` characters into a chapter's
+/// text
+///
+/// Unlike [`offset_path`], an offset between two text nodes stays at the
+/// end of the earlier one, so the range does not reach into the next
+/// paragraph.
+pub fn end_offset_path(xhtml: &str, offset: usize) -> Result<CfiPath, CfiError> {
+    path_at(xhtml, offset, true)
+}
+
+fn path_at(xhtml: &str, offset: usize, end: bool) -> Result<CfiPath, CfiError> {
+    let source = declare_entities(xhtml);
+    let doc = parse(&source)?;
+
+    let mut remaining = offset;
+    let mut last = None;
+    for node in body_texts(&doc) {
+        let len = utf16_len(node);
+        if remaining < len || (end && remaining == len) {
+            return Ok(text_path(node, remaining));
+        }
+        remaining -= len;
+        last = Some((node, len));
+    }
+    Ok(match last {
+        Some((node, len)) => text_path(node, len),
+        None => node_steps(body(&doc)),
+    })
+}
+
+/// Content paths for words of a chapter's search text
+///
+/// Search text is the chapter's words joined by single spaces, with tags
+/// breaking words, followed by any indexed image alt text (see
+/// `search_core::supplementary::extract_with_spans`). Its words are matched
+/// to the words of the DOM's text nodes by position, so entities decoded
+/// differently on each side only shift offsets within a word.
+///
+/// Each target is a word index and a UTF-16 offset into the word (see
+/// [`word_position`]). A word of alt text resolves to its image; words past
+/// the last one, and missing targets, have no path.
+pub fn word_paths(
+    xhtml: &str,
+    targets: &[Option<(usize, usize)>],
+) -> Result<Vec<Option<CfiPath>>, CfiError> {
+    let source = declare_entities(xhtml);
+    let doc = parse(&source)?;
+    let words = dom_words(doc.root_element());
+
+    Ok(targets
+        .iter()
+        .map(|&target| {
+            let (index, offset) = target?;
+            Some(match *words.get(index)? {
+                DomWord::Text { node, start, len } => text_path(node, start + offset.min(len)),
+                DomWord::Image(node) => node_steps(node),
+            })
+        })
+        .collect())
+}
+
+/// Word index and offset into the word of a UTF-16 offset into a text,
+/// given the text's [`utf16_words`]
+///
+/// An offset at the end of a word stays in that word, as the end of a
+/// match should; one past the last word has no position.
+pub fn word_position(words: &[Range<usize>], offset: usize) -> Option<(usize, usize)> {
+    let index = words.partition_point(|word| word.end < offset);
+    let word = words.get(index)?;
+    Some((index, offset.saturating_sub(word.start)))
+}
+
+/// UTF-16 ranges of the whitespace-separated words of a text
+pub fn utf16_words(text: &str) -> Vec<Range<usize>> {
+    let mut words = Vec::new();
+    let mut start = None;
+    let mut position = 0;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if let Some(start) = start.take() {
+                words.push(start..position);
+            }
+        } else if start.is_none() {
+            start = Some(position);
+        }
+        position += c.len_utf16();
+    }
+    if let Some(start) = start {
+        words.push(start..position);
+    }
+    words
+}
+
+/// A word of a chapter's text in the DOM
+#[derive(Clone, Copy)]
+enum DomWord<'a, 'input> {
+    /// UTF-16 range in a text node
+    Text {
+        node: Node<'a, 'input>,
+        start: usize,
+        len: usize,
+    },
+    /// A word of an image's alt text
+    Image(Node<'a, 'input>),
+}
+
+/// Words of the text nodes in document order, outside scripts and styles,
+/// then the words of the images' alt text
+fn dom_words<'a, 'input>(root: Node<'a, 'input>) -> Vec<DomWord<'a, 'input>> {
+    let mut words = Vec::new();
+    for node in root.descendants().filter(Node::is_text) {
+        let hidden = node
+            .parent()
+            .is_some_and(|p| p.has_tag_name("script") || p.has_tag_name("style"));
+        if hidden {
+            continue;
+        }
+        for word in utf16_words(node.text().unwrap_or_default()) {
+            words.push(DomWord::Text {
+                node,
+                start: word.start,
+                len: word.len(),
+            });
+        }
+    }
+
+    for image in root.descendants().filter(|n| n.has_tag_name("img")) {
+        let alt = image.attribute("alt").unwrap_or_default();
+        words.extend(alt.split_whitespace().map(|_| DomWord::Image(image)));
+    }
+    words
+}
+
+/// The `<body>` element, or the document element if there is none
+fn body<'a, 'input>(doc: &'a Document<'input>) -> Node<'a, 'input> {
+    let root = doc.root_element();
+    root.descendants()
+        .find(|n| n.has_tag_name("body"))
+        .unwrap_or(root)
+}
+
+/// Non-blank text nodes under `<body>`, in document order
+fn body_texts<'a, 'input>(doc: &'a Document<'input>) -> impl Iterator<Item = Node<'a, 'input>> {
+    body(doc)
+        .descendants()
+        .filter(|n| n.is_text() && !n.text().unwrap_or_default().trim().is_empty())
+}
+
+/// DOM position of content-document steps and a character offset
+///
+/// An element step whose `[id]` assertion does not match the element at its
+/// index is resolved by the ID instead, so CFIs survive small edits to the
+/// chapter before the target.
+pub fn resolve_path(
+    xhtml: &str,
+    steps: &[CfiStep],
+    offset: Option<u32>,
+) -> Result<DomPosition, CfiError> {
+    let source = declare_entities(xhtml);
+    let doc = parse(&source)?;
+    let not_found = |index: u32| {
+        CfiError::ResolutionFailed(format!("Step /{} not found in the chapter", index))
+    };
+
+    let mut node = doc.root_element();
+    let mut node_path = Vec::new();
+    let mut steps = steps.iter().peekable();
+    while let Some(step) = steps.next() {
+        let index = step.element_index().ok_or_else(|| {
+            CfiError::InvalidFormat("Indirection inside a content document".to_string())
+        })?;
+
+        if index % 2 == 1 {
+            // Text chunk: must be the last step
+            if steps.peek().is_some() {
+                return Err(not_found(index));
+            }
+            return resolve_chunk(node, node_path, (index / 2) as usize, offset)
+                .ok_or_else(|| not_found(index));
+        }
+
+        let by_index = (index as usize / 2)
+            .checked_sub(1)
+            .and_then(|n| node.children().filter(Node::is_element).nth(n));
+        let asserted = step.id_assertion.as_deref();
+        node = match (by_index, asserted) {
+            (Some(element), Some(id)) if element.attribute("id") != Some(id) => {
+                let element = doc
+                    .descendants()
+                    .find(|n| n.attribute("id") == Some(id))
+                    .unwrap_or(element);
+                node_path = node_path_of(element);
+                element
+            }
+            (Some(element), _) => {
+                node_path.push(child_position(element));
+                element
+            }
+            (None, Some(id)) => {
+                let element = doc
+                    .descendants()
+                    .find(|n| n.attribute("id") == Some(id))
+                    .ok_or_else(|| not_found(index))?;
+                node_path = node_path_of(element);
+                element
+            }
+            (None, None) => return Err(not_found(index)),
+        };
+    }
+
+    Ok(DomPosition {
+        node_path,
+        offset: 0,
+    })
+}
+
+/// Find the text node holding `offset` in the chunk after `element_count`
+/// element children of `parent`
+fn resolve_chunk(
+    parent: Node,
+    mut node_path: Vec<usize>,
+    element_count: usize,
+    offset: Option<u32>,
+) -> Option<DomPosition> {
+    let children: Vec<Node> = parent.children().collect();
+    let start = if element_count == 0 {
+        0
+    } else {
+        children
+            .iter()
+            .enumerate()
+            .filter(|(_, child)| child.is_element())
+            .nth(element_count - 1)?
+            .0
+            + 1
+    };
+
+    let mut remaining = offset.unwrap_or(0) as usize;
+    let chunk = children[start..]
+        .iter()
+        .enumerate()
+        .take_while(|(_, child)| !child.is_element());
+    let mut last_text = None;
+    for (i, &child) in chunk {
+        if !child.is_text() {
+            continue;
+        }
+        let len = utf16_len(child);
+        if remaining <= len {
+            node_path.push(start + i);
+            return Some(DomPosition {
+                node_path,
+                offset: remaining,
+            });
+        }
+        remaining -= len;
+        last_text = Some((start + i, len));
+    }
+
+    match last_text {
+        // Offset past the chunk's end: clamp to its end
+        Some((index, len)) => {
+            node_path.push(index);
+            Some(DomPosition {
+                node_path,
+                offset: len,
+            })
+        }
+        // An empty chunk is the boundary before the next element
+        None => Some(DomPosition {
+            node_path,
+            offset: start,
+        }),
+    }
+}
+
+/// CFI step for a node: even for elements, odd for the text around them
+fn step(node: Node) -> CfiStep {
+    let elements_before = node
+        .prev_siblings()
+        .skip(1)
+        .filter(Node::is_element)
+        .count();
+    if node.is_element() {
+        let index = ((elements_before + 1) * 2) as u32;
+        match node.attribute("id") {
+            Some(id) => CfiStep::element_with_id(index, id),
+            None => CfiStep::element(index),
+        }
+    } else {
+        CfiStep::element((elements_before * 2 + 1) as u32)
+    }
+}
+
+/// Steps from the document element to a text node, with a character offset
+/// into the node
+fn text_path(node: Node, offset: usize) -> CfiPath {
+    let mut path = node_steps(node);
+    path.set_character_offset((chunk_prefix_len(node) + offset) as u32);
+    path
+}
+
+/// Steps from the document element to a node
+fn node_steps(node: Node) -> CfiPath {
+    let mut nodes: Vec<Node> = node
+        .ancestors()
+        .take_while(|n| n.parent().is_some_and(|p| !p.is_root()))
+        .collect();
+    nodes.reverse();
+
+    let mut path = CfiPath::new();
+    for node in nodes {
+        path.push(step(node));
+    }
+    path
+}
+
+/// Length of the text before `node` in its chunk
+fn chunk_prefix_len(node: Node) -> usize {
+    node.prev_siblings()
+        .skip(1)
+        .take_while(|sibling| !sibling.is_element())
+        .filter(|sibling| sibling.is_text())
+        .map(utf16_len)
+        .sum()
+}
+
+fn node_path_of(node: Node) -> Vec<usize> {
+    let mut path: Vec<usize> = node
+        .ancestors()
+        .take_while(|n| n.parent().is_some_and(|p| !p.is_root()))
+        .map(child_position)
+        .collect();
+    path.reverse();
+    path
+}
+
+/// Index of a node in its parent's `childNodes`
+fn child_position(node: Node) -> usize {
+    node.prev_siblings().skip(1).count()
+}
+
+fn utf16_len(node: Node) -> usize {
+    node.text().map_or(0, |text| text.encode_utf16().count())
+}
+
+fn parse(xhtml: &str) -> Result<Document<'_>, CfiError> {
+    // EPUB 2 chapters usually carry the XHTML 1.1 doctype
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    Document::parse_with_options(xhtml, options)
+        .map_err(|e| CfiError::ResolutionFailed(format!("Chapter is not well-formed: {}", e)))
+}
+
+/// HTML entities common in EPUB chapters, which XML only knows from the
+/// (unfetched) XHTML DTD
+const HTML_ENTITIES: &[(&str, u32)] = &[
+    ("nbsp", 0xA0),
+    ("shy", 0xAD),
+    ("copy", 0xA9),
+    ("reg", 0xAE),
+    ("deg", 0xB0),
+    ("middot", 0xB7),
+    ("laquo", 0xAB),
+    ("raquo", 0xBB),
+    ("ndash", 0x2013),
+    ("mdash", 0x2014),
+    ("lsquo", 0x2018),
+    ("rsquo", 0x2019),
+    ("ldquo", 0x201C),
+    ("rdquo", 0x201D),
+    ("hellip", 0x2026),
+    ("thinsp", 0x2009),
+    ("zwnj", 0x200C),
+    ("zwj", 0x200D),
+];
+
+/// Replace known HTML entities with character references
+fn declare_entities(xhtml: &str) -> Cow<'_, str> {
+    if !HTML_ENTITIES
+        .iter()
+        .any(|(name, _)| xhtml.contains(&format!("&{};", name)))
+    {
+        return Cow::Borrowed(xhtml);
+    }
+    let mut out = xhtml.to_string();
+    for (name, code) in HTML_ENTITIES {
+        out = out.replace(&format!("&{};", name), &format!("&#{};", code));
+    }
+    Cow::Owned(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHAPTER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.1//EN" "http://www.w3.org/TR/xhtml11/DTD/xhtml11.dtd">
+<html xmlns="http://www.w3.org/1999/xhtml">
+<head><title>One</title></head>
+<body id="body01">
+<p>First paragraph.</p>
+<p id="para02">Call me <em>Ishmael</em>. Some&nbsp;years ago<!-- note --> — never mind 😀 how long.</p>
+</body>
+</html>"#;
+
+    fn position(node_path: &[usize], offset: usize) -> DomPosition {
+        DomPosition {
+            node_path: node_path.to_vec(),
+            offset,
+        }
+    }
+
+    #[test]
+    fn test_content_path() {
+        // html > [text, head, text, body]; body > [text, p, text, p, text]
+        let path = content_path(CHAPTER, &position(&[3, 1, 0], 5)).unwrap();
+        assert_eq!(path.to_string(), "/4[body01]/2/1:5");
+
+        // "years" sits in the chunk after <em>, behind ". Some\u{a0}"
+        let path = content_path(CHAPTER, &position(&[3, 3, 2], 7)).unwrap();
+        assert_eq!(path.to_string(), "/4[body01]/4[para02]/3:7");
+
+        // Text after a comment continues the same chunk; the emoji is two
+        // UTF-16 units
+        let after_comment = content_path(CHAPTER, &position(&[3, 3, 4], 20)).unwrap();
+        assert_eq!(after_comment.to_string(), "/4[body01]/4[para02]/3:36");
+
+        // Range boundaries inside elements
+        let before_em = content_path(CHAPTER, &position(&[3, 3], 1)).unwrap();
+        assert_eq!(before_em.to_string(), "/4[body01]/4[para02]/2");
+        let end = content_path(CHAPTER, &position(&[3, 3], 5)).unwrap();
+        assert_eq!(end.to_string(), "/4[body01]/4[para02]/3:42");
+
+        assert!(content_path(CHAPTER, &position(&[3, 9], 0)).is_err());
+        assert!(content_path(CHAPTER, &position(&[3, 1, 0], 99)).is_err());
+    }
+
+    #[test]
+    fn test_resolve_path_round_trips() {
+        for target in [
+            position(&[3, 1, 0], 5),
+            position(&[3, 3, 2], 7),
+            position(&[3, 3, 4], 20),
+            position(&[3, 3, 1], 0),
+        ] {
+            let path = content_path(CHAPTER, &target).unwrap();
+            let offset = path.character_offset.as_ref().map(|o| o.offset);
+            assert_eq!(
+                resolve_path(CHAPTER, &path.steps, offset).unwrap(),
+                target,
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn test_word_paths() {
+        let text = search_core::extract_plain_text(CHAPTER);
+        let words = utf16_words(&text);
+        let at = |needle: &str| text[..text.find(needle).unwrap()].encode_utf16().count();
+        let targets: Vec<Option<(usize, usize)>> = [
+            at("First"),
+            at("Ishmael"),
+            at("years") + 2,
+            at("long"),
+            // The end of a word
+            at(" Call"),
+        ]
+        .into_iter()
+        .map(|offset| word_position(&words, offset))
+        .collect();
+        assert_eq!(word_position(&words, text.encode_utf16().count() + 1), None);
+
+        let paths: Vec<String> = word_paths(CHAPTER, &targets)
+            .unwrap()
+            .iter()
+            .map(|path| path.as_ref().unwrap().to_string())
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                "/4[body01]/2/1:0",
+                "/4[body01]/4[para02]/2/1:0",
+                "/4[body01]/4[para02]/3:9",
+                "/4[body01]/4[para02]/3:37",
+                "/4[body01]/2/1:16",
+            ]
+        );
+        assert_eq!(
+            word_paths(CHAPTER, &[Some((words.len(), 0))]).unwrap(),
+            vec![None]
+        );
+
+        // Alt text after the body points at its image
+        let html = r#"<html><body><p>Boats <img src="a.png" alt="Fishing nets"/> ashore.</p></body></html>"#;
+        let options = search_core::IndexOptions {
+            alt_text: true,
+            ..Default::default()
+        };
+        let (text, _) = search_core::supplementary::extract_with_spans(html, &options);
+        let nets = word_position(&utf16_words(&text), text.find("nets").unwrap());
+        let paths = word_paths(html, &[nets]).unwrap();
+        assert_eq!(paths[0].as_ref().unwrap().to_string(), "/2/2/2");
+    }
+
+    #[test]
+    fn test_location_paths() {
+        let paths: Vec<String> = location_paths(CHAPTER, 10)
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        // "First paragraph." is 16 units, so the third location starts 4
+        // units into the next paragraph; locations carry on through <em>
+        assert_eq!(
+            paths,
+            vec![
+                "/4[body01]/2/1:0",
+                "/4[body01]/2/1:10",
+                "/4[body01]/4[para02]/1:4",
+                "/4[body01]/4[para02]/2/1:6",
+                "/4[body01]/4[para02]/3:9",
+                "/4[body01]/4[para02]/3:19",
+                "/4[body01]/4[para02]/3:29",
+                "/4[body01]/4[para02]/3:39",
+            ]
+        );
+
+        // Every location resolves back into the chapter
+        for path in location_paths(CHAPTER, 10).unwrap() {
+            let offset = path.character_offset.as_ref().map(|o| o.offset);
+            assert!(resolve_path(CHAPTER, &path.steps, offset).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_text_offsets() {
+        // 16 units in the first paragraph, 8 + 7 + 16 + 26 in the second
+        assert_eq!(text_length(CHAPTER).unwrap(), 73);
+
+        let offset = |node_path: &[usize], offset| {
+            text_offset(CHAPTER, &position(node_path, offset)).unwrap()
+        };
+        assert_eq!(offset(&[3], 0), 0);
+        assert_eq!(offset(&[3, 1, 0], 5), 5);
+        assert_eq!(offset(&[3, 3, 2], 7), 38);
+        assert_eq!(offset(&[3, 3, 4], 20), 67);
+        assert_eq!(offset(&[3, 3], 1), 24);
+        assert_eq!(offset(&[3, 3], 5), 73);
+
+        let path = |offset| offset_path(CHAPTER, offset).unwrap().to_string();
+        assert_eq!(path(38), "/4[body01]/4[para02]/3:7");
+        assert_eq!(path(24), "/4[body01]/4[para02]/2/1:0");
+        assert_eq!(path(999), "/4[body01]/4[para02]/3:42");
+        assert_eq!(
+            offset_path("<html><body><img/></body></html>", 5)
+                .unwrap()
+                .to_string(),
+            "/2"
+        );
+
+        // Offsets survive the trip through a CFI and back
+        for target in 0..=73 {
+            let path = offset_path(CHAPTER, target).unwrap();
+            let offset = path.character_offset.as_ref().map(|o| o.offset);
+            let position = resolve_path(CHAPTER, &path.steps, offset).unwrap();
+            assert_eq!(text_offset(CHAPTER, &position).unwrap(), target, "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_resolve_path_follows_id_assertions() {
+        // An index that no longer matches, but an ID that still does
+        let cfi = cfi_core::parse("epubcfi(/6/4!/4[body01]/2[para02]/1:4)").unwrap();
+        let steps: Vec<CfiStep> = cfi
+            .path
+            .steps
+            .into_iter()
+            .skip_while(|step| !step.is_indirection())
+            .skip(1)
+            .collect();
+        let resolved = resolve_path(CHAPTER, &steps, Some(4)).unwrap();
+        assert_eq!(resolved, position(&[3, 3, 0], 4));
+    }
+}
@@ -8,11 +8,56 @@
 //! - ! - Step indirection (into content document)
 //! - /4/2/1 - Element path within document
 //! - :5 - Character offset within text node
-
+//!
+//! A time into an `<audio>` or `<video>` element is a temporal offset in
+//! seconds, as in epubcfi(/6/4!/4/2/6~12.5); a clip of it is a range whose
+//! ends differ only in time. Read-aloud positions and audiobook bookmarks
+//! use these, so they sort and sync like any other CFI.
+//!
+//! A point in an image is a spatial offset in percentages of its width and
+//! height, as in epubcfi(/6/4!/4/2/4@25:60); a region of an illustration is
+//! a range from its top-left to its bottom-right corner.
+//!
+//! Range CFIs, used to persist selections, share the steps common to both
+//! ends and then give each end's remaining path:
+//! epubcfi(/6/4!/4/2,/1:5,/3:12) runs from /4/2/1:5 to /4/2/3:12.
+//!
+//! Parsing, generation, and ordering are delegated to the shared `cfi-core`
+//! crate (also used by the server); this module maps its structured CFIs onto
+//! the book's spine and the flattened shapes exposed to JavaScript. Steps
+//! within a content document come from walking the chapter XHTML ([`dom`]).
+//! Positions that must survive publisher updates to the chapter can also be
+//! kept as quotes of their text ([`quote`]), the text around a location is
+//! available for dictionary lookups and previews ([`around`]), and ToC
+//! entries are placed in the spine for finding the current chapter
+//! ([`toc`]).
+
+#[cfg(feature = "media-overlays")]
+use cfi_core::TemporalOffset;
+use cfi_core::{CfiBuilder, CfiPath, CfiRange, SpatialOffset};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::epub::EpubBook;
+#[cfg(feature = "search")]
+use epub_core::chunk::{chunk_href, parse_chunk_href};
+#[cfg(feature = "search")]
+use search_core::supplementary::extract_with_spans;
+#[cfg(feature = "search")]
+use search_core::{extract_plain_text, IndexOptions};
+
+use crate::epub::{EpubBook, SpineItem};
+#[cfg(feature = "search")]
+use crate::search::SearchResult;
+
+pub mod around;
+pub mod dom;
+pub mod quote;
+pub mod toc;
+
+pub use around::{text_around, TextAround};
+pub use dom::DomPosition;
+pub use quote::{cfi_to_text_quote, text_quote_to_cfi, TextQuoteAnchor};
+pub use toc::{current_toc_entry, flatten_toc, FlatTocEntry};
 
 #[derive(Error, Debug)]
 pub enum CfiError {
@@ -24,8 +69,14 @@ pub enum CfiError {
 
     #[error("Spine item not found: {0}")]
     SpineNotFound(String),
+
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
 }
 
+/// Characters per location when none is given, as in epub.js
+pub const DEFAULT_CHARS_PER_LOCATION: usize = 150;
+
 /// Parsed CFI structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -38,6 +89,12 @@ pub struct Cfi {
     pub path: Vec<usize>,
     /// Character offset (if any)
     pub offset: Option<usize>,
+    /// Temporal offset in seconds (if any)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seconds: Option<f64>,
+    /// Spatial offset in percentages (if any)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub point: Option<SpatialOffset>,
 }
 
 /// Location resolved from a CFI
@@ -52,155 +109,614 @@ pub struct CfiLocation {
     pub element_path: String,
     /// Character offset within text node
     pub offset: Option<usize>,
+    /// Seconds into the media element, for a temporal CFI
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seconds: Option<f64>,
+    /// Point in the image, for a spatial CFI
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub point: Option<SpatialOffset>,
+    /// The location in the chapter's DOM, if the chapter could be walked
+    pub position: Option<DomPosition>,
+}
+
+/// Locations of both ends of a range CFI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CfiRangeLocation {
+    pub start: CfiLocation,
+    pub end: CfiLocation,
 }
 
-/// Generate a CFI for a specific location in the book
+/// Generate a CFI for a position in a chapter's DOM
 ///
-/// # Arguments
-/// * `book` - The EPUB book
-/// * `spine_index` - Index in the spine
-/// * `path` - DOM path (e.g., "/html/body/p[2]")
-/// * `offset` - Character offset within the element
+/// The position is resolved against the spine item's XHTML, so the CFI has
+/// the element/text step numbering and ID assertions other readers expect.
 pub fn generate_cfi(
     book: &EpubBook,
     spine_index: usize,
-    path: &str,
-    offset: usize,
+    position: &DomPosition,
 ) -> Result<String, CfiError> {
-    // Validate spine index
-    if spine_index >= book.spine.len() {
-        return Err(CfiError::SpineNotFound(format!(
-            "Spine index {} out of range (max: {})",
-            spine_index,
-            book.spine.len() - 1
+    let spine_item = spine_item(book, spine_index)?;
+    let content = dom::content_path(&chapter_xhtml(book, spine_item)?, position)?;
+
+    let mut cfi = spine_item_cfi(spine_index, spine_item);
+    cfi.path.steps.extend(content.steps);
+    cfi.path.character_offset = content.character_offset;
+
+    Ok(cfi.to_string())
+}
+
+/// Generate a range CFI for a selection in a chapter's DOM
+///
+/// The ends may be given in either order (a selection made backwards has
+/// its anchor after its focus); the CFI always runs forwards.
+pub fn generate_cfi_range(
+    book: &EpubBook,
+    spine_index: usize,
+    start: &DomPosition,
+    end: &DomPosition,
+) -> Result<String, CfiError> {
+    let spine_item = spine_item(book, spine_index)?;
+    let xhtml = chapter_xhtml(book, spine_item)?;
+    let start = dom::content_path(&xhtml, start)?;
+    let end = dom::content_path(&xhtml, end)?;
+
+    let mut cfi = spine_item_cfi(spine_index, spine_item);
+    let (common, range) = split_range(start, end);
+    cfi.path.steps.extend(common);
+    Ok(cfi_core::Cfi::with_range(cfi.path, range).to_string())
+}
+
+/// Generate a CFI for a time in a media element, or for a clip of it
+///
+/// `node_path` leads to the `<audio>` or `<video>` element (see
+/// [`DomPosition`]); `start` and `end` are seconds into its media.
+#[cfg(feature = "media-overlays")]
+pub fn generate_temporal_cfi(
+    book: &EpubBook,
+    spine_index: usize,
+    node_path: &[usize],
+    start: f64,
+    end: Option<f64>,
+) -> Result<String, CfiError> {
+    let valid = |seconds: f64| seconds.is_finite() && seconds >= 0.0;
+    if !valid(start) || end.is_some_and(|end| !valid(end) || end < start) {
+        return Err(CfiError::InvalidArgument(format!(
+            "Invalid time range {}..{:?}",
+            start, end
         )));
     }
 
-    // Calculate spine step (EPUB uses 1-based, even-numbered indices for content documents)
-    // /6 is the spine element in the package document
-    // /N where N = (spine_index + 1) * 2 is the specific spine item
-    let spine_step = (spine_index + 1) * 2;
+    let at = |seconds: f64| CfiPath {
+        temporal_offset: Some(TemporalOffset { seconds }),
+        ..CfiPath::new()
+    };
+    element_offset_cfi(
+        book,
+        spine_index,
+        node_path,
+        "Time offsets need a media element",
+        at(start),
+        end.map(at),
+    )
+}
+
+/// Generate a CFI for a point in an image, or for a region of it
+///
+/// `node_path` leads to the `<img>` (or SVG `<image>`) element; `point` and
+/// `corner` are `(x, y)` percentages of its width and height. They may be
+/// any two opposite corners of the region; the CFI always runs from its
+/// top-left to its bottom-right.
+pub fn generate_spatial_cfi(
+    book: &EpubBook,
+    spine_index: usize,
+    node_path: &[usize],
+    point: (f64, f64),
+    corner: Option<(f64, f64)>,
+) -> Result<String, CfiError> {
+    let valid = |(x, y): (f64, f64)| (0.0..=100.0).contains(&x) && (0.0..=100.0).contains(&y);
+    if !valid(point) || corner.is_some_and(|corner| !valid(corner)) {
+        return Err(CfiError::InvalidArgument(format!(
+            "Invalid image region {:?}..{:?}",
+            point, corner
+        )));
+    }
+    let (point, corner) = match corner {
+        Some((x, y)) => (
+            (point.0.min(x), point.1.min(y)),
+            Some((point.0.max(x), point.1.max(y))),
+        ),
+        None => (point, None),
+    };
 
-    // Convert DOM path to CFI path
-    let cfi_path = path_to_cfi_path(path);
+    let at = |(x, y): (f64, f64)| CfiPath {
+        spatial_offset: Some(SpatialOffset { x, y }),
+        ..CfiPath::new()
+    };
+    element_offset_cfi(
+        book,
+        spine_index,
+        node_path,
+        "Spatial offsets need an image",
+        at(point),
+        corner.map(at),
+    )
+}
 
-    // Build the CFI string
-    let cfi = if offset > 0 {
-        format!("epubcfi(/6/{}!{}:{})", spine_step, cfi_path, offset)
-    } else {
-        format!("epubcfi(/6/{}!{})", spine_step, cfi_path)
+/// CFI for offsets into the element at `node_path`, a range when there is
+/// an `end`; `start` and `end` carry only the offsets
+fn element_offset_cfi(
+    book: &EpubBook,
+    spine_index: usize,
+    node_path: &[usize],
+    element: &str,
+    start: CfiPath,
+    end: Option<CfiPath>,
+) -> Result<String, CfiError> {
+    let spine_item = spine_item(book, spine_index)?;
+    let position = DomPosition {
+        node_path: node_path.to_vec(),
+        offset: 0,
     };
+    let content = dom::content_path(&chapter_xhtml(book, spine_item)?, &position)?;
+    if content.character_offset.is_some() {
+        return Err(CfiError::InvalidArgument(format!(
+            "{}, not a text node",
+            element
+        )));
+    }
 
-    Ok(cfi)
+    let mut cfi = spine_item_cfi(spine_index, spine_item);
+    let at = |offsets: CfiPath| CfiPath {
+        steps: content.steps.clone(),
+        ..offsets
+    };
+    let Some(end) = end else {
+        cfi.path = CfiPath {
+            steps: [cfi.path.steps, content.steps.clone()].concat(),
+            ..start
+        };
+        return Ok(cfi.to_string());
+    };
+    let (common, range) = split_range(at(start), at(end));
+    cfi.path.steps.extend(common);
+    Ok(cfi_core::Cfi::with_range(cfi.path, range).to_string())
 }
 
-/// Resolve a CFI to a location in the book
-pub fn resolve_cfi(book: &EpubBook, cfi_str: &str) -> Result<CfiLocation, CfiError> {
-    let cfi = parse_cfi(cfi_str)?;
+/// CFIs every `chars_per_location` characters through the linear spine
+///
+/// Like epub.js locations, each spine item starts a new location. The
+/// position of a CFI among them gives progress and page numbers that agree
+/// across devices, whatever each one's screen size. Chapters that cannot be
+/// parsed contribute no locations.
+pub fn generate_locations(
+    book: &EpubBook,
+    chars_per_location: usize,
+) -> Result<Vec<String>, CfiError> {
+    if chars_per_location == 0 {
+        return Err(CfiError::InvalidArgument(
+            "charsPerLocation must be positive".to_string(),
+        ));
+    }
 
-    // Get spine item
-    let spine_item = book.get_spine_item(cfi.spine_index)
-        .ok_or_else(|| CfiError::SpineNotFound(format!(
-            "Spine index {} not found",
-            cfi.spine_index
-        )))?;
+    let mut locations = Vec::new();
+    for (spine_index, spine_item) in book.spine.iter().enumerate() {
+        if !spine_item.linear {
+            continue;
+        }
+        let paths = chapter_xhtml(book, spine_item)
+            .and_then(|xhtml| dom::location_paths(&xhtml, chars_per_location));
+        let paths = match paths {
+            Ok(paths) => paths,
+            Err(e) => {
+                crate::console_log(&format!(
+                    "[CFI] No locations for '{}': {}",
+                    spine_item.href, e
+                ));
+                continue;
+            }
+        };
+
+        for content in paths {
+            let mut cfi = spine_item_cfi(spine_index, spine_item);
+            cfi.path.steps.extend(content.steps);
+            cfi.path.character_offset = content.character_offset;
+            locations.push(cfi.to_string());
+        }
+    }
 
-    // Convert CFI path back to XPath-like path
-    let element_path = cfi_path_to_xpath(&cfi.path);
+    Ok(locations)
+}
 
-    Ok(CfiLocation {
-        href: spine_item.href.clone(),
-        spine_index: cfi.spine_index,
-        element_path,
-        offset: cfi.offset,
-    })
+/// Characters of text in each spine item, for [`cfi_to_percentage`] and
+/// [`percentage_to_cfi`]
+///
+/// Text is counted like locations. Non-linear items and chapters that
+/// cannot be parsed have none. A book without any text (a comic, say)
+/// counts each linear item as one character, so progression still moves
+/// through its spine.
+pub fn spine_text_lengths(book: &EpubBook) -> Vec<usize> {
+    let mut lengths: Vec<usize> = book
+        .spine
+        .iter()
+        .map(|spine_item| {
+            if !spine_item.linear {
+                return 0;
+            }
+            match chapter_xhtml(book, spine_item).and_then(|xhtml| dom::text_length(&xhtml)) {
+                Ok(length) => length,
+                Err(e) => {
+                    crate::console_log(&format!(
+                        "[CFI] No text length for '{}': {}",
+                        spine_item.href, e
+                    ));
+                    0
+                }
+            }
+        })
+        .collect();
+    if lengths.iter().all(|&length| length == 0) {
+        for (length, spine_item) in lengths.iter_mut().zip(&book.spine) {
+            *length = usize::from(spine_item.linear);
+        }
+    }
+    lengths
 }
 
-/// Parse a CFI string into a Cfi struct
-pub fn parse_cfi(cfi_str: &str) -> Result<Cfi, CfiError> {
-    // Remove the epubcfi() wrapper
-    let inner = cfi_str
-        .strip_prefix("epubcfi(")
-        .and_then(|s| s.strip_suffix(")"))
-        .ok_or_else(|| CfiError::InvalidFormat("Missing epubcfi() wrapper".to_string()))?;
-
-    // Split by the step indirection (!)
-    let parts: Vec<&str> = inner.split('!').collect();
-    if parts.is_empty() {
-        return Err(CfiError::InvalidFormat("Empty CFI".to_string()));
+/// Progression through the book (0.0-1.0) of a CFI's position
+///
+/// `lengths` are the book's [`spine_text_lengths`]. The fraction is the
+/// text before the position over the book's text, so it matches the
+/// server's Progression selector and epub.js percentages. A range CFI gives
+/// its start; a position in a non-linear item, the progression where the
+/// item sits in the spine.
+pub fn cfi_to_percentage(
+    book: &EpubBook,
+    lengths: &[usize],
+    cfi_str: &str,
+) -> Result<f64, CfiError> {
+    let location = resolve_cfi(book, cfi_str)?;
+    let total: usize = lengths.iter().sum();
+    if total == 0 {
+        return Ok(0.0);
     }
 
-    // Parse the package document path to get spine index
-    let spine_index = parse_spine_index(parts[0])?;
+    let before: usize = lengths[..location.spine_index].iter().sum();
+    let length = lengths[location.spine_index];
+    // Best effort: a position the chapter no longer has counts as its start
+    let within = match &location.position {
+        Some(position) if length > 0 => {
+            let xhtml = chapter_xhtml(book, &book.spine[location.spine_index])?;
+            dom::text_offset(&xhtml, position).unwrap_or(0).min(length)
+        }
+        _ => 0,
+    };
+    Ok((before + within) as f64 / total as f64)
+}
 
-    // Parse the content document path (if present)
-    let (path, offset) = if parts.len() > 1 {
-        parse_content_path(parts[1])?
-    } else {
-        (Vec::new(), None)
+/// CFI of the position a fraction (0.0-1.0) of the way through the book
+///
+/// The inverse of [`cfi_to_percentage`]: the CFI points at the character
+/// that far into the book's text, for a scrubber or a position synced from
+/// the server's Progression selector.
+pub fn percentage_to_cfi(
+    book: &EpubBook,
+    lengths: &[usize],
+    fraction: f64,
+) -> Result<String, CfiError> {
+    if !(0.0..=1.0).contains(&fraction) {
+        return Err(CfiError::InvalidArgument(format!(
+            "Progression {} is not between 0 and 1",
+            fraction
+        )));
+    }
+    let total: usize = lengths.iter().sum();
+    let Some(last) = lengths.iter().rposition(|&length| length > 0) else {
+        return Err(CfiError::SpineNotFound(
+            "The book has no linear spine items".to_string(),
+        ));
     };
 
-    Ok(Cfi {
-        raw: cfi_str.to_string(),
-        spine_index,
-        path,
-        offset,
-    })
+    let mut remaining = (fraction * total as f64).round() as usize;
+    let mut spine_index = last;
+    for (index, &length) in lengths.iter().enumerate() {
+        if remaining < length {
+            spine_index = index;
+            break;
+        }
+        if index == last {
+            break;
+        }
+        remaining -= length;
+    }
+
+    let spine_item = spine_item(book, spine_index)?;
+    let content = dom::offset_path(&chapter_xhtml(book, spine_item)?, remaining)?;
+    let mut cfi = spine_item_cfi(spine_index, spine_item);
+    cfi.path.steps.extend(content.steps);
+    cfi.path.character_offset = content.character_offset;
+    Ok(cfi.to_string())
 }
 
-/// Parse spine index from package document path
-fn parse_spine_index(path: &str) -> Result<usize, CfiError> {
-    // Path format: /6/N where N is (spine_index + 1) * 2
-    let steps: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+/// CFI of a spine item's content document, ending at the indirection
+fn spine_item_cfi(spine_index: usize, spine_item: &SpineItem) -> cfi_core::Cfi {
+    // /6 is the spine element in the package document and /N with
+    // N = (spine_index + 1) * 2 is the specific spine item
+    CfiBuilder::new()
+        .package_step()
+        .spine_item_with_id(spine_index, spine_item.id.as_str())
+        .indirection()
+        .build()
+}
 
-    if steps.len() < 2 {
-        return Err(CfiError::InvalidFormat("Invalid package path".to_string()));
+/// Point search results at the text they matched
+///
+/// A search index only knows chapters' text, so its results carry their
+/// chapter's CFI. With the book at hand, each match's offsets are mapped
+/// back through the chapter DOM to a range CFI over the matched text (or
+/// the CFI of the image, for alt text), for scrolling to and highlighting
+/// it. Results in chapters that cannot be parsed keep their CFI.
+#[cfg(feature = "search")]
+pub fn locate_search_results<'a>(
+    book: &EpubBook,
+    results: impl IntoIterator<Item = &'a mut SearchResult>,
+) {
+    let mut results: Vec<&mut SearchResult> = results.into_iter().collect();
+    // Each chapter (or chunk) is parsed once
+    results.sort_by(|a, b| a.href.cmp(&b.href));
+    for group in results.chunk_by_mut(|a, b| a.href == b.href) {
+        let ranges: Vec<(usize, usize)> = group
+            .iter()
+            .map(|result| (result.text_start, result.text_end))
+            .collect();
+        let cfis = match match_cfis(book, group[0].spine_index, &group[0].href, &ranges) {
+            Ok(cfis) => cfis,
+            Err(e) => {
+                crate::console_log(&format!(
+                    "[CFI] Search results in '{}' keep their chapter CFI: {}",
+                    group[0].href, e
+                ));
+                continue;
+            }
+        };
+        for (result, cfi) in group.iter_mut().zip(cfis) {
+            if let Some(cfi) = cfi {
+                result.cfi = cfi;
+            }
+        }
     }
+}
 
-    // First step should be 6 (spine element)
-    if steps[0] != "6" {
-        return Err(CfiError::InvalidFormat("Expected /6 for spine".to_string()));
-    }
+/// CFIs of UTF-16 ranges of a chapter's (or chunk's) search text
+#[cfg(feature = "search")]
+fn match_cfis(
+    book: &EpubBook,
+    spine_index: usize,
+    href: &str,
+    ranges: &[(usize, usize)],
+) -> Result<Vec<Option<String>>, CfiError> {
+    let spine_item = spine_item(book, spine_index)?;
+    let xhtml = chapter_xhtml(book, spine_item)?;
+    // Alt text is only in the text if the index asked for it, but it
+    // always comes after the body, so body offsets are the same either way
+    let alt_text = IndexOptions {
+        alt_text: true,
+        ..IndexOptions::default()
+    };
+    let words = |html: &str| dom::utf16_words(&extract_with_spans(html, &alt_text).0);
+    let (words, chunk) = match parse_chunk_href(href) {
+        Some((parent, index)) => (
+            words(&content_html(book, href)?),
+            Some(ChunkWords::new(book, &xhtml, parent, index)?),
+        ),
+        None => (words(&xhtml), None),
+    };
 
-    // Second step is the spine item index
-    let spine_step: usize = steps[1].parse()
-        .map_err(|_| CfiError::InvalidFormat("Invalid spine step".to_string()))?;
+    let targets: Vec<Option<(usize, usize)>> = ranges
+        .iter()
+        .flat_map(|&(start, end)| [start, end])
+        .map(|offset| {
+            let (word, offset) = dom::word_position(&words, offset)?;
+            match &chunk {
+                Some(chunk) => Some((chunk.to_chapter(word)?, offset)),
+                None => Some((word, offset)),
+            }
+        })
+        .collect();
+    let paths = dom::word_paths(&xhtml, &targets)?;
+
+    Ok(paths
+        .chunks(2)
+        .map(|ends| {
+            let start = ends[0].clone()?;
+            let mut cfi = spine_item_cfi(spine_index, spine_item);
+            match ends[1].clone() {
+                Some(end) if end != start => {
+                    let (common, range) = split_range(start, end);
+                    cfi.path.steps.extend(common);
+                    Some(cfi_core::Cfi::with_range(cfi.path, range).to_string())
+                }
+                _ => {
+                    cfi.path.steps.extend(start.steps);
+                    cfi.path.character_offset = start.character_offset;
+                    Some(cfi.to_string())
+                }
+            }
+        })
+        .collect())
+}
+
+/// Where the words of a chunk's search text sit in its chapter's
+///
+/// Every chunk repeats the chapter's `<head>` (and so its title), followed
+/// by its share of the body. The chapter lists the alt text of all chunks
+/// after its body, so the chunk's own alt text has no place in it.
+#[cfg(feature = "search")]
+struct ChunkWords {
+    /// Words in the `<head>`
+    head: usize,
+    /// Body words in earlier chunks
+    before: usize,
+    /// Words in the chunk before its alt text
+    end: usize,
+}
+
+#[cfg(feature = "search")]
+impl ChunkWords {
+    fn new(book: &EpubBook, xhtml: &str, parent: &str, index: usize) -> Result<Self, CfiError> {
+        let count = |html: &str| dom::utf16_words(&extract_plain_text(html)).len();
+        let head = xhtml.find("<body").map_or(0, |body| count(&xhtml[..body]));
+
+        let mut before = 0;
+        for earlier in 0..index {
+            before +=
+                count(&content_html(book, &chunk_href(parent, earlier))?).saturating_sub(head);
+        }
+        let end = count(&content_html(book, &chunk_href(parent, index))?);
+        Ok(Self { head, before, end })
+    }
 
-    // Convert back to 0-based index
-    Ok((spine_step / 2) - 1)
+    /// Index in the chapter's search text of a word of the chunk's
+    fn to_chapter(&self, word: usize) -> Option<usize> {
+        match word {
+            word if word < self.head => Some(word),
+            word if word < self.end => Some(word + self.before),
+            _ => None,
+        }
+    }
 }
 
-/// Parse content document path
-fn parse_content_path(path: &str) -> Result<(Vec<usize>, Option<usize>), CfiError> {
-    // Check for character offset
-    let (path_part, offset) = if let Some(colon_idx) = path.rfind(':') {
-        let offset_str = &path[colon_idx + 1..];
-        let offset: usize = offset_str.parse()
-            .map_err(|_| CfiError::InvalidFormat("Invalid character offset".to_string()))?;
-        (&path[..colon_idx], Some(offset))
+/// Split two content paths into their common steps and a forward range
+///
+/// Each end keeps at least its last step, so a range within one text node
+/// still reads `,/1:5,/1:12`.
+fn split_range(start: CfiPath, end: CfiPath) -> (Vec<cfi_core::CfiStep>, CfiRange) {
+    let (mut start, mut end) = if end < start {
+        (end, start)
     } else {
-        (path, None)
+        (start, end)
     };
+    let common = start
+        .steps
+        .iter()
+        .zip(&end.steps)
+        .take_while(|(a, b)| a == b)
+        .count()
+        .min(start.steps.len().min(end.steps.len()).saturating_sub(1));
+
+    let steps = start.steps.drain(..common).collect();
+    end.steps.drain(..common);
+    (steps, CfiRange { start, end })
+}
 
-    // Parse the element path
-    let steps: Vec<usize> = path_part
-        .split('/')
-        .filter(|s| !s.is_empty())
-        .map(|s| {
-            // Handle assertions like [id="foo"] by stripping them
-            let base = s.split('[').next().unwrap_or(s);
-            base.parse().unwrap_or(0)
-        })
+/// Resolve a CFI to a location in the book
+///
+/// A range CFI resolves to its start.
+pub fn resolve_cfi(book: &EpubBook, cfi_str: &str) -> Result<CfiLocation, CfiError> {
+    let cfi = parse_cfi(cfi_str)?;
+    let spine_item = spine_item(book, cfi.spine_index)?;
+
+    // Convert CFI path back to XPath-like path
+    let element_path = cfi_path_to_xpath(&cfi.path);
+
+    // Best effort: the CFI may come from an older edition of the chapter
+    let parsed = cfi_core::parse(cfi_str)
+        .map_err(|e| CfiError::InvalidFormat(e.to_string()))?
+        .start();
+    let steps: Vec<_> = parsed
+        .path
+        .steps
+        .into_iter()
+        .skip_while(|step| !step.is_indirection())
+        .skip(1)
         .collect();
+    let position = chapter_xhtml(book, spine_item).and_then(|xhtml| {
+        dom::resolve_path(&xhtml, &steps, cfi.offset.map(|offset| offset as u32))
+    });
+
+    Ok(CfiLocation {
+        href: spine_item.href.clone(),
+        spine_index: cfi.spine_index,
+        element_path,
+        offset: cfi.offset,
+        seconds: cfi.seconds,
+        point: cfi.point,
+        position: position.ok(),
+    })
+}
+
+/// Resolve both ends of a range CFI
+///
+/// A CFI that is not a range resolves as a collapsed range, with both ends
+/// at the same location.
+pub fn resolve_cfi_range(book: &EpubBook, cfi_str: &str) -> Result<CfiRangeLocation, CfiError> {
+    let parsed = cfi_core::parse(cfi_str).map_err(|e| CfiError::InvalidFormat(e.to_string()))?;
+    Ok(CfiRangeLocation {
+        start: resolve_cfi(book, &parsed.start().to_string())?,
+        end: resolve_cfi(book, &parsed.end().to_string())?,
+    })
+}
 
-    Ok((steps, offset))
+fn spine_item(book: &EpubBook, spine_index: usize) -> Result<&SpineItem, CfiError> {
+    book.get_spine_item(spine_index).ok_or_else(|| {
+        CfiError::SpineNotFound(format!(
+            "Spine index {} out of range ({} items)",
+            spine_index,
+            book.spine.len()
+        ))
+    })
+}
+
+/// The whole spine item, even when the reader shows it in chunks
+fn chapter_xhtml(book: &EpubBook, spine_item: &SpineItem) -> Result<String, CfiError> {
+    content_html(book, &spine_item.href)
 }
 
-/// Convert a DOM path to CFI path notation
-fn path_to_cfi_path(path: &str) -> String {
-    // This is a simplified conversion
-    // Full implementation would need actual DOM traversal
-    path.replace("/", "/")
+/// A spine item or chunk as stored
+fn content_html(book: &EpubBook, href: &str) -> Result<String, CfiError> {
+    book.get_chapter_content(href)
+        .map(|chapter| chapter.html)
+        .map_err(|e| CfiError::ResolutionFailed(e.to_string()))
+}
+
+/// Parse a CFI string into a Cfi struct
+///
+/// A range CFI is parsed as its start.
+pub fn parse_cfi(cfi_str: &str) -> Result<Cfi, CfiError> {
+    let parsed = cfi_core::parse(cfi_str)
+        .map_err(|e| CfiError::InvalidFormat(e.to_string()))?
+        .start();
+
+    // The package document path must be /6/N (spine element, spine item)
+    let spine_index = parsed
+        .spine_index()
+        .ok_or_else(|| CfiError::InvalidFormat("Expected /6/N spine reference".to_string()))?
+        as usize;
+
+    // Element steps after the first indirection address the content document
+    let path = parsed
+        .path
+        .steps
+        .iter()
+        .skip_while(|step| !step.is_indirection())
+        .filter_map(|step| step.element_index())
+        .map(|index| index as usize)
+        .collect();
+
+    let offset = parsed
+        .path
+        .character_offset
+        .as_ref()
+        .map(|offset| offset.offset as usize);
+
+    Ok(Cfi {
+        raw: cfi_str.to_string(),
+        spine_index,
+        path,
+        offset,
+        seconds: parsed.temporal_offset(),
+        point: parsed.spatial_offset().map(|(x, y)| SpatialOffset { x, y }),
+    })
 }
 
 /// Convert CFI path steps to XPath-like notation
@@ -216,34 +732,15 @@ fn cfi_path_to_xpath(steps: &[usize]) -> String {
 }
 
 /// Compare two CFIs to determine their order
+///
+/// Range CFIs order by their start, then by their end. The spine is in
+/// reading order whichever way a book's pages turn, so in a right-to-left
+/// book too, later pages compare greater.
 pub fn compare_cfis(cfi_a: &str, cfi_b: &str) -> Result<std::cmp::Ordering, CfiError> {
-    let a = parse_cfi(cfi_a)?;
-    let b = parse_cfi(cfi_b)?;
-
-    // First compare spine index
-    if a.spine_index != b.spine_index {
-        return Ok(a.spine_index.cmp(&b.spine_index));
-    }
-
-    // Then compare path depth and values
-    for (step_a, step_b) in a.path.iter().zip(b.path.iter()) {
-        if step_a != step_b {
-            return Ok(step_a.cmp(step_b));
-        }
-    }
-
-    // If one path is longer, it comes after
-    if a.path.len() != b.path.len() {
-        return Ok(a.path.len().cmp(&b.path.len()));
-    }
+    let a = cfi_core::parse(cfi_a).map_err(|e| CfiError::InvalidFormat(e.to_string()))?;
+    let b = cfi_core::parse(cfi_b).map_err(|e| CfiError::InvalidFormat(e.to_string()))?;
 
-    // Finally compare character offsets
-    match (a.offset, b.offset) {
-        (Some(off_a), Some(off_b)) => Ok(off_a.cmp(&off_b)),
-        (Some(_), None) => Ok(std::cmp::Ordering::Greater),
-        (None, Some(_)) => Ok(std::cmp::Ordering::Less),
-        (None, None) => Ok(std::cmp::Ordering::Equal),
-    }
+    Ok(a.cmp(&b))
 }
 
 #[cfg(test)]
@@ -265,6 +762,97 @@ mod tests {
         assert_eq!(cfi.offset, Some(10));
     }
 
+    #[test]
+    fn test_parse_cfi_with_assertions() {
+        let cfi = parse_cfi("epubcfi(/6/4[chap01]!/4[body01]/2/1:3[foo,bar])").unwrap();
+        assert_eq!(cfi.spine_index, 1);
+        assert_eq!(cfi.path, vec![4, 2, 1]);
+        assert_eq!(cfi.offset, Some(3));
+    }
+
+    #[test]
+    fn test_parse_cfi_rejects_non_spine_path() {
+        assert!(parse_cfi("epubcfi(/4/4!/4/2)").is_err());
+    }
+
+    #[test]
+    fn test_parse_range_cfi_as_start() {
+        let cfi = parse_cfi("epubcfi(/6/4!/4/2,/1:5,/3:12)").unwrap();
+        assert_eq!(cfi.spine_index, 1);
+        assert_eq!(cfi.path, vec![4, 2, 1]);
+        assert_eq!(cfi.offset, Some(5));
+    }
+
+    #[test]
+    fn test_parse_temporal_cfi() {
+        let cfi = parse_cfi("epubcfi(/6/4!/4/2/6~12.5)").unwrap();
+        assert_eq!(cfi.path, vec![4, 2, 6]);
+        assert_eq!(cfi.seconds, Some(12.5));
+        assert_eq!(cfi.offset, None);
+
+        let clip = parse_cfi("epubcfi(/6/4!/4/2,/6~10,/6~15)").unwrap();
+        assert_eq!(clip.seconds, Some(10.0));
+        assert!(parse_cfi("epubcfi(/6/4!/4/2/6~-3)").is_err());
+
+        assert_eq!(
+            compare_cfis("epubcfi(/6/4!/4/2/6~9)", "epubcfi(/6/4!/4/2/6~10)").unwrap(),
+            std::cmp::Ordering::Less
+        );
+    }
+
+    #[test]
+    fn test_parse_spatial_cfi() {
+        let cfi = parse_cfi("epubcfi(/6/4!/4/2/4@25:60)").unwrap();
+        assert_eq!(cfi.path, vec![4, 2, 4]);
+        assert_eq!(cfi.point, Some(SpatialOffset { x: 25.0, y: 60.0 }));
+        assert_eq!(cfi.seconds, None);
+
+        let region = parse_cfi("epubcfi(/6/4!/4/2,/4@10:20,/4@60:80)").unwrap();
+        assert_eq!(region.point, Some(SpatialOffset { x: 10.0, y: 20.0 }));
+        assert!(parse_cfi("epubcfi(/6/4!/4/2/4@25:160)").is_err());
+    }
+
+    #[test]
+    fn test_split_range() {
+        let content = |cfi: &str| {
+            let cfi = cfi_core::parse(cfi).unwrap();
+            CfiPath {
+                steps: cfi.path.steps[3..].to_vec(),
+                ..cfi.path
+            }
+        };
+        let range_cfi = |start: &str, end: &str| {
+            let mut cfi = cfi_core::parse("epubcfi(/6/4!)").unwrap();
+            let (common, range) = split_range(content(start), content(end));
+            cfi.path.steps.extend(common);
+            cfi_core::Cfi::with_range(cfi.path, range).to_string()
+        };
+
+        assert_eq!(
+            range_cfi("epubcfi(/6/4!/4/2/1:5)", "epubcfi(/6/4!/4/2/3:12)"),
+            "epubcfi(/6/4!/4/2,/1:5,/3:12)"
+        );
+        // Backwards selections are turned around
+        assert_eq!(
+            range_cfi("epubcfi(/6/4!/4/6/1:2)", "epubcfi(/6/4!/4/2/1:5)"),
+            "epubcfi(/6/4!/4,/2/1:5,/6/1:2)"
+        );
+        // Both ends in one text node keep the text step
+        assert_eq!(
+            range_cfi("epubcfi(/6/4!/4/2/1:5)", "epubcfi(/6/4!/4/2/1:12)"),
+            "epubcfi(/6/4!/4/2,/1:5,/1:12)"
+        );
+
+        assert_eq!(
+            compare_cfis(
+                "epubcfi(/6/4!/4/2,/1:5,/1:12)",
+                "epubcfi(/6/4!/4/2,/1:5,/3:1)"
+            )
+            .unwrap(),
+            std::cmp::Ordering::Less
+        );
+    }
+
     #[test]
     fn test_compare_cfis() {
         assert_eq!(
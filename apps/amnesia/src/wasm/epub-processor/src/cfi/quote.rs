@@ -0,0 +1,389 @@
+//! Text-quote anchors for positions that outlive their CFIs
+//!
+//! A CFI counts elements and characters, so it breaks when a publisher
+//! updates the book and the chapter's markup shifts. A text quote (the
+//! quoted text plus a little context either side, as in the W3C Web
+//! Annotation `TextQuoteSelector`) can be found again in the new text and
+//! turned back into a fresh CFI.
+//!
+//! Quotes are taken from the chapter's text with whitespace runs collapsed
+//! to single spaces, so reflowed or re-indented markup still matches.
+
+use serde::{Deserialize, Serialize};
+
+use search_core::selection::CONTEXT_CHARS;
+
+use super::{chapter_xhtml, dom, resolve_cfi_range, spine_item, spine_item_cfi, split_range};
+use super::{CfiError, CfiLocation};
+use crate::epub::EpubBook;
+
+/// Characters quoted after a point position, before running on to the end
+/// of the word
+const POINT_QUOTE_CHARS: usize = 32;
+
+/// A position or range in a book, as the text at it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextQuoteAnchor {
+    /// Spine item the quote was taken from; searched first when resolving
+    pub href: String,
+    /// The quoted text: the range's, or the text starting at a point
+    pub exact: String,
+    /// Text just before the quote
+    #[serde(default)]
+    pub prefix: String,
+    /// Text just after the quote
+    #[serde(default)]
+    pub suffix: String,
+    /// Whether the anchor is a point at the start of `exact` rather than a
+    /// range over it
+    #[serde(default)]
+    pub collapsed: bool,
+}
+
+/// Quote the text at a CFI
+///
+/// A range CFI quotes its text; a point CFI quotes the words that follow
+/// it (or, at the very end of a chapter, the words before it, so the
+/// anchor resolves to their start).
+pub fn cfi_to_text_quote(book: &EpubBook, cfi_str: &str) -> Result<TextQuoteAnchor, CfiError> {
+    let range = resolve_cfi_range(book, cfi_str)?;
+    if range.start.spine_index != range.end.spine_index {
+        return Err(CfiError::InvalidArgument(
+            "Range spans more than one spine item".to_string(),
+        ));
+    }
+    let xhtml = chapter_xhtml(book, spine_item(book, range.start.spine_index)?)?;
+    let start = text_offset(&xhtml, &range.start)?;
+    let end = text_offset(&xhtml, &range.end)?.max(start);
+
+    let text = ChapterText::new(&dom::text_nodes(&xhtml)?);
+    let chars = &text.chars;
+    if chars.is_empty() {
+        return Err(CfiError::ResolutionFailed(
+            "Chapter has no text to quote".to_string(),
+        ));
+    }
+
+    let collapsed = start == end;
+    let (mut from, mut to) = if collapsed {
+        match text.index_at(start) {
+            at if at < chars.len() => (
+                at,
+                word_end(chars, (at + POINT_QUOTE_CHARS).min(chars.len())),
+            ),
+            end => (
+                word_start(chars, end.saturating_sub(POINT_QUOTE_CHARS)),
+                end,
+            ),
+        }
+    } else {
+        (text.index_at(start), text.index_after(end))
+    };
+    while from < to && chars[from] == ' ' {
+        from += 1;
+    }
+    while to > from && chars[to - 1] == ' ' {
+        to -= 1;
+    }
+    if from == to {
+        return Err(CfiError::ResolutionFailed(
+            "CFI selects no text".to_string(),
+        ));
+    }
+
+    Ok(TextQuoteAnchor {
+        href: range.start.href,
+        exact: chars[from..to].iter().collect(),
+        prefix: chars[from.saturating_sub(CONTEXT_CHARS)..from]
+            .iter()
+            .collect(),
+        suffix: chars[to..(to + CONTEXT_CHARS).min(chars.len())]
+            .iter()
+            .collect(),
+        collapsed,
+    })
+}
+
+/// Find a quote in the book and return a CFI for it
+///
+/// The quote's own spine item is searched first, and the rest of the book
+/// only when the quote is not found there. Of several occurrences, the one
+/// whose surroundings best match the prefix and suffix wins. Returns `None`
+/// when the text is nowhere in the book.
+pub fn text_quote_to_cfi(
+    book: &EpubBook,
+    anchor: &TextQuoteAnchor,
+) -> Result<Option<String>, CfiError> {
+    let exact = collapse_whitespace(anchor.exact.trim());
+    if exact.is_empty() {
+        return Err(CfiError::InvalidArgument("Quote has no text".to_string()));
+    }
+    let prefix = collapse_whitespace(&anchor.prefix);
+    let suffix = collapse_whitespace(&anchor.suffix);
+
+    let hinted = book.spine.iter().position(|item| item.href == anchor.href);
+    let others = (0..book.spine.len()).filter(|&index| Some(index) != hinted);
+
+    let mut best: Option<(usize, usize, String, ChapterText, usize)> = None;
+    for spine_index in hinted.into_iter().chain(others) {
+        let Ok(xhtml) = chapter_xhtml(book, spine_item(book, spine_index)?) else {
+            continue;
+        };
+        let Ok(nodes) = dom::text_nodes(&xhtml) else {
+            continue;
+        };
+        let text = ChapterText::new(&nodes);
+        let Some((at, score)) = text.best_match(&exact, &prefix, &suffix) else {
+            continue;
+        };
+        if best.as_ref().is_none_or(|best| score > best.1) {
+            best = Some((spine_index, score, xhtml, text, at));
+        }
+        if Some(spine_index) == hinted {
+            break;
+        }
+    }
+
+    let Some((spine_index, _, xhtml, text, at)) = best else {
+        return Ok(None);
+    };
+    let start = dom::offset_path(&xhtml, text.offsets[at])?;
+    let mut cfi = spine_item_cfi(spine_index, spine_item(book, spine_index)?);
+    if anchor.collapsed {
+        cfi.path.steps.extend(start.steps);
+        cfi.path.character_offset = start.character_offset;
+        return Ok(Some(cfi.to_string()));
+    }
+    let end = dom::end_offset_path(&xhtml, text.ends[at + exact.len() - 1])?;
+    let (common, range) = split_range(start, end);
+    cfi.path.steps.extend(common);
+    Ok(Some(cfi_core::Cfi::with_range(cfi.path, range).to_string()))
+}
+
+/// Offset of a resolved location in its chapter's text
+pub(super) fn text_offset(xhtml: &str, location: &CfiLocation) -> Result<usize, CfiError> {
+    let position = location.position.as_ref().ok_or_else(|| {
+        CfiError::ResolutionFailed(format!(
+            "{} does not resolve in the current chapter",
+            location.element_path
+        ))
+    })?;
+    dom::text_offset(xhtml, position)
+}
+
+/// A chapter's text with whitespace collapsed, mapped back to the UTF-16
+/// offsets of [`dom::text_offset`]
+pub(super) struct ChapterText {
+    pub chars: Vec<char>,
+    /// Offset of each character; a collapsed space sits at the next word
+    pub offsets: Vec<usize>,
+    /// Offset just after each character
+    pub ends: Vec<usize>,
+}
+
+impl ChapterText {
+    pub fn new(nodes: &[(String, bool)]) -> Self {
+        let mut text = Self {
+            chars: Vec::new(),
+            offsets: Vec::new(),
+            ends: Vec::new(),
+        };
+        let mut offset = 0;
+        let mut space = false;
+        for (node, counted) in nodes {
+            if !counted {
+                space = true;
+                continue;
+            }
+            for c in node.chars() {
+                if c.is_whitespace() {
+                    space = true;
+                } else {
+                    if space && !text.chars.is_empty() {
+                        text.push(' ', offset, offset);
+                    }
+                    space = false;
+                    text.push(c, offset, offset + c.len_utf16());
+                }
+                offset += c.len_utf16();
+            }
+        }
+        text
+    }
+
+    fn push(&mut self, c: char, offset: usize, end: usize) {
+        self.chars.push(c);
+        self.offsets.push(offset);
+        self.ends.push(end);
+    }
+
+    /// First character at or after `offset`
+    pub fn index_at(&self, offset: usize) -> usize {
+        self.offsets.partition_point(|&o| o < offset)
+    }
+
+    /// End of the last character that ends by `offset`
+    pub fn index_after(&self, offset: usize) -> usize {
+        self.ends.partition_point(|&e| e <= offset)
+    }
+
+    /// Start of the occurrence of `exact` with the most context matching
+    /// `prefix` and `suffix`, and how many context characters matched
+    fn best_match(
+        &self,
+        exact: &[char],
+        prefix: &[char],
+        suffix: &[char],
+    ) -> Option<(usize, usize)> {
+        let mut best: Option<(usize, usize)> = None;
+        for (at, window) in self.chars.windows(exact.len()).enumerate() {
+            if window != exact {
+                continue;
+            }
+            let before = self.chars[..at]
+                .iter()
+                .rev()
+                .zip(prefix.iter().rev())
+                .take_while(|(a, b)| a == b)
+                .count();
+            let after = self.chars[at + exact.len()..]
+                .iter()
+                .zip(suffix)
+                .take_while(|(a, b)| a == b)
+                .count();
+            if best.is_none_or(|(_, score)| before + after > score) {
+                best = Some((at, before + after));
+            }
+        }
+        best
+    }
+}
+
+/// Runs of whitespace as single spaces
+fn collapse_whitespace(text: &str) -> Vec<char> {
+    let mut chars: Vec<char> = Vec::with_capacity(text.len());
+    for c in text.chars() {
+        if !c.is_whitespace() {
+            chars.push(c);
+        } else if chars.last() != Some(&' ') {
+            chars.push(' ');
+        }
+    }
+    chars
+}
+
+/// The end of the word running through `index`
+fn word_end(chars: &[char], index: usize) -> usize {
+    let limit = (index + POINT_QUOTE_CHARS).min(chars.len());
+    chars[index..limit]
+        .iter()
+        .position(|&c| c == ' ')
+        .map_or(limit, |space| index + space)
+}
+
+/// The start of the word running through `index`
+fn word_start(chars: &[char], index: usize) -> usize {
+    chars[..index]
+        .iter()
+        .rposition(|&c| c == ' ')
+        .map_or(0, |space| space + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epub::tests::build_epub;
+
+    /// A book with chapters `ch1.xhtml`, `ch2.xhtml`, ... holding `bodies`
+    fn book(bodies: &[&str]) -> EpubBook {
+        let manifest: String = (1..=bodies.len())
+            .map(|n| {
+                format!(
+                    r#"<item id="ch{n}" href="ch{n}.xhtml" media-type="application/xhtml+xml"/>"#
+                )
+            })
+            .collect();
+        let spine: String = (1..=bodies.len())
+            .map(|n| format!(r#"<itemref idref="ch{n}"/>"#))
+            .collect();
+        let opf = format!(
+            r#"<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+<metadata xmlns:dc="http://purl.org/dc/elements/1.1/"><dc:title>Quotes</dc:title></metadata>
+<manifest>{manifest}</manifest><spine>{spine}</spine></package>"#
+        );
+        let chapters: Vec<(String, String)> = bodies
+            .iter()
+            .enumerate()
+            .map(|(i, body)| {
+                (
+                    format!("OEBPS/ch{}.xhtml", i + 1),
+                    format!("<html><head><title>t</title></head><body>{body}</body></html>"),
+                )
+            })
+            .collect();
+
+        let mut files: Vec<(&str, &[u8])> = vec![
+            (
+                "META-INF/container.xml",
+                br#"<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container"><rootfiles><rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/></rootfiles></container>"#,
+            ),
+            ("OEBPS/content.opf", opf.as_bytes()),
+        ];
+        files.extend(
+            chapters
+                .iter()
+                .map(|(name, html)| (name.as_str(), html.as_bytes())),
+        );
+        EpubBook::from_bytes(&build_epub(&files)).unwrap()
+    }
+
+    #[test]
+    fn test_quote_survives_edits() {
+        let first = book(&["<p>The quick brown fox.</p>\n<p>The   quick <em>brown</em> cat.</p>"]);
+        // "quick brown" in the second paragraph
+        let quote = cfi_to_text_quote(&first, "epubcfi(/6/2!/4/4,/1:6,/2/1:5)").unwrap();
+        assert_eq!(quote.exact, "quick brown");
+        assert_eq!(quote.prefix, "The quick brown fox. The ");
+        assert_eq!(quote.suffix, " cat.");
+        assert!(!quote.collapsed);
+
+        // A paragraph added before it, and the markup reflowed
+        let second = book(&[
+            "<p>Preface.</p><p>The quick brown fox.</p>\n<p>The quick\n<em>brown</em> cat.</p>",
+        ]);
+        let cfi = text_quote_to_cfi(&second, &quote).unwrap().unwrap();
+        assert_eq!(cfi, "epubcfi(/6/2[ch1]!/4/6,/1:4,/2/1:5)");
+        assert_eq!(
+            cfi_to_text_quote(&second, &cfi).unwrap().exact,
+            "quick brown"
+        );
+
+        // The chapter moved behind another
+        let third = book(&["<p>Foreword.</p>", "<p>The quick brown cat.</p>"]);
+        let cfi = text_quote_to_cfi(&third, &quote).unwrap().unwrap();
+        assert_eq!(cfi, "epubcfi(/6/4[ch2]!/4/2,/1:4,/1:15)");
+
+        let gone = book(&["<p>Nothing here.</p>"]);
+        assert_eq!(text_quote_to_cfi(&gone, &quote).unwrap(), None);
+    }
+
+    #[test]
+    fn test_point_quote() {
+        let book =
+            book(&["<p>Call me Ishmael. Some years ago, never mind how long precisely.</p>"]);
+        let quote = cfi_to_text_quote(&book, "epubcfi(/6/2!/4/2/1:8)").unwrap();
+        assert!(quote.collapsed);
+        assert_eq!(quote.exact, "Ishmael. Some years ago, never mind");
+        assert_eq!(quote.prefix, "Call me ");
+        assert_eq!(
+            text_quote_to_cfi(&book, &quote).unwrap().as_deref(),
+            Some("epubcfi(/6/2[ch1]!/4/2/1:8)")
+        );
+
+        // At the end of the chapter, the last words are quoted
+        let end = cfi_to_text_quote(&book, "epubcfi(/6/2!/4/2/1:65)").unwrap();
+        assert_eq!(end.exact, "ago, never mind how long precisely.");
+        assert_eq!(end.suffix, "");
+    }
+}
@@ -0,0 +1,212 @@
+//! The table of contents as a flat list of positions
+//!
+//! ToC entries form a tree whose hrefs are relative to the ToC document and
+//! may point at an element inside a chapter. [`flatten_toc`] lists them in
+//! document order with the spine item each one lands in and the CFI where it
+//! starts: the element with the href's fragment id, or the start of the
+//! spine item. With those, the entry being read is the one starting last at
+//! or before the reading position ([`current_toc_entry`]), and the reader
+//! never has to walk hrefs and fragments itself.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{chapter_xhtml, dom, spine_item_cfi, CfiError};
+use crate::epub::{EpubBook, TocEntry, TocPaths};
+
+/// A ToC entry placed in the spine
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlatTocEntry {
+    pub id: String,
+    /// Href as given in the ToC
+    pub href: String,
+    pub label: String,
+    /// Depth in the ToC, as in [`TocEntry::level`]
+    pub level: usize,
+    /// Index of the parent entry in the flat list
+    pub parent: Option<usize>,
+    /// Spine item the entry points into; `None` for hrefs outside the spine
+    pub spine_index: Option<usize>,
+    /// Where the entry starts: the element with the href's fragment id, or
+    /// the start of the spine item if the href has no fragment or the
+    /// chapter has no such element
+    pub cfi: Option<String>,
+}
+
+/// The ToC in document order, with the spine index and start CFI of each
+/// entry
+pub fn flatten_toc(book: &EpubBook) -> Vec<FlatTocEntry> {
+    let mut flattener = Flattener {
+        book,
+        paths: book.toc_paths(),
+        spine_paths: book
+            .spine
+            .iter()
+            .map(|item| book.resolve_path(&item.href))
+            .collect(),
+        chapters: HashMap::new(),
+        entries: Vec::new(),
+    };
+    flattener.add(&book.toc, None);
+    flattener.entries
+}
+
+/// Index of the entry being read at `cfi`: the one whose start is last at or
+/// before it
+///
+/// Entries that start at the same place resolve to the later, deeper one. A
+/// range is placed by its start. `None` before the first entry.
+pub fn current_toc_entry(entries: &[FlatTocEntry], cfi: &str) -> Result<Option<usize>, CfiError> {
+    let parse = |cfi: &str| {
+        cfi_core::parse(cfi)
+            .map(|cfi| cfi.start())
+            .map_err(|e| CfiError::InvalidFormat(e.to_string()))
+    };
+    let position = parse(cfi)?;
+
+    let mut current: Option<(usize, cfi_core::Cfi)> = None;
+    for (index, entry) in entries.iter().enumerate() {
+        let Some(start) = entry.cfi.as_deref() else {
+            continue;
+        };
+        let start = parse(start)?;
+        if start <= position && current.as_ref().is_none_or(|(_, best)| start >= *best) {
+            current = Some((index, start));
+        }
+    }
+    Ok(current.map(|(index, _)| index))
+}
+
+struct Flattener<'a> {
+    book: &'a EpubBook,
+    paths: TocPaths<'a>,
+    /// Archive path of each spine item
+    spine_paths: Vec<String>,
+    /// Chapter XHTML by spine index, `None` if it cannot be read
+    chapters: HashMap<usize, Option<String>>,
+    entries: Vec<FlatTocEntry>,
+}
+
+impl Flattener<'_> {
+    fn add(&mut self, entries: &[TocEntry], parent: Option<usize>) {
+        for entry in entries {
+            let path = self.paths.resolve(&entry.href);
+            let spine_index = self.spine_paths.iter().position(|p| *p == path);
+            let cfi = spine_index.map(|spine_index| self.start_cfi(spine_index, &entry.href));
+
+            let index = self.entries.len();
+            self.entries.push(FlatTocEntry {
+                id: entry.id.clone(),
+                href: entry.href.clone(),
+                label: entry.label.clone(),
+                level: entry.level,
+                parent,
+                spine_index,
+                cfi,
+            });
+            self.add(&entry.children, Some(index));
+        }
+    }
+
+    fn start_cfi(&mut self, spine_index: usize, href: &str) -> String {
+        let book = self.book;
+        let item = &book.spine[spine_index];
+        let mut cfi = spine_item_cfi(spine_index, item);
+
+        let fragment = href
+            .split_once('#')
+            .map(|(_, id)| id)
+            .filter(|id| !id.is_empty());
+        if let Some(id) = fragment {
+            let xhtml = self
+                .chapters
+                .entry(spine_index)
+                .or_insert_with(|| chapter_xhtml(book, item).ok());
+            if let Some(path) = xhtml
+                .as_deref()
+                .and_then(|xhtml| dom::element_path(xhtml, id).ok().flatten())
+            {
+                cfi.path.steps.extend(path.steps);
+            }
+        }
+        cfi.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epub::tests::build_epub;
+
+    #[test]
+    fn test_flatten_toc() {
+        let book = EpubBook::from_bytes(&build_epub(&[
+            (
+                "META-INF/container.xml",
+                br#"<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container"><rootfiles><rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/></rootfiles></container>"#,
+            ),
+            (
+                "OEBPS/content.opf",
+                br#"<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+<metadata xmlns:dc="http://purl.org/dc/elements/1.1/"><dc:title>Toc</dc:title></metadata>
+<manifest>
+<item id="nav" href="nav/toc.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+<item id="ch1" href="text/ch1.xhtml" media-type="application/xhtml+xml"/>
+<item id="ch2" href="text/ch2.xhtml" media-type="application/xhtml+xml"/>
+</manifest>
+<spine><itemref idref="ch1"/><itemref idref="ch2"/></spine></package>"#,
+            ),
+            (
+                "OEBPS/nav/toc.xhtml",
+                br#"<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops"><body>
+<nav epub:type="toc"><ol>
+<li><a href="../text/ch1.xhtml">One</a><ol>
+<li><a href="../text/ch1.xhtml#s2">One, part two</a></li>
+<li><a href="../text/ch1.xhtml#missing">Gone</a></li>
+</ol></li>
+<li><a href="../text/ch2.xhtml">Two</a></li>
+<li><a href="http://example.com/">Elsewhere</a></li>
+</ol></nav></body></html>"#,
+            ),
+            (
+                "OEBPS/text/ch1.xhtml",
+                br#"<html><head><title>1</title></head><body><p>Start</p><h2 id="s2">Part two</h2><p>More</p></body></html>"#,
+            ),
+            (
+                "OEBPS/text/ch2.xhtml",
+                br#"<html><head><title>2</title></head><body><p>Two</p></body></html>"#,
+            ),
+        ]))
+        .unwrap();
+
+        let entries = flatten_toc(&book);
+        let summary: Vec<_> = entries
+            .iter()
+            .map(|e| (e.label.as_str(), e.parent, e.spine_index, e.cfi.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("One", None, Some(0), Some("epubcfi(/6/2[ch1]!)")),
+                (
+                    "One, part two",
+                    Some(0),
+                    Some(0),
+                    Some("epubcfi(/6/2[ch1]!/4/4[s2])")
+                ),
+                ("Gone", Some(0), Some(0), Some("epubcfi(/6/2[ch1]!)")),
+                ("Two", None, Some(1), Some("epubcfi(/6/4[ch2]!)")),
+                ("Elsewhere", None, None, None),
+            ]
+        );
+
+        let current = |cfi| current_toc_entry(&entries, cfi).unwrap();
+        // "Gone" starts where "One" does, and comes later
+        assert_eq!(current("epubcfi(/6/2!/4/2/1:3)"), Some(2));
+        assert_eq!(current("epubcfi(/6/2!/4/6/1:0)"), Some(1));
+        assert_eq!(current("epubcfi(/6/4!/4/2,/1:0,/1:2)"), Some(3));
+        assert_eq!(current("epubcfi(/4/2!)"), None);
+    }
+}
@@ -0,0 +1,167 @@
+//! DRM detection
+//!
+//! Protected books keep their content documents encrypted, so parsing them
+//! fails somewhere in the XML or the chapter text with an error that says
+//! nothing about why. The container's `META-INF` files name the scheme:
+//!
+//! - Readium LCP: `license.lcpl`, or an `encryption.xml` whose keys are
+//!   retrieved from it (the license may be delivered separately)
+//! - Adobe ADEPT: `rights.xml`, or keys in the `http://ns.adobe.com/adept`
+//!   namespace
+//! - Apple FairPlay: `sinf.xml`
+//!
+//! Any other encrypted resource that is not an obfuscated font also makes
+//! the book unreadable, under an unknown scheme.
+
+use std::fmt;
+use std::io::{Read, Seek};
+
+use serde::{Deserialize, Serialize};
+use zip::ZipArchive;
+
+use super::fonts::{ENCRYPTION_PATH, OBFUSCATION_ALGORITHMS};
+
+const LCP_LICENSE_PATH: &str = "META-INF/license.lcpl";
+const ADEPT_RIGHTS_PATH: &str = "META-INF/rights.xml";
+const FAIRPLAY_SINF_PATH: &str = "META-INF/sinf.xml";
+
+const ADEPT_NAMESPACE: &str = "http://ns.adobe.com/adept";
+
+/// A DRM scheme protecting a book
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DrmScheme {
+    Lcp,
+    AdobeAdept,
+    AppleFairplay,
+    /// Encrypted content under a scheme that is not recognized
+    Unknown,
+}
+
+impl DrmScheme {
+    /// Stable name, as serialized
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DrmScheme::Lcp => "lcp",
+            DrmScheme::AdobeAdept => "adobe-adept",
+            DrmScheme::AppleFairplay => "apple-fairplay",
+            DrmScheme::Unknown => "unknown",
+        }
+    }
+}
+
+impl fmt::Display for DrmScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The DRM scheme protecting an archive, if any
+///
+/// Only reads the directory and `META-INF/encryption.xml`, so it is cheap
+/// enough to run before anything else is parsed.
+pub(super) fn detect<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Option<DrmScheme> {
+    let has = |archive: &mut ZipArchive<R>, path: &str| archive.by_name(path).is_ok();
+    if has(archive, LCP_LICENSE_PATH) {
+        return Some(DrmScheme::Lcp);
+    }
+    if has(archive, ADEPT_RIGHTS_PATH) {
+        return Some(DrmScheme::AdobeAdept);
+    }
+    if has(archive, FAIRPLAY_SINF_PATH) {
+        return Some(DrmScheme::AppleFairplay);
+    }
+
+    let mut encryption = String::new();
+    archive
+        .by_name(ENCRYPTION_PATH)
+        .ok()?
+        .read_to_string(&mut encryption)
+        .ok()?;
+    classify_encryption(&encryption)
+}
+
+/// The scheme behind `encryption.xml`; `None` when it only lists obfuscated
+/// fonts
+fn classify_encryption(encryption_xml: &str) -> Option<DrmScheme> {
+    // Malformed files are left to font de-obfuscation, which ignores them
+    let doc = roxmltree::Document::parse(encryption_xml).ok()?;
+
+    let encrypted = doc
+        .descendants()
+        .filter(|node| node.tag_name().name() == "EncryptedData")
+        .any(|data| {
+            let algorithm = data
+                .descendants()
+                .find(|node| node.tag_name().name() == "EncryptionMethod")
+                .and_then(|node| node.attribute("Algorithm"));
+            !algorithm.is_some_and(|uri| OBFUSCATION_ALGORITHMS.contains(&uri))
+        });
+    if !encrypted {
+        return None;
+    }
+
+    let lcp = doc.descendants().any(|node| {
+        node.tag_name().name() == "RetrievalMethod"
+            && node
+                .attribute("URI")
+                .is_some_and(|uri| uri.starts_with("license.lcpl"))
+    });
+    let adept = doc
+        .descendants()
+        .any(|node| node.tag_name().namespace() == Some(ADEPT_NAMESPACE));
+
+    Some(if lcp {
+        DrmScheme::Lcp
+    } else if adept {
+        DrmScheme::AdobeAdept
+    } else {
+        DrmScheme::Unknown
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encryption_xml(algorithm: &str, key_info: &str) -> String {
+        format!(
+            r#"<encryption xmlns="urn:oasis:names:tc:opendocument:xmlns:container"
+    xmlns:enc="http://www.w3.org/2001/04/xmlenc#" xmlns:ds="http://www.w3.org/2000/09/xmldsig#">
+  <enc:EncryptedData>
+    <enc:EncryptionMethod Algorithm="{}"/>
+    <ds:KeyInfo>{}</ds:KeyInfo>
+    <enc:CipherData><enc:CipherReference URI="OEBPS/ch1.xhtml"/></enc:CipherData>
+  </enc:EncryptedData>
+</encryption>"#,
+            algorithm, key_info
+        )
+    }
+
+    #[test]
+    fn test_classify_encryption() {
+        const AES: &str = "http://www.w3.org/2001/04/xmlenc#aes256-cbc";
+
+        let lcp = encryption_xml(
+            AES,
+            r#"<ds:RetrievalMethod URI="license.lcpl#/encryption/content_key"/>"#,
+        );
+        assert_eq!(classify_encryption(&lcp), Some(DrmScheme::Lcp));
+
+        let adept = encryption_xml(
+            AES,
+            r#"<resource xmlns="http://ns.adobe.com/adept">urn:uuid:1</resource>"#,
+        );
+        assert_eq!(classify_encryption(&adept), Some(DrmScheme::AdobeAdept));
+
+        let unknown = encryption_xml(AES, "");
+        assert_eq!(classify_encryption(&unknown), Some(DrmScheme::Unknown));
+
+        // Obfuscated fonts are not DRM
+        for algorithm in OBFUSCATION_ALGORITHMS {
+            assert_eq!(classify_encryption(&encryption_xml(algorithm, "")), None);
+        }
+        assert_eq!(classify_encryption("not xml"), None);
+        assert_eq!(DrmScheme::AdobeAdept.to_string(), "adobe-adept");
+    }
+}
@@ -0,0 +1,377 @@
+//! Whole-book HTML export
+//!
+//! [`EpubBook::export_html`] merges the reading order into one
+//! self-contained HTML document, for printing or for annotating a book in
+//! tools that cannot open EPUBs. Each spine item becomes a `<section>`
+//! with an id, in spine order; its ids are prefixed with the section's id,
+//! and links between spine items become links within the document.
+//! Stylesheets are inlined once each, and images, fonts and other linked
+//! files are inlined as data URIs up to a size cap. Scripts are removed.
+//!
+//! Chapter markup is copied as written, so XHTML-only constructs (such as
+//! `<div/>`) reach the HTML parser as they would with `getChapter`.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+use base64::Engine;
+use epub_core::path::parent_dir;
+use epub_core::{ChapterParts, ChapterStyle};
+use serde::{Deserialize, Serialize};
+
+use super::{EpubBook, EpubError, ImageFormat, ImageOptions};
+
+/// Largest file inlined when no cap is given (2MB)
+pub const DEFAULT_MAX_INLINE_BYTES: usize = 2 * 1024 * 1024;
+
+/// How a book is exported
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HtmlExportOptions {
+    /// Also export the non-linear spine items (answer keys, documents of
+    /// notes), after the reading order
+    pub include_auxiliary: bool,
+    /// Largest image, font or other file to inline, in bytes, after
+    /// `images` is applied; larger files are left out
+    pub max_inline_bytes: usize,
+    /// Downscaling or transcoding applied to images before inlining, to
+    /// fit more of them under the cap
+    pub images: ImageOptions,
+    /// Start each spine item on a new page when printed
+    pub page_breaks: bool,
+}
+
+impl Default for HtmlExportOptions {
+    fn default() -> Self {
+        Self {
+            include_auxiliary: false,
+            max_inline_bytes: DEFAULT_MAX_INLINE_BYTES,
+            images: ImageOptions::default(),
+            page_breaks: true,
+        }
+    }
+}
+
+/// A book as one HTML document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HtmlExport {
+    pub html: String,
+    /// Hrefs of files that were missing or over `max_inline_bytes`, and
+    /// so left out, in the order they were first referenced
+    pub omitted: Vec<String>,
+}
+
+impl EpubBook {
+    /// Export the book as one self-contained HTML document
+    ///
+    /// Spine items that are missing or not UTF-8 are skipped and listed in
+    /// `omitted`.
+    pub fn export_html(&self, options: &HtmlExportOptions) -> Result<HtmlExport, EpubError> {
+        let mut items: Vec<usize> = (0..self.spine.len())
+            .filter(|&i| self.spine[i].linear)
+            .collect();
+        if options.include_auxiliary {
+            items.extend((0..self.spine.len()).filter(|&i| !self.spine[i].linear));
+        }
+
+        let export = Export {
+            book: self,
+            options,
+            sections: self
+                .spine
+                .iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    let exported = items.contains(&i).then(|| section_id(i));
+                    (self.resolve_path(&item.href), exported)
+                })
+                .collect(),
+            media_types: self
+                .manifest
+                .values()
+                .map(|item| (self.resolve_path(&item.href), item.media_type.as_str()))
+                .collect(),
+            inlined: RefCell::default(),
+            omitted: RefCell::default(),
+        };
+
+        let mut styles = String::new();
+        if options.page_breaks {
+            styles.push_str("<style>\nsection.epub-section + section.epub-section { break-before: page; }\n</style>\n");
+        }
+        let mut seen_styles = HashSet::new();
+        let mut sections = String::new();
+        for &index in &items {
+            let href = &self.spine[index].href;
+            let path = self.resolve_path(href);
+            let Ok(html) = self.get_resource_as_string(&path) else {
+                export.omit(href);
+                continue;
+            };
+            let parts = epub_core::split_chapter(&epub_core::sanitize_html(&html));
+            export.add_styles(&parts, &path, &mut seen_styles, &mut styles);
+            export.add_section(&parts, &path, index, &mut sections);
+        }
+
+        let metadata = &self.metadata;
+        let mut html = String::from("<!DOCTYPE html>\n<html");
+        if let Some(language) = &metadata.language {
+            html.push_str(&format!(" lang=\"{}\"", escape(language)));
+        }
+        html.push_str(">\n<head>\n<meta charset=\"utf-8\"/>\n");
+        html.push_str(&format!("<title>{}</title>\n", escape(&metadata.title)));
+        html.push_str(&styles);
+        html.push_str("</head>\n<body>\n");
+        html.push_str(&sections);
+        html.push_str("</body>\n</html>\n");
+
+        Ok(HtmlExport {
+            html,
+            omitted: export.omitted.into_inner(),
+        })
+    }
+}
+
+/// Id of the section a spine item becomes; its ids get it and `-` as a
+/// prefix
+fn section_id(spine_index: usize) -> String {
+    format!("epub-{}", spine_index)
+}
+
+/// State of one export
+struct Export<'a> {
+    book: &'a EpubBook,
+    options: &'a HtmlExportOptions,
+    /// Section id of each spine item, by archive path; `None` for items
+    /// left out of the export
+    sections: HashMap<String, Option<String>>,
+    /// Manifest media type of each file, by archive path
+    media_types: HashMap<String, &'a str>,
+    /// Data URI of each file inlined so far, or `None` if it was left out
+    inlined: RefCell<HashMap<String, Option<String>>>,
+    omitted: RefCell<Vec<String>>,
+}
+
+impl Export<'_> {
+    /// Append a spine item's stylesheets not yet appended to `out`
+    fn add_styles(
+        &self,
+        parts: &ChapterParts,
+        chapter_path: &str,
+        seen: &mut HashSet<String>,
+        out: &mut String,
+    ) {
+        let chapter_dir = parent_dir(chapter_path);
+        for style in &parts.styles {
+            let (key, css, base_dir) = match style {
+                ChapterStyle::Link(href) => {
+                    let path = epub_core::path::resolve_href(chapter_dir, href);
+                    if seen.contains(&path) {
+                        continue;
+                    }
+                    let Ok(css) = self.book.get_resource_as_string(&path) else {
+                        self.omit(&self.book.resource_href(&path));
+                        seen.insert(path);
+                        continue;
+                    };
+                    let base_dir = parent_dir(&path).to_string();
+                    (path, css, base_dir)
+                }
+                ChapterStyle::Inline(css) => (css.clone(), css.clone(), chapter_dir.to_string()),
+            };
+            if !seen.insert(key) {
+                continue;
+            }
+            let css = epub_core::rewrite_css_urls(&css, &base_dir, |path| self.data_uri(path));
+            out.push_str("<style>\n");
+            out.push_str(css.trim());
+            out.push_str("\n</style>\n");
+        }
+    }
+
+    /// Append a spine item's body to `out` as a section
+    fn add_section(
+        &self,
+        parts: &ChapterParts,
+        chapter_path: &str,
+        index: usize,
+        out: &mut String,
+    ) {
+        let id = section_id(index);
+        let body = epub_core::prefix_ids(&parts.body, &format!("{}-", id));
+        let body =
+            epub_core::rewrite_links(&body, parent_dir(chapter_path), |path, fragment| {
+                match (self.sections.get(path), fragment) {
+                    (Some(Some(section)), Some(fragment)) if !fragment.is_empty() => {
+                        format!("#{}-{}", section, fragment)
+                    }
+                    (Some(Some(section)), _) => format!("#{}", section),
+                    (Some(None), _) => "#".to_string(),
+                    (None, _) => self.data_uri(path),
+                }
+            });
+
+        out.push_str(&format!("<section id=\"{}\" class=\"epub-section", id));
+        if let Some(class) = &parts.body_class {
+            out.push(' ');
+            out.push_str(class);
+        }
+        out.push_str("\">\n");
+        out.push_str(body.trim());
+        out.push_str("\n</section>\n");
+    }
+
+    /// A file as a data URI, or an empty URL if it is missing or too large
+    fn data_uri(&self, path: &str) -> String {
+        if let Some(uri) = self.inlined.borrow().get(path) {
+            return uri.clone().unwrap_or_default();
+        }
+        let href = self.book.resource_href(path);
+        let media_type = self
+            .media_types
+            .get(path)
+            .copied()
+            .unwrap_or("application/octet-stream");
+        let bytes = if media_type.starts_with("image/") {
+            self.book.get_resource_with(&href, &self.options.images)
+        } else {
+            self.book.get_resource(&href)
+        };
+
+        let uri = match bytes {
+            Ok(bytes) if bytes.len() <= self.options.max_inline_bytes => {
+                // Transcoded images are no longer of their manifest type
+                let media_type = ImageFormat::sniff(&bytes)
+                    .filter(|_| media_type.starts_with("image/"))
+                    .map_or(media_type, |format| format.media_type());
+                Some(format!(
+                    "data:{};base64,{}",
+                    media_type,
+                    base64::engine::general_purpose::STANDARD.encode(&bytes)
+                ))
+            }
+            _ => {
+                self.omit(&href);
+                None
+            }
+        };
+        self.inlined
+            .borrow_mut()
+            .insert(path.to_string(), uri.clone());
+        uri.unwrap_or_default()
+    }
+
+    fn omit(&self, href: &str) {
+        let mut omitted = self.omitted.borrow_mut();
+        if !omitted.iter().any(|h| h == href) {
+            omitted.push(href.to_string());
+        }
+    }
+}
+
+/// Escape text for an HTML attribute or element
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epub::tests::build_epub;
+
+    #[test]
+    fn test_export_html() {
+        let png = b"\x89PNG\r\n\x1a\n0000";
+        let big = [0xFFu8; 64];
+        let book = EpubBook::from_bytes(&build_epub(&[
+            (
+                "META-INF/container.xml",
+                br#"<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container"><rootfiles><rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/></rootfiles></container>"#,
+            ),
+            (
+                "OEBPS/content.opf",
+                br#"<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+<metadata xmlns:dc="http://purl.org/dc/elements/1.1/"><dc:title>Ships &amp; Sails</dc:title><dc:language>en</dc:language></metadata>
+<manifest>
+<item id="ch1" href="Text/ch1.xhtml" media-type="application/xhtml+xml"/>
+<item id="ch2" href="Text/ch2.xhtml" media-type="application/xhtml+xml"/>
+<item id="notes" href="Text/notes.xhtml" media-type="application/xhtml+xml"/>
+<item id="css" href="style.css" media-type="text/css"/>
+<item id="png" href="Images/a.png" media-type="image/png"/>
+<item id="big" href="Images/big.jpg" media-type="image/jpeg"/>
+</manifest>
+<spine><itemref idref="ch1"/><itemref idref="notes" linear="no"/><itemref idref="ch2"/></spine></package>"#,
+            ),
+            (
+                "OEBPS/Text/ch1.xhtml",
+                br##"<html><head><link rel="stylesheet" href="../style.css"/><script>x()</script></head>
+<body class="story"><h1 id="top">One</h1><p><img src="../Images/a.png" alt=""/><img src="../Images/big.jpg" alt=""/>
+<a href="ch2.xhtml#s1">on</a> <a href="notes.xhtml#n1">1</a> <a href="#top">up</a></p></body></html>"##,
+            ),
+            (
+                "OEBPS/Text/ch2.xhtml",
+                br#"<html><head><link rel="stylesheet" href="../style.css"/></head>
+<body><h1 id="top">Two</h1><p id="s1"><img src="../Images/a.png" alt=""/></p></body></html>"#,
+            ),
+            (
+                "OEBPS/Text/notes.xhtml",
+                br#"<html><body><p id="n1">Note</p></body></html>"#,
+            ),
+            (
+                "OEBPS/style.css",
+                b"h1 { background: url(Images/a.png) }",
+            ),
+            ("OEBPS/Images/a.png", png),
+            ("OEBPS/Images/big.jpg", &big),
+        ]))
+        .unwrap();
+
+        let options = HtmlExportOptions {
+            max_inline_bytes: 32,
+            ..Default::default()
+        };
+        let export = book.export_html(&options).unwrap();
+        let html = &export.html;
+        let png_uri = format!(
+            "data:image/png;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(png)
+        );
+
+        assert!(html.starts_with("<!DOCTYPE html>\n<html lang=\"en\">"));
+        assert!(html.contains("<title>Ships &amp; Sails</title>"));
+        // Linear items only, in spine order, ids and links prefixed
+        assert!(html.contains(r#"<section id="epub-0" class="epub-section story">"#));
+        assert!(html.contains(r#"<section id="epub-2" class="epub-section">"#));
+        assert!(!html.contains("epub-1") && !html.contains("Note"));
+        assert!(html.contains(r#"<h1 id="epub-0-top">One</h1>"#));
+        assert!(html.contains(
+            r##"<a href="#epub-2-s1">on</a> <a href="#">1</a> <a href="#epub-0-top">up</a>"##
+        ));
+        assert!(html.contains(r#"<p id="epub-2-s1">"#));
+        assert!(!html.contains("x()"));
+        // The shared stylesheet once, with its image inlined
+        assert_eq!(html.matches("h1 { background").count(), 1);
+        assert!(html.contains(&format!("url('{}')", png_uri)));
+        assert_eq!(
+            html.matches(&format!("<img src=\"{}\"", png_uri)).count(),
+            2
+        );
+        // Over the cap
+        assert!(html.contains(r#"<img src="" alt=""/>"#));
+        assert_eq!(export.omitted, vec!["Images/big.jpg".to_string()]);
+
+        let export = book
+            .export_html(&HtmlExportOptions {
+                include_auxiliary: true,
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(export.html.contains(r##"<a href="#epub-1-n1">1</a>"##));
+        assert!(export.html.find("epub-2\"").unwrap() < export.html.find("epub-1\"").unwrap());
+        assert!(export.omitted.is_empty());
+    }
+}
@@ -0,0 +1,246 @@
+//! Font de-obfuscation
+//!
+//! Publishers obfuscate embedded fonts so they cannot be lifted out of the
+//! book as-is: `META-INF/encryption.xml` lists each font and the algorithm
+//! used, and the first bytes of the font are XORed with a key derived from
+//! the book's identifier. Both algorithms in use are supported:
+//!
+//! - IDPF (`http://www.idpf.org/2008/embedding`): the key is the SHA-1 of
+//!   the unique identifier with whitespace removed; the first 1040 bytes are
+//!   XORed.
+//! - Adobe (`http://ns.adobe.com/pdf/enc#RC`): the key is the 16 bytes of
+//!   the `urn:uuid:` identifier; the first 1024 bytes are XORed.
+//!
+//! Other encryption is DRM, which loading rejects (see `drm`).
+
+use std::collections::HashMap;
+
+use epub_core::path::normalize_path;
+
+/// Where encrypted resources are listed
+pub(super) const ENCRYPTION_PATH: &str = "META-INF/encryption.xml";
+
+const IDPF_ALGORITHM: &str = "http://www.idpf.org/2008/embedding";
+const ADOBE_ALGORITHM: &str = "http://ns.adobe.com/pdf/enc#RC";
+
+/// Algorithms that only obfuscate fonts, as opposed to DRM
+pub(super) const OBFUSCATION_ALGORITHMS: &[&str] = &[IDPF_ALGORITHM, ADOBE_ALGORITHM];
+
+/// Bytes the IDPF algorithm obfuscates
+const IDPF_HEADER_LEN: usize = 1040;
+
+/// Bytes the Adobe algorithm obfuscates
+const ADOBE_HEADER_LEN: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Algorithm {
+    Idpf,
+    Adobe,
+}
+
+/// Obfuscated fonts of a book and the keys to restore them
+#[derive(Debug, Default)]
+pub(super) struct ObfuscatedFonts {
+    /// Algorithm by normalized archive path
+    fonts: HashMap<String, Algorithm>,
+    idpf_key: Option<[u8; 20]>,
+    adobe_key: Option<[u8; 16]>,
+}
+
+impl ObfuscatedFonts {
+    /// Read `encryption.xml`, keying with the book's unique identifier
+    ///
+    /// Malformed files obfuscate nothing, so fonts are served as stored.
+    pub fn parse(encryption_xml: &str, identifier: Option<&str>) -> Self {
+        let Ok(doc) = roxmltree::Document::parse(encryption_xml) else {
+            return Self::default();
+        };
+
+        let mut fonts = HashMap::new();
+        for data in doc
+            .descendants()
+            .filter(|node| node.tag_name().name() == "EncryptedData")
+        {
+            let algorithm = data
+                .descendants()
+                .find(|node| node.tag_name().name() == "EncryptionMethod")
+                .and_then(|node| node.attribute("Algorithm"))
+                .and_then(|uri| match uri {
+                    IDPF_ALGORITHM => Some(Algorithm::Idpf),
+                    ADOBE_ALGORITHM => Some(Algorithm::Adobe),
+                    _ => None,
+                });
+            let uri = data
+                .descendants()
+                .find(|node| node.tag_name().name() == "CipherReference")
+                .and_then(|node| node.attribute("URI"));
+
+            if let (Some(algorithm), Some(uri)) = (algorithm, uri) {
+                // URIs are relative to the container root
+                fonts.insert(normalize_path(&percent_decode(uri)), algorithm);
+            }
+        }
+
+        let identifier = identifier.unwrap_or_default();
+        Self {
+            idpf_key: fonts
+                .values()
+                .any(|a| *a == Algorithm::Idpf)
+                .then(|| idpf_key(identifier)),
+            adobe_key: adobe_key(identifier),
+            fonts,
+        }
+    }
+
+    /// Restore a resource in place if it is an obfuscated font
+    pub fn deobfuscate(&self, path: &str, bytes: &mut [u8]) {
+        match self.fonts.get(path) {
+            Some(Algorithm::Idpf) => {
+                if let Some(key) = &self.idpf_key {
+                    xor_header(bytes, key, IDPF_HEADER_LEN);
+                }
+            }
+            Some(Algorithm::Adobe) => match &self.adobe_key {
+                Some(key) => xor_header(bytes, key, ADOBE_HEADER_LEN),
+                None => crate::console_log(&format!(
+                    "[EPUB] No UUID identifier to de-obfuscate '{}'",
+                    path
+                )),
+            },
+            None => {}
+        }
+    }
+}
+
+/// SHA-1 of the identifier without XML whitespace
+fn idpf_key(identifier: &str) -> [u8; 20] {
+    let stripped: String = identifier
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '\t' | '\n' | '\r'))
+        .collect();
+    sha1_smol::Sha1::from(stripped).digest().bytes()
+}
+
+/// The 16 bytes of a `urn:uuid:` identifier
+fn adobe_key(identifier: &str) -> Option<[u8; 16]> {
+    let uuid = identifier.trim();
+    let uuid = uuid
+        .get(..9)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("urn:uuid:"))
+        .map_or(uuid, |_| &uuid[9..]);
+    let hex: String = uuid.chars().filter(|c| *c != '-').collect();
+    if hex.len() != 32 {
+        return None;
+    }
+
+    let mut key = [0; 16];
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(key)
+}
+
+/// XOR the first `len` bytes with the key, repeated
+fn xor_header(bytes: &mut [u8], key: &[u8], len: usize) {
+    for (byte, k) in bytes.iter_mut().take(len).zip(key.iter().cycle()) {
+        *byte ^= k;
+    }
+}
+
+/// Decode `%XX` escapes; invalid escapes are kept as written
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| uri.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UUID: &str = "urn:uuid:12345678-9abc-def0-1234-56789abcdef0";
+
+    fn encryption_xml() -> String {
+        format!(
+            r#"<encryption xmlns="urn:oasis:names:tc:opendocument:xmlns:container"
+    xmlns:enc="http://www.w3.org/2001/04/xmlenc#">
+  <enc:EncryptedData>
+    <enc:EncryptionMethod Algorithm="{}"/>
+    <enc:CipherData><enc:CipherReference URI="OEBPS/fonts/My%20Serif.otf"/></enc:CipherData>
+  </enc:EncryptedData>
+  <enc:EncryptedData>
+    <enc:EncryptionMethod Algorithm="{}"/>
+    <enc:CipherData><enc:CipherReference URI="OEBPS/fonts/sans.ttf"/></enc:CipherData>
+  </enc:EncryptedData>
+  <enc:EncryptedData>
+    <enc:EncryptionMethod Algorithm="http://www.w3.org/2001/04/xmlenc#aes128-cbc"/>
+    <enc:CipherData><enc:CipherReference URI="OEBPS/ch1.xhtml"/></enc:CipherData>
+  </enc:EncryptedData>
+</encryption>"#,
+            IDPF_ALGORITHM, ADOBE_ALGORITHM
+        )
+    }
+
+    #[test]
+    fn test_deobfuscate_idpf_and_adobe() {
+        let fonts = ObfuscatedFonts::parse(&encryption_xml(), Some(UUID));
+        assert_eq!(fonts.fonts.len(), 2);
+
+        let font: Vec<u8> = (0..2000).map(|i| (i % 251) as u8).collect();
+
+        // Obfuscation is its own inverse
+        let mut idpf = font.clone();
+        xor_header(&mut idpf, &idpf_key(UUID), IDPF_HEADER_LEN);
+        assert_ne!(idpf, font);
+        assert_eq!(idpf[IDPF_HEADER_LEN..], font[IDPF_HEADER_LEN..]);
+        fonts.deobfuscate("OEBPS/fonts/My Serif.otf", &mut idpf);
+        assert_eq!(idpf, font);
+
+        let mut adobe = font.clone();
+        xor_header(&mut adobe, &adobe_key(UUID).unwrap(), ADOBE_HEADER_LEN);
+        fonts.deobfuscate("OEBPS/fonts/sans.ttf", &mut adobe);
+        assert_eq!(adobe, font);
+
+        // Other encryption is not ours to undo
+        let mut chapter = font.clone();
+        fonts.deobfuscate("OEBPS/ch1.xhtml", &mut chapter);
+        assert_eq!(chapter, font);
+    }
+
+    #[test]
+    fn test_keys() {
+        // Whitespace in the identifier is ignored
+        assert_eq!(idpf_key(" urn:isbn:123\n"), idpf_key("urn:isbn:123"));
+        assert_eq!(
+            idpf_key("abc")[..4],
+            [0xa9, 0x99, 0x3e, 0x36],
+            "SHA-1 of \"abc\""
+        );
+
+        let key = adobe_key(UUID).unwrap();
+        assert_eq!(key[..3], [0x12, 0x34, 0x56]);
+        assert_eq!(adobe_key("12345678-9abc-def0-1234-56789abcdef0"), Some(key));
+        assert_eq!(adobe_key("urn:isbn:9780000000000"), None);
+
+        assert!(ObfuscatedFonts::parse("not xml", Some(UUID))
+            .fonts
+            .is_empty());
+    }
+}
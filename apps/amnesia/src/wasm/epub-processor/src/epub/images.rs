@@ -0,0 +1,256 @@
+//! Downscaled and transcoded images
+//!
+//! Scanned books embed images far larger than a phone screen, and a mobile
+//! browser decoding a 20MP page scan can run out of memory. With
+//! [`ImageOptions`], `getResource` decodes such an image once in the
+//! processor, scales it to fit a maximum dimension and re-encodes it. Each
+//! book keeps the results in a byte-bounded cache keyed by path and
+//! options, so a page turned back to is not decoded again.
+//!
+//! JPEG, PNG and WebP images are processed; GIFs (which may be animated),
+//! SVGs and other files are returned as stored. Without the `images` cargo
+//! feature the options are accepted and ignored.
+
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use serde::{Deserialize, Serialize};
+
+use super::resources::ResourceCache;
+use super::EpubError;
+
+/// Byte budget of each book's cache of processed images (8MB)
+pub const OPTIMIZED_IMAGE_CACHE_BYTES: usize = 8 * 1024 * 1024;
+
+/// JPEG quality used when none is given
+#[cfg(feature = "images")]
+pub const DEFAULT_JPEG_QUALITY: u8 = 80;
+
+/// How to process an image resource
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ImageOptions {
+    /// Longest side in pixels; larger images are scaled down to fit
+    pub max_dimension: Option<u32>,
+    /// Format to re-encode to; by default images keep their format
+    pub format: Option<ImageFormat>,
+    /// JPEG quality from 1 to 100 (default 80); WebP is encoded lossless
+    pub quality: Option<u8>,
+}
+
+impl ImageOptions {
+    /// Whether the options leave every image as stored
+    pub fn is_none(&self) -> bool {
+        self.max_dimension.is_none() && self.format.is_none()
+    }
+}
+
+/// Formats images can be re-encoded to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    Webp,
+    Jpeg,
+    Png,
+}
+
+impl ImageFormat {
+    pub fn media_type(self) -> &'static str {
+        match self {
+            ImageFormat::Webp => "image/webp",
+            ImageFormat::Jpeg => "image/jpeg",
+            ImageFormat::Png => "image/png",
+        }
+    }
+
+    /// The format of an image file, if it is one the processor re-encodes
+    pub(super) fn sniff(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(ImageFormat::Png)
+        } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(ImageFormat::Jpeg)
+        } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+            Some(ImageFormat::Webp)
+        } else {
+            None
+        }
+    }
+}
+
+/// Processed images of one book
+pub(super) struct OptimizedImages {
+    cache: Mutex<ResourceCache>,
+}
+
+impl OptimizedImages {
+    pub fn new() -> Self {
+        Self {
+            cache: Mutex::new(ResourceCache::new(OPTIMIZED_IMAGE_CACHE_BYTES)),
+        }
+    }
+
+    /// The image at `path` processed with `options`, from the cache when it
+    /// has been before; `read` returns the stored bytes
+    pub fn get(
+        &self,
+        path: &str,
+        options: &ImageOptions,
+        read: impl FnOnce() -> Result<Vec<u8>, EpubError>,
+    ) -> Result<Vec<u8>, EpubError> {
+        let key = format!("{}\n{:?}", path, options);
+        if let Some(bytes) = self.lock().get(&key) {
+            return Ok(bytes.to_vec());
+        }
+        let bytes = read()?;
+        let Some(optimized) = optimize(&bytes, options)? else {
+            return Ok(bytes);
+        };
+        self.lock().insert(key, optimized.clone());
+        Ok(optimized)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ResourceCache> {
+        self.cache.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// `bytes` re-encoded as `options` ask; `None` if the image is kept as
+/// stored
+///
+/// Images are only decoded when they are larger than `max_dimension` or in
+/// a different format than asked for, which is read from their headers.
+#[cfg(feature = "images")]
+pub fn optimize(bytes: &[u8], options: &ImageOptions) -> Result<Option<Vec<u8>>, EpubError> {
+    use image::imageops::FilterType;
+
+    let Some(source) = ImageFormat::sniff(bytes) else {
+        return Ok(None);
+    };
+    let target = options.format.unwrap_or(source);
+    let oversize = match (options.max_dimension, epub_core::image_size(bytes)) {
+        (Some(max), Some((width, height))) => width.max(height) > max.max(1),
+        _ => false,
+    };
+    if !oversize && target == source {
+        return Ok(None);
+    }
+
+    let mut image = image::load_from_memory(bytes).map_err(image_error)?;
+    if let (true, Some(max)) = (oversize, options.max_dimension) {
+        // Keeps the aspect ratio, fitting the longer side to `max`
+        image = image.resize(max.max(1), max.max(1), FilterType::Triangle);
+    }
+
+    let mut encoded = Vec::new();
+    let mut cursor = std::io::Cursor::new(&mut encoded);
+    match target {
+        ImageFormat::Jpeg => {
+            let quality = options
+                .quality
+                .unwrap_or(DEFAULT_JPEG_QUALITY)
+                .clamp(1, 100);
+            let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut cursor, quality);
+            // JPEG has no alpha channel
+            image::DynamicImage::ImageRgb8(image.to_rgb8())
+                .write_with_encoder(encoder)
+                .map_err(image_error)?;
+        }
+        ImageFormat::Png => image
+            .write_to(&mut cursor, image::ImageFormat::Png)
+            .map_err(image_error)?,
+        ImageFormat::Webp => {
+            // The WebP encoder takes 8-bit RGB(A) only
+            let image = if image.color().has_alpha() {
+                image::DynamicImage::ImageRgba8(image.to_rgba8())
+            } else {
+                image::DynamicImage::ImageRgb8(image.to_rgb8())
+            };
+            image
+                .write_to(&mut cursor, image::ImageFormat::WebP)
+                .map_err(image_error)?
+        }
+    }
+    Ok(Some(encoded))
+}
+
+/// Without the `images` feature every image is kept as stored
+#[cfg(not(feature = "images"))]
+pub fn optimize(_bytes: &[u8], _options: &ImageOptions) -> Result<Option<Vec<u8>>, EpubError> {
+    Ok(None)
+}
+
+#[cfg(feature = "images")]
+fn image_error(e: image::ImageError) -> EpubError {
+    EpubError::InvalidImage(e.to_string())
+}
+
+#[cfg(all(test, feature = "images"))]
+mod tests {
+    use super::*;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = image::RgbImage::from_pixel(width, height, image::Rgb([200, 40, 40]));
+        let mut bytes = Vec::new();
+        image::DynamicImage::ImageRgb8(image)
+            .write_to(
+                &mut std::io::Cursor::new(&mut bytes),
+                image::ImageFormat::Png,
+            )
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_optimize() {
+        let scan = png(400, 200);
+        let fits = ImageOptions {
+            max_dimension: Some(400),
+            ..Default::default()
+        };
+        assert_eq!(optimize(&scan, &fits).unwrap(), None);
+        assert_eq!(optimize(b"<svg/>", &fits).unwrap(), None);
+
+        let options = ImageOptions {
+            max_dimension: Some(100),
+            format: Some(ImageFormat::Webp),
+            ..Default::default()
+        };
+        let small = optimize(&scan, &options).unwrap().unwrap();
+        assert_eq!(ImageFormat::sniff(&small), Some(ImageFormat::Webp));
+        assert_eq!(epub_core::image_size(&small), Some((100, 50)));
+
+        let jpeg = ImageOptions {
+            format: Some(ImageFormat::Jpeg),
+            quality: Some(60),
+            ..Default::default()
+        };
+        let transcoded = optimize(&scan, &jpeg).unwrap().unwrap();
+        assert_eq!(ImageFormat::sniff(&transcoded), Some(ImageFormat::Jpeg));
+        assert_eq!(epub_core::image_size(&transcoded), Some((400, 200)));
+
+        let truncated = &scan[..scan.len() / 2];
+        assert!(matches!(
+            optimize(truncated, &options),
+            Err(EpubError::InvalidImage(_))
+        ));
+    }
+
+    #[test]
+    fn test_cache() {
+        let images = OptimizedImages::new();
+        let options = ImageOptions {
+            max_dimension: Some(10),
+            ..Default::default()
+        };
+        let mut reads = 0;
+        for _ in 0..2 {
+            let bytes = images
+                .get("Images/p1.png", &options, || {
+                    reads += 1;
+                    Ok(png(40, 40))
+                })
+                .unwrap();
+            assert_eq!(epub_core::image_size(&bytes), Some((10, 10)));
+        }
+        assert_eq!(reads, 1);
+    }
+}
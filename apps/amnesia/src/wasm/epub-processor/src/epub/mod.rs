@@ -1,18 +1,47 @@
 //! EPUB parsing and extraction module
 //!
-//! Handles reading EPUB files and extracting content.
+//! Handles reading EPUB files and extracting content. Package parsing (OPF,
+//! NAV, NCX, path resolution) is delegated to the shared `epub-core` crate;
+//! this module owns ZIP extraction and the in-memory resource store.
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::io::{Cursor, Read};
+use std::collections::{HashMap, HashSet};
+use std::io::{Cursor, Read, Seek};
 use thiserror::Error;
 use zip::ZipArchive;
 
+use epub_core::chunk::{chunk_spine_item, parse_chunk_href, ChunkOptions};
+use epub_core::path::{parent_dir, percent_encode_path, resolve_href, strip_fragment};
+use epub_core::{
+    block_map, book_fingerprint, content_checksum, estimate_heights, image_size, ChapterChecksum,
+    EpubParseError, LinkGraph, Note, Package, TocDocInfo,
+};
+
+mod drm;
+mod export;
+mod fonts;
+mod images;
 pub mod parser;
-mod opf;
-
-pub use opf::*;
-use opf::{find_toc_doc, TocDocInfo};
+mod resources;
+mod webpub;
+
+pub use drm::DrmScheme;
+pub use export::{HtmlExport, HtmlExportOptions, DEFAULT_MAX_INLINE_BYTES};
+pub use images::{ImageFormat, ImageOptions};
+pub use webpub::{
+    Certification, Contributor, LinkProperties, Presentation, WebPubAccessibility, WebPubLink,
+    WebPubManifest, WebPubMetadata,
+};
+
+use fonts::ObfuscatedFonts;
+use images::OptimizedImages;
+use resources::Resources;
+
+pub use epub_core::{
+    Block, BlockImage, BlockMetrics, BookIndex, BookMetadata, Creator, Glossary, GlossaryEntry,
+    IndexEntry, IndexLocator, IndexMatch, Layout, ManifestItem, PageProgression, PageSpread,
+    Rendition, RenditionSelector, RootFile, SpineItem, TocEntry, Viewport,
+};
 
 #[derive(Error, Debug)]
 pub enum EpubError {
@@ -33,6 +62,68 @@ pub enum EpubError {
 
     #[error("Security violation: {0}")]
     SecurityViolation(String),
+
+    /// The book is encrypted
+    #[error("DRM protected: {0}")]
+    DrmProtected(DrmScheme),
+
+    #[error("Rendition {index} not found; the book has {count}")]
+    RenditionNotFound { index: usize, count: usize },
+
+    #[error("Invalid image: {0}")]
+    InvalidImage(String),
+}
+
+/// How far loading a book has got
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadProgress {
+    /// Spine items read so far
+    pub loaded: usize,
+    pub total: usize,
+}
+
+/// Something wrong with a book that did not stop it loading
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParseWarning {
+    pub code: WarningCode,
+    pub message: String,
+    /// The file the warning is about
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub href: Option<String>,
+}
+
+/// What a [`ParseWarning`] is about, stable across releases
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum WarningCode {
+    /// The NAV or NCX document is missing or not UTF-8; the ToC was
+    /// generated from the spine
+    TocMissing,
+    /// A spine item's file is not in the archive
+    SpineItemMissing,
+    /// A spine item is not UTF-8, so it has no chunks and no text
+    SpineItemNotUtf8,
+}
+
+impl ParseWarning {
+    fn new(code: WarningCode, message: String, href: &str) -> Self {
+        Self {
+            code,
+            message,
+            href: Some(href.to_string()),
+        }
+    }
+}
+
+impl From<EpubParseError> for EpubError {
+    fn from(e: EpubParseError) -> Self {
+        match e {
+            EpubParseError::InvalidEpub(msg) => EpubError::InvalidEpub(msg),
+            EpubParseError::XmlError(msg) => EpubError::XmlError(msg),
+        }
+    }
 }
 
 // ============================================================================
@@ -107,75 +198,83 @@ fn validate_zip_path(path: &str) -> Result<(), EpubError> {
     Ok(())
 }
 
-/// Normalize a file path by removing redundant components.
-fn normalize_path(path: &str) -> String {
-    let mut components: Vec<&str> = Vec::new();
-
-    for component in path.split(['/', '\\']) {
-        match component {
-            "" | "." => continue,
-            ".." => {
-                // This should have been caught by validate_zip_path,
-                // but handle defensively
-                components.pop();
-            }
-            _ => components.push(component),
-        }
-    }
-
-    components.join("/")
-}
-
 /// Parsed book metadata and structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ParsedBook {
     pub id: String,
+    /// What `id` was derived from
+    pub id_source: IdSource,
+    /// Hex SHA-1 of the spine items' checksums, identifying the content
+    pub fingerprint: String,
     pub metadata: BookMetadata,
     pub spine: Vec<SpineItem>,
     pub toc: Vec<TocEntry>,
+    /// Virtual sub-items of oversize spine items
+    pub chunks: Vec<ChapterChunk>,
+    /// Book-wide layout; pre-paginated books render one fixed page per
+    /// spine item
+    pub rendition: Rendition,
+    /// Content checksum of each spine item, for finding the chapters that
+    /// changed when the book file is updated
+    pub checksums: Vec<ChapterChecksum>,
+    /// Every rendition in the container, default first; most books have one
+    pub renditions: Vec<RootFile>,
+    /// Which of `renditions` was loaded
+    pub rendition_index: usize,
 }
 
-/// Book metadata
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+/// Where a book's id comes from
+///
+/// A book that is loaded while a different book (by [`ParsedBook::fingerprint`])
+/// has its id may get a suffix, as set by [`LoadOptions::on_collision`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct BookMetadata {
-    pub title: String,
-    pub creators: Vec<Creator>,
-    pub language: Option<String>,
-    pub identifier: Option<String>,
-    pub publisher: Option<String>,
-    pub description: Option<String>,
-    pub cover_href: Option<String>,
+pub enum IdSource {
+    /// The package's `dc:identifier`
+    Identifier,
+    /// `book-` and the start of the fingerprint, for books without an
+    /// identifier
+    ContentHash,
 }
 
-/// Creator (author) information
+/// A synthetic sub-item of an oversize spine item
+///
+/// Its `href` can be passed to `getChapter` like any spine href.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct Creator {
-    pub name: String,
-    pub role: Option<String>,
+pub struct ChapterChunk {
+    pub href: String,
+    pub parent_href: String,
+    pub spine_index: usize,
+    pub index: usize,
 }
 
-/// Spine item (reading order entry)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A non-linear spine item: content outside the reading order, such as an
+/// answer key or a document of pop-up notes
+///
+/// Its `href` can be passed to `getChapter` like any spine href.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct SpineItem {
-    pub id: String,
+pub struct AuxiliaryItem {
+    pub spine_index: usize,
     pub href: String,
     pub media_type: String,
-    pub linear: bool,
+    /// Label of the first ToC entry pointing into the item, if any
+    pub title: Option<String>,
+    /// Spine items that link to this one, in spine order
+    pub linked_from: Vec<usize>,
 }
 
-/// Table of contents entry
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A footnote or endnote, for showing in a popup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct TocEntry {
-    pub id: String,
+pub struct Footnote {
+    /// Href of the document holding the note
     pub href: String,
-    pub label: String,
-    pub level: usize,
-    pub children: Vec<TocEntry>,
+    /// The note's inner HTML is always sanitized
+    #[serde(flatten)]
+    pub note: Note,
 }
 
 /// Chapter content
@@ -188,410 +287,366 @@ pub struct ChapterContent {
     pub images: Vec<String>,
 }
 
+/// Processing applied to chapter HTML before it is returned
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ChapterOptions {
+    /// Add deterministic `data-anchor` attributes to block elements
+    pub inject_anchors: bool,
+    /// Add ARIA roles derived from `epub:type` and label sections with their
+    /// headings, for screen readers
+    pub aria_roles: bool,
+    /// Remove scripts, event handler attributes and `javascript:` URLs
+    pub sanitize: bool,
+    /// Rewrite relative image, stylesheet and link URLs to
+    /// `epub://{bookId}/{href}`, where `href` can be passed to `getResource`
+    pub rewrite_urls: bool,
+    /// Prefix to rewrite URLs with instead of `epub://{bookId}/`; implies
+    /// `rewrite_urls`
+    pub url_prefix: Option<String>,
+}
+
+/// Default byte budget for a lazily loaded book's decompressed files (32MB)
+pub const DEFAULT_RESOURCE_CACHE_BYTES: usize = 32 * 1024 * 1024;
+
+/// How an EPUB is loaded
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LoadOptions {
+    /// Keep the archive compressed and decompress resources on demand;
+    /// for image-heavy books that would not fit in memory extracted
+    pub lazy: bool,
+    /// Byte budget of the decompressed-file cache in lazy mode
+    pub resource_cache_bytes: Option<usize>,
+    /// What to do when a different book already has the id
+    pub on_collision: CollisionStrategy,
+    /// Which rendition to load from a book with several
+    pub rendition: RenditionSelector,
+}
+
+/// How loading a book whose id belongs to a different loaded book is
+/// resolved
+///
+/// Reloading the same content under its id is not a collision and always
+/// replaces the loaded copy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CollisionStrategy {
+    /// Load under the first free id of `{id}-2`, `{id}-3`, ...
+    #[default]
+    Suffix,
+    /// Fail the load
+    Error,
+    /// Replace the loaded book, e.g. with an updated edition
+    Replace,
+}
+
+/// The package document of the rendition being loaded
+struct SelectedPackage {
+    opf: Package,
+    opf_dir: String,
+    renditions: Vec<RootFile>,
+    rendition_index: usize,
+}
+
 /// Internal representation of an EPUB book
 pub struct EpubBook {
     pub id: String,
+    pub id_source: IdSource,
+    pub fingerprint: String,
     pub metadata: BookMetadata,
     pub spine: Vec<SpineItem>,
     pub toc: Vec<TocEntry>,
     pub manifest: HashMap<String, ManifestItem>,
-    resources: HashMap<String, Vec<u8>>,
+    pub chunks: Vec<ChapterChunk>,
+    pub rendition: Rendition,
+    pub checksums: Vec<ChapterChecksum>,
+    pub renditions: Vec<RootFile>,
+    pub rendition_index: usize,
+    /// Problems found while loading
+    pub warnings: Vec<ParseWarning>,
+    resources: Resources,
+    fonts: ObfuscatedFonts,
+    /// Downscaled and transcoded images
+    optimized_images: OptimizedImages,
+    /// Chunk HTML keyed by chunk href
+    chunk_html: HashMap<String, String>,
     opf_dir: String,
 }
 
-/// Manifest item from OPF
-#[derive(Debug, Clone)]
-pub struct ManifestItem {
-    pub id: String,
-    pub href: String,
-    pub media_type: String,
-    pub properties: Option<String>,
-}
-
 impl EpubBook {
     /// Parse an EPUB from raw bytes
     pub fn from_bytes(data: &[u8]) -> Result<Self, EpubError> {
-        let cursor = Cursor::new(data);
-        let mut archive = ZipArchive::new(cursor)?;
-
-        // Read container.xml to find the OPF file
-        let opf_path = Self::find_opf_path(&mut archive)?;
-        let opf_dir = opf_path.rsplit_once('/')
-            .map(|(dir, _)| dir.to_string())
-            .unwrap_or_default();
+        Self::extracted(data, &RenditionSelector::default(), &mut |_| {})
+    }
 
-        // Read and parse OPF
-        let opf_content = Self::read_file(&mut archive, &opf_path)?;
-        let opf = opf::parse_opf(&opf_content, &opf_dir)?;
-
-        // Generate book ID from identifier or title
-        let id = opf.metadata.identifier
-            .clone()
-            .unwrap_or_else(|| {
-                use std::collections::hash_map::DefaultHasher;
-                use std::hash::{Hash, Hasher};
-                let mut hasher = DefaultHasher::new();
-                opf.metadata.title.hash(&mut hasher);
-                format!("book-{:x}", hasher.finish())
-            });
+    /// Parse an EPUB, decompressing resources only when they are requested
+    ///
+    /// Keeps the compressed archive plus up to `cache_bytes` of recently
+    /// used files rather than every file decompressed.
+    pub fn from_bytes_lazy(data: Vec<u8>, cache_bytes: usize) -> Result<Self, EpubError> {
+        Self::lazy(
+            data,
+            cache_bytes,
+            &RenditionSelector::default(),
+            &mut |_| {},
+        )
+    }
 
-        // Extract all resources into memory with security checks
-        let mut resources = HashMap::new();
-        let mut total_size: u64 = 0;
-        let compressed_size = data.len() as u64;
-        let file_count = archive.len();
-
-        // Check file count limit
-        if file_count > MAX_FILE_COUNT {
-            return Err(EpubError::SecurityViolation(format!(
-                "Too many files in archive: {} (max {})",
-                file_count, MAX_FILE_COUNT
-            )));
-        }
+    /// Parse an EPUB the way `options` ask
+    pub fn load(data: Vec<u8>, options: &LoadOptions) -> Result<Self, EpubError> {
+        Self::load_with_progress(data, options, &mut |_| {})
+    }
 
-        for i in 0..file_count {
-            let mut file = archive.by_index(i)?;
-            if file.is_file() {
-                let raw_name = file.name().to_string();
-
-                // Security: Validate the file path
-                validate_zip_path(&raw_name)?;
-
-                // Normalize the path
-                let name = normalize_path(&raw_name);
-
-                // Read content with size limits
-                let mut content = Vec::new();
-                file.read_to_end(&mut content)?;
-
-                let file_size = content.len() as u64;
-                total_size += file_size;
-
-                // Security: Check for zip bomb (decompression ratio)
-                if compressed_size > 0 && total_size > compressed_size * MAX_DECOMPRESSION_RATIO {
-                    return Err(EpubError::SecurityViolation(format!(
-                        "Decompression ratio exceeded: {}:1 (max {}:1)",
-                        total_size / compressed_size,
-                        MAX_DECOMPRESSION_RATIO
-                    )));
-                }
-
-                // Security: Check total size limit
-                if total_size > MAX_TOTAL_SIZE {
-                    return Err(EpubError::SecurityViolation(format!(
-                        "Total decompressed size exceeded: {} bytes (max {} bytes)",
-                        total_size, MAX_TOTAL_SIZE
-                    )));
-                }
-
-                resources.insert(name, content);
-            }
+    /// Parse an EPUB, calling `progress` as its spine items are read
+    pub fn load_with_progress(
+        data: Vec<u8>,
+        options: &LoadOptions,
+        progress: &mut dyn FnMut(LoadProgress),
+    ) -> Result<Self, EpubError> {
+        if options.lazy {
+            let cache_bytes = options
+                .resource_cache_bytes
+                .unwrap_or(DEFAULT_RESOURCE_CACHE_BYTES);
+            Self::lazy(data, cache_bytes, &options.rendition, progress)
+        } else {
+            Self::extracted(&data, &options.rendition, progress)
         }
-
-        // Parse ToC from NAV or NCX document
-        let opf_doc = roxmltree::Document::parse(&opf_content)
-            .map_err(|e| EpubError::XmlError(e.to_string()))?;
-        let toc_info = find_toc_doc(&opf_doc, &opf.manifest);
-
-        // Debug: Log TOC document info
-        web_sys::console::log_1(&format!("[EPUB] TOC info: {:?}", match &toc_info {
-            TocDocInfo::Nav { href } => format!("NAV: {}", href),
-            TocDocInfo::Ncx { href } => format!("NCX: {}", href),
-            TocDocInfo::None => "None".to_string(),
-        }).into());
-
-        let toc = match toc_info {
-            TocDocInfo::Nav { href } => {
-                let full_path = if opf_dir.is_empty() {
-                    href.clone()
-                } else {
-                    format!("{}/{}", opf_dir, href)
-                };
-                web_sys::console::log_1(&format!("[EPUB] Looking for NAV at: {}", full_path).into());
-                if let Some(bytes) = resources.get(&full_path) {
-                    web_sys::console::log_1(&format!("[EPUB] Found NAV document ({} bytes)", bytes.len()).into());
-                    if let Ok(content) = String::from_utf8(bytes.clone()) {
-                        let entries = Self::parse_nav_document(&content);
-                        web_sys::console::log_1(&format!("[EPUB] Parsed {} NAV entries", entries.len()).into());
-                        entries
-                    } else {
-                        web_sys::console::log_1(&"[EPUB] NAV document is not valid UTF-8".into());
-                        Vec::new()
-                    }
-                } else {
-                    web_sys::console::log_1(&format!("[EPUB] NAV not found. Available resources: {:?}",
-                        resources.keys().take(10).collect::<Vec<_>>()).into());
-                    Vec::new()
-                }
-            }
-            TocDocInfo::Ncx { href } => {
-                let full_path = if opf_dir.is_empty() {
-                    href.clone()
-                } else {
-                    format!("{}/{}", opf_dir, href)
-                };
-                web_sys::console::log_1(&format!("[EPUB] Looking for NCX at: {}", full_path).into());
-                if let Some(bytes) = resources.get(&full_path) {
-                    web_sys::console::log_1(&format!("[EPUB] Found NCX document ({} bytes)", bytes.len()).into());
-                    if let Ok(content) = String::from_utf8(bytes.clone()) {
-                        let entries = Self::parse_ncx_document(&content);
-                        web_sys::console::log_1(&format!("[EPUB] Parsed {} NCX entries", entries.len()).into());
-                        entries
-                    } else {
-                        web_sys::console::log_1(&"[EPUB] NCX document is not valid UTF-8".into());
-                        Vec::new()
-                    }
-                } else {
-                    web_sys::console::log_1(&format!("[EPUB] NCX not found. Available resources: {:?}",
-                        resources.keys().take(10).collect::<Vec<_>>()).into());
-                    Vec::new()
-                }
-            }
-            TocDocInfo::None => {
-                // Generate ToC from spine
-                web_sys::console::log_1(&format!("[EPUB] No NAV/NCX found, generating from spine ({} items)", opf.spine.len()).into());
-                let entries = Self::generate_toc_from_spine(&opf.spine);
-                web_sys::console::log_1(&format!("[EPUB] Generated {} entries from spine", entries.len()).into());
-                entries
-            }
-        };
-
-        Ok(Self {
-            id,
-            metadata: opf.metadata,
-            spine: opf.spine,
-            toc,
-            manifest: opf.manifest,
-            resources,
-            opf_dir,
-        })
     }
 
-    /// Parse EPUB 3 Navigation Document (NAV)
-    fn parse_nav_document(content: &str) -> Vec<TocEntry> {
-        let doc = match roxmltree::Document::parse(content) {
-            Ok(d) => d,
-            Err(_) => return Vec::new(),
-        };
-
-        // Find the nav element with epub:type="toc"
-        for node in doc.descendants() {
-            if node.tag_name().name() == "nav" {
-                // Check for epub:type="toc" or just use the first nav with an ol
-                let is_toc = node.attributes()
-                    .any(|a| a.name() == "type" && a.value().contains("toc"));
-
-                if is_toc {
-                    // Find the ol element inside
-                    for child in node.descendants() {
-                        if child.tag_name().name() == "ol" {
-                            return Self::parse_nav_ol(&child, 0);
-                        }
-                    }
-                }
-            }
-        }
+    fn extracted(
+        data: &[u8],
+        rendition: &RenditionSelector,
+        progress: &mut dyn FnMut(LoadProgress),
+    ) -> Result<Self, EpubError> {
+        let mut archive = ZipArchive::new(Cursor::new(data))?;
+        Self::check_drm(&mut archive)?;
+        let package = Self::read_package(&mut archive, rendition)?;
 
-        // Fallback: find any nav > ol structure
-        for node in doc.descendants() {
-            if node.tag_name().name() == "nav" {
-                for child in node.descendants() {
-                    if child.tag_name().name() == "ol" {
-                        let entries = Self::parse_nav_ol(&child, 0);
-                        if !entries.is_empty() {
-                            return entries;
-                        }
-                    }
-                }
-            }
-        }
+        // Extract all resources into memory with security checks
+        let resources = Resources::extract(&mut archive, data.len() as u64)?;
 
-        Vec::new()
+        Self::assemble(package, resources, progress)
     }
 
-    /// Parse an ol element in the NAV document
-    fn parse_nav_ol(ol: &roxmltree::Node, level: usize) -> Vec<TocEntry> {
-        let mut entries = Vec::new();
-
-        for child in ol.children() {
-            if child.tag_name().name() == "li" {
-                if let Some(entry) = Self::parse_nav_li(&child, level) {
-                    entries.push(entry);
-                }
-            }
-        }
+    fn lazy(
+        data: Vec<u8>,
+        cache_bytes: usize,
+        rendition: &RenditionSelector,
+        progress: &mut dyn FnMut(LoadProgress),
+    ) -> Result<Self, EpubError> {
+        let compressed_size = data.len() as u64;
+        let mut archive = ZipArchive::new(Cursor::new(data))?;
+        Self::check_drm(&mut archive)?;
+        let package = Self::read_package(&mut archive, rendition)?;
 
-        entries
-    }
-
-    /// Parse an li element in the NAV document
-    fn parse_nav_li(li: &roxmltree::Node, level: usize) -> Option<TocEntry> {
-        let mut href = String::new();
-        let mut label = String::new();
-        let mut children = Vec::new();
-
-        for child in li.children() {
-            match child.tag_name().name() {
-                "a" => {
-                    href = child.attribute("href").unwrap_or("").to_string();
-                    // Get text content recursively
-                    label = Self::get_text_content(&child);
-                }
-                "span" => {
-                    if label.is_empty() {
-                        label = Self::get_text_content(&child);
-                    }
-                }
-                "ol" => {
-                    children = Self::parse_nav_ol(&child, level + 1);
-                }
-                _ => {}
-            }
-        }
+        let resources = Resources::lazy(archive, compressed_size, cache_bytes)?;
 
-        if !label.is_empty() || !href.is_empty() {
-            Some(TocEntry {
-                id: format!("toc-{}-{}", level, href.replace(['/', '#', '.'], "-")),
-                href,
-                label: label.trim().to_string(),
-                level,
-                children,
-            })
-        } else {
-            None
-        }
+        Self::assemble(package, resources, progress)
     }
 
-    /// Get text content from a node recursively
-    fn get_text_content(node: &roxmltree::Node) -> String {
-        let mut text = String::new();
-        for child in node.children() {
-            if child.is_text() {
-                if let Some(t) = child.text() {
-                    text.push_str(t);
-                }
-            } else {
-                text.push_str(&Self::get_text_content(&child));
-            }
+    /// Fail with the scheme when the book is DRM-protected
+    fn check_drm<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Result<(), EpubError> {
+        match drm::detect(archive) {
+            Some(scheme) => Err(EpubError::DrmProtected(scheme)),
+            None => Ok(()),
         }
-        text
     }
 
-    /// Parse EPUB 2 NCX Document
-    fn parse_ncx_document(content: &str) -> Vec<TocEntry> {
-        let doc = match roxmltree::Document::parse(content) {
-            Ok(d) => d,
-            Err(_) => return Vec::new(),
-        };
+    /// Read container.xml and the OPF of the rendition `selector` picks
+    fn read_package<R: Read + Seek>(
+        archive: &mut ZipArchive<R>,
+        selector: &RenditionSelector,
+    ) -> Result<SelectedPackage, EpubError> {
+        // Read container.xml to find the OPF files
+        let container = Self::read_file(archive, epub_core::container::CONTAINER_PATH)?;
+        let renditions = epub_core::find_rootfiles(&container)?;
+        let rendition_index = selector
+            .select(&renditions)
+            .ok_or(EpubError::RenditionNotFound {
+                index: selector.index.unwrap_or_default(),
+                count: renditions.len(),
+            })?;
+        let opf_path = &renditions[rendition_index].full_path;
+        let opf_dir = epub_core::path::opf_dir(opf_path);
 
-        // Find navMap element
-        for node in doc.descendants() {
-            if node.tag_name().name() == "navMap" {
-                return Self::parse_ncx_nav_map(&node, 0);
-            }
-        }
+        // Read and parse OPF
+        let opf_content = Self::read_file(archive, opf_path)?;
+        let opf = epub_core::parse_opf(&opf_content)?;
 
-        Vec::new()
+        Ok(SelectedPackage {
+            opf,
+            opf_dir,
+            renditions,
+            rendition_index,
+        })
     }
 
-    /// Parse navMap element in NCX
-    fn parse_ncx_nav_map(nav_map: &roxmltree::Node, level: usize) -> Vec<TocEntry> {
-        let mut entries = Vec::new();
+    /// Build the book from its package and files
+    fn assemble(
+        package: SelectedPackage,
+        resources: Resources,
+        progress: &mut dyn FnMut(LoadProgress),
+    ) -> Result<Self, EpubError> {
+        let SelectedPackage {
+            mut opf,
+            opf_dir,
+            renditions,
+            rendition_index,
+        } = package;
+        let mut warnings = Vec::new();
 
-        for child in nav_map.children() {
-            if child.tag_name().name() == "navPoint" {
-                if let Some(entry) = Self::parse_ncx_nav_point(&child, level) {
-                    entries.push(entry);
-                }
+        // Parse ToC from NAV or NCX document
+        let toc = match &opf.toc_doc {
+            TocDocInfo::Nav { href } | TocDocInfo::Ncx { href } => {
+                let full_path = resolve_href(&opf_dir, href);
+                let is_nav = matches!(opf.toc_doc, TocDocInfo::Nav { .. });
+                let toc = resources
+                    .with(&full_path, |bytes| {
+                        std::str::from_utf8(bytes).ok().map(|content| {
+                            if is_nav {
+                                epub_core::parse_nav_document(content)
+                            } else {
+                                epub_core::parse_ncx_document(content)
+                            }
+                        })
+                    })?
+                    .flatten();
+
+                toc.unwrap_or_else(|| {
+                    let message = format!("ToC document '{}' missing or not UTF-8", full_path);
+                    crate::console_log(&format!("[EPUB] {}", message));
+                    warnings.push(ParseWarning::new(WarningCode::TocMissing, message, href));
+                    Vec::new()
+                })
             }
-        }
-
-        entries
-    }
+            TocDocInfo::None => Vec::new(),
+        };
 
-    /// Parse navPoint element in NCX
-    fn parse_ncx_nav_point(nav_point: &roxmltree::Node, level: usize) -> Option<TocEntry> {
-        let id = nav_point.attribute("id").unwrap_or("").to_string();
-        let mut label = String::new();
-        let mut href = String::new();
-        let mut children = Vec::new();
+        // Generate ToC from spine when the book has none
+        let toc = if toc.is_empty() {
+            epub_core::toc_from_spine(&opf.spine)
+        } else {
+            toc
+        };
 
-        for child in nav_point.children() {
-            match child.tag_name().name() {
-                "navLabel" => {
-                    // Find text element inside navLabel
-                    for sub in child.descendants() {
-                        if sub.tag_name().name() == "text" {
-                            if let Some(text) = sub.text() {
-                                label = text.trim().to_string();
-                            }
-                        }
-                    }
-                }
-                "content" => {
-                    href = child.attribute("src").unwrap_or("").to_string();
-                }
-                "navPoint" => {
-                    // Nested navPoint
-                    if let Some(entry) = Self::parse_ncx_nav_point(&child, level + 1) {
-                        children.push(entry);
-                    }
-                }
-                _ => {}
+        // Pre-paginated items declare their page size in their markup
+        for item in &mut opf.spine {
+            if item.layout_in(&opf.rendition) != Layout::PrePaginated {
+                continue;
             }
+            let viewport = resources
+                .with(&resolve_href(&opf_dir, &item.href), |bytes| {
+                    std::str::from_utf8(bytes)
+                        .ok()
+                        .and_then(epub_core::find_viewport)
+                })?
+                .flatten();
+            item.viewport = viewport.or(opf.rendition.viewport);
         }
 
-        if !label.is_empty() || !href.is_empty() {
-            Some(TocEntry {
-                id: if id.is_empty() {
-                    format!("ncx-{}-{}", level, href.replace(['/', '#', '.'], "-"))
-                } else {
-                    id
-                },
-                href,
-                label,
-                level,
-                children,
-            })
-        } else {
-            None
-        }
-    }
+        let fonts = resources
+            .with(fonts::ENCRYPTION_PATH, |bytes| {
+                std::str::from_utf8(bytes)
+                    .ok()
+                    .map(|xml| ObfuscatedFonts::parse(xml, opf.metadata.identifier.as_deref()))
+            })?
+            .flatten()
+            .unwrap_or_default();
 
-    /// Generate ToC from spine when no NAV/NCX is available
-    fn generate_toc_from_spine(spine: &[SpineItem]) -> Vec<TocEntry> {
-        spine.iter().enumerate()
-            .filter(|(_, item)| item.linear)
-            .map(|(i, item)| TocEntry {
-                id: format!("spine-{}", i),
+        // Checksum spine items and split oversize ones into virtual chunks
+        let options = ChunkOptions::default();
+        let mut chunks = Vec::new();
+        let mut chunk_html = HashMap::new();
+        let mut checksums = Vec::new();
+        let total = opf.spine.len();
+        for (spine_index, item) in opf.spine.iter().enumerate() {
+            progress(LoadProgress {
+                loaded: spine_index,
+                total,
+            });
+            let Some((checksum, split)) =
+                resources.with(&resolve_href(&opf_dir, &item.href), |bytes| {
+                    let split = std::str::from_utf8(bytes)
+                        .ok()
+                        .map(|html| chunk_spine_item(&item.href, html, &options));
+                    (content_checksum(bytes), split)
+                })?
+            else {
+                warnings.push(ParseWarning::new(
+                    WarningCode::SpineItemMissing,
+                    format!("Spine item '{}' is not in the archive", item.href),
+                    &item.href,
+                ));
+                continue;
+            };
+            checksums.push(ChapterChecksum {
+                spine_index,
                 href: item.href.clone(),
-                label: format!("Chapter {}", i + 1),
-                level: 0,
-                children: Vec::new(),
-            })
-            .collect()
-    }
-
-    /// Find the path to the OPF file from container.xml
-    fn find_opf_path(archive: &mut ZipArchive<Cursor<&[u8]>>) -> Result<String, EpubError> {
-        let container_content = Self::read_file(archive, "META-INF/container.xml")?;
-        let doc = roxmltree::Document::parse(&container_content)
-            .map_err(|e| EpubError::XmlError(e.to_string()))?;
-
-        // Find <rootfile> element
-        for node in doc.descendants() {
-            if node.tag_name().name() == "rootfile" {
-                if let Some(path) = node.attribute("full-path") {
-                    return Ok(path.to_string());
-                }
+                checksum,
+            });
+            let Some(split) = split else {
+                warnings.push(ParseWarning::new(
+                    WarningCode::SpineItemNotUtf8,
+                    format!("Spine item '{}' is not UTF-8", item.href),
+                    &item.href,
+                ));
+                continue;
+            };
+
+            for chunk in split {
+                chunks.push(ChapterChunk {
+                    href: chunk.href.clone(),
+                    parent_href: item.href.clone(),
+                    spine_index,
+                    index: chunk.index,
+                });
+                chunk_html.insert(chunk.href, chunk.html);
             }
         }
+        progress(LoadProgress {
+            loaded: total,
+            total,
+        });
+
+        // Books without an identifier are named after their content, so
+        // editions that share a title don't share an id
+        let fingerprint = book_fingerprint(&checksums);
+        let (id, id_source) = match &opf.metadata.identifier {
+            Some(identifier) => (identifier.clone(), IdSource::Identifier),
+            None => (
+                format!("book-{}", &fingerprint[..16]),
+                IdSource::ContentHash,
+            ),
+        };
 
-        Err(EpubError::InvalidEpub("Could not find OPF path in container.xml".to_string()))
+        Ok(Self {
+            id,
+            id_source,
+            fingerprint,
+            metadata: opf.metadata,
+            spine: opf.spine,
+            toc,
+            manifest: opf.manifest,
+            chunks,
+            rendition: opf.rendition,
+            checksums,
+            renditions,
+            rendition_index,
+            warnings,
+            resources,
+            fonts,
+            optimized_images: OptimizedImages::new(),
+            chunk_html,
+            opf_dir,
+        })
     }
 
     /// Read a file from the ZIP archive
-    fn read_file(archive: &mut ZipArchive<Cursor<&[u8]>>, path: &str) -> Result<String, EpubError> {
+    fn read_file<R: Read + Seek>(
+        archive: &mut ZipArchive<R>,
+        path: &str,
+    ) -> Result<String, EpubError> {
         let mut file = archive.by_name(path)?;
         let mut content = String::new();
         file.read_to_string(&mut content)?;
@@ -602,20 +657,67 @@ impl EpubBook {
     pub fn to_parsed_book(&self) -> ParsedBook {
         ParsedBook {
             id: self.id.clone(),
+            id_source: self.id_source,
+            fingerprint: self.fingerprint.clone(),
             metadata: self.metadata.clone(),
             spine: self.spine.clone(),
             toc: self.toc.clone(),
+            chunks: self.chunks.clone(),
+            rendition: self.rendition.clone(),
+            checksums: self.checksums.clone(),
+            renditions: self.renditions.clone(),
+            rendition_index: self.rendition_index,
         }
     }
 
     /// Get chapter content
+    ///
+    /// Accepts spine hrefs and the synthetic hrefs of [`ChapterChunk`]s.
     pub fn get_chapter_content(&self, href: &str) -> Result<ChapterContent, EpubError> {
-        let full_path = self.resolve_path(href);
-        let html = self.get_resource_as_string(&full_path)?;
+        self.get_chapter_content_with(href, &ChapterOptions::default())
+    }
+
+    /// Get chapter content with processing options applied
+    ///
+    /// Anchors are injected before sanitizing, so their ids match the
+    /// server's for the same source markup. `css` and `images` keep the
+    /// hrefs as written in the chapter.
+    pub fn get_chapter_content_with(
+        &self,
+        href: &str,
+        options: &ChapterOptions,
+    ) -> Result<ChapterContent, EpubError> {
+        let html = self.chapter_html(href)?;
+        let html = if options.inject_anchors {
+            epub_core::inject_anchors(&html)
+        } else {
+            html
+        };
+        let html = if options.aria_roles {
+            epub_core::add_aria_roles(&html)
+        } else {
+            html
+        };
+        let html = if options.sanitize {
+            epub_core::sanitize_html(&html)
+        } else {
+            html
+        };
 
         // Parse HTML to extract CSS and image references
         let (css, images) = parser::extract_resources(&html);
 
+        let html = match self.url_prefix(options) {
+            Some(prefix) => {
+                let chapter = parse_chunk_href(href).map_or(href, |(parent, _)| parent);
+                let chapter_path = self.resolve_path(chapter);
+                epub_core::rewrite_urls(&html, parent_dir(&chapter_path), |path| {
+                    format!("{}{}", prefix, self.resource_href(path))
+                })
+            }
+            None => html,
+        };
+
         Ok(ChapterContent {
             href: href.to_string(),
             html,
@@ -624,45 +726,284 @@ impl EpubBook {
         })
     }
 
+    /// The note with id `fragment` in the document at `href`
+    ///
+    /// `href` is the target of a note reference; a fragment on it is
+    /// ignored. The note is found following `epub:type="noteref"` semantics
+    /// (see [`epub_core::extract_note`]) and is always sanitized; of the
+    /// options, only URL rewriting applies. `None` if the document has no
+    /// element with the id.
+    pub fn get_footnote(
+        &self,
+        href: &str,
+        fragment: &str,
+        options: &ChapterOptions,
+    ) -> Result<Option<Footnote>, EpubError> {
+        let href = href.split('#').next().unwrap_or_default();
+        let path = self.resolve_path(href);
+        let html = self.get_resource_as_string(&path)?;
+        let Some(mut note) = epub_core::extract_note(&html, fragment) else {
+            return Ok(None);
+        };
+        note.html = epub_core::sanitize_html(&note.html);
+        if let Some(prefix) = self.url_prefix(options) {
+            note.html = epub_core::rewrite_urls(&note.html, parent_dir(&path), |path| {
+                format!("{}{}", prefix, self.resource_href(path))
+            });
+        }
+        Ok(Some(Footnote {
+            href: href.to_string(),
+            note,
+        }))
+    }
+
+    /// Prefix to rewrite chapter URLs with, if `options` ask for rewriting
+    fn url_prefix(&self, options: &ChapterOptions) -> Option<String> {
+        match &options.url_prefix {
+            Some(prefix) => Some(prefix.clone()),
+            None => options.rewrite_urls.then(|| format!("epub://{}/", self.id)),
+        }
+    }
+
+    /// The top-level blocks of a chapter, for estimating its layout
+    ///
+    /// Accepts the same hrefs as [`Self::get_chapter_content`]. Images
+    /// without pixel `width`/`height` attributes are sized from their files'
+    /// headers. With `metrics`, each block also gets an estimated height.
+    pub fn chapter_blocks(
+        &self,
+        href: &str,
+        metrics: Option<&BlockMetrics>,
+    ) -> Result<Vec<Block>, EpubError> {
+        let mut blocks = block_map(&self.chapter_html(href)?);
+
+        let chapter = parse_chunk_href(href).map_or(href, |(parent, _)| parent);
+        let chapter_path = self.resolve_path(chapter);
+        for image in blocks.iter_mut().flat_map(|block| &mut block.images) {
+            if image.src.is_empty() || (image.width.is_some() && image.height.is_some()) {
+                continue;
+            }
+            let path = resolve_href(parent_dir(&chapter_path), &image.src);
+            // Missing or unreadable images keep their unknown size
+            if let Ok(Some(Some((width, height)))) = self.resources.with(&path, image_size) {
+                image.width = Some(width);
+                image.height = Some(height);
+            }
+        }
+
+        if let Some(metrics) = metrics {
+            estimate_heights(&mut blocks, metrics);
+        }
+        Ok(blocks)
+    }
+
+    /// Raw HTML of a spine item or chunk
+    fn chapter_html(&self, href: &str) -> Result<String, EpubError> {
+        match self.chunk_html.get(href) {
+            Some(html) => Ok(html.clone()),
+            None if parse_chunk_href(href).is_some() => {
+                Err(EpubError::ResourceNotFound(href.to_string()))
+            }
+            None => self.get_resource_as_string(&self.resolve_path(href)),
+        }
+    }
+
     /// Get a resource by href
+    ///
+    /// Obfuscated fonts are returned de-obfuscated.
     pub fn get_resource(&self, href: &str) -> Result<Vec<u8>, EpubError> {
         let full_path = self.resolve_path(href);
-        self.resources.get(&full_path)
-            .cloned()
-            .ok_or_else(|| EpubError::ResourceNotFound(href.to_string()))
+        let mut bytes = self
+            .resources
+            .with(&full_path, <[u8]>::to_vec)?
+            .ok_or_else(|| EpubError::ResourceNotFound(href.to_string()))?;
+        self.fonts.deobfuscate(&full_path, &mut bytes);
+        Ok(bytes)
+    }
+
+    /// Get a resource, downscaling or transcoding images as `options` ask
+    ///
+    /// Other resources, and images the options leave as they are, are
+    /// returned as stored. See the `images` module.
+    pub fn get_resource_with(
+        &self,
+        href: &str,
+        options: &ImageOptions,
+    ) -> Result<Vec<u8>, EpubError> {
+        if options.is_none() {
+            return self.get_resource(href);
+        }
+        let path = self.resolve_path(href);
+        self.optimized_images
+            .get(&path, options, || self.get_resource(href))
     }
 
     /// Get a resource as string
     fn get_resource_as_string(&self, path: &str) -> Result<String, EpubError> {
-        let bytes = self.resources.get(path)
-            .ok_or_else(|| EpubError::ResourceNotFound(path.to_string()))?;
-        String::from_utf8(bytes.clone())
+        self.resources
+            .with(path, |bytes| String::from_utf8(bytes.to_vec()))?
+            .ok_or_else(|| EpubError::ResourceNotFound(path.to_string()))?
             .map_err(|e| EpubError::InvalidEpub(format!("Invalid UTF-8: {}", e)))
     }
 
     /// Resolve a relative path to the full path in the archive
-    fn resolve_path(&self, href: &str) -> String {
-        if self.opf_dir.is_empty() {
-            href.to_string()
+    pub(crate) fn resolve_path(&self, href: &str) -> String {
+        resolve_href(&self.opf_dir, href)
+    }
+
+    /// The percent-encoded href `get_resource` takes for an archive path
+    ///
+    /// Paths outside the OPF directory get a leading `/`.
+    fn resource_href(&self, path: &str) -> String {
+        let relative = if self.opf_dir.is_empty() {
+            Some(path)
         } else {
-            format!("{}/{}", self.opf_dir, href)
+            path.strip_prefix(self.opf_dir.as_str())
+                .and_then(|rest| rest.strip_prefix('/'))
+        };
+        match relative {
+            Some(relative) => percent_encode_path(relative),
+            None => format!("/{}", percent_encode_path(path)),
+        }
+    }
+
+    /// Resolver from ToC hrefs to archive paths
+    pub(crate) fn toc_paths(&self) -> TocPaths<'_> {
+        let dir = self
+            .manifest
+            .values()
+            .find(|item| item.has_property("nav"))
+            .or_else(|| {
+                self.manifest
+                    .values()
+                    .find(|item| item.media_type == NCX_MEDIA_TYPE)
+            })
+            .map(|item| parent_dir(&self.resolve_path(&item.href)).to_string())
+            .unwrap_or_else(|| self.opf_dir.clone());
+        let known = self
+            .manifest
+            .values()
+            .map(|item| self.resolve_path(&item.href))
+            .collect();
+        TocPaths {
+            book: self,
+            dir,
+            known,
         }
     }
 
-    /// Get spine index for a given href
+    /// Get spine index for a given href (spine or chunk href)
     pub fn get_spine_index(&self, href: &str) -> Option<usize> {
+        let href = parse_chunk_href(href).map_or(href, |(parent, _)| parent);
         self.spine.iter().position(|item| item.href == href)
     }
 
+    /// Hrefs to read for a spine item, in order
+    ///
+    /// The chunk hrefs for an oversize item, otherwise the item's own href.
+    pub fn reading_hrefs(&self, spine_index: usize) -> Vec<String> {
+        let chunks: Vec<String> = self
+            .chunks
+            .iter()
+            .filter(|c| c.spine_index == spine_index)
+            .map(|c| c.href.clone())
+            .collect();
+        if !chunks.is_empty() {
+            return chunks;
+        }
+        self.spine
+            .get(spine_index)
+            .map(|item| vec![item.href.clone()])
+            .unwrap_or_default()
+    }
+
     /// Get spine item by index
     pub fn get_spine_item(&self, index: usize) -> Option<&SpineItem> {
         self.spine.get(index)
     }
+
+    /// The back-of-book index, read from the spine items marked
+    /// `epub:type="index"`; empty if the book has none
+    pub fn book_index(&self) -> BookIndex {
+        BookIndex::build(&self.spine, &self.opf_dir, |path| {
+            self.get_resource_as_string(path).ok()
+        })
+    }
+
+    /// Terms and definitions of the spine items marked
+    /// `epub:type="glossary"`; empty if the book has none
+    pub fn glossary(&self) -> Glossary {
+        Glossary::build(&self.spine, &self.opf_dir, |path| {
+            self.get_resource_as_string(path).ok()
+        })
+    }
+
+    /// The spine items marked `linear="no"`, which locations and
+    /// progression skip
+    pub fn auxiliary_items(&self) -> Vec<AuxiliaryItem> {
+        if self.spine.iter().all(|item| item.linear) {
+            return Vec::new();
+        }
+        let links = LinkGraph::build(&self.spine, &self.opf_dir, |path| {
+            self.get_resource_as_string(path).ok()
+        });
+        self.spine
+            .iter()
+            .zip(links.chapters)
+            .enumerate()
+            .filter(|(_, (item, _))| !item.linear)
+            .map(|(spine_index, (item, links))| AuxiliaryItem {
+                spine_index,
+                href: item.href.clone(),
+                media_type: item.media_type.clone(),
+                title: toc_label(&self.toc, &item.href),
+                linked_from: links.linked_from,
+            })
+            .collect()
+    }
+}
+
+/// Media type of NCX documents
+const NCX_MEDIA_TYPE: &str = "application/x-dtbncx+xml";
+
+/// Resolves ToC hrefs to the archive paths of the files they point into
+///
+/// Hrefs are relative to the ToC document, except in a ToC generated from
+/// the spine, whose hrefs are relative to the OPF directory.
+pub(crate) struct TocPaths<'a> {
+    book: &'a EpubBook,
+    dir: String,
+    known: HashSet<String>,
+}
+
+impl TocPaths<'_> {
+    /// Archive path of the file `href` points into, without its fragment
+    pub(crate) fn resolve(&self, href: &str) -> String {
+        let file = strip_fragment(href);
+        Some(resolve_href(&self.dir, file))
+            .filter(|path| self.known.contains(path))
+            .unwrap_or_else(|| self.book.resolve_path(file))
+    }
+}
+
+/// Label of the first ToC entry, in document order, pointing into `href`
+fn toc_label(entries: &[TocEntry], href: &str) -> Option<String> {
+    entries.iter().find_map(|entry| {
+        let target = entry.href.split('#').next().unwrap_or_default();
+        if target == href {
+            Some(entry.label.clone())
+        } else {
+            toc_label(&entry.children, href)
+        }
+    })
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
+    use epub_core::path::normalize_path;
+    use std::io::Write;
+    use zip::write::{FileOptions, ZipWriter};
 
     #[test]
     fn test_metadata_default() {
@@ -722,4 +1063,401 @@ mod tests {
         assert_eq!(normalize_path("./a/b"), "a/b");
         assert_eq!(normalize_path("a\\b\\c"), "a/b/c");
     }
+
+    // ========================================================================
+    // Loading Tests
+    // ========================================================================
+
+    pub(crate) fn build_epub(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in files {
+            writer.start_file(*name, FileOptions::default()).unwrap();
+            writer.write_all(content).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    /// One chapter and a plate image
+    pub(crate) fn sample_epub() -> Vec<u8> {
+        build_epub(&[
+            (
+                "META-INF/container.xml",
+                br#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>"#,
+            ),
+            (
+                "OEBPS/content.opf",
+                br#"<?xml version="1.0"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier>urn:test:lazy</dc:identifier>
+    <dc:title>Plates</dc:title>
+  </metadata>
+  <manifest>
+    <item id="ch1" href="ch1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="plate" href="images/plate.jpg" media-type="image/jpeg"/>
+  </manifest>
+  <spine>
+    <itemref idref="ch1"/>
+  </spine>
+</package>"#,
+            ),
+            (
+                "OEBPS/ch1.xhtml",
+                br#"<html><body><p>Plate one</p><img src="images/plate.jpg"/></body></html>"#,
+            ),
+            ("OEBPS/images/plate.jpg", &[0xAB; 4096]),
+        ])
+    }
+
+    #[test]
+    fn test_lazy_load_matches_eager() {
+        let data = sample_epub();
+        let eager = EpubBook::from_bytes(&data).unwrap();
+        // A budget smaller than the image, so it is inflated on every read
+        let lazy = EpubBook::from_bytes_lazy(data, 1024).unwrap();
+
+        assert_eq!(lazy.id, "urn:test:lazy");
+        assert_eq!(lazy.checksums, eager.checksums);
+        assert_eq!(lazy.checksums.len(), 1);
+        assert_eq!(lazy.checksums[0].href, "ch1.xhtml");
+        assert_eq!(
+            lazy.to_parsed_book().toc.len(),
+            eager.to_parsed_book().toc.len()
+        );
+        assert_eq!(
+            lazy.get_chapter_content("ch1.xhtml").unwrap().html,
+            eager.get_chapter_content("ch1.xhtml").unwrap().html
+        );
+        for _ in 0..2 {
+            assert_eq!(
+                lazy.get_resource("images/plate.jpg").unwrap(),
+                eager.get_resource("images/plate.jpg").unwrap()
+            );
+        }
+        assert!(matches!(
+            lazy.get_resource("images/missing.jpg"),
+            Err(EpubError::ResourceNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_drm_protected() {
+        let protected =
+            |path: &str| build_epub(&[("META-INF/container.xml", b"<container/>"), (path, b"{}")]);
+        let lazy = LoadOptions {
+            lazy: true,
+            ..LoadOptions::default()
+        };
+        for options in [LoadOptions::default(), lazy] {
+            // Detected before the missing package would fail the load
+            assert!(matches!(
+                EpubBook::load(protected("META-INF/license.lcpl"), &options),
+                Err(EpubError::DrmProtected(DrmScheme::Lcp))
+            ));
+            assert!(matches!(
+                EpubBook::load(protected("META-INF/rights.xml"), &options),
+                Err(EpubError::DrmProtected(DrmScheme::AdobeAdept))
+            ));
+            assert!(EpubBook::load(sample_epub(), &options).is_ok());
+        }
+        assert_eq!(
+            EpubError::DrmProtected(DrmScheme::AppleFairplay).to_string(),
+            "DRM protected: apple-fairplay"
+        );
+    }
+
+    #[test]
+    fn test_load_options() {
+        let options = LoadOptions {
+            lazy: true,
+            resource_cache_bytes: Some(8192),
+            ..LoadOptions::default()
+        };
+        let book = EpubBook::load(sample_epub(), &options).unwrap();
+        assert!(matches!(book.resources, Resources::Lazy(_)));
+        assert_eq!(book.get_resource("images/plate.jpg").unwrap().len(), 4096);
+
+        let book = EpubBook::load(sample_epub(), &LoadOptions::default()).unwrap();
+        assert!(matches!(book.resources, Resources::Eager(_)));
+    }
+
+    #[test]
+    fn test_chapter_rewrite_and_sanitize() {
+        let data = build_epub(&[
+            (
+                "META-INF/container.xml",
+                br#"<container xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles><rootfile full-path="OEBPS/content.opf"/></rootfiles>
+</container>"#,
+            ),
+            (
+                "OEBPS/content.opf",
+                br#"<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/"><dc:identifier>b1</dc:identifier></metadata>
+  <manifest><item id="ch1" href="Text/ch1.xhtml" media-type="application/xhtml+xml"/></manifest>
+  <spine><itemref idref="ch1"/></spine>
+</package>"#,
+            ),
+            (
+                "OEBPS/Text/ch1.xhtml",
+                br#"<html><head><link rel="stylesheet" href="../Styles/a.css"/><script>x()</script></head>
+<body><p onclick="y()"><img src="../Images/plate%201.jpg"/><a href="../../extra.xhtml#n">n</a></p></body></html>"#,
+            ),
+        ]);
+        let book = EpubBook::from_bytes(&data).unwrap();
+
+        let options = ChapterOptions {
+            sanitize: true,
+            rewrite_urls: true,
+            ..Default::default()
+        };
+        let chapter = book
+            .get_chapter_content_with("Text/ch1.xhtml", &options)
+            .unwrap();
+        assert!(chapter.html.contains(r#"href="epub://b1/Styles/a.css""#));
+        assert!(chapter
+            .html
+            .contains(r#"<p><img src="epub://b1/Images/plate%201.jpg"/>"#));
+        assert!(chapter.html.contains(r#"href="epub://b1//extra.xhtml#n""#));
+        assert!(!chapter.html.contains("script"));
+        assert_eq!(chapter.images, vec!["../Images/plate%201.jpg"]);
+        // Rewritten hrefs resolve like manifest hrefs
+        assert_eq!(
+            book.resolve_path("Images/plate%201.jpg"),
+            "OEBPS/Images/plate 1.jpg"
+        );
+        assert_eq!(book.resolve_path("/extra.xhtml"), "extra.xhtml");
+
+        let options = ChapterOptions {
+            url_prefix: Some("app://res/".into()),
+            ..Default::default()
+        };
+        let chapter = book
+            .get_chapter_content_with("Text/ch1.xhtml", &options)
+            .unwrap();
+        assert!(chapter
+            .html
+            .contains(r#"src="app://res/Images/plate%201.jpg""#));
+        assert!(chapter.html.contains("<script>"));
+    }
+
+    #[test]
+    fn test_get_footnote() {
+        let data = build_epub(&[
+            (
+                "META-INF/container.xml",
+                br#"<container xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles><rootfile full-path="OEBPS/content.opf"/></rootfiles>
+</container>"#,
+            ),
+            (
+                "OEBPS/content.opf",
+                br#"<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/"><dc:identifier>b1</dc:identifier></metadata>
+  <manifest><item id="notes" href="Text/notes.xhtml" media-type="application/xhtml+xml"/></manifest>
+  <spine><itemref idref="notes"/></spine>
+</package>"#,
+            ),
+            (
+                "OEBPS/Text/notes.xhtml",
+                br#"<html><body><section epub:type="endnotes"><ol>
+<li epub:type="endnote" id="n1"><p onclick="x()">See <img src="../Images/fig.png"/>.</p></li>
+</ol></section></body></html>"#,
+            ),
+        ]);
+        let book = EpubBook::from_bytes(&data).unwrap();
+
+        let options = ChapterOptions {
+            rewrite_urls: true,
+            ..Default::default()
+        };
+        let note = book
+            .get_footnote("Text/notes.xhtml#n1", "n1", &options)
+            .unwrap()
+            .unwrap();
+        assert_eq!(note.href, "Text/notes.xhtml");
+        assert_eq!(note.note.kind.as_deref(), Some("endnote"));
+        assert_eq!(
+            note.note.html,
+            r#"<p>See <img src="epub://b1/Images/fig.png"/>.</p>"#
+        );
+        assert_eq!(note.note.text, "See .");
+
+        let missing = book.get_footnote("Text/notes.xhtml", "n2", &options);
+        assert_eq!(missing.unwrap(), None);
+        assert!(book.get_footnote("missing.xhtml", "n1", &options).is_err());
+    }
+
+    #[test]
+    fn test_chapter_blocks() {
+        let data = build_epub(&[
+            (
+                "META-INF/container.xml",
+                br#"<container xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles><rootfile full-path="OEBPS/content.opf"/></rootfiles>
+</container>"#,
+            ),
+            (
+                "OEBPS/content.opf",
+                br#"<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/"><dc:identifier>b1</dc:identifier></metadata>
+  <manifest>
+    <item id="ch1" href="Text/ch1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="map" href="Images/map.gif" media-type="image/gif"/>
+  </manifest>
+  <spine><itemref idref="ch1"/></spine>
+</package>"#,
+            ),
+            (
+                "OEBPS/Text/ch1.xhtml",
+                br#"<html><body><h2>Maps</h2><p><img src="../Images/map.gif"/></p><img src="../Images/lost.png"/></body></html>"#,
+            ),
+            ("OEBPS/Images/map.gif", b"GIF89a\x20\x03\x58\x02"),
+        ]);
+        let book = EpubBook::from_bytes(&data).unwrap();
+
+        let blocks = book.chapter_blocks("Text/ch1.xhtml", None).unwrap();
+        let tags: Vec<&str> = blocks.iter().map(|b| b.tag.as_str()).collect();
+        assert_eq!(tags, vec!["h2", "p", "img"]);
+        // Sized from the GIF header; a missing file stays unknown
+        assert_eq!(blocks[1].images[0].width, Some(800));
+        assert_eq!(blocks[1].images[0].height, Some(600));
+        assert_eq!(blocks[2].images[0].width, None);
+        assert!(blocks.iter().all(|b| b.estimated_height.is_none()));
+
+        let metrics = BlockMetrics {
+            width: 400.0,
+            ..Default::default()
+        };
+        let blocks = book
+            .chapter_blocks("Text/ch1.xhtml", Some(&metrics))
+            .unwrap();
+        // 800x600 scaled to the 400px column, plus a 16px margin
+        assert_eq!(blocks[1].estimated_height, Some(316.0));
+    }
+
+    #[test]
+    fn test_fixed_layout_viewports() {
+        let data = build_epub(&[
+            (
+                "META-INF/container.xml",
+                br#"<container xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles><rootfile full-path="content.opf"/></rootfiles>
+</container>"#,
+            ),
+            (
+                "content.opf",
+                br#"<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+  <metadata>
+    <meta property="rendition:layout">pre-paginated</meta>
+    <meta property="rendition:viewport">width=600, height=800</meta>
+  </metadata>
+  <manifest>
+    <item id="p1" href="p1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="p2" href="p2.xhtml" media-type="application/xhtml+xml"/>
+    <item id="notes" href="notes.xhtml" media-type="application/xhtml+xml"/>
+  </manifest>
+  <spine>
+    <itemref idref="p1" properties="page-spread-right"/>
+    <itemref idref="p2"/>
+    <itemref idref="notes" properties="rendition:layout-reflowable"/>
+  </spine>
+</package>"#,
+            ),
+            (
+                "p1.xhtml",
+                br#"<html><head><meta name="viewport" content="width=1200, height=1600"/></head><body/></html>"#,
+            ),
+            ("p2.xhtml", b"<html><head></head><body/></html>"),
+            (
+                "notes.xhtml",
+                br#"<html><head><meta name="viewport" content="width=1, height=1"/></head><body/></html>"#,
+            ),
+        ]);
+
+        let book = EpubBook::from_bytes(&data).unwrap().to_parsed_book();
+        assert_eq!(book.rendition.layout, Layout::PrePaginated);
+
+        let viewports: Vec<Option<(u32, u32)>> = book
+            .spine
+            .iter()
+            .map(|item| item.viewport.map(|v| (v.width, v.height)))
+            .collect();
+        // Pages without their own size fall back to the book's; reflowable
+        // items have none
+        assert_eq!(viewports, vec![Some((1200, 1600)), Some((600, 800)), None]);
+        assert_eq!(book.spine[0].page_spread, Some(PageSpread::Right));
+    }
+
+    #[test]
+    fn test_load_rendition() {
+        let package = |title: &str, chapter: &str| {
+            format!(
+                r#"<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier>urn:test:renditions</dc:identifier><dc:title>{title}</dc:title>
+  </metadata>
+  <manifest><item id="c" href="{chapter}" media-type="application/xhtml+xml"/></manifest>
+  <spine><itemref idref="c"/></spine>
+</package>"#
+            )
+        };
+        let english = package("Text", "ch1.xhtml");
+        let spanish = package("Texto", "cap1.xhtml");
+        let data = build_epub(&[
+            (
+                "META-INF/container.xml",
+                br#"<container xmlns="urn:oasis:names:tc:opendocument:xmlns:container"
+    xmlns:rendition="http://www.idpf.org/2013/rendition">
+  <rootfiles>
+    <rootfile full-path="en/content.opf" rendition:language="en"/>
+    <rootfile full-path="es/content.opf" rendition:language="es"/>
+  </rootfiles>
+</container>"#,
+            ),
+            ("en/content.opf", english.as_bytes()),
+            ("en/ch1.xhtml", b"<html><body><p>Hello</p></body></html>"),
+            ("es/content.opf", spanish.as_bytes()),
+            ("es/cap1.xhtml", b"<html><body><p>Hola</p></body></html>"),
+        ]);
+
+        let book = EpubBook::from_bytes(&data).unwrap().to_parsed_book();
+        assert_eq!(book.metadata.title, "Text");
+        assert_eq!(book.renditions.len(), 2);
+        assert_eq!(book.rendition_index, 0);
+
+        let options = LoadOptions {
+            lazy: true,
+            rendition: RenditionSelector {
+                language: Some("es".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let book = EpubBook::load(data.clone(), &options).unwrap();
+        assert_eq!(book.rendition_index, 1);
+        assert_eq!(book.metadata.title, "Texto");
+        assert!(book
+            .get_chapter_content("cap1.xhtml")
+            .unwrap()
+            .html
+            .contains("Hola"));
+
+        let options = LoadOptions {
+            rendition: RenditionSelector {
+                index: Some(2),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(matches!(
+            EpubBook::load(data, &options),
+            Err(EpubError::RenditionNotFound { index: 2, count: 2 })
+        ));
+    }
 }
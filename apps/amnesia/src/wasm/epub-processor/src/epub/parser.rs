@@ -47,35 +47,9 @@ pub fn extract_resources(html: &str) -> (Vec<String>, Vec<String>) {
     (css_refs, image_refs)
 }
 
-/// Normalize whitespace in text content
-pub fn normalize_text(text: &str) -> String {
-    text.split_whitespace().collect::<Vec<_>>().join(" ")
-}
-
-/// Extract plain text from HTML for search indexing
-pub fn extract_plain_text(html: &str) -> String {
-    // Remove script and style content
-    let no_script = Regex::new(r"(?s)<script[^>]*>.*?</script>").unwrap()
-        .replace_all(html, "");
-    let no_style = Regex::new(r"(?s)<style[^>]*>.*?</style>").unwrap()
-        .replace_all(&no_script, "");
-
-    // Remove all HTML tags
-    let no_tags = Regex::new(r"<[^>]+>").unwrap()
-        .replace_all(&no_style, " ");
-
-    // Decode common HTML entities
-    let decoded = no_tags
-        .replace("&nbsp;", " ")
-        .replace("&amp;", "&")
-        .replace("&lt;", "<")
-        .replace("&gt;", ">")
-        .replace("&quot;", "\"")
-        .replace("&#39;", "'")
-        .replace("&apos;", "'");
-
-    normalize_text(&decoded)
-}
+// Text extraction is shared with the server so indexes built on either
+// side produce the same offsets
+pub use search_core::{extract_plain_text, normalize_text};
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,283 @@
+//! Resource storage for loaded books
+//!
+//! Books are either extracted up front, with every file held decompressed,
+//! or loaded lazily: the archive stays in memory compressed and files are
+//! inflated when requested, with the most recently used kept in a
+//! byte-bounded LRU cache. Lazy loading keeps image-heavy books (art books,
+//! comics) from holding every decoded image at once.
+
+use std::collections::{HashMap, VecDeque};
+use std::io::{Cursor, Read, Seek};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use epub_core::path::normalize_path;
+use zip::ZipArchive;
+
+use super::{
+    validate_zip_path, EpubError, MAX_DECOMPRESSION_RATIO, MAX_FILE_COUNT, MAX_TOTAL_SIZE,
+};
+
+/// A book's files keyed by normalized archive path
+pub(super) enum Resources {
+    /// Every file decompressed at load time
+    Eager(HashMap<String, Vec<u8>>),
+    /// Files decompressed on demand
+    Lazy(LazyResources),
+}
+
+impl Resources {
+    /// Decompress every file, enforcing the archive limits
+    pub fn extract<R: Read + Seek>(
+        archive: &mut ZipArchive<R>,
+        compressed_size: u64,
+    ) -> Result<Self, EpubError> {
+        let mut budget = SizeBudget::new(archive.len(), compressed_size)?;
+        let mut resources = HashMap::new();
+
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i)?;
+            if !file.is_file() {
+                continue;
+            }
+            let name = checked_name(file.name())?;
+
+            let mut content = Vec::new();
+            file.read_to_end(&mut content)?;
+            budget.add(content.len() as u64)?;
+
+            resources.insert(name, content);
+        }
+
+        Ok(Self::Eager(resources))
+    }
+
+    /// Keep the archive, indexing its files for on-demand reads
+    ///
+    /// The archive limits are checked against the sizes the archive
+    /// declares; reads reject files that inflate past them.
+    pub fn lazy(
+        mut archive: ZipArchive<Cursor<Vec<u8>>>,
+        compressed_size: u64,
+        cache_bytes: usize,
+    ) -> Result<Self, EpubError> {
+        let mut budget = SizeBudget::new(archive.len(), compressed_size)?;
+        let mut entries = HashMap::new();
+
+        for index in 0..archive.len() {
+            // Raw access reads the directory entry without inflating
+            let file = archive.by_index_raw(index)?;
+            if !file.is_file() {
+                continue;
+            }
+            let name = checked_name(file.name())?;
+            budget.add(file.size())?;
+
+            entries.insert(
+                name,
+                Entry {
+                    index,
+                    size: file.size(),
+                },
+            );
+        }
+
+        Ok(Self::Lazy(LazyResources {
+            archive: Mutex::new(archive),
+            entries,
+            cache: Mutex::new(ResourceCache::new(cache_bytes)),
+        }))
+    }
+
+    /// Run `f` on a file's bytes; `None` if the archive has no such file
+    pub fn with<T>(&self, path: &str, f: impl FnOnce(&[u8]) -> T) -> Result<Option<T>, EpubError> {
+        match self {
+            Self::Eager(resources) => Ok(resources.get(path).map(|bytes| f(bytes.as_slice()))),
+            Self::Lazy(lazy) => lazy.with(path, f),
+        }
+    }
+}
+
+/// Where a file lives in the archive
+struct Entry {
+    index: usize,
+    /// Uncompressed size the archive declares
+    size: u64,
+}
+
+/// A compressed archive and its recently decompressed files
+pub(super) struct LazyResources {
+    archive: Mutex<ZipArchive<Cursor<Vec<u8>>>>,
+    entries: HashMap<String, Entry>,
+    cache: Mutex<ResourceCache>,
+}
+
+impl LazyResources {
+    fn with<T>(&self, path: &str, f: impl FnOnce(&[u8]) -> T) -> Result<Option<T>, EpubError> {
+        let Some(entry) = self.entries.get(path) else {
+            return Ok(None);
+        };
+
+        if let Some(bytes) = lock(&self.cache).get(path) {
+            return Ok(Some(f(bytes)));
+        }
+
+        // Inflate without holding the cache so hits are not blocked
+        let bytes = self.inflate(path, entry)?;
+        let result = f(&bytes);
+        lock(&self.cache).insert(path.to_string(), bytes);
+        Ok(Some(result))
+    }
+
+    /// Decompress a file, rejecting one larger than its declared size
+    fn inflate(&self, path: &str, entry: &Entry) -> Result<Vec<u8>, EpubError> {
+        let mut archive = lock(&self.archive);
+        let file = archive.by_index(entry.index)?;
+
+        let mut bytes = Vec::with_capacity(entry.size as usize);
+        file.take(entry.size + 1).read_to_end(&mut bytes)?;
+        if bytes.len() as u64 > entry.size {
+            return Err(EpubError::SecurityViolation(format!(
+                "File larger than its declared size: {}",
+                path
+            )));
+        }
+
+        Ok(bytes)
+    }
+}
+
+/// Decompressed files within a byte budget, least recently used first
+pub(super) struct ResourceCache {
+    capacity: usize,
+    size: usize,
+    entries: VecDeque<(String, Vec<u8>)>,
+}
+
+impl ResourceCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            size: 0,
+            entries: VecDeque::new(),
+        }
+    }
+
+    /// A cached file, marking it most recently used
+    pub fn get(&mut self, path: &str) -> Option<&[u8]> {
+        let position = self.entries.iter().position(|(cached, _)| cached == path)?;
+        let entry = self.entries.remove(position)?;
+        self.entries.push_back(entry);
+        self.entries.back().map(|(_, bytes)| bytes.as_slice())
+    }
+
+    /// Cache a file, evicting the least recently used past the budget
+    ///
+    /// Files larger than the whole budget are not cached.
+    pub fn insert(&mut self, path: String, bytes: Vec<u8>) {
+        if bytes.len() > self.capacity {
+            return;
+        }
+        // Another reader may have inflated the same file meanwhile
+        if let Some(position) = self.entries.iter().position(|(cached, _)| *cached == path) {
+            if let Some((_, replaced)) = self.entries.remove(position) {
+                self.size -= replaced.len();
+            }
+        }
+
+        self.size += bytes.len();
+        self.entries.push_back((path, bytes));
+        while self.size > self.capacity {
+            match self.entries.pop_front() {
+                Some((_, evicted)) => self.size -= evicted.len(),
+                None => break,
+            }
+        }
+    }
+}
+
+/// Running decompressed size, checked against the archive limits
+struct SizeBudget {
+    compressed: u64,
+    total: u64,
+}
+
+impl SizeBudget {
+    fn new(file_count: usize, compressed: u64) -> Result<Self, EpubError> {
+        // Check file count limit
+        if file_count > MAX_FILE_COUNT {
+            return Err(EpubError::SecurityViolation(format!(
+                "Too many files in archive: {} (max {})",
+                file_count, MAX_FILE_COUNT
+            )));
+        }
+        Ok(Self {
+            compressed,
+            total: 0,
+        })
+    }
+
+    fn add(&mut self, size: u64) -> Result<(), EpubError> {
+        self.total += size;
+
+        // Security: Check for zip bomb (decompression ratio)
+        if self.compressed > 0 && self.total > self.compressed * MAX_DECOMPRESSION_RATIO {
+            return Err(EpubError::SecurityViolation(format!(
+                "Decompression ratio exceeded: {}:1 (max {}:1)",
+                self.total / self.compressed,
+                MAX_DECOMPRESSION_RATIO
+            )));
+        }
+
+        // Security: Check total size limit
+        if self.total > MAX_TOTAL_SIZE {
+            return Err(EpubError::SecurityViolation(format!(
+                "Total decompressed size exceeded: {} bytes (max {} bytes)",
+                self.total, MAX_TOTAL_SIZE
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Validate and normalize an archive file name
+fn checked_name(raw_name: &str) -> Result<String, EpubError> {
+    validate_zip_path(raw_name)?;
+    Ok(normalize_path(raw_name))
+}
+
+/// Lock a mutex, recovering from a panic on another thread
+///
+/// The archive and cache hold no invariants a panicking reader can break.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resource_cache_evicts_least_recently_used() {
+        let mut cache = ResourceCache::new(10);
+        cache.insert("a".to_string(), vec![0; 4]);
+        cache.insert("b".to_string(), vec![0; 4]);
+
+        // Reading "a" makes "b" the eviction candidate
+        assert!(cache.get("a").is_some());
+        cache.insert("c".to_string(), vec![0; 4]);
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("a").is_some());
+        assert!(cache.get("c").is_some());
+        assert_eq!(cache.size, 8);
+
+        // Replacing an entry does not count it twice
+        cache.insert("c".to_string(), vec![0; 5]);
+        assert_eq!(cache.size, 9);
+
+        // Files over the budget are never cached
+        cache.insert("huge".to_string(), vec![0; 11]);
+        assert!(cache.get("huge").is_none());
+        assert_eq!(cache.size, 9);
+    }
+}
@@ -0,0 +1,474 @@
+//! Readium Web Publication Manifest export
+//!
+//! [`EpubBook::to_webpub_manifest`] describes a loaded book as a
+//! [Readium WebPub manifest](https://readium.org/webpub-manifest/), so
+//! Readium navigators can render it with the processor as their fetcher.
+//! The reading order holds the linear spine items; non-linear items and the
+//! rest of the manifest are listed as resources.
+//!
+//! Every href is the percent-encoded archive path relative to the OPF
+//! directory, which is what `getResource` and `getChapter` take; ToC hrefs
+//! are resolved from the ToC document, so they match the reading order.
+
+use std::collections::HashSet;
+
+use epub_core::path::strip_fragment;
+use serde::Serialize;
+
+use super::{EpubBook, Layout, PageProgression, PageSpread, TocEntry, TocPaths};
+
+/// `@context` of a WebPub manifest
+const CONTEXT: &str = "https://readium.org/webpub-manifest/context.jsonld";
+
+/// Profile for publications converted from EPUB
+const EPUB_PROFILE: &str = "https://readium.org/webpub-manifest/profiles/epub";
+
+/// A Readium Web Publication Manifest
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebPubManifest {
+    #[serde(rename = "@context")]
+    pub context: String,
+    pub metadata: WebPubMetadata,
+    /// Empty; the host adds a `self` link where it serves the manifest
+    pub links: Vec<WebPubLink>,
+    pub reading_order: Vec<WebPubLink>,
+    pub resources: Vec<WebPubLink>,
+    pub toc: Vec<WebPubLink>,
+}
+
+/// Publication metadata; unset fields are left out
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebPubMetadata {
+    #[serde(rename = "@type")]
+    pub schema_type: String,
+    pub conforms_to: String,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identifier: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub author: Vec<Contributor>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub translator: Vec<Contributor>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub editor: Vec<Contributor>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub illustrator: Vec<Contributor>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub narrator: Vec<Contributor>,
+    /// Creators in other roles
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub contributor: Vec<Contributor>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub publisher: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// `dc:date`, as written
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub published: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub subject: Vec<String>,
+    /// Direction pages turn in
+    pub reading_progression: PageProgression,
+    pub presentation: Presentation,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accessibility: Option<WebPubAccessibility>,
+}
+
+/// A person or organization credited for the book
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Contributor {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort_as: Option<String>,
+    /// MARC relator code, for creators listed under `contributor`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+}
+
+/// How the publication is laid out (EPUB profile)
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Presentation {
+    /// `fixed` or `reflowable`
+    pub layout: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub orientation: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spread: Option<String>,
+}
+
+/// Accessibility metadata, in the manifest's vocabulary
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebPubAccessibility {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub conforms_to: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub certification: Option<Certification>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub access_mode: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub access_mode_sufficient: Vec<Vec<String>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub feature: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub hazard: Vec<String>,
+}
+
+/// Who certified the book's accessibility
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Certification {
+    pub certified_by: String,
+}
+
+/// A link to a resource of the publication
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebPubLink {
+    pub href: String,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rel: Option<String>,
+    /// Page size of a pre-paginated item, in CSS pixels
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    #[serde(skip_serializing_if = "LinkProperties::is_empty")]
+    pub properties: LinkProperties,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<WebPubLink>,
+}
+
+/// Rendering hints of a link
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkProperties {
+    /// Side of a spread the page goes on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<PageSpread>,
+    /// `fixed` or `reflowable`, where the item overrides the book
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layout: Option<String>,
+    /// When the item is shown in a spread, where it overrides the book
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spread: Option<String>,
+    /// Content needing support from the navigator: `mathml`, `svg`, `js`,
+    /// `remote-resources`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub contains: Vec<String>,
+}
+
+impl LinkProperties {
+    fn is_empty(&self) -> bool {
+        self.page.is_none()
+            && self.layout.is_none()
+            && self.spread.is_none()
+            && self.contains.is_empty()
+    }
+}
+
+impl EpubBook {
+    /// Describe the book as a Readium Web Publication Manifest
+    pub fn to_webpub_manifest(&self) -> WebPubManifest {
+        let toc = self.webpub_toc(&self.toc, &self.toc_paths());
+        let mut reading_order = Vec::new();
+        let mut in_spine = HashSet::new();
+        let mut non_linear = Vec::new();
+        for item in &self.spine {
+            let path = self.resolve_path(&item.href);
+            in_spine.insert(path.clone());
+            let href = self.resource_href(&path);
+            let mut link = WebPubLink {
+                title: toc_title(&toc, &href),
+                href,
+                media_type: Some(item.media_type.clone()),
+                ..Default::default()
+            };
+            if let Some(layout) = item
+                .layout
+                .filter(|&layout| layout != self.rendition.layout)
+            {
+                link.properties.layout = Some(layout_name(layout).to_string());
+            }
+            link.properties.page = item.page_spread;
+            link.properties.spread = item.spread.clone();
+            if item.layout_in(&self.rendition) == Layout::PrePaginated {
+                link.width = item.viewport.map(|viewport| viewport.width);
+                link.height = item.viewport.map(|viewport| viewport.height);
+            }
+            if let Some(manifest_item) = self.manifest.get(&item.id) {
+                link.properties.contains = contains(manifest_item.properties.as_deref());
+            }
+            if item.linear {
+                reading_order.push(link);
+            } else {
+                non_linear.push(link);
+            }
+        }
+
+        let cover = self
+            .metadata
+            .cover_href
+            .as_deref()
+            .map(|href| self.resolve_path(href));
+        let mut resources: Vec<(String, WebPubLink)> = self
+            .manifest
+            .values()
+            .map(|item| (self.resolve_path(&item.href), item))
+            .filter(|(path, _)| !in_spine.contains(path))
+            .map(|(path, item)| {
+                let rel = if cover.as_deref() == Some(path.as_str()) {
+                    Some("cover".to_string())
+                } else if item.has_property("nav") {
+                    Some("contents".to_string())
+                } else {
+                    None
+                };
+                let link = WebPubLink {
+                    href: self.resource_href(&path),
+                    media_type: Some(item.media_type.clone()),
+                    rel,
+                    properties: LinkProperties {
+                        contains: contains(item.properties.as_deref()),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                };
+                (path, link)
+            })
+            .collect();
+        // The manifest is unordered; sort for stable output
+        resources.sort_by(|a, b| a.0.cmp(&b.0));
+
+        WebPubManifest {
+            context: CONTEXT.to_string(),
+            metadata: self.webpub_metadata(),
+            links: Vec::new(),
+            reading_order,
+            resources: non_linear
+                .into_iter()
+                .chain(resources.into_iter().map(|(_, link)| link))
+                .collect(),
+            toc,
+        }
+    }
+
+    fn webpub_metadata(&self) -> WebPubMetadata {
+        let metadata = &self.metadata;
+        let mut manifest = WebPubMetadata {
+            schema_type: "http://schema.org/Book".to_string(),
+            conforms_to: EPUB_PROFILE.to_string(),
+            title: metadata.title.clone(),
+            identifier: metadata.identifier.clone(),
+            language: metadata.language.clone(),
+            author: Vec::new(),
+            translator: Vec::new(),
+            editor: Vec::new(),
+            illustrator: Vec::new(),
+            narrator: Vec::new(),
+            contributor: Vec::new(),
+            publisher: metadata.publisher.clone(),
+            description: metadata.description.clone(),
+            published: metadata.date.clone(),
+            subject: metadata.subjects.clone(),
+            reading_progression: self.rendition.page_progression(),
+            presentation: Presentation {
+                layout: layout_name(self.rendition.layout).to_string(),
+                orientation: self.rendition.orientation.clone(),
+                spread: self.rendition.spread.clone(),
+            },
+            accessibility: None,
+        };
+
+        for creator in &metadata.creators {
+            let mut contributor = Contributor {
+                name: creator.name.clone(),
+                sort_as: creator.file_as.clone(),
+                role: None,
+            };
+            let list = match creator.role.as_deref() {
+                None | Some("aut") => &mut manifest.author,
+                Some("trl") => &mut manifest.translator,
+                Some("edt") => &mut manifest.editor,
+                Some("ill") => &mut manifest.illustrator,
+                Some("nrt") => &mut manifest.narrator,
+                Some(role) => {
+                    contributor.role = Some(role.to_string());
+                    &mut manifest.contributor
+                }
+            };
+            list.push(contributor);
+        }
+
+        let a11y = &metadata.accessibility;
+        if *a11y != Default::default() {
+            manifest.accessibility = Some(WebPubAccessibility {
+                conforms_to: a11y.conforms_to.clone(),
+                certification: a11y
+                    .certified_by
+                    .clone()
+                    .map(|certified_by| Certification { certified_by }),
+                summary: a11y.summary.clone(),
+                access_mode: a11y.access_modes.clone(),
+                access_mode_sufficient: a11y.access_modes_sufficient.clone(),
+                feature: a11y.features.clone(),
+                hazard: a11y.hazards.clone(),
+            });
+        }
+        manifest
+    }
+
+    /// ToC entries as links, with hrefs resolved like the reading order's
+    fn webpub_toc(&self, entries: &[TocEntry], paths: &TocPaths) -> Vec<WebPubLink> {
+        entries
+            .iter()
+            .map(|entry| {
+                let fragment = entry.href.find('#').map_or("", |i| &entry.href[i..]);
+                let path = paths.resolve(&entry.href);
+                WebPubLink {
+                    href: format!("{}{}", self.resource_href(&path), fragment),
+                    title: Some(entry.label.clone()),
+                    children: self.webpub_toc(&entry.children, paths),
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+}
+
+/// Title of the first ToC link, in document order, pointing into `href`
+fn toc_title(toc: &[WebPubLink], href: &str) -> Option<String> {
+    toc.iter().find_map(|link| {
+        if strip_fragment(&link.href) == href {
+            link.title.clone()
+        } else {
+            toc_title(&link.children, href)
+        }
+    })
+}
+
+fn layout_name(layout: Layout) -> &'static str {
+    match layout {
+        Layout::Reflowable => "reflowable",
+        Layout::PrePaginated => "fixed",
+    }
+}
+
+/// `contains` values for a manifest item's `properties`
+fn contains(properties: Option<&str>) -> Vec<String> {
+    properties
+        .unwrap_or_default()
+        .split_whitespace()
+        .filter_map(|property| match property {
+            "mathml" => Some("mathml"),
+            "svg" => Some("svg"),
+            "scripted" => Some("js"),
+            "remote-resources" => Some("remote-resources"),
+            _ => None,
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epub::tests::build_epub;
+
+    #[test]
+    fn test_to_webpub_manifest() {
+        let book = EpubBook::from_bytes(&build_epub(&[
+            (
+                "META-INF/container.xml",
+                br#"<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container"><rootfiles><rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/></rootfiles></container>"#,
+            ),
+            (
+                "OEBPS/content.opf",
+                br#"<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+<metadata xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:opf="http://www.idpf.org/2007/opf">
+<dc:title>Ships</dc:title><dc:identifier>urn:isbn:1</dc:identifier><dc:language>en</dc:language>
+<dc:creator opf:role="aut" opf:file-as="Doe, Jane">Jane Doe</dc:creator>
+<dc:creator opf:role="trl">Tom Roe</dc:creator>
+<dc:creator opf:role="bkp">Bindery</dc:creator>
+</metadata>
+<manifest>
+<item id="nav" href="Text/nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+<item id="ch1" href="Text/ch%201.xhtml" media-type="application/xhtml+xml" properties="mathml"/>
+<item id="notes" href="Text/notes.xhtml" media-type="application/xhtml+xml"/>
+<item id="cover" href="Images/cover.jpg" media-type="image/jpeg" properties="cover-image"/>
+</manifest>
+<spine page-progression-direction="rtl"><itemref idref="ch1" properties="rendition:spread-none"/><itemref idref="notes" linear="no"/></spine></package>"#,
+            ),
+            (
+                "OEBPS/Text/nav.xhtml",
+                br#"<html xmlns:epub="http://www.idpf.org/2007/ops"><body><nav epub:type="toc"><ol>
+<li><a href="ch%201.xhtml">One</a><ol><li><a href="ch%201.xhtml#s1">Part</a></li></ol></li>
+</ol></nav></body></html>"#,
+            ),
+            ("OEBPS/Text/ch 1.xhtml", b"<html><body><p>One</p></body></html>"),
+            ("OEBPS/Text/notes.xhtml", b"<html><body><p>Note</p></body></html>"),
+            ("OEBPS/Images/cover.jpg", b"\xFF\xD8\xFF"),
+        ]))
+        .unwrap();
+
+        let manifest = book.to_webpub_manifest();
+        assert_eq!(manifest.context, CONTEXT);
+
+        let metadata = &manifest.metadata;
+        assert_eq!(metadata.title, "Ships");
+        assert_eq!(metadata.identifier.as_deref(), Some("urn:isbn:1"));
+        assert_eq!(
+            metadata.author,
+            vec![Contributor {
+                name: "Jane Doe".to_string(),
+                sort_as: Some("Doe, Jane".to_string()),
+                role: None,
+            }]
+        );
+        assert_eq!(metadata.translator[0].name, "Tom Roe");
+        assert_eq!(metadata.contributor[0].role.as_deref(), Some("bkp"));
+        assert_eq!(metadata.presentation.layout, "reflowable");
+        assert_eq!(metadata.reading_progression, PageProgression::Rtl);
+        assert!(metadata.accessibility.is_none());
+
+        assert_eq!(manifest.reading_order.len(), 1);
+        let chapter = &manifest.reading_order[0];
+        assert_eq!(chapter.href, "Text/ch%201.xhtml");
+        assert_eq!(chapter.media_type.as_deref(), Some("application/xhtml+xml"));
+        assert_eq!(chapter.title.as_deref(), Some("One"));
+        assert_eq!(chapter.properties.contains, vec!["mathml".to_string()]);
+        assert_eq!(chapter.properties.spread.as_deref(), Some("none"));
+
+        let resources: Vec<(&str, Option<&str>)> = manifest
+            .resources
+            .iter()
+            .map(|link| (link.href.as_str(), link.rel.as_deref()))
+            .collect();
+        assert_eq!(
+            resources,
+            vec![
+                ("Text/notes.xhtml", None),
+                ("Images/cover.jpg", Some("cover")),
+                ("Text/nav.xhtml", Some("contents")),
+            ]
+        );
+
+        // Resolved from the nav document's directory
+        let entry = &manifest.toc[0];
+        assert_eq!(entry.href, "Text/ch%201.xhtml");
+        assert_eq!(entry.children[0].href, "Text/ch%201.xhtml#s1");
+        assert_eq!(entry.children[0].title.as_deref(), Some("Part"));
+    }
+}
@@ -0,0 +1,230 @@
+//! Errors as the bindings report them
+//!
+//! Every [`ProcessorError`] maps to an [`ErrorInfo`]: a stable
+//! [`ErrorCode`] to branch on, the message, and the values the message was
+//! built from, so callers never have to parse messages. The browser
+//! bindings throw it as an `Error` with `code` and `context` properties;
+//! the Node.js bindings throw it as JSON in the error message.
+
+use serde::Serialize;
+
+#[cfg(feature = "cfi")]
+use crate::cfi::CfiError;
+use crate::epub::{DrmScheme, EpubError};
+use crate::processor::{ProcessorError, ResourceLimit};
+#[cfg(feature = "search")]
+use crate::search::SearchError;
+
+/// What went wrong, stable across releases
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    BookNotFound,
+    IndexNotBuilt,
+    /// The file is not a readable ZIP archive
+    InvalidArchive,
+    IoError,
+    /// The archive is not a valid EPUB (missing or malformed package files)
+    InvalidEpub,
+    ResourceNotFound,
+    /// A path in the archive escapes it
+    SecurityViolation,
+    DrmProtected,
+    RenditionNotFound,
+    /// An image could not be decoded or re-encoded
+    InvalidImage,
+    InvalidCfi,
+    CfiResolutionFailed,
+    SpineItemNotFound,
+    /// An argument or options object could not be used
+    InvalidArgument,
+    SearchFailed,
+    InvalidSearchIndex,
+    InvalidHyphenationPatterns,
+    IdCollision,
+    ResourceLimitExceeded,
+    /// A bug in the processor, such as a result that cannot be serialized
+    Internal,
+}
+
+/// Values an error's message was built from; only the fields that apply
+/// to its code are set
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorContext {
+    /// Id of the book that is not loaded or has no search index
+    /// (`BOOK_NOT_FOUND`, `INDEX_NOT_BUILT`), or already used by a different
+    /// book (`ID_COLLISION`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub book_id: Option<String>,
+    /// Href or archive path that was not found (`RESOURCE_NOT_FOUND`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub href: Option<String>,
+    /// `DRM_PROTECTED`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scheme: Option<DrmScheme>,
+    /// Requested rendition and the number the book has
+    /// (`RENDITION_NOT_FOUND`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<usize>,
+    /// The limit, what the call needed and the configured maximum
+    /// (`RESOURCE_LIMIT_EXCEEDED`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<ResourceLimit>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requested: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<usize>,
+}
+
+/// An error as thrown to JavaScript
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorInfo {
+    pub code: ErrorCode,
+    /// Human-readable description, not meant to be matched on
+    pub message: String,
+    pub context: ErrorContext,
+}
+
+impl ErrorInfo {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            context: ErrorContext::default(),
+        }
+    }
+
+    fn with_context(mut self, context: ErrorContext) -> Self {
+        self.context = context;
+        self
+    }
+}
+
+impl From<&ProcessorError> for ErrorInfo {
+    fn from(e: &ProcessorError) -> Self {
+        let info = Self::new(code(e), e.to_string());
+        let context = match e {
+            ProcessorError::Epub(EpubError::ResourceNotFound(href)) => ErrorContext {
+                href: Some(href.clone()),
+                ..Default::default()
+            },
+            ProcessorError::Epub(EpubError::DrmProtected(scheme)) => ErrorContext {
+                scheme: Some(*scheme),
+                ..Default::default()
+            },
+            ProcessorError::Epub(EpubError::RenditionNotFound { index, count }) => ErrorContext {
+                index: Some(*index),
+                count: Some(*count),
+                ..Default::default()
+            },
+            ProcessorError::BookNotFound(book_id) | ProcessorError::IdCollision(book_id) => {
+                ErrorContext {
+                    book_id: Some(book_id.clone()),
+                    ..Default::default()
+                }
+            }
+            #[cfg(feature = "search")]
+            ProcessorError::IndexNotBuilt(book_id) => ErrorContext {
+                book_id: Some(book_id.clone()),
+                ..Default::default()
+            },
+            ProcessorError::LimitExceeded {
+                limit,
+                requested,
+                max,
+            } => ErrorContext {
+                limit: Some(*limit),
+                requested: Some(*requested),
+                max: Some(*max),
+                ..Default::default()
+            },
+            _ => return info,
+        };
+        info.with_context(context)
+    }
+}
+
+impl From<ProcessorError> for ErrorInfo {
+    fn from(e: ProcessorError) -> Self {
+        Self::from(&e)
+    }
+}
+
+fn code(e: &ProcessorError) -> ErrorCode {
+    match e {
+        ProcessorError::BookNotFound(_) => ErrorCode::BookNotFound,
+        #[cfg(feature = "search")]
+        ProcessorError::IndexNotBuilt(_) => ErrorCode::IndexNotBuilt,
+        ProcessorError::Epub(e) => match e {
+            EpubError::ZipError(_) => ErrorCode::InvalidArchive,
+            EpubError::IoError(_) => ErrorCode::IoError,
+            EpubError::InvalidEpub(_) | EpubError::XmlError(_) => ErrorCode::InvalidEpub,
+            EpubError::ResourceNotFound(_) => ErrorCode::ResourceNotFound,
+            EpubError::SecurityViolation(_) => ErrorCode::SecurityViolation,
+            EpubError::DrmProtected(_) => ErrorCode::DrmProtected,
+            EpubError::RenditionNotFound { .. } => ErrorCode::RenditionNotFound,
+            EpubError::InvalidImage(_) => ErrorCode::InvalidImage,
+        },
+        #[cfg(feature = "cfi")]
+        ProcessorError::Cfi(e) => match e {
+            CfiError::InvalidFormat(_) => ErrorCode::InvalidCfi,
+            CfiError::ResolutionFailed(_) => ErrorCode::CfiResolutionFailed,
+            CfiError::SpineNotFound(_) => ErrorCode::SpineItemNotFound,
+            CfiError::InvalidArgument(_) => ErrorCode::InvalidArgument,
+        },
+        #[cfg(feature = "search")]
+        ProcessorError::Search(e) => match e {
+            SearchError::IndexBuildError(_) | SearchError::SearchFailed(_) => {
+                ErrorCode::SearchFailed
+            }
+            SearchError::InvalidIndex(_) | SearchError::WrongBook { .. } => {
+                ErrorCode::InvalidSearchIndex
+            }
+        },
+        ProcessorError::Hyphenation(_) => ErrorCode::InvalidHyphenationPatterns,
+        ProcessorError::IdCollision(_) => ErrorCode::IdCollision,
+        ProcessorError::LimitExceeded { .. } => ErrorCode::ResourceLimitExceeded,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::HyphenationError;
+
+    #[test]
+    fn test_error_info() {
+        let info = ErrorInfo::from(ProcessorError::Epub(EpubError::DrmProtected(
+            DrmScheme::Lcp,
+        )));
+        assert_eq!(info.code, ErrorCode::DrmProtected);
+        assert_eq!(info.message, "DRM protected: lcp");
+        assert_eq!(info.context.scheme, Some(DrmScheme::Lcp));
+
+        let info = ErrorInfo::from(ProcessorError::LimitExceeded {
+            limit: ResourceLimit::Books,
+            requested: 3,
+            max: 2,
+        });
+        assert_eq!(info.code, ErrorCode::ResourceLimitExceeded);
+        assert_eq!(info.context.limit, Some(ResourceLimit::Books));
+        assert_eq!(info.context.max, Some(2));
+
+        let info = ErrorInfo::from(ProcessorError::Epub(EpubError::ResourceNotFound(
+            "ch9.xhtml".into(),
+        )));
+        assert_eq!(info.code, ErrorCode::ResourceNotFound);
+        assert_eq!(info.context.href.as_deref(), Some("ch9.xhtml"));
+
+        let info = ErrorInfo::from(ProcessorError::BookNotFound("b1".into()));
+        assert_eq!(info.code, ErrorCode::BookNotFound);
+        assert_eq!(info.context.book_id.as_deref(), Some("b1"));
+
+        let info = ErrorInfo::from(ProcessorError::Hyphenation(HyphenationError::Empty));
+        assert_eq!(info.context, ErrorContext::default());
+    }
+}
@@ -0,0 +1,52 @@
+//! Events raised while the processor works
+//!
+//! A host registers one listener (`onEvent` in both bindings) to drive
+//! progress UI and logging, instead of polling each call. Nothing is
+//! collected until a listener is registered; from then on calls queue
+//! their events on the [`Processor`](crate::Processor), and the bindings
+//! hand them to the listener before the call returns.
+
+use serde::Serialize;
+
+use crate::epub::{LoadProgress, ParseWarning};
+use crate::processor::ResourceLimit;
+#[cfg(feature = "search")]
+use crate::search::IndexProgress;
+
+/// Share of a limit in use at which [`ProcessorEvent::MemoryPressure`] is
+/// raised, in percent
+pub const PRESSURE_PERCENT: usize = 80;
+
+/// Something the host may want to show or log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum ProcessorEvent {
+    /// Spine items read while loading a book, raised before each item and
+    /// once all are read; the book's id is only known once it has loaded
+    LoadProgress(LoadProgress),
+    /// A problem that did not stop a book loading
+    ParseWarning {
+        book_id: String,
+        #[serde(flatten)]
+        warning: ParseWarning,
+    },
+    /// Chapters indexed by a search index build
+    #[cfg(feature = "search")]
+    IndexProgress {
+        book_id: String,
+        #[serde(flatten)]
+        progress: IndexProgress,
+    },
+    /// A call left `used` at or above [`PRESSURE_PERCENT`] of a configured
+    /// limit; unload books or drop search indexes before the next call
+    /// fails
+    MemoryPressure {
+        limit: ResourceLimit,
+        used: usize,
+        max: usize,
+    },
+}
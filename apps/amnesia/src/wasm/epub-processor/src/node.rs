@@ -0,0 +1,1046 @@
+//! Node.js bindings (N-API)
+//!
+//! Enabled by the `node` feature for Electron and CLI import scripts:
+//!
+//! ```text
+//! cargo build --release --no-default-features --features node,full
+//! cp target/release/libepub_processor.so epub-processor.node
+//! ```
+//!
+//! The `EpubProcessor` class mirrors the browser API. `loadBook` and
+//! `buildSearchIndex` return Promises backed by libuv worker threads, so no
+//! async runtime is involved; everything else is synchronous. Values cross
+//! the boundary as plain JS objects with the same camelCase shapes, and byte
+//! arrays as `Buffer`s. Events reach the `onEvent` listener through a
+//! threadsafe function, so load progress arrives while the worker thread
+//! parses.
+//!
+//! Every call locks the processor, so one instance can be used from the
+//! libuv pool and from `worker_threads` without corrupting it. Workers
+//! share one processor through `share()` and `EpubProcessor.attach(id)`,
+//! since the addon, unlike JS objects, is loaded once per process; a
+//! `BookHandle` from `openBook` reads one book without taking the lock, so
+//! workers reading different books do not wait on each other.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+
+use epub_core::ChapterChecksum;
+use napi::bindgen_prelude::{AsyncTask, Buffer};
+use napi::threadsafe_function::{
+    ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode,
+};
+use napi::{Env, JsFunction, JsUnknown, Task};
+use napi_derive::napi;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+#[cfg(feature = "cfi")]
+use crate::cfi::{DomPosition, TextQuoteAnchor, DEFAULT_CHARS_PER_LOCATION};
+use crate::epub::{
+    BlockMetrics, ChapterOptions, EpubBook, EpubError, HtmlExportOptions, ImageOptions,
+    LoadOptions, ParsedBook,
+};
+use crate::error::{ErrorCode, ErrorInfo};
+use crate::events::ProcessorEvent;
+use crate::processor::{
+    Processor, ProcessorError, ProcessorLimits, ResourceLimit, DEFAULT_INDEX_MATCHES,
+};
+#[cfg(feature = "search")]
+use crate::search::{IndexOptions, MAX_FUZZINESS};
+use crate::text::{self, TextFolding};
+
+/// EPUB Processor - main interface for working with EPUB files
+#[napi]
+pub struct EpubProcessor {
+    inner: Arc<Mutex<Processor>>,
+    /// Called with each event, see `onEvent`
+    listener: Option<Listener>,
+}
+
+/// Calls the `onEvent` callback on the JavaScript thread from any thread
+type Listener = ThreadsafeFunction<ProcessorEvent, ErrorStrategy::Fatal>;
+
+/// Processors shared with other worker threads, by share id; a processor
+/// is dropped once no `EpubProcessor` holds it
+static SHARED: Mutex<Option<HashMap<u32, Weak<Mutex<Processor>>>>> = Mutex::new(None);
+static NEXT_SHARE_ID: AtomicU32 = AtomicU32::new(1);
+
+#[napi]
+impl EpubProcessor {
+    /// `limits` is an optional ProcessorLimits object, e.g.
+    /// `{ maxBooks: 2 }`; calls that would go over one fail instead
+    #[napi(constructor)]
+    pub fn new(limits: Option<serde_json::Value>) -> napi::Result<Self> {
+        let limits: ProcessorLimits = from_optional(limits)?;
+        Ok(Self {
+            inner: Arc::new(Mutex::new(Processor::with_limits(limits))),
+            listener: None,
+        })
+    }
+
+    /// An id other worker threads pass to `EpubProcessor.attach` to use this
+    /// processor and its loaded books
+    #[napi]
+    pub fn share(&self) -> u32 {
+        let id = NEXT_SHARE_ID.fetch_add(1, Ordering::Relaxed);
+        let mut shared = SHARED.lock().unwrap_or_else(|e| e.into_inner());
+        let shared = shared.get_or_insert_with(HashMap::new);
+        shared.retain(|_, processor| processor.strong_count() > 0);
+        shared.insert(id, Arc::downgrade(&self.inner));
+        id
+    }
+
+    /// The processor another worker thread shared with `share()`
+    ///
+    /// Calls from every thread are serialized by the processor's lock.
+    /// Events go to the listener registered on the instance that made the
+    /// call.
+    #[napi(factory)]
+    pub fn attach(id: u32) -> napi::Result<Self> {
+        let shared = SHARED.lock().unwrap_or_else(|e| e.into_inner());
+        let inner = shared
+            .as_ref()
+            .and_then(|shared| shared.get(&id))
+            .and_then(Weak::upgrade)
+            .ok_or_else(|| {
+                error_reason(&ErrorInfo::new(
+                    ErrorCode::InvalidArgument,
+                    format!("No shared processor {}", id),
+                ))
+            })?;
+        Ok(Self {
+            inner,
+            listener: None,
+        })
+    }
+
+    /// A handle reading one loaded book without locking the processor
+    ///
+    /// The handle keeps the book readable after `unloadBook` until it is
+    /// garbage collected.
+    #[napi]
+    pub fn open_book(&self, book_id: String) -> napi::Result<BookHandle> {
+        Ok(BookHandle {
+            book: self.lock()?.book_handle(&book_id).map_err(node_error)?,
+        })
+    }
+
+    /// Call `callback` with every event the processor raises (load and
+    /// index progress, parse warnings, memory pressure); `null` stops
+    ///
+    /// The callback does not keep the process alive.
+    #[napi(ts_args_type = "callback: ((event: ProcessorEvent) => void) | null")]
+    pub fn on_event(&mut self, env: Env, callback: Option<JsFunction>) -> napi::Result<()> {
+        let listener = match callback {
+            Some(callback) => {
+                let mut listener: Listener = callback.create_threadsafe_function(
+                    0,
+                    |ctx: ThreadSafeCallContext<ProcessorEvent>| {
+                        Ok(vec![ctx.env.to_js_value(&ctx.value)?])
+                    },
+                )?;
+                listener.unref(&env)?;
+                Some(listener)
+            }
+            None => None,
+        };
+        self.lock()?.set_events_enabled(listener.is_some());
+        self.listener = listener;
+        Ok(())
+    }
+
+    /// Load an EPUB file; resolves to a ParsedBook object
+    ///
+    /// `options` is an optional LoadOptions object, e.g. `{ lazy: true }`.
+    #[napi(ts_return_type = "Promise<ParsedBook>")]
+    pub fn load_book(
+        &self,
+        data: Buffer,
+        options: Option<serde_json::Value>,
+    ) -> napi::Result<AsyncTask<LoadBook>> {
+        Ok(AsyncTask::new(LoadBook {
+            processor: Arc::clone(&self.inner),
+            listener: self.listener.clone(),
+            data: data.to_vec(),
+            options: from_optional(options)?,
+        }))
+    }
+
+    /// Get a chapter's content by href
+    #[napi]
+    pub fn get_chapter(
+        &self,
+        book_id: String,
+        href: String,
+        options: Option<serde_json::Value>,
+    ) -> napi::Result<serde_json::Value> {
+        let options: ChapterOptions = from_optional(options)?;
+        to_json(
+            &self
+                .lock()?
+                .get_chapter(&book_id, &href, &options)
+                .map_err(node_error)?,
+        )
+    }
+
+    /// Get a chapter with `<mark>` elements around each occurrence of
+    /// `terms`, to show search hits
+    #[napi]
+    pub fn get_chapter_with_highlights(
+        &self,
+        book_id: String,
+        href: String,
+        terms: Vec<String>,
+        options: Option<serde_json::Value>,
+    ) -> napi::Result<serde_json::Value> {
+        let options: ChapterOptions = from_optional(options)?;
+        to_json(
+            &self
+                .lock()?
+                .get_chapter_with_highlights(&book_id, &href, &terms, &options)
+                .map_err(node_error)?,
+        )
+    }
+
+    /// Get the note with id `fragment_id` in the document at `href`,
+    /// sanitized, or `null`
+    #[napi]
+    pub fn get_footnote(
+        &self,
+        book_id: String,
+        href: String,
+        fragment_id: String,
+        options: Option<serde_json::Value>,
+    ) -> napi::Result<serde_json::Value> {
+        let options: ChapterOptions = from_optional(options)?;
+        to_json(
+            &self
+                .lock()?
+                .get_footnote(&book_id, &href, &fragment_id, &options)
+                .map_err(node_error)?,
+        )
+    }
+
+    /// Get a chapter's block map, with estimated heights when `metrics` is
+    /// given
+    #[napi]
+    pub fn get_chapter_blocks(
+        &self,
+        book_id: String,
+        href: String,
+        metrics: Option<serde_json::Value>,
+    ) -> napi::Result<serde_json::Value> {
+        let metrics: Option<BlockMetrics> = from_optional(metrics)?;
+        to_json(
+            &self
+                .lock()?
+                .get_chapter_blocks(&book_id, &href, metrics.as_ref())
+                .map_err(node_error)?,
+        )
+    }
+
+    /// Get a resource (image, CSS, etc.) by href
+    ///
+    /// `options` is an optional ImageOptions object, e.g.
+    /// `{ maxDimension: 1600, format: "webp" }`, for downscaled images.
+    #[napi]
+    pub fn get_resource(
+        &self,
+        book_id: String,
+        href: String,
+        options: Option<serde_json::Value>,
+    ) -> napi::Result<Buffer> {
+        let options: ImageOptions = from_optional(options)?;
+        Ok(self
+            .lock()?
+            .get_resource_with(&book_id, &href, &options)
+            .map_err(node_error)?
+            .into())
+    }
+
+    /// Export the book as one self-contained HTML document, with optional
+    /// HtmlExportOptions; returns `{ html, omitted }`
+    #[napi]
+    pub fn export_html(
+        &self,
+        book_id: String,
+        options: Option<serde_json::Value>,
+    ) -> napi::Result<serde_json::Value> {
+        let options: HtmlExportOptions = from_optional(options)?;
+        to_json(
+            &self
+                .lock()?
+                .export_html(&book_id, &options)
+                .map_err(node_error)?,
+        )
+    }
+
+    /// Describe the book as a Readium Web Publication Manifest
+    #[napi]
+    pub fn to_webpub_manifest(&self, book_id: String) -> napi::Result<serde_json::Value> {
+        to_json(
+            &self
+                .lock()?
+                .to_webpub_manifest(&book_id)
+                .map_err(node_error)?,
+        )
+    }
+
+    /// Compare a loaded book with the `checksums` of an earlier version and
+    /// list the chapters that need reindexing or reanchoring
+    #[napi]
+    pub fn changed_chapters(
+        &self,
+        book_id: String,
+        previous: serde_json::Value,
+    ) -> napi::Result<serde_json::Value> {
+        let previous: Vec<ChapterChecksum> = from_json(previous)?;
+        to_json(
+            &self
+                .lock()?
+                .changed_chapters(&book_id, &previous)
+                .map_err(node_error)?,
+        )
+    }
+
+    /// Load Knuth–Liang hyphenation patterns for a language
+    #[napi]
+    pub fn load_hyphenation_patterns(
+        &self,
+        language: String,
+        patterns: String,
+        exceptions: Option<String>,
+        left_min: Option<u32>,
+        right_min: Option<u32>,
+    ) -> napi::Result<u32> {
+        let count = self
+            .lock()?
+            .load_hyphenation_patterns(
+                &language,
+                &patterns,
+                exceptions.as_deref(),
+                left_min.map(|n| n as usize),
+                right_min.map(|n| n as usize),
+            )
+            .map_err(node_error)?;
+        Ok(count as u32)
+    }
+
+    /// Languages with loaded or built-in hyphenation patterns
+    #[napi]
+    pub fn get_hyphenation_languages(&self) -> napi::Result<Vec<String>> {
+        Ok(self.lock()?.hyphenation_languages())
+    }
+
+    /// Hyphenation points of a word as UTF-16 offsets
+    #[napi]
+    pub fn hyphenate(&self, language: String, word: String) -> napi::Result<Vec<u32>> {
+        Ok(self
+            .lock()?
+            .hyphenate(&language, &word)
+            .into_iter()
+            .map(|offset| offset as u32)
+            .collect())
+    }
+
+    /// Get a chapter with soft hyphens at its hyphenation points, for
+    /// justified text
+    #[napi]
+    pub fn hyphenate_chapter(
+        &self,
+        book_id: String,
+        href: String,
+        language: Option<String>,
+        options: Option<serde_json::Value>,
+    ) -> napi::Result<serde_json::Value> {
+        let options: ChapterOptions = from_optional(options)?;
+        to_json(
+            &self
+                .lock()?
+                .hyphenate_chapter(&book_id, &href, language.as_deref(), &options)
+                .map_err(node_error)?,
+        )
+    }
+
+    /// Get a chapter's plain text with word boundaries and hyphenation points
+    #[napi]
+    pub fn get_chapter_text(
+        &self,
+        book_id: String,
+        href: String,
+        language: Option<String>,
+    ) -> napi::Result<serde_json::Value> {
+        to_json(
+            &self
+                .lock()?
+                .get_chapter_text(&book_id, &href, language.as_deref())
+                .map_err(node_error)?,
+        )
+    }
+
+    /// Snap a highlight selection to whole words and trim its whitespace;
+    /// `null` when only whitespace is selected
+    #[napi]
+    pub fn normalize_selection(
+        &self,
+        book_id: String,
+        href: String,
+        start: u32,
+        end: u32,
+    ) -> napi::Result<serde_json::Value> {
+        to_json(
+            &self
+                .lock()?
+                .normalize_selection(&book_id, &href, start as usize, end as usize)
+                .map_err(node_error)?,
+        )
+    }
+
+    /// Text of a selection for the clipboard, with smart punctuation and
+    /// ligatures folded as the optional `TextFolding` object asks
+    #[napi]
+    pub fn copy_text(
+        &self,
+        book_id: String,
+        href: String,
+        start: u32,
+        end: u32,
+        folding: Option<serde_json::Value>,
+    ) -> napi::Result<String> {
+        let folding: TextFolding = from_optional(folding)?;
+        self.lock()?
+            .copy_text(&book_id, &href, start as usize, end as usize, &folding)
+            .map_err(node_error)
+    }
+
+    /// Word counts per spine item, the total, and estimated reading times;
+    /// `words_per_minute` defaults to 238
+    #[napi]
+    pub fn get_statistics(
+        &self,
+        book_id: String,
+        words_per_minute: Option<u32>,
+    ) -> napi::Result<serde_json::Value> {
+        to_json(
+            &self
+                .lock()?
+                .statistics(&book_id, words_per_minute)
+                .map_err(node_error)?,
+        )
+    }
+
+    /// The back-of-book index, empty if the book has none
+    #[napi]
+    pub fn get_book_index(&self, book_id: String) -> napi::Result<serde_json::Value> {
+        to_json(self.lock()?.book_index(&book_id).map_err(node_error)?)
+    }
+
+    /// Index entries whose term is `query` or starts with it, ignoring
+    /// case; exact matches first
+    #[napi]
+    pub fn lookup_index(
+        &self,
+        book_id: String,
+        query: String,
+        limit: Option<u32>,
+    ) -> napi::Result<serde_json::Value> {
+        let limit = limit.map_or(DEFAULT_INDEX_MATCHES, |limit| limit as usize);
+        to_json(
+            &self
+                .lock()?
+                .lookup_index(&book_id, &query, limit)
+                .map_err(node_error)?,
+        )
+    }
+
+    /// The glossary, empty if the book has none
+    #[napi]
+    pub fn get_glossary(&self, book_id: String) -> napi::Result<serde_json::Value> {
+        to_json(self.lock()?.glossary(&book_id).map_err(node_error)?)
+    }
+
+    /// The book's own definition of a term, or null
+    #[napi]
+    pub fn define_term(&self, book_id: String, term: String) -> napi::Result<serde_json::Value> {
+        to_json(
+            &self
+                .lock()?
+                .define_term(&book_id, &term)
+                .map_err(node_error)?,
+        )
+    }
+
+    /// The book's non-linear spine items, which locations and progression
+    /// skip
+    #[napi]
+    pub fn get_auxiliary_items(&self, book_id: String) -> napi::Result<serde_json::Value> {
+        to_json(&self.lock()?.auxiliary_items(&book_id).map_err(node_error)?)
+    }
+
+    /// Unload a book to free memory
+    #[napi]
+    pub fn unload_book(&self, book_id: String) -> napi::Result<()> {
+        self.lock()?.unload_book(&book_id);
+        Ok(())
+    }
+
+    /// Get list of loaded book IDs
+    #[napi]
+    pub fn get_loaded_books(&self) -> napi::Result<Vec<String>> {
+        Ok(self.lock()?.loaded_books())
+    }
+
+    fn lock(&self) -> napi::Result<MutexGuard<'_, Processor>> {
+        lock(&self.inner)
+    }
+}
+
+// napi registers every method of an impl block, so methods of optional
+// features live in blocks of their own
+#[cfg(feature = "cfi")]
+#[napi]
+impl EpubProcessor {
+    /// Generate a CFI from a DOM position (`childNodes` indices from the
+    /// document element, and a `Range` offset into the node)
+    #[napi]
+    pub fn generate_cfi(
+        &self,
+        book_id: String,
+        spine_index: u32,
+        node_path: Vec<u32>,
+        offset: u32,
+    ) -> napi::Result<String> {
+        let position = DomPosition {
+            node_path: node_path.into_iter().map(|i| i as usize).collect(),
+            offset: offset as usize,
+        };
+        self.lock()?
+            .generate_cfi(&book_id, spine_index as usize, &position)
+            .map_err(node_error)
+    }
+
+    /// Resolve a CFI to a location
+    #[napi]
+    pub fn resolve_cfi(&self, book_id: String, cfi: String) -> napi::Result<serde_json::Value> {
+        to_json(
+            &self
+                .lock()?
+                .resolve_cfi(&book_id, &cfi)
+                .map_err(node_error)?,
+        )
+    }
+
+    /// Generate a range CFI for a selection, from the DOM positions of its
+    /// ends (see `generateCfi`)
+    #[napi]
+    pub fn generate_cfi_range(
+        &self,
+        book_id: String,
+        spine_index: u32,
+        start_path: Vec<u32>,
+        start_offset: u32,
+        end_path: Vec<u32>,
+        end_offset: u32,
+    ) -> napi::Result<String> {
+        let start = DomPosition {
+            node_path: start_path.into_iter().map(|i| i as usize).collect(),
+            offset: start_offset as usize,
+        };
+        let end = DomPosition {
+            node_path: end_path.into_iter().map(|i| i as usize).collect(),
+            offset: end_offset as usize,
+        };
+        self.lock()?
+            .generate_cfi_range(&book_id, spine_index as usize, &start, &end)
+            .map_err(node_error)
+    }
+
+    /// Generate a CFI for a point in an image, or for a region of it from
+    /// (`x`, `y`) to the opposite corner (`end_x`, `end_y`), in percentages
+    #[napi]
+    #[allow(clippy::too_many_arguments)]
+    pub fn generate_spatial_cfi(
+        &self,
+        book_id: String,
+        spine_index: u32,
+        node_path: Vec<u32>,
+        x: f64,
+        y: f64,
+        end_x: Option<f64>,
+        end_y: Option<f64>,
+    ) -> napi::Result<String> {
+        let node_path: Vec<usize> = node_path.into_iter().map(|i| i as usize).collect();
+        self.lock()?
+            .generate_spatial_cfi(
+                &book_id,
+                spine_index as usize,
+                &node_path,
+                (x, y),
+                (end_x, end_y),
+            )
+            .map_err(node_error)
+    }
+
+    /// Resolve both ends of a range CFI
+    #[napi]
+    pub fn resolve_cfi_range(
+        &self,
+        book_id: String,
+        cfi: String,
+    ) -> napi::Result<serde_json::Value> {
+        to_json(
+            &self
+                .lock()?
+                .resolve_cfi_range(&book_id, &cfi)
+                .map_err(node_error)?,
+        )
+    }
+
+    /// Quote the text at a CFI, to find it again if the book is updated
+    #[napi]
+    pub fn cfi_to_text_quote(
+        &self,
+        book_id: String,
+        cfi: String,
+    ) -> napi::Result<serde_json::Value> {
+        to_json(
+            &self
+                .lock()?
+                .cfi_to_text_quote(&book_id, &cfi)
+                .map_err(node_error)?,
+        )
+    }
+
+    /// The word, sentence and paragraph text at and around a CFI, within
+    /// `charsBefore` and `charsAfter` characters of it
+    #[napi]
+    pub fn get_text_around(
+        &self,
+        book_id: String,
+        cfi: String,
+        chars_before: u32,
+        chars_after: u32,
+    ) -> napi::Result<serde_json::Value> {
+        to_json(
+            &self
+                .lock()?
+                .text_around(&book_id, &cfi, chars_before as usize, chars_after as usize)
+                .map_err(node_error)?,
+        )
+    }
+
+    /// A fresh CFI for a quote from `cfiToTextQuote`, or null if the text
+    /// is no longer in the book
+    #[napi]
+    pub fn text_quote_to_cfi(
+        &self,
+        book_id: String,
+        quote: serde_json::Value,
+    ) -> napi::Result<Option<String>> {
+        let quote: TextQuoteAnchor = from_json(quote)?;
+        self.lock()?
+            .text_quote_to_cfi(&book_id, &quote)
+            .map_err(node_error)
+    }
+
+    /// Generate epub.js-style locations: CFIs every `charsPerLocation`
+    /// characters (default 150) through the book's linear spine
+    #[napi(ts_return_type = "Promise<string[]>")]
+    pub fn generate_locations(
+        &self,
+        book_id: String,
+        chars_per_location: Option<u32>,
+    ) -> AsyncTask<GenerateLocations> {
+        AsyncTask::new(GenerateLocations {
+            processor: Arc::clone(&self.inner),
+            book_id,
+            chars_per_location: chars_per_location
+                .map_or(DEFAULT_CHARS_PER_LOCATION, |chars| chars as usize),
+        })
+    }
+
+    /// Progression through the book (0.0-1.0) of a CFI's position, by
+    /// characters of text
+    #[napi]
+    pub fn cfi_to_percentage(&self, book_id: String, cfi: String) -> napi::Result<f64> {
+        self.lock()?
+            .cfi_to_percentage(&book_id, &cfi)
+            .map_err(node_error)
+    }
+
+    /// CFI of the position a fraction (0.0-1.0) of the way through a book's
+    /// text
+    #[napi]
+    pub fn percentage_to_cfi(&self, book_id: String, fraction: f64) -> napi::Result<String> {
+        self.lock()?
+            .percentage_to_cfi(&book_id, fraction)
+            .map_err(node_error)
+    }
+
+    /// The ToC as a flat list in document order, with each entry's `level`,
+    /// `parent` index, `spineIndex` and start `cfi`
+    #[napi]
+    pub fn flatten_toc(&self, book_id: String) -> napi::Result<serde_json::Value> {
+        to_json(&self.lock()?.flatten_toc(&book_id).map_err(node_error)?)
+    }
+
+    /// The `flattenToc` entry being read at a CFI, or null before the first
+    /// entry
+    #[napi]
+    pub fn get_current_toc_entry(
+        &self,
+        book_id: String,
+        cfi: String,
+    ) -> napi::Result<serde_json::Value> {
+        to_json(
+            &self
+                .lock()?
+                .current_toc_entry(&book_id, &cfi)
+                .map_err(node_error)?,
+        )
+    }
+}
+
+#[cfg(feature = "media-overlays")]
+#[napi]
+impl EpubProcessor {
+    /// Generate a CFI for a time in an `<audio>` or `<video>` element, or
+    /// for a clip of it from `start` to `end` seconds
+    #[napi]
+    pub fn generate_temporal_cfi(
+        &self,
+        book_id: String,
+        spine_index: u32,
+        node_path: Vec<u32>,
+        start: f64,
+        end: Option<f64>,
+    ) -> napi::Result<String> {
+        let node_path: Vec<usize> = node_path.into_iter().map(|i| i as usize).collect();
+        self.lock()?
+            .generate_temporal_cfi(&book_id, spine_index as usize, &node_path, start, end)
+            .map_err(node_error)
+    }
+}
+
+#[cfg(feature = "search")]
+#[napi]
+impl EpubProcessor {
+    /// Build a search index for a book, optionally covering alt text,
+    /// captions and footnotes (`{ altText, captions, footnotes }`)
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn build_search_index(
+        &self,
+        book_id: String,
+        options: Option<serde_json::Value>,
+    ) -> napi::Result<AsyncTask<BuildSearchIndex>> {
+        Ok(AsyncTask::new(BuildSearchIndex {
+            processor: Arc::clone(&self.inner),
+            listener: self.listener.clone(),
+            book_id,
+            options: from_optional(options)?,
+        }))
+    }
+
+    /// Index the next `chapters_per_tick` chapters of a book, starting a
+    /// build with `options` if none is under way; returns the progress
+    #[napi]
+    pub fn build_search_index_chunked(
+        &self,
+        book_id: String,
+        chapters_per_tick: u32,
+        options: Option<serde_json::Value>,
+    ) -> napi::Result<serde_json::Value> {
+        let options: IndexOptions = from_optional(options)?;
+        let mut processor = self.lock()?;
+        let progress =
+            processor.build_search_index_chunked(&book_id, chapters_per_tick as usize, &options);
+        deliver(self.listener.as_ref(), processor.take_events());
+        to_json(&progress.map_err(node_error)?)
+    }
+
+    /// Import a prebuilt search index (e.g. downloaded from the server)
+    #[napi]
+    pub fn import_search_index(&self, book_id: String, data: Buffer) -> napi::Result<()> {
+        let mut processor = self.lock()?;
+        let imported = processor.import_search_index(&book_id, &data);
+        deliver(self.listener.as_ref(), processor.take_events());
+        imported.map_err(node_error)
+    }
+
+    /// Serialize a built search index so it can be cached
+    #[napi]
+    pub fn export_search_index(&self, book_id: String) -> napi::Result<Buffer> {
+        Ok(self
+            .lock()?
+            .export_search_index(&book_id)
+            .map_err(node_error)?
+            .into())
+    }
+
+    /// Search a book's content; a `fuzziness` of 1 or 2 also matches
+    /// misspelled words
+    #[napi]
+    pub fn search(
+        &self,
+        book_id: String,
+        query: String,
+        limit: Option<u32>,
+        fuzziness: Option<u32>,
+    ) -> napi::Result<serde_json::Value> {
+        let limit = limit.unwrap_or(50) as usize;
+        let fuzziness = fuzziness.unwrap_or(0).min(u32::from(MAX_FUZZINESS)) as u8;
+        to_json(
+            &self
+                .lock()?
+                .search(&book_id, &query, limit, fuzziness)
+                .map_err(node_error)?,
+        )
+    }
+
+    /// Search every book with a built or imported index, best first
+    #[napi]
+    pub fn search_all(&self, query: String, limit: Option<u32>) -> napi::Result<serde_json::Value> {
+        let limit = limit.unwrap_or(50) as usize;
+        to_json(&self.lock()?.search_all(&query, limit))
+    }
+}
+
+/// A loaded book, read without the processor's lock
+#[napi]
+pub struct BookHandle {
+    book: Arc<EpubBook>,
+}
+
+#[napi]
+impl BookHandle {
+    #[napi(getter)]
+    pub fn id(&self) -> String {
+        self.book.id.clone()
+    }
+
+    /// Get a chapter's content by href
+    #[napi]
+    pub fn get_chapter(
+        &self,
+        href: String,
+        options: Option<serde_json::Value>,
+    ) -> napi::Result<serde_json::Value> {
+        let options: ChapterOptions = from_optional(options)?;
+        let chapter = book_result(self.book.get_chapter_content_with(&href, &options))?;
+        to_json(&chapter)
+    }
+
+    /// Get a chapter with `<mark>` elements around each occurrence of
+    /// `terms`, to show search hits
+    #[napi]
+    pub fn get_chapter_with_highlights(
+        &self,
+        href: String,
+        terms: Vec<String>,
+        options: Option<serde_json::Value>,
+    ) -> napi::Result<serde_json::Value> {
+        let options: ChapterOptions = from_optional(options)?;
+        let mut chapter = book_result(self.book.get_chapter_content_with(&href, &options))?;
+        chapter.html = text::mark_terms(&chapter.html, &terms);
+        to_json(&chapter)
+    }
+
+    /// Get the note with id `fragment_id` in the document at `href`,
+    /// sanitized, or `null`
+    #[napi]
+    pub fn get_footnote(
+        &self,
+        href: String,
+        fragment_id: String,
+        options: Option<serde_json::Value>,
+    ) -> napi::Result<serde_json::Value> {
+        let options: ChapterOptions = from_optional(options)?;
+        let note = book_result(self.book.get_footnote(&href, &fragment_id, &options))?;
+        to_json(&note)
+    }
+
+    /// Get a chapter's block map, with estimated heights when `metrics` is
+    /// given
+    #[napi]
+    pub fn get_chapter_blocks(
+        &self,
+        href: String,
+        metrics: Option<serde_json::Value>,
+    ) -> napi::Result<serde_json::Value> {
+        let metrics: Option<BlockMetrics> = from_optional(metrics)?;
+        let blocks = book_result(self.book.chapter_blocks(&href, metrics.as_ref()))?;
+        to_json(&blocks)
+    }
+
+    /// Get a resource (image, CSS, etc.) by href, optionally with
+    /// ImageOptions
+    #[napi]
+    pub fn get_resource(
+        &self,
+        href: String,
+        options: Option<serde_json::Value>,
+    ) -> napi::Result<Buffer> {
+        let options: ImageOptions = from_optional(options)?;
+        Ok(book_result(self.book.get_resource_with(&href, &options))?.into())
+    }
+
+    /// Export the book as one HTML document, with optional
+    /// HtmlExportOptions, without holding up the processor
+    #[napi]
+    pub fn export_html(
+        &self,
+        options: Option<serde_json::Value>,
+    ) -> napi::Result<serde_json::Value> {
+        let options: HtmlExportOptions = from_optional(options)?;
+        let export = book_result(self.book.export_html(&options))?;
+        to_json(&export)
+    }
+
+    /// Describe the book as a Readium Web Publication Manifest
+    #[napi]
+    pub fn to_webpub_manifest(&self) -> napi::Result<serde_json::Value> {
+        to_json(&self.book.to_webpub_manifest())
+    }
+}
+
+/// Parses an EPUB on a worker thread, then stores it
+pub struct LoadBook {
+    processor: Arc<Mutex<Processor>>,
+    listener: Option<Listener>,
+    data: Vec<u8>,
+    options: LoadOptions,
+}
+
+impl Task for LoadBook {
+    type Output = ParsedBook;
+    type JsValue = JsUnknown;
+
+    fn compute(&mut self) -> napi::Result<ParsedBook> {
+        lock(&self.processor)?
+            .limits()
+            .check(ResourceLimit::BookSize, self.data.len())
+            .map_err(node_error)?;
+        // Parse without holding the lock so other calls are not blocked
+        let data = std::mem::take(&mut self.data);
+        let listener = self.listener.as_ref();
+        let book = EpubBook::load_with_progress(data, &self.options, &mut |progress| {
+            deliver(listener, vec![ProcessorEvent::LoadProgress(progress)]);
+        });
+        let book = book_result(book)?;
+        let mut processor = lock(&self.processor)?;
+        let parsed = processor.insert_book(book, self.options.on_collision);
+        deliver(listener, processor.take_events());
+        parsed.map_err(node_error)
+    }
+
+    fn resolve(&mut self, env: Env, output: ParsedBook) -> napi::Result<JsUnknown> {
+        env.to_js_value(&output)
+    }
+}
+
+/// Builds a search index on a worker thread
+#[cfg(feature = "search")]
+pub struct BuildSearchIndex {
+    processor: Arc<Mutex<Processor>>,
+    listener: Option<Listener>,
+    book_id: String,
+    options: IndexOptions,
+}
+
+#[cfg(feature = "search")]
+impl Task for BuildSearchIndex {
+    type Output = ();
+    type JsValue = ();
+
+    fn compute(&mut self) -> napi::Result<()> {
+        let mut processor = lock(&self.processor)?;
+        let built = processor.build_search_index(&self.book_id, &self.options);
+        deliver(self.listener.as_ref(), processor.take_events());
+        built.map_err(node_error)
+    }
+
+    fn resolve(&mut self, _env: Env, _output: ()) -> napi::Result<()> {
+        Ok(())
+    }
+}
+
+/// Walks a book's text for locations on a worker thread
+#[cfg(feature = "cfi")]
+pub struct GenerateLocations {
+    processor: Arc<Mutex<Processor>>,
+    book_id: String,
+    chars_per_location: usize,
+}
+
+#[cfg(feature = "cfi")]
+impl Task for GenerateLocations {
+    type Output = Vec<String>;
+    type JsValue = Vec<String>;
+
+    fn compute(&mut self) -> napi::Result<Vec<String>> {
+        lock(&self.processor)?
+            .generate_locations(&self.book_id, self.chars_per_location)
+            .map_err(node_error)
+    }
+
+    fn resolve(&mut self, _env: Env, output: Vec<String>) -> napi::Result<Vec<String>> {
+        Ok(output)
+    }
+}
+
+/// Queue events for the listener, if there is one
+fn deliver(listener: Option<&Listener>, events: Vec<ProcessorEvent>) {
+    let Some(listener) = listener else {
+        return;
+    };
+    for event in events {
+        listener.call(event, ThreadsafeFunctionCallMode::NonBlocking);
+    }
+}
+
+fn lock(processor: &Mutex<Processor>) -> napi::Result<MutexGuard<'_, Processor>> {
+    processor.lock().map_err(|_| {
+        error_reason(&ErrorInfo::new(
+            ErrorCode::Internal,
+            "EPUB processor state is poisoned",
+        ))
+    })
+}
+
+fn to_json<T: Serialize>(value: &T) -> napi::Result<serde_json::Value> {
+    serde_json::to_value(value)
+        .map_err(|e| error_reason(&ErrorInfo::new(ErrorCode::Internal, e.to_string())))
+}
+
+/// Deserialize an argument object
+fn from_json<T: DeserializeOwned>(value: serde_json::Value) -> napi::Result<T> {
+    serde_json::from_value(value)
+        .map_err(|e| error_reason(&ErrorInfo::new(ErrorCode::InvalidArgument, e.to_string())))
+}
+
+/// Deserialize an options object, defaulting when none was passed
+fn from_optional<T: DeserializeOwned + Default>(
+    value: Option<serde_json::Value>,
+) -> napi::Result<T> {
+    match value {
+        Some(value) if !value.is_null() => from_json(value),
+        _ => Ok(T::default()),
+    }
+}
+
+fn node_error(e: ProcessorError) -> napi::Error {
+    error_reason(&ErrorInfo::from(e))
+}
+
+fn book_result<T>(result: Result<T, EpubError>) -> napi::Result<T> {
+    result.map_err(|e| node_error(ProcessorError::Epub(e)))
+}
+
+/// An error whose message is the info as JSON, since N-API errors carry
+/// only a status and a message
+fn error_reason(info: &ErrorInfo) -> napi::Error {
+    let json = serde_json::to_string(info).unwrap_or_else(|_| info.message.clone());
+    napi::Error::from_reason(json)
+}
@@ -0,0 +1,1514 @@
+//! Binding-agnostic processor state
+//!
+//! [`Processor`] owns the loaded books, search indices, and hyphenation
+//! dictionaries and exposes the whole API with plain Rust types. The browser
+//! (`web`) and Node.js (`node`) bindings are thin wrappers that convert
+//! arguments and results for their runtime, so both share every line of
+//! parsing, search, and CFI code.
+//!
+//! A processor is not meant to be shared: the bindings own it behind a
+//! single borrow (browser) or lock (Node.js). Loaded books are immutable
+//! and kept behind an `Arc`, so [`Processor::book_handle`] can hand one
+//! out to other threads, which read it without touching the processor.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use epub_core::{ChapterChecksum, ChecksumDiff};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[cfg(feature = "cfi")]
+use crate::cfi::{
+    self, CfiError, CfiLocation, CfiRangeLocation, DomPosition, FlatTocEntry, TextAround,
+    TextQuoteAnchor,
+};
+use crate::epub::{
+    AuxiliaryItem, Block, BlockMetrics, BookIndex, ChapterContent, ChapterOptions,
+    CollisionStrategy, EpubBook, EpubError, Footnote, Glossary, GlossaryEntry, HtmlExport,
+    HtmlExportOptions, ImageOptions, IndexMatch, LoadOptions, ParsedBook, WebPubManifest,
+};
+use crate::events::{ProcessorEvent, PRESSURE_PERCENT};
+#[cfg(feature = "search")]
+use crate::search::{
+    self, BookSearchResult, IndexBuilder, IndexOptions, IndexProgress, SearchError, SearchIndex,
+    SearchResult,
+};
+use crate::text::{
+    self, BookStatistics, ChapterText, HyphenationError, Hyphenator, NormalizedSelection,
+    TextFolding, DEFAULT_WORDS_PER_MINUTE,
+};
+
+/// Default minimum chars before the first / after the last hyphen
+pub const DEFAULT_LEFT_HYPHEN_MIN: usize = 2;
+pub const DEFAULT_RIGHT_HYPHEN_MIN: usize = 3;
+
+/// Index entries a lookup returns when no limit is given
+pub const DEFAULT_INDEX_MATCHES: usize = 20;
+
+#[derive(Error, Debug)]
+pub enum ProcessorError {
+    #[error("Book not found: {0}")]
+    BookNotFound(String),
+
+    #[cfg(feature = "search")]
+    #[error("Search index not built for '{0}'. Call buildSearchIndex first.")]
+    IndexNotBuilt(String),
+
+    #[error(transparent)]
+    Epub(#[from] EpubError),
+
+    #[cfg(feature = "cfi")]
+    #[error(transparent)]
+    Cfi(#[from] CfiError),
+
+    #[cfg(feature = "search")]
+    #[error(transparent)]
+    Search(#[from] SearchError),
+
+    #[error(transparent)]
+    Hyphenation(#[from] HyphenationError),
+
+    #[error("Book id '{0}' is already used by a different book")]
+    IdCollision(String),
+
+    /// A [`ProcessorLimits`] limit would be exceeded; the call's work is
+    /// discarded
+    #[error("Resource limit exceeded ({limit}): {requested} requested, limit is {max}")]
+    LimitExceeded {
+        limit: ResourceLimit,
+        requested: usize,
+        max: usize,
+    },
+}
+
+/// Limits on the memory a processor takes, for low-memory devices
+///
+/// A call that would go over a limit fails with
+/// [`ProcessorError::LimitExceeded`] instead of running out of memory,
+/// which aborts a WASM instance. Every limit is off by default.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ProcessorLimits {
+    /// Largest EPUB file `loadBook` accepts, in bytes
+    pub max_book_bytes: Option<usize>,
+    /// Books that can be loaded at once
+    pub max_books: Option<usize>,
+    /// Estimated memory all search indexes together may take, in bytes,
+    /// counting builds under way
+    pub max_index_bytes: Option<usize>,
+}
+
+/// Which of the [`ProcessorLimits`] an error is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ResourceLimit {
+    BookSize,
+    Books,
+    IndexMemory,
+}
+
+impl fmt::Display for ResourceLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // As serialized
+        f.write_str(match self {
+            Self::BookSize => "bookSize",
+            Self::Books => "books",
+            Self::IndexMemory => "indexMemory",
+        })
+    }
+}
+
+impl ProcessorLimits {
+    /// Fail if `requested` is over `limit`
+    pub fn check(&self, limit: ResourceLimit, requested: usize) -> ProcessorResult<()> {
+        match self.max(limit) {
+            Some(max) if requested > max => Err(ProcessorError::LimitExceeded {
+                limit,
+                requested,
+                max,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// The configured maximum of `limit`
+    pub fn max(&self, limit: ResourceLimit) -> Option<usize> {
+        match limit {
+            ResourceLimit::BookSize => self.max_book_bytes,
+            ResourceLimit::Books => self.max_books,
+            ResourceLimit::IndexMemory => self.max_index_bytes,
+        }
+    }
+}
+
+pub type ProcessorResult<T> = Result<T, ProcessorError>;
+
+/// Loaded books and their derived data
+#[derive(Default)]
+pub struct Processor {
+    books: HashMap<String, Arc<EpubBook>>,
+    #[cfg(feature = "search")]
+    search_indices: HashMap<String, SearchIndex>,
+    /// Search indexes being built a few chapters at a time
+    #[cfg(feature = "search")]
+    index_builders: HashMap<String, IndexBuilder>,
+    /// Characters of text per spine item, for progression conversions
+    #[cfg(feature = "cfi")]
+    text_lengths: HashMap<String, Vec<usize>>,
+    /// Flattened ToCs, placed in the spine on first use
+    #[cfg(feature = "cfi")]
+    flat_tocs: HashMap<String, Vec<FlatTocEntry>>,
+    /// Back-of-book indexes, parsed on first use
+    book_indexes: HashMap<String, BookIndex>,
+    /// Glossaries, parsed on first use
+    glossaries: HashMap<String, Glossary>,
+    /// Hyphenation dictionaries keyed by normalized language tag
+    hyphenators: HashMap<String, Hyphenator>,
+    limits: ProcessorLimits,
+    /// Events raised since the bindings last took them; `None` while no
+    /// listener is registered
+    events: Option<Vec<ProcessorEvent>>,
+}
+
+impl Processor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A processor that refuses work going over `limits`
+    pub fn with_limits(limits: ProcessorLimits) -> Self {
+        Self {
+            limits,
+            ..Self::default()
+        }
+    }
+
+    pub fn limits(&self) -> &ProcessorLimits {
+        &self.limits
+    }
+
+    /// Start or stop collecting [`ProcessorEvent`]s for [`Self::take_events`]
+    pub fn set_events_enabled(&mut self, enabled: bool) {
+        self.events = enabled.then(Vec::new);
+    }
+
+    /// Events raised since the last call, oldest first
+    pub fn take_events(&mut self) -> Vec<ProcessorEvent> {
+        self.events.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Parse and store an EPUB
+    pub fn load_book(
+        &mut self,
+        data: Vec<u8>,
+        options: &LoadOptions,
+    ) -> ProcessorResult<ParsedBook> {
+        self.limits.check(ResourceLimit::BookSize, data.len())?;
+        let events = &mut self.events;
+        let book = EpubBook::load_with_progress(data, options, &mut |progress| {
+            if let Some(events) = events {
+                events.push(ProcessorEvent::LoadProgress(progress));
+            }
+        })?;
+        self.insert_book(book, options.on_collision)
+    }
+
+    /// Store an already parsed book
+    ///
+    /// A book with the same id and content is replaced; one with the same id
+    /// and different content is resolved by `on_collision`, and the returned
+    /// [`ParsedBook::id`] is the id the book was stored under. Lets bindings
+    /// parse off the thread that owns the processor.
+    pub fn insert_book(
+        &mut self,
+        mut book: EpubBook,
+        on_collision: CollisionStrategy,
+    ) -> ProcessorResult<ParsedBook> {
+        let collides = |books: &HashMap<String, Arc<EpubBook>>, id: &str| {
+            books
+                .get(id)
+                .is_some_and(|loaded| loaded.fingerprint != book.fingerprint)
+        };
+        if collides(&self.books, &book.id) {
+            match on_collision {
+                CollisionStrategy::Replace => {}
+                CollisionStrategy::Error => return Err(ProcessorError::IdCollision(book.id)),
+                CollisionStrategy::Suffix => {
+                    let id = (2..)
+                        .map(|n| format!("{}-{}", book.id, n))
+                        .find(|id| !collides(&self.books, id))
+                        .expect("unbounded suffixes");
+                    book.id = id;
+                }
+            }
+        }
+
+        if !self.books.contains_key(&book.id) {
+            self.limits
+                .check(ResourceLimit::Books, self.books.len() + 1)?;
+        }
+
+        let parsed = book.to_parsed_book();
+        // The replaced book's index, and any build under way, point into
+        // its text
+        #[cfg(feature = "search")]
+        {
+            self.search_indices.remove(&book.id);
+            self.index_builders.remove(&book.id);
+        }
+        #[cfg(feature = "cfi")]
+        {
+            self.text_lengths.remove(&book.id);
+            self.flat_tocs.remove(&book.id);
+        }
+        self.book_indexes.remove(&book.id);
+        self.glossaries.remove(&book.id);
+        for warning in &book.warnings {
+            self.emit(ProcessorEvent::ParseWarning {
+                book_id: book.id.clone(),
+                warning: warning.clone(),
+            });
+        }
+        self.books.insert(book.id.clone(), Arc::new(book));
+        self.report_pressure(ResourceLimit::Books, self.books.len());
+        Ok(parsed)
+    }
+
+    pub fn book(&self, book_id: &str) -> ProcessorResult<&EpubBook> {
+        self.books
+            .get(book_id)
+            .map(Arc::as_ref)
+            .ok_or_else(|| ProcessorError::BookNotFound(book_id.to_string()))
+    }
+
+    /// A shared handle to a loaded book
+    ///
+    /// The handle can be sent to other threads, which read the book
+    /// (chapters, resources, notes) concurrently and without the processor.
+    /// It keeps the book alive after [`Self::unload_book`] until dropped.
+    pub fn book_handle(&self, book_id: &str) -> ProcessorResult<Arc<EpubBook>> {
+        self.books
+            .get(book_id)
+            .cloned()
+            .ok_or_else(|| ProcessorError::BookNotFound(book_id.to_string()))
+    }
+
+    pub fn get_chapter(
+        &self,
+        book_id: &str,
+        href: &str,
+        options: &ChapterOptions,
+    ) -> ProcessorResult<ChapterContent> {
+        Ok(self
+            .book(book_id)?
+            .get_chapter_content_with(href, options)?)
+    }
+
+    /// A chapter with `<mark>` around each occurrence of `terms`, for
+    /// showing search hits (see [`text::mark_terms`])
+    ///
+    /// The marks are extra elements, so CFIs are generated against the
+    /// chapter without them; anchors injected by `options` are not affected.
+    pub fn get_chapter_with_highlights(
+        &self,
+        book_id: &str,
+        href: &str,
+        terms: &[String],
+        options: &ChapterOptions,
+    ) -> ProcessorResult<ChapterContent> {
+        let mut chapter = self.get_chapter(book_id, href, options)?;
+        chapter.html = text::mark_terms(&chapter.html, terms);
+        Ok(chapter)
+    }
+
+    /// The note a note reference points at, for a popup
+    pub fn get_footnote(
+        &self,
+        book_id: &str,
+        href: &str,
+        fragment: &str,
+        options: &ChapterOptions,
+    ) -> ProcessorResult<Option<Footnote>> {
+        Ok(self.book(book_id)?.get_footnote(href, fragment, options)?)
+    }
+
+    /// Block map of a chapter, with estimated heights when `metrics` is given
+    pub fn get_chapter_blocks(
+        &self,
+        book_id: &str,
+        href: &str,
+        metrics: Option<&BlockMetrics>,
+    ) -> ProcessorResult<Vec<Block>> {
+        Ok(self.book(book_id)?.chapter_blocks(href, metrics)?)
+    }
+
+    pub fn get_resource(&self, book_id: &str, href: &str) -> ProcessorResult<Vec<u8>> {
+        Ok(self.book(book_id)?.get_resource(href)?)
+    }
+
+    /// Get a resource, with images downscaled or transcoded as `options`
+    /// ask; processed images are cached per book, href and options
+    pub fn get_resource_with(
+        &self,
+        book_id: &str,
+        href: &str,
+        options: &ImageOptions,
+    ) -> ProcessorResult<Vec<u8>> {
+        Ok(self.book(book_id)?.get_resource_with(href, options)?)
+    }
+
+    /// The book as one self-contained HTML document, for printing or
+    /// annotating elsewhere
+    pub fn export_html(
+        &self,
+        book_id: &str,
+        options: &HtmlExportOptions,
+    ) -> ProcessorResult<HtmlExport> {
+        Ok(self.book(book_id)?.export_html(options)?)
+    }
+
+    /// The book as a Readium Web Publication Manifest, for Readium
+    /// navigators fetching its resources from the processor
+    pub fn to_webpub_manifest(&self, book_id: &str) -> ProcessorResult<WebPubManifest> {
+        Ok(self.book(book_id)?.to_webpub_manifest())
+    }
+
+    /// Chapters of a loaded book that differ from an earlier version
+    ///
+    /// `previous` is the `checksums` list of the version the client last
+    /// indexed or anchored; only the chapters in the result need redoing.
+    pub fn changed_chapters(
+        &self,
+        book_id: &str,
+        previous: &[ChapterChecksum],
+    ) -> ProcessorResult<ChecksumDiff> {
+        Ok(epub_core::diff_checksums(
+            previous,
+            &self.book(book_id)?.checksums,
+        ))
+    }
+
+    #[cfg(feature = "cfi")]
+    pub fn generate_cfi(
+        &self,
+        book_id: &str,
+        spine_index: usize,
+        position: &DomPosition,
+    ) -> ProcessorResult<String> {
+        Ok(cfi::generate_cfi(self.book(book_id)?, spine_index, position)?)
+    }
+
+    #[cfg(feature = "cfi")]
+    pub fn resolve_cfi(&self, book_id: &str, cfi_str: &str) -> ProcessorResult<CfiLocation> {
+        Ok(cfi::resolve_cfi(self.book(book_id)?, cfi_str)?)
+    }
+
+    #[cfg(feature = "cfi")]
+    pub fn generate_cfi_range(
+        &self,
+        book_id: &str,
+        spine_index: usize,
+        start: &DomPosition,
+        end: &DomPosition,
+    ) -> ProcessorResult<String> {
+        Ok(cfi::generate_cfi_range(
+            self.book(book_id)?,
+            spine_index,
+            start,
+            end,
+        )?)
+    }
+
+    /// CFI for a time (or a clip, with `end`) in a media element
+    #[cfg(feature = "media-overlays")]
+    pub fn generate_temporal_cfi(
+        &self,
+        book_id: &str,
+        spine_index: usize,
+        node_path: &[usize],
+        start: f64,
+        end: Option<f64>,
+    ) -> ProcessorResult<String> {
+        Ok(cfi::generate_temporal_cfi(
+            self.book(book_id)?,
+            spine_index,
+            node_path,
+            start,
+            end,
+        )?)
+    }
+
+    /// CFI for a point in an image, or for a region of it when the opposite
+    /// corner (`end_x`, `end_y`) is given
+    #[cfg(feature = "cfi")]
+    pub fn generate_spatial_cfi(
+        &self,
+        book_id: &str,
+        spine_index: usize,
+        node_path: &[usize],
+        (x, y): (f64, f64),
+        (end_x, end_y): (Option<f64>, Option<f64>),
+    ) -> ProcessorResult<String> {
+        let corner = match (end_x, end_y) {
+            (Some(end_x), Some(end_y)) => Some((end_x, end_y)),
+            (None, None) => None,
+            _ => {
+                return Err(
+                    CfiError::InvalidArgument("A region needs both corners".to_string()).into(),
+                )
+            }
+        };
+        Ok(cfi::generate_spatial_cfi(
+            self.book(book_id)?,
+            spine_index,
+            node_path,
+            (x, y),
+            corner,
+        )?)
+    }
+
+    #[cfg(feature = "cfi")]
+    pub fn resolve_cfi_range(
+        &self,
+        book_id: &str,
+        cfi_str: &str,
+    ) -> ProcessorResult<CfiRangeLocation> {
+        Ok(cfi::resolve_cfi_range(self.book(book_id)?, cfi_str)?)
+    }
+
+    /// The text at a CFI, to re-anchor it if the book is updated
+    #[cfg(feature = "cfi")]
+    pub fn cfi_to_text_quote(
+        &self,
+        book_id: &str,
+        cfi_str: &str,
+    ) -> ProcessorResult<TextQuoteAnchor> {
+        Ok(cfi::cfi_to_text_quote(self.book(book_id)?, cfi_str)?)
+    }
+
+    /// The word, sentence and paragraph text at and around a CFI
+    #[cfg(feature = "cfi")]
+    pub fn text_around(
+        &self,
+        book_id: &str,
+        cfi_str: &str,
+        chars_before: usize,
+        chars_after: usize,
+    ) -> ProcessorResult<TextAround> {
+        Ok(cfi::text_around(
+            self.book(book_id)?,
+            cfi_str,
+            chars_before,
+            chars_after,
+        )?)
+    }
+
+    /// A fresh CFI for a text quote, or `None` if the text is gone
+    #[cfg(feature = "cfi")]
+    pub fn text_quote_to_cfi(
+        &self,
+        book_id: &str,
+        anchor: &TextQuoteAnchor,
+    ) -> ProcessorResult<Option<String>> {
+        Ok(cfi::text_quote_to_cfi(self.book(book_id)?, anchor)?)
+    }
+
+    /// CFIs at fixed character intervals through the book
+    #[cfg(feature = "cfi")]
+    pub fn generate_locations(
+        &self,
+        book_id: &str,
+        chars_per_location: usize,
+    ) -> ProcessorResult<Vec<String>> {
+        Ok(cfi::generate_locations(
+            self.book(book_id)?,
+            chars_per_location,
+        )?)
+    }
+
+    /// Progression through the book (0.0-1.0) of a CFI's position
+    #[cfg(feature = "cfi")]
+    pub fn cfi_to_percentage(&mut self, book_id: &str, cfi_str: &str) -> ProcessorResult<f64> {
+        let (book, lengths) = self.book_with_text_lengths(book_id)?;
+        Ok(cfi::cfi_to_percentage(book, lengths, cfi_str)?)
+    }
+
+    /// CFI of the position a fraction (0.0-1.0) of the way through a book
+    #[cfg(feature = "cfi")]
+    pub fn percentage_to_cfi(&mut self, book_id: &str, fraction: f64) -> ProcessorResult<String> {
+        let (book, lengths) = self.book_with_text_lengths(book_id)?;
+        Ok(cfi::percentage_to_cfi(book, lengths, fraction)?)
+    }
+
+    /// A book and its spine items' text lengths, counted on first use
+    #[cfg(feature = "cfi")]
+    fn book_with_text_lengths(&mut self, book_id: &str) -> ProcessorResult<(&EpubBook, &[usize])> {
+        let book = self
+            .books
+            .get(book_id)
+            .ok_or_else(|| ProcessorError::BookNotFound(book_id.to_string()))?;
+        let lengths = self
+            .text_lengths
+            .entry(book_id.to_string())
+            .or_insert_with(|| cfi::spine_text_lengths(book));
+        Ok((book, lengths))
+    }
+
+    /// The ToC in document order, with each entry's spine index and start
+    /// CFI
+    #[cfg(feature = "cfi")]
+    pub fn flatten_toc(&mut self, book_id: &str) -> ProcessorResult<&[FlatTocEntry]> {
+        let book = self
+            .books
+            .get(book_id)
+            .ok_or_else(|| ProcessorError::BookNotFound(book_id.to_string()))?;
+        Ok(self
+            .flat_tocs
+            .entry(book_id.to_string())
+            .or_insert_with(|| cfi::flatten_toc(book)))
+    }
+
+    /// The ToC entry being read at a CFI, or `None` before the first entry
+    #[cfg(feature = "cfi")]
+    pub fn current_toc_entry(
+        &mut self,
+        book_id: &str,
+        cfi_str: &str,
+    ) -> ProcessorResult<Option<FlatTocEntry>> {
+        let entries = self.flatten_toc(book_id)?;
+        let index = cfi::current_toc_entry(entries, cfi_str)?;
+        Ok(index.map(|index| entries[index].clone()))
+    }
+
+    /// Build a search index, optionally covering alt text, captions and
+    /// footnotes
+    #[cfg(feature = "search")]
+    pub fn build_search_index(
+        &mut self,
+        book_id: &str,
+        options: &IndexOptions,
+    ) -> ProcessorResult<()> {
+        let elsewhere = self.index_bytes_elsewhere(book_id);
+        let book = self
+            .books
+            .get(book_id)
+            .ok_or_else(|| ProcessorError::BookNotFound(book_id.to_string()))?;
+        // Read a chapter at a time so a build over the limit stops early
+        let mut builder = IndexBuilder::new(book, options);
+        loop {
+            let progress = builder.step(book, 1);
+            if let Some(events) = &mut self.events {
+                events.push(ProcessorEvent::IndexProgress {
+                    book_id: book_id.to_string(),
+                    progress,
+                });
+            }
+            if progress.done {
+                break;
+            }
+            self.limits.check(
+                ResourceLimit::IndexMemory,
+                elsewhere + builder.memory_bytes(),
+            )?;
+        }
+        let index = builder.finish();
+        let used = elsewhere + index.memory_bytes();
+        self.limits.check(ResourceLimit::IndexMemory, used)?;
+        self.index_builders.remove(book_id);
+        self.search_indices.insert(book_id.to_string(), index);
+        self.report_pressure(ResourceLimit::IndexMemory, used);
+        Ok(())
+    }
+
+    /// Index the next `chapters` chapters of a book
+    ///
+    /// The first call starts a build with `options`; later calls continue
+    /// it and ignore theirs. The book's current index, if any, stays
+    /// searchable until the call that reads the last chapter replaces it.
+    #[cfg(feature = "search")]
+    pub fn build_search_index_chunked(
+        &mut self,
+        book_id: &str,
+        chapters: usize,
+        options: &IndexOptions,
+    ) -> ProcessorResult<IndexProgress> {
+        let elsewhere = self.index_bytes_elsewhere(book_id);
+        let book = self
+            .books
+            .get(book_id)
+            .ok_or_else(|| ProcessorError::BookNotFound(book_id.to_string()))?;
+        let builder = self
+            .index_builders
+            .entry(book_id.to_string())
+            .or_insert_with(|| IndexBuilder::new(book, options));
+
+        let progress = builder.step(book, chapters.max(1));
+        let mut used = elsewhere + builder.memory_bytes();
+        let checked = self.limits.check(ResourceLimit::IndexMemory, used);
+        self.emit(ProcessorEvent::IndexProgress {
+            book_id: book_id.to_string(),
+            progress,
+        });
+        if checked.is_err() || progress.done {
+            // A build over the limit is dropped, so the next call starts over
+            if let Some(builder) = self.index_builders.remove(book_id) {
+                checked?;
+                let index = builder.finish();
+                used = elsewhere + index.memory_bytes();
+                self.limits.check(ResourceLimit::IndexMemory, used)?;
+                self.search_indices.insert(book_id.to_string(), index);
+            }
+        }
+        self.report_pressure(ResourceLimit::IndexMemory, used);
+        Ok(progress)
+    }
+
+    /// Import a prebuilt search index; the book does not need to be loaded
+    ///
+    /// The index must have been built for `book_id`.
+    #[cfg(feature = "search")]
+    pub fn import_search_index(&mut self, book_id: &str, bytes: &[u8]) -> ProcessorResult<()> {
+        let index = SearchIndex::from_bytes(bytes)?;
+        if index.book_id() != book_id {
+            return Err(SearchError::WrongBook {
+                expected: book_id.to_string(),
+                found: index.book_id().to_string(),
+            }
+            .into());
+        }
+        let used = self.index_bytes_elsewhere(book_id) + index.memory_bytes();
+        self.limits.check(ResourceLimit::IndexMemory, used)?;
+        self.search_indices.insert(book_id.to_string(), index);
+        self.report_pressure(ResourceLimit::IndexMemory, used);
+        Ok(())
+    }
+
+    #[cfg(feature = "search")]
+    pub fn export_search_index(&self, book_id: &str) -> ProcessorResult<Vec<u8>> {
+        Ok(self.search_index(book_id)?.to_bytes())
+    }
+
+    /// Search a book; `fuzziness` is the edits per word a match may be
+    /// away from the query (0 for exact matches)
+    #[cfg(feature = "search")]
+    pub fn search(
+        &self,
+        book_id: &str,
+        query: &str,
+        limit: usize,
+        fuzziness: u8,
+    ) -> ProcessorResult<Vec<SearchResult>> {
+        let mut results = self
+            .search_index(book_id)?
+            .search_fuzzy(query, limit, fuzziness);
+        // An imported index may belong to a book that is not loaded
+        if let Ok(book) = self.book(book_id) {
+            search::mark_non_linear(book, &mut results);
+            #[cfg(feature = "cfi")]
+            cfi::locate_search_results(book, &mut results);
+        }
+        Ok(results)
+    }
+
+    /// Search every book with a built or imported index
+    #[cfg(feature = "search")]
+    pub fn search_all(&self, query: &str, limit: usize) -> Vec<BookSearchResult> {
+        let mut indices: Vec<(&str, &SearchIndex)> = self
+            .search_indices
+            .iter()
+            .map(|(book_id, index)| (book_id.as_str(), index))
+            .collect();
+        // Books in a stable order, so equal scores do not shuffle
+        indices.sort_unstable_by_key(|(book_id, _)| *book_id);
+        let mut results = search::search_all(indices, query, limit);
+
+        for (book_id, book) in &self.books {
+            let mut book_results: Vec<&mut SearchResult> = results
+                .iter_mut()
+                .filter(|r| r.book_id == *book_id)
+                .map(|r| &mut r.result)
+                .collect();
+            search::mark_non_linear(book, book_results.iter_mut().map(|r| &mut **r));
+            #[cfg(feature = "cfi")]
+            cfi::locate_search_results(book, book_results);
+        }
+        results
+    }
+
+    /// Load hyph-utf8 patterns for a language; returns the pattern count
+    pub fn load_hyphenation_patterns(
+        &mut self,
+        language: &str,
+        patterns: &str,
+        exceptions: Option<&str>,
+        left_min: Option<usize>,
+        right_min: Option<usize>,
+    ) -> ProcessorResult<usize> {
+        let hyphenator = Hyphenator::new(
+            patterns,
+            exceptions.unwrap_or_default(),
+            left_min.unwrap_or(DEFAULT_LEFT_HYPHEN_MIN),
+            right_min.unwrap_or(DEFAULT_RIGHT_HYPHEN_MIN),
+        )?;
+
+        let count = hyphenator.pattern_count();
+        self.hyphenators
+            .insert(text::language_key(language), hyphenator);
+        Ok(count)
+    }
+
+    /// Languages with loaded or built-in patterns
+    pub fn hyphenation_languages(&self) -> Vec<String> {
+        let mut languages: Vec<String> = self
+            .hyphenators
+            .keys()
+            .chain(text::builtin_hyphenators().keys())
+            .cloned()
+            .collect();
+        languages.sort();
+        languages.dedup();
+        languages
+    }
+
+    /// Hyphenation points of a word as UTF-16 offsets
+    pub fn hyphenate(&self, language: &str, word: &str) -> Vec<usize> {
+        self.hyphenator(language)
+            .map(|(_, hyphenator)| text::hyphenate_word(word, 0, hyphenator))
+            .unwrap_or_default()
+    }
+
+    /// A chapter with soft hyphens at its hyphenation points, for justified
+    /// text (see [`text::insert_soft_hyphens`])
+    ///
+    /// `language` defaults to the book's; the chapter is returned as is when
+    /// there are no patterns for it. The soft hyphens are extra text, so
+    /// CFIs are generated against the chapter without them.
+    pub fn hyphenate_chapter(
+        &self,
+        book_id: &str,
+        href: &str,
+        language: Option<&str>,
+        options: &ChapterOptions,
+    ) -> ProcessorResult<ChapterContent> {
+        let book = self.book(book_id)?;
+        let mut chapter = book.get_chapter_content_with(href, options)?;
+        let hyphenator = language
+            .or(book.metadata.language.as_deref())
+            .and_then(|tag| self.hyphenator(tag));
+        if let Some((_, hyphenator)) = hyphenator {
+            chapter.html = text::insert_soft_hyphens(&chapter.html, hyphenator);
+        }
+        Ok(chapter)
+    }
+
+    /// Chapter text with word boundaries; `language` defaults to the book's
+    pub fn get_chapter_text(
+        &self,
+        book_id: &str,
+        href: &str,
+        language: Option<&str>,
+    ) -> ProcessorResult<ChapterText> {
+        let book = self.book(book_id)?;
+        let content = book.get_chapter_content(href)?;
+
+        let hyphenator = language
+            .or(book.metadata.language.as_deref())
+            .and_then(|tag| self.hyphenator(tag));
+        Ok(ChapterText::from_html(href, &content.html, hyphenator))
+    }
+
+    /// Patterns for a language tag: loaded ones, then built-in ones
+    fn hyphenator(&self, tag: &str) -> Option<(&str, &Hyphenator)> {
+        text::resolve_language(&self.hyphenators, tag)
+            .or_else(|| text::resolve_language(text::builtin_hyphenators(), tag))
+    }
+
+    /// Snap a selection of a chapter's text to whole words
+    ///
+    /// Offsets are UTF-16 code units into the chapter text; `None` when
+    /// only whitespace is selected.
+    pub fn normalize_selection(
+        &self,
+        book_id: &str,
+        href: &str,
+        start: usize,
+        end: usize,
+    ) -> ProcessorResult<Option<NormalizedSelection>> {
+        let content = self.book(book_id)?.get_chapter_content(href)?;
+        Ok(NormalizedSelection::from_html(
+            href,
+            &content.html,
+            start,
+            end,
+        ))
+    }
+
+    /// A selection of a chapter's text for the clipboard, with smart
+    /// punctuation and ligatures folded as `folding` asks
+    ///
+    /// Offsets are UTF-16 code units into the chapter text.
+    pub fn copy_text(
+        &self,
+        book_id: &str,
+        href: &str,
+        start: usize,
+        end: usize,
+        folding: &TextFolding,
+    ) -> ProcessorResult<String> {
+        let content = self.book(book_id)?.get_chapter_content(href)?;
+        Ok(text::copied_text(&content.html, start, end, folding))
+    }
+
+    /// Word counts per spine item and estimated reading times, at
+    /// `words_per_minute` or [`DEFAULT_WORDS_PER_MINUTE`]
+    pub fn statistics(
+        &self,
+        book_id: &str,
+        words_per_minute: Option<u32>,
+    ) -> ProcessorResult<BookStatistics> {
+        Ok(BookStatistics::from_book(
+            self.book(book_id)?,
+            words_per_minute.unwrap_or(DEFAULT_WORDS_PER_MINUTE),
+        ))
+    }
+
+    /// The book's back-of-book index; empty if it has none
+    pub fn book_index(&mut self, book_id: &str) -> ProcessorResult<&BookIndex> {
+        let book = self
+            .books
+            .get(book_id)
+            .ok_or_else(|| ProcessorError::BookNotFound(book_id.to_string()))?;
+        Ok(self
+            .book_indexes
+            .entry(book_id.to_string())
+            .or_insert_with(|| book.book_index()))
+    }
+
+    /// Index entries whose term is `query` or starts with it
+    pub fn lookup_index(
+        &mut self,
+        book_id: &str,
+        query: &str,
+        limit: usize,
+    ) -> ProcessorResult<Vec<IndexMatch>> {
+        Ok(self.book_index(book_id)?.lookup(query, limit))
+    }
+
+    /// The book's glossary; empty if it has none
+    pub fn glossary(&mut self, book_id: &str) -> ProcessorResult<&Glossary> {
+        let book = self
+            .books
+            .get(book_id)
+            .ok_or_else(|| ProcessorError::BookNotFound(book_id.to_string()))?;
+        Ok(self
+            .glossaries
+            .entry(book_id.to_string())
+            .or_insert_with(|| book.glossary()))
+    }
+
+    /// The book's own definition of `term`, if its glossary has one
+    pub fn define_term(
+        &mut self,
+        book_id: &str,
+        term: &str,
+    ) -> ProcessorResult<Option<GlossaryEntry>> {
+        Ok(self.glossary(book_id)?.define(term).cloned())
+    }
+
+    /// The book's non-linear spine items, with the items linking to them
+    pub fn auxiliary_items(&self, book_id: &str) -> ProcessorResult<Vec<AuxiliaryItem>> {
+        Ok(self.book(book_id)?.auxiliary_items())
+    }
+
+    pub fn unload_book(&mut self, book_id: &str) {
+        self.books.remove(book_id);
+        #[cfg(feature = "search")]
+        {
+            self.search_indices.remove(book_id);
+            self.index_builders.remove(book_id);
+        }
+        #[cfg(feature = "cfi")]
+        {
+            self.text_lengths.remove(book_id);
+            self.flat_tocs.remove(book_id);
+        }
+        self.book_indexes.remove(book_id);
+        self.glossaries.remove(book_id);
+    }
+
+    pub fn loaded_books(&self) -> Vec<String> {
+        self.books.keys().cloned().collect()
+    }
+
+    fn emit(&mut self, event: ProcessorEvent) {
+        if let Some(events) = &mut self.events {
+            events.push(event);
+        }
+    }
+
+    /// Raise [`ProcessorEvent::MemoryPressure`] if `used` is close to
+    /// `limit`
+    fn report_pressure(&mut self, limit: ResourceLimit, used: usize) {
+        match self.limits.max(limit) {
+            Some(max) if under_pressure(used, max) => {
+                self.emit(ProcessorEvent::MemoryPressure { limit, used, max })
+            }
+            _ => {}
+        }
+    }
+
+    /// Estimated memory of the search indexes and builds under way of
+    /// books other than `book_id`
+    #[cfg(feature = "search")]
+    fn index_bytes_elsewhere(&self, book_id: &str) -> usize {
+        // Skip the sums when there is no limit to check them against
+        if self.limits.max_index_bytes.is_none() {
+            return 0;
+        }
+        let indexes = self
+            .search_indices
+            .iter()
+            .filter(|(id, _)| id.as_str() != book_id)
+            .map(|(_, index)| index.memory_bytes());
+        let builds = self
+            .index_builders
+            .iter()
+            .filter(|(id, _)| id.as_str() != book_id)
+            .map(|(_, builder)| builder.memory_bytes());
+        indexes.sum::<usize>() + builds.sum::<usize>()
+    }
+
+    #[cfg(feature = "search")]
+    fn search_index(&self, book_id: &str) -> ProcessorResult<&SearchIndex> {
+        self.search_indices
+            .get(book_id)
+            .ok_or_else(|| ProcessorError::IndexNotBuilt(book_id.to_string()))
+    }
+}
+
+/// Whether `used` is at least [`PRESSURE_PERCENT`] of `max`
+///
+/// Computed in `u128`: byte counts past about 43 MB overflow a `usize` on
+/// wasm32 when multiplied by 100.
+fn under_pressure(used: usize, max: usize) -> bool {
+    used as u128 * 100 >= max as u128 * PRESSURE_PERCENT as u128
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epub::{IdSource, LoadProgress, ParseWarning, WarningCode};
+
+    #[test]
+    fn test_processor_creation() {
+        let processor = Processor::new();
+        assert!(processor.loaded_books().is_empty());
+    }
+
+    /// A book without an identifier, titled "Plates"
+    fn edition(text: &str) -> Vec<u8> {
+        let chapter = format!("<html><body><p>{}</p></body></html>", text);
+        crate::epub::tests::build_epub(&[
+            (
+                "META-INF/container.xml",
+                br#"<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles><rootfile full-path="content.opf"/></rootfiles>
+</container>"#,
+            ),
+            (
+                "content.opf",
+                br#"<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/"><dc:title>Plates</dc:title></metadata>
+  <manifest><item id="ch1" href="ch1.xhtml" media-type="application/xhtml+xml"/></manifest>
+  <spine><itemref idref="ch1"/></spine>
+</package>"#,
+            ),
+            ("ch1.xhtml", chapter.as_bytes()),
+        ])
+    }
+
+    #[test]
+    fn test_book_id_collisions() {
+        let mut processor = Processor::new();
+        let options = LoadOptions::default();
+        let first = processor.load_book(edition("First"), &options).unwrap();
+        assert_eq!(first.id_source, IdSource::ContentHash);
+        assert_eq!(first.id, format!("book-{}", &first.fingerprint[..16]));
+
+        // Same content keeps its id
+        let again = processor.load_book(edition("First"), &options).unwrap();
+        assert_eq!(again.id, first.id);
+        assert_eq!(processor.loaded_books().len(), 1);
+
+        // A second edition with the same title gets its own id
+        let second = processor.load_book(edition("Second"), &options).unwrap();
+        assert_ne!(second.id, first.id);
+
+        // Editions sharing an identifier are suffixed, rejected or replaced
+        let sample = processor
+            .load_book(crate::epub::tests::sample_epub(), &options)
+            .unwrap();
+        assert_eq!(sample.id_source, IdSource::Identifier);
+        let mut book = EpubBook::from_bytes(&edition("Third")).unwrap();
+        book.id = sample.id.clone();
+        let suffixed = processor
+            .insert_book(book, CollisionStrategy::Suffix)
+            .unwrap();
+        assert_eq!(suffixed.id, "urn:test:lazy-2");
+
+        let mut book = EpubBook::from_bytes(&edition("Fourth")).unwrap();
+        book.id = sample.id.clone();
+        assert!(matches!(
+            processor.insert_book(book, CollisionStrategy::Error),
+            Err(ProcessorError::IdCollision(id)) if id == "urn:test:lazy"
+        ));
+
+        #[cfg(feature = "search")]
+        processor
+            .build_search_index(&sample.id, &IndexOptions::default())
+            .unwrap();
+        let mut book = EpubBook::from_bytes(&edition("Fourth")).unwrap();
+        book.id = sample.id.clone();
+        let replaced = processor
+            .insert_book(book, CollisionStrategy::Replace)
+            .unwrap();
+        assert_eq!(replaced.id, sample.id);
+        assert_eq!(
+            processor.book(&sample.id).unwrap().fingerprint,
+            replaced.fingerprint
+        );
+        assert_eq!(processor.loaded_books().len(), 4);
+        // The old edition's index is gone with it
+        #[cfg(feature = "search")]
+        assert!(matches!(
+            processor.search(&sample.id, "lazy", 10, 0),
+            Err(ProcessorError::IndexNotBuilt(_))
+        ));
+    }
+
+    #[cfg(feature = "search")]
+    #[test]
+    fn test_non_linear_items() {
+        let data = crate::epub::tests::build_epub(&[
+            (
+                "META-INF/container.xml",
+                br#"<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles><rootfile full-path="content.opf"/></rootfiles>
+</container>"#,
+            ),
+            (
+                "content.opf",
+                br#"<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/"><dc:identifier>quiz</dc:identifier></metadata>
+  <manifest>
+    <item id="ch1" href="ch1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="answers" href="answers.xhtml" media-type="application/xhtml+xml"/>
+    <item id="ch2" href="ch2.xhtml" media-type="application/xhtml+xml"/>
+  </manifest>
+  <spine>
+    <itemref idref="ch1"/><itemref idref="answers" linear="no"/><itemref idref="ch2"/>
+  </spine>
+</package>"#,
+            ),
+            (
+                "ch1.xhtml",
+                br#"<html><body><p>Question one: what is a <a href="answers.xhtml#a1">cell</a>?</p></body></html>"#,
+            ),
+            (
+                "answers.xhtml",
+                b"<html><body><p id=\"a1\">A cell is the unit of life.</p></body></html>",
+            ),
+            ("ch2.xhtml", b"<html><body><p>The cell divides.</p></body></html>"),
+        ]);
+        let mut processor = Processor::new();
+        let book = processor.load_book(data, &LoadOptions::default()).unwrap();
+
+        let auxiliary = processor.auxiliary_items(&book.id).unwrap();
+        assert_eq!(auxiliary.len(), 1);
+        assert_eq!(auxiliary[0].spine_index, 1);
+        assert_eq!(auxiliary[0].href, "answers.xhtml");
+        assert_eq!(auxiliary[0].linked_from, vec![0]);
+        // Generated ToCs leave non-linear items out
+        assert_eq!(auxiliary[0].title, None);
+        let chapter = processor
+            .get_chapter(&book.id, &auxiliary[0].href, &ChapterOptions::default())
+            .unwrap();
+        assert!(chapter.html.contains("unit of life"));
+
+        let stats = processor.statistics(&book.id, None).unwrap();
+        assert!(!stats.chapters[1].linear);
+        assert_eq!(stats.chapters[1].word_count, 7);
+        assert_eq!(
+            stats.word_count,
+            stats.chapters[0].word_count + stats.chapters[2].word_count
+        );
+
+        processor
+            .build_search_index(&book.id, &IndexOptions::default())
+            .unwrap();
+        let results = processor.search(&book.id, "cell", 10, 0).unwrap();
+        let flags: Vec<(usize, bool)> = results
+            .iter()
+            .map(|result| (result.spine_index, result.non_linear))
+            .collect();
+        assert_eq!(flags.len(), 3);
+        assert!(flags.contains(&(0, false)));
+        assert!(flags.contains(&(1, true)));
+        assert!(flags.contains(&(2, false)));
+    }
+
+    #[cfg(feature = "search")]
+    #[test]
+    fn test_missing_book_and_index() {
+        let processor = Processor::new();
+        assert!(matches!(
+            processor.get_resource("nope", "a.css"),
+            Err(ProcessorError::BookNotFound(_))
+        ));
+        assert!(matches!(
+            processor.search("nope", "query", 10, 0),
+            Err(ProcessorError::IndexNotBuilt(_))
+        ));
+        assert_eq!(
+            ProcessorError::IndexNotBuilt("nope".to_string()).to_string(),
+            "Search index not built for 'nope'. Call buildSearchIndex first."
+        );
+    }
+
+    #[test]
+    fn test_limits() {
+        let data = edition("First");
+        let mut processor = Processor::with_limits(ProcessorLimits {
+            max_book_bytes: Some(data.len() + 16),
+            max_books: Some(1),
+            ..Default::default()
+        });
+        let options = LoadOptions::default();
+        let first = processor.load_book(data.clone(), &options).unwrap();
+        // Reloading a book replaces it, so does not count
+        processor.load_book(data, &options).unwrap();
+
+        let err = processor
+            .load_book(edition("Second"), &options)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ProcessorError::LimitExceeded {
+                limit: ResourceLimit::Books,
+                requested: 2,
+                max: 1,
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Resource limit exceeded (books): 2 requested, limit is 1"
+        );
+        assert_eq!(processor.loaded_books(), vec![first.id.clone()]);
+
+        processor.unload_book(&first.id);
+        let numbers: Vec<String> = (0..500).map(|n| n.to_string()).collect();
+        let large = edition(&numbers.join(" "));
+        assert!(matches!(
+            processor.load_book(large, &options),
+            Err(ProcessorError::LimitExceeded {
+                limit: ResourceLimit::BookSize,
+                ..
+            })
+        ));
+        assert!(processor.loaded_books().is_empty());
+    }
+
+    #[test]
+    fn test_book_handles() {
+        let mut processor = Processor::new();
+        let options = LoadOptions::default();
+        let texts = ["First", "Second"];
+        let ids: Vec<String> = texts
+            .iter()
+            .map(|text| processor.load_book(edition(text), &options).unwrap().id)
+            .collect();
+        let handles: Vec<Arc<EpubBook>> = ids
+            .iter()
+            .map(|id| processor.book_handle(id).unwrap())
+            .collect();
+
+        // Each book is read on its own thread while the processor goes on
+        std::thread::scope(|scope| {
+            for (handle, text) in handles.iter().zip(texts) {
+                scope.spawn(move || {
+                    for _ in 0..20 {
+                        let chapter = handle.get_chapter_content("ch1.xhtml").unwrap();
+                        assert!(chapter.html.contains(text));
+                    }
+                });
+            }
+            processor.unload_book(&ids[0]);
+        });
+
+        assert_eq!(processor.loaded_books(), vec![ids[1].clone()]);
+        assert!(matches!(
+            processor.book_handle(&ids[0]),
+            Err(ProcessorError::BookNotFound(_))
+        ));
+        // An unloaded book stays readable through its handle
+        assert!(handles[0].get_chapter_content("ch1.xhtml").is_ok());
+    }
+
+    #[test]
+    fn test_events() {
+        let mut processor = Processor::with_limits(ProcessorLimits {
+            max_books: Some(2),
+            ..Default::default()
+        });
+        let options = LoadOptions::default();
+        // Nothing is collected without a listener
+        processor.load_book(edition("First"), &options).unwrap();
+        assert!(processor.take_events().is_empty());
+
+        processor.set_events_enabled(true);
+        let data = crate::epub::tests::build_epub(&[
+            (
+                "META-INF/container.xml",
+                br#"<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles><rootfile full-path="content.opf"/></rootfiles>
+</container>"#,
+            ),
+            (
+                "content.opf",
+                br#"<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/"><dc:title>Gaps</dc:title></metadata>
+  <manifest>
+    <item id="ch1" href="ch1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="ch2" href="ch2.xhtml" media-type="application/xhtml+xml"/>
+  </manifest>
+  <spine><itemref idref="ch1"/><itemref idref="ch2"/></spine>
+</package>"#,
+            ),
+            ("ch1.xhtml", b"<html><body><p>Only one.</p></body></html>"),
+        ]);
+        let book = processor.load_book(data, &options).unwrap();
+        assert_eq!(
+            processor.take_events(),
+            vec![
+                ProcessorEvent::LoadProgress(LoadProgress {
+                    loaded: 0,
+                    total: 2
+                }),
+                ProcessorEvent::LoadProgress(LoadProgress {
+                    loaded: 1,
+                    total: 2
+                }),
+                ProcessorEvent::LoadProgress(LoadProgress {
+                    loaded: 2,
+                    total: 2
+                }),
+                ProcessorEvent::ParseWarning {
+                    book_id: book.id.clone(),
+                    warning: ParseWarning {
+                        code: WarningCode::SpineItemMissing,
+                        message: "Spine item 'ch2.xhtml' is not in the archive".into(),
+                        href: Some("ch2.xhtml".into()),
+                    },
+                },
+                ProcessorEvent::MemoryPressure {
+                    limit: ResourceLimit::Books,
+                    used: 2,
+                    max: 2,
+                },
+            ]
+        );
+        assert!(processor.take_events().is_empty());
+
+        #[cfg(feature = "search")]
+        {
+            processor
+                .build_search_index_chunked(&book.id, 1, &IndexOptions::default())
+                .unwrap();
+            assert!(matches!(
+                processor.take_events().as_slice(),
+                [ProcessorEvent::IndexProgress {
+                    progress: IndexProgress { indexed: 1, .. },
+                    ..
+                }]
+            ));
+        }
+
+        processor.set_events_enabled(false);
+        processor.unload_book(&book.id);
+        processor.load_book(edition("Second"), &options).unwrap();
+        assert!(processor.take_events().is_empty());
+    }
+
+    #[cfg(feature = "search")]
+    #[test]
+    fn test_index_memory_limit() {
+        let options = IndexOptions::default();
+        let mut unlimited = Processor::new();
+        let book = unlimited
+            .load_book(crate::epub::tests::sample_epub(), &LoadOptions::default())
+            .unwrap();
+        unlimited.build_search_index(&book.id, &options).unwrap();
+        let bytes = unlimited.search_index(&book.id).unwrap().memory_bytes();
+        assert!(bytes > 0);
+
+        let mut processor = Processor::with_limits(ProcessorLimits {
+            max_index_bytes: Some(bytes - 1),
+            ..Default::default()
+        });
+        processor
+            .load_book(crate::epub::tests::sample_epub(), &LoadOptions::default())
+            .unwrap();
+        let over_limit = |result: ProcessorResult<_>| {
+            matches!(
+                result,
+                Err(ProcessorError::LimitExceeded {
+                    limit: ResourceLimit::IndexMemory,
+                    ..
+                })
+            )
+        };
+        assert!(over_limit(processor.build_search_index(&book.id, &options)));
+        assert!(over_limit(
+            processor
+                .build_search_index_chunked(&book.id, 1, &options)
+                .map(|_| ())
+        ));
+        assert!(processor.index_builders.is_empty());
+        let exported = unlimited.export_search_index(&book.id).unwrap();
+        assert!(over_limit(
+            processor.import_search_index(&book.id, &exported)
+        ));
+        assert!(matches!(
+            processor.search(&book.id, "plate", 10, 0),
+            Err(ProcessorError::IndexNotBuilt(_))
+        ));
+
+        processor.limits.max_index_bytes = Some(bytes);
+        processor.build_search_index(&book.id, &options).unwrap();
+    }
+
+    #[cfg(feature = "search")]
+    #[test]
+    fn test_import_index_of_other_book() {
+        let mut processor = Processor::new();
+        let book = processor
+            .load_book(crate::epub::tests::sample_epub(), &LoadOptions::default())
+            .unwrap();
+        processor
+            .build_search_index(&book.id, &IndexOptions::default())
+            .unwrap();
+        let exported = processor.export_search_index(&book.id).unwrap();
+
+        assert!(matches!(
+            processor.import_search_index("other", &exported),
+            Err(ProcessorError::Search(SearchError::WrongBook { .. }))
+        ));
+        assert!(matches!(
+            processor.search("other", "plate", 10, 0),
+            Err(ProcessorError::IndexNotBuilt(_))
+        ));
+        processor.import_search_index(&book.id, &exported).unwrap();
+    }
+
+    #[cfg(feature = "search")]
+    #[test]
+    fn test_chunked_index_build() {
+        let mut processor = Processor::new();
+        let book = processor
+            .load_book(crate::epub::tests::sample_epub(), &LoadOptions::default())
+            .unwrap();
+        assert!(matches!(
+            processor.build_search_index_chunked("nope", 1, &IndexOptions::default()),
+            Err(ProcessorError::BookNotFound(_))
+        ));
+
+        let progress = processor
+            .build_search_index_chunked(&book.id, 0, &IndexOptions::default())
+            .unwrap();
+        assert_eq!(
+            progress,
+            IndexProgress {
+                indexed: 1,
+                total: 1,
+                done: true,
+            }
+        );
+        let results = processor.search(&book.id, "plate", 10, 0).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].href, "ch1.xhtml");
+
+        // Finished builds are dropped, so the next call starts over
+        let progress = processor
+            .build_search_index_chunked(&book.id, 5, &IndexOptions::default())
+            .unwrap();
+        assert_eq!(progress.indexed, 1);
+        assert!(processor.index_builders.is_empty());
+    }
+
+    #[cfg(feature = "cfi")]
+    #[test]
+    fn test_percentage_conversions() {
+        let mut processor = Processor::new();
+        let book = processor
+            .load_book(crate::epub::tests::sample_epub(), &LoadOptions::default())
+            .unwrap();
+
+        // "Plate one" is the book's only text
+        let cfi = processor.percentage_to_cfi(&book.id, 0.5).unwrap();
+        assert_eq!(cfi, "epubcfi(/6/2[ch1]!/2/2/1:5)");
+        let fraction = processor.cfi_to_percentage(&book.id, &cfi).unwrap();
+        assert!((fraction - 5.0 / 9.0).abs() < 1e-9);
+
+        let end = processor.percentage_to_cfi(&book.id, 1.0).unwrap();
+        assert_eq!(processor.cfi_to_percentage(&book.id, &end).unwrap(), 1.0);
+        assert_eq!(
+            processor
+                .cfi_to_percentage(&book.id, "epubcfi(/6/2[ch1]!/2)")
+                .unwrap(),
+            0.0
+        );
+        assert!(processor.percentage_to_cfi(&book.id, 1.5).is_err());
+        assert_eq!(processor.text_lengths[&book.id], vec![9]);
+    }
+
+    #[test]
+    fn test_hyphenation_languages() {
+        let mut processor = Processor::new();
+        let count = processor
+            .load_hyphenation_patterns("en_US", "hy3ph he2n hen5at", None, None, None)
+            .unwrap();
+        assert_eq!(count, 3);
+        processor
+            .load_hyphenation_patterns("de", "1na", None, None, None)
+            .unwrap();
+        // Built-in patterns are listed too, in builds that have them
+        let languages = processor.hyphenation_languages();
+        assert!(languages.contains(&"en-us".to_string()));
+        assert!(languages.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(processor.hyphenate("xx", "hyphenation").is_empty());
+    }
+
+    #[cfg(feature = "hyphenation-en")]
+    #[test]
+    fn test_builtin_hyphenation() {
+        let processor = Processor::new();
+        assert!(processor
+            .hyphenation_languages()
+            .contains(&"en".to_string()));
+        // hy-phen-ation
+        assert_eq!(processor.hyphenate("en-GB", "hyphenation"), vec![2, 6]);
+    }
+
+    #[test]
+    fn test_under_pressure_with_byte_counts() {
+        const MB: usize = 1024 * 1024;
+        assert!(under_pressure(2, 2));
+        assert!(!under_pressure(1, 2));
+        // Past u32::MAX / 100, where the products overflowed on wasm32
+        assert!(under_pressure(200 * MB, 250 * MB));
+        assert!(!under_pressure(199 * MB, 250 * MB));
+        assert!(under_pressure(usize::MAX, usize::MAX));
+    }
+}
@@ -0,0 +1,300 @@
+//! Inverted index with BM25 ranking
+//!
+//! Chapters are tokenized with `search-core`'s word segmentation over their
+//! normalized text, so terms and positions line up with what the reader
+//! paginates and what excerpts are cut from. Chinese, Japanese and Thai,
+//! which have no spaces between words, are indexed as character bigrams.
+//! Each chapter is one BM25 document.
+//!
+//! The index is derived from the chapters whenever a [`super::SearchIndex`]
+//! is built or imported; it is not part of the serialized format.
+
+use std::collections::HashMap;
+use std::mem::size_of;
+
+use search_core::{index_terms, normalize_for_search, search_terms, IndexedChapter};
+
+/// Term frequency saturation
+const K1: f32 = 1.2;
+
+/// Document length normalization
+const B: f32 = 0.75;
+
+/// Occurrences of a term in one chapter
+#[derive(Debug, Clone)]
+struct Posting {
+    chapter: u32,
+    /// Byte offsets into the chapter's normalized text
+    positions: Vec<u32>,
+}
+
+/// A chapter's relevance to a query
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoredChapter {
+    /// Index into the indexed chapters
+    pub chapter: usize,
+    pub score: f32,
+}
+
+/// Term → chapter postings for a book
+#[derive(Debug, Clone, Default)]
+pub struct InvertedIndex {
+    postings: HashMap<String, Vec<Posting>>,
+    /// Tokens per chapter
+    lengths: Vec<u32>,
+    average_length: f32,
+}
+
+impl InvertedIndex {
+    /// Index every chapter's normalized text
+    pub fn build(chapters: &[IndexedChapter]) -> Self {
+        let mut postings: HashMap<String, Vec<Posting>> = HashMap::new();
+        let mut lengths = Vec::with_capacity(chapters.len());
+
+        for (chapter, indexed) in chapters.iter().enumerate() {
+            let words = index_terms(&indexed.text);
+            lengths.push(words.len() as u32);
+
+            for word in words {
+                let start = word.start as u32;
+                let list = postings.entry(indexed.text[word].to_string()).or_default();
+                match list.last_mut() {
+                    Some(posting) if posting.chapter == chapter as u32 => {
+                        posting.positions.push(start)
+                    }
+                    _ => list.push(Posting {
+                        chapter: chapter as u32,
+                        positions: vec![start],
+                    }),
+                }
+            }
+        }
+
+        let total: u64 = lengths.iter().map(|&len| len as u64).sum();
+        let average_length = if lengths.is_empty() {
+            0.0
+        } else {
+            total as f32 / lengths.len() as f32
+        };
+        Self {
+            postings,
+            lengths,
+            average_length,
+        }
+    }
+
+    /// Total tokens across all chapters
+    pub fn token_count(&self) -> usize {
+        self.lengths.iter().map(|&len| len as usize).sum()
+    }
+
+    /// Rough heap size of the postings, in bytes
+    pub fn memory_bytes(&self) -> usize {
+        let postings: usize = self
+            .postings
+            .iter()
+            .map(|(term, list)| {
+                size_of::<(String, Vec<Posting>)>()
+                    + term.len()
+                    + list
+                        .iter()
+                        .map(|posting| size_of::<Posting>() + 4 * posting.positions.len())
+                        .sum::<usize>()
+            })
+            .sum();
+        postings + 4 * self.lengths.len()
+    }
+
+    /// Chapters containing any query term, best first
+    pub fn score(&self, terms: &[String]) -> Vec<ScoredChapter> {
+        let weighted: Vec<(&str, f32)> = dedup(terms)
+            .into_iter()
+            .map(|term| (term.as_str(), 1.0))
+            .collect();
+        self.score_weighted(&weighted)
+    }
+
+    /// Chapters containing any query term, best first, with each term's
+    /// contribution scaled by its weight
+    pub fn score_weighted(&self, terms: &[(&str, f32)]) -> Vec<ScoredChapter> {
+        let mut scores: HashMap<u32, f32> = HashMap::new();
+        for &(term, weight) in terms {
+            let Some(list) = self.postings.get(term) else {
+                continue;
+            };
+            let idf = weight * self.idf(list.len());
+            for posting in list {
+                let tf = posting.positions.len() as f32;
+                let length = self.lengths[posting.chapter as usize] as f32;
+                let norm = 1.0 - B + B * length / self.average_length.max(1.0);
+                *scores.entry(posting.chapter).or_default() +=
+                    idf * tf * (K1 + 1.0) / (tf + K1 * norm);
+            }
+        }
+
+        let mut scored: Vec<ScoredChapter> = scores
+            .into_iter()
+            .map(|(chapter, score)| ScoredChapter {
+                chapter: chapter as usize,
+                score,
+            })
+            .collect();
+        // Ties keep spine order
+        scored.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.chapter.cmp(&b.chapter))
+        });
+        scored
+    }
+
+    /// Offsets of `term` in a chapter's normalized text
+    pub fn positions(&self, term: &str, chapter: usize) -> &[u32] {
+        self.postings
+            .get(term)
+            .and_then(|list| {
+                list.binary_search_by_key(&(chapter as u32), |posting| posting.chapter)
+                    .ok()
+                    .map(|i| list[i].positions.as_slice())
+            })
+            .unwrap_or_default()
+    }
+
+    /// Inverse document frequency of a term found in `matching` chapters
+    ///
+    /// Uses Lucene's `ln(1 + ...)` form, which stays positive for terms that
+    /// occur in most chapters.
+    pub fn idf(&self, matching: usize) -> f32 {
+        let total = self.lengths.len() as f32;
+        let matching = matching as f32;
+        (1.0 + (total - matching + 0.5) / (matching + 0.5)).ln()
+    }
+
+    /// Indexed terms starting with `prefix`, in alphabetical order
+    pub fn terms_with_prefix(&self, prefix: &str) -> Vec<&str> {
+        let mut terms: Vec<&str> = self
+            .postings
+            .keys()
+            .map(String::as_str)
+            .filter(|term| term.starts_with(prefix))
+            .collect();
+        terms.sort_unstable();
+        terms
+    }
+
+    /// Indexed terms within `max_distance` edits of `term`, closest first
+    ///
+    /// Distances are Levenshtein distances over characters. Ties are in
+    /// alphabetical order.
+    pub fn terms_within(&self, term: &str, max_distance: usize) -> Vec<(&str, usize)> {
+        let query: Vec<char> = term.chars().collect();
+        let mut terms: Vec<(&str, usize)> = self
+            .postings
+            .keys()
+            .filter_map(|candidate| {
+                let distance = levenshtein_within(&query, candidate, max_distance)?;
+                Some((candidate.as_str(), distance))
+            })
+            .collect();
+        terms.sort_unstable_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(b.0)));
+        terms
+    }
+
+    /// Number of chapters containing `term`
+    pub fn chapter_frequency(&self, term: &str) -> usize {
+        self.postings.get(term).map_or(0, Vec::len)
+    }
+}
+
+/// Normalized terms of a query, in order
+pub fn query_terms(query: &str) -> Vec<String> {
+    let normalized = normalize_for_search(query);
+    search_terms(&normalized)
+        .into_iter()
+        .map(|word| normalized[word].to_string())
+        .collect()
+}
+
+/// Levenshtein distance between two words, if it is at most `max`
+///
+/// Stops as soon as every cell of a row exceeds `max`, so scanning a
+/// book's vocabulary stays cheap.
+fn levenshtein_within(a: &[char], b: &str, max: usize) -> Option<usize> {
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        if current.iter().all(|&d| d > max) {
+            return None;
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    let distance = previous[b.len()];
+    (distance <= max).then_some(distance)
+}
+
+fn dedup(terms: &[String]) -> Vec<&String> {
+    let mut unique: Vec<&String> = Vec::with_capacity(terms.len());
+    for term in terms {
+        if !unique.contains(&term) {
+            unique.push(term);
+        }
+    }
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapter(spine_index: usize, html: &str) -> IndexedChapter {
+        IndexedChapter::from_html(format!("ch{}.xhtml", spine_index), spine_index, html)
+    }
+
+    #[test]
+    fn test_bm25_ranking() {
+        let chapters = vec![
+            chapter(0, "<p>The ship sailed at dawn. The crew slept.</p>"),
+            chapter(1, "<p>A whale! A white whale, the whale of whales.</p>"),
+            chapter(2, "<p>The whale was seen once, far off, by the boy.</p>"),
+            chapter(3, "<p>Nothing happened.</p>"),
+        ];
+        let index = InvertedIndex::build(&chapters);
+
+        let scored = index.score(&query_terms("Whale"));
+        let order: Vec<usize> = scored.iter().map(|s| s.chapter).collect();
+        assert_eq!(order, vec![1, 2]);
+        assert!(scored[0].score > scored[1].score);
+
+        // Any term matches; chapters with more of them rank higher
+        let scored = index.score(&query_terms("white whale"));
+        assert_eq!(scored[0].chapter, 1);
+        assert_eq!(scored.len(), 2);
+
+        assert!(index.score(&query_terms("ahab")).is_empty());
+        assert_eq!(index.positions("whale", 1), &[2, 17, 28]);
+        assert!(index.positions("whale", 3).is_empty());
+        assert_eq!(index.chapter_frequency("the"), 3);
+        assert_eq!(
+            index.terms_with_prefix("wh"),
+            vec!["whale", "whales", "white"]
+        );
+        assert_eq!(index.token_count(), 8 + 9 + 10 + 2);
+        assert_eq!(index.terms_within("whail", 2), vec![("whale", 2)]);
+        assert!(index.terms_within("whail", 1).is_empty());
+        assert_eq!(
+            index.terms_within("whales", 1),
+            vec![("whales", 0), ("whale", 1)]
+        );
+    }
+}
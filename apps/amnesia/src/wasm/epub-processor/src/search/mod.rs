@@ -1,12 +1,41 @@
 //! Full-text search module
 //!
-//! Provides search indexing and querying for EPUB content.
+//! Provides search indexing and querying for EPUB content. Indexes can also
+//! be imported from the server, which builds them with the same `search-core`
+//! code and binary format.
+//!
+//! Queries are answered from an [`InvertedIndex`] of the chapters' words and
+//! ranked with BM25, so a search costs the same whether the book has ten
+//! pages or a thousand.
+//!
+//! Fuzzy search also matches bare query words against indexed words a
+//! few edits away, so "Dostoevski" finds "Dostoevsky". The vocabulary is
+//! scanned once per query word, which is cheap next to building the index.
+//!
+//! Large books can be indexed a few chapters at a time with an
+//! [`IndexBuilder`], so the work can be spread across animation frames
+//! instead of blocking the UI thread until every chapter is read.
+
+pub mod inverted;
+pub mod query;
+
+pub use inverted::{query_terms, InvertedIndex, ScoredChapter};
+pub use query::Query;
+pub use search_core::{IndexOptions, TextKind};
 
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::ops::Range;
+
+use search_core::{
+    normalize_for_search, normalize_text, IndexedChapter, OffsetMap, SearchIndexData,
+};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
 
-use crate::epub::{parser, EpubBook};
+use crate::epub::EpubBook;
+use crate::text::Utf16Cursor;
 
 #[derive(Error, Debug)]
 pub enum SearchError {
@@ -15,6 +44,13 @@ pub enum SearchError {
 
     #[error("Search failed: {0}")]
     SearchFailed(String),
+
+    #[error("Invalid search index: {0}")]
+    InvalidIndex(#[from] search_core::IndexDecodeError),
+
+    /// An imported index was built for a different book
+    #[error("Search index is for book '{found}', not '{expected}'")]
+    WrongBook { expected: String, found: String },
 }
 
 /// A search result
@@ -25,89 +61,328 @@ pub struct SearchResult {
     pub href: String,
     /// Spine index
     pub spine_index: usize,
-    /// CFI of the result location
+    /// Range CFI of the match; just the chapter's CFI when the book was
+    /// not loaded to map the match through its DOM
     pub cfi: String,
     /// Text excerpt with match highlighted
     pub excerpt: String,
-    /// Character position in chapter
+    /// Byte offset of the match in the chapter's normalized search text
     pub position: usize,
+    /// Start of the match in `ChapterText.text`, in UTF-16 code units
+    pub text_start: usize,
+    /// End of the match in `ChapterText.text`, in UTF-16 code units
+    pub text_end: usize,
+    /// The query clause matched, normalized: a word, a run of words, a
+    /// `"phrase"`, a `prefix*`, or a `word~` matched misspelled by a fuzzy
+    /// search
+    pub clause: String,
+    /// Whether the match is in body text, alt text, a caption or a footnote
+    pub kind: TextKind,
+    /// Whether the match is in a non-linear spine item (an answer key,
+    /// pop-up notes) outside the reading order; only known when the book
+    /// is loaded
+    pub non_linear: bool,
+    /// BM25 relevance of the result's chapter to the query
+    pub score: f32,
+}
+
+/// A search result tagged with the book it came from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BookSearchResult {
+    pub book_id: String,
+    #[serde(flatten)]
+    pub result: SearchResult,
+}
+
+/// Search several books, best matches first
+///
+/// Each book's results are ranked against its own statistics, so a term
+/// that is rare in one book counts for more there. Ties keep the order the
+/// indices are given in.
+pub fn search_all<'a>(
+    indices: impl IntoIterator<Item = (&'a str, &'a SearchIndex)>,
+    query: &str,
+    limit: usize,
+) -> Vec<BookSearchResult> {
+    let mut results: Vec<BookSearchResult> = indices
+        .into_iter()
+        .flat_map(|(book_id, index)| {
+            index
+                .search(query, limit)
+                .into_iter()
+                .map(move |result| BookSearchResult {
+                    book_id: book_id.to_string(),
+                    result,
+                })
+        })
+        .collect();
+
+    results.sort_by(|a, b| b.result.score.total_cmp(&a.result.score));
+    results.truncate(limit);
+    results
+}
+
+/// Flag the results that are in non-linear spine items of `book`
+pub fn mark_non_linear<'a>(
+    book: &EpubBook,
+    results: impl IntoIterator<Item = &'a mut SearchResult>,
+) {
+    for result in results {
+        result.non_linear = book
+            .spine
+            .get(result.spine_index)
+            .is_some_and(|item| !item.linear);
+    }
+}
+
+/// Most edits per word a fuzzy search allows
+pub const MAX_FUZZINESS: u8 = 2;
+
+/// How far an [`IndexBuilder`] has got
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexProgress {
+    /// Chapters read so far, including ones that could not be read
+    pub indexed: usize,
+    /// Chapters to read in all (chunks of oversize spine items count
+    /// separately)
+    pub total: usize,
+    /// Whether every chapter has been read
+    pub done: bool,
+}
+
+/// A search index built a few chapters at a time
+///
+/// Each [`IndexBuilder::step`] reads the next chapters of the book; once
+/// all are read, [`IndexBuilder::finish`] builds the word postings and
+/// returns the index.
+pub struct IndexBuilder {
+    book_id: String,
+    options: IndexOptions,
+    /// Spine index and href of every chapter to read, in reading order
+    hrefs: Vec<(usize, String)>,
+    /// Chapters read so far, as an index into `hrefs`
+    next: usize,
+    chapters: Vec<IndexedChapter>,
+}
+
+impl IndexBuilder {
+    /// Start indexing a book; no chapter is read yet
+    pub fn new(book: &EpubBook, options: &IndexOptions) -> Self {
+        // Oversize items are indexed per chunk so results point at the
+        // chunk that contains them
+        let hrefs = (0..book.spine.len())
+            .flat_map(|spine_index| {
+                book.reading_hrefs(spine_index)
+                    .into_iter()
+                    .map(move |href| (spine_index, href))
+            })
+            .collect();
+        Self {
+            book_id: book.id.clone(),
+            options: *options,
+            hrefs,
+            next: 0,
+            chapters: Vec::new(),
+        }
+    }
+
+    /// Read up to `count` more chapters
+    ///
+    /// `book` must be the book the builder was started for.
+    pub fn step(&mut self, book: &EpubBook, count: usize) -> IndexProgress {
+        let end = self.next.saturating_add(count).min(self.hrefs.len());
+        for (spine_index, href) in &self.hrefs[self.next..end] {
+            let content = match book.get_chapter_content(href) {
+                Ok(c) => c,
+                Err(_) => continue, // Skip chapters we can't read
+            };
+
+            self.chapters.push(IndexedChapter::from_html_with(
+                href.clone(),
+                *spine_index,
+                &content.html,
+                &self.options,
+            ));
+        }
+        self.next = end;
+        self.progress()
+    }
+
+    pub fn progress(&self) -> IndexProgress {
+        IndexProgress {
+            indexed: self.next,
+            total: self.hrefs.len(),
+            done: self.next == self.hrefs.len(),
+        }
+    }
+
+    /// Rough heap size of the chapters read so far, in bytes; postings
+    /// built by [`IndexBuilder::finish`] come on top
+    pub fn memory_bytes(&self) -> usize {
+        self.chapters.iter().map(chapter_bytes).sum()
+    }
+
+    /// The index of the chapters read so far
+    pub fn finish(self) -> SearchIndex {
+        SearchIndex::new(self.book_id, self.chapters)
+    }
 }
 
 /// Search index for a book
 pub struct SearchIndex {
+    /// Book the index belongs to
+    book_id: String,
     /// Indexed chapters
-    chapters: Vec<ChapterIndex>,
-}
-
-/// Index for a single chapter
-struct ChapterIndex {
-    href: String,
-    spine_index: usize,
-    /// Normalized text content
-    text: String,
-    /// Original text (for excerpts)
-    original_text: String,
+    chapters: Vec<IndexedChapter>,
+    /// Word postings over `chapters`
+    inverted: InvertedIndex,
 }
 
 impl SearchIndex {
     /// Build a search index for a book
     pub fn build(book: &EpubBook) -> Result<Self, SearchError> {
-        let mut chapters = Vec::new();
+        Self::build_with(book, &IndexOptions::default())
+    }
 
-        for (spine_index, item) in book.spine.iter().enumerate() {
-            // Get chapter content
-            let content = match book.get_chapter_content(&item.href) {
-                Ok(c) => c,
-                Err(_) => continue, // Skip chapters we can't read
-            };
+    /// Build a search index that also covers the alt text, captions and
+    /// footnotes `options` asks for
+    pub fn build_with(book: &EpubBook, options: &IndexOptions) -> Result<Self, SearchError> {
+        let mut builder = IndexBuilder::new(book, options);
+        builder.step(book, usize::MAX);
+        Ok(builder.finish())
+    }
 
-            // Extract plain text
-            let original_text = parser::extract_plain_text(&content.html);
-            let text = normalize_for_search(&original_text);
+    /// Load an index serialized by the server or [`SearchIndex::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SearchError> {
+        let data = SearchIndexData::from_bytes(bytes)?;
+        Ok(Self::new(data.book_id, data.chapters))
+    }
 
-            chapters.push(ChapterIndex {
-                href: item.href.clone(),
-                spine_index,
-                text,
-                original_text,
-            });
+    fn new(book_id: String, chapters: Vec<IndexedChapter>) -> Self {
+        let inverted = InvertedIndex::build(&chapters);
+        Self {
+            book_id,
+            chapters,
+            inverted,
         }
+    }
 
-        Ok(Self { chapters })
+    /// Serialize the index in the shared `search-core` format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        SearchIndexData::new(self.book_id.clone(), self.chapters.clone()).to_bytes()
+    }
+
+    /// Book the index was built for
+    pub fn book_id(&self) -> &str {
+        &self.book_id
+    }
+
+    /// Rough heap size of the index, in bytes
+    pub fn memory_bytes(&self) -> usize {
+        self.chapters.iter().map(chapter_bytes).sum::<usize>() + self.inverted.memory_bytes()
     }
 
     /// Search for a query in the book
+    ///
+    /// See [`query`] for the syntax. Chapters matching the query are ranked
+    /// by BM25 over the words of its clauses that are not negated, best
+    /// first. Within a chapter, results are the places those clauses match,
+    /// in reading order, each naming the clause it matched.
     pub fn search(&self, query: &str, limit: usize) -> Vec<SearchResult> {
-        let normalized_query = normalize_for_search(query);
-        let mut results = Vec::new();
-
-        for chapter in &self.chapters {
-            // Find all occurrences in this chapter
-            let mut search_pos = 0;
-            while let Some(pos) = chapter.text[search_pos..].find(&normalized_query) {
-                let absolute_pos = search_pos + pos;
+        self.search_fuzzy(query, limit, 0)
+    }
 
-                // Create excerpt
-                let excerpt = create_excerpt(&chapter.original_text, absolute_pos, query.len());
+    /// Search, letting bare query words match words up to `fuzziness`
+    /// edits (insertions, deletions or substitutions) away
+    ///
+    /// `fuzziness` is capped at [`MAX_FUZZINESS`], and shorter words allow
+    /// fewer edits (see [`edits_for`]). Misspelled matches count for less
+    /// in the ranking than exact ones. Phrases and prefixes match exactly.
+    pub fn search_fuzzy(&self, query: &str, limit: usize, fuzziness: u8) -> Vec<SearchResult> {
+        let Some(query) = Query::parse(query) else {
+            return Vec::new();
+        };
+        let mut expansions = Expansions::default();
+        let mut terms: Vec<(&str, f32)> = Vec::new();
+        let mut add_term = |term, weight: f32| match terms.iter_mut().find(|(t, _)| *t == term) {
+            Some(entry) => entry.1 = entry.1.max(weight),
+            None => terms.push((term, weight)),
+        };
+
+        for term in query.positive_terms() {
+            add_term(term, 1.0);
+        }
+        for prefix in query.positive_prefixes() {
+            let expanded = self.inverted.terms_with_prefix(prefix);
+            for &term in &expanded {
+                add_term(term, 1.0);
+            }
+            expansions.prefixes.insert(prefix, expanded);
+        }
+        for word in query.positive_words() {
+            let edits = edits_for(word, fuzziness);
+            if edits == 0 {
+                continue;
+            }
+            let variants = self.inverted.terms_within(word, edits);
+            for &(term, distance) in &variants {
+                add_term(term, 1.0 / (1.0 + distance as f32));
+            }
+            expansions.fuzzy.insert(word, variants);
+        }
+        let mut results = Vec::new();
 
-                // Generate CFI (simplified - would need actual DOM mapping)
-                let cfi = format!(
-                    "epubcfi(/6/{}!/4:{})",
-                    (chapter.spine_index + 1) * 2,
-                    absolute_pos
+        for ScoredChapter {
+            chapter: index,
+            score,
+        } in self.inverted.score_weighted(&terms)
+        {
+            let Some(mut hits) = self.hits(&query, index, &expansions) else {
+                continue;
+            };
+            // One result per place, for the longest clause matching there
+            hits.sort_by_key(|hit| (hit.position, Reverse(hit.len)));
+            hits.dedup_by_key(|hit| hit.position);
+
+            let chapter = &self.chapters[index];
+            // Hits are in order, so offsets are translated in a single pass
+            let offsets = OffsetMap::new(&chapter.original_text);
+            let mut utf16 = Utf16Cursor::new(&chapter.original_text);
+
+            for Hit {
+                position,
+                len,
+                clause,
+            } in hits
+            {
+                let original = offsets.to_original(position)..offsets.to_original(position + len);
+                // Alt text and notes get excerpts of their own
+                let (kind, scope) = chapter.kind_at(original.start);
+                let excerpt = create_excerpt(
+                    &chapter.original_text[scope.clone()],
+                    original.start - scope.start..original.end.min(scope.end) - scope.start,
                 );
 
+                // The chapter; narrowed to the match when the book is at
+                // hand (see `cfi::locate_search_results`)
+                let cfi = format!("epubcfi(/6/{}!)", (chapter.spine_index + 1) * 2);
+
                 results.push(SearchResult {
                     href: chapter.href.clone(),
                     spine_index: chapter.spine_index,
                     cfi,
                     excerpt,
-                    position: absolute_pos,
+                    position,
+                    text_start: utf16.offset_of(original.start),
+                    text_end: utf16.offset_of(original.end),
+                    clause,
+                    kind,
+                    non_linear: false,
+                    score,
                 });
 
-                // Move past this match
-                search_pos = absolute_pos + normalized_query.len();
-
                 if results.len() >= limit {
                     return results;
                 }
@@ -117,6 +392,124 @@ impl SearchIndex {
         results
     }
 
+    /// Where a query matches in a chapter; `None` if the chapter does not
+    /// match it
+    fn hits(&self, query: &Query, chapter: usize, expansions: &Expansions) -> Option<Vec<Hit>> {
+        let hits = match query {
+            Query::Words { terms, phrase } => {
+                // The whole run where it appears, otherwise any of its words
+                let hits = self.phrase_hits(&terms[0], phrase, chapter);
+                if hits.is_empty() {
+                    self.word_hits(terms, chapter, expansions)
+                } else {
+                    hits
+                }
+            }
+            Query::Phrase { terms, text } => {
+                let mut hits = self.phrase_hits(&terms[0], text, chapter);
+                for hit in &mut hits {
+                    hit.clause = format!("\"{}\"", text);
+                }
+                hits
+            }
+            Query::Prefix(prefix) => {
+                let mut hits = self.term_hits(
+                    expansions.prefixes[prefix.as_str()].iter().copied(),
+                    chapter,
+                );
+                for hit in &mut hits {
+                    hit.clause = format!("{}*", prefix);
+                }
+                hits
+            }
+            Query::And(clauses) => {
+                let mut hits = Vec::new();
+                for clause in clauses {
+                    hits.extend(self.hits(clause, chapter, expansions)?);
+                }
+                return Some(hits);
+            }
+            Query::Or(clauses) => {
+                let matched: Vec<Vec<Hit>> = clauses
+                    .iter()
+                    .filter_map(|clause| self.hits(clause, chapter, expansions))
+                    .collect();
+                return (!matched.is_empty()).then(|| matched.into_iter().flatten().collect());
+            }
+            Query::Not(clause) => {
+                return match self.hits(clause, chapter, expansions) {
+                    Some(_) => None,
+                    None => Some(Vec::new()),
+                };
+            }
+        };
+        (!hits.is_empty()).then_some(hits)
+    }
+
+    /// Occurrences of a normalized phrase starting with `first` in a chapter
+    fn phrase_hits(&self, first: &str, phrase: &str, chapter: usize) -> Vec<Hit> {
+        let text = &self.chapters[chapter].text;
+        self.inverted
+            .positions(first, chapter)
+            .iter()
+            .map(|&pos| pos as usize)
+            .filter(|&pos| text[pos..].starts_with(phrase))
+            .map(|position| Hit {
+                position,
+                len: phrase.len(),
+                clause: phrase.to_string(),
+            })
+            .collect()
+    }
+
+    /// Occurrences of any bare word of a run in a chapter, including the
+    /// misspellings fuzzy search allows
+    fn word_hits(&self, words: &[String], chapter: usize, expansions: &Expansions) -> Vec<Hit> {
+        let mut hits = Vec::new();
+        for word in words {
+            let Some(variants) = expansions.fuzzy.get(word.as_str()) else {
+                hits.extend(self.term_hits([word.as_str()], chapter));
+                continue;
+            };
+            for &(term, distance) in variants {
+                let clause = if distance == 0 {
+                    word.clone()
+                } else {
+                    format!("{}~", word)
+                };
+                hits.extend(
+                    self.inverted
+                        .positions(term, chapter)
+                        .iter()
+                        .map(|&pos| Hit {
+                            position: pos as usize,
+                            len: term.len(),
+                            clause: clause.clone(),
+                        }),
+                );
+            }
+        }
+        hits
+    }
+
+    /// Occurrences of any of `terms` in a chapter
+    fn term_hits<'a>(&self, terms: impl IntoIterator<Item = &'a str>, chapter: usize) -> Vec<Hit> {
+        let mut hits = Vec::new();
+        for term in terms {
+            hits.extend(
+                self.inverted
+                    .positions(term, chapter)
+                    .iter()
+                    .map(|&pos| Hit {
+                        position: pos as usize,
+                        len: term.len(),
+                        clause: term.to_string(),
+                    }),
+            );
+        }
+        hits
+    }
+
     /// Get total word count
     pub fn word_count(&self) -> usize {
         self.chapters.iter()
@@ -125,69 +518,92 @@ impl SearchIndex {
     }
 }
 
-/// Normalize text for search (lowercase, remove accents, normalize unicode)
-fn normalize_for_search(text: &str) -> String {
-    text.nfkd()
-        .filter(|c| !c.is_mark_nonspacing())
-        .collect::<String>()
-        .to_lowercase()
+/// Indexed terms that a query's prefixes and fuzzy words stand for
+#[derive(Default)]
+struct Expansions<'a> {
+    /// Terms starting with each prefix
+    prefixes: HashMap<&'a str, Vec<&'a str>>,
+    /// Terms near each bare word, with their edit distance, closest first
+    fuzzy: HashMap<&'a str, Vec<(&'a str, usize)>>,
 }
 
-/// Create an excerpt around a match position
-fn create_excerpt(text: &str, position: usize, match_len: usize) -> String {
-    const CONTEXT_CHARS: usize = 50;
-
-    let start = position.saturating_sub(CONTEXT_CHARS);
-    let end = (position + match_len + CONTEXT_CHARS).min(text.len());
-
-    // Find word boundaries
-    let start = text[..start].rfind(char::is_whitespace)
-        .map(|i| i + 1)
-        .unwrap_or(start);
-    let end = text[end..].find(char::is_whitespace)
-        .map(|i| end + i)
-        .unwrap_or(end);
-
-    let excerpt = &text[start..end];
-
-    // Add ellipsis if truncated
-    let prefix = if start > 0 { "..." } else { "" };
-    let suffix = if end < text.len() { "..." } else { "" };
-
-    format!("{}{}{}", prefix, excerpt.trim(), suffix)
+/// Edits a fuzzy search allows for a normalized word
+///
+/// Words of up to two characters must match exactly and words of up to
+/// five allow one edit, or short words would match most of the book.
+fn edits_for(word: &str, fuzziness: u8) -> usize {
+    let allowed = match word.chars().count() {
+        0..=2 => 0,
+        3..=5 => 1,
+        _ => 2,
+    };
+    usize::from(fuzziness.min(MAX_FUZZINESS)).min(allowed)
 }
 
-trait IsMarkNonspacing {
-    fn is_mark_nonspacing(&self) -> bool;
+/// A place a query clause matches, in the chapter's normalized text
+struct Hit {
+    position: usize,
+    len: usize,
+    clause: String,
 }
 
-impl IsMarkNonspacing for char {
-    fn is_mark_nonspacing(&self) -> bool {
-        matches!(
-            unicode_normalization::char::decompose_canonical(*self, |_| {}),
-            ()
-        ) && self.is_combining_mark()
+/// The normalized query from its first word to its last, for exact matches
+fn query_phrase(query: &str) -> String {
+    let normalized = normalize_text(&normalize_for_search(query));
+    let words = search_core::segment_words(&normalized);
+    match (words.first(), words.last()) {
+        (Some(first), Some(last)) => normalized[first.start..last.end].to_string(),
+        _ => String::new(),
     }
 }
 
-trait IsCombiningMark {
-    fn is_combining_mark(&self) -> bool;
+/// Rough heap size of an indexed chapter, in bytes
+fn chapter_bytes(chapter: &IndexedChapter) -> usize {
+    std::mem::size_of::<IndexedChapter>()
+        + chapter.href.len()
+        + chapter.original_text.len()
+        + chapter.text.len()
+        + std::mem::size_of_val(chapter.spans.as_slice())
 }
 
-impl IsCombiningMark for char {
-    fn is_combining_mark(&self) -> bool {
-        let code = *self as u32;
-        // Combining Diacritical Marks
-        (0x0300..=0x036F).contains(&code) ||
-        // Combining Diacritical Marks Extended
-        (0x1AB0..=0x1AFF).contains(&code) ||
-        // Combining Diacritical Marks Supplement
-        (0x1DC0..=0x1DFF).contains(&code) ||
-        // Combining Diacritical Marks for Symbols
-        (0x20D0..=0x20FF).contains(&code) ||
-        // Combining Half Marks
-        (0xFE20..=0xFE2F).contains(&code)
+/// Create an excerpt around a match, given as a byte range of `text`
+///
+/// Context is counted in characters, not bytes, and the excerpt starts and
+/// ends on word boundaries (UAX #29) inside that context, so it never cuts
+/// a word, a multi-byte character or a grapheme cluster such as an emoji
+/// sequence in half. Scripts without spaces break between characters, which
+/// keeps CJK excerpts to the same length.
+fn create_excerpt(text: &str, range: Range<usize>) -> String {
+    const CONTEXT_CHARS: usize = 50;
+
+    let earliest = text[..range.start]
+        .char_indices()
+        .rev()
+        .nth(CONTEXT_CHARS - 1)
+        .map_or(0, |(i, _)| i);
+    let latest = text[range.end..]
+        .char_indices()
+        .nth(CONTEXT_CHARS)
+        .map_or(text.len(), |(i, _)| range.end + i);
+
+    let mut start = range.start;
+    let mut end = range.end;
+    for (i, word) in text.split_word_bound_indices() {
+        if i >= earliest && i < start {
+            start = i;
+        }
+        if i >= latest {
+            break;
+        }
+        if i + word.len() <= latest {
+            end = end.max(i + word.len());
+        }
     }
+
+    let prefix = if start > 0 { "..." } else { "" };
+    let suffix = if end < text.len() { "..." } else { "" };
+
+    format!("{}{}{}", prefix, text[start..end].trim(), suffix)
 }
 
 #[cfg(test)]
@@ -201,10 +617,293 @@ mod tests {
         assert_eq!(normalize_for_search("Naïve"), "naive");
     }
 
+    #[test]
+    fn test_search_ranks_by_relevance() {
+        let chapter = |spine_index: usize, html: &str| {
+            IndexedChapter::from_html(format!("ch{}.xhtml", spine_index), spine_index, html)
+        };
+        let index = SearchIndex::new(
+            "book".to_string(),
+            vec![
+                chapter(0, "<p>Call me Ishmael. Some years ago I went to sea.</p>"),
+                chapter(1, "<p>The white whale. The whale! White as snow.</p>"),
+                chapter(2, "<p>A whale is a large animal; a white cat is not.</p>"),
+            ],
+        );
+
+        let results = index.search("White Whale", 10);
+        // The exact phrase is the only result in the best chapter
+        assert_eq!(results[0].spine_index, 1);
+        assert_eq!(results[0].position, 4);
+        assert!(results[0].excerpt.contains("white whale"));
+        // The other chapter has both words apart
+        let rest: Vec<(usize, usize)> = results[1..]
+            .iter()
+            .map(|r| (r.spine_index, r.position))
+            .collect();
+        assert_eq!(rest, vec![(2, 2), (2, 29)]);
+        assert!(results[0].score > results[1].score);
+
+        assert_eq!(index.search("whale", 2).len(), 2);
+        assert!(index.search("ahab", 10).is_empty());
+        assert!(index.search(" ... ", 10).is_empty());
+    }
+
+    #[test]
+    fn test_search_boolean_query() {
+        let index = SearchIndex::new(
+            "book".to_string(),
+            vec![
+                IndexedChapter::from_html("a.xhtml", 0, "<p>Machine learning in Rust.</p>"),
+                IndexedChapter::from_html(
+                    "b.xhtml",
+                    1,
+                    "<p>Machine learning in Python and Rust.</p>",
+                ),
+                IndexedChapter::from_html("c.xhtml", 2, "<p>Learning machine code in Rust.</p>"),
+                IndexedChapter::from_html("d.xhtml", 3, "<p>Learners learn what was learnt.</p>"),
+            ],
+        );
+        let matches = |query: &str| -> Vec<(usize, String)> {
+            index
+                .search(query, 10)
+                .into_iter()
+                .map(|r| (r.spine_index, r.clause))
+                .collect()
+        };
+
+        assert_eq!(
+            matches(r#""machine learning" AND rust -python"#),
+            vec![
+                (0, r#""machine learning""#.to_string()),
+                (0, "rust".to_string())
+            ]
+        );
+        assert_eq!(
+            matches("rust NOT (python OR code)"),
+            vec![(0, "rust".to_string())]
+        );
+
+        let results = matches("python OR learn*");
+        assert_eq!(results.len(), 7);
+        assert_eq!(results[0], (3, "learn*".to_string()));
+        assert!(results.contains(&(1, "python".to_string())));
+
+        // Bare words match as a run where they can, word by word elsewhere
+        let results = matches("machine learning");
+        assert!(results.contains(&(1, "machine learning".to_string())));
+        assert!(results.contains(&(2, "machine".to_string())));
+        assert!(!results.contains(&(1, "machine".to_string())));
+        assert!(matches("-rust").is_empty());
+    }
+
+    #[test]
+    fn test_search_cjk_and_thai() {
+        let index = SearchIndex::new(
+            "book".to_string(),
+            vec![
+                IndexedChapter::from_html("a.xhtml", 0, "<p>私は東京都に住んでいます。</p>"),
+                IndexedChapter::from_html("b.xhtml", 1, "<p>京都は美しい町です。</p>"),
+                IndexedChapter::from_html("c.xhtml", 2, "<p>ขอบคุณมากครับ</p>"),
+            ],
+        );
+        let matches = |query: &str| -> Vec<(usize, String)> {
+            index
+                .search(query, 10)
+                .into_iter()
+                .map(|r| {
+                    let text = &index.chapters[r.spine_index].original_text;
+                    let utf16: Vec<u16> = text.encode_utf16().collect();
+                    let matched = String::from_utf16(&utf16[r.text_start..r.text_end]).unwrap();
+                    (r.spine_index, matched)
+                })
+                .collect()
+        };
+
+        assert_eq!(matches("東京"), vec![(0, "東京".to_string())]);
+        assert_eq!(matches("住んで"), vec![(0, "住んで".to_string())]);
+        // "京都" is also inside "東京都"; the chapter about Kyoto ranks first
+        assert_eq!(
+            matches("京都"),
+            vec![(1, "京都".to_string()), (0, "京都".to_string())]
+        );
+        assert_eq!(matches("町"), vec![(1, "町".to_string())]);
+        assert_eq!(matches("คุณ"), vec![(2, "คุณ".to_string())]);
+        assert!(matches("都京").is_empty());
+    }
+
+    #[test]
+    fn test_search_fuzzy() {
+        let index = SearchIndex::new(
+            "book".to_string(),
+            vec![
+                IndexedChapter::from_html("a.xhtml", 0, "<p>Dostoevsky wrote of crime.</p>"),
+                IndexedChapter::from_html("b.xhtml", 1, "<p>Dostoevski, as some spell it.</p>"),
+                IndexedChapter::from_html("c.xhtml", 2, "<p>The cat sat on a mat.</p>"),
+            ],
+        );
+        let matches = |query: &str, fuzziness: u8| -> Vec<(usize, String)> {
+            index
+                .search_fuzzy(query, 10, fuzziness)
+                .into_iter()
+                .map(|r| (r.spine_index, r.clause))
+                .collect()
+        };
+
+        assert_eq!(matches("Dostoyevsky", 0), vec![]);
+        // Exact matches rank above misspelled ones
+        assert_eq!(
+            matches("Dostoevski", 1),
+            vec![
+                (1, "dostoevski".to_string()),
+                (0, "dostoevski~".to_string())
+            ]
+        );
+        assert_eq!(
+            matches("Dostoyevsky", 1),
+            vec![(0, "dostoyevsky~".to_string())]
+        );
+        assert_eq!(matches("Dostoyevsky", 2).len(), 2);
+        assert_eq!(matches("crme", 1), vec![(0, "crme~".to_string())]);
+        // Short words allow fewer edits, and phrases stay exact
+        assert_eq!(matches("bat", 2), vec![(2, "bat~".to_string()); 3]);
+        assert!(matches("at", 2).is_empty());
+        assert!(matches(r#""dostoyevsky wrote""#, 2).is_empty());
+        assert_eq!(matches("Dostoevski", 5), matches("Dostoevski", 2));
+    }
+
+    #[test]
+    fn test_search_alt_text_and_notes() {
+        let html = r##"<p>The harbour at dawn, as Turner saw it.</p>
+<figure><img src="a.jpg" alt="Fishing boats at Whitby"/>
+<figcaption>Plate 3: the harbour</figcaption></figure>
+<aside epub:type="footnote"><p>Painted in 1824.</p></aside>"##;
+        let index = SearchIndex::new(
+            "book".to_string(),
+            vec![IndexedChapter::from_html_with(
+                "ch1.xhtml",
+                0,
+                html,
+                &IndexOptions::all(),
+            )],
+        );
+        let kinds = |query: &str| -> Vec<(TextKind, String)> {
+            index
+                .search(query, 10)
+                .into_iter()
+                .map(|r| (r.kind, r.excerpt))
+                .collect()
+        };
+
+        assert_eq!(
+            kinds("whitby"),
+            vec![(TextKind::AltText, "Fishing boats at Whitby".to_string())]
+        );
+        assert_eq!(kinds("1824")[0].0, TextKind::Footnote);
+        let harbour = kinds("harbour");
+        assert_eq!(harbour[0].0, TextKind::Body);
+        assert_eq!(
+            harbour[1],
+            (TextKind::Caption, "Plate 3: the harbour".to_string())
+        );
+
+        // Without the options alt text is not searchable
+        let plain = SearchIndex::new(
+            "book".to_string(),
+            vec![IndexedChapter::from_html("ch1.xhtml", 0, html)],
+        );
+        assert!(plain.search("whitby", 10).is_empty());
+        assert_eq!(plain.search("1824", 10)[0].kind, TextKind::Body);
+    }
+
+    #[test]
+    fn test_search_all_merges_books() {
+        let book = |id: &str, html: &str| {
+            SearchIndex::new(
+                id.to_string(),
+                vec![IndexedChapter::from_html("ch1.xhtml".to_string(), 0, html)],
+            )
+        };
+        let moby = book("moby", "<p>The whale. The whale! The whale.</p>");
+        let ahab = book(
+            "ahab",
+            "<p>A whale among many other words in a long line.</p>",
+        );
+        let none = book("none", "<p>Nothing to see here.</p>");
+
+        let indices = [("ahab", &ahab), ("moby", &moby), ("none", &none)];
+        let results = search_all(indices, "whale", 10);
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0].book_id, "moby");
+        assert_eq!(results[3].book_id, "ahab");
+
+        let results = search_all(indices, "whale", 2);
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.book_id == "moby"));
+    }
+
     #[test]
     fn test_create_excerpt() {
         let text = "This is a test of the excerpt creation function for search results.";
-        let excerpt = create_excerpt(text, 10, 4);
-        assert!(excerpt.contains("test"));
+        let excerpt = create_excerpt(text, 10..14);
+        assert_eq!(
+            excerpt,
+            "This is a test of the excerpt creation function for search..."
+        );
+
+        // Context is counted in characters and never splits a cluster
+        let text = format!("{} 👩🏽‍💻 café {}", "é".repeat(60), "👍🏽".repeat(60));
+        let start = text.find("café").unwrap();
+        let excerpt = create_excerpt(&text, start..start + "café".len());
+        assert!(excerpt.starts_with("...👩🏽‍💻 café 👍🏽"));
+        assert!(excerpt.strip_suffix("...").unwrap().ends_with("👍🏽"));
+
+        // CJK has no spaces to snap to, but stays within the context
+        let text = "東".repeat(200);
+        let excerpt = create_excerpt(&text, 300..306);
+        assert_eq!(excerpt.chars().filter(|c| *c == '東').count(), 102);
+    }
+
+    #[test]
+    fn test_search_maps_offsets_to_original_text() {
+        let index = SearchIndex::new(
+            "book".to_string(),
+            vec![IndexedChapter::from_html(
+                "ch1.xhtml",
+                0,
+                "<p>Ｆｕｌｌ ＷＩＤＴＨ 😀 Ça va, naïve café?</p>",
+            )],
+        );
+
+        let results = index.search("Cafe", 10);
+        assert_eq!(results.len(), 1);
+        let text = &index.chapters[0].original_text;
+        let utf16: Vec<u16> = text.encode_utf16().collect();
+        let matched = String::from_utf16(&utf16[results[0].text_start..results[0].text_end]);
+        assert_eq!(matched.unwrap(), "café");
+        assert!(results[0].excerpt.contains("naïve café?"));
+    }
+
+    #[test]
+    fn test_search_folds_smart_quotes() {
+        let index = SearchIndex::new(
+            "book".to_string(),
+            vec![IndexedChapter::from_html(
+                "ch1.xhtml",
+                0,
+                "<p>“I don’t know,” she said. The ﬁnal word.</p>",
+            )],
+        );
+
+        let text = &index.chapters[0].original_text;
+        let utf16: Vec<u16> = text.encode_utf16().collect();
+        let matched = |query: &str| {
+            let results = index.search(query, 10);
+            assert_eq!(results.len(), 1, "{query}");
+            String::from_utf16(&utf16[results[0].text_start..results[0].text_end]).unwrap()
+        };
+        assert_eq!(matched("don't"), "don’t");
+        assert_eq!(matched("\"don’t know\""), "don’t know");
+        assert_eq!(matched("final"), "ﬁnal");
     }
 }
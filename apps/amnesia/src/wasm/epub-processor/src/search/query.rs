@@ -0,0 +1,340 @@
+//! Search query syntax
+//!
+//! A query is a list of clauses that must all match, in the spirit of web
+//! search engines:
+//!
+//! - `white whale`: bare words next to each other form one clause, which
+//!   matches where the words appear together or, failing that, where any
+//!   of them does
+//! - `"machine learning"`: a phrase, matched only as written
+//! - `learn*`: any word starting with `learn`
+//! - `a AND b`, `a OR b`: explicit operators; AND binds tighter than OR,
+//!   and clauses side by side are ANDed
+//! - `NOT a`, `-a`: excludes chapters containing `a`
+//! - `(a OR b) c`: parentheses group clauses
+//!
+//! Operators are only recognized in upper case, so "and" is still a word.
+//! Parsing never fails: stray operators and unbalanced quotes or
+//! parentheses are ignored or closed at the end of the query.
+
+use super::inverted::query_terms;
+use super::query_phrase;
+
+/// A parsed search query
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    /// A run of bare words
+    Words {
+        /// Normalized terms
+        terms: Vec<String>,
+        /// The normalized words from first to last, for exact matches
+        phrase: String,
+    },
+    /// A quoted phrase
+    Phrase {
+        terms: Vec<String>,
+        /// The normalized phrase from its first word to its last
+        text: String,
+    },
+    /// Words starting with a normalized prefix
+    Prefix(String),
+    /// Every clause matches
+    And(Vec<Query>),
+    /// At least one clause matches
+    Or(Vec<Query>),
+    /// The clause does not match
+    Not(Box<Query>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Phrase(String),
+    Prefix(String),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+impl Query {
+    /// Parse a query; `None` when it has no searchable words
+    pub fn parse(query: &str) -> Option<Self> {
+        Parser {
+            tokens: tokenize(query),
+            pos: 0,
+        }
+        .or_expr()
+    }
+
+    /// Normalized words of the clauses that are not negated, in order,
+    /// without prefixes
+    pub fn positive_terms(&self) -> Vec<&str> {
+        let mut terms = Vec::new();
+        self.collect_terms(&mut terms);
+        terms
+    }
+
+    fn collect_terms<'a>(&'a self, terms: &mut Vec<&'a str>) {
+        match self {
+            Query::Words { terms: words, .. } | Query::Phrase { terms: words, .. } => {
+                terms.extend(words.iter().map(String::as_str))
+            }
+            Query::And(clauses) | Query::Or(clauses) => {
+                for clause in clauses {
+                    clause.collect_terms(terms);
+                }
+            }
+            Query::Prefix(_) | Query::Not(_) => {}
+        }
+    }
+
+    /// Terms of the runs of bare words that are not negated, which fuzzy
+    /// search also matches misspelled
+    pub fn positive_words(&self) -> Vec<&str> {
+        match self {
+            Query::Words { terms, .. } => terms.iter().map(String::as_str).collect(),
+            Query::And(clauses) | Query::Or(clauses) => {
+                clauses.iter().flat_map(Query::positive_words).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Prefixes of the clauses that are not negated
+    pub fn positive_prefixes(&self) -> Vec<&str> {
+        match self {
+            Query::Prefix(prefix) => vec![prefix.as_str()],
+            Query::And(clauses) | Query::Or(clauses) => {
+                clauses.iter().flat_map(Query::positive_prefixes).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Split a query into words, phrases and operators
+fn tokenize(query: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut depth = 0usize;
+    let mut chars = query.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '"' => {
+                let start = i + 1;
+                let end = chars
+                    .by_ref()
+                    .find(|&(_, c)| c == '"')
+                    .map_or(query.len(), |(j, _)| j);
+                tokens.push(Token::Phrase(query[start..end].to_string()));
+            }
+            '(' => {
+                depth += 1;
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                // Unmatched closing parentheses are dropped
+                if depth > 0 {
+                    depth -= 1;
+                    tokens.push(Token::Close);
+                }
+            }
+            '-' if chars.peek().is_some_and(|&(_, n)| !n.is_whitespace()) => {
+                tokens.push(Token::Not)
+            }
+            _ => {
+                let mut end = query.len();
+                while let Some(&(j, n)) = chars.peek() {
+                    if n.is_whitespace() || matches!(n, '"' | '(' | ')') {
+                        end = j;
+                        break;
+                    }
+                    chars.next();
+                }
+                tokens.push(match &query[i..end] {
+                    "AND" | "&&" => Token::And,
+                    "OR" | "||" => Token::Or,
+                    "NOT" => Token::Not,
+                    word if word.len() > 1 && word.ends_with('*') => {
+                        Token::Prefix(word.trim_end_matches('*').to_string())
+                    }
+                    word => Token::Word(word.to_string()),
+                });
+            }
+        }
+    }
+
+    tokens
+}
+
+/// Recursive descent over the tokens
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    /// `and_expr (OR and_expr)*`
+    fn or_expr(&mut self) -> Option<Query> {
+        let mut branches = Vec::new();
+        loop {
+            branches.extend(self.and_expr());
+            if self.peek() != Some(&Token::Or) {
+                break;
+            }
+            self.pos += 1;
+        }
+        combine(branches, Query::Or)
+    }
+
+    /// `unary ([AND] unary)*`
+    fn and_expr(&mut self) -> Option<Query> {
+        let mut clauses = Vec::new();
+        loop {
+            match self.peek() {
+                None | Some(Token::Or) | Some(Token::Close) => break,
+                Some(Token::And) => self.pos += 1,
+                Some(_) => clauses.extend(self.unary()),
+            }
+        }
+        combine(clauses, Query::And)
+    }
+
+    /// `(NOT | -) unary | atom`
+    fn unary(&mut self) -> Option<Query> {
+        match self.next()? {
+            Token::Not => match self.peek() {
+                None | Some(Token::And) | Some(Token::Or) | Some(Token::Close) => None,
+                Some(_) => self.unary().map(|q| Query::Not(Box::new(q))),
+            },
+            Token::Open => {
+                let query = self.or_expr();
+                if self.peek() == Some(&Token::Close) {
+                    self.pos += 1;
+                }
+                query
+            }
+            Token::Phrase(text) => {
+                let terms = query_terms(&text);
+                (!terms.is_empty()).then(|| Query::Phrase {
+                    terms,
+                    text: query_phrase(&text),
+                })
+            }
+            Token::Prefix(stem) => {
+                let mut terms = query_terms(&stem);
+                match terms.len() {
+                    0 => None,
+                    1 => terms.pop().map(Query::Prefix),
+                    // "foo.bar*" is two words; search them as such
+                    _ => Some(Query::Words {
+                        terms,
+                        phrase: query_phrase(&stem),
+                    }),
+                }
+            }
+            Token::Word(word) => {
+                let mut words = vec![word];
+                while let Some(Token::Word(word)) = self.peek() {
+                    words.push(word.clone());
+                    self.pos += 1;
+                }
+                let run = words.join(" ");
+                let terms = query_terms(&run);
+                (!terms.is_empty()).then(|| Query::Words {
+                    terms,
+                    phrase: query_phrase(&run),
+                })
+            }
+            Token::And | Token::Or | Token::Close => None,
+        }
+    }
+}
+
+fn combine(mut clauses: Vec<Query>, wrap: fn(Vec<Query>) -> Query) -> Option<Query> {
+    match clauses.len() {
+        0 => None,
+        1 => clauses.pop(),
+        _ => Some(wrap(clauses)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(text: &str) -> Query {
+        Query::Words {
+            terms: query_terms(text),
+            phrase: text.to_string(),
+        }
+    }
+
+    fn phrase(text: &str) -> Query {
+        Query::Phrase {
+            terms: query_terms(text),
+            text: text.to_string(),
+        }
+    }
+
+    fn not(query: Query) -> Query {
+        Query::Not(Box::new(query))
+    }
+
+    #[test]
+    fn test_parse_query() {
+        assert_eq!(Query::parse("White Whale"), Some(words("white whale")));
+        assert_eq!(
+            Query::parse(r#""machine learning" AND rust -python"#),
+            Some(Query::And(vec![
+                phrase("machine learning"),
+                words("rust"),
+                not(words("python")),
+            ]))
+        );
+        let query = Query::parse(r#"white whale "sea" -ship"#).unwrap();
+        assert_eq!(query.positive_words(), vec!["white", "whale"]);
+        assert_eq!(
+            Query::parse("learn* OR (sea NOT ship) well-known"),
+            Some(Query::Or(vec![
+                Query::Prefix("learn".to_string()),
+                Query::And(vec![
+                    Query::And(vec![words("sea"), not(words("ship"))]),
+                    words("well-known"),
+                ]),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_parse_is_lenient() {
+        // Lower-case operators are words
+        assert_eq!(
+            Query::parse("salt and pepper"),
+            Some(words("salt and pepper"))
+        );
+        assert_eq!(
+            Query::parse(r#"OR "open phrase"#),
+            Some(phrase("open phrase"))
+        );
+        assert_eq!(
+            Query::parse("(a OR b"),
+            Some(Query::Or(vec![words("a"), words("b")]))
+        );
+        assert_eq!(Query::parse("a) b NOT"), Some(words("a b")));
+        assert_eq!(Query::parse(" ... * \"\" "), None);
+    }
+}
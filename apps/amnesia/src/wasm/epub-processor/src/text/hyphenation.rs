@@ -0,0 +1,227 @@
+//! Knuth–Liang hyphenation
+//!
+//! Patterns use the TeX format distributed by hyph-utf8 (`hyph-*.pat.txt`):
+//! whitespace-separated letters with interleaved priority digits, `.` for a
+//! word edge, and `%` comments. Exceptions (`hyph-*.hyp.txt`) list fully
+//! hyphenated words such as `as-so-ciate`. The plugin ships the pattern files
+//! and loads the ones it needs, so the WASM binary stays small; the patterns
+//! for English, German, French and Spanish can also be compiled in (see
+//! [`super::patterns`]).
+
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum HyphenationError {
+    #[error("Invalid hyphenation pattern: {0}")]
+    InvalidPattern(String),
+
+    #[error("No hyphenation patterns in input")]
+    Empty,
+}
+
+/// Hyphenation dictionary for one language
+#[derive(Debug, Clone)]
+pub struct Hyphenator {
+    /// Pattern letters -> inter-letter values (one more than the letters)
+    patterns: HashMap<String, Vec<u8>>,
+    /// Lowercased word -> break positions (char indices)
+    exceptions: HashMap<String, Vec<usize>>,
+    /// Longest pattern in chars, bounds the substring search
+    max_pattern_chars: usize,
+    /// Minimum chars before the first break
+    left_min: usize,
+    /// Minimum chars after the last break
+    right_min: usize,
+}
+
+impl Hyphenator {
+    /// Build a hyphenator from pattern and exception file contents
+    pub fn new(
+        patterns: &str,
+        exceptions: &str,
+        left_min: usize,
+        right_min: usize,
+    ) -> Result<Self, HyphenationError> {
+        let mut hyphenator = Self {
+            patterns: HashMap::new(),
+            exceptions: HashMap::new(),
+            max_pattern_chars: 0,
+            left_min: left_min.max(1),
+            right_min: right_min.max(1),
+        };
+
+        for token in tokens(patterns) {
+            hyphenator.add_pattern(token)?;
+        }
+        if hyphenator.patterns.is_empty() {
+            return Err(HyphenationError::Empty);
+        }
+
+        for token in tokens(exceptions) {
+            hyphenator.add_exception(token);
+        }
+
+        Ok(hyphenator)
+    }
+
+    /// Number of loaded patterns
+    pub fn pattern_count(&self) -> usize {
+        self.patterns.len()
+    }
+
+    /// Char indices in `word` before which a hyphen may be inserted
+    pub fn hyphenate(&self, word: &str) -> Vec<usize> {
+        let letters: Vec<char> = word.chars().map(lowercase).collect();
+        let len = letters.len();
+        if len < self.left_min + self.right_min {
+            return Vec::new();
+        }
+
+        let key: String = letters.iter().collect();
+        if let Some(points) = self.exceptions.get(&key) {
+            return points.clone();
+        }
+
+        // `.word.` with one value slot between each pair of chars
+        let mut dotted = Vec::with_capacity(len + 2);
+        dotted.push('.');
+        dotted.extend_from_slice(&letters);
+        dotted.push('.');
+
+        let mut values = vec![0u8; dotted.len() + 1];
+        let mut fragment = String::new();
+        for start in 0..dotted.len() {
+            fragment.clear();
+            let end = (start + self.max_pattern_chars).min(dotted.len());
+            for &c in &dotted[start..end] {
+                fragment.push(c);
+                if let Some(pattern) = self.patterns.get(&fragment) {
+                    for (offset, &value) in pattern.iter().enumerate() {
+                        let slot = &mut values[start + offset];
+                        *slot = (*slot).max(value);
+                    }
+                }
+            }
+        }
+
+        // The slot before word char `i` is `values[i + 1]` (after the dot)
+        (self.left_min..=len - self.right_min)
+            .filter(|&i| values[i + 1] % 2 == 1)
+            .collect()
+    }
+
+    fn add_pattern(&mut self, token: &str) -> Result<(), HyphenationError> {
+        let mut letters = String::new();
+        let mut values = vec![0u8];
+        for c in token.chars() {
+            match c.to_digit(10) {
+                Some(digit) => {
+                    *values.last_mut().expect("values is never empty") = digit as u8;
+                }
+                None => {
+                    letters.push(lowercase(c));
+                    values.push(0);
+                }
+            }
+        }
+
+        if letters.is_empty() {
+            return Err(HyphenationError::InvalidPattern(token.to_string()));
+        }
+
+        self.max_pattern_chars = self.max_pattern_chars.max(letters.chars().count());
+        self.patterns.insert(letters, values);
+        Ok(())
+    }
+
+    fn add_exception(&mut self, token: &str) {
+        let mut word = String::new();
+        let mut points = Vec::new();
+        let mut count = 0;
+        for c in token.chars() {
+            if c == '-' {
+                points.push(count);
+            } else {
+                word.push(lowercase(c));
+                count += 1;
+            }
+        }
+        self.exceptions.insert(word, points);
+    }
+}
+
+/// Pattern-file tokens with comments and TeX commands removed
+fn tokens(text: &str) -> impl Iterator<Item = &str> {
+    text.lines()
+        .map(|line| line.split('%').next().unwrap_or(""))
+        .flat_map(str::split_whitespace)
+        .filter(|token| !token.contains(['\\', '{', '}']))
+}
+
+/// Single-char lowercase so char indices stay aligned with the input
+fn lowercase(c: char) -> char {
+    let mut lower = c.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(l), None) => l,
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Patterns from the "hyphenation" example in The TeXbook, appendix H
+    const PATTERNS: &str = "
+        % Liang's example patterns
+        hy3ph he2n hena4 hen5at 1na n2at 1tio 2io o2n
+    ";
+
+    #[test]
+    fn test_hyphenate_with_patterns() {
+        let hyphenator = Hyphenator::new(PATTERNS, "", 2, 3).unwrap();
+        assert_eq!(hyphenator.pattern_count(), 9);
+        // hy-phen-ation
+        assert_eq!(hyphenator.hyphenate("hyphenation"), vec![2, 6]);
+        assert_eq!(hyphenator.hyphenate("Hyphenation"), vec![2, 6]);
+    }
+
+    #[test]
+    fn test_hyphenate_respects_min_lengths() {
+        let hyphenator = Hyphenator::new(PATTERNS, "", 3, 6).unwrap();
+        assert!(hyphenator.hyphenate("hyphenation").is_empty());
+        assert!(hyphenator.hyphenate("hy").is_empty());
+    }
+
+    #[test]
+    fn test_exceptions_override_patterns() {
+        let hyphenator = Hyphenator::new(PATTERNS, "hyphe-na-tion", 2, 3).unwrap();
+        assert_eq!(hyphenator.hyphenate("hyphenation"), vec![5, 7]);
+    }
+
+    #[test]
+    fn test_hyphenate_with_hyph_utf8_patterns() {
+        let patterns = include_str!("../../patterns/hyph-en-us.pat.txt");
+        let hyphenator = Hyphenator::new(patterns, "", 2, 3).unwrap();
+        let hyphenated = |word: &str| {
+            let points = hyphenator.hyphenate(word);
+            word.chars()
+                .enumerate()
+                .flat_map(|(i, c)| points.contains(&i).then_some('-').into_iter().chain([c]))
+                .collect::<String>()
+        };
+        assert_eq!(hyphenated("hyphenation"), "hy-phen-ation");
+        assert_eq!(hyphenated("extensive"), "ex-ten-sive");
+        assert_eq!(hyphenated("Typography"), "Ty-pog-ra-phy");
+    }
+
+    #[test]
+    fn test_rejects_empty_patterns() {
+        assert!(matches!(
+            Hyphenator::new("% nothing\n\\patterns{ }", "", 2, 3),
+            Err(HyphenationError::Empty)
+        ));
+    }
+}
@@ -0,0 +1,374 @@
+//! Text measurement helpers for client-side pagination
+//!
+//! The JS paginator breaks lines and columns itself; these helpers give it
+//! the same view of a chapter's text that the search index has. Chapter text
+//! comes from `search_core::extract_plain_text`, words from
+//! `search_core::segment_words`, and hyphenation points from a per-language
+//! [`Hyphenator`]. All offsets are UTF-16 code units into `text`, matching
+//! JavaScript string indexing.
+//!
+//! Highlight selections over the same text are snapped to whole words by
+//! [`NormalizedSelection`] before they are stored, and copied with the
+//! [`TextFolding`] the reader has chosen by [`copied_text`].
+//! [`BookStatistics`] counts the same words per chapter and estimates
+//! reading time. [`mark_terms`] wraps search terms in a chapter's markup,
+//! matching them the way the search index does, and
+//! [`insert_soft_hyphens`] marks its hyphenation points for the renderer.
+
+use std::collections::HashMap;
+
+use search_core::segment::is_word_hyphen;
+use search_core::{
+    extract_plain_text, normalize_for_search, normalize_selection, segment_words, utf16_slice,
+    OffsetMap,
+};
+use serde::{Deserialize, Serialize};
+
+pub mod hyphenation;
+pub mod patterns;
+pub mod soft_hyphens;
+pub mod statistics;
+
+pub use hyphenation::{HyphenationError, Hyphenator};
+pub use patterns::builtin_hyphenators;
+pub use search_core::TextFolding;
+pub use soft_hyphens::{insert_soft_hyphens, SOFT_HYPHEN};
+pub use statistics::{BookStatistics, ChapterStatistics, DEFAULT_WORDS_PER_MINUTE};
+
+/// A word in the chapter text
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WordBoundary {
+    /// Start offset (inclusive)
+    pub start: usize,
+    /// End offset (exclusive)
+    pub end: usize,
+    /// Offsets before which a hyphen may be inserted
+    pub hyphens: Vec<usize>,
+}
+
+/// Segmented text of one chapter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChapterText {
+    pub href: String,
+    /// Language whose patterns produced `hyphens`, if any were loaded
+    pub language: Option<String>,
+    /// Plain text as seen by the search index
+    pub text: String,
+    pub words: Vec<WordBoundary>,
+}
+
+impl ChapterText {
+    /// Extract, segment, and optionally hyphenate a chapter
+    pub fn from_html(href: &str, html: &str, hyphenator: Option<(&str, &Hyphenator)>) -> Self {
+        let text = extract_plain_text(html);
+        let mut words = Vec::new();
+
+        // Byte offsets advance monotonically, so UTF-16 offsets are counted
+        // in a single pass
+        let mut utf16 = Utf16Cursor::new(&text);
+        for range in segment_words(&text) {
+            let start = utf16.offset_of(range.start);
+            let hyphens = match hyphenator {
+                Some((_, hyphenator)) => hyphenate_word(&text[range.clone()], start, hyphenator),
+                None => Vec::new(),
+            };
+            let end = utf16.offset_of(range.end);
+            words.push(WordBoundary {
+                start,
+                end,
+                hyphens,
+            });
+        }
+
+        Self {
+            href: href.to_string(),
+            language: hyphenator.map(|(language, _)| language.to_string()),
+            text,
+            words,
+        }
+    }
+}
+
+/// A highlight selection snapped to whole words, without surrounding
+/// whitespace
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NormalizedSelection {
+    pub href: String,
+    /// Start offset (inclusive)
+    pub start: usize,
+    /// End offset (exclusive)
+    pub end: usize,
+    /// Selected text, for a TextQuoteSelector
+    pub exact: String,
+    /// Up to 32 characters of context before `exact`
+    pub prefix: String,
+    /// Up to 32 characters of context after `exact`
+    pub suffix: String,
+}
+
+impl NormalizedSelection {
+    /// Normalize a selection of a chapter's text
+    ///
+    /// Returns `None` when only whitespace is selected.
+    pub fn from_html(href: &str, html: &str, start: usize, end: usize) -> Option<Self> {
+        let selection = normalize_selection(&extract_plain_text(html), start, end)?;
+        Some(Self {
+            href: href.to_string(),
+            start: selection.start,
+            end: selection.end,
+            exact: selection.exact,
+            prefix: selection.prefix,
+            suffix: selection.suffix,
+        })
+    }
+}
+
+/// A selection of a chapter's text as copied, with `folding` applied
+///
+/// `start` and `end` are UTF-16 offsets into the chapter text, as for
+/// [`NormalizedSelection`]; the selection is copied as given, not snapped.
+pub fn copied_text(html: &str, start: usize, end: usize, folding: &TextFolding) -> String {
+    folding.fold(utf16_slice(&extract_plain_text(html), start, end))
+}
+
+/// Chapter HTML with `<mark>` around each occurrence of `terms`
+///
+/// Terms match whole words, ignoring case, accents and curly quotes as
+/// search does; a term of several words matches them in sequence, across
+/// line breaks and inline elements but not across paragraphs. A trailing
+/// `*` lets the last word match as a prefix (`whal*`). Marks are added to
+/// the text only, so attributes, `<head>` and the tags themselves are left
+/// as they are (see [`epub_core::mark_text`]).
+pub fn mark_terms(html: &str, terms: &[String]) -> String {
+    // Each term's normalized words, and whether the last is a prefix
+    let terms: Vec<(Vec<String>, bool)> = terms
+        .iter()
+        .filter_map(|term| {
+            let term = term.trim();
+            let (term, prefix) = match term.strip_suffix('*') {
+                Some(term) => (term, true),
+                None => (term, false),
+            };
+            let normalized = normalize_for_search(term);
+            let words: Vec<String> = segment_words(&normalized)
+                .into_iter()
+                .map(|word| normalized[word].to_string())
+                .collect();
+            (!words.is_empty()).then_some((words, prefix))
+        })
+        .collect();
+    if terms.is_empty() {
+        return html.to_string();
+    }
+
+    epub_core::mark_text(html, |text| {
+        let normalized = normalize_for_search(text);
+        let offsets = OffsetMap::new(text);
+        let words = segment_words(&normalized);
+
+        let mut ranges = Vec::new();
+        for (term, prefix) in &terms {
+            for window in words.windows(term.len()) {
+                let matches = window.iter().zip(term).enumerate().all(|(i, (word, t))| {
+                    let word = &normalized[word.clone()];
+                    if *prefix && i == term.len() - 1 {
+                        word.starts_with(t.as_str())
+                    } else {
+                        word == t
+                    }
+                });
+                if matches {
+                    let (first, last) = (&window[0], &window[window.len() - 1]);
+                    ranges.push(offsets.to_original(first.start)..offsets.to_original(last.end));
+                }
+            }
+        }
+        ranges
+    })
+}
+
+/// Normalize a BCP 47 tag for lookups (`en_US` -> `en-us`)
+pub fn language_key(tag: &str) -> String {
+    tag.trim().replace('_', "-").to_lowercase()
+}
+
+/// Find the best entry for a language tag: exact match, then the primary
+/// subtag (`pt-br` falls back to `pt`)
+pub fn resolve_language<'a, T>(
+    entries: &'a HashMap<String, T>,
+    tag: &str,
+) -> Option<(&'a str, &'a T)> {
+    let key = language_key(tag);
+    let primary = key.split('-').next().unwrap_or_default();
+    let entry = [key.as_str(), primary]
+        .into_iter()
+        .find_map(|k| entries.get_key_value(k));
+    entry.map(|(k, v)| (k.as_str(), v))
+}
+
+/// Hyphenation offsets for a word starting at UTF-16 offset `start`
+///
+/// Compound words are hyphenated part by part; the explicit hyphen is
+/// already a break opportunity.
+pub fn hyphenate_word(word: &str, start: usize, hyphenator: &Hyphenator) -> Vec<usize> {
+    let mut hyphens = Vec::new();
+    let mut part_start = start;
+    for part in word.split(is_word_hyphen) {
+        let units: Vec<usize> = part.chars().map(char::len_utf16).collect();
+        for index in hyphenator.hyphenate(part) {
+            hyphens.push(part_start + units[..index].iter().sum::<usize>());
+        }
+        // Skip the part and the hyphen after it (one UTF-16 unit)
+        part_start += units.iter().sum::<usize>() + 1;
+    }
+    hyphens
+}
+
+/// Converts increasing byte offsets into UTF-16 offsets
+pub(crate) struct Utf16Cursor<'a> {
+    text: &'a str,
+    byte: usize,
+    utf16: usize,
+}
+
+impl<'a> Utf16Cursor<'a> {
+    pub(crate) fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            byte: 0,
+            utf16: 0,
+        }
+    }
+
+    pub(crate) fn offset_of(&mut self, byte: usize) -> usize {
+        self.utf16 += self.text[self.byte..byte]
+            .chars()
+            .map(char::len_utf16)
+            .sum::<usize>();
+        self.byte = byte;
+        self.utf16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hyphenator() -> Hyphenator {
+        Hyphenator::new("hy3ph he2n hena4 hen5at 1na n2at 1tio 2io o2n", "", 2, 3).unwrap()
+    }
+
+    #[test]
+    fn test_chapter_text_words() {
+        let text = ChapterText::from_html("c1.xhtml", "<p>Hello <b>world</b>!</p>", None);
+        assert_eq!(text.text, "Hello world !");
+        assert_eq!(text.language, None);
+        assert_eq!(
+            text.words,
+            vec![
+                WordBoundary {
+                    start: 0,
+                    end: 5,
+                    hyphens: vec![]
+                },
+                WordBoundary {
+                    start: 6,
+                    end: 11,
+                    hyphens: vec![]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_copied_text() {
+        let html = "<p>\u{1D4D7} “Don’t” ﬁnd—</p>";
+        let folding = TextFolding {
+            punctuation: true,
+            ..Default::default()
+        };
+        // Offsets are those of the unfolded chapter text
+        assert_eq!(copied_text(html, 3, 15, &folding), "\"Don't\" ﬁnd--");
+        assert_eq!(copied_text(html, 11, 14, &TextFolding::ALL), "find");
+        assert_eq!(copied_text(html, 3, 10, &TextFolding::default()), "“Don’t”");
+    }
+
+    #[test]
+    fn test_chapter_text_hyphenation_offsets_are_utf16() {
+        let hyphenator = hyphenator();
+        // U+1D4D7 is two UTF-16 units
+        let text = ChapterText::from_html(
+            "c1.xhtml",
+            "<p>\u{1D4D7} hyphenation</p>",
+            Some(("en", &hyphenator)),
+        );
+        assert_eq!(text.language.as_deref(), Some("en"));
+        assert_eq!(text.words[1].start, 3);
+        assert_eq!(text.words[1].hyphens, vec![5, 9]);
+    }
+
+    #[test]
+    fn test_compound_words_hyphenate_per_part() {
+        let hyphenator = hyphenator();
+        let text = ChapterText::from_html(
+            "c1.xhtml",
+            "<p>pre-hyphenation</p>",
+            Some(("en", &hyphenator)),
+        );
+        assert_eq!(text.words.len(), 1);
+        assert_eq!(text.words[0].hyphens, vec![6, 10]);
+    }
+
+    #[test]
+    fn test_normalized_selection_offsets_match_chapter_text() {
+        let html = "<p>Hello <b>world</b>!</p>";
+        let text = ChapterText::from_html("c1.xhtml", html, None);
+
+        // " wor" from the chapter text grows to the whole word
+        let selection = NormalizedSelection::from_html("c1.xhtml", html, 5, 9).unwrap();
+        assert_eq!(selection.exact, "world");
+        assert_eq!(
+            (selection.start, selection.end),
+            (text.words[1].start, text.words[1].end)
+        );
+        assert_eq!(selection.prefix, "Hello ");
+        assert_eq!(selection.suffix, " !");
+
+        assert_eq!(NormalizedSelection::from_html("c1.xhtml", html, 5, 6), None);
+    }
+
+    #[test]
+    fn test_mark_terms() {
+        let html = "<html><head><title>Whales</title></head><body>\
+<p class=\"whale\">The <i>White</i>\n Whale, Moby-Dick; whaling &amp; WHALES.</p>\
+<p>The white</p><p>whale of Café Ahab</p></body></html>";
+        let terms = [
+            "white whale".to_string(),
+            "whal*".to_string(),
+            "cafe".to_string(),
+        ];
+
+        assert_eq!(
+            mark_terms(html, &terms),
+            "<html><head><title>Whales</title></head><body>\
+<p class=\"whale\">The <i><mark>White</mark></i><mark>\n Whale</mark>, Moby-Dick; \
+<mark>whaling</mark> &amp; <mark>WHALES</mark>.</p>\
+<p>The white</p><p><mark>whale</mark> of <mark>Café</mark> Ahab</p></body></html>"
+        );
+        assert_eq!(mark_terms(html, &[" * ".to_string()]), html);
+    }
+
+    #[test]
+    fn test_resolve_language_falls_back_to_primary() {
+        let mut entries = HashMap::new();
+        entries.insert("pt".to_string(), 1);
+        entries.insert("en-gb".to_string(), 2);
+
+        assert_eq!(resolve_language(&entries, "pt_BR"), Some(("pt", &1)));
+        assert_eq!(resolve_language(&entries, "en-GB"), Some(("en-gb", &2)));
+        assert_eq!(resolve_language(&entries, "en-US"), None);
+    }
+}
@@ -0,0 +1,101 @@
+//! Hyphenation patterns built into the binary
+//!
+//! Patterns are normally loaded at runtime (`loadHyphenationPatterns`), so
+//! the WASM binary only carries the code. Builds that have no pattern files
+//! to load can compile some in instead, one Cargo feature per language:
+//!
+//! | Feature | Language | Files in `patterns/` |
+//! |---------|----------|----------------------|
+//! | `hyphenation-en` | `en` | `hyph-en-us.pat.txt` |
+//! | `hyphenation-de` | `de` | `hyph-de-1996.pat.txt` |
+//! | `hyphenation-fr` | `fr` | `hyph-fr.pat.txt` |
+//! | `hyphenation-es` | `es` | `hyph-es.pat.txt` |
+//!
+//! The files are hyph-utf8 patterns, checked in next to `Cargo.toml` with
+//! their provenance and licenses (`patterns/README.md`). Patterns loaded at
+//! runtime take precedence over built-in ones.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use super::Hyphenator;
+
+/// Patterns compiled in for one language
+struct BuiltinPatterns {
+    /// Normalized language tag (see [`super::language_key`])
+    language: &'static str,
+    patterns: &'static str,
+    exceptions: &'static str,
+    left_min: usize,
+    right_min: usize,
+}
+
+const BUILTIN_PATTERNS: &[BuiltinPatterns] = &[
+    #[cfg(feature = "hyphenation-en")]
+    BuiltinPatterns {
+        language: "en",
+        patterns: include_str!("../../patterns/hyph-en-us.pat.txt"),
+        exceptions: "",
+        left_min: 2,
+        right_min: 3,
+    },
+    #[cfg(feature = "hyphenation-de")]
+    BuiltinPatterns {
+        language: "de",
+        patterns: include_str!("../../patterns/hyph-de-1996.pat.txt"),
+        exceptions: "",
+        left_min: 2,
+        right_min: 2,
+    },
+    #[cfg(feature = "hyphenation-fr")]
+    BuiltinPatterns {
+        language: "fr",
+        patterns: include_str!("../../patterns/hyph-fr.pat.txt"),
+        exceptions: "",
+        left_min: 2,
+        right_min: 2,
+    },
+    #[cfg(feature = "hyphenation-es")]
+    BuiltinPatterns {
+        language: "es",
+        patterns: include_str!("../../patterns/hyph-es.pat.txt"),
+        exceptions: "",
+        left_min: 2,
+        right_min: 2,
+    },
+];
+
+/// Hyphenators for the built-in patterns, keyed by language tag; parsed on
+/// first use
+pub fn builtin_hyphenators() -> &'static HashMap<String, Hyphenator> {
+    static HYPHENATORS: OnceLock<HashMap<String, Hyphenator>> = OnceLock::new();
+    HYPHENATORS.get_or_init(|| {
+        BUILTIN_PATTERNS
+            .iter()
+            .map(|builtin| {
+                let hyphenator = Hyphenator::new(
+                    builtin.patterns,
+                    builtin.exceptions,
+                    builtin.left_min,
+                    builtin.right_min,
+                )
+                .expect("built-in hyphenation patterns are valid");
+                (builtin.language.to_string(), hyphenator)
+            })
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_patterns_parse() {
+        for builtin in BUILTIN_PATTERNS {
+            let hyphenator = Hyphenator::new(builtin.patterns, "", 2, 2).unwrap();
+            assert!(hyphenator.pattern_count() > 0, "{}", builtin.language);
+        }
+        assert_eq!(builtin_hyphenators().len(), BUILTIN_PATTERNS.len());
+    }
+}
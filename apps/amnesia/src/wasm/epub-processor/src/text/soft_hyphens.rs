@@ -0,0 +1,95 @@
+//! Soft hyphens in chapter markup
+//!
+//! Justified text set without hyphenation opens wide gaps between words,
+//! which line up into rivers on narrow columns. Browsers only hyphenate
+//! languages they ship dictionaries for, and the CSS `hyphens: auto`
+//! support on mobile WebViews is patchy, so [`insert_soft_hyphens`] puts a
+//! soft hyphen (U+00AD) at each break a [`Hyphenator`] finds, and the
+//! reader sets `hyphens: manual`. A soft hyphen is invisible unless a line
+//! breaks there.
+//!
+//! Only the text is touched: attributes, `<head>`, entities and the tags
+//! themselves are left as they are (see [`epub_core::insert_in_text`]).
+//! Words that cross an inline tag are hyphenated part by part.
+
+use search_core::segment::is_word_hyphen;
+use search_core::segment_words;
+
+use super::Hyphenator;
+
+/// Soft hyphen, shown only where a line breaks
+pub const SOFT_HYPHEN: char = '\u{ad}';
+
+/// Elements whose text is not prose, and must copy as written
+const SKIPPED_ELEMENTS: &[&str] = &["code", "kbd", "math", "pre", "samp", "svg", "var"];
+
+/// Chapter HTML with a soft hyphen at each hyphenation point
+///
+/// Compound words are hyphenated part by part, and only parts made of
+/// letters, so numbers, URLs and words the book already hyphenated are
+/// left alone. Text offsets in the result count the soft hyphens; CFIs and
+/// selections are taken against the chapter without them.
+pub fn insert_soft_hyphens(html: &str, hyphenator: &Hyphenator) -> String {
+    let mut soft_hyphen = [0; 2];
+    let soft_hyphen = SOFT_HYPHEN.encode_utf8(&mut soft_hyphen);
+    epub_core::insert_in_text(
+        html,
+        SKIPPED_ELEMENTS,
+        |text| hyphenation_offsets(text, hyphenator),
+        soft_hyphen,
+    )
+}
+
+/// Byte offsets in `text` before which a hyphen may be inserted
+fn hyphenation_offsets(text: &str, hyphenator: &Hyphenator) -> Vec<usize> {
+    let mut offsets = Vec::new();
+    for range in segment_words(text) {
+        // Soft hyphens end words; one next to a word means the book has
+        // hyphenated it already
+        let hyphenated = text[..range.start].ends_with(SOFT_HYPHEN)
+            || text[range.end..].starts_with(SOFT_HYPHEN);
+        if hyphenated {
+            continue;
+        }
+
+        let word = &text[range.clone()];
+        let mut part_start = range.start;
+        for part in word.split(is_word_hyphen) {
+            if part.chars().all(char::is_alphabetic) {
+                let chars: Vec<usize> = part.char_indices().map(|(i, _)| i).collect();
+                offsets.extend(
+                    hyphenator
+                        .hyphenate(part)
+                        .into_iter()
+                        .map(|i| part_start + chars[i]),
+                );
+            }
+            // Skip the part and the hyphen after it
+            part_start += part.len();
+            part_start += text[part_start..].chars().next().map_or(0, char::len_utf8);
+        }
+    }
+    offsets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_soft_hyphens() {
+        let hyphenator =
+            Hyphenator::new("hy3ph he2n hena4 hen5at 1na n2at 1tio 2io o2n", "", 2, 3).unwrap();
+        let html = "<html><head><title>hyphenation</title></head><body>\
+<p class=\"hyphenation\">Hyphenation, pre-hyphenation &amp; <i>hyphenation</i>.</p>\
+<pre>hyphenation</pre><p>hyphenation2 hy\u{ad}phenation</p></body></html>";
+
+        assert_eq!(
+            insert_soft_hyphens(html, &hyphenator),
+            "<html><head><title>hyphenation</title></head><body>\
+<p class=\"hyphenation\">Hy\u{ad}phen\u{ad}ation, pre-hy\u{ad}phen\u{ad}ation &amp; \
+<i>hy\u{ad}phen\u{ad}ation</i>.</p>\
+<pre>hyphenation</pre><p>hyphenation2 hy\u{ad}phenation</p></body></html>"
+        );
+    }
+}
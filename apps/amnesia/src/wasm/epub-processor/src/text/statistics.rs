@@ -0,0 +1,120 @@
+//! Word counts and reading time
+//!
+//! Words are counted in the text the search index sees, segmented by
+//! `search_core::segment_words`, so every Chinese, Japanese or Thai
+//! character counts as a word; reading times for those books come out
+//! longer than a native reader needs.
+
+use search_core::{extract_plain_text, segment_words};
+use serde::{Deserialize, Serialize};
+
+use crate::epub::EpubBook;
+
+/// Average silent reading speed of adults reading non-fiction in English
+pub const DEFAULT_WORDS_PER_MINUTE: u32 = 238;
+
+/// Word count of one spine item
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChapterStatistics {
+    pub spine_index: usize,
+    pub href: String,
+    /// Whether the item is in the reading order and counts towards the
+    /// book's totals
+    pub linear: bool,
+    pub word_count: usize,
+    pub reading_minutes: f64,
+}
+
+/// Word counts of a book's spine items and their totals
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BookStatistics {
+    /// Every spine item in order; unreadable ones count no words
+    pub chapters: Vec<ChapterStatistics>,
+    /// Words of the linear items
+    pub word_count: usize,
+    pub reading_minutes: f64,
+    /// Reading speed the times were estimated with
+    pub words_per_minute: u32,
+}
+
+impl BookStatistics {
+    /// Count the words of every spine item
+    ///
+    /// Oversize items are read chunk by chunk. Non-linear items are
+    /// counted but left out of the totals, as they are left out of
+    /// locations and progression.
+    pub fn from_book(book: &EpubBook, words_per_minute: u32) -> Self {
+        let words_per_minute = words_per_minute.max(1);
+        let chapters: Vec<ChapterStatistics> = book
+            .spine
+            .iter()
+            .enumerate()
+            .map(|(spine_index, item)| {
+                let word_count = book
+                    .reading_hrefs(spine_index)
+                    .iter()
+                    .filter_map(|href| book.get_chapter_content(href).ok())
+                    .map(|content| count_words(&content.html))
+                    .sum();
+                ChapterStatistics {
+                    spine_index,
+                    href: item.href.clone(),
+                    linear: item.linear,
+                    word_count,
+                    reading_minutes: reading_minutes(word_count, words_per_minute),
+                }
+            })
+            .collect();
+
+        let word_count = chapters
+            .iter()
+            .filter(|chapter| chapter.linear)
+            .map(|chapter| chapter.word_count)
+            .sum();
+        Self {
+            chapters,
+            word_count,
+            reading_minutes: reading_minutes(word_count, words_per_minute),
+            words_per_minute,
+        }
+    }
+}
+
+/// Words in a chapter's text
+pub fn count_words(html: &str) -> usize {
+    segment_words(&extract_plain_text(html)).len()
+}
+
+fn reading_minutes(word_count: usize, words_per_minute: u32) -> f64 {
+    word_count as f64 / f64::from(words_per_minute)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_book_statistics() {
+        // Each CJK character is a word
+        assert_eq!(
+            count_words("<p>Don't split well-known words.</p><p>東京</p>"),
+            6
+        );
+
+        let book = EpubBook::from_bytes(&crate::epub::tests::sample_epub()).unwrap();
+        let stats = BookStatistics::from_book(&book, 0);
+        assert_eq!(stats.words_per_minute, 1);
+        assert_eq!(stats.chapters.len(), book.spine.len());
+        assert!(stats.word_count > 0);
+        assert_eq!(
+            stats.word_count,
+            stats.chapters.iter().map(|c| c.word_count).sum::<usize>()
+        );
+        assert_eq!(stats.reading_minutes, stats.word_count as f64);
+
+        let stats = BookStatistics::from_book(&book, 200);
+        assert_eq!(stats.reading_minutes, stats.word_count as f64 / 200.0);
+    }
+}
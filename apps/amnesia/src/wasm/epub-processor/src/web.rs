@@ -0,0 +1,846 @@
+//! Browser bindings (wasm-bindgen)
+//!
+//! Built by default; `wasm-pack build --target web` produces the module the
+//! Obsidian plugin loads through `wasm-adapter.ts`.
+//!
+//! Each Web Worker that imports the module gets an instance with its own
+//! memory, so processors and books are never shared between workers: a
+//! worker pool loads a book in every worker that reads it. Within an
+//! instance, wasm-bindgen checks borrows of the processor, so a call made
+//! while another is running (from an event listener, say) throws instead
+//! of corrupting its state.
+
+use epub_core::ChapterChecksum;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "cfi")]
+use crate::cfi::{DomPosition, TextQuoteAnchor, DEFAULT_CHARS_PER_LOCATION};
+use crate::epub::{BlockMetrics, ChapterOptions, HtmlExportOptions, ImageOptions, LoadOptions};
+use crate::error::{ErrorCode, ErrorInfo};
+use crate::processor::{
+    Processor, ProcessorError, ProcessorLimits, ResourceLimit, DEFAULT_INDEX_MATCHES,
+};
+#[cfg(feature = "search")]
+use crate::search::IndexOptions;
+use crate::text::TextFolding;
+
+/// Initialize the WASM module
+/// Call this before using any other functions
+#[wasm_bindgen(start)]
+pub fn init() {
+    // Set up better panic messages in debug mode
+    #[cfg(feature = "console_error_panic_hook")]
+    console_error_panic_hook::set_once();
+}
+
+/// EPUB Processor - main interface for working with EPUB files
+#[wasm_bindgen]
+pub struct EpubProcessor {
+    inner: Processor,
+    /// Called with each event, see `onEvent`
+    listener: Option<js_sys::Function>,
+}
+
+#[wasm_bindgen]
+impl EpubProcessor {
+    /// Create a new EPUB processor instance
+    ///
+    /// `limits` is an optional `ProcessorLimits` object, e.g.
+    /// `{ maxBookBytes: 100_000_000, maxBooks: 2, maxIndexBytes: 64_000_000 }`;
+    /// calls that would go over one throw a `RESOURCE_LIMIT_EXCEEDED` error
+    /// instead of running the instance out of memory.
+    ///
+    /// Every method throws errors as an `Error` with a `code` and a
+    /// `context` object (see the `error` module).
+    #[wasm_bindgen(constructor)]
+    pub fn new(limits: JsValue) -> Result<EpubProcessor, JsValue> {
+        let limits: ProcessorLimits = from_optional(limits)?;
+        Ok(Self {
+            inner: Processor::with_limits(limits),
+            listener: None,
+        })
+    }
+
+    /// Call `callback` with every event the processor raises:
+    /// `{ type: "loadProgress", loaded, total }`,
+    /// `{ type: "parseWarning", bookId, code, message, href }`,
+    /// `{ type: "indexProgress", bookId, indexed, total, done }` and
+    /// `{ type: "memoryPressure", limit, used, max }`
+    ///
+    /// Events are delivered before the call that raised them returns, so
+    /// the callback cannot call the processor; pass `null` to stop. Errors
+    /// thrown by the callback are logged and otherwise ignored.
+    #[wasm_bindgen(js_name = "onEvent")]
+    pub fn on_event(&mut self, callback: Option<js_sys::Function>) {
+        self.inner.set_events_enabled(callback.is_some());
+        self.listener = callback;
+    }
+
+    /// Load an EPUB file from raw bytes
+    /// Returns a Promise that resolves to a ParsedBook object
+    ///
+    /// `options` is an optional `LoadOptions` object; `{ lazy: true }` keeps
+    /// the archive compressed and decompresses resources as they are read.
+    #[wasm_bindgen(js_name = "loadBook")]
+    pub async fn load_book(
+        &mut self,
+        data: js_sys::Uint8Array,
+        options: JsValue,
+    ) -> Result<JsValue, JsValue> {
+        // Checked before the bytes are copied into WASM memory
+        self.inner
+            .limits()
+            .check(ResourceLimit::BookSize, data.length() as usize)
+            .map_err(js_error)?;
+        let options: LoadOptions = from_optional(options)?;
+        let parsed = self.inner.load_book(data.to_vec(), &options);
+        self.dispatch_events();
+        to_js(&parsed.map_err(js_error)?)
+    }
+
+    /// Get a chapter's content by href
+    ///
+    /// `options` is an optional `ChapterOptions` object, e.g.
+    /// `{ injectAnchors: true, sanitize: true, rewriteUrls: true }`.
+    #[wasm_bindgen(js_name = "getChapter")]
+    pub fn get_chapter(
+        &self,
+        book_id: &str,
+        href: &str,
+        options: JsValue,
+    ) -> Result<JsValue, JsValue> {
+        let options: ChapterOptions = from_optional(options)?;
+
+        to_js(
+            &self
+                .inner
+                .get_chapter(book_id, href, &options)
+                .map_err(js_error)?,
+        )
+    }
+
+    /// Get a chapter with `<mark>` elements around each occurrence of
+    /// `terms`, to show search hits
+    ///
+    /// Terms match whole words ignoring case and accents, as search does;
+    /// `"white whale"` matches the words in sequence and `"whal*"` any word
+    /// starting with "whal". Only text is marked, never tags or attributes.
+    /// `options` is an optional `ChapterOptions` object, as for `getChapter`.
+    #[wasm_bindgen(js_name = "getChapterWithHighlights")]
+    pub fn get_chapter_with_highlights(
+        &self,
+        book_id: &str,
+        href: &str,
+        terms: Vec<String>,
+        options: JsValue,
+    ) -> Result<JsValue, JsValue> {
+        let options: ChapterOptions = from_optional(options)?;
+
+        to_js(
+            &self
+                .inner
+                .get_chapter_with_highlights(book_id, href, &terms, &options)
+                .map_err(js_error)?,
+        )
+    }
+
+    /// Get the footnote or endnote with id `fragment_id` in the document at
+    /// `href`: `{ href, id, kind, html, text }`, or `null` if there is no
+    /// such element
+    ///
+    /// Only the note is read out of the document, sanitized, for rendering
+    /// in a popup. `options` is an optional `ChapterOptions` object; only
+    /// `rewriteUrls` and `urlPrefix` apply.
+    #[wasm_bindgen(js_name = "getFootnote")]
+    pub fn get_footnote(
+        &self,
+        book_id: &str,
+        href: &str,
+        fragment_id: &str,
+        options: JsValue,
+    ) -> Result<JsValue, JsValue> {
+        let options: ChapterOptions = from_optional(options)?;
+
+        to_js(
+            &self
+                .inner
+                .get_footnote(book_id, href, fragment_id, &options)
+                .map_err(js_error)?,
+        )
+    }
+
+    /// Get a chapter's block map: its top-level blocks with their tag, text
+    /// length and image sizes, for estimating layout without parsing it
+    ///
+    /// `metrics` is an optional `BlockMetrics` object, e.g.
+    /// `{ width: 600, fontSize: 16, lineHeight: 1.5 }`; with it each block
+    /// also gets an `estimatedHeight` in pixels.
+    #[wasm_bindgen(js_name = "getChapterBlocks")]
+    pub fn get_chapter_blocks(
+        &self,
+        book_id: &str,
+        href: &str,
+        metrics: JsValue,
+    ) -> Result<JsValue, JsValue> {
+        let metrics: Option<BlockMetrics> = from_optional(metrics)?;
+
+        to_js(
+            &self
+                .inner
+                .get_chapter_blocks(book_id, href, metrics.as_ref())
+                .map_err(js_error)?,
+        )
+    }
+
+    /// Get a resource (image, CSS, etc.) by href
+    ///
+    /// `options` is an optional `ImageOptions` object, e.g.
+    /// `{ maxDimension: 1600, format: "webp" }`, to downscale and transcode
+    /// large JPEG, PNG and WebP images before the browser decodes them.
+    /// Other resources are returned as stored. Builds without the `images`
+    /// feature ignore the options.
+    #[wasm_bindgen(js_name = "getResource")]
+    pub fn get_resource(
+        &self,
+        book_id: &str,
+        href: &str,
+        options: JsValue,
+    ) -> Result<Vec<u8>, JsValue> {
+        let options: ImageOptions = from_optional(options)?;
+        self.inner
+            .get_resource_with(book_id, href, &options)
+            .map_err(js_error)
+    }
+
+    /// Export the book as one self-contained HTML document
+    ///
+    /// `options` is an optional `HtmlExportOptions` object. Spine items
+    /// become sections in spine order, with stylesheets and files up to
+    /// `maxInlineBytes` inlined; returns `{ html, omitted }`, where
+    /// `omitted` lists the hrefs of files left out.
+    #[wasm_bindgen(js_name = "exportHtml")]
+    pub fn export_html(&self, book_id: &str, options: JsValue) -> Result<JsValue, JsValue> {
+        let options: HtmlExportOptions = from_optional(options)?;
+        to_js(
+            &self
+                .inner
+                .export_html(book_id, &options)
+                .map_err(js_error)?,
+        )
+    }
+
+    /// Describe the book as a Readium Web Publication Manifest
+    ///
+    /// The reading order lists the linear spine items; other files are
+    /// resources. Hrefs are what `getChapter` and `getResource` take, so a
+    /// Readium navigator can fetch the book through this processor.
+    #[wasm_bindgen(js_name = "toWebPubManifest")]
+    pub fn to_webpub_manifest(&self, book_id: &str) -> Result<JsValue, JsValue> {
+        to_js(&self.inner.to_webpub_manifest(book_id).map_err(js_error)?)
+    }
+
+    /// Compare a loaded book with the `checksums` of an earlier version
+    ///
+    /// Returns the hrefs of the `changed`, `added`, `removed` and `moved`
+    /// chapters, so only those need reindexing or reanchoring.
+    #[wasm_bindgen(js_name = "changedChapters")]
+    pub fn changed_chapters(&self, book_id: &str, previous: JsValue) -> Result<JsValue, JsValue> {
+        let previous: Vec<ChapterChecksum> = from_js(previous)?;
+        to_js(
+            &self
+                .inner
+                .changed_chapters(book_id, &previous)
+                .map_err(js_error)?,
+        )
+    }
+
+    /// Generate a CFI from a DOM position
+    ///
+    /// `nodePath` holds the `childNodes` indices from the document element
+    /// to the node; `offset` is a `Range` offset into that node.
+    #[cfg(feature = "cfi")]
+    #[wasm_bindgen(js_name = "generateCfi")]
+    pub fn generate_cfi(
+        &self,
+        book_id: &str,
+        spine_index: usize,
+        node_path: Vec<u32>,
+        offset: usize,
+    ) -> Result<String, JsValue> {
+        let position = DomPosition {
+            node_path: node_path.into_iter().map(|i| i as usize).collect(),
+            offset,
+        };
+        self.inner
+            .generate_cfi(book_id, spine_index, &position)
+            .map_err(js_error)
+    }
+
+    /// Resolve a CFI to a location
+    #[cfg(feature = "cfi")]
+    #[wasm_bindgen(js_name = "resolveCfi")]
+    pub fn resolve_cfi(&self, book_id: &str, cfi_str: &str) -> Result<JsValue, JsValue> {
+        to_js(&self.inner.resolve_cfi(book_id, cfi_str).map_err(js_error)?)
+    }
+
+    /// Generate a range CFI for a selection, from the DOM positions of its
+    /// ends (see `generateCfi`)
+    #[cfg(feature = "cfi")]
+    #[wasm_bindgen(js_name = "generateCfiRange")]
+    pub fn generate_cfi_range(
+        &self,
+        book_id: &str,
+        spine_index: usize,
+        start_path: Vec<u32>,
+        start_offset: usize,
+        end_path: Vec<u32>,
+        end_offset: usize,
+    ) -> Result<String, JsValue> {
+        let start = DomPosition {
+            node_path: start_path.into_iter().map(|i| i as usize).collect(),
+            offset: start_offset,
+        };
+        let end = DomPosition {
+            node_path: end_path.into_iter().map(|i| i as usize).collect(),
+            offset: end_offset,
+        };
+        self.inner
+            .generate_cfi_range(book_id, spine_index, &start, &end)
+            .map_err(js_error)
+    }
+
+    /// Generate a CFI for a time in an `<audio>` or `<video>` element, or
+    /// for a clip of it from `start` to `end` seconds
+    ///
+    /// `nodePath` leads to the media element, as in `generateCfi`.
+    #[cfg(feature = "media-overlays")]
+    #[wasm_bindgen(js_name = "generateTemporalCfi")]
+    pub fn generate_temporal_cfi(
+        &self,
+        book_id: &str,
+        spine_index: usize,
+        node_path: Vec<u32>,
+        start: f64,
+        end: Option<f64>,
+    ) -> Result<String, JsValue> {
+        let node_path: Vec<usize> = node_path.into_iter().map(|i| i as usize).collect();
+        self.inner
+            .generate_temporal_cfi(book_id, spine_index, &node_path, start, end)
+            .map_err(js_error)
+    }
+
+    /// Generate a CFI for a point in an image, or for a region of it
+    ///
+    /// Coordinates are percentages of the image's width and height. A region
+    /// runs from (`x`, `y`) to the opposite corner (`endX`, `endY`).
+    /// `nodePath` leads to the image, as in `generateCfi`.
+    #[cfg(feature = "cfi")]
+    #[wasm_bindgen(js_name = "generateSpatialCfi")]
+    #[allow(clippy::too_many_arguments)]
+    pub fn generate_spatial_cfi(
+        &self,
+        book_id: &str,
+        spine_index: usize,
+        node_path: Vec<u32>,
+        x: f64,
+        y: f64,
+        end_x: Option<f64>,
+        end_y: Option<f64>,
+    ) -> Result<String, JsValue> {
+        let node_path: Vec<usize> = node_path.into_iter().map(|i| i as usize).collect();
+        self.inner
+            .generate_spatial_cfi(book_id, spine_index, &node_path, (x, y), (end_x, end_y))
+            .map_err(js_error)
+    }
+
+    /// Resolve both ends of a range CFI
+    #[cfg(feature = "cfi")]
+    #[wasm_bindgen(js_name = "resolveCfiRange")]
+    pub fn resolve_cfi_range(&self, book_id: &str, cfi_str: &str) -> Result<JsValue, JsValue> {
+        to_js(
+            &self
+                .inner
+                .resolve_cfi_range(book_id, cfi_str)
+                .map_err(js_error)?,
+        )
+    }
+
+    /// Quote the text at a CFI: `{ href, exact, prefix, suffix, collapsed }`
+    ///
+    /// Store the quote with the CFI; if the book is updated and the CFI no
+    /// longer resolves, `textQuoteToCfi` finds the text again.
+    #[cfg(feature = "cfi")]
+    #[wasm_bindgen(js_name = "cfiToTextQuote")]
+    pub fn cfi_to_text_quote(&self, book_id: &str, cfi_str: &str) -> Result<JsValue, JsValue> {
+        to_js(
+            &self
+                .inner
+                .cfi_to_text_quote(book_id, cfi_str)
+                .map_err(js_error)?,
+        )
+    }
+
+    /// The text at and around a point or range CFI, for dictionary lookups,
+    /// shared quotes and highlight previews: `{ href, text, word, before,
+    /// after, sentence, truncatedBefore, truncatedAfter }`
+    ///
+    /// `word` is the word at a point; `before` and `after` hold up to
+    /// `charsBefore` and `charsAfter` characters of the location's
+    /// paragraph, cut at word boundaries, and `sentence` the sentence it is
+    /// in within them.
+    #[cfg(feature = "cfi")]
+    #[wasm_bindgen(js_name = "getTextAround")]
+    pub fn get_text_around(
+        &self,
+        book_id: &str,
+        cfi_str: &str,
+        chars_before: usize,
+        chars_after: usize,
+    ) -> Result<JsValue, JsValue> {
+        to_js(
+            &self
+                .inner
+                .text_around(book_id, cfi_str, chars_before, chars_after)
+                .map_err(js_error)?,
+        )
+    }
+
+    /// A fresh CFI for a quote from `cfiToTextQuote`, or undefined if the
+    /// text is no longer in the book
+    #[cfg(feature = "cfi")]
+    #[wasm_bindgen(js_name = "textQuoteToCfi")]
+    pub fn text_quote_to_cfi(
+        &self,
+        book_id: &str,
+        quote: JsValue,
+    ) -> Result<Option<String>, JsValue> {
+        let quote: TextQuoteAnchor = from_js(quote)?;
+        self.inner
+            .text_quote_to_cfi(book_id, &quote)
+            .map_err(js_error)
+    }
+
+    /// Generate epub.js-style locations: CFIs every `charsPerLocation`
+    /// characters (default 150) through the book's linear spine
+    ///
+    /// A position's index among the locations gives progress and page
+    /// numbers that agree across devices.
+    #[cfg(feature = "cfi")]
+    #[wasm_bindgen(js_name = "generateLocations")]
+    pub fn generate_locations(
+        &self,
+        book_id: &str,
+        chars_per_location: Option<usize>,
+    ) -> Result<Vec<String>, JsValue> {
+        self.inner
+            .generate_locations(
+                book_id,
+                chars_per_location.unwrap_or(DEFAULT_CHARS_PER_LOCATION),
+            )
+            .map_err(js_error)
+    }
+
+    /// Progression through the book (0.0-1.0) of a CFI's position, by
+    /// characters of text
+    ///
+    /// The first conversion for a book counts its text, so it is slower than
+    /// later ones.
+    #[cfg(feature = "cfi")]
+    #[wasm_bindgen(js_name = "cfiToPercentage")]
+    pub fn cfi_to_percentage(&mut self, book_id: &str, cfi: &str) -> Result<f64, JsValue> {
+        self.inner.cfi_to_percentage(book_id, cfi).map_err(js_error)
+    }
+
+    /// CFI of the position a fraction (0.0-1.0) of the way through a book's
+    /// text
+    #[cfg(feature = "cfi")]
+    #[wasm_bindgen(js_name = "percentageToCfi")]
+    pub fn percentage_to_cfi(&mut self, book_id: &str, fraction: f64) -> Result<String, JsValue> {
+        self.inner
+            .percentage_to_cfi(book_id, fraction)
+            .map_err(js_error)
+    }
+
+    /// The ToC as a flat list in document order, with each entry's `level`,
+    /// `parent` index, `spineIndex` and start `cfi`
+    #[cfg(feature = "cfi")]
+    #[wasm_bindgen(js_name = "flattenToc")]
+    pub fn flatten_toc(&mut self, book_id: &str) -> Result<JsValue, JsValue> {
+        to_js(&self.inner.flatten_toc(book_id).map_err(js_error)?)
+    }
+
+    /// The `flattenToc` entry being read at a CFI, or undefined before the
+    /// first entry
+    #[cfg(feature = "cfi")]
+    #[wasm_bindgen(js_name = "getCurrentTocEntry")]
+    pub fn get_current_toc_entry(&mut self, book_id: &str, cfi: &str) -> Result<JsValue, JsValue> {
+        to_js(
+            &self
+                .inner
+                .current_toc_entry(book_id, cfi)
+                .map_err(js_error)?,
+        )
+    }
+
+    /// Build a search index for a book
+    ///
+    /// `options` is an optional `IndexOptions` object, e.g.
+    /// `{ altText: true, captions: true, footnotes: true }`, to make image
+    /// alt text, figure captions and footnotes searchable.
+    #[cfg(feature = "search")]
+    #[wasm_bindgen(js_name = "buildSearchIndex")]
+    pub async fn build_search_index(
+        &mut self,
+        book_id: &str,
+        options: JsValue,
+    ) -> Result<(), JsValue> {
+        let options: IndexOptions = from_optional(options)?;
+        let built = self.inner.build_search_index(book_id, &options);
+        self.dispatch_events();
+        built.map_err(js_error)
+    }
+
+    /// Index the next `chaptersPerTick` chapters of a book
+    ///
+    /// Call once per animation frame or idle callback until the returned
+    /// `IndexProgress` is `done`, so a large book is indexed without
+    /// freezing the UI. `options` applies to the call that starts a build.
+    #[cfg(feature = "search")]
+    #[wasm_bindgen(js_name = "buildSearchIndexChunked")]
+    pub fn build_search_index_chunked(
+        &mut self,
+        book_id: &str,
+        chapters_per_tick: usize,
+        options: JsValue,
+    ) -> Result<JsValue, JsValue> {
+        let options: IndexOptions = from_optional(options)?;
+        let progress = self
+            .inner
+            .build_search_index_chunked(book_id, chapters_per_tick, &options);
+        self.dispatch_events();
+        to_js(&progress.map_err(js_error)?)
+    }
+
+    /// Import a prebuilt search index (e.g. downloaded from the server)
+    ///
+    /// The book itself does not need to be loaded, so a cached index keeps
+    /// search working offline.
+    #[cfg(feature = "search")]
+    #[wasm_bindgen(js_name = "importSearchIndex")]
+    pub fn import_search_index(&mut self, book_id: &str, bytes: &[u8]) -> Result<(), JsValue> {
+        let imported = self.inner.import_search_index(book_id, bytes);
+        self.dispatch_events();
+        imported.map_err(js_error)
+    }
+
+    /// Serialize a built search index so it can be cached
+    #[cfg(feature = "search")]
+    #[wasm_bindgen(js_name = "exportSearchIndex")]
+    pub fn export_search_index(&self, book_id: &str) -> Result<Vec<u8>, JsValue> {
+        self.inner.export_search_index(book_id).map_err(js_error)
+    }
+
+    /// Search a book's content
+    ///
+    /// With a `fuzziness` of 1 or 2, words also match words that many
+    /// edits away, e.g. "Dostoevski" finds "Dostoevsky". Short words allow
+    /// fewer edits; the default is 0, exact matches only.
+    #[cfg(feature = "search")]
+    #[wasm_bindgen(js_name = "search")]
+    pub fn search(
+        &self,
+        book_id: &str,
+        query: &str,
+        limit: usize,
+        fuzziness: Option<u8>,
+    ) -> Result<JsValue, JsValue> {
+        to_js(
+            &self
+                .inner
+                .search(book_id, query, limit, fuzziness.unwrap_or(0))
+                .map_err(js_error)?,
+        )
+    }
+
+    /// Search every book with a built or imported index
+    ///
+    /// Results carry a `bookId` and are merged best first, for searching
+    /// the whole library.
+    #[cfg(feature = "search")]
+    #[wasm_bindgen(js_name = "searchAll")]
+    pub fn search_all(&self, query: &str, limit: usize) -> Result<JsValue, JsValue> {
+        to_js(&self.inner.search_all(query, limit))
+    }
+
+    /// Load Knuth–Liang hyphenation patterns for a language
+    ///
+    /// `patterns` and `exceptions` are the contents of the hyph-utf8
+    /// `hyph-<lang>.pat.txt` and `hyph-<lang>.hyp.txt` files. The minimum
+    /// fragment lengths default to 2 and 3. Returns the number of patterns.
+    #[wasm_bindgen(js_name = "loadHyphenationPatterns")]
+    pub fn load_hyphenation_patterns(
+        &mut self,
+        language: &str,
+        patterns: &str,
+        exceptions: Option<String>,
+        left_min: Option<usize>,
+        right_min: Option<usize>,
+    ) -> Result<usize, JsValue> {
+        self.inner
+            .load_hyphenation_patterns(
+                language,
+                patterns,
+                exceptions.as_deref(),
+                left_min,
+                right_min,
+            )
+            .map_err(js_error)
+    }
+
+    /// Languages with loaded or built-in hyphenation patterns
+    #[wasm_bindgen(js_name = "getHyphenationLanguages")]
+    pub fn get_hyphenation_languages(&self) -> Vec<String> {
+        self.inner.hyphenation_languages()
+    }
+
+    /// Hyphenation points of a word as UTF-16 offsets
+    ///
+    /// Returns an empty list when no patterns are loaded for the language.
+    #[wasm_bindgen(js_name = "hyphenate")]
+    pub fn hyphenate(&self, language: &str, word: &str) -> Vec<usize> {
+        self.inner.hyphenate(language, word)
+    }
+
+    /// Get a chapter with soft hyphens (U+00AD) at its hyphenation points,
+    /// for justified text with `hyphens: manual`
+    ///
+    /// `language` defaults to the book's language; the chapter is returned
+    /// unchanged when no patterns are loaded for it. Code, preformatted
+    /// text and attributes are never hyphenated. Take CFIs and selections
+    /// against the chapter without the soft hyphens. `options` is an
+    /// optional `ChapterOptions` object, as for `getChapter`.
+    #[wasm_bindgen(js_name = "hyphenateChapter")]
+    pub fn hyphenate_chapter(
+        &self,
+        book_id: &str,
+        href: &str,
+        language: Option<String>,
+        options: JsValue,
+    ) -> Result<JsValue, JsValue> {
+        let options: ChapterOptions = from_optional(options)?;
+
+        to_js(
+            &self
+                .inner
+                .hyphenate_chapter(book_id, href, language.as_deref(), &options)
+                .map_err(js_error)?,
+        )
+    }
+
+    /// Get a chapter's plain text with word boundaries and hyphenation points
+    ///
+    /// The text is the one the search index sees. `language` defaults to the
+    /// book's language; words carry no hyphenation points when no patterns
+    /// are loaded for it.
+    #[wasm_bindgen(js_name = "getChapterText")]
+    pub fn get_chapter_text(
+        &self,
+        book_id: &str,
+        href: &str,
+        language: Option<String>,
+    ) -> Result<JsValue, JsValue> {
+        to_js(
+            &self
+                .inner
+                .get_chapter_text(book_id, href, language.as_deref())
+                .map_err(js_error)?,
+        )
+    }
+
+    /// Snap a highlight selection to whole words and trim its whitespace
+    ///
+    /// `start` and `end` are UTF-16 offsets into the chapter text from
+    /// `getChapterText`. Returns the corrected offsets with the text quote
+    /// and its context, or `null` when only whitespace is selected.
+    #[wasm_bindgen(js_name = "normalizeSelection")]
+    pub fn normalize_selection(
+        &self,
+        book_id: &str,
+        href: &str,
+        start: usize,
+        end: usize,
+    ) -> Result<JsValue, JsValue> {
+        to_js(
+            &self
+                .inner
+                .normalize_selection(book_id, href, start, end)
+                .map_err(js_error)?,
+        )
+    }
+
+    /// Text of a selection for the clipboard
+    ///
+    /// `start` and `end` are UTF-16 offsets into the chapter text from
+    /// `getChapterText`. `folding` is an optional `TextFolding` object,
+    /// e.g. `{ punctuation: true, ligatures: true }`; nothing is folded by
+    /// default.
+    #[wasm_bindgen(js_name = "copyText")]
+    pub fn copy_text(
+        &self,
+        book_id: &str,
+        href: &str,
+        start: usize,
+        end: usize,
+        folding: JsValue,
+    ) -> Result<String, JsValue> {
+        let folding: TextFolding = from_optional(folding)?;
+        self.inner
+            .copy_text(book_id, href, start, end, &folding)
+            .map_err(js_error)
+    }
+
+    /// Word counts per spine item, the total, and estimated reading times
+    ///
+    /// Words are counted in the text `getChapterText` returns;
+    /// `wordsPerMinute` defaults to 238.
+    #[wasm_bindgen(js_name = "getStatistics")]
+    pub fn get_statistics(
+        &self,
+        book_id: &str,
+        words_per_minute: Option<u32>,
+    ) -> Result<JsValue, JsValue> {
+        to_js(
+            &self
+                .inner
+                .statistics(book_id, words_per_minute)
+                .map_err(js_error)?,
+        )
+    }
+
+    /// The back-of-book index: `{ documents, entries }`, each entry with its
+    /// `term`, `locators`, `see`, `seeAlso` and `subentries`
+    #[wasm_bindgen(js_name = "getBookIndex")]
+    pub fn get_book_index(&mut self, book_id: &str) -> Result<JsValue, JsValue> {
+        to_js(self.inner.book_index(book_id).map_err(js_error)?)
+    }
+
+    /// Index entries whose term is `query` or starts with it, ignoring
+    /// case; exact matches first
+    #[wasm_bindgen(js_name = "lookupIndex")]
+    pub fn lookup_index(
+        &mut self,
+        book_id: &str,
+        query: &str,
+        limit: Option<usize>,
+    ) -> Result<JsValue, JsValue> {
+        to_js(
+            &self
+                .inner
+                .lookup_index(book_id, query, limit.unwrap_or(DEFAULT_INDEX_MATCHES))
+                .map_err(js_error)?,
+        )
+    }
+
+    /// The glossary: `{ documents, entries }`, each entry with its `terms`,
+    /// `definitions`, and the `href` and `id` of the term
+    #[wasm_bindgen(js_name = "getGlossary")]
+    pub fn get_glossary(&mut self, book_id: &str) -> Result<JsValue, JsValue> {
+        to_js(self.inner.glossary(book_id).map_err(js_error)?)
+    }
+
+    /// The book's own definition of a term, or undefined
+    ///
+    /// Case and a plural ending are ignored; fall back to a dictionary when
+    /// the book does not define the term.
+    #[wasm_bindgen(js_name = "defineTerm")]
+    pub fn define_term(&mut self, book_id: &str, term: &str) -> Result<JsValue, JsValue> {
+        to_js(&self.inner.define_term(book_id, term).map_err(js_error)?)
+    }
+
+    /// The book's non-linear spine items (answer keys, pop-up notes), which
+    /// locations and progression skip
+    ///
+    /// Each item's `href` can be passed to `getChapter`; `linkedFrom` lists
+    /// the spine items that link to it.
+    #[wasm_bindgen(js_name = "getAuxiliaryItems")]
+    pub fn get_auxiliary_items(&self, book_id: &str) -> Result<JsValue, JsValue> {
+        to_js(&self.inner.auxiliary_items(book_id).map_err(js_error)?)
+    }
+
+    /// Unload a book to free memory
+    #[wasm_bindgen(js_name = "unloadBook")]
+    pub fn unload_book(&mut self, book_id: &str) {
+        self.inner.unload_book(book_id);
+    }
+
+    /// Get list of loaded book IDs
+    #[wasm_bindgen(js_name = "getLoadedBooks")]
+    pub fn get_loaded_books(&self) -> Vec<String> {
+        self.inner.loaded_books()
+    }
+}
+
+impl Default for EpubProcessor {
+    fn default() -> Self {
+        Self {
+            inner: Processor::new(),
+            listener: None,
+        }
+    }
+}
+
+impl EpubProcessor {
+    /// Hand the events raised by the last call to the listener
+    fn dispatch_events(&mut self) {
+        let events = self.inner.take_events();
+        let Some(listener) = &self.listener else {
+            return;
+        };
+        for event in events {
+            let delivered = to_js(&event).and_then(|event| listener.call1(&JsValue::NULL, &event));
+            if let Err(e) = delivered {
+                web_sys::console::error_2(&"[EPUB] Event listener failed:".into(), &e);
+            }
+        }
+    }
+}
+
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsValue> {
+    serde_wasm_bindgen::to_value(value)
+        .map_err(|e| error_value(&ErrorInfo::new(ErrorCode::Internal, e.to_string())))
+}
+
+/// Deserialize an argument object
+fn from_js<T: DeserializeOwned>(value: JsValue) -> Result<T, JsValue> {
+    serde_wasm_bindgen::from_value(value)
+        .map_err(|e| error_value(&ErrorInfo::new(ErrorCode::InvalidArgument, e.to_string())))
+}
+
+/// Deserialize an options object, defaulting when none was passed
+fn from_optional<T: DeserializeOwned + Default>(value: JsValue) -> Result<T, JsValue> {
+    if value.is_undefined() || value.is_null() {
+        Ok(T::default())
+    } else {
+        from_js(value)
+    }
+}
+
+fn js_error(e: ProcessorError) -> JsValue {
+    error_value(&ErrorInfo::from(e))
+}
+
+/// An `Error` with the info's `code` and `context` as properties
+fn error_value(info: &ErrorInfo) -> JsValue {
+    let error = js_sys::Error::new(&info.message);
+    let properties = [
+        ("code", serde_wasm_bindgen::to_value(&info.code)),
+        ("context", serde_wasm_bindgen::to_value(&info.context)),
+    ];
+    for (name, value) in properties {
+        // Setting a property on a new plain `Error` cannot fail
+        let _ = js_sys::Reflect::set(&error, &name.into(), &value.unwrap_or(JsValue::UNDEFINED));
+    }
+    error.into()
+}
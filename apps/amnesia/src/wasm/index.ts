@@ -10,13 +10,29 @@ export {
   isWasmSupported,
   isWasmInitialized,
   cleanupWasm,
+  ResourceLimitError,
+  isProcessorError,
+  type ProcessorLimits,
+  type ImageOptions,
+  type HtmlExportOptions,
+  type HtmlExport,
+  type WebPubManifest,
+  type WebPubLink,
+  type WebPubContributor,
+  type ProcessorError,
+  type ProcessorErrorCode,
+  type ProcessorEvent,
+  type WarningCode,
   type WasmEpubProcessor,
   type ParsedBook,
   type BookMetadata,
   type Creator,
   type SpineItem,
   type TocEntry,
+  type FlatTocEntry,
   type ChapterContent,
   type CfiLocation,
+  type TextAround,
   type SearchResult,
+  type BookSearchResult,
 } from './wasm-adapter';
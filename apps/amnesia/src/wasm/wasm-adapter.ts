@@ -7,10 +7,123 @@
 
 // These types mirror the Rust structures
 export interface ParsedBook {
+  /** Id the book is stored under; may carry a `-2`, `-3`, ... collision suffix */
   id: string;
+  /** Whether `id` is the package identifier or derived from `fingerprint` */
+  idSource: 'identifier' | 'contentHash';
+  /** Hex SHA-1 of the spine checksums; equal for identical content */
+  fingerprint: string;
   metadata: BookMetadata;
   spine: SpineItem[];
   toc: TocEntry[];
+  /** Virtual sub-items of oversize spine items; pass `href` to getChapter */
+  chunks: ChapterChunk[];
+  rendition: Rendition;
+  /** Content checksum of each spine item; keep to pass to changedChapters */
+  checksums: ChapterChecksum[];
+  /** Every rendition in the container, default first; most books have one */
+  renditions: RootFile[];
+  /** Which of `renditions` was loaded */
+  renditionIndex: number;
+}
+
+/** A package document listed in container.xml: one rendition of the book */
+export interface RootFile {
+  fullPath: string;
+  label?: string;
+  layout?: string;
+  /** BCP 47 language tag */
+  language?: string;
+  /** CSS media query the rendition targets */
+  media?: string;
+  accessMode?: string;
+}
+
+/**
+ * Which rendition to load. `index` picks one outright; the other fields pick
+ * the first rendition matching all of them, else the default rendition
+ */
+export interface RenditionSelector {
+  index?: number;
+  /** Matched ignoring case */
+  label?: string;
+  layout?: string;
+  /** Matches the same or a more specific tag: `en` matches `en-GB` */
+  language?: string;
+  accessMode?: string;
+}
+
+export type Layout = 'reflowable' | 'pre-paginated';
+
+/** Page size in CSS pixels */
+export interface Viewport {
+  width: number;
+  height: number;
+}
+
+/** Book-wide rendition properties; pre-paginated books have fixed pages */
+export interface Rendition {
+  layout: Layout;
+  orientation?: string;
+  spread?: string;
+  /** Default page size of pre-paginated items */
+  viewport?: Viewport;
+  /** `page-progression-direction` of the spine; unset for "default" */
+  pageProgressionDirection?: PageProgression;
+  /** Kindle `primary-writing-mode`, e.g. "vertical-rl" */
+  writingMode?: string;
+}
+
+/**
+ * Direction pages turn in. The spine, locations and CFIs are in reading
+ * order either way; in an RTL book the next page is to the left.
+ */
+export type PageProgression = 'ltr' | 'rtl';
+
+export interface ChapterChunk {
+  href: string;
+  parentHref: string;
+  spineIndex: number;
+  index: number;
+}
+
+/** A non-linear spine item; pass `href` to getChapter */
+export interface AuxiliaryItem {
+  spineIndex: number;
+  href: string;
+  mediaType: string;
+  /** Label of the first ToC entry pointing into the item */
+  title?: string;
+  /** Spine items linking to this one */
+  linkedFrom: number[];
+}
+
+/** A footnote or endnote cut out of its document, for a popup */
+export interface Footnote {
+  /** Document holding the note */
+  href: string;
+  id: string;
+  /** footnote, endnote, rearnote or note, when marked as one */
+  kind?: string;
+  /** Sanitized inner HTML of the note */
+  html: string;
+  text: string;
+}
+
+export interface ChapterChecksum {
+  spineIndex: number;
+  href: string;
+  /** Hex SHA-1 of the spine item's bytes */
+  checksum: string;
+}
+
+/** Spine hrefs that differ between two versions of a book */
+export interface ChecksumDiff {
+  changed: string[];
+  added: string[];
+  removed: string[];
+  /** Same content at a different spine position, so its CFIs changed */
+  moved: string[];
 }
 
 export interface BookMetadata {
@@ -21,11 +134,34 @@ export interface BookMetadata {
   publisher?: string;
   description?: string;
   coverHref?: string;
+  date?: string;
+  rights?: string;
+  subjects: string[];
+  accessibility: Accessibility;
+}
+
+/** schema.org accessibility metadata; empty when the book declares none */
+export interface Accessibility {
+  /** Senses the content is written for: textual, visual, auditory, ... */
+  accessModes: string[];
+  /** Sets of access modes that are each enough to read the whole book */
+  accessModesSufficient: string[][];
+  /** structuralNavigation, alternativeText, tableOfContents, ... */
+  features: string[];
+  /** flashing, motionSimulation, sound, none, ... */
+  hazards: string[];
+  summary?: string;
+  controls: string[];
+  apis: string[];
+  /** Accessibility specifications the book claims, e.g. WCAG 2.1 Level AA */
+  conformsTo: string[];
+  certifiedBy?: string;
 }
 
 export interface Creator {
   name: string;
   role?: string;
+  fileAs?: string;
 }
 
 export interface SpineItem {
@@ -33,6 +169,13 @@ export interface SpineItem {
   href: string;
   mediaType: string;
   linear: boolean;
+  /** Overrides the book's layout for this item */
+  layout?: Layout;
+  pageSpread?: 'left' | 'right' | 'center';
+  /** `rendition:spread-*`: overrides the book's `spread` for this item */
+  spread?: string;
+  /** Page size of a pre-paginated item */
+  viewport?: Viewport;
 }
 
 export interface TocEntry {
@@ -43,6 +186,24 @@ export interface TocEntry {
   children: TocEntry[];
 }
 
+/** A ToC entry placed in the spine, from flattenToc */
+export interface FlatTocEntry {
+  id: string;
+  /** Href as given in the ToC */
+  href: string;
+  label: string;
+  level: number;
+  /** Index of the parent entry in the flat list */
+  parent?: number;
+  /** Spine item the entry points into; unset for hrefs outside the spine */
+  spineIndex?: number;
+  /**
+   * Where the entry starts: the element with the href's fragment id, or the
+   * start of the spine item
+   */
+  cfi?: string;
+}
+
 export interface ChapterContent {
   href: string;
   html: string;
@@ -50,34 +211,746 @@ export interface ChapterContent {
   images: string[];
 }
 
+export interface LoadOptions {
+  /**
+   * Keep the archive compressed and decompress resources as they are read,
+   * for image-heavy books too large to hold extracted
+   */
+  lazy?: boolean;
+  /** Byte budget of the decompressed-resource cache in lazy mode (default 32MB) */
+  resourceCacheBytes?: number;
+  /**
+   * When a different book already has the id: load it under a suffixed id
+   * (default), throw, or replace the loaded book
+   */
+  onCollision?: 'suffix' | 'error' | 'replace';
+  /** Rendition to load from a book with several (default: the first) */
+  rendition?: RenditionSelector;
+}
+
+/** Limits set when the processor is created; every limit is off by default */
+export interface ProcessorLimits {
+  /** Largest EPUB file loadBook accepts, in bytes */
+  maxBookBytes?: number;
+  /** Books that can be loaded at once; reloading a book does not count */
+  maxBooks?: number;
+  /** Estimated memory all search indexes together may take, in bytes */
+  maxIndexBytes?: number;
+}
+
+/**
+ * How getResource processes an image. JPEG, PNG and WebP images are
+ * downscaled and re-encoded in WASM; other resources are returned as stored.
+ * Results are cached per book, href and options.
+ */
+export interface ImageOptions {
+  /** Longest side in pixels; larger images are scaled down to fit */
+  maxDimension?: number;
+  /** Format to re-encode to (default: keep the image's format) */
+  format?: 'webp' | 'jpeg' | 'png';
+  /** JPEG quality from 1 to 100 (default 80); WebP is encoded lossless */
+  quality?: number;
+}
+
+/** How exportHtml builds its document */
+export interface HtmlExportOptions {
+  /** Also export non-linear spine items (answer keys, notes), after the reading order */
+  includeAuxiliary?: boolean;
+  /**
+   * Largest image, font or other file to inline as a data URI, in bytes,
+   * after `images` is applied (default 2MB); larger files are left out
+   */
+  maxInlineBytes?: number;
+  /** Downscaling or transcoding applied to images before inlining */
+  images?: ImageOptions;
+  /** Start each spine item on a new page when printed (default true) */
+  pageBreaks?: boolean;
+}
+
+/** A book as one self-contained HTML document, from exportHtml */
+export interface HtmlExport {
+  html: string;
+  /** Hrefs of files that were missing or over `maxInlineBytes` */
+  omitted: string[];
+}
+
+/** A person or organization credited in a WebPub manifest */
+export interface WebPubContributor {
+  name: string;
+  sortAs?: string;
+  /** MARC relator code, for entries under `contributor` */
+  role?: string;
+}
+
+/** A link in a WebPub manifest; hrefs are what getChapter and getResource take */
+export interface WebPubLink {
+  href: string;
+  type?: string;
+  title?: string;
+  /** `cover` or `contents` */
+  rel?: string;
+  /** Page size of a pre-paginated item */
+  width?: number;
+  height?: number;
+  properties?: {
+    page?: 'left' | 'right' | 'center';
+    /** Set where the item overrides the book's layout */
+    layout?: 'fixed' | 'reflowable';
+    /** Set where the item overrides the book's spread */
+    spread?: string;
+    /** `mathml`, `svg`, `js`, `remote-resources` */
+    contains?: string[];
+  };
+  children?: WebPubLink[];
+}
+
+/** A Readium Web Publication Manifest, from toWebPubManifest */
+export interface WebPubManifest {
+  '@context': string;
+  metadata: {
+    '@type': string;
+    conformsTo: string;
+    title: string;
+    identifier?: string;
+    language?: string;
+    author?: WebPubContributor[];
+    translator?: WebPubContributor[];
+    editor?: WebPubContributor[];
+    illustrator?: WebPubContributor[];
+    narrator?: WebPubContributor[];
+    contributor?: WebPubContributor[];
+    publisher?: string;
+    description?: string;
+    published?: string;
+    subject?: string[];
+    readingProgression: PageProgression;
+    presentation: {
+      layout: 'fixed' | 'reflowable';
+      orientation?: string;
+      spread?: string;
+    };
+    accessibility?: {
+      conformsTo?: string[];
+      certification?: { certifiedBy: string };
+      summary?: string;
+      accessMode?: string[];
+      accessModeSufficient?: string[][];
+      feature?: string[];
+      hazard?: string[];
+    };
+  };
+  /** Empty; add a `self` link where the manifest is served */
+  links: WebPubLink[];
+  /** Linear spine items */
+  readingOrder: WebPubLink[];
+  /** Non-linear spine items, then the other files */
+  resources: WebPubLink[];
+  toc: WebPubLink[];
+}
+
+export interface ChapterOptions {
+  /** Add deterministic data-anchor attributes to block elements */
+  injectAnchors?: boolean;
+  /**
+   * Add ARIA roles derived from epub:type (doc-chapter, doc-noteref, ...)
+   * and label sections with their headings, for screen readers
+   */
+  ariaRoles?: boolean;
+  /** Remove scripts, event handler attributes and javascript: URLs */
+  sanitize?: boolean;
+  /**
+   * Rewrite relative image, stylesheet and link URLs to
+   * `epub://{bookId}/{href}`, where `href` can be passed to getResource
+   */
+  rewriteUrls?: boolean;
+  /** Prefix to rewrite URLs with instead of `epub://{bookId}/` */
+  urlPrefix?: string;
+}
+
+/** A top-level block of a chapter, from getChapterBlocks */
+export interface ChapterBlock {
+  index: number;
+  /** Element name, lowercase */
+  tag: string;
+  /** Element path below `<body>` (`/4/2`) */
+  path: string;
+  /** data-anchor id, for anchored text elements */
+  anchor?: string;
+  /** Characters of whitespace-normalized text */
+  textLength: number;
+  /** Images in the block; `src` is empty for an inline SVG drawing */
+  images: { src: string; width?: number; height?: number }[];
+  /** Height in pixels, when metrics were passed */
+  estimatedHeight?: number;
+}
+
+/** Page metrics for estimating block heights, in CSS pixels */
+export interface BlockMetrics {
+  /** Width of the text column (default 600) */
+  width?: number;
+  /** Default 16 */
+  fontSize?: number;
+  /** Multiple of the font size (default 1.5) */
+  lineHeight?: number;
+}
+
+/**
+ * A position in a chapter's DOM: `childNodes` indices from the document
+ * element down to a node, and a Range offset into that node
+ */
+export interface DomPosition {
+  nodePath: number[];
+  offset: number;
+}
+
 export interface CfiLocation {
   href: string;
   spineIndex: number;
   elementPath: string;
   offset?: number;
+  /** Seconds into the audio or video, for a temporal CFI */
+  seconds?: number;
+  /** Point in the image as percentages of its size, for a spatial CFI */
+  point?: { x: number; y: number };
+  /** Set when the CFI resolves against the chapter XHTML */
+  position?: DomPosition;
+}
+
+export interface CfiRangeLocation {
+  start: CfiLocation;
+  end: CfiLocation;
+}
+
+/**
+ * The text at a CFI, with whitespace collapsed, for finding the position
+ * again after the book is updated and the CFI breaks
+ */
+export interface TextQuoteAnchor {
+  /** Spine item the quote was taken from; searched first */
+  href: string;
+  exact: string;
+  prefix?: string;
+  suffix?: string;
+  /** A point at the start of `exact` rather than a range over it */
+  collapsed?: boolean;
+}
+
+/** The text at and around a location, from getTextAround */
+export interface TextAround {
+  /** Spine item of the location */
+  href: string;
+  /** Text of a range; empty for a point */
+  text: string;
+  /** For a point, the word it is in, for dictionary lookups */
+  word?: string;
+  /** Up to `charsBefore` characters of the paragraph, cut at a word */
+  before: string;
+  /** Up to `charsAfter` characters of the paragraph, cut at a word */
+  after: string;
+  /** The sentence the location is in, within `before` and `after` */
+  sentence: string;
+  /** Whether `before` stops short of the paragraph's start (show an ellipsis) */
+  truncatedBefore: boolean;
+  /** Whether `after` stops short of the paragraph's end */
+  truncatedAfter: boolean;
+}
+
+export type TextKind = 'body' | 'altText' | 'caption' | 'footnote';
+
+/** Text a search index covers besides the body; all off by default */
+export interface IndexOptions {
+  /** Image alt attributes */
+  altText?: boolean;
+  /** <figcaption> elements */
+  captions?: boolean;
+  /** Footnotes and endnotes marked with epub:type or role */
+  footnotes?: boolean;
+}
+
+/** How far a chunked search index build has got */
+export interface IndexProgress {
+  /** Chapters read so far */
+  indexed: number;
+  /** Chapters to read in all */
+  total: number;
+  /** Whether the index is built and searchable */
+  done: boolean;
 }
 
 export interface SearchResult {
   href: string;
   spineIndex: number;
+  /**
+   * Range CFI of the match, ready for resolveCfiRange and highlighting;
+   * only the chapter's CFI if the book is not loaded (an imported index)
+   */
   cfi: string;
   excerpt: string;
+  /** Byte offset of the match in the normalized search text */
   position: number;
+  /** Match range in ChapterText.text, in UTF-16 code units */
+  textStart: number;
+  textEnd: number;
+  /**
+   * Query clause matched: a word, a run of words, a "phrase", a prefix*, or
+   * a word~ matched misspelled by a fuzzy search
+   */
+  clause: string;
+  /** Where the match is; alt text follows the body in the chapter text */
+  kind: TextKind;
+  /**
+   * Whether the match is in a non-linear spine item (an answer key, pop-up
+   * notes); always false when the book is not loaded
+   */
+  nonLinear: boolean;
+  /** BM25 relevance of the result's chapter; results are sorted by it */
+  score: number;
+}
+
+export interface BookSearchResult extends SearchResult {
+  bookId: string;
+}
+
+/** Offsets are UTF-16 code units into ChapterText.text */
+export interface WordBoundary {
+  start: number;
+  end: number;
+  /** Offsets before which a hyphen may be inserted */
+  hyphens: number[];
+}
+
+export interface ChapterText {
+  href: string;
+  /** Language whose hyphenation patterns were applied, if any */
+  language?: string;
+  /** Plain text as seen by the search index */
+  text: string;
+  words: WordBoundary[];
+}
+
+/** A highlight selection snapped to whole words; offsets as in ChapterText */
+export interface NormalizedSelection {
+  href: string;
+  start: number;
+  end: number;
+  /** Selected text, for a TextQuoteSelector */
+  exact: string;
+  /** Up to 32 characters before `exact` */
+  prefix: string;
+  /** Up to 32 characters after `exact` */
+  suffix: string;
+}
+
+/** Typographic characters replaced with plain ones in copied text */
+export interface TextFolding {
+  /** Curly quotes to `'` and `"`, dashes to `-` (`--` for em), `…` to `...` */
+  punctuation?: boolean;
+  /** Latin ligatures (`ﬁ`, `ﬂ`, ...) split into their letters */
+  ligatures?: boolean;
+}
+
+/** Words of one spine item; CJK characters count as words */
+export interface ChapterStatistics {
+  spineIndex: number;
+  href: string;
+  /** Non-linear items are left out of the book's totals */
+  linear: boolean;
+  wordCount: number;
+  readingMinutes: number;
+}
+
+export interface BookStatistics {
+  /** Every spine item in order */
+  chapters: ChapterStatistics[];
+  wordCount: number;
+  readingMinutes: number;
+  /** Reading speed the times were estimated with */
+  wordsPerMinute: number;
+}
+
+/** Where an index entry points */
+export interface IndexLocator {
+  spineIndex: number;
+  href: string;
+  fragment?: string;
+  /** Text of the locator, usually a page number */
+  label: string;
+}
+
+/** A term of a back-of-book index */
+export interface IndexEntry {
+  term: string;
+  locators: IndexLocator[];
+  /** Terms to look up instead */
+  see: string[];
+  /** Terms to look up as well */
+  seeAlso: string[];
+  subentries: IndexEntry[];
+}
+
+export interface BookIndex {
+  /** Spine indexes of the items holding the index */
+  documents: number[];
+  entries: IndexEntry[];
+}
+
+export interface IndexMatch {
+  /** Terms from the top-level entry down to the match */
+  terms: string[];
+  entry: IndexEntry;
+}
+
+/** A glossary term with the book's definitions of it */
+export interface GlossaryEntry {
+  /** The term and any variants listed with it */
+  terms: string[];
+  definitions: string[];
+  spineIndex: number;
+  href: string;
+  /** Element id of the term, for linking to the glossary */
+  id?: string;
+}
+
+export interface Glossary {
+  /** Spine indexes of the items holding a glossary */
+  documents: number[];
+  entries: GlossaryEntry[];
 }
 
 /**
  * WASM EPUB Processor interface
+ *
+ * Search, CFI and media overlay methods throw when the module was built
+ * without their cargo feature (e.g. the `parse-only` build).
  */
 export interface WasmEpubProcessor {
-  loadBook(data: Uint8Array): Promise<ParsedBook>;
-  getChapter(bookId: string, href: string): ChapterContent;
-  getResource(bookId: string, href: string): Uint8Array;
-  generateCfi(bookId: string, spineIndex: number, path: string, offset: number): string;
+  /**
+   * Throws DrmProtectedError for DRM-protected books, and
+   * ResourceLimitError past `maxBookBytes` or `maxBooks`
+   */
+  loadBook(data: Uint8Array, options?: LoadOptions): Promise<ParsedBook>;
+  getChapter(bookId: string, href: string, options?: ChapterOptions): ChapterContent;
+  /**
+   * A chapter with `<mark>` around each occurrence of `terms`, matched as
+   * search matches them (whole words, ignoring case and accents; `whal*`
+   * as a prefix). Only text is marked, never tags or attributes; generate
+   * CFIs against the unmarked chapter.
+   */
+  getChapterWithHighlights(bookId: string, href: string, terms: string[], options?: ChapterOptions): ChapterContent;
+  /**
+   * The note a noteref points at (`href` and its fragment id), without
+   * loading the whole target chapter; null if there is no such element.
+   * Only `rewriteUrls` and `urlPrefix` of `options` apply.
+   */
+  getFootnote(bookId: string, href: string, fragmentId: string, options?: ChapterOptions): Footnote | null;
+  /**
+   * A chapter's top-level blocks with text lengths and image sizes, for
+   * estimating layout and virtualizing huge chapters without parsing them;
+   * with `metrics`, each block also gets an estimated height
+   */
+  getChapterBlocks(bookId: string, href: string, metrics?: BlockMetrics): ChapterBlock[];
+  /**
+   * A resource's bytes; with `options`, large images are downscaled or
+   * transcoded first, so low-memory devices never decode the original
+   */
+  getResource(bookId: string, href: string, options?: ImageOptions): Uint8Array;
+  /**
+   * The book as one self-contained HTML document, for printing or for
+   * annotating in other tools: spine items in order as sections, with
+   * stylesheets and files up to a size cap inlined
+   */
+  exportHtml(bookId: string, options?: HtmlExportOptions): HtmlExport;
+  /**
+   * The book as a Readium Web Publication Manifest, so Readium navigator
+   * components can render it with this processor fetching its resources
+   */
+  toWebPubManifest(bookId: string): WebPubManifest;
+  /**
+   * Compare a loaded book with the checksums of an earlier version, to
+   * reindex and reanchor only the chapters that changed
+   */
+  changedChapters(bookId: string, previous: ChapterChecksum[]): ChecksumDiff;
+  generateCfi(bookId: string, spineIndex: number, nodePath: number[], offset: number): string;
   resolveCfi(bookId: string, cfi: string): CfiLocation;
-  buildSearchIndex(bookId: string): Promise<void>;
-  search(bookId: string, query: string, limit?: number): SearchResult[];
+  generateCfiRange(bookId: string, spineIndex: number, start: DomPosition, end: DomPosition): string;
+  resolveCfiRange(bookId: string, cfi: string): CfiRangeLocation;
+  /** Quote the text at a CFI, to store alongside it */
+  cfiToTextQuote(bookId: string, cfi: string): TextQuoteAnchor;
+  /** A fresh CFI for a stored quote, or undefined if the text is gone */
+  textQuoteToCfi(bookId: string, quote: TextQuoteAnchor): string | undefined;
+  /**
+   * The word, sentence and surrounding paragraph text at a point or range
+   * CFI, for dictionary lookups, shared quotes and highlight previews
+   */
+  getTextAround(bookId: string, cfi: string, charsBefore: number, charsAfter: number): TextAround;
+  /**
+   * CFI for a time in the `<audio>` or `<video>` element at `nodePath`, or
+   * for a clip of it from `start` to `end` seconds (read-aloud positions,
+   * audiobook bookmarks)
+   */
+  generateTemporalCfi(
+    bookId: string,
+    spineIndex: number,
+    nodePath: number[],
+    start: number,
+    end?: number,
+  ): string;
+  /**
+   * CFI for a point in the image at `nodePath`, or for the region from
+   * (`x`, `y`) to the opposite corner (`endX`, `endY`); coordinates are
+   * percentages of the image's width and height
+   */
+  generateSpatialCfi(
+    bookId: string,
+    spineIndex: number,
+    nodePath: number[],
+    x: number,
+    y: number,
+    endX?: number,
+    endY?: number,
+  ): string;
+  /**
+   * epub.js-style locations: CFIs every `charsPerLocation` characters
+   * (default 150) through the linear spine, for progress and page counts
+   * that agree across devices
+   */
+  generateLocations(bookId: string, charsPerLocation?: number): string[];
+  /**
+   * Progression (0.0-1.0) of a CFI's position by characters of text, as in
+   * the server's Progression selector. The first call per book counts its
+   * text.
+   */
+  cfiToPercentage(bookId: string, cfi: string): number;
+  /** CFI of the position a fraction (0.0-1.0) of the way through the text */
+  percentageToCfi(bookId: string, fraction: number): string;
+  /** The ToC in document order, with each entry's spine index and start CFI */
+  flattenToc(bookId: string): FlatTocEntry[];
+  /**
+   * The entry being read at a CFI: the one starting last at or before it;
+   * undefined before the first entry
+   */
+  getCurrentTocEntry(bookId: string, cfi: string): FlatTocEntry | undefined;
+  /**
+   * Throws ResourceLimitError past `maxIndexBytes`, as do the other methods
+   * that build or import an index
+   */
+  buildSearchIndex(bookId: string, options?: IndexOptions): Promise<void>;
+  /**
+   * Index the next `chaptersPerTick` chapters. Call until `done` to spread
+   * indexing across frames; `options` applies to the call that starts a build
+   */
+  buildSearchIndexChunked(
+    bookId: string,
+    chaptersPerTick: number,
+    options?: IndexOptions,
+  ): IndexProgress;
+  /**
+   * Build a search index a few chapters per animation frame, reporting
+   * progress after each; resolves once the index is searchable
+   */
+  buildSearchIndexInBackground(
+    bookId: string,
+    options?: IndexOptions & {
+      chaptersPerTick?: number;
+      onProgress?: (progress: IndexProgress) => void;
+    },
+  ): Promise<void>;
+  importSearchIndex(bookId: string, data: Uint8Array): void;
+  exportSearchIndex(bookId: string): Uint8Array;
+  /**
+   * Search a book. Supports "quoted phrases", AND/OR/NOT (upper case),
+   * -exclusions, prefix* wildcards and parentheses. With `fuzziness` 1 or 2,
+   * words also match words that many edits away ("Dostoevski" finds
+   * "Dostoevsky"); short words allow fewer edits
+   */
+  search(bookId: string, query: string, limit?: number, fuzziness?: number): SearchResult[];
+  /** Search every book with a built or imported index, best first */
+  searchAll(query: string, limit?: number): BookSearchResult[];
+  loadHyphenationPatterns(
+    language: string,
+    patterns: string,
+    exceptions?: string,
+    leftMin?: number,
+    rightMin?: number
+  ): number;
+  getHyphenationLanguages(): string[];
+  hyphenate(language: string, word: string): number[];
+  /**
+   * A chapter with soft hyphens (U+00AD) at its hyphenation points, for
+   * justified text with `hyphens: manual`. `language` defaults to the
+   * book's; the chapter is unchanged when there are no patterns for it.
+   * Generate CFIs against the chapter without the soft hyphens.
+   */
+  hyphenateChapter(bookId: string, href: string, language?: string, options?: ChapterOptions): ChapterContent;
+  getChapterText(bookId: string, href: string, language?: string): ChapterText;
+  /**
+   * Snap a selection of the chapter text to whole words and trim its
+   * whitespace before the highlight is stored; null if only whitespace
+   */
+  normalizeSelection(
+    bookId: string,
+    href: string,
+    start: number,
+    end: number
+  ): NormalizedSelection | null;
+  /**
+   * Text of a selection of the chapter text for the clipboard, folded as
+   * `folding` asks (nothing by default)
+   */
+  copyText(
+    bookId: string,
+    href: string,
+    start: number,
+    end: number,
+    folding?: TextFolding
+  ): string;
+  /** Word counts and reading times; `wordsPerMinute` defaults to 238 */
+  getStatistics(bookId: string, wordsPerMinute?: number): BookStatistics;
+  /** The back-of-book index (`epub:type="index"`); empty if there is none */
+  getBookIndex(bookId: string): BookIndex;
+  /** Index entries whose term is `query` or starts with it; exact first */
+  lookupIndex(bookId: string, query: string, limit?: number): IndexMatch[];
+  /** Terms and definitions of the glossary (`epub:type="glossary"`) */
+  getGlossary(bookId: string): Glossary;
+  /**
+   * The book's own definition of a term, ignoring case and plural endings;
+   * null if the book does not define it
+   */
+  defineTerm(bookId: string, term: string): GlossaryEntry | null;
+  /**
+   * Non-linear spine items (answer keys, pop-up notes), which locations and
+   * progression skip
+   */
+  getAuxiliaryItems(bookId: string): AuxiliaryItem[];
   unloadBook(bookId: string): void;
   getLoadedBooks(): string[];
+  /**
+   * Call `listener` with the events of loadBook, index builds and imports,
+   * before the call that raised them returns (so it must not call the
+   * processor itself); `null` stops
+   */
+  onEvent(listener: ((event: ProcessorEvent) => void) | null): void;
+}
+
+/** DRM scheme of a protected book */
+export type DrmScheme = 'lcp' | 'adobe-adept' | 'apple-fairplay' | 'unknown';
+
+/** Which of the ProcessorLimits a ResourceLimitError is about */
+export type ResourceLimit = 'bookSize' | 'books' | 'indexMemory';
+
+/** Stable code of a ParseWarning */
+export type WarningCode = 'TOC_MISSING' | 'SPINE_ITEM_MISSING' | 'SPINE_ITEM_NOT_UTF8';
+
+/** Something the processor reports to the onEvent listener */
+export type ProcessorEvent =
+  /** Spine items read so far by loadBook; the book id is not known yet */
+  | { type: 'loadProgress'; loaded: number; total: number }
+  /** A problem that did not stop a book loading */
+  | { type: 'parseWarning'; bookId: string; code: WarningCode; message: string; href?: string }
+  /** Chapters indexed so far by buildSearchIndex or buildSearchIndexChunked */
+  | ({ type: 'indexProgress'; bookId: string } & IndexProgress)
+  /**
+   * A call left `used` at 80% or more of a ProcessorLimits limit; unload
+   * books or drop indexes before the next call fails
+   */
+  | { type: 'memoryPressure'; limit: ResourceLimit; used: number; max: number };
+
+/** Stable code of an error thrown by the processor */
+export type ProcessorErrorCode =
+  | 'BOOK_NOT_FOUND'
+  | 'INDEX_NOT_BUILT'
+  | 'INVALID_ARCHIVE'
+  | 'IO_ERROR'
+  | 'INVALID_EPUB'
+  | 'RESOURCE_NOT_FOUND'
+  | 'SECURITY_VIOLATION'
+  | 'DRM_PROTECTED'
+  | 'RENDITION_NOT_FOUND'
+  | 'INVALID_IMAGE'
+  | 'INVALID_CFI'
+  | 'CFI_RESOLUTION_FAILED'
+  | 'SPINE_ITEM_NOT_FOUND'
+  | 'INVALID_ARGUMENT'
+  | 'SEARCH_FAILED'
+  | 'INVALID_SEARCH_INDEX'
+  | 'INVALID_HYPHENATION_PATTERNS'
+  | 'ID_COLLISION'
+  | 'RESOURCE_LIMIT_EXCEEDED'
+  | 'INTERNAL';
+
+/** Values an error's message was built from, set for the codes they apply to */
+export interface ProcessorErrorContext {
+  /**
+   * BOOK_NOT_FOUND and INDEX_NOT_BUILT: the book that is not loaded or has
+   * no search index; ID_COLLISION: the id already used by a different book
+   */
+  bookId?: string;
+  /** RESOURCE_NOT_FOUND */
+  href?: string;
+  /** DRM_PROTECTED */
+  scheme?: DrmScheme;
+  /** RENDITION_NOT_FOUND: the requested rendition and how many there are */
+  index?: number;
+  count?: number;
+  /** RESOURCE_LIMIT_EXCEEDED */
+  limit?: ResourceLimit;
+  requested?: number;
+  max?: number;
+}
+
+/** An Error thrown by any processor method */
+export interface ProcessorError extends Error {
+  code: ProcessorErrorCode;
+  context: ProcessorErrorContext;
+}
+
+/** Whether `error` was thrown by the processor, so `code` can be branched on */
+export function isProcessorError(error: unknown): error is ProcessorError {
+  return error instanceof Error && typeof (error as Partial<ProcessorError>).code === 'string';
+}
+
+/**
+ * Thrown by loadBook for DRM-protected books, which cannot be read
+ */
+export class DrmProtectedError extends Error {
+  constructor(readonly scheme: DrmScheme) {
+    super(`This book is protected by DRM (${scheme}) and cannot be opened`);
+    this.name = 'DrmProtectedError';
+  }
+}
+
+/**
+ * Thrown instead of running the WASM instance out of memory when a call
+ * would go over one of the ProcessorLimits
+ */
+export class ResourceLimitError extends Error {
+  constructor(
+    readonly limit: ResourceLimit,
+    readonly requested: number,
+    readonly max: number,
+  ) {
+    super(`Resource limit exceeded (${limit}): ${requested} requested, limit is ${max}`);
+    this.name = 'ResourceLimitError';
+  }
+}
+
+/**
+ * Convert the processor's DRM_PROTECTED and RESOURCE_LIMIT_EXCEEDED errors
+ * to DrmProtectedError and ResourceLimitError; other errors are returned
+ * unchanged
+ */
+function classifyError(error: unknown): unknown {
+  if (!isProcessorError(error)) {
+    return error;
+  }
+  const { code, context } = error;
+  if (code === 'DRM_PROTECTED') {
+    return new DrmProtectedError(context.scheme ?? 'unknown');
+  }
+  if (code === 'RESOURCE_LIMIT_EXCEEDED' && context.limit) {
+    return new ResourceLimitError(context.limit, context.requested ?? 0, context.max ?? 0);
+  }
+  return error;
 }
 
 /**
@@ -109,9 +982,14 @@ export function isWasmSupported(): boolean {
  * Initialize the WASM module
  *
  * @param wasmSource - Path/URL to the WASM file, or WASM bytes as ArrayBuffer
+ * @param limits - Resource limits for the processor, e.g. on low-memory
+ *   mobile devices; ignored once the module is initialized
  * @returns Promise that resolves when WASM is ready
  */
-export async function initializeWasm(wasmSource?: string | ArrayBuffer): Promise<WasmEpubProcessor> {
+export async function initializeWasm(
+  wasmSource?: string | ArrayBuffer,
+  limits?: ProcessorLimits,
+): Promise<WasmEpubProcessor> {
   if (processorInstance) {
     return createProcessor();
   }
@@ -140,7 +1018,7 @@ export async function initializeWasm(wasmSource?: string | ArrayBuffer): Promise
 
     // Create processor instance
     if (wasm.EpubProcessor) {
-      processorInstance = new wasm.EpubProcessor();
+      processorInstance = new wasm.EpubProcessor(limits);
     } else {
       throw new Error('EpubProcessor class not found in WASM module');
     }
@@ -161,32 +1039,265 @@ function createProcessor(): WasmEpubProcessor {
   }
 
   return {
-    async loadBook(data: Uint8Array): Promise<ParsedBook> {
-      return await processorInstance.loadBook(data);
+    async loadBook(data: Uint8Array, options?: LoadOptions): Promise<ParsedBook> {
+      try {
+        return await processorInstance.loadBook(data, options);
+      } catch (error) {
+        throw classifyError(error);
+      }
+    },
+
+    getChapter(bookId: string, href: string, options?: ChapterOptions): ChapterContent {
+      return processorInstance.getChapter(bookId, href, options);
+    },
+
+    getChapterWithHighlights(bookId: string, href: string, terms: string[], options?: ChapterOptions): ChapterContent {
+      return processorInstance.getChapterWithHighlights(bookId, href, terms, options);
+    },
+
+    getFootnote(bookId: string, href: string, fragmentId: string, options?: ChapterOptions): Footnote | null {
+      return processorInstance.getFootnote(bookId, href, fragmentId, options);
+    },
+
+    getChapterBlocks(bookId: string, href: string, metrics?: BlockMetrics): ChapterBlock[] {
+      return processorInstance.getChapterBlocks(bookId, href, metrics);
     },
 
-    getChapter(bookId: string, href: string): ChapterContent {
-      return processorInstance.getChapter(bookId, href);
+    getResource(bookId: string, href: string, options?: ImageOptions): Uint8Array {
+      return processorInstance.getResource(bookId, href, options);
     },
 
-    getResource(bookId: string, href: string): Uint8Array {
-      return processorInstance.getResource(bookId, href);
+    exportHtml(bookId: string, options?: HtmlExportOptions): HtmlExport {
+      return processorInstance.exportHtml(bookId, options);
     },
 
-    generateCfi(bookId: string, spineIndex: number, path: string, offset: number): string {
-      return processorInstance.generateCfi(bookId, spineIndex, path, offset);
+    toWebPubManifest(bookId: string): WebPubManifest {
+      return processorInstance.toWebPubManifest(bookId);
+    },
+
+    changedChapters(bookId: string, previous: ChapterChecksum[]): ChecksumDiff {
+      return processorInstance.changedChapters(bookId, previous);
+    },
+
+    generateCfi(bookId: string, spineIndex: number, nodePath: number[], offset: number): string {
+      return processorInstance.generateCfi(bookId, spineIndex, new Uint32Array(nodePath), offset);
     },
 
     resolveCfi(bookId: string, cfi: string): CfiLocation {
       return processorInstance.resolveCfi(bookId, cfi);
     },
 
-    async buildSearchIndex(bookId: string): Promise<void> {
-      await processorInstance.buildSearchIndex(bookId);
+    generateCfiRange(bookId: string, spineIndex: number, start: DomPosition, end: DomPosition): string {
+      return processorInstance.generateCfiRange(
+        bookId,
+        spineIndex,
+        new Uint32Array(start.nodePath),
+        start.offset,
+        new Uint32Array(end.nodePath),
+        end.offset,
+      );
+    },
+
+    resolveCfiRange(bookId: string, cfi: string): CfiRangeLocation {
+      return processorInstance.resolveCfiRange(bookId, cfi);
+    },
+
+    cfiToTextQuote(bookId: string, cfi: string): TextQuoteAnchor {
+      return processorInstance.cfiToTextQuote(bookId, cfi);
     },
 
-    search(bookId: string, query: string, limit = 50): SearchResult[] {
-      return processorInstance.search(bookId, query, limit);
+    textQuoteToCfi(bookId: string, quote: TextQuoteAnchor): string | undefined {
+      return processorInstance.textQuoteToCfi(bookId, quote);
+    },
+
+    getTextAround(bookId: string, cfi: string, charsBefore: number, charsAfter: number): TextAround {
+      return processorInstance.getTextAround(bookId, cfi, charsBefore, charsAfter);
+    },
+
+    generateTemporalCfi(
+      bookId: string,
+      spineIndex: number,
+      nodePath: number[],
+      start: number,
+      end?: number,
+    ): string {
+      return processorInstance.generateTemporalCfi(
+        bookId,
+        spineIndex,
+        new Uint32Array(nodePath),
+        start,
+        end,
+      );
+    },
+
+    generateSpatialCfi(
+      bookId: string,
+      spineIndex: number,
+      nodePath: number[],
+      x: number,
+      y: number,
+      endX?: number,
+      endY?: number,
+    ): string {
+      return processorInstance.generateSpatialCfi(
+        bookId,
+        spineIndex,
+        new Uint32Array(nodePath),
+        x,
+        y,
+        endX,
+        endY,
+      );
+    },
+
+    generateLocations(bookId: string, charsPerLocation?: number): string[] {
+      return processorInstance.generateLocations(bookId, charsPerLocation);
+    },
+
+    cfiToPercentage(bookId: string, cfi: string): number {
+      return processorInstance.cfiToPercentage(bookId, cfi);
+    },
+
+    percentageToCfi(bookId: string, fraction: number): string {
+      return processorInstance.percentageToCfi(bookId, fraction);
+    },
+
+    flattenToc(bookId: string): FlatTocEntry[] {
+      return processorInstance.flattenToc(bookId);
+    },
+
+    getCurrentTocEntry(bookId: string, cfi: string): FlatTocEntry | undefined {
+      return processorInstance.getCurrentTocEntry(bookId, cfi);
+    },
+
+    async buildSearchIndex(bookId: string, options?: IndexOptions): Promise<void> {
+      try {
+        await processorInstance.buildSearchIndex(bookId, options);
+      } catch (error) {
+        throw classifyError(error);
+      }
+    },
+
+    buildSearchIndexChunked(
+      bookId: string,
+      chaptersPerTick: number,
+      options?: IndexOptions,
+    ): IndexProgress {
+      try {
+        return processorInstance.buildSearchIndexChunked(bookId, chaptersPerTick, options);
+      } catch (error) {
+        throw classifyError(error);
+      }
+    },
+
+    async buildSearchIndexInBackground(
+      bookId: string,
+      options: IndexOptions & {
+        chaptersPerTick?: number;
+        onProgress?: (progress: IndexProgress) => void;
+      } = {},
+    ): Promise<void> {
+      const { chaptersPerTick = 4, onProgress, ...indexOptions } = options;
+      for (;;) {
+        let progress: IndexProgress;
+        try {
+          progress = processorInstance.buildSearchIndexChunked(bookId, chaptersPerTick, indexOptions);
+        } catch (error) {
+          throw classifyError(error);
+        }
+        onProgress?.(progress);
+        if (progress.done) return;
+        await nextFrame();
+      }
+    },
+
+    importSearchIndex(bookId: string, data: Uint8Array): void {
+      try {
+        processorInstance.importSearchIndex(bookId, data);
+      } catch (error) {
+        throw classifyError(error);
+      }
+    },
+
+    exportSearchIndex(bookId: string): Uint8Array {
+      return processorInstance.exportSearchIndex(bookId);
+    },
+
+    search(bookId: string, query: string, limit = 50, fuzziness = 0): SearchResult[] {
+      return processorInstance.search(bookId, query, limit, fuzziness);
+    },
+
+    searchAll(query: string, limit = 50): BookSearchResult[] {
+      return processorInstance.searchAll(query, limit);
+    },
+
+    loadHyphenationPatterns(
+      language: string,
+      patterns: string,
+      exceptions?: string,
+      leftMin?: number,
+      rightMin?: number
+    ): number {
+      return processorInstance.loadHyphenationPatterns(language, patterns, exceptions, leftMin, rightMin);
+    },
+
+    getHyphenationLanguages(): string[] {
+      return processorInstance.getHyphenationLanguages();
+    },
+
+    hyphenate(language: string, word: string): number[] {
+      return Array.from(processorInstance.hyphenate(language, word) as Uint32Array);
+    },
+
+    hyphenateChapter(bookId: string, href: string, language?: string, options?: ChapterOptions): ChapterContent {
+      return processorInstance.hyphenateChapter(bookId, href, language, options);
+    },
+
+    getChapterText(bookId: string, href: string, language?: string): ChapterText {
+      return processorInstance.getChapterText(bookId, href, language);
+    },
+
+    normalizeSelection(
+      bookId: string,
+      href: string,
+      start: number,
+      end: number
+    ): NormalizedSelection | null {
+      return processorInstance.normalizeSelection(bookId, href, start, end) ?? null;
+    },
+
+    copyText(
+      bookId: string,
+      href: string,
+      start: number,
+      end: number,
+      folding?: TextFolding
+    ): string {
+      return processorInstance.copyText(bookId, href, start, end, folding);
+    },
+
+    getStatistics(bookId: string, wordsPerMinute?: number): BookStatistics {
+      return processorInstance.getStatistics(bookId, wordsPerMinute);
+    },
+
+    getBookIndex(bookId: string): BookIndex {
+      return processorInstance.getBookIndex(bookId);
+    },
+
+    lookupIndex(bookId: string, query: string, limit?: number): IndexMatch[] {
+      return processorInstance.lookupIndex(bookId, query, limit);
+    },
+
+    getGlossary(bookId: string): Glossary {
+      return processorInstance.getGlossary(bookId);
+    },
+
+    defineTerm(bookId: string, term: string): GlossaryEntry | null {
+      return processorInstance.defineTerm(bookId, term) ?? null;
+    },
+
+    getAuxiliaryItems(bookId: string): AuxiliaryItem[] {
+      return processorInstance.getAuxiliaryItems(bookId);
     },
 
     unloadBook(bookId: string): void {
@@ -196,12 +1307,27 @@ function createProcessor(): WasmEpubProcessor {
     getLoadedBooks(): string[] {
       return processorInstance.getLoadedBooks();
     },
+
+    onEvent(listener: ((event: ProcessorEvent) => void) | null): void {
+      processorInstance.onEvent(listener);
+    },
   };
 }
 
 /**
  * Get the current processor instance (if initialized)
  */
+/** Yield to the browser until the next frame (or macrotask, off the main thread) */
+function nextFrame(): Promise<void> {
+  return new Promise((resolve) => {
+    if (typeof requestAnimationFrame === 'function') {
+      requestAnimationFrame(() => resolve());
+    } else {
+      setTimeout(resolve, 0);
+    }
+  });
+}
+
 export function getProcessor(): WasmEpubProcessor | null {
   if (!processorInstance) {
     return null;
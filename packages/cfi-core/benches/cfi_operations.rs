@@ -0,0 +1,111 @@
+//! CFI Benchmarks
+//!
+//! Performance benchmarks for CFI parsing, generation, and comparison.
+//! These run on every highlight load and every progress sync, so they are
+//! cheap individually but add up on books with thousands of annotations.
+//!
+//! Run with: `cargo bench --bench cfi_operations`
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use cfi_core::{compare_cfi_strings, generate_cfi, generate_cfi_range, parse, Cfi};
+
+/// Representative CFIs, from a bare spine reference to a fully asserted range
+const SAMPLE_CFIS: &[(&str, &str)] = &[
+    ("spine_only", "epubcfi(/6/4)"),
+    ("simple_offset", "epubcfi(/6/4!/4/2/1:42)"),
+    ("with_ids", "epubcfi(/6/14[chap05ref]!/4[body01]/10[para05]/3:10)"),
+    (
+        "text_assertion",
+        "epubcfi(/6/4[chap01ref]!/4[body01]/10[para05]/2/1:3[yyy,zzz])",
+    ),
+    ("range", "epubcfi(/6/4[chap01ref]!/4[body01]/10[para05],/2/1:1,/3:4)"),
+];
+
+/// Build a shuffled set of CFIs spread across spine items and paragraphs
+fn create_cfi_set(count: usize) -> Vec<Cfi> {
+    (0..count)
+        .map(|i| {
+            // Cheap deterministic scramble so sort input is not pre-ordered
+            let n = (i * 7919) % count;
+            generate_cfi(n % 40, &[n % 25, n % 3], n % 2, (n % 500) as u32)
+        })
+        .collect()
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cfi_parse");
+
+    for (name, cfi) in SAMPLE_CFIS {
+        group.throughput(Throughput::Bytes(cfi.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), cfi, |b, cfi| {
+            b.iter(|| parse(black_box(cfi)))
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_generate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cfi_generate");
+
+    group.bench_function("point", |b| {
+        b.iter(|| generate_cfi(black_box(12), black_box(&[3, 0, 5]), 0, black_box(128)))
+    });
+
+    group.bench_function("range", |b| {
+        b.iter(|| {
+            generate_cfi_range(
+                black_box(12),
+                black_box(&[3, 0, 5]),
+                0,
+                black_box(10),
+                black_box(&[3, 2]),
+                0,
+                black_box(40),
+            )
+        })
+    });
+
+    group.bench_function("to_string", |b| {
+        let cfi = parse(SAMPLE_CFIS[3].1).unwrap();
+        b.iter(|| black_box(&cfi).to_string())
+    });
+
+    group.finish();
+}
+
+fn bench_compare(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cfi_compare");
+
+    let a = parse("epubcfi(/6/4!/4/2/1:10)").unwrap();
+    let b_cfi = parse("epubcfi(/6/4!/4/2/1:20)").unwrap();
+    group.bench_function("parsed", |b| b.iter(|| black_box(&a).cmp(black_box(&b_cfi))));
+
+    group.bench_function("strings", |b| {
+        b.iter(|| {
+            compare_cfi_strings(
+                black_box("epubcfi(/6/4!/4/2/1:10)"),
+                black_box("epubcfi(/6/4!/4/2/1:20)"),
+            )
+        })
+    });
+
+    // Sorting is what the annotation list does on every load
+    for count in [100, 1_000, 10_000] {
+        let cfis = create_cfi_set(count);
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::new("sort", count), &cfis, |b, cfis| {
+            b.iter(|| {
+                let mut sorted = cfis.clone();
+                sorted.sort();
+                sorted
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse, bench_generate, bench_compare);
+criterion_main!(benches);
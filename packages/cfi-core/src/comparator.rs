@@ -0,0 +1,334 @@
+//! CFI Comparison and Ordering
+//!
+//! Implements comparison logic for CFIs to enable sorting annotations
+//! and determining reading progress order.
+
+use core::cmp::Ordering;
+
+use super::types::*;
+
+impl Ord for Cfi {
+    /// Ranges order by their start, then by their end; a non-range CFI
+    /// compares like a collapsed range
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare_endpoints(self, other, false).then_with(|| compare_endpoints(self, other, true))
+    }
+}
+
+impl PartialOrd for Cfi {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CfiPath {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Compare steps first, then character, temporal and spatial offsets
+        compare_steps(self.steps.iter(), other.steps.iter())
+            .then_with(|| {
+                compare_offsets(
+                    self.character_offset.as_ref(),
+                    other.character_offset.as_ref(),
+                )
+            })
+            .then_with(|| {
+                compare_temporal(
+                    self.temporal_offset.as_ref(),
+                    other.temporal_offset.as_ref(),
+                )
+            })
+            .then_with(|| {
+                compare_spatial(self.spatial_offset.as_ref(), other.spatial_offset.as_ref())
+            })
+    }
+}
+
+impl PartialOrd for CfiPath {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CfiStep {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (&self.step_type, &other.step_type) {
+            // Indirection steps are "equal" for ordering purposes
+            // (they just mark document boundaries)
+            (StepType::Indirection, StepType::Indirection) => Ordering::Equal,
+            // Element steps are compared by index
+            (StepType::Element(a), StepType::Element(b)) => a.cmp(b),
+            // Indirection comes before element steps at the same level
+            (StepType::Indirection, StepType::Element(_)) => Ordering::Less,
+            (StepType::Element(_), StepType::Indirection) => Ordering::Greater,
+        }
+    }
+}
+
+impl PartialOrd for CfiStep {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Compare two sequences of CFI steps
+fn compare_steps<'a>(
+    mut a: impl Iterator<Item = &'a CfiStep>,
+    mut b: impl Iterator<Item = &'a CfiStep>,
+) -> Ordering {
+    loop {
+        match (a.next(), b.next()) {
+            (Some(step_a), Some(step_b)) => {
+                let cmp = step_a.cmp(step_b);
+                if cmp != Ordering::Equal {
+                    return cmp;
+                }
+            }
+            // If all compared steps are equal, longer path is "greater"
+            (Some(_), None) => return Ordering::Greater,
+            (None, Some(_)) => return Ordering::Less,
+            (None, None) => return Ordering::Equal,
+        }
+    }
+}
+
+fn compare_offsets(a: Option<&CharacterOffset>, b: Option<&CharacterOffset>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.offset.cmp(&b.offset),
+        (Some(_), None) => Ordering::Greater,
+        (None, Some(_)) => Ordering::Less,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Temporal offsets order by time; a location without one (the start of the
+/// media) comes first
+fn compare_temporal(a: Option<&TemporalOffset>, b: Option<&TemporalOffset>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.seconds.total_cmp(&b.seconds),
+        (Some(_), None) => Ordering::Greater,
+        (None, Some(_)) => Ordering::Less,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Spatial offsets order top to bottom, then left to right, like text on
+/// the page; a location without one (the whole image) comes first
+fn compare_spatial(a: Option<&SpatialOffset>, b: Option<&SpatialOffset>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.y.total_cmp(&b.y).then_with(|| a.x.total_cmp(&b.x)),
+        (Some(_), None) => Ordering::Greater,
+        (None, Some(_)) => Ordering::Less,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Compare the starts (or the ends) of two CFIs without building the full
+/// paths (see [`Cfi::start`])
+fn compare_endpoints(a: &Cfi, b: &Cfi, end: bool) -> Ordering {
+    let (local_a, local_b) = (local_path(a, end), local_path(b, end));
+    let (path_a, path_b) = (local_a.unwrap_or(&a.path), local_b.unwrap_or(&b.path));
+    compare_steps(endpoint_steps(a, local_a), endpoint_steps(b, local_b))
+        .then_with(|| {
+            compare_offsets(
+                path_a.character_offset.as_ref(),
+                path_b.character_offset.as_ref(),
+            )
+        })
+        .then_with(|| {
+            compare_temporal(
+                path_a.temporal_offset.as_ref(),
+                path_b.temporal_offset.as_ref(),
+            )
+        })
+        .then_with(|| {
+            compare_spatial(
+                path_a.spatial_offset.as_ref(),
+                path_b.spatial_offset.as_ref(),
+            )
+        })
+}
+
+/// A range's local start or end path
+fn local_path(cfi: &Cfi, end: bool) -> Option<&CfiPath> {
+    cfi.range
+        .as_ref()
+        .map(|range| if end { &range.end } else { &range.start })
+}
+
+fn endpoint_steps<'a>(
+    cfi: &'a Cfi,
+    local: Option<&'a CfiPath>,
+) -> impl Iterator<Item = &'a CfiStep> {
+    cfi.path
+        .steps
+        .iter()
+        .chain(local.into_iter().flat_map(|path| path.steps.iter()))
+}
+
+/// Determine if CFI `a` comes before CFI `b` in reading order
+pub fn is_before(a: &Cfi, b: &Cfi) -> bool {
+    a < b
+}
+
+/// Determine if CFI `a` comes after CFI `b` in reading order
+pub fn is_after(a: &Cfi, b: &Cfi) -> bool {
+    a > b
+}
+
+/// Check if a CFI falls within a range
+pub fn is_in_range(cfi: &Cfi, start: &Cfi, end: &Cfi) -> bool {
+    cfi >= start && cfi <= end
+}
+
+/// Compare two CFI strings, returning their ordering
+/// Returns None if either CFI is invalid
+pub fn compare_cfi_strings(a: &str, b: &str) -> Option<Ordering> {
+    let cfi_a = super::parser::parse(a).ok()?;
+    let cfi_b = super::parser::parse(b).ok()?;
+    Some(cfi_a.cmp(&cfi_b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_cfi_ordering_same_chapter() {
+        let a = parse("epubcfi(/6/4!/4/2/1:10)").unwrap();
+        let b = parse("epubcfi(/6/4!/4/2/1:20)").unwrap();
+
+        assert!(a < b);
+        assert!(is_before(&a, &b));
+        assert!(is_after(&b, &a));
+    }
+
+    #[test]
+    fn test_cfi_ordering_different_chapters() {
+        let a = parse("epubcfi(/6/4!/4/2)").unwrap();
+        let b = parse("epubcfi(/6/6!/4/2)").unwrap();
+
+        assert!(a < b);
+    }
+
+    #[test]
+    fn test_cfi_ordering_different_elements() {
+        let a = parse("epubcfi(/6/4!/4/2)").unwrap();
+        let b = parse("epubcfi(/6/4!/4/4)").unwrap();
+
+        assert!(a < b);
+    }
+
+    #[test]
+    fn test_cfi_ordering_nested_depth() {
+        let a = parse("epubcfi(/6/4!/4/2)").unwrap();
+        let b = parse("epubcfi(/6/4!/4/2/1)").unwrap();
+
+        // Deeper path comes after shallower path
+        assert!(a < b);
+    }
+
+    #[test]
+    fn test_cfi_equality() {
+        let a = parse("epubcfi(/6/4!/4/2/1:42)").unwrap();
+        let b = parse("epubcfi(/6/4!/4/2/1:42)").unwrap();
+
+        assert_eq!(a, b);
+        assert_eq!(a.cmp(&b), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_cfi_in_range() {
+        let start = parse("epubcfi(/6/4!/4/2/1:0)").unwrap();
+        let end = parse("epubcfi(/6/4!/4/2/1:100)").unwrap();
+        let middle = parse("epubcfi(/6/4!/4/2/1:50)").unwrap();
+        let outside = parse("epubcfi(/6/4!/4/2/1:150)").unwrap();
+
+        assert!(is_in_range(&middle, &start, &end));
+        assert!(!is_in_range(&outside, &start, &end));
+    }
+
+    #[test]
+    fn test_sort_cfis() {
+        let mut cfis = [
+            parse("epubcfi(/6/8!/4/2/1:50)").unwrap(),
+            parse("epubcfi(/6/4!/4/2/1:10)").unwrap(),
+            parse("epubcfi(/6/6!/4/2/1:30)").unwrap(),
+            parse("epubcfi(/6/4!/4/2/1:5)").unwrap(),
+        ];
+
+        cfis.sort();
+
+        // Verify sorted order
+        assert_eq!(cfis[0].to_string(), "epubcfi(/6/4!/4/2/1:5)");
+        assert_eq!(cfis[1].to_string(), "epubcfi(/6/4!/4/2/1:10)");
+        assert_eq!(cfis[2].to_string(), "epubcfi(/6/6!/4/2/1:30)");
+        assert_eq!(cfis[3].to_string(), "epubcfi(/6/8!/4/2/1:50)");
+    }
+
+    #[test]
+    fn test_range_ordering() {
+        let range = parse("epubcfi(/6/4!/4/2,/1:5,/3:12)").unwrap();
+        assert_eq!(range.start().to_string(), "epubcfi(/6/4!/4/2/1:5)");
+        assert_eq!(range.end().to_string(), "epubcfi(/6/4!/4/2/3:12)");
+
+        // Ranges order by start, then end
+        let later_start = parse("epubcfi(/6/4!/4/2,/1:6,/1:8)").unwrap();
+        let longer = parse("epubcfi(/6/4!/4/2,/1:5,/5:1)").unwrap();
+        assert!(range < later_start);
+        assert!(range < longer);
+        assert!(parse("epubcfi(/6/4!/4/2/1:5)").unwrap() < range);
+        assert!(parse("epubcfi(/6/4!/4/2/1:4)").unwrap() < range);
+        assert!(parse("epubcfi(/6/4!/4/2/1:6)").unwrap() > range);
+        assert!(is_in_range(
+            &parse("epubcfi(/6/4!/4/2/2)").unwrap(),
+            &range.start(),
+            &range.end()
+        ));
+    }
+
+    #[test]
+    fn test_temporal_ordering() {
+        let start = parse("epubcfi(/6/4!/4/2/6)").unwrap();
+        let early = parse("epubcfi(/6/4!/4/2/6~9.5)").unwrap();
+        let late = parse("epubcfi(/6/4!/4/2/6~61)").unwrap();
+        assert!(start < early);
+        assert!(early < late);
+        // Steps still decide first
+        assert!(late < parse("epubcfi(/6/4!/4/2/8~0)").unwrap());
+
+        let clip = parse("epubcfi(/6/4!/4/2,/6~10,/6~15)").unwrap();
+        assert!(early < clip);
+        assert!(is_in_range(
+            &parse("epubcfi(/6/4!/4/2/6~12)").unwrap(),
+            &clip.start(),
+            &clip.end()
+        ));
+        assert!(!is_in_range(&late, &clip.start(), &clip.end()));
+    }
+
+    #[test]
+    fn test_spatial_ordering() {
+        let image = parse("epubcfi(/6/4!/4/2/4)").unwrap();
+        let top_right = parse("epubcfi(/6/4!/4/2/4@80:10)").unwrap();
+        let middle_left = parse("epubcfi(/6/4!/4/2/4@5:50)").unwrap();
+        assert!(image < top_right);
+        assert!(top_right < middle_left);
+        assert!(middle_left < parse("epubcfi(/6/4!/4/2/4@6:50)").unwrap());
+
+        let region = parse("epubcfi(/6/4!/4/2,/4@10:20,/4@60:80)").unwrap();
+        assert!(top_right < region);
+        assert!(is_in_range(&middle_left, &region.start(), &region.end()));
+    }
+
+    #[test]
+    fn test_compare_cfi_strings() {
+        assert_eq!(
+            compare_cfi_strings("epubcfi(/6/4!/4/2/1:10)", "epubcfi(/6/4!/4/2/1:20)"),
+            Some(Ordering::Less)
+        );
+
+        assert_eq!(compare_cfi_strings("invalid", "epubcfi(/6/4!/4/2)"), None);
+    }
+}
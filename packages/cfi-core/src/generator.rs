@@ -2,6 +2,10 @@
 //!
 //! Generates CFI strings from document positions and text selections.
 
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
 use super::types::*;
 
 /// Builder for constructing CFIs programmatically
@@ -139,10 +143,11 @@ impl Default for CfiBuilder {
 /// * `char_offset` - Character offset within the text node
 ///
 /// # Example
-/// ```ignore
+/// ```
+/// # use cfi_core::generate_cfi;
 /// // CFI for character 42 in the first text node of the first paragraph in chapter 2
 /// let cfi = generate_cfi(1, &[0, 0], 0, 42);
-/// // Returns: epubcfi(/6/4!/4/2/1:42)
+/// assert_eq!(cfi.to_string(), "epubcfi(/6/4!/2/2/2/1:42)");
 /// ```
 pub fn generate_cfi(
     spine_index: usize,
@@ -237,6 +242,111 @@ pub fn generate_cfi_range(
     )
 }
 
+/// Generate a CFI for a time in an audio or video element, or for a clip of
+/// it when `end` is given
+///
+/// # Arguments
+/// * `spine_index` - 0-based index of the spine item
+/// * `element_path` - Element path to the media element within the body
+/// * `start` - Seconds into the media
+/// * `end` - Seconds into the media where the clip ends
+///
+/// # Example
+/// ```
+/// # use cfi_core::generate_temporal_cfi;
+/// let cfi = generate_temporal_cfi(1, &[0, 2], 12.5, None);
+/// assert_eq!(cfi.to_string(), "epubcfi(/6/4!/2/2/6~12.5)");
+///
+/// let clip = generate_temporal_cfi(1, &[0, 2], 12.5, Some(20.0));
+/// assert_eq!(clip.to_string(), "epubcfi(/6/4!/2/2,/6~12.5,/6~20)");
+/// ```
+pub fn generate_temporal_cfi(
+    spine_index: usize,
+    element_path: &[usize],
+    start: f64,
+    end: Option<f64>,
+) -> Cfi {
+    let builder = element_builder(spine_index, element_path);
+    let Some(end) = end else {
+        return builder.temporal_offset(start).build();
+    };
+
+    let offset = |seconds: f64| CfiPath {
+        temporal_offset: Some(TemporalOffset { seconds }),
+        ..CfiPath::new()
+    };
+    element_range(builder.path, offset(start), offset(end))
+}
+
+/// Generate a CFI for a point in an image, or for a rectangular region of it
+/// when `corner` is given
+///
+/// Points are `(x, y)` percentages of the image's width and height. A region
+/// runs from `point` (its top-left corner) to `corner` (its bottom-right).
+///
+/// # Arguments
+/// * `spine_index` - 0-based index of the spine item
+/// * `element_path` - Element path to the image within the body
+/// * `point` - Point in the image, or the region's top-left corner
+/// * `corner` - The region's bottom-right corner
+///
+/// # Example
+/// ```
+/// # use cfi_core::generate_spatial_cfi;
+/// let cfi = generate_spatial_cfi(1, &[0, 2], (50.0, 25.5), None);
+/// assert_eq!(cfi.to_string(), "epubcfi(/6/4!/2/2/6@50:25.5)");
+///
+/// let region = generate_spatial_cfi(1, &[0, 2], (10.0, 20.0), Some((60.0, 80.0)));
+/// assert_eq!(region.to_string(), "epubcfi(/6/4!/2/2,/6@10:20,/6@60:80)");
+/// ```
+pub fn generate_spatial_cfi(
+    spine_index: usize,
+    element_path: &[usize],
+    point: (f64, f64),
+    corner: Option<(f64, f64)>,
+) -> Cfi {
+    let builder = element_builder(spine_index, element_path);
+    let Some(corner) = corner else {
+        return builder.spatial_offset(point.0, point.1).build();
+    };
+
+    let offset = |(x, y): (f64, f64)| CfiPath {
+        spatial_offset: Some(SpatialOffset { x, y }),
+        ..CfiPath::new()
+    };
+    element_range(builder.path, offset(point), offset(corner))
+}
+
+/// Builder positioned at an element within a spine item's body
+fn element_builder(spine_index: usize, element_path: &[usize]) -> CfiBuilder {
+    let mut builder = CfiBuilder::new()
+        .package_step()
+        .spine_item(spine_index)
+        .indirection()
+        .element(0); // body
+    for &idx in element_path {
+        builder = builder.element(idx);
+    }
+    builder
+}
+
+/// A range whose ends differ only in their offsets into one element; each
+/// end keeps the element's step
+fn element_range(mut path: CfiPath, start: CfiPath, end: CfiPath) -> Cfi {
+    let element = path.steps.pop().expect("path ends at the body or deeper");
+    let local = |offsets: CfiPath| CfiPath {
+        steps: vec![element.clone()],
+        ..offsets
+    };
+    Cfi::with_range(
+        path,
+        CfiRange {
+            start: local(start),
+            end: local(end),
+        },
+    )
+}
+
 /// Generate a simple progression-based CFI
 /// This creates a CFI that represents a percentage through a spine item
 ///
@@ -258,6 +368,7 @@ pub fn generate_progression_cfi(spine_index: usize, _progression: f64) -> Cfi {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::string::ToString;
 
     #[test]
     fn test_cfi_builder_simple() {
@@ -305,6 +416,30 @@ mod tests {
         assert!(s.starts_with("epubcfi(/6/2!/2/2,"));
     }
 
+    #[test]
+    fn test_generate_temporal_cfi() {
+        let cfi = generate_temporal_cfi(0, &[3], 90.0, None);
+        assert_eq!(cfi.to_string(), "epubcfi(/6/2!/2/8~90)");
+        assert_eq!(cfi.temporal_offset(), Some(90.0));
+
+        let clip = generate_temporal_cfi(0, &[3], 90.0, Some(95.5));
+        assert!(clip.is_range());
+        assert_eq!(clip.start().to_string(), "epubcfi(/6/2!/2/8~90)");
+        assert_eq!(clip.end().to_string(), "epubcfi(/6/2!/2/8~95.5)");
+    }
+
+    #[test]
+    fn test_generate_spatial_cfi() {
+        let cfi = generate_spatial_cfi(0, &[3], (12.5, 40.0), None);
+        assert_eq!(cfi.to_string(), "epubcfi(/6/2!/2/8@12.5:40)");
+        assert_eq!(cfi.spatial_offset(), Some((12.5, 40.0)));
+
+        let region = generate_spatial_cfi(0, &[3], (0.0, 0.0), Some((100.0, 50.0)));
+        assert!(region.is_range());
+        assert_eq!(region.start().to_string(), "epubcfi(/6/2!/2/8@0:0)");
+        assert_eq!(region.end().to_string(), "epubcfi(/6/2!/2/8@100:50)");
+    }
+
     #[test]
     fn test_spine_index_conversion() {
         // Spine index 0 should become /2 in CFI
@@ -12,46 +12,57 @@
 //! range     = path "," path
 //! ```
 
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
 use super::types::*;
-use thiserror::Error;
 
 /// CFI parsing errors
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum CfiParseError {
-    #[error("Empty CFI string")]
     Empty,
-
-    #[error("CFI must start with 'epubcfi('")]
     MissingPrefix,
-
-    #[error("CFI must end with ')'")]
     MissingClosingParen,
-
-    #[error("Expected '/' or '!' at position {0}")]
     ExpectedStep(usize),
-
-    #[error("Expected number at position {0}")]
     ExpectedNumber(usize),
-
-    #[error("Unclosed bracket at position {0}")]
     UnclosedBracket(usize),
-
-    #[error("Invalid character offset at position {0}")]
     InvalidCharacterOffset(usize),
-
-    #[error("Invalid temporal offset at position {0}")]
     InvalidTemporalOffset(usize),
-
-    #[error("Invalid spatial offset at position {0}")]
     InvalidSpatialOffset(usize),
-
-    #[error("Unexpected character '{0}' at position {1}")]
     UnexpectedChar(char, usize),
-
-    #[error("Invalid range format")]
     InvalidRange,
 }
 
+impl fmt::Display for CfiParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "Empty CFI string"),
+            Self::MissingPrefix => write!(f, "CFI must start with 'epubcfi('"),
+            Self::MissingClosingParen => write!(f, "CFI must end with ')'"),
+            Self::ExpectedStep(pos) => write!(f, "Expected '/' or '!' at position {}", pos),
+            Self::ExpectedNumber(pos) => write!(f, "Expected number at position {}", pos),
+            Self::UnclosedBracket(pos) => write!(f, "Unclosed bracket at position {}", pos),
+            Self::InvalidCharacterOffset(pos) => {
+                write!(f, "Invalid character offset at position {}", pos)
+            }
+            Self::InvalidTemporalOffset(pos) => {
+                write!(f, "Invalid temporal offset at position {}", pos)
+            }
+            Self::InvalidSpatialOffset(pos) => {
+                write!(f, "Invalid spatial offset at position {}", pos)
+            }
+            Self::UnexpectedChar(ch, pos) => {
+                write!(f, "Unexpected character '{}' at position {}", ch, pos)
+            }
+            Self::InvalidRange => write!(f, "Invalid range format"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CfiParseError {}
+
 /// Parser state
 struct Parser<'a> {
     input: &'a str,
@@ -110,10 +121,6 @@ impl<'a> Parser<'a> {
         self.pos >= self.input.len()
     }
 
-    fn remaining(&self) -> &str {
-        &self.input[self.pos..]
-    }
-
     /// Parse a sequence of digits as u32
     fn parse_number(&mut self) -> Result<u32, CfiParseError> {
         let start = self.pos;
@@ -293,16 +300,24 @@ impl<'a> Parser<'a> {
         }
 
         if self.skip_if('~') {
-            // Temporal offset
+            // Temporal offset: a time into the media, so never negative
+            let start = self.pos;
             let seconds = self.parse_float()?;
+            if !seconds.is_finite() || seconds < 0.0 {
+                return Err(CfiParseError::InvalidTemporalOffset(start));
+            }
             path.temporal_offset = Some(TemporalOffset { seconds });
         }
 
         if self.skip_if('@') {
-            // Spatial offset
+            // Spatial offset: percentages of the image's width and height
+            let start = self.pos;
             let x = self.parse_float()?;
             self.expect(':')?;
             let y = self.parse_float()?;
+            if !(0.0..=100.0).contains(&x) || !(0.0..=100.0).contains(&y) {
+                return Err(CfiParseError::InvalidSpatialOffset(start));
+            }
             path.spatial_offset = Some(SpatialOffset { x, y });
         }
 
@@ -383,6 +398,7 @@ pub fn try_parse(input: &str) -> Option<Cfi> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::string::ToString;
 
     #[test]
     fn test_parse_simple_cfi() {
@@ -433,12 +449,35 @@ mod tests {
         assert_eq!(cfi.path.temporal_offset.as_ref().unwrap().seconds, 12.5);
     }
 
+    #[test]
+    fn test_parse_cfi_temporal_range() {
+        let original = "epubcfi(/6/4!/4/2,/6~10,/6~15.25)";
+        let cfi = parse(original).unwrap();
+        assert_eq!(cfi.temporal_offset(), Some(10.0));
+        assert_eq!(cfi.end().temporal_offset(), Some(15.25));
+        assert_eq!(cfi.to_string(), original);
+
+        assert!(matches!(
+            parse("epubcfi(/6/4!/4~-1)"),
+            Err(CfiParseError::InvalidTemporalOffset(16))
+        ));
+    }
+
     #[test]
     fn test_parse_cfi_spatial_offset() {
         let cfi = parse("epubcfi(/6/4!/4@50.5:25.0)").unwrap();
         let spatial = cfi.path.spatial_offset.as_ref().unwrap();
         assert_eq!(spatial.x, 50.5);
         assert_eq!(spatial.y, 25.0);
+
+        let region = parse("epubcfi(/6/4!/4,/2@10:20,/2@60:80)").unwrap();
+        assert_eq!(region.spatial_offset(), Some((10.0, 20.0)));
+        assert_eq!(region.end().spatial_offset(), Some((60.0, 80.0)));
+
+        assert!(matches!(
+            parse("epubcfi(/6/4!/4@50:120)"),
+            Err(CfiParseError::InvalidSpatialOffset(16))
+        ));
     }
 
     #[test]
@@ -5,11 +5,16 @@
 //!
 //! Reference: <https://idpf.org/epub/linking/cfi/epub-cfi.html>
 
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
-use std::fmt;
 
 /// A complete EPUB CFI
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Cfi {
     /// The path components of this CFI
     pub path: CfiPath,
@@ -18,7 +23,8 @@ pub struct Cfi {
 }
 
 /// A CFI path (sequence of steps)
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CfiPath {
     /// Steps in this path
     pub steps: Vec<CfiStep>,
@@ -31,7 +37,8 @@ pub struct CfiPath {
 }
 
 /// A CFI range (for text selections)
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CfiRange {
     /// Start of the range (relative path from common ancestor)
     pub start: CfiPath,
@@ -40,7 +47,8 @@ pub struct CfiRange {
 }
 
 /// A single step in a CFI path
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CfiStep {
     /// The step type (element index or indirection)
     pub step_type: StepType,
@@ -51,7 +59,8 @@ pub struct CfiStep {
 }
 
 /// Type of CFI step
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum StepType {
     /// Element step with index (e.g., /4 = 4th child element)
     Element(u32),
@@ -60,7 +69,8 @@ pub enum StepType {
 }
 
 /// Text location assertion for disambiguation
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct TextAssertion {
     /// Text before the location
     pub prefix: Option<String>,
@@ -71,7 +81,8 @@ pub struct TextAssertion {
 }
 
 /// Character offset within a text node
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CharacterOffset {
     /// The character index (0-based)
     pub offset: u32,
@@ -80,7 +91,8 @@ pub struct CharacterOffset {
 }
 
 /// Temporal offset for audio/video (in seconds)
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct TemporalOffset {
     /// Time in seconds
     pub seconds: f64,
@@ -89,7 +101,8 @@ pub struct TemporalOffset {
 impl Eq for TemporalOffset {}
 
 /// Spatial offset for images (percentage-based)
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct SpatialOffset {
     /// X coordinate as percentage (0.0-100.0)
     pub x: f64,
@@ -118,6 +131,53 @@ impl Cfi {
         self.range.is_some()
     }
 
+    /// Start of a range as a single-location CFI; a non-range CFI is its own start
+    pub fn start(&self) -> Cfi {
+        self.endpoint(self.range.as_ref().map(|range| &range.start))
+    }
+
+    /// End of a range as a single-location CFI; a non-range CFI is its own end
+    pub fn end(&self) -> Cfi {
+        self.endpoint(self.range.as_ref().map(|range| &range.end))
+    }
+
+    /// The common path extended with a range's local path
+    fn endpoint(&self, local: Option<&CfiPath>) -> Cfi {
+        let Some(local) = local else {
+            return Cfi::new(self.path.clone());
+        };
+        let mut path = self.path.clone();
+        path.steps.extend(local.steps.iter().cloned());
+        path.character_offset = local.character_offset.clone();
+        path.temporal_offset = local.temporal_offset.clone();
+        path.spatial_offset = local.spatial_offset.clone();
+        Cfi::new(path)
+    }
+
+    /// Time into the audio or video this CFI points at, in seconds
+    ///
+    /// For a range this is the time at its start.
+    pub fn temporal_offset(&self) -> Option<f64> {
+        let local = self.range.as_ref().map(|range| &range.start);
+        local
+            .unwrap_or(&self.path)
+            .temporal_offset
+            .as_ref()
+            .map(|temporal| temporal.seconds)
+    }
+
+    /// Point in the image this CFI points at, as `(x, y)` percentages
+    ///
+    /// For a range this is the point at its start.
+    pub fn spatial_offset(&self) -> Option<(f64, f64)> {
+        let local = self.range.as_ref().map(|range| &range.start);
+        local
+            .unwrap_or(&self.path)
+            .spatial_offset
+            .as_ref()
+            .map(|spatial| (spatial.x, spatial.y))
+    }
+
     /// Get the spine index if this CFI references a spine item
     /// The spine index is typically at position 2 in the path (after /6/N)
     pub fn spine_index(&self) -> Option<u32> {
@@ -292,6 +352,8 @@ impl fmt::Display for TextAssertion {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::string::ToString;
+    use alloc::vec;
 
     #[test]
     fn test_simple_cfi_display() {
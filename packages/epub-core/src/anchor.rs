@@ -0,0 +1,201 @@
+//! Deterministic position anchors for chapter XHTML
+//!
+//! CFIs address nodes by child position, so they break when a client's DOM
+//! differs from the source (injected highlight wrappers, reflowed chunks,
+//! sanitizers). As a fallback, every block-level text element in the body
+//! gets a `data-anchor` attribute derived from its element path and text.
+//! The id only changes when the element moves or its text changes, and the
+//! WASM processor and the server compute the same id for the same markup.
+
+use crate::markup::{body_range, Token, Tokens};
+
+/// Attribute carrying the anchor id
+pub const ANCHOR_ATTR: &str = "data-anchor";
+
+/// Elements that receive an anchor
+pub(crate) const ANCHORED_ELEMENTS: &[&str] = &[
+    "p",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "li",
+    "dt",
+    "dd",
+    "blockquote",
+    "pre",
+    "figcaption",
+    "td",
+    "th",
+];
+
+/// Anchor id for an element
+///
+/// `path` is the element's CFI-style step path below `<body>` (`/4/2`) and
+/// `text` its whitespace-normalized text content. The id is the 64-bit
+/// FNV-1a hash of both, so it is stable across platforms and builds.
+pub fn anchor_id(path: &str, text: &str) -> String {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    let mut hash = OFFSET;
+    for byte in path.bytes().chain([0x1f]).chain(text.bytes()) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(PRIME);
+    }
+    format!("a{:016x}", hash)
+}
+
+/// Add a [`ANCHOR_ATTR`] attribute to each block-level text element
+///
+/// Elements that already carry the attribute are left alone, so injecting
+/// twice is a no-op. Documents without a `<body>` are returned unchanged.
+pub fn inject_anchors(html: &str) -> String {
+    let Some((body_start, body_end)) = body_range(html) else {
+        return html.to_string();
+    };
+    let body = &html[body_start..body_end];
+
+    // An open element with its path, child element count, and index into
+    // `anchors` when the element is anchored
+    struct Frame {
+        name: String,
+        path: String,
+        children: usize,
+        anchor: Option<usize>,
+    }
+    // (byte offset to insert at, path, accumulated text)
+    let mut anchors: Vec<(usize, String, String)> = Vec::new();
+    let mut stack = vec![Frame {
+        name: String::new(),
+        path: String::new(),
+        children: 0,
+        anchor: None,
+    }];
+
+    for token in Tokens::new(body) {
+        match token {
+            Token::Start {
+                name,
+                start,
+                end,
+                self_closing,
+            } => {
+                let parent = stack.last_mut().expect("root frame is never popped");
+                parent.children += 1;
+                let path = format!("{}/{}", parent.path, parent.children * 2);
+
+                let tag = &body[start..end];
+                let anchor = (ANCHORED_ELEMENTS.contains(&name.as_str())
+                    && !tag.contains(ANCHOR_ATTR))
+                .then(|| {
+                    let insert_at = if self_closing && tag.ends_with("/>") {
+                        end - 2
+                    } else {
+                        end - 1
+                    };
+                    anchors.push((insert_at, path.clone(), String::new()));
+                    anchors.len() - 1
+                });
+
+                if !self_closing {
+                    stack.push(Frame {
+                        name,
+                        path,
+                        children: 0,
+                        anchor,
+                    });
+                }
+            }
+            Token::End { name, .. } => {
+                // Never pop the root frame
+                if let Some(depth) = stack.iter().skip(1).rposition(|f| f.name == name) {
+                    stack.truncate(depth + 1);
+                }
+            }
+            Token::Text { start, end } => {
+                let text = &body[start..end];
+                for index in stack.iter().filter_map(|f| f.anchor) {
+                    anchors[index].2.push_str(text);
+                }
+            }
+        }
+    }
+
+    let mut out = String::with_capacity(html.len() + anchors.len() * 32);
+    out.push_str(&html[..body_start]);
+    let mut copied = 0;
+    for (insert_at, path, text) in &anchors {
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        out.push_str(&body[copied..*insert_at]);
+        out.push_str(&format!(r#" {}="{}""#, ANCHOR_ATTR, anchor_id(path, &text)));
+        copied = *insert_at;
+    }
+    out.push_str(&body[copied..]);
+    out.push_str(&html[body_end..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(body: &str) -> String {
+        format!(
+            "<html><head><title>T</title></head><body>{}</body></html>",
+            body
+        )
+    }
+
+    fn anchors(html: &str) -> Vec<String> {
+        html.match_indices(r#"data-anchor=""#)
+            .map(|(i, m)| html[i + m.len()..i + m.len() + 17].to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_inject_anchors() {
+        let html = document(r#"<h1>Title</h1><div><p class="x">One <em>two</em></p><p/></div>"#);
+        let out = inject_anchors(&html);
+
+        assert_eq!(
+            out,
+            document(&format!(
+                r#"<h1 data-anchor="{}">Title</h1><div><p class="x" data-anchor="{}">One <em>two</em></p><p data-anchor="{}"/></div>"#,
+                anchor_id("/2", "Title"),
+                anchor_id("/4/2", "One two"),
+                anchor_id("/4/4", ""),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_anchors_are_deterministic_and_idempotent() {
+        let html = document("<p>Alpha</p><p>Beta</p>");
+        let once = inject_anchors(&html);
+        assert_eq!(once, inject_anchors(&html));
+        assert_eq!(once, inject_anchors(&once));
+        assert_eq!(anchors(&once).len(), 2);
+    }
+
+    #[test]
+    fn test_anchor_depends_on_text_and_position() {
+        let a = anchors(&inject_anchors(&document("<p>Alpha</p><p>Beta</p>")));
+        let b = anchors(&inject_anchors(&document("<p>Alpha</p><p>Gamma</p>")));
+        let c = anchors(&inject_anchors(&document("<hr/><p>Alpha</p>")));
+
+        assert_eq!(a[0], b[0]);
+        assert_ne!(a[1], b[1]);
+        assert_ne!(a[0], c[0]);
+    }
+
+    #[test]
+    fn test_head_and_scripts_are_untouched() {
+        let html = document("<script>var p = '<p>';</script><p>Text</p>");
+        let out = inject_anchors(&html);
+        assert!(out.starts_with("<html><head><title>T</title></head><body><script>var p = '<p>';</script><p data-anchor="));
+        assert_eq!(inject_anchors("<p>fragment</p>"), "<p>fragment</p>");
+    }
+}
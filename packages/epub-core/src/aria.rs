@@ -0,0 +1,247 @@
+//! ARIA roles and landmarks for chapter XHTML
+//!
+//! EPUB 3 marks up structure with `epub:type` (`chapter`, `footnote`,
+//! `noteref`, `pagebreak`, ...), which reading systems understand but
+//! browsers ignore. A client that inserts chapter markup into its own page
+//! loses that structure for screen readers. [`add_aria_roles`] maps each
+//! `epub:type` to its DPUB-ARIA role, following the EPUB Type to ARIA Role
+//! Authoring Guide, and names every sectioning element after its first
+//! heading so it becomes a navigable region.
+//!
+//! Existing `role`, `aria-label` and `aria-labelledby` attributes win over
+//! derived ones, and element nesting is unchanged, so CFIs and anchors still
+//! resolve.
+
+use crate::markup::{attribute, body_range, Token, Tokens};
+
+/// `epub:type` values with a DPUB-ARIA role, and whether the role is a
+/// landmark
+const EPUB_TYPE_ROLES: &[(&str, &str, bool)] = &[
+    ("abstract", "doc-abstract", false),
+    ("acknowledgments", "doc-acknowledgments", true),
+    ("afterword", "doc-afterword", true),
+    ("appendix", "doc-appendix", true),
+    ("backlink", "doc-backlink", false),
+    ("bibliography", "doc-bibliography", true),
+    ("biblioref", "doc-biblioref", false),
+    ("chapter", "doc-chapter", true),
+    ("colophon", "doc-colophon", false),
+    ("conclusion", "doc-conclusion", true),
+    ("cover", "doc-cover", false),
+    ("credit", "doc-credit", false),
+    ("credits", "doc-credits", true),
+    ("dedication", "doc-dedication", false),
+    ("endnotes", "doc-endnotes", true),
+    ("epigraph", "doc-epigraph", false),
+    ("epilogue", "doc-epilogue", true),
+    ("errata", "doc-errata", true),
+    ("example", "doc-example", false),
+    ("footnote", "doc-footnote", false),
+    ("foreword", "doc-foreword", true),
+    ("glossary", "doc-glossary", true),
+    ("glossref", "doc-glossref", false),
+    ("index", "doc-index", true),
+    ("introduction", "doc-introduction", true),
+    ("noteref", "doc-noteref", false),
+    ("notice", "doc-notice", false),
+    ("page-list", "doc-pagelist", true),
+    ("pagebreak", "doc-pagebreak", false),
+    ("part", "doc-part", true),
+    ("preface", "doc-preface", true),
+    ("prologue", "doc-prologue", true),
+    ("pullquote", "doc-pullquote", false),
+    ("qna", "doc-qna", false),
+    ("rearnotes", "doc-endnotes", true),
+    ("subtitle", "doc-subtitle", false),
+    ("tip", "doc-tip", false),
+    ("toc", "doc-toc", true),
+];
+
+/// Elements that are regions once they have a name
+const SECTIONING_ELEMENTS: &[&str] = &["section", "article", "aside", "nav"];
+
+const HEADINGS: &[&str] = &["h1", "h2", "h3", "h4", "h5", "h6"];
+
+/// Prefix of the ids given to headings that label a region but have none
+pub const HEADING_ID_PREFIX: &str = "amnesia-heading-";
+
+/// The DPUB-ARIA role for a space-separated `epub:type` value
+///
+/// The first value with a role wins; `None` when no value has one.
+pub fn aria_role(epub_type: &str) -> Option<&'static str> {
+    epub_type.split_whitespace().find_map(|value| {
+        // Prefixed values from other vocabularies have no role
+        EPUB_TYPE_ROLES
+            .iter()
+            .find(|(name, _, _)| *name == value)
+            .map(|&(_, role, _)| role)
+    })
+}
+
+/// Add ARIA roles and region labels to a chapter's body
+///
+/// Each element with an `epub:type` that maps to a DPUB-ARIA role and no
+/// `role` of its own gets one. Sectioning elements (`section`, `article`,
+/// `aside`, `nav`) and elements with a landmark role get an
+/// `aria-labelledby` pointing at their first heading, which is given an id
+/// ([`HEADING_ID_PREFIX`]) if it lacks one. Running it twice is a no-op.
+/// Documents without a `<body>` are returned unchanged.
+pub fn add_aria_roles(html: &str) -> String {
+    let Some((body_start, body_end)) = body_range(html) else {
+        return html.to_string();
+    };
+    let body = &html[body_start..body_end];
+
+    struct Frame {
+        name: String,
+        /// Index into `regions` for sectioning elements
+        region: Option<usize>,
+    }
+    // (insert position in the start tag, whether it still needs a name)
+    let mut regions: Vec<(usize, bool)> = Vec::new();
+    // (byte offset to insert at, attribute text)
+    let mut inserts: Vec<(usize, String)> = Vec::new();
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut generated_ids = 0;
+
+    for token in Tokens::new(body) {
+        match token {
+            Token::Start {
+                name,
+                start,
+                end,
+                self_closing,
+            } => {
+                let tag = &body[start..end];
+                let insert_at = if self_closing && tag.ends_with("/>") {
+                    end - 2
+                } else {
+                    end - 1
+                };
+
+                let existing = attribute(tag, "role");
+                let derived = existing
+                    .is_none()
+                    .then(|| attribute(tag, "epub:type").and_then(aria_role))
+                    .flatten();
+                if let Some(role) = derived {
+                    inserts.push((insert_at, format!(r#" role="{}""#, role)));
+                }
+
+                let landmark = existing.or(derived).is_some_and(|role| {
+                    EPUB_TYPE_ROLES
+                        .iter()
+                        .any(|&(_, r, landmark)| landmark && role.trim() == r)
+                });
+                let region = (!self_closing
+                    && (landmark || SECTIONING_ELEMENTS.contains(&name.as_str())))
+                .then(|| {
+                    let named = attribute(tag, "aria-label").is_some()
+                        || attribute(tag, "aria-labelledby").is_some();
+                    regions.push((insert_at, !named));
+                    regions.len() - 1
+                });
+
+                if HEADINGS.contains(&name.as_str()) {
+                    // Only the nearest region is named after the heading
+                    let nearest = stack.iter().rev().find_map(|f| f.region);
+                    if let Some(index) = nearest.filter(|&i| regions[i].1) {
+                        let id = match attribute(tag, "id") {
+                            Some(id) if is_idref(id) => Some(id.to_string()),
+                            Some(_) => None,
+                            None => {
+                                generated_ids += 1;
+                                let id = format!("{}{}", HEADING_ID_PREFIX, generated_ids);
+                                inserts.push((insert_at, format!(r#" id="{}""#, id)));
+                                Some(id)
+                            }
+                        };
+                        if let Some(id) = id {
+                            let (region_at, needs_name) = &mut regions[index];
+                            inserts.push((*region_at, format!(r#" aria-labelledby="{}""#, id)));
+                            *needs_name = false;
+                        }
+                    }
+                }
+
+                if !self_closing {
+                    stack.push(Frame { name, region });
+                }
+            }
+            Token::End { name, .. } => {
+                if let Some(depth) = stack.iter().rposition(|f| f.name == name) {
+                    stack.truncate(depth);
+                }
+            }
+            Token::Text { .. } => {}
+        }
+    }
+
+    // A region's label is only known after the tags inside it were seen; a
+    // stable sort keeps each tag's own attributes in the order derived
+    inserts.sort_by_key(|(at, _)| *at);
+
+    let extra: usize = inserts.iter().map(|(_, text)| text.len()).sum();
+    let mut out = String::with_capacity(html.len() + extra);
+    out.push_str(&html[..body_start]);
+    let mut copied = 0;
+    for (insert_at, text) in &inserts {
+        out.push_str(&body[copied..*insert_at]);
+        out.push_str(text);
+        copied = *insert_at;
+    }
+    out.push_str(&body[copied..]);
+    out.push_str(&html[body_end..]);
+    out
+}
+
+/// Whether an id can be referenced from `aria-labelledby` as written
+fn is_idref(id: &str) -> bool {
+    !id.is_empty() && !id.contains(|c: char| c.is_whitespace() || c == '"')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(body: &str) -> String {
+        format!(
+            "<html><head><title>T</title></head><body>{}</body></html>",
+            body
+        )
+    }
+
+    #[test]
+    fn test_add_aria_roles() {
+        let html = document(concat!(
+            r#"<section epub:type="bodymatter chapter"><h1 id="c1">One</h1>"#,
+            r##"<p>Text<a epub:type="noteref" href="#n1">1</a></p>"##,
+            r#"<span epub:type="pagebreak" title="12"/>"#,
+            r#"<section><div><h2>Part</h2></div><h3>Sub</h3></section>"#,
+            r#"<aside epub:type="footnote" id="n1"><p>Note</p></aside></section>"#,
+            r#"<nav epub:type="toc" role="navigation" aria-label="Contents"><h2>Contents</h2></nav>"#,
+        ));
+        let out = add_aria_roles(&html);
+
+        assert_eq!(
+            out,
+            document(concat!(
+                r#"<section epub:type="bodymatter chapter" role="doc-chapter" aria-labelledby="c1"><h1 id="c1">One</h1>"#,
+                r##"<p>Text<a epub:type="noteref" href="#n1" role="doc-noteref">1</a></p>"##,
+                r#"<span epub:type="pagebreak" title="12" role="doc-pagebreak"/>"#,
+                r#"<section aria-labelledby="amnesia-heading-1"><div><h2 id="amnesia-heading-1">Part</h2></div><h3>Sub</h3></section>"#,
+                r#"<aside epub:type="footnote" id="n1" role="doc-footnote"><p>Note</p></aside></section>"#,
+                r#"<nav epub:type="toc" role="navigation" aria-label="Contents"><h2>Contents</h2></nav>"#,
+            ))
+        );
+        assert_eq!(add_aria_roles(&out), out);
+    }
+
+    #[test]
+    fn test_aria_role() {
+        assert_eq!(aria_role("chapter"), Some("doc-chapter"));
+        assert_eq!(aria_role("bodymatter  toc"), Some("doc-toc"));
+        assert_eq!(aria_role("z3998:poem"), None);
+        assert_eq!(add_aria_roles("<p>fragment</p>"), "<p>fragment</p>");
+    }
+}
@@ -0,0 +1,367 @@
+//! Block maps for paginating and virtualizing chapters
+//!
+//! A client that renders a huge chapter a screenful at a time needs to know
+//! how tall the parts it hasn't rendered yet will be. The block map lists a
+//! chapter's top-level blocks (paragraphs, headings, list items, tables,
+//! figures, standalone images) in document order, with their text length
+//! and image sizes, so the client can estimate layout without parsing the
+//! HTML itself.
+//!
+//! Blocks nest no deeper than the outermost block element: a table is one
+//! block, and the paragraphs of a blockquote belong to it. Text outside any
+//! block element (bare text in a `<div>`) is not counted.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::anchor::{anchor_id, ANCHORED_ELEMENTS, ANCHOR_ATTR};
+use crate::image::{css_pixels, svg_size};
+use crate::markup::{attribute, body_range, Token, Tokens};
+
+/// Elements that form a block besides the anchored text elements
+const OTHER_BLOCK_ELEMENTS: &[&str] = &["figure", "table", "hr", "img", "svg", "video"];
+
+/// Average glyph width as a fraction of the font size
+const AVERAGE_CHAR_WIDTH: f64 = 0.5;
+
+/// A top-level block of a chapter
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct Block {
+    /// Position among the chapter's blocks
+    pub index: usize,
+    /// Element name, lowercase
+    pub tag: String,
+    /// Element path below `<body>` (`/4/2`), as used by anchors and CFIs
+    pub path: String,
+    /// The element's `data-anchor` id, for anchored text elements
+    pub anchor: Option<String>,
+    /// Characters of whitespace-normalized text; entities count as written
+    pub text_length: usize,
+    pub images: Vec<BlockImage>,
+    /// Height in CSS pixels for the metrics passed to [`estimate_heights`]
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub estimated_height: Option<f64>,
+}
+
+/// An image in a block
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct BlockImage {
+    /// Source as written in the chapter; empty for an inline `<svg>`
+    /// drawing
+    pub src: String,
+    /// Size in pixels, from the element's attributes or, once filled in by
+    /// the caller, the image file
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+/// Page metrics for [`estimate_heights`], in CSS pixels
+///
+/// Fields left out when deserializing take their defaults.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase", default))]
+pub struct BlockMetrics {
+    /// Width of the text column
+    pub width: f64,
+    pub font_size: f64,
+    /// Line height as a multiple of the font size
+    pub line_height: f64,
+}
+
+impl Default for BlockMetrics {
+    fn default() -> Self {
+        Self {
+            width: 600.0,
+            font_size: 16.0,
+            line_height: 1.5,
+        }
+    }
+}
+
+/// The top-level blocks of a chapter's body, in document order
+///
+/// Documents without a `<body>` have no blocks. Image sizes come from
+/// `width` and `height` attributes given in pixels; callers with access to
+/// the image files can fill in the rest (see [`crate::image::image_size`]).
+pub fn block_map(html: &str) -> Vec<Block> {
+    let Some((body_start, body_end)) = body_range(html) else {
+        return Vec::new();
+    };
+    let body = &html[body_start..body_end];
+
+    struct Frame {
+        name: String,
+        path: String,
+        children: usize,
+    }
+    let mut stack = vec![Frame {
+        name: String::new(),
+        path: String::new(),
+        children: 0,
+    }];
+    let mut blocks: Vec<Block> = Vec::new();
+    // Depth of the open block's frame and its raw text
+    let mut open: Option<(usize, String)> = None;
+
+    for token in Tokens::new(body) {
+        match token {
+            Token::Start {
+                name,
+                start,
+                end,
+                self_closing,
+            } => {
+                let parent = stack.last_mut().expect("root frame is never popped");
+                parent.children += 1;
+                let path = format!("{}/{}", parent.path, parent.children * 2);
+                let tag = &body[start..end];
+
+                if open.is_none() && is_block(&name) {
+                    blocks.push(Block {
+                        index: blocks.len(),
+                        tag: name.clone(),
+                        path: path.clone(),
+                        anchor: attribute(tag, ANCHOR_ATTR).map(str::to_string),
+                        text_length: 0,
+                        images: Vec::new(),
+                        estimated_height: None,
+                    });
+                    if !self_closing {
+                        open = Some((stack.len(), String::new()));
+                    }
+                }
+                let in_block = open.is_some() || is_block(&name);
+                if let (Some(image), Some(block)) = (
+                    block_image(&name, tag),
+                    blocks.last_mut().filter(|_| in_block),
+                ) {
+                    match block.images.last_mut() {
+                        // An `<image>` is the picture of the `<svg>` drawing it
+                        Some(svg) if name == "image" && svg.src.is_empty() => {
+                            svg.src = image.src;
+                            svg.width = svg.width.or(image.width);
+                            svg.height = svg.height.or(image.height);
+                        }
+                        _ => block.images.push(image),
+                    }
+                }
+
+                if !self_closing {
+                    stack.push(Frame {
+                        name,
+                        path,
+                        children: 0,
+                    });
+                }
+            }
+            Token::End { name, .. } => {
+                // Never pop the root frame
+                if let Some(depth) = stack.iter().skip(1).rposition(|f| f.name == name) {
+                    stack.truncate(depth + 1);
+                }
+                if open
+                    .as_ref()
+                    .is_some_and(|(depth, _)| stack.len() <= *depth)
+                {
+                    let (_, text) = open.take().expect("checked above");
+                    close_block(blocks.last_mut().expect("open block exists"), &text);
+                }
+            }
+            Token::Text { start, end } => {
+                if let Some((_, text)) = &mut open {
+                    text.push_str(&body[start..end]);
+                }
+            }
+        }
+    }
+    // A block left open by truncated markup
+    if let Some((_, text)) = open {
+        close_block(blocks.last_mut().expect("open block exists"), &text);
+    }
+    blocks
+}
+
+/// Fill in each block's [`Block::estimated_height`]
+///
+/// A rough guide for reserving space: text wraps at an average glyph width,
+/// headings are scaled like browser defaults, images are scaled down to the
+/// column width (images of unknown size are taken as 4:3), and every block
+/// gets a 1em margin.
+pub fn estimate_heights(blocks: &mut [Block], metrics: &BlockMetrics) {
+    for block in blocks {
+        let font_size = metrics.font_size * heading_scale(&block.tag);
+        let line = font_size * metrics.line_height;
+        let chars_per_line = (metrics.width / (font_size * AVERAGE_CHAR_WIDTH)).max(1.0);
+        let lines = (block.text_length as f64 / chars_per_line).ceil();
+
+        let images: f64 = block
+            .images
+            .iter()
+            .map(|image| match (image.width, image.height) {
+                (Some(width), Some(height)) => {
+                    let scale = (metrics.width / f64::from(width)).min(1.0);
+                    f64::from(height) * scale
+                }
+                _ => metrics.width * 0.75,
+            })
+            .sum();
+
+        block.estimated_height = Some(lines * line + images + metrics.font_size);
+    }
+}
+
+fn is_block(name: &str) -> bool {
+    ANCHORED_ELEMENTS.contains(&name) || OTHER_BLOCK_ELEMENTS.contains(&name)
+}
+
+/// Record a finished block's text and, for anchored elements, the anchor id
+/// `inject_anchors` would give it
+fn close_block(block: &mut Block, text: &str) {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    block.text_length = text.chars().count();
+    if block.anchor.is_none() && ANCHORED_ELEMENTS.contains(&block.tag.as_str()) {
+        block.anchor = Some(anchor_id(&block.path, &text));
+    }
+}
+
+/// The image an `<img>`, SVG `<image>` or `<svg>` start tag shows
+fn block_image(name: &str, tag: &str) -> Option<BlockImage> {
+    let src = match name {
+        "img" => attribute(tag, "src")?,
+        "image" => attribute(tag, "xlink:href").or_else(|| attribute(tag, "href"))?,
+        // An inline drawing, sized by its viewBox when not given in pixels
+        "svg" => {
+            let size = svg_size(tag.as_bytes());
+            return Some(BlockImage {
+                src: String::new(),
+                width: size.map(|(width, _)| width),
+                height: size.map(|(_, height)| height),
+            });
+        }
+        _ => return None,
+    };
+    Some(BlockImage {
+        src: src.to_string(),
+        width: attribute(tag, "width").and_then(css_pixels),
+        height: attribute(tag, "height").and_then(css_pixels),
+    })
+}
+
+/// Default font size of a heading relative to body text
+fn heading_scale(tag: &str) -> f64 {
+    match tag {
+        "h1" => 2.0,
+        "h2" => 1.5,
+        "h3" => 1.17,
+        "h5" => 0.83,
+        "h6" => 0.67,
+        _ => 1.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inject_anchors;
+
+    fn document(body: &str) -> String {
+        format!(
+            "<html><head><title>T</title></head><body>{}</body></html>",
+            body
+        )
+    }
+
+    #[test]
+    fn test_block_map() {
+        let html = document(concat!(
+            "<h1>Title</h1>",
+            "<div><p>One <em>two</em>  three</p>",
+            "<blockquote><p>Quoted</p><p>text</p></blockquote></div>",
+            r#"<img src="../images/map.png" width="800" height="600px"/>"#,
+            r#"<figure><img src="a.jpg"/><figcaption>Caption</figcaption></figure>"#,
+            "<table><tr><td>1</td><td>2</td></tr></table>",
+            r#"<svg viewBox="0 0 300 200"><image xlink:href="plate.jpg"/></svg>"#,
+        ));
+        let blocks = block_map(&html);
+
+        let summary: Vec<(&str, &str, usize)> = blocks
+            .iter()
+            .map(|b| (b.tag.as_str(), b.path.as_str(), b.text_length))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("h1", "/2", 5),
+                ("p", "/4/2", 13),
+                ("blockquote", "/4/4", 10),
+                ("img", "/6", 0),
+                ("figure", "/8", 7),
+                ("table", "/10", 2),
+                ("svg", "/12", 0),
+            ]
+        );
+        assert_eq!(blocks[5].index, 5);
+        assert_eq!(
+            blocks[6].images,
+            vec![BlockImage {
+                src: "plate.jpg".to_string(),
+                width: Some(300),
+                height: Some(200),
+            }]
+        );
+
+        assert_eq!(
+            blocks[3].images,
+            vec![BlockImage {
+                src: "../images/map.png".to_string(),
+                width: Some(800),
+                height: Some(600),
+            }]
+        );
+        assert_eq!(blocks[4].images[0].src, "a.jpg");
+        assert_eq!(blocks[4].images[0].width, None);
+
+        // Anchors match the injected ones
+        assert_eq!(
+            blocks[1].anchor.as_deref(),
+            Some(anchor_id("/4/2", "One two three").as_str())
+        );
+        assert_eq!(blocks[3].anchor, None);
+        let injected = block_map(&inject_anchors(&html));
+        assert_eq!(injected[0].anchor, blocks[0].anchor);
+        assert_eq!(injected[1].anchor, blocks[1].anchor);
+
+        assert!(block_map("<p>fragment</p>").is_empty());
+    }
+
+    #[test]
+    fn test_estimate_heights() {
+        let html = document(concat!(
+            "<h1>Title</h1>",
+            "<p>Forty characters of text, more or less..</p>",
+            r#"<img src="wide.png" width="1200" height="600"/>"#,
+            r#"<img src="unknown.png"/>"#,
+        ));
+        let mut blocks = block_map(&html);
+        let metrics = BlockMetrics {
+            width: 160.0,
+            font_size: 10.0,
+            line_height: 1.5,
+        };
+        estimate_heights(&mut blocks, &metrics);
+
+        let heights: Vec<f64> = blocks.iter().map(|b| b.estimated_height.unwrap()).collect();
+        // One 30px heading line; 32 characters per line so two 15px lines;
+        // the wide image scaled to 80px; 4:3 of the column width. Each
+        // block adds a 10px margin.
+        assert_eq!(heights, vec![40.0, 40.0, 90.0, 130.0]);
+    }
+}
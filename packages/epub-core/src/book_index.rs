@@ -0,0 +1,456 @@
+//! Back-of-book indexes
+//!
+//! EPUB Indexes 1.0 marks up an index with `epub:type="index"` on its
+//! `<body>` or `<section>`, and each entry as a list item: the term, then
+//! `index-locator` links to where it is discussed, `index-xref-preferred`
+//! ("see") and `index-xref-related` ("see also") links to other entries,
+//! and a nested list of subentries. Older books mark only the section, so
+//! within it every `<li>` is read as an entry and every link to a spine
+//! item as a locator.
+//!
+//! Entries whose links lead nowhere in the spine keep their term but no
+//! locators; `index-group` items (the "A", "B", ... headings) are dropped
+//! and their entries lifted to the level above.
+
+use std::collections::HashMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::markup::{attribute, collapse_whitespace, decode_entities, Token, Tokens};
+use crate::path::{parent_dir, resolve_href};
+use crate::rewrite::is_relative;
+use crate::types::SpineItem;
+
+/// Deepest subentry nesting kept
+const MAX_DEPTH: usize = 8;
+
+/// Where an index entry points
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct IndexLocator {
+    pub spine_index: usize,
+    /// Href of the spine item, relative to the OPF directory
+    pub href: String,
+    /// Element id the locator points at, if any
+    pub fragment: Option<String>,
+    /// Text of the locator, usually a page number
+    pub label: String,
+}
+
+/// A term of the index
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct IndexEntry {
+    pub term: String,
+    pub locators: Vec<IndexLocator>,
+    /// Terms to look up instead ("see")
+    pub see: Vec<String>,
+    /// Terms to look up as well ("see also")
+    pub see_also: Vec<String>,
+    pub subentries: Vec<IndexEntry>,
+}
+
+/// An entry found by [`BookIndex::lookup`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct IndexMatch {
+    /// Terms from the top-level entry down to the match
+    pub terms: Vec<String>,
+    pub entry: IndexEntry,
+}
+
+/// The index of a whole book
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct BookIndex {
+    /// Spine indexes of the items holding the index
+    pub documents: Vec<usize>,
+    /// Top-level entries, in document order
+    pub entries: Vec<IndexEntry>,
+}
+
+impl BookIndex {
+    /// Read the index out of every spine item that has one
+    ///
+    /// `read` returns the markup at an archive path; items it cannot read
+    /// are skipped.
+    pub fn build(
+        spine: &[SpineItem],
+        opf_dir: &str,
+        mut read: impl FnMut(&str) -> Option<String>,
+    ) -> Self {
+        let paths: Vec<String> = spine
+            .iter()
+            .map(|item| resolve_href(opf_dir, &item.href))
+            .collect();
+        let index_of: HashMap<&str, usize> = paths
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(index, path)| (path.as_str(), index))
+            .collect();
+        let locate = |path: &str| {
+            let spine_index = *index_of.get(path)?;
+            Some((spine_index, spine[spine_index].href.as_str()))
+        };
+
+        let mut index = Self::default();
+        for (spine_index, path) in paths.iter().enumerate() {
+            let Some(html) = read(path) else {
+                continue;
+            };
+            if !html.contains("index") {
+                continue;
+            }
+            let entries = parse_index(&html, parent_dir(path), &locate);
+            if !entries.is_empty() {
+                index.documents.push(spine_index);
+                index.entries.extend(entries);
+            }
+        }
+        index
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Entries, at any depth, whose term is `query` or starts with it
+    ///
+    /// Matching ignores case and runs of whitespace. Exact matches come
+    /// first, then prefix matches, each in index order; at most `limit` are
+    /// returned.
+    pub fn lookup(&self, query: &str, limit: usize) -> Vec<IndexMatch> {
+        let query = normalize_term(query);
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let mut exact = Vec::new();
+        let mut prefix = Vec::new();
+        let mut terms = Vec::new();
+        collect_matches(&self.entries, &query, &mut terms, &mut exact, &mut prefix);
+        exact.extend(prefix);
+        exact.truncate(limit);
+        exact
+    }
+}
+
+fn collect_matches(
+    entries: &[IndexEntry],
+    query: &str,
+    terms: &mut Vec<String>,
+    exact: &mut Vec<IndexMatch>,
+    prefix: &mut Vec<IndexMatch>,
+) {
+    for entry in entries {
+        terms.push(entry.term.clone());
+        let term = normalize_term(&entry.term);
+        if term == query || term.starts_with(query) {
+            let found = IndexMatch {
+                terms: terms.clone(),
+                entry: entry.clone(),
+            };
+            if term == query {
+                exact.push(found);
+            } else {
+                prefix.push(found);
+            }
+        }
+        collect_matches(&entry.subentries, query, terms, exact, prefix);
+        terms.pop();
+    }
+}
+
+/// What a link in an entry is
+enum LinkKind {
+    Locator(usize, String, Option<String>),
+    See,
+    SeeAlso,
+}
+
+/// An entry being read: its term text so far and what it holds
+#[derive(Default)]
+struct OpenEntry {
+    entry: IndexEntry,
+    term: String,
+    /// Set once a link or subentry list ends the term
+    term_done: bool,
+}
+
+/// Entries of the `epub:type="index"` regions of one document
+///
+/// `locate` maps an archive path to its spine index and href.
+fn parse_index<'a>(
+    html: &str,
+    base_dir: &str,
+    locate: &impl Fn(&str) -> Option<(usize, &'a str)>,
+) -> Vec<IndexEntry> {
+    let mut entries = Vec::new();
+    // Open elements from the outermost index region in
+    let mut elements: Vec<String> = Vec::new();
+    // The `<li>`s open within the region; `None` for index groups
+    let mut open: Vec<Option<OpenEntry>> = Vec::new();
+    // The open link and its text
+    let mut link: Option<(LinkKind, String)> = None;
+
+    for token in Tokens::new(html) {
+        match token {
+            Token::Start {
+                name,
+                start,
+                end,
+                self_closing,
+            } => {
+                let tag = &html[start..end];
+                let types = attribute(tag, "epub:type").unwrap_or_default();
+                let has_type = |wanted: &str| types.split_whitespace().any(|t| t == wanted);
+                if elements.is_empty() {
+                    if has_type("index") && !self_closing {
+                        elements.push(name);
+                    }
+                    continue;
+                }
+                if !self_closing {
+                    elements.push(name.clone());
+                }
+
+                match name.as_str() {
+                    "li" if !self_closing => {
+                        let group = has_type("index-group") || open.len() >= MAX_DEPTH;
+                        open.push((!group).then(OpenEntry::default));
+                    }
+                    "ol" | "ul" => {
+                        if let Some(Some(entry)) = open.last_mut() {
+                            entry.term_done = true;
+                        }
+                    }
+                    "a" => {
+                        let Some(Some(entry)) = open.last_mut() else {
+                            continue;
+                        };
+                        // Any link ends the term, even one not followed
+                        entry.term_done = true;
+                        let kind = if has_type("index-xref-preferred") {
+                            LinkKind::See
+                        } else if has_type("index-xref-related") {
+                            LinkKind::SeeAlso
+                        } else {
+                            let Some(href) = attribute(tag, "href").map(str::trim) else {
+                                continue;
+                            };
+                            if !is_relative(href) || href.starts_with('#') {
+                                continue;
+                            }
+                            let Some((spine_index, spine_href)) =
+                                locate(&resolve_href(base_dir, href))
+                            else {
+                                continue;
+                            };
+                            let fragment = href
+                                .split_once('#')
+                                .map(|(_, fragment)| fragment.to_string())
+                                .filter(|fragment| !fragment.is_empty());
+                            LinkKind::Locator(spine_index, spine_href.to_string(), fragment)
+                        };
+                        if !self_closing {
+                            link = Some((kind, String::new()));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Token::End { name, .. } => {
+                // Close up to the matching element, tolerating missing end
+                // tags inside the region
+                let Some(depth) = elements.iter().rposition(|open| *open == name) else {
+                    continue;
+                };
+                for closed in elements.drain(depth..).rev() {
+                    match closed.as_str() {
+                        "a" => {
+                            if let (Some((kind, text)), Some(Some(entry))) =
+                                (link.take(), open.last_mut())
+                            {
+                                add_link(&mut entry.entry, kind, &text);
+                            }
+                        }
+                        "li" => {
+                            if let Some(closed) = open.pop() {
+                                close_entry(closed, &mut open, &mut entries);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Token::Text { start, end } => {
+                let text = &html[start..end];
+                if let Some((_, link_text)) = link.as_mut() {
+                    link_text.push_str(text);
+                } else if let Some(Some(entry)) = open.last_mut() {
+                    if !entry.term_done {
+                        entry.term.push_str(text);
+                    }
+                }
+            }
+        }
+    }
+    while let Some(closed) = open.pop() {
+        close_entry(closed, &mut open, &mut entries);
+    }
+    entries
+}
+
+fn add_link(entry: &mut IndexEntry, kind: LinkKind, text: &str) {
+    let text = collapse_whitespace(&decode_entities(text));
+    match kind {
+        LinkKind::Locator(spine_index, href, fragment) => entry.locators.push(IndexLocator {
+            spine_index,
+            href,
+            fragment,
+            label: text,
+        }),
+        LinkKind::See if !text.is_empty() => entry.see.push(text),
+        LinkKind::SeeAlso if !text.is_empty() => entry.see_also.push(text),
+        _ => {}
+    }
+}
+
+/// Finish a closed `<li>` and add it to its parent entry, or lift a
+/// group's entries to the level above
+fn close_entry(
+    closed: Option<OpenEntry>,
+    open: &mut [Option<OpenEntry>],
+    entries: &mut Vec<IndexEntry>,
+) {
+    let Some(OpenEntry {
+        mut entry, term, ..
+    }) = closed
+    else {
+        return;
+    };
+    let mut term = collapse_whitespace(&decode_entities(&term));
+    if !entry.see.is_empty() || !entry.see_also.is_empty() {
+        term = strip_see(&term).to_string();
+    }
+    entry.term = trim_term(&term).to_string();
+    if entry.term.is_empty() && entry.locators.is_empty() && entry.subentries.is_empty() {
+        return;
+    }
+    match open.iter_mut().rev().find_map(Option::as_mut) {
+        Some(parent) => parent.entry.subentries.push(entry),
+        None => entries.push(entry),
+    }
+}
+
+/// A term without the separators before its locators
+fn trim_term(term: &str) -> &str {
+    term.trim_end_matches([',', ';', ':', '.', ' '])
+}
+
+/// A term without the "See" or "See also" introducing its cross-references
+fn strip_see(term: &str) -> &str {
+    for see in ["see also", "see"] {
+        let Some(at) = term.len().checked_sub(see.len()) else {
+            continue;
+        };
+        if term.is_char_boundary(at) && term[at..].eq_ignore_ascii_case(see) {
+            return trim_term(&term[..at]);
+        }
+    }
+    term
+}
+
+fn normalize_term(term: &str) -> String {
+    collapse_whitespace(term).to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::spine_item;
+
+    const INDEX: &str = r##"<html><body><h1>Contents</h1><ul><li>Not an entry</li></ul>
+<section epub:type="index">
+  <h1>Index</h1>
+  <section epub:type="index-group"><h2>C</h2>
+  <ul epub:type="index-entry-list">
+    <li epub:type="index-entry"><span epub:type="index-term">Cells</span>,
+      <a epub:type="index-locator" href="ch1.xhtml#p12">12</a>,
+      <a epub:type="index-locator" href="ch2.xhtml">40</a>
+      <ul epub:type="index-entry-list">
+        <li epub:type="index-entry">division of, <a href="ch2.xhtml#p41">41</a></li>
+        <li epub:type="index-entry">walls. <i>See also</i>
+          <a epub:type="index-xref-related" href="#plants">Plants</a></li>
+      </ul>
+    </li>
+    <li epub:type="index-entry">Cell theory &amp; history,
+      <a href="../elsewhere.xhtml">9</a></li>
+  </ul></section>
+  <ul><li epub:type="index-group">P<ul>
+    <li id="plants">Plants, <a href="ch1.xhtml#p3">3</a></li>
+    <li>Protoplasm. <i>See</i> <a epub:type="index-xref-preferred" href="#cells">Cells</a></li>
+  </ul></li></ul>
+</section></body></html>"##;
+
+    fn sample_index() -> BookIndex {
+        let spine = [
+            spine_item("Text/ch1.xhtml"),
+            spine_item("Text/ch2.xhtml"),
+            spine_item("Text/index.xhtml"),
+        ];
+        BookIndex::build(&spine, "OEBPS", |path| match path {
+            "OEBPS/Text/index.xhtml" => Some(INDEX.to_string()),
+            "OEBPS/Text/ch1.xhtml" => Some("<p>Cells, see the index</p>".to_string()),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn test_parse_index() {
+        let index = sample_index();
+        assert_eq!(index.documents, vec![2]);
+        let terms: Vec<&str> = index.entries.iter().map(|e| e.term.as_str()).collect();
+        assert_eq!(
+            terms,
+            vec!["Cells", "Cell theory & history", "Plants", "Protoplasm"]
+        );
+
+        let cells = &index.entries[0];
+        assert_eq!(cells.locators.len(), 2);
+        assert_eq!(cells.locators[0].href, "Text/ch1.xhtml");
+        assert_eq!(cells.locators[0].fragment.as_deref(), Some("p12"));
+        assert_eq!(cells.locators[1].spine_index, 1);
+        assert_eq!(cells.locators[1].label, "40");
+        assert_eq!(cells.subentries[0].term, "division of");
+        assert_eq!(cells.subentries[0].locators[0].label, "41");
+        assert_eq!(cells.subentries[1].see_also, vec!["Plants"]);
+
+        // Locators outside the spine are dropped
+        assert!(index.entries[1].locators.is_empty());
+        assert_eq!(index.entries[3].see, vec!["Cells"]);
+    }
+
+    #[test]
+    fn test_lookup() {
+        let index = sample_index();
+        let found = index.lookup("  CELL", 10);
+        let terms: Vec<&[String]> = found.iter().map(|m| m.terms.as_slice()).collect();
+        assert_eq!(terms, vec![["Cells"], ["Cell theory & history"]]);
+        assert_eq!(found[0].entry.subentries.len(), 2);
+        assert_eq!(found[0].entry.subentries[1].term, "walls");
+
+        let found = index.lookup("division of", 10);
+        assert_eq!(found[0].terms, vec!["Cells", "division of"]);
+        assert_eq!(found[0].entry.locators[0].spine_index, 1);
+
+        assert_eq!(index.lookup("cel", 1).len(), 1);
+        assert!(index.lookup(" ", 10).is_empty());
+        assert!(BookIndex::default().is_empty());
+    }
+}
@@ -0,0 +1,163 @@
+//! Per-chapter content checksums
+//!
+//! When a book file is replaced by a new edition or a corrected release,
+//! most chapters are usually byte-for-byte the same. Recording a checksum
+//! of every spine item at parse time lets clients and sync compare the old
+//! and new lists and reindex or reanchor only the chapters that changed,
+//! instead of the whole book.
+//!
+//! Checksums are taken over the raw bytes of the spine item as stored in
+//! the archive, so the WASM processor and the server agree on them.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The checksum of one spine item
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct ChapterChecksum {
+    pub spine_index: usize,
+    pub href: String,
+    /// Hex SHA-1 of the item's bytes
+    pub checksum: String,
+}
+
+/// How the chapters of a book changed between two checksum lists
+///
+/// Each list holds spine hrefs in the order of the list they come from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct ChecksumDiff {
+    /// In both, with different content
+    pub changed: Vec<String>,
+    /// Only in the new list
+    pub added: Vec<String>,
+    /// Only in the old list
+    pub removed: Vec<String>,
+    /// Same content at a different spine position, which changes the
+    /// chapter's CFIs
+    pub moved: Vec<String>,
+}
+
+impl ChecksumDiff {
+    /// Whether no chapter needs reindexing or reanchoring
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty()
+            && self.added.is_empty()
+            && self.removed.is_empty()
+            && self.moved.is_empty()
+    }
+}
+
+/// Hex SHA-1 of a spine item's bytes
+pub fn content_checksum(bytes: &[u8]) -> String {
+    sha1_smol::Sha1::from(bytes).digest().to_string()
+}
+
+/// Hex SHA-1 identifying a book's content: its spine items' hrefs and
+/// checksums, in spine order
+///
+/// Two editions with the same title but different text get different
+/// fingerprints, and the same file always gets the same one.
+pub fn book_fingerprint(checksums: &[ChapterChecksum]) -> String {
+    let mut hasher = sha1_smol::Sha1::new();
+    for item in checksums {
+        hasher.update(item.href.as_bytes());
+        hasher.update(b"\0");
+        hasher.update(item.checksum.as_bytes());
+        hasher.update(b"\n");
+    }
+    hasher.digest().to_string()
+}
+
+/// Compare the checksums of an old and a new version of a book by href
+pub fn diff_checksums(old: &[ChapterChecksum], new: &[ChapterChecksum]) -> ChecksumDiff {
+    fn find<'a>(list: &'a [ChapterChecksum], href: &str) -> Option<&'a ChapterChecksum> {
+        list.iter().find(|item| item.href == href)
+    }
+
+    let mut diff = ChecksumDiff::default();
+    for item in new {
+        match find(old, &item.href) {
+            None => diff.added.push(item.href.clone()),
+            Some(previous) if previous.checksum != item.checksum => {
+                diff.changed.push(item.href.clone())
+            }
+            Some(previous) if previous.spine_index != item.spine_index => {
+                diff.moved.push(item.href.clone())
+            }
+            Some(_) => {}
+        }
+    }
+    diff.removed = old
+        .iter()
+        .filter(|item| find(new, &item.href).is_none())
+        .map(|item| item.href.clone())
+        .collect();
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checksums(items: &[(&str, &str)]) -> Vec<ChapterChecksum> {
+        items
+            .iter()
+            .enumerate()
+            .map(|(spine_index, (href, html))| ChapterChecksum {
+                spine_index,
+                href: href.to_string(),
+                checksum: content_checksum(html.as_bytes()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_content_checksum() {
+        assert_eq!(
+            content_checksum(b"abc"),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+    }
+
+    #[test]
+    fn test_book_fingerprint() {
+        let book = checksums(&[("ch1.xhtml", "<p>One</p>"), ("ch2.xhtml", "<p>Two</p>")]);
+        let edition = checksums(&[("ch1.xhtml", "<p>One</p>"), ("ch2.xhtml", "<p>2</p>")]);
+        let reordered = checksums(&[("ch2.xhtml", "<p>Two</p>"), ("ch1.xhtml", "<p>One</p>")]);
+
+        assert_ne!(book_fingerprint(&book), book_fingerprint(&edition));
+        assert_ne!(book_fingerprint(&book), book_fingerprint(&reordered));
+        assert_eq!(book_fingerprint(&book).len(), 40);
+    }
+
+    #[test]
+    fn test_diff_checksums() {
+        let old = checksums(&[
+            ("cover.xhtml", "<img/>"),
+            ("ch1.xhtml", "<p>One</p>"),
+            ("ch2.xhtml", "<p>Two</p>"),
+            ("ch3.xhtml", "<p>Three</p>"),
+        ]);
+        assert!(diff_checksums(&old, &old).is_empty());
+
+        let new = checksums(&[
+            ("ch1.xhtml", "<p>One</p>"),
+            ("ch2.xhtml", "<p>Two, fixed</p>"),
+            ("ch3.xhtml", "<p>Three</p>"),
+            ("ch4.xhtml", "<p>Four</p>"),
+        ]);
+        assert_eq!(
+            diff_checksums(&old, &new),
+            ChecksumDiff {
+                changed: vec!["ch2.xhtml".to_string()],
+                added: vec!["ch4.xhtml".to_string()],
+                removed: vec!["cover.xhtml".to_string()],
+                moved: vec!["ch1.xhtml".to_string(), "ch3.xhtml".to_string()],
+            }
+        );
+    }
+}
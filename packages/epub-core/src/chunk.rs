@@ -0,0 +1,296 @@
+//! Virtual chunking of oversize spine items
+//!
+//! Some EPUBs put the whole book into one XHTML file, which defeats
+//! chapter-based streaming and makes search excerpts point at "chapter 1" for
+//! every hit. Spine items larger than [`ChunkOptions::max_item_bytes`] are
+//! split on block boundaries into synthetic sub-items. Each chunk is a
+//! complete document: it keeps the original `<head>` (so stylesheets still
+//! apply) and re-opens any wrapper elements (`<div>`, `<section>`, ...) the
+//! split point was nested in.
+//!
+//! Chunk hrefs are the spine href plus a `#amnesia-chunk-N` fragment, so
+//! relative resource paths still resolve against the original file and the
+//! same input always yields the same ids.
+
+use crate::markup::{body_range, Token, Tokens};
+
+/// Fragment marker that identifies a synthetic chunk href
+pub const CHUNK_MARKER: &str = "#amnesia-chunk-";
+
+/// Thresholds for splitting spine items
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkOptions {
+    /// Spine items larger than this are split
+    pub max_item_bytes: usize,
+    /// Approximate size of each chunk; splits happen at the first block
+    /// boundary past this size
+    pub target_chunk_bytes: usize,
+}
+
+impl Default for ChunkOptions {
+    fn default() -> Self {
+        Self {
+            max_item_bytes: 512 * 1024,
+            target_chunk_bytes: 128 * 1024,
+        }
+    }
+}
+
+/// One synthetic sub-item of a spine item
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HtmlChunk {
+    /// Position within the spine item (0-based)
+    pub index: usize,
+    /// Synthetic href (see [`chunk_href`])
+    pub href: String,
+    /// Standalone XHTML document for the chunk
+    pub html: String,
+}
+
+/// Synthetic href for chunk `index` of `href`
+pub fn chunk_href(href: &str, index: usize) -> String {
+    format!("{}{}{}", href, CHUNK_MARKER, index)
+}
+
+/// Split a chunk href into the spine href and chunk index
+pub fn parse_chunk_href(href: &str) -> Option<(&str, usize)> {
+    let pos = href.rfind(CHUNK_MARKER)?;
+    let index = href[pos + CHUNK_MARKER.len()..].parse().ok()?;
+    Some((&href[..pos], index))
+}
+
+/// Split a spine item if it exceeds the size threshold
+///
+/// Returns an empty vector when the item is small enough or has no usable
+/// block boundaries, meaning the item should be used as-is.
+pub fn chunk_spine_item(href: &str, html: &str, options: &ChunkOptions) -> Vec<HtmlChunk> {
+    if html.len() <= options.max_item_bytes {
+        return Vec::new();
+    }
+
+    let parts = split_html(html, options.target_chunk_bytes);
+    if parts.len() < 2 {
+        return Vec::new();
+    }
+
+    parts
+        .into_iter()
+        .enumerate()
+        .map(|(index, html)| HtmlChunk {
+            index,
+            href: chunk_href(href, index),
+            html,
+        })
+        .collect()
+}
+
+/// Elements a split may happen in front of
+const BLOCK_ELEMENTS: &[&str] = &[
+    "p",
+    "div",
+    "section",
+    "article",
+    "aside",
+    "header",
+    "footer",
+    "nav",
+    "main",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "blockquote",
+    "ul",
+    "ol",
+    "dl",
+    "table",
+    "pre",
+    "figure",
+    "hr",
+];
+
+/// Elements a split may happen inside of (re-opened in the next chunk)
+const CONTAINER_ELEMENTS: &[&str] = &["div", "section", "article", "main"];
+
+/// An element open at some point of the scan
+#[derive(Debug, Clone)]
+struct OpenElement<'a> {
+    name: String,
+    tag: &'a str,
+}
+
+/// Split an XHTML document into standalone documents of roughly
+/// `target_bytes` each
+///
+/// Documents without a `<body>` are returned unchanged as a single part.
+pub fn split_html(html: &str, target_bytes: usize) -> Vec<String> {
+    let Some((body_start, body_end)) = body_range(html) else {
+        return vec![html.to_string()];
+    };
+    let prefix = &html[..body_start];
+    let suffix = &html[body_end..];
+    let body = &html[body_start..body_end];
+
+    // (start, end, ancestors open at start, ancestors open at end)
+    let mut parts: Vec<(usize, usize, Vec<OpenElement>, Vec<OpenElement>)> = Vec::new();
+    let mut stack: Vec<OpenElement> = Vec::new();
+    let mut chunk_start = 0;
+    let mut chunk_stack: Vec<OpenElement> = Vec::new();
+
+    for token in Tokens::new(body) {
+        match token {
+            Token::End { name, .. } => {
+                if let Some(depth) = stack.iter().rposition(|e| e.name == name) {
+                    stack.truncate(depth);
+                }
+            }
+            Token::Start {
+                name,
+                start,
+                end,
+                self_closing,
+            } => {
+                let at_boundary = BLOCK_ELEMENTS.contains(&name.as_str())
+                    && stack
+                        .iter()
+                        .all(|e| CONTAINER_ELEMENTS.contains(&e.name.as_str()));
+                if at_boundary && start - chunk_start >= target_bytes {
+                    parts.push((chunk_start, start, chunk_stack, stack.clone()));
+                    chunk_start = start;
+                    chunk_stack = stack.clone();
+                }
+
+                if !self_closing {
+                    stack.push(OpenElement {
+                        name,
+                        tag: &body[start..end],
+                    });
+                }
+            }
+            Token::Text { .. } => {}
+        }
+    }
+    parts.push((chunk_start, body.len(), chunk_stack, stack));
+
+    parts
+        .into_iter()
+        .map(|(start, end, open_at_start, open_at_end)| {
+            let mut out = String::with_capacity(prefix.len() + (end - start) + suffix.len());
+            out.push_str(prefix);
+            for element in &open_at_start {
+                out.push_str(element.tag);
+            }
+            out.push_str(&body[start..end]);
+            for element in open_at_end.iter().rev() {
+                out.push_str("</");
+                out.push_str(&element.name);
+                out.push('>');
+            }
+            out.push_str(suffix);
+            out
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(body: &str) -> String {
+        format!(
+            r#"<?xml version="1.0"?><html><head><link rel="stylesheet" href="style.css"/></head><body class="book">{}</body></html>"#,
+            body
+        )
+    }
+
+    fn paragraphs(count: usize) -> String {
+        (0..count)
+            .map(|i| format!("<p>Paragraph {} with some text.</p>", i))
+            .collect()
+    }
+
+    #[test]
+    fn test_chunk_href_round_trip() {
+        let href = chunk_href("Text/book.xhtml", 3);
+        assert_eq!(href, "Text/book.xhtml#amnesia-chunk-3");
+        assert_eq!(parse_chunk_href(&href), Some(("Text/book.xhtml", 3)));
+        assert_eq!(parse_chunk_href("Text/book.xhtml#note-3"), None);
+    }
+
+    #[test]
+    fn test_small_items_are_not_chunked() {
+        let html = document(&paragraphs(5));
+        assert!(chunk_spine_item("a.xhtml", &html, &ChunkOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn test_split_keeps_head_and_paragraphs() {
+        let html = document(&paragraphs(100));
+        let options = ChunkOptions {
+            max_item_bytes: 1024,
+            target_chunk_bytes: 512,
+        };
+        let chunks = chunk_spine_item("book.xhtml", &html, &options);
+
+        assert!(chunks.len() > 2);
+        for (i, chunk) in chunks.iter().enumerate() {
+            assert_eq!(chunk.index, i);
+            assert!(chunk
+                .html
+                .contains(r#"<link rel="stylesheet" href="style.css"/>"#));
+            assert!(chunk.html.contains(r#"<body class="book">"#));
+            assert!(chunk.html.ends_with("</body></html>"));
+            // Every chunk starts at a paragraph boundary
+            let body_start = chunk.html.find(r#"<body class="book">"#).unwrap() + 19;
+            assert!(chunk.html[body_start..].starts_with("<p>"));
+        }
+
+        // No content lost or duplicated
+        let total: usize = chunks.iter().map(|c| c.html.matches("<p>").count()).sum();
+        assert_eq!(total, 100);
+
+        // Deterministic ids and content
+        assert_eq!(chunks, chunk_spine_item("book.xhtml", &html, &options));
+    }
+
+    #[test]
+    fn test_split_reopens_wrapper_elements() {
+        let html = document(&format!(r#"<div class="chapter">{}</div>"#, paragraphs(50)));
+        let parts = split_html(&html, 400);
+
+        assert!(parts.len() > 1);
+        for part in &parts {
+            assert_eq!(
+                part.matches(r#"<div class="chapter">"#).count(),
+                1,
+                "{}",
+                part
+            );
+            assert_eq!(part.matches("</div>").count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_split_ignores_markup_in_comments_and_scripts() {
+        let body = format!(
+            "{}<script>if (a < b) {{ document.write('<p>x</p>'); }}</script><!-- <p> -->{}",
+            paragraphs(20),
+            paragraphs(20)
+        );
+        let parts = split_html(&document(&body), 200);
+        assert!(parts.iter().all(|p| p.matches("<script>").count() <= 1));
+        let total: usize = parts
+            .iter()
+            .map(|p| p.matches("<p>Paragraph").count())
+            .sum();
+        assert_eq!(total, 40);
+    }
+
+    #[test]
+    fn test_document_without_body_is_single_part() {
+        let parts = split_html("<p>loose</p>", 1);
+        assert_eq!(parts, vec!["<p>loose</p>".to_string()]);
+    }
+}
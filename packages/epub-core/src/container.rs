@@ -0,0 +1,229 @@
+//! `META-INF/container.xml` parsing
+//!
+//! A container lists one `<rootfile>` per rendition of the publication:
+//! a reflowable and a fixed-layout version, or one per language. The first
+//! is the default rendition; the EPUB Multiple-Rendition Publications spec
+//! describes the others with `rendition:*` selection attributes.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::EpubParseError;
+
+/// Path of the container document inside every EPUB archive
+pub const CONTAINER_PATH: &str = "META-INF/container.xml";
+
+/// Media type of OPF package documents
+const PACKAGE_MEDIA_TYPE: &str = "application/oebps-package+xml";
+
+/// Namespace of the rendition selection attributes
+const RENDITION_NS: &str = "http://www.idpf.org/2013/rendition";
+
+/// A package document listed in container.xml: one rendition of the book
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct RootFile {
+    /// Archive path of the OPF
+    pub full_path: String,
+    /// `rendition:label`, a name to show when offering the rendition
+    pub label: Option<String>,
+    /// `rendition:layout` (`reflowable` or `pre-paginated`)
+    pub layout: Option<String>,
+    /// `rendition:language`, a BCP 47 tag
+    pub language: Option<String>,
+    /// `rendition:media`, a CSS media query
+    pub media: Option<String>,
+    /// `rendition:accessMode` (`textual`, `visual`, `auditory`, `tactile`)
+    pub access_mode: Option<String>,
+}
+
+/// Preferences for choosing among a book's renditions
+///
+/// Unset fields match anything. `index` picks a rendition outright; the
+/// other fields pick the first rendition matching all of them, falling back
+/// to the default rendition when none does.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase", default))]
+pub struct RenditionSelector {
+    /// Position in the container's rootfile list
+    pub index: Option<usize>,
+    /// Matched against `rendition:label`, ignoring case
+    pub label: Option<String>,
+    pub layout: Option<String>,
+    /// Matches the same tag or a more specific one: `en` matches `en-GB`
+    pub language: Option<String>,
+    pub access_mode: Option<String>,
+}
+
+impl RenditionSelector {
+    /// Index of the rendition to load, or `None` when `index` is out of
+    /// range or there are no renditions
+    pub fn select(&self, rootfiles: &[RootFile]) -> Option<usize> {
+        if let Some(index) = self.index {
+            return (index < rootfiles.len()).then_some(index);
+        }
+        if rootfiles.is_empty() {
+            return None;
+        }
+        let matched = rootfiles.iter().position(|rootfile| self.matches(rootfile));
+        Some(matched.unwrap_or(0))
+    }
+
+    fn matches(&self, rootfile: &RootFile) -> bool {
+        let same = |wanted: &Option<String>, value: &Option<String>| match (wanted, value) {
+            (None, _) => true,
+            (Some(wanted), Some(value)) => wanted.eq_ignore_ascii_case(value),
+            (Some(_), None) => false,
+        };
+        let language = match (&self.language, &rootfile.language) {
+            (None, _) => true,
+            (Some(wanted), Some(value)) => {
+                let (wanted, value) = (wanted.to_ascii_lowercase(), value.to_ascii_lowercase());
+                value == wanted || value.starts_with(&format!("{wanted}-"))
+            }
+            (Some(_), None) => false,
+        };
+        same(&self.label, &rootfile.label)
+            && same(&self.layout, &rootfile.layout)
+            && same(&self.access_mode, &rootfile.access_mode)
+            && language
+    }
+}
+
+/// Every package document in container.xml, default rendition first
+///
+/// Rootfiles of other media types (a PDF version, say) are skipped.
+pub fn find_rootfiles(container_xml: &str) -> Result<Vec<RootFile>, EpubParseError> {
+    let doc = roxmltree::Document::parse(container_xml)?;
+
+    let rootfiles: Vec<RootFile> = doc
+        .descendants()
+        .filter(|node| node.tag_name().name() == "rootfile")
+        .filter(|node| {
+            node.attribute("media-type")
+                .is_none_or(|media_type| media_type.trim() == PACKAGE_MEDIA_TYPE)
+        })
+        .filter_map(|node| {
+            let rendition = |name: &str| {
+                node.attribute((RENDITION_NS, name))
+                    .map(str::trim)
+                    .filter(|value| !value.is_empty())
+                    .map(str::to_string)
+            };
+            Some(RootFile {
+                full_path: node.attribute("full-path")?.to_string(),
+                label: rendition("label"),
+                layout: rendition("layout"),
+                language: rendition("language"),
+                media: rendition("media"),
+                access_mode: rendition("accessMode"),
+            })
+        })
+        .collect();
+
+    if rootfiles.is_empty() {
+        return Err(EpubParseError::InvalidEpub(
+            "Could not find OPF path in container.xml".to_string(),
+        ));
+    }
+    Ok(rootfiles)
+}
+
+/// Find the path to the OPF package document from container.xml
+///
+/// Books with several renditions get the default (first) one.
+pub fn find_opf_path(container_xml: &str) -> Result<String, EpubParseError> {
+    let mut rootfiles = find_rootfiles(container_xml)?;
+    Ok(rootfiles.swap_remove(0).full_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_opf_path() {
+        let xml = r#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+    <rootfiles>
+        <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+    </rootfiles>
+</container>"#;
+
+        assert_eq!(find_opf_path(xml).unwrap(), "OEBPS/content.opf");
+    }
+
+    #[test]
+    fn test_find_opf_path_missing_rootfile() {
+        let xml = r#"<container><rootfiles/></container>"#;
+        assert!(matches!(
+            find_opf_path(xml),
+            Err(EpubParseError::InvalidEpub(_))
+        ));
+    }
+
+    #[test]
+    fn test_find_rootfiles() {
+        let xml = r#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container"
+    xmlns:rendition="http://www.idpf.org/2013/rendition">
+    <rootfiles>
+        <rootfile full-path="book.pdf" media-type="application/pdf"/>
+        <rootfile full-path="reflow/content.opf" media-type="application/oebps-package+xml"
+            rendition:label="Text" rendition:layout="reflowable" rendition:language="en-GB"
+            rendition:accessMode="textual"/>
+        <rootfile full-path="fixed/content.opf" media-type="application/oebps-package+xml"
+            rendition:label="Print replica" rendition:layout="pre-paginated"
+            rendition:media="(min-width: 1024px)" rendition:accessMode="visual"/>
+    </rootfiles>
+</container>"#;
+
+        let rootfiles = find_rootfiles(xml).unwrap();
+        assert_eq!(rootfiles.len(), 2);
+        assert_eq!(find_opf_path(xml).unwrap(), "reflow/content.opf");
+        assert_eq!(rootfiles[1].label.as_deref(), Some("Print replica"));
+        assert_eq!(rootfiles[1].media.as_deref(), Some("(min-width: 1024px)"));
+        assert_eq!(rootfiles[0].access_mode.as_deref(), Some("textual"));
+
+        let select = |selector: RenditionSelector| selector.select(&rootfiles);
+        assert_eq!(select(RenditionSelector::default()), Some(0));
+        assert_eq!(
+            select(RenditionSelector {
+                layout: Some("pre-paginated".to_string()),
+                ..Default::default()
+            }),
+            Some(1)
+        );
+        assert_eq!(
+            select(RenditionSelector {
+                label: Some("print REPLICA".to_string()),
+                ..Default::default()
+            }),
+            Some(1)
+        );
+        assert_eq!(
+            select(RenditionSelector {
+                language: Some("en".to_string()),
+                ..Default::default()
+            }),
+            Some(0)
+        );
+        // No match falls back to the default rendition
+        assert_eq!(
+            select(RenditionSelector {
+                language: Some("fr".to_string()),
+                ..Default::default()
+            }),
+            Some(0)
+        );
+        assert_eq!(
+            select(RenditionSelector {
+                index: Some(2),
+                ..Default::default()
+            }),
+            None
+        );
+    }
+}
@@ -0,0 +1,272 @@
+//! Glossaries
+//!
+//! EPUB 3 marks up a glossary as a definition list with
+//! `epub:type="glossary"` (on the `<dl>` or on the section or body holding
+//! it). Each `<dt>` names a term and the `<dd>`s after it define it; several
+//! `<dt>`s in a row share the definitions that follow. Definitions are kept
+//! as plain text, for showing inline next to the term in the book.
+
+use std::collections::HashMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::markup::{attribute, collapse_whitespace, decode_entities, Token, Tokens};
+use crate::path::resolve_href;
+use crate::types::SpineItem;
+
+/// A glossary term and its definitions
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct GlossaryEntry {
+    /// The term and any variants listed with it
+    pub terms: Vec<String>,
+    /// Definitions, whitespace collapsed
+    pub definitions: Vec<String>,
+    /// Spine item holding the glossary
+    pub spine_index: usize,
+    pub href: String,
+    /// Id of the term's `<dt>` (or an element in it), for linking to it
+    pub id: Option<String>,
+}
+
+/// The glossaries of a whole book
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct Glossary {
+    /// Spine indexes of the items holding a glossary
+    pub documents: Vec<usize>,
+    /// Entries in document order
+    pub entries: Vec<GlossaryEntry>,
+}
+
+impl Glossary {
+    /// Read the glossaries out of every spine item that has one
+    ///
+    /// `read` returns the markup at an archive path; items it cannot read
+    /// are skipped.
+    pub fn build(
+        spine: &[SpineItem],
+        opf_dir: &str,
+        mut read: impl FnMut(&str) -> Option<String>,
+    ) -> Self {
+        let mut glossary = Self::default();
+        for (spine_index, item) in spine.iter().enumerate() {
+            let Some(html) = read(&resolve_href(opf_dir, &item.href)) else {
+                continue;
+            };
+            if !html.contains("glossary") {
+                continue;
+            }
+            let entries = parse_glossary(&html);
+            if entries.is_empty() {
+                continue;
+            }
+            glossary.documents.push(spine_index);
+            glossary
+                .entries
+                .extend(entries.into_iter().map(|entry| GlossaryEntry {
+                    spine_index,
+                    href: item.href.clone(),
+                    ..entry
+                }));
+        }
+        glossary
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The entry for `term`
+    ///
+    /// Matching ignores case and runs of whitespace. A term not found as
+    /// written is tried without a plural `s` or `es`, so "enzymes" finds
+    /// "enzyme".
+    pub fn define(&self, term: &str) -> Option<&GlossaryEntry> {
+        let term = normalize_term(term);
+        if term.is_empty() {
+            return None;
+        }
+        let mut by_term: HashMap<String, &GlossaryEntry> = HashMap::new();
+        for entry in &self.entries {
+            for name in &entry.terms {
+                by_term.entry(normalize_term(name)).or_insert(entry);
+            }
+        }
+
+        let singular = [term.strip_suffix("es"), term.strip_suffix('s')];
+        let found = std::iter::once(term.as_str())
+            .chain(singular.into_iter().flatten())
+            .find_map(|candidate| by_term.get(candidate).copied());
+        found
+    }
+}
+
+/// What the scanner is reading
+enum Reading {
+    Term(String),
+    Definition(String),
+}
+
+/// Entries of the glossaries in one document, without their spine item
+fn parse_glossary(html: &str) -> Vec<GlossaryEntry> {
+    let mut entries = Vec::new();
+    // Open elements from the outermost glossary region in
+    let mut elements: Vec<String> = Vec::new();
+    // The entry being read, and the `<dt>` or `<dd>` within it
+    let mut entry: Option<GlossaryEntry> = None;
+    let mut reading: Option<(Reading, usize)> = None;
+
+    for token in Tokens::new(html) {
+        match token {
+            Token::Start {
+                name,
+                start,
+                end,
+                self_closing,
+            } => {
+                let tag = &html[start..end];
+                if elements.is_empty() {
+                    let glossary = attribute(tag, "epub:type")
+                        .is_some_and(|types| types.split_whitespace().any(|t| t == "glossary"));
+                    if glossary && !self_closing {
+                        elements.push(name);
+                    }
+                    continue;
+                }
+                if self_closing {
+                    // `<br/>` and the like separate words
+                    if let Some((Reading::Term(text) | Reading::Definition(text), _)) =
+                        reading.as_mut()
+                    {
+                        text.push(' ');
+                    }
+                    continue;
+                }
+                elements.push(name.clone());
+
+                match (name.as_str(), &reading) {
+                    ("dt", None) => {
+                        // A term after definitions starts the next entry
+                        if entry.as_ref().is_some_and(|e| !e.definitions.is_empty()) {
+                            entries.extend(entry.take());
+                        }
+                        let entry = entry.get_or_insert_with(GlossaryEntry::default);
+                        if entry.id.is_none() {
+                            entry.id = attribute(tag, "id").map(str::to_string);
+                        }
+                        reading = Some((Reading::Term(String::new()), elements.len()));
+                    }
+                    ("dd", None) if entry.is_some() => {
+                        reading = Some((Reading::Definition(String::new()), elements.len()));
+                    }
+                    (_, Some((Reading::Term(_), _))) => {
+                        if let Some(entry) = entry.as_mut().filter(|e| e.id.is_none()) {
+                            entry.id = attribute(tag, "id").map(str::to_string);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Token::End { name, .. } => {
+                let Some(depth) = elements.iter().rposition(|open| *open == name) else {
+                    continue;
+                };
+                elements.truncate(depth);
+                if reading.as_ref().is_some_and(|(_, at)| *at > depth) {
+                    if let (Some((read, _)), Some(entry)) = (reading.take(), entry.as_mut()) {
+                        finish(entry, read);
+                    }
+                }
+                if elements.is_empty() {
+                    entries.extend(entry.take());
+                }
+            }
+            Token::Text { start, end } => match reading.as_mut() {
+                Some((Reading::Term(text) | Reading::Definition(text), _)) => {
+                    text.push_str(&html[start..end]);
+                }
+                None => {}
+            },
+        }
+    }
+    if let (Some((read, _)), Some(entry)) = (reading, entry.as_mut()) {
+        finish(entry, read);
+    }
+    entries.extend(entry);
+    entries.retain(|entry| !entry.terms.is_empty() && !entry.definitions.is_empty());
+    entries
+}
+
+fn finish(entry: &mut GlossaryEntry, reading: Reading) {
+    let (text, list) = match reading {
+        Reading::Term(text) => (text, &mut entry.terms),
+        Reading::Definition(text) => (text, &mut entry.definitions),
+    };
+    let text = collapse_whitespace(&decode_entities(&text));
+    if !text.is_empty() {
+        list.push(text);
+    }
+}
+
+fn normalize_term(term: &str) -> String {
+    collapse_whitespace(term).to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::spine_item;
+
+    const GLOSSARY: &str = r#"<html><body>
+<dl><dt>Not</dt><dd>in a glossary</dd></dl>
+<section epub:type="glossary"><h1>Glossary</h1>
+<dl>
+  <dt epub:type="glossterm" id="g-atp"><dfn>ATP</dfn></dt>
+  <dt epub:type="glossterm"><dfn>Adenosine triphosphate</dfn></dt>
+  <dd epub:type="glossdef">The cell's energy
+    currency; see <a href="ch3.xhtml">chapter 3</a>.</dd>
+  <dt><dfn id="g-enzyme">Enzyme</dfn></dt>
+  <dd>A protein that speeds up a reaction.</dd>
+  <dd>Also: any biological catalyst &amp; its cofactors.</dd>
+  <dt>Orphan term</dt>
+</dl></section></body></html>"#;
+
+    #[test]
+    fn test_parse_glossary() {
+        let spine = [spine_item("ch1.xhtml"), spine_item("glossary.xhtml")];
+        let glossary = Glossary::build(&spine, "OEBPS", |path| match path {
+            "OEBPS/glossary.xhtml" => Some(GLOSSARY.to_string()),
+            "OEBPS/ch1.xhtml" => Some("<p>No glossary here</p>".to_string()),
+            _ => None,
+        });
+
+        assert_eq!(glossary.documents, vec![1]);
+        assert_eq!(glossary.entries.len(), 2);
+        let atp = &glossary.entries[0];
+        assert_eq!(atp.terms, vec!["ATP", "Adenosine triphosphate"]);
+        assert_eq!(
+            atp.definitions,
+            vec!["The cell's energy currency; see chapter 3."]
+        );
+        assert_eq!(atp.id.as_deref(), Some("g-atp"));
+        assert_eq!(atp.href, "glossary.xhtml");
+
+        let enzyme = &glossary.entries[1];
+        assert_eq!(enzyme.id.as_deref(), Some("g-enzyme"));
+        assert_eq!(enzyme.definitions.len(), 2);
+        assert_eq!(
+            enzyme.definitions[1],
+            "Also: any biological catalyst & its cofactors."
+        );
+
+        assert_eq!(glossary.define("  adenosine  TRIPHOSPHATE"), Some(atp));
+        assert_eq!(glossary.define("Enzymes"), Some(enzyme));
+        assert_eq!(glossary.define("orphan term"), None);
+        assert_eq!(glossary.define("Not"), None);
+        assert_eq!(glossary.define(""), None);
+    }
+}
@@ -0,0 +1,288 @@
+//! `<mark>` wrappers around ranges of chapter text
+//!
+//! Marking search hits with regular expressions over the markup breaks as
+//! soon as a hit spans an inline element (`<em>Moby</em> Dick`) or an
+//! entity, or a term also occurs inside an attribute. Here matches are found
+//! in the decoded text of each block and mapped back onto the text runs they
+//! cover; a match that crosses inline tags is wrapped piecewise, one
+//! `<mark>` per run, so tags are never split or reordered.
+//!
+//! [`insert_in_text`] maps offsets back the same way to insert a string
+//! into the text, such as soft hyphens, without splitting an entity.
+
+use std::ops::Range;
+
+use crate::markup::{body_range, decode_entities, Token, Tokens};
+
+/// Elements that do not end the block of text they appear in
+const INLINE_ELEMENTS: &[&str] = &[
+    "a", "abbr", "b", "bdi", "bdo", "cite", "code", "data", "del", "dfn", "em", "i", "ins", "kbd",
+    "mark", "q", "s", "samp", "small", "span", "strong", "sub", "sup", "time", "u", "var",
+];
+
+/// A text run of the current block
+struct Run {
+    /// Byte range of the raw run in the body
+    raw: Range<usize>,
+    /// Start of the decoded run in the block text
+    text_start: usize,
+    /// (decoded offset, raw offset) of each character, relative to the run
+    /// starts, followed by the end of both
+    chars: Vec<(usize, usize)>,
+}
+
+impl Run {
+    /// Raw offset of a decoded offset; offsets inside a decoded character
+    /// move to the next one
+    fn raw_offset(&self, decoded: usize) -> usize {
+        let i = self.chars.partition_point(|&(d, _)| d < decoded);
+        self.raw.start + self.chars[i.min(self.chars.len() - 1)].1
+    }
+}
+
+/// Wrap ranges of a chapter's body text in `<mark>` elements
+///
+/// The body is cut into blocks at every element that is not inline
+/// (paragraphs, headings, list items, images, `<br>`, ...). `find` is called
+/// with the text of each block, entities decoded and whitespace kept as is,
+/// and returns byte ranges of that text to mark. Overlapping ranges are
+/// merged. Documents without a `<body>` are treated as a fragment.
+pub fn mark_text(html: &str, mut find: impl FnMut(&str) -> Vec<Range<usize>>) -> String {
+    let (body_start, body_end) = body_range(html).unwrap_or((0, html.len()));
+    let body = &html[body_start..body_end];
+
+    // (byte offset into the body, is an opening tag)
+    let mut inserts: Vec<(usize, bool)> = Vec::new();
+    let mut text = String::new();
+    let mut runs: Vec<Run> = Vec::new();
+
+    let mut flush = |text: &mut String, runs: &mut Vec<Run>| {
+        if !text.trim().is_empty() {
+            for range in merge_ranges(find(text)) {
+                mark_range(runs, range, &mut inserts);
+            }
+        }
+        text.clear();
+        runs.clear();
+    };
+
+    for token in Tokens::new(body) {
+        match token {
+            Token::Text { start, end } => {
+                let (decoded, chars) = decode_run(&body[start..end]);
+                runs.push(Run {
+                    raw: start..end,
+                    text_start: text.len(),
+                    chars,
+                });
+                text.push_str(&decoded);
+            }
+            Token::Start { name, .. } | Token::End { name, .. } => {
+                if !INLINE_ELEMENTS.contains(&name.as_str()) {
+                    flush(&mut text, &mut runs);
+                }
+            }
+        }
+    }
+    flush(&mut text, &mut runs);
+
+    if inserts.is_empty() {
+        return html.to_string();
+    }
+    // Closing tags first where a mark ends and the next begins
+    inserts.sort_by_key(|&(offset, open)| (offset, open));
+
+    let mut out = String::with_capacity(html.len() + inserts.len() * 7);
+    out.push_str(&html[..body_start]);
+    let mut copied = 0;
+    for (offset, open) in inserts {
+        out.push_str(&body[copied..offset]);
+        out.push_str(if open { "<mark>" } else { "</mark>" });
+        copied = offset;
+    }
+    out.push_str(&body[copied..]);
+    out.push_str(&html[body_end..]);
+    out
+}
+
+/// Insert `insert` into a chapter's body text
+///
+/// `find` is called with each text run, entities decoded, and returns the
+/// byte offsets of that text to insert at. Runs inside the elements named
+/// in `skip` are left alone, as are scripts and styles. Documents without a
+/// `<body>` are treated as a fragment.
+pub fn insert_in_text(
+    html: &str,
+    skip: &[&str],
+    mut find: impl FnMut(&str) -> Vec<usize>,
+    insert: &str,
+) -> String {
+    let (body_start, body_end) = body_range(html).unwrap_or((0, html.len()));
+    let body = &html[body_start..body_end];
+
+    let mut offsets: Vec<usize> = Vec::new();
+    // Elements named in `skip` that are open
+    let mut skipped = 0usize;
+    for token in Tokens::new(body) {
+        match token {
+            Token::Start {
+                name,
+                self_closing: false,
+                ..
+            } if skip.contains(&name.as_str()) => skipped += 1,
+            Token::End { name, .. } if skip.contains(&name.as_str()) => {
+                skipped = skipped.saturating_sub(1);
+            }
+            Token::Text { start, end } if skipped == 0 => {
+                let (text, chars) = decode_run(&body[start..end]);
+                let run = Run {
+                    raw: start..end,
+                    text_start: 0,
+                    chars,
+                };
+                let mut found = find(&text);
+                found.retain(|&offset| offset <= text.len());
+                found.sort_unstable();
+                found.dedup();
+                offsets.extend(found.into_iter().map(|offset| run.raw_offset(offset)));
+            }
+            _ => {}
+        }
+    }
+
+    if offsets.is_empty() {
+        return html.to_string();
+    }
+
+    let mut out = String::with_capacity(html.len() + offsets.len() * insert.len());
+    out.push_str(&html[..body_start]);
+    let mut copied = 0;
+    for offset in offsets {
+        out.push_str(&body[copied..offset]);
+        out.push_str(insert);
+        copied = offset;
+    }
+    out.push_str(&body[copied..]);
+    out.push_str(&html[body_end..]);
+    out
+}
+
+/// Record the tags wrapping `range` of the block text, per run it covers
+fn mark_range(runs: &[Run], range: Range<usize>, inserts: &mut Vec<(usize, bool)>) {
+    for run in runs {
+        let run_len = run.chars.last().map_or(0, |&(d, _)| d);
+        let start = range.start.max(run.text_start);
+        let end = range.end.min(run.text_start + run_len);
+        if start >= end {
+            continue;
+        }
+        let raw_start = run.raw_offset(start - run.text_start);
+        let raw_end = run.raw_offset(end - run.text_start);
+        if raw_start < raw_end {
+            inserts.push((raw_start, true));
+            inserts.push((raw_end, false));
+        }
+    }
+}
+
+/// Sorted ranges with overlapping and empty ones merged away
+fn merge_ranges(mut ranges: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    ranges.retain(|range| range.start < range.end);
+    ranges.sort_by_key(|range| range.start);
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// Decode the entities of a raw text run, with the offsets of each
+/// character in both
+fn decode_run(raw: &str) -> (String, Vec<(usize, usize)>) {
+    let mut decoded = String::with_capacity(raw.len());
+    let mut chars = Vec::with_capacity(raw.len() + 1);
+    let mut pos = 0;
+    while pos < raw.len() {
+        let rest = &raw[pos..];
+        let entity = rest
+            .strip_prefix('&')
+            .and_then(|after| after.find(';').filter(|&semi| semi <= 32))
+            .and_then(|semi| {
+                let reference = &rest[..semi + 2];
+                let c = decode_entities(reference);
+                (c != reference).then_some((c, reference.len()))
+            });
+        let (text, len) = match entity {
+            Some((c, len)) => (c, len),
+            None => {
+                let len = rest.chars().next().map_or(1, char::len_utf8);
+                (rest[..len].to_string(), len)
+            }
+        };
+        chars.push((decoded.len(), pos));
+        decoded.push_str(&text);
+        pos += len;
+    }
+    chars.push((decoded.len(), raw.len()));
+    (decoded, chars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find_all(needle: &'static str) -> impl FnMut(&str) -> Vec<Range<usize>> {
+        move |text| {
+            text.match_indices(needle)
+                .map(|(i, m)| i..i + m.len())
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_mark_text() {
+        let html = r#"<html><head><title>Moby Dick</title></head><body>
+<p title="Moby Dick">Call me <em>Moby</em> Dick &amp; Moby&#160;Dick.</p>
+<p>Moby</p><p>Dick</p></body></html>"#;
+
+        let marked = mark_text(html, find_all("Moby Dick"));
+        assert_eq!(
+            marked,
+            r#"<html><head><title>Moby Dick</title></head><body>
+<p title="Moby Dick">Call me <em><mark>Moby</mark></em><mark> Dick</mark> &amp; Moby&#160;Dick.</p>
+<p>Moby</p><p>Dick</p></body></html>"#
+        );
+
+        let marked = mark_text(html, find_all("Dick & Moby\u{a0}D"));
+        assert!(marked.contains("</em> <mark>Dick &amp; Moby&#160;D</mark>ick."));
+
+        let marked = mark_text(html, |_| Vec::new());
+        assert_eq!(marked, html);
+    }
+
+    #[test]
+    fn test_insert_in_text() {
+        let html = r#"<html><head><title>Caf&#233;s</title></head><body>
+<p title="Cafés">Caf&#233;s <code>Cafés</code> &amp; caf&eacute;s</p></body></html>"#;
+
+        // Before the last "s" of each word
+        let inserted = insert_in_text(
+            html,
+            &["code"],
+            |text| {
+                text.match_indices("és")
+                    .map(|(i, _)| i + "é".len())
+                    .collect()
+            },
+            "|",
+        );
+        assert_eq!(
+            inserted,
+            r#"<html><head><title>Caf&#233;s</title></head><body>
+<p title="Cafés">Caf&#233;|s <code>Cafés</code> &amp; caf&eacute;s</p></body></html>"#
+        );
+    }
+}
@@ -0,0 +1,155 @@
+//! Pixel dimensions read from image headers
+//!
+//! Readers need an image's size before it has loaded to reserve space for it
+//! (see [`crate::blocks`]). EPUB manifests don't record sizes, so they are
+//! read from the first bytes of the file: PNG, GIF, JPEG and WebP headers,
+//! and the `width`/`height` (or `viewBox`) of an SVG root element.
+
+use crate::markup::attribute;
+
+/// `(width, height)` of an image in pixels, if its format is recognized
+pub fn image_size(bytes: &[u8]) -> Option<(u32, u32)> {
+    let size = if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        // IHDR is always the first chunk
+        Some((be32(bytes, 16)?, be32(bytes, 20)?))
+    } else if bytes.starts_with(b"GIF8") {
+        Some((u32::from(le16(bytes, 6)?), u32::from(le16(bytes, 8)?)))
+    } else if bytes.starts_with(b"\xff\xd8") {
+        jpeg_size(bytes)
+    } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        webp_size(bytes)
+    } else {
+        svg_size(bytes)
+    };
+    size.filter(|&(width, height)| width > 0 && height > 0)
+}
+
+/// Size from the first start-of-frame segment
+fn jpeg_size(bytes: &[u8]) -> Option<(u32, u32)> {
+    let mut pos = 2;
+    loop {
+        // Markers may be padded with any number of 0xff bytes
+        while *bytes.get(pos)? == 0xff && *bytes.get(pos + 1)? == 0xff {
+            pos += 1;
+        }
+        if *bytes.get(pos)? != 0xff {
+            return None;
+        }
+        let marker = *bytes.get(pos + 1)?;
+        match marker {
+            // Standalone markers without a length
+            0x01 | 0xd0..=0xd7 => pos += 2,
+            // SOF0-SOF15, except DHT, JPG and DAC which share the range
+            0xc0..=0xcf if !matches!(marker, 0xc4 | 0xc8 | 0xcc) => {
+                let height = be16(bytes, pos + 5)?;
+                let width = be16(bytes, pos + 7)?;
+                return Some((u32::from(width), u32::from(height)));
+            }
+            _ => pos += 2 + usize::from(be16(bytes, pos + 2)?),
+        }
+    }
+}
+
+/// Size from a lossy, lossless or extended WebP header
+fn webp_size(bytes: &[u8]) -> Option<(u32, u32)> {
+    match bytes.get(12..16)? {
+        b"VP8 " => {
+            let width = le16(bytes, 26)? & 0x3fff;
+            let height = le16(bytes, 28)? & 0x3fff;
+            Some((u32::from(width), u32::from(height)))
+        }
+        b"VP8L" => {
+            let bits = u32::from_le_bytes(bytes.get(21..25)?.try_into().ok()?);
+            Some(((bits & 0x3fff) + 1, ((bits >> 14) & 0x3fff) + 1))
+        }
+        b"VP8X" => {
+            let le24 = |at: usize| -> Option<u32> {
+                let b = bytes.get(at..at + 3)?;
+                Some(u32::from(b[0]) | u32::from(b[1]) << 8 | u32::from(b[2]) << 16)
+            };
+            Some((le24(24)? + 1, le24(27)? + 1))
+        }
+        _ => None,
+    }
+}
+
+/// Size from the root `<svg>` element's `width` and `height`, falling back
+/// to its `viewBox`
+pub(crate) fn svg_size(bytes: &[u8]) -> Option<(u32, u32)> {
+    // The root element is near the start; don't scan a whole drawing
+    let head = &bytes[..bytes.len().min(4096)];
+    let head = String::from_utf8_lossy(head);
+    let start = head.find("<svg")?;
+    let end = start + head[start..].find('>')? + 1;
+    let tag = &head[start..end];
+
+    let width = attribute(tag, "width").and_then(css_pixels);
+    let height = attribute(tag, "height").and_then(css_pixels);
+    if let (Some(width), Some(height)) = (width, height) {
+        return Some((width, height));
+    }
+    let view_box: Vec<f64> = attribute(tag, "viewBox")?
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .ok()?;
+    match view_box[..] {
+        [_, _, width, height] => Some((width.round() as u32, height.round() as u32)),
+        _ => None,
+    }
+}
+
+/// A length in pixels (`300` or `300px`); other units are not resolved
+pub(crate) fn css_pixels(value: &str) -> Option<u32> {
+    let value = value.trim();
+    let number = value.strip_suffix("px").unwrap_or(value).trim();
+    let pixels: f64 = number.parse().ok()?;
+    (pixels.is_finite() && pixels > 0.0).then(|| pixels.round() as u32)
+}
+
+fn be16(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn le16(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn be32(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_size() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend_from_slice(&640u32.to_be_bytes());
+        png.extend_from_slice(&480u32.to_be_bytes());
+        assert_eq!(image_size(&png), Some((640, 480)));
+
+        assert_eq!(image_size(b"GIF89a\x20\x03\x58\x02"), Some((800, 600)));
+
+        // APP0 segment, then a baseline SOF0
+        let jpeg = [
+            0xff, 0xd8, 0xff, 0xe0, 0x00, 0x04, 0x4a, 0x46, 0xff, 0xc0, 0x00, 0x11, 0x08, 0x01,
+            0x2c, 0x01, 0x90,
+        ];
+        assert_eq!(image_size(&jpeg), Some((400, 300)));
+
+        let mut webp = b"RIFF\0\0\0\0WEBPVP8X\x0a\0\0\0\0\0\0\0".to_vec();
+        webp.extend_from_slice(&[0x1f, 0x03, 0x00, 0x57, 0x02, 0x00]);
+        assert_eq!(image_size(&webp), Some((800, 600)));
+
+        let svg = br#"<?xml version="1.0"?><svg xmlns="http://www.w3.org/2000/svg" width="120px" height="90">"#;
+        assert_eq!(image_size(svg), Some((120, 90)));
+        let svg = br#"<svg viewBox="0 0 1600 1200" width="100%">"#;
+        assert_eq!(image_size(svg), Some((1600, 1200)));
+
+        assert_eq!(image_size(b"\x89PNG\r\n\x1a\n"), None);
+        assert_eq!(image_size(b"plain text"), None);
+    }
+}
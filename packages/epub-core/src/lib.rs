@@ -0,0 +1,99 @@
+//! EPUB parsing core for Amnesia
+//!
+//! Pure-Rust parsing of the EPUB package structure, shared by the WASM
+//! epub-processor and the server so both builds read books the same way:
+//! - `container`: locating the OPF package documents of a book's renditions
+//!   via `META-INF/container.xml`
+//! - `opf`: metadata (including accessibility metadata), manifest, spine,
+//!   rendition properties, and ToC document discovery
+//! - `rendition`: page sizes of fixed-layout (pre-paginated) chapters
+//! - `nav`: EPUB 3 navigation documents and EPUB 2 NCX table of contents
+//! - `path`: resolving hrefs against the package directory
+//! - `chunk`: splitting oversize spine items into virtual sub-items
+//! - `checksum`: per-chapter content checksums for detecting which
+//!   chapters changed when a book file is updated, and book fingerprints
+//! - `anchor`: deterministic `data-anchor` ids for chapter elements
+//! - `aria`: ARIA roles and region labels derived from `epub:type` and
+//!   headings
+//! - `book_index`: back-of-book indexes (`epub:type="index"`) as looked-up
+//!   terms and locators
+//! - `blocks`: block maps for estimating chapter layout without a DOM
+//! - `glossary`: glossary terms and definitions (`epub:type="glossary"`)
+//! - `highlight`: `<mark>` wrappers around matches in chapter text, and
+//!   insertions such as soft hyphens
+//! - `image`: pixel dimensions from image file headers
+//! - `links`: cross-references between chapters, as a link graph, and
+//!   links to web pages
+//! - `notes`: footnotes and endnotes cut out of chapters for popups
+//! - `rewrite`: resolving chapter URLs and stripping scripts for injection
+//!   into a reader DOM
+//! - `merge`: splitting chapters into stylesheets and body for merging into
+//!   one document
+//!
+//! The crate does not read ZIP archives itself; callers hand it the XML
+//! documents they extracted.
+
+pub mod anchor;
+pub mod aria;
+pub mod blocks;
+pub mod book_index;
+pub mod checksum;
+pub mod chunk;
+pub mod container;
+pub mod glossary;
+pub mod highlight;
+pub mod image;
+pub mod links;
+mod markup;
+pub mod merge;
+pub mod nav;
+pub mod notes;
+pub mod opf;
+pub mod path;
+pub mod rendition;
+pub mod rewrite;
+mod types;
+
+pub use anchor::inject_anchors;
+pub use aria::{add_aria_roles, aria_role};
+pub use blocks::{block_map, estimate_heights, Block, BlockImage, BlockMetrics};
+pub use book_index::{BookIndex, IndexEntry, IndexLocator, IndexMatch};
+pub use checksum::{
+    book_fingerprint, content_checksum, diff_checksums, ChapterChecksum, ChecksumDiff,
+};
+pub use chunk::{chunk_spine_item, ChunkOptions, HtmlChunk};
+pub use container::{find_opf_path, find_rootfiles, RenditionSelector, RootFile};
+pub use glossary::{Glossary, GlossaryEntry};
+pub use highlight::{insert_in_text, mark_text};
+pub use image::image_size;
+pub use links::{
+    extract_external_links, extract_links, ChapterLink, ChapterLinks, CrossReference, ExternalLink,
+    LinkGraph,
+};
+pub use merge::{split_chapter, ChapterParts, ChapterStyle};
+pub use nav::{parse_nav_document, parse_ncx_document, toc_from_spine};
+pub use notes::{extract_note, Note};
+pub use opf::{parse_opf, Package, TocDocInfo};
+pub use rendition::find_viewport;
+pub use rewrite::{prefix_ids, rewrite_css_urls, rewrite_links, rewrite_urls, sanitize_html};
+pub use types::{
+    Accessibility, BookMetadata, Creator, Layout, ManifestItem, PageProgression, PageSpread,
+    Rendition, SpineItem, TocEntry, Viewport,
+};
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum EpubParseError {
+    #[error("Invalid EPUB: {0}")]
+    InvalidEpub(String),
+
+    #[error("XML parse error: {0}")]
+    XmlError(String),
+}
+
+impl From<roxmltree::Error> for EpubParseError {
+    fn from(e: roxmltree::Error) -> Self {
+        EpubParseError::XmlError(e.to_string())
+    }
+}
@@ -0,0 +1,356 @@
+//! Cross-references between chapters
+//!
+//! Textbooks and reference works link chapters to each other: "see
+//! Section 4.2", glossary terms, notes at the end of the book. Reading the
+//! links out of every spine item gives a graph a reader can walk backwards,
+//! listing the places that refer to the chapter on screen.
+//!
+//! Only links from one spine item to another (itself included) are kept;
+//! links to external sites, images or stylesheets are not cross-references.
+//! Links to web pages are read separately by [`extract_external_links`], for
+//! checking whether they still resolve.
+
+use std::collections::{BTreeSet, HashMap};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::markup::{attribute, decode_entities, Token, Tokens};
+use crate::path::{parent_dir, resolve_href};
+use crate::rewrite::is_relative;
+use crate::types::SpineItem;
+
+/// Longest link text kept, in characters
+const MAX_TEXT_CHARS: usize = 120;
+
+/// A link in a chapter, as written
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct ChapterLink {
+    /// Archive path of the linked document
+    pub path: String,
+    pub fragment: Option<String>,
+    /// Text of the link, whitespace collapsed
+    pub text: String,
+    /// `epub:type` of the link (`noteref`, `glossref`, ...)
+    pub epub_type: Option<String>,
+}
+
+/// A link in a chapter to a web page
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct ExternalLink {
+    /// Absolute `http:` or `https:` URL
+    pub url: String,
+    /// Text of the link, whitespace collapsed
+    pub text: String,
+}
+
+/// A link from one spine item to another
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct CrossReference {
+    /// Spine index of the linking item
+    pub source: usize,
+    /// Spine index of the linked item
+    pub target: usize,
+    /// Element id the link points at, if any
+    pub fragment: Option<String>,
+    pub text: String,
+    pub epub_type: Option<String>,
+}
+
+/// Links of one spine item, as spine indexes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct ChapterLinks {
+    pub spine_index: usize,
+    /// Href relative to the OPF directory
+    pub href: String,
+    /// Other items this one links to, in spine order
+    pub links_to: Vec<usize>,
+    /// Other items linking to this one, in spine order
+    pub linked_from: Vec<usize>,
+}
+
+/// Cross-references of a whole book
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct LinkGraph {
+    /// Adjacency of every spine item, in spine order
+    pub chapters: Vec<ChapterLinks>,
+    /// Every link, by source then document order
+    pub links: Vec<CrossReference>,
+}
+
+impl LinkGraph {
+    /// Read the links of every spine item
+    ///
+    /// `read` returns the markup at an archive path; items it cannot read
+    /// have no outgoing links.
+    pub fn build(
+        spine: &[SpineItem],
+        opf_dir: &str,
+        mut read: impl FnMut(&str) -> Option<String>,
+    ) -> Self {
+        let paths: Vec<String> = spine
+            .iter()
+            .map(|item| resolve_href(opf_dir, &item.href))
+            .collect();
+        let index_of: HashMap<&str, usize> = paths
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(index, path)| (path.as_str(), index))
+            .collect();
+
+        let mut links = Vec::new();
+        for (source, path) in paths.iter().enumerate() {
+            let Some(html) = read(path) else {
+                continue;
+            };
+            for link in extract_links(&html, parent_dir(path)) {
+                if let Some(&target) = index_of.get(link.path.as_str()) {
+                    links.push(CrossReference {
+                        source,
+                        target,
+                        fragment: link.fragment,
+                        text: link.text,
+                        epub_type: link.epub_type,
+                    });
+                }
+            }
+        }
+
+        let mut links_to = vec![BTreeSet::new(); spine.len()];
+        let mut linked_from = vec![BTreeSet::new(); spine.len()];
+        for link in links.iter().filter(|link| link.source != link.target) {
+            links_to[link.source].insert(link.target);
+            linked_from[link.target].insert(link.source);
+        }
+        let chapters = spine
+            .iter()
+            .enumerate()
+            .zip(links_to.into_iter().zip(linked_from))
+            .map(
+                |((spine_index, item), (links_to, linked_from))| ChapterLinks {
+                    spine_index,
+                    href: item.href.clone(),
+                    links_to: links_to.into_iter().collect(),
+                    linked_from: linked_from.into_iter().collect(),
+                },
+            )
+            .collect();
+
+        Self { chapters, links }
+    }
+
+    /// Links pointing into spine item `target` from other items
+    pub fn references_to(&self, target: usize) -> impl Iterator<Item = &CrossReference> {
+        self.links
+            .iter()
+            .filter(move |link| link.target == target && link.source != target)
+    }
+}
+
+/// Links in chapter markup to documents in the archive
+///
+/// `base_dir` is the chapter's directory in the archive. Absolute URLs,
+/// fragment-only links and `<a>` elements without `href` are skipped.
+pub fn extract_links(html: &str, base_dir: &str) -> Vec<ChapterLink> {
+    anchors(html)
+        .into_iter()
+        .filter(|anchor| is_relative(&anchor.href))
+        .map(|anchor| ChapterLink {
+            path: resolve_href(base_dir, &anchor.href),
+            fragment: anchor
+                .href
+                .split_once('#')
+                .map(|(_, fragment)| fragment.to_string())
+                .filter(|fragment| !fragment.is_empty()),
+            text: anchor.text,
+            epub_type: anchor.epub_type,
+        })
+        .collect()
+}
+
+/// Links in chapter markup to web pages (`http:` and `https:` URLs)
+///
+/// URLs are returned as written, entities decoded; `mailto:` and other
+/// schemes are skipped.
+pub fn extract_external_links(html: &str) -> Vec<ExternalLink> {
+    anchors(html)
+        .into_iter()
+        .filter_map(|anchor| {
+            let url = decode_entities(&anchor.href);
+            let scheme = url.split_once(':')?.0;
+            if !scheme.eq_ignore_ascii_case("http") && !scheme.eq_ignore_ascii_case("https") {
+                return None;
+            }
+            Some(ExternalLink {
+                url,
+                text: anchor.text,
+            })
+        })
+        .collect()
+}
+
+/// An `<a href>` in chapter markup
+struct Anchor {
+    /// `href` as written, trimmed
+    href: String,
+    epub_type: Option<String>,
+    /// Text of the link, whitespace collapsed
+    text: String,
+}
+
+/// The `<a>` elements with an `href`, in document order
+fn anchors(html: &str) -> Vec<Anchor> {
+    let mut anchors = Vec::new();
+    // The open `<a>` and the text seen inside it so far
+    let mut open: Option<(Anchor, String)> = None;
+
+    for token in Tokens::new(html) {
+        match token {
+            Token::Start {
+                name,
+                start,
+                end,
+                self_closing,
+            } if name == "a" => {
+                let tag = &html[start..end];
+                if let Some((anchor, text)) = open.take() {
+                    anchors.push(finish(anchor, &text));
+                }
+                let Some(href) = attribute(tag, "href").map(str::trim) else {
+                    continue;
+                };
+                let anchor = Anchor {
+                    href: href.to_string(),
+                    epub_type: attribute(tag, "epub:type")
+                        .map(|epub_type| epub_type.trim().to_string())
+                        .filter(|epub_type| !epub_type.is_empty()),
+                    text: String::new(),
+                };
+                if self_closing {
+                    anchors.push(anchor);
+                } else {
+                    open = Some((anchor, String::new()));
+                }
+            }
+            Token::End { name, .. } if name == "a" => {
+                if let Some((anchor, text)) = open.take() {
+                    anchors.push(finish(anchor, &text));
+                }
+            }
+            Token::Text { start, end } => {
+                if let Some((_, text)) = open.as_mut() {
+                    text.push_str(&html[start..end]);
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Some((anchor, text)) = open {
+        anchors.push(finish(anchor, &text));
+    }
+    anchors
+}
+
+fn finish(mut anchor: Anchor, text: &str) -> Anchor {
+    let text = decode_entities(text);
+    anchor.text = text
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(MAX_TEXT_CHARS)
+        .collect();
+    anchor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::spine_item;
+
+    #[test]
+    fn test_extract_links() {
+        let html = r##"<p>See <a href="ch2.xhtml#s4.2">Section
+  4.2</a>, <a href="#local">above</a>, <a href="https://example.com/">the site</a>
+<a epub:type="noteref" href="../notes/notes.xhtml#n1"><sup>1</sup></a>
+<a href="../Images/fig%201.png">Figure &amp; table</a> <a id="x">no href</a></p>"##;
+        let links = extract_links(html, "OEBPS/Text");
+        assert_eq!(links.len(), 3);
+        assert_eq!(links[0].path, "OEBPS/Text/ch2.xhtml");
+        assert_eq!(links[0].fragment.as_deref(), Some("s4.2"));
+        assert_eq!(links[0].text, "Section 4.2");
+        assert_eq!(links[1].path, "OEBPS/notes/notes.xhtml");
+        assert_eq!(links[1].epub_type.as_deref(), Some("noteref"));
+        assert_eq!(links[1].text, "1");
+        assert_eq!(links[2].path, "OEBPS/Images/fig 1.png");
+        assert_eq!(links[2].text, "Figure & table");
+        assert_eq!(links[2].fragment, None);
+    }
+
+    #[test]
+    fn test_extract_external_links() {
+        let html = r##"<p><a href=" https://example.com/a?x=1&amp;y=2#top ">The
+  site</a> <a href="HTTP://old.example.org"/> <a href="mailto:a@example.com">mail</a>
+<a href="ch2.xhtml">next</a> <a href="javascript:void(0)">x</a></p>"##;
+        let links = extract_external_links(html);
+        assert_eq!(
+            links,
+            vec![
+                ExternalLink {
+                    url: "https://example.com/a?x=1&y=2#top".to_string(),
+                    text: "The site".to_string(),
+                },
+                ExternalLink {
+                    url: "HTTP://old.example.org".to_string(),
+                    text: String::new(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_link_graph() {
+        let spine = [
+            spine_item("Text/ch1.xhtml"),
+            spine_item("Text/ch2.xhtml"),
+            spine_item("notes.xhtml"),
+        ];
+        let graph = LinkGraph::build(&spine, "OEBPS", |path| {
+            Some(
+                match path {
+                    "OEBPS/Text/ch1.xhtml" => {
+                        r#"<a href="ch2.xhtml">Next</a><a href="../notes.xhtml#n1">1</a>
+<a href="ch1.xhtml#top">Top</a><a href="../style.css">css</a>"#
+                    }
+                    "OEBPS/Text/ch2.xhtml" => r#"<a href="../notes.xhtml#n2">2</a>"#,
+                    _ => return None,
+                }
+                .to_string(),
+            )
+        });
+
+        assert_eq!(graph.links.len(), 4);
+        assert_eq!(graph.chapters[0].links_to, vec![1, 2]);
+        assert!(graph.chapters[0].linked_from.is_empty());
+        assert_eq!(graph.chapters[2].href, "notes.xhtml");
+        assert_eq!(graph.chapters[2].linked_from, vec![0, 1]);
+        assert!(graph.chapters[2].links_to.is_empty());
+
+        // The link to the top of ch1 is within ch1
+        let to_ch1: Vec<_> = graph.references_to(0).collect();
+        assert!(to_ch1.is_empty());
+        let to_notes: Vec<_> = graph.references_to(2).map(|l| l.text.as_str()).collect();
+        assert_eq!(to_notes, vec!["1", "2"]);
+    }
+}
@@ -0,0 +1,381 @@
+//! Minimal tag scanner for chapter XHTML
+//!
+//! Chapter rewriting (chunking, anchors, URLs, sanitizing) only needs tag
+//! boundaries and element nesting, not a DOM, and has to tolerate the
+//! malformed markup found in real books. The scanner yields start tags, end
+//! tags, and text runs as byte ranges into the input; comments, CDATA
+//! sections, and declarations are skipped, as is the content of `<script>`
+//! and `<style>`.
+
+/// Elements that never have a closing tag
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Elements whose content is not markup
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style"];
+
+/// A piece of markup; offsets are byte ranges into the scanned input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Token {
+    Start {
+        name: String,
+        start: usize,
+        end: usize,
+        /// `<br/>`, `<img>`, ... (no matching end tag follows)
+        self_closing: bool,
+    },
+    End {
+        name: String,
+        start: usize,
+        end: usize,
+    },
+    Text {
+        start: usize,
+        end: usize,
+    },
+}
+
+/// Iterator over the [`Token`]s of a document fragment
+pub(crate) struct Tokens<'a> {
+    html: &'a str,
+    pos: usize,
+    /// End tag to skip ahead to after a raw-text start tag
+    raw_close: Option<String>,
+}
+
+impl<'a> Tokens<'a> {
+    pub(crate) fn new(html: &'a str) -> Self {
+        Self {
+            html,
+            pos: 0,
+            raw_close: None,
+        }
+    }
+}
+
+impl Iterator for Tokens<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        let html = self.html;
+        loop {
+            if let Some(close) = self.raw_close.take() {
+                self.pos = find_ignore_case(html, self.pos, &close).unwrap_or(html.len());
+            }
+            if self.pos >= html.len() {
+                return None;
+            }
+
+            let start = self.pos;
+            let tag_start = match html[start..].find('<') {
+                Some(0) => start,
+                Some(offset) => {
+                    self.pos = start + offset;
+                    return Some(Token::Text {
+                        start,
+                        end: self.pos,
+                    });
+                }
+                None => {
+                    self.pos = html.len();
+                    return Some(Token::Text {
+                        start,
+                        end: html.len(),
+                    });
+                }
+            };
+
+            let rest = &html[tag_start..];
+            if rest.starts_with("<!--") {
+                self.pos = skip_past(html, tag_start, "-->");
+                continue;
+            }
+            if rest.starts_with("<![CDATA[") {
+                self.pos = skip_past(html, tag_start, "]]>");
+                continue;
+            }
+            if rest.starts_with("<!") || rest.starts_with("<?") {
+                self.pos = skip_past(html, tag_start, ">");
+                continue;
+            }
+
+            let tag_end = find_tag_end(html, tag_start);
+            let tag = &html[tag_start..tag_end];
+
+            if let Some(closing) = tag.strip_prefix("</") {
+                self.pos = tag_end;
+                return Some(Token::End {
+                    name: tag_name(closing),
+                    start: tag_start,
+                    end: tag_end,
+                });
+            }
+
+            let name = tag_name(&tag[1..]);
+            if name.is_empty() {
+                // A stray `<` in text
+                self.pos = tag_start + 1;
+                return Some(Token::Text {
+                    start: tag_start,
+                    end: self.pos,
+                });
+            }
+
+            let self_closing = tag.ends_with("/>") || VOID_ELEMENTS.contains(&name.as_str());
+            if !self_closing && RAW_TEXT_ELEMENTS.contains(&name.as_str()) {
+                self.raw_close = Some(format!("</{}", name));
+            }
+
+            self.pos = tag_end;
+            return Some(Token::Start {
+                name,
+                start: tag_start,
+                end: tag_end,
+                self_closing,
+            });
+        }
+    }
+}
+
+/// Byte range of the body content (after `<body ...>`, before `</body>`)
+pub(crate) fn body_range(html: &str) -> Option<(usize, usize)> {
+    let open = find_ignore_case(html, 0, "<body")?;
+    let start = find_tag_end(html, open);
+    let end = rfind_ignore_case(html, "</body")
+        .filter(|&e| e >= start)
+        .unwrap_or(html.len());
+    Some((start, end))
+}
+
+/// An attribute of a start tag
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Attribute<'a> {
+    pub name: &'a str,
+    /// Value without quotes; entities are left as is
+    pub value: &'a str,
+    /// Byte range of the whole attribute, name to closing quote, in the tag
+    pub start: usize,
+    pub end: usize,
+}
+
+/// The attributes of a start tag, in order
+///
+/// `tag` is the whole tag, `<` to `>`.
+pub(crate) fn attributes(tag: &str) -> impl Iterator<Item = Attribute<'_>> {
+    let inner = tag.trim_end_matches('>').trim_end_matches('/');
+    // Skip `<` and the element name
+    let mut pos = inner.find(char::is_whitespace).unwrap_or(inner.len());
+
+    std::iter::from_fn(move || {
+        let rest = &inner[pos..];
+        let start = pos + (rest.len() - rest.trim_start().len());
+        let rest = &inner[start..];
+        let key_end = rest
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        if key_end == 0 {
+            return None;
+        }
+        let name = &rest[..key_end];
+
+        let after_key = rest[key_end..].trim_start();
+        let (value, end) = match after_key.strip_prefix('=') {
+            Some(after) => {
+                let after = after.trim_start();
+                let value_start = inner.len() - after.len();
+                match after.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let body = &after[1..];
+                        let close = body.find(quote).unwrap_or(body.len());
+                        let end = (value_start + close + 2).min(inner.len());
+                        (&body[..close], end)
+                    }
+                    _ => {
+                        let len = after.find(char::is_whitespace).unwrap_or(after.len());
+                        (&after[..len], value_start + len)
+                    }
+                }
+            }
+            None => ("", start + key_end),
+        };
+
+        pos = end;
+        Some(Attribute {
+            name,
+            value,
+            start,
+            end,
+        })
+    })
+}
+
+/// Value of attribute `name` (ASCII case-insensitive) in a start tag
+///
+/// `tag` is the whole tag, `<` to `>`. Entities in the value are left as is.
+pub(crate) fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    attributes(tag)
+        .find(|attr| attr.name.eq_ignore_ascii_case(name))
+        .map(|attr| attr.value)
+}
+
+/// Decode the XML entities and character references
+pub(crate) fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest.find(';').and_then(|semi| {
+            let entity = &rest[1..semi];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some('\u{a0}'),
+                _ => {
+                    let code = match entity.strip_prefix("#x").or(entity.strip_prefix("#X")) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                        None => entity.strip_prefix('#').and_then(|dec| dec.parse().ok()),
+                    };
+                    code.and_then(char::from_u32)
+                }
+            };
+            c.map(|c| (c, semi))
+        });
+        match decoded {
+            Some((c, semi)) => {
+                out.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Runs of whitespace as single spaces, trimmed
+pub(crate) fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Index just past the `>` closing the tag at `start`, honouring quotes
+fn find_tag_end(s: &str, start: usize) -> usize {
+    let mut quote: Option<u8> = None;
+    for (i, &b) in s.as_bytes()[start..].iter().enumerate() {
+        match (quote, b) {
+            (Some(q), _) if b == q => quote = None,
+            (Some(_), _) => {}
+            (None, b'"' | b'\'') => quote = Some(b),
+            (None, b'>') => return start + i + 1,
+            _ => {}
+        }
+    }
+    s.len()
+}
+
+fn skip_past(s: &str, from: usize, terminator: &str) -> usize {
+    s[from..]
+        .find(terminator)
+        .map(|i| from + i + terminator.len())
+        .unwrap_or(s.len())
+}
+
+/// Lowercased element name at the start of `s` (after `<` or `</`)
+fn tag_name(s: &str) -> String {
+    s.chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, ':' | '-' | '_'))
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+fn find_ignore_case(haystack: &str, from: usize, needle: &str) -> Option<usize> {
+    let needle = needle.as_bytes();
+    haystack.as_bytes()[from..]
+        .windows(needle.len())
+        .position(|w| w.eq_ignore_ascii_case(needle))
+        .map(|i| from + i)
+}
+
+fn rfind_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    let needle = needle.as_bytes();
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .rposition(|w| w.eq_ignore_ascii_case(needle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens() {
+        let html = r#"<p class="a>b">Hi<br/><!-- <x> --><script>if (a<b) {}</script></p>"#;
+        let tokens: Vec<Token> = Tokens::new(html).collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Start {
+                    name: "p".into(),
+                    start: 0,
+                    end: 15,
+                    self_closing: false
+                },
+                Token::Text { start: 15, end: 17 },
+                Token::Start {
+                    name: "br".into(),
+                    start: 17,
+                    end: 22,
+                    self_closing: true
+                },
+                Token::Start {
+                    name: "script".into(),
+                    start: 34,
+                    end: 42,
+                    self_closing: false
+                },
+                Token::End {
+                    name: "script".into(),
+                    start: 53,
+                    end: 62
+                },
+                Token::End {
+                    name: "p".into(),
+                    start: 62,
+                    end: 66
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_attribute() {
+        let tag = r#"<meta NAME="viewport" content='width=10, height=20' hidden data-x=y/>"#;
+        assert_eq!(attribute(tag, "name"), Some("viewport"));
+        assert_eq!(attribute(tag, "content"), Some("width=10, height=20"));
+        assert_eq!(attribute(tag, "hidden"), Some(""));
+        assert_eq!(attribute(tag, "data-x"), Some("y"));
+        assert_eq!(attribute(tag, "meta"), None);
+        assert_eq!(attribute("<br>", "class"), None);
+
+        let tag = r#"<img src = "a b.png"  alt='x'/>"#;
+        let ranges: Vec<&str> = attributes(tag).map(|a| &tag[a.start..a.end]).collect();
+        assert_eq!(ranges, vec![r#"src = "a b.png""#, "alt='x'"]);
+    }
+
+    #[test]
+    fn test_body_range() {
+        let html = "<html><BODY id=\"b\">text</BODY></html>";
+        let (start, end) = body_range(html).unwrap();
+        assert_eq!(&html[start..end], "text");
+        assert!(body_range("<p>no body</p>").is_none());
+    }
+}
@@ -0,0 +1,129 @@
+//! Splitting chapters for merging into one document
+//!
+//! A book exported as a single HTML document keeps each chapter's body and
+//! the stylesheets its head links or embeds. [`split_chapter`] separates
+//! the two; [`prefix_ids`](crate::rewrite::prefix_ids) and
+//! [`rewrite_links`](crate::rewrite::rewrite_links) then keep the body's
+//! ids and links working next to the other chapters.
+
+use crate::markup::{attribute, body_range, Token, Tokens};
+
+/// A stylesheet of a chapter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChapterStyle {
+    /// Href of a `<link rel="stylesheet">`, as written
+    Link(String),
+    /// Content of a `<style>` element
+    Inline(String),
+}
+
+/// A chapter's stylesheets and body
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChapterParts {
+    /// Stylesheets before `<body>`, in document order
+    pub styles: Vec<ChapterStyle>,
+    /// `class` attribute of `<body>`, as written
+    pub body_class: Option<String>,
+    /// Markup inside `<body>`
+    pub body: String,
+}
+
+/// Split chapter markup into its stylesheets and body
+///
+/// Alternate stylesheets are skipped. A document without a `<body>` (such
+/// as an SVG spine item) is all body, from its first element on.
+pub fn split_chapter(html: &str) -> ChapterParts {
+    let mut parts = ChapterParts::default();
+    let mut style_content: Option<usize> = None;
+    let mut first_element = None;
+
+    for token in Tokens::new(html) {
+        match token {
+            Token::Start {
+                name,
+                start,
+                end,
+                self_closing,
+            } => {
+                first_element.get_or_insert(start);
+                let tag = &html[start..end];
+                match name.as_str() {
+                    "body" => {
+                        parts.body_class = attribute(tag, "class").map(str::to_string);
+                        break;
+                    }
+                    "link" if is_stylesheet(tag) => {
+                        if let Some(href) = attribute(tag, "href").filter(|href| !href.is_empty()) {
+                            parts.styles.push(ChapterStyle::Link(href.to_string()));
+                        }
+                    }
+                    "style" if !self_closing => style_content = Some(end),
+                    _ => {}
+                }
+            }
+            Token::End { name, start, .. } if name == "style" => {
+                if let Some(content_start) = style_content.take() {
+                    let css = &html[content_start..start];
+                    if !css.trim().is_empty() {
+                        parts.styles.push(ChapterStyle::Inline(css.to_string()));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    parts.body = match body_range(html) {
+        Some((start, end)) => html[start..end].to_string(),
+        None => {
+            parts.styles.clear();
+            html[first_element.unwrap_or(html.len())..].to_string()
+        }
+    };
+    parts
+}
+
+/// Whether a `<link>` tag links a (non-alternate) stylesheet
+fn is_stylesheet(tag: &str) -> bool {
+    let Some(rel) = attribute(tag, "rel") else {
+        return false;
+    };
+    let rel = rel.to_ascii_lowercase();
+    let mut kinds = rel.split_whitespace();
+    kinds.clone().any(|kind| kind == "stylesheet") && !kinds.any(|kind| kind == "alternate")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_chapter() {
+        let html = r#"<?xml version="1.0" encoding="utf-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml"><head><title>One</title>
+<link rel="stylesheet" type="text/css" href="../Styles/main.css"/>
+<link rel="alternate stylesheet" href="night.css"/>
+<style>p { margin: 0 }</style><style></style>
+</head><body class="chapter" id="c1"><p>Text</p>
+<style>em { color: red }</style></body></html>"#;
+
+        let parts = split_chapter(html);
+        assert_eq!(
+            parts.styles,
+            vec![
+                ChapterStyle::Link("../Styles/main.css".to_string()),
+                ChapterStyle::Inline("p { margin: 0 }".to_string()),
+            ]
+        );
+        assert_eq!(parts.body_class.as_deref(), Some("chapter"));
+        assert_eq!(parts.body, "<p>Text</p>\n<style>em { color: red }</style>");
+
+        // No body: the document is the body
+        let svg = r#"<?xml version="1.0"?><svg xmlns="http://www.w3.org/2000/svg"><style>x{}</style></svg>"#;
+        let parts = split_chapter(svg);
+        assert!(parts.styles.is_empty());
+        assert_eq!(parts.body_class, None);
+        assert!(parts.body.starts_with("<svg xmlns"));
+    }
+}
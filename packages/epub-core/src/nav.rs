@@ -0,0 +1,258 @@
+//! Table of contents parsing
+//!
+//! Handles EPUB 3 Navigation Documents (`<nav epub:type="toc">`) and EPUB 2
+//! NCX documents, plus a spine-derived fallback for books that have neither.
+
+use crate::{SpineItem, TocEntry};
+
+/// Parse EPUB 3 Navigation Document (NAV)
+pub fn parse_nav_document(content: &str) -> Vec<TocEntry> {
+    let doc = match roxmltree::Document::parse(content) {
+        Ok(d) => d,
+        Err(_) => return Vec::new(),
+    };
+
+    // Find the nav element with epub:type="toc"
+    for node in doc.descendants() {
+        if node.tag_name().name() == "nav" {
+            let is_toc = node
+                .attributes()
+                .any(|a| a.name() == "type" && a.value().split_whitespace().any(|t| t == "toc"));
+
+            if is_toc {
+                // Find the ol element inside
+                if let Some(ol) = node.descendants().find(|c| c.tag_name().name() == "ol") {
+                    return parse_nav_ol(&ol, 0);
+                }
+            }
+        }
+    }
+
+    // Fallback: find any nav > ol structure
+    for node in doc.descendants() {
+        if node.tag_name().name() == "nav" {
+            for child in node.descendants() {
+                if child.tag_name().name() == "ol" {
+                    let entries = parse_nav_ol(&child, 0);
+                    if !entries.is_empty() {
+                        return entries;
+                    }
+                }
+            }
+        }
+    }
+
+    Vec::new()
+}
+
+/// Parse an ol element in the NAV document
+fn parse_nav_ol(ol: &roxmltree::Node, level: usize) -> Vec<TocEntry> {
+    ol.children()
+        .filter(|child| child.tag_name().name() == "li")
+        .filter_map(|li| parse_nav_li(&li, level))
+        .collect()
+}
+
+/// Parse an li element in the NAV document
+fn parse_nav_li(li: &roxmltree::Node, level: usize) -> Option<TocEntry> {
+    let mut href = String::new();
+    let mut label = String::new();
+    let mut children = Vec::new();
+
+    for child in li.children() {
+        match child.tag_name().name() {
+            "a" => {
+                href = child.attribute("href").unwrap_or("").to_string();
+                label = text_content(&child);
+            }
+            "span" if label.is_empty() => {
+                label = text_content(&child);
+            }
+            "ol" => {
+                children = parse_nav_ol(&child, level + 1);
+            }
+            _ => {}
+        }
+    }
+
+    if !label.is_empty() || !href.is_empty() {
+        Some(TocEntry {
+            id: format!("toc-{}-{}", level, href.replace(['/', '#', '.'], "-")),
+            href,
+            label: normalize_label(&label),
+            level,
+            children,
+        })
+    } else {
+        None
+    }
+}
+
+/// Get text content from a node recursively
+fn text_content(node: &roxmltree::Node) -> String {
+    node.descendants()
+        .filter(|n| n.is_text())
+        .filter_map(|n| n.text())
+        .collect()
+}
+
+/// Collapse the whitespace that pretty-printed navigation documents leave in labels
+fn normalize_label(label: &str) -> String {
+    label.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Parse EPUB 2 NCX Document
+pub fn parse_ncx_document(content: &str) -> Vec<TocEntry> {
+    let doc = match roxmltree::Document::parse(content) {
+        Ok(d) => d,
+        Err(_) => return Vec::new(),
+    };
+
+    doc.descendants()
+        .find(|node| node.tag_name().name() == "navMap")
+        .map(|nav_map| parse_ncx_nav_points(&nav_map, 0))
+        .unwrap_or_default()
+}
+
+/// Parse the navPoint children of a navMap (or of a parent navPoint)
+fn parse_ncx_nav_points(parent: &roxmltree::Node, level: usize) -> Vec<TocEntry> {
+    parent
+        .children()
+        .filter(|child| child.tag_name().name() == "navPoint")
+        .filter_map(|nav_point| parse_ncx_nav_point(&nav_point, level))
+        .collect()
+}
+
+/// Parse navPoint element in NCX
+fn parse_ncx_nav_point(nav_point: &roxmltree::Node, level: usize) -> Option<TocEntry> {
+    let id = nav_point.attribute("id").unwrap_or("").to_string();
+    let mut label = String::new();
+    let mut href = String::new();
+
+    for child in nav_point.children() {
+        match child.tag_name().name() {
+            "navLabel" => {
+                if let Some(text) = child
+                    .descendants()
+                    .find(|sub| sub.tag_name().name() == "text")
+                    .and_then(|sub| sub.text())
+                {
+                    label = normalize_label(text);
+                }
+            }
+            "content" => {
+                href = child.attribute("src").unwrap_or("").to_string();
+            }
+            _ => {}
+        }
+    }
+
+    let children = parse_ncx_nav_points(nav_point, level + 1);
+
+    if !label.is_empty() || !href.is_empty() {
+        Some(TocEntry {
+            id: if id.is_empty() {
+                format!("ncx-{}-{}", level, href.replace(['/', '#', '.'], "-"))
+            } else {
+                id
+            },
+            href,
+            label,
+            level,
+            children,
+        })
+    } else {
+        None
+    }
+}
+
+/// Generate ToC from spine when no NAV/NCX is available
+pub fn toc_from_spine(spine: &[SpineItem]) -> Vec<TocEntry> {
+    spine
+        .iter()
+        .enumerate()
+        .filter(|(_, item)| item.linear)
+        .map(|(i, item)| TocEntry {
+            id: format!("spine-{}", i),
+            href: item.href.clone(),
+            label: format!("Chapter {}", i + 1),
+            level: 0,
+            children: Vec::new(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::spine_item;
+
+    #[test]
+    fn test_parse_nav_document() {
+        let nav = r#"<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<body>
+    <nav epub:type="landmarks"><ol><li><a href="cover.xhtml">Cover</a></li></ol></nav>
+    <nav epub:type="toc">
+        <ol>
+            <li><a href="ch1.xhtml">Chapter
+                One</a>
+                <ol><li><a href="ch1.xhtml#s1">Section 1</a></li></ol>
+            </li>
+            <li><span>Part Two</span></li>
+        </ol>
+    </nav>
+</body>
+</html>"#;
+
+        let toc = parse_nav_document(nav);
+        assert_eq!(toc.len(), 2);
+        assert_eq!(toc[0].label, "Chapter One");
+        assert_eq!(toc[0].href, "ch1.xhtml");
+        assert_eq!(toc[0].children.len(), 1);
+        assert_eq!(toc[0].children[0].level, 1);
+        assert_eq!(toc[1].label, "Part Two");
+    }
+
+    #[test]
+    fn test_parse_ncx_document() {
+        let ncx = r#"<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/">
+<navMap>
+    <navPoint id="np1" playOrder="1">
+        <navLabel><text>Chapter 1</text></navLabel>
+        <content src="ch1.html"/>
+        <navPoint id="np2" playOrder="2">
+            <navLabel><text>Section 1.1</text></navLabel>
+            <content src="ch1.html#s1"/>
+        </navPoint>
+    </navPoint>
+</navMap>
+</ncx>"#;
+
+        let toc = parse_ncx_document(ncx);
+        assert_eq!(toc.len(), 1);
+        assert_eq!(toc[0].id, "np1");
+        assert_eq!(toc[0].children[0].label, "Section 1.1");
+        assert_eq!(toc[0].children[0].level, 1);
+    }
+
+    #[test]
+    fn test_toc_from_spine_skips_non_linear() {
+        let spine = vec![
+            spine_item("c1.xhtml"),
+            SpineItem {
+                linear: false,
+                ..spine_item("notes.xhtml")
+            },
+        ];
+
+        let toc = toc_from_spine(&spine);
+        assert_eq!(toc.len(), 1);
+        assert_eq!(toc[0].label, "Chapter 1");
+    }
+
+    #[test]
+    fn test_invalid_xml_yields_empty_toc() {
+        assert!(parse_nav_document("<nav><ol>").is_empty());
+        assert!(parse_ncx_document("not xml").is_empty());
+    }
+}
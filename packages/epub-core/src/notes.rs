@@ -0,0 +1,203 @@
+//! Footnotes and endnotes
+//!
+//! A note reference (`epub:type="noteref"`) links to the note by id. The id
+//! is usually on the note itself, an `<aside epub:type="footnote">` or an
+//! `<li epub:type="endnote">`, but older books put it on an anchor or a
+//! paragraph inside the note, or on an anchor at the start of a plain
+//! paragraph. [`extract_note`] finds the element the reference means, so a
+//! reader can show the note in a popup without rendering its chapter.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::markup::{attribute, collapse_whitespace, decode_entities, Token, Tokens};
+
+/// `epub:type` and `role` values (without `doc-`) of a single note
+const NOTE_TYPES: &[&str] = &["footnote", "endnote", "rearnote", "note"];
+
+/// Elements that hold a note's text when the id is on something smaller
+const BLOCK_ELEMENTS: &[&str] = &[
+    "p",
+    "li",
+    "div",
+    "aside",
+    "section",
+    "article",
+    "footer",
+    "blockquote",
+    "dd",
+    "dt",
+    "td",
+    "th",
+    "figure",
+];
+
+/// A note cut out of its document
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct Note {
+    /// The id the reference pointed at
+    pub id: String,
+    /// `footnote`, `endnote`, `rearnote` or `note`, when the note is marked
+    /// as one
+    pub kind: Option<String>,
+    /// Markup inside the note element, as written
+    pub html: String,
+    /// Text of the note, whitespace collapsed
+    pub text: String,
+}
+
+/// An open element
+#[derive(Clone)]
+struct Open {
+    name: String,
+    /// End of its start tag
+    end: usize,
+    /// Note type from `epub:type` or `role`
+    kind: Option<String>,
+}
+
+/// The note with id `id` in a chapter, if the chapter has the id
+///
+/// The note is the nearest element marked as a note around the id, or
+/// else the element with the id when it is a block, or else the nearest
+/// block around it.
+pub fn extract_note(html: &str, id: &str) -> Option<Note> {
+    let mut open: Vec<Open> = Vec::new();
+    // Depth of the note in `open` and its start tag, once the id is found
+    let mut note: Option<(usize, Open)> = None;
+
+    for token in Tokens::new(html) {
+        match token {
+            Token::Start {
+                name,
+                start,
+                end,
+                self_closing,
+            } => {
+                let tag = &html[start..end];
+                let element = Open {
+                    kind: note_kind(tag),
+                    name,
+                    end,
+                };
+                let found = note.is_none() && attribute(tag, "id") == Some(id);
+                open.push(element);
+                if found {
+                    let depth = note_depth(&open);
+                    if self_closing && depth == open.len() - 1 {
+                        // `<a id="n1"/>` with no block around it
+                        return Some(finish(html, id, &open[depth], end, end));
+                    }
+                    note = Some((depth, open[depth].clone()));
+                }
+                if self_closing {
+                    open.pop();
+                }
+            }
+            Token::End { name, start, .. } => {
+                let Some(depth) = open.iter().rposition(|element| element.name == name) else {
+                    continue;
+                };
+                if let Some((note_depth, element)) = &note {
+                    if depth <= *note_depth {
+                        return Some(finish(html, id, element, element.end, start));
+                    }
+                }
+                open.truncate(depth);
+            }
+            Token::Text { .. } => {}
+        }
+    }
+    // An unclosed note runs to the end of the document
+    note.map(|(_, element)| finish(html, id, &element, element.end, html.len()))
+}
+
+/// Position in `open` of the note around its last element, which has the id
+fn note_depth(open: &[Open]) -> usize {
+    let target = open.len() - 1;
+    if let Some(depth) = open.iter().rposition(|element| element.kind.is_some()) {
+        return depth;
+    }
+    if BLOCK_ELEMENTS.contains(&open[target].name.as_str()) {
+        return target;
+    }
+    open.iter()
+        .rposition(|element| BLOCK_ELEMENTS.contains(&element.name.as_str()))
+        .unwrap_or(target)
+}
+
+/// The note type an element is marked with
+fn note_kind(tag: &str) -> Option<String> {
+    ["epub:type", "role"]
+        .into_iter()
+        .filter_map(|name| attribute(tag, name))
+        .flat_map(str::split_whitespace)
+        .map(|t| t.strip_prefix("doc-").unwrap_or(t))
+        .find(|t| NOTE_TYPES.contains(t))
+        .map(str::to_string)
+}
+
+fn finish(html: &str, id: &str, element: &Open, start: usize, end: usize) -> Note {
+    let inner = &html[start..end.max(start)];
+    let mut text = String::new();
+    for token in Tokens::new(inner) {
+        match token {
+            Token::Text { start, end } => text.push_str(&inner[start..end]),
+            // Blocks and line breaks separate words, as in the rendered note
+            Token::Start { name, .. } | Token::End { name, .. }
+                if name == "br" || BLOCK_ELEMENTS.contains(&name.as_str()) =>
+            {
+                text.push(' ')
+            }
+            _ => {}
+        }
+    }
+    Note {
+        id: id.to_string(),
+        kind: element.kind.clone(),
+        html: inner.trim().to_string(),
+        text: collapse_whitespace(&decode_entities(&text)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTES: &str = r##"<html><body>
+<p>Text<a epub:type="noteref" href="#fn1">1</a>.</p>
+<aside epub:type="footnote" id="fn1"><p>A <em>short</em> note &amp; more.</p></aside>
+<ol epub:type="endnotes">
+  <li role="doc-endnote"><p><a id="en2" href="ch1.xhtml#r2">2.</a> An endnote.</p>
+  <p>Second paragraph.</p></li>
+</ol>
+<p><a id="old3"></a>3. An old-style note.</p>
+<div><span id="inline">inline</span></div>
+</body></html>"##;
+
+    #[test]
+    fn test_extract_note() {
+        let note = extract_note(NOTES, "fn1").unwrap();
+        assert_eq!(note.kind.as_deref(), Some("footnote"));
+        assert_eq!(note.html, "<p>A <em>short</em> note &amp; more.</p>");
+        assert_eq!(note.text, "A short note & more.");
+
+        // The id is on an anchor inside the note
+        let note = extract_note(NOTES, "en2").unwrap();
+        assert_eq!(note.kind.as_deref(), Some("endnote"));
+        assert!(note.html.ends_with("<p>Second paragraph.</p>"));
+        assert_eq!(note.text, "2. An endnote. Second paragraph.");
+
+        // No note markup: the paragraph around the anchor
+        let note = extract_note(NOTES, "old3").unwrap();
+        assert_eq!(note.kind, None);
+        assert_eq!(note.text, "3. An old-style note.");
+
+        let note = extract_note(NOTES, "inline").unwrap();
+        assert_eq!(note.html, r#"<span id="inline">inline</span>"#);
+
+        assert_eq!(extract_note(NOTES, "missing"), None);
+    }
+}
@@ -0,0 +1,591 @@
+//! OPF (Open Packaging Format) parser
+//!
+//! Parses the OPF file to extract metadata, manifest, spine, and the location
+//! of the ToC document.
+
+use std::collections::HashMap;
+
+use crate::rendition::{parse_resolution, parse_viewport};
+use crate::{
+    Accessibility, BookMetadata, Creator, EpubParseError, Layout, ManifestItem, PageProgression,
+    PageSpread, Rendition, SpineItem,
+};
+
+/// Parsed OPF structure
+#[derive(Debug, Clone)]
+pub struct Package {
+    pub metadata: BookMetadata,
+    pub manifest: HashMap<String, ManifestItem>,
+    pub spine: Vec<SpineItem>,
+    /// Book-wide layout; spine items may override it
+    pub rendition: Rendition,
+    /// Where the table of contents lives (NAV, NCX, or nowhere)
+    pub toc_doc: TocDocInfo,
+}
+
+/// Information about the ToC document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TocDocInfo {
+    /// EPUB 3 Navigation Document
+    Nav { href: String },
+    /// EPUB 2 NCX Document
+    Ncx { href: String },
+    /// No ToC document found
+    None,
+}
+
+/// Parse an OPF file
+pub fn parse_opf(content: &str) -> Result<Package, EpubParseError> {
+    let doc = roxmltree::Document::parse(content)?;
+
+    let manifest = parse_manifest(&doc);
+    let mut metadata = parse_metadata(&doc);
+    metadata.cover_href = find_cover_href(&doc, &manifest);
+    let spine = parse_spine(&doc, &manifest);
+    let rendition = parse_rendition(&doc);
+    let toc_doc = find_toc_doc(&doc, &manifest);
+
+    Ok(Package {
+        metadata,
+        manifest,
+        spine,
+        rendition,
+        toc_doc,
+    })
+}
+
+fn trimmed_text(node: &roxmltree::Node) -> Option<String> {
+    node.text()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+fn parse_metadata(doc: &roxmltree::Document) -> BookMetadata {
+    let mut metadata = BookMetadata::default();
+
+    let Some(metadata_node) = doc
+        .descendants()
+        .find(|node| node.tag_name().name() == "metadata")
+    else {
+        return metadata;
+    };
+
+    // The package's unique-identifier attribute names the canonical dc:identifier
+    let unique_id = doc.root_element().attribute("unique-identifier");
+
+    for node in metadata_node.descendants().filter(|n| n.is_element()) {
+        match node.tag_name().name() {
+            // Keep the first title (the main title precedes subtitles)
+            "title" if metadata.title.is_empty() => {
+                if let Some(text) = trimmed_text(&node) {
+                    metadata.title = text;
+                }
+            }
+            "creator" => {
+                if let Some(name) = trimmed_text(&node) {
+                    let role = attribute_any_ns(&node, "role");
+                    let file_as = attribute_any_ns(&node, "file-as");
+                    metadata.creators.push(Creator {
+                        name,
+                        role,
+                        file_as,
+                    });
+                }
+            }
+            "language" if metadata.language.is_none() => {
+                metadata.language = trimmed_text(&node);
+            }
+            "identifier" => {
+                let is_unique = unique_id.is_some() && node.attribute("id") == unique_id;
+                if is_unique || metadata.identifier.is_none() {
+                    metadata.identifier = trimmed_text(&node);
+                }
+            }
+            "publisher" => metadata.publisher = trimmed_text(&node),
+            "description" => metadata.description = trimmed_text(&node),
+            "date" if metadata.date.is_none() => {
+                metadata.date = trimmed_text(&node);
+            }
+            "rights" => metadata.rights = trimmed_text(&node),
+            "subject" => {
+                if let Some(subject) = trimmed_text(&node) {
+                    metadata.subjects.push(subject);
+                }
+            }
+            // Refining metas describe another element, not the book
+            "meta" if node.attribute("refines").is_none() => {
+                // EPUB 3 `property` with text content, or EPUB 2 `name` and `content`
+                let property = node.attribute("property").map(|p| (p, trimmed_text(&node)));
+                let named = || {
+                    let name = node.attribute("name")?;
+                    let content = attribute_any_ns(&node, "content");
+                    Some((name, content))
+                };
+                if let Some((property, Some(value))) = property.or_else(named) {
+                    add_accessibility(&mut metadata.accessibility, property, value);
+                }
+            }
+            // EPUB Accessibility 1.0 links to the specification it conforms to
+            "link" if node.attribute("rel") == Some("dcterms:conformsTo") => {
+                if let Some(href) = attribute_any_ns(&node, "href") {
+                    metadata.accessibility.conforms_to.push(href);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    metadata
+}
+
+/// Record an accessibility property; other properties are ignored
+fn add_accessibility(accessibility: &mut Accessibility, property: &str, value: String) {
+    let property = property.strip_prefix("schema:").unwrap_or(property);
+    match property {
+        "accessMode" => accessibility.access_modes.push(value),
+        "accessModeSufficient" => accessibility.access_modes_sufficient.push(
+            value
+                .split(',')
+                .map(|mode| mode.trim().to_string())
+                .filter(|mode| !mode.is_empty())
+                .collect(),
+        ),
+        "accessibilityFeature" => accessibility.features.push(value),
+        "accessibilityHazard" => accessibility.hazards.push(value),
+        "accessibilitySummary" => accessibility.summary = Some(value),
+        "accessibilityControl" => accessibility.controls.push(value),
+        "accessibilityAPI" => accessibility.apis.push(value),
+        "dcterms:conformsTo" => accessibility.conforms_to.push(value),
+        "a11y:certifiedBy" => accessibility.certified_by = Some(value),
+        _ => {}
+    }
+}
+
+/// Read an attribute regardless of namespace (opf:role vs role)
+fn attribute_any_ns(node: &roxmltree::Node, name: &str) -> Option<String> {
+    node.attributes()
+        .find(|a| a.name() == name)
+        .map(|a| a.value().trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+fn parse_manifest(doc: &roxmltree::Document) -> HashMap<String, ManifestItem> {
+    let mut manifest = HashMap::new();
+
+    for node in doc.descendants() {
+        if node.tag_name().name() == "item" {
+            if let (Some(id), Some(href), Some(media_type)) = (
+                node.attribute("id"),
+                node.attribute("href"),
+                node.attribute("media-type"),
+            ) {
+                let properties = node.attribute("properties").map(|s| s.to_string());
+
+                manifest.insert(
+                    id.to_string(),
+                    ManifestItem {
+                        id: id.to_string(),
+                        href: href.to_string(),
+                        media_type: media_type.to_string(),
+                        properties,
+                    },
+                );
+            }
+        }
+    }
+
+    manifest
+}
+
+fn parse_spine(
+    doc: &roxmltree::Document,
+    manifest: &HashMap<String, ManifestItem>,
+) -> Vec<SpineItem> {
+    let mut spine = Vec::new();
+
+    for node in doc.descendants() {
+        if node.tag_name().name() == "itemref" {
+            if let Some(item) = node.attribute("idref").and_then(|idref| manifest.get(idref)) {
+                let linear = node.attribute("linear").map(|s| s != "no").unwrap_or(true);
+                let properties = node.attribute("properties").unwrap_or_default();
+
+                let mut layout = None;
+                let mut page_spread = None;
+                let mut spread = None;
+                for property in properties.split_whitespace() {
+                    match property {
+                        "rendition:layout-pre-paginated" => layout = Some(Layout::PrePaginated),
+                        "rendition:layout-reflowable" => layout = Some(Layout::Reflowable),
+                        "page-spread-left" | "rendition:page-spread-left" => {
+                            page_spread = Some(PageSpread::Left)
+                        }
+                        "page-spread-right" | "rendition:page-spread-right" => {
+                            page_spread = Some(PageSpread::Right)
+                        }
+                        "rendition:page-spread-center" => page_spread = Some(PageSpread::Center),
+                        // `portrait` is deprecated and read as `both`
+                        "rendition:spread-portrait" => spread = Some("both".to_string()),
+                        _ => {
+                            if let Some(value) = property.strip_prefix("rendition:spread-") {
+                                spread = Some(value.to_string());
+                            }
+                        }
+                    }
+                }
+
+                spine.push(SpineItem {
+                    id: item.id.clone(),
+                    href: item.href.clone(),
+                    media_type: item.media_type.clone(),
+                    linear,
+                    layout,
+                    page_spread,
+                    spread,
+                    // Only known once the item's markup is read
+                    viewport: None,
+                });
+            }
+        }
+    }
+
+    spine
+}
+
+/// Read the book-wide `rendition:*` properties and the spine's
+/// `page-progression-direction`
+///
+/// Kindle-style `fixed-layout` and `original-resolution` metas are honoured
+/// when the EPUB 3 properties are absent, and `primary-writing-mode` is kept
+/// for books that only declare their direction that way.
+fn parse_rendition(doc: &roxmltree::Document) -> Rendition {
+    let mut rendition = Rendition::default();
+    let mut fixed_layout = false;
+    let mut layout = None;
+    let mut resolution = None;
+
+    let metas = doc
+        .descendants()
+        .filter(|node| node.tag_name().name() == "meta")
+        // Refining metas describe a single item, not the book
+        .filter(|node| node.attribute("refines").is_none());
+    for node in metas {
+        if let Some(property) = node.attribute("property") {
+            let Some(value) = trimmed_text(&node) else {
+                continue;
+            };
+            match property {
+                "rendition:layout" => {
+                    layout = match value.as_str() {
+                        "pre-paginated" => Some(Layout::PrePaginated),
+                        "reflowable" => Some(Layout::Reflowable),
+                        _ => layout,
+                    }
+                }
+                "rendition:orientation" => rendition.orientation = Some(value),
+                "rendition:spread" => rendition.spread = Some(value),
+                "rendition:viewport" => rendition.viewport = parse_viewport(&value),
+                _ => {}
+            }
+        } else {
+            let content = node.attribute("content").unwrap_or_default();
+            match node.attribute("name") {
+                Some("fixed-layout") => fixed_layout = content.trim() == "true",
+                Some("original-resolution") => resolution = parse_resolution(content),
+                Some("primary-writing-mode") => {
+                    rendition.writing_mode = Some(content.trim().to_string())
+                }
+                _ => {}
+            }
+        }
+    }
+
+    rendition.layout = layout.unwrap_or(if fixed_layout {
+        Layout::PrePaginated
+    } else {
+        Layout::Reflowable
+    });
+    rendition.viewport = rendition.viewport.or(resolution);
+    let direction = doc
+        .descendants()
+        .find(|node| node.tag_name().name() == "spine")
+        .and_then(|spine| spine.attribute("page-progression-direction"));
+    rendition.page_progression_direction = match direction {
+        Some("ltr") => Some(PageProgression::Ltr),
+        Some("rtl") => Some(PageProgression::Rtl),
+        _ => None,
+    };
+    rendition
+}
+
+/// Find the cover image: EPUB 3 `cover-image` property, then EPUB 2 `<meta name="cover">`
+fn find_cover_href(
+    doc: &roxmltree::Document,
+    manifest: &HashMap<String, ManifestItem>,
+) -> Option<String> {
+    if let Some(item) = manifest.values().find(|item| item.has_property("cover-image")) {
+        return Some(item.href.clone());
+    }
+
+    doc.descendants()
+        .filter(|node| node.tag_name().name() == "meta")
+        .find(|node| node.attribute("name") == Some("cover"))
+        .and_then(|node| node.attribute("content"))
+        .and_then(|id| manifest.get(id))
+        .map(|item| item.href.clone())
+}
+
+/// Find the ToC document (NAV or NCX)
+pub fn find_toc_doc(
+    doc: &roxmltree::Document,
+    manifest: &HashMap<String, ManifestItem>,
+) -> TocDocInfo {
+    // Try to find NAV (EPUB 3) first
+    if let Some(item) = manifest.values().find(|item| item.has_property("nav")) {
+        return TocDocInfo::Nav {
+            href: item.href.clone(),
+        };
+    }
+
+    // Fall back to NCX (EPUB 2), referenced by the spine's toc attribute
+    let ncx = doc
+        .descendants()
+        .find(|node| node.tag_name().name() == "spine")
+        .and_then(|spine| spine.attribute("toc"))
+        .and_then(|toc_id| manifest.get(toc_id))
+        // Some EPUB 2 books omit the toc attribute; look for the NCX media type
+        .or_else(|| {
+            manifest
+                .values()
+                .find(|item| item.media_type == "application/x-dtbncx+xml")
+        });
+
+    match ncx {
+        Some(item) => TocDocInfo::Ncx {
+            href: item.href.clone(),
+        },
+        None => TocDocInfo::None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPF3: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="uid">
+    <metadata xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:opf="http://www.idpf.org/2007/opf">
+        <dc:identifier id="isbn">978-0000000000</dc:identifier>
+        <dc:identifier id="uid">urn:uuid:1234</dc:identifier>
+        <dc:title>Test Book</dc:title>
+        <dc:title>A Subtitle</dc:title>
+        <dc:creator opf:role="aut" opf:file-as="Author, Test">Test Author</dc:creator>
+        <dc:language>en</dc:language>
+        <dc:subject>Fiction</dc:subject>
+        <dc:date>2020-01-01</dc:date>
+    </metadata>
+    <manifest>
+        <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+        <item id="cover" href="images/cover.jpg" media-type="image/jpeg" properties="cover-image"/>
+        <item id="chapter1" href="chapter1.xhtml" media-type="application/xhtml+xml"/>
+        <item id="notes" href="notes.xhtml" media-type="application/xhtml+xml"/>
+    </manifest>
+    <spine>
+        <itemref idref="chapter1"/>
+        <itemref idref="notes" linear="no"/>
+    </spine>
+</package>"#;
+
+    #[test]
+    fn test_parse_simple_opf() {
+        let opf = r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+    <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+        <dc:title>Test Book</dc:title>
+        <dc:creator>Test Author</dc:creator>
+        <dc:language>en</dc:language>
+    </metadata>
+    <manifest>
+        <item id="chapter1" href="chapter1.xhtml" media-type="application/xhtml+xml"/>
+    </manifest>
+    <spine>
+        <itemref idref="chapter1"/>
+    </spine>
+</package>"#;
+
+        let parsed = parse_opf(opf).unwrap();
+        assert_eq!(parsed.metadata.title, "Test Book");
+        assert_eq!(parsed.spine.len(), 1);
+        assert_eq!(parsed.toc_doc, TocDocInfo::None);
+    }
+
+    #[test]
+    fn test_parse_epub3_metadata() {
+        let parsed = parse_opf(OPF3).unwrap();
+        let metadata = &parsed.metadata;
+
+        assert_eq!(metadata.title, "Test Book");
+        assert_eq!(metadata.identifier.as_deref(), Some("urn:uuid:1234"));
+        assert_eq!(metadata.creators[0].role.as_deref(), Some("aut"));
+        assert_eq!(metadata.creators[0].file_as.as_deref(), Some("Author, Test"));
+        assert_eq!(metadata.cover_href.as_deref(), Some("images/cover.jpg"));
+        assert_eq!(metadata.subjects, vec!["Fiction".to_string()]);
+        assert_eq!(metadata.date.as_deref(), Some("2020-01-01"));
+    }
+
+    #[test]
+    fn test_parse_accessibility() {
+        let opf = r##"<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+    <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+        <dc:title>Accessible</dc:title>
+        <meta property="schema:accessMode">textual</meta>
+        <meta property="schema:accessMode">visual</meta>
+        <meta property="schema:accessModeSufficient">textual,visual</meta>
+        <meta property="schema:accessModeSufficient">textual</meta>
+        <meta property="schema:accessibilityFeature">structuralNavigation</meta>
+        <meta property="schema:accessibilityFeature">alternativeText</meta>
+        <meta property="schema:accessibilityHazard">none</meta>
+        <meta property="schema:accessibilitySummary">All images have alt text.</meta>
+        <meta property="dcterms:conformsTo">EPUB Accessibility 1.1 - WCAG 2.1 Level AA</meta>
+        <meta property="a11y:certifiedBy">Example Certifier</meta>
+        <meta refines="#certifier" property="a11y:certifierCredential">Ignored</meta>
+        <meta name="schema:accessibilityControl" content="fullKeyboardControl"/>
+        <link rel="dcterms:conformsTo" href="http://www.idpf.org/epub/a11y/accessibility-20170105.html#wcag-aa"/>
+    </metadata>
+    <manifest/>
+    <spine/>
+</package>"##;
+
+        let accessibility = parse_opf(opf).unwrap().metadata.accessibility;
+        assert_eq!(accessibility.access_modes, vec!["textual", "visual"]);
+        assert_eq!(
+            accessibility.access_modes_sufficient,
+            vec![vec!["textual", "visual"], vec!["textual"]]
+        );
+        assert_eq!(
+            accessibility.features,
+            vec!["structuralNavigation", "alternativeText"]
+        );
+        assert_eq!(accessibility.hazards, vec!["none"]);
+        assert_eq!(
+            accessibility.summary.as_deref(),
+            Some("All images have alt text.")
+        );
+        assert_eq!(accessibility.controls, vec!["fullKeyboardControl"]);
+        assert_eq!(accessibility.conforms_to.len(), 2);
+        assert_eq!(
+            accessibility.certified_by.as_deref(),
+            Some("Example Certifier")
+        );
+        assert!(accessibility.is_textual());
+
+        let none = parse_opf(OPF3).unwrap().metadata.accessibility;
+        assert_eq!(none, Default::default());
+        assert!(!none.is_textual());
+    }
+
+    #[test]
+    fn test_parse_spine_linear() {
+        let parsed = parse_opf(OPF3).unwrap();
+        assert_eq!(parsed.spine.len(), 2);
+        assert!(parsed.spine[0].linear);
+        assert!(!parsed.spine[1].linear);
+    }
+
+    #[test]
+    fn test_parse_rendition() {
+        let opf = r##"<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+    <metadata>
+        <meta property="rendition:layout">pre-paginated</meta>
+        <meta property="rendition:spread">landscape</meta>
+        <meta property="rendition:viewport">width=1200, height=1600</meta>
+        <meta refines="#p2" property="rendition:layout">reflowable</meta>
+    </metadata>
+    <manifest>
+        <item id="p1" href="p1.xhtml" media-type="application/xhtml+xml"/>
+        <item id="p2" href="p2.xhtml" media-type="application/xhtml+xml"/>
+    </manifest>
+    <spine page-progression-direction="rtl">
+        <itemref idref="p1" properties="page-spread-right rendition:spread-none"/>
+        <itemref idref="p2" properties="rendition:layout-reflowable rendition:page-spread-center"/>
+    </spine>
+</package>"##;
+
+        let parsed = parse_opf(opf).unwrap();
+        let rendition = &parsed.rendition;
+        assert_eq!(rendition.layout, Layout::PrePaginated);
+        assert_eq!(rendition.spread.as_deref(), Some("landscape"));
+        assert_eq!(rendition.orientation, None);
+        assert_eq!(rendition.page_progression(), PageProgression::Rtl);
+        assert_eq!(
+            rendition.viewport,
+            Some(crate::Viewport {
+                width: 1200,
+                height: 1600
+            })
+        );
+
+        assert_eq!(parsed.spine[0].page_spread, Some(PageSpread::Right));
+        assert_eq!(parsed.spine[0].spread.as_deref(), Some("none"));
+        assert_eq!(parsed.spine[0].layout_in(rendition), Layout::PrePaginated);
+        assert_eq!(parsed.spine[1].page_spread, Some(PageSpread::Center));
+        assert_eq!(parsed.spine[1].spread, None);
+        assert_eq!(parsed.spine[1].layout_in(rendition), Layout::Reflowable);
+
+        // Reflowable unless declared otherwise
+        assert_eq!(parse_opf(OPF3).unwrap().rendition, Rendition::default());
+    }
+
+    #[test]
+    fn test_parse_kindle_fixed_layout() {
+        let opf = r#"<package xmlns="http://www.idpf.org/2007/opf" version="2.0">
+    <metadata>
+        <meta name="fixed-layout" content="true"/>
+        <meta name="original-resolution" content="1072x1448"/>
+        <meta name="primary-writing-mode" content="vertical-rl"/>
+    </metadata>
+    <manifest/>
+    <spine/>
+</package>"#;
+
+        let rendition = parse_opf(opf).unwrap().rendition;
+        assert_eq!(rendition.layout, Layout::PrePaginated);
+        assert_eq!(
+            rendition.viewport.map(|v| (v.width, v.height)),
+            Some((1072, 1448))
+        );
+        // Vertical Japanese text turns right to left
+        assert_eq!(rendition.writing_mode.as_deref(), Some("vertical-rl"));
+        assert_eq!(rendition.page_progression(), PageProgression::Rtl);
+    }
+
+    #[test]
+    fn test_find_nav_doc() {
+        let parsed = parse_opf(OPF3).unwrap();
+        assert_eq!(
+            parsed.toc_doc,
+            TocDocInfo::Nav {
+                href: "nav.xhtml".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_find_ncx_doc() {
+        let opf = r#"<package xmlns="http://www.idpf.org/2007/opf" version="2.0">
+    <metadata/>
+    <manifest>
+        <item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>
+        <item id="c1" href="c1.html" media-type="application/xhtml+xml"/>
+    </manifest>
+    <spine toc="ncx"><itemref idref="c1"/></spine>
+</package>"#;
+
+        let parsed = parse_opf(opf).unwrap();
+        assert_eq!(
+            parsed.toc_doc,
+            TocDocInfo::Ncx {
+                href: "toc.ncx".to_string()
+            }
+        );
+    }
+}
@@ -0,0 +1,133 @@
+//! Resource path resolution
+//!
+//! Manifest, spine, and ToC hrefs are relative to the document that contains
+//! them (usually the OPF) and may be percent-encoded. Archive entries are
+//! plain, `/`-separated paths from the archive root. These helpers bridge the
+//! two.
+
+/// Directory containing the OPF file ("" when the OPF is at the archive root)
+pub fn opf_dir(opf_path: &str) -> String {
+    parent_dir(opf_path).to_string()
+}
+
+/// Directory part of an archive path ("" for top-level entries)
+pub fn parent_dir(path: &str) -> &str {
+    path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("")
+}
+
+/// Strip a URL fragment (`chapter1.xhtml#section1` → `chapter1.xhtml`)
+pub fn strip_fragment(href: &str) -> &str {
+    href.split('#').next().unwrap_or(href)
+}
+
+/// Normalize a file path by removing redundant components.
+///
+/// `.` and empty components are dropped, `..` pops the previous component
+/// (never escaping the archive root), and backslashes are treated as separators.
+pub fn normalize_path(path: &str) -> String {
+    let mut components: Vec<&str> = Vec::new();
+
+    for component in path.split(['/', '\\']) {
+        match component {
+            "" | "." => continue,
+            ".." => {
+                components.pop();
+            }
+            _ => components.push(component),
+        }
+    }
+
+    components.join("/")
+}
+
+/// Decode `%XX` escapes; malformed escapes are kept verbatim
+pub fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(byte) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8(decoded).unwrap_or_else(|_| input.to_string())
+}
+
+/// Percent-encode a path for use in a URL; `/` and unreserved characters
+/// are kept
+pub fn percent_encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'/' | b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Resolve an href relative to `base_dir` into a normalized archive path.
+///
+/// The fragment is dropped and percent-escapes are decoded, so the result can
+/// be used directly as an archive entry name.
+pub fn resolve_href(base_dir: &str, href: &str) -> String {
+    let href = percent_decode(strip_fragment(href));
+
+    if base_dir.is_empty() || href.starts_with('/') {
+        normalize_path(&href)
+    } else {
+        normalize_path(&format!("{}/{}", base_dir, href))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path("a/b/c"), "a/b/c");
+        assert_eq!(normalize_path("a//b/c"), "a/b/c");
+        assert_eq!(normalize_path("a/./b/c"), "a/b/c");
+        assert_eq!(normalize_path("./a/b"), "a/b");
+        assert_eq!(normalize_path("a\\b\\c"), "a/b/c");
+        assert_eq!(normalize_path("a/b/../c"), "a/c");
+        assert_eq!(normalize_path("../../a"), "a");
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("chapter%201.xhtml"), "chapter 1.xhtml");
+        assert_eq!(percent_decode("caf%C3%A9.css"), "café.css");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz"), "%zz");
+
+        let encoded = percent_encode_path("Images/café #1.png");
+        assert_eq!(encoded, "Images/caf%C3%A9%20%231.png");
+        assert_eq!(percent_decode(&encoded), "Images/café #1.png");
+    }
+
+    #[test]
+    fn test_resolve_href() {
+        assert_eq!(resolve_href("OEBPS", "Text/ch1.xhtml"), "OEBPS/Text/ch1.xhtml");
+        assert_eq!(resolve_href("OEBPS/Text", "../Images/a%20b.png"), "OEBPS/Images/a b.png");
+        assert_eq!(resolve_href("", "ch1.xhtml#s1"), "ch1.xhtml");
+        assert_eq!(resolve_href("OEBPS", "/META-INF/container.xml"), "META-INF/container.xml");
+    }
+
+    #[test]
+    fn test_opf_dir() {
+        assert_eq!(opf_dir("OEBPS/content.opf"), "OEBPS");
+        assert_eq!(opf_dir("content.opf"), "");
+    }
+}
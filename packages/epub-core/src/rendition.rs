@@ -0,0 +1,155 @@
+//! Fixed-layout page sizes
+//!
+//! Pre-paginated XHTML pages give their size in a viewport meta tag
+//! (`<meta name="viewport" content="width=1200, height=1600"/>`) and SVG
+//! pages in the root element's `viewBox` or `width`/`height`. The OPF parser
+//! reads the book-wide rendition properties; callers holding a page's markup
+//! use [`find_viewport`] for the page's own size.
+
+use crate::markup::{attribute, Token, Tokens};
+use crate::Viewport;
+
+/// Parse viewport meta content such as `width=1200, height=1600`
+///
+/// Values relative to the device (`device-width`) give no page size.
+pub fn parse_viewport(content: &str) -> Option<Viewport> {
+    let mut width = None;
+    let mut height = None;
+
+    for pair in content.split([',', ';']) {
+        let Some((key, value)) = pair.split_once('=') else {
+            continue;
+        };
+        match key.trim().to_ascii_lowercase().as_str() {
+            "width" => width = dimension(value),
+            "height" => height = dimension(value),
+            _ => {}
+        }
+    }
+
+    Some(Viewport {
+        width: width?,
+        height: height?,
+    })
+}
+
+/// Parse a `WIDTHxHEIGHT` resolution, as in Kindle's `original-resolution`
+pub fn parse_resolution(content: &str) -> Option<Viewport> {
+    let (width, height) = content.split_once(['x', 'X'])?;
+    Some(Viewport {
+        width: dimension(width)?,
+        height: dimension(height)?,
+    })
+}
+
+/// The page size a chapter document declares
+///
+/// Looks for a viewport meta tag before `<body>`, or the size of an SVG
+/// document's root element.
+pub fn find_viewport(markup: &str) -> Option<Viewport> {
+    for token in Tokens::new(markup) {
+        let Token::Start {
+            name, start, end, ..
+        } = token
+        else {
+            continue;
+        };
+        let tag = &markup[start..end];
+
+        match name.as_str() {
+            "meta"
+                if attribute(tag, "name").is_some_and(|n| n.eq_ignore_ascii_case("viewport")) =>
+            {
+                if let Some(viewport) = attribute(tag, "content").and_then(parse_viewport) {
+                    return Some(viewport);
+                }
+            }
+            "body" => return None,
+            name if name == "svg" || name.ends_with(":svg") => return svg_viewport(tag),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Size of an `<svg>` element: its `viewBox`, else `width` and `height`
+fn svg_viewport(tag: &str) -> Option<Viewport> {
+    let view_box = attribute(tag, "viewBox").and_then(|view_box| {
+        let numbers: Vec<&str> = view_box
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|n| !n.is_empty())
+            .collect();
+        match numbers[..] {
+            [_, _, width, height] => Some(Viewport {
+                width: dimension(width)?,
+                height: dimension(height)?,
+            }),
+            _ => None,
+        }
+    });
+
+    view_box.or_else(|| {
+        Some(Viewport {
+            width: dimension(attribute(tag, "width")?)?,
+            height: dimension(attribute(tag, "height")?)?,
+        })
+    })
+}
+
+/// A positive length in pixels, rounded; `px` is allowed, other units are not
+fn dimension(value: &str) -> Option<u32> {
+    let value = value.trim();
+    let number: f64 = value
+        .strip_suffix("px")
+        .unwrap_or(value)
+        .trim()
+        .parse()
+        .ok()?;
+    (number.is_finite() && number >= 0.5 && number <= u32::MAX as f64)
+        .then(|| number.round() as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn viewport(width: u32, height: u32) -> Option<Viewport> {
+        Some(Viewport { width, height })
+    }
+
+    #[test]
+    fn test_parse_viewport() {
+        assert_eq!(
+            parse_viewport("width=1200, height=1600"),
+            viewport(1200, 1600)
+        );
+        assert_eq!(
+            parse_viewport("height = 800px; width = 600.4"),
+            viewport(600, 800)
+        );
+        assert_eq!(parse_viewport("width=device-width, initial-scale=1"), None);
+        assert_eq!(parse_viewport("width=600"), None);
+        assert_eq!(parse_resolution("1072x1448"), viewport(1072, 1448));
+        assert_eq!(parse_resolution("1072"), None);
+    }
+
+    #[test]
+    fn test_find_viewport() {
+        let xhtml = r#"<?xml version="1.0"?>
+<html xmlns="http://www.w3.org/1999/xhtml">
+<head><title>p1</title><meta name="viewport" content="width=1024,height=768"/></head>
+<body><img src="p1.jpg"/></body></html>"#;
+        assert_eq!(find_viewport(xhtml), viewport(1024, 768));
+
+        // Meta tags in the body and inline SVG illustrations do not count
+        let reflowable = r#"<html><head></head><body>
+<meta name="viewport" content="width=1,height=1"/><svg viewBox="0 0 10 10"/></body></html>"#;
+        assert_eq!(find_viewport(reflowable), None);
+
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 595.3 841.9"></svg>"#;
+        assert_eq!(find_viewport(svg), viewport(595, 842));
+        let svg = r#"<svg:svg width="300px" height="400"></svg:svg>"#;
+        assert_eq!(find_viewport(svg), viewport(300, 400));
+        assert_eq!(find_viewport(r#"<svg width="100%" height="100%"/>"#), None);
+    }
+}
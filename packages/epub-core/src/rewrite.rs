@@ -0,0 +1,579 @@
+//! Chapter markup rewriting for injection into a reader DOM
+//!
+//! Chapter XHTML refers to images, stylesheets and other chapters by URLs
+//! relative to the chapter file, which break once the markup is inserted
+//! into a page with a different base URL. [`rewrite_urls`] resolves them to
+//! archive paths and lets the caller map each path to a URL it can serve.
+//! [`sanitize_html`] removes scripts, embedded documents, event handler
+//! attributes and `javascript:` URLs. [`rewrite_links`] and [`prefix_ids`] let chapters be
+//! merged into one document, with links between them turned into links
+//! within it.
+//!
+//! Both work on the tag scanner's byte ranges, so everything they do not
+//! touch (comments, whitespace, entities, attribute order) is kept as is.
+
+use crate::markup::{attributes, Attribute, Token, Tokens};
+use crate::path::resolve_href;
+
+/// Attributes holding a single URL
+const URL_ATTRIBUTES: &[&str] = &["src", "href", "xlink:href", "poster", "data"];
+
+/// URL schemes that run code
+const SCRIPT_SCHEMES: &[&str] = &["javascript:", "vbscript:"];
+
+/// Elements that load another document or change where URLs resolve; their
+/// tags are dropped and their fallback content kept
+const EMBEDDING_ELEMENTS: &[&str] = &["iframe", "object", "embed", "base", "frame", "frameset"];
+
+/// Named character references that may spell out a URL scheme
+const SCHEME_ENTITIES: &[(&str, char)] = &[
+    ("amp", '&'),
+    ("colon", ':'),
+    ("Tab", '\t'),
+    ("NewLine", '\n'),
+    ("nbsp", '\u{a0}'),
+];
+
+/// What to do with an attribute
+enum Edit {
+    Keep,
+    Remove,
+    Replace(String),
+}
+
+/// Rewrite relative URLs in chapter markup
+///
+/// `base_dir` is the chapter's directory in the archive. Each relative URL
+/// in a URL attribute, `srcset`, `style` attribute or `<style>` element is
+/// resolved to an archive path and replaced by `map_url(path)`, with the
+/// original fragment appended. Absolute URLs and fragment-only links are
+/// left alone.
+pub fn rewrite_urls(html: &str, base_dir: &str, map_url: impl Fn(&str) -> String) -> String {
+    rewrite_links(html, base_dir, |path, fragment| {
+        let mut mapped = map_url(path);
+        if let Some(fragment) = fragment {
+            mapped.push('#');
+            mapped.push_str(fragment);
+        }
+        mapped
+    })
+}
+
+/// Rewrite relative URLs in chapter markup, fragments included
+///
+/// As [`rewrite_urls`], but `map_link(path, fragment)` builds the whole
+/// replacement URL, so a link into another chapter can become a link to
+/// an id in the same document.
+pub fn rewrite_links(
+    html: &str,
+    base_dir: &str,
+    map_link: impl Fn(&str, Option<&str>) -> String,
+) -> String {
+    let rewrite = |url: &str| resolve_relative(url, base_dir, &map_link);
+
+    let mut out = String::with_capacity(html.len());
+    let mut copied = 0;
+    let mut style_content: Option<usize> = None;
+
+    for token in Tokens::new(html) {
+        match token {
+            Token::Start {
+                name,
+                start,
+                end,
+                self_closing,
+            } => {
+                let edited = edit_tag(&html[start..end], |attr| {
+                    let name = attr.name.to_ascii_lowercase();
+                    let value = if URL_ATTRIBUTES.contains(&name.as_str()) {
+                        rewrite(attr.value)
+                    } else if name == "srcset" {
+                        rewrite_srcset(attr.value, &rewrite)
+                    } else if name == "style" {
+                        rewrite_css(attr.value, &rewrite)
+                    } else {
+                        None
+                    };
+                    value.map_or(Edit::Keep, Edit::Replace)
+                });
+                if let Some(tag) = edited {
+                    out.push_str(&html[copied..start]);
+                    out.push_str(&tag);
+                    copied = end;
+                }
+                if name == "style" && !self_closing {
+                    style_content = Some(end);
+                }
+            }
+            Token::End { name, start, .. } if name == "style" => {
+                if let Some(content_start) = style_content.take() {
+                    if let Some(css) = rewrite_css(&html[content_start..start], &rewrite) {
+                        out.push_str(&html[copied..content_start]);
+                        out.push_str(&css);
+                        copied = start;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    out.push_str(&html[copied..]);
+    out
+}
+
+/// Rewrite the relative `url(...)` references in a stylesheet
+///
+/// `base_dir` is the stylesheet's directory in the archive; each relative
+/// URL is resolved to an archive path and replaced by `map_url(path)`.
+pub fn rewrite_css_urls(css: &str, base_dir: &str, map_url: impl Fn(&str) -> String) -> String {
+    let rewrite = |url: &str| resolve_relative(url, base_dir, &|path, _| map_url(path));
+    rewrite_css(css, &rewrite).unwrap_or_else(|| css.to_string())
+}
+
+/// Prefix the ids in a chapter, and the links and references to them
+///
+/// Ids must be unique in a document, so chapters merged into one get a
+/// prefix each. `id` attributes, fragment-only links (`href="#note"`) and
+/// the id lists of `aria-labelledby` and `aria-describedby` are prefixed.
+/// Prefix before [`rewrite_links`] turns links to other chapters into
+/// fragment-only links, or those are prefixed too.
+pub fn prefix_ids(html: &str, prefix: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut copied = 0;
+
+    for token in Tokens::new(html) {
+        let Token::Start { start, end, .. } = token else {
+            continue;
+        };
+        let edited = edit_tag(&html[start..end], |attr| {
+            let name = attr.name.to_ascii_lowercase();
+            let value = match name.as_str() {
+                "id" if !attr.value.is_empty() => Some(format!("{}{}", prefix, attr.value)),
+                "href" | "xlink:href" => attr
+                    .value
+                    .trim()
+                    .strip_prefix('#')
+                    .filter(|id| !id.is_empty())
+                    .map(|id| format!("#{}{}", prefix, id)),
+                "aria-labelledby" | "aria-describedby" if !attr.value.trim().is_empty() => Some(
+                    attr.value
+                        .split_whitespace()
+                        .map(|id| format!("{}{}", prefix, id))
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                ),
+                _ => None,
+            };
+            value.map_or(Edit::Keep, Edit::Replace)
+        });
+        if let Some(tag) = edited {
+            out.push_str(&html[copied..start]);
+            out.push_str(&tag);
+            copied = end;
+        }
+    }
+
+    out.push_str(&html[copied..]);
+    out
+}
+
+/// Remove scripts, embedded documents, event handler attributes and script
+/// URLs
+pub fn sanitize_html(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut copied = 0;
+    // Start of a `<script>` element whose end tag has not been seen
+    let mut script_start: Option<usize> = None;
+
+    for token in Tokens::new(html) {
+        match token {
+            Token::Start {
+                name,
+                start,
+                end,
+                self_closing,
+            } => {
+                if script_start.is_some() {
+                    continue;
+                }
+                if is_script(&name) {
+                    out.push_str(&html[copied..start]);
+                    copied = end;
+                    if !self_closing {
+                        script_start = Some(start);
+                    }
+                    continue;
+                }
+                if is_embedding(&name) {
+                    out.push_str(&html[copied..start]);
+                    copied = end;
+                    continue;
+                }
+
+                let edited = edit_tag(&html[start..end], |attr| {
+                    let name = attr.name.to_ascii_lowercase();
+                    let handler = name.len() > 2 && name.starts_with("on");
+                    let script_url = (URL_ATTRIBUTES.contains(&name.as_str())
+                        || name == "action"
+                        || name == "formaction")
+                        && is_script_url(attr.value);
+                    if handler || script_url || name == "srcdoc" {
+                        Edit::Remove
+                    } else {
+                        Edit::Keep
+                    }
+                });
+                if let Some(tag) = edited {
+                    out.push_str(&html[copied..start]);
+                    out.push_str(&tag);
+                    copied = end;
+                }
+            }
+            Token::End { name, end, .. } if is_script(&name) && script_start.is_some() => {
+                script_start = None;
+                copied = end;
+            }
+            Token::End { name, start, end } if is_embedding(&name) && script_start.is_none() => {
+                out.push_str(&html[copied..start]);
+                copied = end;
+            }
+            _ => {}
+        }
+    }
+
+    // An unclosed script runs to the end of the document
+    if script_start.is_none() {
+        out.push_str(&html[copied..]);
+    }
+    out
+}
+
+fn is_script(name: &str) -> bool {
+    name == "script" || name.ends_with(":script")
+}
+
+fn is_embedding(name: &str) -> bool {
+    let local = name.rsplit(':').next().unwrap_or(name);
+    EMBEDDING_ELEMENTS.contains(&local)
+}
+
+/// Whether a URL runs code, ignoring the whitespace browsers ignore
+///
+/// The attribute value is raw markup, so character references are decoded
+/// first, the way a browser would before looking at the scheme.
+fn is_script_url(url: &str) -> bool {
+    let url: String = decode_references(url)
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .take(16)
+        .collect::<String>()
+        .to_ascii_lowercase();
+    SCRIPT_SCHEMES.iter().any(|scheme| url.starts_with(scheme))
+}
+
+/// Decode the character references of an attribute value
+///
+/// Numeric references are decoded with or without their `;`, as HTML
+/// parsers do. Named references outside [`SCHEME_ENTITIES`] are left as
+/// written, since they cannot spell out a scheme.
+fn decode_references(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp + 1..];
+
+        if let Some(number) = rest.strip_prefix('#') {
+            let (digits, radix) = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => (hex, 16),
+                None => (number, 10),
+            };
+            let len = digits
+                .find(|c: char| !c.is_digit(radix))
+                .unwrap_or(digits.len());
+            if len > 0 {
+                let c = u32::from_str_radix(&digits[..len], radix)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .unwrap_or(char::REPLACEMENT_CHARACTER);
+                out.push(c);
+                let after = &digits[len..];
+                rest = after.strip_prefix(';').unwrap_or(after);
+                continue;
+            }
+        } else if let Some((name, c)) = SCHEME_ENTITIES
+            .iter()
+            .find(|(name, _)| rest.starts_with(name) && rest[name.len()..].starts_with(';'))
+        {
+            out.push(*c);
+            rest = &rest[name.len() + 1..];
+            continue;
+        }
+        out.push('&');
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Whether a URL is relative to the document (and not just a fragment)
+pub(crate) fn is_relative(url: &str) -> bool {
+    if url.is_empty() || url.starts_with('#') || url.starts_with("//") {
+        return false;
+    }
+    let Some(colon) = url.find(':') else {
+        return true;
+    };
+    let scheme = &url[..colon];
+    let is_scheme = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    !is_scheme
+}
+
+/// Resolve a relative URL against `base_dir` and map it; `None` for URLs
+/// that are not relative
+fn resolve_relative(
+    url: &str,
+    base_dir: &str,
+    map_link: &impl Fn(&str, Option<&str>) -> String,
+) -> Option<String> {
+    let url = url.trim();
+    if !is_relative(url) {
+        return None;
+    }
+    let (path, fragment) = match url.split_once('#') {
+        Some((path, fragment)) => (path, Some(fragment)),
+        None => (url, None),
+    };
+    Some(map_link(&resolve_href(base_dir, path), fragment))
+}
+
+/// Rewrite each candidate URL of a `srcset`
+fn rewrite_srcset(srcset: &str, rewrite: &impl Fn(&str) -> Option<String>) -> Option<String> {
+    let mut changed = false;
+    let candidates: Vec<String> = srcset
+        .split(',')
+        .map(|candidate| {
+            let candidate = candidate.trim();
+            let (url, descriptor) = candidate
+                .split_once(char::is_whitespace)
+                .unwrap_or((candidate, ""));
+            match rewrite(url) {
+                Some(url) => {
+                    changed = true;
+                    format!("{} {}", url, descriptor.trim())
+                        .trim_end()
+                        .to_string()
+                }
+                None => candidate.to_string(),
+            }
+        })
+        .collect();
+    changed.then(|| candidates.join(", "))
+}
+
+/// Rewrite the `url(...)` references in CSS
+fn rewrite_css(css: &str, rewrite: &impl Fn(&str) -> Option<String>) -> Option<String> {
+    let mut out = String::with_capacity(css.len());
+    let mut copied = 0;
+    let lower = css.to_ascii_lowercase();
+
+    let mut search = 0;
+    while let Some(offset) = lower[search..].find("url(") {
+        let open = search + offset + 4;
+        let Some(close) = css[open..].find(')').map(|i| open + i) else {
+            break;
+        };
+        search = close + 1;
+
+        let url = css[open..close]
+            .trim()
+            .trim_matches(|c| c == '"' || c == '\'');
+        if let Some(url) = rewrite(url) {
+            out.push_str(&css[copied..open]);
+            // Single quotes survive inside a double-quoted style attribute
+            out.push('\'');
+            out.push_str(&url.replace('\'', "%27"));
+            out.push('\'');
+            copied = close;
+        }
+    }
+
+    (copied > 0).then(|| {
+        out.push_str(&css[copied..]);
+        out
+    })
+}
+
+/// Rebuild a start tag with edited attributes; `None` when nothing changed
+fn edit_tag(tag: &str, mut edit: impl FnMut(&Attribute) -> Edit) -> Option<String> {
+    let mut out = String::new();
+    let mut copied = 0;
+
+    for attr in attributes(tag) {
+        match edit(&attr) {
+            Edit::Keep => continue,
+            Edit::Remove => {
+                // Drop the whitespace before the attribute too
+                out.push_str(&tag[copied..tag[..attr.start].trim_end().len()]);
+            }
+            Edit::Replace(value) => {
+                out.push_str(&tag[copied..attr.start]);
+                out.push_str(attr.name);
+                out.push_str("=\"");
+                out.push_str(&escape_attribute(&value));
+                out.push('"');
+            }
+        }
+        copied = attr.end;
+    }
+
+    (copied > 0).then(|| {
+        out.push_str(&tag[copied..]);
+        out
+    })
+}
+
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn epub_url(path: &str) -> String {
+        format!("epub://book/{}", path)
+    }
+
+    #[test]
+    fn test_rewrite_urls() {
+        let html = r##"<html><head>
+<link rel="stylesheet" href="../Styles/main.css"/>
+<style>body { background: url('../Images/bg.png') }</style>
+</head><body>
+<p><img src="../Images/a%20b.jpg" alt="A &amp; B"/> <a href="ch2.xhtml#s1">next</a>
+<a href="#note">note</a> <a href="https://example.com/x">web</a></p>
+<img srcset="../Images/s.jpg 1x, ../Images/l.jpg 2x" src="data:image/png;base64,AA=="/>
+<div style="background-image: url(../Images/c.png)"><svg><image xlink:href="../Images/d.png"/></svg></div>
+</body></html>"##;
+
+        let rewritten = rewrite_urls(html, "OEBPS/Text", epub_url);
+        assert!(rewritten.contains(r#"href="epub://book/OEBPS/Styles/main.css""#));
+        assert!(rewritten.contains("url('epub://book/OEBPS/Images/bg.png')"));
+        assert!(
+            rewritten.contains(r#"<img src="epub://book/OEBPS/Images/a b.jpg" alt="A &amp; B"/>"#)
+        );
+        assert!(rewritten.contains(r#"href="epub://book/OEBPS/Text/ch2.xhtml#s1""#));
+        assert!(rewritten.contains(r##"<a href="#note">"##));
+        assert!(rewritten.contains(r#"href="https://example.com/x""#));
+        assert!(rewritten.contains(
+            r#"srcset="epub://book/OEBPS/Images/s.jpg 1x, epub://book/OEBPS/Images/l.jpg 2x" src="data:"#
+        ));
+        assert!(rewritten.contains("url('epub://book/OEBPS/Images/c.png')"));
+        assert!(rewritten.contains(r#"xlink:href="epub://book/OEBPS/Images/d.png""#));
+
+        // Nothing relative: unchanged
+        let plain = "<p><a href=\"#x\">x</a><!-- <img src=\"a.png\"> --></p>";
+        assert_eq!(rewrite_urls(plain, "", epub_url), plain);
+    }
+
+    #[test]
+    fn test_merged_links() {
+        let html = r##"<h1 id="t">One</h1><section aria-labelledby="t"><a href="#n1">1</a>
+<a href="ch2.xhtml#s1">next</a> <a href="ch2.xhtml">ch2</a> <a href="#">top</a></section>
+<p id="n1" style="background: url(../Images/n.png)">note</p>"##;
+
+        let merged = rewrite_links(
+            &prefix_ids(html, "c1-"),
+            "OEBPS/Text",
+            |path, fragment| match (path, fragment) {
+                ("OEBPS/Text/ch2.xhtml", Some(id)) => format!("#c2-{}", id),
+                ("OEBPS/Text/ch2.xhtml", None) => "#c2".to_string(),
+                _ => format!("data:{}", path),
+            },
+        );
+        assert_eq!(
+            merged,
+            r##"<h1 id="c1-t">One</h1><section aria-labelledby="c1-t"><a href="#c1-n1">1</a>
+<a href="#c2-s1">next</a> <a href="#c2">ch2</a> <a href="#">top</a></section>
+<p id="c1-n1" style="background: url('data:OEBPS/Images/n.png')">note</p>"##
+        );
+
+        assert_eq!(
+            rewrite_css_urls(
+                "@font-face { src: url(\"../Fonts/a.otf\") } p { color: red }",
+                "OEBPS/Styles",
+                epub_url
+            ),
+            "@font-face { src: url('epub://book/OEBPS/Fonts/a.otf') } p { color: red }"
+        );
+    }
+
+    #[test]
+    fn test_sanitize_html() {
+        let html = r#"<head><script src="app.js"/><script type="text/javascript">if (a < b) { go() }</script></head>
+<body onload="init()"><p ONCLICK='x()' class="c">Hi</p>
+<a href=" javascript:alert(1)" title="t">bad</a><a href="ch2.xhtml">ok</a>
+<svg><svg:script>evil()</svg:script></svg></body>"#;
+
+        assert_eq!(
+            sanitize_html(html),
+            r#"<head></head>
+<body><p class="c">Hi</p>
+<a title="t">bad</a><a href="ch2.xhtml">ok</a>
+<svg></svg></body>"#
+        );
+
+        // An unclosed script takes the rest of the document with it
+        assert_eq!(sanitize_html("<p>a</p><script>x"), "<p>a</p>");
+    }
+
+    #[test]
+    fn test_sanitize_encoded_script_urls() {
+        for href in [
+            "&#106;avascript:alert(1)",
+            "&#x6A;avascript:alert(1)",
+            "&#106avascript:alert(1)",
+            "java&#x09;script:alert(1)",
+            "java&Tab;script:alert(1)",
+            "javascript&colon;alert(1)",
+            "javascript&#58;alert(1)",
+            "&#0000106;avascript:alert(1)",
+        ] {
+            let html = format!(r#"<a href="{}">x</a>"#, href);
+            assert_eq!(sanitize_html(&html), "<a>x</a>", "{}", href);
+        }
+
+        // References that do not spell a scheme are kept as written
+        let html = r#"<a href="notes.xhtml?a=1&amp;b=2">x</a>"#;
+        assert_eq!(sanitize_html(html), html);
+    }
+
+    #[test]
+    fn test_sanitize_embedded_documents() {
+        let html = r#"<p>a</p><iframe srcdoc="&lt;script&gt;alert(1)&lt;/script&gt;"></iframe>
+<object data="x.svg"><p>fallback</p></object><embed src="x.swf"/><base href="https://evil.example/"/>
+<div srcdoc="x">b</div>"#;
+
+        assert_eq!(
+            sanitize_html(html),
+            "<p>a</p>\n<p>fallback</p>\n<div>b</div>"
+        );
+    }
+
+    #[test]
+    fn test_is_relative() {
+        assert!(is_relative("img.png"));
+        assert!(is_relative("../a/b:c.png"));
+        assert!(is_relative("/OEBPS/img.png"));
+        assert!(!is_relative("#id"));
+        assert!(!is_relative("mailto:a@b.c"));
+        assert!(!is_relative("//cdn.example.com/a.png"));
+    }
+}
@@ -0,0 +1,226 @@
+//! Parsed EPUB structures
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Book metadata from the OPF `<metadata>` element
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct BookMetadata {
+    pub title: String,
+    pub creators: Vec<Creator>,
+    pub language: Option<String>,
+    pub identifier: Option<String>,
+    pub publisher: Option<String>,
+    pub description: Option<String>,
+    /// Cover image href, relative to the OPF directory
+    pub cover_href: Option<String>,
+    pub date: Option<String>,
+    pub rights: Option<String>,
+    pub subjects: Vec<String>,
+    /// schema.org accessibility metadata
+    pub accessibility: Accessibility,
+}
+
+/// Accessibility metadata from schema.org `<meta>` properties, as described
+/// by EPUB Accessibility 1.1
+///
+/// Values are kept as written (`textual`, `structuralNavigation`, `none`,
+/// ...); a book that declares nothing has every field empty.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct Accessibility {
+    /// `schema:accessMode`: senses the content is written for
+    pub access_modes: Vec<String>,
+    /// `schema:accessModeSufficient`: sets of access modes that are each
+    /// enough to read the whole book
+    pub access_modes_sufficient: Vec<Vec<String>>,
+    /// `schema:accessibilityFeature`
+    pub features: Vec<String>,
+    /// `schema:accessibilityHazard`
+    pub hazards: Vec<String>,
+    /// `schema:accessibilitySummary`
+    pub summary: Option<String>,
+    /// `schema:accessibilityControl`
+    pub controls: Vec<String>,
+    /// `schema:accessibilityAPI`
+    pub apis: Vec<String>,
+    /// `dcterms:conformsTo`: accessibility specifications the book claims
+    pub conforms_to: Vec<String>,
+    /// `a11y:certifiedBy`
+    pub certified_by: Option<String>,
+}
+
+impl Accessibility {
+    /// Whether text alone is enough to read the book, so a screen reader
+    /// can read all of it
+    pub fn is_textual(&self) -> bool {
+        self.access_modes_sufficient
+            .iter()
+            .any(|modes| modes.iter().all(|mode| mode == "textual"))
+    }
+}
+
+/// Creator (author) information
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct Creator {
+    pub name: String,
+    pub role: Option<String>,
+    pub file_as: Option<String>,
+}
+
+/// Manifest item from OPF
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct ManifestItem {
+    pub id: String,
+    /// Href relative to the OPF directory
+    pub href: String,
+    pub media_type: String,
+    pub properties: Option<String>,
+}
+
+impl ManifestItem {
+    /// Check whether the space-separated `properties` attribute contains `property`
+    pub fn has_property(&self, property: &str) -> bool {
+        self.properties
+            .as_deref()
+            .is_some_and(|props| props.split_whitespace().any(|p| p == property))
+    }
+}
+
+/// Spine item (reading order entry)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct SpineItem {
+    pub id: String,
+    /// Href relative to the OPF directory
+    pub href: String,
+    pub media_type: String,
+    pub linear: bool,
+    /// Layout set on this item, overriding the book's
+    pub layout: Option<Layout>,
+    /// Side of a two-page spread this page goes on
+    pub page_spread: Option<PageSpread>,
+    /// `rendition:spread-*`: when this item is shown in a spread, overriding
+    /// the book's `rendition:spread`
+    pub spread: Option<String>,
+    /// Page size of a pre-paginated item, from its viewport meta tag (or
+    /// SVG `viewBox`), else the book's default
+    pub viewport: Option<Viewport>,
+}
+
+impl SpineItem {
+    /// The layout this item renders with
+    pub fn layout_in(&self, rendition: &Rendition) -> Layout {
+        self.layout.unwrap_or(rendition.layout)
+    }
+}
+
+/// A linear XHTML spine item with `href` as its id, for tests
+#[cfg(test)]
+pub(crate) fn spine_item(href: &str) -> SpineItem {
+    SpineItem {
+        id: href.to_string(),
+        href: href.to_string(),
+        media_type: "application/xhtml+xml".to_string(),
+        linear: true,
+        layout: None,
+        page_spread: None,
+        spread: None,
+        viewport: None,
+    }
+}
+
+/// How a book or page is laid out (`rendition:layout`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Layout {
+    /// Text reflows to fit the screen
+    #[default]
+    Reflowable,
+    /// Each page has a fixed size and is scaled as a whole
+    PrePaginated,
+}
+
+/// Which side of a spread a page is placed on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum PageSpread {
+    Left,
+    Right,
+    Center,
+}
+
+/// Page dimensions in CSS pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Viewport {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Book-wide rendition properties from the OPF `<metadata>`
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct Rendition {
+    pub layout: Layout,
+    /// `rendition:orientation`: "auto", "landscape", or "portrait"
+    pub orientation: Option<String>,
+    /// `rendition:spread`: "auto", "none", "landscape", or "both"
+    pub spread: Option<String>,
+    /// Default page size of pre-paginated items
+    pub viewport: Option<Viewport>,
+    /// `page-progression-direction` of the spine; `None` for "default"
+    pub page_progression_direction: Option<PageProgression>,
+    /// Kindle `primary-writing-mode`, e.g. "horizontal-rl" or "vertical-rl"
+    pub writing_mode: Option<String>,
+}
+
+impl Rendition {
+    /// The direction pages turn in
+    ///
+    /// Without a `page-progression-direction`, books written right to left
+    /// (Kindle manga and vertical Japanese text) turn right to left, and
+    /// everything else left to right.
+    pub fn page_progression(&self) -> PageProgression {
+        self.page_progression_direction
+            .unwrap_or_else(|| match self.writing_mode.as_deref() {
+                Some(mode) if mode.ends_with("-rl") => PageProgression::Rtl,
+                _ => PageProgression::Ltr,
+            })
+    }
+}
+
+/// Direction pages turn in; the spine is in reading order either way
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum PageProgression {
+    /// The next page is to the right
+    #[default]
+    Ltr,
+    /// The next page is to the left, as in manga and Arabic books
+    Rtl,
+}
+
+/// Table of contents entry
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct TocEntry {
+    pub id: String,
+    pub href: String,
+    pub label: String,
+    pub level: usize,
+    pub children: Vec<TocEntry>,
+}
@@ -0,0 +1,358 @@
+//! Serialized search index
+//!
+//! Binary layout (all integers little-endian, strings are UTF-8 prefixed
+//! with a `u32` byte length):
+//!
+//! ```text
+//! magic        4 bytes  "AMSI"
+//! version      u16
+//! book_id      string
+//! chapters     u32 count, then per chapter:
+//!   href           string
+//!   spine_index    u32
+//!   original_text  string
+//!   text           string (normalized)
+//!   spans          u32 count, then per span:
+//!     kind           u8 (1 alt text, 2 caption, 3 footnote)
+//!     start          u32 (byte offset into original_text)
+//!     end            u32
+//! ```
+//!
+//! The normalized text is stored rather than recomputed on import so match
+//! offsets are identical regardless of which build's Unicode tables are used.
+//! Version 1 indexes, which have no spans, are still read. Indexes before
+//! version 3 kept curly quotes in the normalized text; theirs is recomputed
+//! so it lines up with today's query normalization and [`crate::OffsetMap`].
+
+use std::ops::Range;
+
+use crate::supplementary::{extract_with_spans, IndexOptions, TextKind, TextSpan};
+use crate::text::normalize_for_search;
+use crate::IndexDecodeError;
+
+/// Leading bytes of every serialized index
+pub const MAGIC: &[u8; 4] = b"AMSI";
+
+/// Current binary format version
+pub const FORMAT_VERSION: u16 = 3;
+
+/// Oldest binary format version that can still be read
+const MIN_FORMAT_VERSION: u16 = 1;
+
+/// Indexed text for a single spine item
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexedChapter {
+    /// Chapter href as listed in the spine (relative to the OPF)
+    pub href: String,
+    /// Position in the spine
+    pub spine_index: usize,
+    /// Extracted plain text (for excerpts)
+    pub original_text: String,
+    /// Normalized text that queries are matched against
+    pub text: String,
+    /// Alt text, captions and footnotes in `original_text`, in order; empty
+    /// unless indexed with [`IndexOptions`]
+    pub spans: Vec<TextSpan>,
+}
+
+impl IndexedChapter {
+    /// Index a chapter from its XHTML content
+    pub fn from_html(href: impl Into<String>, spine_index: usize, html: &str) -> Self {
+        Self::from_html_with(href, spine_index, html, &IndexOptions::default())
+    }
+
+    /// Index a chapter, including the supplementary text `options` asks for
+    pub fn from_html_with(
+        href: impl Into<String>,
+        spine_index: usize,
+        html: &str,
+        options: &IndexOptions,
+    ) -> Self {
+        let (original_text, spans) = extract_with_spans(html, options);
+        let text = normalize_for_search(&original_text);
+        Self {
+            href: href.into(),
+            spine_index,
+            original_text,
+            text,
+            spans,
+        }
+    }
+
+    /// Kind of text at a byte offset into `original_text`, and its range
+    pub fn kind_at(&self, offset: usize) -> (TextKind, Range<usize>) {
+        let span = self
+            .spans
+            .iter()
+            .find(|span| (span.start..span.end).contains(&offset));
+        match span {
+            Some(span) => (span.kind, span.start..span.end),
+            None => (TextKind::Body, 0..self.original_text.len()),
+        }
+    }
+}
+
+/// Search index for a book in its portable form
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchIndexData {
+    /// Book the index was built for
+    pub book_id: String,
+    /// Indexed chapters in spine order
+    pub chapters: Vec<IndexedChapter>,
+}
+
+impl SearchIndexData {
+    pub fn new(book_id: impl Into<String>, chapters: Vec<IndexedChapter>) -> Self {
+        Self {
+            book_id: book_id.into(),
+            chapters,
+        }
+    }
+
+    /// Serialize to the shared binary format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let text_len: usize = self
+            .chapters
+            .iter()
+            .map(|c| c.href.len() + c.original_text.len() + c.text.len() + 16)
+            .sum();
+        let mut out = Vec::with_capacity(MAGIC.len() + 2 + 8 + self.book_id.len() + text_len);
+
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        write_str(&mut out, &self.book_id);
+        write_u32(&mut out, self.chapters.len());
+        for chapter in &self.chapters {
+            write_str(&mut out, &chapter.href);
+            write_u32(&mut out, chapter.spine_index);
+            write_str(&mut out, &chapter.original_text);
+            write_str(&mut out, &chapter.text);
+            write_u32(&mut out, chapter.spans.len());
+            for span in &chapter.spans {
+                out.push(span.kind.code());
+                write_u32(&mut out, span.start);
+                write_u32(&mut out, span.end);
+            }
+        }
+        out
+    }
+
+    /// Deserialize from the shared binary format
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, IndexDecodeError> {
+        let mut reader = Reader { bytes, pos: 0 };
+
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(IndexDecodeError::BadMagic);
+        }
+        let version = u16::from_le_bytes(reader.array()?);
+        if !(MIN_FORMAT_VERSION..=FORMAT_VERSION).contains(&version) {
+            return Err(IndexDecodeError::UnsupportedVersion(version));
+        }
+
+        let book_id = reader.string()?;
+        let count = reader.u32()?;
+        // Each chapter needs at least 16 bytes, so a bogus count fails fast
+        // instead of reserving a huge allocation
+        let mut chapters = Vec::with_capacity(count.min(reader.remaining() / 16));
+        for _ in 0..count {
+            let mut chapter = IndexedChapter {
+                href: reader.string()?,
+                spine_index: reader.u32()?,
+                original_text: reader.string()?,
+                text: reader.string()?,
+                spans: Vec::new(),
+            };
+            if version >= 2 {
+                chapter.spans = reader.spans(chapter.original_text.len())?;
+            }
+            if version < 3 {
+                chapter.text = normalize_for_search(&chapter.original_text);
+            }
+            chapters.push(chapter);
+        }
+
+        if reader.remaining() > 0 {
+            return Err(IndexDecodeError::TrailingBytes(reader.remaining()));
+        }
+
+        Ok(Self { book_id, chapters })
+    }
+}
+
+fn write_u32(out: &mut Vec<u8>, value: usize) {
+    let value = u32::try_from(value).expect("search index field exceeds u32");
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn write_str(out: &mut Vec<u8>, value: &str) {
+    write_u32(out, value.len());
+    out.extend_from_slice(value.as_bytes());
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.pos
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], IndexDecodeError> {
+        if len > self.remaining() {
+            return Err(IndexDecodeError::Truncated);
+        }
+        let slice = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(slice)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], IndexDecodeError> {
+        let mut array = [0u8; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn u32(&mut self) -> Result<usize, IndexDecodeError> {
+        Ok(u32::from_le_bytes(self.array()?) as usize)
+    }
+
+    /// Spans of a text `text_len` bytes long
+    fn spans(&mut self, text_len: usize) -> Result<Vec<TextSpan>, IndexDecodeError> {
+        let count = self.u32()?;
+        let mut spans = Vec::with_capacity(count.min(self.remaining() / 9));
+        for _ in 0..count {
+            let [code] = self.array()?;
+            let kind = TextKind::from_code(code).ok_or(IndexDecodeError::InvalidSpan)?;
+            let (start, end) = (self.u32()?, self.u32()?);
+            if start > end || end > text_len {
+                return Err(IndexDecodeError::InvalidSpan);
+            }
+            spans.push(TextSpan { kind, start, end });
+        }
+        Ok(spans)
+    }
+
+    fn string(&mut self) -> Result<String, IndexDecodeError> {
+        let len = self.u32()?;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| IndexDecodeError::InvalidUtf8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> SearchIndexData {
+        SearchIndexData::new(
+            "book-1",
+            vec![
+                IndexedChapter::from_html("ch1.xhtml", 0, "<p>Call me <i>Ishmael</i>.</p>"),
+                IndexedChapter::from_html("text/ch2.xhtml", 3, "<p>Café naïve</p>"),
+                IndexedChapter::from_html_with(
+                    "ch3.xhtml",
+                    4,
+                    r#"<p>Text</p><img src="a.png" alt="A map"/>"#,
+                    &IndexOptions::all(),
+                ),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_from_html() {
+        let chapter = IndexedChapter::from_html("ch.xhtml", 2, "<p>Café <b>Society</b></p>");
+        assert_eq!(chapter.original_text, "Café Society");
+        assert_eq!(chapter.text, "cafe society");
+        assert_eq!(chapter.spine_index, 2);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let index = sample();
+        let bytes = index.to_bytes();
+        assert_eq!(&bytes[..4], MAGIC);
+        assert_eq!(SearchIndexData::from_bytes(&bytes).unwrap(), index);
+    }
+
+    #[test]
+    fn test_reads_version_1() {
+        let mut v1 = Vec::new();
+        v1.extend_from_slice(MAGIC);
+        v1.extend_from_slice(&1u16.to_le_bytes());
+        write_str(&mut v1, "book-1");
+        write_u32(&mut v1, 1);
+        write_str(&mut v1, "ch1.xhtml");
+        write_u32(&mut v1, 0);
+        write_str(&mut v1, "Café");
+        write_str(&mut v1, "cafe");
+
+        let index = SearchIndexData::from_bytes(&v1).unwrap();
+        assert_eq!(index.chapters[0].text, "cafe");
+        assert!(index.chapters[0].spans.is_empty());
+    }
+
+    #[test]
+    fn test_renormalizes_version_2() {
+        let mut v2 = Vec::new();
+        v2.extend_from_slice(MAGIC);
+        v2.extend_from_slice(&2u16.to_le_bytes());
+        write_str(&mut v2, "book-1");
+        write_u32(&mut v2, 1);
+        write_str(&mut v2, "ch1.xhtml");
+        write_u32(&mut v2, 0);
+        write_str(&mut v2, "Don’t");
+        write_str(&mut v2, "don’t");
+        write_u32(&mut v2, 0);
+
+        let index = SearchIndexData::from_bytes(&v2).unwrap();
+        assert_eq!(index.chapters[0].text, "don't");
+    }
+
+    #[test]
+    fn test_kind_at() {
+        let chapter = &sample().chapters[2];
+        assert_eq!(chapter.original_text, "Text A map");
+        assert_eq!(chapter.kind_at(0), (TextKind::Body, 0..10));
+        assert_eq!(chapter.kind_at(7), (TextKind::AltText, 5..10));
+    }
+
+    #[test]
+    fn test_rejects_bad_input() {
+        let bytes = sample().to_bytes();
+
+        assert_eq!(
+            SearchIndexData::from_bytes(b"NOPE\x01\x00"),
+            Err(IndexDecodeError::BadMagic)
+        );
+        assert_eq!(
+            SearchIndexData::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(IndexDecodeError::Truncated)
+        );
+
+        let mut future = bytes.clone();
+        future[4..6].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert_eq!(
+            SearchIndexData::from_bytes(&future),
+            Err(IndexDecodeError::UnsupportedVersion(FORMAT_VERSION + 1))
+        );
+
+        // The alt text span claims to run past the chapter text
+        let mut overlong = bytes.clone();
+        let end = overlong.len() - 4;
+        overlong[end..].copy_from_slice(&100u32.to_le_bytes());
+        assert_eq!(
+            SearchIndexData::from_bytes(&overlong),
+            Err(IndexDecodeError::InvalidSpan)
+        );
+
+        let mut padded = bytes;
+        padded.push(0);
+        assert_eq!(
+            SearchIndexData::from_bytes(&padded),
+            Err(IndexDecodeError::TrailingBytes(1))
+        );
+    }
+}
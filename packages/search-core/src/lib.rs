@@ -0,0 +1,51 @@
+//! Search index core for Amnesia
+//!
+//! Shared by the WASM epub-processor and the server so an index built on
+//! either side is byte-for-byte the same:
+//! - `text`: plain-text extraction from XHTML, search normalization, and
+//!   folding of typographic punctuation and ligatures
+//! - `index`: per-chapter index data and its binary serialization
+//! - `segment`: word-boundary segmentation of extracted text, and the terms
+//!   indexed for it (character bigrams for CJK and Thai)
+//! - `selection`: snapping highlight selections to whole words, and slicing
+//!   text by DOM (UTF-16) offsets
+//! - `supplementary`: alt text, captions and footnotes as tagged spans
+//!
+//! The server builds the index from the stored book and serves the bytes;
+//! the WASM reader imports them and searches offline without re-parsing
+//! every chapter in the browser.
+
+pub mod index;
+pub mod segment;
+pub mod selection;
+pub mod supplementary;
+pub mod text;
+
+pub use index::{IndexedChapter, SearchIndexData, FORMAT_VERSION, MAGIC};
+pub use segment::{index_terms, search_terms, segment_words};
+pub use selection::{normalize_selection, utf16_slice, TextSelection};
+pub use supplementary::{IndexOptions, TextKind, TextSpan};
+pub use text::{extract_plain_text, normalize_for_search, normalize_text, OffsetMap, TextFolding};
+
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum IndexDecodeError {
+    #[error("Not a search index (bad magic)")]
+    BadMagic,
+
+    #[error("Unsupported search index version {0} (expected {FORMAT_VERSION})")]
+    UnsupportedVersion(u16),
+
+    #[error("Search index is truncated")]
+    Truncated,
+
+    #[error("Search index contains invalid UTF-8")]
+    InvalidUtf8,
+
+    #[error("Search index has an invalid text span")]
+    InvalidSpan,
+
+    #[error("Search index has {0} trailing bytes")]
+    TrailingBytes(usize),
+}
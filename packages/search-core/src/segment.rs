@@ -0,0 +1,235 @@
+//! Word-boundary segmentation of extracted chapter text
+//!
+//! Operates on the output of [`crate::extract_plain_text`], so word offsets
+//! line up with the text the search index is built from. A word is a run of
+//! letters, digits, and combining marks; an apostrophe or hyphen joins two
+//! runs into one word ("don't", "well-known") only when it sits between word
+//! characters.
+//!
+//! Chinese, Japanese and Thai are written without spaces between words, so
+//! a run of letters there would be a whole clause or sentence. Their
+//! characters are words of their own instead, together with the marks that
+//! attach to them. For search, [`index_terms`] and [`search_terms`] turn
+//! such runs into overlapping character bigrams, the usual dictionary-free
+//! way to index CJK text: "東京都" is found by "東京" and "京都" but not by
+//! "京東".
+
+use std::ops::Range;
+
+use crate::text::is_combining_mark;
+
+/// Characters that join word runs when surrounded by word characters
+const JOINERS: &[char] = &['\'', '\u{2019}', '-', '\u{2010}'];
+
+/// Byte ranges of the words in `text`, in order
+pub fn segment_words(text: &str) -> Vec<Range<usize>> {
+    let mut words = Vec::new();
+    let mut start: Option<usize> = None;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        if is_unspaced(c) {
+            if let Some(word_start) = start.take() {
+                words.push(word_start..i);
+            }
+            let mut end = i + c.len_utf8();
+            while let Some(&(j, mark)) = chars.peek().filter(|&&(_, n)| is_cluster_mark(n)) {
+                end = j + mark.len_utf8();
+                chars.next();
+            }
+            words.push(i..end);
+            continue;
+        }
+        if is_word_char(c) {
+            start.get_or_insert(i);
+            continue;
+        }
+
+        let Some(word_start) = start else {
+            continue;
+        };
+
+        let joins = JOINERS.contains(&c)
+            && chars
+                .peek()
+                .is_some_and(|&(_, n)| is_word_char(n) && !is_unspaced(n));
+        if !joins {
+            words.push(word_start..i);
+            start = None;
+        }
+    }
+
+    if let Some(word_start) = start {
+        words.push(word_start..text.len());
+    }
+
+    words
+}
+
+/// Terms to index: the words of `text`, with runs of unspaced characters
+/// as their single characters and overlapping bigrams, in order
+///
+/// Single characters are indexed so one-character queries still match.
+pub fn index_terms(text: &str) -> Vec<Range<usize>> {
+    terms(text, true)
+}
+
+/// Terms to look a query up by: like [`index_terms`], but runs of unspaced
+/// characters are only bigrams, so longer queries match more precisely
+pub fn search_terms(text: &str) -> Vec<Range<usize>> {
+    terms(text, false)
+}
+
+fn terms(text: &str, unigrams: bool) -> Vec<Range<usize>> {
+    let words = segment_words(text);
+    let unspaced = |word: &Range<usize>| text[word.clone()].chars().next().is_some_and(is_unspaced);
+    let mut terms = Vec::with_capacity(words.len());
+
+    let mut i = 0;
+    while i < words.len() {
+        // A run of characters with no space between them
+        let mut end = i + 1;
+        if unspaced(&words[i]) {
+            while end < words.len()
+                && words[end].start == words[end - 1].end
+                && unspaced(&words[end])
+            {
+                end += 1;
+            }
+        }
+
+        let run = &words[i..end];
+        if run.len() == 1 {
+            terms.push(run[0].clone());
+        } else {
+            for (k, word) in run.iter().enumerate() {
+                if unigrams {
+                    terms.push(word.clone());
+                }
+                if let Some(next) = run.get(k + 1) {
+                    terms.push(word.start..next.end);
+                }
+            }
+        }
+        i = end;
+    }
+
+    terms
+}
+
+/// Whether `c` is a hyphen that joins the parts of a compound word
+pub fn is_word_hyphen(c: char) -> bool {
+    matches!(c, '-' | '\u{2010}')
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || is_combining_mark(c) || is_cluster_mark(c)
+}
+
+/// Letters of scripts written without spaces between words: Han, kana,
+/// Thai and Lao
+fn is_unspaced(c: char) -> bool {
+    matches!(c as u32,
+        // Iteration and closing marks (々〆〇)
+        0x3005..=0x3007
+        // Hiragana, Katakana and their extensions
+        | 0x3041..=0x3096
+        | 0x309D..=0x30FF
+        | 0x31F0..=0x31FF
+        // CJK Unified Ideographs, Extension A and compatibility ideographs
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xF900..=0xFAFF
+        // Supplementary ideographs (Extensions B onwards)
+        | 0x20000..=0x3134F
+        // Thai and Lao letters and vowels, without digits and punctuation
+        | 0x0E01..=0x0E4E
+        | 0x0E81..=0x0ECF
+    ) && !is_cluster_mark(c)
+}
+
+/// Marks that belong to the character before them in unspaced scripts:
+/// kana voicing marks and Thai and Lao vowel and tone marks
+fn is_cluster_mark(c: char) -> bool {
+    matches!(c as u32,
+        0x3099..=0x309A
+        | 0x0E31
+        | 0x0E34..=0x0E3A
+        | 0x0E47..=0x0E4E
+        | 0x0EB1
+        | 0x0EB4..=0x0EBC
+        | 0x0EC8..=0x0ECD
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(text: &str) -> Vec<&str> {
+        segment_words(text).into_iter().map(|r| &text[r]).collect()
+    }
+
+    #[test]
+    fn test_segment_words() {
+        assert_eq!(
+            words("Hello, world! It's a well-known fact."),
+            vec!["Hello", "world", "It's", "a", "well-known", "fact"]
+        );
+    }
+
+    #[test]
+    fn test_segment_trailing_joiners() {
+        assert_eq!(
+            words("rock 'n' roll -- ok-"),
+            vec!["rock", "n", "roll", "ok"]
+        );
+    }
+
+    #[test]
+    fn test_segment_unspaced_scripts() {
+        let text = "東京に行きました。Tokyo-東京";
+        assert_eq!(
+            words(text),
+            vec!["東", "京", "に", "行", "き", "ま", "し", "た", "Tokyo", "東", "京"]
+        );
+        // Voicing marks left by normalization stay with their kana
+        let text = crate::normalize_for_search("ﾃﾚﾋﾞ");
+        assert_eq!(words(&text), vec!["テ", "レ", "ヒ\u{3099}"]);
+        assert_eq!(words("ขอบคุณ"), vec!["ข", "อ", "บ", "คุ", "ณ"]);
+    }
+
+    #[test]
+    fn test_index_and_search_terms() {
+        let terms = |text: &'static str, index: bool| -> Vec<&'static str> {
+            let ranges = if index {
+                index_terms(text)
+            } else {
+                search_terms(text)
+            };
+            ranges.into_iter().map(|r| &text[r]).collect()
+        };
+
+        assert_eq!(
+            terms("東京都 in Japan", false),
+            vec!["東京", "京都", "in", "Japan"]
+        );
+        assert_eq!(
+            terms("東京都 東", true),
+            vec!["東", "東京", "京", "京都", "都", "東"]
+        );
+        assert_eq!(terms("東", false), vec!["東"]);
+        assert_eq!(terms("well-known", true), vec!["well-known"]);
+    }
+
+    #[test]
+    fn test_segment_non_ascii() {
+        let text = "Café naïve — Straße 42";
+        assert_eq!(words(text), vec!["Café", "naïve", "Straße", "42"]);
+        // Decomposed accents stay inside the word
+        assert_eq!(
+            words("Cafe\u{301} au lait"),
+            vec!["Cafe\u{301}", "au", "lait"]
+        );
+    }
+}
@@ -0,0 +1,180 @@
+//! Selection normalization for stable highlights
+//!
+//! Reader selections often start or end on whitespace, cut a word in half,
+//! or (as UTF-16 offsets from the DOM) split a surrogate pair, and each
+//! variant anchors slightly differently. [`normalize_selection`] snaps a
+//! proposed range over a chapter's extracted text to whole words without
+//! surrounding whitespace, so the same passage always yields the same
+//! selectors. Offsets are UTF-16 code units, as in the DOM.
+
+use crate::segment::segment_words;
+
+/// Characters of context kept on either side, as Hypothes.is does
+pub const CONTEXT_CHARS: usize = 32;
+
+/// A selection snapped to whole words
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextSelection {
+    /// Start offset (inclusive)
+    pub start: usize,
+    /// End offset (exclusive)
+    pub end: usize,
+    /// The selected text
+    pub exact: String,
+    /// Up to [`CONTEXT_CHARS`] characters before the selection
+    pub prefix: String,
+    /// Up to [`CONTEXT_CHARS`] characters after the selection
+    pub suffix: String,
+}
+
+/// Snap a selection of `text` to word boundaries and trim its whitespace
+///
+/// `start` and `end` may come in either order and are clamped to the text.
+/// A boundary inside a word moves outwards to take in the whole word, and
+/// one inside a surrogate pair takes in the whole character. Returns `None`
+/// when only whitespace is selected.
+pub fn normalize_selection(text: &str, start: usize, end: usize) -> Option<TextSelection> {
+    let (start, end) = if end < start {
+        (end, start)
+    } else {
+        (start, end)
+    };
+    let mut start = floor_byte(text, start);
+    let mut end = ceil_byte(text, end);
+
+    let selected = &text[start..end];
+    let trimmed = selected.trim_start();
+    start += selected.len() - trimmed.len();
+    end = start + trimmed.trim_end().len();
+    if start == end {
+        return None;
+    }
+
+    for word in segment_words(text) {
+        if word.start < start && start < word.end {
+            start = word.start;
+        }
+        if word.start < end && end < word.end {
+            end = word.end;
+        }
+    }
+
+    let prefix_start = text[..start]
+        .char_indices()
+        .rev()
+        .nth(CONTEXT_CHARS - 1)
+        .map_or(0, |(i, _)| i);
+    let suffix_end = text[end..]
+        .char_indices()
+        .nth(CONTEXT_CHARS)
+        .map_or(text.len(), |(i, _)| end + i);
+
+    Some(TextSelection {
+        start: utf16_offset(text, start),
+        end: utf16_offset(text, end),
+        exact: text[start..end].to_string(),
+        prefix: text[prefix_start..start].to_string(),
+        suffix: text[end..suffix_end].to_string(),
+    })
+}
+
+/// The text between two UTF-16 offsets, exactly as selected
+///
+/// Unlike [`normalize_selection`] nothing is snapped or trimmed; a boundary
+/// inside a surrogate pair takes in the whole character. Offsets may come
+/// in either order and are clamped to the text.
+pub fn utf16_slice(text: &str, start: usize, end: usize) -> &str {
+    let (start, end) = if end < start {
+        (end, start)
+    } else {
+        (start, end)
+    };
+    &text[floor_byte(text, start)..ceil_byte(text, end)]
+}
+
+/// Byte offset of the character holding UTF-16 offset `offset`
+fn floor_byte(text: &str, offset: usize) -> usize {
+    let mut units = 0;
+    for (i, c) in text.char_indices() {
+        units += c.len_utf16();
+        if units > offset {
+            return i;
+        }
+    }
+    text.len()
+}
+
+/// Byte offset of the first character boundary at or after UTF-16 offset
+/// `offset`
+fn ceil_byte(text: &str, offset: usize) -> usize {
+    let mut units = 0;
+    for (i, c) in text.char_indices() {
+        if units >= offset {
+            return i;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+fn utf16_offset(text: &str, byte: usize) -> usize {
+    text[..byte].encode_utf16().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exact(text: &str, start: usize, end: usize) -> Option<String> {
+        normalize_selection(text, start, end).map(|selection| selection.exact)
+    }
+
+    #[test]
+    fn test_utf16_slice() {
+        let text = " 👍 ’til";
+        assert_eq!(utf16_slice(text, 0, 3), " 👍");
+        // Halfway into the emoji in either direction takes all of it
+        assert_eq!(utf16_slice(text, 2, 1), "👍");
+        assert_eq!(utf16_slice(text, 4, 100), "’til");
+    }
+
+    #[test]
+    fn test_normalize_selection_snaps_to_words() {
+        let text = "Call me Ishmael. Some years ago";
+
+        // " me Ishm" loses its space and takes in the rest of the name
+        let selection = normalize_selection(text, 4, 12).unwrap();
+        assert_eq!(selection.exact, "me Ishmael");
+        assert_eq!((selection.start, selection.end), (5, 15));
+        assert_eq!(selection.prefix, "Call ");
+        assert_eq!(selection.suffix, ". Some years ago");
+
+        // Punctuation at the ends is kept; reversed ends are fine
+        assert_eq!(exact(text, 16, 9).as_deref(), Some("Ishmael."));
+        // Past the end is clamped
+        assert_eq!(exact(text, 27, 99).as_deref(), Some("ago"));
+        assert_eq!(exact(text, 4, 5), None);
+        assert_eq!(exact(text, 40, 50), None);
+    }
+
+    #[test]
+    fn test_normalize_selection_keeps_surrogate_pairs_whole() {
+        // U+1F600 is two UTF-16 units, at 4 and 5
+        let text = "hi! \u{1F600} there";
+        let selection = normalize_selection(text, 5, 6).unwrap();
+        assert_eq!(selection.exact, "\u{1F600}");
+        assert_eq!((selection.start, selection.end), (4, 6));
+
+        assert_eq!(exact(text, 0, 5).as_deref(), Some("hi! \u{1F600}"));
+    }
+
+    #[test]
+    fn test_normalize_selection_context_is_bounded() {
+        let text = format!("{} middle {}", "a".repeat(50), "b".repeat(50));
+        let selection = normalize_selection(&text, 51, 57).unwrap();
+        assert_eq!(selection.exact, "middle");
+        assert_eq!(selection.prefix.chars().count(), CONTEXT_CHARS);
+        assert_eq!(selection.suffix.chars().count(), CONTEXT_CHARS);
+        assert!(selection.prefix.ends_with("a "));
+    }
+}
@@ -0,0 +1,279 @@
+//! Alt text, captions and footnotes
+//!
+//! Plain-text extraction keeps figure captions and footnotes inline with the
+//! body and drops image alt text. With [`IndexOptions`] a chapter's index
+//! also records where captions and footnotes sit in its text, and appends
+//! the alt text after it. Each is a [`TextSpan`] tagged with its
+//! [`TextKind`], so a search can report that a match was in a caption.
+//!
+//! The body text is left exactly as [`crate::extract_plain_text`] produces
+//! it, so offsets into it still agree with the reader's view of the chapter.
+
+use regex::Regex;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::text::extract_plain_text;
+
+/// `epub:type` and `role` values (without `doc-`) that mark footnotes
+const FOOTNOTE_TYPES: &[&str] = &[
+    "footnote",
+    "footnotes",
+    "endnote",
+    "endnotes",
+    "rearnote",
+    "rearnotes",
+];
+
+/// What to index besides the body text
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase", default))]
+pub struct IndexOptions {
+    /// Image `alt` attributes
+    pub alt_text: bool,
+    /// `<figcaption>` elements
+    pub captions: bool,
+    /// Elements marked as footnotes or endnotes with `epub:type` or `role`
+    pub footnotes: bool,
+}
+
+impl IndexOptions {
+    /// Index alt text, captions and footnotes
+    pub fn all() -> Self {
+        Self {
+            alt_text: true,
+            captions: true,
+            footnotes: true,
+        }
+    }
+
+    fn any(&self) -> bool {
+        self.alt_text || self.captions || self.footnotes
+    }
+}
+
+/// The kind of text a match is in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub enum TextKind {
+    #[default]
+    Body,
+    AltText,
+    Caption,
+    Footnote,
+}
+
+impl TextKind {
+    /// Code in the serialized index
+    pub(crate) fn code(self) -> u8 {
+        match self {
+            TextKind::Body => 0,
+            TextKind::AltText => 1,
+            TextKind::Caption => 2,
+            TextKind::Footnote => 3,
+        }
+    }
+
+    pub(crate) fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(TextKind::Body),
+            1 => Some(TextKind::AltText),
+            2 => Some(TextKind::Caption),
+            3 => Some(TextKind::Footnote),
+            _ => None,
+        }
+    }
+}
+
+/// A byte range of a chapter's plain text that is not body text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextSpan {
+    pub kind: TextKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Extract plain text and the spans of the supplementary text it includes
+///
+/// Without any option this is [`extract_plain_text`] with no spans.
+pub fn extract_with_spans(html: &str, options: &IndexOptions) -> (String, Vec<TextSpan>) {
+    if !options.any() {
+        return (extract_plain_text(html), Vec::new());
+    }
+
+    let mut text = String::new();
+    let mut spans = Vec::new();
+    let push = |text: &mut String, part: String| -> Option<(usize, usize)> {
+        if part.is_empty() {
+            return None;
+        }
+        // Tags become spaces, so the parts join as the whole would extract
+        if !text.is_empty() {
+            text.push(' ');
+        }
+        let start = text.len();
+        text.push_str(&part);
+        Some((start, text.len()))
+    };
+
+    let mut copied = 0;
+    for (kind, start, end) in element_ranges(html, options) {
+        push(&mut text, extract_plain_text(&html[copied..start]));
+        if let Some((start, end)) = push(&mut text, extract_plain_text(&html[start..end])) {
+            spans.push(TextSpan { kind, start, end });
+        }
+        copied = end;
+    }
+    push(&mut text, extract_plain_text(&html[copied..]));
+
+    if options.alt_text {
+        for alt in alt_texts(html) {
+            if let Some((start, end)) = push(&mut text, alt) {
+                spans.push(TextSpan {
+                    kind: TextKind::AltText,
+                    start,
+                    end,
+                });
+            }
+        }
+    }
+
+    (text, spans)
+}
+
+/// Byte ranges of the outermost caption and footnote elements, in order
+fn element_ranges(html: &str, options: &IndexOptions) -> Vec<(TextKind, usize, usize)> {
+    if !options.captions && !options.footnotes {
+        return Vec::new();
+    }
+    let tag = Regex::new(r"<(/?)([A-Za-z][^\s/>]*)([^>]*)>").unwrap();
+    let type_attr =
+        Regex::new(r#"(?i)\b(?:epub:type|role)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap();
+
+    let mut ranges = Vec::new();
+    // Element being skipped over: kind, start, name, nesting depth
+    let mut open: Option<(TextKind, usize, String, usize)> = None;
+
+    for caps in tag.captures_iter(html) {
+        let whole = caps.get(0).unwrap();
+        let closing = !caps[1].is_empty();
+        let name = caps[2].to_ascii_lowercase();
+        let attrs = &caps[3];
+        let self_closing = attrs.ends_with('/');
+
+        if let Some((kind, start, open_name, depth)) = &mut open {
+            if name != *open_name || self_closing {
+                continue;
+            }
+            if !closing {
+                *depth += 1;
+            } else if *depth > 1 {
+                *depth -= 1;
+            } else {
+                ranges.push((*kind, *start, whole.end()));
+                open = None;
+            }
+            continue;
+        }
+        if closing || self_closing {
+            continue;
+        }
+
+        let local_name = name.rsplit(':').next().unwrap_or(&name);
+        let is_footnote = || {
+            type_attr.captures_iter(attrs).any(|types| {
+                let value = types
+                    .get(1)
+                    .or_else(|| types.get(2))
+                    .map_or("", |m| m.as_str());
+                value.split_whitespace().any(|t| {
+                    let t = t.to_ascii_lowercase();
+                    FOOTNOTE_TYPES.contains(&t.strip_prefix("doc-").unwrap_or(&t))
+                })
+            })
+        };
+        let kind = if options.captions && local_name == "figcaption" {
+            TextKind::Caption
+        } else if options.footnotes && is_footnote() {
+            TextKind::Footnote
+        } else {
+            continue;
+        };
+        open = Some((kind, whole.start(), name, 1));
+    }
+
+    // An unclosed element runs to the end of the chapter
+    if let Some((kind, start, _, _)) = open {
+        ranges.push((kind, start, html.len()));
+    }
+    ranges
+}
+
+/// Non-empty `alt` attributes of the images, in order
+fn alt_texts(html: &str) -> Vec<String> {
+    let img = Regex::new(r"(?i)<(?:[a-z]+:)?img\b[^>]*>").unwrap();
+    let alt = Regex::new(r#"(?i)\balt\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap();
+
+    img.find_iter(html)
+        .filter_map(|tag| {
+            let caps = alt.captures(tag.as_str())?;
+            let value = caps.get(1).or_else(|| caps.get(2))?.as_str();
+            Some(extract_plain_text(value))
+        })
+        .filter(|alt| !alt.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HTML: &str = r##"<body><p>The harbour at dawn.</p>
+<figure><img src="a.jpg" alt="Fishing boats &amp; nets"/><figcaption>Plate 3: <i>Whitby</i></figcaption></figure>
+<p>See note<a epub:type="noteref" href="#n1">1</a>.</p><img src="b.png" alt=""/>
+<aside epub:type="footnote" id="n1"><aside>Nested</aside> Painted by Turner.</aside>
+<div role="doc-endnotes"><p>Endnote</p></div></body>"##;
+
+    #[test]
+    fn test_extract_with_spans() {
+        let (text, spans) = extract_with_spans(HTML, &IndexOptions::all());
+        let body = extract_plain_text(HTML);
+        // The body is unchanged; alt text follows it
+        assert_eq!(text[..body.len()], body);
+        assert_eq!(text[body.len()..], *" Fishing boats & nets");
+
+        let tagged: Vec<(TextKind, &str)> = spans
+            .iter()
+            .map(|span| (span.kind, &text[span.start..span.end]))
+            .collect();
+        assert_eq!(
+            tagged,
+            vec![
+                (TextKind::Caption, "Plate 3: Whitby"),
+                (TextKind::Footnote, "Nested Painted by Turner."),
+                (TextKind::Footnote, "Endnote"),
+                (TextKind::AltText, "Fishing boats & nets"),
+            ]
+        );
+
+        let (text, spans) = extract_with_spans(
+            HTML,
+            &IndexOptions {
+                captions: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(text, body);
+        assert_eq!(spans.len(), 1);
+    }
+
+    #[test]
+    fn test_no_options_is_plain_extraction() {
+        let (text, spans) = extract_with_spans(HTML, &IndexOptions::default());
+        assert_eq!(text, extract_plain_text(HTML));
+        assert!(spans.is_empty());
+    }
+}
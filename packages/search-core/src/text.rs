@@ -0,0 +1,320 @@
+//! Text extraction and normalization for search
+//!
+//! Match positions are offsets into the normalized text, so both builds must
+//! run exactly these functions to produce compatible indexes. [`OffsetMap`]
+//! translates them back to offsets into the original text.
+//!
+//! [`TextFolding`] replaces typographic punctuation and ligatures with plain
+//! characters in text that is copied or exported, where readers expect to
+//! paste `fi` rather than `ﬁ`.
+
+use std::ops::Range;
+
+use regex::Regex;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+
+/// Normalize whitespace in text content
+pub fn normalize_text(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Extract plain text from HTML for search indexing
+pub fn extract_plain_text(html: &str) -> String {
+    // Remove script and style content
+    let no_script = Regex::new(r"(?s)<script[^>]*>.*?</script>")
+        .unwrap()
+        .replace_all(html, "");
+    let no_style = Regex::new(r"(?s)<style[^>]*>.*?</style>")
+        .unwrap()
+        .replace_all(&no_script, "");
+
+    // Remove all HTML tags
+    let no_tags = Regex::new(r"<[^>]+>").unwrap().replace_all(&no_style, " ");
+
+    // Decode common HTML entities
+    let decoded = no_tags
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'");
+
+    normalize_text(&decoded)
+}
+
+/// Normalize text for search (lowercase, remove accents, normalize unicode)
+///
+/// Curly quotes and apostrophes become straight ones, so "don't" finds
+/// "don’t". Ligatures are split by the compatibility decomposition. Dashes
+/// are kept: folding them to hyphens would join the words around them.
+pub fn normalize_for_search(text: &str) -> String {
+    text.chars()
+        .map(|c| fold_quote(c).unwrap_or(c))
+        .nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Length in bytes of a character after [`normalize_for_search`]
+fn normalized_len(c: char) -> usize {
+    fold_quote(c)
+        .unwrap_or(c)
+        .nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .map(char::len_utf8)
+        .sum()
+}
+
+/// Typographic characters to replace with plain ones in copied or exported
+/// text
+///
+/// Nothing is folded by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase", default))]
+pub struct TextFolding {
+    /// Curly quotes, apostrophes and primes become `'` and `"`, dashes `-`
+    /// (`--` for em dashes), and ellipses `...`
+    pub punctuation: bool,
+    /// The Latin ligatures `ﬀ ﬁ ﬂ ﬃ ﬄ ﬅ ﬆ` are split into their letters
+    pub ligatures: bool,
+}
+
+impl TextFolding {
+    /// Fold both punctuation and ligatures
+    pub const ALL: Self = Self {
+        punctuation: true,
+        ligatures: true,
+    };
+
+    /// Whether the folding leaves text unchanged
+    pub fn is_none(&self) -> bool {
+        !self.punctuation && !self.ligatures
+    }
+
+    /// `text` with the enabled replacements made
+    pub fn fold(&self, text: &str) -> String {
+        let mut folded = String::with_capacity(text.len());
+        for c in text.chars() {
+            match self.replacement(c) {
+                Some(replacement) => folded.push_str(replacement),
+                None => folded.push(c),
+            }
+        }
+        folded
+    }
+
+    /// Translates offsets in [`TextFolding::fold`] output back to `original`
+    pub fn offset_map(&self, original: &str) -> OffsetMap {
+        OffsetMap::with_lengths(original, |c| {
+            self.replacement(c).map_or(c.len_utf8(), str::len)
+        })
+    }
+
+    fn replacement(&self, c: char) -> Option<&'static str> {
+        let punctuation = self.punctuation.then(|| fold_punctuation(c)).flatten();
+        punctuation.or_else(|| self.ligatures.then(|| split_ligature(c)).flatten())
+    }
+}
+
+fn fold_punctuation(c: char) -> Option<&'static str> {
+    let replacement = match c {
+        '\u{2010}'..='\u{2013}' | '\u{2212}' => "-",
+        '\u{2014}' | '\u{2015}' => "--",
+        '\u{2026}' => "...",
+        _ => match fold_quote(c)? {
+            '\'' => "'",
+            _ => "\"",
+        },
+    };
+    Some(replacement)
+}
+
+fn split_ligature(c: char) -> Option<&'static str> {
+    let letters = match c {
+        '\u{FB00}' => "ff",
+        '\u{FB01}' => "fi",
+        '\u{FB02}' => "fl",
+        '\u{FB03}' => "ffi",
+        '\u{FB04}' => "ffl",
+        '\u{FB05}' | '\u{FB06}' => "st",
+        _ => return None,
+    };
+    Some(letters)
+}
+
+/// The straight quote for a curly quote, apostrophe or prime
+fn fold_quote(c: char) -> Option<char> {
+    match c {
+        '\u{2018}'..='\u{201B}' | '\u{2032}' | '\u{02BC}' => Some('\''),
+        '\u{201C}'..='\u{201F}' | '\u{2033}' => Some('"'),
+        _ => None,
+    }
+}
+
+/// Translates offsets in [`normalize_for_search`] (or [`TextFolding`])
+/// output back to the text it was produced from
+///
+/// Normalization changes the length of some characters ("é" becomes "e",
+/// "ﬁ" becomes "fi", combining marks disappear), so byte offsets drift
+/// apart after the first such character. Only those characters are
+/// recorded; offsets between them shift by a constant.
+#[derive(Debug, Clone, Default)]
+pub struct OffsetMap {
+    /// Characters whose normalized length differs, as
+    /// (normalized range, original range), in order
+    changed: Vec<(Range<usize>, Range<usize>)>,
+    original_len: usize,
+}
+
+impl OffsetMap {
+    /// Record the length changes of normalizing `original`
+    pub fn new(original: &str) -> Self {
+        Self::with_lengths(original, normalized_len)
+    }
+
+    /// Record the length changes of a character-by-character transform,
+    /// given the byte length each character becomes
+    fn with_lengths(original: &str, len_of: impl Fn(char) -> usize) -> Self {
+        let mut changed = Vec::new();
+        let mut normalized = 0;
+
+        for (i, c) in original.char_indices() {
+            let len = len_of(c);
+            if len != c.len_utf8() {
+                changed.push((normalized..normalized + len, i..i + c.len_utf8()));
+            }
+            normalized += len;
+        }
+
+        Self {
+            changed,
+            original_len: original.len(),
+        }
+    }
+
+    /// Original offset of a normalized offset
+    ///
+    /// An offset inside a character's normalized form maps to the start of
+    /// that character, so ranges never split an original character. An
+    /// offset right after a character maps past any combining marks that
+    /// follow it, since they were folded into it.
+    pub fn to_original(&self, offset: usize) -> usize {
+        let preceding = self
+            .changed
+            .partition_point(|(normalized, _)| normalized.start <= offset);
+        let original = match preceding.checked_sub(1).map(|i| &self.changed[i]) {
+            None => offset,
+            Some((normalized, original)) if offset < normalized.end => original.start,
+            Some((normalized, original)) => original.end + (offset - normalized.end),
+        };
+        original.min(self.original_len)
+    }
+}
+
+/// Combining diacritics stripped after NFKD decomposition
+pub(crate) fn is_combining_mark(c: char) -> bool {
+    let code = c as u32;
+    // Combining Diacritical Marks
+    (0x0300..=0x036F).contains(&code) ||
+    // Combining Diacritical Marks Extended
+    (0x1AB0..=0x1AFF).contains(&code) ||
+    // Combining Diacritical Marks Supplement
+    (0x1DC0..=0x1DFF).contains(&code) ||
+    // Combining Diacritical Marks for Symbols
+    (0x20D0..=0x20FF).contains(&code) ||
+    // Combining Half Marks
+    (0xFE20..=0xFE2F).contains(&code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_plain_text() {
+        let html = "<p>Hello <b>World</b>!</p><script>alert('x')</script>";
+        let text = extract_plain_text(html);
+        assert_eq!(text, "Hello World !");
+    }
+
+    #[test]
+    fn test_extract_decodes_entities() {
+        let html = "<p>Fish&nbsp;&amp;&nbsp;Chips</p><style>p { color: red }</style>";
+        assert_eq!(extract_plain_text(html), "Fish & Chips");
+    }
+
+    #[test]
+    fn test_normalize_for_search() {
+        assert_eq!(normalize_for_search("Hello World"), "hello world");
+        assert_eq!(normalize_for_search("Café"), "cafe");
+        assert_eq!(normalize_for_search("Naïve"), "naive");
+        assert_eq!(normalize_for_search("Don’t “Stop”"), "don't \"stop\"");
+        assert_eq!(normalize_for_search("1990–95"), "1990–95");
+    }
+
+    #[test]
+    fn test_text_folding() {
+        let original = "“Don’t”—the ﬁnal ﬄight… ’90–’95";
+        assert_eq!(TextFolding::default().fold(original), original);
+        assert!(TextFolding::default().is_none());
+
+        let punctuation = TextFolding {
+            punctuation: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            punctuation.fold(original),
+            "\"Don't\"--the ﬁnal ﬄight... '90-'95"
+        );
+        let ligatures = TextFolding {
+            ligatures: true,
+            ..Default::default()
+        };
+        assert_eq!(ligatures.fold("ﬁnal ﬄight"), "final fflight");
+
+        let folded = TextFolding::ALL.fold(original);
+        assert_eq!(folded, "\"Don't\"--the final fflight... '90-'95");
+        let map = TextFolding::ALL.offset_map(original);
+        let word = |w: &str| {
+            let start = folded.find(w).unwrap();
+            &original[map.to_original(start)..map.to_original(start + w.len())]
+        };
+        assert_eq!(word("Don't"), "Don’t");
+        assert_eq!(word("fflight..."), "ﬄight…");
+        assert_eq!(word("'95"), "’95");
+        assert_eq!(map.to_original(folded.len()), original.len());
+    }
+
+    #[test]
+    fn test_offset_map() {
+        // Precomposed and decomposed accents, a ligature, CJK and an emoji
+        let original = "Café nai\u{308}ve ﬁsh ’til 東京 👍🏽 end";
+        let normalized = normalize_for_search(original);
+        let map = OffsetMap::new(original);
+
+        let word = |w: &str| {
+            let start = normalized.find(w).unwrap();
+            let range = map.to_original(start)..map.to_original(start + w.len());
+            &original[range]
+        };
+        assert_eq!(word("cafe"), "Café");
+        assert_eq!(word("naive"), "nai\u{308}ve");
+        assert_eq!(word("fish"), "ﬁsh");
+        assert_eq!(word("'til"), "’til");
+        assert_eq!(word("東京"), "東京");
+        assert_eq!(word("end"), "end");
+        assert_eq!(map.to_original(normalized.len()), original.len());
+
+        // Inside a normalized character: the start of the original one
+        let fi = normalized.find("fish").unwrap();
+        assert_eq!(map.to_original(fi + 1), original.find('ﬁ').unwrap());
+        assert_eq!(map.to_original(normalized.len() + 10), original.len());
+    }
+}
@@ -0,0 +1,43 @@
+#!/usr/bin/env bash
+#
+# Run the Rust Criterion benchmark suites and save or compare baselines.
+#
+# Usage:
+#   scripts/rust-bench.sh save    [baseline]   # record a baseline (default: main)
+#   scripts/rust-bench.sh compare [baseline]   # compare against a saved baseline
+#   scripts/rust-bench.sh smoke                # run every bench once, no timing
+#
+# Results are written to $CRITERION_HOME (default: ./bench-baselines) so CI can
+# cache or upload a single directory and restore it on the next run.
+#
+# Set BENCH_CRATES to a space-separated subset of crate directories to limit
+# the run, e.g. BENCH_CRATES="packages/cfi-core".
+
+set -euo pipefail
+
+ROOT="$(cd "$(dirname "${BASH_SOURCE[0]}")/.." && pwd)"
+MODE="${1:-compare}"
+BASELINE="${2:-main}"
+
+export CRITERION_HOME="${CRITERION_HOME:-$ROOT/bench-baselines}"
+
+CRATES=(${BENCH_CRATES:-packages/cfi-core apps/amnesia/src/wasm/epub-processor apps/amnesia-server})
+
+case "$MODE" in
+  save)    CRITERION_ARGS=(--save-baseline "$BASELINE") ;;
+  compare) CRITERION_ARGS=(--baseline "$BASELINE") ;;
+  smoke)   CRITERION_ARGS=(--test) ;;
+  *)
+    echo "unknown mode: $MODE (expected save, compare, or smoke)" >&2
+    exit 2
+    ;;
+esac
+
+for crate in "${CRATES[@]}"; do
+  echo "==> $crate"
+  (cd "$ROOT/$crate" && cargo bench --bench '*' -- "${CRITERION_ARGS[@]}")
+done
+
+if [ "$MODE" != "smoke" ]; then
+  echo "Criterion reports: $CRITERION_HOME/report/index.html"
+fi